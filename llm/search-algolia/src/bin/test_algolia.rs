@@ -5,7 +5,7 @@ use log::{info, error};
 // Import the client directly using the crate name
 use golem_search_algolia::client::{AlgoliaClient, AlgoliaConfig, AlgoliaIndexSettings, AlgoliaSearchQuery};
 
-#[tokio::main]
+#[tokio::main(flavor = "current_thread")]
 async fn main() -> Result<()> {
     // Initialize logging
     env_logger::init();
@@ -183,7 +183,7 @@ async fn run_comprehensive_test(test_index: &str) -> Result<()> {
     tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
     
     // Get a specific document to verify storage
-    let doc = client.get_object(test_index, "1").await
+    let doc = client.get_object(test_index, "1", None).await
         .map_err(|e| anyhow!("Failed to retrieve document: {}", e))?;
     
     if doc.get("objectID").and_then(|v| v.as_str()) != Some("1") {
@@ -220,6 +220,7 @@ async fn run_comprehensive_test(test_index: &str) -> Result<()> {
         synonyms: None,
         replaceSynonymsInHighlight: None,
         minProximity: None,
+        ..Default::default()
     };
     
     let results = client.search(test_index, &search_query).await
@@ -381,7 +382,7 @@ async fn run_comprehensive_test(test_index: &str) -> Result<()> {
     tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
     
     // Verify document is gone
-    let delete_verify = client.get_object(test_index, "5").await;
+    let delete_verify = client.get_object(test_index, "5", None).await;
     if delete_verify.is_ok() {
         return Err(anyhow!("Document should have been deleted"));
     }
@@ -1,7 +1,12 @@
 use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::Duration;
 use anyhow::{anyhow, Result};
-use reqwest::{Client, Method, Response};
+use log::{debug, warn};
+use golem_search::circuit_breaker;
+use golem_search::http::{self, Client, Method, Response};
+use golem_search::rate_limit::{self, Throttle};
+use golem_search::retry::RetryPolicy;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 // URL parsing (removed unused import)
@@ -12,6 +17,9 @@ pub struct AlgoliaConfig {
     pub app_id: String,
     pub api_key: String,
     pub timeout: Duration,
+    pub max_retries: u32,
+    /// Pending indexing tasks past which writes are refused
+    pub max_pending_tasks: Option<u32>,
 }
 
 impl AlgoliaConfig {
@@ -27,18 +35,51 @@ impl AlgoliaConfig {
             .parse::<u64>()
             .map_err(|_| anyhow!("Invalid timeout value"))?;
 
+        let max_retries = std::env::var("SEARCH_PROVIDER_MAX_RETRIES")
+            .unwrap_or_else(|_| "3".to_string())
+            .parse::<u32>()
+            .map_err(|_| anyhow!("Invalid max_retries value"))?;
+        
+        let max_pending_tasks = match std::env::var("SEARCH_MAX_PENDING_TASKS") {
+            Ok(value) => Some(value.parse::<u32>()
+                .map_err(|_| anyhow!("Invalid SEARCH_MAX_PENDING_TASKS value"))?)
+                .filter(|&max| max > 0),
+            Err(_) => None,
+        };
+
         Ok(Self {
             app_id,
             api_key,
             timeout: Duration::from_secs(timeout),
+            max_retries,
+            max_pending_tasks,
         })
     }
 }
 
+/// Whether an object fetch answer found its object
+///
+/// Both a missing object and a missing index answer 404; only the object's
+/// message is `ObjectID does not exist`.
+fn object_presence(index: &str, status: u16, body: &str) -> Result<bool> {
+    match status {
+        200..=299 => Ok(true),
+        404 => {
+            let answer: Value = serde_json::from_str(body).unwrap_or_default();
+            match answer["message"].as_str() {
+                Some("ObjectID does not exist") => Ok(false),
+                _ => Err(anyhow::Error::new(golem_search::SearchError::index_not_found(index))),
+            }
+        }
+        status => Err(anyhow!("Algolia API error {}: {}", status, body)),
+    }
+}
+
 /// Algolia API client
 pub struct AlgoliaClient {
     config: AlgoliaConfig,
     http_client: Client,
+    throttle: Arc<Throttle>,
 }
 
 impl AlgoliaClient {
@@ -46,31 +87,66 @@ impl AlgoliaClient {
     pub fn new(config: AlgoliaConfig) -> Result<Self> {
         let http_client = Client::builder()
             .timeout(config.timeout)
+            .retry(RetryPolicy::new(config.max_retries))
+            .http2(true)
             .build()
             .map_err(|e| anyhow!("Failed to create HTTP client: {}", e))?;
 
         Ok(Self {
             config,
             http_client,
+            throttle: rate_limit::throttle("algolia"),
         })
     }
 
+    /// Pending task limit configured for writes
+    pub fn max_pending_tasks(&self) -> Option<u32> {
+        self.config.max_pending_tasks
+    }
+
     /// Get the base URL for API requests
     fn base_url(&self) -> String {
         format!("https://{}-dsn.algolia.net/1", self.config.app_id)
     }
 
-    /// Make an authenticated request to the Algolia API
+    /// Make an authenticated request to the Algolia API, failing on error statuses
     async fn request<T: Serialize + ?Sized>(
         &self,
         method: Method,
         path: &str,
         body: Option<&T>,
     ) -> Result<Response> {
-        let url = format!("{}/{}", self.base_url(), path.trim_start_matches('/'));
+        let (request_id, response) = self.send(method, path, body).await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().unwrap_or_else(|_| "Unknown error".to_string());
+            warn!("[{}] Algolia API error {}: {}", request_id, status, error_text);
+            return Err(anyhow!("Algolia API error {}: {}", status, error_text));
+        }
+
+        Ok(response)
+    }
+
+    /// Make an authenticated request to the Algolia API, returning its request ID and response whatever the status
+    async fn send<T: Serialize + ?Sized>(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&T>,
+    ) -> Result<(String, Response)> {
+        let endpoint = self.base_url();
+        let url = format!("{}/{}", endpoint, path.trim_start_matches('/'));
+        let breaker = circuit_breaker::global();
+        breaker.check(&endpoint)?;
+        self.throttle.acquire()?;
+        
+        // Algolia has no request ID of its own; send ours so both sides can be correlated
+        let request_id = uuid::Uuid::new_v4().simple().to_string();
+        debug!("[{}] {} {}", request_id, method, path);
         
         let mut request = self.http_client
             .request(method, &url)
+            .header("X-Request-Id", &request_id)
             .header("X-Algolia-Application-Id", &self.config.app_id)
             .header("X-Algolia-API-Key", &self.config.api_key)
             .header("Content-Type", "application/json");
@@ -79,17 +155,14 @@ impl AlgoliaClient {
             request = request.json(body);
         }
 
-        let response = request
-            .send()
-            .map_err(|e| anyhow!("HTTP request failed: {}", e))?;
+        let response = request.send().map_err(|e| {
+            breaker.record_failure(&endpoint, &e.to_string());
+            anyhow!("HTTP request failed: {}", e)
+        })?;
+        breaker.record_status(&endpoint, response.status().as_u16());
+        self.throttle.record(response.status(), response.headers())?;
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().unwrap_or_else(|_| "Unknown error".to_string());
-            return Err(anyhow!("Algolia API error {}: {}", status, error_text));
-        }
-
-        Ok(response)
+        Ok((request_id, response))
     }
 
     /// Create an index
@@ -127,12 +200,85 @@ impl AlgoliaClient {
         Ok(data.items.into_iter().map(|item| item.name).collect())
     }
 
+    /// Number of indexing tasks not yet published for an index
+    ///
+    /// Algolia reports a count per index in its index listing, not the tasks themselves.
+    pub async fn pending_task_count(&self, index: &str) -> Result<u32> {
+        let response = self.request(Method::GET, "indexes", None::<&()>).await?;
+        let data: ListIndicesResponse = response.json()
+            .map_err(|e| anyhow!("Failed to parse response: {}", e))?;
+        
+        data.items.into_iter()
+            .find(|item| item.name == index)
+            .map(|item| item.number_of_pending_tasks.unwrap_or(0))
+            .ok_or_else(|| anyhow!("Index {} not found", index))
+    }
+
     /// Update index settings
     pub async fn update_index_settings(&self, name: &str, settings: &AlgoliaIndexSettings) -> Result<()> {
         self.request(Method::PUT, &format!("indexes/{}/settings", name), Some(settings)).await?;
         Ok(())
     }
 
+    /// Raw settings of an index
+    pub async fn get_settings(&self, name: &str) -> Result<Value> {
+        let response = self.request(Method::GET, &format!("indexes/{}/settings", name), None::<&()>).await?;
+        response.json()
+            .map_err(|e| anyhow!("Failed to parse settings: {}", e))
+    }
+
+    /// Replace index settings with raw settings, such as those returned by `get_settings`
+    pub async fn set_settings(&self, name: &str, settings: &Value) -> Result<()> {
+        self.request(Method::PUT, &format!("indexes/{}/settings", name), Some(settings)).await?;
+        Ok(())
+    }
+
+    /// Every synonym of an index
+    pub async fn list_synonyms(&self, name: &str) -> Result<Vec<Value>> {
+        const PAGE_SIZE: usize = 1000;
+        let mut synonyms = Vec::new();
+        for page in 0.. {
+            let body = serde_json::json!({ "query": "", "page": page, "hitsPerPage": PAGE_SIZE });
+            let response = self.request(Method::POST, &format!("indexes/{}/synonyms/search", name), Some(&body)).await?;
+            let result: Value = response.json()
+                .map_err(|e| anyhow!("Failed to parse synonyms: {}", e))?;
+            let hits = result["hits"].as_array().cloned().unwrap_or_default();
+            let last = hits.len() < PAGE_SIZE;
+            synonyms.extend(hits);
+            if last {
+                break;
+            }
+        }
+        Ok(synonyms)
+    }
+
+    /// Replace every synonym of an index with `synonyms`
+    pub async fn replace_synonyms(&self, name: &str, synonyms: &[Value]) -> Result<()> {
+        self.request(
+            Method::POST,
+            &format!("indexes/{}/synonyms/batch?replaceExistingSynonyms=true", name),
+            Some(synonyms),
+        ).await?;
+        Ok(())
+    }
+
+    /// Delete every object but keep the settings
+    pub async fn clear_objects(&self, name: &str) -> Result<()> {
+        self.request(Method::POST, &format!("indexes/{}/clear", name), None::<&()>).await?;
+        Ok(())
+    }
+
+    /// One page of every object in an index; pass the previous page's `cursor` to continue
+    pub async fn browse(&self, index: &str, cursor: Option<&str>) -> Result<Value> {
+        let body = match cursor {
+            Some(cursor) => serde_json::json!({ "cursor": cursor }),
+            None => serde_json::json!({ "hitsPerPage": 1000 }),
+        };
+        let response = self.request(Method::POST, &format!("indexes/{}/browse", index), Some(&body)).await?;
+        response.json()
+            .map_err(|e| anyhow!("Failed to parse browse response: {}", e))
+    }
+
     /// Add or update a single object
     pub async fn upsert_object(&self, index: &str, object_id: &str, object: &Value) -> Result<()> {
         self.request(Method::PUT, &format!("indexes/{}/objects/{}", index, object_id), Some(object)).await?;
@@ -157,13 +303,47 @@ impl AlgoliaClient {
     }
 
     /// Get an object by ID
-    pub async fn get_object(&self, index: &str, object_id: &str) -> Result<Value> {
-        let response = self.request(Method::GET, &format!("indexes/{}/objects/{}", index, object_id), None::<&()>).await?;
+    pub async fn get_object(&self, index: &str, object_id: &str, attributes: Option<&[String]>) -> Result<Value> {
+        let mut path = format!("indexes/{}/objects/{}", index, object_id);
+        if let Some(attributes) = attributes {
+            let attributes: String = url::form_urlencoded::byte_serialize(attributes.join(",").as_bytes()).collect();
+            path.push_str(&format!("?attributesToRetrieve={}", attributes));
+        }
+        let response = self.request(Method::GET, &path, None::<&()>).await?;
         let object: Value = response.json()
             .map_err(|e| anyhow!("Failed to parse object: {}", e))?;
         Ok(object)
     }
 
+    /// Check whether an object exists, retrieving only its `objectID`
+    ///
+    /// A missing index is an error rather than an absent object.
+    pub async fn object_exists(&self, index: &str, object_id: &str) -> Result<bool> {
+        let path = format!("indexes/{}/{}?attributesToRetrieve=objectID", index, http::path_segment(object_id));
+        let (_, response) = self.send(Method::GET, &path, None::<&()>).await?;
+        let status = response.status().as_u16();
+        object_presence(index, status, &response.text().unwrap_or_default())
+    }
+
+    /// Get several objects by ID in one request
+    ///
+    /// Returns one entry per requested ID, in request order, `None` for missing objects.
+    pub async fn get_objects(&self, index: &str, object_ids: &[String], attributes: Option<&[String]>) -> Result<Vec<Option<Value>>> {
+        let requests: Vec<GetObjectRequest> = object_ids.iter().map(|id| {
+            GetObjectRequest {
+                index_name: index.to_string(),
+                object_id: id.clone(),
+                attributes_to_retrieve: attributes.map(<[String]>::to_vec),
+            }
+        }).collect();
+
+        let body = GetObjectsRequest { requests };
+        let response = self.request(Method::POST, "indexes/*/objects", Some(&body)).await?;
+        let objects: GetObjectsResponse = response.json()
+            .map_err(|e| anyhow!("Failed to parse objects: {}", e))?;
+        Ok(objects.results)
+    }
+
     /// Delete an object by ID
     pub async fn delete_object(&self, index: &str, object_id: &str) -> Result<()> {
         self.request(Method::DELETE, &format!("indexes/{}/objects/{}", index, object_id), None::<&()>).await?;
@@ -274,7 +454,7 @@ impl Default for AlgoliaIndexSettings {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct AlgoliaSearchQuery {
     pub query: String,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -327,6 +507,9 @@ pub struct AlgoliaSearchQuery {
     pub distinct: Option<Value>, // Can be bool or number
     #[serde(skip_serializing_if = "Option::is_none")]
     pub typo_tolerance: Option<String>, // "true", "false", "min", "strict"
+    #[serde(rename = "minWordSizefor2Typos")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_word_size_for_2_typos: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub analytics: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -335,6 +518,21 @@ pub struct AlgoliaSearchQuery {
     pub replaceSynonymsInHighlight: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub minProximity: Option<u32>,
+    #[serde(rename = "insidePolygon")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inside_polygon: Option<Value>,
+    #[serde(rename = "aroundLatLng")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub around_lat_lng: Option<String>,
+    #[serde(rename = "aroundRadius")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub around_radius: Option<Value>, // Meters, or "all"
+    #[serde(rename = "optionalWords")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub optional_words: Option<Vec<String>>,
+    #[serde(rename = "removeWordsIfNoResults")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remove_words_if_no_results: Option<String>, // "none", "lastWords", "firstWords" or "allOptional"
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -345,10 +543,23 @@ pub struct AlgoliaSearchResults {
     pub page: u32,
     #[serde(rename = "hitsPerPage")]
     pub hits_per_page: u32,
+    #[serde(rename = "nbPages", default)]
+    pub nb_pages: Option<u32>,
     #[serde(rename = "processingTimeMS")]
     pub processing_time_ms: u32,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub facets: Option<HashMap<String, HashMap<String, u32>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub facets_stats: Option<HashMap<String, AlgoliaFacetStats>>,
+}
+
+/// Summary Algolia returns for numeric facets
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct AlgoliaFacetStats {
+    pub min: f64,
+    pub max: f64,
+    pub avg: f64,
+    pub sum: f64,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -371,8 +582,11 @@ struct ListIndicesResponse {
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 struct IndexInfo {
     name: String,
+    #[serde(default)]
+    number_of_pending_tasks: Option<u32>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -386,8 +600,42 @@ struct BatchRequestWrapper {
     requests: Vec<BatchRequest>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GetObjectRequest {
+    index_name: String,
+    #[serde(rename = "objectID")]
+    object_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    attributes_to_retrieve: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct GetObjectsRequest {
+    requests: Vec<GetObjectRequest>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct GetObjectsResponse {
+    results: Vec<Option<Value>>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct BatchResponse {
     #[serde(rename = "objectIDs")]
     object_ids: Vec<String>,
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_object_presence_tells_missing_objects_from_missing_indexes() {
+        assert!(object_presence("products", 200, r#"{"objectID":"a/1"}"#).unwrap());
+        assert!(!object_presence("products", 404, r#"{"message":"ObjectID does not exist","status":404}"#).unwrap());
+
+        let missing = object_presence("products", 404, r#"{"message":"Index does not exist","status":404}"#).unwrap_err();
+        assert!(matches!(missing.downcast_ref(), Some(golem_search::SearchError::IndexNotFound(_))));
+    }
+}
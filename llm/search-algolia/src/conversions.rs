@@ -1,10 +1,102 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use anyhow::{anyhow, Result};
-use serde_json::Value;
+use log::warn;
+use serde_json::{json, Value};
 use uuid::Uuid;
+use golem_search::circuit_breaker;
+use golem_search::cursor::{CursorPosition, PageCursor};
+use golem_search::deadline;
+use golem_search::facets::{FacetSpec, RangeBucket};
+use golem_search::geo::{GeoDistanceSort, GeoPolygonFilter};
+use golem_search::id_set::IdSetFilter;
+use golem_search::provider_params::parse_provider_params;
+use golem_search::query::{parse_fuzziness, parse_minimum_should_match, Fuzziness, MinimumShouldMatch};
+use golem_search::rate_limit;
+use golem_search::rescue::{self, Relaxation, RescueQuery, RescueResults};
+use golem_search::spellfix::{self, Correction};
+use golem_search::suggestions;
+use golem_search::types::QueryText;
+use golem_search::{IndexSettings, LookupSpec};
 
 use crate::bindings::*;
-use crate::client::{AlgoliaIndexSettings, AlgoliaSearchQuery, AlgoliaSearchResults, AlgoliaSearchHit};
+use crate::client::{AlgoliaFacetStats, AlgoliaIndexSettings, AlgoliaSearchQuery, AlgoliaSearchResults, AlgoliaSearchHit};
+
+/// Searchable and faceting attributes for a shared schema, as raw settings
+pub fn shared_schema_to_algolia_settings(schema: &golem_search::Schema) -> Value {
+    json!({
+        "searchableAttributes": schema.fields.iter()
+            .filter(|f| f.index && f.field_type == golem_search::FieldType::Text)
+            .map(|f| f.name.as_str())
+            .collect::<Vec<_>>(),
+        "attributesForFaceting": schema.fields.iter()
+            .filter(|f| f.facet)
+            .map(|f| format!("filterOnly({})", f.name))
+            .collect::<Vec<_>>(),
+    })
+}
+
+/// Build a settings snapshot from raw index settings and synonyms
+///
+/// The custom ranking becomes the ranking rules and the other settings are
+/// kept as provider settings. One-way synonyms map their input to their
+/// synonyms and regular ones their first synonym to the others; other synonym
+/// types have no portable form and are left out.
+pub fn algolia_settings_to_snapshot(settings: &Value, synonyms: &[Value]) -> IndexSettings {
+    let strings = |value: &Value| -> Vec<String> { serde_json::from_value(value.clone()).unwrap_or_default() };
+
+    let mut snapshot = IndexSettings {
+        ranking_rules: strings(&settings["customRanking"]),
+        ..Default::default()
+    };
+    for synonym in synonyms {
+        let mut terms = strings(&synonym["synonyms"]);
+        let term = match synonym["type"].as_str() {
+            Some("onewaysynonym") => synonym["input"].as_str().map(str::to_string),
+            Some("synonym") if !terms.is_empty() => Some(terms.remove(0)),
+            _ => None,
+        };
+        if let Some(term) = term {
+            snapshot.synonyms.insert(term, terms);
+        }
+    }
+    if let Some(mut other) = settings.as_object().cloned() {
+        other.remove("customRanking");
+        if !other.is_empty() {
+            snapshot.provider_settings = Some(Value::Object(other));
+        }
+    }
+    snapshot
+}
+
+/// Raw settings and synonym objects applying a settings snapshot
+///
+/// Settings are a partial update, so attributes the snapshot does not manage are kept.
+pub fn snapshot_to_algolia(snapshot: &IndexSettings) -> (Value, Vec<Value>) {
+    let mut settings = match &snapshot.provider_settings {
+        Some(Value::Object(provider_settings)) => Value::Object(provider_settings.clone()),
+        _ => json!({}),
+    };
+    if let Some(Value::Object(attributes)) = snapshot.schema.as_ref().map(shared_schema_to_algolia_settings) {
+        for (key, value) in attributes {
+            settings[key.as_str()] = value;
+        }
+    }
+    if !snapshot.ranking_rules.is_empty() {
+        settings["customRanking"] = json!(snapshot.ranking_rules);
+    }
+
+    let synonyms = snapshot.synonyms
+        .iter()
+        .enumerate()
+        .map(|(position, (term, equivalents))| json!({
+            "objectID": format!("settings-{}", position),
+            "type": "onewaysynonym",
+            "input": term,
+            "synonyms": equivalents,
+        }))
+        .collect();
+    (settings, synonyms)
+}
 
 /// Convert WIT Schema to Algolia Index Settings
 pub fn schema_to_index_settings(schema: &Schema) -> AlgoliaIndexSettings {
@@ -219,10 +311,16 @@ pub fn search_query_to_algolia_query(query: &SearchQuery) -> Result<AlgoliaSearc
         get_ranking_info: None,
         distinct: None,
         typo_tolerance: None,
+        min_word_size_for_2_typos: None,
         analytics: None,
         synonyms: None,
         replaceSynonymsInHighlight: None,
         minProximity: None,
+        inside_polygon: None,
+        around_lat_lng: None,
+        around_radius: None,
+        optional_words: None,
+        remove_words_if_no_results: None,
     };
     
     // Convert facet filters to Algolia facet filters (more sophisticated approach)
@@ -294,9 +392,272 @@ pub fn search_query_to_algolia_query(query: &SearchQuery) -> Result<AlgoliaSearc
     algolia_query.analytics = Some(true);
     algolia_query.synonyms = Some(true);
     
+    if let Some(fuzziness) = query.fuzziness.as_deref() {
+        apply_fuzziness(&mut algolia_query, fuzziness)?;
+    }
+    
+    apply_minimum_should_match(&mut algolia_query, query.minimum_should_match.as_deref())?;
+    
+    if let Some(filter) = query.geo_filter.as_deref() {
+        let polygons = GeoPolygonFilter::parse(filter)
+            .ok_or_else(|| anyhow!("Geo filter {} is not a geo_polygon filter", filter))?
+            .map_err(|e| anyhow!("{}", e))?;
+        algolia_query.inside_polygon = Some(polygons.to_algolia_polygons());
+    }
+    
+    // Sets of more than one batch are searched batch by batch (see id_batch_query)
+    algolia_query.filters = id_filter_batches(query)?.into_iter().next();
+    
+    if let Some(sort) = query.geo_sort.as_deref() {
+        let sort = GeoDistanceSort::parse(sort)
+            .ok_or_else(|| anyhow!("Geo sort {} is not a geo_distance sort", sort))?
+            .map_err(|e| anyhow!("{}", e))?;
+        if sort.descending {
+            return Err(anyhow!("Algolia ranks by distance nearest first only"));
+        }
+        // Without a radius Algolia also drops hits beyond one it picks from the density of the area
+        algolia_query.around_lat_lng = Some(sort.to_algolia_around());
+        algolia_query.around_radius = Some(json!("all"));
+    }
+    
+    // Grouping relies on the index's distinct attribute; faceting on it
+    // yields each group's match count
+    let group_by = query.group_by.as_deref().map(str::trim).filter(|f| !f.is_empty());
+    if group_by.is_some() {
+        algolia_query.distinct = Some(Value::Bool(true));
+    }
+    
+    // Group fields are faceted for group totals, stats fields for facets_stats
+    let stats_fields = query.stats_facets.iter().map(|f| f.trim()).filter(|f| !f.is_empty());
+    for field in group_by.into_iter().chain(stats_fields) {
+        let facets = algolia_query.facets.get_or_insert_with(Vec::new);
+        if !facets.iter().any(|f| f == field) {
+            facets.push(field.to_string());
+        }
+    }
+    
+    // Provider params are applied last so they can override the defaults above
+    apply_provider_query_params(&mut algolia_query, query.provider_params.as_deref())?;
+    
     Ok(algolia_query)
 }
 
+/// Algolia `filters` of each batch of the query's ID set; empty without one
+pub fn id_filter_batches(query: &SearchQuery) -> Result<Vec<String>> {
+    let Some(filter) = query.id_filter.as_deref() else {
+        return Ok(Vec::new());
+    };
+    IdSetFilter::parse(filter)
+        .ok_or_else(|| anyhow!("ID filter is not an id_set filter"))?
+        .and_then(|ids| ids.to_algolia_filters())
+        .map_err(|e| anyhow!("{}", e))
+}
+
+/// Hits per page Algolia returns when a query sets none
+pub const DEFAULT_HITS_PER_PAGE: u32 = 20;
+
+/// Search of one batch of an ID set, for the hits up to the end of the page `base` asks for
+///
+/// A batch cannot be paged on its own, since the page is cut from the hits
+/// of every batch; see [`merge_id_batches`].
+pub fn id_batch_query(base: &AlgoliaSearchQuery, filter: &str) -> AlgoliaSearchQuery {
+    let mut query = base.clone();
+    let per_page = base.hits_per_page.unwrap_or(DEFAULT_HITS_PER_PAGE);
+    let end = (base.page.unwrap_or(0) + 1).saturating_mul(per_page);
+    query.filters = Some(filter.to_string());
+    query.page = Some(0);
+    query.hits_per_page = Some(end.min(LOOKUP_HITS_LIMIT));
+    query
+}
+
+/// Results of the page `page` of `per_page` hits from the searches of every batch of an ID set
+///
+/// Rankings of separate searches cannot be compared, so hits are
+/// interleaved by their rank within their batch. Totals and facet counts are
+/// summed; facet stats are combined, with the average weighted by the
+/// values behind it.
+pub fn merge_id_batches(batches: Vec<AlgoliaSearchResults>, page: u32, per_page: u32) -> AlgoliaSearchResults {
+    let nb_hits = batches.iter().fold(0u32, |total, batch| total.saturating_add(batch.nb_hits));
+    let processing_time_ms = batches.iter().map(|batch| batch.processing_time_ms).sum();
+    let mut facets: Option<HashMap<String, HashMap<String, u32>>> = None;
+    let mut stats: Option<HashMap<String, (AlgoliaFacetStats, f64)>> = None;
+    let mut ranked: Vec<(usize, AlgoliaSearchHit)> = Vec::new();
+
+    for batch in batches {
+        for (field, values) in batch.facets.into_iter().flatten() {
+            let counts = facets.get_or_insert_with(HashMap::new).entry(field).or_default();
+            for (value, count) in values {
+                *counts.entry(value).or_default() += count;
+            }
+        }
+        for (field, batch_stats) in batch.facets_stats.into_iter().flatten() {
+            let values = if batch_stats.avg == 0.0 { 0.0 } else { batch_stats.sum / batch_stats.avg };
+            stats
+                .get_or_insert_with(HashMap::new)
+                .entry(field)
+                .and_modify(|(merged, count)| {
+                    merged.min = merged.min.min(batch_stats.min);
+                    merged.max = merged.max.max(batch_stats.max);
+                    merged.sum += batch_stats.sum;
+                    *count += values;
+                })
+                .or_insert((batch_stats, values));
+        }
+        ranked.extend(batch.hits.into_iter().enumerate());
+    }
+
+    // A document whose field holds IDs of two batches is found twice
+    let mut seen = HashSet::new();
+    ranked.sort_by_key(|(rank, _)| *rank);
+    let start = page.saturating_mul(per_page) as usize;
+    let hits = ranked
+        .into_iter()
+        .map(|(_, hit)| hit)
+        .filter(|hit| seen.insert(hit.object_id.clone()))
+        .skip(start)
+        .take(per_page as usize)
+        .collect();
+
+    let facets_stats = stats.map(|stats| {
+        stats
+            .into_iter()
+            .map(|(field, (mut merged, count))| {
+                merged.avg = if count == 0.0 { 0.0 } else { merged.sum / count };
+                (field, merged)
+            })
+            .collect()
+    });
+
+    AlgoliaSearchResults {
+        hits,
+        nb_hits,
+        page,
+        hits_per_page: per_page,
+        nb_pages: None,
+        processing_time_ms,
+        facets,
+        facets_stats,
+    }
+}
+
+/// Map a minimum_should_match to the words Algolia may leave out
+///
+/// Algolia requires every word by default. Listing the whole query as
+/// optional words lets any one of them match; other minimums drop words from
+/// the end of the query, and only when nothing matches.
+pub fn apply_minimum_should_match(query: &mut AlgoliaSearchQuery, minimum: Option<&str>) -> Result<()> {
+    let Some(minimum) = parse_minimum_should_match(minimum).map_err(|e| anyhow!("{}", e))? else {
+        return Ok(());
+    };
+    if minimum.is_all() {
+        query.remove_words_if_no_results = Some("none".to_string());
+    } else if minimum.is_any() {
+        query.optional_words = Some(vec![query.query.clone()]);
+    } else {
+        warn!("Algolia cannot require {} of the query words; dropping the last words when nothing matches", minimum);
+        query.remove_words_if_no_results = Some("lastWords".to_string());
+    }
+    Ok(())
+}
+
+/// Word length that never qualifies for a second typo
+const NO_SECOND_TYPO: u32 = 255;
+
+/// Map a fuzziness of "0", "1", "2" or "auto" to Algolia typo tolerance
+///
+/// Algolia has no typo cap, so one typo is expressed by raising the word
+/// length needed for a second one out of reach.
+pub fn apply_fuzziness(query: &mut AlgoliaSearchQuery, fuzziness: &str) -> Result<()> {
+    match fuzziness.trim().to_ascii_lowercase().as_str() {
+        "0" => query.typo_tolerance = Some("false".to_string()),
+        "1" => {
+            query.typo_tolerance = Some("true".to_string());
+            query.min_word_size_for_2_typos = Some(NO_SECOND_TYPO);
+        }
+        "2" | "auto" => query.typo_tolerance = Some("true".to_string()),
+        "" => {}
+        other => return Err(anyhow!("Fuzziness {} is invalid; expected 0, 1, 2 or auto", other)),
+    }
+    Ok(())
+}
+
+/// Field and buckets of a range facet such as `price:[0-25, 25-50, 50+]`
+pub fn parse_range_facet(spec: &str) -> Result<(String, Vec<RangeBucket>)> {
+    match FacetSpec::parse(spec)? {
+        FacetSpec::Range { field, buckets } => Ok((field, buckets)),
+        _ => Err(anyhow!("Range facet {} is invalid; expected field:[from-to, ...]", spec)),
+    }
+}
+
+/// numericFilters selecting the documents of `field` in `bucket`
+pub fn bucket_numeric_filters(field: &str, bucket: &RangeBucket) -> Vec<String> {
+    let mut filters = Vec::new();
+    if let Some(from) = bucket.from {
+        filters.push(format!("{} >= {}", field, from));
+    }
+    if let Some(to) = bucket.to {
+        filters.push(format!("{} < {}", field, to));
+    }
+    filters
+}
+
+/// Query counting the matches of `base` that fall into one bucket
+///
+/// Algolia has no range facets, so each bucket is counted by a search that
+/// adds the bucket bounds to the numeric filters and returns no hits.
+pub fn range_count_query(base: &AlgoliaSearchQuery, field: &str, bucket: &RangeBucket) -> AlgoliaSearchQuery {
+    let mut query = base.clone();
+    query
+        .numeric_filters
+        .get_or_insert_with(Vec::new)
+        .extend(bucket_numeric_filters(field, bucket));
+    query.page = Some(0);
+    query.hits_per_page = Some(0);
+    query.facets = None;
+    query.get_ranking_info = None;
+    query.analytics = Some(false);
+    query
+}
+
+/// Search of the Query Suggestions index for suggestions close to `text`, typos allowed
+pub fn spelling_lookup_query(text: &str) -> AlgoliaSearchQuery {
+    AlgoliaSearchQuery {
+        query: suggestions::prefix_text(text),
+        hits_per_page: Some(spellfix::LOOKUP_LIMIT),
+        attributes_to_retrieve: Some(vec!["query".to_string(), "popularity".to_string()]),
+        analytics: Some(false),
+        ..AlgoliaSearchQuery::default()
+    }
+}
+
+/// Most hits Algolia returns for one query, its default `paginationLimitedTo`
+pub const LOOKUP_HITS_LIMIT: u32 = 1000;
+
+/// Search of a lookup index for the objects whose foreign field matches `keys`
+///
+/// Keys are matched as facet values, so the foreign field must be in the
+/// lookup index's `attributesForFaceting`.
+pub fn lookup_query(lookup: &LookupSpec, keys: &[String]) -> AlgoliaSearchQuery {
+    let filters = keys
+        .iter()
+        .map(|key| format!("{}:\"{}\"", lookup.foreign_field, key.replace('\\', "\\\\").replace('"', "\\\"")))
+        .collect::<Vec<_>>()
+        .join(" OR ");
+    let attributes = (!lookup.fields.is_empty()).then(|| {
+        let mut fields = lookup.fields.clone();
+        fields.push(lookup.foreign_field.clone());
+        fields
+    });
+    AlgoliaSearchQuery {
+        query: String::new(),
+        filters: Some(filters),
+        hits_per_page: Some(LOOKUP_HITS_LIMIT),
+        attributes_to_retrieve: attributes,
+        analytics: Some(false),
+        ..AlgoliaSearchQuery::default()
+    }
+}
+
 /// Convert Algolia search results to WIT SearchResults
 pub fn algolia_results_to_search_results(results: AlgoliaSearchResults) -> Result<SearchResults> {
     let hits: Result<Vec<SearchHit>> = results.hits
@@ -328,16 +689,170 @@ pub fn algolia_results_to_search_results(results: AlgoliaSearchResults) -> Resul
         None
     };
     
+    let facet_stats = results.facets_stats
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(field, stats)| FacetStats {
+            field,
+            min: stats.min,
+            max: stats.max,
+            avg: stats.avg,
+            sum: stats.sum,
+        })
+        .collect();
+    
     Ok(SearchResults {
         hits,
         total_hits: results.nb_hits,
         page: results.page,
         per_page: results.hits_per_page,
         facets: facets.unwrap_or_default(),
+        facet_stats,
         processing_time_ms: Some(results.processing_time_ms),
+        groups: None,
+        next_cursor: None,
+        relaxations: Vec::new(),
+        original_query: None,
+        corrected_query: None,
     })
 }
 
+impl RescueQuery for SearchQuery {
+    fn relaxed(&self, relaxation: &Relaxation) -> Option<Self> {
+        let text = QueryText::parse(Some(&self.query));
+        match relaxation {
+            Relaxation::DropFilters => {
+                if self.facet_filters.is_empty() && self.geo_filter.is_none() && self.id_filter.is_none() {
+                    return None;
+                }
+                Some(Self { facet_filters: Vec::new(), geo_filter: None, id_filter: None, ..self.clone() })
+            }
+            Relaxation::Typos => {
+                if text == QueryText::MatchAll {
+                    return None;
+                }
+                // Algolia allows typos by word length without a fuzziness
+                let current = parse_fuzziness(self.fuzziness.as_deref()).ok().flatten();
+                let fuzziness = rescue::relaxed_fuzziness(current, Fuzziness::Auto)?;
+                Some(Self { fuzziness: Some(fuzziness.as_str().to_string()), ..self.clone() })
+            }
+            Relaxation::AnyTerms => {
+                let current = parse_minimum_should_match(self.minimum_should_match.as_deref()).ok().flatten();
+                rescue::widens_terms(text, current)
+                    .then(|| Self { minimum_should_match: Some(MinimumShouldMatch::Any.to_string()), ..self.clone() })
+            }
+            Relaxation::Popular { sort } => {
+                // "field:order", as the other providers take sorts
+                let sort = sort.as_deref().map(|sort| match sort.rsplit_once(':') {
+                    Some((field, order)) => (field.to_string(), order.to_string()),
+                    None => (sort.to_string(), "desc".to_string()),
+                });
+                let sorted = sort
+                    .as_ref()
+                    .is_none_or(|(field, order)| self.sort_by.as_ref() == Some(field) && self.sort_order.as_ref() == Some(order));
+                let unfiltered = self.facet_filters.is_empty() && self.geo_filter.is_none() && self.id_filter.is_none();
+                if text == QueryText::MatchAll && unfiltered && sorted {
+                    return None;
+                }
+                let (sort_by, sort_order) = match sort {
+                    Some((field, order)) => (Some(field), Some(order)),
+                    None => (self.sort_by.clone(), self.sort_order.clone()),
+                };
+                Some(Self {
+                    query: String::new(),
+                    facet_filters: Vec::new(),
+                    geo_filter: None,
+                    id_filter: None,
+                    sort_by,
+                    sort_order,
+                    ..self.clone()
+                })
+            }
+        }
+    }
+
+    fn query_text(&self) -> Option<&str> {
+        match QueryText::parse(Some(&self.query)) {
+            QueryText::Text(text) => Some(text),
+            QueryText::MatchAll => None,
+        }
+    }
+
+    fn with_text(&self, text: &str) -> Self {
+        Self { query: text.to_string(), ..self.clone() }
+    }
+}
+
+impl RescueResults for SearchResults {
+    fn found_nothing(&self) -> bool {
+        self.total_hits == 0
+    }
+
+    fn set_relaxations(&mut self, relaxations: &[Relaxation]) {
+        self.relaxations = rescue::names(relaxations);
+    }
+
+    fn hit_count(&self) -> u64 {
+        self.total_hits.into()
+    }
+
+    fn set_correction(&mut self, correction: &Correction) {
+        self.original_query = Some(correction.original.clone());
+        self.corrected_query = Some(correction.corrected.clone());
+    }
+}
+
+/// Cursor of the page after `results`, unless it was the last one
+pub fn next_page_cursor(index: &str, results: &AlgoliaSearchResults) -> Option<String> {
+    let next = results.page + 1;
+    let has_next = match results.nb_pages {
+        Some(pages) => next < pages,
+        None => next.saturating_mul(results.hits_per_page) < results.nb_hits,
+    };
+    // Cursors hold 1-based pages, as on the other providers
+    has_next.then(|| PageCursor::new("algolia", index, CursorPosition::Page { page: next + 1 }).encode())
+}
+
+/// Group distinct hits by their value of `field`, in hit order
+///
+/// Group totals are read from the facet counts of `field`, which Algolia
+/// computes before deduplicating; hits without the field are left out.
+pub fn group_hits(hits: &[SearchHit], field: &str, facets: &[FacetResult]) -> Vec<SearchGroup> {
+    let counts: HashMap<&str, u32> = facets
+        .iter()
+        .filter(|facet| facet.field == field)
+        .flat_map(|facet| facet.values.iter().map(|v| (v.value.as_str(), v.count)))
+        .collect();
+    
+    let mut groups: Vec<SearchGroup> = Vec::new();
+    for hit in hits {
+        let key = match serde_json::from_str::<Value>(&hit.data).ok().and_then(|data| data.get(field).cloned()) {
+            Some(Value::Array(values)) => values.iter().map(key_text).collect(),
+            Some(Value::Null) | None => continue,
+            Some(value) => vec![key_text(&value)],
+        };
+        match groups.iter_mut().find(|group| group.key == key) {
+            Some(group) => group.hits.push(hit.clone()),
+            None => {
+                let total = match key.as_slice() {
+                    [single] => counts.get(single.as_str()).copied(),
+                    _ => None,
+                };
+                groups.push(SearchGroup { key, total, hits: vec![hit.clone()] });
+            }
+        }
+    }
+    groups
+}
+
+/// Group key text: strings as is, anything else as JSON
+fn key_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
 /// Convert Algolia search hit to WIT SearchHit
 fn algolia_hit_to_search_hit(hit: AlgoliaSearchHit) -> Result<SearchHit> {
     // Extract the data without the objectID and other Algolia-specific fields
@@ -408,11 +923,26 @@ fn algolia_hit_to_search_hit(hit: AlgoliaSearchHit) -> Result<SearchHit> {
         None
     };
     
+    // Normalized explanation envelope, shared with the other providers
+    let explanation = hit.ranking_info.as_ref().map(|ranking_info| {
+        serde_json::json!({
+            "provider": "algolia",
+            "score": score,
+            "summary": null,
+            "details": ranking_info,
+        }).to_string()
+    });
+    
+    // Only present when the query set aroundLatLng
+    let distance_meters = hit.ranking_info.as_ref().and_then(|info| info.get("geoDistance")).and_then(Value::as_f64);
+    
     Ok(SearchHit {
         id: hit.object_id,
         data: data_str,
         score: score.map(|s| s as f32),
         highlights: highlighted,
+        explanation,
+        distance_meters,
     })
 }
 
@@ -598,71 +1128,89 @@ pub fn configure_attribute_retrieval(
     }
 }
 
+/// Query parameters that may be supplied through `provider-params`
+pub const ALGOLIA_ALLOWED_PARAMS: &[&str] = &[
+    "numericFilters",
+    "tagFilters",
+    "typoTolerance",
+    "synonyms",
+    "replaceSynonymsInHighlight",
+    "minProximity",
+    "distinct",
+];
+
 /// Apply provider-specific query parameters for advanced features
+///
+/// Params must be a JSON object whose keys are listed in `ALGOLIA_ALLOWED_PARAMS`.
 pub fn apply_provider_query_params(
     query: &mut AlgoliaSearchQuery,
     provider_params: Option<&str>
 ) -> Result<()> {
-    if let Some(params_str) = provider_params {
-        if let Ok(params) = serde_json::from_str::<HashMap<String, Value>>(params_str) {
-            // Advanced filter configuration
-            if let Some(numeric_filters) = params.get("numericFilters") {
-                if let Some(filters_array) = numeric_filters.as_array() {
-                    let numeric_filter_strings: Vec<String> = filters_array
-                        .iter()
-                        .filter_map(|v| v.as_str().map(|s| s.to_string()))
-                        .collect();
-                    if !numeric_filter_strings.is_empty() {
-                        query.numeric_filters = Some(numeric_filter_strings);
-                    }
-                }
-            }
-            
-            // Tag filters
-            if let Some(tag_filters) = params.get("tagFilters") {
-                query.tag_filters = Some(tag_filters.clone());
-            }
-            
-            // Query-level typo tolerance
-            if let Some(typo_tolerance) = params.get("typoTolerance") {
-                if let Some(tolerance_str) = typo_tolerance.as_str() {
-                    query.typo_tolerance = Some(tolerance_str.to_string());
-                }
-            }
-            
-            // Synonyms configuration
-            if let Some(synonyms) = params.get("synonyms") {
-                if let Some(enabled) = synonyms.as_bool() {
-                    query.synonyms = Some(enabled);
-                }
-            }
-            
-            // Replace synonyms in highlight
-            if let Some(replace_synonyms) = params.get("replaceSynonymsInHighlight") {
-                if let Some(enabled) = replace_synonyms.as_bool() {
-                    query.replaceSynonymsInHighlight = Some(enabled);
-                }
-            }
-            
-            // Minimum proximity
-            if let Some(min_proximity) = params.get("minProximity") {
-                if let Some(proximity) = min_proximity.as_u64() {
-                    query.minProximity = Some(proximity as u32);
-                }
-            }
-            
-            // Distinct configuration
-            if let Some(distinct) = params.get("distinct") {
-                query.distinct = Some(distinct.clone());
+    let params = parse_provider_params(provider_params, ALGOLIA_ALLOWED_PARAMS)
+        .map_err(anyhow::Error::new)?;
+
+    // Advanced filter configuration
+    if let Some(numeric_filters) = params.get("numericFilters") {
+        if let Some(filters_array) = numeric_filters.as_array() {
+            let numeric_filter_strings: Vec<String> = filters_array
+                .iter()
+                .filter_map(|v| v.as_str().map(|s| s.to_string()))
+                .collect();
+            if !numeric_filter_strings.is_empty() {
+                query.numeric_filters = Some(numeric_filter_strings);
             }
         }
     }
     
+    // Tag filters
+    if let Some(tag_filters) = params.get("tagFilters") {
+        query.tag_filters = Some(tag_filters.clone());
+    }
+    
+    // Query-level typo tolerance
+    if let Some(typo_tolerance) = params.get("typoTolerance") {
+        if let Some(tolerance_str) = typo_tolerance.as_str() {
+            query.typo_tolerance = Some(tolerance_str.to_string());
+        }
+    }
+    
+    // Synonyms configuration
+    if let Some(synonyms) = params.get("synonyms") {
+        if let Some(enabled) = synonyms.as_bool() {
+            query.synonyms = Some(enabled);
+        }
+    }
+    
+    // Replace synonyms in highlight
+    if let Some(replace_synonyms) = params.get("replaceSynonymsInHighlight") {
+        if let Some(enabled) = replace_synonyms.as_bool() {
+            query.replaceSynonymsInHighlight = Some(enabled);
+        }
+    }
+    
+    // Minimum proximity
+    if let Some(min_proximity) = params.get("minProximity") {
+        if let Some(proximity) = min_proximity.as_u64() {
+            query.minProximity = Some(proximity as u32);
+        }
+    }
+    
+    // Distinct configuration
+    if let Some(distinct) = params.get("distinct") {
+        query.distinct = Some(distinct.clone());
+    }
+    
     Ok(())
 }
 
 /// Map Algolia API errors to WIT error types
 pub fn map_algolia_error(error: anyhow::Error) -> Error {
+    if let Some(shared @ (golem_search::SearchError::InvalidQuery(_) | golem_search::SearchError::IndexNotFound(_))) = error.downcast_ref() {
+        return map_shared_error(shared.clone());
+    }
+    if let Some(rejection) = circuit_breaker::open_circuit(&error).or_else(|| rate_limit::rate_limited(&error)) {
+        return map_shared_error(rejection);
+    }
     let error_message = error.to_string();
     
     // Analyze the error message to determine the appropriate error code
@@ -672,6 +1220,8 @@ pub fn map_algolia_error(error: anyhow::Error) -> Error {
         (ErrorCode::AuthenticationFailed, "Authentication failed".to_string())
     } else if error_message.contains("429") || error_message.contains("rate limit") {
         (ErrorCode::RateLimitExceeded, "Rate limit exceeded".to_string())
+    } else if error_message.contains("timed out") {
+        (ErrorCode::Timeout, deadline::timeout_error().to_string())
     } else if error_message.contains("400") || error_message.contains("invalid") {
         (ErrorCode::InvalidRequest, "Invalid query or request".to_string())
     } else if error_message.contains("unsupported") {
@@ -691,6 +1241,63 @@ pub fn map_algolia_error(error: anyhow::Error) -> Error {
     }
 }
 
+/// Map an error from the shared golem-search library
+pub fn map_shared_error(error: golem_search::SearchError) -> Error {
+    use golem_search::SearchError as Shared;
+
+    let code = match &error {
+        Shared::InvalidQuery(_) | Shared::IndexNotFound(_) | Shared::IndexAlreadyExists(_) => ErrorCode::InvalidRequest,
+        Shared::Unsupported => ErrorCode::Unsupported,
+        Shared::RateLimited { .. } => ErrorCode::RateLimitExceeded,
+        Shared::Backpressure { .. } => ErrorCode::Backpressure,
+        Shared::Timeout { .. } => ErrorCode::Timeout,
+        Shared::Unavailable { .. } => ErrorCode::Unavailable,
+        _ => ErrorCode::InternalError,
+    };
+    let retry_after = match &error {
+        Shared::Unavailable { retry_after_ms: Some(ms) } | Shared::RateLimited { retry_after_ms: Some(ms) } => {
+            Some(ms.div_ceil(1000) as u32)
+        }
+        _ => None,
+    };
+    Error { code, message: error.to_string(), retry_after }
+}
+
+/// Map a client error to the shared golem-search error, as the methods shared with the other providers return it
+pub fn map_algolia_shared_error(error: anyhow::Error) -> golem_search::SearchError {
+    use golem_search::SearchError as Shared;
+
+    if let Some(shared) = error.downcast_ref::<Shared>() {
+        return shared.clone();
+    }
+    let error = map_algolia_error(error);
+    let retry_after_ms = error.retry_after.map(|seconds| seconds as u64 * 1000);
+    match error.code {
+        ErrorCode::InvalidRequest => Shared::InvalidQuery(error.message),
+        ErrorCode::Unsupported => Shared::Unsupported,
+        ErrorCode::RateLimitExceeded => Shared::RateLimited { retry_after_ms },
+        ErrorCode::Timeout => Shared::Timeout { remaining_ms: None },
+        ErrorCode::Unavailable => Shared::Unavailable { retry_after_ms },
+        _ => Shared::Internal(error.message),
+    }
+}
+
+/// Error refusing a write while `queue_depth` tasks are pending, past `max_pending`
+///
+/// The suggested wait starts at 5 seconds and grows with how far the queue
+/// is past the limit, up to a minute.
+pub fn backpressure_error(queue_depth: u32, max_pending: u32) -> Option<Error> {
+    if queue_depth <= max_pending {
+        return None;
+    }
+    let retry_after = (5 * queue_depth as u64 / max_pending.max(1) as u64).min(60) as u32;
+    Some(Error {
+        code: ErrorCode::Backpressure,
+        message: format!("Indexing queue saturated with {} pending tasks", queue_depth),
+        retry_after: Some(retry_after),
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -728,6 +1335,26 @@ mod tests {
         assert!(settings.typo_tolerance.is_some());
     }
 
+    #[test]
+    fn test_settings_snapshot_round_trips_through_algolia_settings() {
+        let settings = json!({ "customRanking": ["desc(popularity)"], "searchableAttributes": ["title"] });
+        let synonyms = vec![
+            json!({ "objectID": "1", "type": "onewaysynonym", "input": "phone", "synonyms": ["mobile"] }),
+            json!({ "objectID": "2", "type": "synonym", "synonyms": ["color", "colour"] }),
+            json!({ "objectID": "3", "type": "placeholder", "placeholder": "<size>", "replacements": ["xl"] }),
+        ];
+
+        let snapshot = algolia_settings_to_snapshot(&settings, &synonyms);
+        assert_eq!(snapshot.ranking_rules, vec!["desc(popularity)".to_string()]);
+        assert_eq!(snapshot.synonyms.len(), 2);
+        assert_eq!(snapshot.synonyms["color"], vec!["colour".to_string()]);
+        assert_eq!(snapshot.provider_settings, Some(json!({ "searchableAttributes": ["title"] })));
+
+        let (applied, rules) = snapshot_to_algolia(&snapshot);
+        assert_eq!(applied, settings);
+        assert_eq!(algolia_settings_to_snapshot(&applied, &rules), snapshot);
+    }
+
     #[test]
     fn test_document_conversion() {
         let document = Document {
@@ -758,6 +1385,31 @@ mod tests {
         let mapped = map_algolia_error(error);
         assert!(matches!(mapped.code, ErrorCode::RateLimitExceeded));
         assert!(mapped.retry_after.is_some());
+
+        let _deadline = deadline::begin(Some(60_000));
+        let mapped = map_algolia_error(anyhow!("request timed out: no response within 250 ms"));
+        assert!(matches!(mapped.code, ErrorCode::Timeout));
+        assert!(mapped.message.ends_with("ms of the deadline left"), "{}", mapped.message);
+
+        let rejection = golem_search::SearchError::Unavailable { retry_after_ms: Some(12_500) };
+        let mapped = map_algolia_error(anyhow!(rejection));
+        assert!(matches!(mapped.code, ErrorCode::Unavailable));
+        assert_eq!(mapped.retry_after, Some(13));
+
+        let rejection = golem_search::SearchError::RateLimited { retry_after_ms: Some(2_500) };
+        let mapped = map_algolia_error(anyhow!(rejection));
+        assert!(matches!(mapped.code, ErrorCode::RateLimitExceeded));
+        assert_eq!(mapped.retry_after, Some(3));
+    }
+
+    #[test]
+    fn test_backpressure_error() {
+        assert!(backpressure_error(100, 100).is_none());
+        
+        let error = backpressure_error(300, 100).unwrap();
+        assert!(matches!(error.code, ErrorCode::Backpressure));
+        assert_eq!(error.retry_after, Some(15));
+        assert_eq!(backpressure_error(100_000, 100).unwrap().retry_after, Some(60));
     }
 
     #[test]
@@ -838,6 +1490,18 @@ mod tests {
             per_page: Some(20),
             sort_by: Some("price,popularity".to_string()),
             sort_order: Some("asc,desc".to_string()),
+            provider_params: None,
+            explain: false,
+            fuzziness: None,
+            range_facets: vec![],
+            stats_facets: vec![],
+            group_by: None,
+            cursor: None,
+            geo_filter: None,
+            geo_sort: None,
+            id_filter: None,
+            minimum_should_match: None,
+            timeout_ms: None,
         };
 
         let algolia_query = search_query_to_algolia_query(&query).unwrap();
@@ -910,10 +1574,16 @@ mod tests {
             get_ranking_info: None,
             distinct: None,
             typo_tolerance: None,
+            min_word_size_for_2_typos: None,
             analytics: None,
             synonyms: None,
             replaceSynonymsInHighlight: None,
             minProximity: None,
+            inside_polygon: None,
+            around_lat_lng: None,
+            around_radius: None,
+            optional_words: None,
+            remove_words_if_no_results: None,
         };
         
         let provider_params = r#"{
@@ -929,5 +1599,292 @@ mod tests {
         assert_eq!(query.typo_tolerance, Some("strict".to_string()));
         assert_eq!(query.synonyms, Some(false));
         assert_eq!(query.minProximity, Some(2));
+        
+        // Keys outside the allowlist are rejected rather than silently dropped
+        let rejected = apply_provider_query_params(&mut query, Some(r#"{"query": "override"}"#)).unwrap_err();
+        assert!(matches!(map_algolia_error(rejected).code, ErrorCode::InvalidRequest));
+    }
+
+    #[test]
+    fn test_fuzziness_maps_to_typo_tolerance() {
+        let mut query = SearchQuery {
+            query: "AB-1234".to_string(),
+            facet_filters: vec![],
+            page: None,
+            per_page: None,
+            sort_by: None,
+            sort_order: None,
+            provider_params: None,
+            explain: false,
+            fuzziness: Some("0".to_string()),
+            range_facets: vec![],
+            stats_facets: vec![],
+            group_by: None,
+            cursor: None,
+            geo_filter: None,
+            geo_sort: None,
+            id_filter: None,
+            minimum_should_match: None,
+            timeout_ms: None,
+        };
+        let algolia_query = search_query_to_algolia_query(&query).unwrap();
+        assert_eq!(algolia_query.typo_tolerance, Some("false".to_string()));
+        
+        query.fuzziness = Some("1".to_string());
+        let algolia_query = search_query_to_algolia_query(&query).unwrap();
+        assert_eq!(algolia_query.typo_tolerance, Some("true".to_string()));
+        assert_eq!(algolia_query.min_word_size_for_2_typos, Some(NO_SECOND_TYPO));
+        
+        query.fuzziness = Some("3".to_string());
+        assert!(search_query_to_algolia_query(&query).is_err());
+    }
+
+    #[test]
+    fn test_minimum_should_match_maps_to_optional_words() {
+        let mut algolia_query = search_query_to_algolia_query(&SearchQuery {
+            query: "red wool socks".to_string(),
+            facet_filters: vec![],
+            page: None,
+            per_page: None,
+            sort_by: None,
+            sort_order: None,
+            provider_params: None,
+            explain: false,
+            fuzziness: None,
+            range_facets: vec![],
+            stats_facets: vec![],
+            group_by: None,
+            cursor: None,
+            geo_filter: None,
+            geo_sort: None,
+            id_filter: None,
+            minimum_should_match: Some("any".to_string()),
+            timeout_ms: None,
+        }).unwrap();
+        assert_eq!(algolia_query.optional_words, Some(vec!["red wool socks".to_string()]));
+        
+        apply_minimum_should_match(&mut algolia_query, Some("-1")).unwrap();
+        assert_eq!(algolia_query.remove_words_if_no_results, Some("lastWords".to_string()));
+        assert!(apply_minimum_should_match(&mut algolia_query, Some("0")).is_err());
+    }
+
+    #[test]
+    fn test_rescue_steps_relax_the_query() {
+        let query = SearchQuery {
+            query: "red wool socks".to_string(),
+            facet_filters: vec![FacetFilter { field: "brand".to_string(), value: "acme".to_string() }],
+            page: None,
+            per_page: None,
+            sort_by: None,
+            sort_order: None,
+            provider_params: None,
+            explain: false,
+            fuzziness: Some("0".to_string()),
+            range_facets: vec![],
+            stats_facets: vec![],
+            group_by: None,
+            cursor: None,
+            geo_filter: None,
+            geo_sort: None,
+            id_filter: None,
+            minimum_should_match: None,
+            timeout_ms: None,
+        };
+        let plan = golem_search::rescue::RescuePlan::parse("drop_filters,typos,any_terms,popular=sales:desc").unwrap();
+        let attempts = plan.attempts(&query);
+        assert_eq!(rescue::names(&attempts.last().unwrap().1), ["drop_filters", "typos", "popular"]);
+        assert!(attempts[0].0.facet_filters.is_empty());
+        assert_eq!(attempts[1].0.fuzziness.as_deref(), Some("auto"));
+        let popular = &attempts[2].0;
+        assert!(popular.query.is_empty());
+        assert_eq!((popular.sort_by.as_deref(), popular.sort_order.as_deref()), (Some("sales"), Some("desc")));
+    }
+
+    #[test]
+    fn test_range_facet_count_queries() {
+        let (field, buckets) = parse_range_facet("price:[-10-0, 0-25, 50+]").unwrap();
+        assert_eq!(field, "price");
+        assert_eq!(buckets[0], RangeBucket { from: Some(-10.0), to: Some(0.0) });
+        assert_eq!(bucket_numeric_filters("price", &buckets[2]), vec!["price >= 50".to_string()]);
+        assert!(parse_range_facet("price:[25-0]").is_err());
+        assert!(parse_range_facet("price").is_err());
+        
+        let base = search_query_to_algolia_query(&SearchQuery {
+            query: "shoe".to_string(),
+            facet_filters: vec![],
+            page: Some(3),
+            per_page: Some(20),
+            sort_by: None,
+            sort_order: None,
+            provider_params: Some(r#"{"numericFilters": ["stock > 0"]}"#.to_string()),
+            explain: false,
+            fuzziness: None,
+            range_facets: vec!["price:[0-25]".to_string()],
+            stats_facets: vec![],
+            group_by: None,
+            cursor: None,
+            geo_filter: None,
+            geo_sort: None,
+            id_filter: None,
+            minimum_should_match: None,
+            timeout_ms: None,
+        }).unwrap();
+        let count = range_count_query(&base, &field, &buckets[1]);
+        assert_eq!(count.hits_per_page, Some(0));
+        assert_eq!(count.numeric_filters, Some(vec![
+            "stock > 0".to_string(),
+            "price >= 0".to_string(),
+            "price < 25".to_string(),
+        ]));
+    }
+
+    #[test]
+    fn test_stats_facets() {
+        let query = search_query_to_algolia_query(&SearchQuery {
+            query: "shoe".to_string(),
+            facet_filters: vec![],
+            page: None,
+            per_page: None,
+            sort_by: None,
+            sort_order: None,
+            provider_params: None,
+            explain: false,
+            fuzziness: None,
+            range_facets: vec![],
+            stats_facets: vec!["price".to_string(), " price ".to_string()],
+            group_by: None,
+            cursor: None,
+            geo_filter: None,
+            geo_sort: None,
+            id_filter: None,
+            minimum_should_match: None,
+            timeout_ms: None,
+        }).unwrap();
+        assert_eq!(query.facets, Some(vec!["price".to_string()]));
+
+        let results: AlgoliaSearchResults = serde_json::from_value(serde_json::json!({
+            "hits": [],
+            "nbHits": 3,
+            "page": 0,
+            "hitsPerPage": 20,
+            "processingTimeMS": 1,
+            "facets_stats": { "price": { "min": 5.0, "max": 40.0, "avg": 20.0, "sum": 60.0 } }
+        })).unwrap();
+        let converted = algolia_results_to_search_results(results).unwrap();
+        assert_eq!(converted.facet_stats.len(), 1);
+        assert_eq!(converted.facet_stats[0].field, "price");
+        assert_eq!(converted.facet_stats[0].avg, 20.0);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_group_hits_by_distinct_attribute() {
+        let hit = |id: &str, data: &str| SearchHit {
+            id: id.to_string(),
+            data: data.to_string(),
+            score: None,
+            highlights: None,
+            explanation: None,
+            distance_meters: None,
+        };
+        let hits = vec![
+            hit("1", r#"{"brand": "acme"}"#),
+            hit("2", r#"{"brand": "zenith"}"#),
+            hit("3", r#"{"brand": "acme"}"#),
+            hit("4", r#"{"title": "unbranded"}"#),
+        ];
+        let facets = vec![FacetResult {
+            field: "brand".to_string(),
+            values: vec![FacetValue { value: "acme".to_string(), count: 7 }],
+        }];
+        let groups = group_hits(&hits, "brand", &facets);
+        assert_eq!(groups.len(), 2);
+        assert_eq!((groups[0].key.clone(), groups[0].total, groups[0].hits.len()), (vec!["acme".to_string()], Some(7), 2));
+        assert_eq!((groups[1].key.clone(), groups[1].total), (vec!["zenith".to_string()], None));
+    }
+
+    #[test]
+    fn test_page_cursor() {
+
+        let results: AlgoliaSearchResults = serde_json::from_value(json!({
+            "hits": [], "nbHits": 45, "page": 1, "hitsPerPage": 20, "nbPages": 3, "processingTimeMS": 1
+        })).unwrap();
+        let next = next_page_cursor("products", &results).unwrap();
+        let cursor = PageCursor::parse_for(&next, "algolia", "products").unwrap();
+        assert_eq!(cursor.position, CursorPosition::Page { page: 3 });
+        assert_eq!(cursor.offset(20) / 20, 2);
+        assert!(PageCursor::parse_for(&next, "algolia", "orders").is_err());
+
+        let last: AlgoliaSearchResults = serde_json::from_value(json!({
+            "hits": [], "nbHits": 45, "page": 2, "hitsPerPage": 20, "processingTimeMS": 1
+        })).unwrap();
+        assert!(next_page_cursor("products", &last).is_none());
+    }
+
+    #[test]
+    fn test_lookup_query_quotes_keys_and_keeps_the_join_field() {
+        let lookup = LookupSpec {
+            index: "authors".to_string(),
+            local_field: "author_id".to_string(),
+            foreign_field: "id".to_string(),
+            fields: vec!["name".to_string()],
+        };
+        let query = lookup_query(&lookup, &["a1".to_string(), "say \"hi\"".to_string()]);
+        assert_eq!(query.filters.as_deref(), Some(r#"id:"a1" OR id:"say \"hi\"""#));
+        assert_eq!(query.attributes_to_retrieve, Some(vec!["name".to_string(), "id".to_string()]));
+        assert_eq!(query.hits_per_page, Some(LOOKUP_HITS_LIMIT));
+    }
+
+    #[test]
+    fn test_id_sets_over_the_limit_are_searched_in_batches() {
+        let ids: Vec<String> = (0..1001).map(|i| format!("\"d{}\"", i)).collect();
+        let query = SearchQuery {
+            query: "".to_string(),
+            facet_filters: vec![],
+            page: Some(1),
+            per_page: Some(2),
+            sort_by: None,
+            sort_order: None,
+            provider_params: None,
+            explain: false,
+            fuzziness: None,
+            range_facets: vec![],
+            stats_facets: vec![],
+            group_by: None,
+            cursor: None,
+            geo_filter: None,
+            geo_sort: None,
+            id_filter: Some(format!("id_set(id, [{}])", ids.join(", "))),
+            minimum_should_match: None,
+            timeout_ms: None,
+        };
+        let batches = id_filter_batches(&query).unwrap();
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[1], r#"id:"d1000""#);
+
+        let algolia_query = search_query_to_algolia_query(&query).unwrap();
+        assert_eq!(algolia_query.filters.as_ref(), Some(&batches[0]));
+        let batch_query = id_batch_query(&algolia_query, &batches[1]);
+        assert_eq!(batch_query.filters.as_ref(), Some(&batches[1]));
+        assert_eq!((batch_query.page, batch_query.hits_per_page), (Some(0), Some(4)));
+
+        let batch = |ids: &[&str], nb_hits: u32, avg: f64, sum: f64| -> AlgoliaSearchResults {
+            serde_json::from_value(json!({
+                "hits": ids.iter().map(|id| json!({ "objectID": id })).collect::<Vec<_>>(),
+                "nbHits": nb_hits, "page": 0, "hitsPerPage": 4, "nbPages": 1, "processingTimeMS": 2,
+                "facets": { "brand": { "acme": nb_hits } },
+                "facets_stats": { "price": { "min": avg - 1.0, "max": avg + 1.0, "avg": avg, "sum": sum } }
+            })).unwrap()
+        };
+        let merged = merge_id_batches(vec![
+            batch(&["a1", "a2", "a3", "a4"], 6, 10.0, 60.0),
+            batch(&["b1", "a2"], 2, 30.0, 60.0),
+        ], 1, 2);
+        let ids: Vec<&str> = merged.hits.iter().map(|hit| hit.object_id.as_str()).collect();
+        assert_eq!(ids, vec!["a2", "a3"]);
+        assert_eq!((merged.nb_hits, merged.page, merged.nb_pages), (8, 1, None));
+        assert_eq!(merged.facets.as_ref().unwrap()["brand"]["acme"], 8);
+        let price = merged.facets_stats.as_ref().unwrap()["price"];
+        assert_eq!((price.min, price.max, price.sum, price.avg), (9.0, 31.0, 120.0, 15.0));
+        assert!(next_page_cursor("products", &merged).is_some());
+    }
+}
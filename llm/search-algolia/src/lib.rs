@@ -1,3 +1,5 @@
+use std::future::Future;
+use std::rc::Rc;
 use anyhow::Result;
 use log::{error, info, warn};
 
@@ -6,8 +8,61 @@ pub mod client;
 mod conversions;
 
 use bindings::*;
-use client::{AlgoliaClient, AlgoliaConfig};
+use client::{AlgoliaClient, AlgoliaConfig, AlgoliaSearchQuery, AlgoliaSearchResults};
 use conversions::*;
+use golem_search::{IndexSettings, LookupSpec, SettingsDiff};
+use golem_search::audit;
+use golem_search::batching::{submit_adaptive, AdaptiveBatchConfig, AdaptiveBatcher};
+use golem_search::blob;
+use golem_search::capabilities::algolia_capability_matrix;
+use golem_search::checksum;
+use golem_search::cursor::PageCursor;
+use golem_search::deadline;
+use golem_search::delta_sync::{self, IndexFingerprint, SyncReport};
+use golem_search::field_mask::FieldMask;
+use golem_search::ingest;
+use golem_search::instance::{self, ProviderCache};
+use golem_search::lookup::{check_lookup_complete, fetch_in_batches, lookup_keys_from_contents, LookupTable};
+use golem_search::rescue::{self, RescuePlan, RescueQuery, RescueResults};
+use golem_search::snapshot::{self, SnapshotInfo, RESTORE_BATCH_SIZE};
+use golem_search::spellfix;
+use golem_search::suggestions::{self, Suggestion};
+use golem_search::templates::{self, IndexTemplate, TemplateOverrides};
+use serde_json::{json, Value};
+
+thread_local! {
+    static PROVIDER: ProviderCache<AlgoliaSearchProvider> = const { ProviderCache::new() };
+}
+
+/// Run a future on the instance's runtime
+fn block_on<F: Future>(future: F) -> Result<F::Output, Error> {
+    Ok(instance::runtime().map_err(map_shared_error)?.block_on(future))
+}
+
+/// Send objects through `send` in adaptively sized chunks, recording checksums per accepted chunk
+///
+/// Objects are paired with the document recorded once their chunk is
+/// accepted; objects without an ID have none.
+async fn upsert_chunks<F, Fut>(
+    index: &str,
+    objects: Vec<(Value, Option<golem_search::types::Doc>)>,
+    max_batch_size: usize,
+    send: F,
+) -> Result<golem_search::batching::BatchMetrics, golem_search::batching::BulkError>
+where
+    F: Fn(Vec<Value>) -> Fut,
+    Fut: Future<Output = Result<()>>,
+{
+    let mut batcher = AdaptiveBatcher::new(AdaptiveBatchConfig::with_max(max_batch_size));
+    let send = &send;
+    submit_adaptive(objects, &mut batcher, |chunk: Vec<(Value, Option<golem_search::types::Doc>)>| async move {
+        let (objects, written): (Vec<Value>, Vec<_>) = chunk.into_iter().unzip();
+        send(objects).await?;
+        checksum::record(index, &written.into_iter().flatten().collect::<Vec<_>>());
+        Ok::<_, anyhow::Error>(())
+    })
+    .await
+}
 
 /// The main Algolia search provider implementation
 pub struct AlgoliaSearchProvider {
@@ -41,10 +96,317 @@ impl AlgoliaSearchProvider {
         Ok(Self { client })
     }
 
+    /// The instance's provider, built with its configuration and client on first use
+    fn shared() -> Result<Rc<Self>, Error> {
+        instance::get_or_try_init_sync(&PROVIDER, Self::new)
+    }
+
     /// Get the client for internal use
     fn get_client(&self) -> &AlgoliaClient {
         &self.client
     }
+
+    /// Write an index's settings and every object as an NDJSON snapshot
+    async fn create_snapshot(&self, index: &str) -> Result<SnapshotInfo, Error> {
+        let settings = self.client.get_settings(index).await.map_err(map_algolia_error)?;
+
+        let mut objects = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let page = self.client.browse(index, cursor.as_deref()).await.map_err(map_algolia_error)?;
+            if let Some(hits) = page.get("hits").and_then(Value::as_array) {
+                objects.extend(hits.iter().cloned());
+            }
+            cursor = page.get("cursor").and_then(Value::as_str).map(str::to_string);
+            if cursor.is_none() {
+                break;
+            }
+        }
+
+        let sink = blob::from_env().map_err(map_shared_error)?;
+        snapshot::write_ndjson(sink.as_ref(), index, "algolia", settings, &objects).map_err(map_shared_error)
+    }
+
+    /// Converge an index to `documents`, adding new and changed objects and deleting missing ones
+    async fn sync(&self, index: &str, documents: Vec<Document>, dry_run: bool) -> Result<SyncReport, Error> {
+        let mut docs = Vec::with_capacity(documents.len());
+        for document in documents {
+            let Some(id) = document.id else {
+                return Err(Error {
+                    code: ErrorCode::InvalidRequest,
+                    message: "Every document needs an ID to be synced".to_string(),
+                    retry_after: None,
+                });
+            };
+            docs.push(golem_search::types::Doc { id, content: document.data });
+        }
+
+        let mut existing = IndexFingerprint::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let page = self.client.browse(index, cursor.as_deref()).await.map_err(map_algolia_error)?;
+            for hit in page.get("hits").and_then(Value::as_array).into_iter().flatten() {
+                let mut object = hit.clone();
+                let id = object.as_object_mut().and_then(|fields| fields.remove("objectID"));
+                if let Some(Value::String(id)) = id {
+                    existing.add(&id, &object);
+                }
+            }
+            cursor = page.get("cursor").and_then(Value::as_str).map(str::to_string);
+            if cursor.is_none() {
+                break;
+            }
+        }
+
+        delta_sync::plan(index, existing, docs, dry_run)
+            .map_err(map_shared_error)?
+            .execute(|batch| async move {
+                let upserts = ingest::apply(index, &batch.upserts).map_err(map_shared_error)?;
+                if !upserts.is_empty() {
+                    let objects = upserts
+                        .iter()
+                        .map(|doc| document_to_algolia_object(&Document { id: Some(doc.id.clone()), data: doc.content.clone() }))
+                        .map(|converted| converted.map(|(_, object)| object))
+                        .collect::<Result<Vec<_>>>()
+                        .map_err(map_algolia_error)?;
+                    self.client.batch_objects(index, &objects).await.map_err(map_algolia_error)?;
+                    checksum::record(index, upserts.iter());
+                }
+                if !batch.deletes.is_empty() {
+                    self.client.delete_objects(index, &batch.deletes).await.map_err(map_algolia_error)?;
+                    checksum::forget(index, &batch.deletes);
+                }
+                Ok(())
+            })
+            .await
+    }
+
+    /// Replace an index's objects and settings with those of an NDJSON snapshot
+    async fn restore_snapshot(&self, snapshot_id: &str) -> Result<SnapshotInfo, Error> {
+        let sink = blob::from_env().map_err(map_shared_error)?;
+        let (header, objects) = snapshot::read_ndjson(sink.as_ref(), snapshot_id, "algolia")
+            .map_err(map_shared_error)?;
+        let index = header.index.as_str();
+
+        let existing = self.client.list_indices().await.map_err(map_algolia_error)?;
+        if existing.iter().any(|name| name == index) {
+            self.client.clear_objects(index).await.map_err(map_algolia_error)?;
+            checksum::forget_index(index);
+        }
+        self.client.set_settings(index, &header.settings).await.map_err(map_algolia_error)?;
+        for chunk in objects.chunks(RESTORE_BATCH_SIZE) {
+            self.client.batch_objects(index, chunk).await.map_err(map_algolia_error)?;
+        }
+        Ok(header.info(None))
+    }
+
+    /// Create an index from a `golem_search::templates` blueprint
+    ///
+    /// Algolia has no custom stop word lists, so the template's are skipped;
+    /// `removeStopWords` can be set per query instead.
+    async fn create_index_from_template(
+        &self,
+        name: &str,
+        template: &str,
+        overrides: Option<&str>,
+    ) -> Result<IndexTemplate, Error> {
+        let overrides = TemplateOverrides::from_json(overrides).map_err(map_shared_error)?;
+        let template = templates::instantiate(template, &overrides).map_err(map_shared_error)?;
+        let mut settings = shared_schema_to_algolia_settings(&template.schema);
+        settings["customRanking"] = json!(template.settings("algolia").ranking_rules);
+        let synonyms: Vec<Value> = template.synonyms.iter()
+            .enumerate()
+            .map(|(position, (term, equivalents))| json!({
+                "objectID": format!("template-{}", position),
+                "type": "onewaysynonym",
+                "input": term,
+                "synonyms": equivalents,
+            }))
+            .collect();
+
+        self.client.create_index(name).await.map_err(map_algolia_error)?;
+        self.client.set_settings(name, &settings).await.map_err(map_algolia_error)?;
+        self.client.replace_synonyms(name, &synonyms).await.map_err(map_algolia_error)?;
+        if !template.stop_words.is_empty() {
+            warn!("Algolia has no custom stop words; skipping {} template stop words", template.stop_words.len());
+        }
+        Ok(template)
+    }
+
+    /// Export custom ranking, synonyms and the other index settings as a JSON settings snapshot
+    ///
+    /// Algolia indexes have no typed schema, so the snapshot has none; the
+    /// searchable and faceting attributes are among the provider settings.
+    pub async fn export_settings(&self, index: &str) -> golem_search::SearchResult<String> {
+        let snapshot = self.current_settings(index).await?;
+        snapshot.normalized().to_json()
+    }
+
+    /// Settings and synonyms currently applied to an index
+    async fn current_settings(&self, index: &str) -> golem_search::SearchResult<IndexSettings> {
+        let settings = self.client.get_settings(index).await.map_err(map_algolia_shared_error)?;
+        let synonyms = self.client.list_synonyms(index).await.map_err(map_algolia_shared_error)?;
+        Ok(algolia_settings_to_snapshot(&settings, &synonyms))
+    }
+
+    /// Apply a JSON settings snapshot; applying the same snapshot twice is a no-op
+    ///
+    /// A schema sets the searchable and faceting attributes, as for templates.
+    /// Algolia has no custom stop word lists or normalization settings, so
+    /// those are skipped; `removeStopWords` can be set per query instead.
+    pub async fn apply_settings(&self, index: &str, settings_json: &str) -> golem_search::SearchResult<()> {
+        audit::audited("algolia", "apply_settings", index, audit::settings_details(settings_json), async {
+            info!("Applying settings to Algolia index {}", index);
+
+            let desired = IndexSettings::from_json(settings_json)?;
+            if !desired.stop_words.is_empty() {
+                warn!("Algolia has no custom stop words; ignoring {} stop words", desired.stop_words.len());
+            }
+            if desired.normalization.is_some() {
+                warn!("Algolia keeps no normalization settings; set it in the search config instead");
+            }
+
+            let (settings, synonyms) = snapshot_to_algolia(&desired);
+            self.client.set_settings(index, &settings).await.map_err(map_algolia_shared_error)?;
+            let current = self.client.list_synonyms(index).await.map_err(map_algolia_shared_error)?;
+            if algolia_settings_to_snapshot(&Value::Null, &current).synonyms != desired.synonyms {
+                self.client.replace_synonyms(index, &synonyms).await.map_err(map_algolia_shared_error)?;
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    /// Report drift between the index and a desired settings snapshot, optionally reconciling it
+    ///
+    /// Stop words, which Algolia cannot hold, and the schema, which it keeps
+    /// as attribute settings, are left out of the comparison.
+    pub async fn diff_settings(&self, index: &str, desired_json: &str, reconcile: bool) -> golem_search::SearchResult<SettingsDiff> {
+        let desired = IndexSettings::from_json(desired_json)?;
+        let desired = IndexSettings { schema: None, stop_words: Vec::new(), ..desired };
+
+        let mut diff = self.current_settings(index).await?.diff(&desired);
+
+        if reconcile && !diff.is_empty() {
+            info!("Reconciling {} setting changes on Algolia index {}", diff.changes.len(), index);
+            self.apply_settings(index, desired_json).await?;
+            diff.reconciled = true;
+        }
+
+        Ok(diff)
+    }
+
+    /// Objects of the lookup index whose foreign field matches `keys`
+    ///
+    /// A query returns at most [`LOOKUP_HITS_LIMIT`] objects, so keys matching
+    /// more fail the lookup rather than join part of them.
+    async fn lookup_objects(&self, lookup: &LookupSpec, keys: Vec<String>) -> Result<Vec<Value>, Error> {
+        let response = self.client.search(&lookup.index, &lookup_query(lookup, &keys)).await
+            .map_err(map_algolia_error)?;
+        let objects: Vec<Value> = response.hits
+            .into_iter()
+            .map(|hit| {
+                let mut object = hit.data;
+                object["objectID"] = json!(hit.object_id);
+                object
+            })
+            .collect();
+        check_lookup_complete(lookup, objects.len(), Some(response.nb_hits as u64)).map_err(map_shared_error)?;
+        Ok(objects)
+    }
+
+    /// Suggestions from the Query Suggestions index of `index` close to `text`, to correct its spelling with
+    fn spelling_candidates(&self, index: &str, text: &str) -> Result<Vec<Suggestion>, Error> {
+        let target = suggestions::suggestions_index(index);
+        let results = block_on(self.client.search(&target, &spelling_lookup_query(text)))?
+            .map_err(map_algolia_error)?;
+        Ok(results.hits.iter().filter_map(|hit| suggestions::from_document(&hit.data)).collect())
+    }
+
+    /// Search `index` with `query`, one search per batch of `id_batches` when the ID set spans more than one
+    async fn search_batched(&self, index: &str, query: &AlgoliaSearchQuery, id_batches: &[String]) -> Result<AlgoliaSearchResults> {
+        if id_batches.len() <= 1 {
+            return self.client.search(index, query).await;
+        }
+        let mut results = Vec::with_capacity(id_batches.len());
+        for filter in id_batches {
+            results.push(self.client.search(index, &id_batch_query(query, filter)).await?);
+        }
+        Ok(merge_id_batches(
+            results,
+            query.page.unwrap_or(0),
+            query.hits_per_page.unwrap_or(DEFAULT_HITS_PER_PAGE),
+        ))
+    }
+
+    /// Run one search, with its range facet counts
+    fn search_once(&self, index: &str, query: &SearchQuery) -> Result<SearchResults, Error> {
+        let mut algolia_query = search_query_to_algolia_query(query)
+            .map_err(map_algolia_error)?;
+        if let Some(cursor) = query.cursor.as_deref() {
+            let cursor = PageCursor::parse_for(cursor, "algolia", index).map_err(map_shared_error)?;
+            let per_page = algolia_query.hits_per_page.unwrap_or(DEFAULT_HITS_PER_PAGE).max(1);
+            algolia_query.page = Some(cursor.offset(per_page) / per_page);
+        }
+        let range_facets = query.range_facets
+            .iter()
+            .map(|spec| parse_range_facet(spec))
+            .collect::<Result<Vec<_>>>()
+            .map_err(map_algolia_error)?;
+        let id_batches = id_filter_batches(query).map_err(map_algolia_error)?;
+        
+        match block_on(self.search_batched(index, &algolia_query, &id_batches))? {
+            Ok(algolia_results) => {
+                let next_cursor = next_page_cursor(index, &algolia_results);
+                let mut search_results = algolia_results_to_search_results(algolia_results)
+                    .map_err(map_algolia_error)?;
+                search_results.next_cursor = next_cursor;
+                
+                let group_by = query.group_by.as_deref().map(str::trim).filter(|f| !f.is_empty());
+                if let Some(field) = group_by {
+                    search_results.groups = Some(group_hits(&search_results.hits, field, &search_results.facets));
+                }
+                
+                // Stats and grouping fields are faceted for their stats and group
+                // totals only; drop their value counts
+                search_results.facets.retain(|facet| {
+                    let summarized = query.stats_facets.iter().any(|s| s.trim() == facet.field)
+                        || group_by == Some(facet.field.as_str());
+                    !summarized || query.facet_filters.iter().any(|f| f.field == facet.field)
+                });
+                
+                // One extra count query per bucket, since Algolia has no range facets
+                for (field, buckets) in &range_facets {
+                    let mut values = Vec::with_capacity(buckets.len());
+                    for bucket in buckets {
+                        let count_query = range_count_query(&algolia_query, field, bucket);
+                        let counted = block_on(self.search_batched(index, &count_query, &id_batches))?
+                            .map_err(map_algolia_error)?;
+                        values.push(FacetValue { value: bucket.key(), count: counted.nb_hits });
+                    }
+                    search_results.facets.push(FacetResult { field: field.clone(), values });
+                }
+                
+                // Ranking info is always requested; only surface it when asked to explain
+                if !query.explain {
+                    for hit in &mut search_results.hits {
+                        hit.explanation = None;
+                    }
+                }
+                
+                info!("Search completed. Found {} hits in {} ms", 
+                    search_results.total_hits, 
+                    search_results.processing_time_ms.unwrap_or(0)
+                );
+                
+                Ok(search_results)
+            }
+            Err(e) => {
+                error!("Search failed for index {} with query '{}': {}", index, query.query, e);
+                Err(map_algolia_error(e))
+            }
+        }
+    }
 }
 
 /// Implementation of the golem:search interface
@@ -52,7 +414,7 @@ impl exports::golem::search_algolia::search::Guest for AlgoliaSearchProvider {
     // Index Management
 
     fn create_index(name: String, schema: Schema) -> Result<(), Error> {
-        let provider = Self::new()?;
+        let provider = Self::shared()?;
         
         info!("Creating index: {}", name);
         
@@ -60,17 +422,13 @@ impl exports::golem::search_algolia::search::Guest for AlgoliaSearchProvider {
         let settings = schema_to_index_settings(&schema);
         
         // Create the index
-        if let Err(e) = tokio::task::block_in_place(|| {
-            tokio::runtime::Handle::current().block_on(provider.client.create_index(&name))
-        }) {
+        if let Err(e) = block_on(provider.client.create_index(&name))? {
             error!("Failed to create index {}: {}", name, e);
             return Err(map_algolia_error(e));
         }
         
         // Apply the settings
-        if let Err(e) = tokio::task::block_in_place(|| {
-            tokio::runtime::Handle::current().block_on(provider.client.update_index_settings(&name, &settings))
-        }) {
+        if let Err(e) = block_on(provider.client.update_index_settings(&name, &settings))? {
             warn!("Index created but failed to apply settings: {}", e);
             // Don't fail entirely if settings can't be applied
         }
@@ -80,29 +438,26 @@ impl exports::golem::search_algolia::search::Guest for AlgoliaSearchProvider {
     }
 
     fn delete_index(name: String) -> Result<(), Error> {
-        let provider = Self::new()?;
+        let provider = Self::shared()?;
         
         info!("Deleting index: {}", name);
         
-        if let Err(e) = tokio::task::block_in_place(|| {
-            tokio::runtime::Handle::current().block_on(provider.client.delete_index(&name))
-        }) {
+        if let Err(e) = block_on(provider.client.delete_index(&name))? {
             error!("Failed to delete index {}: {}", name, e);
             return Err(map_algolia_error(e));
         }
+        checksum::forget_index(&name);
         
         info!("Successfully deleted index: {}", name);
         Ok(())
     }
 
     fn list_indices() -> Result<Vec<String>, Error> {
-        let provider = Self::new()?;
+        let provider = Self::shared()?;
         
         info!("Listing indices");
         
-        match tokio::task::block_in_place(|| {
-            tokio::runtime::Handle::current().block_on(provider.client.list_indices())
-        }) {
+        match block_on(provider.client.list_indices())? {
             Ok(indices) => {
                 info!("Found {} indices", indices.len());
                 Ok(indices)
@@ -114,48 +469,161 @@ impl exports::golem::search_algolia::search::Guest for AlgoliaSearchProvider {
         }
     }
 
+    fn list_pending_tasks(index: String) -> Result<u32, Error> {
+        let provider = Self::shared()?;
+        
+        match block_on(provider.client.pending_task_count(&index))? {
+            Ok(count) => {
+                info!("Index {} has {} pending tasks", index, count);
+                Ok(count)
+            }
+            Err(e) => {
+                error!("Failed to get pending tasks for index {}: {}", index, e);
+                Err(map_algolia_error(e))
+            }
+        }
+    }
+
+    fn create_index_from_template(name: String, template: String, overrides: Option<String>) -> Result<String, Error> {
+        let provider = Self::shared()?;
+
+        info!("Creating index {} from template {}", name, template);
+
+        let template = block_on(provider.create_index_from_template(&name, &template, overrides.as_deref()))?
+            .inspect_err(|e| error!("Failed to create index {} from template {}: {}", name, template, e.message))?;
+
+        info!("Successfully created index {} from template {}", name, template.name);
+        serde_json::to_string(&template).map_err(|e| map_algolia_error(e.into()))
+    }
+
+    fn export_settings(index: String) -> Result<String, Error> {
+        let provider = Self::shared()?;
+
+        info!("Exporting settings of index: {}", index);
+
+        block_on(provider.export_settings(&index))?.map_err(map_shared_error)
+    }
+
+    fn apply_settings(index: String, settings: String) -> Result<(), Error> {
+        let provider = Self::shared()?;
+
+        block_on(provider.apply_settings(&index, &settings))?
+            .map_err(map_shared_error)
+            .inspect_err(|e| error!("Failed to apply settings to index {}: {}", index, e.message))
+    }
+
+    fn diff_settings(index: String, desired: String, reconcile: bool) -> Result<String, Error> {
+        let provider = Self::shared()?;
+
+        let diff = block_on(provider.diff_settings(&index, &desired, reconcile))?.map_err(map_shared_error)?;
+        diff.to_json().map_err(map_shared_error)
+    }
+
+    fn create_snapshot(index: String) -> Result<String, Error> {
+        let provider = Self::shared()?;
+
+        info!("Snapshotting index: {}", index);
+
+        let snapshot = block_on(provider.create_snapshot(&index))?
+            .inspect_err(|e| error!("Failed to snapshot index {}: {}", index, e.message))?;
+
+        info!("Snapshotted {} objects of index {} as {}", snapshot.documents.unwrap_or_default(), index, snapshot.id);
+        serde_json::to_string(&snapshot).map_err(|e| map_algolia_error(e.into()))
+    }
+
+    fn restore_snapshot(snapshot_id: String) -> Result<String, Error> {
+        let provider = Self::shared()?;
+
+        info!("Restoring snapshot: {}", snapshot_id);
+
+        let snapshot = block_on(provider.restore_snapshot(&snapshot_id))?
+            .inspect_err(|e| error!("Failed to restore snapshot {}: {}", snapshot_id, e.message))?;
+
+        info!("Restored index {} from snapshot {}", snapshot.index, snapshot_id);
+        serde_json::to_string(&snapshot).map_err(|e| map_algolia_error(e.into()))
+    }
+
     // Document Operations
 
     fn upsert_documents(index: String, documents: Vec<Document>) -> Result<u32, Error> {
-        let provider = Self::new()?;
+        let provider = Self::shared()?;
         
         info!("Upserting {} documents in index {}", documents.len(), index);
         
+        if let Some(max_pending) = provider.client.max_pending_tasks() {
+            let pending = block_on(provider.client.pending_task_count(&index))?
+                .map_err(map_algolia_error)?;
+            if let Some(error) = backpressure_error(pending, max_pending) {
+                warn!("Refusing upsert into index {}: {}", index, error.message);
+                return Err(error);
+            }
+        }
+        
         // Convert all documents to Algolia objects
+        let processed = documents.len() as u32;
         let mut algolia_objects = Vec::new();
-        let mut object_ids = Vec::new();
         
-        for document in documents {
-            let (object_id, algolia_object) = document_to_algolia_object(&document)
+        for mut document in documents {
+            let id = document.id.clone().unwrap_or_default();
+            document.data = ingest::apply_document(&index, &id, &document.data).map_err(map_shared_error)?.into_owned();
+            // Objects without an ID get a new one from Algolia, so only those with one can be unchanged
+            let written = match &document.id {
+                Some(id) => {
+                    let doc = golem_search::types::Doc { id: id.clone(), content: document.data.clone() };
+                    if checksum::is_unchanged(&index, &doc) {
+                        continue;
+                    }
+                    Some(doc)
+                }
+                None => None,
+            };
+            let (_, algolia_object) = document_to_algolia_object(&document)
                 .map_err(map_algolia_error)?;
-            object_ids.push(object_id);
-            algolia_objects.push(algolia_object);
+            algolia_objects.push((algolia_object, written));
+        }
+        if algolia_objects.is_empty() {
+            info!("All {} documents for index {} are unchanged", processed, index);
+            return Ok(processed);
         }
         
-        // Batch upsert
-        match tokio::task::block_in_place(|| {
-            tokio::runtime::Handle::current().block_on(provider.client.batch_objects(&index, &algolia_objects))
-        }) {
-            Ok(_) => {
-                info!("Successfully upserted {} documents in index {}", object_ids.len(), index);
-                Ok(object_ids.len() as u32)
+        // Batch upsert in chunks of at most max_batch_size objects
+        let max_batch_size = algolia_capability_matrix().performance_limits.max_batch_size.unwrap_or(1000) as usize;
+        let (client, index) = (&provider.client, index.as_str());
+        let submitted = block_on(upsert_chunks(index, algolia_objects, max_batch_size, |objects| async move {
+            client.batch_objects(index, &objects).await.map(drop)
+        }))?;
+        match submitted {
+            Ok(metrics) => {
+                info!("Successfully upserted {} documents in index {} in {} batches", metrics.items_submitted, index, metrics.batch_sizes.len());
+                // Skipped unchanged documents count as processed
+                Ok(processed)
             }
             Err(e) => {
                 error!("Failed to batch upsert documents in index {}: {}", index, e);
-                Err(map_algolia_error(e))
+                Err(map_algolia_error(e.into()))
             }
         }
     }
 
-    fn get_document(index: String, id: String) -> Result<Document, Error> {
-        let provider = Self::new()?;
+    fn set_ingest_pipeline(index: String, pipeline: Option<String>) -> Result<(), Error> {
+        let pipeline = pipeline.as_deref().map(ingest::IngestPipeline::from_json).transpose().map_err(map_shared_error)?;
+        ingest::set_pipeline(&index, pipeline).map_err(map_shared_error)
+    }
+
+    fn get_document(index: String, id: String, fields: Option<Vec<String>>) -> Result<Document, Error> {
+        let provider = Self::shared()?;
+        let mask = FieldMask::from_fields(fields.as_deref()).map_err(map_shared_error)?;
+        // Algolia retrieves top-level attributes; nested paths are projected here
+        let attributes = mask.as_ref().map(FieldMask::top_level);
         
         info!("Getting document {} from index {}", id, index);
         
-        match tokio::task::block_in_place(|| {
-            tokio::runtime::Handle::current().block_on(provider.client.get_object(&index, &id))
-        }) {
+        match block_on(provider.client.get_object(&index, &id, attributes.as_deref()))? {
             Ok(algolia_object) => {
+                let algolia_object = match &mask {
+                    Some(mask) => mask.project(&algolia_object),
+                    None => algolia_object,
+                };
                 let document = algolia_object_to_document(id.clone(), algolia_object)
                     .map_err(map_algolia_error)?;
                 info!("Successfully retrieved document {} from index {}", id, index);
@@ -168,17 +636,68 @@ impl exports::golem::search_algolia::search::Guest for AlgoliaSearchProvider {
         }
     }
 
+    fn get_documents(index: String, ids: Vec<String>, fields: Option<Vec<String>>) -> Result<Vec<Option<Document>>, Error> {
+        let provider = Self::shared()?;
+        let mask = FieldMask::from_fields(fields.as_deref()).map_err(map_shared_error)?;
+        let attributes = mask.as_ref().map(FieldMask::top_level);
+        
+        info!("Getting {} documents from index {}", ids.len(), index);
+        
+        match block_on(provider.client.get_objects(&index, &ids, attributes.as_deref()))? {
+            Ok(objects) => objects
+                .into_iter()
+                .zip(ids)
+                .map(|(object, id)| {
+                    object
+                        .map(|object| match &mask {
+                            Some(mask) => mask.project(&object),
+                            None => object,
+                        })
+                        .map(|object| algolia_object_to_document(id, object).map_err(map_algolia_error))
+                        .transpose()
+                })
+                .collect(),
+            Err(e) => {
+                error!("Failed to get documents from index {}: {}", index, e);
+                Err(map_algolia_error(e))
+            }
+        }
+    }
+
+    fn document_exists(index: String, id: String) -> Result<bool, Error> {
+        let provider = Self::shared()?;
+        
+        block_on(provider.client.object_exists(&index, &id))?.map_err(|e| {
+            error!("Failed to check document {} in index {}: {}", id, index, e);
+            map_algolia_error(e)
+        })
+    }
+
+    fn sync(index: String, documents: Vec<Document>, dry_run: bool) -> Result<String, Error> {
+        let provider = Self::shared()?;
+
+        info!("Syncing {} documents into index {}", documents.len(), index);
+
+        let report = block_on(provider.sync(&index, documents, dry_run))?
+            .inspect_err(|e| error!("Failed to sync index {}: {}", index, e.message))?;
+
+        info!(
+            "Synced index {}: {} upserted, {} deleted, {} unchanged",
+            index, report.upserted, report.deleted, report.unchanged
+        );
+        serde_json::to_string(&report).map_err(|e| map_algolia_error(e.into()))
+    }
+
     fn delete_documents(index: String, ids: Vec<String>) -> Result<u32, Error> {
-        let provider = Self::new()?;
+        let provider = Self::shared()?;
         
         info!("Deleting {} documents from index {}", ids.len(), index);
         
-        if let Err(e) = tokio::task::block_in_place(|| {
-            tokio::runtime::Handle::current().block_on(provider.client.delete_objects(&index, &ids))
-        }) {
+        if let Err(e) = block_on(provider.client.delete_objects(&index, &ids))? {
             error!("Failed to delete {} documents from index {}: {}", ids.len(), index, e);
             return Err(map_algolia_error(e));
         }
+        checksum::forget(&index, &ids);
         
         info!("Successfully deleted {} documents from index {}", ids.len(), index);
         Ok(ids.len() as u32)
@@ -186,33 +705,64 @@ impl exports::golem::search_algolia::search::Guest for AlgoliaSearchProvider {
 
     // Search Operations
 
+    // Unlike the other providers, Algolia is not wired into the shared result
+    // cache: every search reaches Algolia, so writes need no invalidation here.
     fn search(index: String, query: SearchQuery) -> Result<SearchResults, Error> {
-        let provider = Self::new()?;
+        let provider = Self::shared()?;
         
         info!("Searching index {} with query: '{}'", index, query.query);
+        let _deadline = deadline::begin(query.timeout_ms);
         
-        let algolia_query = search_query_to_algolia_query(&query)
-            .map_err(map_algolia_error)?;
-        
-        match tokio::task::block_in_place(|| {
-            tokio::runtime::Handle::current().block_on(provider.client.search(&index, &algolia_query))
-        }) {
-            Ok(algolia_results) => {
-                let search_results = algolia_results_to_search_results(algolia_results)
-                    .map_err(map_algolia_error)?;
-                
-                info!("Search completed. Found {} hits in {} ms", 
-                    search_results.total_hits, 
-                    search_results.processing_time_ms.unwrap_or(0)
-                );
-                
-                Ok(search_results)
+        // Searches block on the runtime, so the rescue steps run here rather than in rescue::search_rescued
+        let plan = RescuePlan::from_env();
+        let mut results = provider.search_once(&index, &query)?;
+        if let (Some(config), Some(text)) = (plan.spellfix(), query.query_text()) {
+            if config.checks(results.hit_count()) {
+                match provider.spelling_candidates(&index, text) {
+                    Ok(candidates) => {
+                        if let Some(correction) = spellfix::best_correction(text, &candidates, config.min_confidence) {
+                            let mut corrected = provider.search_once(&index, &query.with_text(&correction.corrected))?;
+                            if config.improves(results.hit_count(), corrected.hit_count()) {
+                                info!("Query on {} corrected from \"{}\" to \"{}\"", index, correction.original, correction.corrected);
+                                corrected.set_correction(&correction);
+                                results = corrected;
+                            }
+                        }
+                    }
+                    Err(e) => warn!("Spelling lookup on {} failed, searching the query as typed: {}", index, e.message),
+                }
             }
-            Err(e) => {
-                error!("Search failed for index {} with query '{}': {}", index, query.query, e);
-                Err(map_algolia_error(e))
+        }
+        if !results.found_nothing() {
+            return Ok(results);
+        }
+        for (relaxed, relaxations) in plan.attempts(&query) {
+            let mut rescued = provider.search_once(&index, &relaxed)?;
+            if !rescued.found_nothing() {
+                info!("Query on {} found nothing; rescued with {}", index, rescue::names(&relaxations).join(", "));
+                rescued.set_relaxations(&relaxations);
+                return Ok(rescued);
             }
         }
+        Ok(results)
+    }
+
+    fn search_with_lookup(index: String, query: SearchQuery, lookup: String) -> Result<SearchResults, Error> {
+        let lookup = LookupSpec::from_json(&lookup).map_err(map_shared_error)?;
+        let mut results = Self::search(index, query)?;
+        let keys = lookup_keys_from_contents(results.hits.iter().map(|hit| Some(hit.data.as_str())), &lookup);
+        if keys.is_empty() {
+            return Ok(results);
+        }
+
+        info!("Looking up {} keys in index {}", keys.len(), lookup.index);
+        let provider = Self::shared()?;
+        let docs = block_on(fetch_in_batches(&keys, |batch| provider.lookup_objects(&lookup, batch)))??;
+        let table = LookupTable::new(&lookup, &docs);
+        for hit in &mut results.hits {
+            hit.data = table.enrich(&hit.data).map_err(map_shared_error)?;
+        }
+        Ok(results)
     }
 }
 
@@ -256,4 +806,33 @@ mod tests {
         let settings = schema_to_index_settings(&schema);
         assert!(settings.searchable_attributes.is_some());
     }
+
+    #[test]
+    fn test_upsert_chunks_records_only_accepted_chunks() {
+        std::env::set_var(checksum::SKIP_UNCHANGED_ENV, "true");
+        let index = "upsert-chunks-test";
+        let docs: Vec<golem_search::types::Doc> = (0..25)
+            .map(|n| golem_search::types::Doc { id: n.to_string(), content: json!({ "n": n }).to_string() })
+            .collect();
+        let objects = docs.iter().map(|doc| (json!({ "objectID": doc.id }), Some(doc.clone()))).collect();
+
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let err = runtime
+            .block_on(upsert_chunks(index, objects, 10, |objects| async move {
+                if objects.iter().any(|object| object["objectID"] == "12") {
+                    Err(anyhow::anyhow!("Algolia API error 400: invalid object"))
+                } else {
+                    Ok(())
+                }
+            }))
+            .unwrap_err();
+
+        assert!(checksum::is_enabled());
+        let recorded: Vec<bool> = docs.iter().map(|doc| checksum::is_unchanged(index, doc)).collect();
+        assert_eq!(recorded, (0..25).map(|n| !(10..20).contains(&n)).collect::<Vec<_>>());
+        assert_eq!(err.failures.len(), 1);
+        assert_eq!((err.failures[0].offset, err.failures[0].len), (10, 10));
+        assert!(err.to_string().contains("[10..20] Algolia API error 400"));
+        checksum::forget_index(index);
+    }
 }
\ No newline at end of file
@@ -1,13 +1,24 @@
 //! ElasticSearch client implementation with authentication and connection management
 
 use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::Duration;
 use anyhow::{anyhow, Result};
-use reqwest::{Client, Method, Response, header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE}};
+use golem_search::http::{self, Client, Method, Response, header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_ENCODING, CONTENT_TYPE}};
 use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
-use url::Url;
 use base64::Engine as _;
+use log::debug;
+use golem_search::config::ConfigProfile;
+use golem_search::endpoint_pool::{self, EndpointPool};
+use golem_search::json;
+use golem_search::rate_limit::{self, Throttle};
+use golem_search::request_id::{self, REQUEST_ID_HEADER};
+use golem_search::retry::RetryPolicy;
+use golem_search::routing;
+use golem_search::version::ServerVersion;
+use golem_search::compression::Compression;
+use golem_search::ndjson::NdjsonWriter;
 
 /// Configuration for the ElasticSearch client
 #[derive(Debug, Clone)]
@@ -19,11 +30,25 @@ pub struct ElasticConfig {
     pub cloud_id: Option<String>,
     pub timeout: Duration,
     pub max_retries: u32,
+    pub compression: Compression,
 }
 
 impl ElasticConfig {
     /// Create configuration from environment variables
     pub fn from_env() -> Result<Self> {
+        if let Some(profile) = ConfigProfile::active("elasticsearch")? {
+            return Ok(Self {
+                endpoint: profile.endpoint,
+                username: profile.username,
+                password: profile.password,
+                api_key: profile.api_key,
+                cloud_id: None,
+                timeout: profile.timeout,
+                max_retries: profile.max_retries,
+                compression: Compression::from_env(),
+            });
+        }
+
         let endpoint = std::env::var("SEARCH_PROVIDER_ENDPOINT")
             .or_else(|_| std::env::var("ELASTICSEARCH_ENDPOINT"))
             .unwrap_or_else(|_| "http://localhost:9200".to_string());
@@ -67,6 +92,7 @@ impl ElasticConfig {
             cloud_id,
             timeout: Duration::from_secs(timeout),
             max_retries,
+            compression: Compression::from_env(),
         })
     }
 }
@@ -93,11 +119,41 @@ fn parse_cloud_id(cloud_id: &str) -> Result<String> {
     Ok(format!("https://{}", endpoint_parts[0]))
 }
 
+/// `_source_includes` query string limiting the returned source to `fields`, empty without fields
+fn source_includes(fields: Option<&[String]>) -> String {
+    match fields {
+        Some(fields) => {
+            let fields: String = url::form_urlencoded::byte_serialize(fields.join(",").as_bytes()).collect();
+            format!("?_source_includes={}", fields)
+        }
+        None => String::new(),
+    }
+}
+
+/// Whether a `GET _doc` answer found its document
+///
+/// Both a missing document and a missing index answer 404, but only the
+/// index's carries an `error`, such as `index_not_found_exception`.
+fn document_presence(status: u16, body: &str) -> Result<bool> {
+    match status {
+        200..=299 => Ok(true),
+        404 => {
+            let answer: Value = serde_json::from_str(body).unwrap_or_default();
+            match answer["error"]["type"].as_str() {
+                Some(kind) => Err(anyhow!("{}: HTTP 404 {}", kind, body)),
+                None => Ok(false),
+            }
+        }
+        status => Err(anyhow!("Failed to check document existence: HTTP {} {}", status, body)),
+    }
+}
+
 /// ElasticSearch API client
 pub struct ElasticClient {
     config: ElasticConfig,
     http_client: Client,
-    base_url: Url,
+    endpoints: Arc<EndpointPool>,
+    throttle: Arc<Throttle>,
 }
 
 impl ElasticClient {
@@ -108,17 +164,19 @@ impl ElasticClient {
 
         let http_client = Client::builder()
             .timeout(config.timeout)
+            .retry(RetryPolicy::new(config.max_retries))
+            .accept_compressed(config.compression.is_enabled())
             .default_headers(headers)
             .build()
             .map_err(|e| anyhow!("Failed to create HTTP client: {}", e))?;
 
-        let base_url = Url::parse(&config.endpoint)
-            .map_err(|e| anyhow!("Invalid endpoint URL: {}", e))?;
+        let endpoints = endpoint_pool::pool(&config.endpoint)?;
 
         Ok(Self {
             config,
             http_client,
-            base_url,
+            endpoints,
+            throttle: rate_limit::throttle("elasticsearch"),
         })
     }
 
@@ -129,29 +187,30 @@ impl ElasticClient {
         path: &str,
         body: Option<Value>,
     ) -> Result<Response> {
-        let url = self.base_url.join(path)
-            .map_err(|e| anyhow!("Failed to build URL: {}", e))?;
-
-        let mut request = self.http_client.request(method, url);
-
-        // Add authentication
-        if let Some(ref api_key) = self.config.api_key {
-            request = request.header(AUTHORIZATION, format!("ApiKey {}", api_key));
-        } else if let (Some(ref username), Some(ref password)) = 
-            (&self.config.username, &self.config.password) {
-            let auth = base64::engine::general_purpose::STANDARD
-                .encode(format!("{}:{}", username, password));
-            request = request.header(AUTHORIZATION, format!("Basic {}", auth));
-        }
-
-        if let Some(body) = body {
-            request = request.json(&body);
-        }
-
-        let response = request.send()
-            .map_err(|e| anyhow!("Request failed: {}", e))?;
-
-        Ok(response)
+        let request_id = request_id::current_or_generate();
+        debug!("[{}] {} {}", request_id, method, path);
+
+        self.throttle.send(|| self.endpoints.send(&method, path, |url| {
+            let mut request = self.http_client
+                .request(method.clone(), url)
+                .header(REQUEST_ID_HEADER, request_id.as_str());
+
+            // Add authentication
+            if let Some(ref api_key) = self.config.api_key {
+                request = request.header(AUTHORIZATION, format!("ApiKey {}", api_key));
+            } else if let (Some(ref username), Some(ref password)) = 
+                (&self.config.username, &self.config.password) {
+                let auth = base64::engine::general_purpose::STANDARD
+                    .encode(format!("{}:{}", username, password));
+                request = request.header(AUTHORIZATION, format!("Basic {}", auth));
+            }
+
+            if let Some(ref body) = body {
+                request = request.json(body);
+            }
+
+            request.send()
+        }))
     }
 
     /// Check cluster health
@@ -166,7 +225,7 @@ impl ElasticClient {
         let response = self.request_sync(Method::PUT, name, Some(body))?;
         
         if response.status().is_success() {
-            let result: Value = response.json()
+            let result: Value = json::from_response(response)
                 .map_err(|e| anyhow!("Failed to parse response: {}", e))?;
             Ok(result)
         } else {
@@ -176,12 +235,104 @@ impl ElasticClient {
         }
     }
 
+    /// Base URL of the first node of the cluster
+    pub fn endpoint(&self) -> &str {
+        self.endpoints.primary().as_str()
+    }
+
+    /// Detect the server version from the root endpoint
+    pub async fn detect_version(&self) -> Result<ServerVersion> {
+        let response = self.request_sync(Method::GET, "", None)?;
+        if !response.status().is_success() {
+            return Err(anyhow!("Failed to get server info: HTTP {}", response.status()));
+        }
+        
+        let info: Value = json::from_response(response)
+            .map_err(|e| anyhow!("Failed to parse response: {}", e))?;
+        info.pointer("/version/number")
+            .and_then(Value::as_str)
+            .and_then(ServerVersion::parse)
+            .ok_or_else(|| anyhow!("No version in server info response"))
+    }
+
+    /// Count the documents in an index
+    pub async fn count_documents(&self, index: &str) -> Result<u64> {
+        let path = format!("{}/_count", index);
+        let response = self.request_sync(Method::GET, &path, None)?;
+        if !response.status().is_success() {
+            return Err(anyhow!("Failed to count documents: HTTP {}", response.status()));
+        }
+        
+        let result: Value = json::from_response(response)
+            .map_err(|e| anyhow!("Failed to parse response: {}", e))?;
+        result.pointer("/count")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| anyhow!("No count in response"))
+    }
+
+    /// Indexing counters of an index (`GET /{index}/_stats/indexing`)
+    pub async fn index_stats(&self, index: &str) -> Result<Value> {
+        let path = format!("{}/_stats/indexing", index);
+        let response = self.request_sync(Method::GET, &path, None)?;
+        if !response.status().is_success() {
+            return Err(anyhow!("Failed to get index stats: HTTP {}", response.status()));
+        }
+        
+        json::from_response(response)
+            .map_err(|e| anyhow!("Failed to parse response: {}", e))
+    }
+
+    /// Cluster state changes queued on the master node
+    pub async fn pending_tasks(&self) -> Result<Value> {
+        let response = self.request_sync(Method::GET, "_cluster/pending_tasks", None)?;
+        if !response.status().is_success() {
+            return Err(anyhow!("Failed to list pending tasks: HTTP {}", response.status()));
+        }
+        
+        json::from_response(response)
+            .map_err(|e| anyhow!("Failed to parse response: {}", e))
+    }
+
+    /// Count the documents matching a query
+    pub async fn count_matching(&self, index: &str, query: Value) -> Result<u64> {
+        let path = format!("{}/_count", index);
+        let response = self.request_sync(Method::POST, &path, Some(json!({ "query": query })))?;
+        if !response.status().is_success() {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(anyhow!("Failed to count documents: {}", error_text));
+        }
+
+        let result: Value = json::from_response(response)
+            .map_err(|e| anyhow!("Failed to parse response: {}", e))?;
+        result.pointer("/count")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| anyhow!("No count in response"))
+    }
+
+    /// Delete the documents matching a query, returning how many were deleted
+    pub async fn delete_by_query(&self, index: &str, query: Value) -> Result<u64> {
+        let path = format!("{}/_delete_by_query?conflicts=proceed&refresh=true", index);
+        let response = self.request_sync(Method::POST, &path, Some(json!({ "query": query })))?;
+        if !response.status().is_success() {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(anyhow!("Failed to delete by query: {}", error_text));
+        }
+
+        let result: Value = json::from_response(response)
+            .map_err(|e| anyhow!("Failed to parse response: {}", e))?;
+        result.pointer("/deleted")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| anyhow!("No deleted count in response"))
+    }
+
     /// Delete an index
     pub async fn delete_index(&self, name: &str) -> Result<Value> {
         let response = self.request_sync(Method::DELETE, name, None)?;
         
         if response.status().is_success() {
-            let result: Value = response.json()
+            let result: Value = json::from_response(response)
                 .map_err(|e| anyhow!("Failed to parse response: {}", e))?;
             Ok(result)
         } else {
@@ -191,12 +342,42 @@ impl ElasticClient {
         }
     }
 
+    /// Fetch several documents by ID in one request, with only the `source` fields when given
+    pub async fn mget(&self, index: &str, ids: &[String], source: Option<&[String]>) -> Result<Value> {
+        let path = format!("{}/_mget{}", index, source_includes(source));
+        let response = self.request_sync(Method::POST, &path, Some(json!({ "ids": ids })))?;
+        
+        if response.status().is_success() {
+            let result: Value = json::from_response(response)
+                .map_err(|e| anyhow!("Failed to parse response: {}", e))?;
+            Ok(result)
+        } else {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            Err(anyhow!("Failed to get documents: {}", error_text))
+        }
+    }
+
+    /// Refresh an index, making all acknowledged writes searchable
+    pub async fn refresh_index(&self, name: &str) -> Result<()> {
+        let path = format!("{}/_refresh", name);
+        let response = self.request_sync(Method::POST, &path, None)?;
+        
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            Err(anyhow!("Failed to refresh index: {}", error_text))
+        }
+    }
+
     /// List all indexes
     pub async fn list_indexes(&self) -> Result<Vec<String>> {
         let response = self.request_sync(Method::GET, "_cat/indices?format=json", None)?;
         
         if response.status().is_success() {
-            let indices: Vec<Value> = response.json()
+            let indices: Vec<Value> = json::from_response(response)
                 .map_err(|e| anyhow!("Failed to parse response: {}", e))?;
             
             let names = indices.into_iter()
@@ -216,17 +397,20 @@ impl ElasticClient {
     }
 
     /// Index a document
+    ///
+    /// `params` are URL parameters such as `routing` and `pipeline`.
     pub async fn index_document(
         &self,
         index: &str,
         id: &str,
         document: Value,
+        params: &[(String, String)],
     ) -> Result<Value> {
-        let path = format!("{}/_doc/{}", index, id);
+        let path = routing::with_query_params(&format!("{}/_doc/{}", index, http::path_segment(id)), params);
         let response = self.request_sync(Method::PUT, &path, Some(document))?;
         
         if response.status().is_success() {
-            let result: Value = response.json()
+            let result: Value = json::from_response(response)
                 .map_err(|e| anyhow!("Failed to parse response: {}", e))?;
             Ok(result)
         } else {
@@ -236,13 +420,13 @@ impl ElasticClient {
         }
     }
 
-    /// Get a document by ID
-    pub async fn get_document(&self, index: &str, id: &str) -> Result<Option<Value>> {
-        let path = format!("{}/_doc/{}", index, id);
+    /// Get a document by ID, with only the `source` fields when given
+    pub async fn get_document(&self, index: &str, id: &str, source: Option<&[String]>, routing: Option<&str>) -> Result<Option<Value>> {
+        let path = routing::with_routing(&format!("{}/_doc/{}{}", index, http::path_segment(id), source_includes(source)), routing);
         let response = self.request_sync(Method::GET, &path, None)?;
         
         if response.status().is_success() {
-            let result: Value = response.json()
+            let result: Value = json::from_response(response)
                 .map_err(|e| anyhow!("Failed to parse response: {}", e))?;
             Ok(Some(result))
         } else if response.status().as_u16() == 404 {
@@ -254,13 +438,23 @@ impl ElasticClient {
         }
     }
 
-    /// Delete a document by ID
-    pub async fn delete_document(&self, index: &str, id: &str) -> Result<Value> {
-        let path = format!("{}/_doc/{}", index, id);
+    /// Check whether a document exists without fetching its source
+    ///
+    /// A missing index is an error rather than an absent document.
+    pub async fn document_exists(&self, index: &str, id: &str) -> Result<bool> {
+        let path = format!("{}/_doc/{}?_source=false", index, http::path_segment(id));
+        let response = self.request_sync(Method::GET, &path, None)?;
+        let status = response.status().as_u16();
+        document_presence(status, &response.text().unwrap_or_default())
+    }
+
+    /// Delete a document by ID, from the shard of `routing` when given
+    pub async fn delete_document(&self, index: &str, id: &str, routing: Option<&str>) -> Result<Value> {
+        let path = routing::with_routing(&format!("{}/_doc/{}", index, http::path_segment(id)), routing);
         let response = self.request_sync(Method::DELETE, &path, None)?;
         
         if response.status().is_success() {
-            let result: Value = response.json()
+            let result: Value = json::from_response(response)
                 .map_err(|e| anyhow!("Failed to parse response: {}", e))?;
             Ok(result)
         } else {
@@ -271,40 +465,55 @@ impl ElasticClient {
     }
 
     /// Bulk operation
-    pub async fn bulk(&self, operations: Vec<Value>) -> Result<Value> {
-        let mut body = String::new();
-        for op in operations {
-            body.push_str(&serde_json::to_string(&op)?);
-            body.push('\n');
-        }
-
-        let url = self.base_url.join("_bulk")?;
-        let response = self.http_client
-            .post(url)
-            .header(CONTENT_TYPE, "application/x-ndjson")
-            .body(body)
-            .send()?;
-
-        if response.status().is_success() {
-            let result: Value = response.json()
+    pub async fn bulk(&self, operations: impl IntoIterator<Item = Value>) -> Result<Value> {
+        let mut writer = NdjsonWriter::new(self.config.compression);
+        writer.extend(operations)?;
+        let body = writer.finish()?;
+
+        // Bulk requests are not idempotent, so the body is sent at most once
+        let encoding = body.content_encoding();
+        let request_id = request_id::current_or_generate();
+        let mut bytes = Some(body.bytes);
+        let response = self.throttle.send(|| self.endpoints.send(&Method::POST, "_bulk", |url| {
+            let mut request = self.http_client
+                .post(url)
+                .header(CONTENT_TYPE, "application/x-ndjson")
+                .header(REQUEST_ID_HEADER, request_id.as_str());
+            if let Some(encoding) = encoding {
+                request = request.header(CONTENT_ENCODING, encoding);
+            }
+            request.body(bytes.take().unwrap_or_default()).send()
+        }))?;
+
+        let status = response.status();
+        if status.is_success() {
+            let result: Value = json::from_response(response)
                 .map_err(|e| anyhow!("Failed to parse response: {}", e))?;
             Ok(result)
         } else {
             let error_text = response.text()
                 .unwrap_or_else(|_| "Unknown error".to_string());
-            Err(anyhow!("Bulk operation failed: {}", error_text))
+            Err(anyhow!("Bulk operation failed ({}): {}", status, error_text))
         }
     }
 
     /// Search documents
     pub async fn search(&self, index: &str, query: Value) -> Result<Value> {
-        let path = format!("{}/_search", index);
+        let body = self.search_raw(index, query, &[]).await?;
+        json::from_str(&body)
+            .map_err(|e| anyhow!("Failed to parse response: {}", e))
+    }
+
+    /// Search documents, returning the unparsed response body
+    ///
+    /// `params` are URL parameters such as `routing` and `preference`.
+    pub async fn search_raw(&self, index: &str, query: Value, params: &[(String, String)]) -> Result<String> {
+        let path = routing::with_query_params(&format!("{}/_search", index), params);
         let response = self.request_sync(Method::POST, &path, Some(query))?;
         
         if response.status().is_success() {
-            let result: Value = response.json()
-                .map_err(|e| anyhow!("Failed to parse response: {}", e))?;
-            Ok(result)
+            response.text()
+                .map_err(|e| anyhow!("Failed to read response: {}", e))
         } else {
             let error_text = response.text()
                 .unwrap_or_else(|_| "Unknown error".to_string());
@@ -312,13 +521,58 @@ impl ElasticClient {
         }
     }
 
+    /// Open a scroll over every document of an index, returning the first page
+    pub async fn open_scroll(&self, index: &str, size: usize, keep_alive: &str) -> Result<Value> {
+        let path = format!("{}/_search?scroll={}", index, keep_alive);
+        let query = json!({ "size": size, "sort": ["_doc"], "query": { "match_all": {} } });
+        let response = self.request_sync(Method::POST, &path, Some(query))?;
+
+        if response.status().is_success() {
+            json::from_response(response)
+                .map_err(|e| anyhow!("Failed to parse response: {}", e))
+        } else {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            Err(anyhow!("Failed to open scroll: {}", error_text))
+        }
+    }
+
+    /// Fetch the next page of a scroll
+    pub async fn scroll(&self, scroll_id: &str, keep_alive: &str) -> Result<Value> {
+        let body = json!({ "scroll": keep_alive, "scroll_id": scroll_id });
+        let response = self.request_sync(Method::POST, "_search/scroll", Some(body))?;
+
+        if response.status().is_success() {
+            json::from_response(response)
+                .map_err(|e| anyhow!("Failed to parse response: {}", e))
+        } else {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            Err(anyhow!("Scroll failed: {}", error_text))
+        }
+    }
+
+    /// Release a scroll before it expires
+    pub async fn clear_scroll(&self, scroll_id: &str) -> Result<()> {
+        let body = json!({ "scroll_id": scroll_id });
+        let response = self.request_sync(Method::DELETE, "_search/scroll", Some(body))?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            Err(anyhow!("Failed to clear scroll: {}", error_text))
+        }
+    }
+
     /// Get index mapping
     pub async fn get_mapping(&self, index: &str) -> Result<Value> {
         let path = format!("{}/_mapping", index);
         let response = self.request_sync(Method::GET, &path, None)?;
         
         if response.status().is_success() {
-            let result: Value = response.json()
+            let result: Value = json::from_response(response)
                 .map_err(|e| anyhow!("Failed to parse response: {}", e))?;
             Ok(result)
         } else {
@@ -328,13 +582,120 @@ impl ElasticClient {
         }
     }
 
+    /// Get index settings
+    pub async fn get_settings(&self, index: &str) -> Result<Value> {
+        let path = format!("{}/_settings", index);
+        let response = self.request_sync(Method::GET, &path, None)?;
+        
+        if response.status().is_success() {
+            let result: Value = json::from_response(response)
+                .map_err(|e| anyhow!("Failed to parse response: {}", e))?;
+            Ok(result)
+        } else {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            Err(anyhow!("Failed to get settings: {}", error_text))
+        }
+    }
+
+    /// Put index settings (static settings such as analysis require a closed index)
+    pub async fn put_settings(&self, index: &str, settings: Value) -> Result<Value> {
+        let path = format!("{}/_settings", index);
+        let response = self.request_sync(Method::PUT, &path, Some(settings))?;
+        
+        if response.status().is_success() {
+            let result: Value = json::from_response(response)
+                .map_err(|e| anyhow!("Failed to parse response: {}", e))?;
+            Ok(result)
+        } else {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            Err(anyhow!("Failed to put settings: {}", error_text))
+        }
+    }
+
+    /// Close an index
+    pub async fn close_index(&self, index: &str) -> Result<()> {
+        let path = format!("{}/_close", index);
+        let response = self.request_sync(Method::POST, &path, None)?;
+        
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            Err(anyhow!("Failed to close index: {}", error_text))
+        }
+    }
+
+    /// Open a closed index
+    pub async fn open_index(&self, index: &str) -> Result<()> {
+        let path = format!("{}/_open", index);
+        let response = self.request_sync(Method::POST, &path, None)?;
+        
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            Err(anyhow!("Failed to open index: {}", error_text))
+        }
+    }
+
+    /// Snapshot indices into a registered repository, waiting until the snapshot is complete
+    pub async fn create_snapshot(&self, repository: &str, snapshot: &str, indices: &str, metadata: Value) -> Result<Value> {
+        let path = format!("_snapshot/{}/{}?wait_for_completion=true", repository, snapshot);
+        let body = json!({ "indices": indices, "include_global_state": false, "metadata": metadata });
+        let response = self.request_sync(Method::PUT, &path, Some(body))?;
+
+        if response.status().is_success() {
+            json::from_response(response)
+                .map_err(|e| anyhow!("Failed to parse response: {}", e))
+        } else {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            Err(anyhow!("Failed to create snapshot: {}", error_text))
+        }
+    }
+
+    /// Describe a snapshot in a repository
+    pub async fn get_snapshot(&self, repository: &str, snapshot: &str) -> Result<Value> {
+        let path = format!("_snapshot/{}/{}", repository, snapshot);
+        let response = self.request_sync(Method::GET, &path, None)?;
+
+        if response.status().is_success() {
+            json::from_response(response)
+                .map_err(|e| anyhow!("Failed to parse response: {}", e))
+        } else {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            Err(anyhow!("Failed to get snapshot: {}", error_text))
+        }
+    }
+
+    /// Restore indices from a snapshot, waiting until they are recovered
+    pub async fn restore_snapshot(&self, repository: &str, snapshot: &str, indices: &[String]) -> Result<Value> {
+        let path = format!("_snapshot/{}/{}/_restore?wait_for_completion=true", repository, snapshot);
+        let body = json!({ "indices": indices.join(","), "include_global_state": false });
+        let response = self.request_sync(Method::POST, &path, Some(body))?;
+
+        if response.status().is_success() {
+            json::from_response(response)
+                .map_err(|e| anyhow!("Failed to parse response: {}", e))
+        } else {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            Err(anyhow!("Failed to restore snapshot: {}", error_text))
+        }
+    }
+
     /// Put index mapping
     pub async fn put_mapping(&self, index: &str, mapping: Value) -> Result<Value> {
         let path = format!("{}/_mapping", index);
         let response = self.request_sync(Method::PUT, &path, Some(mapping))?;
         
         if response.status().is_success() {
-            let result: Value = response.json()
+            let result: Value = json::from_response(response)
                 .map_err(|e| anyhow!("Failed to parse response: {}", e))?;
             Ok(result)
         } else {
@@ -343,4 +704,65 @@ impl ElasticClient {
             Err(anyhow!("Failed to put mapping: {}", error_text))
         }
     }
-}
\ No newline at end of file
+
+    /// Indexes behind an alias with their alias settings, `None` when there is no such alias
+    pub async fn get_alias(&self, alias: &str) -> Result<Option<Value>> {
+        let path = format!("_alias/{}", alias);
+        let response = self.request_sync(Method::GET, &path, None)?;
+
+        if response.status().is_success() {
+            json::from_response(response)
+                .map(Some)
+                .map_err(|e| anyhow!("Failed to parse response: {}", e))
+        } else if response.status().as_u16() == 404 {
+            Ok(None)
+        } else {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            Err(anyhow!("Failed to get alias: {}", error_text))
+        }
+    }
+
+    /// Roll the write index of an alias over, or with `dry_run` only evaluate the conditions
+    pub async fn rollover(&self, alias: &str, body: Value, dry_run: bool) -> Result<Value> {
+        let path = format!("{}/_rollover{}", alias, if dry_run { "?dry_run=true" } else { "" });
+        let response = self.request_sync(Method::POST, &path, Some(body))?;
+
+        if response.status().is_success() {
+            json::from_response(response)
+                .map_err(|e| anyhow!("Failed to parse response: {}", e))
+        } else {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            Err(anyhow!("Failed to roll over alias: {}", error_text))
+        }
+    }
+
+    /// Apply alias actions in one atomic update
+    pub async fn update_aliases(&self, actions: Value) -> Result<()> {
+        let response = self.request_sync(Method::POST, "_aliases", Some(actions))?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            Err(anyhow!("Failed to update aliases: {}", error_text))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_document_presence_tells_missing_documents_from_missing_indexes() {
+        assert!(document_presence(200, r#"{"_index":"products","_id":"a/1","found":true}"#).unwrap());
+        assert!(!document_presence(404, r#"{"_index":"products","_id":"a/1","found":false}"#).unwrap());
+
+        let missing = r#"{"error":{"type":"index_not_found_exception","reason":"no such index [products]"},"status":404}"#;
+        let error = crate::conversions::map_elastic_error(document_presence(404, missing).unwrap_err());
+        assert!(matches!(error, golem_search::SearchError::IndexNotFound(_)));
+    }
+}
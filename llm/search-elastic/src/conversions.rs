@@ -2,23 +2,66 @@
 
 use std::collections::HashMap;
 use anyhow::{anyhow, Result};
+use log::warn;
 use serde_json::{Value, json};
 use golem_search::{
     SearchError, Doc, SearchQuery, SearchResults, SearchHit, Schema, SchemaField, FieldType,
-    HighlightConfig, SearchConfig as WitSearchConfig
+    IndexSettings, LookupSpec, QueryText
 };
+use golem_search::analysis::{unsupported_analysis, FieldAnalysis, Normalization};
+use golem_search::facets::parse_facets;
+use golem_search::lookup::MAX_LOOKUP_DOCS;
+use golem_search::geo::{GeoDistanceSort, GeoPolygonFilter};
+use golem_search::highlight;
+use golem_search::id_set::IdSetFilter;
+use golem_search::numeric::{self, DECIMAL_META_KEY};
+use golem_search::query::{lucene_filter_clause, ParsedQuery, PatternLimits};
+use golem_search::circuit_breaker;
+use golem_search::cursor::LucenePage;
+use golem_search::deadline;
+use golem_search::raw::lucene_page_to_results;
+use golem_search::rate_limit;
+use golem_search::request_id;
+use golem_search::ui_metadata::{self, UI_META_KEY};
+use golem_search::sampling::{random_score_query, random_seed};
+use golem_search::provider_params::{merge_into_body, parse_provider_params};
+use golem_search::routing::{self, RoutingFields};
+
+/// Top-level search body keys that may be supplied through `provider_params`
+pub const ELASTIC_ALLOWED_PARAMS: &[&str] = &[
+    "track_total_hits",
+    "min_score",
+    "timeout",
+    "terminate_after",
+    "post_filter",
+    "collapse",
+    "rescore",
+    "search_after",
+    "_source",
+    "indices_boost",
+    "track_scores",
+    "runtime_mappings",
+    "knn",
+    "routing",
+    "preference",
+];
 
 /// Convert a WIT Schema to ElasticSearch mapping
 pub fn schema_to_elastic_mapping(schema: &Schema) -> Result<Value> {
     let mut properties = serde_json::Map::new();
     
+    for warning in unsupported_analysis("elasticsearch", schema) {
+        warn!("{}", warning);
+    }
+    
     for field in &schema.fields {
         let field_mapping = match field.field_type {
             FieldType::Text => {
+                let analysis = field.analysis.clone().unwrap_or_default();
                 json!({
                     "type": "text",
                     "index": field.index,
-                    "analyzer": "standard"
+                    "analyzer": analysis.lucene_analyzer()
                 })
             }
             FieldType::Keyword => {
@@ -33,6 +76,19 @@ pub fn schema_to_elastic_mapping(schema: &Schema) -> Result<Value> {
                     "index": field.index
                 })
             }
+            FieldType::Int64 => {
+                json!({
+                    "type": "long",
+                    "index": field.index
+                })
+            }
+            // Kept as the decimal text, so no digits are lost
+            FieldType::Decimal => {
+                json!({
+                    "type": "keyword",
+                    "index": field.index
+                })
+            }
             FieldType::Float => {
                 json!({
                     "type": "float",
@@ -63,11 +119,19 @@ pub fn schema_to_elastic_mapping(schema: &Schema) -> Result<Value> {
         properties.insert(field.name.clone(), field_mapping);
     }
     
-    Ok(json!({
+    let mut mapping = json!({
         "mappings": {
             "properties": properties
         }
-    }))
+    });
+    let meta: serde_json::Map<String, Value> = [(UI_META_KEY, ui_metadata::to_meta(schema)), (DECIMAL_META_KEY, numeric::to_meta(schema))]
+        .into_iter()
+        .filter_map(|(key, value)| Some((key.to_string(), value?)))
+        .collect();
+    if !meta.is_empty() {
+        mapping["mappings"]["_meta"] = Value::Object(meta);
+    }
+    Ok(mapping)
 }
 
 /// Convert ElasticSearch mapping to WIT Schema
@@ -89,7 +153,8 @@ pub fn elastic_mapping_to_schema(mapping: &Value, index_name: &str) -> Result<Sc
             let wit_field_type = match field_type {
                 "text" => FieldType::Text,
                 "keyword" => FieldType::Keyword,
-                "integer" | "long" | "short" | "byte" => FieldType::Integer,
+                "integer" | "short" | "byte" => FieldType::Integer,
+                "long" => FieldType::Int64,
                 "float" | "double" | "half_float" | "scaled_float" => FieldType::Float,
                 "boolean" => FieldType::Boolean,
                 "date" => FieldType::Date,
@@ -102,6 +167,11 @@ pub fn elastic_mapping_to_schema(mapping: &Value, index_name: &str) -> Result<Sc
                 .and_then(|i| i.as_bool())
                 .unwrap_or(true);
             
+            let analysis = field_def
+                .get("analyzer")
+                .and_then(|a| a.as_str())
+                .and_then(FieldAnalysis::from_lucene_analyzer);
+            
             fields.push(SchemaField {
                 name: field_name.clone(),
                 field_type: wit_field_type,
@@ -109,14 +179,28 @@ pub fn elastic_mapping_to_schema(mapping: &Value, index_name: &str) -> Result<Sc
                 facet: field_type == "keyword", // Only keyword fields can be faceted
                 sort: field_type != "text", // Text fields typically can't be sorted
                 index,
+                analysis,
+                ui: None,
             });
         }
     }
     
-    Ok(Schema {
+    let mut schema = Schema {
         fields,
         primary_key: Some("_id".to_string()), // ElasticSearch always has _id
-    })
+    };
+    let meta = mapping.get("mappings").and_then(|m| m.get("_meta"));
+    ui_metadata::apply_meta(&mut schema, meta.and_then(|m| m.get(UI_META_KEY)));
+    numeric::apply_meta(&mut schema, meta.and_then(|m| m.get(DECIMAL_META_KEY)));
+    Ok(schema)
+}
+
+/// Apply the client-side part of a query's normalization to its text
+pub fn normalize_query_text(query: &SearchQuery, provider: &str, text: &str) -> String {
+    match query.config.as_ref().and_then(|c| c.normalization) {
+        Some(normalization) => normalization.prepare_query(provider, text),
+        None => text.to_string(),
+    }
 }
 
 /// Convert WIT SearchQuery to ElasticSearch query DSL
@@ -130,44 +214,60 @@ pub fn search_query_to_elastic_query(query: &SearchQuery) -> Result<Value> {
         }
     });
     
-    // Add main query
-    if let Some(ref q) = query.q {
-        if !q.trim().is_empty() {
-            let query_part = json!({
-                "multi_match": {
-                    "query": q,
-                    "type": "best_fields",
-                    "operator": "or"
-                }
-            });
-            elastic_query["query"]["bool"]["must"]
-                .as_array_mut()
-                .unwrap()
-                .push(query_part);
+    // Add main query; match_all scores every hit 1.0 like the other providers' placeholder search
+    let query_part = match query.text() {
+        QueryText::MatchAll => json!({ "match_all": {} }),
+        QueryText::Text(q) => {
+            let text = normalize_query_text(query, "elasticsearch", q);
+            let parsed = ParsedQuery::parse(&text);
+            parsed.check_patterns(&PatternLimits::from_env())?;
+            let mut query_part = if parsed.has_operators() {
+                parsed.to_lucene_query()
+            } else {
+                json!({
+                    "multi_match": {
+                        "query": text,
+                        "type": "best_fields",
+                        "operator": "or"
+                    }
+                })
+            };
+            if let Some(fuzziness) = query.config.as_ref().and_then(|c| c.fuzziness) {
+                fuzziness.apply_to_lucene(&mut query_part);
+            }
+            if let Some(minimum) = query.config.as_ref().and_then(|c| c.minimum_should_match) {
+                minimum.apply_to_lucene(&mut query_part);
+            }
+            query_part
         }
-    }
+    };
+    elastic_query["query"]["bool"]["must"]
+        .as_array_mut()
+        .unwrap()
+        .push(query_part);
     
-    // Add filters
+    // Add filters: "field:value" terms, Lucene-lite expressions, geo polygons or ID sets
     for filter in &query.filters {
-        // Simple term filter format: "field:value"
-        if let Some((field, value)) = filter.split_once(':') {
-            let filter_part = json!({
-                "term": {
-                    field: value
-                }
-            });
-            elastic_query["query"]["bool"]["filter"]
-                .as_array_mut()
-                .unwrap()
-                .push(filter_part);
-        }
+        let clause = match GeoPolygonFilter::parse(filter) {
+            Some(polygons) => polygons.map(|p| p.to_geo_shape_query()),
+            None => match IdSetFilter::parse(filter) {
+                Some(ids) => ids.map(|ids| ids.to_terms_query()),
+                None => lucene_filter_clause(filter),
+            },
+        };
+        elastic_query["query"]["bool"]["filter"]
+            .as_array_mut()
+            .unwrap()
+            .push(clause?);
     }
     
     // Add sorting
     if !query.sort.is_empty() {
         let mut sort_array = Vec::new();
         for sort_field in &query.sort {
-            if sort_field.starts_with('-') {
+            if let Some(geo_sort) = GeoDistanceSort::parse(sort_field) {
+                sort_array.push(geo_sort.map_err(|e| anyhow!("{}", e))?.to_lucene_sort());
+            } else if sort_field.starts_with('-') {
                 // Descending sort
                 let field = &sort_field[1..];
                 sort_array.push(json!({ field: { "order": "desc" } }));
@@ -192,6 +292,18 @@ pub fn search_query_to_elastic_query(query: &SearchQuery) -> Result<Value> {
         elastic_query["size"] = json!(query.per_page.unwrap_or(10));
     }
     
+    // Sampling keeps the matching clauses but replaces the score with a seeded
+    // random one; an explicit sort would undo that, so it is dropped
+    if let Some(sample) = query.config.as_ref().and_then(|c| c.sample) {
+        let matching = elastic_query["query"].take();
+        elastic_query["query"] = random_score_query(matching, random_seed());
+        elastic_query["from"] = json!(0);
+        elastic_query["size"] = json!(sample);
+        if let Some(body) = elastic_query.as_object_mut() {
+            body.remove("sort");
+        }
+    }
+    
     // Add highlighting
     if let Some(ref highlight_config) = query.highlight {
         let mut highlight = json!({
@@ -202,109 +314,81 @@ pub fn search_query_to_elastic_query(query: &SearchQuery) -> Result<Value> {
             highlight["fields"][field] = json!({});
         }
         
-        if let Some(ref pre_tag) = highlight_config.pre_tag {
-            highlight["pre_tags"] = json!([pre_tag]);
-        }
-        
-        if let Some(ref post_tag) = highlight_config.post_tag {
-            highlight["post_tags"] = json!([post_tag]);
-        }
-        
-        if let Some(max_length) = highlight_config.max_length {
-            highlight["fragment_size"] = json!(max_length);
+        if highlight_config.offsets {
+            // Whole field values between marks, turned into offsets once the hits are back
+            highlight["number_of_fragments"] = json!(0);
+            highlight["pre_tags"] = json!([highlight::MARK_START]);
+            highlight["post_tags"] = json!([highlight::MARK_END]);
+        } else {
+            if let Some(ref pre_tag) = highlight_config.pre_tag {
+                highlight["pre_tags"] = json!([pre_tag]);
+            }
+            
+            if let Some(ref post_tag) = highlight_config.post_tag {
+                highlight["post_tags"] = json!([post_tag]);
+            }
+            
+            if let Some(max_length) = highlight_config.max_length {
+                highlight["fragment_size"] = json!(max_length);
+            }
         }
         
         elastic_query["highlight"] = highlight;
     }
     
-    // Add aggregations for facets
+    // Add aggregations for facets: terms per value, range buckets, histograms or stats
     if !query.facets.is_empty() {
         let mut aggs = serde_json::Map::new();
-        for facet_field in &query.facets {
-            aggs.insert(
-                format!("{}_facet", facet_field),
-                json!({
-                    "terms": {
-                        "field": facet_field,
-                        "size": 100
-                    }
-                })
-            );
+        for facet in parse_facets(&query.facets)? {
+            aggs.insert(facet.aggregation_name(), facet.to_lucene_aggregation());
         }
         elastic_query["aggs"] = json!(aggs);
     }
     
-    Ok(elastic_query)
-}
-
-/// Convert ElasticSearch search response to WIT SearchResults
-pub fn elastic_response_to_search_results(response: &Value) -> Result<SearchResults> {
-    let hits_obj = response
-        .get("hits")
-        .ok_or_else(|| anyhow!("Missing hits in response"))?;
-    
-    let total = hits_obj
-        .get("total")
-        .and_then(|t| {
-            // Handle both old format (number) and new format (object with value)
-            if t.is_number() {
-                t.as_u64()
-            } else {
-                t.get("value").and_then(|v| v.as_u64())
-            }
-        })
-        .map(|t| t as u32);
-    
-    let hits_array = hits_obj
-        .get("hits")
-        .and_then(|h| h.as_array())
-        .ok_or_else(|| anyhow!("Missing hits array in response"))?;
-    
-    let mut hits = Vec::new();
-    for hit in hits_array {
-        let id = hit
-            .get("_id")
-            .and_then(|id| id.as_str())
-            .ok_or_else(|| anyhow!("Missing document ID"))?
-            .to_string();
-        
-        let source = hit.get("_source");
-        let content = if let Some(source) = source {
-            Some(serde_json::to_string(source)?)
-        } else {
-            None
-        };
-        
-        let score = hit.get("_score").and_then(|s| s.as_f64());
-        
-        let highlights = hit.get("highlight").map(|h| serde_json::to_string(h)).transpose()?;
-        
-        hits.push(SearchHit {
-            id,
-            score,
-            content,
-            highlights,
-        });
+    // Ask for Lucene scoring trees when the caller wants to debug relevance
+    if query.config.as_ref().is_some_and(|c| c.explain) {
+        elastic_query["explain"] = json!(true);
     }
     
-    // Extract facets from aggregations
-    let facets = response.get("aggregations").map(|aggs| {
-        serde_json::to_string(aggs).unwrap_or_default()
-    });
+    // Totals are capped at 10,000 unless every match is counted
+    if query.config.as_ref().is_some_and(|c| c.exact_total) {
+        elastic_query["track_total_hits"] = json!(true);
+    }
     
-    let took_ms = response
-        .get("took")
-        .and_then(|t| t.as_u64())
-        .map(|t| t as u32);
+    // Provider params are merged last so they can tune the generated request
+    let provider_params = query.config.as_ref().and_then(|c| c.provider_params.as_deref());
+    let mut params = parse_provider_params(provider_params, ELASTIC_ALLOWED_PARAMS)?;
+    // Routing and preference go in the URL of the search, not its body
+    routing::take_search_url_params(&mut params)?;
+    merge_into_body(&mut elastic_query, &params);
     
-    Ok(SearchResults {
-        total,
-        page: None, // Will be calculated from request context
-        per_page: None, // Will be calculated from request context
-        hits,
-        facets,
-        took_ms,
-    })
+    Ok(elastic_query)
+}
+
+/// Convert `field:value` filters to a bool query, rejecting any that do not parse
+///
+/// Search skips a malformed filter; a delete must not select more documents
+/// than the caller asked for.
+pub fn filters_to_elastic_query(filters: &[String]) -> Result<Value> {
+    let clauses = filters
+        .iter()
+        .map(|filter| {
+            filter
+                .split_once(':')
+                .map(|(field, value)| json!({ "term": { field: value } }))
+                .ok_or_else(|| anyhow!("Filter {} is not of the form field:value", filter))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(json!({ "bool": { "filter": clauses } }))
+}
+
+/// Convert an ElasticSearch search response body to WIT SearchResults
+///
+/// Document sources are copied straight out of the response body rather than
+/// being decoded into `Value` and re-serialized. Hits are grouped when
+/// `request` collapsed them, and `page` yields the cursor of the next page.
+pub fn elastic_response_to_search_results(body: &str, request: &Value, page: &LucenePage) -> Result<SearchResults> {
+    lucene_page_to_results(body, request, page).map_err(|e| anyhow!(e.to_string()))
 }
 
 /// Convert WIT Doc to ElasticSearch document
@@ -333,54 +417,255 @@ pub fn elastic_document_to_doc(response: &Value) -> Result<Doc> {
     Ok(Doc { id, content })
 }
 
+/// Build a terms query fetching the lookup documents for a set of join keys
+pub fn lookup_to_elastic_query(lookup: &LookupSpec, keys: &[String]) -> Value {
+    let mut query = json!({
+        "query": {
+            "terms": {
+                lookup.foreign_field.as_str(): keys
+            }
+        },
+        "size": MAX_LOOKUP_DOCS,
+        "track_total_hits": true
+    });
+
+    if !lookup.fields.is_empty() {
+        let mut source = lookup.fields.clone();
+        source.push(lookup.foreign_field.clone());
+        query["_source"] = json!(source);
+    }
+
+    query
+}
+
+/// Documents a search response counts as matching, when it reports a total
+pub fn elastic_response_total(response: &Value) -> Option<u64> {
+    response.pointer("/hits/total/value").and_then(Value::as_u64)
+}
+
+/// Extract the `_source` documents from a search response
+pub fn elastic_response_sources(response: &Value) -> Vec<Value> {
+    response
+        .get("hits")
+        .and_then(|h| h.get("hits"))
+        .and_then(|h| h.as_array())
+        .map(|hits| hits.iter().filter_map(|hit| hit.get("_source").cloned()).collect())
+        .unwrap_or_default()
+}
+
 /// Convert bulk operations to ElasticSearch bulk format
-pub fn docs_to_bulk_operations(index: &str, docs: &[Doc], operation: &str) -> Result<Vec<Value>> {
+///
+/// Index operations go through the provider-side `pipeline` when given.
+pub fn docs_to_bulk_operations(index: &str, docs: &[Doc], operation: &str, pipeline: Option<&str>) -> Result<Vec<Value>> {
     let mut operations = Vec::new();
+    let routing_fields = RoutingFields::from_env();
     
     for doc in docs {
-        // Add operation header
-        let op_header = match operation {
-            "index" => json!({
-                "index": {
+        match operation {
+            "index" => {
+                let content: Value = serde_json::from_str(&doc.content)
+                    .map_err(|e| anyhow!("Invalid JSON in document content: {}", e))?;
+                let mut action = json!({
                     "_index": index,
                     "_id": doc.id
+                });
+                // Routed documents must be written to the shard of their routing value
+                if let Some(routing) = routing_fields.document_routing(index, &content) {
+                    action["routing"] = json!(routing);
+                }
+                if let Some(pipeline) = pipeline {
+                    action["pipeline"] = json!(pipeline);
                 }
-            }),
-            "delete" => json!({
+                operations.push(json!({ "index": action }));
+                operations.push(content);
+            }
+            "delete" => operations.push(json!({
                 "delete": {
                     "_index": index,
                     "_id": doc.id
                 }
-            }),
+            })),
             _ => return Err(anyhow!("Unsupported bulk operation: {}", operation)),
-        };
-        
-        operations.push(op_header);
-        
-        // Add document body for index operations
-        if operation == "index" {
-            let content: Value = serde_json::from_str(&doc.content)
-                .map_err(|e| anyhow!("Invalid JSON in document content: {}", e))?;
-            operations.push(content);
         }
     }
     
     Ok(operations)
 }
 
+/// Name of the synonym filter managed through settings snapshots
+const SNAPSHOT_SYNONYM_FILTER: &str = "golem_synonyms";
+
+/// Name of the stop word filter managed through settings snapshots
+const SNAPSHOT_STOP_FILTER: &str = "golem_stop_words";
+
+/// Extract synonyms and stop words from the analysis section of index settings
+pub fn elastic_settings_to_snapshot(settings: &Value, index: &str) -> IndexSettings {
+    let index_settings = settings
+        .get(index)
+        .and_then(|i| i.get("settings"))
+        .and_then(|s| s.get("index"))
+        .unwrap_or(&Value::Null);
+    let filters = index_settings
+        .get("analysis")
+        .and_then(|a| a.get("filter"));
+
+    let mut snapshot = IndexSettings::default();
+
+    if let Some(rules) = filters
+        .and_then(|f| f.get(SNAPSHOT_SYNONYM_FILTER))
+        .and_then(|f| f.get("synonyms"))
+        .and_then(|s| s.as_array())
+    {
+        for rule in rules.iter().filter_map(|r| r.as_str()) {
+            let mut terms = rule.split(',').map(|t| t.trim().to_string()).filter(|t| !t.is_empty());
+            if let Some(term) = terms.next() {
+                snapshot.synonyms.insert(term, terms.collect());
+            }
+        }
+    }
+
+    if let Some(words) = filters
+        .and_then(|f| f.get(SNAPSHOT_STOP_FILTER))
+        .and_then(|f| f.get("stopwords"))
+        .and_then(|s| s.as_array())
+    {
+        snapshot.stop_words = words.iter().filter_map(|w| w.as_str().map(|s| s.to_string())).collect();
+    }
+
+    if let Some(analysis) = index_settings.get("analysis") {
+        snapshot.provider_settings = Some(json!({ "analysis": analysis }));
+    }
+
+    snapshot
+}
+
+/// Key in the mapping `_meta` holding the normalization of a settings snapshot
+const NORMALIZATION_META_KEY: &str = "golem_normalization";
+
+/// Token filters implementing a normalization; the Unicode form is applied client-side
+fn normalization_filters(normalization: &Normalization) -> Vec<&'static str> {
+    let mut filters = Vec::new();
+    if !normalization.case_sensitive {
+        filters.push("lowercase");
+    }
+    if normalization.fold_diacritics {
+        filters.push("asciifolding");
+    }
+    filters
+}
+
+/// Read the normalization recorded in an index mapping
+pub fn elastic_mapping_normalization(mapping: &Value, index: &str) -> Option<Normalization> {
+    mapping
+        .get(index)
+        .and_then(|i| i.get("mappings"))
+        .and_then(|m| m.get("_meta"))
+        .and_then(|m| m.get(NORMALIZATION_META_KEY))
+        .and_then(|n| serde_json::from_value(n.clone()).ok())
+}
+
+/// Mapping update recording a normalization, since analyzers cannot express the Unicode form
+///
+/// `mapping` is the current index mapping, whose other `_meta` keys are kept.
+pub fn normalization_to_elastic_meta(mapping: &Value, index: &str, normalization: &Normalization) -> Value {
+    elastic_meta_update(mapping, index, &[(NORMALIZATION_META_KEY, Some(json!(normalization)))])
+}
+
+/// Mapping update setting the field UI metadata and decimal fields of a schema, removing those it has none of
+pub fn schema_meta_to_elastic_meta(mapping: &Value, index: &str, schema: &Schema) -> Value {
+    elastic_meta_update(
+        mapping,
+        index,
+        &[(UI_META_KEY, ui_metadata::to_meta(schema)), (DECIMAL_META_KEY, numeric::to_meta(schema))],
+    )
+}
+
+/// Mapping update setting `_meta` keys; a mapping update replaces the whole `_meta`, so the other keys are copied
+fn elastic_meta_update(mapping: &Value, index: &str, entries: &[(&str, Option<Value>)]) -> Value {
+    let mut meta = mapping
+        .get(index)
+        .and_then(|i| i.get("mappings"))
+        .and_then(|m| m.get("_meta"))
+        .and_then(Value::as_object)
+        .cloned()
+        .unwrap_or_default();
+    for (key, value) in entries {
+        match value {
+            Some(value) => meta.insert(key.to_string(), value.clone()),
+            None => meta.remove(*key),
+        };
+    }
+    json!({ "_meta": meta })
+}
+
+/// Build the analysis settings for the synonyms, stop words and normalization of a snapshot
+///
+/// A normalization also replaces the `default` index analyzer; documents indexed
+/// before the change keep their old tokens until they are reindexed.
+pub fn snapshot_to_elastic_analysis(snapshot: &IndexSettings) -> Value {
+    let synonyms: Vec<String> = snapshot.synonyms
+        .iter()
+        .map(|(term, equivalents)| {
+            std::iter::once(term.as_str())
+                .chain(equivalents.iter().map(|s| s.as_str()))
+                .collect::<Vec<_>>()
+                .join(", ")
+        })
+        .collect();
+
+    let normalization = snapshot.normalization.unwrap_or_default();
+    let mut search_filters = normalization_filters(&normalization);
+    search_filters.extend([SNAPSHOT_SYNONYM_FILTER, SNAPSHOT_STOP_FILTER]);
+
+    let mut analysis = json!({
+        "analysis": {
+            "filter": {
+                SNAPSHOT_SYNONYM_FILTER: {
+                    "type": "synonym_graph",
+                    "synonyms": synonyms
+                },
+                SNAPSHOT_STOP_FILTER: {
+                    "type": "stop",
+                    "stopwords": snapshot.stop_words
+                }
+            },
+            "analyzer": {
+                "default_search": {
+                    "type": "custom",
+                    "tokenizer": "standard",
+                    "filter": search_filters
+                }
+            }
+        }
+    });
+    if snapshot.normalization.is_some() {
+        analysis["analysis"]["analyzer"]["default"] = json!({
+            "type": "custom",
+            "tokenizer": "standard",
+            "filter": normalization_filters(&normalization)
+        });
+    }
+    analysis
+}
+
 /// Map ElasticSearch errors to SearchError
 pub fn map_elastic_error(error: anyhow::Error) -> SearchError {
+    if let Some(rejection) = circuit_breaker::open_circuit(&error).or_else(|| rate_limit::rate_limited(&error)) {
+        return rejection;
+    }
     let error_string = error.to_string();
     
-    if error_string.contains("index_not_found") || error_string.contains("404") {
-        SearchError::IndexNotFound(error_string)
+    if error_string.contains("resource_already_exists_exception") {
+        SearchError::IndexAlreadyExists(request_id::tag(error_string))
+    } else if error_string.contains("index_not_found") || error_string.contains("404") {
+        SearchError::IndexNotFound(request_id::tag(error_string))
     } else if error_string.contains("parsing_exception") || error_string.contains("400") {
-        SearchError::InvalidQuery(error_string)
+        SearchError::InvalidQuery(request_id::tag(error_string))
     } else if error_string.contains("timeout") {
-        SearchError::Timeout
+        deadline::timeout_error()
     } else if error_string.contains("rate") || error_string.contains("429") {
-        SearchError::RateLimited
+        SearchError::RateLimited { retry_after_ms: None }
     } else {
-        SearchError::Internal(error_string)
+        SearchError::Internal(request_id::tag(error_string))
     }
 }
\ No newline at end of file
@@ -12,6 +12,7 @@ use golem_search::{
         FacetFallback, HighlightFallback, StreamingFallback, VectorSearchFallback, GeoSearchFallback,
     },
 };
+use golem_search::config::ConfigProfile;
 use log::{warn, debug, info};
 
 /// ElasticSearch provider with graceful degradation support
@@ -34,6 +35,13 @@ impl ElasticSearchProviderWithDegradation {
             log_unsupported_warnings: true,
             strict_mode: false,
         };
+
+        // A selected configuration profile decides how strict fallbacks are;
+        // its errors surface when the client configuration is loaded
+        let degradation_strategy = match ConfigProfile::active("elasticsearch") {
+            Ok(Some(profile)) => profile.degradation.apply(degradation_strategy),
+            _ => degradation_strategy,
+        };
         
         let fallback_processor = FallbackProcessor::new(degradation_strategy.clone());
         
@@ -260,6 +268,7 @@ mod tests {
                 pre_tag: Some("<mark>".to_string()),
                 post_tag: Some("</mark>".to_string()),
                 max_length: Some(200),
+                offsets: false,
             }),
             config: None,
         };
@@ -1,7 +1,7 @@
 //! ElasticSearch provider implementation for the golem:search interface
 
 use anyhow::Result;
-use log::{debug, error, info};
+use log::{debug, error, info, warn};
 
 mod client;
 mod conversions;
@@ -11,8 +11,58 @@ use client::{ElasticClient, ElasticConfig};
 use conversions::*;
 use golem_search::{
     SearchError, SearchResult, Doc, SearchQuery, SearchResults, Schema,
-    SearchCapabilities, FieldType,
+    SearchCapabilities, FieldType, IndexSettings, SettingsDiff, LookupSpec,
 };
+use golem_search::lookup::{check_lookup_complete, collect_lookup_keys, fetch_in_batches, merge_lookup};
+use golem_search::analysis::Normalization;
+use golem_search::alerts;
+use golem_search::audit;
+use golem_search::batching::{submit_adaptive, AdaptiveBatchConfig, AdaptiveBatcher};
+use golem_search::cache::{self, CacheKind};
+use golem_search::capabilities::elasticsearch_capability_matrix;
+use golem_search::circuit_breaker;
+use golem_search::shutdown::{self, ShutdownReport};
+use golem_search::compiled_query;
+use golem_search::consistency::{self, ConsistencyToken};
+use golem_search::cost::{self, CostEstimate, IndexStats};
+use golem_search::cursor::LucenePage;
+use golem_search::deadline;
+use golem_search::delta_sync::{self, IndexFingerprint, SyncReport};
+use golem_search::describe::{self, ProviderDescription};
+use golem_search::diagnose;
+use golem_search::events::{self, LifecycleEventKind};
+use golem_search::field_mask::FieldMask;
+use golem_search::geo;
+use golem_search::highlight;
+use golem_search::ingest;
+use golem_search::dry_run::{self, OperationPlan};
+use golem_search::quota;
+use golem_search::request_id;
+use golem_search::rescue::{self, RescuePlan};
+use golem_search::query_split::{self, SplitLimits};
+use golem_search::response_limits::{results_size, ResponseLimits};
+use golem_search::promotion::{self, PromotionChecks, PromotionReport};
+use golem_search::rollover::{self, RolloverConditions, RolloverResult};
+use golem_search::router::{LocalBoxFuture, RoutedBackend};
+use golem_search::routing;
+use golem_search::schema::check_schema_compatibility;
+use golem_search::metrics;
+use golem_search::telemetry;
+use golem_search::version::{self, ServerVersion};
+use golem_search::watch::{ChangeSummary, WatchCursor, WriteCounters};
+use golem_search::slow_query;
+use golem_search::snapshot::{self, SnapshotInfo, SnapshotKind};
+use golem_search::spellfix;
+use golem_search::suggestions::{self, PopularQuery, Suggestion, SuggestionsBuilder, SuggestionsConfig};
+use golem_search::tasks::PendingTasks;
+use golem_search::templates::{self, IndexTemplate, TemplateOverrides};
+use golem_search::ui_metadata::{self, FacetMetadata};
+use golem_search::change_feed::ChangeBatch;
+use golem_search::checksum;
+use golem_search::numeric;
+use golem_search::outbox::{self, DrainReport, Mutation};
+use golem_search::write_buffer;
+use std::time::Instant;
 
 // TODO: Enable WIT bindings when the WIT file structure is fixed
 // wit_bindgen::generate!({
@@ -31,7 +81,7 @@ impl ElasticSearchProvider {
         let config = ElasticConfig::from_env()
             .map_err(|e| {
                 error!("Failed to load ElasticSearch configuration: {}", e);
-                SearchError::Internal(format!("Configuration error: {}", e))
+                SearchError::Internal(format!("Configuration error: {} (diagnose_config lists every variable read)", e))
             })?;
 
         let client = ElasticClient::new(config)
@@ -66,49 +116,391 @@ impl ElasticSearchProvider {
                 FieldType::Boolean,
                 FieldType::Date,
                 FieldType::GeoPoint,
+                FieldType::Int64,
+                FieldType::Decimal,
             ],
             provider_features: std::collections::HashMap::new(),
         }
     }
 
+    /// Server version, detected once per worker and endpoint
+    pub async fn server_version(&self) -> Option<ServerVersion> {
+        version::cached_version("elasticsearch", self.client.endpoint(), || self.client.detect_version()).await
+    }
+
+    /// Capabilities, versions, configured limits and degradation, for orchestrators choosing a provider
+    pub async fn describe(&self) -> ProviderDescription {
+        describe::describe(elasticsearch_capability_matrix(), self.server_version().await)
+    }
+
     /// Create an index
     pub async fn create_index(&self, name: &str, schema: Option<&Schema>) -> SearchResult<()> {
-        info!("Creating ElasticSearch index: {}", name);
+        audit::audited("elasticsearch", "create_index", name, serde_json::json!({ "schema": schema }), async {
+            telemetry::traced("elasticsearch", "create_index", name, async {
+                info!("Creating ElasticSearch index: {}", name);
 
-        let settings = if let Some(schema) = schema {
-            let mapping = schema_to_elastic_mapping(schema)
-                .map_err(|e| SearchError::Internal(e.to_string()))?;
-            Some(mapping)
-        } else {
-            None
-        };
+                let settings = if let Some(schema) = schema {
+                    let mapping = schema_to_elastic_mapping(schema)
+                        .map_err(|e| SearchError::Internal(e.to_string()))?;
+                    Some(mapping)
+                } else {
+                    None
+                };
 
-        self.client
-            .create_index(name, settings)
+                self.client
+                    .create_index(name, settings)
+                    .await
+                    .map_err(|e| {
+                        error!("Failed to create index {}: {}", name, e);
+                        map_elastic_error(e)
+                    })?;
+
+                info!("Successfully created ElasticSearch index: {}", name);
+                if let Some(schema) = schema {
+                    numeric::remember("elasticsearch", name, schema);
+                }
+                events::emit(LifecycleEventKind::IndexCreated, "elasticsearch", name, serde_json::Value::Null);
+                Ok(())
+            })
             .await
-            .map_err(|e| {
-                error!("Failed to create index {}: {}", name, e);
-                map_elastic_error(e)
-            })?;
+        })
+        .await
+    }
 
-        info!("Successfully created ElasticSearch index: {}", name);
-        Ok(())
+    /// Create an index from a `golem_search::templates` blueprint
+    ///
+    /// Synonyms and stop words are applied as analysis settings right after
+    /// creation. ElasticSearch has no ranking rules, so the template ranking
+    /// is left to query-time sorting.
+    pub async fn create_index_from_template(
+        &self,
+        name: &str,
+        template: &str,
+        overrides: Option<&str>,
+    ) -> SearchResult<IndexTemplate> {
+        let template = templates::instantiate(template, &TemplateOverrides::from_json(overrides)?)?;
+        self.create_index(name, Some(&template.schema)).await?;
+
+        let settings = IndexSettings { schema: None, ..template.settings("elasticsearch") };
+        if !settings.synonyms.is_empty() || !settings.stop_words.is_empty() {
+            self.apply_settings(name, &settings.to_json()?).await?;
+
+            // A field analyzer takes precedence over `default_search`, so point the
+            // standard-analyzed text fields at it explicitly
+            let mut mapping = schema_to_elastic_mapping(&template.schema).map_err(|e| SearchError::Internal(e.to_string()))?;
+            let mut text_fields = serde_json::Map::new();
+            if let Some(properties) = mapping.pointer_mut("/mappings/properties").and_then(serde_json::Value::as_object_mut) {
+                for (field, definition) in properties.iter_mut().filter(|(_, d)| d["analyzer"] == "standard") {
+                    definition["search_analyzer"] = serde_json::json!("default_search");
+                    text_fields.insert(field.clone(), definition.take());
+                }
+            }
+            if !text_fields.is_empty() {
+                self.client
+                    .put_mapping(name, serde_json::json!({ "properties": text_fields }))
+                    .await
+                    .map_err(map_elastic_error)?;
+            }
+        }
+        if !template.ranking.is_empty() {
+            warn!("ElasticSearch has no ranking rules; sort on {:?} at query time", template.ranking);
+        }
+        Ok(template)
     }
 
-    /// Delete an index
-    pub async fn delete_index(&self, name: &str) -> SearchResult<()> {
-        info!("Deleting ElasticSearch index: {}", name);
+    /// Create an index if it does not exist, otherwise add missing schema fields
+    ///
+    /// Returns `true` when the index was created. Changes that would need a
+    /// reindex (see `golem_search::schema`) are reported as an error.
+    pub async fn ensure_index(&self, name: &str, schema: Option<&Schema>) -> SearchResult<bool> {
+        audit::audited("elasticsearch", "ensure_index", name, serde_json::json!({ "schema": schema }), async {
+            match self.create_index(name, schema).await {
+                Ok(()) => return Ok(true),
+                Err(SearchError::IndexAlreadyExists(_)) => debug!("Index {} already exists, checking schema", name),
+                Err(e) => return Err(e),
+            }
 
-        self.client
-            .delete_index(name)
+            let Some(desired) = schema else {
+                return Ok(false);
+            };
+
+            let report = check_schema_compatibility("elasticsearch", &self.get_schema(name).await?, desired);
+            report.ensure_applicable(name)?;
+            let added = report.added();
+            if !added.is_empty() {
+                info!("Adding fields {:?} to index {}", added, name);
+                self.update_schema(name, &report.patch_schema(desired)).await?;
+            }
+            Ok(false)
+        })
+        .await
+    }
+
+    /// Delete an index, or with `dry_run` only return the plan for it
+    pub async fn delete_index(&self, name: &str, dry_run: bool) -> SearchResult<OperationPlan> {
+        audit::audited("elasticsearch", "delete_index", name, serde_json::json!({ "dry_run": dry_run }), async {
+            telemetry::traced("elasticsearch", "delete_index", name, async {
+                dry_run::validate_target(name)?;
+                let count = self.client.count_documents(name).await.map_err(map_elastic_error)?;
+                let plan = OperationPlan::new("elasticsearch", "delete_index", name, dry_run)
+                    .with_affected_documents(Some(count))
+                    .step(format!("DELETE /{}", name));
+
+                dry_run::execute(plan, async {
+                    info!("Deleting ElasticSearch index: {}", name);
+
+                    self.client
+                        .delete_index(name)
+                        .await
+                        .map_err(|e| {
+                            error!("Failed to delete index {}: {}", name, e);
+                            map_elastic_error(e)
+                        })?;
+
+                    info!("Successfully deleted ElasticSearch index: {}", name);
+                    cache::global().remove(CacheKind::Schema, &cache::index_key("elasticsearch", name));
+                    write_buffer::discard_index(name);
+                    checksum::forget_index(name);
+                    cache::invalidate_results("elasticsearch", name);
+                    numeric::forget(name);
+                    events::emit(LifecycleEventKind::IndexDeleted, "elasticsearch", name, serde_json::Value::Null);
+                    Ok(None)
+                })
+                .await
+            })
             .await
-            .map_err(|e| {
-                error!("Failed to delete index {}: {}", name, e);
-                map_elastic_error(e)
-            })?;
+        })
+        .await
+    }
 
-        info!("Successfully deleted ElasticSearch index: {}", name);
-        Ok(())
+    /// Delete the documents matching `field:value` filters, or with `dry_run` only count them
+    pub async fn delete_by_query(&self, index: &str, filters: &[String], dry_run: bool) -> SearchResult<OperationPlan> {
+        let details = serde_json::json!({ "filters": filters, "dry_run": dry_run });
+        audit::audited("elasticsearch", "delete_by_query", index, details, async {
+            telemetry::traced("elasticsearch", "delete_by_query", index, async {
+                dry_run::validate_target(index)?;
+                dry_run::validate_filters(filters)?;
+                let query = filters_to_elastic_query(filters).map_err(|e| SearchError::invalid_query(e.to_string()))?;
+                let count = self.client.count_matching(index, query.clone()).await.map_err(map_elastic_error)?;
+                let plan = OperationPlan::new("elasticsearch", "delete_by_query", index, dry_run)
+                    .with_filter(query.to_string())
+                    .with_affected_documents(Some(count))
+                    .step(format!("POST /{}/_delete_by_query", index));
+
+                dry_run::execute(plan, async {
+                    let deleted = self.client.delete_by_query(index, query).await.map_err(map_elastic_error)?;
+                    info!("Deleted {} documents from ElasticSearch index {}", deleted, index);
+                    // Which documents matched is unknown, so every hash of the index goes
+                    checksum::forget_index(index);
+                    cache::invalidate_results("elasticsearch", index);
+                    Ok(Some(deleted))
+                })
+                .await
+            })
+            .await
+        })
+        .await
+    }
+
+    /// Delete every document but keep the index with its mappings and settings
+    pub async fn clear_index(&self, index: &str, dry_run: bool) -> SearchResult<OperationPlan> {
+        audit::audited("elasticsearch", "clear_index", index, serde_json::json!({ "dry_run": dry_run }), async {
+            telemetry::traced("elasticsearch", "clear_index", index, async {
+                dry_run::validate_target(index)?;
+                let count = self.client.count_documents(index).await.map_err(map_elastic_error)?;
+                let plan = OperationPlan::new("elasticsearch", "clear_index", index, dry_run)
+                    .with_affected_documents(Some(count))
+                    .step(format!("POST /{}/_delete_by_query (match_all)", index));
+
+                dry_run::execute(plan, async {
+                    let deleted = self.client
+                        .delete_by_query(index, serde_json::json!({ "match_all": {} }))
+                        .await
+                        .map_err(map_elastic_error)?;
+                    info!("Cleared {} documents from ElasticSearch index {}", deleted, index);
+                    write_buffer::discard_index(index);
+                    checksum::forget_index(index);
+                    cache::invalidate_results("elasticsearch", index);
+                    Ok(Some(deleted))
+                })
+                .await
+            })
+            .await
+        })
+        .await
+    }
+
+    /// Snapshot an index into the `SEARCH_SNAPSHOT_REPOSITORY` repository
+    pub async fn create_snapshot(&self, index: &str) -> SearchResult<SnapshotInfo> {
+        let repository = snapshot::repository_from_env()?;
+        audit::audited("elasticsearch", "create_snapshot", index, serde_json::json!({ "repository": repository }), async {
+            telemetry::traced("elasticsearch", "create_snapshot", index, async {
+                let documents = self.client.count_documents(index).await.map_err(map_elastic_error)?;
+                let info = SnapshotInfo {
+                    location: Some(repository.clone()),
+                    documents: Some(documents),
+                    ..SnapshotInfo::new(snapshot::new_snapshot_id(index), index, "elasticsearch", SnapshotKind::Repository)
+                };
+                let metadata = serde_json::json!({ "index": index, "created_at": info.created_at });
+                self.client
+                    .create_snapshot(&repository, &info.id, index, metadata)
+                    .await
+                    .map_err(map_elastic_error)?;
+
+                info!("Snapshotted index {} as {}/{}", index, repository, info.id);
+                Ok(info)
+            })
+            .await
+        })
+        .await
+    }
+
+    /// Restore the indices of a snapshot in the `SEARCH_SNAPSHOT_REPOSITORY` repository
+    ///
+    /// An existing index is closed first, as restoring requires, and is
+    /// replaced by the snapshot's copy.
+    pub async fn restore_snapshot(&self, snapshot_id: &str) -> SearchResult<SnapshotInfo> {
+        let repository = snapshot::repository_from_env()?;
+        audit::audited("elasticsearch", "restore_snapshot", snapshot_id, serde_json::json!({ "repository": repository }), async {
+            telemetry::traced("elasticsearch", "restore_snapshot", snapshot_id, async {
+                let description = self.client.get_snapshot(&repository, snapshot_id).await.map_err(map_elastic_error)?;
+                let snapshot = description.pointer("/snapshots/0")
+                    .ok_or_else(|| SearchError::invalid_query(format!("No snapshot {} in {}", snapshot_id, repository)))?;
+                let indices: Vec<String> = snapshot.get("indices")
+                    .and_then(|indices| indices.as_array())
+                    .map(|indices| indices.iter().filter_map(|i| i.as_str().map(str::to_string)).collect())
+                    .unwrap_or_default();
+
+                let existing = self.client.list_indexes().await.map_err(map_elastic_error)?;
+                for index in indices.iter().filter(|index| existing.contains(index)) {
+                    self.client.close_index(index).await.map_err(map_elastic_error)?;
+                }
+                self.client.restore_snapshot(&repository, snapshot_id, &indices).await.map_err(map_elastic_error)?;
+
+                for index in &indices {
+                    cache::global().remove(CacheKind::Schema, &cache::index_key("elasticsearch", index));
+                    write_buffer::discard_index(index);
+                    checksum::forget_index(index);
+                    cache::invalidate_results("elasticsearch", index);
+                }
+                info!("Restored {} from snapshot {}/{}", indices.join(", "), repository, snapshot_id);
+                Ok(SnapshotInfo {
+                    id: snapshot_id.to_string(),
+                    index: indices.join(","),
+                    provider: "elasticsearch".to_string(),
+                    kind: SnapshotKind::Repository,
+                    location: Some(repository.clone()),
+                    documents: None,
+                    created_at: snapshot.pointer("/metadata/created_at")
+                        .or_else(|| snapshot.get("start_time"))
+                        .and_then(|t| t.as_str())
+                        .unwrap_or_default()
+                        .to_string(),
+                })
+            })
+            .await
+        })
+        .await
+    }
+
+    /// Create the first index behind a rollover alias, `<alias>-000001`, as the alias's write index
+    pub async fn create_rollover_index(&self, alias: &str, schema: Option<&Schema>) -> SearchResult<String> {
+        audit::audited("elasticsearch", "create_rollover_index", alias, serde_json::json!({ "schema": schema }), async {
+            let index = rollover::first_index(alias);
+            let mut body = match schema {
+                Some(schema) => schema_to_elastic_mapping(schema).map_err(|e| SearchError::Internal(e.to_string()))?,
+                None => serde_json::json!({}),
+            };
+            body["aliases"] = rollover::write_alias(alias);
+
+            self.client.create_index(&index, Some(body)).await.map_err(map_elastic_error)?;
+            info!("Created ElasticSearch index {} behind write alias {}", index, alias);
+            if let Some(schema) = schema {
+                numeric::remember("elasticsearch", alias, schema);
+            }
+            events::emit(LifecycleEventKind::IndexCreated, "elasticsearch", &index, serde_json::json!({ "alias": alias }));
+            Ok(index)
+        })
+        .await
+    }
+
+    /// Roll the write index of `alias` over to a new index once any of `conditions` is met
+    ///
+    /// An alias that does not exist yet is created with its first index
+    /// instead. The new index gets the mappings and analysis settings of the
+    /// current write index. With `dry_run` the conditions are only evaluated.
+    pub async fn rollover(&self, alias: &str, conditions: &RolloverConditions, dry_run: bool) -> SearchResult<RolloverResult> {
+        let details = serde_json::json!({ "conditions": conditions, "dry_run": dry_run });
+        audit::audited("elasticsearch", "rollover", alias, details, async {
+            telemetry::traced("elasticsearch", "rollover", alias, async {
+                conditions.validate()?;
+                let Some(aliases) = self.client.get_alias(alias).await.map_err(map_elastic_error)? else {
+                    if !dry_run {
+                        self.create_rollover_index(alias, None).await?;
+                    }
+                    return Ok(RolloverResult::created(alias, dry_run));
+                };
+                let write_index = rollover::write_index(&aliases, alias)
+                    .ok_or_else(|| SearchError::invalid_query(format!("Alias {} has no write index", alias)))?;
+
+                let mappings = self.client.get_mapping(&write_index).await.map_err(map_elastic_error)?
+                    .get(&write_index)
+                    .and_then(|index| index.get("mappings"))
+                    .cloned();
+                let analysis = self.client.get_settings(&write_index).await.map_err(map_elastic_error)?
+                    .pointer(&format!("/{}/settings/index/analysis", write_index))
+                    .cloned()
+                    .map(|analysis| serde_json::json!({ "analysis": analysis }));
+                let response = self.client
+                    .rollover(alias, conditions.to_request(mappings, analysis), dry_run)
+                    .await
+                    .map_err(map_elastic_error)?;
+
+                let result = RolloverResult::from_response(alias, &response)?;
+                if result.rolled_over {
+                    info!("Rolled alias {} over from {} to {}", alias, write_index, result.new_index);
+                    cache::global().remove(CacheKind::Schema, &cache::index_key("elasticsearch", alias));
+                    let covered = promotion::alias_indexes(&aliases);
+                    let moved = covered.iter().chain([&result.new_index]).map(String::as_str);
+                    cache::invalidate_alias_results("elasticsearch", alias, moved);
+                    let details = serde_json::json!({ "alias": alias, "old_index": write_index });
+                    events::emit(LifecycleEventKind::IndexCreated, "elasticsearch", &result.new_index, details);
+                }
+                Ok(result)
+            })
+            .await
+        })
+        .await
+    }
+
+    /// Point `live_alias` at `staging_index` once the staging index passes `checks`
+    ///
+    /// The alias is swapped in one update and checked again; failing that, it
+    /// is swapped back to the indexes it covered before. See `promotion`.
+    pub async fn promote(&self, staging_index: &str, live_alias: &str, checks: &PromotionChecks) -> SearchResult<PromotionReport> {
+        let details = serde_json::json!({ "alias": live_alias, "checks": checks });
+        audit::audited("elasticsearch", "promote", staging_index, details, async {
+            telemetry::traced("elasticsearch", "promote", staging_index, async {
+                let previous = self.client.get_alias(live_alias).await.map_err(map_elastic_error)?
+                    .map(|aliases| promotion::alias_indexes(&aliases))
+                    .unwrap_or_default();
+                let count = |index: String| async move { self.client.count_documents(&index).await.map_err(map_elastic_error) };
+                let hits = |index: String, text: String| async move {
+                    self.search(&index, &promotion::sample_query(&text)).await.map(|results| promotion::hit_count(&results))
+                };
+                let moved: Vec<String> = previous.iter().cloned().chain([staging_index.to_string()]).collect();
+                let moved = &moved;
+                let update = |actions: serde_json::Value| async move {
+                    self.client.update_aliases(actions).await.map_err(map_elastic_error)?;
+                    cache::global().remove(CacheKind::Schema, &cache::index_key("elasticsearch", live_alias));
+                    cache::invalidate_alias_results("elasticsearch", live_alias, moved.iter().map(String::as_str));
+                    Ok(())
+                };
+                promotion::promote(live_alias, staging_index, previous, checks, count, hits, update).await
+            })
+            .await
+        })
+        .await
     }
 
     /// List all indexes
@@ -129,13 +521,44 @@ impl ElasticSearchProvider {
 
     /// Upsert a document
     pub async fn upsert(&self, index: &str, doc: &Doc) -> SearchResult<()> {
+        self.upsert_with_pipeline(index, doc, None).await
+    }
+
+    /// Upsert a document through the provider-side `pipeline`, or that of the index when `None`
+    pub async fn upsert_with_pipeline(&self, index: &str, doc: &Doc, pipeline: Option<&str>) -> SearchResult<()> {
+        if write_buffer::is_enabled() {
+            return self.upsert_many_with_pipeline(index, std::slice::from_ref(doc), pipeline).await;
+        }
+        telemetry::traced("elasticsearch", "upsert", index, async {
+            self.index_document(index, doc, pipeline).await.map(|_| ())
+        })
+        .await
+    }
+
+    /// Upsert a document and return a consistency token for the write
+    pub async fn upsert_with_token(&self, index: &str, doc: &Doc) -> SearchResult<String> {
+        self.index_document(index, doc, None).await
+    }
+
+    /// Write one document through `pipeline`, or the index's, returning the consistency token of the write
+    async fn index_document(&self, index: &str, doc: &Doc, pipeline: Option<&str>) -> SearchResult<String> {
+        quota::global().check_documents(index, 1)?;
         debug!("Upserting document {} in index {}", doc.id, index);
 
+        let docs = ingest::apply(index, std::slice::from_ref(doc))?;
+        let doc = &docs[0];
         let (doc_id, content) = doc_to_elastic_document(doc)
             .map_err(|e| SearchError::InvalidQuery(e.to_string()))?;
+        let mut params = Vec::new();
+        if let Some(routing) = routing::document_routing(index, &content) {
+            params.push(("routing".to_string(), routing));
+        }
+        if let Some(pipeline) = ingest::server_pipeline(index, pipeline)? {
+            params.push(("pipeline".to_string(), pipeline));
+        }
 
-        self.client
-            .index_document(index, &doc_id, content)
+        let response = self.client
+            .index_document(index, &doc_id, content, &params)
             .await
             .map_err(|e| {
                 error!("Failed to upsert document {}: {}", doc_id, e);
@@ -143,118 +566,576 @@ impl ElasticSearchProvider {
             })?;
 
         debug!("Successfully upserted document {}", doc_id);
-        Ok(())
+        checksum::record(index, std::slice::from_ref(doc));
+        cache::invalidate_results("elasticsearch", index);
+        alerts::observe(index, std::slice::from_ref(doc));
+        Ok(ConsistencyToken::from_write_response("elasticsearch", index, &response).encode())
+    }
+
+    /// Wait until the write behind a query's consistency token is searchable
+    ///
+    /// Writes become visible on the next refresh, so refreshing the token's
+    /// index is enough; it is retried until it succeeds or the wait times out.
+    async fn wait_for_token(&self, query: &SearchQuery) -> SearchResult<()> {
+        let Some(token) = query.config.as_ref().and_then(|c| c.consistency_token.as_deref()) else {
+            return Ok(());
+        };
+        let token = ConsistencyToken::parse_for(token, "elasticsearch")?;
+
+        consistency::wait_until_applied(&token, consistency::wait_timeout_from_env(), || async {
+            Ok(self.client.refresh_index(&token.index).await.is_ok())
+        })
+        .await
     }
 
     /// Upsert multiple documents
+    ///
+    /// With the write buffer enabled the documents are only buffered, and
+    /// written once their index is due for a flush, already through the
+    /// index's ingest pipeline.
     pub async fn upsert_many(&self, index: &str, docs: &[Doc]) -> SearchResult<()> {
+        self.upsert_many_with_pipeline(index, docs, None).await
+    }
+
+    /// Upsert documents through the provider-side `pipeline`, or that of the index when `None`
+    ///
+    /// Writes naming a pipeline skip the write buffer, whose batches go through the index's.
+    pub async fn upsert_many_with_pipeline(&self, index: &str, docs: &[Doc], pipeline: Option<&str>) -> SearchResult<()> {
+        telemetry::traced("elasticsearch", "upsert_many", index, async {
+            quota::global().check_documents(index, docs.len())?;
+            let docs = ingest::apply(index, docs)?;
+            let docs = checksum::skip_unchanged(index, &docs);
+            if docs.is_empty() {
+                return Ok(());
+            }
+            if write_buffer::is_enabled() && pipeline.is_none() {
+                return write_buffer::buffer_upserts(index, docs.into_owned(), |index, docs| async move {
+                    self.bulk_upsert(&index, &docs, None).await
+                })
+                .await;
+            }
+            self.bulk_upsert(index, &docs, pipeline).await
+        })
+        .await
+    }
+
+    /// Write buffered upserts for `index`, or for every index, returning the number of documents written
+    pub async fn flush_writes(&self, index: Option<&str>) -> SearchResult<u32> {
+        let written = write_buffer::flush(index, |index, docs| async move {
+            self.bulk_upsert(&index, &docs, None).await
+        })
+        .await?;
+        Ok(written as u32)
+    }
+
+    /// Apply the writes recorded in the worker's outbox, oldest first
+    pub async fn drain_outbox(&self) -> DrainReport {
+        outbox::drain(|index, mutation| async move {
+            match mutation {
+                Mutation::Upsert { doc } => self.bulk_upsert(&index, &ingest::apply(&index, &[doc])?, None).await,
+                // Bulk deletes succeed for missing documents, which keeps replays harmless
+                Mutation::Delete { id } => self.delete_many(&index, &[id]).await,
+            }
+        })
+        .await
+    }
+
+    /// Write the whole write buffer and drain the outbox, before the worker is suspended
+    pub async fn flush(&self) -> ShutdownReport {
+        shutdown::flush(self.flush_writes(None), self.drain_outbox()).await
+    }
+
+    /// Same as [`flush`](Self::flush); Elasticsearch keeps no scroll contexts open
+    pub async fn shutdown(&self) -> ShutdownReport {
+        self.flush().await
+    }
+
+    /// Write a batch of change-feed events, as collapsed by a `ChangeFeedAdapter`
+    pub async fn apply_changes(&self, index: &str, batch: &ChangeBatch) -> SearchResult<()> {
+        telemetry::traced("elasticsearch", "apply_changes", index, async {
+            quota::global().check_documents(index, batch.upserts.len())?;
+            let upserts = ingest::apply(index, &batch.upserts)?;
+            let upserts = checksum::skip_unchanged(index, &upserts);
+            if !upserts.is_empty() {
+                self.bulk_upsert(index, &upserts, None).await?;
+            }
+            if !batch.deletes.is_empty() {
+                self.delete_many(index, &batch.deletes).await?;
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    /// Converge an index to `docs`, upserting new and changed documents and deleting missing ones
+    pub async fn sync(&self, index: &str, docs: Vec<Doc>, dry_run: bool) -> SearchResult<SyncReport> {
+        telemetry::traced("elasticsearch", "sync", index, async {
+            // Buffered writes would otherwise land after the comparison
+            self.flush_writes(Some(index)).await?;
+            // The scan only sees refreshed documents
+            self.client.refresh_index(index).await.map_err(map_elastic_error)?;
+            let existing = self.fingerprint_index(index).await?;
+            let report = delta_sync::plan(index, existing, docs, dry_run)?
+                .execute(|batch| async move { self.apply_changes(index, &batch).await })
+                .await?;
+            info!(
+                "Synced index {}: {} upserted, {} deleted, {} unchanged",
+                index, report.upserted, report.deleted, report.unchanged
+            );
+            Ok(report)
+        })
+        .await
+    }
+
+    /// Build or refresh the suggestions index of `index` from analytics and document titles
+    ///
+    /// The suggestions index is created when missing and converged to the new
+    /// suggestions with [`Self::sync`].
+    pub async fn build_suggestions(&self, index: &str, queries: &[PopularQuery], config: &SuggestionsConfig) -> SearchResult<SyncReport> {
+        telemetry::traced("elasticsearch", "build_suggestions", index, async {
+            let mut builder = SuggestionsBuilder::new(config.clone());
+            builder.add_queries(queries);
+            if let Some(field) = &config.title_field {
+                self.scan_documents(index, |_, document| {
+                    if let Some(title) = document.get(field).and_then(serde_json::Value::as_str) {
+                        builder.add_title(title);
+                    }
+                })
+                .await?;
+            }
+            let target = suggestions::suggestions_index(index);
+            self.ensure_index(&target, Some(&suggestions::schema())).await?;
+            self.sync(&target, builder.documents(), false).await
+        })
+        .await
+    }
+
+    /// Suggestions from the suggestions index of `index` that complete `prefix`, most popular first
+    pub async fn suggest(&self, index: &str, prefix: &str, limit: u32) -> SearchResult<Vec<Suggestion>> {
+        let target = suggestions::suggestions_index(index);
+        let results = self.fetch_results(&target, &suggestions::suggest_query(prefix, limit)).await?;
+        Ok(suggestions::from_results(&results))
+    }
+
+    /// Suggestions from the suggestions index of `index` close to `text`, to correct its spelling with
+    pub async fn spelling_candidates(&self, index: &str, text: &str) -> SearchResult<Vec<Suggestion>> {
+        let target = suggestions::suggestions_index(index);
+        let results = self.fetch_results(&target, &spellfix::lookup_query(text)).await?;
+        Ok(suggestions::from_results(&results))
+    }
+
+    /// IDs and hashes of every document in an index
+    async fn fingerprint_index(&self, index: &str) -> SearchResult<IndexFingerprint> {
+        let mut fingerprint = IndexFingerprint::new();
+        self.scan_documents(index, |id, document| fingerprint.add(id, document)).await?;
+        Ok(fingerprint)
+    }
+
+    /// Visit the ID and source of every document in an index, read with the scroll API
+    async fn scan_documents(&self, index: &str, mut visit: impl FnMut(&str, &serde_json::Value)) -> SearchResult<()> {
+        let mut page = self.client.open_scroll(index, 1000, "1m").await.map_err(map_elastic_error)?;
+        loop {
+            let hits = page["hits"]["hits"].as_array().cloned().unwrap_or_default();
+            for hit in &hits {
+                if let Some(id) = hit["_id"].as_str() {
+                    visit(id, &hit["_source"]);
+                }
+            }
+            let scroll_id = page["_scroll_id"].as_str().map(str::to_string);
+            match scroll_id {
+                Some(scroll_id) if !hits.is_empty() => {
+                    page = self.client.scroll(&scroll_id, "1m").await.map_err(map_elastic_error)?;
+                }
+                Some(scroll_id) => {
+                    if let Err(e) = self.client.clear_scroll(&scroll_id).await {
+                        warn!("Failed to clear scroll on index {}: {}", index, e);
+                    }
+                    break;
+                }
+                None => break,
+            }
+        }
+        Ok(())
+    }
+
+    /// Send documents to the _bulk API in adaptively sized chunks
+    async fn bulk_upsert(&self, index: &str, docs: &[Doc], pipeline: Option<&str>) -> SearchResult<()> {
         info!("Bulk upserting {} documents in index {}", docs.len(), index);
 
-        let operations = docs_to_bulk_operations(index, docs, "index")
-            .map_err(|e| SearchError::InvalidQuery(e.to_string()))?;
+        let max_batch_size = self.get_capabilities().max_batch_size.unwrap_or(1000) as usize;
+        let mut batcher = AdaptiveBatcher::new(AdaptiveBatchConfig::with_max(max_batch_size));
+        let pipeline = ingest::server_pipeline(index, pipeline)?;
+        let pipeline = pipeline.as_deref();
 
-        self.client
-            .bulk(operations)
-            .await
-            .map_err(|e| {
-                error!("Failed to bulk upsert documents: {}", e);
-                map_elastic_error(e)
-            })?;
+        let metrics = submit_adaptive(docs.to_vec(), &mut batcher, |chunk: Vec<Doc>| async move {
+            let operations = docs_to_bulk_operations(index, &chunk, "index", pipeline)?;
+            let response = self.client.bulk(operations).await?;
+            checksum::record_bulk(index, &chunk, &response);
+            cache::invalidate_results("elasticsearch", index);
+            Ok::<_, anyhow::Error>(())
+        })
+        .await
+        .map_err(|e| {
+            error!("Failed to bulk upsert documents: {}", e);
+            map_elastic_error(e.into())
+        })?;
 
-        info!("Successfully bulk upserted {} documents", docs.len());
+        info!(
+            "Successfully bulk upserted {} documents in {} batches (sizes: {:?})",
+            metrics.items_submitted,
+            metrics.batch_sizes.len(),
+            metrics.batch_sizes
+        );
+        alerts::observe(index, docs);
         Ok(())
     }
 
     /// Delete a document
     pub async fn delete(&self, index: &str, id: &str) -> SearchResult<()> {
-        debug!("Deleting document {} from index {}", id, index);
+        self.delete_routed(index, id, None).await
+    }
 
-        self.client
-            .delete_document(index, id)
-            .await
-            .map_err(|e| {
-                error!("Failed to delete document {}: {}", id, e);
-                map_elastic_error(e)
-            })?;
+    /// Delete a document from the shard of `routing`
+    pub async fn delete_routed(&self, index: &str, id: &str, routing: Option<&str>) -> SearchResult<()> {
+        telemetry::traced("elasticsearch", "delete", index, async {
+            debug!("Deleting document {} from index {}", id, index);
+            write_buffer::discard(index, &[id.to_string()]);
+            checksum::forget(index, &[id.to_string()]);
+            cache::invalidate_results("elasticsearch", index);
 
-        debug!("Successfully deleted document {}", id);
-        Ok(())
+            self.client
+                .delete_document(index, id, routing)
+                .await
+                .map_err(|e| {
+                    error!("Failed to delete document {}: {}", id, e);
+                    map_elastic_error(e)
+                })?;
+
+            debug!("Successfully deleted document {}", id);
+            Ok(())
+        })
+        .await
     }
 
     /// Delete multiple documents
     pub async fn delete_many(&self, index: &str, ids: &[String]) -> SearchResult<()> {
-        info!("Bulk deleting {} documents from index {}", ids.len(), index);
+        telemetry::traced("elasticsearch", "delete_many", index, async {
+            info!("Bulk deleting {} documents from index {}", ids.len(), index);
+            write_buffer::discard(index, ids);
+            checksum::forget(index, ids);
+            cache::invalidate_results("elasticsearch", index);
 
-        let docs: Vec<Doc> = ids.iter().map(|id| Doc {
-            id: id.clone(),
-            content: "{}".to_string(), // Empty content for delete operations
-        }).collect();
+            let docs: Vec<Doc> = ids.iter().map(|id| Doc {
+                id: id.clone(),
+                content: "{}".to_string(), // Empty content for delete operations
+            }).collect();
 
-        let operations = docs_to_bulk_operations(index, &docs, "delete")
-            .map_err(|e| SearchError::InvalidQuery(e.to_string()))?;
+            let operations = docs_to_bulk_operations(index, &docs, "delete", None)
+                .map_err(|e| SearchError::InvalidQuery(e.to_string()))?;
 
-        self.client
-            .bulk(operations)
-            .await
-            .map_err(|e| {
-                error!("Failed to bulk delete documents: {}", e);
-                map_elastic_error(e)
-            })?;
+            self.client
+                .bulk(operations)
+                .await
+                .map_err(|e| {
+                    error!("Failed to bulk delete documents: {}", e);
+                    map_elastic_error(e)
+                })?;
 
-        info!("Successfully bulk deleted {} documents", docs.len());
-        Ok(())
+            info!("Successfully bulk deleted {} documents", docs.len());
+            Ok(())
+        })
+        .await
     }
 
-    /// Get a document by ID
-    pub async fn get(&self, index: &str, id: &str) -> SearchResult<Option<Doc>> {
-        debug!("Getting document {} from index {}", id, index);
+    /// Get a document by ID, with only the `fields` paths in its content when given
+    pub async fn get(&self, index: &str, id: &str, fields: Option<&FieldMask>) -> SearchResult<Option<Doc>> {
+        self.get_routed(index, id, None, fields).await
+    }
+
+    /// Get a document by ID from the shard of `routing`, which a routed document is only found with
+    pub async fn get_routed(&self, index: &str, id: &str, routing: Option<&str>, fields: Option<&FieldMask>) -> SearchResult<Option<Doc>> {
+        telemetry::traced("elasticsearch", "get", index, async {
+            debug!("Getting document {} from index {}", id, index);
+
+            let source = fields.map(FieldMask::paths);
+            let result = self.client
+                .get_document(index, id, source.as_deref(), routing)
+                .await
+                .map_err(|e| {
+                    error!("Failed to get document {}: {}", id, e);
+                    map_elastic_error(e)
+                })?;
+
+            if let Some(response) = result {
+                let doc = elastic_document_to_doc(&response)
+                    .map_err(|e| SearchError::Internal(e.to_string()))?;
+            
+                debug!("Successfully retrieved document {}", id);
+                Ok(Some(doc))
+            } else {
+                debug!("Document {} not found", id);
+                Ok(None)
+            }
+        })
+        .await
+    }
 
-        let result = self.client
-            .get_document(index, id)
+    /// Check whether a document exists without transferring it
+    pub async fn exists(&self, index: &str, id: &str) -> SearchResult<bool> {
+        debug!("Checking whether document {} exists in index {}", id, index);
+
+        self.client
+            .document_exists(index, id)
             .await
             .map_err(|e| {
-                error!("Failed to get document {}: {}", id, e);
+                error!("Failed to check document {}: {}", id, e);
                 map_elastic_error(e)
-            })?;
+            })
+    }
 
-        if let Some(response) = result {
-            let doc = elastic_document_to_doc(&response)
-                .map_err(|e| SearchError::Internal(e.to_string()))?;
-            
-            debug!("Successfully retrieved document {}", id);
-            Ok(Some(doc))
-        } else {
-            debug!("Document {} not found", id);
-            Ok(None)
-        }
+    /// Get several documents by ID with a single `_mget` request
+    ///
+    /// Returns one entry per requested ID, in request order, `None` for missing documents.
+    /// Source filtering selects the `fields` paths, nested ones included.
+    pub async fn get_many(&self, index: &str, ids: &[String], fields: Option<&FieldMask>) -> SearchResult<Vec<Option<Doc>>> {
+        telemetry::traced("elasticsearch", "get_many", index, async {
+            debug!("Getting {} documents from index {}", ids.len(), index);
+
+            if ids.is_empty() {
+                return Ok(Vec::new());
+            }
+
+            let source = fields.map(FieldMask::paths);
+            let response = self.client
+                .mget(index, ids, source.as_deref())
+                .await
+                .map_err(|e| {
+                    error!("Failed to get documents from index {}: {}", index, e);
+                    map_elastic_error(e)
+                })?;
+
+            let docs = response.get("docs").and_then(serde_json::Value::as_array);
+            docs.into_iter()
+                .flatten()
+                .map(|doc| {
+                    if doc.get("found").and_then(serde_json::Value::as_bool) == Some(true) {
+                        elastic_document_to_doc(doc)
+                            .map(Some)
+                            .map_err(|e| SearchError::Internal(e.to_string()))
+                    } else {
+                        Ok(None)
+                    }
+                })
+                .collect()
+        })
+        .await
     }
 
-    /// Search documents
+    /// Search documents through the shared result cache, honouring per-index cache policies
+    ///
+    /// All backend calls of one search share a request ID, returned in the results.
     pub async fn search(&self, index: &str, query: &SearchQuery) -> SearchResult<SearchResults> {
+        telemetry::traced("elasticsearch", "search", index, async {
+            quota::global().check_query(index)?;
+            let scope = request_id::begin();
+            let _deadline = deadline::begin(query.config.as_ref().and_then(|config| config.timeout_ms));
+            let mut results = cache::with_result_cache("elasticsearch", index, query, results_size, |index, query| async move {
+                let limits = SplitLimits::new(&elasticsearch_capability_matrix().performance_limits);
+                limits.check(&query)?;
+                let (index, limits) = (index.as_str(), &limits);
+                let lookup = |text: String| async move { self.spelling_candidates(index, &text).await };
+                rescue::search_rescued(&query, &RescuePlan::from_env(), lookup, |query| async move {
+                    query_split::search_split(&query, limits, |part| async move { self.fetch_results(index, &part).await }).await
+                })
+                .await
+            })
+            .await?;
+            results.request_id = Some(scope.id().to_string());
+            results.facet_metadata = self.facet_metadata(index, &query.facets).await;
+            Ok(results)
+        })
+        .await
+    }
+
+    /// Display metadata of the requested facets; left empty when the schema cannot be read
+    async fn facet_metadata(&self, index: &str, facets: &[String]) -> Vec<FacetMetadata> {
+        if facets.is_empty() {
+            return Vec::new();
+        }
+        match self.get_schema(index).await {
+            Ok(schema) => ui_metadata::facet_metadata(&schema, facets),
+            Err(e) => {
+                warn!("Failed to read facet metadata of index {}: {}", index, e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Run a search against the backend, bypassing the result cache
+    async fn fetch_results(&self, index: &str, query: &SearchQuery) -> SearchResult<SearchResults> {
+        self.wait_for_token(query).await?;
+
         debug!("Searching index {} with query: {:?}", index, query.q);
 
-        let elastic_query = search_query_to_elastic_query(query)
+        let mut elastic_query = compiled_query::compile("elasticsearch", index, query, search_query_to_elastic_query)
             .map_err(|e| SearchError::InvalidQuery(e.to_string()))?;
+        let cursor = query.config.as_ref().and_then(|c| c.cursor.as_deref());
+        let page = LucenePage::apply(&mut elastic_query, "elasticsearch", index, cursor)?;
+        if elastic_query.get("knn").is_some() {
+            version::require(&version::ELASTIC_KNN, self.server_version().await)?;
+        }
 
+        let params = routing::search_url_params(query)?;
+        let started = Instant::now();
         let response = self.client
-            .search(index, elastic_query)
+            .search_raw(index, elastic_query.clone(), &params)
             .await
             .map_err(|e| {
                 error!("Search failed for index {}: {}", index, e);
                 map_elastic_error(e)
             })?;
+        slow_query::record_search("elasticsearch", index, &elastic_query, query, started.elapsed());
 
-        let results = elastic_response_to_search_results(&response)
+        let limits = ResponseLimits::from_env();
+        limits.check_body(response.len())?;
+        
+        let mut results = elastic_response_to_search_results(&response, &elastic_query, &page)
             .map_err(|e| SearchError::Internal(e.to_string()))?;
+        if query.highlight.as_ref().is_some_and(|h| h.offsets) {
+            highlight::replace_marked(&mut results.hits);
+        }
+        if let Some(sort) = geo::distance_sort(&query.sort)? {
+            geo::fill_distances(&mut results.hits, &sort);
+            for group in results.groups.iter_mut().flatten() {
+                geo::fill_distances(&mut group.hits, &sort);
+            }
+        }
+        limits.enforce(&mut results)?;
 
         debug!("Search completed. Found {} hits", results.hits.len());
         Ok(results)
     }
 
+    /// Search the primary index and enrich each hit with documents from a lookup index
+    pub async fn search_with_lookup(
+        &self,
+        primary_index: &str,
+        query: &SearchQuery,
+        lookup: &LookupSpec,
+    ) -> SearchResult<SearchResults> {
+        lookup.validate()?;
+
+        let mut results = self.search(primary_index, query).await?;
+        let keys = collect_lookup_keys(&results, lookup);
+        if keys.is_empty() {
+            return Ok(results);
+        }
+
+        debug!("Looking up {} keys in index {}", keys.len(), lookup.index);
+
+        let docs = fetch_in_batches(&keys, |batch| async move {
+            let response = self.client
+                .search(&lookup.index, lookup_to_elastic_query(lookup, &batch))
+                .await
+                .map_err(|e| {
+                    error!("Lookup query failed for index {}: {}", lookup.index, e);
+                    map_elastic_error(e)
+                })?;
+            let docs = elastic_response_sources(&response);
+            check_lookup_complete(lookup, docs.len(), elastic_response_total(&response))?;
+            Ok::<_, SearchError>(docs)
+        })
+        .await?;
+        merge_lookup(&mut results, lookup, &docs)?;
+
+        Ok(results)
+    }
+
+    /// Estimate the cost of a query without running it
+    pub async fn estimate_query(&self, index: &str, query: &SearchQuery) -> SearchResult<CostEstimate> {
+        let document_count = match self.client.count_documents(index).await {
+            Ok(count) => Some(count),
+            Err(e) => {
+                debug!("Estimating without a document count for {}: {}", index, e);
+                None
+            }
+        };
+        let stats = IndexStats { document_count };
+        Ok(cost::estimate_query(&elasticsearch_capability_matrix(), index, &stats, query))
+    }
+
+    /// Reindex jobs are not offered here; the cluster copies indexes itself with `_reindex`
+    ///
+    /// Always fails with `unsupported`, as `reindex_jobs` in the capability matrix says.
+    pub async fn start_reindex(&self, _source: &str, _destination: &str) -> SearchResult<String> {
+        Err(SearchError::Unsupported)
+    }
+
+    /// Queued cluster state changes that name the index
+    ///
+    /// Document writes are applied synchronously and only wait for the next
+    /// refresh, so the list stays empty unless mapping, settings or index
+    /// changes are backed up.
+    pub async fn list_pending_tasks(&self, index: &str) -> SearchResult<PendingTasks> {
+        let body = self.client.pending_tasks().await.map_err(map_elastic_error)?;
+        Ok(PendingTasks::from_cluster_pending_tasks("elasticsearch", index, &body))
+    }
+
+    /// Changes to an index since the position of `since`, or an empty summary marking the current position
+    ///
+    /// Writes are read from the primary shards' index and delete counters.
+    /// Those restart with the shard, and a poll across a restart sees only
+    /// the net change in documents.
+    pub async fn index_changes(&self, index: &str, since: Option<&str>) -> SearchResult<ChangeSummary> {
+        let documents = self.client.count_documents(index).await.map_err(map_elastic_error)?;
+        let stats = self.client.index_stats(index).await.map_err(map_elastic_error)?;
+        let mut now = WatchCursor::new("elasticsearch", index, documents);
+        now.counters = WriteCounters::from_index_stats(&stats);
+
+        let Some(since) = since else {
+            return Ok(ChangeSummary::start(&now));
+        };
+        let before = WatchCursor::parse_for(since, "elasticsearch", index)?;
+        let writes = before.counters.zip(now.counters).and_then(|(before, now)| now.since(&before));
+        Ok(ChangeSummary::between(&before, &now, writes))
+    }
+
+    /// Slow queries recorded by this worker, as JSON
+    pub fn get_slow_queries(&self) -> SearchResult<String> {
+        Ok(serde_json::to_string(&slow_query::get_slow_queries())?)
+    }
+
+    /// Extended health report with per-endpoint circuit breaker state, as JSON
+    pub fn health_report(&self) -> SearchResult<String> {
+        Ok(serde_json::to_string(&circuit_breaker::health_report("elasticsearch"))?)
+    }
+
+    /// Operation counters, latency histograms and cache hit rates in Prometheus text format
+    pub fn metrics_prometheus(&self) -> SearchResult<String> {
+        Ok(metrics::render_prometheus())
+    }
+
+    /// Every environment variable the provider reads, whether it is set and parses, as JSON
+    ///
+    /// Needs no provider instance, so it also works when `new()` fails.
+    pub fn diagnose_config() -> SearchResult<String> {
+        Ok(serde_json::to_string(&diagnose::diagnose_config("elasticsearch"))?)
+    }
+
+    /// Audit log entries at or after `since` (RFC 3339), as JSON
+    pub fn get_audit_log(&self, since: Option<&str>) -> SearchResult<String> {
+        Ok(serde_json::to_string(&audit::get_audit_log(since)?)?)
+    }
+
     /// Get schema for an index
     pub async fn get_schema(&self, index: &str) -> SearchResult<Schema> {
         debug!("Getting schema for index {}", index);
 
+        let cache_key = cache::index_key("elasticsearch", index);
+        if let Some(schema) = cache::global().get::<Schema>(CacheKind::Schema, &cache_key) {
+            return Ok((*schema).clone());
+        }
+
         let mapping = self.client
             .get_mapping(index)
             .await
@@ -265,6 +1146,10 @@ impl ElasticSearchProvider {
 
         let schema = elastic_mapping_to_schema(&mapping, index)
             .map_err(|e| SearchError::Internal(e.to_string()))?;
+        numeric::remember("elasticsearch", index, &schema);
+
+        let size = cache::schema_size(schema.fields.iter().map(|f| f.name.as_str()));
+        cache::global().insert(CacheKind::Schema, &cache_key, schema.clone(), size);
 
         debug!("Successfully retrieved schema for index {}", index);
         Ok(schema)
@@ -272,21 +1157,176 @@ impl ElasticSearchProvider {
 
     /// Update schema for an index
     pub async fn update_schema(&self, index: &str, schema: &Schema) -> SearchResult<()> {
-        info!("Updating schema for index {}", index);
+        audit::audited("elasticsearch", "update_schema", index, serde_json::json!({ "schema": schema }), async {
+            info!("Updating schema for index {}", index);
 
-        let mapping = schema_to_elastic_mapping(schema)
-            .map_err(|e| SearchError::Internal(e.to_string()))?;
+            let mapping = schema_to_elastic_mapping(schema)
+                .map_err(|e| SearchError::Internal(e.to_string()))?;
 
-        self.client
-            .put_mapping(index, mapping)
+            self.client
+                .put_mapping(index, mapping)
+                .await
+                .map_err(|e| {
+                    error!("Failed to update mapping for index {}: {}", index, e);
+                    map_elastic_error(e)
+                })?;
+            let current = self.client.get_mapping(index).await.map_err(map_elastic_error)?;
+            self.client
+                .put_mapping(index, schema_meta_to_elastic_meta(&current, index, schema))
+                .await
+                .map_err(map_elastic_error)?;
+            numeric::remember("elasticsearch", index, schema);
+
+            cache::global().remove(CacheKind::Schema, &cache::index_key("elasticsearch", index));
+            info!("Successfully updated schema for index {}", index);
+            let fields: Vec<&str> = schema.fields.iter().map(|f| f.name.as_str()).collect();
+            events::emit(LifecycleEventKind::SchemaUpdated, "elasticsearch", index, serde_json::json!({ "fields": fields }));
+            Ok(())
+        })
+        .await
+    }
+
+    /// Export schema, synonyms and stop words as a JSON settings snapshot
+    pub async fn export_settings(&self, index: &str) -> SearchResult<String> {
+        debug!("Exporting settings for index {}", index);
+
+        let settings = self.client
+            .get_settings(index)
             .await
             .map_err(|e| {
-                error!("Failed to update mapping for index {}: {}", index, e);
+                error!("Failed to get settings for index {}: {}", index, e);
                 map_elastic_error(e)
             })?;
 
-        info!("Successfully updated schema for index {}", index);
-        Ok(())
+        let mut snapshot = elastic_settings_to_snapshot(&settings, index);
+        snapshot.schema = Some(self.get_schema(index).await?);
+        snapshot.normalization = self.current_normalization(index).await?;
+
+        snapshot.normalized().to_json()
+    }
+
+    /// Normalization recorded in the index mapping by `apply_settings`
+    async fn current_normalization(&self, index: &str) -> SearchResult<Option<Normalization>> {
+        let mapping = self.client
+            .get_mapping(index)
+            .await
+            .map_err(map_elastic_error)?;
+        Ok(elastic_mapping_normalization(&mapping, index))
+    }
+
+    /// Apply a JSON settings snapshot, reopening the index only when analysis changed
+    pub async fn apply_settings(&self, index: &str, settings_json: &str) -> SearchResult<()> {
+        audit::audited("elasticsearch", "apply_settings", index, audit::settings_details(settings_json), async {
+            info!("Applying settings to index {}", index);
+
+            let desired = IndexSettings::from_json(settings_json)?;
+
+            if let Some(schema) = &desired.schema {
+                self.update_schema(index, schema).await?;
+            }
+
+            if !desired.ranking_rules.is_empty() {
+                warn!("ElasticSearch has no ranking rules setting; ignoring {} rules", desired.ranking_rules.len());
+            }
+
+            let current_settings = self.client
+                .get_settings(index)
+                .await
+                .map_err(map_elastic_error)?;
+            let mut current = elastic_settings_to_snapshot(&current_settings, index);
+            current.normalization = self.current_normalization(index).await?;
+
+            let normalization_changed = desired.normalization.is_some() && current.normalization != desired.normalization;
+            if current.synonyms == desired.synonyms && current.stop_words == desired.stop_words && !normalization_changed {
+                debug!("Analysis settings for index {} already up to date", index);
+                return Ok(());
+            }
+
+            // Analysis settings are static and can only be changed on a closed index
+            self.client.close_index(index).await.map_err(map_elastic_error)?;
+            let result = self.client
+                .put_settings(index, snapshot_to_elastic_analysis(&desired))
+                .await;
+            self.client.open_index(index).await.map_err(map_elastic_error)?;
+
+            result.map_err(|e| {
+                error!("Failed to update analysis settings for index {}: {}", index, e);
+                map_elastic_error(e)
+            })?;
+
+            if let Some(normalization) = desired.normalization.filter(|_| normalization_changed) {
+                let mapping = self.client.get_mapping(index).await.map_err(map_elastic_error)?;
+                self.client
+                    .put_mapping(index, normalization_to_elastic_meta(&mapping, index, &normalization))
+                    .await
+                    .map_err(map_elastic_error)?;
+            }
+
+            info!("Successfully applied settings to index {}", index);
+            Ok(())
+        })
+        .await
+    }
+
+    /// Report drift between the index and a desired settings snapshot, optionally reconciling it
+    pub async fn diff_settings(&self, index: &str, desired_json: &str, reconcile: bool) -> SearchResult<SettingsDiff> {
+        let desired = IndexSettings::from_json(desired_json)?;
+
+        let settings = self.client
+            .get_settings(index)
+            .await
+            .map_err(map_elastic_error)?;
+        let mut current = elastic_settings_to_snapshot(&settings, index);
+        current.schema = Some(self.get_schema(index).await?);
+        current.normalization = self.current_normalization(index).await?;
+
+        let mut diff = current.diff(&desired);
+
+        if reconcile && !diff.is_empty() {
+            info!("Reconciling {} setting changes on index {}", diff.changes.len(), index);
+            self.apply_settings(index, desired_json).await?;
+            diff.reconciled = true;
+        }
+
+        Ok(diff)
+    }
+}
+
+impl RoutedBackend for ElasticSearchProvider {
+    fn search<'a>(&'a self, index: &'a str, query: &'a SearchQuery) -> LocalBoxFuture<'a, SearchResult<SearchResults>> {
+        Box::pin(ElasticSearchProvider::search(self, index, query))
+    }
+
+    fn get<'a>(&'a self, index: &'a str, id: &'a str) -> LocalBoxFuture<'a, SearchResult<Option<Doc>>> {
+        Box::pin(ElasticSearchProvider::get(self, index, id, None))
+    }
+
+    fn upsert_many<'a>(&'a self, index: &'a str, docs: &'a [Doc], pipeline: Option<&'a str>) -> LocalBoxFuture<'a, SearchResult<()>> {
+        Box::pin(ElasticSearchProvider::upsert_many_with_pipeline(self, index, docs, pipeline))
+    }
+
+    fn delete<'a>(&'a self, index: &'a str, id: &'a str) -> LocalBoxFuture<'a, SearchResult<()>> {
+        Box::pin(ElasticSearchProvider::delete(self, index, id))
+    }
+
+    fn create_index<'a>(&'a self, name: &'a str, schema: Option<&'a Schema>) -> LocalBoxFuture<'a, SearchResult<()>> {
+        Box::pin(ElasticSearchProvider::create_index(self, name, schema))
+    }
+
+    fn delete_index<'a>(&'a self, name: &'a str) -> LocalBoxFuture<'a, SearchResult<()>> {
+        Box::pin(async move { ElasticSearchProvider::delete_index(self, name, false).await.map(|_| ()) })
+    }
+
+    fn list_indexes(&self) -> LocalBoxFuture<'_, SearchResult<Vec<String>>> {
+        Box::pin(ElasticSearchProvider::list_indexes(self))
+    }
+
+    fn get_schema<'a>(&'a self, index: &'a str) -> LocalBoxFuture<'a, SearchResult<Schema>> {
+        Box::pin(ElasticSearchProvider::get_schema(self, index))
+    }
+
+    fn describe(&self) -> LocalBoxFuture<'_, ProviderDescription> {
+        Box::pin(ElasticSearchProvider::describe(self))
     }
 }
 
@@ -310,6 +1350,7 @@ mod tests {
                 cloud_id: None,
                 ca_cert: None,
             },
+            quotas: Default::default(),
         }
     }
 
@@ -360,6 +1401,7 @@ mod tests {
                 pre_tag: Some("<mark>".to_string()),
                 post_tag: Some("</mark>".to_string()),
                 max_length: Some(150),
+                offsets: false,
             }),
             config: None,
         };
@@ -370,4 +1412,69 @@ mod tests {
         assert_eq!(query.facets.len(), 1);
         assert!(query.highlight.is_some());
     }
+
+    #[test]
+    fn test_delete_by_query_filters_must_parse() {
+        let query = filters_to_elastic_query(&["category:test".to_string()]).unwrap();
+        assert_eq!(query, json!({ "bool": { "filter": [{ "term": { "category": "test" } }] } }));
+
+        // A filter search would skip must fail rather than widen the delete
+        assert!(filters_to_elastic_query(&["category:test".to_string(), "oops".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_match_all_spellings_build_the_same_query() {
+        let expected = search_query_to_elastic_query(&SearchQuery::match_all()).unwrap();
+        assert_eq!(expected["query"]["bool"]["must"], json!([{ "match_all": {} }]));
+
+        for q in [None, Some(""), Some("  ")] {
+            let query = SearchQuery { q: q.map(str::to_string), ..SearchQuery::match_all() };
+            assert_eq!(search_query_to_elastic_query(&query).unwrap(), expected, "{:?}", q);
+        }
+    }
+
+    #[test]
+    fn test_malformed_filters_fail_the_search() {
+        let filters = ["oops", "geo_polygon(location, [[0, 0], [0, 1]]", "id_set(sku, [1, 2]"];
+        for filter in filters {
+            let query = SearchQuery { filters: vec!["category:test".to_string(), filter.to_string()], ..SearchQuery::match_all() };
+            let err = search_query_to_elastic_query(&query).unwrap_err();
+            assert!(matches!(err.downcast_ref::<SearchError>(), Some(SearchError::InvalidQuery(_))), "{}: {}", filter, err);
+        }
+    }
+
+    #[test]
+    fn test_exact_total_tracks_every_hit_and_capped_totals_are_inexact() {
+        let mut query = SearchQuery::match_all();
+        assert!(search_query_to_elastic_query(&query).unwrap().get("track_total_hits").is_none());
+        query.config = Some(golem_search::types::SearchConfig { exact_total: true, ..Default::default() });
+        let mut request = search_query_to_elastic_query(&query).unwrap();
+        assert_eq!(request["track_total_hits"], json!(true));
+
+        let page = golem_search::cursor::LucenePage::apply(&mut request, "elasticsearch", "products", None).unwrap();
+        let capped = r#"{"took":5,"hits":{"total":{"value":10000,"relation":"gte"},"hits":[]}}"#;
+        let results = elastic_response_to_search_results(capped, &request, &page).unwrap();
+        assert_eq!((results.total, results.total_exact), (Some(10000), Some(false)));
+    }
+
+    #[test]
+    fn test_field_analysis_round_trips_through_mapping() {
+        use golem_search::analysis::{FieldAnalysis, Tokenizer};
+
+        let schema = golem_search::SchemaBuilder::new()
+            .analyzed_text_field("body_ja", FieldAnalysis::with_tokenizer(Tokenizer::Kuromoji))
+            .analyzed_text_field("body_zh", FieldAnalysis::with_tokenizer(Tokenizer::Jieba))
+            .analyzed_text_field("summary", FieldAnalysis::stemmed("fr"))
+            .text_field("title")
+            .build();
+        let mapping = schema_to_elastic_mapping(&schema).unwrap();
+        assert_eq!(mapping["mappings"]["properties"]["body_ja"]["analyzer"], "kuromoji");
+        assert_eq!(mapping["mappings"]["properties"]["body_zh"]["analyzer"], "smartcn");
+
+        let read_back = elastic_mapping_to_schema(&mapping, "books").unwrap();
+        let analysis = |name: &str| read_back.fields.iter().find(|f| f.name == name).unwrap().analysis.clone();
+        assert_eq!(analysis("body_zh"), Some(FieldAnalysis::with_tokenizer(Tokenizer::Jieba)));
+        assert_eq!(analysis("summary"), Some(FieldAnalysis::stemmed("fr")));
+        assert_eq!(analysis("title"), None);
+    }
 }
@@ -4,22 +4,115 @@
 //! It features instant search, typo tolerance, faceted search, and built-in ranking.
 
 use anyhow::Result;
-use log::{error, info};
+use log::{debug, error, info, warn};
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::future::Future;
+use std::rc::Rc;
+use std::sync::Arc;
 use std::time::Duration;
-use reqwest::{Client, Method, header::{HeaderMap, HeaderValue, CONTENT_TYPE, AUTHORIZATION}};
+use golem_search::http::{self, Client, Method, header::{HeaderMap, HeaderValue, CONTENT_ENCODING, CONTENT_TYPE, AUTHORIZATION}};
+use serde::Deserialize;
 use serde_json::{Value, json};
-use url::Url;
+use golem_search::{IndexSettings, LookupSpec, SettingsDiff};
+use golem_search::lookup::{align_to_ids, check_lookup_complete, fetch_in_batches, lookup_keys_from_contents, LookupTable, MAX_LOOKUP_DOCS};
+use golem_search::raw::{raw_explanation, raw_to_json, RawValue};
+use golem_search::analysis::{parse_field_analysis, parse_normalization, unsupported_analysis, FieldAnalysis, Normalization};
+use golem_search::alerts;
+use golem_search::audit;
+use golem_search::batching::{submit_adaptive, AdaptiveBatchConfig, AdaptiveBatcher};
+use golem_search::cache::{self, CacheKind, CacheQuery};
+use golem_search::capabilities::meilisearch_capability_matrix;
+use golem_search::circuit_breaker;
+use golem_search::shutdown::{self, ShutdownReport};
+use golem_search::compiled_query::{self, TemplateQuery};
+use golem_search::compression::Compression;
+use golem_search::config::{ConfigProfile, DegradationMode};
+use golem_search::consistency::{self, ConsistencyToken};
+use golem_search::cost::{self, CostEstimate, IndexStats};
+use golem_search::cursor::{self, CursorPosition, PageCursor};
+use golem_search::deadline;
+use golem_search::delta_sync::{self, IndexFingerprint, SyncReport};
+use golem_search::describe::{self, ProviderDescription};
+use golem_search::diagnose;
+use golem_search::events::{self, LifecycleEventKind};
+use golem_search::field_mask::FieldMask;
+use golem_search::geo::{self, GeoDistanceSort, GeoPolygonFilter};
+use golem_search::highlight;
+use golem_search::id_set::IdSetFilter;
+use golem_search::ingest;
+use golem_search::instance::{self, ProviderCache};
+use golem_search::dry_run::{self, OperationPlan};
+use golem_search::endpoint_pool::{self, EndpointPool};
+use golem_search::facets::{
+    bucket_facet_distribution, parse_facets, ClientFacets, FacetSpec,
+};
+use golem_search::json;
+use golem_search::query::{parse_fuzziness, parse_minimum_should_match, Fuzziness, MinimumShouldMatch, ParsedQuery, PatternLimits};
+use golem_search::types::QueryText;
+use golem_search::ui_metadata::{self, parse_field_ui};
+use golem_search::quota;
+#[cfg(feature = "durability")]
+use golem_search::reindex::{self, JobStore, ReindexStatus, SourcePage, JOBS_INDEX, REINDEX_PAGE_SIZE};
+#[cfg(feature = "durability")]
+use golem_search::router::LocalBoxFuture;
+use golem_search::query_split::{self, SplitLimits, SplitQuery, SplitResults};
+use golem_search::rate_limit::{self, Throttle};
+use golem_search::request_id::{self, REQUEST_ID_HEADER};
+use golem_search::rescue::{self, RescuePlan, RescueQuery, RescueResults, Relaxation};
+use golem_search::session::{self, SessionContext};
+use golem_search::metrics::{self, ErrorKind};
+use golem_search::telemetry;
+use golem_search::version::{self, ServerVersion};
+use golem_search::watch::{ChangeSummary, TaskPage, WatchCursor, WriteCounters};
+use golem_search::response_limits::ResponseLimits;
+use golem_search::retry::RetryPolicy;
+use golem_search::sampling;
+use golem_search::schema::check_schema_compatibility;
+use golem_search::slow_query;
+use golem_search::snapshot::{SnapshotInfo, SnapshotKind};
+use golem_search::spellfix::{self, Correction};
+use golem_search::suggestions::{self, PopularQuery, Suggestion, SuggestionsBuilder, SuggestionsConfig};
+use golem_search::tasks::{self, Backpressure, PendingTasks};
+use golem_search::templates::{self, IndexTemplate, TemplateOverrides};
+use golem_search::change_feed::ChangeBatch;
+use golem_search::checksum;
+use golem_search::numeric;
+use golem_search::outbox::{self, DrainReport, Mutation};
+use golem_search::write_buffer;
+use golem_search::query_ast::{self, AstNode};
+use golem_search::provider_params::{merge_into_body, parse_provider_params};
 
 // Use the generated WIT types
 use golem::search::types::{
     SearchError, Doc, SearchQuery, SearchResults, Schema,
-    SearchCapabilities, FieldType, SchemaField,
+    SearchCapabilities, FieldType, SchemaField, BackpressureInfo, OutboxMutation,
+    FacetMetadata, FacetWidget,
+    QueryAst, QueryAstNode, QueryBool, QueryPattern, QueryPhrase, QueryRange, QueryTerm, RangeBound,
 };
 
 // Helper type alias
 type SearchResult<T> = Result<T, SearchError>;
 
+/// Search body keys that may be supplied through `provider-params`
+pub const MEILISEARCH_ALLOWED_PARAMS: &[&str] = &[
+    "matchingStrategy",
+    "showRankingScore",
+    "showRankingScoreDetails",
+    "rankingScoreThreshold",
+    "attributesToSearchOn",
+    "attributesToCrop",
+    "cropLength",
+    "cropMarker",
+    "distinct",
+    "hybrid",
+    "vector",
+    "locales",
+];
+
+/// Header carrying a session's user token, which Meilisearch Cloud analytics attributes events to
+const USER_ID_HEADER: &str = "X-MS-USER-ID";
+
 /// Configuration for the Meilisearch client
 #[derive(Debug, Clone)]
 pub struct MeilisearchConfig {
@@ -27,11 +120,28 @@ pub struct MeilisearchConfig {
     pub master_key: Option<String>,
     pub timeout: Duration,
     pub max_retries: u32,
+
+    /// Whether queries needing a fallback, such as polygon filters, fail instead
+    pub degradation: DegradationMode,
+
+    /// Compression of document additions
+    pub compression: Compression,
 }
 
 impl MeilisearchConfig {
     /// Create configuration from environment variables
     pub fn from_env() -> Result<Self> {
+        if let Some(profile) = ConfigProfile::active("meilisearch")? {
+            return Ok(Self {
+                endpoint: profile.endpoint,
+                master_key: profile.api_key,
+                timeout: profile.timeout,
+                max_retries: profile.max_retries,
+                degradation: profile.degradation,
+                compression: Compression::from_env(),
+            });
+        }
+
         let endpoint = std::env::var("SEARCH_PROVIDER_ENDPOINT")
             .or_else(|_| std::env::var("MEILISEARCH_ENDPOINT"))
             .unwrap_or_else(|_| "http://localhost:7700".to_string());
@@ -55,6 +165,8 @@ impl MeilisearchConfig {
             master_key,
             timeout: Duration::from_secs(timeout),
             max_retries,
+            degradation: DegradationMode::default(),
+            compression: Compression::from_env(),
         })
     }
 }
@@ -63,7 +175,8 @@ impl MeilisearchConfig {
 pub struct MeilisearchClient {
     config: MeilisearchConfig,
     http_client: Client,
-    base_url: Url,
+    endpoints: Arc<EndpointPool>,
+    throttle: Arc<Throttle>,
 }
 
 impl MeilisearchClient {
@@ -79,35 +192,43 @@ impl MeilisearchClient {
 
         let http_client = Client::builder()
             .timeout(config.timeout)
+            .retry(RetryPolicy::new(config.max_retries))
+            .accept_compressed(config.compression.is_enabled())
+            .http2(true)
             .default_headers(headers)
             .build()
             .map_err(|e| anyhow::anyhow!("Failed to create HTTP client: {}", e))?;
 
-        let base_url = Url::parse(&config.endpoint)
-            .map_err(|e| anyhow::anyhow!("Invalid endpoint URL: {}", e))?;
+        let endpoints = endpoint_pool::pool(&config.endpoint)?;
 
         Ok(Self {
             config,
             http_client,
-            base_url,
+            endpoints,
+            throttle: rate_limit::throttle("meilisearch"),
         })
     }
 
     /// Execute an HTTP request
-    fn request_sync(&self, method: Method, path: &str, body: Option<Value>) -> Result<reqwest::Response> {
-        let url = self.base_url.join(path)
-            .map_err(|e| anyhow::anyhow!("Failed to build URL: {}", e))?;
-
-        let mut request = self.http_client.request(method, url);
-
-        if let Some(body) = body {
-            request = request.json(&body);
-        }
+    fn request_sync(&self, method: Method, path: &str, body: Option<Value>) -> Result<golem_search::http::Response> {
+        let request_id = request_id::current_or_generate();
+        debug!("[{}] {} {}", request_id, method, path);
+        let user_token = session::current_user_token();
+
+        self.throttle.send(|| self.endpoints.send(&method, path, |url| {
+            let mut request = self.http_client
+                .request(method.clone(), url)
+                .header(REQUEST_ID_HEADER, request_id.as_str());
+            if let Some(ref user_token) = user_token {
+                request = request.header(USER_ID_HEADER, user_token.as_str());
+            }
 
-        let response = request.send()
-            .map_err(|e| anyhow::anyhow!("Request failed: {}", e))?;
+            if let Some(ref body) = body {
+                request = request.json(body);
+            }
 
-        Ok(response)
+            request.send()
+        }))
     }
 
     /// Create an index
@@ -123,7 +244,7 @@ impl MeilisearchClient {
         let response = self.request_sync(Method::POST, "indexes", Some(body))?;
         
         if response.status().is_success() {
-            let result: Value = response.json()
+            let result: Value = json::from_response(response)
                 .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?;
             Ok(result)
         } else {
@@ -133,13 +254,92 @@ impl MeilisearchClient {
         }
     }
 
+    /// Detect the server version (`GET /version`)
+    pub async fn detect_version(&self) -> Result<ServerVersion> {
+        let response = self.request_sync(Method::GET, "version", None)?;
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!("Failed to get version: HTTP {}", response.status()));
+        }
+        
+        let info: Value = json::from_response(response)
+            .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?;
+        info.pointer("/pkgVersion")
+            .and_then(Value::as_str)
+            .and_then(ServerVersion::parse)
+            .ok_or_else(|| anyhow::anyhow!("No version in version response"))
+    }
+
+    /// Number of documents in an index, from its stats
+    pub async fn count_documents(&self, index_name: &str) -> Result<u64> {
+        let path = format!("indexes/{}/stats", index_name);
+        let response = self.request_sync(Method::GET, &path, None)?;
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!("Failed to get index stats: HTTP {}", response.status()));
+        }
+        
+        let stats: Value = json::from_response(response)
+            .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?;
+        stats.pointer("/numberOfDocuments")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| anyhow::anyhow!("No document count in index stats"))
+    }
+
+    /// Number of documents matching a filter (`POST /indexes/{uid}/documents/fetch`)
+    ///
+    /// Unlike a search, the total of a document fetch is not capped by `maxTotalHits`.
+    pub async fn count_matching(&self, index_name: &str, filter: &str) -> Result<u64> {
+        let path = format!("indexes/{}/documents/fetch", index_name);
+        let response = self.request_sync(Method::POST, &path, Some(json!({ "filter": filter, "limit": 0 })))?;
+        if !response.status().is_success() {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(anyhow::anyhow!("Failed to count documents: {}", error_text));
+        }
+
+        let result: Value = json::from_response(response)
+            .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?;
+        result.pointer("/total")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| anyhow::anyhow!("No total in document fetch response"))
+    }
+
+    /// Enqueue the deletion of the documents matching a filter
+    pub async fn delete_documents_by_filter(&self, index_name: &str, filter: &str) -> Result<Value> {
+        let path = format!("indexes/{}/documents/delete", index_name);
+        let response = self.request_sync(Method::POST, &path, Some(json!({ "filter": filter })))?;
+
+        if response.status().is_success() {
+            json::from_response(response)
+                .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))
+        } else {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            Err(anyhow::anyhow!("Failed to delete documents: {}", error_text))
+        }
+    }
+
+    /// Enqueue the deletion of every document, keeping the index and its settings
+    pub async fn delete_all_documents(&self, index_name: &str) -> Result<Value> {
+        let path = format!("indexes/{}/documents", index_name);
+        let response = self.request_sync(Method::DELETE, &path, None)?;
+
+        if response.status().is_success() {
+            json::from_response(response)
+                .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))
+        } else {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            Err(anyhow::anyhow!("Failed to delete documents: {}", error_text))
+        }
+    }
+
     /// Delete an index
     pub async fn delete_index(&self, index_name: &str) -> Result<Value> {
         let path = format!("indexes/{}", index_name);
         let response = self.request_sync(Method::DELETE, &path, None)?;
         
         if response.status().is_success() {
-            let result: Value = response.json()
+            let result: Value = json::from_response(response)
                 .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?;
             Ok(result)
         } else {
@@ -154,7 +354,7 @@ impl MeilisearchClient {
         let response = self.request_sync(Method::GET, "indexes", None)?;
         
         if response.status().is_success() {
-            let indexes_response: Value = response.json()
+            let indexes_response: Value = json::from_response(response)
                 .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?;
             
             let empty_vec = vec![];
@@ -184,7 +384,7 @@ impl MeilisearchClient {
         let response = self.request_sync(Method::GET, &path, None)?;
         
         if response.status().is_success() {
-            let result: Value = response.json()
+            let result: Value = json::from_response(response)
                 .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?;
             Ok(result)
         } else {
@@ -194,13 +394,80 @@ impl MeilisearchClient {
         }
     }
 
+    /// Get the status of an asynchronous task
+    pub async fn get_task(&self, task_uid: u64) -> Result<Value> {
+        let path = format!("tasks/{}", task_uid);
+        let response = self.request_sync(Method::GET, &path, None)?;
+        
+        if response.status().is_success() {
+            let result: Value = json::from_response(response)
+                .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?;
+            Ok(result)
+        } else {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            Err(anyhow::anyhow!("Failed to get task: {}", error_text))
+        }
+    }
+
+    /// Start a dump of the whole instance
+    pub async fn create_dump(&self) -> Result<Value> {
+        let response = self.request_sync(Method::POST, "dumps", None)?;
+
+        if response.status().is_success() {
+            json::from_response(response)
+                .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))
+        } else {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            Err(anyhow::anyhow!("Failed to create dump: {}", error_text))
+        }
+    }
+
+    /// List the enqueued and processing tasks of an index, newest first
+    pub async fn pending_tasks(&self, index_name: &str, limit: u32) -> Result<Value> {
+        let path = format!("tasks?indexUids={}&statuses=enqueued,processing&limit={}", index_name, limit);
+        let response = self.request_sync(Method::GET, &path, None)?;
+        
+        if response.status().is_success() {
+            let result: Value = json::from_response(response)
+                .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?;
+            Ok(result)
+        } else {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            Err(anyhow::anyhow!("Failed to list tasks: {}", error_text))
+        }
+    }
+
+    /// List the succeeded document tasks of an index, newest first, starting at task `from`
+    pub async fn finished_write_tasks(&self, index_name: &str, from: Option<u64>, limit: u32) -> Result<Value> {
+        let mut path = format!(
+            "tasks?indexUids={}&statuses=succeeded&types=documentAdditionOrUpdate,documentDeletion,documentEdition&limit={}",
+            index_name, limit
+        );
+        if let Some(from) = from {
+            path.push_str(&format!("&from={}", from));
+        }
+        let response = self.request_sync(Method::GET, &path, None)?;
+        
+        if response.status().is_success() {
+            json::from_response(response)
+                .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))
+        } else {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            Err(anyhow::anyhow!("Failed to list tasks: {}", error_text))
+        }
+    }
+
     /// Update index settings
     pub async fn update_settings(&self, index_name: &str, settings: Value) -> Result<Value> {
         let path = format!("indexes/{}/settings", index_name);
         let response = self.request_sync(Method::PATCH, &path, Some(settings))?;
         
         if response.status().is_success() {
-            let result: Value = response.json()
+            let result: Value = json::from_response(response)
                 .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?;
             Ok(result)
         } else {
@@ -216,7 +483,7 @@ impl MeilisearchClient {
         let response = self.request_sync(Method::GET, &path, None)?;
         
         if response.status().is_success() {
-            let result: Value = response.json()
+            let result: Value = json::from_response(response)
                 .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?;
             Ok(result)
         } else {
@@ -226,13 +493,25 @@ impl MeilisearchClient {
         }
     }
 
-    /// Add or update documents
+    /// Add or update documents, in a body compressed as configured
     pub async fn add_documents(&self, index_name: &str, documents: Value) -> Result<Value> {
         let path = format!("indexes/{}/documents", index_name);
-        let response = self.request_sync(Method::POST, &path, Some(documents))?;
+        let compression = self.config.compression;
+        let body = compression.compress(serde_json::to_vec(&documents)?)?;
+        let request_id = request_id::current_or_generate();
+        debug!("[{}] POST {}", request_id, path);
+        let response = self.throttle.send(|| self.endpoints.send(&Method::POST, &path, |url| {
+            let mut request = self.http_client
+                .post(url)
+                .header(REQUEST_ID_HEADER, request_id.as_str());
+            if let Some(encoding) = compression.content_encoding() {
+                request = request.header(CONTENT_ENCODING, encoding);
+            }
+            request.body(body.clone()).send()
+        }))?;
         
         if response.status().is_success() || response.status().as_u16() == 202 {
-            let result: Value = response.json()
+            let result: Value = json::from_response(response)
                 .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?;
             Ok(result)
         } else {
@@ -242,13 +521,17 @@ impl MeilisearchClient {
         }
     }
 
-    /// Get a document by ID
-    pub async fn get_document(&self, index_name: &str, id: &str) -> Result<Option<Value>> {
-        let path = format!("indexes/{}/documents/{}", index_name, id);
+    /// Get a document by ID, with only the top-level `fields` when given
+    pub async fn get_document(&self, index_name: &str, id: &str, fields: Option<&[String]>) -> Result<Option<Value>> {
+        let mut path = format!("indexes/{}/documents/{}", index_name, id);
+        if let Some(fields) = fields {
+            let fields: String = url::form_urlencoded::byte_serialize(fields.join(",").as_bytes()).collect();
+            path.push_str(&format!("?fields={}", fields));
+        }
         let response = self.request_sync(Method::GET, &path, None)?;
         
         if response.status().is_success() {
-            let result: Value = response.json()
+            let result: Value = json::from_response(response)
                 .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?;
             Ok(Some(result))
         } else if response.status().as_u16() == 404 {
@@ -260,13 +543,59 @@ impl MeilisearchClient {
         }
     }
 
+    /// Fetch several documents by ID (`POST /documents/fetch`, Meilisearch 1.12+)
+    pub async fn fetch_documents(&self, index_name: &str, ids: &[String], fields: Option<&[String]>) -> Result<Value> {
+        let path = format!("indexes/{}/documents/fetch", index_name);
+        let mut body = json!({ "ids": ids, "limit": ids.len() });
+        if let Some(fields) = fields {
+            body["fields"] = json!(fields);
+        }
+        let response = self.request_sync(Method::POST, &path, Some(body))?;
+        
+        if response.status().is_success() {
+            let result: Value = json::from_response(response)
+                .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?;
+            Ok(result)
+        } else {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            Err(anyhow::anyhow!("Failed to fetch documents: {}", error_text))
+        }
+    }
+
+    /// Read a page of an index's documents in storage order
+    pub async fn list_documents(&self, index_name: &str, offset: usize, limit: usize) -> Result<Value> {
+        let path = format!("indexes/{}/documents?offset={}&limit={}", index_name, offset, limit);
+        let response = self.request_sync(Method::GET, &path, None)?;
+        
+        if response.status().is_success() {
+            let result: Value = json::from_response(response)
+                .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?;
+            Ok(result)
+        } else {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            Err(anyhow::anyhow!("Failed to list documents: {}", error_text))
+        }
+    }
+
+    /// Check whether a document exists, fetching only its `id` field
+    ///
+    /// A missing index is an error rather than an absent document.
+    pub async fn document_exists(&self, index_name: &str, id: &str) -> Result<bool> {
+        let path = format!("indexes/{}/documents/{}?fields=id", index_name, http::path_segment(id));
+        let response = self.request_sync(Method::GET, &path, None)?;
+        let status = response.status().as_u16();
+        document_presence(status, &response.text().unwrap_or_default())
+    }
+
     /// Delete a document by ID
     pub async fn delete_document(&self, index_name: &str, id: &str) -> Result<Value> {
         let path = format!("indexes/{}/documents/{}", index_name, id);
         let response = self.request_sync(Method::DELETE, &path, None)?;
         
         if response.status().is_success() || response.status().as_u16() == 202 {
-            let result: Value = response.json()
+            let result: Value = json::from_response(response)
                 .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?;
             Ok(result)
         } else {
@@ -276,15 +605,36 @@ impl MeilisearchClient {
         }
     }
 
+    /// Enqueue the deletion of documents by ID
+    pub async fn delete_documents(&self, index_name: &str, ids: &[String]) -> Result<Value> {
+        let path = format!("indexes/{}/documents/delete-batch", index_name);
+        let response = self.request_sync(Method::POST, &path, Some(json!(ids)))?;
+
+        if response.status().is_success() {
+            json::from_response(response)
+                .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))
+        } else {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            Err(anyhow::anyhow!("Failed to delete documents: {}", error_text))
+        }
+    }
+
     /// Search documents
     pub async fn search(&self, index_name: &str, query: Value) -> Result<Value> {
+        let body = self.search_raw(index_name, query).await?;
+        json::from_str(&body)
+            .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))
+    }
+
+    /// Search documents, returning the unparsed response body
+    pub async fn search_raw(&self, index_name: &str, query: Value) -> Result<String> {
         let path = format!("indexes/{}/search", index_name);
         let response = self.request_sync(Method::POST, &path, Some(query))?;
         
         if response.status().is_success() {
-            let result: Value = response.json()
-                .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?;
-            Ok(result)
+            response.text()
+                .map_err(|e| anyhow::anyhow!("Failed to read response: {}", e))
         } else {
             let error_text = response.text()
                 .unwrap_or_else(|_| "Unknown error".to_string());
@@ -297,7 +647,7 @@ impl MeilisearchClient {
         let response = self.request_sync(Method::GET, "stats", None)?;
         
         if response.status().is_success() {
-            let result: Value = response.json()
+            let result: Value = json::from_response(response)
                 .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?;
             Ok(result)
         } else {
@@ -309,19 +659,472 @@ impl MeilisearchClient {
 }
 
 /// Map Meilisearch errors to SearchError
+/// Whether a document fetch answer found its document
+///
+/// Both a missing document and a missing index answer 404; the error `code`
+/// of the body is `document_not_found` only for the document.
+fn document_presence(status: u16, body: &str) -> Result<bool> {
+    match status {
+        200..=299 => Ok(true),
+        404 => {
+            let answer: Value = serde_json::from_str(body).unwrap_or_default();
+            match answer["code"].as_str() {
+                Some("document_not_found") => Ok(false),
+                code => Err(anyhow::anyhow!("{}: HTTP 404 {}", code.unwrap_or("index_not_found"), body)),
+            }
+        }
+        status => Err(anyhow::anyhow!("Failed to check document existence: HTTP {} {}", status, body)),
+    }
+}
+
 pub fn map_meilisearch_error(error: anyhow::Error) -> SearchError {
+    if let Some(rejection) = circuit_breaker::open_circuit(&error).or_else(|| rate_limit::rate_limited(&error)) {
+        return map_shared_error(rejection);
+    }
     let error_string = error.to_string();
     
-    if error_string.contains("index_not_found") || error_string.contains("404") {
-        SearchError::IndexNotFound(error_string)
+    if error_string.contains("index_already_exists") {
+        SearchError::IndexAlreadyExists(request_id::tag(error_string))
+    } else if error_string.contains("index_not_found") || error_string.contains("404") {
+        SearchError::IndexNotFound(request_id::tag(error_string))
     } else if error_string.contains("invalid_request") || error_string.contains("400") {
-        SearchError::InvalidQuery(error_string)
+        SearchError::InvalidQuery(request_id::tag(error_string))
     } else if error_string.contains("timeout") {
-        SearchError::Timeout
+        SearchError::Timeout(deadline::remaining_ms())
     } else if error_string.contains("rate") || error_string.contains("429") {
-        SearchError::RateLimited
+        SearchError::RateLimited(None)
     } else {
-        SearchError::Internal(error_string)
+        SearchError::Internal(request_id::tag(error_string))
+    }
+}
+
+/// Map errors from the shared golem-search library to the WIT error type
+fn map_shared_error(error: golem_search::SearchError) -> SearchError {
+    match request_id::annotate(error) {
+        golem_search::SearchError::IndexNotFound(msg) => SearchError::IndexNotFound(msg),
+        golem_search::SearchError::IndexAlreadyExists(msg) => SearchError::IndexAlreadyExists(msg),
+        golem_search::SearchError::InvalidQuery(msg) => SearchError::InvalidQuery(msg),
+        golem_search::SearchError::Timeout { remaining_ms } => SearchError::Timeout(remaining_ms),
+        golem_search::SearchError::RateLimited { retry_after_ms } => SearchError::RateLimited(retry_after_ms),
+        golem_search::SearchError::Unavailable { retry_after_ms } => SearchError::Unavailable(retry_after_ms),
+        golem_search::SearchError::Unsupported => SearchError::Unsupported("Operation not supported".to_string()),
+        e @ golem_search::SearchError::ResponseTooLarge { .. } => SearchError::ResponseTooLarge(e.to_string()),
+        golem_search::SearchError::Backpressure { queue_depth, retry_after_ms } => {
+            SearchError::Backpressure(BackpressureInfo { queue_depth, retry_after_ms })
+        }
+        other => SearchError::Internal(other.to_string()),
+    }
+}
+
+/// Approximate payload size of a hit
+fn hit_size(hit: &golem::search::types::SearchHit) -> usize {
+    hit.id.len()
+        + hit.content.as_ref().map_or(0, String::len)
+        + hit.highlights.as_ref().map_or(0, String::len)
+        + hit.explanation.as_ref().map_or(0, String::len)
+}
+
+/// Approximate payload size of a result page, used to account for cached results
+fn results_size(results: &SearchResults) -> usize {
+    results.hits.iter().map(hit_size).sum::<usize>() + results.facets.as_ref().map_or(0, String::len)
+}
+
+/// Convert a WIT field type to the shared golem-search representation
+fn to_shared_field_type(field_type: &FieldType) -> golem_search::FieldType {
+    match field_type {
+        FieldType::Text => golem_search::FieldType::Text,
+        FieldType::Keyword => golem_search::FieldType::Keyword,
+        FieldType::Integer => golem_search::FieldType::Integer,
+        FieldType::Float => golem_search::FieldType::Float,
+        FieldType::Boolean => golem_search::FieldType::Boolean,
+        FieldType::Date => golem_search::FieldType::Date,
+        FieldType::GeoPoint => golem_search::FieldType::GeoPoint,
+        FieldType::Int64 => golem_search::FieldType::Int64,
+        FieldType::Decimal => golem_search::FieldType::Decimal,
+    }
+}
+
+/// Convert a shared golem-search field type to the WIT representation
+fn from_shared_field_type(field_type: golem_search::FieldType) -> FieldType {
+    match field_type {
+        golem_search::FieldType::Text => FieldType::Text,
+        golem_search::FieldType::Keyword => FieldType::Keyword,
+        golem_search::FieldType::Integer => FieldType::Integer,
+        golem_search::FieldType::Float => FieldType::Float,
+        golem_search::FieldType::Boolean => FieldType::Boolean,
+        golem_search::FieldType::Date => FieldType::Date,
+        golem_search::FieldType::GeoPoint => FieldType::GeoPoint,
+        golem_search::FieldType::Int64 => FieldType::Int64,
+        golem_search::FieldType::Decimal => FieldType::Decimal,
+    }
+}
+
+/// Convert a WIT schema to the shared golem-search schema
+fn to_shared_schema(schema: &Schema) -> golem_search::Schema {
+    golem_search::Schema {
+        fields: schema.fields.iter().map(|f| golem_search::SchemaField {
+            name: f.name.clone(),
+            field_type: to_shared_field_type(&f.field_type),
+            required: f.required,
+            facet: f.facet,
+            sort: f.sort,
+            index: f.index,
+            // Malformed hints are rejected when the schema is applied
+            analysis: parse_field_analysis(f.analysis.as_deref()).ok().flatten(),
+            ui: parse_field_ui(f.ui.as_deref()).ok().flatten(),
+        }).collect(),
+        primary_key: schema.primary_key.clone(),
+    }
+}
+
+/// Convert shared facet display metadata to the WIT record
+fn from_shared_facet_metadata(metadata: ui_metadata::FacetMetadata) -> FacetMetadata {
+    FacetMetadata {
+        field: metadata.field,
+        display_name: metadata.display_name,
+        widget: match metadata.widget {
+            ui_metadata::FacetWidget::Checkbox => FacetWidget::Checkbox,
+            ui_metadata::FacetWidget::Radio => FacetWidget::Radio,
+            ui_metadata::FacetWidget::Dropdown => FacetWidget::Dropdown,
+            ui_metadata::FacetWidget::Toggle => FacetWidget::Toggle,
+            ui_metadata::FacetWidget::Range => FacetWidget::Range,
+            ui_metadata::FacetWidget::DateRange => FacetWidget::DateRange,
+        },
+        sort_priority: metadata.sort_priority,
+    }
+}
+
+/// Replace the highlights of hits with offsets from their `_matchesPosition` in `fields`
+fn replace_with_offsets(hits: &mut [golem::search::types::SearchHit], fields: &[String]) {
+    for hit in hits {
+        let content: Value = hit.content.as_deref().and_then(|c| serde_json::from_str(c).ok()).unwrap_or(Value::Null);
+        let positions: serde_json::Map<String, Value> = content
+            .get("_matchesPosition")
+            .and_then(Value::as_object)
+            .map(|positions| positions.iter().filter(|(field, _)| fields.contains(field)).map(|(k, v)| (k.clone(), v.clone())).collect())
+            .unwrap_or_default();
+        hit.highlights = Some(highlight::offsets_json(highlight::offsets_from_positions(&content, &Value::Object(positions))));
+    }
+}
+
+/// Set the field UI metadata kept in worker memory, which Meilisearch settings cannot hold
+fn with_recalled_ui(index: &str, mut schema: Schema) -> Schema {
+    if let Some(meta) = ui_metadata::recall(index) {
+        for field in &mut schema.fields {
+            field.ui = meta.get(&field.name).map(Value::to_string);
+        }
+    }
+    schema
+}
+
+/// Shared form of a WIT query, for the checks that only look at its shape
+fn to_shared_query(query: &SearchQuery) -> golem_search::SearchQuery {
+    golem_search::SearchQuery {
+        q: query.q.clone(),
+        filters: query.filters.clone(),
+        sort: query.sort.clone(),
+        facets: query.facets.clone(),
+        page: query.page,
+        per_page: query.per_page,
+        offset: query.offset,
+        highlight: None,
+        config: None,
+    }
+}
+
+/// WIT form of a shared query tree
+fn from_shared_query_ast(ast: query_ast::QueryAst) -> QueryAst {
+    let bound = |bound: Option<golem_search::query::RangeBound>| {
+        bound.map(|bound| RangeBound { value: bound.value, inclusive: bound.inclusive })
+    };
+    let nodes = ast
+        .nodes
+        .into_iter()
+        .map(|node| match node {
+            AstNode::MatchAll => QueryAstNode::MatchAll,
+            AstNode::Term { field, value } => QueryAstNode::Term(QueryTerm { field, value }),
+            AstNode::Phrase { field, text, slop } => QueryAstNode::Phrase(QueryPhrase { field, text, slop }),
+            AstNode::Wildcard { field, pattern } => QueryAstNode::Wildcard(QueryPattern { field, pattern }),
+            AstNode::Regex { field, pattern } => QueryAstNode::Regex(QueryPattern { field, pattern }),
+            AstNode::Range { field, lower, upper } => {
+                QueryAstNode::Range(QueryRange { field, lower: bound(lower), upper: bound(upper) })
+            }
+            AstNode::Bool { must, should, must_not } => QueryAstNode::Boolean(QueryBool { must, should, must_not }),
+        })
+        .collect();
+    QueryAst { nodes, root: ast.root }
+}
+
+/// Shared form of a WIT query tree
+fn to_shared_query_ast(ast: QueryAst) -> query_ast::QueryAst {
+    let bound = |bound: Option<RangeBound>| {
+        bound.map(|bound| golem_search::query::RangeBound { value: bound.value, inclusive: bound.inclusive })
+    };
+    let nodes = ast
+        .nodes
+        .into_iter()
+        .map(|node| match node {
+            QueryAstNode::MatchAll => AstNode::MatchAll,
+            QueryAstNode::Term(QueryTerm { field, value }) => AstNode::Term { field, value },
+            QueryAstNode::Phrase(QueryPhrase { field, text, slop }) => AstNode::Phrase { field, text, slop },
+            QueryAstNode::Wildcard(QueryPattern { field, pattern }) => AstNode::Wildcard { field, pattern },
+            QueryAstNode::Regex(QueryPattern { field, pattern }) => AstNode::Regex { field, pattern },
+            QueryAstNode::Range(QueryRange { field, lower, upper }) => {
+                AstNode::Range { field, lower: bound(lower), upper: bound(upper) }
+            }
+            QueryAstNode::Boolean(QueryBool { must, should, must_not }) => AstNode::Bool { must, should, must_not },
+        })
+        .collect();
+    query_ast::QueryAst { nodes, root: ast.root }
+}
+
+/// Convert a shared golem-search schema to the WIT schema
+fn from_shared_schema(schema: &golem_search::Schema) -> Schema {
+    Schema {
+        fields: schema.fields.iter().map(|f| SchemaField {
+            name: f.name.clone(),
+            field_type: from_shared_field_type(f.field_type),
+            required: f.required,
+            facet: f.facet,
+            sort: f.sort,
+            index: f.index,
+            analysis: f.analysis.as_ref().and_then(|a| serde_json::to_string(a).ok()),
+            ui: f.ui.as_ref().and_then(|ui| serde_json::to_string(ui).ok()),
+        }).collect(),
+        primary_key: schema.primary_key.clone(),
+    }
+}
+
+/// Switch a query from offset/limit to page/hitsPerPage
+///
+/// Meilisearch only counts matches exhaustively, returning `totalHits`, for
+/// page-based requests. An offset that is not a multiple of the limit is
+/// rounded down to the start of its page; that start is returned.
+fn exhaustive_pagination(query: &mut Value, limit: u32) -> u32 {
+    let limit = limit.max(1);
+    let offset = query["offset"].as_u64().unwrap_or(0) as u32;
+    if let Some(body) = query.as_object_mut() {
+        body.remove("offset");
+        body.remove("limit");
+    }
+    query["page"] = json!(offset / limit + 1);
+    query["hitsPerPage"] = json!(limit);
+    offset - offset % limit
+}
+
+/// Borrowed view of a Meilisearch search response
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MeilisearchSearchResponse<'a> {
+    #[serde(default)]
+    estimated_total_hits: Option<u64>,
+
+    /// Exact count, returned instead of the estimate for page-based requests
+    #[serde(default)]
+    total_hits: Option<u64>,
+
+    #[serde(default)]
+    processing_time_ms: Option<u64>,
+
+    #[serde(borrow)]
+    hits: Vec<&'a RawValue>,
+
+    #[serde(borrow, default)]
+    facet_distribution: Option<&'a RawValue>,
+}
+
+/// Metadata fields of a Meilisearch hit; the document itself stays raw
+#[derive(Deserialize)]
+struct MeilisearchHitMeta<'a> {
+    #[serde(borrow, default)]
+    id: Option<&'a RawValue>,
+
+    #[serde(rename = "_rankingScore", default)]
+    ranking_score: Option<f64>,
+
+    #[serde(rename = "_formatted", borrow, default)]
+    formatted: Option<&'a RawValue>,
+
+    #[serde(rename = "_rankingScoreDetails", borrow, default)]
+    ranking_score_details: Option<&'a RawValue>,
+
+    /// Distance from the `_geoPoint` of a sort, in meters
+    #[serde(rename = "_geoDistance", default)]
+    geo_distance: Option<f64>,
+}
+
+/// Metric label for a failed operation
+impl ErrorKind for SearchError {
+    fn error_kind(&self) -> &'static str {
+        match self {
+            SearchError::IndexNotFound(_) => "index_not_found",
+            SearchError::IndexAlreadyExists(_) => "index_already_exists",
+            SearchError::InvalidQuery(_) => "invalid_query",
+            SearchError::Timeout(_) => "timeout",
+            SearchError::RateLimited(_) => "rate_limited",
+            SearchError::Internal(_) => "internal",
+            SearchError::Unsupported(_) => "unsupported",
+            SearchError::ResponseTooLarge(_) => "response_too_large",
+            SearchError::Unavailable(_) => "unavailable",
+            SearchError::Backpressure(_) => "backpressure",
+        }
+    }
+}
+
+/// Hit count reported on `search` spans
+impl telemetry::SpanOutput for SearchResults {
+    fn returned_rows(&self) -> Option<u64> {
+        Some(self.hits.len() as u64)
+    }
+}
+
+/// Shape of a query for reusing its conversion; normalized and sampled queries are converted in full
+impl TemplateQuery for SearchQuery {
+    fn template_text(&self) -> Option<&str> {
+        if self.normalization.is_some() || self.sample.is_some() {
+            return None;
+        }
+        match QueryText::parse(self.q.as_deref()) {
+            QueryText::Text(text) => Some(text),
+            QueryText::MatchAll => None,
+        }
+    }
+
+    fn with_text(&self, text: &str) -> Self {
+        Self { q: Some(text.to_string()), ..self.clone() }
+    }
+}
+
+/// Facets and filters of a query, for splitting it over the Meilisearch limits
+impl SplitQuery for SearchQuery {
+    fn facets(&self) -> &[String] {
+        &self.facets
+    }
+
+    fn filters(&self) -> &[String] {
+        &self.filters
+    }
+
+    fn cursor(&self) -> Option<&str> {
+        self.cursor.as_deref()
+    }
+
+    fn paging(&self) -> (Option<u32>, Option<u32>, Option<u32>) {
+        (self.page, self.per_page, self.offset)
+    }
+
+    fn with_parts(&self, facets: &[String], filters: &[String]) -> Self {
+        Self { facets: facets.to_vec(), filters: filters.to_vec(), ..self.clone() }
+    }
+
+    fn with_window(&self, offset: u32, per_page: u32) -> Self {
+        Self { page: None, per_page: Some(per_page), offset: Some(offset), cursor: None, ..self.clone() }
+    }
+}
+
+impl SplitResults for SearchResults {
+    fn hit_ids(&self) -> Vec<&str> {
+        self.hits.iter().map(|hit| hit.id.as_str()).collect()
+    }
+
+    fn total(&self) -> Option<u32> {
+        self.total
+    }
+
+    fn facets_mut(&mut self) -> &mut Option<String> {
+        &mut self.facets
+    }
+
+    fn extend_hits(&mut self, page: Self) {
+        self.hits.extend(page.hits);
+        self.took_ms = match (self.took_ms, page.took_ms) {
+            (Some(took), Some(more)) => Some(took.saturating_add(more)),
+            (took, more) => took.or(more),
+        };
+    }
+
+    fn retain_hits(&mut self, keep: &dyn Fn(&str) -> bool) {
+        self.hits.retain(|hit| keep(&hit.id));
+    }
+
+    fn set_page(&mut self, offset: u32, per_page: u32, total: u32, total_exact: bool) {
+        self.hits = self.hits.drain(..).skip(offset as usize).take(per_page as usize).collect();
+        self.total = Some(total);
+        self.total_exact = Some(total_exact);
+        self.page = Some(offset.checked_div(per_page).map_or(1, |page| page + 1));
+        self.per_page = Some(per_page);
+        self.next_cursor = None;
+    }
+}
+
+impl RescueQuery for SearchQuery {
+    fn relaxed(&self, relaxation: &Relaxation) -> Option<Self> {
+        let text = QueryText::parse(self.q.as_deref());
+        match relaxation {
+            Relaxation::DropFilters => (!self.filters.is_empty()).then(|| Self { filters: Vec::new(), ..self.clone() }),
+            Relaxation::Typos => {
+                if text == QueryText::MatchAll {
+                    return None;
+                }
+                // Meilisearch allows typos by term length without a fuzziness
+                let current = parse_fuzziness(self.fuzziness.as_deref()).ok().flatten();
+                let fuzziness = rescue::relaxed_fuzziness(current, Fuzziness::Auto)?;
+                Some(Self { fuzziness: Some(fuzziness.as_str().to_string()), ..self.clone() })
+            }
+            Relaxation::AnyTerms => {
+                let current = parse_minimum_should_match(self.minimum_should_match.as_deref()).ok().flatten();
+                rescue::widens_terms(text, current)
+                    .then(|| Self { minimum_should_match: Some(MinimumShouldMatch::Any.to_string()), ..self.clone() })
+            }
+            Relaxation::Popular { sort } => {
+                let sorted = sort.as_ref().is_none_or(|sort| self.sort == [sort.clone()]);
+                if text == QueryText::MatchAll && self.filters.is_empty() && sorted {
+                    return None;
+                }
+                let sort = sort.as_ref().map_or_else(|| self.sort.clone(), |sort| vec![sort.clone()]);
+                Some(Self { q: None, filters: Vec::new(), sort, ..self.clone() })
+            }
+        }
+    }
+
+    fn query_text(&self) -> Option<&str> {
+        match QueryText::parse(self.q.as_deref()) {
+            QueryText::Text(text) => Some(text),
+            QueryText::MatchAll => None,
+        }
+    }
+
+    fn with_text(&self, text: &str) -> Self {
+        Self { q: Some(text.to_string()), ..self.clone() }
+    }
+}
+
+impl RescueResults for SearchResults {
+    fn found_nothing(&self) -> bool {
+        self.total.map_or(self.hits.is_empty(), |total| total == 0)
+    }
+
+    fn set_relaxations(&mut self, relaxations: &[Relaxation]) {
+        self.relaxations = rescue::names(relaxations);
+    }
+
+    fn hit_count(&self) -> u64 {
+        self.total.map_or(self.hits.len() as u64, u64::from)
+    }
+
+    fn set_correction(&mut self, correction: &Correction) {
+        self.original_query = Some(correction.original.clone());
+        self.corrected_query = Some(correction.corrected.clone());
+    }
+}
+
+impl CacheQuery for SearchQuery {
+    fn normalized(&self) -> Self {
+        Self {
+            q: self.q.as_deref().map(cache::normalize_text),
+            filters: cache::normalize_filters(&self.filters),
+            ..self.clone()
+        }
     }
 }
 
@@ -336,7 +1139,7 @@ impl MeilisearchProvider {
         let config = MeilisearchConfig::from_env()
             .map_err(|e| {
                 error!("Failed to load Meilisearch configuration: {}", e);
-                SearchError::Internal(format!("Configuration error: {}", e))
+                SearchError::Internal(format!("Configuration error: {} (diagnose_config lists every variable read)", e))
             })?;
 
         let client = MeilisearchClient::new(config)
@@ -371,6 +1174,8 @@ impl MeilisearchProvider {
                 FieldType::Boolean,
                 FieldType::Date,
                 FieldType::GeoPoint,
+                FieldType::Int64,
+                FieldType::Decimal,
             ],
             provider_features: {
                 let mut features = HashMap::new();
@@ -389,8 +1194,21 @@ impl MeilisearchProvider {
         let mut searchable_attributes = Vec::new();
         let mut filterable_attributes = Vec::new();
         let mut sortable_attributes = Vec::new();
+        let mut localized_attributes = Vec::new();
         
-        for field in &schema.fields {
+        for warning in unsupported_analysis("meilisearch", &to_shared_schema(schema)) {
+            warn!("{}", warning);
+        }
+        
+        for field in &schema.fields {
+            // Tokenizer and language map to a locale; Meilisearch segments the field with its own dictionary
+            let analysis = parse_field_analysis(field.analysis.as_deref()).map_err(map_shared_error)?;
+            let locale = analysis.and_then(|a| a.meilisearch_locale());
+            parse_field_ui(field.ui.as_deref()).map_err(map_shared_error)?;
+            if let (Some(locale), FieldType::Text) = (locale, &field.field_type) {
+                localized_attributes.push(json!({ "attributePatterns": [field.name], "locales": [locale] }));
+            }
+            
             // Add to searchable attributes if it's a text field
             if matches!(field.field_type, FieldType::Text) && field.index {
                 searchable_attributes.push(&field.name);
@@ -407,6 +1225,13 @@ impl MeilisearchProvider {
             }
         }
         
+        // Geo filters and sorts read the `_geo` copy of the point
+        let geo_field = geo::MEILISEARCH_GEO_FIELD.to_string();
+        if schema.fields.iter().any(|f| matches!(f.field_type, FieldType::GeoPoint)) {
+            filterable_attributes.push(&geo_field);
+            sortable_attributes.push(&geo_field);
+        }
+        
         let mut settings = json!({});
         
         if !searchable_attributes.is_empty() {
@@ -421,6 +1246,10 @@ impl MeilisearchProvider {
             settings["sortableAttributes"] = json!(sortable_attributes);
         }
         
+        if !localized_attributes.is_empty() {
+            settings["localizedAttributes"] = json!(localized_attributes);
+        }
+        
         Ok(settings)
     }
 
@@ -446,6 +1275,23 @@ impl MeilisearchProvider {
             .and_then(|s| s.as_array())
             .unwrap_or(&empty_vec3);
         
+        // Analysis hints from the locale of exactly matching attribute patterns
+        let mut analyses = HashMap::new();
+        for rule in settings.get("localizedAttributes").and_then(Value::as_array).into_iter().flatten() {
+            let analysis = rule
+                .get("locales")
+                .and_then(Value::as_array)
+                .and_then(|locales| locales.first())
+                .and_then(Value::as_str)
+                .and_then(FieldAnalysis::from_meilisearch_locale);
+            let Some(analysis) = analysis else { continue };
+            for pattern in rule.get("attributePatterns").and_then(Value::as_array).into_iter().flatten() {
+                if let Some(name) = pattern.as_str().filter(|p| !p.contains('*')) {
+                    analyses.insert(name, analysis.clone());
+                }
+            }
+        }
+        
         // Collect all unique field names
         let mut field_names = std::collections::HashSet::new();
         
@@ -467,6 +1313,9 @@ impl MeilisearchProvider {
             }
         }
         
+        // `_geo` is the copy of a geo field, not a field of the documents
+        field_names.remove(geo::MEILISEARCH_GEO_FIELD);
+        
         // Create schema fields
         for field_name in field_names {
             let is_searchable = searchable_attrs.iter()
@@ -500,6 +1349,8 @@ impl MeilisearchProvider {
                 facet: is_filterable,
                 sort: is_sortable,
                 index: is_searchable,
+                analysis: analyses.get(field_name).and_then(|a| serde_json::to_string(a).ok()),
+                ui: None,
             });
         }
         
@@ -513,26 +1364,101 @@ impl MeilisearchProvider {
         })
     }
 
+    /// Filter standing in for a polygon filter, which Meilisearch lacks
+    ///
+    /// The bounding boxes of the polygons also match documents just outside
+    /// them; with strict degradation the query fails instead.
+    fn polygon_fallback(&self, filter: GeoPolygonFilter) -> SearchResult<String> {
+        match self.client.config.degradation {
+            DegradationMode::Strict => Err(SearchError::Unsupported(format!(
+                "Meilisearch has no polygon filter (on {})", filter.field
+            ))),
+            DegradationMode::Lenient => {
+                warn!("Meilisearch has no polygon filter; matching the bounding boxes of the polygons on {}", filter.field);
+                Ok(filter.to_meilisearch_bounding_box_filter())
+            }
+            DegradationMode::Quiet => Ok(filter.to_meilisearch_bounding_box_filter()),
+        }
+    }
+
     /// Convert WIT SearchQuery to Meilisearch query
-    fn query_to_meilisearch(&self, query: &SearchQuery) -> Value {
+    fn query_to_meilisearch(&self, query: &SearchQuery) -> SearchResult<Value> {
         let mut meilisearch_query = json!({});
         
-        // Main query
-        if let Some(ref q) = query.q {
-            if !q.trim().is_empty() {
+        // Main query; without `q` Meilisearch runs a placeholder search over every document
+        if let QueryText::Text(q) = QueryText::parse(query.q.as_deref()) {
+            let q = match parse_normalization(query.normalization.as_deref()).map_err(map_shared_error)? {
+                Some(normalization) => normalization.prepare_query("meilisearch", q),
+                None => q.to_string(),
+            };
+            // Phrases and negative keywords are native; required terms need the `all` strategy
+            // and a trailing `*` becomes the prefix search on the last word
+            let mut parsed = ParsedQuery::parse(&q);
+            parsed.check_patterns(&PatternLimits::from_env()).map_err(map_shared_error)?;
+            
+            // Typo tolerance is an index setting; per query only exact matching can be
+            // asked for, by quoting every word
+            match parse_fuzziness(query.fuzziness.as_deref()).map_err(map_shared_error)? {
+                Some(Fuzziness::Exact) => parsed = parsed.exact_terms(),
+                Some(fuzziness @ (Fuzziness::One | Fuzziness::Two)) => warn!(
+                    "Meilisearch cannot cap typos per query; fuzziness {} falls back to the index typoTolerance settings",
+                    fuzziness.as_str()
+                ),
+                Some(Fuzziness::Auto) | None => {}
+            }
+            if parsed.has_operators() {
+                let syntax = parsed.to_simple_syntax("meilisearch").map_err(map_shared_error)?;
+                for warning in &syntax.warnings {
+                    warn!("{}", warning);
+                }
+                if syntax.require_all_terms {
+                    meilisearch_query["matchingStrategy"] = json!("all");
+                }
+                meilisearch_query["q"] = json!(syntax.text);
+            } else {
                 meilisearch_query["q"] = json!(q);
             }
+            
+            // Matching strategies drop words until documents match: the last ones first,
+            // the most frequent ones first, or none at all
+            let minimum = parse_minimum_should_match(query.minimum_should_match.as_deref()).map_err(map_shared_error)?;
+            if let Some(minimum) = minimum.filter(|_| meilisearch_query.get("matchingStrategy").is_none()) {
+                let strategy = if minimum.is_all() {
+                    "all"
+                } else if minimum.is_any() {
+                    "frequency"
+                } else {
+                    warn!("Meilisearch cannot require {} of the query terms; dropping the last words first", minimum);
+                    "last"
+                };
+                meilisearch_query["matchingStrategy"] = json!(strategy);
+            }
         }
         
         // Filters
         if !query.filters.is_empty() {
-            let filter_str = query.filters.join(" AND ");
+            let filters = query.filters.iter()
+                .map(|filter| match GeoPolygonFilter::parse(filter) {
+                    Some(polygons) => self.polygon_fallback(polygons.map_err(map_shared_error)?),
+                    None => match IdSetFilter::parse(filter) {
+                        Some(ids) => ids.and_then(|ids| ids.to_meilisearch_filter()).map_err(map_shared_error),
+                        None => Ok(filter.clone()),
+                    },
+                })
+                .collect::<SearchResult<Vec<_>>>()?;
+            let filter_str = filters.join(" AND ");
             meilisearch_query["filter"] = json!(filter_str);
         }
         
         // Sorting
         if !query.sort.is_empty() {
-            meilisearch_query["sort"] = json!(query.sort);
+            let sorts = query.sort.iter()
+                .map(|sort| match GeoDistanceSort::parse(sort) {
+                    Some(geo_sort) => geo_sort.map(|s| s.to_meilisearch_sort()).map_err(map_shared_error),
+                    None => Ok(sort.clone()),
+                })
+                .collect::<SearchResult<Vec<_>>>()?;
+            meilisearch_query["sort"] = json!(sorts);
         }
         
         // Pagination
@@ -546,13 +1472,18 @@ impl MeilisearchProvider {
             meilisearch_query["offset"] = json!(offset);
         }
         
-        // Facets
-        if !query.facets.is_empty() {
-            meilisearch_query["facets"] = json!(query.facets);
+        // Facets; range buckets are counted from the per-value distribution,
+        // date histograms and stats afterwards by scanning the matches
+        let facets = parse_facets(&query.facets).map_err(map_shared_error)?;
+        let fields: Vec<&str> = facets.iter().filter(|f| f.is_value_count()).map(FacetSpec::field).collect();
+        if !fields.is_empty() {
+            meilisearch_query["facets"] = json!(fields);
         }
         
-        // Highlighting
-        if let Some(ref highlight_config) = query.highlight {
+        // Highlighting; offsets come from the byte positions Meilisearch reports for each match
+        if query.highlight.as_ref().is_some_and(|h| h.offsets) {
+            meilisearch_query["showMatchesPosition"] = json!(true);
+        } else if let Some(ref highlight_config) = query.highlight {
             if !highlight_config.fields.is_empty() {
                 meilisearch_query["attributesToHighlight"] = json!(highlight_config.fields);
                 
@@ -565,102 +1496,317 @@ impl MeilisearchProvider {
             }
         }
         
-        meilisearch_query
+        // Score details are only computed on request since they add overhead
+        if query.explain {
+            meilisearch_query["showRankingScore"] = json!(true);
+            meilisearch_query["showRankingScoreDetails"] = json!(true);
+        }
+        
+        // Provider params are merged last so they can tune the generated request
+        let params = parse_provider_params(query.provider_params.as_deref(), MEILISEARCH_ALLOWED_PARAMS)
+            .map_err(map_shared_error)?;
+        merge_into_body(&mut meilisearch_query, &params);
+        
+        Ok(meilisearch_query)
     }
 
     /// Convert Meilisearch search response to WIT SearchResults
-    fn response_to_results(&self, response: &Value) -> SearchResult<SearchResults> {
-        let estimated_total_hits = response
-            .get("estimatedTotalHits")
-            .and_then(|f| f.as_u64())
-            .map(|f| f as u32);
-        
-        let hits_array = response
-            .get("hits")
-            .and_then(|h| h.as_array())
-            .ok_or_else(|| SearchError::Internal("Missing hits array in response".to_string()))?;
-        
-        let mut hits = Vec::new();
-        for hit in hits_array {
-            let id = hit
-                .get("id")
-                .and_then(|id| id.as_str())
-                .unwrap_or_else(|| {
-                    // Try to find any field that could be an ID
-                    hit.as_object()
-                        .and_then(|obj| obj.keys().next())
-                        .and_then(|first_key| hit.get(first_key))
-                        .and_then(|val| val.as_str())
-                        .unwrap_or("unknown")
-                })
-                .to_string();
-            
-            let content = serde_json::to_string(hit)
+    fn response_to_results(&self, body: &str) -> SearchResult<SearchResults> {
+        let limits = ResponseLimits::from_env();
+        limits.check_body(body.len()).map_err(map_shared_error)?;
+        
+        let response: MeilisearchSearchResponse = serde_json::from_str(body)
+            .map_err(|e| SearchError::Internal(format!("Failed to parse search response: {}", e)))?;
+        
+        let mut hits = Vec::with_capacity(response.hits.len());
+        for hit in response.hits {
+            let meta: MeilisearchHitMeta = serde_json::from_str(hit.get())
                 .map_err(|e| SearchError::Internal(e.to_string()))?;
             
-            // Meilisearch doesn't provide scores in the same way, but we can use ranking score if available
-            let score = hit.get("_rankingScore").and_then(|s| s.as_f64());
+            let id = match meta.id.and_then(|id| serde_json::from_str::<String>(id.get()).ok()) {
+                Some(id) => id,
+                // Try to find any field that could be an ID
+                None => serde_json::from_str::<serde_json::Map<String, Value>>(hit.get())
+                    .ok()
+                    .and_then(|obj| obj.values().next().and_then(|v| v.as_str()).map(|s| s.to_string()))
+                    .unwrap_or_else(|| "unknown".to_string()),
+            };
             
-            let highlights = hit.get("_formatted")
-                .map(|h| serde_json::to_string(h))
-                .transpose()
-                .map_err(|e| SearchError::Internal(e.to_string()))?;
+            // Meilisearch doesn't provide scores in the same way, but we can use ranking score if available
+            let score = meta.ranking_score;
+            let explanation = raw_explanation("meilisearch", score, meta.ranking_score_details);
             
             hits.push(golem::search::types::SearchHit {
                 id,
                 score,
-                content: Some(content),
-                highlights,
+                explanation,
+                content: Some(hit.get().to_string()),
+                highlights: raw_to_json(meta.formatted),
+                distance_meters: meta.geo_distance,
             });
         }
         
-        let facets = response.get("facetDistribution")
-            .map(|f| serde_json::to_string(f).unwrap_or_default());
-        
-        let took_ms = response
-            .get("processingTimeMs")
-            .and_then(|t| t.as_u64())
-            .map(|t| t as u32);
+        let truncated = limits
+            .truncate_hits(&mut hits, hit_size)
+            .map_err(map_shared_error)?;
         
         Ok(SearchResults {
-            total: estimated_total_hits,
+            total: response.total_hits.or(response.estimated_total_hits).map(|f| f as u32),
             page: None,
             per_page: None,
             hits,
-            facets,
-            took_ms,
+            facets: raw_to_json(response.facet_distribution),
+            took_ms: response.processing_time_ms.map(|t| t as u32),
+            truncated,
+            request_id: None,
+            groups: None,
+            next_cursor: None,
+            total_exact: response.total_hits.map(|_| true).or(response.estimated_total_hits.map(|_| false)),
+            facet_metadata: Vec::new(),
+            relaxations: Vec::new(),
+            original_query: None,
+            corrected_query: None,
         })
     }
 
+    /// Server version, detected once per worker and endpoint
+    pub async fn server_version(&self) -> Option<ServerVersion> {
+        version::cached_version("meilisearch", self.client.endpoints.primary().as_str(), || self.client.detect_version()).await
+    }
+
+    /// Capabilities, versions, configured limits and degradation, for orchestrators choosing a provider
+    pub async fn describe(&self) -> ProviderDescription {
+        describe::describe(meilisearch_capability_matrix(), self.server_version().await)
+    }
+
     /// Basic CRUD and search operations
     pub async fn create_index(&self, name: &str, schema: Option<&Schema>) -> SearchResult<()> {
-        info!("Creating Meilisearch index: {}", name);
+        audit::audited("meilisearch", "create_index", name, json!({ "schema": schema.map(to_shared_schema) }), async {
+            telemetry::traced("meilisearch", "create_index", name, async {
+                info!("Creating Meilisearch index: {}", name);
+
+                // Creation is an asynchronous task, so a duplicate would only show up
+                // later as a failed task; check for the index up front instead
+                if self.client.get_index(name).await.is_ok() {
+                    return Err(SearchError::IndexAlreadyExists(name.to_string()));
+                }
 
-        let primary_key = schema.as_ref()
-            .and_then(|s| s.primary_key.as_ref())
-            .map(|s| s.as_str());
+                let primary_key = schema.as_ref()
+                    .and_then(|s| s.primary_key.as_ref())
+                    .map(|s| s.as_str());
+
+                self.client
+                    .create_index(name, primary_key)
+                    .await
+                    .map_err(map_meilisearch_error)?;
+
+                // Update settings if schema is provided
+                if let Some(schema) = schema {
+                    let settings = self.schema_to_meilisearch_settings(schema)?;
+                    self.client
+                        .update_settings(name, settings)
+                        .await
+                        .map_err(map_meilisearch_error)?;
+                    ui_metadata::remember(name, &to_shared_schema(schema));
+                    geo::remember(name, &to_shared_schema(schema));
+                    numeric::remember("meilisearch", name, &to_shared_schema(schema));
+                }
 
-        self.client
-            .create_index(name, primary_key)
+                info!("Successfully created Meilisearch index: {}", name);
+                events::emit(LifecycleEventKind::IndexCreated, "meilisearch", name, Value::Null);
+                Ok(())
+            })
             .await
-            .map_err(map_meilisearch_error)?;
+        })
+        .await
+    }
+
+    /// Create an index from a `golem_search::templates` blueprint
+    ///
+    /// Synonyms, stop words and the template ranking, as custom rules after the
+    /// built-in ones, are applied as settings in the same task queue.
+    pub async fn create_index_from_template(
+        &self,
+        name: &str,
+        template: &str,
+        overrides: Option<&str>,
+    ) -> SearchResult<IndexTemplate> {
+        let overrides = TemplateOverrides::from_json(overrides).map_err(map_shared_error)?;
+        let template = templates::instantiate(template, &overrides).map_err(map_shared_error)?;
+        self.create_index(name, Some(&from_shared_schema(&template.schema))).await?;
+
+        let settings = IndexSettings { schema: None, ..template.settings("meilisearch") };
+        self.apply_settings(name, &settings.to_json().map_err(map_shared_error)?).await?;
+        Ok(template)
+    }
+
+    /// Create an index if it does not exist, otherwise re-apply the schema settings
+    ///
+    /// Meilisearch settings are declarative, so re-applying them is the patch;
+    /// only a different primary key cannot be reconciled. Returns `true` when
+    /// the index was created.
+    pub async fn ensure_index(&self, name: &str, schema: Option<&Schema>) -> SearchResult<bool> {
+        audit::audited("meilisearch", "ensure_index", name, json!({ "schema": schema.map(to_shared_schema) }), async {
+            match self.create_index(name, schema).await {
+                Ok(()) => return Ok(true),
+                Err(SearchError::IndexAlreadyExists(_)) => debug!("Index {} already exists, reconciling settings", name),
+                Err(e) => return Err(e),
+            }
+
+            let Some(schema) = schema else {
+                return Ok(false);
+            };
+
+            let current = self.get_schema(name).await?;
+            let report = check_schema_compatibility("meilisearch", &to_shared_schema(&current), &to_shared_schema(schema));
+            report.ensure_applicable(name).map_err(map_shared_error)?;
+            // The UI metadata is not part of the settings, so it is updated even when they match
+            if report.is_noop() {
+                ui_metadata::remember(name, &to_shared_schema(schema));
+                geo::remember(name, &to_shared_schema(schema));
+                numeric::remember("meilisearch", name, &to_shared_schema(schema));
+                return Ok(false);
+            }
 
-        // Update settings if schema is provided
-        if let Some(schema) = schema {
             let settings = self.schema_to_meilisearch_settings(schema)?;
-            self.client
-                .update_settings(name, settings)
-                .await
+            self.client.update_settings(name, settings).await
                 .map_err(map_meilisearch_error)?;
-        }
+            ui_metadata::remember(name, &to_shared_schema(schema));
+            geo::remember(name, &to_shared_schema(schema));
+            numeric::remember("meilisearch", name, &to_shared_schema(schema));
+            cache::global().remove(CacheKind::Schema, &cache::index_key("meilisearch", name));
+            let fields: Vec<&str> = schema.fields.iter().map(|f| f.name.as_str()).collect();
+            events::emit(LifecycleEventKind::SchemaUpdated, "meilisearch", name, json!({ "fields": fields }));
+            Ok(false)
+        })
+        .await
+    }
 
-        info!("Successfully created Meilisearch index: {}", name);
-        Ok(())
+    /// Delete an index, or with `dry_run` only return the plan for it
+    pub async fn delete_index(&self, name: &str, dry_run: bool) -> SearchResult<OperationPlan> {
+        audit::audited("meilisearch", "delete_index", name, json!({ "dry_run": dry_run }), async {
+            telemetry::traced("meilisearch", "delete_index", name, async {
+                dry_run::validate_target(name).map_err(map_shared_error)?;
+                let count = self.client.count_documents(name).await.map_err(map_meilisearch_error)?;
+                let plan = OperationPlan::new("meilisearch", "delete_index", name, dry_run)
+                    .with_affected_documents(Some(count))
+                    .step(format!("DELETE /indexes/{}", name));
+
+                dry_run::execute(plan, async {
+                    self.client.delete_index(name).await.map_err(map_meilisearch_error)?;
+                    cache::global().remove(CacheKind::Schema, &cache::index_key("meilisearch", name));
+                    write_buffer::discard_index(name);
+                    checksum::forget_index(name);
+                    cache::invalidate_results("meilisearch", name);
+                    ui_metadata::forget(name);
+                    geo::forget(name);
+                    numeric::forget(name);
+                    events::emit(LifecycleEventKind::IndexDeleted, "meilisearch", name, Value::Null);
+                    Ok(None)
+                })
+                .await
+            })
+            .await
+        })
+        .await
     }
 
-    pub async fn delete_index(&self, name: &str) -> SearchResult<()> {
-        self.client.delete_index(name).await.map_err(map_meilisearch_error)?;
-        Ok(())
+    /// Delete the documents matching filters, or with `dry_run` only count them
+    ///
+    /// Each filter is parenthesized before they are joined, so an `OR` inside
+    /// one filter cannot widen the others. The deletion is enqueued as a task.
+    pub async fn delete_by_query(&self, index: &str, filters: &[String], dry_run: bool) -> SearchResult<OperationPlan> {
+        let details = json!({ "filters": filters, "dry_run": dry_run });
+        audit::audited("meilisearch", "delete_by_query", index, details, async {
+            telemetry::traced("meilisearch", "delete_by_query", index, async {
+                dry_run::validate_target(index).map_err(map_shared_error)?;
+                dry_run::validate_filters(filters).map_err(map_shared_error)?;
+                let filter = filters
+                    .iter()
+                    .filter(|f| !f.trim().is_empty())
+                    .map(|f| format!("({})", f))
+                    .collect::<Vec<_>>()
+                    .join(" AND ");
+                let count = self.client.count_matching(index, &filter).await.map_err(map_meilisearch_error)?;
+                let plan = OperationPlan::new("meilisearch", "delete_by_query", index, dry_run)
+                    .with_filter(filter.clone())
+                    .with_affected_documents(Some(count))
+                    .step(format!("POST /indexes/{}/documents/delete", index));
+
+                dry_run::execute(plan, async {
+                    self.client.delete_documents_by_filter(index, &filter).await.map_err(map_meilisearch_error)?;
+                    checksum::forget_index(index);
+                    cache::invalidate_results("meilisearch", index);
+                    Ok(None)
+                })
+                .await
+            })
+            .await
+        })
+        .await
+    }
+
+    /// Delete every document but keep the index with its settings
+    pub async fn clear_index(&self, index: &str, dry_run: bool) -> SearchResult<OperationPlan> {
+        audit::audited("meilisearch", "clear_index", index, json!({ "dry_run": dry_run }), async {
+            telemetry::traced("meilisearch", "clear_index", index, async {
+                dry_run::validate_target(index).map_err(map_shared_error)?;
+                let count = self.client.count_documents(index).await.map_err(map_meilisearch_error)?;
+                let plan = OperationPlan::new("meilisearch", "clear_index", index, dry_run)
+                    .with_affected_documents(Some(count))
+                    .step(format!("DELETE /indexes/{}/documents", index));
+
+                dry_run::execute(plan, async {
+                    self.client.delete_all_documents(index).await.map_err(map_meilisearch_error)?;
+                    write_buffer::discard_index(index);
+                    checksum::forget_index(index);
+                    cache::invalidate_results("meilisearch", index);
+                    Ok(None)
+                })
+                .await
+            })
+            .await
+        })
+        .await
+    }
+
+    /// Start a dump, which covers every index of the instance; the snapshot ID is the dump task's UID
+    pub async fn create_snapshot(&self, index: &str) -> SearchResult<SnapshotInfo> {
+        audit::audited("meilisearch", "create_snapshot", index, Value::Null, async {
+            telemetry::traced("meilisearch", "create_snapshot", index, async {
+                let documents = self.client.count_documents(index).await.map_err(map_meilisearch_error)?;
+                let task = self.client.create_dump().await.map_err(map_meilisearch_error)?;
+                let uid = task.get("taskUid").and_then(Value::as_u64)
+                    .ok_or_else(|| SearchError::Internal("No taskUid in dump response".to_string()))?;
+
+                info!("Started Meilisearch dump task {} for index {}", uid, index);
+                Ok(SnapshotInfo {
+                    documents: Some(documents),
+                    ..SnapshotInfo::new(uid.to_string(), index, "meilisearch", SnapshotKind::Dump)
+                })
+            })
+            .await
+        })
+        .await
+    }
+
+    /// Explain how to restore a dump
+    ///
+    /// Meilisearch imports dumps only at startup (`--import-dump`), so this
+    /// always fails, naming the dump file once the dump task has succeeded.
+    pub async fn restore_snapshot(&self, snapshot_id: &str) -> SearchResult<SnapshotInfo> {
+        let uid: u64 = snapshot_id.parse()
+            .map_err(|_| SearchError::InvalidQuery(format!("Invalid Meilisearch dump ID '{}'", snapshot_id)))?;
+        let task = self.client.get_task(uid).await.map_err(map_meilisearch_error)?;
+
+        let status = task.get("status").and_then(Value::as_str).unwrap_or("unknown");
+        match task.pointer("/details/dumpUid").and_then(Value::as_str) {
+            Some(dump) if status == "succeeded" => Err(SearchError::Unsupported(format!(
+                "Meilisearch imports dumps only at startup: restart it with --import-dump dumps/{}.dump",
+                dump
+            ))),
+            _ => Err(SearchError::InvalidQuery(format!("Dump task {} is {}, not succeeded", uid, status))),
+        }
     }
 
     pub async fn list_indexes(&self) -> SearchResult<Vec<String>> {
@@ -668,207 +1814,1613 @@ impl MeilisearchProvider {
     }
 
     pub async fn upsert(&self, index: &str, doc: &Doc) -> SearchResult<()> {
-        let mut content: Value = serde_json::from_str(&doc.content)
-            .map_err(|e| SearchError::InvalidQuery(e.to_string()))?;
-        
-        // Ensure the document has an id field
-        content["id"] = json!(doc.id);
-        
-        // Meilisearch expects an array of documents
-        let documents = json!([content]);
-        
-        self.client.add_documents(index, documents).await
-            .map_err(map_meilisearch_error)?;
-        Ok(())
+        if write_buffer::is_enabled() {
+            return self.upsert_many(index, std::slice::from_ref(doc)).await;
+        }
+        telemetry::traced("meilisearch", "upsert", index, async {
+            self.upsert_with_token(index, doc).await.map(|_| ())
+        })
+        .await
     }
 
-    pub async fn get(&self, index: &str, id: &str) -> SearchResult<Option<Doc>> {
-        let result = self.client.get_document(index, id).await
-            .map_err(map_meilisearch_error)?;
-        
-        if let Some(response) = result {
-            let content = serde_json::to_string(&response)
-                .map_err(|e| SearchError::Internal(e.to_string()))?;
-            
-            Ok(Some(Doc { 
-                id: id.to_string(), 
-                content 
-            }))
-        } else {
-            Ok(None)
+    /// Upsert documents, through the write buffer when it is enabled
+    pub async fn upsert_many(&self, index: &str, docs: &[Doc]) -> SearchResult<()> {
+        quota::global().check_documents(index, docs.len()).map_err(map_shared_error)?;
+        let docs: Vec<_> = docs.iter().map(|doc| golem_search::types::Doc { id: doc.id.clone(), content: doc.content.clone() }).collect();
+        let docs = ingest::apply(index, &docs).map_err(map_shared_error)?;
+        let docs = checksum::skip_unchanged(index, &docs);
+        if docs.is_empty() {
+            return Ok(());
+        }
+        if write_buffer::is_enabled() {
+            return write_buffer::buffer_upserts(index, docs.into_owned(), |index, docs| async move {
+                self.add_documents(&index, &docs).await
+            })
+            .await;
         }
+        self.add_documents(index, &docs).await
     }
 
-    pub async fn delete(&self, index: &str, id: &str) -> SearchResult<()> {
-        self.client.delete_document(index, id).await
-            .map_err(map_meilisearch_error)?;
-        Ok(())
+    /// Write buffered upserts for `index`, or for every index, returning the number of documents written
+    pub async fn flush_writes(&self, index: Option<&str>) -> SearchResult<u32> {
+        let written = write_buffer::flush(index, |index, docs| async move {
+            self.add_documents(&index, &docs).await
+        })
+        .await?;
+        Ok(written as u32)
     }
 
-    pub async fn search(&self, index: &str, query: &SearchQuery) -> SearchResult<SearchResults> {
-        let meilisearch_query = self.query_to_meilisearch(query);
-        
-        let response = self.client.search(index, meilisearch_query).await
-            .map_err(map_meilisearch_error)?;
-        
-        self.response_to_results(&response)
+    /// Apply the writes recorded in the worker's outbox, oldest first
+    pub async fn drain_outbox(&self) -> DrainReport {
+        outbox::drain(|index, mutation| async move {
+            match mutation {
+                Mutation::Upsert { doc } => {
+                    let docs = [doc];
+                    let docs = ingest::apply(&index, &docs).map_err(map_shared_error)?;
+                    self.add_documents(&index, &docs).await
+                }
+                Mutation::Delete { id } => self.delete(&index, &id).await,
+            }
+        })
+        .await
     }
 
-    pub async fn get_schema(&self, index: &str) -> SearchResult<Schema> {
-        let settings = self.client.get_settings(index).await
-            .map_err(map_meilisearch_error)?;
-        
-        let index_info = self.client.get_index(index).await
-            .map_err(map_meilisearch_error)?;
-        
-        self.meilisearch_settings_to_schema(&settings, &index_info)
+    /// Write the whole write buffer and drain the outbox, before the worker is suspended
+    pub async fn flush(&self) -> ShutdownReport {
+        shutdown::flush(self.flush_writes(None), self.drain_outbox()).await
     }
-}
-
-// WIT bindings
-wit_bindgen::generate!({
-    world: "meilisearch-provider",
-    path: "wit",
-    generate_unused_types: true,
-    with: {
-        "golem:search/types@1.0.0": generate,
-        "golem:search/core@1.0.0": generate,
-    },
-});
 
-use exports::golem::search::core::Guest;
-
-// Export the implementation
-struct Component;
+    /// Same as [`flush`](Self::flush); Meilisearch keeps no scroll contexts open
+    pub async fn shutdown(&self) -> ShutdownReport {
+        self.flush().await
+    }
 
-impl Guest for Component {
-    fn search(index: String, query: SearchQuery) -> SearchResult<SearchResults> {
-        // Synchronous wrapper for the async implementation
-        let rt = tokio::runtime::Runtime::new()
-            .map_err(|e| SearchError::Internal(format!("Failed to create async runtime: {}", e)))?;
-        
-        rt.block_on(async {
-            let provider = MeilisearchProvider::new().await?;
-            provider.search(&index, &query).await
+    /// Write a batch of change-feed events, as collapsed by a `ChangeFeedAdapter`
+    pub async fn apply_changes(&self, index: &str, batch: &ChangeBatch) -> SearchResult<()> {
+        telemetry::traced("meilisearch", "apply_changes", index, async {
+            quota::global().check_documents(index, batch.upserts.len()).map_err(map_shared_error)?;
+            let upserts = ingest::apply(index, &batch.upserts).map_err(map_shared_error)?;
+            let upserts = checksum::skip_unchanged(index, &upserts);
+            if !upserts.is_empty() {
+                self.add_documents(index, &upserts).await?;
+            }
+            if !batch.deletes.is_empty() {
+                write_buffer::discard(index, &batch.deletes);
+                checksum::forget(index, &batch.deletes);
+                cache::invalidate_results("meilisearch", index);
+                self.client.delete_documents(index, &batch.deletes).await
+                    .map_err(map_meilisearch_error)?;
+            }
+            Ok(())
         })
+        .await
     }
 
-    fn upsert(index: String, doc: Doc) -> SearchResult<()> {
-        let rt = tokio::runtime::Runtime::new()
-            .map_err(|e| SearchError::Internal(format!("Failed to create async runtime: {}", e)))?;
-        
-        rt.block_on(async {
-            let provider = MeilisearchProvider::new().await?;
-            provider.upsert(&index, &doc).await
+    /// Converge an index to `docs`, upserting new and changed documents and deleting missing ones
+    ///
+    /// Writes still queued as Meilisearch tasks when the index is read are not
+    /// seen, so a sync should not overlap other writes to the index.
+    pub async fn sync(&self, index: &str, docs: &[Doc], dry_run: bool) -> SearchResult<SyncReport> {
+        telemetry::traced("meilisearch", "sync", index, async {
+            // Buffered writes would otherwise land after the comparison
+            self.flush_writes(Some(index)).await?;
+            let existing = self.fingerprint_index(index).await?;
+            let docs = docs.iter().map(|doc| golem_search::types::Doc { id: doc.id.clone(), content: doc.content.clone() });
+            let report = delta_sync::plan(index, existing, docs, dry_run)
+                .map_err(map_shared_error)?
+                .execute(|batch| async move { self.apply_changes(index, &batch).await })
+                .await?;
+            info!(
+                "Synced index {}: {} upserted, {} deleted, {} unchanged",
+                index, report.upserted, report.deleted, report.unchanged
+            );
+            Ok(report)
         })
+        .await
     }
 
-    fn get(index: String, id: String) -> SearchResult<Option<Doc>> {
-        let rt = tokio::runtime::Runtime::new()
-            .map_err(|e| SearchError::Internal(format!("Failed to create async runtime: {}", e)))?;
-        
-        rt.block_on(async {
-            let provider = MeilisearchProvider::new().await?;
-            provider.get(&index, &id).await
+    /// Build or refresh the suggestions index of `index` from analytics and document titles
+    ///
+    /// The suggestions index is created when missing and converged to the new
+    /// suggestions with [`Self::sync`].
+    pub async fn build_suggestions(&self, index: &str, queries: &[PopularQuery], config: &SuggestionsConfig) -> SearchResult<SyncReport> {
+        telemetry::traced("meilisearch", "build_suggestions", index, async {
+            let mut builder = SuggestionsBuilder::new(config.clone());
+            builder.add_queries(queries);
+            if let Some(field) = &config.title_field {
+                self.scan_documents(index, |_, document| {
+                    if let Some(title) = document.get(field).and_then(Value::as_str) {
+                        builder.add_title(title);
+                    }
+                })
+                .await?;
+            }
+            let target = suggestions::suggestions_index(index);
+            self.ensure_index(&target, Some(&from_shared_schema(&suggestions::schema()))).await?;
+            let docs: Vec<Doc> = builder.documents().into_iter().map(|doc| Doc { id: doc.id, content: doc.content }).collect();
+            self.sync(&target, &docs, false).await
         })
+        .await
     }
 
-    fn delete(index: String, id: String) -> SearchResult<()> {
-        let rt = tokio::runtime::Runtime::new()
-            .map_err(|e| SearchError::Internal(format!("Failed to create async runtime: {}", e)))?;
-        
-        rt.block_on(async {
-            let provider = MeilisearchProvider::new().await?;
-            provider.delete(&index, &id).await
-        })
+    /// Suggestions from the suggestions index of `index` that complete `prefix`, most popular first
+    ///
+    /// Meilisearch matches the last word as a prefix unless it is followed by a space.
+    pub async fn suggest(&self, index: &str, prefix: &str, limit: u32) -> SearchResult<Vec<Suggestion>> {
+        let target = suggestions::suggestions_index(index);
+        let mut q = suggestions::prefix_text(prefix);
+        if !q.is_empty() && prefix.ends_with(char::is_whitespace) {
+            q.push(' ');
+        }
+        let query = json!({
+            "q": q,
+            "limit": limit,
+            "sort": ["popularity:desc"],
+            "matchingStrategy": "all",
+            "attributesToRetrieve": ["query", "popularity"],
+        });
+        let response = self.client.search_raw(&target, query).await.map_err(map_meilisearch_error)?;
+        let response: Value = json::from_str(&response).map_err(map_shared_error)?;
+        let hits = response["hits"].as_array().map(Vec::as_slice).unwrap_or_default();
+        Ok(hits.iter().filter_map(suggestions::from_document).collect())
     }
 
-    fn create_index(name: String, schema: Option<Schema>) -> SearchResult<()> {
-        let rt = tokio::runtime::Runtime::new()
-            .map_err(|e| SearchError::Internal(format!("Failed to create async runtime: {}", e)))?;
-        
-        rt.block_on(async {
-            let provider = MeilisearchProvider::new().await?;
-            provider.create_index(&name, schema.as_ref()).await
-        })
+    /// Suggestions from the suggestions index of `index` close to `text`, to correct its spelling with
+    ///
+    /// Meilisearch allows typos by term length, as the lookup needs.
+    pub async fn spelling_candidates(&self, index: &str, text: &str) -> SearchResult<Vec<Suggestion>> {
+        let target = suggestions::suggestions_index(index);
+        let query = json!({
+            "q": suggestions::prefix_text(text),
+            "limit": spellfix::LOOKUP_LIMIT,
+            "attributesToRetrieve": ["query", "popularity"],
+        });
+        let response = self.client.search_raw(&target, query).await.map_err(map_meilisearch_error)?;
+        let response: Value = json::from_str(&response).map_err(map_shared_error)?;
+        let hits = response["hits"].as_array().map(Vec::as_slice).unwrap_or_default();
+        Ok(hits.iter().filter_map(suggestions::from_document).collect())
+    }
+
+    /// IDs and hashes of every document in an index
+    async fn fingerprint_index(&self, index: &str) -> SearchResult<IndexFingerprint> {
+        let mut fingerprint = IndexFingerprint::new();
+        self.scan_documents(index, |id, document| fingerprint.add(id, document)).await?;
+        Ok(fingerprint)
+    }
+
+    /// Visit the ID and content of every document in an index, read page by page
+    async fn scan_documents(&self, index: &str, mut visit: impl FnMut(&str, &Value)) -> SearchResult<()> {
+        const PAGE_SIZE: usize = 1000;
+        let geo_fields = geo::recall(index);
+        let mut offset = 0;
+        loop {
+            let page = self.client.list_documents(index, offset, PAGE_SIZE).await.map_err(map_meilisearch_error)?;
+            let results = page["results"].as_array().cloned().unwrap_or_default();
+            let count = results.len();
+            for mut document in results {
+                geo::from_provider("meilisearch", &mut document, &geo_fields);
+                if let Some(id) = document["id"].as_str() {
+                    visit(id, &document);
+                }
+            }
+            if count < PAGE_SIZE {
+                return Ok(());
+            }
+            offset += count;
+        }
     }
 
-    fn delete_index(name: String) -> SearchResult<()> {
-        let rt = tokio::runtime::Runtime::new()
-            .map_err(|e| SearchError::Internal(format!("Failed to create async runtime: {}", e)))?;
+    /// Add documents in adaptively sized batches, unless the task queue is saturated
+    ///
+    /// Checksums are recorded once every batch is enqueued, as Meilisearch
+    /// only reports a document it cannot index when its task is processed.
+    async fn add_documents(&self, index: &str, docs: &[golem_search::types::Doc]) -> SearchResult<()> {
+        self.check_backpressure(index).await?;
         
-        rt.block_on(async {
-            let provider = MeilisearchProvider::new().await?;
-            provider.delete_index(&name).await
+        let geo_fields = geo::recall(index);
+        let mut documents = Vec::with_capacity(docs.len());
+        for doc in docs {
+            let mut content: Value = serde_json::from_str(&doc.content)
+                .map_err(|e| SearchError::InvalidQuery(e.to_string()))?;
+            content["id"] = json!(doc.id);
+            geo::to_provider("meilisearch", &mut content, &geo_fields);
+            documents.push(content);
+        }
+        
+        let max_batch_size = self.get_capabilities().max_batch_size.unwrap_or(1000) as usize;
+        let mut batcher = AdaptiveBatcher::new(AdaptiveBatchConfig::with_max(max_batch_size));
+        let client = &self.client;
+
+        submit_adaptive(documents, &mut batcher, |chunk: Vec<Value>| async move {
+            client.add_documents(index, json!(chunk)).await.map(|_| ())
         })
+        .await
+        .map_err(|e| map_meilisearch_error(e.into()))?;
+
+        checksum::record(index, docs);
+        cache::invalidate_results("meilisearch", index);
+        alerts::observe(index, docs);
+        Ok(())
     }
 
-    fn list_indexes() -> SearchResult<Vec<String>> {
-        let rt = tokio::runtime::Runtime::new()
-            .map_err(|e| SearchError::Internal(format!("Failed to create async runtime: {}", e)))?;
+    /// Upsert a document and return a consistency token for its indexing task
+    pub async fn upsert_with_token(&self, index: &str, doc: &Doc) -> SearchResult<String> {
+        quota::global().check_documents(index, 1).map_err(map_shared_error)?;
+        let processed = ingest::apply_document(index, &doc.id, &doc.content).map_err(map_shared_error)?;
+        let mut content: Value = serde_json::from_str(&processed)
+            .map_err(|e| SearchError::InvalidQuery(e.to_string()))?;
         
-        rt.block_on(async {
-            let provider = MeilisearchProvider::new().await?;
-            provider.list_indexes().await
+        // Ensure the document has an id field
+        content["id"] = json!(doc.id);
+        geo::to_provider("meilisearch", &mut content, &geo::recall(index));
+        
+        // Meilisearch expects an array of documents
+        let documents = json!([content]);
+        
+        let task = self.client.add_documents(index, documents).await
+            .map_err(map_meilisearch_error)?;
+        let written = [golem_search::types::Doc { id: doc.id.clone(), content: processed.into_owned() }];
+        checksum::record(index, &written);
+        cache::invalidate_results("meilisearch", index);
+        alerts::observe(index, &written);
+        Ok(ConsistencyToken::from_write_response("meilisearch", index, &task).encode())
+    }
+
+    /// Wait until the task behind a query's consistency token has been processed
+    async fn wait_for_token(&self, token: Option<&str>) -> SearchResult<()> {
+        let Some(token) = token else {
+            return Ok(());
+        };
+        let token = ConsistencyToken::parse_for(token, "meilisearch").map_err(map_shared_error)?;
+        let consistency::WritePosition::Task { id } = token.position else {
+            return Ok(());
+        };
+
+        consistency::wait_until_applied(&token, consistency::wait_timeout_from_env(), || async move {
+            let task = self.client.get_task(id).await.map_err(golem_search::SearchError::internal)?;
+            match task.get("status").and_then(Value::as_str) {
+                Some("succeeded") => Ok(true),
+                Some("failed") | Some("canceled") => Err(golem_search::SearchError::internal(format!(
+                    "Task {} did not succeed: {}",
+                    id,
+                    task.get("error").map(Value::to_string).unwrap_or_default()
+                ))),
+                _ => Ok(false),
+            }
         })
+        .await
+        .map_err(map_shared_error)
     }
 
-    fn get_schema(index: String) -> SearchResult<Schema> {
-        let rt = tokio::runtime::Runtime::new()
-            .map_err(|e| SearchError::Internal(format!("Failed to create async runtime: {}", e)))?;
+    /// Get a document by ID, with only the `fields` paths in its content when given
+    ///
+    /// Meilisearch selects top-level attributes; nested paths are projected here.
+    pub async fn get(&self, index: &str, id: &str, fields: Option<&FieldMask>) -> SearchResult<Option<Doc>> {
+        telemetry::traced("meilisearch", "get", index, async {
+            let attributes = fields.map(FieldMask::top_level);
+            let result = self.client.get_document(index, id, attributes.as_deref()).await
+                .map_err(map_meilisearch_error)?;
         
-        rt.block_on(async {
-            let provider = MeilisearchProvider::new().await?;
-            provider.get_schema(&index).await
+            if let Some(mut response) = result {
+                geo::from_provider("meilisearch", &mut response, &geo::recall(index));
+                let response = match fields {
+                    Some(mask) => mask.project(&response),
+                    None => response,
+                };
+                let content = serde_json::to_string(&response)
+                    .map_err(|e| SearchError::Internal(e.to_string()))?;
+            
+                Ok(Some(Doc { 
+                    id: id.to_string(), 
+                    content 
+                }))
+            } else {
+                Ok(None)
+            }
         })
+        .await
     }
 
-    fn get_capabilities() -> SearchCapabilities {
-        // Create a minimal provider instance for capabilities (doesn't need actual connection)
-        let config = MeilisearchConfig {
-            endpoint: "http://localhost:7700".to_string(),
-            master_key: None,
-            timeout: Duration::from_secs(30),
-            max_retries: 3,
+    /// Estimate the cost of a query without running it
+    pub async fn estimate_query(&self, index: &str, query: &SearchQuery) -> SearchResult<CostEstimate> {
+        let document_count = match self.client.count_documents(index).await {
+            Ok(count) => Some(count),
+            Err(e) => {
+                debug!("Estimating without a document count for {}: {}", index, e);
+                None
+            }
         };
-        
-        let client = MeilisearchClient::new(config).unwrap();
-        let provider = MeilisearchProvider { client };
-        provider.get_capabilities()
+        let stats = IndexStats { document_count };
+        Ok(cost::estimate_query(&meilisearch_capability_matrix(), index, &stats, &to_shared_query(query)))
     }
 
-    fn batch_upsert(index: String, docs: Vec<Doc>) -> SearchResult<()> {
-        let rt = tokio::runtime::Runtime::new()
-            .map_err(|e| SearchError::Internal(format!("Failed to create async runtime: {}", e)))?;
-        
-        rt.block_on(async {
-            let provider = MeilisearchProvider::new().await?;
-            
-            // Meilisearch supports native batch operations
-            let mut documents = Vec::new();
-            for doc in docs {
-                let mut content: Value = serde_json::from_str(&doc.content)
-                    .map_err(|e| SearchError::InvalidQuery(e.to_string()))?;
-                content["id"] = json!(doc.id);
-                documents.push(content);
+    /// Enqueued and processing tasks of an index, to tell an indexing backlog
+    /// apart from documents that are really missing
+    pub async fn list_pending_tasks(&self, index: &str) -> SearchResult<PendingTasks> {
+        let body = self.client.pending_tasks(index, tasks::MAX_LISTED_TASKS).await
+            .map_err(map_meilisearch_error)?;
+        Ok(PendingTasks::from_meilisearch(index, &body))
+    }
+
+    /// Changes to an index since the position of `since`, or an empty summary marking the current position
+    ///
+    /// Writes are summed from the index's succeeded document tasks newer than the cursor.
+    pub async fn index_changes(&self, index: &str, since: Option<&str>) -> SearchResult<ChangeSummary> {
+        let before = since
+            .map(|since| WatchCursor::parse_for(since, "meilisearch", index))
+            .transpose()
+            .map_err(map_shared_error)?;
+        let after_task = before.as_ref().and_then(|before| before.task);
+
+        let mut writes = WriteCounters::default();
+        let mut newest = after_task;
+        let mut from = None;
+        loop {
+            let limit = if before.is_some() { tasks::MAX_LISTED_TASKS } else { 1 };
+            let body = self.client.finished_write_tasks(index, from, limit).await
+                .map_err(map_meilisearch_error)?;
+            let page = TaskPage::from_meilisearch(&body, after_task);
+            writes.add(page.writes);
+            newest = newest.max(page.newest);
+            match page.next {
+                Some(next) if before.is_some() => from = Some(next),
+                _ => break,
             }
-            
-            let documents_array = json!(documents);
-            provider.client.add_documents(&index, documents_array).await
+        }
+
+        let documents = self.client.count_documents(index).await.map_err(map_meilisearch_error)?;
+        let mut now = WatchCursor::new("meilisearch", index, documents);
+        now.task = newest;
+        Ok(match before {
+            Some(before) => ChangeSummary::between(&before, &now, Some(writes)),
+            None => ChangeSummary::start(&now),
+        })
+    }
+
+    /// Refuse a batch write while the index's task queue is past `SEARCH_MAX_PENDING_TASKS`
+    pub async fn check_backpressure(&self, index: &str) -> SearchResult<()> {
+        let backpressure = Backpressure::from_env();
+        if !backpressure.is_enabled() {
+            return Ok(());
+        }
+        // Only the total is needed
+        let body = self.client.pending_tasks(index, 1).await
+            .map_err(map_meilisearch_error)?;
+        let pending = PendingTasks::from_meilisearch(index, &body);
+        backpressure.check(pending.total).map_err(map_shared_error)
+    }
+
+    /// Check whether a document exists without transferring it
+    pub async fn exists(&self, index: &str, id: &str) -> SearchResult<bool> {
+        self.client.document_exists(index, id).await
+            .map_err(map_meilisearch_error)
+    }
+
+    /// Get several documents by ID in one request, in request order
+    pub async fn get_many(&self, index: &str, ids: &[String], fields: Option<&FieldMask>) -> SearchResult<Vec<Option<Doc>>> {
+        telemetry::traced("meilisearch", "get_many", index, async {
+            if ids.is_empty() {
+                return Ok(Vec::new());
+            }
+
+            // The ID is fetched even when not selected, to match documents to the requested IDs
+            let attributes = fields.map(|mask| {
+                let mut attributes = mask.top_level();
+                attributes.push("id".to_string());
+                attributes
+            });
+            let response = self.client.fetch_documents(index, ids, attributes.as_deref()).await
+                .map_err(map_meilisearch_error)?;
+            let mut docs = response.get("results").and_then(Value::as_array).cloned().unwrap_or_default();
+            let geo_fields = geo::recall(index);
+            for doc in &mut docs {
+                geo::from_provider("meilisearch", doc, &geo_fields);
+            }
+        
+            align_to_ids(ids, &docs, "id")
+                .into_iter()
+                .zip(ids)
+                .map(|(doc, id)| {
+                    doc.map(|doc| {
+                        let content = match fields {
+                            Some(mask) => serde_json::to_string(&mask.project(doc)),
+                            None => serde_json::to_string(doc),
+                        }
+                        .map_err(|e| SearchError::Internal(e.to_string()))?;
+                        Ok(Doc { id: id.clone(), content })
+                    })
+                    .transpose()
+                })
+                .collect()
+        })
+        .await
+    }
+
+    pub async fn delete(&self, index: &str, id: &str) -> SearchResult<()> {
+        telemetry::traced("meilisearch", "delete", index, async {
+            write_buffer::discard(index, &[id.to_string()]);
+            checksum::forget(index, &[id.to_string()]);
+            cache::invalidate_results("meilisearch", index);
+            self.client.delete_document(index, id).await
                 .map_err(map_meilisearch_error)?;
-            
             Ok(())
         })
+        .await
     }
 
-    fn health_check() -> SearchResult<()> {
-        let rt = tokio::runtime::Runtime::new()
-            .map_err(|e| SearchError::Internal(format!("Failed to create async runtime: {}", e)))?;
-        
-        rt.block_on(async {
-            let provider = MeilisearchProvider::new().await?;
-            // Simple health check by getting stats
-            provider.client.get_stats().await.map_err(map_meilisearch_error).map(|_| ())
+    /// Search through the shared result cache, honouring per-index cache policies
+    pub async fn search(&self, index: &str, query: &SearchQuery) -> SearchResult<SearchResults> {
+        telemetry::traced("meilisearch", "search", index, async {
+            quota::global().check_query(index).map_err(map_shared_error)?;
+            let scope = request_id::begin();
+            let _deadline = deadline::begin(query.timeout_ms);
+            let mut results = cache::with_result_cache("meilisearch", index, query, results_size, |index, query| async move {
+                let limits = SplitLimits::new(&meilisearch_capability_matrix().performance_limits);
+                limits.check(&query).map_err(map_shared_error)?;
+                let (index, limits) = (index.as_str(), &limits);
+                let lookup = |text: String| async move { self.spelling_candidates(index, &text).await };
+                rescue::search_rescued(&query, &RescuePlan::from_env(), lookup, |query| async move {
+                    query_split::search_split(&query, limits, |part| async move { self.fetch_results(index, &part).await }).await
+                })
+                .await
+            })
+            .await?;
+            let geo_fields = geo::recall(index);
+            if !geo_fields.is_empty() {
+                for hit in &mut results.hits {
+                    hit.content = hit.content.as_deref().map(|c| geo::content_from_provider("meilisearch", c, &geo_fields));
+                }
+            }
+            results.request_id = Some(scope.id().to_string());
+            results.facet_metadata = self.facet_metadata(index, &query.facets).await;
+            Ok(results)
         })
+        .await
+    }
+
+    /// Display metadata of the requested facets; left empty when the schema cannot be read
+    async fn facet_metadata(&self, index: &str, facets: &[String]) -> Vec<FacetMetadata> {
+        if facets.is_empty() {
+            return Vec::new();
+        }
+        match self.get_schema(index).await {
+            Ok(schema) => ui_metadata::facet_metadata(&to_shared_schema(&schema), facets)
+                .into_iter()
+                .map(from_shared_facet_metadata)
+                .collect(),
+            Err(e) => {
+                warn!("Failed to read facet metadata of index {}: {}", index, e);
+                Vec::new()
+            }
+        }
     }
-}
\ No newline at end of file
+
+    /// Run a search against the backend, bypassing the result cache
+    async fn fetch_results(&self, index: &str, query: &SearchQuery) -> SearchResult<SearchResults> {
+        self.wait_for_token(query.consistency_token.as_deref()).await?;
+        
+        let mut meilisearch_query = compiled_query::compile("meilisearch", index, query, |query| self.query_to_meilisearch(query))?;
+        let limit = meilisearch_query["limit"].as_u64().map_or(cursor::DEFAULT_PER_PAGE, |l| l as u32);
+        if let Some(token) = query.cursor.as_deref() {
+            let cursor = PageCursor::parse_for(token, "meilisearch", index).map_err(map_shared_error)?;
+            meilisearch_query["offset"] = json!(cursor.offset(limit));
+        }
+        let offset = meilisearch_query["offset"].as_u64().unwrap_or(0) as u32;
+        if meilisearch_query.get("hybrid").is_some() || meilisearch_query.get("vector").is_some() {
+            version::require(&version::MEILISEARCH_VECTOR_SEARCH, self.server_version().await)
+                .map_err(map_shared_error)?;
+        }
+        if let Some(sample) = query.sample {
+            return self.sample_results(index, &meilisearch_query, sample).await;
+        }
+        let offset = if query.exact_total {
+            exhaustive_pagination(&mut meilisearch_query, limit)
+        } else {
+            offset
+        };
+        
+        let started = std::time::Instant::now();
+        let response = self.client.search_raw(index, meilisearch_query.clone()).await
+            .map_err(map_meilisearch_error)?;
+        slow_query::record_search("meilisearch", index, &meilisearch_query, &to_shared_query(query), started.elapsed());
+        
+        let mut results = self.response_to_results(&response)?;
+        if cursor::has_more(offset, results.hits.len(), limit, results.total) {
+            let next = CursorPosition::Offset { offset: offset + results.hits.len() as u32 };
+            results.next_cursor = Some(PageCursor::new("meilisearch", index, next).encode());
+        }
+        let facets = parse_facets(&query.facets).map_err(map_shared_error)?;
+        if facets.iter().any(|f| f.buckets().is_some()) {
+            if let Some(distribution) = results.facets.as_deref() {
+                results.facets = Some(bucket_facet_distribution(distribution, &facets).map_err(map_shared_error)?);
+            }
+        }
+        let mut client_facets = ClientFacets::for_specs(&facets).map_err(map_shared_error)?;
+        client_facets.limit_stats(index, results.total);
+        if !client_facets.is_empty() {
+            self.scan_client_facets(index, &meilisearch_query, &mut client_facets).await?;
+            results.facets = Some(
+                client_facets.merge_into_distribution(results.facets.as_deref()).map_err(map_shared_error)?,
+            );
+        }
+        if let Some(config) = query.highlight.as_ref().filter(|h| h.offsets) {
+            replace_with_offsets(&mut results.hits, &config.fields);
+        }
+        Ok(results)
+    }
+
+    /// Compute date histograms and stats client-side by paging through the matches
+    ///
+    /// Only the faceted fields are retrieved. Meilisearch stops paging at
+    /// `maxTotalHits` (1000 by default), which also caps the scan.
+    async fn scan_client_facets(&self, index: &str, base_query: &Value, client_facets: &mut ClientFacets) -> SearchResult<()> {
+        let mut scan_query = base_query.clone();
+        if let Some(body) = scan_query.as_object_mut() {
+            // Exhaustive page-based paging would override the scan's offsets
+            for key in [
+                "facets", "attributesToHighlight", "showMatchesPosition", "showRankingScore", "showRankingScoreDetails", "page", "hitsPerPage",
+            ] {
+                body.remove(key);
+            }
+        }
+        scan_query["attributesToRetrieve"] = json!(client_facets.fields());
+        
+        let scan_query = &scan_query;
+        let scanned = sampling::scan_pages(
+            |offset, limit| async move {
+                let mut page_query = scan_query.clone();
+                page_query["offset"] = json!(offset);
+                page_query["limit"] = json!(limit);
+                let response = self.client.search_raw(index, page_query).await
+                    .map_err(map_meilisearch_error)?;
+                Ok::<_, SearchError>(self.response_to_results(&response)?.hits)
+            },
+            |hit| {
+                if let Some(document) = hit.content.as_deref().and_then(|c| serde_json::from_str::<Value>(c).ok()) {
+                    client_facets.add_document(&document);
+                }
+            },
+        )
+        .await?;
+        if scanned >= u64::from(sampling::MAX_SAMPLE_SCAN) && !client_facets.histograms.is_empty() {
+            warn!("Date histograms on {} cover the first {} matches only", index, scanned);
+        }
+        Ok(())
+    }
+
+    /// Draw a random sample client-side by paging through the matches
+    async fn sample_results(&self, index: &str, base_query: &Value, sample: u32) -> SearchResult<SearchResults> {
+        let started = std::time::Instant::now();
+        let (hits, scanned) = sampling::sample_pages(sample, |offset, limit| {
+            let mut page_query = base_query.clone();
+            page_query["offset"] = json!(offset);
+            page_query["limit"] = json!(limit);
+            async move {
+                let response = self.client.search_raw(index, page_query).await
+                    .map_err(map_meilisearch_error)?;
+                Ok(self.response_to_results(&response)?.hits)
+            }
+        })
+        .await?;
+        slow_query::record_query("meilisearch", index, base_query, started.elapsed());
+        
+        Ok(SearchResults {
+            total: sampling::scanned_total(scanned),
+            page: None,
+            per_page: Some(sample),
+            hits,
+            facets: None,
+            took_ms: Some(started.elapsed().as_millis() as u32),
+            truncated: false,
+            request_id: None,
+            groups: None,
+            next_cursor: None,
+            total_exact: None,
+            facet_metadata: Vec::new(),
+            relaxations: Vec::new(),
+            original_query: None,
+            corrected_query: None,
+        })
+    }
+
+    /// Search the primary index and enrich each hit with documents from a lookup index
+    ///
+    /// The lookup index must declare `foreign_field` as a filterable attribute.
+    pub async fn search_with_lookup(
+        &self,
+        primary_index: &str,
+        query: &SearchQuery,
+        lookup: &LookupSpec,
+    ) -> SearchResult<SearchResults> {
+        lookup.validate().map_err(map_shared_error)?;
+
+        let mut results = self.search(primary_index, query).await?;
+        let keys = lookup_keys_from_contents(results.hits.iter().map(|h| h.content.as_deref()), lookup);
+        if keys.is_empty() {
+            return Ok(results);
+        }
+
+        debug!("Looking up {} keys in index {}", keys.len(), lookup.index);
+
+        let docs = fetch_in_batches(&keys, |batch| async move {
+            let values: Vec<String> = batch.iter().map(|k| json!(k).to_string()).collect();
+            let mut lookup_query = json!({
+                "filter": format!("{} IN [{}]", lookup.foreign_field, values.join(", ")),
+                "limit": MAX_LOOKUP_DOCS,
+            });
+            if !lookup.fields.is_empty() {
+                let mut fields = lookup.fields.clone();
+                fields.push(lookup.foreign_field.clone());
+                lookup_query["attributesToRetrieve"] = json!(fields);
+            }
+
+            let response = self.client.search(&lookup.index, lookup_query).await
+                .map_err(map_meilisearch_error)?;
+            let docs = response.get("hits")
+                .and_then(|h| h.as_array())
+                .cloned()
+                .unwrap_or_default();
+            let matched = response.get("estimatedTotalHits").and_then(Value::as_u64);
+            check_lookup_complete(lookup, docs.len(), matched).map_err(map_shared_error)?;
+            Ok::<_, SearchError>(docs)
+        })
+        .await?;
+
+        let table = LookupTable::new(lookup, &docs);
+        for hit in &mut results.hits {
+            if let Some(raw) = hit.content.as_deref() {
+                hit.content = Some(table.enrich(raw).map_err(map_shared_error)?);
+            }
+        }
+
+        Ok(results)
+    }
+
+    pub async fn get_schema(&self, index: &str) -> SearchResult<Schema> {
+        let cache_key = cache::index_key("meilisearch", index);
+        if let Some(schema) = cache::global().get::<Schema>(CacheKind::Schema, &cache_key) {
+            return Ok(with_recalled_ui(index, (*schema).clone()));
+        }
+        
+        let settings = self.client.get_settings(index).await
+            .map_err(map_meilisearch_error)?;
+        
+        let index_info = self.client.get_index(index).await
+            .map_err(map_meilisearch_error)?;
+        
+        let schema = self.meilisearch_settings_to_schema(&settings, &index_info)?;
+        let size = cache::schema_size(schema.fields.iter().map(|f| f.name.as_str()));
+        cache::global().insert(CacheKind::Schema, &cache_key, schema.clone(), size);
+        Ok(with_recalled_ui(index, schema))
+    }
+
+    /// Export schema, synonyms, ranking rules and stop words as a JSON snapshot
+    pub async fn export_settings(&self, index: &str) -> SearchResult<String> {
+        debug!("Exporting settings for Meilisearch index {}", index);
+
+        let settings = self.client.get_settings(index).await
+            .map_err(map_meilisearch_error)?;
+        let index_info = self.client.get_index(index).await
+            .map_err(map_meilisearch_error)?;
+        let schema = self.meilisearch_settings_to_schema(&settings, &index_info)?;
+
+        let snapshot = self.meilisearch_settings_to_snapshot(&settings, &schema);
+        snapshot.normalized().to_json().map_err(map_shared_error)
+    }
+
+    /// Apply a JSON settings snapshot; applying the same snapshot twice is a no-op
+    pub async fn apply_settings(&self, index: &str, settings_json: &str) -> SearchResult<()> {
+        audit::audited("meilisearch", "apply_settings", index, audit::settings_details(settings_json), async {
+            info!("Applying settings to Meilisearch index {}", index);
+
+            let snapshot = IndexSettings::from_json(settings_json).map_err(map_shared_error)?;
+            if let Some(normalization) = snapshot.normalization {
+                // Meilisearch has no normalization settings; its tokenizer always folds
+                for warning in normalization.plan("meilisearch").warnings {
+                    warn!("{}", warning);
+                }
+            }
+            let settings = self.snapshot_to_meilisearch_settings(&snapshot)?;
+
+            self.client.update_settings(index, settings).await
+                .map_err(map_meilisearch_error)?;
+            cache::global().remove(CacheKind::Schema, &cache::index_key("meilisearch", index));
+            Ok(())
+        })
+        .await
+    }
+
+    /// Report drift between the index and a desired settings snapshot, optionally reconciling it
+    pub async fn diff_settings(&self, index: &str, desired_json: &str, reconcile: bool) -> SearchResult<SettingsDiff> {
+        let desired = IndexSettings::from_json(desired_json).map_err(map_shared_error)?;
+
+        let settings = self.client.get_settings(index).await
+            .map_err(map_meilisearch_error)?;
+        let index_info = self.client.get_index(index).await
+            .map_err(map_meilisearch_error)?;
+        let schema = self.meilisearch_settings_to_schema(&settings, &index_info)?;
+
+        let mut diff = self.meilisearch_settings_to_snapshot(&settings, &schema).diff(&desired);
+
+        if reconcile && !diff.is_empty() {
+            info!("Reconciling {} setting changes on Meilisearch index {}", diff.changes.len(), index);
+            let settings = self.snapshot_to_meilisearch_settings(&desired)?;
+            self.client.update_settings(index, settings).await
+                .map_err(map_meilisearch_error)?;
+            diff.reconciled = true;
+        }
+
+        Ok(diff)
+    }
+
+    /// Build a portable snapshot from native Meilisearch settings
+    fn meilisearch_settings_to_snapshot(&self, settings: &Value, schema: &Schema) -> IndexSettings {
+        let synonyms = settings.get("synonyms")
+            .and_then(|s| serde_json::from_value(s.clone()).ok())
+            .unwrap_or_default();
+        let ranking_rules = settings.get("rankingRules")
+            .and_then(|r| serde_json::from_value(r.clone()).ok())
+            .unwrap_or_default();
+        let stop_words = settings.get("stopWords")
+            .and_then(|w| serde_json::from_value(w.clone()).ok())
+            .unwrap_or_default();
+
+        // Keep the remaining native settings that have no portable equivalent
+        let mut provider_settings = serde_json::Map::new();
+        for key in ["distinctAttribute", "typoTolerance", "displayedAttributes"] {
+            if let Some(value) = settings.get(key) {
+                provider_settings.insert(key.to_string(), value.clone());
+            }
+        }
+
+        IndexSettings {
+            schema: Some(to_shared_schema(schema)),
+            synonyms,
+            ranking_rules,
+            stop_words,
+            normalization: Some(Normalization { fold_diacritics: true, ..Normalization::default() }),
+            provider_settings: if provider_settings.is_empty() {
+                None
+            } else {
+                Some(Value::Object(provider_settings))
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Build the native Meilisearch settings payload for a snapshot
+    fn snapshot_to_meilisearch_settings(&self, snapshot: &IndexSettings) -> SearchResult<Value> {
+        let mut settings = match &snapshot.schema {
+            Some(schema) => self.schema_to_meilisearch_settings(&from_shared_schema(schema))?,
+            None => json!({}),
+        };
+
+        // Synonyms and stop words are always sent so removed entries are cleared
+        settings["synonyms"] = json!(snapshot.synonyms);
+        settings["stopWords"] = json!(snapshot.stop_words);
+
+        if !snapshot.ranking_rules.is_empty() {
+            settings["rankingRules"] = json!(snapshot.ranking_rules);
+        }
+
+        if let Some(Value::Object(extra)) = &snapshot.provider_settings {
+            for (key, value) in extra {
+                settings[key.as_str()] = value.clone();
+            }
+        }
+
+        Ok(settings)
+    }
+}
+
+#[cfg(feature = "durability")]
+impl MeilisearchProvider {
+    /// Start a job copying `source` into `destination`, returning its ID
+    ///
+    /// Both indexes have to exist; the source is counted for the ETA.
+    pub async fn start_reindex(&self, source: &str, destination: &str) -> SearchResult<String> {
+        audit::audited("meilisearch", "start_reindex", destination, json!({ "source": source }), async {
+            let total = self.client.count_documents(source).await.map_err(map_meilisearch_error)?;
+            self.client.count_documents(destination).await.map_err(map_meilisearch_error)?;
+            reindex::start(self, "meilisearch", source, destination, Some(total)).await
+        })
+        .await
+    }
+
+    /// Copy pages of a job for up to `SEARCH_REINDEX_STEP_MS`, returning its status
+    pub async fn run_reindex(&self, id: &str) -> SearchResult<ReindexStatus> {
+        let mut job = reindex::job(self, "meilisearch", id).await?;
+        job.run(
+            self,
+            reindex::step_budget(),
+            |index, position| async move { self.reindex_page(&index, position.as_deref()).await },
+            |index, docs| async move { self.upsert_many(&index, &docs).await },
+        )
+        .await?;
+        Ok(job.status())
+    }
+
+    /// A page of an index's documents for a reindex job, the position being the offset
+    async fn reindex_page(&self, index: &str, position: Option<&str>) -> SearchResult<SourcePage<Doc>> {
+        let offset: usize = position.unwrap_or("0").parse()
+            .map_err(|_| SearchError::InvalidQuery(format!("Invalid reindex position {:?}", position)))?;
+        let geo_fields = geo::recall(index);
+        let page = self.client.list_documents(index, offset, REINDEX_PAGE_SIZE).await.map_err(map_meilisearch_error)?;
+
+        let results = page["results"].as_array().cloned().unwrap_or_default();
+        let next = (results.len() == REINDEX_PAGE_SIZE).then(|| (offset + results.len()).to_string());
+        let docs = results.into_iter()
+            .filter_map(|mut document| {
+                geo::from_provider("meilisearch", &mut document, &geo_fields);
+                let id = document["id"].as_str()?.to_string();
+                Some(Doc { id, content: document.to_string() })
+            })
+            .collect();
+        Ok(SourcePage { docs, next })
+    }
+}
+
+/// Jobs are documents of the jobs index, which Meilisearch creates on the first write
+#[cfg(feature = "durability")]
+impl JobStore for MeilisearchProvider {
+    type Error = SearchError;
+
+    fn load_job<'a>(&'a self, id: &'a str) -> LocalBoxFuture<'a, SearchResult<Option<golem_search::Doc>>> {
+        Box::pin(async move {
+            let document = self.client.get_document(JOBS_INDEX, id, None).await.map_err(map_meilisearch_error)?;
+            Ok(document.map(|document| golem_search::Doc { id: id.to_string(), content: document.to_string() }))
+        })
+    }
+
+    fn save_job(&self, doc: golem_search::Doc) -> LocalBoxFuture<'_, SearchResult<()>> {
+        Box::pin(async move {
+            let mut document: Value = serde_json::from_str(&doc.content).map_err(|e| SearchError::Internal(e.to_string()))?;
+            document["id"] = json!(doc.id);
+            self.client.add_documents(JOBS_INDEX, json!([document])).await.map_err(map_meilisearch_error)?;
+            Ok(())
+        })
+    }
+}
+
+impl From<golem_search::SearchError> for SearchError {
+    fn from(error: golem_search::SearchError) -> Self {
+        map_shared_error(error)
+    }
+}
+
+// WIT bindings
+wit_bindgen::generate!({
+    world: "meilisearch-provider",
+    path: "wit",
+    generate_unused_types: true,
+    with: {
+        "golem:search/types@1.0.0": generate,
+        "golem:search/core@1.0.0": generate,
+    },
+});
+
+use exports::golem::search::core::{Guest, GuestIndexWatch, GuestReindexJob, GuestSearchSession, IndexWatch, ReindexJob, SearchSession};
+
+// Export the implementation
+struct Component;
+
+thread_local! {
+    static PROVIDER: ProviderCache<MeilisearchProvider> = const { ProviderCache::new() };
+}
+
+/// Run an export on the instance's runtime
+fn block_on<T>(call: impl Future<Output = SearchResult<T>>) -> SearchResult<T> {
+    instance::runtime().map_err(map_shared_error)?.block_on(call)
+}
+
+/// The instance's provider, built with its configuration and client on first use
+async fn shared_provider() -> SearchResult<Rc<MeilisearchProvider>> {
+    instance::get_or_try_init(&PROVIDER, MeilisearchProvider::new).await
+}
+
+/// Refuse a named server ingest pipeline, which Meilisearch does not have
+fn no_server_pipeline(pipeline: Option<&str>) -> SearchResult<()> {
+    match pipeline {
+        Some(name) => Err(SearchError::Unsupported(format!(
+            "Meilisearch has no server ingest pipeline {}; use set-ingest-pipeline",
+            name
+        ))),
+        None => Ok(()),
+    }
+}
+
+impl Guest for Component {
+    type SearchSession = MeilisearchSession;
+    type IndexWatch = MeilisearchIndexWatch;
+    type ReindexJob = MeilisearchReindexJob;
+
+    fn search(index: String, query: SearchQuery) -> SearchResult<SearchResults> {
+        // Synchronous wrapper for the async implementation
+        block_on(async {
+            let provider = shared_provider().await?;
+            provider.search(&index, &query).await
+        })
+    }
+
+    fn search_with_lookup(index: String, query: SearchQuery, lookup: String) -> SearchResult<SearchResults> {
+        block_on(async {
+            let lookup = LookupSpec::from_json(&lookup).map_err(map_shared_error)?;
+            let provider = shared_provider().await?;
+            provider.search_with_lookup(&index, &query, &lookup).await
+        })
+    }
+
+    fn estimate_query(index: String, query: SearchQuery) -> SearchResult<String> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            let estimate = provider.estimate_query(&index, &query).await?;
+            serde_json::to_string(&estimate).map_err(|e| SearchError::Internal(e.to_string()))
+        })
+    }
+
+    fn parse_query(text: String) -> SearchResult<QueryAst> {
+        query_ast::parse_query(&text).map(from_shared_query_ast).map_err(map_shared_error)
+    }
+
+    fn render_query(ast: QueryAst) -> SearchResult<String> {
+        query_ast::render_query(&to_shared_query_ast(ast)).map_err(map_shared_error)
+    }
+
+    fn upsert(index: String, doc: Doc) -> SearchResult<()> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            provider.upsert(&index, &doc).await
+        })
+    }
+
+    fn upsert_with_token(index: String, doc: Doc) -> SearchResult<String> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            provider.upsert_with_token(&index, &doc).await
+        })
+    }
+
+    fn get(index: String, id: String, fields: Option<Vec<String>>) -> SearchResult<Option<Doc>> {
+        block_on(async {
+            let mask = FieldMask::from_fields(fields.as_deref()).map_err(map_shared_error)?;
+            let provider = shared_provider().await?;
+            provider.get(&index, &id, mask.as_ref()).await
+        })
+    }
+
+    fn get_many(index: String, ids: Vec<String>, fields: Option<Vec<String>>) -> SearchResult<Vec<Option<Doc>>> {
+        block_on(async {
+            let mask = FieldMask::from_fields(fields.as_deref()).map_err(map_shared_error)?;
+            let provider = shared_provider().await?;
+            provider.get_many(&index, &ids, mask.as_ref()).await
+        })
+    }
+
+    fn exists(index: String, id: String) -> SearchResult<bool> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            provider.exists(&index, &id).await
+        })
+    }
+
+    fn delete(index: String, id: String) -> SearchResult<()> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            provider.delete(&index, &id).await
+        })
+    }
+
+    fn create_index(name: String, schema: Option<Schema>) -> SearchResult<()> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            provider.create_index(&name, schema.as_ref()).await
+        })
+    }
+
+    fn ensure_index(name: String, schema: Option<Schema>) -> SearchResult<bool> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            provider.ensure_index(&name, schema.as_ref()).await
+        })
+    }
+
+    fn create_index_from_template(name: String, template: String, overrides: Option<String>) -> SearchResult<String> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            let template = provider.create_index_from_template(&name, &template, overrides.as_deref()).await?;
+            serde_json::to_string(&template).map_err(|e| SearchError::Internal(e.to_string()))
+        })
+    }
+
+    fn export_settings(index: String) -> SearchResult<String> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            provider.export_settings(&index).await
+        })
+    }
+
+    fn apply_settings(index: String, settings: String) -> SearchResult<()> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            provider.apply_settings(&index, &settings).await
+        })
+    }
+
+    fn diff_settings(index: String, desired: String, reconcile: bool) -> SearchResult<String> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            let diff = provider.diff_settings(&index, &desired, reconcile).await?;
+            diff.to_json().map_err(map_shared_error)
+        })
+    }
+
+    fn delete_index(name: String, dry_run: bool) -> SearchResult<String> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            let plan = provider.delete_index(&name, dry_run).await?;
+            serde_json::to_string(&plan).map_err(|e| SearchError::Internal(e.to_string()))
+        })
+    }
+
+    fn delete_by_query(index: String, filters: Vec<String>, dry_run: bool) -> SearchResult<String> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            let plan = provider.delete_by_query(&index, &filters, dry_run).await?;
+            serde_json::to_string(&plan).map_err(|e| SearchError::Internal(e.to_string()))
+        })
+    }
+
+    fn clear_index(index: String, dry_run: bool) -> SearchResult<String> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            let plan = provider.clear_index(&index, dry_run).await?;
+            serde_json::to_string(&plan).map_err(|e| SearchError::Internal(e.to_string()))
+        })
+    }
+
+    fn list_indexes() -> SearchResult<Vec<String>> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            provider.list_indexes().await
+        })
+    }
+
+    fn create_snapshot(index: String) -> SearchResult<String> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            let snapshot = provider.create_snapshot(&index).await?;
+            serde_json::to_string(&snapshot).map_err(|e| SearchError::Internal(e.to_string()))
+        })
+    }
+
+    fn restore_snapshot(snapshot_id: String) -> SearchResult<String> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            let snapshot = provider.restore_snapshot(&snapshot_id).await?;
+            serde_json::to_string(&snapshot).map_err(|e| SearchError::Internal(e.to_string()))
+        })
+    }
+
+    fn get_schema(index: String) -> SearchResult<Schema> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            provider.get_schema(&index).await
+        })
+    }
+
+    fn get_capabilities() -> SearchCapabilities {
+        // Create a minimal provider instance for capabilities (doesn't need actual connection)
+        let config = MeilisearchConfig {
+            endpoint: "http://localhost:7700".to_string(),
+            master_key: None,
+            timeout: Duration::from_secs(30),
+            max_retries: 3,
+            degradation: DegradationMode::default(),
+            compression: Compression::None,
+        };
+        
+        let client = MeilisearchClient::new(config).unwrap();
+        let provider = MeilisearchProvider { client };
+        provider.get_capabilities()
+    }
+
+    fn batch_upsert(index: String, docs: Vec<Doc>) -> SearchResult<()> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            provider.upsert_many(&index, &docs).await
+        })
+    }
+
+    fn upsert_with_pipeline(index: String, doc: Doc, pipeline: Option<String>) -> SearchResult<()> {
+        no_server_pipeline(pipeline.as_deref())?;
+        Self::upsert(index, doc)
+    }
+
+    fn batch_upsert_with_pipeline(index: String, docs: Vec<Doc>, pipeline: Option<String>) -> SearchResult<()> {
+        no_server_pipeline(pipeline.as_deref())?;
+        Self::batch_upsert(index, docs)
+    }
+
+    fn flush_writes(index: Option<String>) -> SearchResult<u32> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            provider.flush_writes(index.as_deref()).await
+        })
+    }
+
+    fn outbox_record(index: String, mutation: OutboxMutation, idempotency_key: Option<String>) -> SearchResult<Option<u64>> {
+        let mutation = match mutation {
+            OutboxMutation::Upsert(doc) => {
+                // Rejected now rather than left to fail on every drain
+                serde_json::from_str::<Value>(&doc.content)
+                    .map_err(|e| SearchError::InvalidQuery(format!("Invalid document {}: {}", doc.id, e)))?;
+                Mutation::Upsert { doc: golem_search::types::Doc { id: doc.id, content: doc.content } }
+            }
+            OutboxMutation::Delete(id) => Mutation::Delete { id },
+        };
+        Ok(outbox::record(&index, mutation, idempotency_key))
+    }
+
+    fn drain_outbox() -> SearchResult<String> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            let report = provider.drain_outbox().await;
+            serde_json::to_string(&report).map_err(|e| SearchError::Internal(e.to_string()))
+        })
+    }
+
+    fn flush() -> SearchResult<String> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            let report = provider.flush().await;
+            serde_json::to_string(&report).map_err(|e| SearchError::Internal(e.to_string()))
+        })
+    }
+
+    fn shutdown() -> SearchResult<String> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            let report = provider.shutdown().await;
+            serde_json::to_string(&report).map_err(|e| SearchError::Internal(e.to_string()))
+        })
+    }
+
+    fn sync(index: String, docs: Vec<Doc>, dry_run: bool) -> SearchResult<String> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            let report = provider.sync(&index, &docs, dry_run).await?;
+            serde_json::to_string(&report).map_err(|e| SearchError::Internal(e.to_string()))
+        })
+    }
+
+    fn build_suggestions(index: String, queries: String, config: Option<String>) -> SearchResult<String> {
+        let queries = suggestions::parse_popular_queries(&queries).map_err(map_shared_error)?;
+        let config = SuggestionsConfig::from_json(config.as_deref()).map_err(map_shared_error)?;
+        block_on(async {
+            let provider = shared_provider().await?;
+            let report = provider.build_suggestions(&index, &queries, &config).await?;
+            serde_json::to_string(&report).map_err(|e| SearchError::Internal(e.to_string()))
+        })
+    }
+
+    fn suggest(index: String, prefix: String, limit: u32) -> SearchResult<String> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            let suggestions = provider.suggest(&index, &prefix, limit).await?;
+            serde_json::to_string(&suggestions).map_err(|e| SearchError::Internal(e.to_string()))
+        })
+    }
+
+    fn set_ingest_pipeline(index: String, pipeline: Option<String>) -> SearchResult<()> {
+        let pipeline = pipeline.as_deref().map(ingest::IngestPipeline::from_json).transpose().map_err(map_shared_error)?;
+        ingest::set_pipeline(&index, pipeline).map_err(map_shared_error)
+    }
+
+    fn save_search(id: String, index: String, query: String, filters: Vec<String>) -> SearchResult<()> {
+        alerts::save_search(&id, &index, &query, filters).map_err(map_shared_error)
+    }
+
+    fn delete_saved_search(id: String) -> SearchResult<bool> {
+        Ok(alerts::delete_search(&id))
+    }
+
+    fn run_alerts() -> SearchResult<String> {
+        serde_json::to_string(&alerts::run_alerts()).map_err(|e| SearchError::Internal(e.to_string()))
+    }
+
+    fn health_check() -> SearchResult<()> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            // Simple health check by getting stats
+            provider.client.get_stats().await.map_err(map_meilisearch_error).map(|_| ())
+        })
+    }
+
+    fn get_slow_queries() -> String {
+        serde_json::to_string(&slow_query::get_slow_queries()).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    fn health_report() -> String {
+        serde_json::to_string(&circuit_breaker::health_report("meilisearch")).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    fn metrics_prometheus() -> String {
+        metrics::render_prometheus()
+    }
+
+    fn diagnose_config() -> String {
+        serde_json::to_string(&diagnose::diagnose_config("meilisearch")).unwrap_or_default()
+    }
+
+    fn describe() -> SearchResult<String> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            let description = provider.describe().await;
+            serde_json::to_string(&description).map_err(|e| SearchError::Internal(e.to_string()))
+        })
+    }
+
+    fn get_audit_log(since: Option<String>) -> SearchResult<String> {
+        let entries = audit::get_audit_log(since.as_deref()).map_err(map_shared_error)?;
+        serde_json::to_string(&entries).map_err(|e| SearchError::Internal(e.to_string()))
+    }
+
+    fn list_pending_tasks(index: String) -> SearchResult<String> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            let pending = provider.list_pending_tasks(&index).await?;
+            serde_json::to_string(&pending).map_err(|e| SearchError::Internal(e.to_string()))
+        })
+    }
+}
+
+/// Defaults applied to the calls made on one `search-session` resource
+pub struct MeilisearchSession {
+    provider: Rc<MeilisearchProvider>,
+    context: RefCell<SessionContext>,
+}
+
+impl GuestSearchSession for MeilisearchSession {
+    fn open(default_index: Option<String>, user_token: Option<String>) -> SearchResult<SearchSession> {
+        let provider = block_on(shared_provider())?;
+        let context = RefCell::new(SessionContext::new(default_index, user_token));
+        Ok(SearchSession::new(Self { provider, context }))
+    }
+
+    fn search(&self, index: Option<String>, mut query: SearchQuery) -> SearchResult<SearchResults> {
+        let context = self.context.borrow();
+        let index = context.index(index.as_deref()).map_err(map_shared_error)?;
+        context.apply_consistency(&mut query.consistency_token);
+        let _user = context.enter();
+        block_on(self.provider.search(index, &query))
+    }
+
+    fn upsert(&self, index: Option<String>, doc: Doc) -> SearchResult<()> {
+        let token = {
+            let context = self.context.borrow();
+            let index = context.index(index.as_deref()).map_err(map_shared_error)?;
+            let _user = context.enter();
+            block_on(self.provider.upsert_with_token(index, &doc))?
+        };
+        self.context.borrow_mut().record_write(token);
+        Ok(())
+    }
+
+    fn get(&self, index: Option<String>, id: String, fields: Option<Vec<String>>) -> SearchResult<Option<Doc>> {
+        let context = self.context.borrow();
+        let index = context.index(index.as_deref()).map_err(map_shared_error)?;
+        let mask = FieldMask::from_fields(fields.as_deref()).map_err(map_shared_error)?;
+        let _user = context.enter();
+        block_on(self.provider.get(index, &id, mask.as_ref()))
+    }
+
+    fn delete(&self, index: Option<String>, id: String) -> SearchResult<()> {
+        let context = self.context.borrow();
+        let index = context.index(index.as_deref()).map_err(map_shared_error)?;
+        let _user = context.enter();
+        block_on(self.provider.delete(index, &id))
+    }
+
+    fn set_default_index(&self, index: Option<String>) {
+        self.context.borrow_mut().default_index = index;
+    }
+
+    fn set_user_token(&self, user_token: Option<String>) {
+        self.context.borrow_mut().user_token = user_token;
+    }
+
+    fn consistency_token(&self) -> Option<String> {
+        self.context.borrow().consistency_token.clone()
+    }
+}
+
+/// Position of one `index-watch` resource
+pub struct MeilisearchIndexWatch {
+    provider: Rc<MeilisearchProvider>,
+    index: String,
+    cursor: RefCell<String>,
+}
+
+impl GuestIndexWatch for MeilisearchIndexWatch {
+    fn open(index: String, since: Option<String>) -> SearchResult<IndexWatch> {
+        let provider = block_on(shared_provider())?;
+        let cursor = match since {
+            Some(since) => {
+                WatchCursor::parse_for(&since, "meilisearch", &index).map_err(map_shared_error)?;
+                since
+            }
+            None => block_on(provider.index_changes(&index, None))?.cursor,
+        };
+        Ok(IndexWatch::new(Self { provider, index, cursor: RefCell::new(cursor) }))
+    }
+
+    fn poll(&self) -> SearchResult<String> {
+        let since = self.cursor.borrow().clone();
+        let summary = block_on(self.provider.index_changes(&self.index, Some(&since)))?;
+        *self.cursor.borrow_mut() = summary.cursor.clone();
+        serde_json::to_string(&summary).map_err(|e| SearchError::Internal(e.to_string()))
+    }
+
+    fn cursor(&self) -> String {
+        self.cursor.borrow().clone()
+    }
+}
+
+/// Handle on a reindex job, stored on the backend by its ID
+pub struct MeilisearchReindexJob {
+    id: String,
+}
+
+#[cfg(feature = "durability")]
+impl GuestReindexJob for MeilisearchReindexJob {
+    fn start(source: String, destination: String) -> SearchResult<ReindexJob> {
+        let id = block_on(async { shared_provider().await?.start_reindex(&source, &destination).await })?;
+        Ok(ReindexJob::new(Self { id }))
+    }
+
+    fn resume(id: String) -> SearchResult<ReindexJob> {
+        block_on(async { reindex::job(&*shared_provider().await?, "meilisearch", &id).await })?;
+        Ok(ReindexJob::new(Self { id }))
+    }
+
+    fn id(&self) -> String {
+        self.id.clone()
+    }
+
+    fn run(&self) -> SearchResult<String> {
+        let status = block_on(async { shared_provider().await?.run_reindex(&self.id).await })?;
+        serde_json::to_string(&status).map_err(|e| SearchError::Internal(e.to_string()))
+    }
+
+    fn status(&self) -> SearchResult<String> {
+        let status = block_on(async { reindex::status(&*shared_provider().await?, "meilisearch", &self.id).await })?;
+        serde_json::to_string(&status).map_err(|e| SearchError::Internal(e.to_string()))
+    }
+
+    fn cancel(&self) -> SearchResult<String> {
+        let status = block_on(async { reindex::cancel(&*shared_provider().await?, "meilisearch", &self.id).await })?;
+        serde_json::to_string(&status).map_err(|e| SearchError::Internal(e.to_string()))
+    }
+}
+
+#[cfg(not(feature = "durability"))]
+impl GuestReindexJob for MeilisearchReindexJob {
+    fn start(_source: String, _destination: String) -> SearchResult<ReindexJob> {
+        Err(SearchError::Unsupported("Reindex jobs need the durability feature".to_string()))
+    }
+
+    fn resume(_id: String) -> SearchResult<ReindexJob> {
+        Err(SearchError::Unsupported("Reindex jobs need the durability feature".to_string()))
+    }
+
+    fn id(&self) -> String {
+        self.id.clone()
+    }
+
+    fn run(&self) -> SearchResult<String> {
+        Err(SearchError::Unsupported("Reindex jobs need the durability feature".to_string()))
+    }
+
+    fn status(&self) -> SearchResult<String> {
+        Err(SearchError::Unsupported("Reindex jobs need the durability feature".to_string()))
+    }
+
+    fn cancel(&self) -> SearchResult<String> {
+        Err(SearchError::Unsupported("Reindex jobs need the durability feature".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+    use std::sync::Mutex;
+    use golem_search::http::{HttpClient, Request, Response, StatusCode, Url};
+
+    /// Scripted answer of a node: a status and body, or `None` to refuse the connection
+    type Reply = Option<(u16, &'static str)>;
+
+    /// Transport answering each node with its scripted replies in turn, repeating the last one
+    #[derive(Default)]
+    struct Nodes {
+        replies: Mutex<HashMap<String, VecDeque<Reply>>>,
+        seen: Mutex<Vec<String>>,
+    }
+
+    impl Nodes {
+        fn new(replies: &[(&str, &[Reply])]) -> Arc<Self> {
+            let replies = replies.iter().map(|(host, replies)| (host.to_string(), replies.iter().copied().collect())).collect();
+            Arc::new(Self { replies: Mutex::new(replies), seen: Mutex::new(Vec::new()) })
+        }
+
+        fn seen(&self) -> Vec<String> {
+            self.seen.lock().unwrap().clone()
+        }
+    }
+
+    impl HttpClient for Nodes {
+        fn execute(&self, request: Request) -> Result<Response, http::Error> {
+            let host = request.url.host_str().unwrap_or_default().to_string();
+            self.seen.lock().unwrap().push(format!("{} {}{}", request.method, host, request.url.path()));
+            let mut replies = self.replies.lock().unwrap();
+            let queue = replies.get_mut(&host).expect("no replies for host");
+            let reply = if queue.len() > 1 { queue.pop_front().unwrap() } else { queue[0] };
+            match reply {
+                Some((status, body)) => {
+                    let status = StatusCode::from_u16(status).unwrap();
+                    Ok(Response::new(status, HeaderMap::new(), body.as_bytes().to_vec(), request.url))
+                }
+                None => Err(http::Error::connect("connection refused")),
+            }
+        }
+    }
+
+    /// Client of the comma-separated `endpoints` sending through `nodes`, with quick retries
+    fn client_over(nodes: &Arc<Nodes>, endpoints: &str, max_retries: u32) -> MeilisearchClient {
+        let policy = RetryPolicy { base_delay: Duration::from_millis(1), ..RetryPolicy::new(max_retries) };
+        MeilisearchClient {
+            config: MeilisearchConfig {
+                endpoint: endpoints.to_string(),
+                master_key: None,
+                timeout: Duration::from_secs(5),
+                max_retries,
+                degradation: DegradationMode::default(),
+                compression: Compression::default(),
+            },
+            http_client: Client::builder().retry(policy).transport(nodes.clone()).build().unwrap(),
+            endpoints: Arc::new(EndpointPool::parse(endpoints, Duration::from_secs(60)).unwrap()),
+            throttle: rate_limit::throttle("meilisearch"),
+        }
+    }
+
+    #[test]
+    fn test_searches_fail_over_to_the_next_node_and_document_additions_do_not() {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let nodes = Nodes::new(&[
+            ("meili-failover-a", &[None]),
+            ("meili-failover-b", &[Some((200, r#"{"hits":[]}"#))]),
+        ]);
+        let client = client_over(&nodes, "http://meili-failover-a:7700,http://meili-failover-b:7700", 0);
+
+        runtime.block_on(client.search("books", json!({ "q": "rust" }))).unwrap();
+        assert!(runtime.block_on(client.add_documents("books", json!([{ "id": "1" }]))).is_ok());
+        assert_eq!(
+            nodes.seen(),
+            [
+                "POST meili-failover-a/indexes/books/search",
+                "POST meili-failover-b/indexes/books/search",
+                "POST meili-failover-b/indexes/books/documents",
+            ]
+        );
+
+        // With both nodes down the write goes to the one that recovers first, and only there
+        client.endpoints.mark_down(&Url::parse("http://meili-failover-b:7700/").unwrap());
+        assert!(runtime.block_on(client.add_documents("books", json!([{ "id": "2" }]))).is_err());
+        assert_eq!(nodes.seen().last().unwrap(), "POST meili-failover-a/indexes/books/documents");
+        assert_eq!(nodes.seen().len(), 4);
+    }
+
+    fn text_query(q: &str) -> SearchQuery {
+        SearchQuery {
+            q: Some(q.to_string()),
+            filters: Vec::new(),
+            sort: Vec::new(),
+            page: None,
+            per_page: None,
+            offset: None,
+            facets: Vec::new(),
+            highlight: None,
+            provider_params: None,
+            explain: false,
+            consistency_token: None,
+            sample: None,
+            normalization: None,
+            fuzziness: None,
+            minimum_should_match: None,
+            timeout_ms: None,
+            cursor: None,
+            exact_total: false,
+        }
+    }
+
+    #[test]
+    fn test_cached_results_are_served_until_a_write_to_the_index() {
+        std::env::set_var(cache::RESULT_CACHE_POLICIES_ENV, "meili-cached-books:60000:0");
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let nodes = Nodes::new(&[(
+            "meili-cache",
+            &[Some((200, r#"{"hits":[{"id":"1","title":"Dune"}],"estimatedTotalHits":1,"processingTimeMs":1}"#))],
+        )]);
+        let provider = MeilisearchProvider { client: client_over(&nodes, "http://meili-cache:7700", 0) };
+        let searches = || nodes.seen().iter().filter(|request| request.ends_with("/search")).count();
+
+        let first = runtime.block_on(provider.search("meili-cached-books", &text_query("dune"))).unwrap();
+        let second = runtime.block_on(provider.search("meili-cached-books", &text_query("  dune"))).unwrap();
+        assert_eq!((first.hits.len(), second.hits.len()), (1, 1));
+        assert_eq!(searches(), 1);
+
+        let doc = Doc { id: "2".to_string(), content: r#"{"title":"Emma"}"#.to_string() };
+        runtime.block_on(provider.upsert("meili-cached-books", &doc)).unwrap();
+        runtime.block_on(provider.search("meili-cached-books", &text_query("dune"))).unwrap();
+        assert_eq!(searches(), 2);
+    }
+
+    #[test]
+    fn test_searches_are_retried_and_document_additions_sent_once() {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let nodes = Nodes::new(&[
+            ("meili-retry-read", &[Some((503, "busy")), Some((503, "busy")), Some((200, r#"{"hits":[]}"#))]),
+            ("meili-retry-write", &[Some((503, "busy")), Some((202, r#"{"taskUid":1}"#))]),
+        ]);
+
+        let reader = client_over(&nodes, "http://meili-retry-read:7700", 2);
+        let results = runtime.block_on(reader.search("books", json!({ "q": "rust" }))).unwrap();
+        assert_eq!(results["hits"], json!([]));
+
+        let writer = client_over(&nodes, "http://meili-retry-write:7700", 2);
+        let err = runtime.block_on(writer.add_documents("books", json!([{ "id": "1" }]))).unwrap_err();
+        assert!(err.to_string().contains("busy"), "{}", err);
+
+        assert_eq!(
+            nodes.seen(),
+            [
+                "POST meili-retry-read/indexes/books/search",
+                "POST meili-retry-read/indexes/books/search",
+                "POST meili-retry-read/indexes/books/search",
+                "POST meili-retry-write/indexes/books/documents",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_exact_total_pages_exhaustively_and_estimates_are_inexact() {
+        let mut query = json!({ "q": "rust", "offset": 25, "limit": 10 });
+        assert_eq!(exhaustive_pagination(&mut query, 10), 20);
+        assert_eq!(query, json!({ "q": "rust", "page": 3, "hitsPerPage": 10 }));
+
+        let config = MeilisearchConfig {
+            endpoint: "http://localhost:7700".to_string(),
+            master_key: None,
+            timeout: Duration::from_secs(5),
+            max_retries: 0,
+            degradation: DegradationMode::default(),
+            compression: Compression::default(),
+        };
+        let provider = MeilisearchProvider { client: MeilisearchClient::new(config).unwrap() };
+        let estimated = provider.response_to_results(r#"{"hits":[],"estimatedTotalHits":1000}"#).unwrap();
+        assert_eq!((estimated.total, estimated.total_exact), (Some(1000), Some(false)));
+        let exact = provider.response_to_results(r#"{"hits":[],"totalHits":1234}"#).unwrap();
+        assert_eq!((exact.total, exact.total_exact), (Some(1234), Some(true)));
+    }
+
+    #[test]
+    fn test_document_presence_tells_missing_documents_from_missing_indexes() {
+        assert!(document_presence(200, r#"{"id":"a/1"}"#).unwrap());
+        let absent = r#"{"message":"Document `a/1` not found.","code":"document_not_found","type":"invalid_request"}"#;
+        assert!(!document_presence(404, absent).unwrap());
+
+        let missing = r#"{"message":"Index `products` not found.","code":"index_not_found","type":"invalid_request"}"#;
+        let error = map_meilisearch_error(document_presence(404, missing).unwrap_err());
+        assert!(matches!(error, SearchError::IndexNotFound(_)));
+    }
+}
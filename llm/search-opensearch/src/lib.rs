@@ -4,18 +4,200 @@
 //! so this implementation largely reuses ElasticSearch patterns.
 
 use anyhow::Result;
-use log::{debug, error, info};
-use std::collections::HashMap;
+use log::{debug, error, info, warn};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
 use std::time::Duration;
-use reqwest::{Client, Method, header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE}};
+use golem_search::http::{self, Client, Method, header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_ENCODING, CONTENT_TYPE}};
 use serde_json::{Value, json};
-use url::Url;
 use base64::Engine as _;
 
 use golem_search::{
     SearchError, SearchResult, Doc, SearchQuery, SearchResults, Schema,
-    SearchCapabilities, FieldType, SchemaField,
+    SearchCapabilities, FieldType, SchemaField, QueryText, IndexSettings, SettingsDiff, LookupSpec,
 };
+use golem_search::lookup::{check_lookup_complete, collect_lookup_keys, fetch_in_batches, merge_lookup, MAX_LOOKUP_DOCS};
+use golem_search::analysis::{unsupported_analysis, FieldAnalysis};
+use golem_search::facets::parse_facets;
+use golem_search::query::{lucene_filter_clause, ParsedQuery, PatternLimits};
+use golem_search::cursor::LucenePage;
+use golem_search::deadline;
+use golem_search::delta_sync::{self, IndexFingerprint, SyncReport};
+use golem_search::raw::lucene_page_to_results;
+use golem_search::alerts;
+use golem_search::audit;
+use golem_search::batching::{submit_adaptive, AdaptiveBatchConfig, AdaptiveBatcher};
+use golem_search::cache::{self, CacheKind};
+use golem_search::capabilities::opensearch_capability_matrix;
+use golem_search::circuit_breaker;
+use golem_search::shutdown::{self, ShutdownReport};
+use golem_search::compiled_query;
+use golem_search::config::ConfigProfile;
+use golem_search::consistency::{self, ConsistencyToken};
+use golem_search::cost::{self, CostEstimate, IndexStats};
+use golem_search::describe::{self, ProviderDescription};
+use golem_search::diagnose;
+use golem_search::events::{self, LifecycleEventKind};
+use golem_search::field_mask::FieldMask;
+use golem_search::geo::{self, GeoPolygonFilter};
+use golem_search::highlight;
+use golem_search::id_set::IdSetFilter;
+use golem_search::ingest;
+use golem_search::dry_run::{self, OperationPlan};
+use golem_search::endpoint_pool::{self, EndpointPool};
+use golem_search::json;
+use golem_search::quota;
+use golem_search::rate_limit::{self, Throttle};
+use golem_search::request_id::{self, REQUEST_ID_HEADER};
+use golem_search::rescue::{self, RescuePlan};
+use golem_search::metrics;
+use golem_search::telemetry;
+use golem_search::version::{self, ServerVersion};
+use golem_search::watch::{ChangeSummary, WatchCursor, WriteCounters};
+use golem_search::compression::Compression;
+use golem_search::ndjson::NdjsonWriter;
+use golem_search::query_split::{self, SplitLimits};
+use golem_search::response_limits::{results_size, ResponseLimits};
+use golem_search::retry::RetryPolicy;
+use golem_search::promotion::{self, PromotionChecks, PromotionReport};
+use golem_search::rollover::{self, RolloverConditions, RolloverResult};
+use golem_search::router::{LocalBoxFuture, RoutedBackend};
+use golem_search::routing::{self, RoutingFields};
+use golem_search::sampling::{random_score_query, random_seed};
+use golem_search::schema::check_schema_compatibility;
+use golem_search::slow_query;
+use golem_search::snapshot::{self, SnapshotInfo, SnapshotKind};
+use golem_search::spellfix;
+use golem_search::suggestions::{self, PopularQuery, Suggestion, SuggestionsBuilder, SuggestionsConfig};
+use golem_search::tasks::PendingTasks;
+use golem_search::templates::{self, IndexTemplate, TemplateOverrides};
+use golem_search::ui_metadata::{self, FacetMetadata, UI_META_KEY};
+use golem_search::change_feed::ChangeBatch;
+use golem_search::checksum;
+use golem_search::numeric::{self, DECIMAL_META_KEY};
+use golem_search::outbox::{self, DrainReport, Mutation};
+use golem_search::write_buffer;
+use golem_search::provider_params::{merge_into_body, parse_provider_params};
+
+/// Top-level search body keys that may be supplied through `provider_params`
+pub const OPENSEARCH_ALLOWED_PARAMS: &[&str] = &[
+    "track_total_hits",
+    "min_score",
+    "timeout",
+    "terminate_after",
+    "post_filter",
+    "collapse",
+    "rescore",
+    "search_after",
+    "_source",
+    "indices_boost",
+    "track_scores",
+    "routing",
+    "preference",
+];
+
+/// Search analyzer carrying the synonyms and stop words of an index template or settings snapshot
+const TEMPLATE_SEARCH_ANALYZER: &str = "golem_template_search";
+
+/// Synonym filter of the search analyzer
+const SYNONYM_FILTER: &str = "golem_template_synonyms";
+
+/// Stop word filter of the search analyzer
+const STOP_FILTER: &str = "golem_template_stop";
+
+/// Analysis settings for a template's synonyms and stop words, if it has any
+fn template_analysis(template: &IndexTemplate) -> Option<Value> {
+    if template.synonyms.is_empty() && template.stop_words.is_empty() {
+        return None;
+    }
+    Some(search_analysis(&template.synonyms, &template.stop_words))
+}
+
+/// Analysis settings defining the search analyzer for `synonyms` and `stop_words`
+fn search_analysis(synonyms: &BTreeMap<String, Vec<String>>, stop_words: &[String]) -> Value {
+    let synonyms: Vec<String> = synonyms
+        .iter()
+        .map(|(term, equivalents)| {
+            std::iter::once(term.as_str())
+                .chain(equivalents.iter().map(String::as_str))
+                .collect::<Vec<_>>()
+                .join(", ")
+        })
+        .collect();
+
+    // An empty synonym_graph filter is rejected, so each filter is only added when used
+    let mut filters = serde_json::Map::new();
+    let mut chain = vec![json!("lowercase")];
+    if !synonyms.is_empty() {
+        filters.insert(SYNONYM_FILTER.to_string(), json!({ "type": "synonym_graph", "synonyms": synonyms }));
+        chain.push(json!(SYNONYM_FILTER));
+    }
+    if !stop_words.is_empty() {
+        filters.insert(STOP_FILTER.to_string(), json!({ "type": "stop", "stopwords": stop_words }));
+        chain.push(json!(STOP_FILTER));
+    }
+    json!({
+        "analysis": {
+            "filter": filters,
+            "analyzer": {
+                TEMPLATE_SEARCH_ANALYZER: { "type": "custom", "tokenizer": "standard", "filter": chain }
+            }
+        }
+    })
+}
+
+/// Synonyms and stop words of the search analyzer in `GET _settings` output
+///
+/// Filters the analyzer no longer uses stay in the index settings, since
+/// analysis filters cannot be removed, so only those in its chain are read.
+fn settings_to_snapshot(settings: &Value, index: &str) -> IndexSettings {
+    let analysis = &settings[index]["settings"]["index"]["analysis"];
+    let chain = analysis["analyzer"][TEMPLATE_SEARCH_ANALYZER]["filter"].as_array().cloned().unwrap_or_default();
+    let used = |filter: &str| chain.iter().any(|name| name == filter);
+    let strings = |value: &Value| -> Vec<String> {
+        value.as_array().into_iter().flatten().filter_map(Value::as_str).map(str::to_string).collect()
+    };
+
+    let mut snapshot = IndexSettings::default();
+    if used(SYNONYM_FILTER) {
+        for rule in strings(&analysis["filter"][SYNONYM_FILTER]["synonyms"]) {
+            let mut terms = rule.split(',').map(|t| t.trim().to_string()).filter(|t| !t.is_empty());
+            if let Some(term) = terms.next() {
+                snapshot.synonyms.insert(term, terms.collect());
+            }
+        }
+    }
+    if used(STOP_FILTER) {
+        snapshot.stop_words = strings(&analysis["filter"][STOP_FILTER]["stopwords"]);
+    }
+    snapshot
+}
+
+/// Search body fetching the lookup documents whose foreign field matches `keys`
+fn lookup_to_opensearch_query(lookup: &LookupSpec, keys: &[String]) -> Value {
+    let mut query = json!({
+        "query": { "terms": { lookup.foreign_field.as_str(): keys } },
+        "size": MAX_LOOKUP_DOCS,
+        "track_total_hits": true
+    });
+    if !lookup.fields.is_empty() {
+        let mut source = lookup.fields.clone();
+        source.push(lookup.foreign_field.clone());
+        query["_source"] = json!(source);
+    }
+    query
+}
+
+/// `_source_includes` query string limiting the returned source to `fields`, empty without fields
+fn source_includes(fields: Option<&[String]>) -> String {
+    match fields {
+        Some(fields) => {
+            let fields: String = url::form_urlencoded::byte_serialize(fields.join(",").as_bytes()).collect();
+            format!("?_source_includes={}", fields)
+        }
+        None => String::new(),
+    }
+}
 
 /// Configuration for the OpenSearch client
 #[derive(Debug, Clone)]
@@ -26,11 +208,24 @@ pub struct OpenSearchConfig {
     pub api_key: Option<String>,
     pub timeout: Duration,
     pub max_retries: u32,
+    pub compression: Compression,
 }
 
 impl OpenSearchConfig {
     /// Create configuration from environment variables
     pub fn from_env() -> Result<Self> {
+        if let Some(profile) = ConfigProfile::active("opensearch")? {
+            return Ok(Self {
+                endpoint: profile.endpoint,
+                username: profile.username,
+                password: profile.password,
+                api_key: profile.api_key,
+                timeout: profile.timeout,
+                max_retries: profile.max_retries,
+                compression: Compression::from_env(),
+            });
+        }
+
         let endpoint = std::env::var("SEARCH_PROVIDER_ENDPOINT")
             .or_else(|_| std::env::var("OPENSEARCH_ENDPOINT"))
             .unwrap_or_else(|_| "http://localhost:9200".to_string());
@@ -62,6 +257,7 @@ impl OpenSearchConfig {
             api_key,
             timeout: Duration::from_secs(timeout),
             max_retries,
+            compression: Compression::from_env(),
         })
     }
 }
@@ -70,7 +266,8 @@ impl OpenSearchConfig {
 pub struct OpenSearchClient {
     config: OpenSearchConfig,
     http_client: Client,
-    base_url: Url,
+    endpoints: Arc<EndpointPool>,
+    throttle: Arc<Throttle>,
 }
 
 impl OpenSearchClient {
@@ -81,45 +278,48 @@ impl OpenSearchClient {
 
         let http_client = Client::builder()
             .timeout(config.timeout)
+            .retry(RetryPolicy::new(config.max_retries))
+            .accept_compressed(config.compression.is_enabled())
             .default_headers(headers)
             .build()
             .map_err(|e| anyhow::anyhow!("Failed to create HTTP client: {}", e))?;
 
-        let base_url = Url::parse(&config.endpoint)
-            .map_err(|e| anyhow::anyhow!("Invalid endpoint URL: {}", e))?;
+        let endpoints = endpoint_pool::pool(&config.endpoint)?;
 
         Ok(Self {
             config,
             http_client,
-            base_url,
+            endpoints,
+            throttle: rate_limit::throttle("opensearch"),
         })
     }
 
     /// Execute an HTTP request with authentication
-    fn request_sync(&self, method: Method, path: &str, body: Option<Value>) -> Result<reqwest::Response> {
-        let url = self.base_url.join(path)
-            .map_err(|e| anyhow::anyhow!("Failed to build URL: {}", e))?;
+    fn request_sync(&self, method: Method, path: &str, body: Option<Value>) -> Result<golem_search::http::Response> {
+        let request_id = request_id::current_or_generate();
+        debug!("[{}] {} {}", request_id, method, path);
 
-        let mut request = self.http_client.request(method, url);
+        self.throttle.send(|| self.endpoints.send(&method, path, |url| {
+            let mut request = self.http_client
+                .request(method.clone(), url)
+                .header(REQUEST_ID_HEADER, request_id.as_str());
 
-        // Add authentication
-        if let Some(ref api_key) = self.config.api_key {
-            request = request.header(AUTHORIZATION, format!("ApiKey {}", api_key));
-        } else if let (Some(ref username), Some(ref password)) = 
-            (&self.config.username, &self.config.password) {
-            let auth = base64::engine::general_purpose::STANDARD
-                .encode(format!("{}:{}", username, password));
-            request = request.header(AUTHORIZATION, format!("Basic {}", auth));
-        }
-
-        if let Some(body) = body {
-            request = request.json(&body);
-        }
+            // Add authentication
+            if let Some(ref api_key) = self.config.api_key {
+                request = request.header(AUTHORIZATION, format!("ApiKey {}", api_key));
+            } else if let (Some(ref username), Some(ref password)) = 
+                (&self.config.username, &self.config.password) {
+                let auth = base64::engine::general_purpose::STANDARD
+                    .encode(format!("{}:{}", username, password));
+                request = request.header(AUTHORIZATION, format!("Basic {}", auth));
+            }
 
-        let response = request.send()
-            .map_err(|e| anyhow::anyhow!("Request failed: {}", e))?;
+            if let Some(ref body) = body {
+                request = request.json(body);
+            }
 
-        Ok(response)
+            request.send()
+        }))
     }
 
     /// Create an index
@@ -128,7 +328,7 @@ impl OpenSearchClient {
         let response = self.request_sync(Method::PUT, name, Some(body))?;
         
         if response.status().is_success() {
-            let result: Value = response.json()
+            let result: Value = json::from_response(response)
                 .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?;
             Ok(result)
         } else {
@@ -138,12 +338,99 @@ impl OpenSearchClient {
         }
     }
 
+    /// Detect the server version from the root endpoint
+    pub async fn detect_version(&self) -> Result<ServerVersion> {
+        let response = self.request_sync(Method::GET, "", None)?;
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!("Failed to get server info: HTTP {}", response.status()));
+        }
+        
+        let info: Value = json::from_response(response)
+            .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?;
+        info.pointer("/version/number")
+            .and_then(Value::as_str)
+            .and_then(ServerVersion::parse)
+            .ok_or_else(|| anyhow::anyhow!("No version in server info response"))
+    }
+
+    /// Count the documents in an index
+    pub async fn count_documents(&self, index: &str) -> Result<u64> {
+        let path = format!("{}/_count", index);
+        let response = self.request_sync(Method::GET, &path, None)?;
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!("Failed to count documents: HTTP {}", response.status()));
+        }
+        
+        let result: Value = json::from_response(response)
+            .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?;
+        result.pointer("/count")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| anyhow::anyhow!("No count in response"))
+    }
+
+    /// Indexing counters of an index (`GET /{index}/_stats/indexing`)
+    pub async fn index_stats(&self, index: &str) -> Result<Value> {
+        let path = format!("{}/_stats/indexing", index);
+        let response = self.request_sync(Method::GET, &path, None)?;
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!("Failed to get index stats: HTTP {}", response.status()));
+        }
+        
+        json::from_response(response)
+            .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))
+    }
+
+    /// Cluster state changes queued on the cluster manager node
+    pub async fn pending_tasks(&self) -> Result<Value> {
+        let response = self.request_sync(Method::GET, "_cluster/pending_tasks", None)?;
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!("Failed to list pending tasks: HTTP {}", response.status()));
+        }
+        
+        json::from_response(response)
+            .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))
+    }
+
+    /// Count the documents matching a query
+    pub async fn count_matching(&self, index: &str, query: Value) -> Result<u64> {
+        let path = format!("{}/_count", index);
+        let response = self.request_sync(Method::POST, &path, Some(json!({ "query": query })))?;
+        if !response.status().is_success() {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(anyhow::anyhow!("Failed to count documents: {}", error_text));
+        }
+
+        let result: Value = json::from_response(response)
+            .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?;
+        result.pointer("/count")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| anyhow::anyhow!("No count in response"))
+    }
+
+    /// Delete the documents matching a query, returning how many were deleted
+    pub async fn delete_by_query(&self, index: &str, query: Value) -> Result<u64> {
+        let path = format!("{}/_delete_by_query?conflicts=proceed&refresh=true", index);
+        let response = self.request_sync(Method::POST, &path, Some(json!({ "query": query })))?;
+        if !response.status().is_success() {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(anyhow::anyhow!("Failed to delete by query: {}", error_text));
+        }
+
+        let result: Value = json::from_response(response)
+            .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?;
+        result.pointer("/deleted")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| anyhow::anyhow!("No deleted count in response"))
+    }
+
     /// Delete an index
     pub async fn delete_index(&self, name: &str) -> Result<Value> {
         let response = self.request_sync(Method::DELETE, name, None)?;
         
         if response.status().is_success() {
-            let result: Value = response.json()
+            let result: Value = json::from_response(response)
                 .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?;
             Ok(result)
         } else {
@@ -153,12 +440,117 @@ impl OpenSearchClient {
         }
     }
 
+    /// Fetch several documents by ID in one request, with only the `source` fields when given
+    pub async fn mget(&self, index: &str, ids: &[String], source: Option<&[String]>) -> Result<Value> {
+        let path = format!("{}/_mget{}", index, source_includes(source));
+        let response = self.request_sync(Method::POST, &path, Some(json!({ "ids": ids })))?;
+        
+        if response.status().is_success() {
+            let result: Value = json::from_response(response)
+                .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?;
+            Ok(result)
+        } else {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            Err(anyhow::anyhow!("Failed to get documents: {}", error_text))
+        }
+    }
+
+    /// Close an index
+    pub async fn close_index(&self, index: &str) -> Result<()> {
+        let path = format!("{}/_close", index);
+        let response = self.request_sync(Method::POST, &path, None)?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            Err(anyhow::anyhow!("Failed to close index: {}", error_text))
+        }
+    }
+
+    /// Open a closed index
+    pub async fn open_index(&self, index: &str) -> Result<()> {
+        let path = format!("{}/_open", index);
+        let response = self.request_sync(Method::POST, &path, None)?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            Err(anyhow::anyhow!("Failed to open index: {}", error_text))
+        }
+    }
+
+    /// Snapshot indices into a registered repository, waiting until the snapshot is complete
+    pub async fn create_snapshot(&self, repository: &str, snapshot: &str, indices: &str, metadata: Value) -> Result<Value> {
+        let path = format!("_snapshot/{}/{}?wait_for_completion=true", repository, snapshot);
+        let body = json!({ "indices": indices, "include_global_state": false, "metadata": metadata });
+        let response = self.request_sync(Method::PUT, &path, Some(body))?;
+
+        if response.status().is_success() {
+            json::from_response(response)
+                .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))
+        } else {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            Err(anyhow::anyhow!("Failed to create snapshot: {}", error_text))
+        }
+    }
+
+    /// Describe a snapshot in a repository
+    pub async fn get_snapshot(&self, repository: &str, snapshot: &str) -> Result<Value> {
+        let path = format!("_snapshot/{}/{}", repository, snapshot);
+        let response = self.request_sync(Method::GET, &path, None)?;
+
+        if response.status().is_success() {
+            json::from_response(response)
+                .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))
+        } else {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            Err(anyhow::anyhow!("Failed to get snapshot: {}", error_text))
+        }
+    }
+
+    /// Restore indices from a snapshot, waiting until they are recovered
+    pub async fn restore_snapshot(&self, repository: &str, snapshot: &str, indices: &[String]) -> Result<Value> {
+        let path = format!("_snapshot/{}/{}/_restore?wait_for_completion=true", repository, snapshot);
+        let body = json!({ "indices": indices.join(","), "include_global_state": false });
+        let response = self.request_sync(Method::POST, &path, Some(body))?;
+
+        if response.status().is_success() {
+            json::from_response(response)
+                .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))
+        } else {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            Err(anyhow::anyhow!("Failed to restore snapshot: {}", error_text))
+        }
+    }
+
+    /// Refresh an index, making all acknowledged writes searchable
+    pub async fn refresh_index(&self, name: &str) -> Result<()> {
+        let path = format!("{}/_refresh", name);
+        let response = self.request_sync(Method::POST, &path, None)?;
+        
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            Err(anyhow::anyhow!("Failed to refresh index: {}", error_text))
+        }
+    }
+
     /// List all indexes
     pub async fn list_indexes(&self) -> Result<Vec<String>> {
         let response = self.request_sync(Method::GET, "_cat/indices?format=json", None)?;
         
         if response.status().is_success() {
-            let indices: Vec<Value> = response.json()
+            let indices: Vec<Value> = json::from_response(response)
                 .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?;
             
             let names = indices.into_iter()
@@ -178,12 +570,14 @@ impl OpenSearchClient {
     }
 
     /// Index a document
-    pub async fn index_document(&self, index: &str, id: &str, document: Value) -> Result<Value> {
-        let path = format!("{}/_doc/{}", index, id);
+    ///
+    /// `params` are URL parameters such as `routing` and `pipeline`.
+    pub async fn index_document(&self, index: &str, id: &str, document: Value, params: &[(String, String)]) -> Result<Value> {
+        let path = routing::with_query_params(&format!("{}/_doc/{}", index, http::path_segment(id)), params);
         let response = self.request_sync(Method::PUT, &path, Some(document))?;
         
         if response.status().is_success() {
-            let result: Value = response.json()
+            let result: Value = json::from_response(response)
                 .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?;
             Ok(result)
         } else {
@@ -193,13 +587,13 @@ impl OpenSearchClient {
         }
     }
 
-    /// Get a document by ID
-    pub async fn get_document(&self, index: &str, id: &str) -> Result<Option<Value>> {
-        let path = format!("{}/_doc/{}", index, id);
+    /// Get a document by ID, with only the `source` fields when given
+    pub async fn get_document(&self, index: &str, id: &str, source: Option<&[String]>, routing: Option<&str>) -> Result<Option<Value>> {
+        let path = routing::with_routing(&format!("{}/_doc/{}{}", index, http::path_segment(id), source_includes(source)), routing);
         let response = self.request_sync(Method::GET, &path, None)?;
         
         if response.status().is_success() {
-            let result: Value = response.json()
+            let result: Value = json::from_response(response)
                 .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?;
             Ok(Some(result))
         } else if response.status().as_u16() == 404 {
@@ -211,13 +605,23 @@ impl OpenSearchClient {
         }
     }
 
-    /// Delete a document by ID
-    pub async fn delete_document(&self, index: &str, id: &str) -> Result<Value> {
-        let path = format!("{}/_doc/{}", index, id);
+    /// Check whether a document exists without fetching its source
+    ///
+    /// A missing index is an error rather than an absent document.
+    pub async fn document_exists(&self, index: &str, id: &str) -> Result<bool> {
+        let path = format!("{}/_doc/{}?_source=false", index, http::path_segment(id));
+        let response = self.request_sync(Method::GET, &path, None)?;
+        let status = response.status().as_u16();
+        document_presence(status, &response.text().unwrap_or_default())
+    }
+
+    /// Delete a document by ID, from the shard of `routing` when given
+    pub async fn delete_document(&self, index: &str, id: &str, routing: Option<&str>) -> Result<Value> {
+        let path = routing::with_routing(&format!("{}/_doc/{}", index, http::path_segment(id)), routing);
         let response = self.request_sync(Method::DELETE, &path, None)?;
         
         if response.status().is_success() {
-            let result: Value = response.json()
+            let result: Value = json::from_response(response)
                 .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?;
             Ok(result)
         } else {
@@ -228,40 +632,55 @@ impl OpenSearchClient {
     }
 
     /// Bulk operation
-    pub async fn bulk(&self, operations: Vec<Value>) -> Result<Value> {
-        let mut body = String::new();
-        for op in operations {
-            body.push_str(&serde_json::to_string(&op)?);
-            body.push('\n');
-        }
+    pub async fn bulk(&self, operations: impl IntoIterator<Item = Value>) -> Result<Value> {
+        let mut writer = NdjsonWriter::new(self.config.compression);
+        writer.extend(operations)?;
+        let body = writer.finish()?;
 
-        let url = self.base_url.join("_bulk")?;
-        let response = self.http_client
-            .post(url)
-            .header(CONTENT_TYPE, "application/x-ndjson")
-            .body(body)
-            .send()?;
+        // Bulk requests are not idempotent, so the body is sent at most once
+        let encoding = body.content_encoding();
+        let request_id = request_id::current_or_generate();
+        let mut bytes = Some(body.bytes);
+        let response = self.throttle.send(|| self.endpoints.send(&Method::POST, "_bulk", |url| {
+            let mut request = self.http_client
+                .post(url)
+                .header(CONTENT_TYPE, "application/x-ndjson")
+                .header(REQUEST_ID_HEADER, request_id.as_str());
+            if let Some(encoding) = encoding {
+                request = request.header(CONTENT_ENCODING, encoding);
+            }
+            request.body(bytes.take().unwrap_or_default()).send()
+        }))?;
 
-        if response.status().is_success() {
-            let result: Value = response.json()
+        let status = response.status();
+        if status.is_success() {
+            let result: Value = json::from_response(response)
                 .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?;
             Ok(result)
         } else {
             let error_text = response.text()
                 .unwrap_or_else(|_| "Unknown error".to_string());
-            Err(anyhow::anyhow!("Bulk operation failed: {}", error_text))
+            Err(anyhow::anyhow!("Bulk operation failed ({}): {}", status, error_text))
         }
     }
 
     /// Search documents
     pub async fn search(&self, index: &str, query: Value) -> Result<Value> {
-        let path = format!("{}/_search", index);
+        let body = self.search_raw(index, query, &[]).await?;
+        json::from_str(&body)
+            .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))
+    }
+
+    /// Search documents, returning the unparsed response body
+    ///
+    /// `params` are URL parameters such as `routing` and `preference`.
+    pub async fn search_raw(&self, index: &str, query: Value, params: &[(String, String)]) -> Result<String> {
+        let path = routing::with_query_params(&format!("{}/_search", index), params);
         let response = self.request_sync(Method::POST, &path, Some(query))?;
         
         if response.status().is_success() {
-            let result: Value = response.json()
-                .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?;
-            Ok(result)
+            response.text()
+                .map_err(|e| anyhow::anyhow!("Failed to read response: {}", e))
         } else {
             let error_text = response.text()
                 .unwrap_or_else(|_| "Unknown error".to_string());
@@ -269,13 +688,58 @@ impl OpenSearchClient {
         }
     }
 
+    /// Open a scroll over every document of an index, returning the first page
+    pub async fn open_scroll(&self, index: &str, size: usize, keep_alive: &str) -> Result<Value> {
+        let path = format!("{}/_search?scroll={}", index, keep_alive);
+        let query = json!({ "size": size, "sort": ["_doc"], "query": { "match_all": {} } });
+        let response = self.request_sync(Method::POST, &path, Some(query))?;
+
+        if response.status().is_success() {
+            json::from_response(response)
+                .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))
+        } else {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            Err(anyhow::anyhow!("Failed to open scroll: {}", error_text))
+        }
+    }
+
+    /// Fetch the next page of a scroll
+    pub async fn scroll(&self, scroll_id: &str, keep_alive: &str) -> Result<Value> {
+        let body = json!({ "scroll": keep_alive, "scroll_id": scroll_id });
+        let response = self.request_sync(Method::POST, "_search/scroll", Some(body))?;
+
+        if response.status().is_success() {
+            json::from_response(response)
+                .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))
+        } else {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            Err(anyhow::anyhow!("Scroll failed: {}", error_text))
+        }
+    }
+
+    /// Release a scroll before it expires
+    pub async fn clear_scroll(&self, scroll_id: &str) -> Result<()> {
+        let body = json!({ "scroll_id": scroll_id });
+        let response = self.request_sync(Method::DELETE, "_search/scroll", Some(body))?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            Err(anyhow::anyhow!("Failed to clear scroll: {}", error_text))
+        }
+    }
+
     /// Get index mapping
     pub async fn get_mapping(&self, index: &str) -> Result<Value> {
         let path = format!("{}/_mapping", index);
         let response = self.request_sync(Method::GET, &path, None)?;
         
         if response.status().is_success() {
-            let result: Value = response.json()
+            let result: Value = json::from_response(response)
                 .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?;
             Ok(result)
         } else {
@@ -291,7 +755,7 @@ impl OpenSearchClient {
         let response = self.request_sync(Method::PUT, &path, Some(mapping))?;
         
         if response.status().is_success() {
-            let result: Value = response.json()
+            let result: Value = json::from_response(response)
                 .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?;
             Ok(result)
         } else {
@@ -300,22 +764,139 @@ impl OpenSearchClient {
             Err(anyhow::anyhow!("Failed to put mapping: {}", error_text))
         }
     }
+
+    /// Get index settings
+    pub async fn get_settings(&self, index: &str) -> Result<Value> {
+        let path = format!("{}/_settings", index);
+        let response = self.request_sync(Method::GET, &path, None)?;
+
+        if response.status().is_success() {
+            json::from_response(response)
+                .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))
+        } else {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            Err(anyhow::anyhow!("Failed to get settings: {}", error_text))
+        }
+    }
+
+    /// Put index settings (static settings such as analysis require a closed index)
+    pub async fn put_settings(&self, index: &str, settings: Value) -> Result<Value> {
+        let path = format!("{}/_settings", index);
+        let response = self.request_sync(Method::PUT, &path, Some(settings))?;
+
+        if response.status().is_success() {
+            json::from_response(response)
+                .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))
+        } else {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            Err(anyhow::anyhow!("Failed to put settings: {}", error_text))
+        }
+    }
+
+    /// Indexes behind an alias with their alias settings, `None` when there is no such alias
+    pub async fn get_alias(&self, alias: &str) -> Result<Option<Value>> {
+        let path = format!("_alias/{}", alias);
+        let response = self.request_sync(Method::GET, &path, None)?;
+
+        if response.status().is_success() {
+            json::from_response(response)
+                .map(Some)
+                .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))
+        } else if response.status().as_u16() == 404 {
+            Ok(None)
+        } else {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            Err(anyhow::anyhow!("Failed to get alias: {}", error_text))
+        }
+    }
+
+    /// Roll the write index of an alias over, or with `dry_run` only evaluate the conditions
+    pub async fn rollover(&self, alias: &str, body: Value, dry_run: bool) -> Result<Value> {
+        let path = format!("{}/_rollover{}", alias, if dry_run { "?dry_run=true" } else { "" });
+        let response = self.request_sync(Method::POST, &path, Some(body))?;
+
+        if response.status().is_success() {
+            json::from_response(response)
+                .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))
+        } else {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            Err(anyhow::anyhow!("Failed to roll over alias: {}", error_text))
+        }
+    }
+
+    /// Apply alias actions in one atomic update
+    pub async fn update_aliases(&self, actions: Value) -> Result<()> {
+        let response = self.request_sync(Method::POST, "_aliases", Some(actions))?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            Err(anyhow::anyhow!("Failed to update aliases: {}", error_text))
+        }
+    }
+}
+
+/// Mapping update setting `_meta` keys; a mapping update replaces the whole `_meta`, so the other keys are copied
+fn mapping_meta_update(mapping: &Value, index: &str, entries: &[(&str, Option<Value>)]) -> Value {
+    let mut meta = mapping
+        .get(index)
+        .unwrap_or(mapping)
+        .pointer("/mappings/_meta")
+        .and_then(Value::as_object)
+        .cloned()
+        .unwrap_or_default();
+    for (key, value) in entries {
+        match value {
+            Some(value) => meta.insert(key.to_string(), value.clone()),
+            None => meta.remove(*key),
+        };
+    }
+    json!({ "_meta": meta })
 }
 
 /// Map OpenSearch errors to SearchError
+/// Whether a `GET _doc` answer found its document
+///
+/// Both a missing document and a missing index answer 404, but only the
+/// index's carries an `error`, such as `index_not_found_exception`.
+fn document_presence(status: u16, body: &str) -> Result<bool> {
+    match status {
+        200..=299 => Ok(true),
+        404 => {
+            let answer: Value = serde_json::from_str(body).unwrap_or_default();
+            match answer["error"]["type"].as_str() {
+                Some(kind) => Err(anyhow::anyhow!("{}: HTTP 404 {}", kind, body)),
+                None => Ok(false),
+            }
+        }
+        status => Err(anyhow::anyhow!("Failed to check document existence: HTTP {} {}", status, body)),
+    }
+}
+
 pub fn map_opensearch_error(error: anyhow::Error) -> SearchError {
+    if let Some(rejection) = circuit_breaker::open_circuit(&error).or_else(|| rate_limit::rate_limited(&error)) {
+        return rejection;
+    }
     let error_string = error.to_string();
     
-    if error_string.contains("index_not_found") || error_string.contains("404") {
-        SearchError::IndexNotFound(error_string)
+    if error_string.contains("resource_already_exists_exception") {
+        SearchError::IndexAlreadyExists(request_id::tag(error_string))
+    } else if error_string.contains("index_not_found") || error_string.contains("404") {
+        SearchError::IndexNotFound(request_id::tag(error_string))
     } else if error_string.contains("parsing_exception") || error_string.contains("400") {
-        SearchError::InvalidQuery(error_string)
+        SearchError::InvalidQuery(request_id::tag(error_string))
     } else if error_string.contains("timeout") {
-        SearchError::Timeout
+        deadline::timeout_error()
     } else if error_string.contains("rate") || error_string.contains("429") {
-        SearchError::RateLimited
+        SearchError::RateLimited { retry_after_ms: None }
     } else {
-        SearchError::Internal(error_string)
+        SearchError::Internal(request_id::tag(error_string))
     }
 }
 
@@ -330,7 +911,7 @@ impl OpenSearchProvider {
         let config = OpenSearchConfig::from_env()
             .map_err(|e| {
                 error!("Failed to load OpenSearch configuration: {}", e);
-                SearchError::Internal(format!("Configuration error: {}", e))
+                SearchError::Internal(format!("Configuration error: {} (diagnose_config lists every variable read)", e))
             })?;
 
         let client = OpenSearchClient::new(config)
@@ -365,6 +946,8 @@ impl OpenSearchProvider {
                 FieldType::Boolean,
                 FieldType::Date,
                 FieldType::GeoPoint,
+                FieldType::Int64,
+                FieldType::Decimal,
             ],
             provider_features: {
                 let mut features = HashMap::new();
@@ -376,10 +959,18 @@ impl OpenSearchProvider {
         }
     }
 
+    /// Server version, detected once per worker and endpoint
+    pub async fn server_version(&self) -> Option<ServerVersion> {
+        version::cached_version("opensearch", self.client.endpoints.primary().as_str(), || self.client.detect_version()).await
+    }
+
+    /// Capabilities, versions, configured limits and degradation, for orchestrators choosing a provider
+    pub async fn describe(&self) -> ProviderDescription {
+        describe::describe(opensearch_capability_matrix(), self.server_version().await)
+    }
+
     /// Create an index
     pub async fn create_index(&self, name: &str, schema: Option<&Schema>) -> SearchResult<()> {
-        info!("Creating OpenSearch index: {}", name);
-
         let settings = if let Some(schema) = schema {
             // Convert schema to OpenSearch mapping (same as ElasticSearch)
             let mapping = self.schema_to_mapping(schema)?;
@@ -387,43 +978,309 @@ impl OpenSearchProvider {
         } else {
             None
         };
+        self.create_index_with_body(name, schema, settings).await
+    }
 
-        self.client
-            .create_index(name, settings)
-            .await
-            .map_err(|e| {
-                error!("Failed to create index {}: {}", name, e);
-                map_opensearch_error(e)
-            })?;
+    /// Create an index from a `golem_search::templates` blueprint
+    ///
+    /// The template's synonyms and stop words become a search analyzer on its
+    /// standard-analyzed text fields. OpenSearch has no ranking rules, so the
+    /// template ranking is left to query-time sorting.
+    pub async fn create_index_from_template(
+        &self,
+        name: &str,
+        template: &str,
+        overrides: Option<&str>,
+    ) -> SearchResult<IndexTemplate> {
+        let template = templates::instantiate(template, &TemplateOverrides::from_json(overrides)?)?;
+        let mut body = self.schema_to_mapping(&template.schema)?;
+        if let Some(analysis) = template_analysis(&template) {
+            body["settings"] = analysis;
+            if let Some(properties) = body.pointer_mut("/mappings/properties").and_then(Value::as_object_mut) {
+                for mapping in properties.values_mut().filter(|m| m["analyzer"] == "standard") {
+                    mapping["search_analyzer"] = json!(TEMPLATE_SEARCH_ANALYZER);
+                }
+            }
+        }
+        if !template.ranking.is_empty() {
+            warn!("OpenSearch has no ranking rules; sort on {:?} at query time", template.ranking);
+        }
 
-        info!("Successfully created OpenSearch index: {}", name);
-        Ok(())
+        self.create_index_with_body(name, Some(&template.schema), Some(body)).await?;
+        Ok(template)
     }
 
-    /// Convert schema to OpenSearch mapping (reuse ElasticSearch logic)
-    fn schema_to_mapping(&self, schema: &Schema) -> SearchResult<Value> {
-        let mut properties = serde_json::Map::new();
-        
-        for field in &schema.fields {
-            let field_mapping = match field.field_type {
-                FieldType::Text => {
-                    json!({
-                        "type": "text",
-                        "index": field.index,
-                        "analyzer": "standard"
-                    })
-                }
-                FieldType::Keyword => {
-                    json!({
-                        "type": "keyword",
-                        "index": field.index
-                    })
-                }
-                FieldType::Integer => {
-                    json!({
-                        "type": "integer",
-                        "index": field.index
-                    })
+    async fn create_index_with_body(&self, name: &str, schema: Option<&Schema>, settings: Option<Value>) -> SearchResult<()> {
+        audit::audited("opensearch", "create_index", name, json!({ "schema": schema }), async {
+            telemetry::traced("opensearch", "create_index", name, async {
+                info!("Creating OpenSearch index: {}", name);
+
+                self.client
+                    .create_index(name, settings)
+                    .await
+                    .map_err(|e| {
+                        error!("Failed to create index {}: {}", name, e);
+                        map_opensearch_error(e)
+                    })?;
+
+                info!("Successfully created OpenSearch index: {}", name);
+                if let Some(schema) = schema {
+                    numeric::remember("opensearch", name, schema);
+                }
+                events::emit(LifecycleEventKind::IndexCreated, "opensearch", name, Value::Null);
+                Ok(())
+            })
+            .await
+        })
+        .await
+    }
+
+    /// Create an index if it does not exist, otherwise add missing mapped fields
+    ///
+    /// Returns `true` when the index was created. Mapping changes OpenSearch
+    /// cannot apply in place are rejected rather than silently skipped.
+    pub async fn ensure_index(&self, name: &str, schema: Option<&Schema>) -> SearchResult<bool> {
+        audit::audited("opensearch", "ensure_index", name, json!({ "schema": schema }), async {
+            match self.create_index(name, schema).await {
+                Ok(()) => return Ok(true),
+                Err(SearchError::IndexAlreadyExists(_)) => debug!("Index {} already exists, checking mapping", name),
+                Err(e) => return Err(e),
+            }
+
+            let Some(desired) = schema else {
+                return Ok(false);
+            };
+
+            let mapping = self.client.get_mapping(name).await.map_err(map_opensearch_error)?;
+            let current = self.mapping_to_schema(&mapping, name);
+            let report = check_schema_compatibility("opensearch", &current, desired);
+            report.ensure_applicable(name)?;
+            let added = report.added();
+            if !added.is_empty() {
+                info!("Adding fields {:?} to index {}", added, name);
+                let mut added = self.schema_to_mapping(&report.patch_schema(desired))?;
+                // The patch only covers new fields, so its _meta would drop the metadata of the others
+                if let Some(mapping) = added["mappings"].as_object_mut() {
+                    mapping.remove("_meta");
+                }
+                self.client
+                    .put_mapping(name, added["mappings"].take())
+                    .await
+                    .map_err(map_opensearch_error)?;
+                events::emit(LifecycleEventKind::SchemaUpdated, "opensearch", name, json!({ "fields": report.added() }));
+            }
+            let desired_meta = [(UI_META_KEY, ui_metadata::to_meta(desired)), (DECIMAL_META_KEY, numeric::to_meta(desired))];
+            let current_meta = [(UI_META_KEY, ui_metadata::to_meta(&current)), (DECIMAL_META_KEY, numeric::to_meta(&current))];
+            if current_meta != desired_meta {
+                self.client
+                    .put_mapping(name, mapping_meta_update(&mapping, name, &desired_meta))
+                    .await
+                    .map_err(map_opensearch_error)?;
+            }
+            numeric::remember("opensearch", name, desired);
+            cache::global().remove(CacheKind::Schema, &cache::index_key("opensearch", name));
+            Ok(false)
+        })
+        .await
+    }
+
+    /// Read field names and types from a `_mapping` response
+    fn mapping_to_schema(&self, mapping: &Value, index: &str) -> Schema {
+        let properties = mapping
+            .get(index)
+            .unwrap_or(mapping)
+            .pointer("/mappings/properties")
+            .and_then(Value::as_object);
+
+        let fields = properties
+            .into_iter()
+            .flatten()
+            .map(|(name, def)| {
+                let mapped_type = def.get("type").and_then(Value::as_str).unwrap_or("object");
+                let field_type = match mapped_type {
+                    "keyword" => FieldType::Keyword,
+                    "integer" | "short" | "byte" => FieldType::Integer,
+                    "long" => FieldType::Int64,
+                    "float" | "double" | "half_float" | "scaled_float" => FieldType::Float,
+                    "boolean" => FieldType::Boolean,
+                    "date" => FieldType::Date,
+                    "geo_point" => FieldType::GeoPoint,
+                    _ => FieldType::Text,
+                };
+                SchemaField {
+                    name: name.clone(),
+                    field_type,
+                    required: false,
+                    facet: mapped_type == "keyword",
+                    sort: mapped_type != "text",
+                    index: def.get("index").and_then(Value::as_bool).unwrap_or(true),
+                    analysis: def.get("analyzer").and_then(Value::as_str).and_then(FieldAnalysis::from_lucene_analyzer),
+                    ui: None,
+                }
+            })
+            .collect();
+
+        let mut schema = Schema { fields, primary_key: Some("_id".to_string()) };
+        let meta = mapping.get(index).unwrap_or(mapping).pointer("/mappings/_meta");
+        ui_metadata::apply_meta(&mut schema, meta.and_then(|m| m.get(UI_META_KEY)));
+        numeric::apply_meta(&mut schema, meta.and_then(|m| m.get(DECIMAL_META_KEY)));
+        schema
+    }
+
+    /// Schema of an index, read from its mapping
+    pub async fn get_schema(&self, index: &str) -> SearchResult<Schema> {
+        let cache_key = cache::index_key("opensearch", index);
+        if let Some(schema) = cache::global().get::<Schema>(CacheKind::Schema, &cache_key) {
+            return Ok((*schema).clone());
+        }
+
+        let mapping = self.client.get_mapping(index).await.map_err(map_opensearch_error)?;
+        let schema = self.mapping_to_schema(&mapping, index);
+        numeric::remember("opensearch", index, &schema);
+        let size = cache::schema_size(schema.fields.iter().map(|f| f.name.as_str()));
+        cache::global().insert(CacheKind::Schema, &cache_key, schema.clone(), size);
+        Ok(schema)
+    }
+
+    /// Export schema, synonyms and stop words as a JSON settings snapshot
+    pub async fn export_settings(&self, index: &str) -> SearchResult<String> {
+        debug!("Exporting settings for index {}", index);
+
+        let mut snapshot = self.current_settings(index).await?;
+        snapshot.schema = Some(self.get_schema(index).await?);
+        snapshot.normalized().to_json()
+    }
+
+    /// Synonyms and stop words currently applied to an index
+    async fn current_settings(&self, index: &str) -> SearchResult<IndexSettings> {
+        let settings = self.client.get_settings(index).await.map_err(|e| {
+            error!("Failed to get settings for index {}: {}", index, e);
+            map_opensearch_error(e)
+        })?;
+        Ok(settings_to_snapshot(&settings, index))
+    }
+
+    /// Apply a JSON settings snapshot, reopening the index only when analysis changed
+    ///
+    /// Synonyms and stop words go into the search analyzer of index templates,
+    /// which the standard-analyzed text fields are pointed at. OpenSearch has
+    /// no ranking rules and keeps no normalization, so those are skipped.
+    pub async fn apply_settings(&self, index: &str, settings_json: &str) -> SearchResult<()> {
+        audit::audited("opensearch", "apply_settings", index, audit::settings_details(settings_json), async {
+            info!("Applying settings to index {}", index);
+
+            let desired = IndexSettings::from_json(settings_json)?;
+
+            if let Some(schema) = &desired.schema {
+                self.ensure_index(index, Some(schema)).await?;
+            }
+            if !desired.ranking_rules.is_empty() {
+                warn!("OpenSearch has no ranking rules setting; ignoring {} rules", desired.ranking_rules.len());
+            }
+            if desired.normalization.is_some() {
+                warn!("OpenSearch keeps no normalization settings; set it in the search config instead");
+            }
+
+            let current = self.current_settings(index).await?;
+            if current.synonyms == desired.synonyms && current.stop_words == desired.stop_words {
+                debug!("Analysis settings for index {} already up to date", index);
+                return Ok(());
+            }
+
+            // Analysis settings are static and can only be changed on a closed index
+            self.client.close_index(index).await.map_err(map_opensearch_error)?;
+            let result = self.client
+                .put_settings(index, search_analysis(&desired.synonyms, &desired.stop_words))
+                .await;
+            self.client.open_index(index).await.map_err(map_opensearch_error)?;
+            result.map_err(|e| {
+                error!("Failed to update analysis settings for index {}: {}", index, e);
+                map_opensearch_error(e)
+            })?;
+
+            let mapping = self.client.get_mapping(index).await.map_err(map_opensearch_error)?;
+            let mut text_fields = serde_json::Map::new();
+            if let Some(properties) = mapping[index]["mappings"]["properties"].as_object() {
+                for (field, definition) in properties.iter().filter(|(_, d)| d["analyzer"] == "standard" && d["search_analyzer"] != TEMPLATE_SEARCH_ANALYZER) {
+                    let mut definition = definition.clone();
+                    definition["search_analyzer"] = json!(TEMPLATE_SEARCH_ANALYZER);
+                    text_fields.insert(field.clone(), definition);
+                }
+            }
+            if !text_fields.is_empty() {
+                self.client
+                    .put_mapping(index, json!({ "properties": text_fields }))
+                    .await
+                    .map_err(map_opensearch_error)?;
+            }
+
+            info!("Successfully applied settings to index {}", index);
+            Ok(())
+        })
+        .await
+    }
+
+    /// Report drift between the index and a desired settings snapshot, optionally reconciling it
+    pub async fn diff_settings(&self, index: &str, desired_json: &str, reconcile: bool) -> SearchResult<SettingsDiff> {
+        let desired = IndexSettings::from_json(desired_json)?;
+
+        let mut current = self.current_settings(index).await?;
+        current.schema = Some(self.get_schema(index).await?);
+
+        let mut diff = current.diff(&desired);
+
+        if reconcile && !diff.is_empty() {
+            info!("Reconciling {} setting changes on index {}", diff.changes.len(), index);
+            self.apply_settings(index, desired_json).await?;
+            diff.reconciled = true;
+        }
+
+        Ok(diff)
+    }
+
+    /// Convert schema to OpenSearch mapping (reuse ElasticSearch logic)
+    fn schema_to_mapping(&self, schema: &Schema) -> SearchResult<Value> {
+        let mut properties = serde_json::Map::new();
+        
+        for warning in unsupported_analysis("opensearch", schema) {
+            warn!("{}", warning);
+        }
+        
+        for field in &schema.fields {
+            let field_mapping = match field.field_type {
+                FieldType::Text => {
+                    // CJK analyzers need the matching analysis plugin on the cluster
+                    let analysis = field.analysis.clone().unwrap_or_default();
+                    json!({
+                        "type": "text",
+                        "index": field.index,
+                        "analyzer": analysis.lucene_analyzer()
+                    })
+                }
+                FieldType::Keyword => {
+                    json!({
+                        "type": "keyword",
+                        "index": field.index
+                    })
+                }
+                FieldType::Integer => {
+                    json!({
+                        "type": "integer",
+                        "index": field.index
+                    })
+                }
+                FieldType::Int64 => {
+                    json!({
+                        "type": "long",
+                        "index": field.index
+                    })
+                }
+                // Kept as the decimal text, so no digits are lost
+                FieldType::Decimal => {
+                    json!({
+                        "type": "keyword",
+                        "index": field.index
+                    })
                 }
                 FieldType::Float => {
                     json!({
@@ -455,11 +1312,19 @@ impl OpenSearchProvider {
             properties.insert(field.name.clone(), field_mapping);
         }
         
-        Ok(json!({
+        let mut mapping = json!({
             "mappings": {
                 "properties": properties
             }
-        }))
+        });
+        let meta: serde_json::Map<String, Value> = [(UI_META_KEY, ui_metadata::to_meta(schema)), (DECIMAL_META_KEY, numeric::to_meta(schema))]
+            .into_iter()
+            .filter_map(|(key, value)| Some((key.to_string(), value?)))
+            .collect();
+        if !meta.is_empty() {
+            mapping["mappings"]["_meta"] = Value::Object(meta);
+        }
+        Ok(mapping)
     }
 
     /// Convert query to OpenSearch format (reuse ElasticSearch logic)
@@ -473,36 +1338,59 @@ impl OpenSearchProvider {
             }
         });
         
-        // Add main query
-        if let Some(ref q) = query.q {
-            if !q.trim().is_empty() {
-                let query_part = json!({
-                    "multi_match": {
-                        "query": q,
-                        "type": "best_fields",
-                        "operator": "or"
-                    }
-                });
-                opensearch_query["query"]["bool"]["must"]
-                    .as_array_mut()
-                    .unwrap()
-                    .push(query_part);
+        // Add main query; match_all scores every hit 1.0 like the other providers' placeholder search
+        let query_part = match query.text() {
+            QueryText::MatchAll => json!({ "match_all": {} }),
+            QueryText::Text(q) => {
+                let text = match query.config.as_ref().and_then(|c| c.normalization) {
+                    Some(normalization) => normalization.prepare_query("opensearch", q),
+                    None => q.to_string(),
+                };
+                let parsed = ParsedQuery::parse(&text);
+                parsed.check_patterns(&PatternLimits::from_env())?;
+                let mut query_part = if parsed.has_operators() {
+                    parsed.to_lucene_query()
+                } else {
+                    json!({
+                        "multi_match": {
+                            "query": text,
+                            "type": "best_fields",
+                            "operator": "or"
+                        }
+                    })
+                };
+                if let Some(fuzziness) = query.config.as_ref().and_then(|c| c.fuzziness) {
+                    fuzziness.apply_to_lucene(&mut query_part);
+                }
+                if let Some(minimum) = query.config.as_ref().and_then(|c| c.minimum_should_match) {
+                    minimum.apply_to_lucene(&mut query_part);
+                }
+                query_part
             }
-        }
+        };
+        opensearch_query["query"]["bool"]["must"]
+            .as_array_mut()
+            .unwrap()
+            .push(query_part);
         
-        // Add filters
+        // Add filters: "field:value" terms, Lucene-lite expressions, geo polygons or ID sets
         for filter in &query.filters {
-            if let Some((field, value)) = filter.split_once(':') {
-                let filter_part = json!({
-                    "term": {
-                        field: value
-                    }
-                });
-                opensearch_query["query"]["bool"]["filter"]
-                    .as_array_mut()
-                    .unwrap()
-                    .push(filter_part);
-            }
+            let clause = match GeoPolygonFilter::parse(filter) {
+                Some(polygons) => polygons.map(|p| p.to_geo_polygon_query()),
+                None => match IdSetFilter::parse(filter) {
+                    Some(ids) => ids.map(|ids| ids.to_terms_query()),
+                    None => lucene_filter_clause(filter),
+                },
+            };
+            opensearch_query["query"]["bool"]["filter"]
+                .as_array_mut()
+                .unwrap()
+                .push(clause?);
+        }
+        
+        // A distance sort orders by `_geo_distance`, whose sort values are the hits' distances
+        if let Some(sort) = geo::distance_sort(&query.sort)? {
+            opensearch_query["sort"] = json!([sort.to_lucene_sort()]);
         }
         
         // Add pagination
@@ -518,83 +1406,316 @@ impl OpenSearchProvider {
             opensearch_query["size"] = json!(query.per_page.unwrap_or(10));
         }
         
+        // Facets become aggregations: terms per value, range buckets, histograms or stats
+        if !query.facets.is_empty() {
+            let mut aggs = serde_json::Map::new();
+            for facet in parse_facets(&query.facets)? {
+                aggs.insert(facet.aggregation_name(), facet.to_lucene_aggregation());
+            }
+            opensearch_query["aggs"] = json!(aggs);
+        }
+        
+        // Random sampling through function_score: same matches, random order
+        if let Some(sample) = query.config.as_ref().and_then(|c| c.sample) {
+            let matching = opensearch_query["query"].take();
+            opensearch_query["query"] = random_score_query(matching, random_seed());
+            opensearch_query["from"] = json!(0);
+            opensearch_query["size"] = json!(sample);
+        }
+        
+        // Ask for Lucene scoring trees when the caller wants to debug relevance
+        if query.config.as_ref().is_some_and(|c| c.explain) {
+            opensearch_query["explain"] = json!(true);
+        }
+        
+        // Totals are capped at 10,000 unless every match is counted
+        if query.config.as_ref().is_some_and(|c| c.exact_total) {
+            opensearch_query["track_total_hits"] = json!(true);
+        }
+        
+        // Provider params are merged last so they can tune the generated request
+        let provider_params = query.config.as_ref().and_then(|c| c.provider_params.as_deref());
+        let mut params = parse_provider_params(provider_params, OPENSEARCH_ALLOWED_PARAMS)?;
+        // Routing and preference go in the URL of the search, not its body
+        routing::take_search_url_params(&mut params)?;
+        merge_into_body(&mut opensearch_query, &params);
+        
         Ok(opensearch_query)
     }
 
+    /// Convert `field:value` filters to a bool query for a delete, rejecting any that do not parse
+    fn filters_to_opensearch(&self, filters: &[String]) -> SearchResult<Value> {
+        let clauses = filters
+            .iter()
+            .map(|filter| {
+                filter
+                    .split_once(':')
+                    .map(|(field, value)| json!({ "term": { field: value } }))
+                    .ok_or_else(|| SearchError::invalid_query(format!("Filter {} is not of the form field:value", filter)))
+            })
+            .collect::<SearchResult<Vec<_>>>()?;
+        Ok(json!({ "bool": { "filter": clauses } }))
+    }
+
     /// Convert OpenSearch response to search results (reuse ElasticSearch logic)
-    fn response_to_results(&self, response: &Value) -> SearchResult<SearchResults> {
-        let hits_obj = response
-            .get("hits")
-            .ok_or_else(|| SearchError::Internal("Missing hits in response".to_string()))?;
-        
-        let total = hits_obj
-            .get("total")
-            .and_then(|t| {
-                if t.is_number() {
-                    t.as_u64()
-                } else {
-                    t.get("value").and_then(|v| v.as_u64())
+    ///
+    /// Hits are grouped when `request` collapsed them.
+    fn response_to_results(&self, body: &str, request: &Value, page: &LucenePage) -> SearchResult<SearchResults> {
+        let limits = ResponseLimits::from_env();
+        limits.check_body(body.len())?;
+        
+        let mut results = lucene_page_to_results(body, request, page)?;
+        limits.enforce(&mut results)?;
+        Ok(results)
+    }
+
+    // Basic CRUD and search operations
+
+    /// Delete an index, or with `dry_run` only return the plan for it
+    pub async fn delete_index(&self, name: &str, dry_run: bool) -> SearchResult<OperationPlan> {
+        audit::audited("opensearch", "delete_index", name, json!({ "dry_run": dry_run }), async {
+            telemetry::traced("opensearch", "delete_index", name, async {
+                dry_run::validate_target(name)?;
+                let count = self.client.count_documents(name).await.map_err(map_opensearch_error)?;
+                let plan = OperationPlan::new("opensearch", "delete_index", name, dry_run)
+                    .with_affected_documents(Some(count))
+                    .step(format!("DELETE /{}", name));
+
+                dry_run::execute(plan, async {
+                    self.client.delete_index(name).await.map_err(map_opensearch_error)?;
+                    cache::global().remove(CacheKind::Schema, &cache::index_key("opensearch", name));
+                    write_buffer::discard_index(name);
+                    checksum::forget_index(name);
+                    cache::invalidate_results("opensearch", name);
+                    numeric::forget(name);
+                    events::emit(LifecycleEventKind::IndexDeleted, "opensearch", name, Value::Null);
+                    Ok(None)
+                })
+                .await
+            })
+            .await
+        })
+        .await
+    }
+
+    /// Delete the documents matching `field:value` filters, or with `dry_run` only count them
+    pub async fn delete_by_query(&self, index: &str, filters: &[String], dry_run: bool) -> SearchResult<OperationPlan> {
+        let details = json!({ "filters": filters, "dry_run": dry_run });
+        audit::audited("opensearch", "delete_by_query", index, details, async {
+            telemetry::traced("opensearch", "delete_by_query", index, async {
+                dry_run::validate_target(index)?;
+                dry_run::validate_filters(filters)?;
+                let query = self.filters_to_opensearch(filters)?;
+                let count = self.client.count_matching(index, query.clone()).await.map_err(map_opensearch_error)?;
+                let plan = OperationPlan::new("opensearch", "delete_by_query", index, dry_run)
+                    .with_filter(query.to_string())
+                    .with_affected_documents(Some(count))
+                    .step(format!("POST /{}/_delete_by_query", index));
+
+                dry_run::execute(plan, async {
+                    let deleted = self.client.delete_by_query(index, query).await.map_err(map_opensearch_error)?;
+                    checksum::forget_index(index);
+                    cache::invalidate_results("opensearch", index);
+                    Ok(Some(deleted))
+                })
+                .await
+            })
+            .await
+        })
+        .await
+    }
+
+    /// Delete every document but keep the index with its mappings and settings
+    pub async fn clear_index(&self, index: &str, dry_run: bool) -> SearchResult<OperationPlan> {
+        audit::audited("opensearch", "clear_index", index, json!({ "dry_run": dry_run }), async {
+            telemetry::traced("opensearch", "clear_index", index, async {
+                dry_run::validate_target(index)?;
+                let count = self.client.count_documents(index).await.map_err(map_opensearch_error)?;
+                let plan = OperationPlan::new("opensearch", "clear_index", index, dry_run)
+                    .with_affected_documents(Some(count))
+                    .step(format!("POST /{}/_delete_by_query (match_all)", index));
+
+                dry_run::execute(plan, async {
+                    let deleted = self.client
+                        .delete_by_query(index, json!({ "match_all": {} }))
+                        .await
+                        .map_err(map_opensearch_error)?;
+                    write_buffer::discard_index(index);
+                    checksum::forget_index(index);
+                    cache::invalidate_results("opensearch", index);
+                    Ok(Some(deleted))
+                })
+                .await
+            })
+            .await
+        })
+        .await
+    }
+
+    /// Snapshot an index into the `SEARCH_SNAPSHOT_REPOSITORY` repository
+    pub async fn create_snapshot(&self, index: &str) -> SearchResult<SnapshotInfo> {
+        let repository = snapshot::repository_from_env()?;
+        audit::audited("opensearch", "create_snapshot", index, json!({ "repository": repository }), async {
+            telemetry::traced("opensearch", "create_snapshot", index, async {
+                let documents = self.client.count_documents(index).await.map_err(map_opensearch_error)?;
+                let info = SnapshotInfo {
+                    location: Some(repository.clone()),
+                    documents: Some(documents),
+                    ..SnapshotInfo::new(snapshot::new_snapshot_id(index), index, "opensearch", SnapshotKind::Repository)
+                };
+                let metadata = json!({ "index": index, "created_at": info.created_at });
+                self.client
+                    .create_snapshot(&repository, &info.id, index, metadata)
+                    .await
+                    .map_err(map_opensearch_error)?;
+
+                info!("Snapshotted index {} as {}/{}", index, repository, info.id);
+                Ok(info)
+            })
+            .await
+        })
+        .await
+    }
+
+    /// Restore the indices of a snapshot in the `SEARCH_SNAPSHOT_REPOSITORY` repository
+    ///
+    /// An existing index is closed first, as restoring requires, and is
+    /// replaced by the snapshot's copy.
+    pub async fn restore_snapshot(&self, snapshot_id: &str) -> SearchResult<SnapshotInfo> {
+        let repository = snapshot::repository_from_env()?;
+        audit::audited("opensearch", "restore_snapshot", snapshot_id, json!({ "repository": repository }), async {
+            telemetry::traced("opensearch", "restore_snapshot", snapshot_id, async {
+                let description = self.client.get_snapshot(&repository, snapshot_id).await.map_err(map_opensearch_error)?;
+                let snapshot = description.pointer("/snapshots/0")
+                    .ok_or_else(|| SearchError::invalid_query(format!("No snapshot {} in {}", snapshot_id, repository)))?;
+                let indices: Vec<String> = snapshot.get("indices")
+                    .and_then(|indices| indices.as_array())
+                    .map(|indices| indices.iter().filter_map(|i| i.as_str().map(str::to_string)).collect())
+                    .unwrap_or_default();
+
+                let existing = self.client.list_indexes().await.map_err(map_opensearch_error)?;
+                for index in indices.iter().filter(|index| existing.contains(index)) {
+                    self.client.close_index(index).await.map_err(map_opensearch_error)?;
+                }
+                self.client.restore_snapshot(&repository, snapshot_id, &indices).await.map_err(map_opensearch_error)?;
+
+                for index in &indices {
+                    cache::global().remove(CacheKind::Schema, &cache::index_key("opensearch", index));
+                    write_buffer::discard_index(index);
+                    checksum::forget_index(index);
+                    cache::invalidate_results("opensearch", index);
                 }
+                info!("Restored {} from snapshot {}/{}", indices.join(", "), repository, snapshot_id);
+                Ok(SnapshotInfo {
+                    id: snapshot_id.to_string(),
+                    index: indices.join(","),
+                    provider: "opensearch".to_string(),
+                    kind: SnapshotKind::Repository,
+                    location: Some(repository.clone()),
+                    documents: None,
+                    created_at: snapshot.pointer("/metadata/created_at")
+                        .or_else(|| snapshot.get("start_time"))
+                        .and_then(|t| t.as_str())
+                        .unwrap_or_default()
+                        .to_string(),
+                })
             })
-            .map(|t| t as u32);
-        
-        let hits_array = hits_obj
-            .get("hits")
-            .and_then(|h| h.as_array())
-            .ok_or_else(|| SearchError::Internal("Missing hits array in response".to_string()))?;
-        
-        let mut hits = Vec::new();
-        for hit in hits_array {
-            let id = hit
-                .get("_id")
-                .and_then(|id| id.as_str())
-                .ok_or_else(|| SearchError::Internal("Missing document ID".to_string()))?
-                .to_string();
-            
-            let source = hit.get("_source");
-            let content = if let Some(source) = source {
-                Some(serde_json::to_string(source)
-                    .map_err(|e| SearchError::Internal(e.to_string()))?)
-            } else {
-                None
-            };
-            
-            let score = hit.get("_score").and_then(|s| s.as_f64());
-            let highlights = hit.get("highlight")
-                .map(|h| serde_json::to_string(h))
-                .transpose()
-                .map_err(|e| SearchError::Internal(e.to_string()))?;
-            
-            hits.push(golem_search::SearchHit {
-                id,
-                score,
-                content,
-                highlights,
-            });
+            .await
+        })
+        .await
+    }
+
+    /// Create the first index behind a rollover alias, `<alias>-000001`, as the alias's write index
+    pub async fn create_rollover_index(&self, alias: &str, schema: Option<&Schema>) -> SearchResult<String> {
+        let index = rollover::first_index(alias);
+        let mut body = match schema {
+            Some(schema) => self.schema_to_mapping(schema)?,
+            None => json!({}),
+        };
+        body["aliases"] = rollover::write_alias(alias);
+        self.create_index_with_body(&index, schema, Some(body)).await?;
+        if let Some(schema) = schema {
+            numeric::remember("opensearch", alias, schema);
         }
-        
-        let facets = response.get("aggregations")
-            .map(|aggs| serde_json::to_string(aggs).unwrap_or_default());
-        
-        let took_ms = response
-            .get("took")
-            .and_then(|t| t.as_u64())
-            .map(|t| t as u32);
-        
-        Ok(SearchResults {
-            total,
-            page: None,
-            per_page: None,
-            hits,
-            facets,
-            took_ms,
+        Ok(index)
+    }
+
+    /// Roll the write index of `alias` over to a new index once any of `conditions` is met
+    ///
+    /// An alias that does not exist yet is created with its first index
+    /// instead. The new index gets the mappings and analysis settings of the
+    /// current write index. With `dry_run` the conditions are only evaluated.
+    pub async fn rollover(&self, alias: &str, conditions: &RolloverConditions, dry_run: bool) -> SearchResult<RolloverResult> {
+        audit::audited("opensearch", "rollover", alias, json!({ "conditions": conditions, "dry_run": dry_run }), async {
+            telemetry::traced("opensearch", "rollover", alias, async {
+                conditions.validate()?;
+                let Some(aliases) = self.client.get_alias(alias).await.map_err(map_opensearch_error)? else {
+                    if !dry_run {
+                        self.create_rollover_index(alias, None).await?;
+                    }
+                    return Ok(RolloverResult::created(alias, dry_run));
+                };
+                let write_index = rollover::write_index(&aliases, alias)
+                    .ok_or_else(|| SearchError::invalid_query(format!("Alias {} has no write index", alias)))?;
+
+                let mappings = self.client.get_mapping(&write_index).await.map_err(map_opensearch_error)?
+                    .get(&write_index)
+                    .and_then(|index| index.get("mappings"))
+                    .cloned();
+                let analysis = self.client.get_settings(&write_index).await.map_err(map_opensearch_error)?
+                    .pointer(&format!("/{}/settings/index/analysis", write_index))
+                    .cloned()
+                    .map(|analysis| json!({ "analysis": analysis }));
+                let response = self.client
+                    .rollover(alias, conditions.to_request(mappings, analysis), dry_run)
+                    .await
+                    .map_err(map_opensearch_error)?;
+
+                let result = RolloverResult::from_response(alias, &response)?;
+                if result.rolled_over {
+                    info!("Rolled alias {} over from {} to {}", alias, write_index, result.new_index);
+                    cache::global().remove(CacheKind::Schema, &cache::index_key("opensearch", alias));
+                    let covered = promotion::alias_indexes(&aliases);
+                    let moved = covered.iter().chain([&result.new_index]).map(String::as_str);
+                    cache::invalidate_alias_results("opensearch", alias, moved);
+                    let details = json!({ "alias": alias, "old_index": write_index });
+                    events::emit(LifecycleEventKind::IndexCreated, "opensearch", &result.new_index, details);
+                }
+                Ok(result)
+            })
+            .await
         })
+        .await
     }
 
-    /// Basic CRUD and search operations
-    pub async fn delete_index(&self, name: &str) -> SearchResult<()> {
-        self.client.delete_index(name).await.map_err(map_opensearch_error)?;
-        Ok(())
+    /// Point `live_alias` at `staging_index` once the staging index passes `checks`
+    ///
+    /// The alias is swapped in one update and checked again; failing that, it
+    /// is swapped back to the indexes it covered before. See `promotion`.
+    pub async fn promote(&self, staging_index: &str, live_alias: &str, checks: &PromotionChecks) -> SearchResult<PromotionReport> {
+        let details = json!({ "alias": live_alias, "checks": checks });
+        audit::audited("opensearch", "promote", staging_index, details, async {
+            telemetry::traced("opensearch", "promote", staging_index, async {
+                let previous = self.client.get_alias(live_alias).await.map_err(map_opensearch_error)?
+                    .map(|aliases| promotion::alias_indexes(&aliases))
+                    .unwrap_or_default();
+                let count = |index: String| async move { self.client.count_documents(&index).await.map_err(map_opensearch_error) };
+                let hits = |index: String, text: String| async move {
+                    self.search(&index, &promotion::sample_query(&text)).await.map(|results| promotion::hit_count(&results))
+                };
+                let moved: Vec<String> = previous.iter().cloned().chain([staging_index.to_string()]).collect();
+                let moved = &moved;
+                let update = |actions: serde_json::Value| async move {
+                    self.client.update_aliases(actions).await.map_err(map_opensearch_error)?;
+                    cache::global().remove(CacheKind::Schema, &cache::index_key("opensearch", live_alias));
+                    cache::invalidate_alias_results("opensearch", live_alias, moved.iter().map(String::as_str));
+                    Ok(())
+                };
+                promotion::promote(live_alias, staging_index, previous, checks, count, hits, update).await
+            })
+            .await
+        })
+        .await
     }
 
     pub async fn list_indexes(&self) -> SearchResult<Vec<String>> {
@@ -602,40 +1723,839 @@ impl OpenSearchProvider {
     }
 
     pub async fn upsert(&self, index: &str, doc: &Doc) -> SearchResult<()> {
+        self.upsert_with_pipeline(index, doc, None).await
+    }
+
+    /// Upsert a document through the provider-side `pipeline`, or that of the index when `None`
+    pub async fn upsert_with_pipeline(&self, index: &str, doc: &Doc, pipeline: Option<&str>) -> SearchResult<()> {
+        if write_buffer::is_enabled() {
+            return self.upsert_many_with_pipeline(index, std::slice::from_ref(doc), pipeline).await;
+        }
+        telemetry::traced("opensearch", "upsert", index, async {
+            self.index_document(index, doc, pipeline).await.map(|_| ())
+        })
+        .await
+    }
+
+    /// Upsert a document and return a consistency token for the write
+    pub async fn upsert_with_token(&self, index: &str, doc: &Doc) -> SearchResult<String> {
+        self.index_document(index, doc, None).await
+    }
+
+    /// Write one document through `pipeline`, or the index's, returning the consistency token of the write
+    async fn index_document(&self, index: &str, doc: &Doc, pipeline: Option<&str>) -> SearchResult<String> {
+        quota::global().check_documents(index, 1)?;
+        let docs = ingest::apply(index, std::slice::from_ref(doc))?;
+        let doc = &docs[0];
         let content: Value = serde_json::from_str(&doc.content)
             .map_err(|e| SearchError::InvalidQuery(e.to_string()))?;
         
-        self.client.index_document(index, &doc.id, content).await
+        let mut params = Vec::new();
+        if let Some(routing) = routing::document_routing(index, &content) {
+            params.push(("routing".to_string(), routing));
+        }
+        if let Some(pipeline) = ingest::server_pipeline(index, pipeline)? {
+            params.push(("pipeline".to_string(), pipeline));
+        }
+        let response = self.client.index_document(index, &doc.id, content, &params).await
             .map_err(map_opensearch_error)?;
+        checksum::record(index, std::slice::from_ref(doc));
+        cache::invalidate_results("opensearch", index);
+        alerts::observe(index, std::slice::from_ref(doc));
+        Ok(ConsistencyToken::from_write_response("opensearch", index, &response).encode())
+    }
+
+    /// Wait until the write behind a query's consistency token is searchable
+    ///
+    /// Writes become visible on the next refresh, so refreshing the token's
+    /// index is enough; it is retried until it succeeds or the wait times out.
+    async fn wait_for_token(&self, query: &SearchQuery) -> SearchResult<()> {
+        let Some(token) = query.config.as_ref().and_then(|c| c.consistency_token.as_deref()) else {
+            return Ok(());
+        };
+        let token = ConsistencyToken::parse_for(token, "opensearch")?;
+
+        consistency::wait_until_applied(&token, consistency::wait_timeout_from_env(), || async {
+            Ok(self.client.refresh_index(&token.index).await.is_ok())
+        })
+        .await
+    }
+
+    /// Upsert documents, through the write buffer when it is enabled
+    pub async fn upsert_many(&self, index: &str, docs: &[Doc]) -> SearchResult<()> {
+        self.upsert_many_with_pipeline(index, docs, None).await
+    }
+
+    /// Upsert documents through the provider-side `pipeline`, or that of the index when `None`
+    ///
+    /// Writes naming a pipeline skip the write buffer, whose batches go through the index's.
+    pub async fn upsert_many_with_pipeline(&self, index: &str, docs: &[Doc], pipeline: Option<&str>) -> SearchResult<()> {
+        telemetry::traced("opensearch", "upsert_many", index, async {
+            quota::global().check_documents(index, docs.len())?;
+            let docs = ingest::apply(index, docs)?;
+            let docs = checksum::skip_unchanged(index, &docs);
+            if docs.is_empty() {
+                return Ok(());
+            }
+            if write_buffer::is_enabled() && pipeline.is_none() {
+                return write_buffer::buffer_upserts(index, docs.into_owned(), |index, docs| async move {
+                    self.bulk_upsert(&index, &docs, None).await
+                })
+                .await;
+            }
+            self.bulk_upsert(index, &docs, pipeline).await
+        })
+        .await
+    }
+
+    /// Write buffered upserts for `index`, or for every index, returning the number of documents written
+    pub async fn flush_writes(&self, index: Option<&str>) -> SearchResult<u32> {
+        let written = write_buffer::flush(index, |index, docs| async move {
+            self.bulk_upsert(&index, &docs, None).await
+        })
+        .await?;
+        Ok(written as u32)
+    }
+
+    /// Apply the writes recorded in the worker's outbox, oldest first
+    pub async fn drain_outbox(&self) -> DrainReport {
+        outbox::drain(|index, mutation| async move {
+            match mutation {
+                Mutation::Upsert { doc } => self.bulk_upsert(&index, &ingest::apply(&index, &[doc])?, None).await,
+                Mutation::Delete { id } => self.bulk_delete(&index, &[id]).await,
+            }
+        })
+        .await
+    }
+
+    /// Write the whole write buffer and drain the outbox, before the worker is suspended
+    pub async fn flush(&self) -> ShutdownReport {
+        shutdown::flush(self.flush_writes(None), self.drain_outbox()).await
+    }
+
+    /// Flush, then release the scroll contexts left open by interrupted reads
+    pub async fn shutdown(&self) -> ShutdownReport {
+        let mut report = self.flush().await;
+        (report.scrolls_closed, report.scrolls_failed) = shutdown::close_scrolls("opensearch", |scroll_id| async move {
+            self.client.clear_scroll(&scroll_id).await
+        })
+        .await;
+        report
+    }
+
+    /// Write a batch of change-feed events, as collapsed by a `ChangeFeedAdapter`
+    pub async fn apply_changes(&self, index: &str, batch: &ChangeBatch) -> SearchResult<()> {
+        telemetry::traced("opensearch", "apply_changes", index, async {
+            quota::global().check_documents(index, batch.upserts.len())?;
+            let upserts = ingest::apply(index, &batch.upserts)?;
+            let upserts = checksum::skip_unchanged(index, &upserts);
+            if !upserts.is_empty() {
+                self.bulk_upsert(index, &upserts, None).await?;
+            }
+            if !batch.deletes.is_empty() {
+                self.bulk_delete(index, &batch.deletes).await?;
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    /// Converge an index to `docs`, upserting new and changed documents and deleting missing ones
+    pub async fn sync(&self, index: &str, docs: Vec<Doc>, dry_run: bool) -> SearchResult<SyncReport> {
+        telemetry::traced("opensearch", "sync", index, async {
+            // Buffered writes would otherwise land after the comparison
+            self.flush_writes(Some(index)).await?;
+            // The scan only sees refreshed documents
+            self.client.refresh_index(index).await.map_err(map_opensearch_error)?;
+            let existing = self.fingerprint_index(index).await?;
+            let report = delta_sync::plan(index, existing, docs, dry_run)?
+                .execute(|batch| async move { self.apply_changes(index, &batch).await })
+                .await?;
+            info!(
+                "Synced index {}: {} upserted, {} deleted, {} unchanged",
+                index, report.upserted, report.deleted, report.unchanged
+            );
+            Ok(report)
+        })
+        .await
+    }
+
+    /// Build or refresh the suggestions index of `index` from analytics and document titles
+    ///
+    /// The suggestions index is created when missing and converged to the new
+    /// suggestions with [`Self::sync`].
+    pub async fn build_suggestions(&self, index: &str, queries: &[PopularQuery], config: &SuggestionsConfig) -> SearchResult<SyncReport> {
+        telemetry::traced("opensearch", "build_suggestions", index, async {
+            let mut builder = SuggestionsBuilder::new(config.clone());
+            builder.add_queries(queries);
+            if let Some(field) = &config.title_field {
+                self.scan_documents(index, |_, document| {
+                    if let Some(title) = document.get(field).and_then(Value::as_str) {
+                        builder.add_title(title);
+                    }
+                })
+                .await?;
+            }
+            let target = suggestions::suggestions_index(index);
+            self.ensure_index(&target, Some(&suggestions::schema())).await?;
+            self.sync(&target, builder.documents(), false).await
+        })
+        .await
+    }
+
+    /// Suggestions from the suggestions index of `index` that complete `prefix`, most popular first
+    pub async fn suggest(&self, index: &str, prefix: &str, limit: u32) -> SearchResult<Vec<Suggestion>> {
+        let target = suggestions::suggestions_index(index);
+        let results = self.fetch_results(&target, &suggestions::suggest_query(prefix, limit)).await?;
+        Ok(suggestions::from_results(&results))
+    }
+
+    /// Suggestions from the suggestions index of `index` close to `text`, to correct its spelling with
+    pub async fn spelling_candidates(&self, index: &str, text: &str) -> SearchResult<Vec<Suggestion>> {
+        let target = suggestions::suggestions_index(index);
+        let results = self.fetch_results(&target, &spellfix::lookup_query(text)).await?;
+        Ok(suggestions::from_results(&results))
+    }
+
+    /// IDs and hashes of every document in an index
+    async fn fingerprint_index(&self, index: &str) -> SearchResult<IndexFingerprint> {
+        let mut fingerprint = IndexFingerprint::new();
+        self.scan_documents(index, |id, document| fingerprint.add(id, document)).await?;
+        Ok(fingerprint)
+    }
+
+    /// Visit the ID and source of every document in an index, read with the scroll API
+    async fn scan_documents(&self, index: &str, mut visit: impl FnMut(&str, &Value)) -> SearchResult<()> {
+        let mut page = self.client.open_scroll(index, 1000, "1m").await.map_err(map_opensearch_error)?;
+        loop {
+            let hits = page["hits"]["hits"].as_array().cloned().unwrap_or_default();
+            for hit in &hits {
+                if let Some(id) = hit["_id"].as_str() {
+                    visit(id, &hit["_source"]);
+                }
+            }
+            let scroll_id = page["_scroll_id"].as_str().map(str::to_string);
+            match scroll_id {
+                Some(scroll_id) if !hits.is_empty() => {
+                    // Tracked while the next page is read, so a failed read leaves it for shutdown to clear
+                    shutdown::track_scroll("opensearch", &scroll_id);
+                    page = self.client.scroll(&scroll_id, "1m").await.map_err(map_opensearch_error)?;
+                    shutdown::forget_scroll("opensearch", &scroll_id);
+                }
+                Some(scroll_id) => {
+                    if let Err(e) = self.client.clear_scroll(&scroll_id).await {
+                        warn!("Failed to clear scroll on index {}: {}", index, e);
+                    }
+                    break;
+                }
+                None => break,
+            }
+        }
         Ok(())
     }
 
-    pub async fn get(&self, index: &str, id: &str) -> SearchResult<Option<Doc>> {
-        let result = self.client.get_document(index, id).await
-            .map_err(map_opensearch_error)?;
+    /// Delete documents through _bulk, where a missing document is not an error, unlike DELETE /_doc
+    async fn bulk_delete(&self, index: &str, ids: &[String]) -> SearchResult<()> {
+        write_buffer::discard(index, ids);
+        checksum::forget(index, ids);
+        cache::invalidate_results("opensearch", index);
+        let operations = ids.iter().map(|id| json!({"delete": {"_index": index, "_id": id}}));
+        self.client.bulk(operations).await
+            .map(|_| ())
+            .map_err(map_opensearch_error)
+    }
+
+    async fn bulk_upsert(&self, index: &str, docs: &[Doc], pipeline: Option<&str>) -> SearchResult<()> {
+        info!("Bulk upserting {} documents in index {}", docs.len(), index);
+
+        let max_batch_size = self.get_capabilities().max_batch_size.unwrap_or(1000) as usize;
+        let mut batcher = AdaptiveBatcher::new(AdaptiveBatchConfig::with_max(max_batch_size));
+
+        let routing_fields = RoutingFields::from_env();
+        let routing_fields = &routing_fields;
+        let pipeline = ingest::server_pipeline(index, pipeline)?;
+        let pipeline = pipeline.as_deref();
+        submit_adaptive(docs.to_vec(), &mut batcher, |chunk: Vec<Doc>| async move {
+            let mut operations = Vec::with_capacity(chunk.len() * 2);
+            for doc in &chunk {
+                let content = serde_json::from_str::<Value>(&doc.content)?;
+                let mut action = json!({"_index": index, "_id": doc.id});
+                if let Some(routing) = routing_fields.document_routing(index, &content) {
+                    action["routing"] = json!(routing);
+                }
+                if let Some(pipeline) = pipeline {
+                    action["pipeline"] = json!(pipeline);
+                }
+                operations.push(json!({ "index": action }));
+                operations.push(content);
+            }
+            let response = self.client.bulk(operations).await?;
+            checksum::record_bulk(index, &chunk, &response);
+            cache::invalidate_results("opensearch", index);
+            Ok::<_, anyhow::Error>(())
+        })
+        .await
+        .map_err(|e| {
+            error!("Failed to bulk upsert documents: {}", e);
+            map_opensearch_error(e.into())
+        })?;
+        alerts::observe(index, docs);
+        Ok(())
+    }
+
+    /// Delete a document
+    pub async fn delete(&self, index: &str, id: &str) -> SearchResult<()> {
+        self.delete_routed(index, id, None).await
+    }
+
+    /// Delete a document from the shard of `routing`
+    pub async fn delete_routed(&self, index: &str, id: &str, routing: Option<&str>) -> SearchResult<()> {
+        telemetry::traced("opensearch", "delete", index, async {
+            write_buffer::discard(index, &[id.to_string()]);
+            checksum::forget(index, &[id.to_string()]);
+            cache::invalidate_results("opensearch", index);
+            self.client.delete_document(index, id, routing).await
+                .map(|_| ())
+                .map_err(map_opensearch_error)
+        })
+        .await
+    }
+
+    /// Get a document by ID, with only the `fields` paths in its content when given
+    pub async fn get(&self, index: &str, id: &str, fields: Option<&FieldMask>) -> SearchResult<Option<Doc>> {
+        self.get_routed(index, id, None, fields).await
+    }
+
+    /// Get a document by ID from the shard of `routing`, which a routed document is only found with
+    pub async fn get_routed(&self, index: &str, id: &str, routing: Option<&str>, fields: Option<&FieldMask>) -> SearchResult<Option<Doc>> {
+        telemetry::traced("opensearch", "get", index, async {
+            let source = fields.map(FieldMask::paths);
+            let result = self.client.get_document(index, id, source.as_deref(), routing).await
+                .map_err(map_opensearch_error)?;
         
-        if let Some(response) = result {
-            let id = response.get("_id")
-                .and_then(|id| id.as_str())
-                .ok_or_else(|| SearchError::Internal("Missing document ID".to_string()))?
-                .to_string();
+            if let Some(response) = result {
+                let id = response.get("_id")
+                    .and_then(|id| id.as_str())
+                    .ok_or_else(|| SearchError::Internal("Missing document ID".to_string()))?
+                    .to_string();
             
-            let source = response.get("_source")
-                .ok_or_else(|| SearchError::Internal("Missing document source".to_string()))?;
+                let source = response.get("_source")
+                    .ok_or_else(|| SearchError::Internal("Missing document source".to_string()))?;
             
-            let content = serde_json::to_string(source)
-                .map_err(|e| SearchError::Internal(e.to_string()))?;
+                let content = serde_json::to_string(source)
+                    .map_err(|e| SearchError::Internal(e.to_string()))?;
             
-            Ok(Some(Doc { id, content }))
-        } else {
-            Ok(None)
-        }
+                Ok(Some(Doc { id, content }))
+            } else {
+                Ok(None)
+            }
+        })
+        .await
+    }
+
+    /// Check whether a document exists without transferring it
+    pub async fn exists(&self, index: &str, id: &str) -> SearchResult<bool> {
+        self.client.document_exists(index, id).await
+            .map_err(map_opensearch_error)
     }
 
+    /// Get several documents by ID with a single `_mget` request, in request order
+    ///
+    /// Source filtering selects the `fields` paths, nested ones included.
+    pub async fn get_many(&self, index: &str, ids: &[String], fields: Option<&FieldMask>) -> SearchResult<Vec<Option<Doc>>> {
+        telemetry::traced("opensearch", "get_many", index, async {
+            if ids.is_empty() {
+                return Ok(Vec::new());
+            }
+
+            let source = fields.map(FieldMask::paths);
+            let response = self.client.mget(index, ids, source.as_deref()).await
+                .map_err(map_opensearch_error)?;
+        
+            let docs = response.get("docs").and_then(Value::as_array);
+            docs.into_iter()
+                .flatten()
+                .map(|doc| {
+                    if doc.get("found").and_then(Value::as_bool) != Some(true) {
+                        return Ok(None);
+                    }
+                    let id = doc.get("_id")
+                        .and_then(|id| id.as_str())
+                        .ok_or_else(|| SearchError::Internal("Missing document ID".to_string()))?
+                        .to_string();
+                    let source = doc.get("_source")
+                        .ok_or_else(|| SearchError::Internal("Missing document source".to_string()))?;
+                    Ok(Some(Doc { id, content: serde_json::to_string(source)? }))
+                })
+                .collect()
+        })
+        .await
+    }
+
+    /// Search through the shared result cache, honouring per-index cache policies
     pub async fn search(&self, index: &str, query: &SearchQuery) -> SearchResult<SearchResults> {
-        let opensearch_query = self.query_to_opensearch(query)?;
-        let response = self.client.search(index, opensearch_query).await
+        telemetry::traced("opensearch", "search", index, async {
+            quota::global().check_query(index)?;
+            let scope = request_id::begin();
+            let _deadline = deadline::begin(query.config.as_ref().and_then(|config| config.timeout_ms));
+            let mut results = cache::with_result_cache("opensearch", index, query, results_size, |index, query| async move {
+                let limits = SplitLimits::new(&opensearch_capability_matrix().performance_limits);
+                limits.check(&query)?;
+                let (index, limits) = (index.as_str(), &limits);
+                let lookup = |text: String| async move { self.spelling_candidates(index, &text).await };
+                rescue::search_rescued(&query, &RescuePlan::from_env(), lookup, |query| async move {
+                    query_split::search_split(&query, limits, |part| async move { self.fetch_results(index, &part).await }).await
+                })
+                .await
+            })
+            .await?;
+            results.request_id = Some(scope.id().to_string());
+            results.facet_metadata = self.facet_metadata(index, &query.facets).await;
+            Ok(results)
+        })
+        .await
+    }
+
+    /// Search the primary index and enrich each hit with documents from a lookup index
+    pub async fn search_with_lookup(
+        &self,
+        primary_index: &str,
+        query: &SearchQuery,
+        lookup: &LookupSpec,
+    ) -> SearchResult<SearchResults> {
+        lookup.validate()?;
+
+        let mut results = self.search(primary_index, query).await?;
+        let keys = collect_lookup_keys(&results, lookup);
+        if keys.is_empty() {
+            return Ok(results);
+        }
+
+        debug!("Looking up {} keys in index {}", keys.len(), lookup.index);
+
+        let docs = fetch_in_batches(&keys, |batch| async move {
+            let response = self.client
+                .search(&lookup.index, lookup_to_opensearch_query(lookup, &batch))
+                .await
+                .map_err(map_opensearch_error)?;
+            let hits = response.pointer("/hits/hits").and_then(Value::as_array);
+            let docs: Vec<Value> = hits
+                .map(|hits| hits.iter().filter_map(|hit| hit.get("_source").cloned()).collect())
+                .unwrap_or_default();
+            check_lookup_complete(lookup, docs.len(), response.pointer("/hits/total/value").and_then(Value::as_u64))?;
+            Ok::<_, SearchError>(docs)
+        })
+        .await?;
+        merge_lookup(&mut results, lookup, &docs)?;
+
+        Ok(results)
+    }
+
+    /// Display metadata of the requested facets; left empty when the schema cannot be read
+    async fn facet_metadata(&self, index: &str, facets: &[String]) -> Vec<FacetMetadata> {
+        if facets.is_empty() {
+            return Vec::new();
+        }
+        match self.get_schema(index).await {
+            Ok(schema) => ui_metadata::facet_metadata(&schema, facets),
+            Err(e) => {
+                warn!("Failed to read facet metadata of index {}: {}", index, e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Run a search against the backend, bypassing the result cache
+    async fn fetch_results(&self, index: &str, query: &SearchQuery) -> SearchResult<SearchResults> {
+        self.wait_for_token(query).await?;
+        let mut opensearch_query = compiled_query::compile("opensearch", index, query, |query| self.query_to_opensearch(query))?;
+        let cursor = query.config.as_ref().and_then(|c| c.cursor.as_deref());
+        let page = LucenePage::apply(&mut opensearch_query, "opensearch", index, cursor)?;
+        let started = std::time::Instant::now();
+        let params = routing::search_url_params(query)?;
+        let response = self.client.search_raw(index, opensearch_query.clone(), &params).await
             .map_err(map_opensearch_error)?;
-        self.response_to_results(&response)
+        slow_query::record_search("opensearch", index, &opensearch_query, query, started.elapsed());
+        let mut results = self.response_to_results(&response, &opensearch_query, &page)?;
+        // Tagged highlights are not requested from OpenSearch, so offsets come from matching the query words
+        if let Some(config) = query.highlight.as_ref().filter(|h| h.offsets) {
+            highlight::replace_matched(&mut results.hits, &config.fields, query.q.as_deref().unwrap_or_default());
+        }
+        if let Some(sort) = geo::distance_sort(&query.sort)? {
+            geo::fill_distances(&mut results.hits, &sort);
+            for group in results.groups.iter_mut().flatten() {
+                geo::fill_distances(&mut group.hits, &sort);
+            }
+        }
+        Ok(results)
     }
-}
\ No newline at end of file
+
+    /// Estimate the cost of a query without running it
+    pub async fn estimate_query(&self, index: &str, query: &SearchQuery) -> SearchResult<CostEstimate> {
+        let document_count = match self.client.count_documents(index).await {
+            Ok(count) => Some(count),
+            Err(e) => {
+                debug!("Estimating without a document count for {}: {}", index, e);
+                None
+            }
+        };
+        let stats = IndexStats { document_count };
+        Ok(cost::estimate_query(&opensearch_capability_matrix(), index, &stats, query))
+    }
+
+    /// Reindex jobs are not offered here; the cluster copies indexes itself with `_reindex`
+    ///
+    /// Always fails with `unsupported`, as `reindex_jobs` in the capability matrix says.
+    pub async fn start_reindex(&self, _source: &str, _destination: &str) -> SearchResult<String> {
+        Err(SearchError::Unsupported)
+    }
+
+    /// Queued cluster state changes that name the index
+    ///
+    /// Document writes are applied synchronously and only wait for the next
+    /// refresh, so the list stays empty unless mapping, settings or index
+    /// changes are backed up.
+    pub async fn list_pending_tasks(&self, index: &str) -> SearchResult<PendingTasks> {
+        let body = self.client.pending_tasks().await.map_err(map_opensearch_error)?;
+        Ok(PendingTasks::from_cluster_pending_tasks("opensearch", index, &body))
+    }
+
+    /// Changes to an index since the position of `since`, or an empty summary marking the current position
+    ///
+    /// Writes are read from the primary shards' index and delete counters.
+    /// Those restart with the shard, and a poll across a restart sees only
+    /// the net change in documents.
+    pub async fn index_changes(&self, index: &str, since: Option<&str>) -> SearchResult<ChangeSummary> {
+        let documents = self.client.count_documents(index).await.map_err(map_opensearch_error)?;
+        let stats = self.client.index_stats(index).await.map_err(map_opensearch_error)?;
+        let mut now = WatchCursor::new("opensearch", index, documents);
+        now.counters = WriteCounters::from_index_stats(&stats);
+
+        let Some(since) = since else {
+            return Ok(ChangeSummary::start(&now));
+        };
+        let before = WatchCursor::parse_for(since, "opensearch", index)?;
+        let writes = before.counters.zip(now.counters).and_then(|(before, now)| now.since(&before));
+        Ok(ChangeSummary::between(&before, &now, writes))
+    }
+
+    /// Slow queries recorded by this worker, as JSON
+    pub fn get_slow_queries(&self) -> SearchResult<String> {
+        Ok(serde_json::to_string(&slow_query::get_slow_queries())?)
+    }
+
+    /// Extended health report with per-endpoint circuit breaker state, as JSON
+    pub fn health_report(&self) -> SearchResult<String> {
+        Ok(serde_json::to_string(&circuit_breaker::health_report("opensearch"))?)
+    }
+
+    /// Operation counters, latency histograms and cache hit rates in Prometheus text format
+    pub fn metrics_prometheus(&self) -> SearchResult<String> {
+        Ok(metrics::render_prometheus())
+    }
+
+    /// Every environment variable the provider reads, whether it is set and parses, as JSON
+    ///
+    /// Needs no provider instance, so it also works when `new()` fails.
+    pub fn diagnose_config() -> SearchResult<String> {
+        Ok(serde_json::to_string(&diagnose::diagnose_config("opensearch"))?)
+    }
+
+    /// Audit log entries at or after `since` (RFC 3339), as JSON
+    pub fn get_audit_log(&self, since: Option<&str>) -> SearchResult<String> {
+        Ok(serde_json::to_string(&audit::get_audit_log(since)?)?)
+    }
+}
+
+impl RoutedBackend for OpenSearchProvider {
+    fn search<'a>(&'a self, index: &'a str, query: &'a SearchQuery) -> LocalBoxFuture<'a, SearchResult<SearchResults>> {
+        Box::pin(OpenSearchProvider::search(self, index, query))
+    }
+
+    fn get<'a>(&'a self, index: &'a str, id: &'a str) -> LocalBoxFuture<'a, SearchResult<Option<Doc>>> {
+        Box::pin(OpenSearchProvider::get(self, index, id, None))
+    }
+
+    fn upsert_many<'a>(&'a self, index: &'a str, docs: &'a [Doc], pipeline: Option<&'a str>) -> LocalBoxFuture<'a, SearchResult<()>> {
+        Box::pin(OpenSearchProvider::upsert_many_with_pipeline(self, index, docs, pipeline))
+    }
+
+    fn delete<'a>(&'a self, index: &'a str, id: &'a str) -> LocalBoxFuture<'a, SearchResult<()>> {
+        Box::pin(OpenSearchProvider::delete(self, index, id))
+    }
+
+    fn create_index<'a>(&'a self, name: &'a str, schema: Option<&'a Schema>) -> LocalBoxFuture<'a, SearchResult<()>> {
+        Box::pin(OpenSearchProvider::create_index(self, name, schema))
+    }
+
+    fn delete_index<'a>(&'a self, name: &'a str) -> LocalBoxFuture<'a, SearchResult<()>> {
+        Box::pin(async move { OpenSearchProvider::delete_index(self, name, false).await.map(|_| ()) })
+    }
+
+    fn list_indexes(&self) -> LocalBoxFuture<'_, SearchResult<Vec<String>>> {
+        Box::pin(OpenSearchProvider::list_indexes(self))
+    }
+
+    fn get_schema<'a>(&'a self, index: &'a str) -> LocalBoxFuture<'a, SearchResult<Schema>> {
+        Box::pin(OpenSearchProvider::get_schema(self, index))
+    }
+
+    fn describe(&self) -> LocalBoxFuture<'_, ProviderDescription> {
+        Box::pin(OpenSearchProvider::describe(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+    use std::sync::Mutex;
+    use golem_search::http::{HttpClient, Request, Response, StatusCode};
+
+    /// Scripted answer of a node: a status and body, or `None` to refuse the connection
+    type Reply = Option<(u16, &'static str)>;
+
+    /// Transport answering each node with its scripted replies in turn, repeating the last one
+    struct Nodes {
+        replies: Mutex<HashMap<String, VecDeque<Reply>>>,
+        seen: Mutex<Vec<String>>,
+    }
+
+    impl Nodes {
+        fn new(replies: &[(&str, &[Reply])]) -> Arc<Self> {
+            let replies = replies.iter().map(|(host, replies)| (host.to_string(), replies.iter().copied().collect())).collect();
+            Arc::new(Self { replies: Mutex::new(replies), seen: Mutex::new(Vec::new()) })
+        }
+
+        fn seen(&self) -> Vec<String> {
+            self.seen.lock().unwrap().clone()
+        }
+    }
+
+    impl HttpClient for Nodes {
+        fn execute(&self, request: Request) -> Result<Response, http::Error> {
+            let host = request.url.host_str().unwrap_or_default().to_string();
+            self.seen.lock().unwrap().push(format!("{} {}{}", request.method, host, request.url.path()));
+            let mut replies = self.replies.lock().unwrap();
+            let queue = replies.get_mut(&host).expect("no replies for host");
+            let reply = if queue.len() > 1 { queue.pop_front().unwrap() } else { queue[0] };
+            match reply {
+                Some((status, body)) => {
+                    let status = StatusCode::from_u16(status).unwrap();
+                    Ok(Response::new(status, HeaderMap::new(), body.as_bytes().to_vec(), request.url))
+                }
+                None => Err(http::Error::connect("connection refused")),
+            }
+        }
+    }
+
+    /// Transport keeping documents by the ID decoded from their `_doc/{id}` path
+    #[derive(Default)]
+    struct Documents {
+        stored: Mutex<HashMap<String, Value>>,
+        paths: Mutex<Vec<String>>,
+    }
+
+    /// Percent-decode one path segment
+    fn decode_segment(segment: &str) -> String {
+        let bytes = segment.as_bytes();
+        let mut decoded = Vec::with_capacity(bytes.len());
+        let mut i = 0;
+        while i < bytes.len() {
+            if bytes[i] == b'%' {
+                decoded.push(u8::from_str_radix(&segment[i + 1..i + 3], 16).unwrap());
+                i += 3;
+            } else {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+        String::from_utf8(decoded).unwrap()
+    }
+
+    impl HttpClient for Documents {
+        fn execute(&self, request: Request) -> Result<Response, http::Error> {
+            let path = request.url.path().to_string();
+            self.paths.lock().unwrap().push(format!("{} {}", request.method, path));
+            let (_, segment) = path.split_once("/_doc/").expect("not a document request");
+            assert!(!segment.contains('/'), "ID split into several segments: {}", path);
+            let id = decode_segment(segment);
+
+            let mut stored = self.stored.lock().unwrap();
+            let (status, body) = if request.method == Method::PUT {
+                let source: Value = serde_json::from_slice(request.body.as_deref().unwrap()).unwrap();
+                stored.insert(id.clone(), source);
+                (201, json!({ "_id": id, "result": "created" }))
+            } else if request.method == Method::DELETE {
+                match stored.remove(&id) {
+                    Some(_) => (200, json!({ "_id": id, "result": "deleted" })),
+                    None => (404, json!({ "_id": id, "result": "not_found" })),
+                }
+            } else {
+                match stored.get(&id) {
+                    Some(source) => (200, json!({ "_id": id, "found": true, "_source": source })),
+                    None => (404, json!({ "_id": id, "found": false })),
+                }
+            };
+            let status = StatusCode::from_u16(status).unwrap();
+            Ok(Response::new(status, HeaderMap::new(), body.to_string().into_bytes(), request.url))
+        }
+    }
+
+    /// Client of the comma-separated `endpoints` sending through `nodes`, with quick retries
+    fn client_over(nodes: &Arc<Nodes>, endpoints: &str, max_retries: u32) -> OpenSearchClient {
+        client_with(nodes.clone(), endpoints, max_retries)
+    }
+
+    /// Client of the comma-separated `endpoints` sending through `transport`, with quick retries
+    fn client_with(transport: Arc<dyn HttpClient>, endpoints: &str, max_retries: u32) -> OpenSearchClient {
+        let policy = RetryPolicy { base_delay: Duration::from_millis(1), ..RetryPolicy::new(max_retries) };
+        OpenSearchClient {
+            config: OpenSearchConfig {
+                endpoint: endpoints.to_string(),
+                username: None,
+                password: None,
+                api_key: None,
+                timeout: Duration::from_secs(5),
+                max_retries,
+                compression: Compression::default(),
+            },
+            http_client: Client::builder().retry(policy).transport(transport).build().unwrap(),
+            endpoints: Arc::new(EndpointPool::parse(endpoints, Duration::from_secs(60)).unwrap()),
+            throttle: rate_limit::throttle("opensearch"),
+        }
+    }
+
+    #[test]
+    fn test_searches_fail_over_to_the_next_node_and_bulk_writes_do_not() {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let nodes = Nodes::new(&[
+            ("os-failover-a", &[None]),
+            ("os-failover-b", &[Some((200, r#"{"hits":{"hits":[]}}"#)), Some((503, "overloaded"))]),
+        ]);
+        let client = client_over(&nodes, "http://os-failover-a:9200,http://os-failover-b:9200", 2);
+
+        let results = runtime.block_on(client.search("books", json!({ "query": { "match_all": {} } }))).unwrap();
+        assert_eq!(results["hits"]["hits"], json!([]));
+
+        let operations = [json!({ "index": { "_index": "books", "_id": "1" } }), json!({ "title": "Dune" })];
+        let err = runtime.block_on(client.bulk(operations)).unwrap_err();
+        assert!(err.to_string().contains("overloaded"), "{}", err);
+
+        // The refused node was retried before failing over; the bulk request was sent once
+        assert_eq!(
+            nodes.seen(),
+            [
+                "POST os-failover-a/books/_search",
+                "POST os-failover-a/books/_search",
+                "POST os-failover-a/books/_search",
+                "POST os-failover-b/books/_search",
+                "POST os-failover-b/_bulk",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_malformed_filters_fail_the_search_without_a_request() {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let nodes = Nodes::new(&[("os-bad-filter", &[Some((200, r#"{"hits":{"total":{"value":0},"hits":[]}}"#))])]);
+        let provider = OpenSearchProvider { client: client_over(&nodes, "http://os-bad-filter:9200", 0) };
+
+        let filters = ["oops", "geo_polygon(location, [[0, 0], [0, 1]]", "id_set(sku, [1, 2]"];
+        for filter in filters {
+            let query = SearchQuery { filters: vec!["category:test".to_string(), filter.to_string()], ..SearchQuery::match_all() };
+            let err = runtime.block_on(provider.search("products", &query)).unwrap_err();
+            assert!(matches!(err, SearchError::InvalidQuery(_)), "{}: {}", filter, err);
+        }
+        assert!(nodes.seen().is_empty());
+    }
+
+    #[test]
+    fn test_lookup_query_matches_keys_and_keeps_the_join_field() {
+        let lookup = LookupSpec {
+            index: "authors".to_string(),
+            local_field: "author_id".to_string(),
+            foreign_field: "id".to_string(),
+            fields: vec!["name".to_string()],
+        };
+        let query = lookup_to_opensearch_query(&lookup, &["a1".to_string(), "7".to_string()]);
+        assert_eq!(query["query"], json!({ "terms": { "id": ["a1", "7"] } }));
+        assert_eq!(query["_source"], json!(["name", "id"]));
+        assert_eq!(query["size"], json!(MAX_LOOKUP_DOCS));
+    }
+
+    #[test]
+    fn test_exact_total_tracks_every_hit_and_capped_totals_are_inexact() {
+        let config = OpenSearchConfig {
+            endpoint: "http://localhost:9200".to_string(),
+            username: None,
+            password: None,
+            api_key: None,
+            timeout: Duration::from_secs(5),
+            max_retries: 0,
+            compression: Compression::default(),
+        };
+        let provider = OpenSearchProvider { client: OpenSearchClient::new(config).unwrap() };
+        let mut query = SearchQuery::match_all();
+        assert!(provider.query_to_opensearch(&query).unwrap().get("track_total_hits").is_none());
+        query.config = Some(golem_search::types::SearchConfig { exact_total: true, ..Default::default() });
+        let mut request = provider.query_to_opensearch(&query).unwrap();
+        assert_eq!(request["track_total_hits"], json!(true));
+
+        let page = LucenePage::apply(&mut request, "opensearch", "products", None).unwrap();
+        let capped = r#"{"took":5,"hits":{"total":{"value":10000,"relation":"gte"},"hits":[]}}"#;
+        let results = provider.response_to_results(capped, &request, &page).unwrap();
+        assert_eq!((results.total, results.total_exact), (Some(10000), Some(false)));
+    }
+
+    #[test]
+    fn test_settings_snapshot_reads_the_search_analyzer_filters_in_use() {
+        let mut synonyms = BTreeMap::new();
+        synonyms.insert("phone".to_string(), vec!["mobile".to_string(), "cell".to_string()]);
+        let analysis = search_analysis(&synonyms, &["the".to_string()]);
+        let settings = json!({ "products": { "settings": { "index": analysis.clone() } } });
+
+        let snapshot = settings_to_snapshot(&settings, "products");
+        assert_eq!(snapshot.synonyms, synonyms);
+        assert_eq!(snapshot.stop_words, vec!["the".to_string()]);
+
+        // Clearing the stop words leaves their filter behind, out of the analyzer chain
+        let mut cleared = analysis;
+        cleared["analysis"]["analyzer"] = search_analysis(&synonyms, &[])["analysis"]["analyzer"].clone();
+        let settings = json!({ "products": { "settings": { "index": cleared } } });
+        assert!(settings_to_snapshot(&settings, "products").stop_words.is_empty());
+    }
+
+    #[test]
+    fn test_document_presence_tells_missing_documents_from_missing_indexes() {
+        assert!(document_presence(200, r#"{"_index":"products","_id":"a/1","found":true}"#).unwrap());
+        assert!(!document_presence(404, r#"{"_index":"products","_id":"a/1","found":false}"#).unwrap());
+
+        let missing = r#"{"error":{"type":"index_not_found_exception","reason":"no such index [products]"},"status":404}"#;
+        let error = map_opensearch_error(document_presence(404, missing).unwrap_err());
+        assert!(matches!(error, SearchError::IndexNotFound(_)));
+    }
+
+    #[test]
+    fn test_document_ids_with_reserved_characters_round_trip() {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let documents = Arc::new(Documents::default());
+        let provider = OpenSearchProvider { client: client_with(documents.clone(), "http://os-doc-ids:9200", 0) };
+        let id = "a/b?c#d%e";
+
+        runtime.block_on(async {
+            provider.client.index_document("books", id, json!({ "title": "Dune" }), &[]).await.unwrap();
+            let doc = provider.get("books", id, None).await.unwrap().unwrap();
+            assert_eq!(doc.id, id);
+            assert_eq!(serde_json::from_str::<Value>(&doc.content).unwrap(), json!({ "title": "Dune" }));
+            assert!(provider.client.document_exists("books", id).await.unwrap());
+
+            provider.delete("books", id).await.unwrap();
+            assert!(provider.get("books", id, None).await.unwrap().is_none());
+        });
+
+        let encoded = "/books/_doc/a%2Fb%3Fc%23d%25e";
+        let expected: Vec<String> = ["PUT", "GET", "GET", "DELETE", "GET"].iter().map(|m| format!("{} {}", m, encoded)).collect();
+        assert_eq!(*documents.paths.lock().unwrap(), expected);
+    }
+}
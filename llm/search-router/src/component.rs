@@ -0,0 +1,682 @@
+//! `router-provider` world: golem:search/core served by [`RouterProvider`]
+//!
+//! Each call on an index goes to the backend of its route. Calls that have
+//! no single backend to go to, or that the routed backends do not expose
+//! through `RoutedBackend`, return `search-error::unsupported`.
+
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::rc::Rc;
+use golem_search::alerts;
+use golem_search::analysis::{parse_field_analysis, parse_normalization};
+use golem_search::audit;
+use golem_search::circuit_breaker;
+use golem_search::dry_run::{self, OperationPlan};
+use golem_search::field_mask::FieldMask;
+use golem_search::ingest;
+use golem_search::instance::{self, ProviderCache};
+use golem_search::metrics;
+use golem_search::query::{parse_fuzziness, parse_minimum_should_match};
+use golem_search::query_ast::{self, AstNode};
+use golem_search::request_id;
+use golem_search::router::IndexRoutes;
+use golem_search::slow_query;
+use golem_search::ui_metadata::{self, parse_field_ui};
+
+use crate::RouterProvider;
+
+// WIT bindings
+wit_bindgen::generate!({
+    world: "router-provider",
+    path: "wit",
+    generate_unused_types: true,
+    with: {
+        "golem:search/types@1.0.0": generate,
+        "golem:search/core@1.0.0": generate,
+    },
+});
+
+use exports::golem::search::core::{Guest, GuestIndexWatch, GuestReindexJob, GuestSearchSession, IndexWatch, ReindexJob, SearchSession};
+use golem::search::types::{
+    SearchError, Doc, SearchQuery, SearchResults, SearchHit, SearchGroup, Schema,
+    SearchCapabilities, FieldType, SchemaField, BackpressureInfo, OutboxMutation, FacetMetadata, FacetWidget,
+    QueryAst, QueryAstNode, QueryBool, QueryPattern, QueryPhrase, QueryRange, QueryTerm, RangeBound,
+};
+
+type SearchResult<T> = Result<T, SearchError>;
+
+struct Component;
+
+thread_local! {
+    static PROVIDER: ProviderCache<RouterProvider> = const { ProviderCache::new() };
+}
+
+/// Run an export on the instance's runtime
+fn block_on<T>(call: impl Future<Output = SearchResult<T>>) -> SearchResult<T> {
+    instance::runtime().map_err(map_shared_error)?.block_on(call)
+}
+
+/// The instance's router, with the backends its routes name, built on first use
+async fn shared_provider() -> SearchResult<Rc<RouterProvider>> {
+    instance::get_or_try_init(&PROVIDER, RouterProvider::new).await.map_err(map_shared_error)
+}
+
+/// Error of a call the router does not pass to its backends
+fn unsupported(call: &str) -> SearchError {
+    SearchError::Unsupported(format!("{} is not supported by the router; call the provider's component", call))
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> SearchResult<String> {
+    serde_json::to_string(value).map_err(|e| SearchError::Internal(e.to_string()))
+}
+
+/// Map errors from the shared golem-search library to the WIT error type
+fn map_shared_error(error: golem_search::SearchError) -> SearchError {
+    match request_id::annotate(error) {
+        golem_search::SearchError::IndexNotFound(msg) => SearchError::IndexNotFound(msg),
+        golem_search::SearchError::IndexAlreadyExists(msg) => SearchError::IndexAlreadyExists(msg),
+        golem_search::SearchError::InvalidQuery(msg) => SearchError::InvalidQuery(msg),
+        golem_search::SearchError::Timeout { remaining_ms } => SearchError::Timeout(remaining_ms),
+        golem_search::SearchError::RateLimited { retry_after_ms } => SearchError::RateLimited(retry_after_ms),
+        golem_search::SearchError::Unavailable { retry_after_ms } => SearchError::Unavailable(retry_after_ms),
+        golem_search::SearchError::Unsupported => SearchError::Unsupported("Operation not supported".to_string()),
+        e @ golem_search::SearchError::ResponseTooLarge { .. } => SearchError::ResponseTooLarge(e.to_string()),
+        golem_search::SearchError::Backpressure { queue_depth, retry_after_ms } => {
+            SearchError::Backpressure(BackpressureInfo { queue_depth, retry_after_ms })
+        }
+        other => SearchError::Internal(other.to_string()),
+    }
+}
+
+/// Shared form of a WIT query, with the settings the WIT record carries as text parsed
+fn to_shared_query(query: SearchQuery) -> SearchResult<golem_search::SearchQuery> {
+    let config = golem_search::SearchConfigType {
+        timeout_ms: query.timeout_ms,
+        provider_params: query.provider_params,
+        explain: query.explain,
+        consistency_token: query.consistency_token,
+        sample: query.sample,
+        normalization: parse_normalization(query.normalization.as_deref()).map_err(map_shared_error)?,
+        fuzziness: parse_fuzziness(query.fuzziness.as_deref()).map_err(map_shared_error)?,
+        minimum_should_match: parse_minimum_should_match(query.minimum_should_match.as_deref()).map_err(map_shared_error)?,
+        cursor: query.cursor,
+        exact_total: query.exact_total,
+        ..Default::default()
+    };
+    Ok(golem_search::SearchQuery {
+        q: query.q,
+        filters: query.filters,
+        sort: query.sort,
+        facets: query.facets,
+        page: query.page,
+        per_page: query.per_page,
+        offset: query.offset,
+        highlight: query.highlight.map(|h| golem_search::HighlightConfig {
+            fields: h.fields,
+            pre_tag: h.pre_tag,
+            post_tag: h.post_tag,
+            max_length: h.fragment_size,
+            offsets: h.offsets,
+        }),
+        config: Some(config),
+    })
+}
+
+fn from_shared_hit(hit: golem_search::SearchHit) -> SearchHit {
+    SearchHit {
+        id: hit.id,
+        score: hit.score,
+        content: hit.content,
+        highlights: hit.highlights,
+        explanation: hit.explanation,
+        distance_meters: hit.distance_meters,
+    }
+}
+
+fn from_shared_results(results: golem_search::SearchResults) -> SearchResults {
+    SearchResults {
+        total: results.total,
+        page: results.page,
+        per_page: results.per_page,
+        hits: results.hits.into_iter().map(from_shared_hit).collect(),
+        facets: results.facets,
+        took_ms: results.took_ms,
+        truncated: results.truncated,
+        request_id: results.request_id,
+        groups: results.groups.map(|groups| {
+            groups
+                .into_iter()
+                .map(|group| SearchGroup {
+                    key: group.key,
+                    total: group.total,
+                    hits: group.hits.into_iter().map(from_shared_hit).collect(),
+                })
+                .collect()
+        }),
+        next_cursor: results.next_cursor,
+        total_exact: results.total_exact,
+        facet_metadata: results.facet_metadata.into_iter().map(from_shared_facet_metadata).collect(),
+        relaxations: results.relaxations,
+        original_query: results.original_query,
+        corrected_query: results.corrected_query,
+    }
+}
+
+fn to_shared_doc(doc: Doc) -> golem_search::Doc {
+    golem_search::Doc { id: doc.id, content: doc.content }
+}
+
+fn from_shared_doc(doc: golem_search::Doc) -> Doc {
+    Doc { id: doc.id, content: doc.content }
+}
+
+/// Convert a WIT field type to the shared golem-search representation
+fn to_shared_field_type(field_type: &FieldType) -> golem_search::FieldType {
+    match field_type {
+        FieldType::Text => golem_search::FieldType::Text,
+        FieldType::Keyword => golem_search::FieldType::Keyword,
+        FieldType::Integer => golem_search::FieldType::Integer,
+        FieldType::Float => golem_search::FieldType::Float,
+        FieldType::Boolean => golem_search::FieldType::Boolean,
+        FieldType::Date => golem_search::FieldType::Date,
+        FieldType::GeoPoint => golem_search::FieldType::GeoPoint,
+        FieldType::Int64 => golem_search::FieldType::Int64,
+        FieldType::Decimal => golem_search::FieldType::Decimal,
+    }
+}
+
+/// Convert a WIT schema to the shared golem-search schema, rejecting malformed hints
+fn to_shared_schema(schema: &Schema) -> SearchResult<golem_search::Schema> {
+    let fields = schema
+        .fields
+        .iter()
+        .map(|f| {
+            Ok(golem_search::SchemaField {
+                name: f.name.clone(),
+                field_type: to_shared_field_type(&f.field_type),
+                required: f.required,
+                facet: f.facet,
+                sort: f.sort,
+                index: f.index,
+                analysis: parse_field_analysis(f.analysis.as_deref()).map_err(map_shared_error)?,
+                ui: parse_field_ui(f.ui.as_deref()).map_err(map_shared_error)?,
+            })
+        })
+        .collect::<SearchResult<_>>()?;
+    Ok(golem_search::Schema { fields, primary_key: schema.primary_key.clone() })
+}
+
+/// Convert a shared golem-search field type to the WIT representation
+fn from_shared_field_type(field_type: golem_search::FieldType) -> FieldType {
+    match field_type {
+        golem_search::FieldType::Text => FieldType::Text,
+        golem_search::FieldType::Keyword => FieldType::Keyword,
+        golem_search::FieldType::Integer => FieldType::Integer,
+        golem_search::FieldType::Float => FieldType::Float,
+        golem_search::FieldType::Boolean => FieldType::Boolean,
+        golem_search::FieldType::Date => FieldType::Date,
+        golem_search::FieldType::GeoPoint => FieldType::GeoPoint,
+        golem_search::FieldType::Int64 => FieldType::Int64,
+        golem_search::FieldType::Decimal => FieldType::Decimal,
+    }
+}
+
+/// Convert a shared golem-search schema to the WIT schema
+fn from_shared_schema(schema: &golem_search::Schema) -> Schema {
+    Schema {
+        fields: schema.fields.iter().map(|f| SchemaField {
+            name: f.name.clone(),
+            field_type: from_shared_field_type(f.field_type),
+            required: f.required,
+            facet: f.facet,
+            sort: f.sort,
+            index: f.index,
+            analysis: f.analysis.as_ref().and_then(|a| serde_json::to_string(a).ok()),
+            ui: f.ui.as_ref().and_then(|ui| serde_json::to_string(ui).ok()),
+        }).collect(),
+        primary_key: schema.primary_key.clone(),
+    }
+}
+
+/// Convert shared facet display metadata to the WIT record
+fn from_shared_facet_metadata(metadata: ui_metadata::FacetMetadata) -> FacetMetadata {
+    FacetMetadata {
+        field: metadata.field,
+        display_name: metadata.display_name,
+        widget: match metadata.widget {
+            ui_metadata::FacetWidget::Checkbox => FacetWidget::Checkbox,
+            ui_metadata::FacetWidget::Radio => FacetWidget::Radio,
+            ui_metadata::FacetWidget::Dropdown => FacetWidget::Dropdown,
+            ui_metadata::FacetWidget::Toggle => FacetWidget::Toggle,
+            ui_metadata::FacetWidget::Range => FacetWidget::Range,
+            ui_metadata::FacetWidget::DateRange => FacetWidget::DateRange,
+        },
+        sort_priority: metadata.sort_priority,
+    }
+}
+
+/// WIT form of a shared query tree
+fn from_shared_query_ast(ast: query_ast::QueryAst) -> QueryAst {
+    let bound = |bound: Option<golem_search::query::RangeBound>| {
+        bound.map(|bound| RangeBound { value: bound.value, inclusive: bound.inclusive })
+    };
+    let nodes = ast
+        .nodes
+        .into_iter()
+        .map(|node| match node {
+            AstNode::MatchAll => QueryAstNode::MatchAll,
+            AstNode::Term { field, value } => QueryAstNode::Term(QueryTerm { field, value }),
+            AstNode::Phrase { field, text, slop } => QueryAstNode::Phrase(QueryPhrase { field, text, slop }),
+            AstNode::Wildcard { field, pattern } => QueryAstNode::Wildcard(QueryPattern { field, pattern }),
+            AstNode::Regex { field, pattern } => QueryAstNode::Regex(QueryPattern { field, pattern }),
+            AstNode::Range { field, lower, upper } => {
+                QueryAstNode::Range(QueryRange { field, lower: bound(lower), upper: bound(upper) })
+            }
+            AstNode::Bool { must, should, must_not } => QueryAstNode::Boolean(QueryBool { must, should, must_not }),
+        })
+        .collect();
+    QueryAst { nodes, root: ast.root }
+}
+
+/// Shared form of a WIT query tree
+fn to_shared_query_ast(ast: QueryAst) -> query_ast::QueryAst {
+    let bound = |bound: Option<RangeBound>| {
+        bound.map(|bound| golem_search::query::RangeBound { value: bound.value, inclusive: bound.inclusive })
+    };
+    let nodes = ast
+        .nodes
+        .into_iter()
+        .map(|node| match node {
+            QueryAstNode::MatchAll => AstNode::MatchAll,
+            QueryAstNode::Term(QueryTerm { field, value }) => AstNode::Term { field, value },
+            QueryAstNode::Phrase(QueryPhrase { field, text, slop }) => AstNode::Phrase { field, text, slop },
+            QueryAstNode::Wildcard(QueryPattern { field, pattern }) => AstNode::Wildcard { field, pattern },
+            QueryAstNode::Regex(QueryPattern { field, pattern }) => AstNode::Regex { field, pattern },
+            QueryAstNode::Range(QueryRange { field, lower, upper }) => {
+                AstNode::Range { field, lower: bound(lower), upper: bound(upper) }
+            }
+            QueryAstNode::Boolean(QueryBool { must, should, must_not }) => AstNode::Bool { must, should, must_not },
+        })
+        .collect();
+    query_ast::QueryAst { nodes, root: ast.root }
+}
+
+/// Get a document through the router, keeping only the selected fields
+async fn get_projected(provider: &RouterProvider, index: &str, id: &str, mask: Option<&FieldMask>) -> SearchResult<Option<Doc>> {
+    let doc = provider.get(index, id).await.map_err(map_shared_error)?;
+    Ok(doc.map(|doc| from_shared_doc(match mask {
+        Some(mask) => mask.project_doc(doc),
+        None => doc,
+    })))
+}
+
+impl Guest for Component {
+    type SearchSession = RouterSession;
+    type IndexWatch = RouterIndexWatch;
+    type ReindexJob = RouterReindexJob;
+
+    fn search(index: String, query: SearchQuery) -> SearchResult<SearchResults> {
+        let query = to_shared_query(query)?;
+        block_on(async {
+            let provider = shared_provider().await?;
+            provider.search(&index, &query).await.map(from_shared_results).map_err(map_shared_error)
+        })
+    }
+
+    fn search_with_lookup(_index: String, _query: SearchQuery, _lookup: String) -> SearchResult<SearchResults> {
+        Err(unsupported("search-with-lookup"))
+    }
+
+    fn estimate_query(_index: String, _query: SearchQuery) -> SearchResult<String> {
+        Err(unsupported("estimate-query"))
+    }
+
+    fn parse_query(text: String) -> SearchResult<QueryAst> {
+        query_ast::parse_query(&text).map(from_shared_query_ast).map_err(map_shared_error)
+    }
+
+    fn render_query(ast: QueryAst) -> SearchResult<String> {
+        query_ast::render_query(&to_shared_query_ast(ast)).map_err(map_shared_error)
+    }
+
+    fn upsert(index: String, doc: Doc) -> SearchResult<()> {
+        Self::batch_upsert(index, vec![doc])
+    }
+
+    fn upsert_with_token(_index: String, _doc: Doc) -> SearchResult<String> {
+        Err(unsupported("upsert-with-token"))
+    }
+
+    fn get(index: String, id: String, fields: Option<Vec<String>>) -> SearchResult<Option<Doc>> {
+        let mask = FieldMask::from_fields(fields.as_deref()).map_err(map_shared_error)?;
+        block_on(async {
+            let provider = shared_provider().await?;
+            get_projected(&provider, &index, &id, mask.as_ref()).await
+        })
+    }
+
+    fn get_many(index: String, ids: Vec<String>, fields: Option<Vec<String>>) -> SearchResult<Vec<Option<Doc>>> {
+        let mask = FieldMask::from_fields(fields.as_deref()).map_err(map_shared_error)?;
+        block_on(async {
+            let provider = shared_provider().await?;
+            let mut docs = Vec::with_capacity(ids.len());
+            for id in &ids {
+                docs.push(get_projected(&provider, &index, id, mask.as_ref()).await?);
+            }
+            Ok(docs)
+        })
+    }
+
+    fn exists(index: String, id: String) -> SearchResult<bool> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            provider.get(&index, &id).await.map(|doc| doc.is_some()).map_err(map_shared_error)
+        })
+    }
+
+    fn delete(index: String, id: String) -> SearchResult<()> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            provider.delete(&index, &id).await.map_err(map_shared_error)
+        })
+    }
+
+    fn create_index(name: String, schema: Option<Schema>) -> SearchResult<()> {
+        let schema = schema.as_ref().map(to_shared_schema).transpose()?;
+        block_on(async {
+            let provider = shared_provider().await?;
+            provider.create_index(&name, schema.as_ref()).await.map_err(map_shared_error)
+        })
+    }
+
+    fn ensure_index(_name: String, _schema: Option<Schema>) -> SearchResult<bool> {
+        Err(unsupported("ensure-index"))
+    }
+
+    fn create_index_from_template(_name: String, _template: String, _overrides: Option<String>) -> SearchResult<String> {
+        Err(unsupported("create-index-from-template"))
+    }
+
+    fn export_settings(_index: String) -> SearchResult<String> {
+        Err(unsupported("export-settings"))
+    }
+
+    fn apply_settings(_index: String, _settings: String) -> SearchResult<()> {
+        Err(unsupported("apply-settings"))
+    }
+
+    fn diff_settings(_index: String, _desired: String, _reconcile: bool) -> SearchResult<String> {
+        Err(unsupported("diff-settings"))
+    }
+
+    fn delete_index(name: String, dry_run: bool) -> SearchResult<String> {
+        dry_run::validate_target(&name).map_err(map_shared_error)?;
+        block_on(async {
+            let provider = shared_provider().await?;
+            let routed = provider.routes().provider_for(&name).unwrap_or("no route");
+            // The backends do not count documents through the router, so the plan has no estimate
+            let plan = OperationPlan::new("router", "delete_index", &name, dry_run)
+                .step(format!("delete_index {} on {}", name, routed));
+            let plan = dry_run::execute(plan, async {
+                provider.delete_index(&name).await.map(|_| None)
+            })
+            .await
+            .map_err(map_shared_error)?;
+            to_json(&plan)
+        })
+    }
+
+    fn delete_by_query(_index: String, _filters: Vec<String>, _dry_run: bool) -> SearchResult<String> {
+        Err(unsupported("delete-by-query"))
+    }
+
+    fn clear_index(_index: String, _dry_run: bool) -> SearchResult<String> {
+        Err(unsupported("clear-index"))
+    }
+
+    fn list_indexes() -> SearchResult<Vec<String>> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            provider.list_indexes().await.map_err(map_shared_error)
+        })
+    }
+
+    fn create_snapshot(_index: String) -> SearchResult<String> {
+        Err(unsupported("create-snapshot"))
+    }
+
+    fn restore_snapshot(_snapshot_id: String) -> SearchResult<String> {
+        Err(unsupported("restore-snapshot"))
+    }
+
+    fn get_schema(index: String) -> SearchResult<Schema> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            provider.get_schema(&index).await.map(|schema| from_shared_schema(&schema)).map_err(map_shared_error)
+        })
+    }
+
+    fn get_capabilities() -> SearchCapabilities {
+        // What the router itself passes through; describe reports each backend
+        let routes = IndexRoutes::from_env();
+        let mut features = BTreeMap::new();
+        features.insert("routes", serde_json::json!(routes.providers()));
+        SearchCapabilities {
+            supports_index_creation: true,
+            supports_schema_definition: true,
+            supports_facets: true,
+            supports_highlighting: true,
+            supports_full_text_search: true,
+            supports_vector_search: false,
+            supports_streaming: false,
+            supports_geo_search: false,
+            supports_aggregations: false,
+            max_batch_size: None,
+            max_query_size: None,
+            supported_field_types: vec![
+                FieldType::Text,
+                FieldType::Keyword,
+                FieldType::Integer,
+                FieldType::Float,
+                FieldType::Boolean,
+                FieldType::Date,
+                FieldType::GeoPoint,
+                FieldType::Int64,
+                FieldType::Decimal,
+            ],
+            provider_features: serde_json::to_string(&features).unwrap_or_default(),
+        }
+    }
+
+    fn batch_upsert(index: String, docs: Vec<Doc>) -> SearchResult<()> {
+        Self::batch_upsert_with_pipeline(index, docs, None)
+    }
+
+    fn upsert_with_pipeline(index: String, doc: Doc, pipeline: Option<String>) -> SearchResult<()> {
+        Self::batch_upsert_with_pipeline(index, vec![doc], pipeline)
+    }
+
+    fn batch_upsert_with_pipeline(index: String, docs: Vec<Doc>, pipeline: Option<String>) -> SearchResult<()> {
+        let docs: Vec<_> = docs.into_iter().map(to_shared_doc).collect();
+        block_on(async {
+            let provider = shared_provider().await?;
+            provider.upsert_many_with_pipeline(&index, &docs, pipeline.as_deref()).await.map_err(map_shared_error)
+        })
+    }
+
+    fn flush_writes(_index: Option<String>) -> SearchResult<u32> {
+        Err(unsupported("flush-writes"))
+    }
+
+    fn outbox_record(_index: String, _mutation: OutboxMutation, _idempotency_key: Option<String>) -> SearchResult<Option<u64>> {
+        Err(unsupported("outbox-record"))
+    }
+
+    fn drain_outbox() -> SearchResult<String> {
+        Err(unsupported("drain-outbox"))
+    }
+
+    fn flush() -> SearchResult<String> {
+        Err(unsupported("flush"))
+    }
+
+    fn shutdown() -> SearchResult<String> {
+        Err(unsupported("shutdown"))
+    }
+
+    fn sync(_index: String, _docs: Vec<Doc>, _dry_run: bool) -> SearchResult<String> {
+        Err(unsupported("sync"))
+    }
+
+    fn build_suggestions(_index: String, _queries: String, _config: Option<String>) -> SearchResult<String> {
+        Err(unsupported("build-suggestions"))
+    }
+
+    fn suggest(_index: String, _prefix: String, _limit: u32) -> SearchResult<String> {
+        Err(unsupported("suggest"))
+    }
+
+    fn set_ingest_pipeline(index: String, pipeline: Option<String>) -> SearchResult<()> {
+        // Pipelines are kept in the worker, where the routed backends apply them on write
+        let pipeline = pipeline.as_deref().map(ingest::IngestPipeline::from_json).transpose().map_err(map_shared_error)?;
+        ingest::set_pipeline(&index, pipeline).map_err(map_shared_error)
+    }
+
+    fn save_search(id: String, index: String, query: String, filters: Vec<String>) -> SearchResult<()> {
+        alerts::save_search(&id, &index, &query, filters).map_err(map_shared_error)
+    }
+
+    fn delete_saved_search(id: String) -> SearchResult<bool> {
+        Ok(alerts::delete_search(&id))
+    }
+
+    fn run_alerts() -> SearchResult<String> {
+        to_json(&alerts::run_alerts())
+    }
+
+    fn health_check() -> SearchResult<()> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            provider.list_indexes().await.map(|_| ()).map_err(map_shared_error)
+        })
+    }
+
+    fn get_slow_queries() -> String {
+        serde_json::to_string(&slow_query::get_slow_queries()).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    fn health_report() -> String {
+        let routes = IndexRoutes::from_env();
+        let reports: BTreeMap<_, _> = routes
+            .providers()
+            .into_iter()
+            .map(|provider| (provider, circuit_breaker::health_report(provider)))
+            .collect();
+        serde_json::to_string(&reports).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    fn metrics_prometheus() -> String {
+        metrics::render_prometheus()
+    }
+
+    fn diagnose_config() -> String {
+        RouterProvider::diagnose_config().unwrap_or_default()
+    }
+
+    fn describe() -> SearchResult<String> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            to_json(&provider.describe().await)
+        })
+    }
+
+    fn get_audit_log(since: Option<String>) -> SearchResult<String> {
+        let entries = audit::get_audit_log(since.as_deref()).map_err(map_shared_error)?;
+        to_json(&entries)
+    }
+
+    fn list_pending_tasks(_index: String) -> SearchResult<String> {
+        Err(unsupported("list-pending-tasks"))
+    }
+}
+
+/// `search-session` resource; sessions hold a connection to one provider, so the router has none
+pub struct RouterSession;
+
+impl GuestSearchSession for RouterSession {
+    fn open(_default_index: Option<String>, _user_token: Option<String>) -> SearchResult<SearchSession> {
+        Err(unsupported("search-session"))
+    }
+
+    fn search(&self, _index: Option<String>, _query: SearchQuery) -> SearchResult<SearchResults> {
+        Err(unsupported("search-session"))
+    }
+
+    fn upsert(&self, _index: Option<String>, _doc: Doc) -> SearchResult<()> {
+        Err(unsupported("search-session"))
+    }
+
+    fn get(&self, _index: Option<String>, _id: String, _fields: Option<Vec<String>>) -> SearchResult<Option<Doc>> {
+        Err(unsupported("search-session"))
+    }
+
+    fn delete(&self, _index: Option<String>, _id: String) -> SearchResult<()> {
+        Err(unsupported("search-session"))
+    }
+
+    fn set_default_index(&self, _index: Option<String>) {}
+
+    fn set_user_token(&self, _user_token: Option<String>) {}
+
+    fn consistency_token(&self) -> Option<String> {
+        None
+    }
+}
+
+/// `index-watch` resource; change cursors belong to one provider, so the router has none
+pub struct RouterIndexWatch;
+
+impl GuestIndexWatch for RouterIndexWatch {
+    fn open(_index: String, _since: Option<String>) -> SearchResult<IndexWatch> {
+        Err(unsupported("index-watch"))
+    }
+
+    fn poll(&self) -> SearchResult<String> {
+        Err(unsupported("index-watch"))
+    }
+
+    fn cursor(&self) -> String {
+        String::new()
+    }
+}
+
+/// `reindex-job` resource; a copy between indexes of two providers is not routed
+pub struct RouterReindexJob;
+
+impl GuestReindexJob for RouterReindexJob {
+    fn start(_source: String, _destination: String) -> SearchResult<ReindexJob> {
+        Err(unsupported("reindex-job"))
+    }
+
+    fn resume(_id: String) -> SearchResult<ReindexJob> {
+        Err(unsupported("reindex-job"))
+    }
+
+    fn id(&self) -> String {
+        String::new()
+    }
+
+    fn run(&self) -> SearchResult<String> {
+        Err(unsupported("reindex-job"))
+    }
+
+    fn status(&self) -> SearchResult<String> {
+        Err(unsupported("reindex-job"))
+    }
+
+    fn cancel(&self) -> SearchResult<String> {
+        Err(unsupported("reindex-job"))
+    }
+}
+
+export!(Component);
@@ -0,0 +1,305 @@
+//! Router for the golem:search interface
+//!
+//! Shards indexes across providers: each index goes to the provider its
+//! route in `SEARCH_ROUTES` names, such as `logs-*=opensearch,*=elasticsearch`.
+//! Elasticsearch and OpenSearch backends are created from their usual
+//! configuration when a route names them; any other provider takes part once
+//! its backend is registered with `golem_search::router::register`.
+//! Indexes named in `SEARCH_LANGUAGE_ROUTES` are split into one index per
+//! language.
+//!
+//! The `router-provider` world exports golem:search/core on top of
+//! [`RouterProvider`]; calls with no single backend to go to return
+//! `unsupported`.
+
+mod component;
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use log::{error, info, warn};
+
+use golem_search::{SearchError, SearchResult, Doc, SearchQuery, SearchResults, Schema};
+use golem_search::describe::ProviderDescription;
+use golem_search::diagnose;
+use golem_search::language::LanguageRoutes;
+use golem_search::router::{self, IndexRoutes, Router, ROUTES_ENV};
+use golem_search_elastic::ElasticSearchProvider;
+use golem_search_opensearch::OpenSearchProvider;
+
+/// Provider sending each index to the backend of its route
+pub struct RouterProvider {
+    router: Router,
+}
+
+impl RouterProvider {
+    /// Create a router over `SEARCH_ROUTES`, creating the built-in backends it names
+    pub async fn new() -> SearchResult<Self> {
+        let routes = IndexRoutes::from_env();
+        if routes.providers().is_empty() {
+            error!("No index routes configured");
+            return Err(SearchError::Internal(format!(
+                "{} is not set; diagnose_config lists every variable read",
+                ROUTES_ENV
+            )));
+        }
+
+        for provider in routes.providers() {
+            if router::backend(provider).is_some() {
+                continue;
+            }
+            match provider {
+                "elasticsearch" | "elastic" => {
+                    router::register(provider, Arc::new(ElasticSearchProvider::new().await?));
+                }
+                "opensearch" => router::register(provider, Arc::new(OpenSearchProvider::new().await?)),
+                _ => warn!("No backend registered for {}; its indexes fail until one is", provider),
+            }
+        }
+
+        info!("Routing indexes to {}", routes.providers().join(", "));
+        Ok(Self { router: Router::new(routes).with_languages(LanguageRoutes::from_env()) })
+    }
+
+    pub fn routes(&self) -> &IndexRoutes {
+        self.router.routes()
+    }
+
+    pub async fn search(&self, index: &str, query: &SearchQuery) -> SearchResult<SearchResults> {
+        self.router.search(index, query).await
+    }
+
+    pub async fn get(&self, index: &str, id: &str) -> SearchResult<Option<Doc>> {
+        self.router.get(index, id).await
+    }
+
+    pub async fn upsert_many(&self, index: &str, docs: &[Doc]) -> SearchResult<()> {
+        self.router.upsert_many(index, docs).await
+    }
+
+    /// Upsert documents through the provider-side ingest `pipeline`, or that of the index when `None`
+    pub async fn upsert_many_with_pipeline(&self, index: &str, docs: &[Doc], pipeline: Option<&str>) -> SearchResult<()> {
+        self.router.upsert_many_with_pipeline(index, docs, pipeline).await
+    }
+
+    pub async fn delete(&self, index: &str, id: &str) -> SearchResult<()> {
+        self.router.delete(index, id).await
+    }
+
+    pub async fn create_index(&self, name: &str, schema: Option<&Schema>) -> SearchResult<()> {
+        self.router.create_index(name, schema).await
+    }
+
+    pub async fn delete_index(&self, name: &str) -> SearchResult<()> {
+        self.router.delete_index(name).await
+    }
+
+    pub async fn get_schema(&self, index: &str) -> SearchResult<Schema> {
+        self.router.get_schema(index).await
+    }
+
+    /// Indexes of every routed backend, leaving out those the routes send elsewhere
+    pub async fn list_indexes(&self) -> SearchResult<Vec<String>> {
+        self.router.list_indexes().await
+    }
+
+    /// Description of every routed backend, by provider
+    pub async fn describe(&self) -> BTreeMap<String, ProviderDescription> {
+        self.router.describe().await
+    }
+
+    /// Every environment variable the router reads, as JSON
+    ///
+    /// The variables of the routed backends are reported by their own components.
+    pub fn diagnose_config() -> SearchResult<String> {
+        Ok(serde_json::to_string(&diagnose::diagnose_config("router"))?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use golem_search::capabilities::typesense_capability_matrix;
+    use golem_search::language::LanguageRoutes;
+    use golem_search::router::{LocalBoxFuture, RoutedBackend};
+    use golem_search::{describe, SearchConfigType, SearchHit};
+
+    /// Backend answering every call with `failure`, or else with results naming the index searched
+    struct Recorded {
+        indexes: Vec<&'static str>,
+        failure: Option<SearchError>,
+        calls: Mutex<Vec<String>>,
+    }
+
+    impl Recorded {
+        fn register(provider: &str, indexes: Vec<&'static str>, failure: Option<SearchError>) -> Arc<Self> {
+            let backend = Arc::new(Self { indexes, failure, calls: Mutex::new(Vec::new()) });
+            router::register(provider, backend.clone());
+            backend
+        }
+
+        fn calls(&self) -> Vec<String> {
+            self.calls.lock().unwrap().clone()
+        }
+
+        fn answer<T: 'static>(&self, call: String, value: T) -> LocalBoxFuture<'_, SearchResult<T>> {
+            self.calls.lock().unwrap().push(call);
+            let result = match &self.failure {
+                Some(failure) => Err(failure.clone()),
+                None => Ok(value),
+            };
+            Box::pin(async move { result })
+        }
+    }
+
+    fn results_of(index: &str) -> SearchResults {
+        SearchResults {
+            total: Some(1),
+            page: Some(1),
+            per_page: Some(10),
+            hits: vec![SearchHit {
+                id: index.to_string(),
+                score: Some(1.0),
+                content: None,
+                highlights: None,
+                explanation: None,
+                distance_meters: None,
+            }],
+            facets: None,
+            took_ms: Some(1),
+            truncated: false,
+            request_id: None,
+            groups: None,
+            next_cursor: None,
+            total_exact: Some(true),
+            facet_metadata: Vec::new(),
+            relaxations: Vec::new(),
+            original_query: None,
+            corrected_query: None,
+        }
+    }
+
+    impl RoutedBackend for Recorded {
+        fn search<'a>(&'a self, index: &'a str, _: &'a SearchQuery) -> LocalBoxFuture<'a, SearchResult<SearchResults>> {
+            self.answer(format!("search {}", index), results_of(index))
+        }
+
+        fn get<'a>(&'a self, index: &'a str, id: &'a str) -> LocalBoxFuture<'a, SearchResult<Option<Doc>>> {
+            self.answer(format!("get {} {}", index, id), None)
+        }
+
+        fn upsert_many<'a>(&'a self, index: &'a str, docs: &'a [Doc], pipeline: Option<&'a str>) -> LocalBoxFuture<'a, SearchResult<()>> {
+            let via = pipeline.map(|pipeline| format!(" via {}", pipeline)).unwrap_or_default();
+            self.answer(format!("upsert {} {}{}", index, docs.len(), via), ())
+        }
+
+        fn delete<'a>(&'a self, index: &'a str, id: &'a str) -> LocalBoxFuture<'a, SearchResult<()>> {
+            self.answer(format!("delete {} {}", index, id), ())
+        }
+
+        fn create_index<'a>(&'a self, name: &'a str, _: Option<&'a Schema>) -> LocalBoxFuture<'a, SearchResult<()>> {
+            self.answer(format!("create {}", name), ())
+        }
+
+        fn delete_index<'a>(&'a self, name: &'a str) -> LocalBoxFuture<'a, SearchResult<()>> {
+            self.answer(format!("drop {}", name), ())
+        }
+
+        fn list_indexes(&self) -> LocalBoxFuture<'_, SearchResult<Vec<String>>> {
+            let indexes = self.indexes.iter().map(|index| index.to_string()).collect();
+            self.answer("list".to_string(), indexes)
+        }
+
+        fn get_schema<'a>(&'a self, index: &'a str) -> LocalBoxFuture<'a, SearchResult<Schema>> {
+            self.answer(format!("schema {}", index), Schema { fields: Vec::new(), primary_key: None })
+        }
+
+        fn describe(&self) -> LocalBoxFuture<'_, ProviderDescription> {
+            Box::pin(async { describe::describe(typesense_capability_matrix(), None) })
+        }
+    }
+
+    fn routed(routes: &str) -> RouterProvider {
+        RouterProvider { router: Router::new(IndexRoutes::parse(routes)) }
+    }
+
+    fn unavailable() -> SearchError {
+        SearchError::Unavailable { retry_after_ms: Some(500) }
+    }
+
+    #[test]
+    fn test_operations_go_to_the_backend_of_the_first_matching_route() {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let logs = Recorded::register("rc-route-logs", vec![], None);
+        let catalog = Recorded::register("rc-route-catalog", vec![], None);
+        let provider = routed("logs-*=rc-route-logs,*=rc-route-catalog");
+
+        runtime.block_on(async {
+            let results = provider.search("logs-2024.06", &SearchQuery::match_all()).await.unwrap();
+            assert_eq!(results.hits[0].id, "logs-2024.06");
+            provider.upsert_many("products", &[Doc { id: "1".to_string(), content: "{}".to_string() }]).await.unwrap();
+            provider.upsert_many_with_pipeline("products", &[Doc { id: "2".to_string(), content: "{}".to_string() }], Some("geoip")).await.unwrap();
+            provider.delete("logs", "7").await.unwrap();
+            provider.create_index("logs-2024.07", None).await.unwrap();
+        });
+
+        assert_eq!(logs.calls(), ["search logs-2024.06", "create logs-2024.07"]);
+        assert_eq!(catalog.calls(), ["upsert products 1", "upsert products 1 via geoip", "delete logs 7"]);
+    }
+
+    #[test]
+    fn test_backend_errors_are_returned_without_falling_back_to_another_route() {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let logs = Recorded::register("rc-fail-logs", vec![], Some(unavailable()));
+        let fallback = Recorded::register("rc-fail-default", vec![], None);
+        let provider = routed("logs-*=rc-fail-logs,orders=rc-fail-missing,*=rc-fail-default");
+
+        runtime.block_on(async {
+            let error = provider.search("logs-1", &SearchQuery::match_all()).await.unwrap_err();
+            assert!(matches!(error, SearchError::Unavailable { retry_after_ms: Some(500) }));
+            assert!(provider.get("logs-1", "1").await.is_err());
+
+            // A route to a provider without a backend fails rather than using the catch-all
+            let error = provider.get_schema("orders").await.unwrap_err();
+            assert!(matches!(error, SearchError::Internal(message) if message.contains("rc-fail-missing")));
+        });
+
+        assert_eq!(logs.calls(), ["search logs-1", "get logs-1 1"]);
+        assert!(fallback.calls().is_empty());
+
+        let unrouted = runtime.block_on(routed("logs-*=rc-fail-logs").search("users", &SearchQuery::match_all()));
+        assert!(matches!(unrouted, Err(SearchError::InvalidQuery(_))));
+    }
+
+    #[test]
+    fn test_fanned_out_operations_fail_with_the_first_backend_error() {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        Recorded::register("rc-fan-ok", vec!["products", "products_en", "logs-stray"], None);
+        let failing = Recorded::register("rc-fan-failing", vec!["products_de"], Some(unavailable()));
+        let routes = "products_de=rc-fan-failing,logs-*=rc-fan-unregistered,*=rc-fan-ok";
+        let languages = LanguageRoutes::parse(r#"{"products": {"languages": ["en", "de"]}}"#);
+        let provider = RouterProvider { router: Router::new(IndexRoutes::parse(routes)).with_languages(languages) };
+
+        runtime.block_on(async {
+            // Listing stops at the failing backend instead of returning a partial list
+            let error = provider.list_indexes().await.unwrap_err();
+            assert!(matches!(error, SearchError::Unavailable { .. }));
+
+            // Describing skips the route without a backend and keeps the failing one
+            let described: Vec<String> = provider.describe().await.into_keys().collect();
+            assert_eq!(described, ["rc-fan-failing", "rc-fan-ok"]);
+
+            // A search over every language index fails with the error of the one that failed
+            let error = provider.search("products", &SearchQuery::match_all()).await.unwrap_err();
+            assert!(matches!(error, SearchError::Unavailable { retry_after_ms: Some(500) }));
+
+            // The same search restricted to a healthy language succeeds
+            let mut query = SearchQuery::match_all();
+            query.config = Some(SearchConfigType { language: Some("en".to_string()), ..Default::default() });
+            let results = provider.search("products", &query).await.unwrap();
+            assert_eq!(results.hits[0].id, "products_en");
+        });
+
+        assert_eq!(failing.calls(), ["list", "search products_de"]);
+    }
+}
@@ -4,22 +4,123 @@
 //! It features built-in typo tolerance, faceted search, and geo-search capabilities.
 
 use anyhow::Result;
-use log::{debug, error, info};
-use std::collections::HashMap;
+use log::{debug, error, info, warn};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::rc::Rc;
+use std::sync::Arc;
 use std::time::Duration;
-use reqwest::{Client, Method, header::{HeaderMap, HeaderValue, CONTENT_TYPE}};
+use golem_search::http::{self, Client, Method, header::{HeaderMap, HeaderValue, CONTENT_ENCODING, CONTENT_TYPE}};
+use serde::Deserialize;
 use serde_json::{Value, json};
-use url::Url;
+use golem_search::{IndexSettings, LookupSpec, SettingsDiff};
+use golem_search::lookup::{align_to_ids, check_lookup_complete, fetch_in_batches, lookup_keys_from_contents, LookupTable, MAX_LOOKUP_DOCS};
+use golem_search::raw::{group_key_text, raw_document_id, raw_explanation, raw_to_json, RawValue};
+use golem_search::analysis::{parse_field_analysis, parse_normalization, unsupported_analysis, FieldAnalysis};
+use golem_search::alerts;
+use golem_search::audit;
+use golem_search::change_feed::ChangeBatch;
+use golem_search::checksum;
+use golem_search::numeric::{self, DECIMAL_META_KEY};
+use golem_search::outbox::{self, DrainReport, Mutation};
+use golem_search::write_buffer;
+use golem_search::tasks::PendingTasks;
+use golem_search::batching::{submit_adaptive, AdaptiveBatchConfig, AdaptiveBatcher};
+use golem_search::cache::{self, CacheKind, CacheQuery};
+use golem_search::capabilities::typesense_capability_matrix;
+use golem_search::circuit_breaker;
+use golem_search::shutdown::{self, ShutdownReport};
+use golem_search::compiled_query::{self, TemplateQuery};
+use golem_search::compression::Compression;
+use golem_search::config::ConfigProfile;
+use golem_search::consistency::{ConsistencyToken, WritePosition};
+use golem_search::cost::{self, CostEstimate, IndexStats};
+use golem_search::cursor::{self, CursorPosition, PageCursor};
+use golem_search::deadline;
+use golem_search::delta_sync::{self, IndexFingerprint, SyncReport};
+use golem_search::describe::{self, ProviderDescription};
+use golem_search::diagnose;
+use golem_search::events::{self, LifecycleEventKind};
+use golem_search::field_mask::FieldMask;
+use golem_search::geo::{self, GeoDistanceSort, GeoPolygonFilter};
+use golem_search::highlight;
+use golem_search::id_set::IdSetFilter;
+use golem_search::ui_metadata::{self, parse_field_ui, UI_META_KEY};
+use golem_search::ingest;
+use golem_search::instance::{self, ProviderCache};
+use golem_search::dry_run::{self, OperationPlan};
+use golem_search::endpoint_pool::{self, EndpointPool};
+use golem_search::facets::{parse_facets, ClientFacets, FacetSpec};
+use golem_search::json;
+use golem_search::types::{QueryText, MATCH_ALL};
+use golem_search::query::{parse_fuzziness, parse_minimum_should_match, Fuzziness, MinimumShouldMatch, ParsedQuery, PatternLimits};
+use golem_search::quota;
+#[cfg(feature = "durability")]
+use golem_search::reindex::{self, JobStore, ReindexStatus, SourcePage, JOBS_INDEX, REINDEX_PAGE_SIZE};
+#[cfg(feature = "durability")]
+use golem_search::router::LocalBoxFuture;
+use golem_search::query_split::{self, SplitLimits, SplitQuery, SplitResults};
+use golem_search::rate_limit::{self, Throttle};
+use golem_search::request_id::{self, REQUEST_ID_HEADER};
+use golem_search::rescue::{self, RescuePlan, RescueQuery, RescueResults, Relaxation};
+use golem_search::session::{self, SessionContext};
+use golem_search::metrics::{self, ErrorKind};
+use golem_search::telemetry;
+use golem_search::version::{self, ServerVersion};
+use golem_search::watch::{ChangeSummary, WatchCursor};
+use golem_search::response_limits::ResponseLimits;
+use golem_search::retry::RetryPolicy;
+use golem_search::sampling;
+use golem_search::schema::check_schema_compatibility;
+use golem_search::slow_query;
+use golem_search::blob;
+use golem_search::snapshot::{self, SnapshotInfo, RESTORE_BATCH_SIZE};
+use golem_search::spellfix::{self, Correction};
+use golem_search::suggestions::{self, PopularQuery, Suggestion, SuggestionsBuilder, SuggestionsConfig};
+use golem_search::templates::{self, IndexTemplate, TemplateOverrides};
+use golem_search::query_ast::{self, AstNode};
+use golem_search::provider_params::{parse_provider_params, to_query_pairs};
 
 // Use the generated WIT types
 use golem::search::types::{
-    SearchError, Doc, SearchQuery, SearchResults, Schema,
-    SearchCapabilities, FieldType, SchemaField,
+    SearchError, Doc, SearchQuery, SearchResults, SearchGroup, Schema,
+    SearchCapabilities, FieldType, SchemaField, BackpressureInfo, OutboxMutation, FacetMetadata, FacetWidget,
+    QueryAst, QueryAstNode, QueryBool, QueryPattern, QueryPhrase, QueryRange, QueryTerm, RangeBound,
 };
 
 // Helper type alias
 type SearchResult<T> = Result<T, SearchError>;
 
+/// Search parameters that may be supplied through `provider-params`
+pub const TYPESENSE_ALLOWED_PARAMS: &[&str] = &[
+    "query_by_weights",
+    "prefix",
+    "infix",
+    "num_typos",
+    "typo_tokens_threshold",
+    "drop_tokens_threshold",
+    "prioritize_exact_match",
+    "exhaustive_search",
+    "group_by",
+    "group_limit",
+    "pinned_hits",
+    "hidden_hits",
+    "search_cutoff_ms",
+    "use_cache",
+    "cache_ttl",
+    "stopwords",
+];
+
+/// Maximum number of IDs in a single `get_many` export filter
+const GET_MANY_CHUNK_SIZE: usize = 250;
+
+/// Longest query string Typesense accepts on a search URL; longer searches go through `multi_search`
+const MAX_SEARCH_QUERY_LENGTH: usize = 4000;
+
+/// Header carrying a session's user token, which Typesense analytics attributes events to
+const USER_ID_HEADER: &str = "X-TYPESENSE-USER-ID";
+
 /// Configuration for the Typesense client
 #[derive(Debug, Clone)]
 pub struct TypesenseConfig {
@@ -27,11 +128,24 @@ pub struct TypesenseConfig {
     pub api_key: String,
     pub timeout: Duration,
     pub max_retries: u32,
+
+    /// Compression of document imports
+    pub compression: Compression,
 }
 
 impl TypesenseConfig {
     /// Create configuration from environment variables
     pub fn from_env() -> Result<Self> {
+        if let Some(profile) = ConfigProfile::active("typesense")? {
+            return Ok(Self {
+                endpoint: profile.endpoint,
+                api_key: profile.api_key.unwrap_or_default(),
+                timeout: profile.timeout,
+                max_retries: profile.max_retries,
+                compression: Compression::from_env(),
+            });
+        }
+
         let endpoint = std::env::var("SEARCH_PROVIDER_ENDPOINT")
             .or_else(|_| std::env::var("TYPESENSE_ENDPOINT"))
             .unwrap_or_else(|_| "http://localhost:8108".to_string());
@@ -55,6 +169,7 @@ impl TypesenseConfig {
             api_key,
             timeout: Duration::from_secs(timeout),
             max_retries,
+            compression: Compression::from_env(),
         })
     }
 }
@@ -63,7 +178,8 @@ impl TypesenseConfig {
 pub struct TypesenseClient {
     config: TypesenseConfig,
     http_client: Client,
-    base_url: Url,
+    endpoints: Arc<EndpointPool>,
+    throttle: Arc<Throttle>,
 }
 
 impl TypesenseClient {
@@ -75,35 +191,42 @@ impl TypesenseClient {
 
         let http_client = Client::builder()
             .timeout(config.timeout)
+            .retry(RetryPolicy::new(config.max_retries))
+            .accept_compressed(config.compression.is_enabled())
             .default_headers(headers)
             .build()
             .map_err(|e| anyhow::anyhow!("Failed to create HTTP client: {}", e))?;
 
-        let base_url = Url::parse(&config.endpoint)
-            .map_err(|e| anyhow::anyhow!("Invalid endpoint URL: {}", e))?;
+        let endpoints = endpoint_pool::pool(&config.endpoint)?;
 
         Ok(Self {
             config,
             http_client,
-            base_url,
+            endpoints,
+            throttle: rate_limit::throttle("typesense"),
         })
     }
 
     /// Execute an HTTP request
-    fn request_sync(&self, method: Method, path: &str, body: Option<Value>) -> Result<reqwest::Response> {
-        let url = self.base_url.join(path)
-            .map_err(|e| anyhow::anyhow!("Failed to build URL: {}", e))?;
-
-        let mut request = self.http_client.request(method, url);
-
-        if let Some(body) = body {
-            request = request.json(&body);
-        }
+    fn request_sync(&self, method: Method, path: &str, body: Option<Value>) -> Result<golem_search::http::Response> {
+        let request_id = request_id::current_or_generate();
+        debug!("[{}] {} {}", request_id, method, path);
+        let user_token = session::current_user_token();
+
+        self.throttle.send(|| self.endpoints.send(&method, path, |url| {
+            let mut request = self.http_client
+                .request(method.clone(), url)
+                .header(REQUEST_ID_HEADER, request_id.as_str());
+            if let Some(ref user_token) = user_token {
+                request = request.header(USER_ID_HEADER, user_token.as_str());
+            }
 
-        let response = request.send()
-            .map_err(|e| anyhow::anyhow!("Request failed: {}", e))?;
+            if let Some(ref body) = body {
+                request = request.json(body);
+            }
 
-        Ok(response)
+            request.send()
+        }))
     }
 
     /// Create a collection (Typesense equivalent of index)
@@ -111,7 +234,7 @@ impl TypesenseClient {
         let response = self.request_sync(Method::POST, "collections", Some(schema))?;
         
         if response.status().is_success() {
-            let result: Value = response.json()
+            let result: Value = json::from_response(response)
                 .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?;
             Ok(result)
         } else {
@@ -121,13 +244,126 @@ impl TypesenseClient {
         }
     }
 
+    /// Patch a collection schema in place; Typesense only accepts field changes here
+    pub async fn update_collection(&self, name: &str, schema: Value) -> Result<Value> {
+        let path = format!("collections/{}", name);
+        let response = self.request_sync(Method::PATCH, &path, Some(schema))?;
+        
+        if response.status().is_success() {
+            let result: Value = json::from_response(response)
+                .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?;
+            Ok(result)
+        } else {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            Err(anyhow::anyhow!("Failed to update collection: {}", error_text))
+        }
+    }
+
+    /// Create or replace a synonym rule of a collection
+    pub async fn upsert_synonym(&self, collection: &str, id: &str, synonym: Value) -> Result<Value> {
+        let path = format!("collections/{}/synonyms/{}", collection, id);
+        let response = self.request_sync(Method::PUT, &path, Some(synonym))?;
+        
+        if response.status().is_success() {
+            let result: Value = json::from_response(response)
+                .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?;
+            Ok(result)
+        } else {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            Err(anyhow::anyhow!("Failed to upsert synonym: {}", error_text))
+        }
+    }
+
+    /// Every synonym rule of a collection
+    pub async fn list_synonyms(&self, collection: &str) -> Result<Vec<Value>> {
+        let path = format!("collections/{}/synonyms", collection);
+        let response = self.request_sync(Method::GET, &path, None)?;
+        
+        if response.status().is_success() {
+            let result: Value = json::from_response(response)
+                .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?;
+            Ok(result["synonyms"].as_array().cloned().unwrap_or_default())
+        } else {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            Err(anyhow::anyhow!("Failed to list synonyms: {}", error_text))
+        }
+    }
+
+    /// Delete a synonym rule of a collection
+    pub async fn delete_synonym(&self, collection: &str, id: &str) -> Result<()> {
+        let path = format!("collections/{}/synonyms/{}", collection, http::path_segment(id));
+        let response = self.request_sync(Method::DELETE, &path, None)?;
+        
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            Err(anyhow::anyhow!("Failed to delete synonym: {}", error_text))
+        }
+    }
+
+    /// Words of a stopword set, empty when there is no such set
+    pub async fn get_stopwords(&self, set: &str) -> Result<Vec<String>> {
+        let path = format!("stopwords/{}", http::path_segment(set));
+        let response = self.request_sync(Method::GET, &path, None)?;
+        
+        if response.status().is_success() {
+            let result: Value = json::from_response(response)
+                .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?;
+            Ok(serde_json::from_value(result["stopwords"]["stopwords"].clone()).unwrap_or_default())
+        } else if response.status().as_u16() == 404 {
+            Ok(Vec::new())
+        } else {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            Err(anyhow::anyhow!("Failed to get stopwords: {}", error_text))
+        }
+    }
+
+    /// Create or replace a stopword set, deleting it when `words` is empty
+    pub async fn put_stopwords(&self, set: &str, words: &[String]) -> Result<()> {
+        let path = format!("stopwords/{}", http::path_segment(set));
+        let response = if words.is_empty() {
+            self.request_sync(Method::DELETE, &path, None)?
+        } else {
+            self.request_sync(Method::PUT, &path, Some(json!({ "stopwords": words })))?
+        };
+        
+        if response.status().is_success() || (words.is_empty() && response.status().as_u16() == 404) {
+            Ok(())
+        } else {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            Err(anyhow::anyhow!("Failed to put stopwords: {}", error_text))
+        }
+    }
+
+    /// Detect the server version (`GET /debug`)
+    pub async fn detect_version(&self) -> Result<ServerVersion> {
+        let response = self.request_sync(Method::GET, "debug", None)?;
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!("Failed to get debug info: HTTP {}", response.status()));
+        }
+        
+        let info: Value = json::from_response(response)
+            .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?;
+        info.pointer("/version")
+            .and_then(Value::as_str)
+            .and_then(ServerVersion::parse)
+            .ok_or_else(|| anyhow::anyhow!("No version in debug info response"))
+    }
+
     /// Delete a collection
     pub async fn delete_collection(&self, name: &str) -> Result<Value> {
         let path = format!("collections/{}", name);
         let response = self.request_sync(Method::DELETE, &path, None)?;
         
         if response.status().is_success() {
-            let result: Value = response.json()
+            let result: Value = json::from_response(response)
                 .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?;
             Ok(result)
         } else {
@@ -142,7 +378,7 @@ impl TypesenseClient {
         let response = self.request_sync(Method::GET, "collections", None)?;
         
         if response.status().is_success() {
-            let collections: Vec<Value> = response.json()
+            let collections: Vec<Value> = json::from_response(response)
                 .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?;
             
             let names = collections.into_iter()
@@ -167,7 +403,7 @@ impl TypesenseClient {
         let response = self.request_sync(Method::POST, &path, Some(document))?;
         
         if response.status().is_success() {
-            let result: Value = response.json()
+            let result: Value = json::from_response(response)
                 .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?;
             Ok(result)
         } else {
@@ -183,7 +419,7 @@ impl TypesenseClient {
         let response = self.request_sync(Method::POST, &path, Some(document))?;
         
         if response.status().is_success() {
-            let result: Value = response.json()
+            let result: Value = json::from_response(response)
                 .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?;
             Ok(result)
         } else {
@@ -199,7 +435,7 @@ impl TypesenseClient {
         let response = self.request_sync(Method::GET, &path, None)?;
         
         if response.status().is_success() {
-            let result: Value = response.json()
+            let result: Value = json::from_response(response)
                 .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?;
             Ok(Some(result))
         } else if response.status().as_u16() == 404 {
@@ -211,13 +447,115 @@ impl TypesenseClient {
         }
     }
 
+    /// Upsert documents given as JSON lines in one request, compressed as configured
+    ///
+    /// Typesense answers 200 even when some documents fail; the first failure is returned as an error.
+    pub async fn import_documents(&self, collection: &str, jsonl: String) -> Result<()> {
+        let path = format!("collections/{}/documents/import", collection);
+        let compression = self.config.compression;
+        let body = compression.compress(jsonl.into_bytes())?;
+        let request_id = request_id::current_or_generate();
+        let response = self.throttle.send(|| self.endpoints.send(&Method::POST, &path, |mut url| {
+            url.query_pairs_mut().append_pair("action", "upsert");
+            let mut request = self.http_client
+                .post(url)
+                .header(REQUEST_ID_HEADER, request_id.as_str())
+                .header(CONTENT_TYPE, "text/plain");
+            if let Some(encoding) = compression.content_encoding() {
+                request = request.header(CONTENT_ENCODING, encoding);
+            }
+            request.body(body.clone()).send()
+        }))?;
+        
+        if !response.status().is_success() {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(anyhow::anyhow!("Failed to import documents: {}", error_text));
+        }
+        let body = response.text()
+            .map_err(|e| anyhow::anyhow!("Failed to read response: {}", e))?;
+        let failure = body.lines()
+            .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+            .find(|result| result.get("success").and_then(Value::as_bool) == Some(false));
+        match failure {
+            Some(result) => Err(anyhow::anyhow!("Failed to import documents: {}", result)),
+            None => Ok(()),
+        }
+    }
+
+    /// Export the documents matching a filter, or every document, as JSON lines
+    pub async fn export_documents(&self, collection: &str, filter_by: Option<&str>, include_fields: Option<&[String]>) -> Result<String> {
+        let path = format!("collections/{}/documents/export", collection);
+        let request_id = request_id::current_or_generate();
+        let response = self.throttle.send(|| self.endpoints.send(&Method::GET, &path, |mut url| {
+            if let Some(filter_by) = filter_by {
+                url.query_pairs_mut().append_pair("filter_by", filter_by);
+            }
+            if let Some(include_fields) = include_fields {
+                url.query_pairs_mut().append_pair("include_fields", &include_fields.join(","));
+            }
+            self.http_client
+                .get(url)
+                .header(REQUEST_ID_HEADER, request_id.as_str())
+                .send()
+        }))?;
+        
+        if response.status().is_success() {
+            response.text()
+                .map_err(|e| anyhow::anyhow!("Failed to read response: {}", e))
+        } else {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            Err(anyhow::anyhow!("Failed to export documents: {}", error_text))
+        }
+    }
+
+    /// Delete the documents selected by a `filter_by` or `truncate` parameter, returning how many were deleted
+    pub async fn delete_documents(&self, collection: &str, param: (&str, &str)) -> Result<u64> {
+        let query = url::form_urlencoded::Serializer::new(String::new())
+            .append_pair(param.0, param.1)
+            .finish();
+        let path = format!("collections/{}/documents?{}", collection, query);
+        let response = self.request_sync(Method::DELETE, &path, None)?;
+
+        if response.status().is_success() {
+            let result: Value = json::from_response(response)
+                .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?;
+            result.pointer("/num_deleted")
+                .and_then(Value::as_u64)
+                .ok_or_else(|| anyhow::anyhow!("No num_deleted in response"))
+        } else {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            Err(anyhow::anyhow!("Failed to delete documents: {}", error_text))
+        }
+    }
+
+    /// Count the documents matching a filter with an empty wildcard search
+    pub async fn count_matching(&self, collection: &str, filter_by: &str) -> Result<u64> {
+        let result = self.search(collection, &[("q", "*"), ("filter_by", filter_by), ("per_page", "0")]).await?;
+        result.pointer("/found")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| anyhow::anyhow!("No found count in search response"))
+    }
+
+    /// Check whether a document exists, fetching only its `id` field
+    ///
+    /// A missing collection is an error rather than an absent document.
+    pub async fn document_exists(&self, collection: &str, id: &str) -> Result<bool> {
+        let path = format!("collections/{}/documents/{}?include_fields=id", collection, http::path_segment(id));
+        let response = self.request_sync(Method::GET, &path, None)?;
+        let status = response.status().as_u16();
+        document_presence(status, &response.text().unwrap_or_default())
+    }
+
     /// Delete a document by ID
     pub async fn delete_document(&self, collection: &str, id: &str) -> Result<Value> {
         let path = format!("collections/{}/documents/{}", collection, id);
         let response = self.request_sync(Method::DELETE, &path, None)?;
         
         if response.status().is_success() {
-            let result: Value = response.json()
+            let result: Value = json::from_response(response)
                 .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?;
             Ok(result)
         } else {
@@ -229,20 +567,63 @@ impl TypesenseClient {
 
     /// Search documents
     pub async fn search(&self, collection: &str, params: &[(&str, &str)]) -> Result<Value> {
+        let body = self.search_raw(collection, params).await?;
+        json::from_str(&body)
+            .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))
+    }
+
+    /// Search documents, returning the unparsed response body
+    pub async fn search_raw(&self, collection: &str, params: &[(&str, &str)]) -> Result<String> {
+        let query_length: usize = params.iter().map(|(k, v)| k.len() + v.len() + 2).sum();
+        if query_length > MAX_SEARCH_QUERY_LENGTH {
+            return self.multi_search_raw(collection, params).await;
+        }
         let path = format!("collections/{}/documents/search", collection);
-        let mut url = self.base_url.join(&path)?;
+        let request_id = request_id::current_or_generate();
+        let user_token = session::current_user_token();
+        let response = self.throttle.send(|| self.endpoints.send(&Method::GET, &path, |mut url| {
+            // Add query parameters
+            for (key, value) in params {
+                url.query_pairs_mut().append_pair(key, value);
+            }
+
+            let mut request = self.http_client
+                .get(url)
+                .header(REQUEST_ID_HEADER, request_id.as_str());
+            if let Some(ref user_token) = user_token {
+                request = request.header(USER_ID_HEADER, user_token.as_str());
+            }
+            request.send()
+        }))?;
         
-        // Add query parameters
-        for (key, value) in params {
-            url.query_pairs_mut().append_pair(key, value);
+        if response.status().is_success() {
+            response.text()
+                .map_err(|e| anyhow::anyhow!("Failed to read response: {}", e))
+        } else {
+            let error_text = response.text()
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            Err(anyhow::anyhow!("Search failed: {}", error_text))
         }
+    }
 
-        let response = self.http_client.get(url).send()?;
+    /// Search documents through `multi_search`, which takes the params in the request body
+    async fn multi_search_raw(&self, collection: &str, params: &[(&str, &str)]) -> Result<String> {
+        let mut search: serde_json::Map<String, Value> = params.iter()
+            .map(|(k, v)| (k.to_string(), json!(v)))
+            .collect();
+        search.insert("collection".to_string(), json!(collection));
+        let response = self.request_sync(Method::POST, "multi_search", Some(json!({ "searches": [search] })))?;
         
         if response.status().is_success() {
-            let result: Value = response.json()
+            let mut body: Value = json::from_response(response)
                 .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?;
-            Ok(result)
+            let result = body["results"].get_mut(0).map(Value::take)
+                .ok_or_else(|| anyhow::anyhow!("Search failed: multi_search returned no result"))?;
+            // Failed searches are reported inside a successful multi_search response
+            if let Some(error) = result.get("error").and_then(Value::as_str) {
+                return Err(anyhow::anyhow!("Search failed: {}", error));
+            }
+            Ok(result.to_string())
         } else {
             let error_text = response.text()
                 .unwrap_or_else(|_| "Unknown error".to_string());
@@ -256,7 +637,7 @@ impl TypesenseClient {
         let response = self.request_sync(Method::GET, &path, None)?;
         
         if response.status().is_success() {
-            let result: Value = response.json()
+            let result: Value = json::from_response(response)
                 .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?;
             Ok(result)
         } else {
@@ -268,143 +649,673 @@ impl TypesenseClient {
 }
 
 /// Map Typesense errors to SearchError
+/// Whether a document fetch answer found its document
+///
+/// Both a missing document and a missing collection answer 404. Typesense has
+/// no error codes, but only the document's message names the document.
+fn document_presence(status: u16, body: &str) -> Result<bool> {
+    match status {
+        200..=299 => Ok(true),
+        404 => {
+            let answer: Value = serde_json::from_str(body).unwrap_or_default();
+            match answer["message"].as_str() {
+                Some(message) if message.starts_with("Could not find a document") => Ok(false),
+                _ => Err(anyhow::anyhow!("collection not found: HTTP 404 {}", body)),
+            }
+        }
+        status => Err(anyhow::anyhow!("Failed to check document existence: HTTP {} {}", status, body)),
+    }
+}
+
 pub fn map_typesense_error(error: anyhow::Error) -> SearchError {
+    if let Some(rejection) = circuit_breaker::open_circuit(&error).or_else(|| rate_limit::rate_limited(&error)) {
+        return map_shared_error(rejection);
+    }
     let error_string = error.to_string();
     
-    if error_string.contains("collection not found") || error_string.contains("404") {
-        SearchError::IndexNotFound(error_string)
+    if error_string.contains("already exists") || error_string.contains("409") {
+        SearchError::IndexAlreadyExists(request_id::tag(error_string))
+    } else if error_string.contains("collection not found") || error_string.contains("404") {
+        SearchError::IndexNotFound(request_id::tag(error_string))
     } else if error_string.contains("bad request") || error_string.contains("400") {
-        SearchError::InvalidQuery(error_string)
+        SearchError::InvalidQuery(request_id::tag(error_string))
     } else if error_string.contains("timeout") {
-        SearchError::Timeout
+        SearchError::Timeout(deadline::remaining_ms())
     } else if error_string.contains("rate") || error_string.contains("429") {
-        SearchError::RateLimited
+        SearchError::RateLimited(None)
     } else {
-        SearchError::Internal(error_string)
+        SearchError::Internal(request_id::tag(error_string))
     }
 }
 
-/// The Typesense search provider implementation
-pub struct TypesenseProvider {
-    client: TypesenseClient,
+/// Map errors from the shared golem-search library to the WIT error type
+fn map_shared_error(error: golem_search::SearchError) -> SearchError {
+    match request_id::annotate(error) {
+        golem_search::SearchError::IndexNotFound(msg) => SearchError::IndexNotFound(msg),
+        golem_search::SearchError::IndexAlreadyExists(msg) => SearchError::IndexAlreadyExists(msg),
+        golem_search::SearchError::InvalidQuery(msg) => SearchError::InvalidQuery(msg),
+        golem_search::SearchError::Timeout { remaining_ms } => SearchError::Timeout(remaining_ms),
+        golem_search::SearchError::RateLimited { retry_after_ms } => SearchError::RateLimited(retry_after_ms),
+        golem_search::SearchError::Unavailable { retry_after_ms } => SearchError::Unavailable(retry_after_ms),
+        golem_search::SearchError::Unsupported => SearchError::Unsupported("Operation not supported".to_string()),
+        e @ golem_search::SearchError::ResponseTooLarge { .. } => SearchError::ResponseTooLarge(e.to_string()),
+        golem_search::SearchError::Backpressure { queue_depth, retry_after_ms } => {
+            SearchError::Backpressure(BackpressureInfo { queue_depth, retry_after_ms })
+        }
+        other => SearchError::Internal(other.to_string()),
+    }
 }
 
-impl TypesenseProvider {
-    /// Create a new Typesense provider
-    pub async fn new() -> SearchResult<Self> {
-        let config = TypesenseConfig::from_env()
-            .map_err(|e| {
-                error!("Failed to load Typesense configuration: {}", e);
-                SearchError::Internal(format!("Configuration error: {}", e))
-            })?;
+/// Approximate payload size of a hit
+fn hit_size(hit: &golem::search::types::SearchHit) -> usize {
+    hit.id.len()
+        + hit.content.as_ref().map_or(0, String::len)
+        + hit.highlights.as_ref().map_or(0, String::len)
+        + hit.explanation.as_ref().map_or(0, String::len)
+}
 
-        let client = TypesenseClient::new(config)
-            .map_err(|e| {
-                error!("Failed to create Typesense client: {}", e);
-                SearchError::Internal(format!("Client initialization error: {}", e))
-            })?;
+/// Approximate payload size of a result page, used to account for cached results
+fn results_size(results: &SearchResults) -> usize {
+    let group_hits = results.groups.iter().flatten().flat_map(|group| &group.hits);
+    results.hits.iter().chain(group_hits).map(hit_size).sum::<usize>() + results.facets.as_ref().map_or(0, String::len)
+}
 
-        info!("Typesense search provider initialized successfully");
-        Ok(Self { client })
+/// Convert a WIT field type to the shared golem-search representation
+fn to_shared_field_type(field_type: &FieldType) -> golem_search::FieldType {
+    match field_type {
+        FieldType::Text => golem_search::FieldType::Text,
+        FieldType::Keyword => golem_search::FieldType::Keyword,
+        FieldType::Integer => golem_search::FieldType::Integer,
+        FieldType::Float => golem_search::FieldType::Float,
+        FieldType::Boolean => golem_search::FieldType::Boolean,
+        FieldType::Date => golem_search::FieldType::Date,
+        FieldType::GeoPoint => golem_search::FieldType::GeoPoint,
+        FieldType::Int64 => golem_search::FieldType::Int64,
+        FieldType::Decimal => golem_search::FieldType::Decimal,
     }
+}
 
-    /// Get Typesense-specific capabilities
-    pub fn get_capabilities(&self) -> SearchCapabilities {
-        SearchCapabilities {
-            supports_index_creation: true,
-            supports_schema_definition: true,
-            supports_facets: true,
-            supports_highlighting: true,
-            supports_full_text_search: true,
-            supports_vector_search: true, // Typesense supports vector search
-            supports_streaming: false, // Typesense doesn't have scroll API
-            supports_geo_search: true,
-            supports_aggregations: true,
-            max_batch_size: Some(100), // Typesense prefers smaller batches
-            max_query_size: Some(2048),
-            supported_field_types: vec![
-                FieldType::Text,
-                FieldType::Keyword,
-                FieldType::Integer,
-                FieldType::Float,
-                FieldType::Boolean,
-                FieldType::Date,
-                FieldType::GeoPoint,
-            ],
-            provider_features: {
-                let mut features = HashMap::new();
-                features.insert("typo_tolerance".to_string(), serde_json::Value::String("automatic".to_string()));
-                features.insert("instant_search".to_string(), serde_json::Value::String("optimized".to_string()));
-                features.insert("geo_search".to_string(), serde_json::Value::String("native".to_string()));
-                features.insert("vector_search".to_string(), serde_json::Value::String("supported".to_string()));
-                serde_json::to_string(&features).unwrap_or_default()
-            },
-        }
+/// Convert a WIT schema to the shared golem-search schema
+fn to_shared_schema(schema: &Schema) -> golem_search::Schema {
+    golem_search::Schema {
+        fields: schema.fields.iter().map(|f| golem_search::SchemaField {
+            name: f.name.clone(),
+            field_type: to_shared_field_type(&f.field_type),
+            required: f.required,
+            facet: f.facet,
+            sort: f.sort,
+            index: f.index,
+            // Malformed hints and UI metadata are rejected when the schema is applied
+            analysis: parse_field_analysis(f.analysis.as_deref()).ok().flatten(),
+            ui: parse_field_ui(f.ui.as_deref()).ok().flatten(),
+        }).collect(),
+        primary_key: schema.primary_key.clone(),
     }
+}
 
-    /// Convert WIT Schema to Typesense collection schema
-    fn schema_to_typesense(&self, schema: &Schema, collection_name: &str) -> SearchResult<Value> {
-        let mut fields = Vec::new();
-        
-        for field in &schema.fields {
-            let field_type = match field.field_type {
-                FieldType::Text => "string",
-                FieldType::Keyword => "string",
-                FieldType::Integer => "int32",
-                FieldType::Float => "float",
-                FieldType::Boolean => "bool",
-                FieldType::Date => "int64", // Typesense uses timestamps
-                FieldType::GeoPoint => "geopoint",
-            };
-            
-            let mut typesense_field = json!({
-                "name": field.name,
-                "type": field_type,
-                "index": field.index
-            });
+/// Convert a shared golem-search field type to the WIT representation
+fn from_shared_field_type(field_type: golem_search::FieldType) -> FieldType {
+    match field_type {
+        golem_search::FieldType::Text => FieldType::Text,
+        golem_search::FieldType::Keyword => FieldType::Keyword,
+        golem_search::FieldType::Integer => FieldType::Integer,
+        golem_search::FieldType::Float => FieldType::Float,
+        golem_search::FieldType::Boolean => FieldType::Boolean,
+        golem_search::FieldType::Date => FieldType::Date,
+        golem_search::FieldType::GeoPoint => FieldType::GeoPoint,
+        golem_search::FieldType::Int64 => FieldType::Int64,
+        golem_search::FieldType::Decimal => FieldType::Decimal,
+    }
+}
 
-            // Add faceting support
-            if field.facet {
-                typesense_field["facet"] = json!(true);
+/// Convert a shared golem-search schema to the WIT schema
+fn from_shared_schema(schema: &golem_search::Schema) -> Schema {
+    Schema {
+        fields: schema.fields.iter().map(|f| SchemaField {
+            name: f.name.clone(),
+            field_type: from_shared_field_type(f.field_type),
+            required: f.required,
+            facet: f.facet,
+            sort: f.sort,
+            index: f.index,
+            analysis: f.analysis.as_ref().and_then(|a| serde_json::to_string(a).ok()),
+            ui: f.ui.as_ref().and_then(|ui| serde_json::to_string(ui).ok()),
+        }).collect(),
+        primary_key: schema.primary_key.clone(),
+    }
+}
+
+/// Convert shared facet display metadata to the WIT record
+fn from_shared_facet_metadata(metadata: ui_metadata::FacetMetadata) -> FacetMetadata {
+    FacetMetadata {
+        field: metadata.field,
+        display_name: metadata.display_name,
+        widget: match metadata.widget {
+            ui_metadata::FacetWidget::Checkbox => FacetWidget::Checkbox,
+            ui_metadata::FacetWidget::Radio => FacetWidget::Radio,
+            ui_metadata::FacetWidget::Dropdown => FacetWidget::Dropdown,
+            ui_metadata::FacetWidget::Toggle => FacetWidget::Toggle,
+            ui_metadata::FacetWidget::Range => FacetWidget::Range,
+            ui_metadata::FacetWidget::DateRange => FacetWidget::DateRange,
+        },
+        sort_priority: metadata.sort_priority,
+    }
+}
+
+/// Shared form of a WIT query, for the checks that only look at its shape
+fn to_shared_query(query: &SearchQuery) -> golem_search::SearchQuery {
+    golem_search::SearchQuery {
+        q: query.q.clone(),
+        filters: query.filters.clone(),
+        sort: query.sort.clone(),
+        facets: query.facets.clone(),
+        page: query.page,
+        per_page: query.per_page,
+        offset: query.offset,
+        highlight: None,
+        config: None,
+    }
+}
+
+/// WIT form of a shared query tree
+fn from_shared_query_ast(ast: query_ast::QueryAst) -> QueryAst {
+    let bound = |bound: Option<golem_search::query::RangeBound>| {
+        bound.map(|bound| RangeBound { value: bound.value, inclusive: bound.inclusive })
+    };
+    let nodes = ast
+        .nodes
+        .into_iter()
+        .map(|node| match node {
+            AstNode::MatchAll => QueryAstNode::MatchAll,
+            AstNode::Term { field, value } => QueryAstNode::Term(QueryTerm { field, value }),
+            AstNode::Phrase { field, text, slop } => QueryAstNode::Phrase(QueryPhrase { field, text, slop }),
+            AstNode::Wildcard { field, pattern } => QueryAstNode::Wildcard(QueryPattern { field, pattern }),
+            AstNode::Regex { field, pattern } => QueryAstNode::Regex(QueryPattern { field, pattern }),
+            AstNode::Range { field, lower, upper } => {
+                QueryAstNode::Range(QueryRange { field, lower: bound(lower), upper: bound(upper) })
             }
+            AstNode::Bool { must, should, must_not } => QueryAstNode::Boolean(QueryBool { must, should, must_not }),
+        })
+        .collect();
+    QueryAst { nodes, root: ast.root }
+}
 
-            // Add sorting support
-            if field.sort {
-                typesense_field["sort"] = json!(true);
+/// Shared form of a WIT query tree
+fn to_shared_query_ast(ast: QueryAst) -> query_ast::QueryAst {
+    let bound = |bound: Option<RangeBound>| {
+        bound.map(|bound| golem_search::query::RangeBound { value: bound.value, inclusive: bound.inclusive })
+    };
+    let nodes = ast
+        .nodes
+        .into_iter()
+        .map(|node| match node {
+            QueryAstNode::MatchAll => AstNode::MatchAll,
+            QueryAstNode::Term(QueryTerm { field, value }) => AstNode::Term { field, value },
+            QueryAstNode::Phrase(QueryPhrase { field, text, slop }) => AstNode::Phrase { field, text, slop },
+            QueryAstNode::Wildcard(QueryPattern { field, pattern }) => AstNode::Wildcard { field, pattern },
+            QueryAstNode::Regex(QueryPattern { field, pattern }) => AstNode::Regex { field, pattern },
+            QueryAstNode::Range(QueryRange { field, lower, upper }) => {
+                AstNode::Range { field, lower: bound(lower), upper: bound(upper) }
             }
+            QueryAstNode::Boolean(QueryBool { must, should, must_not }) => AstNode::Bool { must, should, must_not },
+        })
+        .collect();
+    query_ast::QueryAst { nodes, root: ast.root }
+}
 
-            fields.push(typesense_field);
-        }
-        
-        Ok(json!({
-            "name": collection_name,
-            "fields": fields,
-            "default_sorting_field": schema.primary_key.as_ref().unwrap_or(&"id".to_string())
-        }))
+/// Append date histograms and stats to Typesense `facet_counts`, in the same shape
+///
+/// Stats use the fields Typesense reports for numeric facets, with the
+/// number of values as `total_values`.
+fn merge_client_facets_into_facet_counts(facet_counts: Option<&str>, client_facets: &ClientFacets) -> SearchResult<String> {
+    let mut counts: Vec<Value> = match facet_counts {
+        Some(json) => serde_json::from_str(json)
+            .map_err(|e| SearchError::Internal(format!("Failed to parse facet counts: {}", e)))?,
+        None => Vec::new(),
+    };
+    for histogram in &client_facets.histograms {
+        let buckets: Vec<Value> = histogram.buckets()
+            .into_iter()
+            .map(|(key, count)| json!({ "value": key, "count": count }))
+            .collect();
+        counts.push(json!({ "field_name": histogram.field, "counts": buckets }));
+    }
+    for stats in &client_facets.stats {
+        let summary = stats.to_json();
+        counts.push(json!({
+            "field_name": stats.field,
+            "counts": [],
+            "stats": {
+                "min": summary["min"],
+                "max": summary["max"],
+                "avg": summary["avg"],
+                "sum": summary["sum"],
+                "total_values": summary["count"]
+            }
+        }));
     }
+    Ok(Value::Array(counts).to_string())
+}
 
-    /// Convert Typesense collection to WIT Schema
-    fn typesense_to_schema(&self, collection: &Value) -> SearchResult<Schema> {
-        let fields_array = collection
-            .get("fields")
-            .and_then(|f| f.as_array())
-            .ok_or_else(|| SearchError::Internal("Missing fields in collection".to_string()))?;
-        
-        let mut fields = Vec::new();
-        
-        for field in fields_array {
-            let name = field
-                .get("name")
-                .and_then(|n| n.as_str())
-                .ok_or_else(|| SearchError::Internal("Missing field name".to_string()))?
-                .to_string();
-            
-            let field_type_str = field
-                .get("type")
+/// Numeric value of a search parameter
+fn param_value(params: &[(&str, String)], key: &str) -> Option<u32> {
+    params.iter().find(|(k, _)| *k == key).and_then(|(_, v)| v.parse().ok())
+}
+
+/// Convert one Typesense hit, copying the document once
+fn typesense_hit(hit: &TypesenseHit<'_>) -> SearchResult<golem::search::types::SearchHit> {
+    let id = raw_document_id(hit.document)
+        .ok_or_else(|| SearchError::Internal("Missing document ID".to_string()))?;
+    Ok(golem::search::types::SearchHit {
+        id,
+        score: hit.text_match,
+        explanation: raw_explanation("typesense", hit.text_match, hit.text_match_info),
+        content: Some(hit.document.get().to_string()),
+        highlights: raw_to_json(hit.highlights),
+        distance_meters: hit.geo_distance_meters.as_ref().and_then(|d| d.values().next().copied()),
+    })
+}
+
+/// Replace the highlights of hits with the offsets of the words of `q` in `fields`
+fn replace_with_offsets(hits: &mut [golem::search::types::SearchHit], fields: &[String], q: &str) {
+    for hit in hits {
+        let content = hit.content.as_deref().and_then(|c| serde_json::from_str(c).ok()).unwrap_or(Value::Null);
+        hit.highlights = Some(highlight::offsets_json(highlight::match_offsets(&content, fields, q)));
+    }
+}
+
+/// Borrowed view of a Typesense search response
+#[derive(Deserialize)]
+struct TypesenseSearchResponse<'a> {
+    #[serde(default)]
+    found: Option<u64>,
+
+    #[serde(default)]
+    search_time_ms: Option<u64>,
+
+    #[serde(borrow, default)]
+    hits: Vec<TypesenseHit<'a>>,
+
+    #[serde(borrow, default)]
+    facet_counts: Option<&'a RawValue>,
+
+    /// Matching documents of a grouped search, where `found` counts groups
+    #[serde(default)]
+    found_docs: Option<u64>,
+
+    #[serde(borrow, default)]
+    grouped_hits: Option<Vec<TypesenseGroup<'a>>>,
+
+    /// Whether `search_cutoff_ms` stopped the search, leaving `found` short
+    #[serde(default)]
+    search_cutoff: bool,
+}
+
+#[derive(Deserialize)]
+struct TypesenseGroup<'a> {
+    group_key: Vec<Value>,
+
+    #[serde(default)]
+    found: Option<u64>,
+
+    #[serde(borrow)]
+    hits: Vec<TypesenseHit<'a>>,
+}
+
+#[derive(Deserialize)]
+struct TypesenseHit<'a> {
+    #[serde(borrow)]
+    document: &'a RawValue,
+
+    #[serde(default)]
+    text_match: Option<f64>,
+
+    #[serde(borrow, default)]
+    highlights: Option<&'a RawValue>,
+
+    #[serde(borrow, default)]
+    text_match_info: Option<&'a RawValue>,
+
+    /// Distance from the origin of a geo sort, keyed by the sorted field
+    #[serde(default)]
+    geo_distance_meters: Option<HashMap<String, f64>>,
+}
+
+/// Metric label for a failed operation
+impl ErrorKind for SearchError {
+    fn error_kind(&self) -> &'static str {
+        match self {
+            SearchError::IndexNotFound(_) => "index_not_found",
+            SearchError::IndexAlreadyExists(_) => "index_already_exists",
+            SearchError::InvalidQuery(_) => "invalid_query",
+            SearchError::Timeout(_) => "timeout",
+            SearchError::RateLimited(_) => "rate_limited",
+            SearchError::Internal(_) => "internal",
+            SearchError::Unsupported(_) => "unsupported",
+            SearchError::ResponseTooLarge(_) => "response_too_large",
+            SearchError::Unavailable(_) => "unavailable",
+            SearchError::Backpressure(_) => "backpressure",
+        }
+    }
+}
+
+/// Hit count reported on `search` spans
+impl telemetry::SpanOutput for SearchResults {
+    fn returned_rows(&self) -> Option<u64> {
+        Some(self.hits.len() as u64)
+    }
+}
+
+/// Shape of a query for reusing its conversion; normalized and sampled queries are converted in full
+impl TemplateQuery for SearchQuery {
+    fn template_text(&self) -> Option<&str> {
+        if self.normalization.is_some() || self.sample.is_some() {
+            return None;
+        }
+        match QueryText::parse(self.q.as_deref()) {
+            QueryText::Text(text) => Some(text),
+            QueryText::MatchAll => None,
+        }
+    }
+
+    fn with_text(&self, text: &str) -> Self {
+        Self { q: Some(text.to_string()), ..self.clone() }
+    }
+}
+
+/// Facets and filters of a query, for splitting it over the Typesense limits
+impl SplitQuery for SearchQuery {
+    fn facets(&self) -> &[String] {
+        &self.facets
+    }
+
+    fn filters(&self) -> &[String] {
+        &self.filters
+    }
+
+    fn cursor(&self) -> Option<&str> {
+        self.cursor.as_deref()
+    }
+
+    fn paging(&self) -> (Option<u32>, Option<u32>, Option<u32>) {
+        (self.page, self.per_page, self.offset)
+    }
+
+    fn with_parts(&self, facets: &[String], filters: &[String]) -> Self {
+        Self { facets: facets.to_vec(), filters: filters.to_vec(), ..self.clone() }
+    }
+
+    fn with_window(&self, offset: u32, per_page: u32) -> Self {
+        Self { page: None, per_page: Some(per_page), offset: Some(offset), cursor: None, ..self.clone() }
+    }
+}
+
+impl SplitResults for SearchResults {
+    fn hit_ids(&self) -> Vec<&str> {
+        self.hits.iter().map(|hit| hit.id.as_str()).collect()
+    }
+
+    fn total(&self) -> Option<u32> {
+        self.total
+    }
+
+    fn facets_mut(&mut self) -> &mut Option<String> {
+        &mut self.facets
+    }
+
+    fn extend_hits(&mut self, page: Self) {
+        self.hits.extend(page.hits);
+        self.took_ms = match (self.took_ms, page.took_ms) {
+            (Some(took), Some(more)) => Some(took.saturating_add(more)),
+            (took, more) => took.or(more),
+        };
+    }
+
+    fn retain_hits(&mut self, keep: &dyn Fn(&str) -> bool) {
+        self.hits.retain(|hit| keep(&hit.id));
+    }
+
+    fn set_page(&mut self, offset: u32, per_page: u32, total: u32, total_exact: bool) {
+        self.hits = self.hits.drain(..).skip(offset as usize).take(per_page as usize).collect();
+        self.total = Some(total);
+        self.total_exact = Some(total_exact);
+        self.page = Some(offset.checked_div(per_page).map_or(1, |page| page + 1));
+        self.per_page = Some(per_page);
+        self.next_cursor = None;
+        self.groups = None;
+    }
+}
+
+impl RescueQuery for SearchQuery {
+    fn relaxed(&self, relaxation: &Relaxation) -> Option<Self> {
+        let text = QueryText::parse(self.q.as_deref());
+        match relaxation {
+            Relaxation::DropFilters => (!self.filters.is_empty()).then(|| Self { filters: Vec::new(), ..self.clone() }),
+            Relaxation::Typos => {
+                if text == QueryText::MatchAll {
+                    return None;
+                }
+                // Typesense allows typos by term length without a fuzziness
+                let current = parse_fuzziness(self.fuzziness.as_deref()).ok().flatten();
+                let fuzziness = rescue::relaxed_fuzziness(current, Fuzziness::Auto)?;
+                Some(Self { fuzziness: Some(fuzziness.as_str().to_string()), ..self.clone() })
+            }
+            Relaxation::AnyTerms => {
+                let current = parse_minimum_should_match(self.minimum_should_match.as_deref()).ok().flatten();
+                rescue::widens_terms(text, current)
+                    .then(|| Self { minimum_should_match: Some(MinimumShouldMatch::Any.to_string()), ..self.clone() })
+            }
+            Relaxation::Popular { sort } => {
+                let sorted = sort.as_ref().is_none_or(|sort| self.sort == [sort.clone()]);
+                if text == QueryText::MatchAll && self.filters.is_empty() && sorted {
+                    return None;
+                }
+                let sort = sort.as_ref().map_or_else(|| self.sort.clone(), |sort| vec![sort.clone()]);
+                Some(Self { q: None, filters: Vec::new(), sort, ..self.clone() })
+            }
+        }
+    }
+
+    fn query_text(&self) -> Option<&str> {
+        match QueryText::parse(self.q.as_deref()) {
+            QueryText::Text(text) => Some(text),
+            QueryText::MatchAll => None,
+        }
+    }
+
+    fn with_text(&self, text: &str) -> Self {
+        Self { q: Some(text.to_string()), ..self.clone() }
+    }
+}
+
+impl RescueResults for SearchResults {
+    fn found_nothing(&self) -> bool {
+        self.total.map_or(self.hits.is_empty(), |total| total == 0)
+    }
+
+    fn set_relaxations(&mut self, relaxations: &[Relaxation]) {
+        self.relaxations = rescue::names(relaxations);
+    }
+
+    fn hit_count(&self) -> u64 {
+        self.total.map_or(self.hits.len() as u64, u64::from)
+    }
+
+    fn set_correction(&mut self, correction: &Correction) {
+        self.original_query = Some(correction.original.clone());
+        self.corrected_query = Some(correction.corrected.clone());
+    }
+}
+
+impl CacheQuery for SearchQuery {
+    fn normalized(&self) -> Self {
+        Self {
+            q: self.q.as_deref().map(cache::normalize_text),
+            filters: cache::normalize_filters(&self.filters),
+            ..self.clone()
+        }
+    }
+}
+
+/// The Typesense search provider implementation
+pub struct TypesenseProvider {
+    client: TypesenseClient,
+}
+
+impl TypesenseProvider {
+    /// Create a new Typesense provider
+    pub async fn new() -> SearchResult<Self> {
+        let config = TypesenseConfig::from_env()
+            .map_err(|e| {
+                error!("Failed to load Typesense configuration: {}", e);
+                SearchError::Internal(format!("Configuration error: {} (diagnose_config lists every variable read)", e))
+            })?;
+
+        let client = TypesenseClient::new(config)
+            .map_err(|e| {
+                error!("Failed to create Typesense client: {}", e);
+                SearchError::Internal(format!("Client initialization error: {}", e))
+            })?;
+
+        info!("Typesense search provider initialized successfully");
+        Ok(Self { client })
+    }
+
+    /// Get Typesense-specific capabilities
+    pub fn get_capabilities(&self) -> SearchCapabilities {
+        SearchCapabilities {
+            supports_index_creation: true,
+            supports_schema_definition: true,
+            supports_facets: true,
+            supports_highlighting: true,
+            supports_full_text_search: true,
+            supports_vector_search: true, // Typesense supports vector search
+            supports_streaming: false, // Typesense doesn't have scroll API
+            supports_geo_search: true,
+            supports_aggregations: true,
+            max_batch_size: Some(100), // Typesense prefers smaller batches
+            max_query_size: Some(2048),
+            supported_field_types: vec![
+                FieldType::Text,
+                FieldType::Keyword,
+                FieldType::Integer,
+                FieldType::Float,
+                FieldType::Boolean,
+                FieldType::Date,
+                FieldType::GeoPoint,
+                FieldType::Int64,
+                FieldType::Decimal,
+            ],
+            provider_features: {
+                let mut features = HashMap::new();
+                features.insert("typo_tolerance".to_string(), serde_json::Value::String("automatic".to_string()));
+                features.insert("instant_search".to_string(), serde_json::Value::String("optimized".to_string()));
+                features.insert("geo_search".to_string(), serde_json::Value::String("native".to_string()));
+                features.insert("vector_search".to_string(), serde_json::Value::String("supported".to_string()));
+                serde_json::to_string(&features).unwrap_or_default()
+            },
+        }
+    }
+
+    /// Convert WIT Schema to Typesense collection schema
+    fn schema_to_typesense(&self, schema: &Schema, collection_name: &str) -> SearchResult<Value> {
+        let mut fields = Vec::new();
+        
+        for warning in unsupported_analysis("typesense", &to_shared_schema(schema)) {
+            warn!("{}", warning);
+        }
+        
+        for field in &schema.fields {
+            let field_type = match field.field_type {
+                FieldType::Text => "string",
+                FieldType::Keyword => "string",
+                FieldType::Integer => "int32",
+                FieldType::Float => "float",
+                FieldType::Boolean => "bool",
+                FieldType::Date => "int64", // Typesense uses timestamps
+                FieldType::GeoPoint => "geopoint",
+                FieldType::Int64 => "int64",
+                FieldType::Decimal => "string", // Kept as the decimal text, so no digits are lost
+            };
+            
+            let mut typesense_field = json!({
+                "name": field.name,
+                "type": field_type,
+                "index": field.index
+            });
+
+            // Add faceting support
+            if field.facet {
+                typesense_field["facet"] = json!(true);
+            }
+
+            // Add sorting support
+            if field.sort {
+                typesense_field["sort"] = json!(true);
+            }
+
+            // The locale picks the segmenter for CJK text and the Snowball stemmer language
+            let analysis = parse_field_analysis(field.analysis.as_deref()).map_err(map_shared_error)?;
+            if let (Some(analysis), FieldType::Text) = (analysis, &field.field_type) {
+                if let Some(locale) = analysis.typesense_locale() {
+                    typesense_field["locale"] = json!(locale);
+                    if analysis.stems() {
+                        typesense_field["stem"] = json!(true);
+                    }
+                }
+            }
+
+            fields.push(typesense_field);
+        }
+        
+        let mut collection = json!({
+            "name": collection_name,
+            "fields": fields,
+            "default_sorting_field": schema.primary_key.as_ref().unwrap_or(&"id".to_string())
+        });
+        for field in &schema.fields {
+            parse_field_ui(field.ui.as_deref()).map_err(map_shared_error)?;
+        }
+        let shared = to_shared_schema(schema);
+        let metadata: serde_json::Map<String, Value> = [(UI_META_KEY, ui_metadata::to_meta(&shared)), (DECIMAL_META_KEY, numeric::to_meta(&shared))]
+            .into_iter()
+            .filter_map(|(key, value)| Some((key.to_string(), value?)))
+            .collect();
+        if !metadata.is_empty() {
+            collection["metadata"] = Value::Object(metadata);
+        }
+        Ok(collection)
+    }
+
+    /// Convert Typesense collection to WIT Schema
+    fn typesense_to_schema(&self, collection: &Value) -> SearchResult<Schema> {
+        let fields_array = collection
+            .get("fields")
+            .and_then(|f| f.as_array())
+            .ok_or_else(|| SearchError::Internal("Missing fields in collection".to_string()))?;
+        
+        let decimals = collection
+            .get("metadata")
+            .and_then(|m| m.get(DECIMAL_META_KEY))
+            .and_then(Value::as_array)
+            .cloned()
+            .unwrap_or_default();
+        let mut fields = Vec::new();
+        
+        for field in fields_array {
+            let name = field
+                .get("name")
+                .and_then(|n| n.as_str())
+                .ok_or_else(|| SearchError::Internal("Missing field name".to_string()))?
+                .to_string();
+            
+            let field_type_str = field
+                .get("type")
                 .and_then(|t| t.as_str())
                 .ok_or_else(|| SearchError::Internal("Missing field type".to_string()))?;
             
             let field_type = match field_type_str {
+                "string" if decimals.iter().any(|d| d.as_str() == Some(name.as_str())) => FieldType::Decimal,
                 "string" => {
                     // Distinguish between text and keyword based on faceting
                     if field.get("facet").and_then(|f| f.as_bool()).unwrap_or(false) {
@@ -413,7 +1324,8 @@ impl TypesenseProvider {
                         FieldType::Text
                     }
                 }
-                "int32" | "int64" => FieldType::Integer,
+                "int32" => FieldType::Integer,
+                "int64" => FieldType::Int64,
                 "float" => FieldType::Float,
                 "bool" => FieldType::Boolean,
                 "geopoint" => FieldType::GeoPoint,
@@ -435,6 +1347,18 @@ impl TypesenseProvider {
                 .and_then(|s| s.as_bool())
                 .unwrap_or(false);
             
+            let analysis = FieldAnalysis::from_typesense_field(
+                field.get("locale").and_then(|l| l.as_str()),
+                field.get("stem").and_then(|s| s.as_bool()).unwrap_or(false),
+            )
+            .and_then(|a| serde_json::to_string(&a).ok());
+            
+            let ui = collection
+                .get("metadata")
+                .and_then(|m| m.get(UI_META_KEY))
+                .and_then(|ui| ui.get(&name))
+                .map(Value::to_string);
+            
             fields.push(SchemaField {
                 name,
                 field_type,
@@ -442,6 +1366,8 @@ impl TypesenseProvider {
                 facet,
                 sort,
                 index,
+                analysis,
+                ui,
             });
         }
         
@@ -457,29 +1383,83 @@ impl TypesenseProvider {
     }
 
     /// Convert WIT SearchQuery to Typesense search parameters
-    fn query_to_typesense_params(&self, query: &SearchQuery) -> Vec<(&'static str, String)> {
+    fn query_to_typesense_params(&self, query: &SearchQuery) -> SearchResult<Vec<(&'static str, String)>> {
         let mut params = Vec::new();
         
-        // Main query
-        if let Some(ref q) = query.q {
-            if !q.trim().is_empty() {
-                params.push(("q", q.clone()));
-                params.push(("query_by", "*".to_string())); // Search all fields
+        // Main query; Typesense requires `q` and treats `*` as match-all
+        let q = match QueryText::parse(query.q.as_deref()) {
+            QueryText::MatchAll => MATCH_ALL.to_string(),
+            QueryText::Text(q) => match parse_normalization(query.normalization.as_deref()).map_err(map_shared_error)? {
+                Some(normalization) => normalization.prepare_query("typesense", q),
+                None => q.to_string(),
+            },
+        };
+        // Phrases and exclusions are native; required terms need every token to match
+        // and a trailing `*` becomes the prefix search Typesense runs on every token
+        let parsed = ParsedQuery::parse(&q);
+        parsed.check_patterns(&PatternLimits::from_env()).map_err(map_shared_error)?;
+        if parsed.has_operators() {
+            let syntax = parsed.to_simple_syntax("typesense").map_err(map_shared_error)?;
+            for warning in &syntax.warnings {
+                warn!("{}", warning);
+            }
+            if syntax.require_all_terms {
+                params.push(("drop_tokens_threshold", "0".to_string()));
             }
+            params.push(("q", syntax.text));
         } else {
-            params.push(("q", "*".to_string()));
-            params.push(("query_by", "*".to_string()));
+            params.push(("q", q));
+        }
+        params.push(("query_by", "*".to_string())); // Search all fields
+        
+        // A fixed typo budget maps to num_typos; auto keeps the length-based default
+        let fuzziness = parse_fuzziness(query.fuzziness.as_deref()).map_err(map_shared_error)?;
+        if let Some(typos) = fuzziness.and_then(|f| f.max_typos()) {
+            params.push(("num_typos", typos.to_string()));
+        }
+        
+        // Typesense drops the tokens with the fewest hits while fewer documents than the
+        // threshold match: never to require all terms, and until the page fills for any term
+        let minimum = parse_minimum_should_match(query.minimum_should_match.as_deref()).map_err(map_shared_error)?;
+        let all_required = params.iter().any(|(name, _)| *name == "drop_tokens_threshold");
+        match minimum.filter(|_| !all_required) {
+            Some(minimum) if minimum.is_all() => params.push(("drop_tokens_threshold", "0".to_string())),
+            Some(minimum) if minimum.is_any() => {
+                let per_page = query.per_page.unwrap_or(10);
+                let first = query.page.map_or(query.offset.unwrap_or(0), |page| page.saturating_mul(per_page));
+                params.push(("drop_tokens_threshold", first.saturating_add(per_page).to_string()));
+            }
+            Some(minimum) => warn!(
+                "Typesense cannot require {} of the query terms; tokens are dropped only while nothing matches",
+                minimum
+            ),
+            None => {}
         }
         
         // Filters
         if !query.filters.is_empty() {
-            let filter_str = query.filters.join(" && ");
+            let filters = query.filters.iter()
+                .map(|filter| match GeoPolygonFilter::parse(filter) {
+                    Some(polygons) => polygons.map(|p| p.to_typesense_filter()).map_err(map_shared_error),
+                    None => match IdSetFilter::parse(filter) {
+                        Some(ids) => ids.and_then(|ids| ids.to_typesense_filter()).map_err(map_shared_error),
+                        None => Ok(filter.clone()),
+                    },
+                })
+                .collect::<SearchResult<Vec<_>>>()?;
+            let filter_str = filters.join(" && ");
             params.push(("filter_by", filter_str));
         }
         
         // Sorting
         if !query.sort.is_empty() {
-            let sort_str = query.sort.join(",");
+            let sorts = query.sort.iter()
+                .map(|sort| match GeoDistanceSort::parse(sort) {
+                    Some(geo_sort) => geo_sort.map(|s| s.to_typesense_sort()).map_err(map_shared_error),
+                    None => Ok(sort.clone()),
+                })
+                .collect::<SearchResult<Vec<_>>>()?;
+            let sort_str = sorts.join(",");
             params.push(("sort_by", sort_str));
         }
         
@@ -494,14 +1474,16 @@ impl TypesenseProvider {
             params.push(("page", page.to_string()));
         }
         
-        // Facets
-        if !query.facets.is_empty() {
-            let facet_str = query.facets.join(",");
-            params.push(("facet_by", facet_str));
+        // Facets; range buckets use Typesense facet ranges, date histograms
+        // and stats are computed afterwards by scanning the matches
+        let facets = parse_facets(&query.facets).map_err(map_shared_error)?;
+        let facet_by: Vec<String> = facets.iter().filter_map(FacetSpec::to_typesense_facet).collect();
+        if !facet_by.is_empty() {
+            params.push(("facet_by", facet_by.join(",")));
         }
         
-        // Highlighting
-        if let Some(ref highlight_config) = query.highlight {
+        // Highlighting; offsets are computed from the returned documents instead
+        if let Some(highlight_config) = query.highlight.as_ref().filter(|h| !h.offsets) {
             if !highlight_config.fields.is_empty() {
                 let highlight_fields = highlight_config.fields.join(",");
                 params.push(("highlight_fields", highlight_fields));
@@ -516,73 +1498,79 @@ impl TypesenseProvider {
             }
         }
         
-        params
+        // Consider every typo and token-drop variation, so `found` is not
+        // cut short once enough matches turn up
+        if query.exact_total {
+            params.push(("exhaustive_search", "true".to_string()));
+        }
+        
+        Ok(params)
     }
 
     /// Convert Typesense search response to WIT SearchResults
-    fn response_to_results(&self, response: &Value) -> SearchResult<SearchResults> {
-        let found = response
-            .get("found")
-            .and_then(|f| f.as_u64())
-            .map(|f| f as u32);
-        
-        let hits_array = response
-            .get("hits")
-            .and_then(|h| h.as_array())
-            .ok_or_else(|| SearchError::Internal("Missing hits array in response".to_string()))?;
-        
-        let mut hits = Vec::new();
-        for hit in hits_array {
-            let document = hit
-                .get("document")
-                .ok_or_else(|| SearchError::Internal("Missing document in hit".to_string()))?;
-            
-            let id = document
-                .get("id")
-                .and_then(|id| id.as_str())
-                .ok_or_else(|| SearchError::Internal("Missing document ID".to_string()))?
-                .to_string();
-            
-            let content = serde_json::to_string(document)
-                .map_err(|e| SearchError::Internal(e.to_string()))?;
-            
-            let score = hit.get("text_match").and_then(|s| s.as_f64());
-            
-            let highlights = hit.get("highlights")
-                .map(|h| serde_json::to_string(h))
-                .transpose()
-                .map_err(|e| SearchError::Internal(e.to_string()))?;
-            
-            hits.push(golem::search::types::SearchHit {
-                id,
-                score,
-                content: Some(content),
-                highlights,
-            });
-        }
+    fn response_to_results(&self, body: &str) -> SearchResult<SearchResults> {
+        let limits = ResponseLimits::from_env();
+        limits.check_body(body.len()).map_err(map_shared_error)?;
+        
+        let response: TypesenseSearchResponse = serde_json::from_str(body)
+            .map_err(|e| SearchError::Internal(format!("Failed to parse search response: {}", e)))?;
         
-        let facets = response.get("facet_counts")
-            .map(|f| serde_json::to_string(f).unwrap_or_default());
+        let mut hits = response.hits.iter().map(typesense_hit).collect::<SearchResult<Vec<_>>>()?;
         
-        let took_ms = response
-            .get("search_time_ms")
-            .and_then(|t| t.as_u64())
-            .map(|t| t as u32);
+        // A grouped search lists its hits per group only; they are also
+        // flattened into `hits`, group by group
+        let groups = match &response.grouped_hits {
+            Some(grouped) => {
+                let mut groups = Vec::with_capacity(grouped.len());
+                for group in grouped {
+                    let group_hits = group.hits.iter().map(typesense_hit).collect::<SearchResult<Vec<_>>>()?;
+                    hits.extend(group_hits.iter().cloned());
+                    groups.push(SearchGroup {
+                        key: group.group_key.iter().map(group_key_text).collect(),
+                        total: group.found.map(|f| f as u32),
+                        hits: group_hits,
+                    });
+                }
+                Some(groups)
+            }
+            None => None,
+        };
+        
+        let truncated = limits
+            .truncate_hits(&mut hits, hit_size)
+            .map_err(map_shared_error)?;
         
         Ok(SearchResults {
-            total: found,
+            total: response.found_docs.or(response.found).map(|f| f as u32),
             page: None,
             per_page: None,
             hits,
-            facets,
-            took_ms,
+            facets: raw_to_json(response.facet_counts),
+            took_ms: response.search_time_ms.map(|t| t as u32),
+            truncated,
+            request_id: None,
+            groups,
+            next_cursor: None,
+            total_exact: response.search_cutoff.then_some(false),
+            facet_metadata: Vec::new(),
+            relaxations: Vec::new(),
+            original_query: None,
+            corrected_query: None,
         })
     }
 
+    /// Server version, detected once per worker and endpoint
+    pub async fn server_version(&self) -> Option<ServerVersion> {
+        version::cached_version("typesense", self.client.endpoints.primary().as_str(), || self.client.detect_version()).await
+    }
+
+    /// Capabilities, versions, configured limits and degradation, for orchestrators choosing a provider
+    pub async fn describe(&self) -> ProviderDescription {
+        describe::describe(typesense_capability_matrix(), self.server_version().await)
+    }
+
     /// Basic CRUD and search operations
     pub async fn create_index(&self, name: &str, schema: Option<&Schema>) -> SearchResult<()> {
-        info!("Creating Typesense collection: {}", name);
-
         let collection_schema = if let Some(schema) = schema {
             self.schema_to_typesense(schema, name)?
         } else {
@@ -599,77 +1587,1191 @@ impl TypesenseProvider {
                 "default_sorting_field": "id"
             })
         };
-
-        self.client
-            .create_collection(collection_schema)
-            .await
-            .map_err(map_typesense_error)?;
-
-        info!("Successfully created Typesense collection: {}", name);
-        Ok(())
+        self.create_collection_from(name, schema, collection_schema).await
     }
 
-    pub async fn delete_index(&self, name: &str) -> SearchResult<()> {
-        self.client.delete_collection(name).await.map_err(map_typesense_error)?;
-        Ok(())
-    }
+    /// Create a collection from a `golem_search::templates` blueprint
+    ///
+    /// The first descending numeric or date field of the template ranking
+    /// becomes the default sorting field, and synonyms become one-way synonym
+    /// rules. Typesense applies stop words per query, so the template's are
+    /// not installed.
+    pub async fn create_index_from_template(
+        &self,
+        name: &str,
+        template: &str,
+        overrides: Option<&str>,
+    ) -> SearchResult<IndexTemplate> {
+        let overrides = TemplateOverrides::from_json(overrides).map_err(map_shared_error)?;
+        let template = templates::instantiate(template, &overrides).map_err(map_shared_error)?;
+        let schema = from_shared_schema(&template.schema);
+
+        let mut collection_schema = self.schema_to_typesense(&schema, name)?;
+        if let Some(field) = template.default_sorting_field() {
+            collection_schema["default_sorting_field"] = json!(field);
+        }
+        self.create_collection_from(name, Some(&schema), collection_schema).await?;
+
+        for (position, (term, equivalents)) in template.synonyms.iter().enumerate() {
+            let rule = json!({ "root": term, "synonyms": equivalents });
+            self.client
+                .upsert_synonym(name, &format!("template-{}", position), rule)
+                .await
+                .map_err(map_typesense_error)?;
+        }
+        if !template.stop_words.is_empty() {
+            warn!("Typesense applies stop words per query; skipping {} template stop words", template.stop_words.len());
+        }
+        Ok(template)
+    }
+
+    async fn create_collection_from(&self, name: &str, schema: Option<&Schema>, collection_schema: Value) -> SearchResult<()> {
+        audit::audited("typesense", "create_index", name, json!({ "schema": schema.map(to_shared_schema) }), async {
+            telemetry::traced("typesense", "create_index", name, async {
+                info!("Creating Typesense collection: {}", name);
+
+                self.client
+                    .create_collection(collection_schema)
+                    .await
+                    .map_err(map_typesense_error)?;
+
+                info!("Successfully created Typesense collection: {}", name);
+                if let Some(schema) = schema {
+                    numeric::remember("typesense", name, &to_shared_schema(schema));
+                }
+                events::emit(LifecycleEventKind::IndexCreated, "typesense", name, Value::Null);
+                Ok(())
+            })
+            .await
+        })
+        .await
+    }
+
+    /// Create a collection if it does not exist, otherwise patch its fields
+    ///
+    /// New fields are added and fields with changed facet/sort/index flags are
+    /// dropped and re-added in the same request. Type changes are rejected.
+    /// Returns `true` when the collection was created.
+    pub async fn ensure_index(&self, name: &str, schema: Option<&Schema>) -> SearchResult<bool> {
+        audit::audited("typesense", "ensure_index", name, json!({ "schema": schema.map(to_shared_schema) }), async {
+            match self.create_index(name, schema).await {
+                Ok(()) => return Ok(true),
+                Err(SearchError::IndexAlreadyExists(_)) => debug!("Collection {} already exists, checking schema", name),
+                Err(e) => return Err(e),
+            }
+
+            let Some(desired) = schema else {
+                return Ok(false);
+            };
+
+            let current = self.get_schema(name).await?;
+            let report = check_schema_compatibility("typesense", &to_shared_schema(&current), &to_shared_schema(desired));
+            report.ensure_applicable(name).map_err(map_shared_error)?;
+            numeric::remember("typesense", name, &to_shared_schema(desired));
+            let updated = report.updated_in_place();
+            let mut changed = report.added();
+            changed.extend(updated.iter().cloned());
+            if changed.is_empty() {
+                return Ok(false);
+            }
+
+            version::require(&version::TYPESENSE_SCHEMA_ALTER, self.server_version().await)
+                .map_err(map_shared_error)?;
+            info!("Patching fields {:?} of collection {}", changed, name);
+            let patch = Schema {
+                fields: desired.fields.iter().filter(|f| changed.contains(&f.name)).cloned().collect(),
+                primary_key: None,
+            };
+            let mut collection_schema = self.schema_to_typesense(&patch, name)?;
+            let fields = updated.iter()
+                .map(|field| json!({ "name": field, "drop": true }))
+                .chain(collection_schema["fields"].take().as_array().into_iter().flatten().cloned())
+                .collect::<Vec<_>>();
+            self.client
+                .update_collection(name, json!({ "fields": fields }))
+                .await
+                .map_err(map_typesense_error)?;
+            cache::global().remove(CacheKind::Schema, &cache::index_key("typesense", name));
+            events::emit(LifecycleEventKind::SchemaUpdated, "typesense", name, json!({ "fields": changed }));
+            Ok(false)
+        })
+        .await
+    }
+
+    /// Delete a collection, or with `dry_run` only return the plan for it
+    pub async fn delete_index(&self, name: &str, dry_run: bool) -> SearchResult<OperationPlan> {
+        audit::audited("typesense", "delete_index", name, json!({ "dry_run": dry_run }), async {
+            telemetry::traced("typesense", "delete_index", name, async {
+                dry_run::validate_target(name).map_err(map_shared_error)?;
+                let count = self.collection_size(name).await?;
+                let plan = OperationPlan::new("typesense", "delete_index", name, dry_run)
+                    .with_affected_documents(count)
+                    .step(format!("DELETE /collections/{}", name));
+
+                dry_run::execute(plan, async {
+                    self.client.delete_collection(name).await.map_err(map_typesense_error)?;
+                    cache::global().remove(CacheKind::Schema, &cache::index_key("typesense", name));
+                    write_buffer::discard_index(name);
+                    checksum::forget_index(name);
+                    cache::invalidate_results("typesense", name);
+                    numeric::forget(name);
+                    events::emit(LifecycleEventKind::IndexDeleted, "typesense", name, Value::Null);
+                    Ok(None)
+                })
+                .await
+            })
+            .await
+        })
+        .await
+    }
+
+    /// Delete the documents matching filters, or with `dry_run` only count them
+    ///
+    /// Filters are parenthesized and joined with `&&`, so an `||` inside one
+    /// filter cannot widen the others.
+    pub async fn delete_by_query(&self, index: &str, filters: &[String], dry_run: bool) -> SearchResult<OperationPlan> {
+        let details = json!({ "filters": filters, "dry_run": dry_run });
+        audit::audited("typesense", "delete_by_query", index, details, async {
+            telemetry::traced("typesense", "delete_by_query", index, async {
+                dry_run::validate_target(index).map_err(map_shared_error)?;
+                dry_run::validate_filters(filters).map_err(map_shared_error)?;
+                let filter_by = filters
+                    .iter()
+                    .filter(|f| !f.trim().is_empty())
+                    .map(|f| format!("({})", f))
+                    .collect::<Vec<_>>()
+                    .join(" && ");
+                let count = self.client.count_matching(index, &filter_by).await.map_err(map_typesense_error)?;
+                let plan = OperationPlan::new("typesense", "delete_by_query", index, dry_run)
+                    .with_filter(filter_by.clone())
+                    .with_affected_documents(Some(count))
+                    .step(format!("DELETE /collections/{}/documents?filter_by=...", index));
+
+                dry_run::execute(plan, async {
+                    let deleted = self.client
+                        .delete_documents(index, ("filter_by", &filter_by))
+                        .await
+                        .map_err(map_typesense_error)?;
+                    checksum::forget_index(index);
+                    cache::invalidate_results("typesense", index);
+                    Ok(Some(deleted))
+                })
+                .await
+            })
+            .await
+        })
+        .await
+    }
+
+    /// Delete every document but keep the collection and its schema
+    pub async fn clear_index(&self, index: &str, dry_run: bool) -> SearchResult<OperationPlan> {
+        audit::audited("typesense", "clear_index", index, json!({ "dry_run": dry_run }), async {
+            telemetry::traced("typesense", "clear_index", index, async {
+                dry_run::validate_target(index).map_err(map_shared_error)?;
+                let count = self.collection_size(index).await?;
+                let plan = OperationPlan::new("typesense", "clear_index", index, dry_run)
+                    .with_affected_documents(count)
+                    .step(format!("DELETE /collections/{}/documents?truncate=true", index));
+
+                dry_run::execute(plan, async {
+                    let deleted = self.client
+                        .delete_documents(index, ("truncate", "true"))
+                        .await
+                        .map_err(map_typesense_error)?;
+                    write_buffer::discard_index(index);
+                    checksum::forget_index(index);
+                    cache::invalidate_results("typesense", index);
+                    Ok(Some(deleted))
+                })
+                .await
+            })
+            .await
+        })
+        .await
+    }
+
+    /// Document count of a collection, failing when the collection does not exist
+    async fn collection_size(&self, name: &str) -> SearchResult<Option<u64>> {
+        let collection = self.client.get_collection(name).await.map_err(map_typesense_error)?;
+        Ok(collection.get("num_documents").and_then(Value::as_u64))
+    }
+
+    /// Changes to a collection since the position of `since`, or an empty summary marking the current position
+    ///
+    /// Typesense reports only the number of documents, so updates go unseen.
+    pub async fn index_changes(&self, index: &str, since: Option<&str>) -> SearchResult<ChangeSummary> {
+        let documents = self.collection_size(index).await?.unwrap_or(0);
+        let now = WatchCursor::new("typesense", index, documents);
+        match since {
+            Some(since) => {
+                let before = WatchCursor::parse_for(since, "typesense", index).map_err(map_shared_error)?;
+                Ok(ChangeSummary::between(&before, &now, None))
+            }
+            None => Ok(ChangeSummary::start(&now)),
+        }
+    }
+
+    /// Export a collection's schema and documents as an NDJSON snapshot
+    pub async fn create_snapshot(&self, index: &str) -> SearchResult<SnapshotInfo> {
+        audit::audited("typesense", "create_snapshot", index, Value::Null, async {
+            telemetry::traced("typesense", "create_snapshot", index, async {
+                let mut schema = self.client.get_collection(index).await.map_err(map_typesense_error)?;
+                if let Some(schema) = schema.as_object_mut() {
+                    for stats in ["created_at", "num_documents", "num_memory_shards"] {
+                        schema.remove(stats);
+                    }
+                }
+
+                let jsonl = self.client.export_documents(index, None, None).await.map_err(map_typesense_error)?;
+                let documents = jsonl.lines()
+                    .filter(|line| !line.trim().is_empty())
+                    .map(serde_json::from_str)
+                    .collect::<Result<Vec<Value>, _>>()
+                    .map_err(|e| SearchError::Internal(format!("Invalid document in export of {}: {}", index, e)))?;
+
+                let sink = blob::from_env().map_err(map_shared_error)?;
+                let info = snapshot::write_ndjson(sink.as_ref(), index, "typesense", schema, &documents)
+                    .map_err(map_shared_error)?;
+                info!("Snapshotted {} documents of collection {} as {}", documents.len(), index, info.id);
+                Ok(info)
+            })
+            .await
+        })
+        .await
+    }
+
+    /// Recreate a collection from an NDJSON snapshot, replacing the collection if it exists
+    pub async fn restore_snapshot(&self, snapshot_id: &str) -> SearchResult<SnapshotInfo> {
+        audit::audited("typesense", "restore_snapshot", snapshot_id, Value::Null, async {
+            telemetry::traced("typesense", "restore_snapshot", snapshot_id, async {
+                let sink = blob::from_env().map_err(map_shared_error)?;
+                let (header, documents) = snapshot::read_ndjson(sink.as_ref(), snapshot_id, "typesense")
+                    .map_err(map_shared_error)?;
+                let index = header.index.as_str();
+
+                let existing = self.client.list_collections().await.map_err(map_typesense_error)?;
+                if existing.iter().any(|name| name == index) {
+                    self.client.delete_collection(index).await.map_err(map_typesense_error)?;
+                }
+                let mut schema = header.settings.clone();
+                schema["name"] = json!(index);
+                self.client.create_collection(schema).await.map_err(map_typesense_error)?;
+
+                for chunk in documents.chunks(RESTORE_BATCH_SIZE) {
+                    let jsonl: Vec<String> = chunk.iter().map(Value::to_string).collect();
+                    self.client.import_documents(index, jsonl.join("\n")).await.map_err(map_typesense_error)?;
+                }
+
+                cache::global().remove(CacheKind::Schema, &cache::index_key("typesense", index));
+                write_buffer::discard_index(index);
+                checksum::forget_index(index);
+                cache::invalidate_results("typesense", index);
+                info!("Restored collection {} from snapshot {}", index, snapshot_id);
+                Ok(header.info(None))
+            })
+            .await
+        })
+        .await
+    }
 
     pub async fn list_indexes(&self) -> SearchResult<Vec<String>> {
         self.client.list_collections().await.map_err(map_typesense_error)
     }
 
     pub async fn upsert(&self, index: &str, doc: &Doc) -> SearchResult<()> {
+        if write_buffer::is_enabled() {
+            return self.upsert_many(index, std::slice::from_ref(doc)).await;
+        }
+        telemetry::traced("typesense", "upsert", index, async {
+            self.upsert_with_token(index, doc).await.map(|_| ())
+        })
+        .await
+    }
+
+    /// Upsert documents one by one, or buffer them when the write buffer is enabled
+    pub async fn upsert_many(&self, index: &str, docs: &[Doc]) -> SearchResult<()> {
+        quota::global().check_documents(index, docs.len()).map_err(map_shared_error)?;
+        let docs: Vec<_> = docs.iter().map(|doc| golem_search::types::Doc { id: doc.id.clone(), content: doc.content.clone() }).collect();
+        let docs = ingest::apply(index, &docs).map_err(map_shared_error)?;
+        let docs = checksum::skip_unchanged(index, &docs).into_owned();
+        if !write_buffer::is_enabled() {
+            return self.import_upserts(index, &docs).await;
+        }
+        write_buffer::buffer_upserts(index, docs, |index, docs| async move {
+            self.import_upserts(&index, &docs).await
+        })
+        .await
+    }
+
+    /// Write buffered upserts for `index`, or for every index, returning the number of documents written
+    pub async fn flush_writes(&self, index: Option<&str>) -> SearchResult<u32> {
+        let written = write_buffer::flush(index, |index, docs| async move {
+            self.import_upserts(&index, &docs).await
+        })
+        .await?;
+        Ok(written as u32)
+    }
+
+    /// Apply the writes recorded in the worker's outbox, oldest first
+    pub async fn drain_outbox(&self) -> DrainReport {
+        outbox::drain(|index, mutation| async move {
+            match mutation {
+                Mutation::Upsert { doc } => {
+                    self.upsert_with_token(&index, &Doc { id: doc.id, content: doc.content }).await.map(|_| ())
+                }
+                Mutation::Delete { id } => self.delete_ids(&index, &[id]).await,
+            }
+        })
+        .await
+    }
+
+    /// Write the whole write buffer and drain the outbox, before the worker is suspended
+    pub async fn flush(&self) -> ShutdownReport {
+        shutdown::flush(self.flush_writes(None), self.drain_outbox()).await
+    }
+
+    /// Same as [`flush`](Self::flush); Typesense keeps no scroll contexts open
+    pub async fn shutdown(&self) -> ShutdownReport {
+        self.flush().await
+    }
+
+    /// Write a batch of change-feed events, as collapsed by a `ChangeFeedAdapter`
+    pub async fn apply_changes(&self, index: &str, batch: &ChangeBatch) -> SearchResult<()> {
+        telemetry::traced("typesense", "apply_changes", index, async {
+            quota::global().check_documents(index, batch.upserts.len()).map_err(map_shared_error)?;
+            let upserts = ingest::apply(index, &batch.upserts).map_err(map_shared_error)?;
+            let upserts = checksum::skip_unchanged(index, &upserts);
+            if !upserts.is_empty() {
+                self.import_upserts(index, &upserts).await?;
+            }
+            if !batch.deletes.is_empty() {
+                self.delete_ids(index, &batch.deletes).await?;
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    /// Converge an index to `docs`, upserting new and changed documents and deleting missing ones
+    pub async fn sync(&self, index: &str, docs: &[Doc], dry_run: bool) -> SearchResult<SyncReport> {
+        telemetry::traced("typesense", "sync", index, async {
+            // Buffered writes would otherwise land after the comparison
+            self.flush_writes(Some(index)).await?;
+            let existing = self.fingerprint_index(index).await?;
+            let docs = docs.iter().map(|doc| golem_search::types::Doc { id: doc.id.clone(), content: doc.content.clone() });
+            let report = delta_sync::plan(index, existing, docs, dry_run)
+                .map_err(map_shared_error)?
+                .execute(|batch| async move { self.apply_changes(index, &batch).await })
+                .await?;
+            info!(
+                "Synced collection {}: {} upserted, {} deleted, {} unchanged",
+                index, report.upserted, report.deleted, report.unchanged
+            );
+            Ok(report)
+        })
+        .await
+    }
+
+    /// Build or refresh the suggestions collection of `index` from analytics and document titles
+    ///
+    /// The suggestions collection is created when missing and converged to the
+    /// new suggestions with [`Self::sync`].
+    pub async fn build_suggestions(&self, index: &str, queries: &[PopularQuery], config: &SuggestionsConfig) -> SearchResult<SyncReport> {
+        telemetry::traced("typesense", "build_suggestions", index, async {
+            let mut builder = SuggestionsBuilder::new(config.clone());
+            builder.add_queries(queries);
+            if let Some(field) = &config.title_field {
+                self.scan_documents(index, |_, document| {
+                    if let Some(title) = document.get(field).and_then(Value::as_str) {
+                        builder.add_title(title);
+                    }
+                })
+                .await?;
+            }
+            let target = suggestions::suggestions_index(index);
+            self.ensure_index(&target, Some(&from_shared_schema(&suggestions::schema()))).await?;
+            let docs: Vec<Doc> = builder.documents().into_iter().map(|doc| Doc { id: doc.id, content: doc.content }).collect();
+            self.sync(&target, &docs, false).await
+        })
+        .await
+    }
+
+    /// Suggestions from the suggestions collection of `index` that complete `prefix`, most popular first
+    ///
+    /// Typesense matches the last word as a prefix by itself; every word has to match.
+    pub async fn suggest(&self, index: &str, prefix: &str, limit: u32) -> SearchResult<Vec<Suggestion>> {
+        let target = suggestions::suggestions_index(index);
+        let text = suggestions::prefix_text(prefix);
+        let q = if text.is_empty() { "*".to_string() } else { text };
+        let limit = limit.to_string();
+        let params = [
+            ("q", q.as_str()),
+            ("query_by", "query"),
+            ("sort_by", "popularity:desc"),
+            ("drop_tokens_threshold", "0"),
+            ("per_page", limit.as_str()),
+        ];
+        let response = self.client.search_raw(&target, &params).await.map_err(map_typesense_error)?;
+        let response: Value = json::from_str(&response).map_err(map_shared_error)?;
+        let hits = response["hits"].as_array().map(Vec::as_slice).unwrap_or_default();
+        Ok(hits.iter().filter_map(|hit| suggestions::from_document(&hit["document"])).collect())
+    }
+
+    /// Suggestions from the suggestions collection of `index` close to `text`, to correct its spelling with
+    pub async fn spelling_candidates(&self, index: &str, text: &str) -> SearchResult<Vec<Suggestion>> {
+        let target = suggestions::suggestions_index(index);
+        let q = suggestions::prefix_text(text);
+        let limit = spellfix::LOOKUP_LIMIT.to_string();
+        let params = [
+            ("q", q.as_str()),
+            ("query_by", "query"),
+            ("num_typos", "2"),
+            ("prefix", "false"),
+            ("per_page", limit.as_str()),
+        ];
+        let response = self.client.search_raw(&target, &params).await.map_err(map_typesense_error)?;
+        let response: Value = json::from_str(&response).map_err(map_shared_error)?;
+        let hits = response["hits"].as_array().map(Vec::as_slice).unwrap_or_default();
+        Ok(hits.iter().filter_map(|hit| suggestions::from_document(&hit["document"])).collect())
+    }
+
+    /// IDs and hashes of every document in a collection
+    async fn fingerprint_index(&self, index: &str) -> SearchResult<IndexFingerprint> {
+        let mut fingerprint = IndexFingerprint::new();
+        self.scan_documents(index, |id, document| fingerprint.add(id, document)).await?;
+        Ok(fingerprint)
+    }
+
+    /// Visit the ID and content of every document in a collection, read through the export endpoint
+    async fn scan_documents(&self, index: &str, mut visit: impl FnMut(&str, &Value)) -> SearchResult<()> {
+        let geo_fields = self.geo_fields(index).await;
+        let export = self.client.export_documents(index, None, None).await.map_err(map_typesense_error)?;
+        for line in export.lines().filter(|line| !line.trim().is_empty()) {
+            let mut document: Value = json::from_str(line)
+                .map_err(|e| SearchError::Internal(format!("Failed to parse exported document: {}", e)))?;
+            geo::from_provider("typesense", &mut document, &geo_fields);
+            if let Some(id) = document["id"].as_str() {
+                visit(id, &document);
+            }
+        }
+        Ok(())
+    }
+
+    /// Geo point fields of a collection, read from its schema
+    ///
+    /// A collection whose schema cannot be read is treated as having none.
+    async fn geo_fields(&self, index: &str) -> Vec<String> {
+        match self.get_schema(index).await {
+            Ok(schema) => schema.fields.into_iter()
+                .filter(|field| matches!(field.field_type, FieldType::GeoPoint))
+                .map(|field| field.name)
+                .collect(),
+            Err(e) => {
+                debug!("Converting no geo fields for {}: {:?}", index, e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Delete documents by ID filter, which matches nothing once they are gone, so repeats succeed
+    async fn delete_ids(&self, index: &str, ids: &[String]) -> SearchResult<()> {
+        write_buffer::discard(index, ids);
+        checksum::forget(index, ids);
+        cache::invalidate_results("typesense", index);
+        let quoted: Vec<String> = ids.iter().map(|id| format!("`{}`", id)).collect();
+        let filter = format!("id:=[{}]", quoted.join(","));
+        self.client.delete_documents(index, ("filter_by", &filter)).await
+            .map(|_| ())
+            .map_err(map_typesense_error)
+    }
+
+    /// Upsert documents through the import endpoint, in chunks of at most `max_batch_size`
+    ///
+    /// Chunks go out with bounded concurrency; failed chunks do not stop the
+    /// others and are reported together.
+    async fn import_upserts(&self, index: &str, docs: &[golem_search::types::Doc]) -> SearchResult<()> {
+        let geo_fields = self.geo_fields(index).await;
+        let mut lines = Vec::with_capacity(docs.len());
+        for doc in docs {
+            let mut content: Value = serde_json::from_str(&doc.content)
+                .map_err(|e| SearchError::InvalidQuery(e.to_string()))?;
+            content["id"] = json!(doc.id);
+            geo::to_provider("typesense", &mut content, &geo_fields);
+            lines.push((doc.clone(), content.to_string()));
+        }
+        debug!("Importing {} documents into collection {}", docs.len(), index);
+
+        let max_batch_size = self.get_capabilities().max_batch_size.unwrap_or(100) as usize;
+        let client = &self.client;
+
+        import_chunks(index, lines, max_batch_size, |jsonl| client.import_documents(index, jsonl))
+            .await
+            .map_err(|e| map_typesense_error(e.into()))?;
+        Ok(())
+    }
+
+    /// Upsert a document and return a consistency token
+    ///
+    /// Typesense writes are searchable once acknowledged, so the token never
+    /// makes a later query wait.
+    pub async fn upsert_with_token(&self, index: &str, doc: &Doc) -> SearchResult<String> {
+        quota::global().check_documents(index, 1).map_err(map_shared_error)?;
+        let content = ingest::apply_document(index, &doc.id, &doc.content).map_err(map_shared_error)?;
+        self.write_document(index, &golem_search::types::Doc { id: doc.id.clone(), content: content.into_owned() }).await?;
+        Ok(ConsistencyToken::new("typesense", index, WritePosition::Immediate).encode())
+    }
+
+    /// Upsert one document that already went through the ingest pipeline
+    async fn write_document(&self, index: &str, doc: &golem_search::types::Doc) -> SearchResult<()> {
         let mut content: Value = serde_json::from_str(&doc.content)
             .map_err(|e| SearchError::InvalidQuery(e.to_string()))?;
         
         // Ensure the document has an id field
         content["id"] = json!(doc.id);
+        geo::to_provider("typesense", &mut content, &self.geo_fields(index).await);
         
         self.client.upsert_document(index, content).await
             .map_err(map_typesense_error)?;
+        checksum::record(index, std::slice::from_ref(doc));
+        cache::invalidate_results("typesense", index);
+        if alerts::is_watched(index) {
+            alerts::observe(index, std::slice::from_ref(doc));
+        }
         Ok(())
     }
 
-    pub async fn get(&self, index: &str, id: &str) -> SearchResult<Option<Doc>> {
-        let result = self.client.get_document(index, id).await
-            .map_err(map_typesense_error)?;
+    /// Get a document by ID, with only the `fields` paths in its content when given
+    ///
+    /// The document endpoint has no field selection, so the mask is applied here.
+    pub async fn get(&self, index: &str, id: &str, fields: Option<&FieldMask>) -> SearchResult<Option<Doc>> {
+        telemetry::traced("typesense", "get", index, async {
+            let result = self.client.get_document(index, id).await
+                .map_err(map_typesense_error)?;
         
-        if let Some(response) = result {
-            let content = serde_json::to_string(&response)
-                .map_err(|e| SearchError::Internal(e.to_string()))?;
+            if let Some(mut response) = result {
+                geo::from_provider("typesense", &mut response, &self.geo_fields(index).await);
+                let response = match fields {
+                    Some(mask) => mask.project(&response),
+                    None => response,
+                };
+                let content = serde_json::to_string(&response)
+                    .map_err(|e| SearchError::Internal(e.to_string()))?;
             
-            Ok(Some(Doc { 
-                id: id.to_string(), 
-                content 
-            }))
-        } else {
-            Ok(None)
-        }
+                Ok(Some(Doc { 
+                    id: id.to_string(), 
+                    content 
+                }))
+            } else {
+                Ok(None)
+            }
+        })
+        .await
+    }
+
+    /// Estimate the cost of a query without running it
+    pub async fn estimate_query(&self, index: &str, query: &SearchQuery) -> SearchResult<CostEstimate> {
+        let document_count = match self.client.get_collection(index).await {
+            Ok(collection) => collection.get("num_documents").and_then(Value::as_u64),
+            Err(e) => {
+                debug!("Estimating without a document count for {}: {}", index, e);
+                None
+            }
+        };
+        let stats = IndexStats { document_count };
+        Ok(cost::estimate_query(&typesense_capability_matrix(), index, &stats, &to_shared_query(query)))
+    }
+
+    /// Check whether a document exists without transferring it
+    pub async fn exists(&self, index: &str, id: &str) -> SearchResult<bool> {
+        self.client.document_exists(index, id).await
+            .map_err(map_typesense_error)
+    }
+
+    /// Get several documents by ID through a filtered export, in request order
+    pub async fn get_many(&self, index: &str, ids: &[String], fields: Option<&FieldMask>) -> SearchResult<Vec<Option<Doc>>> {
+        telemetry::traced("typesense", "get_many", index, async {
+            let mut docs = Vec::with_capacity(ids.len());
+            // The export selects top-level fields; the ID is kept to match documents to the requested IDs
+            let include_fields = fields.map(|mask| {
+                let mut include_fields = mask.top_level();
+                include_fields.push("id".to_string());
+                include_fields
+            });
+        
+            // Keep the filter (and so the request URL) to a bounded size
+            for chunk in ids.chunks(GET_MANY_CHUNK_SIZE) {
+                let values = chunk.iter().map(|id| format!("`{}`", id)).collect::<Vec<_>>().join(",");
+                let body = self.client.export_documents(index, Some(&format!("id:[{}]", values)), include_fields.as_deref()).await
+                    .map_err(map_typesense_error)?;
+            
+                for line in body.lines().filter(|line| !line.trim().is_empty()) {
+                    docs.push(serde_json::from_str::<Value>(line)
+                        .map_err(|e| SearchError::Internal(e.to_string()))?);
+                }
+            }
+            let geo_fields = self.geo_fields(index).await;
+            for doc in &mut docs {
+                geo::from_provider("typesense", doc, &geo_fields);
+            }
+        
+            align_to_ids(ids, &docs, "id")
+                .into_iter()
+                .zip(ids)
+                .map(|(doc, id)| {
+                    doc.map(|doc| {
+                        let content = match fields {
+                            Some(mask) => serde_json::to_string(&mask.project(doc)),
+                            None => serde_json::to_string(doc),
+                        }
+                        .map_err(|e| SearchError::Internal(e.to_string()))?;
+                        Ok(Doc { id: id.clone(), content })
+                    })
+                    .transpose()
+                })
+                .collect()
+        })
+        .await
     }
 
     pub async fn delete(&self, index: &str, id: &str) -> SearchResult<()> {
-        self.client.delete_document(index, id).await
-            .map_err(map_typesense_error)?;
+        telemetry::traced("typesense", "delete", index, async {
+            write_buffer::discard(index, &[id.to_string()]);
+            checksum::forget(index, &[id.to_string()]);
+            cache::invalidate_results("typesense", index);
+            self.client.delete_document(index, id).await
+                .map_err(map_typesense_error)?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Draw a random sample client-side by paging through the matches
+    ///
+    /// Every sampling page but the last has the same size, so offsets map onto
+    /// Typesense's 1-based `page` exactly.
+    async fn sample_results(&self, index: &str, params: &[(&str, &str)], sample: u32) -> SearchResult<SearchResults> {
+        let base: Vec<(&str, &str)> = params.iter()
+            .filter(|(k, _)| *k != "page" && *k != "per_page")
+            .copied()
+            .collect();
+        
+        let started = std::time::Instant::now();
+        let (hits, scanned) = sampling::sample_pages(sample, |offset, limit| {
+            let per_page = limit.to_string();
+            let page = (offset / limit + 1).to_string();
+            let base = &base;
+            async move {
+                let mut page_params = base.clone();
+                page_params.push(("per_page", per_page.as_str()));
+                page_params.push(("page", page.as_str()));
+                let response = self.client.search_raw(index, &page_params).await
+                    .map_err(map_typesense_error)?;
+                Ok(self.response_to_results(&response)?.hits)
+            }
+        })
+        .await?;
+        
+        Ok(SearchResults {
+            total: sampling::scanned_total(scanned),
+            page: None,
+            per_page: Some(sample),
+            hits,
+            facets: None,
+            took_ms: Some(started.elapsed().as_millis() as u32),
+            truncated: false,
+            request_id: None,
+            groups: None,
+            next_cursor: None,
+            total_exact: None,
+            facet_metadata: Vec::new(),
+            relaxations: Vec::new(),
+            original_query: None,
+            corrected_query: None,
+        })
+    }
+
+    /// Compute date histograms and stats client-side by paging through the matches
+    ///
+    /// Only the faceted fields are retrieved, and no facets or highlights
+    /// are computed for the scanned pages.
+    async fn scan_client_facets(&self, index: &str, params: &[(&str, &str)], client_facets: &mut ClientFacets) -> SearchResult<()> {
+        let include_fields = client_facets.fields().join(",");
+        let mut base: Vec<(&str, &str)> = params.iter()
+            .filter(|(k, _)| !matches!(*k, "page" | "per_page" | "facet_by" | "highlight_fields" | "include_fields"))
+            .copied()
+            .collect();
+        base.push(("include_fields", include_fields.as_str()));
+        
+        let base = &base;
+        let scanned = sampling::scan_pages(
+            |offset, limit| async move {
+                let per_page = limit.to_string();
+                let page = (offset / limit + 1).to_string();
+                let mut page_params = base.clone();
+                page_params.push(("per_page", per_page.as_str()));
+                page_params.push(("page", page.as_str()));
+                let response = self.client.search_raw(index, &page_params).await
+                    .map_err(map_typesense_error)?;
+                Ok::<_, SearchError>(self.response_to_results(&response)?.hits)
+            },
+            |hit| {
+                if let Some(document) = hit.content.as_deref().and_then(|c| serde_json::from_str::<Value>(c).ok()) {
+                    client_facets.add_document(&document);
+                }
+            },
+        )
+        .await?;
+        if scanned >= u64::from(sampling::MAX_SAMPLE_SCAN) && !client_facets.histograms.is_empty() {
+            warn!("Date histograms on {} cover the first {} matches only", index, scanned);
+        }
         Ok(())
     }
 
+    /// Search through the shared result cache, honouring per-index cache policies
     pub async fn search(&self, index: &str, query: &SearchQuery) -> SearchResult<SearchResults> {
-        let params = self.query_to_typesense_params(query);
+        telemetry::traced("typesense", "search", index, async {
+            quota::global().check_query(index).map_err(map_shared_error)?;
+            let scope = request_id::begin();
+            let _deadline = deadline::begin(query.timeout_ms);
+            let mut results = cache::with_result_cache("typesense", index, query, results_size, |index, query| async move {
+                let limits = SplitLimits::new(&typesense_capability_matrix().performance_limits);
+                limits.check(&query).map_err(map_shared_error)?;
+                let (index, limits) = (index.as_str(), &limits);
+                let lookup = |text: String| async move { self.spelling_candidates(index, &text).await };
+                rescue::search_rescued(&query, &RescuePlan::from_env(), lookup, |query| async move {
+                    query_split::search_split(&query, limits, |part| async move { self.fetch_results(index, &part).await }).await
+                })
+                .await
+            })
+            .await?;
+            results.request_id = Some(scope.id().to_string());
+            results.facet_metadata = self.facet_metadata(index, &query.facets).await;
+            Ok(results)
+        })
+        .await
+    }
+
+    /// Display metadata of the requested facets; left empty when the schema cannot be read
+    async fn facet_metadata(&self, index: &str, facets: &[String]) -> Vec<FacetMetadata> {
+        if facets.is_empty() {
+            return Vec::new();
+        }
+        match self.get_schema(index).await {
+            Ok(schema) => ui_metadata::facet_metadata(&to_shared_schema(&schema), facets)
+                .into_iter()
+                .map(from_shared_facet_metadata)
+                .collect(),
+            Err(e) => {
+                warn!("Failed to read facet metadata of index {}: {}", index, e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Run a search against the backend, bypassing the result cache
+    async fn fetch_results(&self, index: &str, query: &SearchQuery) -> SearchResult<SearchResults> {
+        if let Some(token) = query.consistency_token.as_deref() {
+            // Validate the token; Typesense writes need no waiting
+            ConsistencyToken::parse_for(token, "typesense").map_err(map_shared_error)?;
+        }
+        
+        let mut params = compiled_query::compile("typesense", index, query, |query| self.query_to_typesense_params(query))?;
+        let per_page = param_value(&params, "per_page").unwrap_or(cursor::DEFAULT_PER_PAGE).max(1);
+        if let Some(token) = query.cursor.as_deref() {
+            let cursor = PageCursor::parse_for(token, "typesense", index).map_err(map_shared_error)?;
+            params.retain(|(k, _)| *k != "page");
+            params.push(("page", (cursor.offset(per_page) / per_page + 1).to_string()));
+        }
+        let page = param_value(&params, "page").unwrap_or(1);
+        let facets = parse_facets(&query.facets).map_err(map_shared_error)?;
+        if facets.iter().any(|f| f.buckets().is_some()) {
+            version::require(&version::TYPESENSE_RANGE_FACETS, self.server_version().await)
+                .map_err(map_shared_error)?;
+        }
+        let provider_params = parse_provider_params(query.provider_params.as_deref(), TYPESENSE_ALLOWED_PARAMS)
+            .map_err(map_shared_error)?;
+        let extra_params = to_query_pairs(&provider_params);
         let param_refs: Vec<(&str, &str)> = params.iter()
             .map(|(k, v)| (*k, v.as_str()))
+            .chain(extra_params.iter().map(|(k, v)| (k.as_str(), v.as_str())))
+            .collect();
+        let logged_params: serde_json::Map<String, Value> = param_refs.iter()
+            .map(|(k, v)| (k.to_string(), json!(v)))
             .collect();
         
-        let response = self.client.search(index, &param_refs).await
-            .map_err(map_typesense_error)?;
+        let started = std::time::Instant::now();
+        let mut results = match query.sample {
+            Some(sample) => self.sample_results(index, &param_refs, sample).await?,
+            None => {
+                let response = self.client.search_raw(index, &param_refs).await
+                    .map_err(map_typesense_error)?;
+                self.response_to_results(&response)?
+            }
+        };
+        slow_query::record_search("typesense", index, &Value::Object(logged_params), &to_shared_query(query), started.elapsed());
+        
+        if query.exact_total && results.total_exact.is_none() {
+            results.total_exact = Some(true);
+        }
+        
+        if query.sample.is_none() {
+            // Grouped searches page by group, and their total counts documents
+            let (returned, total) = match &results.groups {
+                Some(groups) => (groups.len(), None),
+                None => (results.hits.len(), results.total),
+            };
+            if cursor::has_more((page - 1) * per_page, returned, per_page, total) {
+                let next = CursorPosition::Page { page: page + 1 };
+                results.next_cursor = Some(PageCursor::new("typesense", index, next).encode());
+            }
+        }
+        
+        let mut client_facets = ClientFacets::for_specs(&facets).map_err(map_shared_error)?;
+        client_facets.limit_stats(index, results.total);
+        if !client_facets.is_empty() {
+            self.scan_client_facets(index, &param_refs, &mut client_facets).await?;
+            results.facets = Some(merge_client_facets_into_facet_counts(results.facets.as_deref(), &client_facets)?);
+        }
+        
+        let geo_fields = self.geo_fields(index).await;
+        if !geo_fields.is_empty() {
+            let hits = results.hits.iter_mut().chain(results.groups.iter_mut().flatten().flat_map(|g| g.hits.iter_mut()));
+            for hit in hits {
+                hit.content = hit.content.as_deref().map(|c| geo::content_from_provider("typesense", c, &geo_fields));
+            }
+        }
+        
+        // Hits of grouped searches may lack the distance Typesense reports for sorted hits
+        if let Some(sort) = geo::distance_sort(&query.sort).map_err(map_shared_error)? {
+            let hits = results.hits.iter_mut().chain(results.groups.iter_mut().flatten().flat_map(|g| g.hits.iter_mut()));
+            for hit in hits.filter(|hit| hit.distance_meters.is_none()) {
+                hit.distance_meters = sort.distance_in_content(hit.content.as_deref());
+            }
+        }
+        
+        // Typesense always returns text_match_info; only surface it on request
+        if !query.explain {
+            for hit in &mut results.hits {
+                hit.explanation = None;
+            }
+        }
         
-        self.response_to_results(&response)
+        // Typesense reports matched tokens but not where they are, so the query words are matched here
+        if let Some(config) = query.highlight.as_ref().filter(|h| h.offsets) {
+            let q = query.q.as_deref().unwrap_or_default();
+            replace_with_offsets(&mut results.hits, &config.fields, q);
+            for group in results.groups.iter_mut().flatten() {
+                replace_with_offsets(&mut group.hits, &config.fields, q);
+            }
+        }
+        
+        Ok(results)
+    }
+
+    /// Search the primary collection and enrich each hit with documents from a lookup collection
+    pub async fn search_with_lookup(
+        &self,
+        primary_index: &str,
+        query: &SearchQuery,
+        lookup: &LookupSpec,
+    ) -> SearchResult<SearchResults> {
+        lookup.validate().map_err(map_shared_error)?;
+
+        let mut results = self.search(primary_index, query).await?;
+        let keys = lookup_keys_from_contents(results.hits.iter().map(|h| h.content.as_deref()), lookup);
+        if keys.is_empty() {
+            return Ok(results);
+        }
+
+        debug!("Looking up {} keys in collection {}", keys.len(), lookup.index);
+
+        let docs = fetch_in_batches(&keys, |batch| self.lookup_documents(lookup, batch)).await?;
+
+        let table = LookupTable::new(lookup, &docs);
+        for hit in &mut results.hits {
+            if let Some(raw) = hit.content.as_deref() {
+                hit.content = Some(table.enrich(raw).map_err(map_shared_error)?);
+            }
+        }
+
+        Ok(results)
+    }
+
+    /// Documents of the lookup collection matching `keys`, read page by page
+    async fn lookup_documents(&self, lookup: &LookupSpec, keys: Vec<String>) -> SearchResult<Vec<Value>> {
+        const PAGE_SIZE: usize = 250;
+
+        // Backticks escape values containing commas or operators
+        let values: Vec<String> = keys.iter().map(|k| format!("`{}`", k)).collect();
+        let mut params = vec![
+            ("q", "*".to_string()),
+            ("filter_by", format!("{}:=[{}]", lookup.foreign_field, values.join(","))),
+            ("per_page", PAGE_SIZE.to_string()),
+        ];
+        if !lookup.fields.is_empty() {
+            let mut fields = lookup.fields.clone();
+            fields.push(lookup.foreign_field.clone());
+            params.push(("include_fields", fields.join(",")));
+        }
+
+        let mut docs = Vec::new();
+        let mut page = 1;
+        loop {
+            let page_param = page.to_string();
+            let mut param_refs: Vec<(&str, &str)> = params.iter()
+                .map(|(k, v)| (*k, v.as_str()))
+                .collect();
+            param_refs.push(("page", &page_param));
+            let response = self.client.search(&lookup.index, &param_refs).await
+                .map_err(map_typesense_error)?;
+
+            let hits = response["hits"].as_array().cloned().unwrap_or_default();
+            let found = response["found"].as_u64();
+            let count = hits.len();
+            docs.extend(hits.into_iter().filter_map(|mut hit| hit.get_mut("document").map(Value::take)));
+            if count < PAGE_SIZE || found.is_some_and(|found| docs.len() as u64 >= found) {
+                return Ok(docs);
+            }
+            if docs.len() >= MAX_LOOKUP_DOCS {
+                check_lookup_complete(lookup, docs.len(), found).map_err(map_shared_error)?;
+                return Ok(docs);
+            }
+            page += 1;
+        }
     }
 
     pub async fn get_schema(&self, index: &str) -> SearchResult<Schema> {
+        let cache_key = cache::index_key("typesense", index);
+        if let Some(schema) = cache::global().get::<Schema>(CacheKind::Schema, &cache_key) {
+            return Ok((*schema).clone());
+        }
+        
         let collection = self.client.get_collection(index).await
             .map_err(map_typesense_error)?;
         
-        self.typesense_to_schema(&collection)
+        let schema = self.typesense_to_schema(&collection)?;
+        numeric::remember("typesense", index, &to_shared_schema(&schema));
+        let size = cache::schema_size(schema.fields.iter().map(|f| f.name.as_str()));
+        cache::global().insert(CacheKind::Schema, &cache_key, schema.clone(), size);
+        Ok(schema)
+    }
+
+    /// Export schema, synonyms and stop words as a JSON settings snapshot
+    pub async fn export_settings(&self, index: &str) -> SearchResult<String> {
+        debug!("Exporting settings for Typesense collection {}", index);
+
+        let snapshot = self.current_settings(index).await?;
+        snapshot.normalized().to_json().map_err(map_shared_error)
+    }
+
+    /// Schema, synonyms and stop words currently applied to a collection
+    async fn current_settings(&self, index: &str) -> SearchResult<IndexSettings> {
+        let schema = self.get_schema(index).await?;
+        let rules = self.client.list_synonyms(index).await.map_err(map_typesense_error)?;
+        let stop_words = self.client.get_stopwords(index).await.map_err(map_typesense_error)?;
+        Ok(IndexSettings {
+            schema: Some(to_shared_schema(&schema)),
+            synonyms: synonym_rules_to_map(&rules),
+            stop_words,
+            ..Default::default()
+        })
+    }
+
+    /// Apply a JSON settings snapshot; applying the same snapshot twice is a no-op
+    ///
+    /// Synonyms become one-way rules of the collection and stop words a
+    /// stopword set named after it, which searches use by passing its name as
+    /// the `stopwords` provider param. Typesense has no ranking rules or
+    /// normalization settings, so those are skipped.
+    pub async fn apply_settings(&self, index: &str, settings_json: &str) -> SearchResult<()> {
+        audit::audited("typesense", "apply_settings", index, audit::settings_details(settings_json), async {
+            info!("Applying settings to Typesense collection {}", index);
+
+            let desired = IndexSettings::from_json(settings_json).map_err(map_shared_error)?;
+            if let Some(schema) = &desired.schema {
+                self.ensure_index(index, Some(&from_shared_schema(schema))).await?;
+            }
+            if !desired.ranking_rules.is_empty() {
+                warn!("Typesense has no ranking rules setting; ignoring {} rules", desired.ranking_rules.len());
+            }
+            if desired.normalization.is_some() {
+                warn!("Typesense keeps no normalization settings; set it in the search config instead");
+            }
+
+            let rules = self.client.list_synonyms(index).await.map_err(map_typesense_error)?;
+            if synonym_rules_to_map(&rules) != desired.synonyms {
+                for id in rules.iter().filter_map(|rule| rule["id"].as_str()) {
+                    self.client.delete_synonym(index, id).await.map_err(map_typesense_error)?;
+                }
+                for (position, (term, equivalents)) in desired.synonyms.iter().enumerate() {
+                    let rule = json!({ "root": term, "synonyms": equivalents });
+                    self.client
+                        .upsert_synonym(index, &format!("settings-{}", position), rule)
+                        .await
+                        .map_err(map_typesense_error)?;
+                }
+            }
+
+            let stop_words = self.client.get_stopwords(index).await.map_err(map_typesense_error)?;
+            if stop_words != desired.stop_words {
+                self.client.put_stopwords(index, &desired.stop_words).await.map_err(map_typesense_error)?;
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    /// Report drift between the collection and a desired settings snapshot, optionally reconciling it
+    pub async fn diff_settings(&self, index: &str, desired_json: &str, reconcile: bool) -> SearchResult<SettingsDiff> {
+        let desired = IndexSettings::from_json(desired_json).map_err(map_shared_error)?;
+
+        let mut diff = self.current_settings(index).await?.diff(&desired);
+
+        if reconcile && !diff.is_empty() {
+            info!("Reconciling {} setting changes on Typesense collection {}", diff.changes.len(), index);
+            self.apply_settings(index, desired_json).await?;
+            diff.reconciled = true;
+        }
+
+        Ok(diff)
+    }
+}
+
+/// Send JSONL lines through `send` in adaptively sized chunks, recording checksums per accepted chunk
+async fn import_chunks<F, Fut>(
+    index: &str,
+    lines: Vec<(golem_search::types::Doc, String)>,
+    max_batch_size: usize,
+    send: F,
+) -> Result<golem_search::batching::BatchMetrics, golem_search::batching::BulkError>
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = Result<()>>,
+{
+    let mut batcher = AdaptiveBatcher::new(AdaptiveBatchConfig::with_max(max_batch_size));
+    let send = &send;
+    submit_adaptive(lines, &mut batcher, |chunk: Vec<(golem_search::types::Doc, String)>| async move {
+        let mut jsonl = String::new();
+        for (_, line) in &chunk {
+            jsonl.push_str(line);
+            jsonl.push('\n');
+        }
+        send(jsonl).await?;
+        let written: Vec<_> = chunk.into_iter().map(|(doc, _)| doc).collect();
+        checksum::record(index, &written);
+        cache::invalidate_results("typesense", index);
+        alerts::observe(index, &written);
+        Ok::<_, anyhow::Error>(())
+    })
+    .await
+}
+
+/// Synonyms of a collection's rules, keyed by term
+///
+/// A one-way rule maps its root to its synonyms; a multi-way rule maps its
+/// first synonym to the others, as the snapshot has no multi-way form.
+fn synonym_rules_to_map(rules: &[Value]) -> BTreeMap<String, Vec<String>> {
+    let mut synonyms = BTreeMap::new();
+    for rule in rules {
+        let mut terms: Vec<String> = serde_json::from_value(rule["synonyms"].clone()).unwrap_or_default();
+        let term = match rule["root"].as_str().filter(|root| !root.is_empty()) {
+            Some(root) => root.to_string(),
+            None if !terms.is_empty() => terms.remove(0),
+            None => continue,
+        };
+        synonyms.insert(term, terms);
+    }
+    synonyms
+}
+
+#[cfg(feature = "durability")]
+impl TypesenseProvider {
+    /// Start a job copying `source` into `destination`, returning its ID
+    ///
+    /// Both collections have to exist; the source is counted for the ETA.
+    pub async fn start_reindex(&self, source: &str, destination: &str) -> SearchResult<String> {
+        audit::audited("typesense", "start_reindex", destination, json!({ "source": source }), async {
+            let total = self.collection_size(source).await?;
+            self.collection_size(destination).await?;
+            reindex::start(self, "typesense", source, destination, total).await
+        })
+        .await
+    }
+
+    /// Copy pages of a job for up to `SEARCH_REINDEX_STEP_MS`, returning its status
+    ///
+    /// The source is exported once per step and paged through in export order.
+    pub async fn run_reindex(&self, id: &str) -> SearchResult<ReindexStatus> {
+        let mut job = reindex::job(self, "typesense", id).await?;
+        let export = RefCell::new(None);
+        let export = &export;
+        job.run(
+            self,
+            reindex::step_budget(),
+            |index, position| async move { self.reindex_page(&index, position.as_deref(), export).await },
+            |index, docs| async move { self.upsert_many(&index, &docs).await },
+        )
+        .await?;
+        Ok(job.status())
+    }
+
+    /// A page of a collection's documents for a reindex job, read from its export
+    ///
+    /// The export lists documents in the order they were first written, which
+    /// upserts do not change, so a listing position stays valid across steps.
+    /// `export` holds the documents exported in this step.
+    async fn reindex_page(&self, index: &str, position: Option<&str>, export: &RefCell<Option<Vec<Doc>>>) -> SearchResult<SourcePage<Doc>> {
+        if export.borrow().is_none() {
+            let mut docs = Vec::new();
+            self.scan_documents(index, |id, document| docs.push(Doc { id: id.to_string(), content: document.to_string() })).await?;
+            *export.borrow_mut() = Some(docs);
+        }
+        let export = export.borrow();
+        let docs = export.as_deref().unwrap_or_default();
+
+        let ids: Vec<&str> = docs.iter().map(|doc| doc.id.as_str()).collect();
+        let start = reindex::resume_listing(&ids, position).map_err(map_shared_error)?;
+        let end = (start + REINDEX_PAGE_SIZE).min(docs.len());
+        let next = (end < docs.len()).then(|| reindex::listing_position(end, &ids[start..end]));
+        Ok(SourcePage { docs: docs[start..end].to_vec(), next })
+    }
+}
+
+/// Jobs are documents of the jobs collection, created with an auto-detected schema on the first write
+#[cfg(feature = "durability")]
+impl JobStore for TypesenseProvider {
+    type Error = SearchError;
+
+    fn load_job<'a>(&'a self, id: &'a str) -> LocalBoxFuture<'a, SearchResult<Option<golem_search::Doc>>> {
+        Box::pin(async move {
+            let document = self.client.get_document(JOBS_INDEX, id).await.map_err(map_typesense_error)?;
+            Ok(document.map(|document| golem_search::Doc { id: id.to_string(), content: document.to_string() }))
+        })
+    }
+
+    fn save_job(&self, doc: golem_search::Doc) -> LocalBoxFuture<'_, SearchResult<()>> {
+        Box::pin(async move {
+            let mut document: Value = serde_json::from_str(&doc.content).map_err(|e| SearchError::Internal(e.to_string()))?;
+            document["id"] = json!(doc.id);
+            let upserted = self.client.upsert_document(JOBS_INDEX, document.clone()).await.map_err(map_typesense_error);
+            if !matches!(upserted, Err(SearchError::IndexNotFound(_))) {
+                return upserted.map(|_| ());
+            }
+            let schema = json!({ "name": JOBS_INDEX, "fields": [{ "name": ".*", "type": "auto" }] });
+            match self.client.create_collection(schema).await.map_err(map_typesense_error) {
+                // Another worker created it first
+                Ok(_) | Err(SearchError::IndexAlreadyExists(_)) => {}
+                Err(e) => return Err(e),
+            }
+            self.client.upsert_document(JOBS_INDEX, document).await.map_err(map_typesense_error)?;
+            Ok(())
+        })
+    }
+}
+
+impl From<golem_search::SearchError> for SearchError {
+    fn from(error: golem_search::SearchError) -> Self {
+        map_shared_error(error)
     }
 }
 
@@ -684,89 +2786,211 @@ wit_bindgen::generate!({
     },
 });
 
-use exports::golem::search::core::Guest;
+use exports::golem::search::core::{Guest, GuestIndexWatch, GuestReindexJob, GuestSearchSession, IndexWatch, ReindexJob, SearchSession};
 
 // Export the implementation
 struct Component;
 
+thread_local! {
+    static PROVIDER: ProviderCache<TypesenseProvider> = const { ProviderCache::new() };
+}
+
+/// Run an export on the instance's runtime
+fn block_on<T>(call: impl Future<Output = SearchResult<T>>) -> SearchResult<T> {
+    instance::runtime().map_err(map_shared_error)?.block_on(call)
+}
+
+/// The instance's provider, built with its configuration and client on first use
+async fn shared_provider() -> SearchResult<Rc<TypesenseProvider>> {
+    instance::get_or_try_init(&PROVIDER, TypesenseProvider::new).await
+}
+
+/// Refuse a named server ingest pipeline, which Typesense does not have
+fn no_server_pipeline(pipeline: Option<&str>) -> SearchResult<()> {
+    match pipeline {
+        Some(name) => Err(SearchError::Unsupported(format!(
+            "Typesense has no server ingest pipeline {}; use set-ingest-pipeline",
+            name
+        ))),
+        None => Ok(()),
+    }
+}
+
 impl Guest for Component {
+    type SearchSession = TypesenseSession;
+    type IndexWatch = TypesenseIndexWatch;
+    type ReindexJob = TypesenseReindexJob;
+
     fn search(index: String, query: SearchQuery) -> SearchResult<SearchResults> {
         // Synchronous wrapper for the async implementation
-        let rt = tokio::runtime::Runtime::new()
-            .map_err(|e| SearchError::Internal(format!("Failed to create async runtime: {}", e)))?;
-        
-        rt.block_on(async {
-            let provider = TypesenseProvider::new().await?;
+        block_on(async {
+            let provider = shared_provider().await?;
             provider.search(&index, &query).await
         })
     }
 
+    fn search_with_lookup(index: String, query: SearchQuery, lookup: String) -> SearchResult<SearchResults> {
+        block_on(async {
+            let lookup = LookupSpec::from_json(&lookup).map_err(map_shared_error)?;
+            let provider = shared_provider().await?;
+            provider.search_with_lookup(&index, &query, &lookup).await
+        })
+    }
+
+    fn estimate_query(index: String, query: SearchQuery) -> SearchResult<String> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            let estimate = provider.estimate_query(&index, &query).await?;
+            serde_json::to_string(&estimate).map_err(|e| SearchError::Internal(e.to_string()))
+        })
+    }
+
+    fn parse_query(text: String) -> SearchResult<QueryAst> {
+        query_ast::parse_query(&text).map(from_shared_query_ast).map_err(map_shared_error)
+    }
+
+    fn render_query(ast: QueryAst) -> SearchResult<String> {
+        query_ast::render_query(&to_shared_query_ast(ast)).map_err(map_shared_error)
+    }
+
     fn upsert(index: String, doc: Doc) -> SearchResult<()> {
-        let rt = tokio::runtime::Runtime::new()
-            .map_err(|e| SearchError::Internal(format!("Failed to create async runtime: {}", e)))?;
-        
-        rt.block_on(async {
-            let provider = TypesenseProvider::new().await?;
+        block_on(async {
+            let provider = shared_provider().await?;
             provider.upsert(&index, &doc).await
         })
     }
 
-    fn get(index: String, id: String) -> SearchResult<Option<Doc>> {
-        let rt = tokio::runtime::Runtime::new()
-            .map_err(|e| SearchError::Internal(format!("Failed to create async runtime: {}", e)))?;
-        
-        rt.block_on(async {
-            let provider = TypesenseProvider::new().await?;
-            provider.get(&index, &id).await
+    fn upsert_with_token(index: String, doc: Doc) -> SearchResult<String> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            provider.upsert_with_token(&index, &doc).await
+        })
+    }
+
+    fn get(index: String, id: String, fields: Option<Vec<String>>) -> SearchResult<Option<Doc>> {
+        block_on(async {
+            let mask = FieldMask::from_fields(fields.as_deref()).map_err(map_shared_error)?;
+            let provider = shared_provider().await?;
+            provider.get(&index, &id, mask.as_ref()).await
+        })
+    }
+
+    fn get_many(index: String, ids: Vec<String>, fields: Option<Vec<String>>) -> SearchResult<Vec<Option<Doc>>> {
+        block_on(async {
+            let mask = FieldMask::from_fields(fields.as_deref()).map_err(map_shared_error)?;
+            let provider = shared_provider().await?;
+            provider.get_many(&index, &ids, mask.as_ref()).await
+        })
+    }
+
+    fn exists(index: String, id: String) -> SearchResult<bool> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            provider.exists(&index, &id).await
         })
     }
 
     fn delete(index: String, id: String) -> SearchResult<()> {
-        let rt = tokio::runtime::Runtime::new()
-            .map_err(|e| SearchError::Internal(format!("Failed to create async runtime: {}", e)))?;
-        
-        rt.block_on(async {
-            let provider = TypesenseProvider::new().await?;
+        block_on(async {
+            let provider = shared_provider().await?;
             provider.delete(&index, &id).await
         })
     }
 
     fn create_index(name: String, schema: Option<Schema>) -> SearchResult<()> {
-        let rt = tokio::runtime::Runtime::new()
-            .map_err(|e| SearchError::Internal(format!("Failed to create async runtime: {}", e)))?;
-        
-        rt.block_on(async {
-            let provider = TypesenseProvider::new().await?;
+        block_on(async {
+            let provider = shared_provider().await?;
             provider.create_index(&name, schema.as_ref()).await
         })
     }
 
-    fn delete_index(name: String) -> SearchResult<()> {
-        let rt = tokio::runtime::Runtime::new()
-            .map_err(|e| SearchError::Internal(format!("Failed to create async runtime: {}", e)))?;
-        
-        rt.block_on(async {
-            let provider = TypesenseProvider::new().await?;
-            provider.delete_index(&name).await
+    fn ensure_index(name: String, schema: Option<Schema>) -> SearchResult<bool> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            provider.ensure_index(&name, schema.as_ref()).await
+        })
+    }
+
+    fn create_index_from_template(name: String, template: String, overrides: Option<String>) -> SearchResult<String> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            let template = provider.create_index_from_template(&name, &template, overrides.as_deref()).await?;
+            serde_json::to_string(&template).map_err(|e| SearchError::Internal(e.to_string()))
+        })
+    }
+
+    fn export_settings(index: String) -> SearchResult<String> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            provider.export_settings(&index).await
+        })
+    }
+
+    fn apply_settings(index: String, settings: String) -> SearchResult<()> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            provider.apply_settings(&index, &settings).await
+        })
+    }
+
+    fn diff_settings(index: String, desired: String, reconcile: bool) -> SearchResult<String> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            let diff = provider.diff_settings(&index, &desired, reconcile).await?;
+            diff.to_json().map_err(map_shared_error)
+        })
+    }
+
+    fn delete_index(name: String, dry_run: bool) -> SearchResult<String> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            let plan = provider.delete_index(&name, dry_run).await?;
+            serde_json::to_string(&plan).map_err(|e| SearchError::Internal(e.to_string()))
+        })
+    }
+
+    fn delete_by_query(index: String, filters: Vec<String>, dry_run: bool) -> SearchResult<String> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            let plan = provider.delete_by_query(&index, &filters, dry_run).await?;
+            serde_json::to_string(&plan).map_err(|e| SearchError::Internal(e.to_string()))
+        })
+    }
+
+    fn clear_index(index: String, dry_run: bool) -> SearchResult<String> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            let plan = provider.clear_index(&index, dry_run).await?;
+            serde_json::to_string(&plan).map_err(|e| SearchError::Internal(e.to_string()))
         })
     }
 
     fn list_indexes() -> SearchResult<Vec<String>> {
-        let rt = tokio::runtime::Runtime::new()
-            .map_err(|e| SearchError::Internal(format!("Failed to create async runtime: {}", e)))?;
-        
-        rt.block_on(async {
-            let provider = TypesenseProvider::new().await?;
+        block_on(async {
+            let provider = shared_provider().await?;
             provider.list_indexes().await
         })
     }
 
+    fn create_snapshot(index: String) -> SearchResult<String> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            let snapshot = provider.create_snapshot(&index).await?;
+            serde_json::to_string(&snapshot).map_err(|e| SearchError::Internal(e.to_string()))
+        })
+    }
+
+    fn restore_snapshot(snapshot_id: String) -> SearchResult<String> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            let snapshot = provider.restore_snapshot(&snapshot_id).await?;
+            serde_json::to_string(&snapshot).map_err(|e| SearchError::Internal(e.to_string()))
+        })
+    }
+
     fn get_schema(index: String) -> SearchResult<Schema> {
-        let rt = tokio::runtime::Runtime::new()
-            .map_err(|e| SearchError::Internal(format!("Failed to create async runtime: {}", e)))?;
-        
-        rt.block_on(async {
-            let provider = TypesenseProvider::new().await?;
+        block_on(async {
+            let provider = shared_provider().await?;
             provider.get_schema(&index).await
         })
     }
@@ -778,6 +3002,7 @@ impl Guest for Component {
             api_key: "dummy".to_string(),
             timeout: Duration::from_secs(30),
             max_retries: 3,
+            compression: Compression::None,
         };
         
         let client = TypesenseClient::new(config).unwrap();
@@ -786,27 +3011,498 @@ impl Guest for Component {
     }
 
     fn batch_upsert(index: String, docs: Vec<Doc>) -> SearchResult<()> {
-        let rt = tokio::runtime::Runtime::new()
-            .map_err(|e| SearchError::Internal(format!("Failed to create async runtime: {}", e)))?;
-        
-        rt.block_on(async {
-            let provider = TypesenseProvider::new().await?;
-            // Typesense doesn't have native batch upsert, so we'll do sequential upserts
-            for doc in docs {
-                provider.upsert(&index, &doc).await?;
+        block_on(async {
+            let provider = shared_provider().await?;
+            provider.upsert_many(&index, &docs).await
+        })
+    }
+
+    fn upsert_with_pipeline(index: String, doc: Doc, pipeline: Option<String>) -> SearchResult<()> {
+        no_server_pipeline(pipeline.as_deref())?;
+        Self::upsert(index, doc)
+    }
+
+    fn batch_upsert_with_pipeline(index: String, docs: Vec<Doc>, pipeline: Option<String>) -> SearchResult<()> {
+        no_server_pipeline(pipeline.as_deref())?;
+        Self::batch_upsert(index, docs)
+    }
+
+    fn flush_writes(index: Option<String>) -> SearchResult<u32> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            provider.flush_writes(index.as_deref()).await
+        })
+    }
+
+    fn outbox_record(index: String, mutation: OutboxMutation, idempotency_key: Option<String>) -> SearchResult<Option<u64>> {
+        let mutation = match mutation {
+            OutboxMutation::Upsert(doc) => {
+                // Rejected now rather than left to fail on every drain
+                serde_json::from_str::<Value>(&doc.content)
+                    .map_err(|e| SearchError::InvalidQuery(format!("Invalid document {}: {}", doc.id, e)))?;
+                Mutation::Upsert { doc: golem_search::types::Doc { id: doc.id, content: doc.content } }
             }
-            Ok(())
+            OutboxMutation::Delete(id) => Mutation::Delete { id },
+        };
+        Ok(outbox::record(&index, mutation, idempotency_key))
+    }
+
+    fn drain_outbox() -> SearchResult<String> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            let report = provider.drain_outbox().await;
+            serde_json::to_string(&report).map_err(|e| SearchError::Internal(e.to_string()))
         })
     }
 
+    fn flush() -> SearchResult<String> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            let report = provider.flush().await;
+            serde_json::to_string(&report).map_err(|e| SearchError::Internal(e.to_string()))
+        })
+    }
+
+    fn shutdown() -> SearchResult<String> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            let report = provider.shutdown().await;
+            serde_json::to_string(&report).map_err(|e| SearchError::Internal(e.to_string()))
+        })
+    }
+
+    fn sync(index: String, docs: Vec<Doc>, dry_run: bool) -> SearchResult<String> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            let report = provider.sync(&index, &docs, dry_run).await?;
+            serde_json::to_string(&report).map_err(|e| SearchError::Internal(e.to_string()))
+        })
+    }
+
+    fn build_suggestions(index: String, queries: String, config: Option<String>) -> SearchResult<String> {
+        let queries = suggestions::parse_popular_queries(&queries).map_err(map_shared_error)?;
+        let config = SuggestionsConfig::from_json(config.as_deref()).map_err(map_shared_error)?;
+        block_on(async {
+            let provider = shared_provider().await?;
+            let report = provider.build_suggestions(&index, &queries, &config).await?;
+            serde_json::to_string(&report).map_err(|e| SearchError::Internal(e.to_string()))
+        })
+    }
+
+    fn suggest(index: String, prefix: String, limit: u32) -> SearchResult<String> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            let suggestions = provider.suggest(&index, &prefix, limit).await?;
+            serde_json::to_string(&suggestions).map_err(|e| SearchError::Internal(e.to_string()))
+        })
+    }
+
+    fn set_ingest_pipeline(index: String, pipeline: Option<String>) -> SearchResult<()> {
+        let pipeline = pipeline.as_deref().map(ingest::IngestPipeline::from_json).transpose().map_err(map_shared_error)?;
+        ingest::set_pipeline(&index, pipeline).map_err(map_shared_error)
+    }
+
+    fn save_search(id: String, index: String, query: String, filters: Vec<String>) -> SearchResult<()> {
+        alerts::save_search(&id, &index, &query, filters).map_err(map_shared_error)
+    }
+
+    fn delete_saved_search(id: String) -> SearchResult<bool> {
+        Ok(alerts::delete_search(&id))
+    }
+
+    fn run_alerts() -> SearchResult<String> {
+        serde_json::to_string(&alerts::run_alerts()).map_err(|e| SearchError::Internal(e.to_string()))
+    }
+
     fn health_check() -> SearchResult<()> {
-        let rt = tokio::runtime::Runtime::new()
-            .map_err(|e| SearchError::Internal(format!("Failed to create async runtime: {}", e)))?;
-        
-        rt.block_on(async {
-            let provider = TypesenseProvider::new().await?;
+        block_on(async {
+            let provider = shared_provider().await?;
             // Simple health check by listing collections
             provider.list_indexes().await.map(|_| ())
         })
     }
-}
\ No newline at end of file
+
+    fn get_slow_queries() -> String {
+        serde_json::to_string(&slow_query::get_slow_queries()).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    fn health_report() -> String {
+        serde_json::to_string(&circuit_breaker::health_report("typesense")).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    fn metrics_prometheus() -> String {
+        metrics::render_prometheus()
+    }
+
+    fn diagnose_config() -> String {
+        serde_json::to_string(&diagnose::diagnose_config("typesense")).unwrap_or_default()
+    }
+
+    fn describe() -> SearchResult<String> {
+        block_on(async {
+            let provider = shared_provider().await?;
+            let description = provider.describe().await;
+            serde_json::to_string(&description).map_err(|e| SearchError::Internal(e.to_string()))
+        })
+    }
+
+    fn get_audit_log(since: Option<String>) -> SearchResult<String> {
+        let entries = audit::get_audit_log(since.as_deref()).map_err(map_shared_error)?;
+        serde_json::to_string(&entries).map_err(|e| SearchError::Internal(e.to_string()))
+    }
+
+    fn list_pending_tasks(index: String) -> SearchResult<String> {
+        // Documents are indexed during the write request, so there is never a backlog
+        let pending = PendingTasks { provider: "typesense".to_string(), index, total: 0, tasks: Vec::new() };
+        serde_json::to_string(&pending).map_err(|e| SearchError::Internal(e.to_string()))
+    }
+}
+
+/// Defaults applied to the calls made on one `search-session` resource
+pub struct TypesenseSession {
+    provider: Rc<TypesenseProvider>,
+    context: RefCell<SessionContext>,
+}
+
+impl GuestSearchSession for TypesenseSession {
+    fn open(default_index: Option<String>, user_token: Option<String>) -> SearchResult<SearchSession> {
+        let provider = block_on(shared_provider())?;
+        let context = RefCell::new(SessionContext::new(default_index, user_token));
+        Ok(SearchSession::new(Self { provider, context }))
+    }
+
+    fn search(&self, index: Option<String>, mut query: SearchQuery) -> SearchResult<SearchResults> {
+        let context = self.context.borrow();
+        let index = context.index(index.as_deref()).map_err(map_shared_error)?;
+        context.apply_consistency(&mut query.consistency_token);
+        let _user = context.enter();
+        block_on(self.provider.search(index, &query))
+    }
+
+    fn upsert(&self, index: Option<String>, doc: Doc) -> SearchResult<()> {
+        let token = {
+            let context = self.context.borrow();
+            let index = context.index(index.as_deref()).map_err(map_shared_error)?;
+            let _user = context.enter();
+            block_on(self.provider.upsert_with_token(index, &doc))?
+        };
+        self.context.borrow_mut().record_write(token);
+        Ok(())
+    }
+
+    fn get(&self, index: Option<String>, id: String, fields: Option<Vec<String>>) -> SearchResult<Option<Doc>> {
+        let context = self.context.borrow();
+        let index = context.index(index.as_deref()).map_err(map_shared_error)?;
+        let mask = FieldMask::from_fields(fields.as_deref()).map_err(map_shared_error)?;
+        let _user = context.enter();
+        block_on(self.provider.get(index, &id, mask.as_ref()))
+    }
+
+    fn delete(&self, index: Option<String>, id: String) -> SearchResult<()> {
+        let context = self.context.borrow();
+        let index = context.index(index.as_deref()).map_err(map_shared_error)?;
+        let _user = context.enter();
+        block_on(self.provider.delete(index, &id))
+    }
+
+    fn set_default_index(&self, index: Option<String>) {
+        self.context.borrow_mut().default_index = index;
+    }
+
+    fn set_user_token(&self, user_token: Option<String>) {
+        self.context.borrow_mut().user_token = user_token;
+    }
+
+    fn consistency_token(&self) -> Option<String> {
+        self.context.borrow().consistency_token.clone()
+    }
+}
+
+/// Position of one `index-watch` resource
+pub struct TypesenseIndexWatch {
+    provider: Rc<TypesenseProvider>,
+    index: String,
+    cursor: RefCell<String>,
+}
+
+impl GuestIndexWatch for TypesenseIndexWatch {
+    fn open(index: String, since: Option<String>) -> SearchResult<IndexWatch> {
+        let provider = block_on(shared_provider())?;
+        let cursor = match since {
+            Some(since) => {
+                WatchCursor::parse_for(&since, "typesense", &index).map_err(map_shared_error)?;
+                since
+            }
+            None => block_on(provider.index_changes(&index, None))?.cursor,
+        };
+        Ok(IndexWatch::new(Self { provider, index, cursor: RefCell::new(cursor) }))
+    }
+
+    fn poll(&self) -> SearchResult<String> {
+        let since = self.cursor.borrow().clone();
+        let summary = block_on(self.provider.index_changes(&self.index, Some(&since)))?;
+        *self.cursor.borrow_mut() = summary.cursor.clone();
+        serde_json::to_string(&summary).map_err(|e| SearchError::Internal(e.to_string()))
+    }
+
+    fn cursor(&self) -> String {
+        self.cursor.borrow().clone()
+    }
+}
+
+/// Handle on a reindex job, stored on the backend by its ID
+pub struct TypesenseReindexJob {
+    id: String,
+}
+
+#[cfg(feature = "durability")]
+impl GuestReindexJob for TypesenseReindexJob {
+    fn start(source: String, destination: String) -> SearchResult<ReindexJob> {
+        let id = block_on(async { shared_provider().await?.start_reindex(&source, &destination).await })?;
+        Ok(ReindexJob::new(Self { id }))
+    }
+
+    fn resume(id: String) -> SearchResult<ReindexJob> {
+        block_on(async { reindex::job(&*shared_provider().await?, "typesense", &id).await })?;
+        Ok(ReindexJob::new(Self { id }))
+    }
+
+    fn id(&self) -> String {
+        self.id.clone()
+    }
+
+    fn run(&self) -> SearchResult<String> {
+        let status = block_on(async { shared_provider().await?.run_reindex(&self.id).await })?;
+        serde_json::to_string(&status).map_err(|e| SearchError::Internal(e.to_string()))
+    }
+
+    fn status(&self) -> SearchResult<String> {
+        let status = block_on(async { reindex::status(&*shared_provider().await?, "typesense", &self.id).await })?;
+        serde_json::to_string(&status).map_err(|e| SearchError::Internal(e.to_string()))
+    }
+
+    fn cancel(&self) -> SearchResult<String> {
+        let status = block_on(async { reindex::cancel(&*shared_provider().await?, "typesense", &self.id).await })?;
+        serde_json::to_string(&status).map_err(|e| SearchError::Internal(e.to_string()))
+    }
+}
+
+#[cfg(not(feature = "durability"))]
+impl GuestReindexJob for TypesenseReindexJob {
+    fn start(_source: String, _destination: String) -> SearchResult<ReindexJob> {
+        Err(SearchError::Unsupported("Reindex jobs need the durability feature".to_string()))
+    }
+
+    fn resume(_id: String) -> SearchResult<ReindexJob> {
+        Err(SearchError::Unsupported("Reindex jobs need the durability feature".to_string()))
+    }
+
+    fn id(&self) -> String {
+        self.id.clone()
+    }
+
+    fn run(&self) -> SearchResult<String> {
+        Err(SearchError::Unsupported("Reindex jobs need the durability feature".to_string()))
+    }
+
+    fn status(&self) -> SearchResult<String> {
+        Err(SearchError::Unsupported("Reindex jobs need the durability feature".to_string()))
+    }
+
+    fn cancel(&self) -> SearchResult<String> {
+        Err(SearchError::Unsupported("Reindex jobs need the durability feature".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+    use std::sync::Mutex;
+    use golem_search::http::{HttpClient, Request, Response, StatusCode, Url};
+
+    /// Scripted answer of a node: a status and body, or `None` to refuse the connection
+    type Reply = Option<(u16, &'static str)>;
+
+    /// Transport answering each node with its scripted replies in turn, repeating the last one
+    struct Nodes {
+        replies: Mutex<HashMap<String, VecDeque<Reply>>>,
+        seen: Mutex<Vec<String>>,
+    }
+
+    impl Nodes {
+        fn new(replies: &[(&str, &[Reply])]) -> Arc<Self> {
+            let replies = replies.iter().map(|(host, replies)| (host.to_string(), replies.iter().copied().collect())).collect();
+            Arc::new(Self { replies: Mutex::new(replies), seen: Mutex::new(Vec::new()) })
+        }
+
+        fn seen(&self) -> Vec<String> {
+            self.seen.lock().unwrap().clone()
+        }
+    }
+
+    impl HttpClient for Nodes {
+        fn execute(&self, request: Request) -> Result<Response, http::Error> {
+            let host = request.url.host_str().unwrap_or_default().to_string();
+            self.seen.lock().unwrap().push(format!("{} {}{}", request.method, host, request.url.path()));
+            let mut replies = self.replies.lock().unwrap();
+            let queue = replies.get_mut(&host).expect("no replies for host");
+            let reply = if queue.len() > 1 { queue.pop_front().unwrap() } else { queue[0] };
+            match reply {
+                Some((status, body)) => {
+                    let status = StatusCode::from_u16(status).unwrap();
+                    Ok(Response::new(status, HeaderMap::new(), body.as_bytes().to_vec(), request.url))
+                }
+                None => Err(http::Error::connect("connection refused")),
+            }
+        }
+    }
+
+    /// Client of the comma-separated `endpoints` sending through `nodes`, with quick retries
+    fn client_over(nodes: &Arc<Nodes>, endpoints: &str, max_retries: u32) -> TypesenseClient {
+        let policy = RetryPolicy { base_delay: Duration::from_millis(1), ..RetryPolicy::new(max_retries) };
+        TypesenseClient {
+            config: TypesenseConfig {
+                endpoint: endpoints.to_string(),
+                api_key: "test".to_string(),
+                timeout: Duration::from_secs(5),
+                max_retries,
+                compression: Compression::default(),
+            },
+            http_client: Client::builder().retry(policy).transport(nodes.clone()).build().unwrap(),
+            endpoints: Arc::new(EndpointPool::parse(endpoints, Duration::from_secs(60)).unwrap()),
+            throttle: rate_limit::throttle("typesense"),
+        }
+    }
+
+    #[test]
+    fn test_searches_fail_over_on_gateway_errors_and_imports_do_not() {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let nodes = Nodes::new(&[
+            ("ts-failover-a", &[Some((502, "bad gateway"))]),
+            ("ts-failover-b", &[Some((200, r#"{"hits":[]}"#))]),
+        ]);
+        let client = client_over(&nodes, "http://ts-failover-a:8108,http://ts-failover-b:8108", 0);
+
+        let results = runtime.block_on(client.search("books", &[("q", "rust")])).unwrap();
+        assert_eq!(results["hits"], json!([]));
+
+        // Both nodes are down now; the import goes to the first to recover and is not repeated
+        client.endpoints.mark_down(&Url::parse("http://ts-failover-b:8108/").unwrap());
+        let err = runtime.block_on(client.import_documents("books", r#"{"id":"1"}"#.to_string())).unwrap_err();
+        assert!(err.to_string().contains("bad gateway"), "{}", err);
+
+        assert_eq!(
+            nodes.seen(),
+            [
+                "GET ts-failover-a/collections/books/documents/search",
+                "GET ts-failover-b/collections/books/documents/search",
+                "POST ts-failover-a/collections/books/documents/import",
+            ]
+        );
+    }
+
+
+    #[test]
+    fn test_searches_are_retried_until_the_retry_limit() {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let nodes = Nodes::new(&[("ts-retry", &[Some((503, "overloaded"))])]);
+        let client = client_over(&nodes, "http://ts-retry:8108", 2);
+
+        let err = runtime.block_on(client.search("books", &[("q", "rust")])).unwrap_err();
+        assert!(err.to_string().contains("overloaded"), "{}", err);
+        assert_eq!(nodes.seen().len(), 3);
+    }
+
+    #[test]
+    fn test_synonym_rules_map_one_way_and_multi_way_rules() {
+        let rules = vec![
+            json!({ "id": "settings-0", "root": "phone", "synonyms": ["mobile", "cell"] }),
+            json!({ "id": "colours", "synonyms": ["color", "colour"] }),
+            json!({ "id": "empty", "synonyms": [] }),
+        ];
+        let synonyms = synonym_rules_to_map(&rules);
+        assert_eq!(synonyms.len(), 2);
+        assert_eq!(synonyms["phone"], vec!["mobile".to_string(), "cell".to_string()]);
+        assert_eq!(synonyms["color"], vec!["colour".to_string()]);
+    }
+
+    #[test]
+    fn test_document_presence_tells_missing_documents_from_missing_collections() {
+        assert!(document_presence(200, r#"{"id":"a/1"}"#).unwrap());
+        assert!(!document_presence(404, r#"{"message":"Could not find a document with id: a/1"}"#).unwrap());
+
+        let error = map_typesense_error(document_presence(404, r#"{"message":"Not Found"}"#).unwrap_err());
+        assert!(matches!(error, SearchError::IndexNotFound(_)));
+    }
+
+    #[test]
+    fn test_exact_total_searches_exhaustively_and_cut_off_totals_are_inexact() {
+        let config = TypesenseConfig {
+            endpoint: "http://localhost:8108".to_string(),
+            api_key: "test".to_string(),
+            timeout: Duration::from_secs(5),
+            max_retries: 0,
+            compression: Compression::default(),
+        };
+        let provider = TypesenseProvider { client: TypesenseClient::new(config).unwrap() };
+        let mut query = SearchQuery {
+            q: Some("rust".to_string()),
+            filters: Vec::new(),
+            sort: Vec::new(),
+            page: None,
+            per_page: None,
+            offset: None,
+            facets: Vec::new(),
+            highlight: None,
+            provider_params: None,
+            explain: false,
+            consistency_token: None,
+            sample: None,
+            normalization: None,
+            fuzziness: None,
+            minimum_should_match: None,
+            timeout_ms: None,
+            cursor: None,
+            exact_total: false,
+        };
+        let exhaustive = ("exhaustive_search", "true".to_string());
+        assert!(!provider.query_to_typesense_params(&query).unwrap().contains(&exhaustive));
+        query.exact_total = true;
+        assert!(provider.query_to_typesense_params(&query).unwrap().contains(&exhaustive));
+
+        let cut_off = provider.response_to_results(r#"{"found":250,"hits":[],"search_cutoff":true}"#).unwrap();
+        assert_eq!((cut_off.total, cut_off.total_exact), (Some(250), Some(false)));
+        let complete = provider.response_to_results(r#"{"found":250,"hits":[]}"#).unwrap();
+        assert_eq!(complete.total_exact, None);
+    }
+
+    #[test]
+    fn test_import_chunks_records_only_accepted_chunks() {
+        std::env::set_var(checksum::SKIP_UNCHANGED_ENV, "true");
+        let index = "import-chunks-test";
+        let docs: Vec<golem_search::types::Doc> = (0..25)
+            .map(|n| golem_search::types::Doc { id: n.to_string(), content: json!({ "n": n }).to_string() })
+            .collect();
+        let lines = docs.iter().map(|doc| (doc.clone(), json!({ "id": doc.id }).to_string())).collect();
+
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let err = runtime
+            .block_on(import_chunks(index, lines, 10, |jsonl| async move {
+                if jsonl.lines().any(|line| line == r#"{"id":"12"}"#) {
+                    Err(anyhow::anyhow!("bad request: HTTP 400 - invalid document"))
+                } else {
+                    Ok(())
+                }
+            }))
+            .unwrap_err();
+
+        assert!(checksum::is_enabled());
+        let recorded: Vec<bool> = docs.iter().map(|doc| checksum::is_unchanged(index, doc)).collect();
+        assert_eq!(recorded, (0..25).map(|n| !(10..20).contains(&n)).collect::<Vec<_>>());
+        assert_eq!(err.failures.len(), 1);
+        assert_eq!((err.failures[0].offset, err.failures[0].len), (10, 10));
+        assert!(err.to_string().contains("[10..20] bad request"));
+        checksum::forget_index(index);
+    }
+}
@@ -0,0 +1,85 @@
+//! Compare response parsing with and without the `simd-json` feature
+//!
+//! Run with:
+//!
+//! ```text
+//! cargo bench -p golem-search --bench json_parsing
+//! cargo bench -p golem-search --bench json_parsing --features simd-json
+//! ```
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+use serde_json::{json, Value};
+
+/// Build an Elasticsearch-style response page with `hits` documents
+fn search_response(hits: usize) -> String {
+    let hits: Vec<Value> = (0..hits)
+        .map(|i| {
+            json!({
+                "_index": "products",
+                "_id": format!("product-{}", i),
+                "_score": 1.0 / (i as f64 + 1.0),
+                "_source": {
+                    "name": format!("Product {}", i),
+                    "description": "A fairly long product description that repeats a few words to look like real catalog text. ".repeat(4),
+                    "price": i as f64 * 1.25,
+                    "tags": ["electronics", "sale", "featured"],
+                    "in_stock": i % 3 != 0,
+                    "location": {"lat": 52.52, "lon": 13.405},
+                    "attributes": {"color": "black", "weight_g": 250 + i, "rating": 4.5}
+                },
+                "highlight": {"name": [format!("<em>Product</em> {}", i)]}
+            })
+        })
+        .collect();
+
+    json!({
+        "took": 12,
+        "timed_out": false,
+        "hits": {"total": {"value": 10000, "relation": "gte"}, "max_score": 1.0, "hits": hits},
+        "aggregations": {"tags": {"buckets": [{"key": "sale", "doc_count": 420}]}}
+    })
+    .to_string()
+}
+
+fn bench<F: FnMut()>(name: &str, bytes: usize, mut f: F) {
+    // Warm up, then run for roughly one second
+    for _ in 0..3 {
+        f();
+    }
+
+    let mut iterations = 0u32;
+    let started = Instant::now();
+    while started.elapsed() < Duration::from_secs(1) {
+        f();
+        iterations += 1;
+    }
+
+    let per_iter = started.elapsed() / iterations;
+    let throughput = bytes as f64 / per_iter.as_secs_f64() / (1024.0 * 1024.0);
+    println!("{:<40} {:>10.2?}/iter {:>10.1} MiB/s", name, per_iter, throughput);
+}
+
+fn main() {
+    println!("golem_search::json backend: {}", golem_search::json::backend_name());
+
+    for hits in [100, 1_000, 10_000] {
+        let body = search_response(hits);
+        println!("\n{} hits, {} KiB", hits, body.len() / 1024);
+
+        bench("serde_json::from_str", body.len(), || {
+            let value: Value = serde_json::from_str(black_box(&body)).unwrap();
+            black_box(value);
+        });
+
+        bench("golem_search::json::from_str", body.len(), || {
+            let value: Value = golem_search::json::from_str(black_box(&body)).unwrap();
+            black_box(value);
+        });
+
+        bench("golem_search::raw (borrowed)", body.len(), || {
+            let results = golem_search::raw::lucene_response_to_results(black_box(&body), "bench").unwrap();
+            black_box(results);
+        });
+    }
+}
@@ -0,0 +1,468 @@
+//! Saved-search alerts
+//!
+//! A saved search is a query and filters in Lucene-lite syntax stored under
+//! an ID for one index. Providers hand every document they write to an index
+//! with saved searches to [`observe`]; [`run_alerts`] then matches the
+//! documents written since the last run against the saved searches and
+//! delivers the matches, which is what "notify me when a matching listing
+//! appears" features are built on.
+//!
+//! Matching happens in the worker, percolator style, so it behaves the same on
+//! every provider. It approximates the engines' analysis: text is compared as
+//! lowercased alphanumeric words, so stemming, synonyms and typo tolerance do
+//! not apply, and phrase slop only requires the words to appear in order.
+//!
+//! Matches go to hooks registered with [`subscribe`] and, with
+//! `SEARCH_ALERT_WEBHOOK_URL` set, are POSTed there in one JSON body per run;
+//! `run_alerts` also returns them. Saved searches and pending documents live in
+//! worker memory, which Golem persists.
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use chrono::Utc;
+use log::{info, warn};
+use regex::RegexBuilder;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use crate::error::{SearchError, SearchResult};
+use crate::events::post_webhook;
+use crate::query::{parse_lucene, QueryNode, RangeBound};
+use crate::types::Doc;
+
+/// Environment variable holding the URL alert matches are POSTed to
+pub const ALERT_WEBHOOK_URL_ENV: &str = "SEARCH_ALERT_WEBHOOK_URL";
+
+/// Environment variable holding a bearer token sent with alert webhook requests
+pub const ALERT_WEBHOOK_TOKEN_ENV: &str = "SEARCH_ALERT_WEBHOOK_TOKEN";
+
+/// Written documents held for the next run; older ones are dropped past it
+const MAX_PENDING_DOCS: usize = 10_000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedSearch {
+    pub id: String,
+    pub index: String,
+
+    /// Lucene-lite query; empty matches every document
+    pub query: String,
+
+    /// Lucene-lite filters that must all match
+    pub filters: Vec<String>,
+
+    /// RFC 3339 timestamp
+    pub created_at: String,
+}
+
+impl SavedSearch {
+    fn compile(&self) -> SearchResult<Vec<QueryNode>> {
+        std::iter::once(&self.query).chain(&self.filters).map(|text| parse_lucene(text)).collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlertMatch {
+    pub search_id: String,
+    pub index: String,
+    pub document_id: String,
+    pub document: Value,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AlertReport {
+    /// Documents checked against the saved searches
+    pub documents: usize,
+
+    /// Written documents dropped before this run because too many were pending
+    pub dropped: usize,
+    pub matches: Vec<AlertMatch>,
+}
+
+type Hook = Arc<dyn Fn(&[AlertMatch]) + Send + Sync>;
+
+#[derive(Default)]
+struct Alerts {
+    searches: BTreeMap<String, SavedSearch>,
+    pending: VecDeque<(String, Doc)>,
+    dropped: usize,
+    hooks: Vec<Hook>,
+}
+
+fn alerts() -> MutexGuard<'static, Alerts> {
+    static ALERTS: OnceLock<Mutex<Alerts>> = OnceLock::new();
+    ALERTS.get_or_init(Default::default).lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Save a search, replacing any with the same ID
+pub fn save_search(id: &str, index: &str, query: &str, filters: Vec<String>) -> SearchResult<()> {
+    if id.trim().is_empty() {
+        return Err(SearchError::invalid_query("Saved search ID must not be empty"));
+    }
+    let search = SavedSearch {
+        id: id.to_string(),
+        index: index.to_string(),
+        query: query.to_string(),
+        filters,
+        created_at: Utc::now().to_rfc3339(),
+    };
+    for node in search.compile()? {
+        check_regexes(&node)?;
+    }
+    alerts().searches.insert(id.to_string(), search);
+    Ok(())
+}
+
+/// Remove a saved search, returning whether it existed
+pub fn delete_search(id: &str) -> bool {
+    alerts().searches.remove(id).is_some()
+}
+
+pub fn saved_searches() -> Vec<SavedSearch> {
+    alerts().searches.values().cloned().collect()
+}
+
+/// Whether documents written to `index` are checked by the next run
+pub fn is_watched(index: &str) -> bool {
+    alerts().searches.values().any(|search| search.index == index)
+}
+
+/// Hold documents just written to `index` for the next run
+pub fn observe(index: &str, docs: &[Doc]) {
+    let mut alerts = alerts();
+    if !alerts.searches.values().any(|search| search.index == index) {
+        return;
+    }
+    alerts.pending.extend(docs.iter().map(|doc| (index.to_string(), doc.clone())));
+    while alerts.pending.len() > MAX_PENDING_DOCS {
+        alerts.pending.pop_front();
+        alerts.dropped += 1;
+    }
+}
+
+/// Call `hook` with the matches of every run that found any
+pub fn subscribe(hook: impl Fn(&[AlertMatch]) + Send + Sync + 'static) {
+    alerts().hooks.push(Arc::new(hook));
+}
+
+/// Match the documents written since the last run against the saved searches and deliver the matches
+pub fn run_alerts() -> AlertReport {
+    let (searches, pending, dropped, hooks) = {
+        let mut alerts = alerts();
+        let pending = std::mem::take(&mut alerts.pending);
+        let dropped = std::mem::take(&mut alerts.dropped);
+        (alerts.searches.clone(), pending, dropped, alerts.hooks.clone())
+    };
+
+    // A document written twice since the last run is checked once, as last written
+    let mut latest: HashMap<(String, String), usize> = HashMap::new();
+    let docs: Vec<(String, Doc)> = pending.into_iter().collect();
+    for (position, (index, doc)) in docs.iter().enumerate() {
+        latest.insert((index.clone(), doc.id.clone()), position);
+    }
+
+    let compiled: Vec<(&SavedSearch, Vec<QueryNode>)> = searches
+        .values()
+        .filter_map(|search| search.compile().ok().map(|nodes| (search, nodes)))
+        .collect();
+
+    let mut report = AlertReport { dropped, ..AlertReport::default() };
+    for (position, (index, doc)) in docs.iter().enumerate() {
+        if latest.get(&(index.clone(), doc.id.clone())) != Some(&position) {
+            continue;
+        }
+        let Ok(document) = serde_json::from_str::<Value>(&doc.content) else {
+            continue;
+        };
+        report.documents += 1;
+        for (search, nodes) in compiled.iter().filter(|(search, _)| &search.index == index) {
+            if nodes.iter().all(|node| matches(node, &document)) {
+                report.matches.push(AlertMatch {
+                    search_id: search.id.clone(),
+                    index: index.clone(),
+                    document_id: doc.id.clone(),
+                    document: document.clone(),
+                });
+            }
+        }
+    }
+
+    if !report.matches.is_empty() {
+        info!("Saved-search alerts: {} match(es) in {} document(s)", report.matches.len(), report.documents);
+        for hook in &hooks {
+            hook(&report.matches);
+        }
+        if let Ok(url) = std::env::var(ALERT_WEBHOOK_URL_ENV) {
+            let token = std::env::var(ALERT_WEBHOOK_TOKEN_ENV).ok();
+            let body = json!({ "matches": report.matches }).to_string();
+            post_webhook(&url, token.as_deref(), body, &format!("{} alert match(es)", report.matches.len()));
+        }
+    }
+    if dropped > 0 {
+        warn!("Saved-search alerts skipped {} document(s) written while too many were pending", dropped);
+    }
+    report
+}
+
+/// Fail on regular expressions that do not compile, instead of never matching
+fn check_regexes(node: &QueryNode) -> SearchResult<()> {
+    match node {
+        QueryNode::Regex { pattern, .. } => full_match_regex(pattern)
+            .map(|_| ())
+            .map_err(|e| SearchError::invalid_query(format!("Invalid regular expression /{}/: {}", pattern, e))),
+        QueryNode::Bool { must, should, must_not } => {
+            must.iter().chain(should).chain(must_not).try_for_each(check_regexes)
+        }
+        _ => Ok(()),
+    }
+}
+
+fn full_match_regex(pattern: &str) -> Result<regex::Regex, regex::Error> {
+    RegexBuilder::new(&format!("^(?:{})$", pattern)).case_insensitive(true).build()
+}
+
+/// Whether `document` matches `node`, with Lucene `bool` semantics
+fn matches(node: &QueryNode, document: &Value) -> bool {
+    match node {
+        QueryNode::MatchAll => true,
+        QueryNode::Term { field, value } => {
+            let term = value.to_lowercase();
+            scalars(document, field.as_deref()).iter().any(|scalar| match scalar {
+                Value::String(text) => text.to_lowercase() == term || words(text).contains(&term),
+                other => scalar_text(other) == term || compare(other, value) == Some(Ordering::Equal),
+            })
+        }
+        QueryNode::Phrase { field, text, slop } => {
+            let phrase = words(text);
+            scalars(document, field.as_deref()).iter().any(|scalar| {
+                let words = words(&scalar_text(scalar));
+                match slop {
+                    Some(slop) if *slop > 0 => in_order(&words, &phrase),
+                    _ => phrase.is_empty() || words.windows(phrase.len()).any(|window| window == phrase.as_slice()),
+                }
+            })
+        }
+        QueryNode::Wildcard { field, pattern } => {
+            let values = scalars(document, field.as_deref());
+            if pattern == "*" {
+                return !values.is_empty();
+            }
+            let regex = regex::escape(pattern).replace(r"\*", ".*").replace(r"\?", ".");
+            full_match_regex(&regex).is_ok_and(|regex| any_word_or_value(&values, |text| regex.is_match(text)))
+        }
+        QueryNode::Regex { field, pattern } => full_match_regex(pattern)
+            .is_ok_and(|regex| any_word_or_value(&scalars(document, field.as_deref()), |text| regex.is_match(text))),
+        QueryNode::Range { field, lower, upper } => scalars(document, Some(field)).iter().any(|scalar| {
+            let within = |bound: &Option<RangeBound>, above: bool| {
+                bound.as_ref().is_none_or(|bound| match compare(scalar, &bound.value) {
+                    Some(Ordering::Equal) => bound.inclusive,
+                    Some(order) => (order == Ordering::Greater) == above,
+                    None => false,
+                })
+            };
+            within(lower, true) && within(upper, false)
+        }),
+        QueryNode::Bool { must, should, must_not } => {
+            must.iter().all(|node| matches(node, document))
+                && !must_not.iter().any(|node| matches(node, document))
+                && (!must.is_empty() || should.is_empty() || should.iter().any(|node| matches(node, document)))
+        }
+    }
+}
+
+/// Scalar values at a dotted `field` path, through arrays, or anywhere in the document
+fn scalars<'a>(document: &'a Value, field: Option<&str>) -> Vec<&'a Value> {
+    fn leaves<'a>(value: &'a Value, out: &mut Vec<&'a Value>) {
+        match value {
+            Value::Array(items) => items.iter().for_each(|item| leaves(item, out)),
+            Value::Object(map) => map.values().for_each(|item| leaves(item, out)),
+            Value::Null => {}
+            scalar => out.push(scalar),
+        }
+    }
+
+    let mut out = Vec::new();
+    match field {
+        None => leaves(document, &mut out),
+        Some(path) => {
+            let mut current = vec![document];
+            for key in path.split('.') {
+                current = current
+                    .into_iter()
+                    .flat_map(|value| match value {
+                        Value::Array(items) => items.iter().filter_map(|item| item.get(key)).collect(),
+                        other => other.get(key).into_iter().collect::<Vec<_>>(),
+                    })
+                    .collect();
+            }
+            current.into_iter().for_each(|value| leaves(value, &mut out));
+        }
+    }
+    out
+}
+
+fn scalar_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.to_lowercase(),
+        other => other.to_string(),
+    }
+}
+
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty()).map(str::to_lowercase).collect()
+}
+
+fn in_order(words: &[String], phrase: &[String]) -> bool {
+    let mut remaining = words.iter();
+    phrase.iter().all(|word| remaining.any(|candidate| candidate == word))
+}
+
+fn any_word_or_value(values: &[&Value], mut test: impl FnMut(&str) -> bool) -> bool {
+    values.iter().any(|value| {
+        let text = scalar_text(value);
+        test(&text) || words(&text).iter().any(|word| test(word))
+    })
+}
+
+/// Numeric comparison when both sides are numbers, otherwise by text, which orders ISO dates
+fn compare(value: &Value, bound: &str) -> Option<Ordering> {
+    let number = match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.parse::<f64>().ok(),
+        _ => None,
+    };
+    match (number, bound.parse::<f64>()) {
+        (Some(value), Ok(bound)) => value.partial_cmp(&bound),
+        _ => match value {
+            Value::String(s) => Some(s.as_str().cmp(bound)),
+            _ => None,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(query: &str, document: &Value) -> bool {
+        matches(&parse_lucene(query).unwrap(), document)
+    }
+
+    fn listing() -> Value {
+        json!({
+            "title": "Sunny two-bedroom flat near the park",
+            "city": "Lisbon",
+            "price": 1250,
+            "listed": "2024-05-02",
+            "tags": [{ "name": "balcony" }, { "name": "pets" }]
+        })
+    }
+
+    #[test]
+    fn test_terms_match_whole_words_ignoring_case_and_numbers_by_value() {
+        let listing = listing();
+        assert!(check("title:BEDROOM", &listing));
+        assert!(check("city:lisbon", &listing));
+        assert!(check("price:1250.0", &listing));
+        assert!(!check("title:bed", &listing), "terms are not prefixes");
+        assert!(!check("price:125", &listing));
+        assert!(check("balcony", &listing), "fieldless terms search the whole document");
+    }
+
+    #[test]
+    fn test_range_bounds_are_inclusive_or_exclusive_as_written() {
+        let listing = listing();
+        assert!(check("price:[1250 TO 1250]", &listing));
+        assert!(!check("price:{1250 TO *]", &listing));
+        assert!(!check("price:[* TO 1250}", &listing));
+        assert!(check("price:[900 TO 10000]", &listing), "numbers compare numerically, not as text");
+        assert!(check("listed:{2024-05-01 TO 2024-05-02]", &listing), "dates compare as ISO text");
+        assert!(!check("city:[a TO z] AND missing:[0 TO *]", &listing));
+    }
+
+    #[test]
+    fn test_phrases_need_adjacent_words_unless_slop_is_given() {
+        let listing = listing();
+        assert!(check("\"near the park\"", &listing));
+        assert!(check("\"two bedroom\"", &listing), "punctuation separates words");
+        assert!(!check("\"sunny flat\"", &listing));
+        assert!(check("\"sunny flat\"~3", &listing));
+        assert!(!check("\"park near\"~3", &listing), "slop keeps the words in order");
+    }
+
+    #[test]
+    fn test_wildcards_and_regexes_match_a_whole_word_or_value() {
+        let listing = listing();
+        assert!(check("title:sun*", &listing));
+        assert!(check("city:l?sbon", &listing));
+        assert!(!check("title:unn*", &listing));
+        assert!(check("title:/bed.*/", &listing));
+        assert!(!check("title:/edroo/", &listing));
+        assert!(check("city:*", &listing));
+        assert!(!check("rooms:*", &listing), "a bare wildcard needs the field to be present");
+    }
+
+    #[test]
+    fn test_dotted_fields_reach_through_arrays() {
+        let listing = listing();
+        assert!(check("tags.name:pets", &listing));
+        assert!(!check("tags.kind:pets", &listing));
+        assert!(!check("title:flat AND -tags.name:pets", &listing));
+    }
+
+    #[test]
+    fn test_optional_clauses_only_count_without_required_ones() {
+        let listing = listing();
+        assert!(check("city:porto OR price:[* TO 1300]", &listing));
+        assert!(!check("city:porto OR city:madrid", &listing));
+        assert!(check("city:lisbon AND (garden OR balcony)", &listing));
+        assert!(check("-city:porto", &listing));
+    }
+
+    #[test]
+    fn test_saved_searches_are_checked_at_save_time() {
+        assert!(save_search(" ", "alerts-test-invalid", "", vec![]).is_err());
+        assert!(save_search("broken-regex", "alerts-test-invalid", "title:/[/", vec![]).is_err());
+        assert!(save_search("broken-filter", "alerts-test-invalid", "", vec!["price:[1 TO".to_string()]).is_err());
+        assert!(!is_watched("alerts-test-invalid"));
+    }
+
+    // Runs share the worker-wide pending queue, so one test covers them in sequence
+    #[test]
+    fn test_runs_check_the_latest_version_of_each_document_once() {
+        let delivered = Arc::new(Mutex::new(0));
+        let counter = delivered.clone();
+        subscribe(move |matches| {
+            *counter.lock().unwrap() += matches.iter().filter(|m| m.index == "alerts-test").count();
+        });
+        save_search("cheap-lisbon", "alerts-test", "city:lisbon", vec!["price:[* TO 1000]".to_string()]).unwrap();
+
+        let doc = |id: &str, price: u32| Doc { id: id.to_string(), content: json!({ "city": "Lisbon", "price": price }).to_string() };
+        observe("alerts-test", &[doc("1", 900), doc("2", 1500), doc("3", 500)]);
+        observe("alerts-test", &[doc("2", 950), doc("3", 5000)]);
+        observe("alerts-test", &[Doc { id: "4".to_string(), content: "not json".to_string() }]);
+        observe("alerts-test-unwatched", &[doc("5", 100)]);
+
+        let report = run_alerts();
+        let mut ids: Vec<&str> = report.matches.iter().filter(|m| m.index == "alerts-test").map(|m| m.document_id.as_str()).collect();
+        ids.sort();
+        assert_eq!(ids, ["1", "2"]);
+        assert_eq!(*delivered.lock().unwrap(), 2);
+        assert!(run_alerts().matches.iter().all(|m| m.index != "alerts-test"), "documents are checked once");
+
+        // Saving under the same ID replaces the search
+        save_search("cheap-lisbon", "alerts-test", "city:porto", vec![]).unwrap();
+        observe("alerts-test", &[doc("6", 100)]);
+        assert!(run_alerts().matches.iter().all(|m| m.index != "alerts-test"));
+
+        // Past the limit the oldest pending documents are dropped
+        let burst: Vec<Doc> = (0..=MAX_PENDING_DOCS).map(|i| doc(&i.to_string(), 100)).collect();
+        observe("alerts-test", &burst);
+        let report = run_alerts();
+        assert!(report.dropped >= 1);
+        assert!(report.documents <= MAX_PENDING_DOCS);
+
+        assert!(delete_search("cheap-lisbon"));
+        assert!(!delete_search("cheap-lisbon"));
+        assert!(!is_watched("alerts-test"));
+    }
+}
@@ -0,0 +1,613 @@
+//! Text analysis settings shared by every provider
+//!
+//! [`Normalization`] controls how text is folded before matching: Unicode
+//! normalization form, diacritic insensitivity and case sensitivity. It can be
+//! set per index through `IndexSettings` and per query through
+//! `SearchConfig`. Providers map what they can onto their analyzers; the rest
+//! is applied to the query text here, and whatever cannot be honored at all is
+//! reported back as a warning.
+//!
+//! [`FieldAnalysis`] carries per-field hints from the schema: the
+//! [`Tokenizer`] used to segment CJK text, which has no word separators, and
+//! the field's language with whether its words are stemmed.
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+use crate::error::{SearchError, SearchResult};
+use crate::types::{FieldType, Schema};
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+/// Unicode normalization form applied to text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UnicodeForm {
+    /// Text is matched as sent
+    #[default]
+    None,
+    Nfc,
+    Nfd,
+    Nfkc,
+    Nfkd,
+}
+
+/// How text is folded before matching
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Normalization {
+    #[serde(default)]
+    pub unicode_form: UnicodeForm,
+
+    /// Match `café` and `cafe` alike
+    #[serde(default)]
+    pub fold_diacritics: bool,
+
+    /// Match `Apple` and `apple` as different terms
+    #[serde(default)]
+    pub case_sensitive: bool,
+}
+
+/// Which normalization steps a provider performs itself
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NativeNormalization {
+    /// The provider can apply a Unicode normalization form in its analyzers
+    pub unicode_form: bool,
+
+    /// Diacritic folding can be switched on and off
+    pub configurable_diacritics: bool,
+
+    /// Case sensitivity can be switched on and off
+    pub configurable_case: bool,
+
+    /// Whether the provider folds diacritics when it cannot be configured
+    pub folds_diacritics: bool,
+}
+
+impl NativeNormalization {
+    pub fn for_provider(provider: &str) -> Self {
+        match provider.to_lowercase().as_str() {
+            // Analyzer chains take lowercase and asciifolding filters; Unicode
+            // forms need the ICU plugin, so they are applied client-side
+            "elasticsearch" | "elastic" | "opensearch" => Self {
+                unicode_form: false,
+                configurable_diacritics: true,
+                configurable_case: true,
+                folds_diacritics: false,
+            },
+            // Both tokenize case-insensitively and strip diacritics from Latin text
+            "meilisearch" | "typesense" => Self {
+                unicode_form: false,
+                configurable_diacritics: false,
+                configurable_case: false,
+                folds_diacritics: true,
+            },
+            _ => Self {
+                unicode_form: false,
+                configurable_diacritics: false,
+                configurable_case: false,
+                folds_diacritics: false,
+            },
+        }
+    }
+}
+
+/// A normalization split into what the provider does and what is done here
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NormalizationPlan {
+    /// Steps applied to the query text before it is sent
+    pub client_side: Normalization,
+
+    /// Requested behavior the provider cannot honor
+    pub warnings: Vec<String>,
+}
+
+impl Normalization {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Apply every step to `text`
+    pub fn normalize(&self, text: &str) -> String {
+        let mut text = if self.fold_diacritics {
+            text.nfd().filter(|c| !is_combining_mark(*c)).nfc().collect()
+        } else {
+            text.to_string()
+        };
+        text = match self.unicode_form {
+            UnicodeForm::None => text,
+            UnicodeForm::Nfc => text.nfc().collect(),
+            UnicodeForm::Nfd => text.nfd().collect(),
+            UnicodeForm::Nfkc => text.nfkc().collect(),
+            UnicodeForm::Nfkd => text.nfkd().collect(),
+        };
+        if self.case_sensitive {
+            text
+        } else {
+            text.to_lowercase()
+        }
+    }
+
+    /// Decide which steps `provider` performs and which are applied client-side
+    ///
+    /// Client-side steps only touch the query text, so they match documents
+    /// that were stored already normalized the same way.
+    pub fn plan(&self, provider: &str) -> NormalizationPlan {
+        let native = NativeNormalization::for_provider(provider);
+        let mut warnings = Vec::new();
+        let mut client_side = Normalization { case_sensitive: true, ..Normalization::default() };
+
+        if !native.unicode_form {
+            client_side.unicode_form = self.unicode_form;
+        }
+
+        if !native.configurable_diacritics && native.folds_diacritics != self.fold_diacritics {
+            if self.fold_diacritics {
+                client_side.fold_diacritics = true;
+            } else {
+                warnings.push(format!("{} always folds diacritics; fold_diacritics=false is ignored", provider));
+            }
+        }
+
+        if !native.configurable_case && self.case_sensitive {
+            warnings.push(format!("{} matches case-insensitively; case_sensitive=true is ignored", provider));
+        }
+
+        NormalizationPlan { client_side, warnings }
+    }
+
+    /// Normalize query text for `provider`, logging steps it cannot honor
+    pub fn prepare_query(&self, provider: &str, text: &str) -> String {
+        let plan = self.plan(provider);
+        for warning in &plan.warnings {
+            warn!("{}", warning);
+        }
+        plan.client_side.normalize(text)
+    }
+}
+
+/// Tokenizer used to split a text field into terms
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Tokenizer {
+    /// Unicode word boundaries
+    #[default]
+    Standard,
+
+    /// Split on whitespace only, keeping punctuation inside terms
+    Whitespace,
+
+    /// Japanese morphological analysis
+    Kuromoji,
+
+    /// Korean morphological analysis
+    Nori,
+
+    /// Chinese word segmentation
+    Jieba,
+}
+
+impl Tokenizer {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "standard" => Some(Self::Standard),
+            "whitespace" => Some(Self::Whitespace),
+            "kuromoji" => Some(Self::Kuromoji),
+            "nori" => Some(Self::Nori),
+            "jieba" => Some(Self::Jieba),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Standard => "standard",
+            Self::Whitespace => "whitespace",
+            Self::Kuromoji => "kuromoji",
+            Self::Nori => "nori",
+            Self::Jieba => "jieba",
+        }
+    }
+
+    /// ISO 639-1 code of the language a CJK tokenizer segments
+    pub fn language(self) -> Option<&'static str> {
+        match self {
+            Self::Kuromoji => Some("ja"),
+            Self::Nori => Some("ko"),
+            Self::Jieba => Some("zh"),
+            Self::Standard | Self::Whitespace => None,
+        }
+    }
+
+    /// Elasticsearch/OpenSearch analyzer
+    ///
+    /// The CJK analyzers come from the `analysis-kuromoji`, `analysis-nori`
+    /// and `analysis-smartcn` plugins; there is no official jieba plugin, so
+    /// Chinese uses smartcn.
+    pub fn lucene_analyzer(self) -> &'static str {
+        match self {
+            Self::Standard => "standard",
+            Self::Whitespace => "whitespace",
+            Self::Kuromoji => "kuromoji",
+            Self::Nori => "nori",
+            Self::Jieba => "smartcn",
+        }
+    }
+
+    pub fn from_lucene_analyzer(analyzer: &str) -> Option<Self> {
+        match analyzer {
+            "standard" => Some(Self::Standard),
+            "whitespace" => Some(Self::Whitespace),
+            "kuromoji" => Some(Self::Kuromoji),
+            "nori" => Some(Self::Nori),
+            "smartcn" => Some(Self::Jieba),
+            _ => None,
+        }
+    }
+
+    /// Meilisearch `localizedAttributes` locale (ISO 639-3)
+    pub fn meilisearch_locale(self) -> Option<&'static str> {
+        match self {
+            Self::Kuromoji => Some("jpn"),
+            Self::Nori => Some("kor"),
+            Self::Jieba => Some("cmn"),
+            Self::Standard | Self::Whitespace => None,
+        }
+    }
+
+    pub fn from_meilisearch_locale(locale: &str) -> Option<Self> {
+        match locale {
+            "jpn" => Some(Self::Kuromoji),
+            "kor" => Some(Self::Nori),
+            "cmn" | "zho" => Some(Self::Jieba),
+            _ => None,
+        }
+    }
+
+    /// Typesense field `locale`
+    pub fn typesense_locale(self) -> Option<&'static str> {
+        self.language()
+    }
+
+    pub fn from_typesense_locale(locale: &str) -> Option<Self> {
+        match locale {
+            "ja" => Some(Self::Kuromoji),
+            "ko" => Some(Self::Nori),
+            "zh" => Some(Self::Jieba),
+            _ => None,
+        }
+    }
+}
+
+/// Built-in Elasticsearch/OpenSearch language analyzers, by ISO 639-1 code
+const LUCENE_LANGUAGE_ANALYZERS: &[(&str, &str)] = &[
+    ("ar", "arabic"),
+    ("bg", "bulgarian"),
+    ("bn", "bengali"),
+    ("ca", "catalan"),
+    ("cs", "czech"),
+    ("da", "danish"),
+    ("de", "german"),
+    ("el", "greek"),
+    ("en", "english"),
+    ("es", "spanish"),
+    ("et", "estonian"),
+    ("eu", "basque"),
+    ("fa", "persian"),
+    ("fi", "finnish"),
+    ("fr", "french"),
+    ("ga", "irish"),
+    ("gl", "galician"),
+    ("hi", "hindi"),
+    ("hu", "hungarian"),
+    ("hy", "armenian"),
+    ("id", "indonesian"),
+    ("it", "italian"),
+    ("lt", "lithuanian"),
+    ("lv", "latvian"),
+    ("nl", "dutch"),
+    ("no", "norwegian"),
+    ("pt", "portuguese"),
+    ("ro", "romanian"),
+    ("ru", "russian"),
+    ("sv", "swedish"),
+    ("th", "thai"),
+    ("tr", "turkish"),
+];
+
+/// Meilisearch locales (ISO 639-3), by ISO 639-1 code
+const MEILISEARCH_LOCALES: &[(&str, &str)] = &[
+    ("ar", "ara"),
+    ("bg", "bul"),
+    ("cs", "ces"),
+    ("da", "dan"),
+    ("de", "deu"),
+    ("el", "ell"),
+    ("en", "eng"),
+    ("es", "spa"),
+    ("fi", "fin"),
+    ("fr", "fra"),
+    ("he", "heb"),
+    ("hi", "hin"),
+    ("hu", "hun"),
+    ("id", "ind"),
+    ("it", "ita"),
+    ("ja", "jpn"),
+    ("ko", "kor"),
+    ("nl", "nld"),
+    ("no", "nob"),
+    ("pl", "pol"),
+    ("pt", "por"),
+    ("ro", "ron"),
+    ("ru", "rus"),
+    ("sv", "swe"),
+    ("th", "tha"),
+    ("tr", "tur"),
+    ("uk", "ukr"),
+    ("vi", "vie"),
+    ("zh", "cmn"),
+];
+
+fn lookup<'a>(table: &[(&'a str, &'a str)], key: &str) -> Option<&'a str> {
+    table.iter().find(|(k, _)| *k == key).map(|(_, v)| *v)
+}
+
+fn reverse_lookup<'a>(table: &[(&'a str, &'a str)], value: &str) -> Option<&'a str> {
+    table.iter().find(|(_, v)| *v == value).map(|(k, _)| *k)
+}
+
+fn is_lucene(provider: &str) -> bool {
+    matches!(provider, "elasticsearch" | "elastic" | "opensearch")
+}
+
+/// Per-field text analysis hints
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct FieldAnalysis {
+    /// Defaults to [`Tokenizer::Standard`]
+    #[serde(default)]
+    pub tokenizer: Option<Tokenizer>,
+
+    /// ISO 639-1 code of the field's language, e.g. `en`
+    #[serde(default)]
+    pub language: Option<String>,
+
+    /// Reduce words to their stem so `running` matches `run`; needs a language
+    #[serde(default)]
+    pub stemming: Option<bool>,
+}
+
+impl FieldAnalysis {
+    pub fn with_tokenizer(tokenizer: Tokenizer) -> Self {
+        Self { tokenizer: Some(tokenizer), ..Self::default() }
+    }
+
+    /// Stemmed analysis for `language`
+    pub fn stemmed(language: &str) -> Self {
+        Self { language: Some(language.to_lowercase()), stemming: Some(true), ..Self::default() }
+    }
+
+    pub fn tokenizer(&self) -> Tokenizer {
+        self.tokenizer.unwrap_or_default()
+    }
+
+    /// Language of the field: the explicit one, else the one a CJK tokenizer implies
+    pub fn language(&self) -> Option<&str> {
+        self.language.as_deref().or_else(|| self.tokenizer().language())
+    }
+
+    pub fn stems(&self) -> bool {
+        self.stemming.unwrap_or(false)
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.tokenizer() == Tokenizer::default() && self.language.is_none() && !self.stems()
+    }
+
+    /// Elasticsearch/OpenSearch analyzer for the field
+    ///
+    /// A non-standard tokenizer takes precedence; otherwise stemming selects
+    /// the built-in analyzer of the language.
+    pub fn lucene_analyzer(&self) -> &'static str {
+        match self.tokenizer() {
+            Tokenizer::Standard if self.stems() => {
+                self.language().and_then(|l| lookup(LUCENE_LANGUAGE_ANALYZERS, l)).unwrap_or("standard")
+            }
+            tokenizer => tokenizer.lucene_analyzer(),
+        }
+    }
+
+    pub fn from_lucene_analyzer(analyzer: &str) -> Option<Self> {
+        if let Some(tokenizer) = Tokenizer::from_lucene_analyzer(analyzer) {
+            return Some(Self::with_tokenizer(tokenizer)).filter(|a| !a.is_default());
+        }
+        reverse_lookup(LUCENE_LANGUAGE_ANALYZERS, analyzer).map(Self::stemmed)
+    }
+
+    /// Meilisearch `localizedAttributes` locale for the field
+    pub fn meilisearch_locale(&self) -> Option<&'static str> {
+        self.tokenizer()
+            .meilisearch_locale()
+            .or_else(|| self.language().and_then(|l| lookup(MEILISEARCH_LOCALES, l)))
+    }
+
+    pub fn from_meilisearch_locale(locale: &str) -> Option<Self> {
+        match Tokenizer::from_meilisearch_locale(locale) {
+            Some(tokenizer) => Some(Self::with_tokenizer(tokenizer)),
+            None => reverse_lookup(MEILISEARCH_LOCALES, locale)
+                .map(|language| Self { language: Some(language.to_string()), ..Self::default() }),
+        }
+    }
+
+    /// Typesense field `locale`; Typesense takes ISO 639-1 codes
+    pub fn typesense_locale(&self) -> Option<&str> {
+        self.language()
+    }
+
+    /// Analysis of a Typesense field from its `locale` and `stem` settings
+    pub fn from_typesense_field(locale: Option<&str>, stem: bool) -> Option<Self> {
+        let mut analysis = match locale.filter(|l| !l.is_empty()) {
+            Some(locale) => match Tokenizer::from_typesense_locale(locale) {
+                Some(tokenizer) => Self::with_tokenizer(tokenizer),
+                None => Self { language: Some(locale.to_string()), ..Self::default() },
+            },
+            None => Self::default(),
+        };
+        if stem {
+            analysis.stemming = Some(true);
+        }
+        Some(analysis).filter(|a| !a.is_default())
+    }
+
+    /// What `provider` actually stores for this analysis
+    ///
+    /// Two analyses with the same native form behave the same on that
+    /// provider, so changing one into the other needs no reindex.
+    pub fn native_form(&self, provider: &str) -> String {
+        match provider.to_lowercase().as_str() {
+            p if is_lucene(p) => self.lucene_analyzer().to_string(),
+            "typesense" => format!("{}:{}", self.typesense_locale().unwrap_or_default(), self.stems()),
+            "meilisearch" => self.meilisearch_locale().unwrap_or_default().to_string(),
+            _ => String::new(),
+        }
+    }
+}
+
+/// Analysis hints in `schema` that `provider` cannot honor
+///
+/// Meilisearch and Typesense only take a locale per field, which covers the
+/// CJK tokenizers but not a whitespace tokenizer; Meilisearch never stems.
+pub fn unsupported_analysis(provider: &str, schema: &Schema) -> Vec<String> {
+    let provider = provider.to_lowercase();
+    let mut warnings = Vec::new();
+    for field in schema.fields.iter().filter(|f| f.field_type == FieldType::Text) {
+        let Some(analysis) = field.analysis.as_ref() else { continue };
+        let name = &field.name;
+
+        if analysis.tokenizer() == Tokenizer::Whitespace && matches!(provider.as_str(), "meilisearch" | "typesense") {
+            warnings.push(format!("{} has no whitespace tokenizer; field {} uses its default segmentation", provider, name));
+        }
+        if !analysis.stems() {
+            continue;
+        }
+        match analysis.language() {
+            None => warnings.push(format!("Stemming field {} needs a language; it is left unstemmed", name)),
+            Some(_) if provider == "meilisearch" => {
+                warnings.push(format!("meilisearch does not stem; field {} is matched on whole words", name))
+            }
+            Some(_) if is_lucene(&provider) && analysis.tokenizer() != Tokenizer::Standard => warnings.push(format!(
+                "The {} tokenizer of field {} replaces the stemming analyzer",
+                analysis.tokenizer().as_str(),
+                name
+            )),
+            Some(language) if is_lucene(&provider) && lookup(LUCENE_LANGUAGE_ANALYZERS, language).is_none() => {
+                warnings.push(format!("{} has no stemming analyzer for {}; field {} is left unstemmed", provider, language, name))
+            }
+            Some(_) => {}
+        }
+    }
+    warnings
+}
+
+/// Parse a normalization passed as JSON, as the WIT interfaces carry it
+pub fn parse_normalization(json: Option<&str>) -> SearchResult<Option<Normalization>> {
+    json.filter(|j| !j.trim().is_empty())
+        .map(|j| {
+            serde_json::from_str(j).map_err(|e| SearchError::invalid_query(format!("Invalid normalization: {}", e)))
+        })
+        .transpose()
+}
+
+/// Parse field analysis hints passed as JSON
+pub fn parse_field_analysis(json: Option<&str>) -> SearchResult<Option<FieldAnalysis>> {
+    json.filter(|j| !j.trim().is_empty())
+        .map(|j| {
+            serde_json::from_str(j).map_err(|e| SearchError::invalid_query(format!("Invalid field analysis: {}", e)))
+        })
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_folds_text() {
+        let fold = Normalization { fold_diacritics: true, ..Normalization::default() };
+        assert_eq!(fold.normalize("Crème Brûlée"), "creme brulee");
+
+        let exact = Normalization { case_sensitive: true, ..Normalization::default() };
+        assert_eq!(exact.normalize("Crème"), "Crème");
+
+        let compat = Normalization { unicode_form: UnicodeForm::Nfkc, case_sensitive: true, ..Normalization::default() };
+        assert_eq!(compat.normalize("ﬁle №1"), "file No1");
+
+        // Decomposed input composes back under NFC
+        let nfc = Normalization { unicode_form: UnicodeForm::Nfc, case_sensitive: true, ..Normalization::default() };
+        assert_eq!(nfc.normalize("e\u{301}"), "\u{e9}");
+    }
+
+    #[test]
+    fn test_plan_splits_native_and_client_side() {
+        let requested = Normalization { unicode_form: UnicodeForm::Nfc, fold_diacritics: false, case_sensitive: true };
+
+        let elastic = requested.plan("elasticsearch");
+        assert!(elastic.warnings.is_empty());
+        assert_eq!(elastic.client_side.unicode_form, UnicodeForm::Nfc);
+        assert!(!elastic.client_side.fold_diacritics);
+
+        let meili = requested.plan("meilisearch");
+        assert_eq!(meili.warnings.len(), 2, "{:?}", meili.warnings);
+
+        let folded = Normalization { fold_diacritics: true, ..Normalization::default() };
+        assert!(folded.plan("typesense").warnings.is_empty());
+        assert_eq!(folded.prepare_query("typesense", "Café"), "Café", "Typesense folds natively");
+
+        let parsed = parse_normalization(Some(r#"{"unicode_form": "nfkd"}"#)).unwrap().unwrap();
+        assert_eq!(parsed.unicode_form, UnicodeForm::Nfkd);
+        assert!(parse_normalization(Some(r#"{"unicode_form": "nfx"}"#)).is_err());
+    }
+
+    #[test]
+    fn test_tokenizers_map_to_provider_options() {
+        assert_eq!(Tokenizer::Kuromoji.lucene_analyzer(), "kuromoji");
+        assert_eq!(Tokenizer::Jieba.lucene_analyzer(), "smartcn");
+        assert_eq!(Tokenizer::from_lucene_analyzer("smartcn"), Some(Tokenizer::Jieba));
+        assert_eq!(Tokenizer::Nori.meilisearch_locale(), Some("kor"));
+        assert_eq!(Tokenizer::Whitespace.typesense_locale(), None);
+        for tokenizer in [Tokenizer::Kuromoji, Tokenizer::Nori, Tokenizer::Jieba] {
+            let locale = tokenizer.typesense_locale().unwrap();
+            assert_eq!(Tokenizer::from_typesense_locale(locale), Some(tokenizer));
+            assert_eq!(Tokenizer::parse(tokenizer.as_str()), Some(tokenizer));
+        }
+
+        let schema = crate::types::SchemaBuilder::new()
+            .analyzed_text_field("title", FieldAnalysis::with_tokenizer(Tokenizer::Whitespace))
+            .analyzed_text_field("body", FieldAnalysis::with_tokenizer(Tokenizer::Kuromoji))
+            .build();
+        assert!(unsupported_analysis("elasticsearch", &schema).is_empty());
+        assert_eq!(unsupported_analysis("meilisearch", &schema).len(), 1);
+        assert!(FieldAnalysis::default().is_default());
+        assert!(FieldAnalysis::with_tokenizer(Tokenizer::Standard).is_default());
+    }
+
+    #[test]
+    fn test_stemming_maps_to_language_settings() {
+        let english = FieldAnalysis::stemmed("EN");
+        assert_eq!(english.lucene_analyzer(), "english");
+        assert_eq!(FieldAnalysis::from_lucene_analyzer("english"), Some(english.clone()));
+        assert_eq!(english.meilisearch_locale(), Some("eng"));
+        assert_eq!(FieldAnalysis::from_typesense_field(Some("en"), true), Some(english.clone()));
+
+        let unstemmed = FieldAnalysis { stemming: Some(false), ..english.clone() };
+        assert_eq!(unstemmed.lucene_analyzer(), "standard");
+        assert_eq!(unstemmed.native_form("elasticsearch"), FieldAnalysis::default().native_form("opensearch"));
+        assert_ne!(unstemmed.native_form("typesense"), english.native_form("typesense"));
+
+        let schema = crate::types::SchemaBuilder::new()
+            .analyzed_text_field("title", english)
+            .analyzed_text_field("body", FieldAnalysis { stemming: Some(true), ..FieldAnalysis::default() })
+            .analyzed_text_field("notes", FieldAnalysis::stemmed("xx"))
+            .build();
+        assert_eq!(unsupported_analysis("typesense", &schema).len(), 1, "only the field without a language");
+        assert_eq!(unsupported_analysis("elasticsearch", &schema).len(), 2);
+        assert_eq!(unsupported_analysis("meilisearch", &schema).len(), 3);
+    }
+}
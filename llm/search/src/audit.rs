@@ -0,0 +1,285 @@
+//! Audit log of administrative operations
+//!
+//! Index creation and deletion, schema and settings changes, and any other
+//! operation a provider marks as administrative are appended to an in-memory
+//! log with a timestamp, the caller and the outcome. The log is part of the
+//! worker's state, so Golem keeps it across restarts by replaying the worker;
+//! it is retrieved with `get_audit_log(since)`.
+//!
+//! Entries are never modified. Once `SEARCH_AUDIT_LOG_CAPACITY` entries are
+//! held the oldest are dropped, and the gap shows in the sequence numbers, so a
+//! consumer that ships the log elsewhere can tell whether it missed entries.
+
+use std::collections::VecDeque;
+use std::fmt::Display;
+use std::future::Future;
+use std::sync::{Mutex, OnceLock};
+use chrono::{DateTime, Utc};
+use log::info;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::config::env_helpers::get_env_parsed;
+use crate::error::{SearchError, SearchResult};
+use crate::{quota, request_id, slow_query};
+
+/// Environment variable holding the number of entries kept
+pub const AUDIT_LOG_CAPACITY_ENV: &str = "SEARCH_AUDIT_LOG_CAPACITY";
+
+/// Default number of entries kept
+pub const DEFAULT_AUDIT_LOG_CAPACITY: usize = 1_000;
+
+/// Environment variable Golem sets to the worker name
+const WORKER_NAME_ENV: &str = "GOLEM_WORKER_NAME";
+
+/// One administrative operation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// Position in the log, increasing by one per entry
+    pub sequence: u64,
+
+    /// Time the operation finished (RFC 3339)
+    pub timestamp: String,
+
+    pub provider: String,
+    pub operation: String,
+    pub index: String,
+
+    /// Tenant the operation ran for, when set through `quota::tenant_scope`
+    pub caller: Option<String>,
+
+    /// Worker that ran the operation
+    pub worker: Option<String>,
+
+    pub request_id: Option<String>,
+
+    /// Whether the operation succeeded
+    pub success: bool,
+
+    /// Error message of a failed operation
+    pub error: Option<String>,
+
+    /// Operation arguments, with credential-like values redacted
+    pub details: Value,
+}
+
+/// Bounded append-only log
+#[derive(Debug)]
+pub struct AuditLog {
+    capacity: usize,
+    next_sequence: u64,
+    entries: VecDeque<AuditEntry>,
+}
+
+impl AuditLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            next_sequence: 1,
+            entries: VecDeque::new(),
+        }
+    }
+
+    /// Create a log sized from `SEARCH_AUDIT_LOG_CAPACITY`
+    pub fn from_env() -> Self {
+        let capacity = get_env_parsed::<usize>(AUDIT_LOG_CAPACITY_ENV)
+            .ok()
+            .flatten()
+            .unwrap_or(DEFAULT_AUDIT_LOG_CAPACITY);
+        Self::new(capacity)
+    }
+
+    /// Append an entry, assigning its sequence number
+    pub fn append(&mut self, mut entry: AuditEntry) -> u64 {
+        entry.sequence = self.next_sequence;
+        self.next_sequence += 1;
+
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        let sequence = entry.sequence;
+        self.entries.push_back(entry);
+        sequence
+    }
+
+    /// Entries recorded at or after `since`, oldest first
+    pub fn since(&self, since: Option<DateTime<Utc>>) -> Vec<AuditEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| match since {
+                Some(since) => DateTime::parse_from_rfc3339(&entry.timestamp).is_ok_and(|t| t >= since),
+                None => true,
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+fn global_log() -> &'static Mutex<AuditLog> {
+    static LOG: OnceLock<Mutex<AuditLog>> = OnceLock::new();
+    LOG.get_or_init(|| Mutex::new(AuditLog::from_env()))
+}
+
+/// Record a finished administrative operation in the worker's audit log
+pub fn record<T, E: Display>(provider: &str, operation: &str, index: &str, details: Value, result: &Result<T, E>) {
+    let entry = AuditEntry {
+        sequence: 0,
+        timestamp: Utc::now().to_rfc3339(),
+        provider: provider.to_string(),
+        operation: operation.to_string(),
+        index: index.to_string(),
+        caller: quota::current_tenant(),
+        worker: std::env::var(WORKER_NAME_ENV).ok(),
+        request_id: request_id::current(),
+        success: result.is_ok(),
+        error: result.as_ref().err().map(|e| e.to_string()),
+        details: slow_query::sanitize_params(&details),
+    };
+
+    info!(
+        "Audit: {} {} on index {} {}",
+        entry.provider,
+        entry.operation,
+        entry.index,
+        if entry.success { "succeeded" } else { "failed" }
+    );
+    if let Ok(mut log) = global_log().lock() {
+        log.append(entry);
+    }
+}
+
+/// Run an administrative operation and record it, whatever its outcome
+pub async fn audited<T, E, Fut>(provider: &str, operation: &str, index: &str, details: Value, future: Fut) -> Result<T, E>
+where
+    E: Display,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let _scope = request_id::begin();
+    let result = future.await;
+    record(provider, operation, index, details, &result);
+    result
+}
+
+/// Audit entries recorded at or after `since` (RFC 3339), oldest first
+pub fn get_audit_log(since: Option<&str>) -> SearchResult<Vec<AuditEntry>> {
+    let since = since
+        .map(|s| {
+            DateTime::parse_from_rfc3339(s)
+                .map(|t| t.with_timezone(&Utc))
+                .map_err(|e| SearchError::invalid_query(format!("Invalid audit log timestamp {}: {}", s, e)))
+        })
+        .transpose()?;
+
+    Ok(global_log().lock().map(|log| log.since(since)).unwrap_or_default())
+}
+
+/// Settings snapshot as audit details, keeping unparsable input as a string
+pub fn settings_details(settings_json: &str) -> Value {
+    serde_json::from_str(settings_json).unwrap_or_else(|_| Value::String(settings_json.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn entry(timestamp: &str, operation: &str) -> AuditEntry {
+        AuditEntry {
+            sequence: 0,
+            timestamp: timestamp.to_string(),
+            provider: "typesense".to_string(),
+            operation: operation.to_string(),
+            index: "books".to_string(),
+            caller: None,
+            worker: None,
+            request_id: None,
+            success: true,
+            error: None,
+            details: Value::Null,
+        }
+    }
+
+    fn at(timestamp: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(timestamp).unwrap().with_timezone(&Utc)
+    }
+
+    fn sequences(entries: &[AuditEntry]) -> Vec<u64> {
+        entries.iter().map(|e| e.sequence).collect()
+    }
+
+    #[test]
+    fn test_dropped_entries_leave_a_gap_in_the_sequence() {
+        let mut log = AuditLog::new(2);
+        for (hour, operation) in ["create_index", "update_schema", "delete_index"].iter().enumerate() {
+            log.append(entry(&format!("2025-01-01T1{}:00:00Z", hour), operation));
+        }
+        assert_eq!(sequences(&log.since(None)), [2, 3]);
+        assert_eq!(log.append(entry("2025-01-01T13:00:00Z", "create_index")), 4);
+        assert_eq!(sequences(&log.since(None)), [3, 4]);
+    }
+
+    #[test]
+    fn test_zero_capacity_still_keeps_the_latest_entry() {
+        let mut log = AuditLog::new(0);
+        log.append(entry("2025-01-01T10:00:00Z", "create_index"));
+        log.append(entry("2025-01-01T11:00:00Z", "delete_index"));
+        assert_eq!(sequences(&log.since(None)), [2]);
+    }
+
+    #[test]
+    fn test_since_is_inclusive_and_compares_instants_across_offsets() {
+        let mut log = AuditLog::new(10);
+        log.append(entry("2025-01-01T10:00:00Z", "create_index"));
+        log.append(entry("2025-01-01T12:30:00+02:00", "update_schema"));
+        log.append(entry("2025-01-01T11:00:00Z", "delete_index"));
+
+        assert_eq!(sequences(&log.since(Some(at("2025-01-01T11:00:00Z")))), [3]);
+        assert_eq!(sequences(&log.since(Some(at("2025-01-01T10:30:00Z")))), [2, 3]);
+    }
+
+    #[test]
+    fn test_entries_with_unreadable_timestamps_only_show_without_since() {
+        let mut log = AuditLog::new(10);
+        log.append(entry("yesterday", "create_index"));
+        assert_eq!(sequences(&log.since(None)), [1]);
+        assert!(log.since(Some(at("2000-01-01T00:00:00Z"))).is_empty());
+    }
+
+    #[test]
+    fn test_settings_details_keep_unparsable_input_as_text() {
+        assert_eq!(settings_details(r#"{"synonyms":[]}"#), json!({ "synonyms": [] }));
+        assert_eq!(settings_details("{broken"), json!("{broken"));
+    }
+
+    #[test]
+    fn test_audited_records_both_outcomes_with_redacted_details() {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let _tenant = quota::tenant_scope("acme");
+        let details = json!({"settings": {"api_key": "secret", "synonyms": ["a,b"]}});
+
+        let result = runtime.block_on(audited("elasticsearch", "apply_settings", "audit-test", details, async {
+            Err::<(), _>(SearchError::IndexNotFound("audit-test".to_string()))
+        }));
+        assert!(result.is_err());
+        runtime
+            .block_on(audited("elasticsearch", "delete_index", "audit-test", Value::Null, async { Ok::<_, SearchError>(()) }))
+            .unwrap();
+
+        let entries: Vec<_> = get_audit_log(None).unwrap().into_iter().filter(|e| e.index == "audit-test").collect();
+        let [failed, succeeded] = entries.as_slice() else { panic!("expected two entries: {:?}", entries) };
+        assert!(!failed.success);
+        assert!(failed.error.as_deref().unwrap().contains("audit-test"));
+        assert_eq!(failed.caller.as_deref(), Some("acme"));
+        assert!(failed.request_id.is_some());
+        assert_eq!(failed.details["settings"]["api_key"], "[redacted]");
+        assert_eq!(failed.details["settings"]["synonyms"], json!(["a,b"]));
+        assert!(succeeded.success && succeeded.error.is_none());
+        assert!(succeeded.sequence > failed.sequence);
+        assert_ne!(succeeded.request_id, failed.request_id);
+    }
+
+    #[test]
+    fn test_invalid_since_is_rejected() {
+        assert!(matches!(get_audit_log(Some("yesterday")), Err(SearchError::InvalidQuery(_))));
+        assert!(get_audit_log(Some("2000-01-01T00:00:00+01:00")).is_ok());
+    }
+}
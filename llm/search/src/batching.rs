@@ -0,0 +1,395 @@
+//! Adaptive batch sizing for bulk imports
+//!
+//! A fixed `max_batch_size` is either too small for fast clusters or too large
+//! for busy ones. [`AdaptiveBatcher`] starts in the middle of the allowed range
+//! and adjusts the chunk size after every submission: it grows while batches
+//! complete well under the target latency, and halves on slow batches,
+//! `413 Payload Too Large` responses and provider backpressure. Rejected
+//! batches are resubmitted with the smaller size. The sizes actually used are
+//! reported in [`BatchMetrics`].
+//!
+//! Chunks are submitted one after another: requests block the calling thread
+//! until the provider answers, so a component has nothing to overlap them
+//! with. Failures are aggregated in item order rather than aborting the import
+//! at the first bad chunk.
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::ops::Range;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use log::{debug, info, warn};
+use crate::error::SearchError;
+
+/// Default target latency for a single batch submission
+pub const DEFAULT_TARGET_LATENCY: Duration = Duration::from_secs(1);
+
+/// Give up after this many consecutive rejected submissions
+pub const MAX_CONSECUTIVE_REJECTIONS: u32 = 5;
+
+/// Base delay before resubmitting after backpressure
+const BACKPRESSURE_DELAY: Duration = Duration::from_millis(100);
+
+/// Bounds and target for adaptive batching
+#[derive(Debug, Clone)]
+pub struct AdaptiveBatchConfig {
+    /// Smallest chunk size the batcher will shrink to
+    pub min_batch_size: usize,
+
+    /// Largest chunk size the batcher will grow to
+    pub max_batch_size: usize,
+
+    /// Chunk size used for the first submission
+    pub initial_batch_size: usize,
+
+    /// Batches faster than half of this grow, slower ones shrink
+    pub target_latency: Duration,
+}
+
+impl AdaptiveBatchConfig {
+    /// Derive a configuration from a provider's `max_batch_size` capability
+    pub fn with_max(max_batch_size: usize) -> Self {
+        let max_batch_size = max_batch_size.max(1);
+        let min_batch_size = max_batch_size.min(10);
+        Self {
+            min_batch_size,
+            max_batch_size,
+            initial_batch_size: (max_batch_size / 2).max(min_batch_size),
+            target_latency: DEFAULT_TARGET_LATENCY,
+        }
+    }
+}
+
+/// Outcome of a single batch submission, as seen by the batcher
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatchOutcome {
+    /// The batch was accepted after the given latency
+    Completed(Duration),
+    /// The provider rejected the request body as too large (HTTP 413)
+    PayloadTooLarge,
+    /// The provider is overloaded (HTTP 429, rejected execution, queue full)
+    Backpressure,
+    /// Any other failure; not retried
+    Failed,
+}
+
+impl BatchOutcome {
+    /// Classify a provider error message
+    pub fn classify_error(message: &str) -> Self {
+        let lower = message.to_lowercase();
+        if lower.contains("413") || lower.contains("payload_too_large") || lower.contains("too large") {
+            Self::PayloadTooLarge
+        } else if lower.contains("429")
+            || lower.contains("rate limit")
+            || lower.contains("rejected_execution")
+            || lower.contains("too many requests")
+        {
+            Self::Backpressure
+        } else {
+            Self::Failed
+        }
+    }
+}
+
+/// Batch sizes chosen during an import, for metrics and logging
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BatchMetrics {
+    /// Size of every accepted batch, in submission order
+    pub batch_sizes: Vec<usize>,
+
+    /// Number of items successfully submitted
+    pub items_submitted: usize,
+
+    /// Submissions rejected as too large
+    pub payload_too_large: u32,
+
+    /// Submissions rejected because of backpressure
+    pub backpressure: u32,
+
+    /// Total time spent submitting batches, in milliseconds
+    pub total_ms: u64,
+}
+
+/// Tracks and adjusts the chunk size used for bulk submissions
+#[derive(Debug, Clone)]
+pub struct AdaptiveBatcher {
+    config: AdaptiveBatchConfig,
+    current: usize,
+}
+
+impl AdaptiveBatcher {
+    /// Create a batcher starting at `initial_batch_size`
+    pub fn new(config: AdaptiveBatchConfig) -> Self {
+        let current = config
+            .initial_batch_size
+            .clamp(config.min_batch_size, config.max_batch_size.max(config.min_batch_size));
+        Self { config, current }
+    }
+
+    /// Chunk size to use for the next submission
+    pub fn current_size(&self) -> usize {
+        self.current
+    }
+
+    /// Adjust the chunk size after a submission
+    pub fn observe(&mut self, outcome: BatchOutcome) {
+        let previous = self.current;
+        self.current = match outcome {
+            BatchOutcome::Completed(latency) if latency * 2 < self.config.target_latency => {
+                (self.current + self.current.div_ceil(4)).min(self.config.max_batch_size)
+            }
+            BatchOutcome::Completed(latency) if latency > self.config.target_latency => {
+                (self.current / 2).max(self.config.min_batch_size)
+            }
+            BatchOutcome::Completed(_) | BatchOutcome::Failed => self.current,
+            BatchOutcome::PayloadTooLarge | BatchOutcome::Backpressure => {
+                (self.current / 2).max(self.config.min_batch_size)
+            }
+        };
+
+        if self.current != previous {
+            debug!("Adjusted batch size from {} to {} after {:?}", previous, self.current, outcome);
+        }
+    }
+}
+
+/// A chunk that could not be submitted
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkFailure {
+    /// Position of the first item of the chunk in the submitted items
+    pub offset: usize,
+
+    /// Number of items in the chunk
+    pub len: usize,
+
+    /// Provider error message
+    pub error: String,
+}
+
+/// Chunks that failed during a bulk submission, ordered by offset
+#[derive(Debug, Clone, Error)]
+#[error("{} of {} items failed in {} chunks: {}", failed_items(.failures), .total_items, .failures.len(), summarize(.failures))]
+pub struct BulkError {
+    /// Failed chunks in item order
+    pub failures: Vec<ChunkFailure>,
+
+    /// Total number of items in the submission
+    pub total_items: usize,
+
+    /// Metrics for the chunks that were accepted
+    pub metrics: BatchMetrics,
+}
+
+fn failed_items(failures: &[ChunkFailure]) -> usize {
+    failures.iter().map(|f| f.len).sum()
+}
+
+fn summarize(failures: &[ChunkFailure]) -> String {
+    failures
+        .iter()
+        .map(|f| format!("[{}..{}] {}", f.offset, f.offset + f.len, f.error))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+impl From<BulkError> for SearchError {
+    fn from(err: BulkError) -> Self {
+        SearchError::Internal(err.to_string())
+    }
+}
+
+/// Take the next chunk range, preferring ranges queued for resubmission
+fn next_range(retry: &mut VecDeque<Range<usize>>, offset: &mut usize, len: usize, size: usize) -> Option<Range<usize>> {
+    if let Some(range) = retry.pop_front() {
+        if range.len() > size {
+            retry.push_front(range.start + size..range.end);
+            return Some(range.start..range.start + size);
+        }
+        return Some(range);
+    }
+
+    if *offset < len {
+        let range = *offset..(*offset + size).min(len);
+        *offset = range.end;
+        return Some(range);
+    }
+
+    None
+}
+
+/// Submit `items` in adaptively sized chunks, one chunk at a time
+///
+/// Chunks rejected as too large or because of backpressure are resubmitted
+/// with a smaller size. Other errors do not stop the remaining chunks; they
+/// are collected in item order and returned together as a [`BulkError`]. More
+/// than [`MAX_CONSECUTIVE_REJECTIONS`] rejections in a row also fail the
+/// chunk that was rejected.
+pub async fn submit_adaptive<T, E, F, Fut>(
+    items: Vec<T>,
+    batcher: &mut AdaptiveBatcher,
+    mut submit: F,
+) -> Result<BatchMetrics, BulkError>
+where
+    T: Clone,
+    E: std::fmt::Display,
+    F: FnMut(Vec<T>) -> Fut,
+    Fut: Future<Output = Result<(), E>>,
+{
+    let mut metrics = BatchMetrics::default();
+    let mut failures = Vec::new();
+    let mut retry = VecDeque::new();
+    let mut offset = 0;
+    let mut rejections = 0;
+    let started = Instant::now();
+
+    while let Some(range) = next_range(&mut retry, &mut offset, items.len(), batcher.current_size()) {
+        let submitted = Instant::now();
+        let result = submit(items[range.clone()].to_vec()).await;
+        let latency = submitted.elapsed();
+
+        let Err(e) = result else {
+            metrics.batch_sizes.push(range.len());
+            metrics.items_submitted += range.len();
+            rejections = 0;
+            batcher.observe(BatchOutcome::Completed(latency));
+            continue;
+        };
+
+        let outcome = BatchOutcome::classify_error(&e.to_string());
+        let retryable = match outcome {
+            BatchOutcome::PayloadTooLarge => range.len() > 1,
+            BatchOutcome::Backpressure => true,
+            _ => false,
+        };
+
+        if retryable && rejections < MAX_CONSECUTIVE_REJECTIONS {
+            warn!("Batch of {} items rejected ({:?}), resubmitting with a smaller batch: {}", range.len(), outcome, e);
+            match outcome {
+                BatchOutcome::PayloadTooLarge => metrics.payload_too_large += 1,
+                _ => metrics.backpressure += 1,
+            }
+            retry.push_front(range);
+            rejections += 1;
+            batcher.observe(outcome);
+            if outcome == BatchOutcome::Backpressure {
+                std::thread::sleep(BACKPRESSURE_DELAY * 2u32.pow(rejections - 1));
+            }
+        } else {
+            failures.push(ChunkFailure {
+                offset: range.start,
+                len: range.len(),
+                error: e.to_string(),
+            });
+            rejections = 0;
+        }
+    }
+
+    metrics.total_ms = started.elapsed().as_millis() as u64;
+    info!(
+        "Submitted {} items in {} batches (sizes: {:?}, 413s: {}, backpressure: {})",
+        metrics.items_submitted,
+        metrics.batch_sizes.len(),
+        metrics.batch_sizes,
+        metrics.payload_too_large,
+        metrics.backpressure
+    );
+
+    if failures.is_empty() {
+        Ok(metrics)
+    } else {
+        failures.sort_by_key(|f| f.offset);
+        Err(BulkError {
+            failures,
+            total_items: items.len(),
+            metrics,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> AdaptiveBatchConfig {
+        AdaptiveBatchConfig {
+            min_batch_size: 10,
+            max_batch_size: 1000,
+            initial_batch_size: 100,
+            target_latency: Duration::from_secs(1),
+        }
+    }
+
+    #[test]
+    fn test_batcher_grows_and_shrinks_within_bounds() {
+        let mut batcher = AdaptiveBatcher::new(config());
+
+        batcher.observe(BatchOutcome::Completed(Duration::from_millis(100)));
+        assert_eq!(batcher.current_size(), 125);
+
+        batcher.observe(BatchOutcome::Completed(Duration::from_millis(700)));
+        assert_eq!(batcher.current_size(), 125);
+
+        batcher.observe(BatchOutcome::PayloadTooLarge);
+        assert_eq!(batcher.current_size(), 62);
+
+        for _ in 0..10 {
+            batcher.observe(BatchOutcome::Completed(Duration::from_secs(5)));
+        }
+        assert_eq!(batcher.current_size(), 10);
+
+        assert_eq!(BatchOutcome::classify_error("Bulk operation failed (413 Payload Too Large)"), BatchOutcome::PayloadTooLarge);
+        assert_eq!(BatchOutcome::classify_error("es_rejected_execution_exception"), BatchOutcome::Backpressure);
+        assert_eq!(BatchOutcome::classify_error("mapper_parsing_exception"), BatchOutcome::Failed);
+    }
+
+    #[test]
+    fn test_submit_adaptive_resubmits_oversized_batches() {
+        let items: Vec<u32> = (0..250).collect();
+        let mut batcher = AdaptiveBatcher::new(config());
+        let mut received = Vec::new();
+
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let metrics = runtime
+            .block_on(submit_adaptive(items, &mut batcher, |chunk: Vec<u32>| {
+                let result = if chunk.len() > 60 {
+                    Err("HTTP 413 Payload Too Large".to_string())
+                } else {
+                    received.extend(chunk);
+                    Ok(())
+                };
+                async move { result }
+            }))
+            .unwrap();
+
+        assert_eq!(received, (0..250).collect::<Vec<u32>>());
+        assert_eq!(metrics.items_submitted, 250);
+        assert!(metrics.payload_too_large >= 1);
+        assert!(metrics.batch_sizes.iter().all(|&s| s <= 60));
+    }
+
+    #[test]
+    fn test_submit_adaptive_aggregates_failures_in_order() {
+        let items: Vec<u32> = (0..100).collect();
+        let mut batcher = AdaptiveBatcher::new(AdaptiveBatchConfig {
+            initial_batch_size: 10,
+            ..config()
+        });
+
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let err = runtime
+            .block_on(submit_adaptive(items, &mut batcher, |chunk: Vec<u32>| async move {
+                if chunk.contains(&75) || chunk.contains(&15) {
+                    Err("mapper_parsing_exception".to_string())
+                } else {
+                    Ok(())
+                }
+            }))
+            .unwrap_err();
+
+        let offsets: Vec<usize> = err.failures.iter().map(|f| f.offset).collect();
+        assert_eq!(offsets.len(), 2);
+        assert!(offsets[0] <= 15 && offsets[1] <= 75 && offsets[0] < offsets[1]);
+        assert_eq!(err.metrics.items_submitted + err.failures.iter().map(|f| f.len).sum::<usize>(), 100);
+    }
+}
@@ -0,0 +1,834 @@
+//! Memory-budgeted cache shared by provider components
+//!
+//! Durable Golem workers live for a long time, so anything a provider keeps
+//! around between invocations (clients, index schemas, search results) has to
+//! be bounded. [`MemoryAccountant`] stores such entries together with an
+//! estimate of their size and evicts the least recently used ones whenever the
+//! total exceeds `SEARCH_CACHE_BUDGET_BYTES`.
+//!
+//! Search results can additionally be cached with stale-while-revalidate
+//! semantics through [`with_result_cache`]. Each index has a [`CachePolicy`]:
+//! results younger than `ttl` are served as-is, results within the following
+//! `stale_while_revalidate` window are served immediately and queued for
+//! refresh. Components can't run work after returning, so the refresh happens
+//! at the start of the next search invocation rather than in the background.
+//!
+//! Result keys hold the normalized query (see [`CacheQuery`]), so requests
+//! differing only in whitespace or in the order of their filters share an
+//! entry. Writes made through a provider drop the cached results of the index
+//! they change with [`invalidate_results`]; writes made by other clients show
+//! once the TTL passes.
+
+use std::any::Any;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt::{Debug, Display};
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use log::{debug, warn};
+use crate::config::env_helpers::get_env_parsed;
+use crate::config::ConfigProfile;
+use crate::metrics;
+use crate::types::SearchQuery;
+
+/// Environment variable holding the cache budget in bytes
+pub const CACHE_BUDGET_ENV: &str = "SEARCH_CACHE_BUDGET_BYTES";
+
+/// Default cache budget (32 MiB)
+pub const DEFAULT_CACHE_BUDGET_BYTES: usize = 32 * 1024 * 1024;
+
+/// Environment variable holding the default result TTL in milliseconds
+pub const RESULT_CACHE_TTL_ENV: &str = "SEARCH_RESULT_CACHE_TTL_MS";
+
+/// Environment variable holding the default stale-while-revalidate window in milliseconds
+pub const RESULT_CACHE_SWR_ENV: &str = "SEARCH_RESULT_CACHE_SWR_MS";
+
+/// Environment variable holding per-index policies as `index:ttl_ms:swr_ms,...`
+pub const RESULT_CACHE_POLICIES_ENV: &str = "SEARCH_RESULT_CACHE_POLICIES";
+
+/// Bookkeeping cost charged per entry on top of its estimated size
+const ENTRY_OVERHEAD: usize = 64;
+
+/// Category of a cached entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheKind {
+    Client,
+    Schema,
+    Result,
+    CompiledQuery,
+}
+
+type CacheKey = (CacheKind, String);
+
+struct CacheEntry {
+    value: Arc<dyn Any + Send + Sync>,
+    size: usize,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct CacheState {
+    used: usize,
+    tick: u64,
+    evictions: u64,
+    entries: HashMap<CacheKey, CacheEntry>,
+    /// Entries ordered by last use, oldest first
+    lru: BTreeMap<u64, CacheKey>,
+    /// Stale results waiting to be refreshed, oldest first
+    revalidate: VecDeque<String>,
+}
+
+impl CacheState {
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    fn remove(&mut self, key: &CacheKey) -> Option<CacheEntry> {
+        let entry = self.entries.remove(key)?;
+        self.lru.remove(&entry.last_used);
+        self.used -= entry.size;
+        Some(entry)
+    }
+
+    fn evict_to(&mut self, budget: usize) {
+        while self.used > budget {
+            let Some((_, key)) = self.lru.pop_first() else {
+                break;
+            };
+            if let Some(entry) = self.entries.remove(&key) {
+                self.used -= entry.size;
+                self.evictions += 1;
+                debug!("Evicted cached {:?} entry {} ({} bytes)", key.0, key.1, entry.size);
+            }
+        }
+    }
+}
+
+/// Snapshot of cache memory usage
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemoryUsage {
+    /// Configured budget in bytes
+    pub budget_bytes: usize,
+
+    /// Estimated bytes currently held
+    pub used_bytes: usize,
+
+    /// Number of cached entries
+    pub entries: usize,
+
+    /// Entries evicted to stay within the budget since startup
+    pub evictions: u64,
+
+    /// Estimated bytes held by cached clients
+    pub client_bytes: usize,
+
+    /// Estimated bytes held by cached schemas
+    pub schema_bytes: usize,
+
+    /// Estimated bytes held by cached results
+    pub result_bytes: usize,
+
+    /// Estimated bytes held by compiled query templates
+    pub compiled_query_bytes: usize,
+}
+
+/// LRU cache that keeps the estimated size of its entries under a budget
+pub struct MemoryAccountant {
+    budget: usize,
+    state: Mutex<CacheState>,
+}
+
+impl MemoryAccountant {
+    /// Create an accountant with a budget in bytes
+    pub fn new(budget: usize) -> Self {
+        Self {
+            budget,
+            state: Mutex::new(CacheState::default()),
+        }
+    }
+
+    /// Create an accountant with the budget from `SEARCH_CACHE_BUDGET_BYTES`
+    pub fn from_env() -> Self {
+        let budget = get_env_parsed::<usize>(CACHE_BUDGET_ENV)
+            .ok()
+            .flatten()
+            .unwrap_or(DEFAULT_CACHE_BUDGET_BYTES);
+        Self::new(budget)
+    }
+
+    /// Look up an entry, marking it as recently used
+    ///
+    /// Returns `None` when the key is missing or holds a value of another type.
+    pub fn get<V: Any + Send + Sync>(&self, kind: CacheKind, key: &str) -> Option<Arc<V>> {
+        let mut guard = self.state.lock().unwrap();
+        let state = &mut *guard;
+        let tick = state.next_tick();
+        let cache_key = (kind, key.to_string());
+
+        let Some(entry) = state.entries.get_mut(&cache_key) else {
+            metrics::record_cache_lookup(kind, false);
+            return None;
+        };
+        let value = entry.value.clone().downcast::<V>().ok()?;
+        metrics::record_cache_lookup(kind, true);
+        let previous = std::mem::replace(&mut entry.last_used, tick);
+        state.lru.remove(&previous);
+        state.lru.insert(tick, cache_key);
+        Some(value)
+    }
+
+    /// Store an entry whose estimated size is `size` bytes
+    ///
+    /// Least recently used entries are evicted until the new entry fits.
+    /// Entries larger than the whole budget are not cached; `false` is
+    /// returned in that case.
+    pub fn insert<V: Any + Send + Sync>(&self, kind: CacheKind, key: &str, value: V, size: usize) -> bool {
+        let size = size + key.len() + ENTRY_OVERHEAD;
+        let cache_key = (kind, key.to_string());
+        let mut state = self.state.lock().unwrap();
+        state.remove(&cache_key);
+
+        if size > self.budget {
+            debug!("Not caching {:?} entry {}: {} bytes exceeds budget of {}", kind, key, size, self.budget);
+            return false;
+        }
+
+        state.evict_to(self.budget - size);
+        let tick = state.next_tick();
+        state.used += size;
+        state.lru.insert(tick, cache_key.clone());
+        state.entries.insert(
+            cache_key,
+            CacheEntry {
+                value: Arc::new(value),
+                size,
+                last_used: tick,
+            },
+        );
+        true
+    }
+
+    /// Remove an entry, returning whether it was present
+    pub fn remove(&self, kind: CacheKind, key: &str) -> bool {
+        let mut state = self.state.lock().unwrap();
+        state.remove(&(kind, key.to_string())).is_some()
+    }
+
+    /// Remove every entry of a kind whose key starts with `prefix`
+    pub fn remove_prefix(&self, kind: CacheKind, prefix: &str) -> usize {
+        let mut state = self.state.lock().unwrap();
+        let keys: Vec<CacheKey> = state
+            .entries
+            .keys()
+            .filter(|(k, key)| *k == kind && key.starts_with(prefix))
+            .cloned()
+            .collect();
+        for key in &keys {
+            state.remove(key);
+        }
+        keys.len()
+    }
+
+    /// Drop all entries
+    pub fn clear(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.entries.clear();
+        state.lru.clear();
+        state.revalidate.clear();
+        state.used = 0;
+    }
+
+    /// Look up a cached search result and classify it against `policy`
+    ///
+    /// Stale results are queued for revalidation the first time they are
+    /// served. Results past the stale window are dropped.
+    pub fn lookup_result<Q, V>(&self, key: &str, policy: &CachePolicy) -> Lookup<CachedResult<Q, V>>
+    where
+        Q: Send + Sync + 'static,
+        V: Send + Sync + 'static,
+    {
+        let Some(entry) = self.get::<CachedResult<Q, V>>(CacheKind::Result, key) else {
+            return Lookup::Miss;
+        };
+
+        let age = entry.stored_at.elapsed();
+        if age <= policy.ttl {
+            return Lookup::Fresh(entry);
+        }
+
+        if age <= policy.ttl + policy.stale_while_revalidate {
+            if !entry.revalidating.swap(true, Ordering::SeqCst) {
+                self.state.lock().unwrap().revalidate.push_back(key.to_string());
+            }
+            return Lookup::Stale(entry);
+        }
+
+        self.remove(CacheKind::Result, key);
+        Lookup::Miss
+    }
+
+    /// Cache a search result, replacing any previous entry for `key`
+    pub fn store_result<Q, V>(&self, key: &str, index: &str, query: Q, value: V, size: usize) -> bool
+    where
+        Q: Send + Sync + 'static,
+        V: Send + Sync + 'static,
+    {
+        let entry = CachedResult {
+            index: index.to_string(),
+            query,
+            value,
+            stored_at: Instant::now(),
+            revalidating: AtomicBool::new(false),
+        };
+        self.insert(CacheKind::Result, key, entry, size)
+    }
+
+    /// Take the oldest queued stale result whose key starts with `prefix`
+    ///
+    /// Entries that were evicted or replaced since being queued are skipped.
+    pub fn take_revalidation<Q, V>(&self, prefix: &str) -> Option<(String, Arc<CachedResult<Q, V>>)>
+    where
+        Q: Send + Sync + 'static,
+        V: Send + Sync + 'static,
+    {
+        loop {
+            let key = {
+                let mut state = self.state.lock().unwrap();
+                let position = state.revalidate.iter().position(|key| key.starts_with(prefix))?;
+                state.revalidate.remove(position)?
+            };
+
+            if let Some(entry) = self.get::<CachedResult<Q, V>>(CacheKind::Result, &key) {
+                if entry.revalidating.load(Ordering::SeqCst) {
+                    return Some((key, entry));
+                }
+            }
+        }
+    }
+
+    /// Current memory usage
+    pub fn usage(&self) -> MemoryUsage {
+        let state = self.state.lock().unwrap();
+        let mut usage = MemoryUsage {
+            budget_bytes: self.budget,
+            used_bytes: state.used,
+            entries: state.entries.len(),
+            evictions: state.evictions,
+            ..MemoryUsage::default()
+        };
+        for ((kind, _), entry) in &state.entries {
+            match kind {
+                CacheKind::Client => usage.client_bytes += entry.size,
+                CacheKind::Schema => usage.schema_bytes += entry.size,
+                CacheKind::Result => usage.result_bytes += entry.size,
+                CacheKind::CompiledQuery => usage.compiled_query_bytes += entry.size,
+            }
+        }
+        usage
+    }
+}
+
+/// Process-wide cache shared by all providers in a worker
+pub fn global() -> &'static MemoryAccountant {
+    static CACHE: OnceLock<MemoryAccountant> = OnceLock::new();
+    CACHE.get_or_init(MemoryAccountant::from_env)
+}
+
+/// Cache key for per-index entries of a provider
+pub fn index_key(provider: &str, index: &str) -> String {
+    format!("{}/{}", provider, index)
+}
+
+/// Cache behaviour for the search results of an index
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CachePolicy {
+    /// How long a result is served without revalidation
+    pub ttl: Duration,
+
+    /// How long after `ttl` a stale result may still be served while it is refreshed
+    pub stale_while_revalidate: Duration,
+}
+
+impl CachePolicy {
+    /// Policy with the given TTL and stale window
+    pub fn new(ttl: Duration, stale_while_revalidate: Duration) -> Self {
+        Self { ttl, stale_while_revalidate }
+    }
+
+    /// Whether results are cached at all
+    pub fn is_enabled(&self) -> bool {
+        !self.ttl.is_zero() || !self.stale_while_revalidate.is_zero()
+    }
+}
+
+/// Default and per-index result cache policies
+#[derive(Debug, Clone, Default)]
+pub struct CachePolicies {
+    pub default: CachePolicy,
+    pub per_index: HashMap<String, CachePolicy>,
+}
+
+impl CachePolicies {
+    /// Read policies from `SEARCH_RESULT_CACHE_TTL_MS`, `SEARCH_RESULT_CACHE_SWR_MS`
+    /// and `SEARCH_RESULT_CACHE_POLICIES`; caching is disabled by default
+    ///
+    /// The cache fields of a selected configuration profile take precedence
+    /// over the first two.
+    pub fn from_env() -> Self {
+        let profile = ConfigProfile::selected();
+        let millis = |key: &str, field: &str| {
+            let from_profile = profile
+                .as_deref()
+                .and_then(|name| get_env_parsed::<u64>(&ConfigProfile::var_name(name, field)).ok().flatten());
+            Duration::from_millis(from_profile.or_else(|| get_env_parsed::<u64>(key).ok().flatten()).unwrap_or(0))
+        };
+        let per_index = std::env::var(RESULT_CACHE_POLICIES_ENV)
+            .map(|spec| Self::parse_per_index(&spec))
+            .unwrap_or_default();
+
+        Self {
+            default: CachePolicy::new(millis(RESULT_CACHE_TTL_ENV, "CACHE_TTL_MS"), millis(RESULT_CACHE_SWR_ENV, "CACHE_SWR_MS")),
+            per_index,
+        }
+    }
+
+    /// Parse `index:ttl_ms:swr_ms` entries separated by commas, skipping malformed ones
+    pub fn parse_per_index(spec: &str) -> HashMap<String, CachePolicy> {
+        let mut policies = HashMap::new();
+        for item in spec.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+            let mut parts = item.rsplitn(3, ':');
+            let (Some(swr), Some(ttl), Some(index)) = (parts.next(), parts.next(), parts.next()) else {
+                warn!("Ignoring malformed result cache policy: {}", item);
+                continue;
+            };
+            match (ttl.trim().parse::<u64>(), swr.trim().parse::<u64>()) {
+                (Ok(ttl), Ok(swr)) => {
+                    policies.insert(
+                        index.trim().to_string(),
+                        CachePolicy::new(Duration::from_millis(ttl), Duration::from_millis(swr)),
+                    );
+                }
+                _ => warn!("Ignoring malformed result cache policy: {}", item),
+            }
+        }
+        policies
+    }
+
+    /// Policy for an index, falling back to the default
+    pub fn for_index(&self, index: &str) -> CachePolicy {
+        self.per_index.get(index).copied().unwrap_or(self.default)
+    }
+}
+
+/// A cached search result together with the request that produced it
+pub struct CachedResult<Q, V> {
+    pub index: String,
+    pub query: Q,
+    pub value: V,
+    stored_at: Instant,
+    revalidating: AtomicBool,
+}
+
+impl<Q, V> CachedResult<Q, V> {
+    /// Time since the result was fetched from the backend
+    pub fn age(&self) -> Duration {
+        self.stored_at.elapsed()
+    }
+}
+
+/// Outcome of a result cache lookup
+pub enum Lookup<T> {
+    /// Within the TTL
+    Fresh(Arc<T>),
+
+    /// Past the TTL but within the stale window; queued for revalidation
+    Stale(Arc<T>),
+
+    Miss,
+}
+
+/// Cache key for a search request against an index
+pub fn result_key<Q: Debug>(provider: &str, index: &str, query: &Q) -> String {
+    format!("{}#{:?}", index_key(provider, index), query)
+}
+
+/// Drop the cached results of an index after a write, returning how many went
+pub fn invalidate_results(provider: &str, index: &str) -> usize {
+    let removed = global().remove_prefix(CacheKind::Result, &format!("{}#", index_key(provider, index)));
+    if removed > 0 {
+        debug!("Invalidated {} cached results of {}/{}", removed, provider, index);
+    }
+    removed
+}
+
+/// Drop the cached results of an alias that moved and of every index it covered before or after
+///
+/// Results cached under a concrete index name or a write alias were read
+/// through the alias as it was, so they go along with those of the alias.
+pub fn invalidate_alias_results<'a>(provider: &str, alias: &'a str, indexes: impl IntoIterator<Item = &'a str>) -> usize {
+    std::iter::once(alias)
+        .chain(indexes)
+        .map(|index| invalidate_results(provider, index))
+        .sum()
+}
+
+/// A provider's search request, as far as caching its results goes
+pub trait CacheQuery: Debug + Clone {
+    /// The same request in a canonical form, used for its cache key
+    fn normalized(&self) -> Self;
+}
+
+/// Query text with whitespace runs collapsed to one space and no leading whitespace
+///
+/// A trailing space is kept, as some providers only match the last term as a
+/// prefix when none follows it.
+pub fn normalize_text(text: &str) -> String {
+    let mut normalized = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if !normalized.is_empty() && text.ends_with(char::is_whitespace) {
+        normalized.push(' ');
+    }
+    normalized
+}
+
+/// Filters trimmed, sorted and without duplicates; every provider combines them with AND
+pub fn normalize_filters(filters: &[String]) -> Vec<String> {
+    let mut normalized: Vec<String> = filters.iter().map(|filter| filter.trim().to_string()).collect();
+    normalized.sort();
+    normalized.dedup();
+    normalized
+}
+
+impl CacheQuery for SearchQuery {
+    fn normalized(&self) -> Self {
+        Self {
+            q: self.q.as_deref().map(normalize_text),
+            filters: normalize_filters(&self.filters),
+            ..self.clone()
+        }
+    }
+}
+
+/// Run a search through the global result cache
+///
+/// `run` executes a search against the backend. Before serving the request,
+/// one stale result queued by an earlier invocation is refreshed with it.
+pub async fn with_result_cache<Q, V, E, F, Fut>(
+    provider: &str,
+    index: &str,
+    query: &Q,
+    size_of: impl Fn(&V) -> usize,
+    run: F,
+) -> Result<V, E>
+where
+    Q: CacheQuery + Send + Sync + 'static,
+    V: Clone + Send + Sync + 'static,
+    E: Display,
+    F: Fn(String, Q) -> Fut,
+    Fut: Future<Output = Result<V, E>>,
+{
+    let cache = global();
+    let prefix = format!("{}/", provider);
+
+    if let Some((key, stale)) = cache.take_revalidation::<Q, V>(&prefix) {
+        debug!("Revalidating cached result {} ({} ms old)", key, stale.age().as_millis());
+        match run(stale.index.clone(), stale.query.clone()).await {
+            Ok(value) => {
+                let size = size_of(&value);
+                cache.store_result(&key, &stale.index, stale.query.clone(), value, size);
+            }
+            Err(e) => warn!("Failed to revalidate cached result for index {}: {}", stale.index, e),
+        }
+    }
+
+    let query = &query.normalized();
+    let key = result_key(provider, index, query);
+    let policy = CachePolicies::from_env().for_index(index);
+    if !policy.is_enabled() {
+        return run(index.to_string(), query.clone()).await;
+    }
+
+    match cache.lookup_result::<Q, V>(&key, &policy) {
+        Lookup::Fresh(entry) | Lookup::Stale(entry) => return Ok(entry.value.clone()),
+        Lookup::Miss => {}
+    }
+
+    let value = run(index.to_string(), query.clone()).await?;
+    cache.store_result(&key, index, query.clone(), value.clone(), size_of(&value));
+    Ok(value)
+}
+
+/// Estimated size of a cached schema from its field names
+pub fn schema_size<'a>(field_names: impl IntoIterator<Item = &'a str>) -> usize {
+    // Each field also carries its type and a handful of flags
+    field_names.into_iter().map(|name| name.len() + 16).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Size to pass to `insert` so that an entry under a one-letter key is charged `charged` bytes
+    fn charged(charged: usize) -> usize {
+        charged - 1 - ENTRY_OVERHEAD
+    }
+
+    fn keys(cache: &MemoryAccountant, keys: &[&str]) -> Vec<bool> {
+        keys.iter().map(|key| cache.get::<u32>(CacheKind::Schema, key).is_some()).collect()
+    }
+
+    #[test]
+    fn test_reads_keep_entries_from_being_evicted() {
+        let cache = MemoryAccountant::new(300);
+        for key in ["a", "b", "c"] {
+            assert!(cache.insert(CacheKind::Schema, key, 0u32, charged(100)));
+        }
+        assert!(cache.get::<u32>(CacheKind::Schema, "a").is_some());
+
+        assert!(cache.insert(CacheKind::Schema, "d", 0u32, charged(100)));
+        assert_eq!(keys(&cache, &["b"]), [false]);
+        assert!(cache.insert(CacheKind::Schema, "e", 0u32, charged(100)));
+        assert_eq!(keys(&cache, &["a", "c", "d", "e"]), [true, false, true, true]);
+        assert_eq!(cache.usage().evictions, 2);
+    }
+
+    #[test]
+    fn test_large_entry_evicts_as_many_least_recent_entries_as_it_needs() {
+        let cache = MemoryAccountant::new(400);
+        for key in ["a", "b", "c", "d"] {
+            cache.insert(CacheKind::Schema, key, 0u32, charged(100));
+        }
+
+        assert!(cache.insert(CacheKind::Schema, "e", 0u32, charged(250)));
+        assert_eq!(keys(&cache, &["a", "b", "c", "d", "e"]), [false, false, false, true, true]);
+
+        let usage = cache.usage();
+        assert_eq!(usage.evictions, 3);
+        assert_eq!(usage.used_bytes, 350);
+        assert_eq!(usage.entries, 2);
+    }
+
+    #[test]
+    fn test_replacing_an_entry_charges_it_once() {
+        let cache = MemoryAccountant::new(300);
+        cache.insert(CacheKind::Schema, "a", 1u32, charged(100));
+        cache.insert(CacheKind::Schema, "b", 2u32, charged(100));
+        assert!(cache.insert(CacheKind::Schema, "a", 3u32, charged(150)));
+
+        assert_eq!(cache.usage().used_bytes, 250);
+        assert_eq!(cache.usage().evictions, 0);
+        assert_eq!(cache.get::<u32>(CacheKind::Schema, "a").as_deref(), Some(&3));
+    }
+
+    #[test]
+    fn test_oversized_entries_are_refused_and_drop_what_they_replace() {
+        let cache = MemoryAccountant::new(300);
+        cache.insert(CacheKind::Schema, "a", 1u32, charged(100));
+        cache.insert(CacheKind::Schema, "b", 2u32, charged(100));
+
+        assert!(!cache.insert(CacheKind::Schema, "a", 3u32, charged(301)));
+        assert_eq!(keys(&cache, &["a", "b"]), [false, true]);
+        assert_eq!(cache.usage().used_bytes, 100);
+        assert_eq!(cache.usage().evictions, 0);
+    }
+
+    #[test]
+    fn test_entries_are_keyed_by_kind_and_typed() {
+        let cache = MemoryAccountant::new(1024);
+        cache.insert(CacheKind::Schema, "books", 1u32, 10);
+
+        assert!(cache.get::<u32>(CacheKind::Result, "books").is_none());
+        assert!(cache.get::<String>(CacheKind::Schema, "books").is_none());
+        assert!(!cache.remove(CacheKind::Client, "books"));
+        assert_eq!(cache.get::<u32>(CacheKind::Schema, "books").as_deref(), Some(&1));
+    }
+
+    #[test]
+    fn test_usage_is_split_by_kind_until_cleared() {
+        let cache = MemoryAccountant::new(4096);
+        let schemas = [index_key("meilisearch", "books"), index_key("meilisearch", "movies"), index_key("typesense", "books")];
+        cache.insert(CacheKind::Client, "a", 0u8, charged(100));
+        for key in &schemas {
+            cache.insert(CacheKind::Schema, key, 0u8, 10);
+        }
+        cache.insert(CacheKind::CompiledQuery, "c", 0u8, charged(300));
+
+        let usage = cache.usage();
+        assert_eq!((usage.client_bytes, usage.compiled_query_bytes, usage.result_bytes), (100, 300, 0));
+        assert_eq!(usage.schema_bytes, schemas.iter().map(|key| 10 + key.len() + ENTRY_OVERHEAD).sum::<usize>());
+        assert_eq!(usage.used_bytes, usage.client_bytes + usage.schema_bytes + usage.compiled_query_bytes);
+
+        assert_eq!(cache.remove_prefix(CacheKind::Schema, "meilisearch/"), 2);
+        assert_eq!(cache.usage().entries, 3);
+
+        cache.clear();
+        assert_eq!(cache.usage(), MemoryUsage { budget_bytes: 4096, ..MemoryUsage::default() });
+    }
+
+    #[test]
+    fn test_equivalent_queries_share_results_until_a_write() {
+        let query = |q: &str, filters: &[&str]| SearchQuery {
+            q: Some(q.to_string()),
+            filters: filters.iter().map(|filter| filter.to_string()).collect(),
+            sort: Vec::new(),
+            facets: Vec::new(),
+            page: None,
+            per_page: None,
+            offset: None,
+            highlight: None,
+            config: None,
+        };
+        let a = query("  red   shoes", &["size = 42", " brand = acme"]);
+        let b = query("red shoes", &["brand = acme", "size = 42", "size = 42"]);
+        assert_eq!(result_key("cachetest", "shoes", &a.normalized()), result_key("cachetest", "shoes", &b.normalized()));
+        assert_eq!(normalize_text("red  sh "), "red sh ");
+        assert_eq!(normalize_text("   "), "");
+
+        let cache = global();
+        for index in ["shoes", "shoes-archive"] {
+            let key = result_key("cachetest", index, &b.normalized());
+            cache.store_result(&key, index, b.normalized(), 1u32, 8);
+        }
+        assert_eq!(invalidate_results("cachetest", "shoes"), 1);
+        assert_eq!(invalidate_results("cachetest", "shoes"), 0);
+        assert_eq!(invalidate_results("cachetest", "shoes-archive"), 1);
+    }
+
+    #[test]
+    fn test_alias_moves_drop_results_of_the_alias_and_its_indexes() {
+        let cache = global();
+        for index in ["live", "live-v1", "live-v2", "other"] {
+            let key = result_key("aliastest", index, &"shoes");
+            cache.store_result(&key, index, "shoes".to_string(), 1u32, 8);
+        }
+
+        assert_eq!(invalidate_alias_results("aliastest", "live", ["live-v1", "live-v2"]), 3);
+        assert_eq!(invalidate_results("aliastest", "other"), 1);
+    }
+
+    /// Cache a result of `query` on the `books` index of `provider`, returning its key
+    fn store(cache: &MemoryAccountant, provider: &str, query: &str) -> String {
+        let key = result_key(provider, "books", &query);
+        cache.store_result(&key, "books", query.to_string(), vec![1u32], 8);
+        key
+    }
+
+    fn lookup(cache: &MemoryAccountant, key: &str, policy: &CachePolicy) -> &'static str {
+        match cache.lookup_result::<String, Vec<u32>>(key, policy) {
+            Lookup::Fresh(_) => "fresh",
+            Lookup::Stale(_) => "stale",
+            Lookup::Miss => "miss",
+        }
+    }
+
+    fn take(cache: &MemoryAccountant, prefix: &str) -> Option<String> {
+        cache.take_revalidation::<String, Vec<u32>>(prefix).map(|(key, _)| key)
+    }
+
+    #[test]
+    fn test_stale_results_are_served_and_queued_once() {
+        let cache = MemoryAccountant::new(4096);
+        let key = store(&cache, "typesense", "rust");
+
+        let fresh = CachePolicy::new(Duration::from_secs(60), Duration::from_secs(60));
+        assert_eq!(lookup(&cache, &key, &fresh), "fresh");
+        assert_eq!(take(&cache, "typesense/"), None);
+
+        let stale = CachePolicy::new(Duration::ZERO, Duration::from_secs(60));
+        assert_eq!(lookup(&cache, &key, &stale), "stale");
+        assert_eq!(lookup(&cache, &key, &stale), "stale");
+        assert_eq!(take(&cache, "typesense/"), Some(key));
+        assert_eq!(take(&cache, "typesense/"), None);
+    }
+
+    #[test]
+    fn test_results_past_the_stale_window_are_dropped_and_not_revalidated() {
+        let cache = MemoryAccountant::new(4096);
+        let key = store(&cache, "typesense", "rust");
+        let policy = CachePolicy::new(Duration::from_millis(1), Duration::from_millis(5));
+        std::thread::sleep(Duration::from_millis(10));
+
+        assert_eq!(lookup(&cache, &key, &policy), "miss");
+        assert_eq!(cache.usage().entries, 0);
+        assert_eq!(take(&cache, "typesense/"), None);
+    }
+
+    #[test]
+    fn test_stale_results_replaced_or_removed_since_queued_are_skipped() {
+        let cache = MemoryAccountant::new(4096);
+        let stale = CachePolicy::new(Duration::ZERO, Duration::from_secs(60));
+        let replaced = store(&cache, "typesense", "replaced");
+        let removed = store(&cache, "typesense", "removed");
+        let kept = store(&cache, "typesense", "kept");
+        for key in [&replaced, &removed, &kept] {
+            assert_eq!(lookup(&cache, key, &stale), "stale");
+        }
+
+        store(&cache, "typesense", "replaced");
+        cache.remove(CacheKind::Result, &removed);
+        assert_eq!(take(&cache, "typesense/"), Some(kept));
+        assert_eq!(take(&cache, "typesense/"), None);
+    }
+
+    #[test]
+    fn test_revalidation_takes_only_results_of_the_provider() {
+        let cache = MemoryAccountant::new(4096);
+        let stale = CachePolicy::new(Duration::ZERO, Duration::from_secs(60));
+        let meilisearch = store(&cache, "meilisearch", "rust");
+        let typesense = store(&cache, "typesense", "rust");
+        for key in [&meilisearch, &typesense] {
+            lookup(&cache, key, &stale);
+        }
+
+        assert_eq!(take(&cache, "typesense/"), Some(typesense));
+        assert_eq!(take(&cache, "typesense/"), None);
+        assert_eq!(take(&cache, "meilisearch/"), Some(meilisearch));
+    }
+
+    #[derive(Debug, Clone)]
+    struct Text(String);
+
+    impl CacheQuery for Text {
+        fn normalized(&self) -> Self {
+            Text(normalize_text(&self.0))
+        }
+    }
+
+    #[test]
+    fn test_stale_results_are_refreshed_by_the_next_search() {
+        // Every cached result of this index is stale as soon as it is stored
+        std::env::set_var(RESULT_CACHE_POLICIES_ENV, "swrtest-books:0:60000");
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let runs = std::cell::RefCell::new(Vec::new());
+        let search = |q: &str| {
+            runtime.block_on(with_result_cache("swrtest", "swrtest-books", &Text(q.to_string()), |_: &String| 8, |_, query| {
+                runs.borrow_mut().push(query.0.clone());
+                let value = format!("{} #{}", query.0, runs.borrow().len());
+                async move { Ok::<_, String>(value) }
+            }))
+            .unwrap()
+        };
+
+        assert_eq!(search("rust"), "rust #1");
+        assert_eq!(search("  rust"), "rust #1", "served stale and queued");
+        assert_eq!(search("go"), "go #3", "the queued result is refreshed first");
+        assert_eq!(*runs.borrow(), ["rust", "rust", "go"]);
+        assert_eq!(search("rust"), "rust #2");
+    }
+
+    #[test]
+    fn test_malformed_policies_are_skipped_and_index_names_may_hold_colons() {
+        let policies = CachePolicies::parse_per_index("products:1000:60000, logs:0:0, bad:x:1, broken, tenant:a:books:5:0");
+
+        assert_eq!(policies.len(), 3);
+        assert_eq!(policies["tenant:a:books"], CachePolicy::new(Duration::from_millis(5), Duration::ZERO));
+        assert_eq!(
+            policies["products"],
+            CachePolicy::new(Duration::from_secs(1), Duration::from_secs(60))
+        );
+        assert!(!policies["logs"].is_enabled());
+    }
+}
@@ -501,6 +501,38 @@ pub fn elasticsearch_capability_matrix() -> CapabilityMatrix {
             features.insert("percolator".to_string(), FeatureSupport::Native);
             features.insert("machine_learning".to_string(), FeatureSupport::Conditional);
             features.insert("security".to_string(), FeatureSupport::Conditional);
+            features.insert("case_sensitive_search".to_string(), FeatureSupport::Native);
+            features.insert("diacritic_folding".to_string(), FeatureSupport::Native);
+            features.insert("unicode_normalization".to_string(), FeatureSupport::Limited); // Client-side without the ICU plugin
+            features.insert("cjk_tokenizers".to_string(), FeatureSupport::Conditional); // Needs the kuromoji, nori and smartcn plugins
+            features.insert("stemming".to_string(), FeatureSupport::Native); // Built-in language analyzers
+            features.insert("phrase_search".to_string(), FeatureSupport::Native);
+            features.insert("proximity_search".to_string(), FeatureSupport::Native); // Phrase slop
+            features.insert("required_terms".to_string(), FeatureSupport::Native);
+            features.insert("wildcard_search".to_string(), FeatureSupport::Native);
+            features.insert("regex_search".to_string(), FeatureSupport::Native);
+            features.insert("fuzziness_control".to_string(), FeatureSupport::Native);
+            features.insert("minimum_should_match".to_string(), FeatureSupport::Native);
+            features.insert("zero_results_rescue".to_string(), FeatureSupport::Emulated); // Relaxed queries sent after zero hits
+            features.insert("spellfix".to_string(), FeatureSupport::Emulated); // Correction from the suggestions index searched after few hits
+            features.insert("range_facets".to_string(), FeatureSupport::Native); // Range aggregation
+            features.insert("date_histogram".to_string(), FeatureSupport::Native);
+            features.insert("stats_facets".to_string(), FeatureSupport::Native); // Stats aggregation
+            features.insert("grouped_results".to_string(), FeatureSupport::Native); // collapse with inner_hits
+            features.insert("cursor_pagination".to_string(), FeatureSupport::Native); // search_after when sorted, offsets otherwise
+            features.insert("exact_total".to_string(), FeatureSupport::Native); // track_total_hits
+            features.insert("pending_tasks".to_string(), FeatureSupport::Native); // Cluster pending tasks; writes are synchronous
+            features.insert("write_buffer".to_string(), FeatureSupport::Emulated); // Worker-side buffer flushed through _bulk
+            features.insert("outbox".to_string(), FeatureSupport::Emulated); // Worker-side outbox applied with _bulk
+            features.insert("change_feed".to_string(), FeatureSupport::Emulated); // ChangeFeedAdapter batches applied with _bulk
+            features.insert("lifecycle_events".to_string(), FeatureSupport::Emulated); // Worker hooks and SEARCH_EVENT_WEBHOOK_URL
+            features.insert("saved_search_alerts".to_string(), FeatureSupport::Emulated); // Matched in the worker by run_alerts
+            features.insert("snapshots".to_string(), FeatureSupport::Native); // Snapshot repository from SEARCH_SNAPSHOT_REPOSITORY
+            features.insert("index_templates".to_string(), FeatureSupport::Limited); // Synonyms and stop words; no ranking rules
+            features.insert("index_rollover".to_string(), FeatureSupport::Native); // _rollover behind a write alias
+            features.insert("index_promotion".to_string(), FeatureSupport::Emulated); // Checked in the worker, swapped with _aliases
+            features.insert("reindex_jobs".to_string(), FeatureSupport::Unsupported);
+            features.insert("id_set_filter".to_string(), FeatureSupport::Native); // Terms queries, or a terms lookup in a stored document
             features
         },
     }
@@ -517,6 +549,35 @@ pub fn opensearch_capability_matrix() -> CapabilityMatrix {
     // Add OpenSearch-specific features
     matrix.provider_specific.insert("neural_search".to_string(), FeatureSupport::Native);
     matrix.provider_specific.insert("anomaly_detection".to_string(), FeatureSupport::Native);
+    matrix.provider_specific.insert("cjk_tokenizers".to_string(), FeatureSupport::Conditional); // Needs the kuromoji, nori and smartcn plugins
+    matrix.provider_specific.insert("stemming".to_string(), FeatureSupport::Native); // Built-in language analyzers
+    matrix.provider_specific.insert("phrase_search".to_string(), FeatureSupport::Native);
+    matrix.provider_specific.insert("proximity_search".to_string(), FeatureSupport::Native); // Phrase slop
+    matrix.provider_specific.insert("required_terms".to_string(), FeatureSupport::Native);
+    matrix.provider_specific.insert("wildcard_search".to_string(), FeatureSupport::Native);
+    matrix.provider_specific.insert("regex_search".to_string(), FeatureSupport::Native);
+    matrix.provider_specific.insert("fuzziness_control".to_string(), FeatureSupport::Native);
+    matrix.provider_specific.insert("minimum_should_match".to_string(), FeatureSupport::Native);
+    matrix.provider_specific.insert("zero_results_rescue".to_string(), FeatureSupport::Emulated); // Relaxed queries sent after zero hits
+    matrix.provider_specific.insert("spellfix".to_string(), FeatureSupport::Emulated); // Correction from the suggestions index searched after few hits
+    matrix.provider_specific.insert("range_facets".to_string(), FeatureSupport::Native); // Range aggregation
+    matrix.provider_specific.insert("date_histogram".to_string(), FeatureSupport::Native);
+    matrix.provider_specific.insert("stats_facets".to_string(), FeatureSupport::Native); // Stats aggregation
+    matrix.provider_specific.insert("grouped_results".to_string(), FeatureSupport::Native); // collapse with inner_hits
+    matrix.provider_specific.insert("cursor_pagination".to_string(), FeatureSupport::Native); // search_after when sorted, offsets otherwise
+    matrix.provider_specific.insert("exact_total".to_string(), FeatureSupport::Native); // track_total_hits
+    matrix.provider_specific.insert("pending_tasks".to_string(), FeatureSupport::Native); // Cluster pending tasks; writes are synchronous
+    matrix.provider_specific.insert("write_buffer".to_string(), FeatureSupport::Emulated); // Worker-side buffer flushed through _bulk
+    matrix.provider_specific.insert("outbox".to_string(), FeatureSupport::Emulated); // Worker-side outbox applied with _bulk
+    matrix.provider_specific.insert("change_feed".to_string(), FeatureSupport::Emulated); // ChangeFeedAdapter batches applied with _bulk
+    matrix.provider_specific.insert("lifecycle_events".to_string(), FeatureSupport::Emulated); // Worker hooks and SEARCH_EVENT_WEBHOOK_URL
+    matrix.provider_specific.insert("saved_search_alerts".to_string(), FeatureSupport::Emulated); // Matched in the worker by run_alerts
+    matrix.provider_specific.insert("snapshots".to_string(), FeatureSupport::Native); // Snapshot repository from SEARCH_SNAPSHOT_REPOSITORY
+    matrix.provider_specific.insert("index_templates".to_string(), FeatureSupport::Limited); // Synonyms and stop words; no ranking rules
+    matrix.provider_specific.insert("index_rollover".to_string(), FeatureSupport::Native); // _rollover behind a write alias
+    matrix.provider_specific.insert("index_promotion".to_string(), FeatureSupport::Emulated); // Checked in the worker, swapped with _aliases
+    matrix.provider_specific.insert("reindex_jobs".to_string(), FeatureSupport::Unsupported);
+    matrix.provider_specific.insert("id_set_filter".to_string(), FeatureSupport::Native); // Terms queries, or a terms lookup in a stored document
     
     matrix
 }
@@ -545,7 +606,7 @@ pub fn typesense_capability_matrix() -> CapabilityMatrix {
             typo_tolerance: FeatureSupport::Native, // Built-in
             custom_ranking: FeatureSupport::Native,
             multilingual: FeatureSupport::Limited,
-            batch_operations: FeatureSupport::Limited, // Sequential only
+            batch_operations: FeatureSupport::Native, // Import endpoint in chunks
         },
         performance_limits: PerformanceLimits {
             max_batch_size: Some(100), // Prefers smaller batches
@@ -561,6 +622,38 @@ pub fn typesense_capability_matrix() -> CapabilityMatrix {
             features.insert("instant_search".to_string(), FeatureSupport::Native);
             features.insert("collection_aliases".to_string(), FeatureSupport::Native);
             features.insert("curation".to_string(), FeatureSupport::Native);
+            features.insert("case_sensitive_search".to_string(), FeatureSupport::Unsupported);
+            features.insert("diacritic_folding".to_string(), FeatureSupport::Native); // Always on
+            features.insert("unicode_normalization".to_string(), FeatureSupport::Limited); // Client-side
+            features.insert("cjk_tokenizers".to_string(), FeatureSupport::Limited); // Per-field locale, no whitespace tokenizer
+            features.insert("stemming".to_string(), FeatureSupport::Native); // Snowball stemmer for the field locale
+            features.insert("phrase_search".to_string(), FeatureSupport::Native);
+            features.insert("proximity_search".to_string(), FeatureSupport::Limited); // Matched as an exact phrase
+            features.insert("required_terms".to_string(), FeatureSupport::Limited); // Makes every term required
+            features.insert("wildcard_search".to_string(), FeatureSupport::Limited); // Trailing `*` prefix search only
+            features.insert("regex_search".to_string(), FeatureSupport::Unsupported);
+            features.insert("fuzziness_control".to_string(), FeatureSupport::Native); // num_typos
+            features.insert("minimum_should_match".to_string(), FeatureSupport::Limited); // All or any term, through drop_tokens_threshold
+            features.insert("zero_results_rescue".to_string(), FeatureSupport::Emulated); // Relaxed queries sent after zero hits
+            features.insert("spellfix".to_string(), FeatureSupport::Emulated); // Correction from the suggestions index searched after few hits
+            features.insert("range_facets".to_string(), FeatureSupport::Native); // facet_by ranges, Typesense 0.25+
+            features.insert("date_histogram".to_string(), FeatureSupport::Limited); // Client-side over the first 10,000 matches
+            features.insert("stats_facets".to_string(), FeatureSupport::Limited); // Client-side, result sets up to 1,000 matches
+            features.insert("grouped_results".to_string(), FeatureSupport::Native); // group_by
+            features.insert("cursor_pagination".to_string(), FeatureSupport::Native); // Page numbers
+            features.insert("exact_total".to_string(), FeatureSupport::Native); // exhaustive_search
+            features.insert("pending_tasks".to_string(), FeatureSupport::Unsupported); // Writes are applied before they are acknowledged
+            features.insert("write_buffer".to_string(), FeatureSupport::Emulated); // Worker-side buffer flushed through documents/import
+            features.insert("outbox".to_string(), FeatureSupport::Emulated); // Worker-side outbox applied one write at a time
+            features.insert("change_feed".to_string(), FeatureSupport::Emulated); // ChangeFeedAdapter batches applied with documents/import
+            features.insert("lifecycle_events".to_string(), FeatureSupport::Emulated); // Worker hooks and SEARCH_EVENT_WEBHOOK_URL
+            features.insert("saved_search_alerts".to_string(), FeatureSupport::Emulated); // Matched in the worker by run_alerts
+            features.insert("snapshots".to_string(), FeatureSupport::Emulated); // NDJSON export to the blob sink
+            features.insert("index_templates".to_string(), FeatureSupport::Limited); // Ranking as default sorting field; stop words are per query
+            features.insert("index_rollover".to_string(), FeatureSupport::Unsupported);
+            features.insert("index_promotion".to_string(), FeatureSupport::Unsupported);
+            features.insert("reindex_jobs".to_string(), FeatureSupport::Emulated); // Pages of a search copied in bounded steps
+            features.insert("id_set_filter".to_string(), FeatureSupport::Limited); // Listed IDs only; long filters sent through multi_search
             features
         },
     }
@@ -607,6 +700,38 @@ pub fn meilisearch_capability_matrix() -> CapabilityMatrix {
             features.insert("synonyms".to_string(), FeatureSupport::Native);
             features.insert("ranking_rules".to_string(), FeatureSupport::Native);
             features.insert("distinct".to_string(), FeatureSupport::Native);
+            features.insert("case_sensitive_search".to_string(), FeatureSupport::Unsupported);
+            features.insert("diacritic_folding".to_string(), FeatureSupport::Native); // Always on
+            features.insert("unicode_normalization".to_string(), FeatureSupport::Limited); // Client-side
+            features.insert("cjk_tokenizers".to_string(), FeatureSupport::Limited); // Per-field locale, no whitespace tokenizer
+            features.insert("stemming".to_string(), FeatureSupport::Unsupported);
+            features.insert("phrase_search".to_string(), FeatureSupport::Native);
+            features.insert("proximity_search".to_string(), FeatureSupport::Limited); // Matched as an exact phrase
+            features.insert("required_terms".to_string(), FeatureSupport::Limited); // Makes every term required
+            features.insert("wildcard_search".to_string(), FeatureSupport::Limited); // Trailing `*` prefix search only
+            features.insert("regex_search".to_string(), FeatureSupport::Unsupported);
+            features.insert("fuzziness_control".to_string(), FeatureSupport::Limited); // Exact matching only; otherwise the index settings apply
+            features.insert("minimum_should_match".to_string(), FeatureSupport::Limited); // matchingStrategy all, frequency or last
+            features.insert("zero_results_rescue".to_string(), FeatureSupport::Emulated); // Relaxed queries sent after zero hits
+            features.insert("spellfix".to_string(), FeatureSupport::Emulated); // Correction from the suggestions index searched after few hits
+            features.insert("range_facets".to_string(), FeatureSupport::Limited); // Bucketed client-side from per-value counts
+            features.insert("date_histogram".to_string(), FeatureSupport::Limited); // Client-side over the first maxTotalHits matches
+            features.insert("stats_facets".to_string(), FeatureSupport::Limited); // Client-side, result sets up to 1,000 matches
+            features.insert("grouped_results".to_string(), FeatureSupport::Unsupported);
+            features.insert("cursor_pagination".to_string(), FeatureSupport::Native); // Offsets, up to maxTotalHits
+            features.insert("exact_total".to_string(), FeatureSupport::Native); // Page-based paging, offsets rounded to pages
+            features.insert("pending_tasks".to_string(), FeatureSupport::Native); // Task queue
+            features.insert("write_buffer".to_string(), FeatureSupport::Emulated); // Worker-side buffer flushed as document batches
+            features.insert("outbox".to_string(), FeatureSupport::Emulated); // Worker-side outbox applied as document tasks
+            features.insert("change_feed".to_string(), FeatureSupport::Emulated); // ChangeFeedAdapter batches applied with document tasks
+            features.insert("lifecycle_events".to_string(), FeatureSupport::Emulated); // Worker hooks and SEARCH_EVENT_WEBHOOK_URL
+            features.insert("saved_search_alerts".to_string(), FeatureSupport::Emulated); // Matched in the worker by run_alerts
+            features.insert("snapshots".to_string(), FeatureSupport::Limited); // Dumps; restored only at startup
+            features.insert("index_templates".to_string(), FeatureSupport::Native); // Synonyms, stop words and custom ranking rules
+            features.insert("index_rollover".to_string(), FeatureSupport::Unsupported);
+            features.insert("index_promotion".to_string(), FeatureSupport::Unsupported);
+            features.insert("reindex_jobs".to_string(), FeatureSupport::Emulated); // Pages of the documents endpoint copied in bounded steps
+            features.insert("id_set_filter".to_string(), FeatureSupport::Limited); // Listed IDs only, as an IN filter
             features
         },
     }
@@ -653,6 +778,28 @@ pub fn algolia_capability_matrix() -> CapabilityMatrix {
             features.insert("ab_testing".to_string(), FeatureSupport::Native);
             features.insert("personalization".to_string(), FeatureSupport::Native);
             features.insert("recommend".to_string(), FeatureSupport::Native);
+            features.insert("fuzziness_control".to_string(), FeatureSupport::Native); // typoTolerance per query
+            features.insert("minimum_should_match".to_string(), FeatureSupport::Limited); // optionalWords for any, removeWordsIfNoResults otherwise
+            features.insert("zero_results_rescue".to_string(), FeatureSupport::Emulated); // Relaxed queries sent after zero hits
+            features.insert("spellfix".to_string(), FeatureSupport::Emulated); // Correction from the suggestions index searched after few hits
+            features.insert("range_facets".to_string(), FeatureSupport::Limited); // One numericFilters count query per bucket
+            features.insert("date_histogram".to_string(), FeatureSupport::Unsupported);
+            features.insert("stats_facets".to_string(), FeatureSupport::Native); // facets_stats
+            features.insert("grouped_results".to_string(), FeatureSupport::Limited); // distinct; totals from facet counts
+            features.insert("cursor_pagination".to_string(), FeatureSupport::Native); // Pages, up to paginationLimitedTo
+            features.insert("exact_total".to_string(), FeatureSupport::Unsupported); // nbHits may be approximate on large indices
+            features.insert("pending_tasks".to_string(), FeatureSupport::Limited); // Pending task count only
+            features.insert("write_buffer".to_string(), FeatureSupport::Unsupported);
+            features.insert("outbox".to_string(), FeatureSupport::Unsupported);
+            features.insert("change_feed".to_string(), FeatureSupport::Unsupported);
+            features.insert("lifecycle_events".to_string(), FeatureSupport::Unsupported);
+            features.insert("saved_search_alerts".to_string(), FeatureSupport::Unsupported);
+            features.insert("snapshots".to_string(), FeatureSupport::Emulated); // NDJSON export to the blob sink
+            features.insert("index_templates".to_string(), FeatureSupport::Limited); // Synonyms and custom ranking; no custom stop words
+            features.insert("index_rollover".to_string(), FeatureSupport::Unsupported);
+            features.insert("index_promotion".to_string(), FeatureSupport::Unsupported);
+            features.insert("reindex_jobs".to_string(), FeatureSupport::Unsupported);
+            features.insert("id_set_filter".to_string(), FeatureSupport::Limited); // Up to 1000 listed IDs in filters
             features
         },
     }
@@ -0,0 +1,357 @@
+//! Change-data-capture ingestion
+//!
+//! A [`ChangeFeed`] hands out insert, update and delete events of a source
+//! system, such as a Golem queue component fed by a database's CDC stream,
+//! each with a sequence number that grows along the feed. The
+//! [`ChangeFeedAdapter`] polls the feed, turns events into index writes and
+//! commits the feed position once the writes succeeded, keeping an index in
+//! sync with the source.
+//!
+//! Feeds are expected to deliver at least once: the adapter keeps its own
+//! checkpoint, in worker memory that Golem persists, and skips events at or
+//! before it, so redelivered events are not applied twice. Within a batch,
+//! events are ordered by sequence and only the last change to each document
+//! is written: an update followed by a delete never writes the update, and a
+//! delete followed by a re-insert keeps the document.
+//!
+//! Delete events remove documents, and so do tombstones, events that carry no
+//! document, which is how Kafka-style CDC marks a deleted key. Deleting a
+//! document the index does not hold is not an error.
+
+use std::collections::HashMap;
+use std::future::Future;
+use serde::{Deserialize, Serialize};
+use crate::error::{SearchError, SearchResult};
+use crate::types::Doc;
+
+/// Events polled from a feed at once unless configured otherwise
+pub const DEFAULT_CHANGE_BATCH_SIZE: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeOp {
+    Insert,
+    Update,
+    Delete,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChangeEvent {
+    /// Position in the feed; grows with every event
+    pub sequence: u64,
+    pub op: ChangeOp,
+    pub id: String,
+
+    /// Full document JSON after the change; `None` for deletes and tombstones
+    pub document: Option<String>,
+}
+
+impl ChangeEvent {
+    /// Whether the event removes its document
+    pub fn is_tombstone(&self) -> bool {
+        self.op == ChangeOp::Delete || self.document.is_none()
+    }
+}
+
+/// Source of change events
+pub trait ChangeFeed {
+    /// Up to `max_events` events with a sequence past `after`, or from the start of the feed
+    ///
+    /// An empty batch means the feed has caught up.
+    fn poll(&mut self, after: Option<u64>, max_events: usize) -> SearchResult<Vec<ChangeEvent>>;
+
+    /// Acknowledge that every event up to `sequence` has been applied
+    fn commit(&mut self, sequence: u64) -> SearchResult<()>;
+}
+
+/// Index writes that bring an index up to date with a batch of events
+#[derive(Debug, Clone, Default)]
+pub struct ChangeBatch {
+    pub upserts: Vec<Doc>,
+    pub deletes: Vec<String>,
+
+    /// Sequence of the last event in the batch
+    pub last_sequence: Option<u64>,
+
+    /// Events received, before redeliveries were skipped and changes collapsed
+    pub events: usize,
+}
+
+impl ChangeBatch {
+    /// Collapse events into one write per document, skipping those at or before `checkpoint`
+    ///
+    /// Upserts and deletes touch distinct documents, so they can be written in either order.
+    pub fn from_events(mut events: Vec<ChangeEvent>, checkpoint: Option<u64>) -> SearchResult<Self> {
+        let received = events.len();
+        events.retain(|event| checkpoint.is_none_or(|checkpoint| event.sequence > checkpoint));
+        events.sort_by_key(|event| event.sequence);
+
+        let last_sequence = events.last().map(|event| event.sequence);
+
+        // Latest event per document, in order of first appearance
+        let mut latest: Vec<ChangeEvent> = Vec::new();
+        let mut positions: HashMap<String, usize> = HashMap::new();
+        for event in events {
+            match positions.get(&event.id) {
+                Some(&position) => latest[position] = event,
+                None => {
+                    positions.insert(event.id.clone(), latest.len());
+                    latest.push(event);
+                }
+            }
+        }
+
+        let mut batch = Self { last_sequence, events: received, ..Self::default() };
+        for event in latest {
+            if event.is_tombstone() {
+                batch.deletes.push(event.id);
+                continue;
+            }
+            let content = event.document.unwrap_or_default();
+            serde_json::from_str::<serde_json::Value>(&content).map_err(|e| {
+                SearchError::invalid_query(format!("Change event {} has an invalid document: {}", event.sequence, e))
+            })?;
+            batch.upserts.push(Doc { id: event.id, content });
+        }
+        Ok(batch)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.upserts.is_empty() && self.deletes.is_empty()
+    }
+}
+
+/// Totals of a sync run
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncReport {
+    pub batches: usize,
+    pub events: usize,
+    pub upserted: usize,
+    pub deleted: usize,
+    pub checkpoint: Option<u64>,
+}
+
+/// Applies a change feed to one index
+pub struct ChangeFeedAdapter<F> {
+    feed: F,
+    index: String,
+    batch_size: usize,
+    checkpoint: Option<u64>,
+}
+
+impl<F: ChangeFeed> ChangeFeedAdapter<F> {
+    pub fn new(feed: F, index: &str) -> Self {
+        Self { feed, index: index.to_string(), batch_size: DEFAULT_CHANGE_BATCH_SIZE, checkpoint: None }
+    }
+
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Resume after `sequence`, such as a checkpoint saved by an earlier adapter
+    pub fn with_checkpoint(mut self, sequence: Option<u64>) -> Self {
+        self.checkpoint = sequence;
+        self
+    }
+
+    pub fn index(&self) -> &str {
+        &self.index
+    }
+
+    /// Sequence of the last applied event
+    pub fn checkpoint(&self) -> Option<u64> {
+        self.checkpoint
+    }
+
+    /// Poll the next batch, or `None` once the feed has caught up
+    ///
+    /// A poll that only returns events at or before the checkpoint counts as caught up.
+    pub fn next_batch(&mut self) -> SearchResult<Option<ChangeBatch>> {
+        let events = self.feed.poll(self.checkpoint, self.batch_size)?;
+        let batch = ChangeBatch::from_events(events, self.checkpoint)?;
+        Ok((!batch.is_empty()).then_some(batch))
+    }
+
+    /// Record a batch as applied and acknowledge it to the feed
+    pub fn commit(&mut self, batch: &ChangeBatch) -> SearchResult<()> {
+        if let Some(sequence) = batch.last_sequence {
+            self.feed.commit(sequence)?;
+            self.checkpoint = Some(sequence);
+        }
+        Ok(())
+    }
+
+    /// Apply batches through `apply` until the feed has caught up
+    ///
+    /// A batch is only committed once `apply` succeeded, so a failed run
+    /// resumes with the same events.
+    pub async fn sync<W, Fut>(&mut self, mut apply: W) -> SearchResult<SyncReport>
+    where
+        W: FnMut(String, ChangeBatch) -> Fut,
+        Fut: Future<Output = SearchResult<()>>,
+    {
+        let mut report = SyncReport { checkpoint: self.checkpoint, ..SyncReport::default() };
+        while let Some(batch) = self.next_batch()? {
+            apply(self.index.clone(), batch.clone()).await?;
+            self.commit(&batch)?;
+            report.batches += 1;
+            report.events += batch.events;
+            report.upserted += batch.upserts.len();
+            report.deleted += batch.deletes.len();
+            report.checkpoint = self.checkpoint;
+        }
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(sequence: u64, op: ChangeOp, id: &str, document: Option<&str>) -> ChangeEvent {
+        ChangeEvent { sequence, op, id: id.to_string(), document: document.map(str::to_string) }
+    }
+
+    fn upserts(batch: &ChangeBatch) -> Vec<(&str, &str)> {
+        batch.upserts.iter().map(|d| (d.id.as_str(), d.content.as_str())).collect()
+    }
+
+    #[test]
+    fn test_only_the_last_change_per_document_is_written_in_sequence_order() {
+        let events = vec![
+            event(3, ChangeOp::Update, "1", Some(r#"{"v":2}"#)),
+            event(1, ChangeOp::Insert, "1", Some(r#"{"v":1}"#)),
+            event(2, ChangeOp::Insert, "2", Some("{}")),
+            event(4, ChangeOp::Delete, "2", None),
+        ];
+        let batch = ChangeBatch::from_events(events, None).unwrap();
+        assert_eq!(upserts(&batch), [("1", r#"{"v":2}"#)]);
+        assert_eq!(batch.deletes, ["2"]);
+        assert_eq!(batch.last_sequence, Some(4));
+    }
+
+    #[test]
+    fn test_a_reinsert_after_a_delete_keeps_the_document() {
+        let events = vec![event(1, ChangeOp::Delete, "1", None), event(2, ChangeOp::Insert, "1", Some("{}"))];
+        let batch = ChangeBatch::from_events(events, None).unwrap();
+        assert_eq!(upserts(&batch), [("1", "{}")]);
+        assert!(batch.deletes.is_empty());
+    }
+
+    #[test]
+    fn test_events_without_a_document_are_tombstones() {
+        let events = vec![event(1, ChangeOp::Update, "1", None), event(2, ChangeOp::Delete, "2", Some("{}"))];
+        let batch = ChangeBatch::from_events(events, None).unwrap();
+        assert!(batch.upserts.is_empty());
+        assert_eq!(batch.deletes, ["1", "2"]);
+    }
+
+    #[test]
+    fn test_redelivered_events_are_skipped_but_counted() {
+        let events = vec![event(7, ChangeOp::Insert, "4", Some("{}")), event(8, ChangeOp::Delete, "1", None)];
+        let batch = ChangeBatch::from_events(events.clone(), Some(7)).unwrap();
+        assert!(batch.upserts.is_empty());
+        assert_eq!(batch.deletes, ["1"]);
+        assert_eq!((batch.events, batch.last_sequence), (2, Some(8)));
+
+        let replayed = ChangeBatch::from_events(events, Some(8)).unwrap();
+        assert!(replayed.is_empty());
+        assert_eq!(replayed.last_sequence, None);
+    }
+
+    #[test]
+    fn test_invalid_documents_fail_the_batch_naming_the_event() {
+        let events = vec![event(8, ChangeOp::Insert, "4", Some("{}")), event(9, ChangeOp::Insert, "5", Some("{"))];
+        let Err(SearchError::InvalidQuery(message)) = ChangeBatch::from_events(events, None) else {
+            panic!("expected an invalid document error");
+        };
+        assert!(message.contains("event 9"), "{}", message);
+
+        // Superseded by a later change, an invalid document is never parsed
+        let events = vec![event(1, ChangeOp::Insert, "5", Some("{")), event(2, ChangeOp::Delete, "5", None)];
+        assert!(ChangeBatch::from_events(events, None).is_ok());
+    }
+
+    struct VecFeed {
+        events: Vec<ChangeEvent>,
+        committed: Option<u64>,
+        fail_commit: bool,
+    }
+
+    impl VecFeed {
+        fn inserts(count: u64) -> Self {
+            let events = (1..=count).map(|n| event(n, ChangeOp::Insert, &n.to_string(), Some("{}"))).collect();
+            Self { events, committed: None, fail_commit: false }
+        }
+    }
+
+    impl ChangeFeed for VecFeed {
+        fn poll(&mut self, after: Option<u64>, max_events: usize) -> SearchResult<Vec<ChangeEvent>> {
+            let start = after.map_or(0, |after| after as usize);
+            Ok(self.events.iter().skip(start).take(max_events).cloned().collect())
+        }
+
+        fn commit(&mut self, sequence: u64) -> SearchResult<()> {
+            if self.fail_commit {
+                return Err(SearchError::internal("feed unavailable"));
+            }
+            self.committed = Some(sequence);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_a_failed_apply_is_not_committed_and_resumes_with_the_same_events() {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let mut adapter = ChangeFeedAdapter::new(VecFeed::inserts(5), "books").with_batch_size(2);
+
+        let mut calls = 0;
+        let failed = runtime.block_on(adapter.sync(|_, _| {
+            calls += 1;
+            let result = if calls == 2 { Err(SearchError::Timeout { remaining_ms: None }) } else { Ok(()) };
+            async move { result }
+        }));
+        assert!(failed.is_err());
+        assert_eq!((adapter.checkpoint(), adapter.feed.committed), (Some(2), Some(2)));
+
+        let mut applied = Vec::new();
+        let report = runtime
+            .block_on(adapter.sync(|index, batch| {
+                applied.push((index, batch.upserts.iter().map(|d| d.id.clone()).collect::<Vec<_>>()));
+                async { Ok(()) }
+            }))
+            .unwrap();
+        assert_eq!(applied, [("books".to_string(), vec!["3".to_string(), "4".to_string()]), ("books".to_string(), vec!["5".to_string()])]);
+        assert_eq!((report.batches, report.events, report.upserted, report.checkpoint), (2, 3, 3, Some(5)));
+        assert_eq!(adapter.feed.committed, Some(5));
+    }
+
+    #[test]
+    fn test_a_failed_commit_keeps_the_checkpoint() {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let mut feed = VecFeed::inserts(2);
+        feed.fail_commit = true;
+        let mut adapter = ChangeFeedAdapter::new(feed, "books").with_checkpoint(Some(1));
+
+        assert!(runtime.block_on(adapter.sync(|_, _| async { Ok(()) })).is_err());
+        assert_eq!(adapter.checkpoint(), Some(1));
+    }
+
+    #[test]
+    fn test_zero_batch_size_still_makes_progress() {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let mut adapter = ChangeFeedAdapter::new(VecFeed::inserts(3), "books").with_batch_size(0);
+        let report = runtime.block_on(adapter.sync(|_, _| async { Ok(()) })).unwrap();
+        assert_eq!((report.batches, report.checkpoint), (3, Some(3)));
+    }
+
+    #[test]
+    fn test_a_caught_up_feed_runs_no_batch() {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let mut adapter = ChangeFeedAdapter::new(VecFeed::inserts(3), "books").with_checkpoint(Some(3));
+        let report = runtime.block_on(adapter.sync(|_, _| async { panic!("nothing to apply") })).unwrap();
+        assert_eq!(report, SyncReport { checkpoint: Some(3), ..SyncReport::default() });
+        assert_eq!(adapter.feed.committed, None);
+    }
+}
@@ -0,0 +1,316 @@
+//! Skipping unchanged upserts
+//!
+//! Periodic full-sync jobs re-send every document although few of them
+//! changed. With `SEARCH_SKIP_UNCHANGED=true` providers keep a hash of each
+//! document they write, per index, and batch upserts leave out documents that
+//! hash the same as when last written. Single upserts always write and
+//! refresh the stored hash.
+//!
+//! The hash covers the document after the ingest pipeline, with JSON objects
+//! compared regardless of key order. Hashes live in worker memory, which Golem
+//! persists, and only know about writes made through this worker: a document
+//! changed or deleted by another client is skipped when re-sent as it was
+//! last written here. Deleting documents, clearing or deleting the index, or
+//! restoring it from a snapshot through this worker forgets the affected
+//! hashes. At most `SEARCH_CHECKSUM_CAPACITY` hashes are kept; documents
+//! beyond that are always written.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use log::debug;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use crate::config::env_helpers::get_env_parsed;
+use crate::types::Doc;
+
+/// Environment variable turning on skipping of unchanged batch upserts
+pub const SKIP_UNCHANGED_ENV: &str = "SEARCH_SKIP_UNCHANGED";
+
+/// Environment variable bounding the number of stored hashes
+pub const CHECKSUM_CAPACITY_ENV: &str = "SEARCH_CHECKSUM_CAPACITY";
+
+pub const DEFAULT_CHECKSUM_CAPACITY: usize = 1_000_000;
+
+/// Truncated SHA-256 of a document's content
+pub type Checksum = [u8; 16];
+
+/// Hash of document content; JSON is hashed in a canonical form
+pub fn checksum(content: &str) -> Checksum {
+    match serde_json::from_str::<Value>(content) {
+        Ok(value) => checksum_value(&value),
+        Err(_) => digest(content.as_bytes()),
+    }
+}
+
+/// Hash of a parsed document
+pub fn checksum_value(document: &Value) -> Checksum {
+    // serde_json keeps object keys sorted, so serializing canonicalizes key order and whitespace
+    digest(document.to_string().as_bytes())
+}
+
+fn digest(bytes: &[u8]) -> Checksum {
+    let digest = Sha256::digest(bytes);
+    let mut checksum = Checksum::default();
+    checksum.copy_from_slice(&digest[..16]);
+    checksum
+}
+
+/// Hashes of the documents last written, per index
+#[derive(Debug)]
+pub struct ChecksumStore {
+    capacity: usize,
+    len: usize,
+    indexes: HashMap<String, HashMap<String, Checksum>>,
+}
+
+impl ChecksumStore {
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity, len: 0, indexes: HashMap::new() }
+    }
+
+    /// Whether `doc` hashes the same as when it was last written to `index`
+    pub fn is_unchanged(&self, index: &str, doc: &Doc) -> bool {
+        self.indexes
+            .get(index)
+            .and_then(|hashes| hashes.get(&doc.id))
+            .is_some_and(|stored| *stored == checksum(&doc.content))
+    }
+
+    /// `docs` without the unchanged ones; borrowed when none was left out
+    pub fn changed<'a>(&self, index: &str, docs: &'a [Doc]) -> Cow<'a, [Doc]> {
+        if !self.indexes.contains_key(index) || !docs.iter().any(|doc| self.is_unchanged(index, doc)) {
+            return Cow::Borrowed(docs);
+        }
+        Cow::Owned(docs.iter().filter(|doc| !self.is_unchanged(index, doc)).cloned().collect())
+    }
+
+    /// Store the hashes of documents written to `index`
+    pub fn record<'a>(&mut self, index: &str, docs: impl IntoIterator<Item = &'a Doc>) {
+        let hashes = self.indexes.entry(index.to_string()).or_default();
+        for doc in docs {
+            let checksum = checksum(&doc.content);
+            if let Some(stored) = hashes.get_mut(&doc.id) {
+                *stored = checksum;
+            } else if self.len < self.capacity {
+                hashes.insert(doc.id.clone(), checksum);
+                self.len += 1;
+            }
+        }
+    }
+
+    /// Store the hashes of the documents an ElasticSearch-style `_bulk` response reports written
+    ///
+    /// The response's `items` are in the order of `docs`.
+    pub fn record_bulk(&mut self, index: &str, docs: &[Doc], response: &Value) {
+        let items = response.get("items").and_then(Value::as_array);
+        let succeeded = docs.iter().enumerate().filter(|(position, _)| {
+            // Each item is keyed by its action, such as {"index": {"status": 201}}
+            items
+                .and_then(|items| items.get(*position))
+                .and_then(Value::as_object)
+                .and_then(|item| item.values().next())
+                .is_some_and(|result| result.get("error").is_none())
+        });
+        self.record(index, succeeded.map(|(_, doc)| doc));
+    }
+
+    /// Forget deleted documents
+    pub fn forget(&mut self, index: &str, ids: &[String]) {
+        if let Some(hashes) = self.indexes.get_mut(index) {
+            for id in ids {
+                if hashes.remove(id).is_some() {
+                    self.len -= 1;
+                }
+            }
+        }
+    }
+
+    /// Forget every document of a cleared, deleted or restored index
+    pub fn forget_index(&mut self, index: &str) {
+        if let Some(hashes) = self.indexes.remove(index) {
+            self.len -= hashes.len();
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+/// The worker's store, or `None` when skipping is off
+fn global_store() -> MutexGuard<'static, Option<ChecksumStore>> {
+    static STORE: OnceLock<Mutex<Option<ChecksumStore>>> = OnceLock::new();
+    STORE
+        .get_or_init(|| {
+            let enabled = get_env_parsed::<bool>(SKIP_UNCHANGED_ENV).ok().flatten().unwrap_or(false);
+            let capacity = get_env_parsed::<usize>(CHECKSUM_CAPACITY_ENV)
+                .ok()
+                .flatten()
+                .unwrap_or(DEFAULT_CHECKSUM_CAPACITY);
+            Mutex::new(enabled.then(|| ChecksumStore::new(capacity)))
+        })
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+pub fn is_enabled() -> bool {
+    global_store().is_some()
+}
+
+/// Whether `doc` is unchanged since it was last written to `index`
+pub fn is_unchanged(index: &str, doc: &Doc) -> bool {
+    global_store().as_ref().is_some_and(|store| store.is_unchanged(index, doc))
+}
+
+/// `docs` without those unchanged since they were last written to `index`
+pub fn skip_unchanged<'a>(index: &str, docs: &'a [Doc]) -> Cow<'a, [Doc]> {
+    let Some(changed) = global_store().as_ref().map(|store| store.changed(index, docs)) else {
+        return Cow::Borrowed(docs);
+    };
+    if changed.len() < docs.len() {
+        debug!("Skipping {} unchanged documents in index {}", docs.len() - changed.len(), index);
+    }
+    changed
+}
+
+/// Store the hashes of documents the provider accepted
+pub fn record<'a>(index: &str, docs: impl IntoIterator<Item = &'a Doc>) {
+    if let Some(store) = global_store().as_mut() {
+        store.record(index, docs);
+    }
+}
+
+/// Store the hashes of the documents a `_bulk` response reports written
+pub fn record_bulk(index: &str, docs: &[Doc], response: &Value) {
+    if let Some(store) = global_store().as_mut() {
+        store.record_bulk(index, docs, response);
+    }
+}
+
+pub fn forget(index: &str, ids: &[String]) {
+    if let Some(store) = global_store().as_mut() {
+        store.forget(index, ids);
+    }
+}
+
+pub fn forget_index(index: &str) {
+    if let Some(store) = global_store().as_mut() {
+        store.forget_index(index);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doc(id: &str, content: &str) -> Doc {
+        Doc { id: id.to_string(), content: content.to_string() }
+    }
+
+    fn ids(docs: &[Doc]) -> Vec<&str> {
+        docs.iter().map(|d| d.id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_json_is_compared_regardless_of_key_order_and_whitespace() {
+        assert_eq!(checksum(r#"{"a": {"x": 1, "y": [1, 2]}, "b": null}"#), checksum(r#"{"b":null,"a":{"y":[1,2],"x":1}}"#));
+        assert_ne!(checksum(r#"{"y": [1, 2]}"#), checksum(r#"{"y": [2, 1]}"#), "array order is content");
+        assert_ne!(checksum(r#"{"n": 1}"#), checksum(r#"{"n": 1.0}"#));
+        assert_eq!(checksum_value(&serde_json::json!({ "n": 1 })), checksum(r#"{ "n" : 1 }"#));
+    }
+
+    #[test]
+    fn test_content_that_is_not_json_is_compared_exactly() {
+        assert_eq!(checksum("plain text"), checksum("plain text"));
+        assert_ne!(checksum("plain text"), checksum("plain  text"));
+    }
+
+    #[test]
+    fn test_batches_are_borrowed_unless_a_document_is_left_out() {
+        let mut store = ChecksumStore::new(10);
+        let first = vec![doc("1", r#"{"title": "Dune"}"#), doc("2", r#"{"title": "Emma"}"#)];
+        assert!(matches!(store.changed("books", &first), Cow::Borrowed(_)));
+        store.record("books", &first);
+
+        let second = vec![doc("1", r#"{"title":"Dune"}"#), doc("2", r#"{"title": "Emma", "year": 1815}"#), doc("3", "{}")];
+        let changed = store.changed("books", &second);
+        assert!(matches!(changed, Cow::Owned(_)));
+        assert_eq!(ids(&changed), ["2", "3"]);
+
+        let all_changed = vec![doc("1", r#"{"title": "Dune Messiah"}"#)];
+        assert!(matches!(store.changed("books", &all_changed), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_hashes_are_kept_per_index() {
+        let mut store = ChecksumStore::new(10);
+        store.record("books", &[doc("1", "{}")]);
+        assert!(store.is_unchanged("books", &doc("1", "{}")));
+        assert!(!store.is_unchanged("films", &doc("1", "{}")));
+
+        store.forget_index("films");
+        store.forget("films", &["1".to_string()]);
+        assert_eq!(store.len(), 1);
+    }
+
+    #[test]
+    fn test_forgetting_makes_documents_count_as_changed_again() {
+        let mut store = ChecksumStore::new(10);
+        store.record("books", &[doc("1", "{}"), doc("2", "{}")]);
+
+        store.forget("books", &["1".to_string(), "1".to_string(), "missing".to_string()]);
+        assert_eq!(store.len(), 1);
+        assert!(!store.is_unchanged("books", &doc("1", "{}")));
+
+        store.forget_index("books");
+        assert!(store.is_empty());
+        assert!(!store.is_unchanged("books", &doc("2", "{}")));
+    }
+
+    #[test]
+    fn test_documents_past_capacity_are_always_written() {
+        let mut store = ChecksumStore::new(2);
+        let docs: Vec<Doc> = (0..3).map(|i| doc(&i.to_string(), "{}")).collect();
+        store.record("books", &docs);
+        assert_eq!(store.len(), 2);
+        assert_eq!(ids(&store.changed("books", &docs)), ["2"]);
+
+        // Stored hashes still update at capacity, and forgetting frees room
+        store.record("books", &[doc("0", r#"{"a": 1}"#)]);
+        assert!(store.is_unchanged("books", &doc("0", r#"{"a": 1}"#)));
+        store.forget("books", &["1".to_string()]);
+        store.record("films", &docs[2..]);
+        assert!(store.is_unchanged("films", &docs[2]));
+        assert_eq!(store.len(), 2);
+    }
+
+    #[test]
+    fn test_a_document_repeated_in_a_batch_is_stored_once_as_last_written() {
+        let mut store = ChecksumStore::new(10);
+        store.record("books", &[doc("1", r#"{"v": 1}"#), doc("1", r#"{"v": 2}"#)]);
+        assert_eq!(store.len(), 1);
+        assert!(store.is_unchanged("books", &doc("1", r#"{"v": 2}"#)));
+    }
+
+    #[test]
+    fn test_bulk_items_with_errors_or_missing_are_not_recorded() {
+        let docs: Vec<Doc> = (0..3).map(|i| doc(&i.to_string(), "{}")).collect();
+        let response = serde_json::json!({
+            "errors": true,
+            "items": [{ "index": { "status": 200 } }, { "index": { "status": 400, "error": { "type": "mapper_parsing_exception" } } }]
+        });
+        let mut store = ChecksumStore::new(10);
+        store.record_bulk("books", &docs, &response);
+        let recorded: Vec<bool> = docs.iter().map(|d| store.is_unchanged("books", d)).collect();
+        assert_eq!(recorded, [true, false, false]);
+
+        let mut store = ChecksumStore::new(10);
+        store.record_bulk("books", &docs, &serde_json::json!({ "took": 3 }));
+        assert!(store.is_empty());
+    }
+}
@@ -0,0 +1,387 @@
+//! Per-endpoint circuit breaker state
+//!
+//! Provider clients report the outcome of every HTTP request here. After
+//! [`FAILURE_THRESHOLD_ENV`] consecutive failures an endpoint's circuit opens
+//! and requests are rejected locally with `SearchError::Unavailable` until the
+//! [`COOLDOWN_ENV`] cooldown elapses; the next request is then let through in
+//! the half-open state and either closes the circuit again or re-opens it.
+//! Requests arriving while that probe is in flight are still rejected.
+//! The current state of every endpoint is exposed via [`health_report`] so
+//! operators can see why searches are being rejected.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use log::{debug, info, warn};
+use crate::cache::MemoryUsage;
+use crate::config::env_helpers::get_env_parsed;
+use crate::error::{SearchError, SearchResult};
+
+/// Environment variable holding the consecutive failures that open a circuit
+pub const FAILURE_THRESHOLD_ENV: &str = "SEARCH_CIRCUIT_FAILURE_THRESHOLD";
+
+/// Environment variable holding how long an open circuit rejects requests, in milliseconds
+pub const COOLDOWN_ENV: &str = "SEARCH_CIRCUIT_COOLDOWN_MS";
+
+/// Default number of consecutive failures before a circuit opens
+pub const DEFAULT_FAILURE_THRESHOLD: u32 = 5;
+
+/// Default time an open circuit rejects requests before probing again
+pub const DEFAULT_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// Circuit state for a single endpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    /// Requests flow normally
+    Closed,
+    /// Requests are rejected locally until the cooldown expires
+    Open,
+    /// A single probe request is allowed to test recovery
+    HalfOpen,
+}
+
+/// Circuit breaker tuning
+#[derive(Debug, Clone)]
+pub struct CircuitBreakerConfig {
+    /// Consecutive failures that trip the circuit
+    pub failure_threshold: u32,
+
+    /// How long an open circuit stays open
+    pub cooldown: Duration,
+}
+
+impl CircuitBreakerConfig {
+    /// Configuration from the environment, with defaults for unset variables
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            failure_threshold: get_env_parsed::<u32>(FAILURE_THRESHOLD_ENV)
+                .ok()
+                .flatten()
+                .map_or(defaults.failure_threshold, |threshold| threshold.max(1)),
+            cooldown: get_env_parsed::<u64>(COOLDOWN_ENV)
+                .ok()
+                .flatten()
+                .map_or(defaults.cooldown, Duration::from_millis),
+        }
+    }
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: DEFAULT_FAILURE_THRESHOLD,
+            cooldown: DEFAULT_COOLDOWN,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct EndpointCircuit {
+    state: CircuitState,
+    consecutive_failures: u32,
+    total_failures: u64,
+    total_requests: u64,
+    last_error: Option<String>,
+    last_failure_at: Option<String>,
+    opened_at: Option<Instant>,
+    /// When the half-open probe was let through, until its outcome is recorded
+    probe_started_at: Option<Instant>,
+}
+
+impl EndpointCircuit {
+    fn new() -> Self {
+        Self {
+            state: CircuitState::Closed,
+            consecutive_failures: 0,
+            total_failures: 0,
+            total_requests: 0,
+            last_error: None,
+            last_failure_at: None,
+            opened_at: None,
+            probe_started_at: None,
+        }
+    }
+}
+
+/// Point-in-time view of an endpoint's circuit, as reported by health checks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EndpointStatus {
+    pub endpoint: String,
+    pub state: CircuitState,
+    pub consecutive_failures: u32,
+    pub total_failures: u64,
+    pub total_requests: u64,
+    pub last_error: Option<String>,
+    pub last_failure_at: Option<String>,
+    /// Milliseconds until an open circuit allows a probe request
+    pub retry_after_ms: Option<u64>,
+}
+
+/// Extended health information for a provider
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthReport {
+    pub provider: String,
+    /// False when any endpoint's circuit is open
+    pub healthy: bool,
+    pub endpoints: Vec<EndpointStatus>,
+    /// Memory held by the shared provider cache
+    pub memory: MemoryUsage,
+}
+
+/// Tracks circuit state for every endpoint a worker talks to
+pub struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    endpoints: Mutex<HashMap<String, EndpointCircuit>>,
+}
+
+impl CircuitBreaker {
+    /// Create a circuit breaker with the given configuration
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            endpoints: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Check whether a request to `endpoint` may proceed
+    ///
+    /// Open circuits whose cooldown has elapsed move to half-open and let the
+    /// request through as a probe. Until that probe's outcome is recorded,
+    /// every other request is rejected; a probe that never reports back is
+    /// replaced by a new one after another cooldown.
+    pub fn check(&self, endpoint: &str) -> SearchResult<()> {
+        let mut endpoints = self.endpoints.lock().unwrap();
+        let circuit = endpoints.entry(endpoint.to_string()).or_insert_with(EndpointCircuit::new);
+
+        match circuit.state {
+            CircuitState::Closed => return Ok(()),
+            CircuitState::HalfOpen => {
+                let probe_age = circuit.probe_started_at.map(|t| t.elapsed()).unwrap_or_default();
+                if circuit.probe_started_at.is_some() && probe_age < self.config.cooldown {
+                    debug!("Circuit for {} is half-open with a probe in flight; rejecting request", endpoint);
+                    return Err(SearchError::Unavailable { retry_after_ms: None });
+                }
+                info!("Circuit for {} is half-open, allowing probe request", endpoint);
+                circuit.probe_started_at = Some(Instant::now());
+                return Ok(());
+            }
+            CircuitState::Open => {}
+        }
+
+        let elapsed = circuit.opened_at.map(|t| t.elapsed()).unwrap_or_default();
+        if elapsed >= self.config.cooldown {
+            info!("Circuit for {} is half-open, allowing probe request", endpoint);
+            circuit.state = CircuitState::HalfOpen;
+            circuit.probe_started_at = Some(Instant::now());
+            return Ok(());
+        }
+
+        let remaining = self.config.cooldown - elapsed;
+        debug!(
+            "Circuit open for {} after {} consecutive failures (last error: {}); retry in {} ms",
+            endpoint,
+            circuit.consecutive_failures,
+            circuit.last_error.as_deref().unwrap_or("unknown"),
+            remaining.as_millis()
+        );
+        Err(SearchError::Unavailable { retry_after_ms: Some(remaining.as_millis() as u64) })
+    }
+
+    /// Record a successful request, closing the circuit
+    pub fn record_success(&self, endpoint: &str) {
+        let mut endpoints = self.endpoints.lock().unwrap();
+        let circuit = endpoints.entry(endpoint.to_string()).or_insert_with(EndpointCircuit::new);
+
+        if circuit.state != CircuitState::Closed {
+            info!("Circuit for {} closed after successful request", endpoint);
+        }
+        circuit.state = CircuitState::Closed;
+        circuit.consecutive_failures = 0;
+        circuit.opened_at = None;
+        circuit.probe_started_at = None;
+        circuit.total_requests += 1;
+    }
+
+    /// Record a failed request, opening the circuit once the threshold is reached
+    pub fn record_failure(&self, endpoint: &str, error: &str) {
+        let mut endpoints = self.endpoints.lock().unwrap();
+        let circuit = endpoints.entry(endpoint.to_string()).or_insert_with(EndpointCircuit::new);
+
+        circuit.consecutive_failures += 1;
+        circuit.total_failures += 1;
+        circuit.total_requests += 1;
+        circuit.last_error = Some(error.to_string());
+        circuit.last_failure_at = Some(chrono::Utc::now().to_rfc3339());
+        circuit.probe_started_at = None;
+
+        let trip = circuit.state == CircuitState::HalfOpen
+            || circuit.consecutive_failures >= self.config.failure_threshold;
+        if trip && circuit.state != CircuitState::Open {
+            warn!(
+                "Circuit for {} opened after {} consecutive failures: {}",
+                endpoint, circuit.consecutive_failures, error
+            );
+            circuit.state = CircuitState::Open;
+            circuit.opened_at = Some(Instant::now());
+        }
+    }
+
+    /// Record the outcome of an HTTP response; 5xx statuses count as failures
+    pub fn record_status(&self, endpoint: &str, status: u16) {
+        if status >= 500 {
+            self.record_failure(endpoint, &format!("HTTP {}", status));
+        } else {
+            self.record_success(endpoint);
+        }
+    }
+
+    /// Current status of every known endpoint, sorted by endpoint
+    pub fn snapshot(&self) -> Vec<EndpointStatus> {
+        let endpoints = self.endpoints.lock().unwrap();
+        let mut statuses: Vec<EndpointStatus> = endpoints
+            .iter()
+            .map(|(endpoint, circuit)| EndpointStatus {
+                endpoint: endpoint.clone(),
+                state: circuit.state,
+                consecutive_failures: circuit.consecutive_failures,
+                total_failures: circuit.total_failures,
+                total_requests: circuit.total_requests,
+                last_error: circuit.last_error.clone(),
+                last_failure_at: circuit.last_failure_at.clone(),
+                retry_after_ms: match (circuit.state, circuit.opened_at) {
+                    (CircuitState::Open, Some(opened)) => Some(
+                        self.config.cooldown.saturating_sub(opened.elapsed()).as_millis() as u64,
+                    ),
+                    _ => None,
+                },
+            })
+            .collect();
+        statuses.sort_by(|a, b| a.endpoint.cmp(&b.endpoint));
+        statuses
+    }
+}
+
+/// Process-wide circuit breaker shared by all provider clients in a worker
+pub fn global() -> &'static CircuitBreaker {
+    static BREAKER: OnceLock<CircuitBreaker> = OnceLock::new();
+    BREAKER.get_or_init(|| CircuitBreaker::new(CircuitBreakerConfig::from_env()))
+}
+
+/// The rejection of an open circuit, when `error` is one
+///
+/// Clients pass it on inside their `anyhow` errors, which providers otherwise
+/// classify by message.
+pub fn open_circuit(error: &anyhow::Error) -> Option<SearchError> {
+    match error.downcast_ref::<SearchError>() {
+        Some(rejection @ SearchError::Unavailable { .. }) => Some(rejection.clone()),
+        _ => None,
+    }
+}
+
+/// Build the extended health report for a provider from the global breaker
+pub fn health_report(provider: &str) -> HealthReport {
+    let endpoints = global().snapshot();
+    HealthReport {
+        provider: provider.to_string(),
+        healthy: endpoints.iter().all(|e| e.state != CircuitState::Open),
+        endpoints,
+        memory: crate::cache::global().usage(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn breaker(cooldown: Duration) -> CircuitBreaker {
+        CircuitBreaker::new(CircuitBreakerConfig {
+            failure_threshold: 2,
+            cooldown,
+        })
+    }
+
+    #[test]
+    fn test_circuit_opens_after_threshold() {
+        let breaker = breaker(Duration::from_secs(60));
+        let endpoint = "http://node-1:9200/";
+
+        breaker.record_failure(endpoint, "connection refused");
+        assert!(breaker.check(endpoint).is_ok());
+
+        breaker.record_status(endpoint, 503);
+        let rejection = breaker.check(endpoint).unwrap_err();
+        assert!(matches!(rejection, SearchError::Unavailable { retry_after_ms: Some(ms) } if ms > 59_000));
+        assert!(open_circuit(&anyhow::anyhow!(rejection)).is_some());
+        assert!(open_circuit(&anyhow::anyhow!("HTTP 503")).is_none());
+
+        let status = &breaker.snapshot()[0];
+        assert_eq!(status.state, CircuitState::Open);
+        assert_eq!(status.last_error.as_deref(), Some("HTTP 503"));
+        assert!(status.retry_after_ms.is_some());
+    }
+
+    #[test]
+    fn test_half_open_probe_closes_on_success() {
+        let breaker = breaker(Duration::from_millis(0));
+        let endpoint = "http://node-1:9200/";
+
+        breaker.record_failure(endpoint, "timeout");
+        breaker.record_failure(endpoint, "timeout");
+        assert!(breaker.check(endpoint).is_ok());
+        assert_eq!(breaker.snapshot()[0].state, CircuitState::HalfOpen);
+
+        breaker.record_success(endpoint);
+        assert_eq!(breaker.snapshot()[0].state, CircuitState::Closed);
+        assert_eq!(breaker.snapshot()[0].consecutive_failures, 0);
+    }
+
+    #[test]
+    fn test_half_open_admits_a_single_probe() {
+        let breaker = CircuitBreaker::new(CircuitBreakerConfig {
+            failure_threshold: 1,
+            cooldown: Duration::from_millis(20),
+        });
+        let endpoint = "http://node-1:9200/";
+
+        breaker.record_failure(endpoint, "timeout");
+        std::thread::sleep(Duration::from_millis(25));
+
+        assert!(breaker.check(endpoint).is_ok());
+        let rejection = breaker.check(endpoint).unwrap_err();
+        assert!(matches!(rejection, SearchError::Unavailable { .. }));
+        assert!(open_circuit(&anyhow::anyhow!(rejection)).is_some());
+        assert_eq!(breaker.snapshot()[0].state, CircuitState::HalfOpen);
+
+        // A failed probe re-opens the circuit for a full cooldown
+        breaker.record_failure(endpoint, "timeout");
+        assert_eq!(breaker.snapshot()[0].state, CircuitState::Open);
+        assert!(breaker.check(endpoint).is_err());
+
+        std::thread::sleep(Duration::from_millis(25));
+        assert!(breaker.check(endpoint).is_ok());
+        breaker.record_success(endpoint);
+        assert!(breaker.check(endpoint).is_ok());
+        assert!(breaker.check(endpoint).is_ok());
+    }
+
+    #[test]
+    fn test_abandoned_probe_is_replaced_after_cooldown() {
+        let breaker = CircuitBreaker::new(CircuitBreakerConfig {
+            failure_threshold: 1,
+            cooldown: Duration::from_millis(20),
+        });
+        let endpoint = "http://node-1:9200/";
+
+        breaker.record_failure(endpoint, "timeout");
+        std::thread::sleep(Duration::from_millis(25));
+        assert!(breaker.check(endpoint).is_ok());
+        assert!(breaker.check(endpoint).is_err());
+
+        std::thread::sleep(Duration::from_millis(25));
+        assert!(breaker.check(endpoint).is_ok());
+        assert!(breaker.check(endpoint).is_err());
+    }
+}
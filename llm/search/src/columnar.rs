@@ -0,0 +1,470 @@
+//! Arrow and Parquet export for analytics
+//!
+//! Available with the `arrow` feature. Documents, search hits and the slow
+//! query log convert to Arrow [`RecordBatch`]es, and [`write_parquet`] puts a
+//! batch into a [`BlobSink`] as a Snappy-compressed Parquet file that DuckDB,
+//! Spark or pandas read directly.
+//!
+//! Columns follow the index schema when one is given: text and keyword
+//! fields become strings, integers `Int64`, floats `Float64`, booleans
+//! `Boolean` and dates UTC millisecond timestamps. Without a schema a column
+//! type is inferred from the values of every document. Values that do not fit
+//! their column, nested objects and arrays are written as JSON text, so no
+//! data is dropped; a document without the field gets a null.
+
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use arrow_array::builder::{BooleanBuilder, Float64Builder, Int64Builder, StringBuilder, TimestampMillisecondBuilder, UInt64Builder};
+use arrow_array::{ArrayRef, Float64Array, RecordBatch, StringArray};
+use arrow_schema::{DataType, Field, Schema as ArrowSchema, TimeUnit};
+use chrono::{DateTime, NaiveDate};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use serde_json::{Map, Value};
+use crate::blob::BlobSink;
+use crate::error::{SearchError, SearchResult};
+use crate::slow_query::SlowQueryEntry;
+use crate::types::{Doc, FieldType, Schema, SearchResults};
+
+/// Column holding the document ID, always first
+pub const ID_COLUMN: &str = "id";
+
+/// Column holding the hit score in [`hits_to_batch`]
+pub const SCORE_COLUMN: &str = "_score";
+
+/// Column type of one document field
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ColumnKind {
+    Text,
+    Integer,
+    Float,
+    Boolean,
+    Timestamp,
+    Json,
+}
+
+impl ColumnKind {
+    fn from_field_type(field_type: &FieldType) -> Self {
+        match field_type {
+            // Decimals stay text, as a float column would round them
+            FieldType::Text | FieldType::Keyword | FieldType::Decimal => ColumnKind::Text,
+            FieldType::Integer | FieldType::Int64 => ColumnKind::Integer,
+            FieldType::Float => ColumnKind::Float,
+            FieldType::Boolean => ColumnKind::Boolean,
+            FieldType::Date => ColumnKind::Timestamp,
+            FieldType::GeoPoint => ColumnKind::Json,
+        }
+    }
+
+    /// The narrowest kind holding every non-null value
+    fn infer<'a>(values: impl Iterator<Item = &'a Value>) -> Self {
+        let mut kind = None;
+        for value in values {
+            let this = match value {
+                Value::Null => continue,
+                Value::String(_) => ColumnKind::Text,
+                Value::Bool(_) => ColumnKind::Boolean,
+                Value::Number(n) if n.is_i64() => ColumnKind::Integer,
+                Value::Number(_) => ColumnKind::Float,
+                Value::Array(_) | Value::Object(_) => ColumnKind::Json,
+            };
+            kind = Some(match (kind, this) {
+                (None, this) => this,
+                (Some(k), this) if k == this => k,
+                (Some(ColumnKind::Integer), ColumnKind::Float) | (Some(ColumnKind::Float), ColumnKind::Integer) => {
+                    ColumnKind::Float
+                }
+                _ => ColumnKind::Json,
+            });
+        }
+        kind.unwrap_or(ColumnKind::Text)
+    }
+
+    fn accepts(self, value: &Value) -> bool {
+        match self {
+            ColumnKind::Text => value.is_string(),
+            ColumnKind::Integer => value.is_i64(),
+            ColumnKind::Float => value.is_number(),
+            ColumnKind::Boolean => value.is_boolean(),
+            ColumnKind::Timestamp => timestamp_millis(value).is_some(),
+            ColumnKind::Json => true,
+        }
+    }
+
+    fn data_type(self) -> DataType {
+        match self {
+            ColumnKind::Text | ColumnKind::Json => DataType::Utf8,
+            ColumnKind::Integer => DataType::Int64,
+            ColumnKind::Float => DataType::Float64,
+            ColumnKind::Boolean => DataType::Boolean,
+            ColumnKind::Timestamp => DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into())),
+        }
+    }
+
+    /// Build the column from one optional value per row
+    fn build<'a>(self, values: impl Iterator<Item = Option<&'a Value>>) -> ArrayRef {
+        match self {
+            ColumnKind::Text | ColumnKind::Json => {
+                let mut builder = StringBuilder::new();
+                for value in values {
+                    builder.append_option(value.filter(|v| !v.is_null()).map(|v| match v {
+                        Value::String(s) => s.clone(),
+                        other => other.to_string(),
+                    }));
+                }
+                Arc::new(builder.finish())
+            }
+            ColumnKind::Integer => {
+                let mut builder = Int64Builder::new();
+                for value in values {
+                    builder.append_option(value.and_then(Value::as_i64));
+                }
+                Arc::new(builder.finish())
+            }
+            ColumnKind::Float => {
+                let mut builder = Float64Builder::new();
+                for value in values {
+                    builder.append_option(value.and_then(Value::as_f64));
+                }
+                Arc::new(builder.finish())
+            }
+            ColumnKind::Boolean => {
+                let mut builder = BooleanBuilder::new();
+                for value in values {
+                    builder.append_option(value.and_then(Value::as_bool));
+                }
+                Arc::new(builder.finish())
+            }
+            ColumnKind::Timestamp => {
+                let mut builder = TimestampMillisecondBuilder::new().with_timezone("UTC");
+                for value in values {
+                    builder.append_option(value.and_then(timestamp_millis));
+                }
+                Arc::new(builder.finish())
+            }
+        }
+    }
+}
+
+/// Milliseconds since the epoch of an RFC 3339 timestamp, a `YYYY-MM-DD` date or an epoch-millisecond number
+fn timestamp_millis(value: &Value) -> Option<i64> {
+    match value {
+        Value::Number(n) => n.as_i64(),
+        Value::String(s) => DateTime::parse_from_rfc3339(s)
+            .map(|t| t.timestamp_millis())
+            .ok()
+            .or_else(|| {
+                let date = NaiveDate::parse_from_str(s, "%Y-%m-%d").ok()?;
+                Some(date.and_hms_opt(0, 0, 0)?.and_utc().timestamp_millis())
+            }),
+        _ => None,
+    }
+}
+
+fn parse_content(id: &str, content: &str) -> SearchResult<Map<String, Value>> {
+    match serde_json::from_str(content) {
+        Ok(Value::Object(fields)) => Ok(fields),
+        Ok(_) => Err(SearchError::invalid_query(format!("Document {} is not a JSON object", id))),
+        Err(e) => Err(SearchError::invalid_query(format!("Document {} has invalid JSON: {}", id, e))),
+    }
+}
+
+/// Rows of `(id, score, fields)` as a batch, with columns from `schema` or inferred
+fn rows_to_batch(
+    schema: Option<&Schema>,
+    rows: &[(String, Option<f64>, Map<String, Value>)],
+    with_score: bool,
+) -> SearchResult<RecordBatch> {
+    let columns: Vec<(String, ColumnKind)> = match schema {
+        Some(schema) => schema
+            .fields
+            .iter()
+            .filter(|f| f.name != ID_COLUMN)
+            .map(|f| (f.name.clone(), ColumnKind::from_field_type(&f.field_type)))
+            .collect(),
+        None => {
+            let names: BTreeSet<&str> = rows
+                .iter()
+                .flat_map(|(_, _, fields)| fields.keys().map(String::as_str))
+                .filter(|name| *name != ID_COLUMN)
+                .collect();
+            names
+                .into_iter()
+                .map(|name| (name.to_string(), ColumnKind::infer(rows.iter().filter_map(|(_, _, fields)| fields.get(name)))))
+                .collect()
+        }
+    };
+
+    let mut fields = vec![Field::new(ID_COLUMN, DataType::Utf8, false)];
+    let mut arrays: Vec<ArrayRef> = vec![Arc::new(StringArray::from_iter_values(rows.iter().map(|(id, _, _)| id)))];
+    if with_score {
+        fields.push(Field::new(SCORE_COLUMN, DataType::Float64, true));
+        arrays.push(Arc::new(Float64Array::from_iter(rows.iter().map(|(_, score, _)| *score))));
+    }
+    for (name, kind) in columns {
+        // Values that do not fit a schema-typed column are kept as JSON instead of nulled
+        let fits = rows.iter().filter_map(|(_, _, f)| f.get(&name)).all(|v| v.is_null() || kind.accepts(v));
+        let kind = if fits { kind } else { ColumnKind::Json };
+        fields.push(Field::new(name.clone(), kind.data_type(), true));
+        arrays.push(kind.build(rows.iter().map(|(_, _, f)| f.get(&name))));
+    }
+
+    RecordBatch::try_new(Arc::new(ArrowSchema::new(fields)), arrays)
+        .map_err(|e| SearchError::Internal(format!("Failed to build record batch: {}", e)))
+}
+
+/// Documents as a batch with an `id` column and one column per field
+pub fn documents_to_batch(schema: Option<&Schema>, documents: &[Doc]) -> SearchResult<RecordBatch> {
+    let rows = documents
+        .iter()
+        .map(|doc| Ok((doc.id.clone(), None, parse_content(&doc.id, &doc.content)?)))
+        .collect::<SearchResult<Vec<_>>>()?;
+    rows_to_batch(schema, &rows, false)
+}
+
+/// Hits of a result page as a batch, with a `_score` column after `id`
+///
+/// Hits returned without content only fill the `id` and `_score` columns.
+pub fn hits_to_batch(schema: Option<&Schema>, results: &SearchResults) -> SearchResult<RecordBatch> {
+    let rows = results
+        .hits
+        .iter()
+        .map(|hit| {
+            let fields = match &hit.content {
+                Some(content) => parse_content(&hit.id, content)?,
+                None => Map::new(),
+            };
+            Ok((hit.id.clone(), hit.score, fields))
+        })
+        .collect::<SearchResult<Vec<_>>>()?;
+    rows_to_batch(schema, &rows, true)
+}
+
+/// Slow query log entries as a batch, one row per query
+///
+/// The query text, filters and sort are only known for entries that kept the
+/// portable query; the native request parameters are always there as JSON.
+pub fn slow_queries_to_batch(entries: &[SlowQueryEntry]) -> SearchResult<RecordBatch> {
+    let json_list = |values: Option<&Vec<String>>| values.map(|v| Value::from(v.clone()).to_string());
+
+    let mut timestamps = TimestampMillisecondBuilder::new().with_timezone("UTC");
+    let mut durations = UInt64Builder::new();
+    let mut provider = StringBuilder::new();
+    let mut index = StringBuilder::new();
+    let mut q = StringBuilder::new();
+    let mut filters = StringBuilder::new();
+    let mut sort = StringBuilder::new();
+    let mut params = StringBuilder::new();
+    for entry in entries {
+        timestamps.append_option(DateTime::parse_from_rfc3339(&entry.timestamp).ok().map(|t| t.timestamp_millis()));
+        durations.append_value(entry.duration_ms);
+        provider.append_value(&entry.provider);
+        index.append_value(&entry.index);
+        let query = entry.query.as_ref();
+        q.append_option(query.and_then(|query| query.q.as_deref()));
+        filters.append_option(json_list(query.map(|query| &query.filters)));
+        sort.append_option(json_list(query.map(|query| &query.sort)));
+        params.append_value(entry.params.to_string());
+    }
+
+    let schema = ArrowSchema::new(vec![
+        Field::new("timestamp", DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into())), true),
+        Field::new("duration_ms", DataType::UInt64, false),
+        Field::new("provider", DataType::Utf8, false),
+        Field::new("index", DataType::Utf8, false),
+        Field::new("q", DataType::Utf8, true),
+        Field::new("filters", DataType::Utf8, true),
+        Field::new("sort", DataType::Utf8, true),
+        Field::new("params", DataType::Utf8, false),
+    ]);
+    let arrays: Vec<ArrayRef> = vec![
+        Arc::new(timestamps.finish()),
+        Arc::new(durations.finish()),
+        Arc::new(provider.finish()),
+        Arc::new(index.finish()),
+        Arc::new(q.finish()),
+        Arc::new(filters.finish()),
+        Arc::new(sort.finish()),
+        Arc::new(params.finish()),
+    ];
+    RecordBatch::try_new(Arc::new(schema), arrays)
+        .map_err(|e| SearchError::Internal(format!("Failed to build record batch: {}", e)))
+}
+
+/// Encode `batches`, which must share a schema, as one Snappy-compressed Parquet file
+pub fn to_parquet(batches: &[RecordBatch]) -> SearchResult<Vec<u8>> {
+    let Some(first) = batches.first() else {
+        return Err(SearchError::invalid_query("Nothing to export: no record batches"));
+    };
+    let properties = WriterProperties::builder().set_compression(Compression::SNAPPY).build();
+    let parquet_error = |e: parquet::errors::ParquetError| SearchError::Internal(format!("Failed to write Parquet: {}", e));
+
+    let mut writer = ArrowWriter::try_new(Vec::new(), first.schema(), Some(properties)).map_err(parquet_error)?;
+    for batch in batches {
+        writer.write(batch).map_err(parquet_error)?;
+    }
+    writer.into_inner().map_err(parquet_error)
+}
+
+/// Write `batches` to `sink` under `key` as a Parquet file and return where it was stored
+pub fn write_parquet(sink: &dyn BlobSink, key: &str, batches: &[RecordBatch]) -> SearchResult<String> {
+    sink.put(key, &to_parquet(batches)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blob::FsBlobSink;
+    use crate::types::SchemaBuilder;
+    use arrow_array::cast::AsArray;
+    use arrow_array::Array;
+    use arrow_array::types::{Float64Type, Int64Type, TimestampMillisecondType};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    fn doc(id: &str, content: &str) -> Doc {
+        Doc { id: id.to_string(), content: content.to_string() }
+    }
+
+    fn names(batch: &RecordBatch) -> Vec<String> {
+        batch.schema().fields().iter().map(|f| f.name().clone()).collect()
+    }
+
+    fn column<'a>(batch: &'a RecordBatch, name: &str) -> &'a ArrayRef {
+        batch.column_by_name(name).unwrap()
+    }
+
+    fn text(batch: &RecordBatch, name: &str) -> Vec<Option<String>> {
+        column(batch, name).as_string::<i32>().iter().map(|v| v.map(str::to_string)).collect()
+    }
+
+    #[test]
+    fn test_inferred_columns_are_sorted_after_the_id_and_take_the_narrowest_type() {
+        let documents = vec![
+            doc("1", r#"{"title": "Dune", "year": 1965, "price": 9, "in_print": true, "id": "ignored"}"#),
+            doc("2", r#"{"title": "Emma", "year": "1815", "price": 7.5, "in_print": false}"#),
+        ];
+        let batch = documents_to_batch(None, &documents).unwrap();
+
+        assert_eq!(names(&batch), ["id", "in_print", "price", "title", "year"]);
+        assert_eq!(text(&batch, "id"), [Some("1".to_string()), Some("2".to_string())]);
+        assert_eq!(column(&batch, "in_print").data_type(), &DataType::Boolean);
+        let price = column(&batch, "price").as_primitive::<Float64Type>();
+        assert_eq!((price.value(0), price.value(1)), (9.0, 7.5), "integers widen to floats");
+        assert_eq!(text(&batch, "year"), [Some("1965".to_string()), Some("1815".to_string())], "mixed types become JSON text");
+    }
+
+    #[test]
+    fn test_nested_values_become_json_text_and_missing_fields_nulls() {
+        let documents = vec![doc("1", r#"{"tags": ["classic"], "meta": {"a": 1}}"#), doc("2", r#"{"meta": null}"#)];
+        let batch = documents_to_batch(None, &documents).unwrap();
+        assert_eq!(text(&batch, "tags"), [Some(r#"["classic"]"#.to_string()), None]);
+        assert_eq!(text(&batch, "meta"), [Some(r#"{"a":1}"#.to_string()), None]);
+
+        let only_nulls = documents_to_batch(None, &[doc("1", r#"{"note": null}"#)]).unwrap();
+        assert_eq!(column(&only_nulls, "note").data_type(), &DataType::Utf8);
+        assert!(column(&only_nulls, "note").is_null(0));
+    }
+
+    #[test]
+    fn test_schema_columns_keep_their_order_and_type_and_drop_other_fields() {
+        let schema = SchemaBuilder::new().keyword_field("id").text_field("title").integer_field("year").build();
+        let documents = vec![doc("1", r#"{"title": "Dune", "year": 1965, "extra": true}"#), doc("2", r#"{"year": 1815}"#)];
+        let batch = documents_to_batch(Some(&schema), &documents).unwrap();
+
+        assert_eq!(names(&batch), ["id", "title", "year"]);
+        assert_eq!(column(&batch, "year").as_primitive::<Int64Type>().value(1), 1815);
+        assert_eq!(text(&batch, "title"), [Some("Dune".to_string()), None]);
+    }
+
+    #[test]
+    fn test_values_that_do_not_fit_their_schema_type_are_kept_as_json() {
+        let schema = SchemaBuilder::new().integer_field("price").date_field("published").build();
+        let documents = vec![
+            doc("1", r#"{"price": 9, "published": "1965-08-01"}"#),
+            doc("2", r#"{"price": 7.5, "published": "soon"}"#),
+        ];
+        let batch = documents_to_batch(Some(&schema), &documents).unwrap();
+        assert_eq!(text(&batch, "price"), [Some("9".to_string()), Some("7.5".to_string())]);
+        assert_eq!(text(&batch, "published"), [Some("1965-08-01".to_string()), Some("soon".to_string())]);
+    }
+
+    #[test]
+    fn test_dates_are_read_from_rfc3339_plain_dates_and_epoch_milliseconds() {
+        let schema = SchemaBuilder::new().date_field("published").build();
+        let documents = vec![
+            doc("1", r#"{"published": "1965-08-01"}"#),
+            doc("2", r#"{"published": "1965-08-01T02:00:00+02:00"}"#),
+            doc("3", r#"{"published": -139449600000}"#),
+            doc("4", "{}"),
+        ];
+        let batch = documents_to_batch(Some(&schema), &documents).unwrap();
+        let published = column(&batch, "published").as_primitive::<TimestampMillisecondType>();
+        let day = Some(-139_449_600_000);
+        assert_eq!(published.iter().collect::<Vec<_>>(), [day, day, day, None]);
+        assert_eq!(published.timezone(), Some("UTC"));
+    }
+
+    #[test]
+    fn test_documents_must_be_json_objects() {
+        assert!(matches!(documents_to_batch(None, &[doc("3", "[1]")]), Err(SearchError::InvalidQuery(_))));
+        assert!(matches!(documents_to_batch(None, &[doc("3", "{")]), Err(SearchError::InvalidQuery(_))));
+    }
+
+    #[test]
+    fn test_hits_without_content_only_fill_the_id_and_score() {
+        let results: SearchResults = serde_json::from_value(serde_json::json!({
+            "hits": [
+                { "id": "1", "score": 1.5, "content": r#"{"title": "Dune"}"# },
+                { "id": "2" }
+            ]
+        }))
+        .unwrap();
+        let batch = hits_to_batch(None, &results).unwrap();
+        assert_eq!(names(&batch), ["id", SCORE_COLUMN, "title"]);
+        let score = column(&batch, SCORE_COLUMN).as_primitive::<Float64Type>();
+        assert_eq!(score.iter().collect::<Vec<_>>(), [Some(1.5), None]);
+        assert_eq!(text(&batch, "title"), [Some("Dune".to_string()), None]);
+    }
+
+    #[test]
+    fn test_slow_queries_without_a_portable_query_leave_its_columns_null() {
+        let entry = SlowQueryEntry {
+            provider: "typesense".to_string(),
+            index: "books".to_string(),
+            params: serde_json::json!({ "q": "dune" }),
+            duration_ms: 1500,
+            timestamp: "not a time".to_string(),
+            query: None,
+        };
+        let batch = slow_queries_to_batch(&[entry]).unwrap();
+        for name in ["timestamp", "q", "filters", "sort"] {
+            assert!(column(&batch, name).is_null(0), "{}", name);
+        }
+        assert_eq!(text(&batch, "params"), [Some(r#"{"q":"dune"}"#.to_string())]);
+    }
+
+    #[test]
+    fn test_parquet_round_trip_of_several_batches() {
+        let root = std::env::temp_dir().join(format!("golem-search-columnar-{}", uuid::Uuid::new_v4()));
+        let sink = FsBlobSink::new(&root);
+        let documents: Vec<Doc> = (0..3).map(|i| doc(&i.to_string(), &format!(r#"{{"rank": {}}}"#, i))).collect();
+        let batch = documents_to_batch(None, &documents).unwrap();
+
+        write_parquet(&sink, "books.parquet", &[batch.clone(), batch]).unwrap();
+        let file = std::fs::File::open(root.join("books.parquet")).unwrap();
+        let reader = ParquetRecordBatchReaderBuilder::try_new(file).unwrap().build().unwrap();
+        let batches: Vec<RecordBatch> = reader.map(Result::unwrap).collect();
+        assert_eq!(batches.iter().map(RecordBatch::num_rows).sum::<usize>(), 6);
+        assert_eq!(batches[0].column(1).as_primitive::<Int64Type>().value(2), 2);
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_parquet_needs_batches_of_one_schema() {
+        assert!(matches!(to_parquet(&[]), Err(SearchError::InvalidQuery(_))));
+        let ranks = documents_to_batch(None, &[doc("1", r#"{"rank": 1}"#)]).unwrap();
+        let titles = documents_to_batch(None, &[doc("1", r#"{"title": "Dune"}"#)]).unwrap();
+        assert!(matches!(to_parquet(&[ranks, titles]), Err(SearchError::Internal(_))));
+    }
+}
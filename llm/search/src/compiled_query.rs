@@ -0,0 +1,246 @@
+//! Reuse of compiled provider queries across query texts
+//!
+//! Instant search sends a query per keystroke, and those queries differ only
+//! in their text. Converting each one runs the whole pipeline again: filters,
+//! sorts, facets, highlighting and provider params are parsed and translated
+//! although none of them changed. [`compile`] converts a query once with a
+//! placeholder for its text and keeps the result in the shared
+//! [`MemoryAccountant`](crate::cache::MemoryAccountant) under
+//! [`CacheKind::CompiledQuery`]; later queries of the same shape only put
+//! their text where the placeholder was. Hits and misses are counted with the
+//! other cache lookups in the Prometheus metrics.
+//!
+//! Only plain text is reused this way: words of letters and digits without
+//! operators, and no normalization, which rewrites the text before it reaches
+//! the conversion. A shape whose conversion does not copy the text through
+//! unchanged, such as quoting each word for exact matching, is remembered as
+//! such and always converted in full.
+
+use std::fmt::Debug;
+use serde_json::Value;
+use crate::cache::{self, CacheKind};
+use crate::config::env_helpers::get_env_parsed;
+use crate::query::ParsedQuery;
+use crate::types::{QueryText, SearchQuery};
+
+/// Environment variable turning the compiled query cache off with `false`
+pub const COMPILED_QUERY_CACHE_ENV: &str = "SEARCH_COMPILED_QUERY_CACHE";
+
+/// Stand-in for the query text in a cached template; a plain word so every conversion keeps it as is
+const PLACEHOLDER: &str = "golemcompiledquerytext";
+
+/// A provider's query, as far as reusing its conversion goes
+pub trait TemplateQuery: Debug + Clone {
+    /// Query text, or `None` when the conversion of the query depends on more than where its text goes
+    fn template_text(&self) -> Option<&str>;
+
+    /// The query with `text` in place of its text
+    fn with_text(&self, text: &str) -> Self;
+}
+
+/// A converted provider query
+pub trait QueryTemplate: Clone + Send + Sync + 'static {
+    /// Replace every value equal to `placeholder` with `text`, returning how many were replaced
+    fn fill(&mut self, placeholder: &str, text: &str) -> usize;
+
+    /// Estimated size in bytes
+    fn size(&self) -> usize;
+}
+
+impl QueryTemplate for Value {
+    fn fill(&mut self, placeholder: &str, text: &str) -> usize {
+        match self {
+            Value::String(value) if value == placeholder => {
+                *value = text.to_string();
+                1
+            }
+            Value::Array(items) => items.iter_mut().map(|item| item.fill(placeholder, text)).sum(),
+            Value::Object(map) => map.values_mut().map(|value| value.fill(placeholder, text)).sum(),
+            _ => 0,
+        }
+    }
+
+    fn size(&self) -> usize {
+        self.to_string().len()
+    }
+}
+
+/// URL parameters, as Typesense searches take them
+impl QueryTemplate for Vec<(&'static str, String)> {
+    fn fill(&mut self, placeholder: &str, text: &str) -> usize {
+        self.iter_mut()
+            .filter(|(_, value)| value == placeholder)
+            .map(|(_, value)| *value = text.to_string())
+            .count()
+    }
+
+    fn size(&self) -> usize {
+        self.iter().map(|(key, value)| key.len() + value.len()).sum()
+    }
+}
+
+impl TemplateQuery for SearchQuery {
+    fn template_text(&self) -> Option<&str> {
+        // Samples are scored with a fresh random seed each time
+        let config = self.config.as_ref();
+        if config.is_some_and(|c| c.normalization.is_some() || c.sample.is_some()) {
+            return None;
+        }
+        match self.text() {
+            QueryText::Text(text) => Some(text),
+            QueryText::MatchAll => None,
+        }
+    }
+
+    fn with_text(&self, text: &str) -> Self {
+        Self { q: Some(text.to_string()), ..self.clone() }
+    }
+}
+
+pub fn is_enabled() -> bool {
+    get_env_parsed::<bool>(COMPILED_QUERY_CACHE_ENV).ok().flatten().unwrap_or(true)
+}
+
+/// Whether `text` goes through every conversion unchanged
+fn is_plain(text: &str) -> bool {
+    text.chars().all(|c| c.is_alphanumeric() || c.is_whitespace()) && !ParsedQuery::parse(text).has_operators()
+}
+
+/// Convert `query` for a search of `index`, reusing the conversion of an earlier query of the same shape
+///
+/// Conversions that fail are not cached; the query is converted in full to
+/// report its own error.
+pub fn compile<Q, T, E>(provider: &str, index: &str, query: &Q, convert: impl Fn(&Q) -> Result<T, E>) -> Result<T, E>
+where
+    Q: TemplateQuery,
+    T: QueryTemplate,
+{
+    let Some(text) = query.template_text().filter(|text| is_plain(text)).filter(|_| is_enabled()) else {
+        return convert(query);
+    };
+    let shape = query.with_text(PLACEHOLDER);
+    let key = cache::result_key(provider, index, &shape);
+
+    // `None` marks a shape whose conversion does not copy the text through
+    if let Some(cached) = cache::global().get::<Option<T>>(CacheKind::CompiledQuery, &key) {
+        return match cached.as_ref() {
+            Some(template) => {
+                let mut compiled = template.clone();
+                compiled.fill(PLACEHOLDER, text);
+                Ok(compiled)
+            }
+            None => convert(query),
+        };
+    }
+
+    let Ok(template) = convert(&shape) else {
+        return convert(query);
+    };
+    let mut compiled = template.clone();
+    if compiled.fill(PLACEHOLDER, text) == 0 {
+        cache::global().insert(CacheKind::CompiledQuery, &key, None::<T>, 0);
+        return convert(query);
+    }
+    let size = template.size();
+    cache::global().insert(CacheKind::CompiledQuery, &key, Some(template), size);
+    Ok(compiled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::cell::Cell;
+    use crate::types::QueryBuilder;
+
+    fn convert(query: &SearchQuery) -> Result<Value, String> {
+        Ok(json!({ "query": { "multi_match": { "query": query.q } }, "filter": query.filters }))
+    }
+
+    /// Compile `query` for `index`, counting the conversions it took
+    fn compile_counted(index: &str, query: &SearchQuery, convert: impl Fn(&SearchQuery) -> Result<Value, String>) -> (Result<Value, String>, usize) {
+        let conversions = Cell::new(0);
+        let result = compile("test", index, query, |query: &SearchQuery| {
+            conversions.set(conversions.get() + 1);
+            convert(query)
+        });
+        (result, conversions.get())
+    }
+
+    fn text_query(q: &str) -> SearchQuery {
+        QueryBuilder::new().query(q).filter("shape:compiled").build()
+    }
+
+    #[test]
+    fn test_queries_differing_only_in_plain_text_share_one_conversion() {
+        let (first, conversions) = compile_counted("compiled-reuse", &text_query("red shoes"), convert);
+        assert_eq!((first.unwrap(), conversions), (convert(&text_query("red shoes")).unwrap(), 1));
+
+        let (second, conversions) = compile_counted("compiled-reuse", &text_query("blue  hats"), convert);
+        assert_eq!(second.unwrap()["query"]["multi_match"]["query"], json!("blue  hats"));
+        assert_eq!(conversions, 0);
+    }
+
+    #[test]
+    fn test_templates_are_kept_per_index_and_shape() {
+        compile_counted("compiled-shape", &text_query("red"), convert).0.unwrap();
+        let other_filter = QueryBuilder::new().query("red").filter("shape:other").build();
+        assert_eq!(compile_counted("compiled-shape", &other_filter, convert).1, 1);
+        assert_eq!(compile_counted("compiled-shape-2", &text_query("red"), convert).1, 1);
+        assert_eq!(compile_counted("compiled-shape", &text_query("green"), convert).1, 0);
+    }
+
+    #[test]
+    fn test_text_with_operators_or_punctuation_is_always_converted_in_full() {
+        for text in ["red -shoes", "+red", "\"red shoes\"", "c++", "title:red", "sho*"] {
+            for _ in 0..2 {
+                let (result, conversions) = compile_counted("compiled-operators", &text_query(text), convert);
+                assert_eq!(result.unwrap()["query"]["multi_match"]["query"], json!(text));
+                assert_eq!(conversions, 1, "{}", text);
+            }
+        }
+    }
+
+    #[test]
+    fn test_match_all_sampled_and_normalized_queries_are_not_templated() {
+        let match_all = QueryBuilder::new().match_all().filter("shape:compiled").build();
+        let mut sampled = text_query("red");
+        sampled.config = Some(crate::types::SearchConfig { sample: Some(10), ..Default::default() });
+        for query in [&match_all, &sampled] {
+            assert!(query.template_text().is_none());
+            for _ in 0..2 {
+                assert_eq!(compile_counted("compiled-untemplated", query, convert).1, 1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_shapes_that_rewrite_the_text_are_remembered_and_converted_in_full() {
+        let quoting = |query: &SearchQuery| Ok(json!({ "q": format!("\"{}\"", query.q.as_deref().unwrap_or_default()) }));
+
+        let (first, conversions) = compile_counted("compiled-quoting", &text_query("red"), quoting);
+        assert_eq!((first.unwrap(), conversions), (json!({ "q": "\"red\"" }), 2));
+        let (second, conversions) = compile_counted("compiled-quoting", &text_query("blue"), quoting);
+        assert_eq!((second.unwrap(), conversions), (json!({ "q": "\"blue\"" }), 1));
+    }
+
+    #[test]
+    fn test_failed_conversions_are_not_cached_and_report_the_query_itself() {
+        let failing = |query: &SearchQuery| Err(format!("cannot convert {}", query.q.as_deref().unwrap_or_default()));
+        let (result, conversions) = compile_counted("compiled-failing", &text_query("red"), failing);
+        assert_eq!((result, conversions), (Err("cannot convert red".to_string()), 2));
+
+        assert_eq!(compile_counted("compiled-failing", &text_query("red"), convert).1, 1);
+    }
+
+    #[test]
+    fn test_fill_replaces_whole_values_only() {
+        let mut template = json!({ "q": PLACEHOLDER, "fields": [PLACEHOLDER, format!("\"{}\"", PLACEHOLDER)] });
+        assert_eq!(template.fill(PLACEHOLDER, "shoes"), 2);
+        assert_eq!(template, json!({ "q": "shoes", "fields": ["shoes", format!("\"{}\"", PLACEHOLDER)] }));
+
+        let mut params = vec![("q", PLACEHOLDER.to_string()), ("query_by", "*".to_string())];
+        assert_eq!(params.fill(PLACEHOLDER, "shoes"), 1);
+        assert_eq!(params[0].1, "shoes");
+    }
+}
@@ -0,0 +1,273 @@
+//! Compression of bulk request bodies and of responses
+//!
+//! Writing thousands of documents sends bodies of many megabytes, which
+//! compress several times over. [`COMPRESSION_ENV`] picks `gzip` or `deflate`
+//! for the bodies of the bulk writes: `_bulk` in Elasticsearch and OpenSearch,
+//! document additions in Meilisearch and imports in Typesense. They are sent
+//! with a `Content-Encoding` header. Clients with compression on also ask for
+//! compressed responses with `Accept-Encoding`, and the HTTP client decodes a
+//! compressed response before it is read (see [`crate::http`]).
+//!
+//! Without the variable, `SEARCH_BULK_GZIP` still turns on gzip.
+
+use std::io::{self, Read, Write};
+use flate2::read::{DeflateDecoder, GzDecoder, ZlibDecoder};
+use flate2::write::{GzEncoder, ZlibEncoder};
+use crate::config::env_helpers::get_env_parsed;
+use crate::error::{SearchError, SearchResult};
+use crate::ndjson;
+
+/// Environment variable selecting the compression of bulk bodies (`none`, `gzip` or `deflate`)
+pub const COMPRESSION_ENV: &str = "SEARCH_PROVIDER_COMPRESSION";
+
+/// `Accept-Encoding` of clients with compression on
+pub const ACCEPTED_ENCODINGS: &str = "gzip, deflate";
+
+/// Content coding of request bodies
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Compression {
+    #[default]
+    None,
+    Gzip,
+
+    /// The zlib format, which HTTP names `deflate`
+    Deflate,
+}
+
+impl std::str::FromStr for Compression {
+    type Err = SearchError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "none" | "off" => Ok(Self::None),
+            "gzip" | "x-gzip" => Ok(Self::Gzip),
+            "deflate" => Ok(Self::Deflate),
+            other => Err(SearchError::invalid_query(format!("Unknown compression: {}", other))),
+        }
+    }
+}
+
+impl Compression {
+    /// Compression from `SEARCH_PROVIDER_COMPRESSION`, or gzip when only `SEARCH_BULK_GZIP` is set
+    pub fn from_env() -> Self {
+        get_env_parsed::<Compression>(COMPRESSION_ENV)
+            .ok()
+            .flatten()
+            .unwrap_or_else(|| if ndjson::gzip_from_env() { Self::Gzip } else { Self::None })
+    }
+
+    pub fn is_enabled(self) -> bool {
+        self != Self::None
+    }
+
+    /// Value for the `Content-Encoding` header, if any
+    pub fn content_encoding(self) -> Option<&'static str> {
+        match self {
+            Self::None => None,
+            Self::Gzip => Some("gzip"),
+            Self::Deflate => Some("deflate"),
+        }
+    }
+
+    /// Writer compressing into a buffer
+    pub fn encoder(self) -> Encoder {
+        let level = flate2::Compression::fast();
+        match self {
+            Self::None => Encoder::Plain(Vec::new()),
+            Self::Gzip => Encoder::Gzip(GzEncoder::new(Vec::new(), level)),
+            Self::Deflate => Encoder::Deflate(ZlibEncoder::new(Vec::new(), level)),
+        }
+    }
+
+    /// `bytes` compressed, or as they are without compression
+    pub fn compress(self, bytes: Vec<u8>) -> SearchResult<Vec<u8>> {
+        if !self.is_enabled() {
+            return Ok(bytes);
+        }
+        let mut encoder = self.encoder();
+        encoder
+            .write_all(&bytes)
+            .and_then(|_| encoder.finish())
+            .map_err(|e| SearchError::internal(format!("Failed to compress request body: {}", e)))
+    }
+
+    /// A body sent with this coding, decoded
+    ///
+    /// Some servers send raw deflate data for `deflate`, so it is read when the
+    /// body is not in the zlib format.
+    pub fn decompress(self, bytes: &[u8]) -> io::Result<Vec<u8>> {
+        let mut decoded = Vec::new();
+        match self {
+            Self::None => decoded.extend_from_slice(bytes),
+            Self::Gzip => {
+                GzDecoder::new(bytes).read_to_end(&mut decoded)?;
+            }
+            Self::Deflate => {
+                if ZlibDecoder::new(bytes).read_to_end(&mut decoded).is_err() {
+                    decoded.clear();
+                    DeflateDecoder::new(bytes).read_to_end(&mut decoded)?;
+                }
+            }
+        }
+        Ok(decoded)
+    }
+}
+
+/// Writer into a buffer, through the encoder of a [`Compression`]
+pub enum Encoder {
+    Plain(Vec<u8>),
+    Gzip(GzEncoder<Vec<u8>>),
+    Deflate(ZlibEncoder<Vec<u8>>),
+}
+
+impl Encoder {
+    /// Finish encoding and return the buffer
+    pub fn finish(self) -> io::Result<Vec<u8>> {
+        match self {
+            Self::Plain(bytes) => Ok(bytes),
+            Self::Gzip(encoder) => encoder.finish(),
+            Self::Deflate(encoder) => encoder.finish(),
+        }
+    }
+}
+
+impl Write for Encoder {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Plain(bytes) => bytes.write(buf),
+            Self::Gzip(encoder) => encoder.write(buf),
+            Self::Deflate(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Plain(_) => Ok(()),
+            Self::Gzip(encoder) => encoder.flush(),
+            Self::Deflate(encoder) => encoder.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ndjson::BULK_GZIP_ENV;
+
+    const ALL: [Compression; 3] = [Compression::None, Compression::Gzip, Compression::Deflate];
+
+    fn body() -> Vec<u8> {
+        br#"{"id":"1","name":"Widget"}"#.repeat(100)
+    }
+
+    #[test]
+    fn test_settings_ignore_case_and_accept_aliases() {
+        for (setting, expected) in [
+            (" GZIP ", Compression::Gzip),
+            ("x-gzip", Compression::Gzip),
+            ("Deflate", Compression::Deflate),
+            ("off", Compression::None),
+            ("NONE", Compression::None),
+        ] {
+            assert_eq!(setting.parse::<Compression>().unwrap(), expected, "{}", setting);
+        }
+        match "br".parse::<Compression>() {
+            Err(SearchError::InvalidQuery(message)) => assert_eq!(message, "Unknown compression: br"),
+            other => panic!("expected an invalid query error, got {:?}", other),
+        }
+        assert!("".parse::<Compression>().is_err());
+    }
+
+    #[test]
+    fn test_content_encodings_parse_back_to_the_same_compression() {
+        assert_eq!(Compression::None.content_encoding(), None);
+        for compression in [Compression::Gzip, Compression::Deflate] {
+            let encoding = compression.content_encoding().unwrap();
+            assert_eq!(encoding.parse::<Compression>().unwrap(), compression);
+            assert!(ACCEPTED_ENCODINGS.contains(encoding));
+        }
+    }
+
+    #[test]
+    fn test_compression_setting_takes_precedence_over_bulk_gzip() {
+        std::env::remove_var(COMPRESSION_ENV);
+        std::env::remove_var(BULK_GZIP_ENV);
+        assert_eq!(Compression::from_env(), Compression::None);
+
+        std::env::set_var(BULK_GZIP_ENV, "true");
+        assert_eq!(Compression::from_env(), Compression::Gzip);
+
+        std::env::set_var(COMPRESSION_ENV, "deflate");
+        assert_eq!(Compression::from_env(), Compression::Deflate);
+        std::env::set_var(COMPRESSION_ENV, "none");
+        assert_eq!(Compression::from_env(), Compression::None, "an explicit none turns bulk gzip off");
+
+        std::env::set_var(COMPRESSION_ENV, "brotli");
+        assert_eq!(Compression::from_env(), Compression::Gzip, "an unknown setting falls back to bulk gzip");
+        std::env::set_var(BULK_GZIP_ENV, "yes");
+        assert_eq!(Compression::from_env(), Compression::None);
+
+        std::env::remove_var(COMPRESSION_ENV);
+        std::env::remove_var(BULK_GZIP_ENV);
+    }
+
+    #[test]
+    fn test_uncompressed_bodies_are_passed_through() {
+        let body = body();
+        assert_eq!(Compression::None.compress(body.clone()).unwrap(), body);
+        assert_eq!(Compression::None.decompress(b"\x1f\x8bnot gzip").unwrap(), b"\x1f\x8bnot gzip");
+        assert!(!Compression::None.is_enabled());
+    }
+
+    #[test]
+    fn test_compressed_bodies_round_trip_in_their_format() {
+        let body = body();
+        let gzip = Compression::Gzip.compress(body.clone()).unwrap();
+        assert_eq!(gzip[..2], [0x1f, 0x8b]);
+        let zlib = Compression::Deflate.compress(body.clone()).unwrap();
+        assert_eq!(zlib[0], 0x78, "deflate bodies are sent in the zlib format");
+
+        for (compression, compressed) in [(Compression::Gzip, gzip), (Compression::Deflate, zlib)] {
+            assert!(compressed.len() < body.len() / 10, "{:?}", compression);
+            assert_eq!(compression.decompress(&compressed).unwrap(), body, "{:?}", compression);
+        }
+    }
+
+    #[test]
+    fn test_raw_deflate_responses_are_decoded() {
+        let mut raw = flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::fast());
+        raw.write_all(&body()).unwrap();
+        assert_eq!(Compression::Deflate.decompress(&raw.finish().unwrap()).unwrap(), body());
+    }
+
+    #[test]
+    fn test_truncated_or_mislabelled_bodies_are_errors() {
+        let gzip = Compression::Gzip.compress(body()).unwrap();
+        assert!(Compression::Gzip.decompress(&gzip[..gzip.len() / 2]).is_err());
+        assert!(Compression::Gzip.decompress(b"not gzip").is_err());
+        assert!(Compression::Gzip.decompress(&Compression::Deflate.compress(body()).unwrap()).is_err());
+        assert!(Compression::Deflate.decompress(&gzip).is_err());
+    }
+
+    #[test]
+    fn test_empty_bodies_round_trip() {
+        for compression in ALL {
+            let compressed = compression.compress(Vec::new()).unwrap();
+            assert_eq!(compressed.is_empty(), !compression.is_enabled(), "{:?}", compression);
+            assert!(compression.decompress(&compressed).unwrap().is_empty(), "{:?}", compression);
+        }
+    }
+
+    #[test]
+    fn test_encoders_join_separate_writes_into_one_body() {
+        for compression in ALL {
+            let mut encoder = compression.encoder();
+            for line in ["{\"id\":\"1\"}\n", "{\"id\":\"2\"}\n", "{\"id\":\"3\"}\n"] {
+                encoder.write_all(line.as_bytes()).unwrap();
+                encoder.flush().unwrap();
+            }
+            let decoded = compression.decompress(&encoder.finish().unwrap()).unwrap();
+            assert_eq!(decoded, b"{\"id\":\"1\"}\n{\"id\":\"2\"}\n{\"id\":\"3\"}\n", "{:?}", compression);
+        }
+    }
+}
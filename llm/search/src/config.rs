@@ -2,11 +2,22 @@
 //!
 //! This module provides utilities for loading configuration from environment
 //! variables and managing provider-specific settings.
+//!
+//! A deployment can keep several named profiles (`dev`, `staging`, `prod`)
+//! side by side as `SEARCH_PROFILE_<NAME>_<FIELD>` variables and pick one with
+//! `SEARCH_PROFILE`. The selected [`ConfigProfile`] replaces the endpoint,
+//! credential and timeout variables the providers otherwise read.
 
 use std::env;
 use std::time::Duration;
 use serde::{Deserialize, Serialize};
+use crate::cache::CachePolicy;
+use crate::capabilities::DegradationStrategy;
 use crate::error::{SearchError, SearchResult};
+use crate::quota::QuotaConfig;
+
+/// Environment variable naming the active configuration profile
+pub const SEARCH_PROFILE_ENV: &str = "SEARCH_PROFILE";
 
 /// Common configuration for all search providers
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,6 +36,10 @@ pub struct SearchConfig {
     
     /// Provider-specific configuration
     pub provider_config: ProviderConfig,
+    
+    /// Per-tenant query and indexing quotas
+    #[serde(default)]
+    pub quotas: QuotaConfig,
 }
 
 /// Provider-specific configuration variants
@@ -60,6 +75,17 @@ pub enum ProviderConfig {
 impl SearchConfig {
     /// Load configuration from environment variables for the specified provider
     pub fn from_env(provider: &str) -> SearchResult<Self> {
+        if let Some(profile) = ConfigProfile::active(provider)? {
+            return Ok(SearchConfig {
+                endpoint: Some(profile.endpoint.clone()),
+                timeout: profile.timeout,
+                max_retries: profile.max_retries,
+                log_level: env::var("SEARCH_PROVIDER_LOG_LEVEL").unwrap_or_else(|_| "info".to_string()),
+                provider_config: profile.provider_config(provider)?,
+                quotas: QuotaConfig::from_env(),
+            });
+        }
+
         let endpoint = env::var("SEARCH_PROVIDER_ENDPOINT").ok();
         
         let timeout = env::var("SEARCH_PROVIDER_TIMEOUT")
@@ -90,6 +116,7 @@ impl SearchConfig {
             max_retries,
             log_level,
             provider_config,
+            quotas: QuotaConfig::from_env(),
         })
     }
     
@@ -198,6 +225,217 @@ impl SearchConfig {
     }
 }
 
+/// How a profile handles features the provider does not support
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DegradationMode {
+    /// Fall back and log a warning
+    #[default]
+    Lenient,
+
+    /// Fail queries that would need a fallback
+    Strict,
+
+    /// Fall back without logging
+    Quiet,
+}
+
+impl DegradationMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "lenient" => Some(Self::Lenient),
+            "strict" => Some(Self::Strict),
+            "quiet" => Some(Self::Quiet),
+            _ => None,
+        }
+    }
+
+    /// Adjust a provider's degradation strategy to this mode
+    pub fn apply(self, mut strategy: DegradationStrategy) -> DegradationStrategy {
+        strategy.strict_mode = self == Self::Strict;
+        strategy.log_unsupported_warnings = self != Self::Quiet;
+        strategy
+    }
+}
+
+/// Named bundle of connection and behaviour settings
+///
+/// Each field is read from `SEARCH_PROFILE_<NAME>_<FIELD>`, for example
+/// `SEARCH_PROFILE_PROD_ENDPOINT`. `ENDPOINT` and `TIMEOUT_SECS` are always
+/// required; Algolia also requires `APP_ID` and `API_KEY`, Typesense `API_KEY`.
+#[derive(Debug, Clone)]
+pub struct ConfigProfile {
+    pub name: String,
+
+    pub endpoint: String,
+
+    /// Credentials; providers ignore the ones they do not use
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub api_key: Option<String>,
+    pub app_id: Option<String>,
+
+    pub timeout: Duration,
+
+    /// Defaults to 3
+    pub max_retries: u32,
+
+    /// Default result cache policy from `CACHE_TTL_MS` and `CACHE_SWR_MS`, disabled when unset
+    pub cache_policy: CachePolicy,
+
+    /// `lenient` (default), `strict` or `quiet`
+    pub degradation: DegradationMode,
+}
+
+impl ConfigProfile {
+    /// Name of the profile selected through `SEARCH_PROFILE`
+    pub fn selected() -> Option<String> {
+        env::var(SEARCH_PROFILE_ENV).ok().filter(|name| !name.trim().is_empty())
+    }
+
+    /// Load the selected profile for `provider`, if one is selected
+    pub fn active(provider: &str) -> SearchResult<Option<Self>> {
+        Self::selected().map(|name| Self::from_env(&name, provider)).transpose()
+    }
+
+    /// Variable holding `field` of the profile `name`
+    pub fn var_name(name: &str, field: &str) -> String {
+        format!("SEARCH_PROFILE_{}_{}", name.trim().to_ascii_uppercase().replace('-', "_"), field)
+    }
+
+    /// Fields a profile must set for `provider`
+    pub fn required_fields(provider: &str) -> &'static [&'static str] {
+        match provider.to_lowercase().as_str() {
+            "algolia" => &["ENDPOINT", "TIMEOUT_SECS", "APP_ID", "API_KEY"],
+            "typesense" => &["ENDPOINT", "TIMEOUT_SECS", "API_KEY"],
+            _ => &["ENDPOINT", "TIMEOUT_SECS"],
+        }
+    }
+
+    /// Load the profile `name` for `provider` from the environment
+    pub fn from_env(name: &str, provider: &str) -> SearchResult<Self> {
+        Self::load(name, provider, |key| env::var(key).ok())
+    }
+
+    /// Load the profile `name` for `provider` through `lookup`
+    ///
+    /// Every missing or unparsable field is reported in the one error.
+    pub fn load(name: &str, provider: &str, lookup: impl Fn(&str) -> Option<String>) -> SearchResult<Self> {
+        let provider = provider.to_lowercase();
+        let mut missing = Vec::new();
+        let mut invalid = Vec::new();
+
+        let required = Self::required_fields(&provider);
+        let mut field = |key: &str| {
+            let var = Self::var_name(name, key);
+            let value = lookup(&var).filter(|v| !v.trim().is_empty());
+            if value.is_none() && required.contains(&key) {
+                missing.push(var);
+            }
+            value
+        };
+        let endpoint = field("ENDPOINT");
+        let timeout = field("TIMEOUT_SECS");
+        let app_id = field("APP_ID");
+        let api_key = field("API_KEY");
+        let username = field("USERNAME");
+        let password = field("PASSWORD");
+        let max_retries = field("MAX_RETRIES");
+        let cache_ttl = field("CACHE_TTL_MS");
+        let cache_swr = field("CACHE_SWR_MS");
+        let degradation = field("DEGRADATION");
+
+        let mut number = |key: &str, value: Option<String>| -> Option<u64> {
+            let value = value?;
+            match value.trim().parse::<u64>() {
+                Ok(n) => Some(n),
+                Err(_) => {
+                    invalid.push(format!("{}={}", Self::var_name(name, key), value));
+                    None
+                }
+            }
+        };
+        let timeout = number("TIMEOUT_SECS", timeout);
+        let max_retries = number("MAX_RETRIES", max_retries);
+        let cache_ttl = number("CACHE_TTL_MS", cache_ttl);
+        let cache_swr = number("CACHE_SWR_MS", cache_swr);
+
+        let degradation = match degradation {
+            Some(value) => DegradationMode::parse(&value).unwrap_or_else(|| {
+                let var = Self::var_name(name, "DEGRADATION");
+                invalid.push(format!("{}={} (expected lenient, strict or quiet)", var, value));
+                DegradationMode::default()
+            }),
+            None => DegradationMode::default(),
+        };
+
+        let mut problems = Vec::new();
+        if !missing.is_empty() {
+            problems.push(format!("missing {}", missing.join(", ")));
+        }
+        if !invalid.is_empty() {
+            problems.push(format!("invalid {}", invalid.join(", ")));
+        }
+        if !problems.is_empty() {
+            return Err(SearchError::invalid_query(format!(
+                "Configuration profile {} for {}: {}",
+                name,
+                provider,
+                problems.join("; ")
+            )));
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            endpoint: endpoint.unwrap_or_default(),
+            username,
+            password,
+            api_key,
+            app_id,
+            timeout: Duration::from_secs(timeout.unwrap_or_default()),
+            max_retries: max_retries.map_or(3, |n| u32::try_from(n).unwrap_or(u32::MAX)),
+            cache_policy: CachePolicy::new(
+                Duration::from_millis(cache_ttl.unwrap_or(0)),
+                Duration::from_millis(cache_swr.unwrap_or(0)),
+            ),
+            degradation,
+        })
+    }
+
+    /// Provider credentials of this profile
+    pub fn provider_config(&self, provider: &str) -> SearchResult<ProviderConfig> {
+        let config = match provider.to_lowercase().as_str() {
+            "algolia" => ProviderConfig::Algolia {
+                app_id: self.app_id.clone().unwrap_or_default(),
+                api_key: self.api_key.clone().unwrap_or_default(),
+            },
+            "elasticsearch" | "elastic" => ProviderConfig::ElasticSearch {
+                username: self.username.clone(),
+                password: self.password.clone(),
+                cloud_id: None,
+                ca_cert: None,
+            },
+            "opensearch" => ProviderConfig::OpenSearch {
+                username: self.username.clone(),
+                password: self.password.clone(),
+                aws_region: None,
+                aws_access_key: None,
+                aws_secret_key: None,
+            },
+            "typesense" => ProviderConfig::Typesense {
+                api_key: self.api_key.clone().unwrap_or_default(),
+                nodes: self.endpoint.split(',').map(|node| node.trim().to_string()).collect(),
+            },
+            "meilisearch" => ProviderConfig::Meilisearch {
+                api_key: self.api_key.clone(),
+                master_key: None,
+            },
+            _ => return Err(SearchError::invalid_query(format!("Unknown provider: {}", provider))),
+        };
+        Ok(config)
+    }
+}
+
 /// Environment variable helper functions
 pub mod env_helpers {
     use super::*;
@@ -246,6 +484,7 @@ mod tests {
                 app_id: "test_app".to_string(),
                 api_key: "test_key".to_string(),
             },
+            quotas: QuotaConfig::default(),
         };
         
         assert!(config.validate().is_ok());
@@ -262,8 +501,46 @@ mod tests {
                 app_id: "".to_string(),
                 api_key: "test_key".to_string(),
             },
+            quotas: QuotaConfig::default(),
         };
         
         assert!(config.validate().is_err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_profile_lists_every_missing_field() {
+        let vars = [
+            ("SEARCH_PROFILE_PROD_TIMEOUT_SECS", "ten"),
+            ("SEARCH_PROFILE_PROD_DEGRADATION", "loose"),
+        ];
+        let lookup = |key: &str| vars.iter().find(|(k, _)| *k == key).map(|(_, v)| v.to_string());
+
+        let err = ConfigProfile::load("prod", "typesense", lookup).unwrap_err().to_string();
+        assert!(err.contains("missing SEARCH_PROFILE_PROD_ENDPOINT, SEARCH_PROFILE_PROD_API_KEY;"), "{}", err);
+        assert!(err.contains("SEARCH_PROFILE_PROD_TIMEOUT_SECS=ten"), "{}", err);
+        assert!(err.contains("SEARCH_PROFILE_PROD_DEGRADATION=loose"), "{}", err);
+        assert!(!err.contains("USERNAME"));
+    }
+
+    #[test]
+    fn test_profile_bundles_settings() {
+        let vars = [
+            ("SEARCH_PROFILE_STAGING_EU_ENDPOINT", "https://staging.example.com:9200"),
+            ("SEARCH_PROFILE_STAGING_EU_TIMEOUT_SECS", "5"),
+            ("SEARCH_PROFILE_STAGING_EU_USERNAME", "elastic"),
+            ("SEARCH_PROFILE_STAGING_EU_CACHE_TTL_MS", "2000"),
+            ("SEARCH_PROFILE_STAGING_EU_DEGRADATION", "strict"),
+        ];
+        let lookup = |key: &str| vars.iter().find(|(k, _)| *k == key).map(|(_, v)| v.to_string());
+
+        let profile = ConfigProfile::load("staging-eu", "elasticsearch", lookup).unwrap();
+        assert_eq!(profile.timeout, Duration::from_secs(5));
+        assert_eq!(profile.max_retries, 3);
+        assert_eq!(profile.cache_policy.ttl, Duration::from_millis(2000));
+        assert!(profile.degradation.apply(DegradationStrategy::default()).strict_mode);
+        assert!(matches!(
+            profile.provider_config("elasticsearch").unwrap(),
+            ProviderConfig::ElasticSearch { username: Some(ref u), .. } if u == "elastic"
+        ));
+    }
+}
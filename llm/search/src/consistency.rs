@@ -0,0 +1,280 @@
+//! Read-your-writes consistency tokens
+//!
+//! Most engines apply writes asynchronously: Meilisearch and Algolia enqueue a
+//! task, Elasticsearch and OpenSearch make documents searchable on the next
+//! refresh. A write can hand back a [`ConsistencyToken`] describing where it
+//! landed; a later query carrying that token makes the provider wait until
+//! the backend has applied the write before searching. Only sessions that ask
+//! for it pay the wait, and writes themselves stay asynchronous.
+
+use std::future::Future;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use log::debug;
+use crate::config::env_helpers::get_env_parsed;
+use crate::error::{SearchError, SearchResult};
+
+/// Environment variable holding the maximum wait for a token in milliseconds
+pub const CONSISTENCY_WAIT_ENV: &str = "SEARCH_CONSISTENCY_WAIT_MS";
+
+/// Default maximum wait for a token
+pub const DEFAULT_CONSISTENCY_WAIT: Duration = Duration::from_secs(5);
+
+/// First delay between polls; doubled after every unsuccessful poll
+const INITIAL_POLL_INTERVAL: Duration = Duration::from_millis(25);
+
+/// Upper bound for the delay between polls
+const MAX_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Backend-specific position of a write
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WritePosition {
+    /// Asynchronous task id (Meilisearch `taskUid`, Algolia `taskID`)
+    Task { id: u64 },
+
+    /// Sequence number of the write in its shard (Elasticsearch/OpenSearch)
+    SeqNo { seq_no: i64, primary_term: i64 },
+
+    /// The write was visible as soon as it was acknowledged
+    Immediate,
+}
+
+/// Opaque marker returned by a write and passed back with later queries
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConsistencyToken {
+    pub provider: String,
+    pub index: String,
+    pub position: WritePosition,
+}
+
+impl ConsistencyToken {
+    pub fn new(provider: &str, index: &str, position: WritePosition) -> Self {
+        Self {
+            provider: provider.to_string(),
+            index: index.to_string(),
+            position,
+        }
+    }
+
+    /// Build a token from a write response
+    ///
+    /// Recognizes `taskUid` (Meilisearch), `taskID` (Algolia) and
+    /// `_seq_no`/`_primary_term` (Elasticsearch/OpenSearch); any other
+    /// response yields an `Immediate` token.
+    pub fn from_write_response(provider: &str, index: &str, response: &Value) -> Self {
+        let task = response.get("taskUid").or_else(|| response.get("taskID")).and_then(Value::as_u64);
+        let seq_no = response.get("_seq_no").and_then(Value::as_i64);
+        let primary_term = response.get("_primary_term").and_then(Value::as_i64);
+
+        let position = match (task, seq_no) {
+            (Some(id), _) => WritePosition::Task { id },
+            (None, Some(seq_no)) => WritePosition::SeqNo {
+                seq_no,
+                primary_term: primary_term.unwrap_or(1),
+            },
+            (None, None) => WritePosition::Immediate,
+        };
+        Self::new(provider, index, position)
+    }
+
+    /// Encode the token as an opaque string
+    pub fn encode(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    /// Decode a token produced by [`encode`](Self::encode)
+    pub fn parse(token: &str) -> SearchResult<Self> {
+        serde_json::from_str(token)
+            .map_err(|e| SearchError::invalid_query(format!("Invalid consistency token: {}", e)))
+    }
+
+    /// Decode a token and check that it was issued by `provider`
+    pub fn parse_for(token: &str, provider: &str) -> SearchResult<Self> {
+        let token = Self::parse(token)?;
+        if token.provider != provider {
+            return Err(SearchError::invalid_query(format!(
+                "Consistency token was issued by {}, not {}",
+                token.provider, provider
+            )));
+        }
+        Ok(token)
+    }
+}
+
+/// Maximum wait for a token, from `SEARCH_CONSISTENCY_WAIT_MS`
+pub fn wait_timeout_from_env() -> Duration {
+    get_env_parsed::<u64>(CONSISTENCY_WAIT_ENV)
+        .ok()
+        .flatten()
+        .map(Duration::from_millis)
+        .unwrap_or(DEFAULT_CONSISTENCY_WAIT)
+}
+
+/// Poll `is_applied` with exponential backoff until it reports the write as applied
+///
+/// Fails with `SearchError::Timeout` once `timeout` has elapsed. Errors from
+/// `is_applied` (for example a failed task) are returned immediately.
+pub async fn wait_until_applied<F, Fut>(token: &ConsistencyToken, timeout: Duration, mut is_applied: F) -> SearchResult<()>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = SearchResult<bool>>,
+{
+    if token.position == WritePosition::Immediate {
+        return Ok(());
+    }
+
+    let started = Instant::now();
+    let mut interval = INITIAL_POLL_INTERVAL;
+    loop {
+        if is_applied().await? {
+            debug!("Write {:?} on {} applied after {} ms", token.position, token.index, started.elapsed().as_millis());
+            return Ok(());
+        }
+
+        let elapsed = started.elapsed();
+        if elapsed >= timeout {
+            return Err(SearchError::Timeout { remaining_ms: None });
+        }
+
+        std::thread::sleep(interval.min(timeout - elapsed));
+        interval = (interval * 2).min(MAX_POLL_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn token(position: WritePosition) -> ConsistencyToken {
+        ConsistencyToken::new("meilisearch", "books", position)
+    }
+
+    fn position(response: Value) -> WritePosition {
+        ConsistencyToken::from_write_response("test", "books", &response).position
+    }
+
+    /// Wait for `token`, counting the polls of a check answering with `answers` and then `true`
+    fn wait(token: &ConsistencyToken, timeout: Duration, answers: Vec<SearchResult<bool>>) -> (SearchResult<()>, usize) {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let mut answers = answers.into_iter();
+        let mut polls = 0;
+        let result = runtime.block_on(wait_until_applied(token, timeout, || {
+            polls += 1;
+            let answer = answers.next().unwrap_or(Ok(true));
+            async move { answer }
+        }));
+        (result, polls)
+    }
+
+    #[test]
+    fn test_task_ids_are_read_from_meilisearch_and_algolia_responses() {
+        assert_eq!(position(json!({ "taskUid": 42, "status": "enqueued" })), WritePosition::Task { id: 42 });
+        assert_eq!(position(json!({ "taskID": 7, "objectIDs": ["1"] })), WritePosition::Task { id: 7 });
+        assert_eq!(
+            position(json!({ "taskUid": 3, "_seq_no": 9 })),
+            WritePosition::Task { id: 3 },
+            "a task id wins over a sequence number"
+        );
+    }
+
+    #[test]
+    fn test_sequence_numbers_default_to_the_first_primary_term() {
+        assert_eq!(position(json!({ "_seq_no": 7, "_primary_term": 2 })), WritePosition::SeqNo { seq_no: 7, primary_term: 2 });
+        assert_eq!(position(json!({ "_seq_no": 0 })), WritePosition::SeqNo { seq_no: 0, primary_term: 1 });
+    }
+
+    #[test]
+    fn test_unrecognized_responses_are_immediate() {
+        for response in [json!({}), json!({ "taskUid": "42" }), json!({ "taskUid": -1 }), json!({ "_seq_no": "7" }), json!([1, 2]), Value::Null] {
+            assert_eq!(position(response.clone()), WritePosition::Immediate, "{}", response);
+        }
+    }
+
+    #[test]
+    fn test_encoded_tokens_are_tagged_json() {
+        let seq_no = ConsistencyToken::new("elasticsearch", "logs", WritePosition::SeqNo { seq_no: 5, primary_term: 3 });
+        assert_eq!(
+            serde_json::from_str::<Value>(&seq_no.encode()).unwrap(),
+            json!({
+                "provider": "elasticsearch",
+                "index": "logs",
+                "position": { "type": "seq_no", "seq_no": 5, "primary_term": 3 }
+            })
+        );
+        let immediate = r#"{"provider":"typesense","index":"books","position":{"type":"immediate"}}"#;
+        assert_eq!(ConsistencyToken::parse(immediate).unwrap().position, WritePosition::Immediate);
+        assert_eq!(ConsistencyToken::parse(&seq_no.encode()).unwrap(), seq_no);
+    }
+
+    #[test]
+    fn test_malformed_tokens_are_invalid_queries() {
+        for text in ["", "not a token", r#"{"provider":"x","index":"y","position":{"type":"epoch"}}"#, r#"{"provider":"x"}"#] {
+            match ConsistencyToken::parse(text) {
+                Err(SearchError::InvalidQuery(message)) => assert!(message.starts_with("Invalid consistency token"), "{}", message),
+                other => panic!("expected an invalid query error for {:?}, got {:?}", text, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_tokens_of_another_provider_are_refused() {
+        let encoded = token(WritePosition::Task { id: 1 }).encode();
+        assert!(ConsistencyToken::parse_for(&encoded, "meilisearch").is_ok());
+        match ConsistencyToken::parse_for(&encoded, "algolia") {
+            Err(SearchError::InvalidQuery(message)) => {
+                assert_eq!(message, "Consistency token was issued by meilisearch, not algolia")
+            }
+            other => panic!("expected an invalid query error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_immediate_writes_are_not_polled() {
+        let (result, polls) = wait(&token(WritePosition::Immediate), Duration::ZERO, vec![Ok(false)]);
+        assert!(result.is_ok());
+        assert_eq!(polls, 0);
+    }
+
+    #[test]
+    fn test_waits_poll_until_the_write_is_applied() {
+        let (result, polls) = wait(&token(WritePosition::Task { id: 1 }), Duration::from_secs(5), vec![Ok(false), Ok(false)]);
+        assert!(result.is_ok());
+        assert_eq!(polls, 3);
+    }
+
+    #[test]
+    fn test_failed_checks_end_the_wait_without_polling_again() {
+        let failed = Err(SearchError::internal("task 1 failed"));
+        let (result, polls) = wait(&token(WritePosition::Task { id: 1 }), Duration::from_secs(5), vec![Ok(false), failed]);
+        assert!(matches!(result, Err(SearchError::Internal(message)) if message == "task 1 failed"));
+        assert_eq!(polls, 2);
+    }
+
+    #[test]
+    fn test_waits_time_out_with_backoff_between_polls() {
+        let never = || (0..100).map(|_| Ok(false)).collect();
+        let (result, polls) = wait(&token(WritePosition::Task { id: 1 }), Duration::ZERO, never());
+        assert!(matches!(result, Err(SearchError::Timeout { remaining_ms: None })));
+        assert_eq!(polls, 1, "a zero timeout still checks once");
+
+        let started = Instant::now();
+        let (result, polls) = wait(&token(WritePosition::Task { id: 1 }), Duration::from_millis(100), never());
+        assert!(matches!(result, Err(SearchError::Timeout { .. })));
+        assert!(started.elapsed() >= Duration::from_millis(100));
+        assert!((3..=5).contains(&polls), "polled {} times at 25, 50 and 100 ms intervals", polls);
+    }
+
+    #[test]
+    fn test_wait_timeout_read_from_the_environment() {
+        std::env::remove_var(CONSISTENCY_WAIT_ENV);
+        assert_eq!(wait_timeout_from_env(), DEFAULT_CONSISTENCY_WAIT);
+        std::env::set_var(CONSISTENCY_WAIT_ENV, "250");
+        assert_eq!(wait_timeout_from_env(), Duration::from_millis(250));
+        std::env::set_var(CONSISTENCY_WAIT_ENV, "5s");
+        assert_eq!(wait_timeout_from_env(), DEFAULT_CONSISTENCY_WAIT);
+        std::env::remove_var(CONSISTENCY_WAIT_ENV);
+    }
+}
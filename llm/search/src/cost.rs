@@ -0,0 +1,364 @@
+//! Query cost estimation
+//!
+//! [`estimate_query`] looks at a query before it is sent and flags the
+//! patterns that make search engines slow or fail outright: deep pagination,
+//! huge pages, many facets, leading wildcards and unfiltered scans of large
+//! indexes. Thresholds come from the provider's capability matrix and the
+//! index document count, so an API gateway can reject or throttle abusive
+//! queries without running them.
+
+use serde::{Deserialize, Serialize};
+use crate::capabilities::CapabilityMatrix;
+use crate::types::SearchQuery;
+
+/// Offset past which pagination is considered deep
+pub const DEEP_PAGINATION_OFFSET: u64 = 1_000;
+
+/// Page size past which a page is considered large
+pub const LARGE_PAGE_SIZE: u32 = 100;
+
+/// Facet count past which a query is considered facet-heavy
+pub const MANY_FACETS: usize = 10;
+
+/// Wildcard terms past which a query is considered wildcard-heavy
+pub const MANY_WILDCARDS: usize = 2;
+
+/// Document count past which unfiltered scans are flagged
+pub const LARGE_INDEX_DOCS: u64 = 1_000_000;
+
+/// Page size assumed when a query does not set one
+const DEFAULT_PER_PAGE: u32 = 10;
+
+/// Expected cost of a query, from cheapest to most expensive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CostLevel {
+    Low,
+    Moderate,
+    High,
+}
+
+/// Query pattern behind a warning
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CostFactor {
+    DeepPagination,
+    LargePage,
+    ManyFacets,
+    LeadingWildcard,
+    WildcardHeavy,
+    LongQuery,
+    FullScan,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CostWarning {
+    pub factor: CostFactor,
+    pub level: CostLevel,
+    pub message: String,
+}
+
+/// Index statistics used to scale the estimate
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexStats {
+    /// Number of documents, when the provider reported it
+    pub document_count: Option<u64>,
+}
+
+/// Predicted cost of a query with the reasons behind it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CostEstimate {
+    pub provider: String,
+    pub index: String,
+    pub level: CostLevel,
+    pub document_count: Option<u64>,
+    pub warnings: Vec<CostWarning>,
+}
+
+impl CostEstimate {
+    /// Whether the estimate reaches `level`, for gateways that reject above a threshold
+    pub fn exceeds(&self, level: CostLevel) -> bool {
+        self.level >= level
+    }
+}
+
+/// Deepest hit a provider will page to by default
+///
+/// Elasticsearch and OpenSearch stop at `index.max_result_window`, Meilisearch
+/// at `maxTotalHits` and Algolia at `paginationLimitedTo`.
+fn result_window(provider: &str) -> Option<u64> {
+    match provider {
+        "elasticsearch" | "opensearch" => Some(10_000),
+        "meilisearch" | "algolia" => Some(1_000),
+        _ => None,
+    }
+}
+
+/// Estimate what running `query` against `index` will cost on the provider of `matrix`
+pub fn estimate_query(matrix: &CapabilityMatrix, index: &str, stats: &IndexStats, query: &SearchQuery) -> CostEstimate {
+    let provider = matrix.provider_name.as_str();
+    let limits = &matrix.performance_limits;
+    let mut warnings = Vec::new();
+    let mut warn = |factor: CostFactor, level: CostLevel, message: String| {
+        warnings.push(CostWarning { factor, level, message });
+    };
+
+    let per_page = query.per_page.unwrap_or(DEFAULT_PER_PAGE);
+    let offset = query
+        .offset
+        .map(u64::from)
+        .unwrap_or_else(|| u64::from(query.page.unwrap_or(1).saturating_sub(1)) * u64::from(per_page));
+    let window_end = offset + u64::from(per_page);
+
+    match result_window(provider) {
+        Some(window) if window_end > window => warn(
+            CostFactor::DeepPagination,
+            CostLevel::High,
+            format!("Hits {}..{} lie beyond the {} result window of {}", offset, window_end, provider, window),
+        ),
+        _ if offset > DEEP_PAGINATION_OFFSET => warn(
+            CostFactor::DeepPagination,
+            CostLevel::Moderate,
+            format!("Offset {} makes the engine rank and skip {} hits; use a cursor instead", offset, offset),
+        ),
+        _ => {}
+    }
+
+    match limits.max_results_per_page {
+        Some(max) if per_page > max => warn(
+            CostFactor::LargePage,
+            CostLevel::High,
+            format!("per_page {} exceeds the {} maximum of {}", per_page, provider, max),
+        ),
+        _ if per_page > LARGE_PAGE_SIZE => warn(
+            CostFactor::LargePage,
+            CostLevel::Moderate,
+            format!("per_page {} fetches and serializes many documents per request", per_page),
+        ),
+        _ => {}
+    }
+
+    let facets = query.facets.len();
+    match limits.max_facets {
+        Some(max) if facets > max as usize => warn(
+            CostFactor::ManyFacets,
+            CostLevel::High,
+            format!("{} facets exceed the {} maximum of {}", facets, provider, max),
+        ),
+        _ if facets > MANY_FACETS => warn(
+            CostFactor::ManyFacets,
+            CostLevel::Moderate,
+            format!("{} facets each add an aggregation over all matches", facets),
+        ),
+        _ => {}
+    }
+
+    let text = query.q.as_deref().map(str::trim).unwrap_or("");
+    let terms: Vec<&str> = text.split_whitespace().collect();
+    let leading = terms.iter().filter(|t| t.len() > 1 && t.starts_with(['*', '?'])).count();
+    let wildcards = terms.iter().filter(|t| t.len() > 1 && t.contains(['*', '?'])).count();
+    if leading > 0 {
+        warn(
+            CostFactor::LeadingWildcard,
+            CostLevel::High,
+            format!("{} term(s) start with a wildcard, which scans the whole term dictionary", leading),
+        );
+    } else if wildcards > MANY_WILDCARDS {
+        warn(
+            CostFactor::WildcardHeavy,
+            CostLevel::Moderate,
+            format!("{} wildcard terms each expand to many index terms", wildcards),
+        );
+    }
+
+    if let Some(max) = limits.max_query_length {
+        if text.len() > max as usize {
+            warn(
+                CostFactor::LongQuery,
+                CostLevel::High,
+                format!("Query text of {} characters exceeds the {} maximum of {}", text.len(), provider, max),
+            );
+        }
+    }
+
+    let match_all = matches!(text, "" | "*");
+    if let Some(count) = stats.document_count {
+        if match_all && query.filters.is_empty() && count > LARGE_INDEX_DOCS && (!query.sort.is_empty() || facets > 0) {
+            warn(
+                CostFactor::FullScan,
+                CostLevel::Moderate,
+                format!("Unfiltered query sorts or facets all {} documents", count),
+            );
+        }
+    }
+
+    let level = warnings.iter().map(|w| w.level).max().unwrap_or(CostLevel::Low);
+    CostEstimate {
+        provider: provider.to_string(),
+        index: index.to_string(),
+        level,
+        document_count: stats.document_count,
+        warnings,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capabilities::{
+        algolia_capability_matrix, elasticsearch_capability_matrix, meilisearch_capability_matrix,
+        typesense_capability_matrix,
+    };
+
+    fn query(q: &str) -> SearchQuery {
+        SearchQuery {
+            q: Some(q.to_string()),
+            filters: Vec::new(),
+            sort: Vec::new(),
+            facets: Vec::new(),
+            page: None,
+            per_page: None,
+            offset: None,
+            highlight: None,
+            config: None,
+        }
+    }
+
+    fn warnings(matrix: &CapabilityMatrix, stats: &IndexStats, query: &SearchQuery) -> Vec<(CostFactor, CostLevel)> {
+        estimate_query(matrix, "books", stats, query).warnings.iter().map(|w| (w.factor, w.level)).collect()
+    }
+
+    fn paged(offset: Option<u32>, page: Option<u32>, per_page: Option<u32>) -> SearchQuery {
+        SearchQuery { offset, page, per_page, ..query("rust") }
+    }
+
+    fn sorted(q: &str) -> SearchQuery {
+        SearchQuery { sort: vec!["price:desc".to_string()], ..query(q) }
+    }
+
+    #[test]
+    fn test_pages_ending_inside_the_result_window_are_cheap() {
+        let meili = meilisearch_capability_matrix();
+        let stats = IndexStats::default();
+        assert!(warnings(&meili, &stats, &paged(Some(990), None, Some(10))).is_empty());
+        assert_eq!(
+            warnings(&meili, &stats, &paged(Some(991), None, Some(10))),
+            [(CostFactor::DeepPagination, CostLevel::High)]
+        );
+        assert_eq!(
+            warnings(&meili, &stats, &paged(None, Some(101), None)),
+            [(CostFactor::DeepPagination, CostLevel::High)],
+            "page 101 of 10 ends at hit 1010"
+        );
+    }
+
+    #[test]
+    fn test_deep_offsets_inside_a_large_window_are_moderate() {
+        let elastic = elasticsearch_capability_matrix();
+        let stats = IndexStats::default();
+        assert!(warnings(&elastic, &stats, &paged(Some(1_000), None, None)).is_empty());
+        assert_eq!(
+            warnings(&elastic, &stats, &paged(Some(1_001), None, None)),
+            [(CostFactor::DeepPagination, CostLevel::Moderate)]
+        );
+        assert_eq!(
+            warnings(&elastic, &stats, &paged(Some(9_995), None, None)),
+            [(CostFactor::DeepPagination, CostLevel::High)]
+        );
+    }
+
+    #[test]
+    fn test_offsets_take_precedence_over_pages() {
+        let meili = meilisearch_capability_matrix();
+        let stats = IndexStats::default();
+        assert!(warnings(&meili, &stats, &paged(Some(0), Some(500), None)).is_empty());
+        assert!(warnings(&meili, &stats, &paged(None, Some(0), None)).is_empty(), "page 0 is read as the first page");
+        let estimate = estimate_query(&meili, "books", &stats, &paged(Some(2_000), Some(1), Some(20)));
+        assert_eq!(estimate.warnings[0].message, "Hits 2000..2020 lie beyond the meilisearch result window of 1000");
+    }
+
+    #[test]
+    fn test_page_sizes_beyond_the_provider_maximum_are_high() {
+        let typesense = typesense_capability_matrix();
+        let stats = IndexStats::default();
+        assert!(warnings(&typesense, &stats, &paged(None, None, Some(LARGE_PAGE_SIZE))).is_empty());
+        assert_eq!(
+            warnings(&typesense, &stats, &paged(None, None, Some(101))),
+            [(CostFactor::LargePage, CostLevel::Moderate)]
+        );
+        assert_eq!(warnings(&typesense, &stats, &paged(None, None, Some(250))), [(CostFactor::LargePage, CostLevel::Moderate)]);
+        assert_eq!(warnings(&typesense, &stats, &paged(None, None, Some(251))), [(CostFactor::LargePage, CostLevel::High)]);
+
+        let mut unlimited = typesense_capability_matrix();
+        unlimited.performance_limits.max_results_per_page = None;
+        assert_eq!(
+            warnings(&unlimited, &stats, &paged(None, None, Some(5_000))),
+            [(CostFactor::LargePage, CostLevel::Moderate)]
+        );
+    }
+
+    #[test]
+    fn test_facet_counts_are_checked_against_the_provider_maximum() {
+        let facets = |count: usize| SearchQuery { facets: (0..count).map(|i| format!("field{}", i)).collect(), ..query("rust") };
+        let typesense = typesense_capability_matrix();
+        let stats = IndexStats::default();
+        assert!(warnings(&typesense, &stats, &facets(MANY_FACETS)).is_empty());
+        assert_eq!(warnings(&typesense, &stats, &facets(11)), [(CostFactor::ManyFacets, CostLevel::Moderate)]);
+        assert_eq!(warnings(&typesense, &stats, &facets(51)), [(CostFactor::ManyFacets, CostLevel::High)]);
+    }
+
+    #[test]
+    fn test_leading_wildcards_outrank_wildcard_heavy_queries() {
+        let elastic = elasticsearch_capability_matrix();
+        let stats = IndexStats::default();
+        let factors = |q: &str| warnings(&elastic, &stats, &query(q));
+
+        assert!(factors("*").is_empty(), "a lone wildcard matches everything");
+        assert!(factors("ru* do?").is_empty());
+        assert_eq!(factors("?ust"), [(CostFactor::LeadingWildcard, CostLevel::High)]);
+        assert_eq!(factors("ru* do? ca*"), [(CostFactor::WildcardHeavy, CostLevel::Moderate)]);
+        assert_eq!(factors("*ust do? ca* ex*"), [(CostFactor::LeadingWildcard, CostLevel::High)]);
+    }
+
+    #[test]
+    fn test_long_queries_are_measured_after_trimming() {
+        let algolia = algolia_capability_matrix();
+        let stats = IndexStats::default();
+        let padded = format!("   {}   ", "a".repeat(512));
+        assert!(warnings(&algolia, &stats, &query(&padded)).is_empty());
+        assert_eq!(warnings(&algolia, &stats, &query(&"a".repeat(513))), [(CostFactor::LongQuery, CostLevel::High)]);
+    }
+
+    #[test]
+    fn test_full_scans_need_a_large_unfiltered_sorted_or_faceted_match_all() {
+        let elastic = elasticsearch_capability_matrix();
+        let large = IndexStats { document_count: Some(LARGE_INDEX_DOCS + 1) };
+        let full_scan = [(CostFactor::FullScan, CostLevel::Moderate)];
+
+        assert_eq!(warnings(&elastic, &large, &sorted("*")), full_scan);
+        assert_eq!(warnings(&elastic, &large, &sorted("")), full_scan);
+        assert_eq!(warnings(&elastic, &large, &SearchQuery { facets: vec!["genre".to_string()], ..query("") }), full_scan);
+
+        assert!(warnings(&elastic, &large, &query("*")).is_empty(), "nothing to sort or facet");
+        assert!(warnings(&elastic, &large, &sorted("rust")).is_empty());
+        assert!(warnings(&elastic, &large, &SearchQuery { filters: vec!["price > 10".to_string()], ..sorted("*") }).is_empty());
+        assert!(warnings(&elastic, &IndexStats { document_count: Some(LARGE_INDEX_DOCS) }, &sorted("*")).is_empty());
+        assert!(warnings(&elastic, &IndexStats::default(), &sorted("*")).is_empty(), "unknown document count");
+    }
+
+    #[test]
+    fn test_level_is_the_highest_warning() {
+        let typesense = typesense_capability_matrix();
+        let stats = IndexStats { document_count: Some(42) };
+        let cheap = estimate_query(&typesense, "books", &stats, &query("rust"));
+        assert_eq!((cheap.level, cheap.document_count), (CostLevel::Low, Some(42)));
+        assert!(cheap.exceeds(CostLevel::Low) && !cheap.exceeds(CostLevel::Moderate));
+
+        let moderate = estimate_query(&typesense, "books", &stats, &paged(Some(2_000), None, Some(200)));
+        assert_eq!((moderate.level, moderate.warnings.len()), (CostLevel::Moderate, 2));
+
+        let high = estimate_query(&typesense, "books", &stats, &SearchQuery { per_page: Some(300), ..query("*ing") });
+        assert_eq!((high.provider.as_str(), high.index.as_str(), high.level), ("typesense", "books", CostLevel::High));
+        assert!(high.exceeds(CostLevel::Moderate) && high.exceeds(CostLevel::High));
+    }
+}
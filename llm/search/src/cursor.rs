@@ -0,0 +1,287 @@
+//! Opaque pagination cursors
+//!
+//! Search results carry a `next_cursor` when more matches follow the page.
+//! Passing it back as the `cursor` of the next query continues right after
+//! that page, with `page` and `offset` ignored, so clients can paginate
+//! without tracking offsets themselves.
+//!
+//! The cursor wraps whatever the engine pages by: `search_after` sort values
+//! on Elasticsearch and OpenSearch when the query sorts (offsets when it ranks
+//! by score, since scores give no stable position), page numbers on Typesense
+//! and offsets on Meilisearch. A cursor is only accepted by the provider and
+//! index that issued it.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use crate::error::{SearchError, SearchResult};
+
+/// Page size engines use when a query does not set one
+pub const DEFAULT_PER_PAGE: u32 = 10;
+
+/// Where the next page starts
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CursorPosition {
+    /// Sort values of the last hit, with the number of hits before the page
+    SearchAfter { values: Vec<Value>, offset: u32 },
+
+    /// Number of hits before the page
+    Offset { offset: u32 },
+
+    /// 1-based page number
+    Page { page: u32 },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PageCursor {
+    pub provider: String,
+    pub index: String,
+    pub position: CursorPosition,
+}
+
+impl PageCursor {
+    pub fn new(provider: &str, index: &str, position: CursorPosition) -> Self {
+        Self { provider: provider.to_string(), index: index.to_string(), position }
+    }
+
+    /// Encode the cursor as an opaque, URL-safe string
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    /// Decode a cursor and check that it was issued for `index` by `provider`
+    pub fn parse_for(token: &str, provider: &str, index: &str) -> SearchResult<Self> {
+        let invalid = |e: String| SearchError::invalid_query(format!("Invalid cursor: {}", e));
+        let bytes = URL_SAFE_NO_PAD.decode(token.trim()).map_err(|e| invalid(e.to_string()))?;
+        let cursor: Self = serde_json::from_slice(&bytes).map_err(|e| invalid(e.to_string()))?;
+        if cursor.provider != provider || cursor.index != index {
+            return Err(SearchError::invalid_query(format!(
+                "Cursor was issued by {} for index {}, not by {} for {}",
+                cursor.provider, cursor.index, provider, index
+            )));
+        }
+        Ok(cursor)
+    }
+
+    /// Number of hits before the page the cursor points at
+    pub fn offset(&self, per_page: u32) -> u32 {
+        match &self.position {
+            CursorPosition::SearchAfter { offset, .. } | CursorPosition::Offset { offset } => *offset,
+            CursorPosition::Page { page } => page.saturating_sub(1).saturating_mul(per_page),
+        }
+    }
+}
+
+/// Whether more hits follow a page that started at `offset` and returned `returned` hits
+///
+/// A short page is the last one; otherwise the total decides, when known.
+pub fn has_more(offset: u32, returned: usize, per_page: u32, total: Option<u32>) -> bool {
+    let returned = returned as u32;
+    returned > 0 && returned >= per_page && total.is_none_or(|total| offset.saturating_add(returned) < total)
+}
+
+/// Position of an Elasticsearch/OpenSearch request in the result list
+#[derive(Debug, Clone, PartialEq)]
+pub struct LucenePage {
+    pub provider: String,
+    pub index: String,
+    pub offset: u32,
+    pub per_page: u32,
+}
+
+impl LucenePage {
+    /// Start `request` where `cursor` points, or where its `from` says without one
+    pub fn apply(request: &mut Value, provider: &str, index: &str, cursor: Option<&str>) -> SearchResult<Self> {
+        let per_page = request.get("size").and_then(Value::as_u64).map_or(DEFAULT_PER_PAGE, |size| size as u32);
+        let offset = match cursor {
+            None => request.get("from").and_then(Value::as_u64).unwrap_or(0) as u32,
+            Some(token) => {
+                let cursor = PageCursor::parse_for(token, provider, index)?;
+                let offset = cursor.offset(per_page);
+                if let CursorPosition::SearchAfter { values, .. } = cursor.position {
+                    request["search_after"] = Value::Array(values);
+                    if let Some(body) = request.as_object_mut() {
+                        body.remove("from");
+                    }
+                } else {
+                    request["from"] = json!(offset);
+                }
+                offset
+            }
+        };
+        Ok(Self { provider: provider.to_string(), index: index.to_string(), offset, per_page })
+    }
+
+    /// Cursor of the following page, if any
+    ///
+    /// `last_sort` holds the sort values of the page's last hit; they are
+    /// used when `request` sorts explicitly.
+    pub fn next_cursor(
+        &self,
+        request: &Value,
+        returned: usize,
+        total: Option<u32>,
+        last_sort: Option<&[Value]>,
+    ) -> Option<String> {
+        if !has_more(self.offset, returned, self.per_page, total) {
+            return None;
+        }
+        let offset = self.offset + returned as u32;
+        let position = match (request.get("sort"), last_sort) {
+            (Some(_), Some(values)) => CursorPosition::SearchAfter { values: values.to_vec(), offset },
+            _ => CursorPosition::Offset { offset },
+        };
+        Some(PageCursor::new(&self.provider, &self.index, position).encode())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(provider: &str, index: &str, position: CursorPosition) -> String {
+        PageCursor::new(provider, index, position).encode()
+    }
+
+    fn invalid(result: SearchResult<PageCursor>) -> String {
+        match result {
+            Err(SearchError::InvalidQuery(message)) => message,
+            other => panic!("expected an invalid query error, got {:?}", other),
+        }
+    }
+
+    fn position(cursor: &str, provider: &str) -> CursorPosition {
+        PageCursor::parse_for(cursor, provider, "books").unwrap().position
+    }
+
+    #[test]
+    fn test_tokens_are_url_safe_and_survive_surrounding_whitespace() {
+        let values = vec![json!("ä/ö+?&="), json!(null), json!(1.5)];
+        let cursor = PageCursor::new("elasticsearch", "books", CursorPosition::SearchAfter { values, offset: 7 });
+        let encoded = cursor.encode();
+        assert!(encoded.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'), "{}", encoded);
+        assert_eq!(PageCursor::parse_for(&format!(" {}\n", encoded), "elasticsearch", "books").unwrap(), cursor);
+    }
+
+    #[test]
+    fn test_cursors_are_scoped_to_their_provider_and_index() {
+        let encoded = token("typesense", "books", CursorPosition::Page { page: 3 });
+        assert_eq!(
+            invalid(PageCursor::parse_for(&encoded, "typesense", "authors")),
+            "Cursor was issued by typesense for index books, not by typesense for authors"
+        );
+        assert_eq!(
+            invalid(PageCursor::parse_for(&encoded, "meilisearch", "books")),
+            "Cursor was issued by typesense for index books, not by meilisearch for books"
+        );
+    }
+
+    #[test]
+    fn test_malformed_tokens_are_invalid_cursors() {
+        let unknown = URL_SAFE_NO_PAD.encode(r#"{"provider":"typesense","index":"books","position":{"type":"scroll"}}"#);
+        let padded = base64::engine::general_purpose::URL_SAFE.encode(token("typesense", "books", CursorPosition::Page { page: 2 }));
+        for text in ["", "not a cursor", "bm90IGpzb24", unknown.as_str(), padded.as_str()] {
+            let message = invalid(PageCursor::parse_for(text, "typesense", "books"));
+            assert!(message.starts_with("Invalid cursor: "), "{:?}: {}", text, message);
+        }
+    }
+
+    #[test]
+    fn test_offsets_from_pages_start_at_the_first_page_and_saturate() {
+        let offset = |position: CursorPosition, per_page: u32| PageCursor::new("typesense", "books", position).offset(per_page);
+        assert_eq!(offset(CursorPosition::Page { page: 1 }, 20), 0);
+        assert_eq!(offset(CursorPosition::Page { page: 0 }, 20), 0);
+        assert_eq!(offset(CursorPosition::Page { page: 4 }, 25), 75);
+        assert_eq!(offset(CursorPosition::Page { page: u32::MAX }, 100), u32::MAX);
+        assert_eq!(offset(CursorPosition::Offset { offset: 13 }, 100), 13, "offsets do not depend on the page size");
+        assert_eq!(offset(CursorPosition::SearchAfter { values: Vec::new(), offset: 13 }, 100), 13);
+    }
+
+    #[test]
+    fn test_only_full_pages_short_of_the_total_have_more() {
+        assert!(has_more(0, 10, 10, None), "a full page with an unknown total");
+        assert!(has_more(0, 10, 10, Some(11)));
+        assert!(!has_more(0, 10, 10, Some(10)), "the page ends at the total");
+        assert!(!has_more(20, 4, 10, None), "a short page is the last");
+        assert!(!has_more(20, 4, 10, Some(1_000)), "a short page is the last whatever the total says");
+        assert!(!has_more(0, 0, 0, None), "an empty page is the last even with a zero page size");
+        assert!(!has_more(u32::MAX - 5, 10, 10, Some(u32::MAX)), "the end of the page saturates");
+    }
+
+    #[test]
+    fn test_requests_without_a_cursor_keep_their_offset_and_size() {
+        let mut request = json!({ "from": 30, "size": 15 });
+        let page = LucenePage::apply(&mut request, "opensearch", "books", None).unwrap();
+        assert_eq!((page.offset, page.per_page), (30, 15));
+        assert_eq!(request, json!({ "from": 30, "size": 15 }));
+
+        let page = LucenePage::apply(&mut json!({}), "opensearch", "books", None).unwrap();
+        assert_eq!((page.offset, page.per_page), (0, DEFAULT_PER_PAGE));
+    }
+
+    #[test]
+    fn test_cursors_replace_the_offset_of_the_request() {
+        let offset_cursor = token("elasticsearch", "books", CursorPosition::Offset { offset: 40 });
+        let mut request = json!({ "from": 0, "size": 20 });
+        assert_eq!(LucenePage::apply(&mut request, "elasticsearch", "books", Some(&offset_cursor)).unwrap().offset, 40);
+        assert_eq!(request["from"], 40);
+
+        let page_cursor = token("elasticsearch", "books", CursorPosition::Page { page: 3 });
+        let mut request = json!({ "size": 20 });
+        assert_eq!(LucenePage::apply(&mut request, "elasticsearch", "books", Some(&page_cursor)).unwrap().offset, 40);
+        assert_eq!(request["from"], 40);
+
+        let search_after = token("elasticsearch", "books", CursorPosition::SearchAfter { values: vec![json!(2020)], offset: 40 });
+        let mut request = json!({ "from": 0, "size": 20, "sort": [{ "year": "desc" }] });
+        assert_eq!(LucenePage::apply(&mut request, "elasticsearch", "books", Some(&search_after)).unwrap().offset, 40);
+        assert_eq!(request, json!({ "size": 20, "sort": [{ "year": "desc" }], "search_after": [2020] }));
+    }
+
+    #[test]
+    fn test_foreign_cursors_leave_the_request_untouched() {
+        let cursor = token("opensearch", "books", CursorPosition::Offset { offset: 40 });
+        let mut request = json!({ "from": 0, "size": 20 });
+        assert!(LucenePage::apply(&mut request, "elasticsearch", "books", Some(&cursor)).is_err());
+        assert_eq!(request, json!({ "from": 0, "size": 20 }));
+    }
+
+    #[test]
+    fn test_search_after_needs_both_a_sort_and_the_last_sort_values() {
+        let last_sort = [json!(2020), json!("b7")];
+        let page = LucenePage { provider: "elasticsearch".to_string(), index: "books".to_string(), offset: 20, per_page: 10 };
+        let sorted = json!({ "sort": [{ "year": "desc" }, { "id": "asc" }] });
+
+        let next = page.next_cursor(&sorted, 10, None, Some(&last_sort)).unwrap();
+        assert_eq!(
+            position(&next, "elasticsearch"),
+            CursorPosition::SearchAfter { values: last_sort.to_vec(), offset: 30 }
+        );
+        let next = page.next_cursor(&sorted, 10, None, None).unwrap();
+        assert_eq!(position(&next, "elasticsearch"), CursorPosition::Offset { offset: 30 });
+        let next = page.next_cursor(&json!({}), 10, None, Some(&last_sort)).unwrap();
+        assert_eq!(position(&next, "elasticsearch"), CursorPosition::Offset { offset: 30 }, "ranked by score");
+
+        assert_eq!(page.next_cursor(&sorted, 9, None, Some(&last_sort)), None);
+        assert_eq!(page.next_cursor(&sorted, 10, Some(30), Some(&last_sort)), None);
+    }
+
+    #[test]
+    fn test_following_cursors_page_through_every_hit_once() {
+        let total = 25;
+        let mut seen: Vec<u32> = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let mut request = json!({ "size": 10, "sort": [{ "id": "asc" }] });
+            let page = LucenePage::apply(&mut request, "opensearch", "books", cursor.as_deref()).unwrap();
+            let hits: Vec<u32> = (page.offset..total.min(page.offset + page.per_page)).collect();
+            seen.extend(&hits);
+            let last_sort = hits.last().map(|id| vec![json!(id)]);
+            cursor = page.next_cursor(&request, hits.len(), Some(total), last_sort.as_deref());
+            if cursor.is_none() {
+                break;
+            }
+        }
+        assert_eq!(seen, (0..total).collect::<Vec<_>>());
+    }
+}
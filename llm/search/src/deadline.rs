@@ -0,0 +1,125 @@
+//! Per-request deadlines
+//!
+//! Clients time out each HTTP call after `SEARCH_PROVIDER_TIMEOUT`. A search
+//! can bring its own budget in `timeout_ms` instead, for an autocomplete that
+//! is worthless after 200 ms or an export that may take minutes. The provider
+//! opens a [`DeadlineScope`] for the operation, and every HTTP call made
+//! inside it is sent with the time left as its timeout, so the lookups, split
+//! requests and retries of one search share the budget. Component calls run
+//! on a single thread, so the active deadline is kept thread-local, as request
+//! IDs are.
+//!
+//! A call made once the budget is spent fails without being sent. Timeouts
+//! inside a scope report the milliseconds left of its budget (see
+//! [`timeout_error`]), so a caller can tell a spent budget from a stalled call.
+
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+use crate::error::SearchError;
+
+thread_local! {
+    static CURRENT: Cell<Option<Deadline>> = const { Cell::new(None) };
+}
+
+/// Point in time by which an operation has to finish
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Deadline {
+    at: Instant,
+    budget: Duration,
+}
+
+impl Deadline {
+    /// Deadline `budget` from now
+    pub fn after(budget: Duration) -> Self {
+        Self { at: Instant::now() + budget, budget }
+    }
+
+    /// Time the operation was given
+    pub fn budget(&self) -> Duration {
+        self.budget
+    }
+
+    /// Time left, zero once the deadline has passed
+    pub fn remaining(&self) -> Duration {
+        self.at.saturating_duration_since(Instant::now())
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.remaining().is_zero()
+    }
+}
+
+/// Deadline of the operation running on this thread, if any
+pub fn current() -> Option<Deadline> {
+    CURRENT.with(Cell::get)
+}
+
+/// Milliseconds left of the active deadline
+pub fn remaining_ms() -> Option<u64> {
+    current().map(|deadline| deadline.remaining().as_millis() as u64)
+}
+
+/// Timeout error naming what is left of the active deadline
+pub fn timeout_error() -> SearchError {
+    SearchError::Timeout { remaining_ms: remaining_ms() }
+}
+
+/// Marks the extent of an operation with a deadline; the previous one is restored on drop
+#[derive(Debug)]
+pub struct DeadlineScope {
+    previous: Option<Deadline>,
+}
+
+impl Drop for DeadlineScope {
+    fn drop(&mut self) {
+        CURRENT.with(|current| current.set(self.previous));
+    }
+}
+
+/// Start an operation with `timeout_ms` to finish, as a search config carries it
+///
+/// Without a timeout, and inside an operation with an earlier deadline, the
+/// active deadline is kept.
+pub fn begin(timeout_ms: Option<u32>) -> DeadlineScope {
+    CURRENT.with(|current| {
+        let previous = current.get();
+        let requested = timeout_ms.map(|ms| Deadline::after(Duration::from_millis(ms.into())));
+        let deadline = match (previous, requested) {
+            (Some(outer), Some(inner)) => Some(if inner.at < outer.at { inner } else { outer }),
+            (outer, inner) => inner.or(outer),
+        };
+        current.set(deadline);
+        DeadlineScope { previous }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scopes_keep_the_earliest_deadline() {
+        assert_eq!(current(), None);
+        {
+            let _outer = begin(Some(60_000));
+            let outer = current().unwrap();
+            assert_eq!(outer.budget(), Duration::from_secs(60));
+            {
+                let _inner = begin(Some(100));
+                assert_eq!(current().unwrap().budget(), Duration::from_millis(100));
+                {
+                    let _looser = begin(Some(120_000));
+                    assert_eq!(current().unwrap().budget(), Duration::from_millis(100));
+                }
+            }
+            let _unbounded = begin(None);
+            assert_eq!(current(), Some(outer));
+            assert!(matches!(timeout_error(), SearchError::Timeout { remaining_ms: Some(ms) } if ms > 59_000));
+        }
+        assert_eq!(current(), None);
+        assert!(matches!(timeout_error(), SearchError::Timeout { remaining_ms: None }));
+
+        let _spent = begin(Some(0));
+        assert!(current().unwrap().is_expired());
+    }
+}
@@ -0,0 +1,357 @@
+//! Delta sync
+//!
+//! [`plan`] works out the fewest writes that converge an index to a source
+//! dataset. Documents that are new or changed in the source are upserted,
+//! documents the index holds but the source lacks are deleted, and the rest
+//! are left alone. Nightly catalog syncs can use it instead of a full reindex.
+//!
+//! A provider first scans every document of the index into an
+//! [`IndexFingerprint`] of IDs and content hashes. Incoming documents are
+//! compared after the index's ingest pipeline. Key order does not count as a
+//! change, and neither does an `id` field equal to the document ID, which some
+//! providers store in the document. Writes go through the provider's
+//! `apply_changes` in batches of [`SYNC_BATCH_SIZE`] documents, so a failed
+//! batch leaves the earlier ones applied and the sync can simply be run again.
+//!
+//! The source must be complete, because every document it leaves out is
+//! deleted. An empty source for an index that has documents is refused; use
+//! `clear_index` to empty an index on purpose.
+
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::change_feed::ChangeBatch;
+use crate::checksum::{checksum_value, Checksum};
+use crate::error::{SearchError, SearchResult};
+use crate::ingest;
+use crate::telemetry::SpanOutput;
+use crate::types::Doc;
+
+/// Documents upserted or deleted per `apply_changes` call
+pub const SYNC_BATCH_SIZE: usize = 500;
+
+/// Hash of a document, ignoring an `id` field that repeats its ID
+fn fingerprint(id: &str, document: &Value) -> Checksum {
+    match document {
+        Value::Object(fields) if fields.get("id").and_then(Value::as_str) == Some(id) => {
+            let mut fields = fields.clone();
+            fields.remove("id");
+            checksum_value(&Value::Object(fields))
+        }
+        _ => checksum_value(document),
+    }
+}
+
+fn fingerprint_content(id: &str, content: &str) -> SearchResult<Checksum> {
+    let document = serde_json::from_str(content)
+        .map_err(|e| SearchError::invalid_query(format!("Document {} has invalid JSON: {}", id, e)))?;
+    Ok(fingerprint(id, &document))
+}
+
+/// IDs and content hashes of the documents an index holds
+#[derive(Debug, Clone, Default)]
+pub struct IndexFingerprint {
+    hashes: HashMap<String, Checksum>,
+}
+
+impl IndexFingerprint {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a document as the provider returned it
+    pub fn add(&mut self, id: &str, document: &Value) {
+        self.hashes.insert(id.to_string(), fingerprint(id, document));
+    }
+
+    pub fn len(&self) -> usize {
+        self.hashes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hashes.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncReport {
+    pub index: String,
+
+    /// Whether the writes were only counted
+    pub dry_run: bool,
+
+    /// Documents in the index before the sync
+    pub existing: usize,
+
+    /// Documents in the source; a repeated ID counts once, last version winning
+    pub received: usize,
+    pub upserted: usize,
+    pub deleted: usize,
+    pub unchanged: usize,
+}
+
+impl SpanOutput for SyncReport {}
+
+/// The writes converging an index to a source
+#[derive(Debug, Clone)]
+pub struct SyncPlan {
+    report: SyncReport,
+    upserts: Vec<Doc>,
+    deletes: Vec<String>,
+}
+
+/// Compare `source` with the `existing` fingerprint of `index`
+///
+/// With `dry_run` the plan is only counted; executing it writes nothing.
+pub fn plan(index: &str, existing: IndexFingerprint, source: impl IntoIterator<Item = Doc>, dry_run: bool) -> SearchResult<SyncPlan> {
+    let pipeline = ingest::pipeline_for(index)?;
+
+    // Later versions of a repeated ID replace earlier ones, as they would when upserted in order
+    let mut incoming: Vec<Doc> = Vec::new();
+    let mut positions: HashMap<String, usize> = HashMap::new();
+    for doc in source {
+        match positions.get(&doc.id) {
+            Some(&position) => incoming[position] = doc,
+            None => {
+                positions.insert(doc.id.clone(), incoming.len());
+                incoming.push(doc);
+            }
+        }
+    }
+    if incoming.is_empty() && !existing.is_empty() {
+        return Err(SearchError::invalid_query(format!(
+            "Refusing to sync an empty source into {}, which would delete all {} documents",
+            index,
+            existing.len()
+        )));
+    }
+
+    let mut upserts = Vec::new();
+    let mut unchanged = 0;
+    for doc in &incoming {
+        let content = match &pipeline {
+            Some(pipeline) => pipeline.apply_content(&doc.id, &doc.content)?,
+            None => doc.content.clone(),
+        };
+        if existing.hashes.get(&doc.id) == Some(&fingerprint_content(&doc.id, &content)?) {
+            unchanged += 1;
+        } else {
+            // The provider runs the pipeline again when writing, so the document goes as received
+            upserts.push(doc.clone());
+        }
+    }
+    let seen: HashSet<&str> = incoming.iter().map(|doc| doc.id.as_str()).collect();
+    let mut deletes: Vec<String> = existing.hashes.keys().filter(|id| !seen.contains(id.as_str())).cloned().collect();
+    deletes.sort();
+
+    let report = SyncReport {
+        index: index.to_string(),
+        dry_run,
+        existing: existing.len(),
+        received: incoming.len(),
+        upserted: upserts.len(),
+        deleted: deletes.len(),
+        unchanged,
+    };
+    Ok(SyncPlan { report, upserts, deletes })
+}
+
+impl SyncPlan {
+    pub fn report(&self) -> &SyncReport {
+        &self.report
+    }
+
+    /// Write the plan through `apply`, upserts first, in batches of [`SYNC_BATCH_SIZE`]
+    pub async fn execute<F, Fut, E>(self, mut apply: F) -> Result<SyncReport, E>
+    where
+        F: FnMut(ChangeBatch) -> Fut,
+        Fut: Future<Output = Result<(), E>>,
+    {
+        if self.report.dry_run {
+            return Ok(self.report);
+        }
+        for chunk in self.upserts.chunks(SYNC_BATCH_SIZE) {
+            apply(ChangeBatch { upserts: chunk.to_vec(), ..ChangeBatch::default() }).await?;
+        }
+        for chunk in self.deletes.chunks(SYNC_BATCH_SIZE) {
+            apply(ChangeBatch { deletes: chunk.to_vec(), ..ChangeBatch::default() }).await?;
+        }
+        Ok(self.report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::cell::RefCell;
+    use crate::ingest::IngestPipeline;
+
+    fn doc(id: &str, content: Value) -> Doc {
+        Doc { id: id.to_string(), content: content.to_string() }
+    }
+
+    fn fingerprint_of(documents: &[(&str, Value)]) -> IndexFingerprint {
+        let mut existing = IndexFingerprint::new();
+        for (id, document) in documents {
+            existing.add(id, document);
+        }
+        existing
+    }
+
+    fn ids(docs: &[Doc]) -> Vec<&str> {
+        docs.iter().map(|doc| doc.id.as_str()).collect()
+    }
+
+    /// Execute `plan`, failing the batch numbered `fail_at`, and return the batches `apply` received
+    fn execute(plan: SyncPlan, fail_at: Option<usize>) -> (Result<SyncReport, String>, Vec<ChangeBatch>) {
+        let batches = RefCell::new(Vec::new());
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let result = runtime.block_on(plan.execute(|batch| {
+            let mut batches = batches.borrow_mut();
+            batches.push(batch);
+            let failed = Some(batches.len() - 1) == fail_at;
+            async move { if failed { Err("batch failed".to_string()) } else { Ok(()) } }
+        }));
+        (result, batches.into_inner())
+    }
+
+    #[test]
+    fn test_key_order_and_a_repeated_id_field_are_not_changes() {
+        let existing = fingerprint_of(&[
+            ("1", json!({ "id": "1", "title": "Dune", "year": 1965 })),
+            ("2", json!({ "title": "Emma" })),
+        ]);
+        let source = vec![doc("1", json!({ "year": 1965, "title": "Dune" })), doc("2", json!({ "id": "2", "title": "Emma" }))];
+        let plan = plan("sync-unchanged", existing, source, false).unwrap();
+        assert_eq!((plan.report().unchanged, plan.report().upserted), (2, 0));
+    }
+
+    #[test]
+    fn test_an_id_field_naming_another_document_is_a_change() {
+        let existing = fingerprint_of(&[("1", json!({ "title": "Dune" }))]);
+        let plan = plan("sync-other-id", existing, vec![doc("1", json!({ "id": "2", "title": "Dune" }))], false).unwrap();
+        assert_eq!(ids(&plan.upserts), ["1"]);
+    }
+
+    #[test]
+    fn test_repeated_ids_keep_their_first_position_and_last_version() {
+        let source = vec![
+            doc("b", json!({ "v": 1 })),
+            doc("a", json!({ "v": 1 })),
+            doc("b", json!({ "v": 2 })),
+            doc("b", json!({ "v": 3 })),
+        ];
+        let plan = plan("sync-repeated", IndexFingerprint::new(), source, false).unwrap();
+        assert_eq!(plan.report().received, 2);
+        assert_eq!(ids(&plan.upserts), ["b", "a"]);
+        assert_eq!(plan.upserts[0].content, json!({ "v": 3 }).to_string());
+    }
+
+    #[test]
+    fn test_documents_missing_from_the_source_are_deleted_in_id_order() {
+        let existing = fingerprint_of(&[
+            ("c", json!({})),
+            ("a", json!({})),
+            ("kept", json!({})),
+            ("b", json!({})),
+        ]);
+        let plan = plan("sync-deletes", existing, vec![doc("kept", json!({}))], false).unwrap();
+        assert_eq!(plan.deletes, ["a", "b", "c"]);
+        assert_eq!(
+            plan.report().clone(),
+            SyncReport {
+                index: "sync-deletes".to_string(),
+                dry_run: false,
+                existing: 4,
+                received: 1,
+                upserted: 0,
+                deleted: 3,
+                unchanged: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_an_empty_source_only_syncs_into_an_empty_index() {
+        let plan = plan("sync-empty", IndexFingerprint::new(), Vec::new(), false).unwrap();
+        let (report, batches) = execute(plan, None);
+        assert_eq!(report.unwrap(), SyncReport { index: "sync-empty".to_string(), ..SyncReport::default() });
+        assert!(batches.is_empty());
+
+        let existing = fingerprint_of(&[("1", json!({})), ("2", json!({}))]);
+        match super::plan("sync-empty", existing, Vec::new(), false) {
+            Err(SearchError::InvalidQuery(message)) => assert_eq!(
+                message,
+                "Refusing to sync an empty source into sync-empty, which would delete all 2 documents"
+            ),
+            other => panic!("expected an invalid query error, got {:?}", other.map(|plan| plan.report)),
+        }
+    }
+
+    #[test]
+    fn test_invalid_source_documents_fail_the_plan() {
+        let source = vec![doc("1", json!({})), Doc { id: "7".to_string(), content: "{\"title\":".to_string() }];
+        match plan("sync-invalid", IndexFingerprint::new(), source, false) {
+            Err(SearchError::InvalidQuery(message)) => assert!(message.starts_with("Document 7 has invalid JSON"), "{}", message),
+            other => panic!("expected an invalid query error, got {:?}", other.map(|plan| plan.report)),
+        }
+    }
+
+    #[test]
+    fn test_documents_are_compared_after_the_ingest_pipeline_and_sent_as_received() {
+        let pipeline = IngestPipeline::from_json(r#"[{"type": "remove", "fields": ["ssn"]}]"#).unwrap();
+        crate::ingest::set_pipeline("sync-pipeline", Some(pipeline)).unwrap();
+
+        let existing = fingerprint_of(&[("1", json!({ "name": "Ada" })), ("2", json!({ "name": "Alan" }))]);
+        let source = vec![
+            doc("1", json!({ "name": "Ada", "ssn": "078-05-1120" })),
+            doc("2", json!({ "name": "Alan Turing", "ssn": "219-09-9999" })),
+        ];
+        let plan = plan("sync-pipeline", existing, source, false);
+        crate::ingest::set_pipeline("sync-pipeline", None).unwrap();
+
+        let plan = plan.unwrap();
+        assert_eq!(plan.report().unchanged, 1);
+        assert_eq!(ids(&plan.upserts), ["2"]);
+        assert!(plan.upserts[0].content.contains("219-09-9999"), "the provider runs the pipeline when writing");
+    }
+
+    #[test]
+    fn test_writes_go_in_batches_with_upserts_first() {
+        let mut existing = IndexFingerprint::new();
+        for i in 0..501 {
+            existing.add(&format!("old-{:03}", i), &json!({}));
+        }
+        let source: Vec<Doc> = (0..1_201).map(|i| doc(&format!("new-{:04}", i), json!({ "n": i }))).collect();
+        let (report, batches) = execute(plan("sync-batches", existing, source, false).unwrap(), None);
+
+        let sizes: Vec<(usize, usize)> = batches.iter().map(|batch| (batch.upserts.len(), batch.deletes.len())).collect();
+        assert_eq!(sizes, [(500, 0), (500, 0), (201, 0), (0, 500), (0, 1)]);
+        assert_eq!(batches[3].deletes[0], "old-000");
+        assert_eq!(batches[4].deletes, ["old-500"]);
+        let report = report.unwrap();
+        assert_eq!((report.upserted, report.deleted), (1_201, 501));
+    }
+
+    #[test]
+    fn test_a_failed_batch_stops_the_sync() {
+        let existing = fingerprint_of(&[("gone", json!({}))]);
+        let source: Vec<Doc> = (0..1_000).map(|i| doc(&i.to_string(), json!({}))).collect();
+        let (result, batches) = execute(plan("sync-failure", existing, source, false).unwrap(), Some(1));
+        assert_eq!(result, Err("batch failed".to_string()));
+        assert_eq!(batches.len(), 2, "the deletes are not attempted after a failed upsert batch");
+    }
+
+    #[test]
+    fn test_dry_runs_count_without_writing() {
+        let existing = fingerprint_of(&[("1", json!({ "title": "Dune" })), ("2", json!({}))]);
+        let source = vec![doc("1", json!({ "title": "Dune Messiah" })), doc("3", json!({}))];
+        let (report, batches) = execute(plan("sync-dry-run", existing, source, true).unwrap(), Some(0));
+        assert!(batches.is_empty());
+        let report = report.unwrap();
+        assert!(report.dry_run);
+        assert_eq!((report.upserted, report.deleted, report.unchanged), (2, 1, 0));
+    }
+}
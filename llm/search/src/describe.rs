@@ -0,0 +1,135 @@
+//! Machine-readable description of a provider
+//!
+//! An orchestrating component that can reach several providers needs to know,
+//! at runtime, which of them handles a query best. [`describe`] gathers what
+//! it needs in one document: the provider's [`CapabilityMatrix`], the version
+//! of the component and of the server behind it, the limits configured for
+//! this worker, and how unsupported features degrade under the selected
+//! configuration profile.
+
+use serde::{Deserialize, Serialize};
+use crate::cache::{CACHE_BUDGET_ENV, DEFAULT_CACHE_BUDGET_BYTES};
+use crate::capabilities::{CapabilityMatrix, DegradationStrategy};
+use crate::config::env_helpers::get_env_parsed;
+use crate::config::{ConfigProfile, DegradationMode};
+use crate::query::PatternLimits;
+use crate::quota::{QuotaConfig, QuotaLimits};
+use crate::response_limits::{OversizePolicy, ResponseLimits};
+use crate::tasks::Backpressure;
+use crate::version::ServerVersion;
+
+/// Version of the search components, shared by every provider
+pub const COMPONENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Everything an orchestrator needs to route queries to a provider
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderDescription {
+    pub provider: String,
+
+    pub component_version: String,
+
+    /// Version reported by the server, `None` when it could not be detected
+    pub server_version: Option<String>,
+
+    pub capabilities: CapabilityMatrix,
+
+    pub limits: ConfiguredLimits,
+
+    /// Degradation mode of the selected configuration profile
+    pub degradation_mode: DegradationMode,
+
+    /// Fallbacks used for unsupported features under that mode
+    pub degradation: DegradationStrategy,
+}
+
+/// Limits configured for this worker through the environment
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConfiguredLimits {
+    pub max_response_bytes: usize,
+
+    pub oversize_policy: OversizePolicy,
+
+    pub max_leading_wildcards: usize,
+
+    pub max_pattern_length: usize,
+
+    /// Quota of tenants without their own entry
+    pub default_quota: QuotaLimits,
+
+    /// Tenants with their own quota
+    pub quota_tenants: Vec<String>,
+
+    /// Pending indexing tasks allowed before writes are refused
+    pub max_pending_tasks: Option<u64>,
+
+    pub cache_budget_bytes: usize,
+}
+
+impl ConfiguredLimits {
+    pub fn from_env() -> Self {
+        let response = ResponseLimits::from_env();
+        let patterns = PatternLimits::from_env();
+        let quota = QuotaConfig::from_env();
+        let mut quota_tenants: Vec<String> = quota.tenants.into_keys().collect();
+        quota_tenants.sort();
+
+        Self {
+            max_response_bytes: response.max_bytes,
+            oversize_policy: response.policy,
+            max_leading_wildcards: patterns.max_leading_wildcards,
+            max_pattern_length: patterns.max_pattern_length,
+            default_quota: quota.defaults,
+            quota_tenants,
+            max_pending_tasks: Backpressure::from_env().max_pending,
+            cache_budget_bytes: get_env_parsed::<usize>(CACHE_BUDGET_ENV)
+                .ok()
+                .flatten()
+                .unwrap_or(DEFAULT_CACHE_BUDGET_BYTES),
+        }
+    }
+}
+
+/// Describe the provider of `matrix`, running against a server of `server_version`
+///
+/// A configuration profile that fails to load leaves the default degradation
+/// mode; its errors surface when the client configuration is loaded.
+pub fn describe(mut matrix: CapabilityMatrix, server_version: Option<ServerVersion>) -> ProviderDescription {
+    let server_version = server_version.map(|version| version.to_string());
+    if server_version.is_some() {
+        matrix.provider_version = server_version.clone();
+    }
+    let degradation_mode = match ConfigProfile::active(&matrix.provider_name) {
+        Ok(Some(profile)) => profile.degradation,
+        _ => DegradationMode::default(),
+    };
+
+    ProviderDescription {
+        provider: matrix.provider_name.clone(),
+        component_version: COMPONENT_VERSION.to_string(),
+        server_version,
+        capabilities: matrix,
+        limits: ConfiguredLimits::from_env(),
+        degradation_mode,
+        degradation: degradation_mode.apply(DegradationStrategy::default()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capabilities::typesense_capability_matrix;
+
+    #[test]
+    fn test_describe_fills_versions_and_limits() {
+        let description = describe(typesense_capability_matrix(), Some(ServerVersion::new(27, 1, 0)));
+        assert_eq!(description.provider, "typesense");
+        assert_eq!(description.server_version.as_deref(), Some("27.1.0"));
+        assert_eq!(description.capabilities.provider_version.as_deref(), Some("27.1.0"));
+
+        let json = serde_json::to_value(&description).unwrap();
+        assert_eq!(json["component_version"], COMPONENT_VERSION);
+        assert_eq!(json["degradation_mode"], "lenient");
+        assert_eq!(json["limits"]["oversize_policy"], "truncate");
+        assert_eq!(json["capabilities"]["provider_name"], "typesense");
+    }
+}
@@ -0,0 +1,239 @@
+//! Dry runs of destructive operations
+//!
+//! `delete_index`, `delete_by_query` and `clear_index` take a `dry_run` flag
+//! and return an [`OperationPlan`]. Either way the provider first validates the
+//! target and counts the documents the operation would remove; a dry run stops
+//! there, so a production worker can log or confirm the plan before a second
+//! call carries it out.
+
+use std::future::Future;
+use log::info;
+use serde::{Deserialize, Serialize};
+use crate::error::{SearchError, SearchResult};
+use crate::telemetry::SpanOutput;
+
+/// What a destructive operation does, or would do on a dry run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OperationPlan {
+    pub provider: String,
+    pub operation: String,
+    pub index: String,
+
+    /// Filter selecting the documents of a delete by query
+    pub filter: Option<String>,
+
+    /// Documents removed, when the provider could count them
+    ///
+    /// Before execution this is the estimate; afterwards it is the count the
+    /// provider reported, if it reports one.
+    pub affected_documents: Option<u64>,
+
+    /// Requests sent to the provider, in order
+    pub steps: Vec<String>,
+
+    pub warnings: Vec<String>,
+
+    pub dry_run: bool,
+
+    /// Whether the operation was carried out
+    pub executed: bool,
+}
+
+impl OperationPlan {
+    pub fn new(provider: &str, operation: &str, index: &str, dry_run: bool) -> Self {
+        Self {
+            provider: provider.to_string(),
+            operation: operation.to_string(),
+            index: index.to_string(),
+            filter: None,
+            affected_documents: None,
+            steps: Vec::new(),
+            warnings: Vec::new(),
+            dry_run,
+            executed: false,
+        }
+    }
+
+    pub fn with_filter(mut self, filter: impl Into<String>) -> Self {
+        self.filter = Some(filter.into());
+        self
+    }
+
+    /// Set the estimated number of affected documents, warning when it is unknown or zero
+    pub fn with_affected_documents(mut self, count: Option<u64>) -> Self {
+        match count {
+            None => self.warnings.push("The provider did not report a document count".to_string()),
+            Some(0) => self.warnings.push("No documents are affected".to_string()),
+            Some(_) => {}
+        }
+        self.affected_documents = count;
+        self
+    }
+
+    pub fn step(mut self, step: impl Into<String>) -> Self {
+        self.steps.push(step.into());
+        self
+    }
+}
+
+impl SpanOutput for OperationPlan {}
+
+/// Reject index names that would make a destructive request hit more than one index
+///
+/// Elasticsearch and OpenSearch expand wildcards, comma-separated lists and
+/// `_all` in the path of a delete.
+pub fn validate_target(index: &str) -> SearchResult<()> {
+    let index = index.trim();
+    if index.is_empty() {
+        return Err(SearchError::invalid_query("Index name must not be empty"));
+    }
+    if index == "_all" || index.contains(['*', '?', ',']) {
+        return Err(SearchError::invalid_query(format!(
+            "Index name {} matches several indexes; destructive operations take a single index",
+            index
+        )));
+    }
+    Ok(())
+}
+
+/// Reject a delete by query whose filters would select every document
+pub fn validate_filters(filters: &[String]) -> SearchResult<()> {
+    if filters.iter().all(|f| f.trim().is_empty()) {
+        return Err(SearchError::invalid_query(
+            "delete_by_query needs at least one filter; use clear_index to remove all documents",
+        ));
+    }
+    Ok(())
+}
+
+/// Carry out `run` unless `plan` is a dry run
+///
+/// `run` may return the number of documents the provider reports as removed,
+/// which replaces the estimate.
+pub async fn execute<E, Fut>(mut plan: OperationPlan, run: Fut) -> Result<OperationPlan, E>
+where
+    Fut: Future<Output = Result<Option<u64>, E>>,
+{
+    if plan.dry_run {
+        info!(
+            "Dry run of {} on {} index {}: {} document(s) affected",
+            plan.operation,
+            plan.provider,
+            plan.index,
+            plan.affected_documents.map_or_else(|| "unknown".to_string(), |n| n.to_string())
+        );
+        return Ok(plan);
+    }
+
+    if let Some(removed) = run.await? {
+        plan.affected_documents = Some(removed);
+    }
+    plan.executed = true;
+    Ok(plan)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    fn message(result: SearchResult<()>) -> String {
+        match result {
+            Err(SearchError::InvalidQuery(message)) => message,
+            other => panic!("expected an invalid query error, got {:?}", other),
+        }
+    }
+
+    fn plan(dry_run: bool) -> OperationPlan {
+        OperationPlan::new("typesense", "clear_index", "books", dry_run)
+            .with_affected_documents(Some(42))
+            .step("DELETE /collections/books/documents?truncate=true")
+    }
+
+    fn run(plan: OperationPlan, removed: Result<Option<u64>, SearchError>) -> (Result<OperationPlan, SearchError>, bool) {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let ran = Cell::new(false);
+        let result = runtime.block_on(execute(plan, async {
+            ran.set(true);
+            removed
+        }));
+        (result, ran.get())
+    }
+
+    #[test]
+    fn test_single_indexes_are_valid_targets() {
+        for name in ["books", " books ", ".kibana_1", "logs-2025.01.01", "_alliance"] {
+            assert!(validate_target(name).is_ok(), "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn test_targets_matching_several_indexes_are_refused() {
+        for name in ["_all", " _all ", "books*", "*", "books,films", "b?oks"] {
+            assert!(message(validate_target(name)).ends_with("destructive operations take a single index"), "{:?}", name);
+        }
+        assert_eq!(
+            message(validate_target("books,films")),
+            "Index name books,films matches several indexes; destructive operations take a single index"
+        );
+        assert_eq!(message(validate_target(" \t")), "Index name must not be empty");
+    }
+
+    #[test]
+    fn test_delete_by_query_needs_a_non_blank_filter() {
+        assert!(validate_filters(&[" ".to_string(), "genre:fantasy".to_string()]).is_ok());
+        for filters in [Vec::new(), vec![String::new()], vec![" ".to_string(), "\n".to_string()]] {
+            assert_eq!(
+                message(validate_filters(&filters)),
+                "delete_by_query needs at least one filter; use clear_index to remove all documents"
+            );
+        }
+    }
+
+    #[test]
+    fn test_unknown_or_zero_counts_are_warned_about() {
+        let warnings = |count| OperationPlan::new("meilisearch", "delete_index", "books", true).with_affected_documents(count).warnings;
+        assert_eq!(warnings(None), ["The provider did not report a document count"]);
+        assert_eq!(warnings(Some(0)), ["No documents are affected"]);
+        assert!(warnings(Some(1)).is_empty());
+    }
+
+    #[test]
+    fn test_plans_keep_their_filter_and_steps_in_order() {
+        let plan = OperationPlan::new("elasticsearch", "delete_by_query", "books", false)
+            .with_filter("year < 1900")
+            .step("POST /books/_count")
+            .step("POST /books/_delete_by_query");
+        assert_eq!(plan.filter.as_deref(), Some("year < 1900"));
+        assert_eq!(plan.steps, ["POST /books/_count", "POST /books/_delete_by_query"]);
+        assert!(!plan.executed);
+    }
+
+    #[test]
+    fn test_dry_runs_never_start_the_operation() {
+        let (result, ran) = run(plan(true), Err(SearchError::Timeout { remaining_ms: None }));
+        assert!(!ran);
+        let planned = result.unwrap();
+        assert_eq!(planned, plan(true), "a dry run returns the plan unchanged");
+        assert!(!planned.executed);
+    }
+
+    #[test]
+    fn test_reported_counts_replace_the_estimate() {
+        let (result, ran) = run(plan(false), Ok(Some(40)));
+        assert!(ran);
+        let executed = result.unwrap();
+        assert!(executed.executed);
+        assert_eq!(executed.affected_documents, Some(40));
+
+        let (result, _) = run(plan(false), Ok(None));
+        assert_eq!(result.unwrap().affected_documents, Some(42), "the estimate stays when nothing is reported");
+    }
+
+    #[test]
+    fn test_failed_operations_return_the_provider_error() {
+        let (result, ran) = run(plan(false), Err(SearchError::Timeout { remaining_ms: Some(0) }));
+        assert!(ran);
+        assert!(matches!(result, Err(SearchError::Timeout { remaining_ms: Some(0) })));
+    }
+}
@@ -290,11 +290,11 @@ impl<'a> DurableBatchExecutor<'a> {
                     self.state.failed_items.push(FailedItem {
                         item_id: self.state.processed_items.to_string(),
                         error_message: e.to_string(),
-                        retryable: matches!(e, SearchError::Timeout | SearchError::RateLimited | SearchError::Internal(_)),
+                        retryable: matches!(e, SearchError::Timeout { .. } | SearchError::RateLimited { .. } | SearchError::Backpressure { .. } | SearchError::Unavailable { .. } | SearchError::Internal(_)),
                     });
                     
                     // For retryable errors, add to remaining items
-                    if matches!(e, SearchError::Timeout | SearchError::RateLimited | SearchError::Internal(_)) {
+                    if matches!(e, SearchError::Timeout { .. } | SearchError::RateLimited { .. } | SearchError::Backpressure { .. } | SearchError::Unavailable { .. } | SearchError::Internal(_)) {
                         remaining_items.push(item_clone);
                     }
                 }
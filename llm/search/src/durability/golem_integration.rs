@@ -384,8 +384,10 @@ pub struct ProcessingResults<T> {
 /// Check if an error is retryable
 fn is_retryable_error(error: &SearchError) -> bool {
     matches!(error, 
-        SearchError::Timeout | 
-        SearchError::RateLimited | 
+        SearchError::Timeout { .. } | 
+        SearchError::RateLimited { .. } | 
+        SearchError::Backpressure { .. } | 
+        SearchError::Unavailable { .. } | 
         SearchError::Internal(_)
     )
 }
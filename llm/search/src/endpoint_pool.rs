@@ -0,0 +1,406 @@
+//! Endpoint pools for multi-node clusters
+//!
+//! `SEARCH_PROVIDER_ENDPOINT` (and the provider-specific endpoint variables)
+//! may list several comma-separated nodes. Requests are spread round-robin over
+//! the healthy ones. A node that fails to answer, or answers with a gateway
+//! error, is marked down for `SEARCH_ENDPOINT_COOLDOWN_MS` and idempotent
+//! requests move on to the next node; a write is never sent twice.
+//!
+//! Pools are kept per endpoint list for the lifetime of the worker, so node
+//! health survives the provider being recreated on every invocation.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use anyhow::anyhow;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use crate::circuit_breaker;
+use crate::config::env_helpers::get_env_parsed;
+use crate::http::{self, Method, Response, Url};
+
+/// Environment variable holding how long a failed node is skipped, in milliseconds
+pub const ENDPOINT_COOLDOWN_ENV: &str = "SEARCH_ENDPOINT_COOLDOWN_MS";
+
+/// Default time a failed node is skipped
+pub const DEFAULT_ENDPOINT_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// Suffixes of POST paths that only read, so they may be retried on another node
+const READ_ONLY_POST_SUFFIXES: &[&str] =
+    &["_search", "_msearch", "_count", "_mget", "/search", "/documents/fetch", "/query", "/browse", "/objects"];
+
+#[derive(Debug)]
+struct Node {
+    url: Url,
+    down_until: Mutex<Option<Instant>>,
+}
+
+impl Node {
+    fn down_until(&self) -> Option<Instant> {
+        self.down_until.lock().ok().and_then(|d| *d).filter(|until| *until > Instant::now())
+    }
+}
+
+/// Health of one node, as reported by [`EndpointPool::status`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NodeStatus {
+    pub url: String,
+    pub healthy: bool,
+
+    /// Time left before a down node is tried again
+    pub down_for_ms: Option<u64>,
+}
+
+/// Nodes of one cluster with their health
+#[derive(Debug)]
+pub struct EndpointPool {
+    nodes: Vec<Node>,
+    next: AtomicUsize,
+    cooldown: Duration,
+}
+
+impl EndpointPool {
+    /// Parse a comma-separated list of node URLs
+    pub fn parse(spec: &str, cooldown: Duration) -> anyhow::Result<Self> {
+        let nodes = spec
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|s| {
+                // Without the trailing slash `join` would replace the last path segment
+                let s = if s.ends_with('/') { s.to_string() } else { format!("{}/", s) };
+                Url::parse(&s)
+                    .map(|url| Node { url, down_until: Mutex::new(None) })
+                    .map_err(|e| anyhow!("Invalid endpoint URL {}: {}", s, e))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        if nodes.is_empty() {
+            return Err(anyhow!("No endpoint configured"));
+        }
+        Ok(Self { nodes, next: AtomicUsize::new(0), cooldown })
+    }
+
+    /// First node, used to key per-cluster caches
+    pub fn primary(&self) -> &Url {
+        &self.nodes[0].url
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Nodes to try for one request, in order
+    ///
+    /// Healthy nodes come first, rotating the starting point on every call;
+    /// nodes that are down follow, soonest to recover first, so a request is
+    /// still attempted when the whole cluster looks down.
+    pub fn candidates(&self) -> Vec<Url> {
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        let count = self.nodes.len();
+        let mut healthy = Vec::with_capacity(count);
+        let mut down = Vec::new();
+        for i in 0..count {
+            let node = &self.nodes[(start + i) % count];
+            match node.down_until() {
+                Some(until) => down.push((until, node.url.clone())),
+                None => healthy.push(node.url.clone()),
+            }
+        }
+        down.sort_by_key(|(until, _)| *until);
+        healthy.extend(down.into_iter().map(|(_, url)| url));
+        healthy
+    }
+
+    /// Skip `url` until the cooldown has passed
+    pub fn mark_down(&self, url: &Url) {
+        if let Some(node) = self.nodes.iter().find(|n| &n.url == url) {
+            if let Ok(mut down_until) = node.down_until.lock() {
+                *down_until = Some(Instant::now() + self.cooldown);
+            }
+        }
+    }
+
+    /// Put `url` back into rotation
+    pub fn mark_up(&self, url: &Url) {
+        if let Some(node) = self.nodes.iter().find(|n| &n.url == url) {
+            if let Ok(mut down_until) = node.down_until.lock() {
+                *down_until = None;
+            }
+        }
+    }
+
+    pub fn status(&self) -> Vec<NodeStatus> {
+        let now = Instant::now();
+        self.nodes
+            .iter()
+            .map(|node| {
+                let down_for = node.down_until().map(|until| until.saturating_duration_since(now));
+                NodeStatus {
+                    url: node.url.to_string(),
+                    healthy: down_for.is_none(),
+                    down_for_ms: down_for.map(|d| d.as_millis() as u64),
+                }
+            })
+            .collect()
+    }
+
+    /// Send a request to `path`, failing over to other nodes where that is safe
+    ///
+    /// `send` builds and sends the request for the full URL on one node. Every
+    /// attempt is reported to the circuit breaker; nodes with an open circuit
+    /// are skipped.
+    pub fn send<F>(&self, method: &Method, path: &str, mut send: F) -> anyhow::Result<Response>
+    where
+        F: FnMut(Url) -> Result<Response, http::Error>,
+    {
+        let breaker = circuit_breaker::global();
+        let retryable = is_idempotent(method, path);
+        let candidates = self.candidates();
+        let mut last_error = None;
+
+        for (attempt, base) in candidates.iter().enumerate() {
+            let endpoint = base.as_str();
+            if let Err(e) = breaker.check(endpoint) {
+                last_error = Some(anyhow!(e));
+                continue;
+            }
+
+            let url = base.join(path).map_err(|e| anyhow!("Failed to build URL: {}", e))?;
+            let has_next = attempt + 1 < candidates.len();
+            match send(url) {
+                Ok(response) => {
+                    let status = response.status().as_u16();
+                    breaker.record_status(endpoint, status);
+                    if !is_node_failure(status) {
+                        self.mark_up(base);
+                        return Ok(response);
+                    }
+
+                    self.mark_down(base);
+                    if !(retryable && has_next) {
+                        return Ok(response);
+                    }
+                    warn!("Node {} answered {} to {} {}, trying the next node", endpoint, status, method, path);
+                    last_error = Some(anyhow!("HTTP {} from {}", status, endpoint));
+                }
+                Err(e) => {
+                    breaker.record_failure(endpoint, &e.to_string());
+                    self.mark_down(base);
+                    if !retryable {
+                        return Err(anyhow!("Request failed: {}", e));
+                    }
+                    if has_next {
+                        warn!("Node {} failed {} {}: {}, trying the next node", endpoint, method, path, e);
+                    }
+                    last_error = Some(anyhow!("Request failed: {}", e));
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| anyhow!("No endpoint available")))
+    }
+}
+
+/// Whether a request can be repeated on another node without side effects
+pub fn is_idempotent(method: &Method, path: &str) -> bool {
+    match *method {
+        Method::GET | Method::HEAD | Method::OPTIONS | Method::PUT | Method::DELETE => true,
+        Method::POST => {
+            let path = path.split('?').next().unwrap_or(path).trim_end_matches('/');
+            READ_ONLY_POST_SUFFIXES.iter().any(|suffix| path.ends_with(suffix))
+        }
+        _ => false,
+    }
+}
+
+/// Statuses that mean the node, not the request, is at fault
+fn is_node_failure(status: u16) -> bool {
+    matches!(status, 502..=504)
+}
+
+/// Shared pool for a comma-separated endpoint list
+pub fn pool(spec: &str) -> anyhow::Result<Arc<EndpointPool>> {
+    static POOLS: OnceLock<Mutex<HashMap<String, Arc<EndpointPool>>>> = OnceLock::new();
+    let pools = POOLS.get_or_init(|| Mutex::new(HashMap::new()));
+    let mut pools = pools.lock().map_err(|_| anyhow!("Endpoint pool registry poisoned"))?;
+
+    if let Some(pool) = pools.get(spec) {
+        return Ok(pool.clone());
+    }
+    let cooldown = get_env_parsed::<u64>(ENDPOINT_COOLDOWN_ENV)
+        .ok()
+        .flatten()
+        .map_or(DEFAULT_ENDPOINT_COOLDOWN, Duration::from_millis);
+    let pool = Arc::new(EndpointPool::parse(spec, cooldown)?);
+    pools.insert(spec.to_string(), pool.clone());
+    Ok(pool)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{HeaderMap, StatusCode};
+
+    fn pool(spec: &str) -> EndpointPool {
+        EndpointPool::parse(spec, Duration::from_secs(60)).unwrap()
+    }
+
+    fn hosts(urls: &[Url]) -> Vec<&str> {
+        urls.iter().map(|url| url.host_str().unwrap()).collect()
+    }
+
+    fn reply(url: Url, status: u16) -> Result<Response, http::Error> {
+        Ok(Response::new(StatusCode::from_u16(status).unwrap(), HeaderMap::new(), Vec::new(), url))
+    }
+
+    #[test]
+    fn test_healthy_nodes_take_turns_and_down_nodes_go_last() {
+        let pool = pool("http://a:9200, http://b:9200/,http://c:9200");
+        assert_eq!(pool.primary().as_str(), "http://a:9200/");
+        assert_eq!(hosts(&pool.candidates()), ["a", "b", "c"]);
+        assert_eq!(hosts(&pool.candidates()), ["b", "c", "a"]);
+
+        let b = Url::parse("http://b:9200/").unwrap();
+        pool.mark_down(&b);
+        assert_eq!(hosts(&pool.candidates()), ["c", "a", "b"]);
+        assert_eq!(hosts(&pool.candidates()), ["a", "c", "b"]);
+        assert!(!pool.status()[1].healthy);
+        assert!(pool.status()[1].down_for_ms.unwrap() > 59_000);
+
+        pool.mark_up(&b);
+        assert!(pool.status().iter().all(|node| node.healthy));
+        assert_eq!(hosts(&pool.candidates()), ["b", "c", "a"]);
+    }
+
+    #[test]
+    fn test_when_every_node_is_down_the_soonest_to_recover_comes_first() {
+        let pool = pool("http://a:9200,http://b:9200,http://c:9200");
+        for host in ["c", "a", "b"] {
+            pool.mark_down(&Url::parse(&format!("http://{}:9200/", host)).unwrap());
+            std::thread::sleep(Duration::from_millis(2));
+        }
+
+        for _ in 0..3 {
+            assert_eq!(hosts(&pool.candidates()), ["c", "a", "b"]);
+        }
+        assert!(pool.status().iter().all(|node| !node.healthy));
+    }
+
+    #[test]
+    fn test_nodes_recover_once_the_cooldown_has_passed() {
+        let pool = EndpointPool::parse("http://a:9200,http://b:9200", Duration::from_millis(5)).unwrap();
+        pool.mark_down(pool.primary());
+        std::thread::sleep(Duration::from_millis(10));
+        assert!(pool.status().iter().all(|node| node.healthy));
+    }
+
+    #[test]
+    fn test_paths_are_joined_under_the_base_path() {
+        let pool = pool("http://pool-join:9200/es");
+        let mut sent = Vec::new();
+        pool.send(&Method::GET, "books/_doc/1", |url| {
+            sent.push(url.to_string());
+            reply(url, 200)
+        })
+        .unwrap();
+        assert_eq!(sent, ["http://pool-join:9200/es/books/_doc/1"]);
+    }
+
+    #[test]
+    fn test_reads_fail_over_on_connection_and_gateway_errors() {
+        let pool = pool("http://pool-read-a:9200,http://pool-read-b:9200,http://pool-read-c:9200");
+        let mut sent = Vec::new();
+        let response = pool
+            .send(&Method::POST, "books/_search", |url| {
+                sent.push(url.host_str().unwrap().to_string());
+                match url.host_str() {
+                    Some("pool-read-a") => Err(http::Error::connect("connection refused")),
+                    Some("pool-read-b") => reply(url, 502),
+                    _ => reply(url, 200),
+                }
+            })
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(sent, ["pool-read-a", "pool-read-b", "pool-read-c"]);
+        let healthy: Vec<_> = pool.status().iter().map(|node| node.healthy).collect();
+        assert_eq!(healthy, [false, false, true]);
+    }
+
+    #[test]
+    fn test_writes_are_not_sent_to_a_second_node() {
+        let pool = pool("http://pool-write-a:9200,http://pool-write-b:9200");
+        let mut sent = 0;
+        let err = pool
+            .send(&Method::POST, "_bulk", |_| {
+                sent += 1;
+                Err(http::Error::connect("connection reset"))
+            })
+            .unwrap_err();
+        assert_eq!(sent, 1);
+        assert!(err.to_string().contains("connection reset"), "{}", err);
+
+        let mut sent = 0;
+        let response = pool
+            .send(&Method::POST, "_bulk", |url| {
+                sent += 1;
+                reply(url, 503)
+            })
+            .unwrap();
+        assert_eq!(sent, 1);
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[test]
+    fn test_last_gateway_error_is_returned_as_the_response() {
+        let pool = pool("http://pool-gateway-a:9200,http://pool-gateway-b:9200");
+        let mut sent = 0;
+        let response = pool
+            .send(&Method::GET, "books/_doc/1", |url| {
+                sent += 1;
+                reply(url, 504)
+            })
+            .unwrap();
+        assert_eq!(sent, 2);
+        assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+    }
+
+    #[test]
+    fn test_client_errors_do_not_mark_the_node_down() {
+        let pool = pool("http://pool-client-a:9200,http://pool-client-b:9200");
+        let mut sent = 0;
+        let response = pool
+            .send(&Method::GET, "books/_doc/missing", |url| {
+                sent += 1;
+                reply(url, 404)
+            })
+            .unwrap();
+        assert_eq!(sent, 1);
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert!(pool.status().iter().all(|node| node.healthy));
+    }
+
+    #[test]
+    fn test_empty_endpoint_lists_are_rejected() {
+        assert!(EndpointPool::parse(" , ", Duration::ZERO).is_err());
+        assert!(EndpointPool::parse("not a url", Duration::ZERO).is_err());
+    }
+
+    #[test]
+    fn test_only_read_only_posts_are_idempotent() {
+        assert!(is_idempotent(&Method::GET, "books/_doc/1"));
+        assert!(is_idempotent(&Method::PUT, "books"));
+        assert!(is_idempotent(&Method::POST, "books/_search?scroll=1m"));
+        assert!(is_idempotent(&Method::POST, "indexes/books/search"));
+        assert!(is_idempotent(&Method::POST, "1/indexes/books/query"));
+        assert!(!is_idempotent(&Method::POST, "_bulk"));
+        assert!(!is_idempotent(&Method::POST, "indexes/books/documents"));
+        assert!(!is_idempotent(&Method::PATCH, "collections/books"));
+    }
+}
@@ -11,6 +11,9 @@ pub enum SearchError {
     #[error("Index not found: {0}")]
     IndexNotFound(String),
     
+    #[error("Index already exists: {0}")]
+    IndexAlreadyExists(String),
+    
     #[error("Invalid query: {0}")]
     InvalidQuery(String),
     
@@ -20,11 +23,20 @@ pub enum SearchError {
     #[error("Internal error: {0}")]
     Internal(String),
     
-    #[error("Operation timed out")]
-    Timeout,
+    #[error("Operation timed out{}", .remaining_ms.map(|ms| format!(", {} ms of the deadline left", ms)).unwrap_or_default())]
+    Timeout { remaining_ms: Option<u64> },
+    
+    #[error("Rate limited{}", .retry_after_ms.map(|ms| format!(", retry after {} ms", ms)).unwrap_or_default())]
+    RateLimited { retry_after_ms: Option<u64> },
+    
+    #[error("Response too large: {size} bytes exceeds the {limit} byte limit")]
+    ResponseTooLarge { size: usize, limit: usize },
     
-    #[error("Rate limited")]
-    RateLimited,
+    #[error("Provider unavailable{}", .retry_after_ms.map(|ms| format!(", retry after {} ms", ms)).unwrap_or_default())]
+    Unavailable { retry_after_ms: Option<u64> },
+    
+    #[error("Indexing queue saturated with {queue_depth} pending tasks{}", .retry_after_ms.map(|ms| format!(", retry after {} ms", ms)).unwrap_or_default())]
+    Backpressure { queue_depth: u64, retry_after_ms: Option<u64> },
 }
 
 /// Result type alias for search operations
@@ -45,6 +57,11 @@ impl SearchError {
     pub fn index_not_found<S: Into<String>>(index_name: S) -> Self {
         Self::IndexNotFound(index_name.into())
     }
+    
+    /// Create an index already exists error
+    pub fn index_already_exists<S: Into<String>>(index_name: S) -> Self {
+        Self::IndexAlreadyExists(index_name.into())
+    }
 }
 
 // Conversion from anyhow::Error
@@ -61,17 +78,13 @@ impl From<serde_json::Error> for SearchError {
     }
 }
 
-// Conversion from reqwest::Error
-impl From<reqwest::Error> for SearchError {
-    fn from(err: reqwest::Error) -> Self {
+// Conversion from crate::http::Error; error statuses arrive as responses, not errors
+impl From<crate::http::Error> for SearchError {
+    fn from(err: crate::http::Error) -> Self {
         if err.is_timeout() {
-            Self::Timeout
-        } else if err.status() == Some(reqwest::StatusCode::TOO_MANY_REQUESTS) {
-            Self::RateLimited
-        } else if err.status() == Some(reqwest::StatusCode::NOT_FOUND) {
-            Self::IndexNotFound("HTTP 404".to_string())
-        } else if err.status() == Some(reqwest::StatusCode::BAD_REQUEST) {
-            Self::InvalidQuery(format!("HTTP 400: {}", err))
+            crate::deadline::timeout_error()
+        } else if err.is_builder() {
+            Self::InvalidQuery(err.to_string())
         } else {
             Self::Internal(err.to_string())
         }
@@ -81,7 +94,7 @@ impl From<reqwest::Error> for SearchError {
 // Conversion from tokio::time::error::Elapsed (timeout)
 impl From<tokio::time::error::Elapsed> for SearchError {
     fn from(_: tokio::time::error::Elapsed) -> Self {
-        Self::Timeout
+        crate::deadline::timeout_error()
     }
 }
 
@@ -0,0 +1,318 @@
+//! Index lifecycle events
+//!
+//! Providers report index creation and deletion and schema updates here, so
+//! that systems depending on the search topology, such as caches keyed by
+//! index or notification services, can react without polling. The
+//! `reindex_completed` and `migration_finished` kinds are part of the same
+//! contract; code running reindexes or migrations reports them with [`emit`].
+//!
+//! Events go to hooks registered in the worker with [`subscribe`] and, with
+//! `SEARCH_EVENT_WEBHOOK_URL` set, are POSTed there as JSON. Delivery is best
+//! effort: a failing webhook is logged and never fails the operation that
+//! emitted the event. `SEARCH_EVENT_WEBHOOK_EVENTS` limits the webhook to
+//! a comma-separated list of event kinds.
+
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use chrono::Utc;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::request_id;
+
+/// Environment variable holding the URL events are POSTed to
+pub const EVENT_WEBHOOK_URL_ENV: &str = "SEARCH_EVENT_WEBHOOK_URL";
+
+/// Environment variable holding a bearer token sent with webhook requests
+pub const EVENT_WEBHOOK_TOKEN_ENV: &str = "SEARCH_EVENT_WEBHOOK_TOKEN";
+
+/// Environment variable holding the event kinds sent to the webhook, comma-separated; all when unset
+pub const EVENT_WEBHOOK_EVENTS_ENV: &str = "SEARCH_EVENT_WEBHOOK_EVENTS";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LifecycleEventKind {
+    IndexCreated,
+    IndexDeleted,
+    SchemaUpdated,
+    ReindexCompleted,
+    MigrationFinished,
+}
+
+impl LifecycleEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LifecycleEventKind::IndexCreated => "index_created",
+            LifecycleEventKind::IndexDeleted => "index_deleted",
+            LifecycleEventKind::SchemaUpdated => "schema_updated",
+            LifecycleEventKind::ReindexCompleted => "reindex_completed",
+            LifecycleEventKind::MigrationFinished => "migration_finished",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LifecycleEvent {
+    pub kind: LifecycleEventKind,
+    pub provider: String,
+    pub index: String,
+
+    /// Time the change was applied (RFC 3339)
+    pub timestamp: String,
+    pub request_id: Option<String>,
+
+    /// Kind-specific data, such as the fields a schema update touched
+    pub details: Value,
+}
+
+/// Handle of a registered hook, used to remove it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HookId(u64);
+
+type Hook = Arc<dyn Fn(&LifecycleEvent) + Send + Sync>;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WebhookConfig {
+    pub url: Option<String>,
+    pub token: Option<String>,
+
+    /// Kinds sent to the webhook; empty sends all
+    pub kinds: Vec<LifecycleEventKind>,
+}
+
+impl WebhookConfig {
+    /// Read the webhook from the `SEARCH_EVENT_WEBHOOK_*` variables; unknown kinds are ignored
+    pub fn from_env() -> Self {
+        let kinds = std::env::var(EVENT_WEBHOOK_EVENTS_ENV)
+            .ok()
+            .map(|list| {
+                list.split(',')
+                    .filter_map(|kind| serde_json::from_value(Value::String(kind.trim().to_string())).ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self {
+            url: std::env::var(EVENT_WEBHOOK_URL_ENV).ok().filter(|url| !url.is_empty()),
+            token: std::env::var(EVENT_WEBHOOK_TOKEN_ENV).ok(),
+            kinds,
+        }
+    }
+
+    /// URL to send `kind` to, if the webhook wants it
+    pub fn target(&self, kind: LifecycleEventKind) -> Option<&str> {
+        self.url.as_deref().filter(|_| self.kinds.is_empty() || self.kinds.contains(&kind))
+    }
+}
+
+#[derive(Default)]
+struct Hooks {
+    next_id: u64,
+    hooks: Vec<(HookId, Hook)>,
+}
+
+fn hooks() -> MutexGuard<'static, Hooks> {
+    static HOOKS: OnceLock<Mutex<Hooks>> = OnceLock::new();
+    HOOKS.get_or_init(Default::default).lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn webhook() -> &'static WebhookConfig {
+    static WEBHOOK: OnceLock<WebhookConfig> = OnceLock::new();
+    WEBHOOK.get_or_init(WebhookConfig::from_env)
+}
+
+/// Call `hook` with every lifecycle event emitted in this worker
+pub fn subscribe(hook: impl Fn(&LifecycleEvent) + Send + Sync + 'static) -> HookId {
+    let mut hooks = hooks();
+    hooks.next_id += 1;
+    let id = HookId(hooks.next_id);
+    hooks.hooks.push((id, Arc::new(hook)));
+    id
+}
+
+pub fn unsubscribe(id: HookId) {
+    hooks().hooks.retain(|(hook_id, _)| *hook_id != id);
+}
+
+/// Report a lifecycle change to the hooks and the webhook
+pub fn emit(kind: LifecycleEventKind, provider: &str, index: &str, details: Value) {
+    let event = LifecycleEvent {
+        kind,
+        provider: provider.to_string(),
+        index: index.to_string(),
+        timestamp: Utc::now().to_rfc3339(),
+        request_id: request_id::current(),
+        details,
+    };
+
+    // Called outside the lock, so a hook may subscribe or emit in turn
+    let subscribed: Vec<Hook> = hooks().hooks.iter().map(|(_, hook)| hook.clone()).collect();
+    for hook in subscribed {
+        hook(&event);
+    }
+
+    if let Some(url) = webhook().target(kind) {
+        let body = serde_json::to_string(&event).unwrap_or_default();
+        post_webhook(url, webhook().token.as_deref(), body, &format!("{} event", kind.as_str()));
+    }
+}
+
+/// POST a JSON body to a webhook, logging rather than returning failures
+///
+/// `what` names the payload in log lines, such as "index_deleted event".
+pub fn post_webhook(url: &str, token: Option<&str>, body: String, what: &str) {
+    let mut request = crate::http::Client::new()
+        .post(url)
+        .header("Content-Type", "application/json");
+    if let Some(token) = token {
+        request = request.header("Authorization", format!("Bearer {}", token));
+    }
+
+    match request.body(body).send() {
+        Ok(response) if response.status().is_success() => debug!("Delivered {} to {}", what, url),
+        Ok(response) => warn!("Webhook {} rejected {}: {}", url, what, response.status()),
+        Err(e) => warn!("Failed to deliver {} to {}: {}", what, url, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    type Received = Arc<Mutex<Vec<LifecycleEvent>>>;
+
+    /// Subscribe a hook keeping the events of `index`; other tests emit concurrently
+    fn record(index: &'static str) -> (HookId, Received) {
+        let received: Received = Arc::default();
+        let sink = received.clone();
+        let id = subscribe(move |event| {
+            if event.index == index {
+                sink.lock().unwrap().push(event.clone());
+            }
+        });
+        (id, received)
+    }
+
+    fn kinds(received: &Received) -> Vec<LifecycleEventKind> {
+        received.lock().unwrap().iter().map(|event| event.kind).collect()
+    }
+
+    #[test]
+    fn test_kind_names_match_their_serialized_form() {
+        for kind in [
+            LifecycleEventKind::IndexCreated,
+            LifecycleEventKind::IndexDeleted,
+            LifecycleEventKind::SchemaUpdated,
+            LifecycleEventKind::ReindexCompleted,
+            LifecycleEventKind::MigrationFinished,
+        ] {
+            assert_eq!(serde_json::to_value(kind).unwrap(), kind.as_str());
+        }
+    }
+
+    #[test]
+    fn test_events_carry_the_emitting_operation() {
+        let (id, received) = record("events-details");
+        let scope = request_id::begin();
+        let request = scope.id().to_string();
+        emit(LifecycleEventKind::SchemaUpdated, "typesense", "events-details", json!({ "fields": ["year"] }));
+        drop(scope);
+        emit(LifecycleEventKind::IndexDeleted, "typesense", "events-details", Value::Null);
+        unsubscribe(id);
+
+        let received = received.lock().unwrap();
+        assert_eq!(received[0].request_id.as_deref(), Some(request.as_str()));
+        assert_eq!((received[0].provider.as_str(), &received[0].details), ("typesense", &json!({ "fields": ["year"] })));
+        assert!(chrono::DateTime::parse_from_rfc3339(&received[0].timestamp).is_ok());
+        assert_eq!(received[1].request_id, None, "emitted outside any operation");
+    }
+
+    #[test]
+    fn test_hooks_are_called_in_subscription_order() {
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let ids: Vec<HookId> = (0..3)
+            .map(|n| {
+                let order = order.clone();
+                subscribe(move |event| {
+                    if event.index == "events-order" {
+                        order.lock().unwrap().push(n);
+                    }
+                })
+            })
+            .collect();
+        emit(LifecycleEventKind::IndexCreated, "meilisearch", "events-order", Value::Null);
+        for id in &ids {
+            unsubscribe(*id);
+        }
+        assert_eq!(*order.lock().unwrap(), [0, 1, 2]);
+        assert!(ids[0] != ids[1] && ids[1] != ids[2]);
+    }
+
+    #[test]
+    fn test_unsubscribing_removes_only_that_hook() {
+        let (first, first_received) = record("events-unsubscribe");
+        let (second, second_received) = record("events-unsubscribe");
+        emit(LifecycleEventKind::IndexCreated, "meilisearch", "events-unsubscribe", Value::Null);
+        unsubscribe(first);
+        unsubscribe(first);
+        emit(LifecycleEventKind::IndexDeleted, "meilisearch", "events-unsubscribe", Value::Null);
+        unsubscribe(second);
+        emit(LifecycleEventKind::IndexCreated, "meilisearch", "events-unsubscribe", Value::Null);
+
+        assert_eq!(kinds(&first_received), [LifecycleEventKind::IndexCreated]);
+        assert_eq!(kinds(&second_received), [LifecycleEventKind::IndexCreated, LifecycleEventKind::IndexDeleted]);
+    }
+
+    #[test]
+    fn test_hooks_may_emit_and_subscribe_in_turn() {
+        let (id, received) = record("events-nested");
+        let nested = subscribe(|event| {
+            if event.index == "events-nested" && event.kind == LifecycleEventKind::MigrationFinished {
+                unsubscribe(subscribe(|_| {}));
+                emit(LifecycleEventKind::ReindexCompleted, "opensearch", "events-nested", Value::Null);
+            }
+        });
+        emit(LifecycleEventKind::MigrationFinished, "opensearch", "events-nested", Value::Null);
+        unsubscribe(nested);
+        unsubscribe(id);
+
+        assert_eq!(
+            kinds(&received),
+            [LifecycleEventKind::MigrationFinished, LifecycleEventKind::ReindexCompleted],
+            "the recording hook was subscribed first, so it sees the outer event first"
+        );
+    }
+
+    #[test]
+    fn test_webhook_kinds_limit_what_is_sent() {
+        let all = WebhookConfig { url: Some("http://hooks.local/search".to_string()), ..Default::default() };
+        assert_eq!(all.target(LifecycleEventKind::ReindexCompleted), Some("http://hooks.local/search"));
+
+        let some = WebhookConfig { kinds: vec![LifecycleEventKind::IndexDeleted], ..all };
+        assert!(some.target(LifecycleEventKind::IndexDeleted).is_some());
+        assert!(some.target(LifecycleEventKind::IndexCreated).is_none());
+
+        let no_url = WebhookConfig { kinds: vec![LifecycleEventKind::IndexDeleted], ..Default::default() };
+        assert!(no_url.target(LifecycleEventKind::IndexDeleted).is_none());
+    }
+
+    #[test]
+    fn test_webhook_settings_skip_unknown_kinds_and_an_empty_url() {
+        // The URL stays empty, so no other test's events are posted anywhere
+        std::env::set_var(EVENT_WEBHOOK_URL_ENV, "");
+        std::env::set_var(EVENT_WEBHOOK_TOKEN_ENV, "secret");
+        std::env::set_var(EVENT_WEBHOOK_EVENTS_ENV, " index_deleted,index-created,,SCHEMA_UPDATED, schema_updated ");
+        let config = WebhookConfig::from_env();
+        std::env::remove_var(EVENT_WEBHOOK_URL_ENV);
+        std::env::remove_var(EVENT_WEBHOOK_TOKEN_ENV);
+        std::env::remove_var(EVENT_WEBHOOK_EVENTS_ENV);
+
+        assert_eq!(
+            config,
+            WebhookConfig {
+                url: None,
+                token: Some("secret".to_string()),
+                kinds: vec![LifecycleEventKind::IndexDeleted, LifecycleEventKind::SchemaUpdated],
+            }
+        );
+    }
+}
@@ -0,0 +1,117 @@
+//! Normalized relevance explanations
+//!
+//! Every provider exposes scoring details in its own format (Lucene explain
+//! trees, Algolia ranking info, Meilisearch score details, Typesense
+//! text match info). When a query sets `SearchConfig::explain`, providers wrap
+//! their native details in an [`Explanation`] so callers can rely on a common
+//! envelope while still having access to the raw data.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::types::Json;
+
+/// Provider-agnostic envelope for per-hit scoring details
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Explanation {
+    /// Provider that produced the explanation, e.g. `elasticsearch`
+    pub provider: String,
+
+    /// Final relevance score as reported by the provider, if any
+    pub score: Option<f64>,
+
+    /// Short human-readable summary of the top-level scoring decision
+    pub summary: Option<String>,
+
+    /// Raw provider-specific scoring details
+    pub details: Value,
+}
+
+impl Explanation {
+    /// Build an explanation from raw provider details
+    pub fn new(provider: &str, score: Option<f64>, details: Value) -> Self {
+        let summary = details
+            .get("description")
+            .and_then(|d| d.as_str())
+            .map(|s| s.to_string());
+
+        Self {
+            provider: provider.to_string(),
+            score,
+            summary,
+            details,
+        }
+    }
+
+    /// Serialize to the JSON blob stored on `SearchHit::explanation`
+    pub fn to_json(&self) -> Option<Json> {
+        serde_json::to_string(self).ok()
+    }
+}
+
+/// Convenience helper: wrap optional raw details into a serialized explanation
+pub fn explanation_json(provider: &str, score: Option<f64>, details: Option<&Value>) -> Option<Json> {
+    details.and_then(|d| Explanation::new(provider, score, d.clone()).to_json())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn envelope(provider: &str, score: Option<f64>, details: Option<&Value>) -> Option<Value> {
+        explanation_json(provider, score, details).map(|json| serde_json::from_str(&json).unwrap())
+    }
+
+    #[test]
+    fn test_summary_is_the_top_level_lucene_description() {
+        let details = json!({
+            "value": 1.2,
+            "description": "sum of:",
+            "details": [{ "value": 1.2, "description": "weight(title:rust in 0)", "details": [] }]
+        });
+        let explanation = Explanation::new("elasticsearch", Some(1.2), details);
+        assert_eq!(explanation.summary.as_deref(), Some("sum of:"));
+    }
+
+    #[test]
+    fn test_details_without_a_text_description_have_no_summary() {
+        for details in [
+            json!({ "description": 3 }),
+            json!({ "words": { "order": 0, "matchingWords": 1 } }),
+            json!([{ "description": "inside an array" }]),
+            json!("description"),
+            Value::Null,
+        ] {
+            assert_eq!(Explanation::new("meilisearch", None, details.clone()).summary, None, "{}", details);
+        }
+    }
+
+    #[test]
+    fn test_native_details_are_kept_as_they_are() {
+        let details = json!({ "text_match": 578730123365187705u64, "text_match_info": { "tokens_matched": 2 } });
+        let explanation = envelope("typesense", None, Some(&details)).unwrap();
+        assert_eq!(
+            explanation,
+            json!({ "provider": "typesense", "score": null, "summary": null, "details": details })
+        );
+    }
+
+    #[test]
+    fn test_explanations_round_trip_through_the_hit_blob() {
+        let explanation = Explanation::new("algolia", Some(0.5), json!({ "description": "typo=0", "nbTypos": 0 }));
+        let parsed: Explanation = serde_json::from_str(&explanation.to_json().unwrap()).unwrap();
+        assert_eq!(parsed, explanation);
+    }
+
+    #[test]
+    fn test_non_finite_scores_are_sent_as_null() {
+        let explanation = envelope("elasticsearch", Some(f64::NAN), Some(&json!({}))).unwrap();
+        assert_eq!(explanation["score"], Value::Null);
+    }
+
+    #[test]
+    fn test_only_hits_with_details_get_an_explanation() {
+        assert_eq!(envelope("typesense", Some(1.0), None), None);
+        assert_eq!(envelope("typesense", Some(1.0), Some(&Value::Null)).unwrap()["details"], Value::Null);
+    }
+}
@@ -0,0 +1,674 @@
+//! Facet requests, including numeric range facets
+//!
+//! A facet is requested by field name, which counts documents per distinct
+//! value, or with explicit bucket boundaries, which counts documents per
+//! numeric range:
+//!
+//! ```text
+//! category
+//! price:[0-25, 25-50, 50+]
+//! created_at:date_histogram(week, Europe/Berlin)
+//! price:stats
+//! ```
+//!
+//! A bucket includes its lower bound and excludes its upper one, so `0-25`
+//! and `25-50` do not overlap. `50+` and `*-25` leave one end open, and
+//! negative bounds are written as is (`-10-0`).
+//!
+//! Elasticsearch and OpenSearch count ranges with a `range` aggregation and
+//! Typesense with facet ranges. Meilisearch only returns counts per distinct
+//! value, which [`bucket_facet_distribution`] sums into the buckets; since it
+//! returns at most `maxValuesPerFacet` values (100 by default), fields with
+//! more distinct values are undercounted.
+//!
+//! Date histograms count documents per calendar day, week (starting Monday)
+//! or month in a time zone, UTC unless one is given. Elasticsearch and
+//! OpenSearch use a `date_histogram` aggregation; elsewhere
+//! [`DateHistogram`] buckets the matching documents client-side, scanning at
+//! most [`MAX_SAMPLE_SCAN`](crate::sampling::MAX_SAMPLE_SCAN) of them. Bucket
+//! keys are ISO-8601 timestamps of the bucket start with the zone's offset,
+//! e.g. `2024-03-01T00:00:00+01:00`.
+//!
+//! Stats facets summarize a numeric field as `count`, `min`, `max`, `avg`
+//! and `sum`. Elasticsearch and OpenSearch use a `stats` aggregation; on
+//! Meilisearch and Typesense [`NumericStats`] reads every matching document,
+//! so they are only computed when the result set has at most
+//! [`MAX_CLIENT_STATS_HITS`] matches and are left out, with a warning,
+//! otherwise.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use chrono::{DateTime, Datelike, Days, LocalResult, Months, NaiveDate, SecondsFormat, TimeZone, Utc};
+use chrono_tz::Tz;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use crate::error::{SearchError, SearchResult};
+use crate::types::Json;
+
+/// One numeric range of a range facet
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RangeBucket {
+    /// Inclusive lower bound; `None` is unbounded
+    pub from: Option<f64>,
+
+    /// Exclusive upper bound; `None` is unbounded
+    pub to: Option<f64>,
+}
+
+impl RangeBucket {
+    pub fn new(from: Option<f64>, to: Option<f64>) -> SearchResult<Self> {
+        match (from, to) {
+            (None, None) => Err(SearchError::invalid_query("A range bucket needs at least one bound")),
+            (Some(from), Some(to)) if from >= to => Err(SearchError::invalid_query(format!(
+                "Range bucket {}-{} is empty",
+                from, to
+            ))),
+            _ => Ok(Self { from, to }),
+        }
+    }
+
+    /// Parse `0-25`, `50+`, `*-25` or `25-*`
+    pub fn parse(text: &str) -> SearchResult<Self> {
+        let text = text.trim();
+        let invalid = || SearchError::invalid_query(format!("Invalid range bucket {}", text));
+
+        if let Some(from) = text.strip_suffix('+') {
+            return Self::new(Some(parse_bound(from).flatten().ok_or_else(invalid)?), None);
+        }
+        // The separator is the first `-` that leaves a bound on both sides,
+        // which skips the sign of a negative lower bound
+        text.match_indices('-')
+            .filter(|(i, _)| *i > 0)
+            .find_map(|(i, _)| Some((parse_bound(&text[..i])?, parse_bound(&text[i + 1..])?)))
+            .ok_or_else(invalid)
+            .and_then(|(from, to)| Self::new(from, to))
+    }
+
+    /// Label of the bucket in facet results, in the syntax it is parsed from
+    pub fn key(&self) -> String {
+        match (self.from, self.to) {
+            (Some(from), None) => format!("{}+", from),
+            (from, to) => format!(
+                "{}-{}",
+                from.map_or("*".to_string(), |f| f.to_string()),
+                to.map_or("*".to_string(), |t| t.to_string())
+            ),
+        }
+    }
+
+    pub fn contains(&self, value: f64) -> bool {
+        self.from.is_none_or(|from| value >= from) && self.to.is_none_or(|to| value < to)
+    }
+}
+
+/// `*` is an open bound, anything else must be a number
+fn parse_bound(text: &str) -> Option<Option<f64>> {
+    match text.trim() {
+        "*" => Some(None),
+        number => number.parse::<f64>().ok().filter(|n| n.is_finite()).map(Some),
+    }
+}
+
+/// Width of a date histogram bucket
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CalendarInterval {
+    Day,
+    Week,
+    Month,
+}
+
+impl CalendarInterval {
+    pub fn parse(value: &str) -> SearchResult<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "day" => Ok(Self::Day),
+            "week" => Ok(Self::Week),
+            "month" => Ok(Self::Month),
+            other => Err(SearchError::invalid_query(format!(
+                "Invalid histogram interval {}; expected day, week or month",
+                other
+            ))),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Day => "day",
+            Self::Week => "week",
+            Self::Month => "month",
+        }
+    }
+
+    /// First day of the bucket holding `date`
+    fn bucket_start(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Self::Day => date,
+            Self::Week => date - Days::new(u64::from(date.weekday().num_days_from_monday())),
+            Self::Month => date.with_day(1).unwrap_or(date),
+        }
+    }
+
+    fn next_bucket(&self, start: NaiveDate) -> Option<NaiveDate> {
+        match self {
+            Self::Day => start.checked_add_days(Days::new(1)),
+            Self::Week => start.checked_add_days(Days::new(7)),
+            Self::Month => start.checked_add_months(Months::new(1)),
+        }
+    }
+}
+
+/// A requested facet
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum FacetSpec {
+    /// Counts per distinct value
+    Terms(String),
+
+    /// Counts per numeric range
+    Range { field: String, buckets: Vec<RangeBucket> },
+
+    /// Counts per calendar interval; `time_zone` is an IANA name, UTC when unset
+    DateHistogram { field: String, interval: CalendarInterval, time_zone: Option<String> },
+
+    /// Min, max, average and sum of a numeric field
+    Stats(String),
+}
+
+impl FacetSpec {
+    /// Parse `field`, `field:[bucket, ...]`, `field:date_histogram(interval[, time zone])` or `field:stats`
+    pub fn parse(spec: &str) -> SearchResult<Self> {
+        let spec = spec.trim();
+        if let Some(field) = spec.strip_suffix(":stats") {
+            return Ok(Self::Stats(field.trim().to_string()));
+        }
+        if let Some((field, args)) = spec.split_once(":date_histogram(") {
+            let args = args
+                .strip_suffix(')')
+                .ok_or_else(|| SearchError::invalid_query(format!("Unclosed date_histogram in facet {}", spec)))?;
+            let (interval, time_zone) = match args.split_once(',') {
+                Some((interval, zone)) => (interval, Some(zone.trim().to_string())),
+                None => (args, None),
+            };
+            if let Some(zone) = &time_zone {
+                parse_time_zone(zone)?;
+            }
+            return Ok(Self::DateHistogram {
+                field: field.trim().to_string(),
+                interval: CalendarInterval::parse(interval)?,
+                time_zone,
+            });
+        }
+        let Some((field, buckets)) = spec.split_once(":[") else {
+            return Ok(Self::Terms(spec.to_string()));
+        };
+        let buckets = buckets
+            .strip_suffix(']')
+            .ok_or_else(|| SearchError::invalid_query(format!("Unclosed bucket list in facet {}", spec)))?;
+        let buckets = buckets
+            .split(',')
+            .filter(|b| !b.trim().is_empty())
+            .map(RangeBucket::parse)
+            .collect::<SearchResult<Vec<_>>>()?;
+        if buckets.is_empty() {
+            return Err(SearchError::invalid_query(format!("Facet {} has no buckets", spec)));
+        }
+        Ok(Self::Range { field: field.trim().to_string(), buckets })
+    }
+
+    pub fn field(&self) -> &str {
+        match self {
+            Self::Terms(field) | Self::Range { field, .. } | Self::DateHistogram { field, .. } | Self::Stats(field) => {
+                field
+            }
+        }
+    }
+
+    pub fn buckets(&self) -> Option<&[RangeBucket]> {
+        match self {
+            Self::Range { buckets, .. } => Some(buckets),
+            Self::Terms(_) | Self::DateHistogram { .. } | Self::Stats(_) => None,
+        }
+    }
+
+    /// Whether the facet is derived from per-value counts, rather than from the documents themselves
+    pub fn is_value_count(&self) -> bool {
+        matches!(self, Self::Terms(_) | Self::Range { .. })
+    }
+
+    /// Name of the aggregation in Elasticsearch requests and responses
+    ///
+    /// Stats get their own suffix so a field can be both counted and summarized.
+    pub fn aggregation_name(&self) -> String {
+        match self {
+            Self::Stats(field) => format!("{}_stats", field),
+            other => format!("{}_facet", other.field()),
+        }
+    }
+
+    /// Elasticsearch aggregation counting this facet
+    pub fn to_lucene_aggregation(&self) -> Value {
+        match self {
+            Self::Terms(field) => json!({ "terms": { "field": field, "size": 100 } }),
+            Self::Range { field, buckets } => {
+                let ranges: Vec<Value> = buckets
+                    .iter()
+                    .map(|bucket| {
+                        let mut range = json!({ "key": bucket.key() });
+                        if let Some(from) = bucket.from {
+                            range["from"] = json!(from);
+                        }
+                        if let Some(to) = bucket.to {
+                            range["to"] = json!(to);
+                        }
+                        range
+                    })
+                    .collect();
+                json!({ "range": { "field": field, "ranges": ranges } })
+            }
+            Self::DateHistogram { field, interval, time_zone } => json!({
+                "date_histogram": {
+                    "field": field,
+                    "calendar_interval": interval.as_str(),
+                    "time_zone": time_zone.as_deref().unwrap_or("UTC"),
+                    "format": "yyyy-MM-dd'T'HH:mm:ssXXX",
+                    "min_doc_count": 0
+                }
+            }),
+            Self::Stats(field) => json!({ "stats": { "field": field } }),
+        }
+    }
+
+    /// Entry of the Typesense `facet_by` parameter, e.g. `price(0-25:[0, 25], 50+:[50, ])`
+    ///
+    /// `None` for date histograms and stats, which Typesense cannot compute.
+    pub fn to_typesense_facet(&self) -> Option<String> {
+        match self {
+            Self::Terms(field) => Some(field.clone()),
+            Self::DateHistogram { .. } | Self::Stats(_) => None,
+            Self::Range { field, buckets } => {
+                let ranges: Vec<String> = buckets
+                    .iter()
+                    .map(|bucket| {
+                        // Typesense needs a lower bound; i64::MIN parses for integer and float fields
+                        let from = bucket.from.map_or(i64::MIN.to_string(), |f| f.to_string());
+                        let to = bucket.to.map_or(String::new(), |t| t.to_string());
+                        format!("{}:[{}, {}]", bucket.key(), from, to)
+                    })
+                    .collect();
+                Some(format!("{}({})", field, ranges.join(", ")))
+            }
+        }
+    }
+}
+
+impl fmt::Display for FacetSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Terms(field) => write!(f, "{}", field),
+            Self::Range { field, buckets } => {
+                let keys: Vec<String> = buckets.iter().map(RangeBucket::key).collect();
+                write!(f, "{}:[{}]", field, keys.join(", "))
+            }
+            Self::DateHistogram { field, interval, time_zone: Some(zone) } => {
+                write!(f, "{}:date_histogram({}, {})", field, interval.as_str(), zone)
+            }
+            Self::DateHistogram { field, interval, time_zone: None } => {
+                write!(f, "{}:date_histogram({})", field, interval.as_str())
+            }
+            Self::Stats(field) => write!(f, "{}:stats", field),
+        }
+    }
+}
+
+/// Parse every facet of a query
+pub fn parse_facets(facets: &[String]) -> SearchResult<Vec<FacetSpec>> {
+    facets.iter().map(|f| FacetSpec::parse(f)).collect()
+}
+
+/// Sum per-value counts into range buckets
+///
+/// `counts` maps values, as the engine prints them, to document counts.
+/// Values that are not numbers or fall outside every bucket are ignored.
+pub fn bucket_counts(buckets: &[RangeBucket], counts: &Map<String, Value>) -> Map<String, Value> {
+    let mut totals = vec![0u64; buckets.len()];
+    for (value, count) in counts {
+        let (Ok(value), Some(count)) = (value.parse::<f64>(), count.as_u64()) else {
+            continue;
+        };
+        for (total, bucket) in totals.iter_mut().zip(buckets) {
+            if bucket.contains(value) {
+                *total += count;
+            }
+        }
+    }
+    buckets.iter().zip(totals).map(|(bucket, total)| (bucket.key(), json!(total))).collect()
+}
+
+/// Replace per-value counts of range facets with bucket counts
+///
+/// `distribution` is a `{field: {value: count}}` object such as the
+/// Meilisearch `facetDistribution`. Terms facets are left untouched.
+pub fn bucket_facet_distribution(distribution: &str, specs: &[FacetSpec]) -> SearchResult<Json> {
+    let mut facets: Map<String, Value> = serde_json::from_str(distribution)
+        .map_err(|e| SearchError::Internal(format!("Failed to parse facet counts: {}", e)))?;
+    for spec in specs {
+        if let FacetSpec::Range { field, buckets } = spec {
+            let counts = facets.get(field).and_then(Value::as_object).cloned().unwrap_or_default();
+            facets.insert(field.clone(), Value::Object(bucket_counts(buckets, &counts)));
+        }
+    }
+    Ok(Value::Object(facets).to_string())
+}
+
+fn parse_time_zone(zone: &str) -> SearchResult<Tz> {
+    zone.parse::<Tz>()
+        .map_err(|_| SearchError::invalid_query(format!("Unknown time zone {}", zone)))
+}
+
+/// Client-side date histogram over documents
+#[derive(Debug, Clone)]
+pub struct DateHistogram {
+    pub field: String,
+    interval: CalendarInterval,
+    zone: Tz,
+    counts: BTreeMap<NaiveDate, u64>,
+}
+
+/// Most buckets emitted with empty ones filled in; wider histograms only list non-empty buckets
+const MAX_FILLED_BUCKETS: usize = 10_000;
+
+impl DateHistogram {
+    /// Histogram for a date histogram facet; `None` for other facets
+    pub fn for_spec(spec: &FacetSpec) -> SearchResult<Option<Self>> {
+        let FacetSpec::DateHistogram { field, interval, time_zone } = spec else {
+            return Ok(None);
+        };
+        let zone = time_zone.as_deref().map(parse_time_zone).transpose()?.unwrap_or(Tz::UTC);
+        Ok(Some(Self { field: field.clone(), interval: *interval, zone, counts: BTreeMap::new() }))
+    }
+
+    /// Count a document by its field value; a missing or unreadable date is skipped
+    ///
+    /// Dates may be RFC 3339 strings, `YYYY-MM-DD` dates (midnight in the
+    /// histogram's zone) or Unix timestamps in seconds or milliseconds.
+    pub fn add_document(&mut self, document: &Value) {
+        if let Some(date) = field_value(document, &self.field).and_then(|v| self.local_date(v)) {
+            *self.counts.entry(self.interval.bucket_start(date)).or_default() += 1;
+        }
+    }
+
+    fn local_date(&self, value: &Value) -> Option<NaiveDate> {
+        let instant = match value {
+            Value::String(text) => match DateTime::parse_from_rfc3339(text) {
+                Ok(instant) => instant.with_timezone(&Utc),
+                Err(_) => return NaiveDate::parse_from_str(text, "%Y-%m-%d").ok(),
+            },
+            Value::Number(number) => {
+                let timestamp = number.as_i64()?;
+                // Seconds would put millisecond timestamps tens of thousands of years out
+                let millis = if timestamp.abs() < 100_000_000_000 { timestamp.checked_mul(1000)? } else { timestamp };
+                DateTime::from_timestamp_millis(millis)?
+            }
+            _ => return None,
+        };
+        Some(instant.with_timezone(&self.zone).date_naive())
+    }
+
+    /// Start of the bucket beginning on `date`, as an ISO-8601 key
+    fn key(&self, date: NaiveDate) -> String {
+        let midnight = date.and_hms_opt(0, 0, 0).unwrap_or_default();
+        let start = match self.zone.from_local_datetime(&midnight) {
+            LocalResult::Single(start) | LocalResult::Ambiguous(start, _) => start,
+            // Midnight skipped by a DST change; the day starts an hour later
+            LocalResult::None => date
+                .and_hms_opt(1, 0, 0)
+                .and_then(|one| self.zone.from_local_datetime(&one).earliest())
+                .unwrap_or_else(|| self.zone.from_utc_datetime(&midnight)),
+        };
+        start.to_rfc3339_opts(SecondsFormat::Secs, true)
+    }
+
+    /// Buckets in order, with empty ones between the first and last filled in
+    pub fn buckets(&self) -> Vec<(String, u64)> {
+        let (Some(first), Some(last)) = (self.counts.keys().next(), self.counts.keys().next_back()) else {
+            return Vec::new();
+        };
+        let mut buckets = Vec::new();
+        let mut date = Some(*first);
+        while let Some(current) = date.filter(|d| d <= last && buckets.len() < MAX_FILLED_BUCKETS) {
+            buckets.push((self.key(current), self.counts.get(&current).copied().unwrap_or(0)));
+            date = self.interval.next_bucket(current);
+        }
+        if date.is_some_and(|d| d <= *last) {
+            return self.counts.iter().map(|(date, count)| (self.key(*date), *count)).collect();
+        }
+        buckets
+    }
+}
+
+/// Value of a possibly dotted field path
+fn field_value<'a>(document: &'a Value, field: &str) -> Option<&'a Value> {
+    field.split('.').try_fold(document, |v, key| v.get(key))
+}
+
+/// Most matches a result set may have for stats to be computed client-side
+pub const MAX_CLIENT_STATS_HITS: u32 = 1_000;
+
+/// Client-side stats of a numeric field
+#[derive(Debug, Clone, PartialEq)]
+pub struct NumericStats {
+    pub field: String,
+    count: u64,
+    min: f64,
+    max: f64,
+    sum: f64,
+}
+
+impl NumericStats {
+    pub fn new(field: impl Into<String>) -> Self {
+        Self { field: field.into(), count: 0, min: f64::INFINITY, max: f64::NEG_INFINITY, sum: 0.0 }
+    }
+
+    /// Add a document's field value; arrays add each element, anything that is not a number is skipped
+    pub fn add_document(&mut self, document: &Value) {
+        match field_value(document, &self.field) {
+            Some(Value::Array(values)) => values.iter().filter_map(Value::as_f64).for_each(|v| self.add(v)),
+            Some(value) => {
+                if let Some(v) = value.as_f64() {
+                    self.add(v);
+                }
+            }
+            None => {}
+        }
+    }
+
+    fn add(&mut self, value: f64) {
+        self.count += 1;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.sum += value;
+    }
+
+    /// `{count, min, max, avg, sum}`, with null summaries when no value was seen
+    ///
+    /// The same shape as an Elasticsearch `stats` aggregation.
+    pub fn to_json(&self) -> Value {
+        if self.count == 0 {
+            return json!({ "count": 0, "min": null, "max": null, "avg": null, "sum": 0.0 });
+        }
+        json!({
+            "count": self.count,
+            "min": self.min,
+            "max": self.max,
+            "avg": self.sum / self.count as f64,
+            "sum": self.sum
+        })
+    }
+}
+
+/// Facets computed from the matching documents on engines that cannot aggregate them
+#[derive(Debug, Clone, Default)]
+pub struct ClientFacets {
+    pub histograms: Vec<DateHistogram>,
+    pub stats: Vec<NumericStats>,
+}
+
+impl ClientFacets {
+    /// Accumulators for the date histogram and stats facets among `specs`
+    pub fn for_specs(specs: &[FacetSpec]) -> SearchResult<Self> {
+        let histograms = specs
+            .iter()
+            .filter_map(|spec| DateHistogram::for_spec(spec).transpose())
+            .collect::<SearchResult<_>>()?;
+        let stats = specs
+            .iter()
+            .filter_map(|spec| match spec {
+                FacetSpec::Stats(field) => Some(NumericStats::new(field.clone())),
+                _ => None,
+            })
+            .collect();
+        Ok(Self { histograms, stats })
+    }
+
+    /// Drop the stats facets when the result set is too large, or of unknown size
+    pub fn limit_stats(&mut self, index: &str, total: Option<u32>) {
+        if self.stats.is_empty() || total.is_some_and(|total| total <= MAX_CLIENT_STATS_HITS) {
+            return;
+        }
+        let fields: Vec<&str> = self.stats.iter().map(|s| s.field.as_str()).collect();
+        warn!(
+            "Stats facets on {} for {} left out: {} matches, at most {} are summarized client-side",
+            index,
+            fields.join(", "),
+            total.map_or("unknown".to_string(), |t| t.to_string()),
+            MAX_CLIENT_STATS_HITS
+        );
+        self.stats.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.histograms.is_empty() && self.stats.is_empty()
+    }
+
+    /// Fields the documents must be retrieved with
+    pub fn fields(&self) -> Vec<&str> {
+        let mut fields: Vec<&str> = self.histograms.iter().map(|h| h.field.as_str()).collect();
+        fields.extend(self.stats.iter().map(|s| s.field.as_str()));
+        fields.sort_unstable();
+        fields.dedup();
+        fields
+    }
+
+    pub fn add_document(&mut self, document: &Value) {
+        self.histograms.iter_mut().for_each(|h| h.add_document(document));
+        self.stats.iter_mut().for_each(|s| s.add_document(document));
+    }
+
+    /// Add the computed facets to a `{field: {value: count}}` facet distribution
+    ///
+    /// Histograms map bucket keys to counts; stats map to the summary object
+    /// of [`NumericStats::to_json`].
+    pub fn merge_into_distribution(&self, distribution: Option<&str>) -> SearchResult<Json> {
+        let mut facets: Map<String, Value> = match distribution {
+            Some(distribution) => serde_json::from_str(distribution)
+                .map_err(|e| SearchError::Internal(format!("Failed to parse facet counts: {}", e)))?,
+            None => Map::new(),
+        };
+        for histogram in &self.histograms {
+            let counts = histogram.buckets().into_iter().map(|(key, count)| (key, json!(count))).collect();
+            facets.insert(histogram.field.clone(), Value::Object(counts));
+        }
+        for stats in &self.stats {
+            facets.insert(stats.field.clone(), stats.to_json());
+        }
+        Ok(Value::Object(facets).to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_range_facets() {
+        let spec = FacetSpec::parse("price:[0-25, 25-50, 50+]").unwrap();
+        assert_eq!(spec.field(), "price");
+        assert_eq!(spec.to_string(), "price:[0-25, 25-50, 50+]");
+        assert_eq!(FacetSpec::parse("category").unwrap(), FacetSpec::Terms("category".to_string()));
+
+        let buckets: Vec<_> = ["-10-0", "*-2.5", "3-*"].iter().map(|b| RangeBucket::parse(b).unwrap()).collect();
+        assert_eq!(buckets[0], RangeBucket { from: Some(-10.0), to: Some(0.0) });
+        assert_eq!(buckets[1].key(), "*-2.5");
+        assert_eq!(buckets[2].key(), "3+");
+        assert!(RangeBucket::parse("5-1").is_err());
+        assert!(RangeBucket::parse("*-*").is_err());
+        assert!(FacetSpec::parse("price:[0-25").is_err());
+
+        assert_eq!(
+            spec.to_lucene_aggregation()["range"]["ranges"][2],
+            json!({ "key": "50+", "from": 50.0 })
+        );
+        assert_eq!(
+            FacetSpec::parse("rating:[0-3, 3+]").unwrap().to_typesense_facet().as_deref(),
+            Some("rating(0-3:[0, 3], 3+:[3, ])")
+        );
+    }
+
+    #[test]
+    fn test_client_side_bucketing() {
+        let specs = parse_facets(&["price:[0-25, 25+]".to_string(), "genre".to_string()]).unwrap();
+        let distribution = r#"{"price": {"9.5": 2, "25": 1, "40": 3, "n/a": 7}, "genre": {"rock": 4}}"#;
+        let bucketed: Value = serde_json::from_str(&bucket_facet_distribution(distribution, &specs).unwrap()).unwrap();
+        assert_eq!(bucketed["price"], json!({ "0-25": 2, "25+": 4 }));
+        assert_eq!(bucketed["genre"], json!({ "rock": 4 }));
+    }
+
+    #[test]
+    fn test_date_histogram() {
+        let spec = FacetSpec::parse("created_at:date_histogram(month, Europe/Berlin)").unwrap();
+        assert_eq!(spec.to_string(), "created_at:date_histogram(month, Europe/Berlin)");
+        assert_eq!(spec.to_lucene_aggregation()["date_histogram"]["time_zone"], "Europe/Berlin");
+        assert_eq!(spec.to_typesense_facet(), None);
+        assert!(FacetSpec::parse("created_at:date_histogram(hour)").is_err());
+        assert!(FacetSpec::parse("created_at:date_histogram(day, Mars/Olympus)").is_err());
+
+        let mut histogram = DateHistogram::for_spec(&spec).unwrap().unwrap();
+        for value in [json!("2024-01-31T23:30:00Z"), json!("2024-03-15"), json!(1706745600), json!("soon")] {
+            histogram.add_document(&json!({ "created_at": value }));
+        }
+        histogram.add_document(&json!({ "title": "no date" }));
+        // 23:30 UTC on January 31st is already February in Berlin
+        assert_eq!(histogram.buckets(), vec![
+            ("2024-02-01T00:00:00+01:00".to_string(), 2),
+            ("2024-03-01T00:00:00+01:00".to_string(), 1),
+        ]);
+
+        let weekly = FacetSpec::parse("ts:date_histogram(week)").unwrap();
+        let mut histogram = DateHistogram::for_spec(&weekly).unwrap().unwrap();
+        histogram.add_document(&json!({ "ts": "2024-05-09T12:00:00+02:00" }));
+        assert_eq!(histogram.buckets()[0].0, "2024-05-06T00:00:00Z");
+    }
+
+    #[test]
+    fn test_stats_facet() {
+        let spec = FacetSpec::parse("price:stats").unwrap();
+        assert_eq!(spec, FacetSpec::Stats("price".to_string()));
+        assert_eq!(spec.to_string(), "price:stats");
+        assert_eq!(spec.aggregation_name(), "price_stats");
+        assert_eq!(spec.to_lucene_aggregation(), json!({ "stats": { "field": "price" } }));
+        assert_eq!(spec.to_typesense_facet(), None);
+
+        let specs = parse_facets(&["price:stats".to_string(), "genre".to_string()]).unwrap();
+        let mut client = ClientFacets::for_specs(&specs).unwrap();
+        for price in [json!(10), json!(2.5), json!([4, 7.5]), json!("free")] {
+            client.add_document(&json!({ "price": price }));
+        }
+        let merged = client.merge_into_distribution(Some(r#"{"genre": {"rock": 4}}"#)).unwrap();
+        let merged: Value = serde_json::from_str(&merged).unwrap();
+        assert_eq!(merged["price"], json!({ "count": 4, "min": 2.5, "max": 10.0, "avg": 6.0, "sum": 24.0 }));
+        assert_eq!(merged["genre"], json!({ "rock": 4 }));
+
+        client.limit_stats("books", Some(MAX_CLIENT_STATS_HITS + 1));
+        assert!(client.is_empty());
+    }
+}
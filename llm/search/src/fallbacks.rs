@@ -5,9 +5,10 @@
 
 use std::collections::HashMap;
 use serde_json::Value;
-use crate::types::{SearchQuery, SearchResults, SearchHit};
+use crate::types::{SearchGroup, SearchQuery, SearchResults, SearchHit};
 use crate::error::{SearchError, SearchResult};
 use crate::capabilities::{FeatureSupport, DegradationStrategy, FacetFallback, HighlightFallback};
+use crate::highlight;
 use log::{warn, debug};
 
 /// Fallback processor for handling unsupported features
@@ -177,6 +178,12 @@ impl FallbackProcessor {
         query: &SearchQuery,
         highlight_config: &crate::types::HighlightConfig,
     ) -> SearchResult<()> {
+        if highlight_config.offsets {
+            highlight::replace_matched(hits, &highlight_config.fields, query.q.as_deref().unwrap_or_default());
+            debug!("Computed client-side highlight offsets for {} hits", hits.len());
+            return Ok(());
+        }
+
         let search_terms = self.extract_search_terms(query)?;
         let pre_tag = highlight_config.pre_tag.as_deref().unwrap_or("<mark>");
         let post_tag = highlight_config.post_tag.as_deref().unwrap_or("</mark>");
@@ -360,15 +367,28 @@ impl StreamingFallback {
                 hits: Vec::new(),
                 facets: None,
                 took_ms: Some(0),
+                truncated: false,
+                request_id: None,
+                groups: None,
+                next_cursor: None,
+                total_exact: None,
+                facet_metadata: Vec::new(),
+                relaxations: Vec::new(),
+                original_query: None,
+                corrected_query: None,
             });
         }
         
         let first_result = &page_results[0];
         let mut combined_hits = Vec::new();
+        let mut combined_groups: Option<Vec<SearchGroup>> = None;
         let mut total_time = 0;
         
         for result in &page_results {
             combined_hits.extend(result.hits.clone());
+            if let Some(groups) = &result.groups {
+                combined_groups.get_or_insert_with(Vec::new).extend(groups.iter().cloned());
+            }
             if let Some(time) = result.took_ms {
                 total_time += time;
             }
@@ -381,6 +401,15 @@ impl StreamingFallback {
             hits: combined_hits,
             facets: first_result.facets.clone(),
             took_ms: Some(total_time),
+            truncated: false,
+            request_id: None,
+            groups: combined_groups,
+            next_cursor: None,
+            total_exact: first_result.total_exact,
+            facet_metadata: first_result.facet_metadata.clone(),
+            relaxations: Vec::new(),
+            original_query: None,
+            corrected_query: None,
         })
     }
 }
@@ -410,6 +439,7 @@ impl FeatureDetector {
         for filter in &query.filters {
             if filter.contains("geo_distance") 
                 || filter.contains("geo_bounding_box")
+                || filter.contains("geo_polygon")
                 || filter.contains("latitude")
                 || filter.contains("longitude") {
                 return true;
@@ -487,18 +517,24 @@ mod tests {
                 score: Some(1.0),
                 content: Some(r#"{"category": "books", "price": 10}"#.to_string()),
                 highlights: None,
+                explanation: None,
+                distance_meters: None,
             },
             SearchHit {
                 id: "2".to_string(),
                 score: Some(0.8),
                 content: Some(r#"{"category": "books", "price": 15}"#.to_string()),
                 highlights: None,
+                explanation: None,
+                distance_meters: None,
             },
             SearchHit {
                 id: "3".to_string(),
                 score: Some(0.6),
                 content: Some(r#"{"category": "electronics", "price": 100}"#.to_string()),
                 highlights: None,
+                explanation: None,
+                distance_meters: None,
             },
         ];
         
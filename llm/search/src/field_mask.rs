@@ -0,0 +1,231 @@
+//! Field masks for document reads
+//!
+//! A mask lists the fields `get` and `get_many` return, as dot paths into the
+//! document: `title`, `author.name`, `variants.sku`. A path through an array
+//! selects from every element, and selecting an object selects all of it, so
+//! `author` wins over `author.name`. Fields the document does not have are
+//! left out rather than returned as null.
+//!
+//! Providers pass the mask to their own source filtering when they can and
+//! project the returned documents with [`FieldMask::project`] otherwise, which
+//! also covers nested paths the provider only filters at the top level.
+
+use std::collections::BTreeMap;
+use serde_json::{Map, Value};
+use crate::error::{SearchError, SearchResult};
+use crate::types::Doc;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum MaskNode {
+    /// The whole value
+    All,
+
+    /// Only these fields of an object
+    Fields(BTreeMap<String, MaskNode>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldMask {
+    root: BTreeMap<String, MaskNode>,
+}
+
+impl FieldMask {
+    /// A mask from dot paths; at least one path is required
+    pub fn new<S: AsRef<str>>(paths: &[S]) -> SearchResult<Self> {
+        if paths.is_empty() {
+            return Err(SearchError::invalid_query("A field mask needs at least one field"));
+        }
+        let mut root = BTreeMap::new();
+        for path in paths {
+            let path = path.as_ref().trim();
+            let segments: Vec<&str> = path.split('.').collect();
+            if segments.iter().any(|segment| segment.is_empty()) {
+                return Err(SearchError::invalid_query(format!("Invalid field path '{}'", path)));
+            }
+            insert(&mut root, &segments);
+        }
+        Ok(Self { root })
+    }
+
+    /// A mask from an optional field list, as the `get` and `get_many` exports take it
+    pub fn from_fields(fields: Option<&[String]>) -> SearchResult<Option<Self>> {
+        fields.map(Self::new).transpose()
+    }
+
+    /// Selected paths, dot-separated and sorted
+    pub fn paths(&self) -> Vec<String> {
+        let mut paths = Vec::new();
+        collect_paths(&self.root, "", &mut paths);
+        paths
+    }
+
+    /// Top-level fields the mask selects from, for providers that only filter top-level attributes
+    pub fn top_level(&self) -> Vec<String> {
+        self.root.keys().cloned().collect()
+    }
+
+    /// Whether every path is a top-level field, so top-level filtering is exact
+    pub fn is_flat(&self) -> bool {
+        self.root.values().all(|node| *node == MaskNode::All)
+    }
+
+    /// The selected parts of a document
+    pub fn project(&self, document: &Value) -> Value {
+        project_node(document, &self.root).unwrap_or_else(|| document.clone())
+    }
+
+    /// `doc` with only the selected fields in its content; content that is not JSON is left alone
+    pub fn project_doc(&self, doc: Doc) -> Doc {
+        match serde_json::from_str::<Value>(&doc.content) {
+            Ok(content) => Doc { id: doc.id, content: self.project(&content).to_string() },
+            Err(_) => doc,
+        }
+    }
+}
+
+fn insert(fields: &mut BTreeMap<String, MaskNode>, segments: &[&str]) {
+    let Some((first, rest)) = segments.split_first() else {
+        return;
+    };
+    if rest.is_empty() {
+        fields.insert(first.to_string(), MaskNode::All);
+        return;
+    }
+    let node = fields.entry(first.to_string()).or_insert_with(|| MaskNode::Fields(BTreeMap::new()));
+    if let MaskNode::Fields(children) = node {
+        insert(children, rest);
+    }
+}
+
+fn collect_paths(fields: &BTreeMap<String, MaskNode>, prefix: &str, paths: &mut Vec<String>) {
+    for (name, node) in fields {
+        let path = if prefix.is_empty() { name.clone() } else { format!("{}.{}", prefix, name) };
+        match node {
+            MaskNode::All => paths.push(path),
+            MaskNode::Fields(children) => collect_paths(children, &path, paths),
+        }
+    }
+}
+
+/// Objects keep the selected fields and arrays project each element; other values have no fields to select
+fn project_node(value: &Value, fields: &BTreeMap<String, MaskNode>) -> Option<Value> {
+    match value {
+        Value::Object(object) => {
+            let mut projected = Map::new();
+            for (name, node) in fields {
+                let Some(field) = object.get(name) else {
+                    continue;
+                };
+                let field = match node {
+                    MaskNode::All => Some(field.clone()),
+                    MaskNode::Fields(children) => project_node(field, children),
+                };
+                if let Some(field) = field {
+                    projected.insert(name.clone(), field);
+                }
+            }
+            Some(Value::Object(projected))
+        }
+        Value::Array(items) => Some(Value::Array(items.iter().filter_map(|item| project_node(item, fields)).collect())),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn mask(paths: &[&str]) -> FieldMask {
+        FieldMask::new(paths).unwrap()
+    }
+
+    fn book() -> Value {
+        json!({
+            "title": "Dune",
+            "subtitle": null,
+            "price": 9.5,
+            "author": { "name": "Frank Herbert", "born": 1920 },
+            "variants": [{ "sku": "D-1", "format": "paperback" }, { "format": "ebook" }, "legacy"]
+        })
+    }
+
+    #[test]
+    fn test_paths_are_trimmed_sorted_and_merged() {
+        let merged = mask(&[" title ", "author.name", "author.born", "variants.sku", "title"]);
+        assert_eq!(merged.paths(), ["author.born", "author.name", "title", "variants.sku"]);
+        assert_eq!(merged.top_level(), ["author", "title", "variants"]);
+        assert!(!merged.is_flat());
+        assert!(mask(&["title", "price"]).is_flat());
+    }
+
+    #[test]
+    fn test_a_whole_object_wins_over_paths_inside_it_in_any_order() {
+        for paths in [["author.name", "author"], ["author", "author.name"]] {
+            let mask = mask(&paths);
+            assert_eq!(mask.paths(), ["author"], "{:?}", paths);
+            assert!(mask.is_flat());
+            assert_eq!(mask.project(&book()), json!({ "author": { "name": "Frank Herbert", "born": 1920 } }));
+        }
+    }
+
+    #[test]
+    fn test_empty_segments_are_refused() {
+        for path in ["", " ", "author..name", ".title", "title."] {
+            match FieldMask::new(&[path]) {
+                Err(SearchError::InvalidQuery(message)) => assert_eq!(message, format!("Invalid field path '{}'", path.trim())),
+                other => panic!("expected an invalid query error for {:?}, got {:?}", path, other),
+            }
+        }
+        assert!(FieldMask::new::<&str>(&[]).is_err());
+        assert!(FieldMask::from_fields(Some(&[])).is_err());
+        assert_eq!(FieldMask::from_fields(None).unwrap(), None);
+    }
+
+    #[test]
+    fn test_missing_fields_are_left_out_and_nulls_kept() {
+        assert_eq!(mask(&["title", "subtitle", "isbn", "author.nickname"]).project(&book()), json!({
+            "title": "Dune",
+            "subtitle": null,
+            "author": {}
+        }));
+    }
+
+    #[test]
+    fn test_paths_into_scalars_select_nothing() {
+        assert_eq!(mask(&["title.length", "price.currency"]).project(&book()), json!({}));
+        assert_eq!(mask(&["subtitle.text"]).project(&book()), json!({}), "null has no fields either");
+    }
+
+    #[test]
+    fn test_paths_through_arrays_select_from_every_object_element() {
+        let document = json!({
+            "variants": [{ "sku": "D-1", "format": "paperback" }, { "format": "ebook" }, "legacy", null],
+            "shelves": [[{ "row": 1, "column": 2 }], [{ "row": 3 }, 4]]
+        });
+        assert_eq!(mask(&["variants.sku", "shelves.row"]).project(&document), json!({
+            "variants": [{ "sku": "D-1" }, {}],
+            "shelves": [[{ "row": 1 }], [{ "row": 3 }]]
+        }));
+    }
+
+    #[test]
+    fn test_documents_that_are_not_objects_are_returned_whole() {
+        let mask = mask(&["title"]);
+        assert_eq!(mask.project(&json!("Dune")), json!("Dune"));
+        assert_eq!(mask.project(&json!(42)), json!(42));
+        assert_eq!(mask.project(&json!([{ "title": "Dune", "price": 1 }, 7])), json!([{ "title": "Dune" }]));
+    }
+
+    #[test]
+    fn test_docs_keep_their_id_and_unparsable_content() {
+        let mask = mask(&["title"]);
+        let doc = Doc { id: "dune".to_string(), content: book().to_string() };
+        let projected = mask.project_doc(doc);
+        assert_eq!(projected.id, "dune");
+        assert_eq!(serde_json::from_str::<Value>(&projected.content).unwrap(), json!({ "title": "Dune" }));
+
+        let doc = Doc { id: "1".to_string(), content: "not json".to_string() };
+        assert_eq!(mask.project_doc(doc).content, "not json");
+    }
+}
@@ -0,0 +1,539 @@
+//! Geo points in documents
+//!
+//! Schemas type a field as `GeoPoint`, but each provider stores the point in
+//! its own shape: Elasticsearch and OpenSearch take an object with `lat` and
+//! `lon`, Typesense a `[lat, lng]` array, and Meilisearch reads the point of a
+//! document only from its reserved `_geo` field. Documents use one shape,
+//! `{"lat": .., "lon": ..}`, and providers convert the geo fields of their
+//! schema on the way in with [`to_provider`] and back with [`from_provider`],
+//! so a geo document written to one provider can be written to any other.
+//!
+//! Writes also accept the other common shapes, read by [`GeoPoint::parse`].
+//! Values that are not a point are passed through for the provider to reject.
+//!
+//! Meilisearch schemas cannot tell which field holds the point, so the geo
+//! fields of its indexes are kept in worker memory with [`remember`].
+//!
+//! A [`GeoPolygonFilter`] in a query's filters keeps the documents inside one
+//! or more polygons, and is translated for each provider. Meilisearch has no
+//! polygon filter and matches the bounding boxes of the polygons instead.
+//! A [`GeoDistanceSort`] orders hits by distance from an origin, and each hit
+//! then reports its distance, from the provider when it computes one and by
+//! [`distance_meters`] otherwise.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use crate::error::{SearchError, SearchResult};
+use crate::types::{FieldType, Schema, SearchHit};
+
+/// Field Meilisearch reads the point of a document from
+pub const MEILISEARCH_GEO_FIELD: &str = "_geo";
+
+/// A point on the globe, in degrees
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GeoPoint {
+    pub lat: f64,
+    pub lon: f64,
+}
+
+impl GeoPoint {
+    /// A point, unless the latitude or longitude is out of range
+    pub fn new(lat: f64, lon: f64) -> SearchResult<Self> {
+        if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+            return Err(SearchError::invalid_query(format!("Geo point out of range: {}, {}", lat, lon)));
+        }
+        Ok(Self { lat, lon })
+    }
+
+    /// Read a point written as `{"lat", "lon"}`, `{"lat", "lng"}`, a `[lat, lng]`
+    /// array, a GeoJSON `Point` or a `"lat,lon"` string
+    ///
+    /// Arrays are read in Typesense order, latitude first, unlike GeoJSON.
+    pub fn parse(value: &Value) -> Option<Self> {
+        let (lat, lon) = match value {
+            Value::Object(object) if object.get("type").and_then(Value::as_str) == Some("Point") => {
+                let coordinates = object.get("coordinates")?.as_array()?;
+                match coordinates.as_slice() {
+                    [lon, lat] => (lat.as_f64()?, lon.as_f64()?),
+                    _ => return None,
+                }
+            }
+            Value::Object(object) => {
+                let lon = object.get("lon").or_else(|| object.get("lng"))?;
+                (number(object.get("lat")?)?, number(lon)?)
+            }
+            Value::Array(pair) => match pair.as_slice() {
+                [lat, lon] => (lat.as_f64()?, lon.as_f64()?),
+                _ => return None,
+            },
+            Value::String(text) => {
+                let (lat, lon) = text.split_once(',')?;
+                (lat.trim().parse().ok()?, lon.trim().parse().ok()?)
+            }
+            _ => return None,
+        };
+        Self::new(lat, lon).ok()
+    }
+
+    /// The point in the shape documents use
+    pub fn to_value(self) -> Value {
+        json!({ "lat": self.lat, "lon": self.lon })
+    }
+
+    /// The point in the shape `provider` stores
+    pub fn to_provider_value(self, provider: &str) -> Value {
+        match provider {
+            "typesense" => json!([self.lat, self.lon]),
+            "meilisearch" => json!({ "lat": self.lat, "lng": self.lon }),
+            _ => self.to_value(),
+        }
+    }
+}
+
+/// A coordinate given as a number or, as Meilisearch allows, a numeric string
+fn number(value: &Value) -> Option<f64> {
+    value.as_f64().or_else(|| value.as_str()?.trim().parse().ok())
+}
+
+/// Names of the geo point fields of a schema
+pub fn geo_fields(schema: &Schema) -> Vec<String> {
+    schema
+        .fields
+        .iter()
+        .filter(|field| field.field_type == FieldType::GeoPoint)
+        .map(|field| field.name.clone())
+        .collect()
+}
+
+/// Convert the geo `fields` of a document to the shape `provider` stores
+///
+/// For Meilisearch the fields keep the document shape and the first point is
+/// copied to `_geo`, unless the document sets `_geo` itself.
+pub fn to_provider(provider: &str, document: &mut Value, fields: &[String]) {
+    let Some(object) = document.as_object_mut() else {
+        return;
+    };
+    let mut first = None;
+    for field in fields {
+        let Some(point) = object.get(field).and_then(GeoPoint::parse) else {
+            continue;
+        };
+        first.get_or_insert(point);
+        let stored = match provider {
+            "meilisearch" => point.to_value(),
+            _ => point.to_provider_value(provider),
+        };
+        object.insert(field.clone(), stored);
+    }
+    if provider == "meilisearch" && !object.contains_key(MEILISEARCH_GEO_FIELD) {
+        if let Some(point) = first {
+            object.insert(MEILISEARCH_GEO_FIELD.to_string(), point.to_provider_value(provider));
+        }
+    }
+}
+
+/// Convert the geo `fields` of a document read from `provider` back to the document shape
+///
+/// The `_geo` copy Meilisearch keeps is removed when the index has geo fields.
+pub fn from_provider(provider: &str, document: &mut Value, fields: &[String]) {
+    let Some(object) = document.as_object_mut() else {
+        return;
+    };
+    for field in fields {
+        if let Some(point) = object.get(field).and_then(GeoPoint::parse) {
+            object.insert(field.clone(), point.to_value());
+        }
+    }
+    if provider == "meilisearch" && !fields.is_empty() {
+        object.remove(MEILISEARCH_GEO_FIELD);
+    }
+}
+
+/// [`from_provider`] applied to serialized content, which is returned unchanged when it is not JSON
+pub fn content_from_provider(provider: &str, content: &str, fields: &[String]) -> String {
+    match serde_json::from_str::<Value>(content) {
+        Ok(mut document) if !fields.is_empty() => {
+            from_provider(provider, &mut document, fields);
+            document.to_string()
+        }
+        _ => content.to_string(),
+    }
+}
+
+/// Name of the polygon filter, `geo_polygon(<field>, <polygons>)`
+pub const POLYGON_FILTER: &str = "geo_polygon";
+
+/// Corners of a latitude and longitude range
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GeoBoundingBox {
+    pub top_left: GeoPoint,
+    pub bottom_right: GeoPoint,
+}
+
+/// Filter keeping documents whose geo field lies inside any of a set of polygons
+///
+/// Written `geo_polygon(location, [[lat, lon], ..])` for one polygon and
+/// `geo_polygon(location, [[[lat, lon], ..], [[lat, lon], ..]])` for several.
+/// Vertices take any shape [`GeoPoint::parse`] reads; a closing vertex equal
+/// to the first is dropped, and each polygon needs at least three others.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeoPolygonFilter {
+    pub field: String,
+    pub polygons: Vec<Vec<GeoPoint>>,
+}
+
+impl GeoPolygonFilter {
+    /// Parse a polygon filter; `None` when `filter` is not one
+    pub fn parse(filter: &str) -> Option<SearchResult<Self>> {
+        let args = filter.trim().strip_prefix(POLYGON_FILTER)?.trim_start().strip_prefix('(')?;
+        let parsed = match args.strip_suffix(')') {
+            Some(args) => Self::parse_args(args),
+            None => Err("missing closing parenthesis".to_string()),
+        };
+        Some(parsed.map_err(|e| SearchError::invalid_query(format!("Invalid geo filter {}: {}", filter, e))))
+    }
+
+    fn parse_args(args: &str) -> Result<Self, String> {
+        let (field, polygons) = args.split_once(',').ok_or("expected a field and polygons")?;
+        let field = field.trim();
+        if field.is_empty() || field.contains(char::is_whitespace) {
+            return Err(format!("invalid field \"{}\"", field));
+        }
+        let polygons: Value = serde_json::from_str(polygons).map_err(|e| e.to_string())?;
+        let polygons = match polygons.as_array() {
+            Some(list) if list.first().and_then(GeoPoint::parse).is_some() => vec![polygons.clone()],
+            Some(list) if !list.is_empty() => list.clone(),
+            _ => return Err("expected a list of vertices or of polygons".to_string()),
+        };
+        let polygons = polygons.iter().map(polygon).collect::<Result<_, _>>()?;
+        Ok(Self { field: field.to_string(), polygons })
+    }
+
+    /// Bounding box of each polygon
+    pub fn bounding_boxes(&self) -> Vec<GeoBoundingBox> {
+        self.polygons
+            .iter()
+            .map(|polygon| {
+                let lats = polygon.iter().map(|p| p.lat);
+                let lons = polygon.iter().map(|p| p.lon);
+                GeoBoundingBox {
+                    top_left: GeoPoint { lat: lats.clone().fold(f64::MIN, f64::max), lon: lons.clone().fold(f64::MAX, f64::min) },
+                    bottom_right: GeoPoint { lat: lats.fold(f64::MAX, f64::min), lon: lons.fold(f64::MIN, f64::max) },
+                }
+            })
+            .collect()
+    }
+
+    /// Elasticsearch `geo_shape` query, which also matches `geo_point` fields
+    pub fn to_geo_shape_query(&self) -> Value {
+        // GeoJSON rings are closed and list longitude first
+        let rings: Vec<Vec<Value>> = self
+            .polygons
+            .iter()
+            .map(|polygon| polygon.iter().chain(polygon.first()).map(|p| json!([p.lon, p.lat])).collect())
+            .collect();
+        let shape = match rings.as_slice() {
+            [ring] => json!({ "type": "polygon", "coordinates": [ring] }),
+            _ => json!({ "type": "multipolygon", "coordinates": rings.iter().map(|ring| json!([ring])).collect::<Vec<_>>() }),
+        };
+        json!({ "geo_shape": { &self.field: { "shape": shape, "relation": "intersects" } } })
+    }
+
+    /// `geo_polygon` query, one per polygon, as OpenSearch still supports it
+    pub fn to_geo_polygon_query(&self) -> Value {
+        let mut queries: Vec<Value> = self
+            .polygons
+            .iter()
+            .map(|polygon| {
+                let points: Vec<Value> = polygon.iter().map(|p| p.to_value()).collect();
+                json!({ "geo_polygon": { &self.field: { "points": points } } })
+            })
+            .collect();
+        match queries.len() {
+            1 => queries.remove(0),
+            _ => json!({ "bool": { "should": queries, "minimum_should_match": 1 } }),
+        }
+    }
+
+    /// Typesense `filter_by` expression
+    pub fn to_typesense_filter(&self) -> String {
+        let filters: Vec<String> = self
+            .polygons
+            .iter()
+            .map(|polygon| {
+                let vertices: Vec<String> = polygon.iter().map(|p| format!("{}, {}", p.lat, p.lon)).collect();
+                format!("{}:({})", self.field, vertices.join(", "))
+            })
+            .collect();
+        match filters.as_slice() {
+            [filter] => filter.clone(),
+            _ => format!("({})", filters.join(" || ")),
+        }
+    }
+
+    /// Algolia `insidePolygon`, one flat list of latitudes and longitudes per polygon
+    ///
+    /// Algolia always filters on `_geoloc`, whatever the field.
+    pub fn to_algolia_polygons(&self) -> Value {
+        let polygons: Vec<Vec<f64>> = self.polygons.iter().map(|polygon| polygon.iter().flat_map(|p| [p.lat, p.lon]).collect()).collect();
+        json!(polygons)
+    }
+
+    /// Meilisearch filter matching the bounding boxes of the polygons, a superset of the polygons
+    ///
+    /// Meilisearch filters on `_geo`, whatever the field.
+    pub fn to_meilisearch_bounding_box_filter(&self) -> String {
+        let filters: Vec<String> = self
+            .bounding_boxes()
+            .iter()
+            .map(|b| {
+                format!(
+                    "_geoBoundingBox([{}, {}], [{}, {}])",
+                    b.top_left.lat, b.bottom_right.lon, b.bottom_right.lat, b.top_left.lon
+                )
+            })
+            .collect();
+        match filters.as_slice() {
+            [filter] => filter.clone(),
+            _ => format!("({})", filters.join(" OR ")),
+        }
+    }
+}
+
+/// Name of the distance sort, `geo_distance(<field>, <origin>)`
+pub const DISTANCE_SORT: &str = "geo_distance";
+
+/// Mean radius of the Earth, in meters
+const EARTH_RADIUS_METERS: f64 = 6_371_008.8;
+
+/// Sort by distance from an origin, nearest first unless `descending`
+///
+/// Written `geo_distance(location, [lat, lon])`, optionally followed by
+/// `:asc` or `:desc`; the origin takes any shape [`GeoPoint::parse`] reads.
+/// Searches sorted this way report each hit's distance in meters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeoDistanceSort {
+    pub field: String,
+    pub origin: GeoPoint,
+    pub descending: bool,
+}
+
+impl GeoDistanceSort {
+    /// Parse a distance sort; `None` when `sort` is not one
+    pub fn parse(sort: &str) -> Option<SearchResult<Self>> {
+        let args = sort.trim().strip_prefix(DISTANCE_SORT)?.trim_start().strip_prefix('(')?;
+        let parsed = match args.rsplit_once(')') {
+            Some((args, order)) => Self::parse_args(args, order),
+            None => Err("missing closing parenthesis".to_string()),
+        };
+        Some(parsed.map_err(|e| SearchError::invalid_query(format!("Invalid geo sort {}: {}", sort, e))))
+    }
+
+    fn parse_args(args: &str, order: &str) -> Result<Self, String> {
+        let (field, origin) = args.split_once(',').ok_or("expected a field and an origin")?;
+        let field = field.trim();
+        if field.is_empty() || field.contains(char::is_whitespace) {
+            return Err(format!("invalid field \"{}\"", field));
+        }
+        let origin = origin.trim();
+        let origin = serde_json::from_str(origin).unwrap_or_else(|_| Value::String(origin.to_string()));
+        let origin = GeoPoint::parse(&origin).ok_or_else(|| format!("invalid origin {}", origin))?;
+        let descending = match order.trim() {
+            "" | ":asc" => false,
+            ":desc" => true,
+            other => return Err(format!("invalid order \"{}\"", other)),
+        };
+        Ok(Self { field: field.to_string(), origin, descending })
+    }
+
+    fn order(&self) -> &'static str {
+        if self.descending { "desc" } else { "asc" }
+    }
+
+    /// Elasticsearch/OpenSearch `_geo_distance` sort, whose sort value is the distance in meters
+    pub fn to_lucene_sort(&self) -> Value {
+        json!({
+            "_geo_distance": {
+                &self.field: self.origin.to_value(),
+                "order": self.order(),
+                "unit": "m",
+                "distance_type": "arc",
+            }
+        })
+    }
+
+    /// Typesense `sort_by` entry, which makes hits carry `geo_distance_meters`
+    pub fn to_typesense_sort(&self) -> String {
+        format!("{}({}, {}):{}", self.field, self.origin.lat, self.origin.lon, self.order())
+    }
+
+    /// Meilisearch sort, which makes hits carry `_geoDistance`; it always sorts on `_geo`
+    pub fn to_meilisearch_sort(&self) -> String {
+        format!("_geoPoint({}, {}):{}", self.origin.lat, self.origin.lon, self.order())
+    }
+
+    /// Algolia `aroundLatLng`, which ranks by distance, nearest first, on `_geoloc`
+    pub fn to_algolia_around(&self) -> String {
+        format!("{},{}", self.origin.lat, self.origin.lon)
+    }
+
+    /// Distance from the origin to the point in the sort field of `content`, for providers that do not report it
+    pub fn distance_in(&self, content: &Value) -> Option<f64> {
+        let point = self.field.split('.').try_fold(content, |value, key| value.get(key)).and_then(GeoPoint::parse)?;
+        Some(distance_meters(self.origin, point))
+    }
+
+    /// [`Self::distance_in`] for serialized content
+    pub fn distance_in_content(&self, content: Option<&str>) -> Option<f64> {
+        self.distance_in(&serde_json::from_str(content?).ok()?)
+    }
+}
+
+/// The distance sort among a query's sorts, if there is one
+pub fn distance_sort(sorts: &[String]) -> SearchResult<Option<GeoDistanceSort>> {
+    sorts.iter().find_map(|sort| GeoDistanceSort::parse(sort)).transpose()
+}
+
+/// Compute the distance of hits the provider returned without one
+pub fn fill_distances(hits: &mut [SearchHit], sort: &GeoDistanceSort) {
+    for hit in hits.iter_mut().filter(|hit| hit.distance_meters.is_none()) {
+        hit.distance_meters = sort.distance_in_content(hit.content.as_deref());
+    }
+}
+
+/// Great-circle distance between two points, in meters
+pub fn distance_meters(a: GeoPoint, b: GeoPoint) -> f64 {
+    let (lat_a, lat_b) = (a.lat.to_radians(), b.lat.to_radians());
+    let half_lat = (lat_b - lat_a) / 2.0;
+    let half_lon = (b.lon - a.lon).to_radians() / 2.0;
+    let h = half_lat.sin().powi(2) + lat_a.cos() * lat_b.cos() * half_lon.sin().powi(2);
+    2.0 * EARTH_RADIUS_METERS * h.sqrt().min(1.0).asin()
+}
+
+/// Vertices of one polygon, without a closing vertex
+fn polygon(value: &Value) -> Result<Vec<GeoPoint>, String> {
+    let mut vertices = value
+        .as_array()
+        .ok_or("expected a list of vertices")?
+        .iter()
+        .map(|vertex| GeoPoint::parse(vertex).ok_or_else(|| format!("invalid vertex {}", vertex)))
+        .collect::<Result<Vec<_>, _>>()?;
+    if vertices.len() > 1 && vertices.first() == vertices.last() {
+        vertices.pop();
+    }
+    if vertices.len() < 3 {
+        return Err("a polygon needs at least three vertices".to_string());
+    }
+    Ok(vertices)
+}
+
+fn registry() -> MutexGuard<'static, HashMap<String, Vec<String>>> {
+    static REGISTRY: OnceLock<Mutex<HashMap<String, Vec<String>>>> = OnceLock::new();
+    REGISTRY
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Keep the geo fields of an index's schema in worker memory
+pub fn remember(index: &str, schema: &Schema) {
+    let fields = geo_fields(schema);
+    if fields.is_empty() {
+        registry().remove(index);
+    } else {
+        registry().insert(index.to_string(), fields);
+    }
+}
+
+/// The geo fields kept by [`remember`]
+pub fn recall(index: &str) -> Vec<String> {
+    registry().get(index).cloned().unwrap_or_default()
+}
+
+/// Drop the geo fields of a deleted index
+pub fn forget(index: &str) {
+    registry().remove(index);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_accepts_common_shapes() {
+        let point = GeoPoint { lat: 48.85, lon: 2.35 };
+        for value in [
+            json!({ "lat": 48.85, "lon": 2.35 }),
+            json!({ "lat": "48.85", "lng": 2.35 }),
+            json!([48.85, 2.35]),
+            json!({ "type": "Point", "coordinates": [2.35, 48.85] }),
+            json!("48.85, 2.35"),
+        ] {
+            assert_eq!(GeoPoint::parse(&value), Some(point), "{}", value);
+        }
+        assert_eq!(GeoPoint::parse(&json!([91.0, 0.0])), None);
+        assert_eq!(GeoPoint::parse(&json!("Paris")), None);
+    }
+
+    #[test]
+    fn test_provider_round_trip() {
+        let fields = vec!["location".to_string(), "office".to_string()];
+        let original = json!({ "name": "Louvre", "location": { "lat": 48.86, "lon": 2.34 }, "office": "n/a" });
+
+        let mut typesense = original.clone();
+        to_provider("typesense", &mut typesense, &fields);
+        assert_eq!(typesense["location"], json!([48.86, 2.34]));
+        assert_eq!(typesense["office"], json!("n/a"));
+        from_provider("typesense", &mut typesense, &fields);
+        assert_eq!(typesense, original);
+
+        let mut meilisearch = original.clone();
+        to_provider("meilisearch", &mut meilisearch, &fields);
+        assert_eq!(meilisearch["_geo"], json!({ "lat": 48.86, "lng": 2.34 }));
+        from_provider("meilisearch", &mut meilisearch, &fields);
+        assert_eq!(meilisearch, original);
+    }
+
+    #[test]
+    fn test_polygon_filter_translations() {
+        let filter = GeoPolygonFilter::parse("geo_polygon(location, [[48.9, 2.2], [48.9, 2.4], [48.8, 2.4], [48.9, 2.2]])")
+            .unwrap()
+            .unwrap();
+        assert_eq!(filter.polygons[0].len(), 3);
+        assert_eq!(filter.to_typesense_filter(), "location:(48.9, 2.2, 48.9, 2.4, 48.8, 2.4)");
+        assert_eq!(filter.to_algolia_polygons(), json!([[48.9, 2.2, 48.9, 2.4, 48.8, 2.4]]));
+        assert_eq!(filter.to_meilisearch_bounding_box_filter(), "_geoBoundingBox([48.9, 2.4], [48.8, 2.2])");
+        let shape = filter.to_geo_shape_query();
+        assert_eq!(shape["geo_shape"]["location"]["shape"]["coordinates"][0][3], json!([2.2, 48.9]));
+
+        let multi = GeoPolygonFilter::parse("geo_polygon(area, [[[1, 1], [1, 2], [2, 2]], [[5, 5], [5, 6], [6, 6]]])")
+            .unwrap()
+            .unwrap();
+        assert_eq!(multi.to_typesense_filter(), "(area:(1, 1, 1, 2, 2, 2) || area:(5, 5, 5, 6, 6, 6))");
+        assert_eq!(multi.to_geo_shape_query()["geo_shape"]["area"]["shape"]["type"], "multipolygon");
+        assert_eq!(multi.to_geo_polygon_query()["bool"]["should"].as_array().unwrap().len(), 2);
+
+        assert!(GeoPolygonFilter::parse("category:books").is_none());
+        assert!(GeoPolygonFilter::parse("geo_polygon(location, [[1, 1], [1, 2]])").unwrap().is_err());
+    }
+
+    #[test]
+    fn test_distance_sort() {
+        let sort = GeoDistanceSort::parse("geo_distance(location, [48.8566, 2.3522]):desc").unwrap().unwrap();
+        assert!(sort.descending);
+        assert_eq!(sort.to_typesense_sort(), "location(48.8566, 2.3522):desc");
+        assert_eq!(sort.to_meilisearch_sort(), "_geoPoint(48.8566, 2.3522):desc");
+        assert_eq!(sort.to_lucene_sort()["_geo_distance"]["location"], json!({ "lat": 48.8566, "lon": 2.3522 }));
+        assert_eq!(GeoDistanceSort::parse("geo_distance(location, 48.8566, 2.3522)").unwrap().unwrap().origin, sort.origin);
+
+        // Paris to London is about 344 km
+        let london = json!({ "location": { "lat": 51.5074, "lon": -0.1278 } });
+        let meters = sort.distance_in(&london).unwrap();
+        assert!((meters - 343_900.0).abs() < 1_000.0, "{}", meters);
+
+        assert!(GeoDistanceSort::parse("price:desc").is_none());
+        assert!(GeoDistanceSort::parse("geo_distance(location, [48.8, 2.3]):up").unwrap().is_err());
+    }
+}
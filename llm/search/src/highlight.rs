@@ -0,0 +1,352 @@
+//! Highlight match offsets
+//!
+//! A highlight config with `offsets` set asks for the positions of the
+//! matched text instead of snippets wrapped in tags, for rich-text and mobile
+//! renderers that cannot consume HTML. Each hit's `highlights` then holds a
+//! JSON list of [`MatchOffset`]s, sorted by field and position, pointing into
+//! the string field values of the hit's content.
+//!
+//! Elasticsearch highlights whole field values between [`MARK_START`] and
+//! [`MARK_END`], so its analyzers decide what matched, and
+//! [`offsets_from_marked`] turns the marks into offsets. Meilisearch reports
+//! match positions itself, read by [`offsets_from_positions`]. For the other
+//! providers [`match_offsets`] finds the query words in the fields, ignoring
+//! case; stemmed or typo-tolerant matches are not found this way.
+//!
+//! Only fields holding a string are covered; matches inside arrays are left out.
+
+use std::collections::HashSet;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::query::{Clause, Occur, ParsedQuery};
+use crate::types::{Json, SearchHit};
+
+/// Opening mark of a match, a private-use character that does not occur in text
+pub const MARK_START: &str = "\u{E000}";
+
+/// Closing mark of a match
+pub const MARK_END: &str = "\u{E001}";
+
+/// Position of one match in a field value
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MatchOffset {
+    pub field: String,
+
+    /// Byte range in the UTF-8 field value
+    pub start: usize,
+    pub end: usize,
+
+    /// The same range counted in characters
+    pub char_start: usize,
+    pub char_end: usize,
+}
+
+impl MatchOffset {
+    /// Offset of the bytes `start..end` of `text`, unless they split a character or run past its end
+    pub fn from_bytes(field: &str, text: &str, start: usize, end: usize) -> Option<Self> {
+        if start > end || !text.is_char_boundary(start) || !text.is_char_boundary(end) {
+            return None;
+        }
+        let char_start = text[..start].chars().count();
+        Some(MatchOffset {
+            field: field.to_string(),
+            start,
+            end,
+            char_start,
+            char_end: char_start + text[start..end].chars().count(),
+        })
+    }
+}
+
+/// String value at a dot path of a document
+fn field_text<'a>(content: &'a Value, field: &str) -> Option<&'a str> {
+    field.split('.').try_fold(content, |value, key| value.get(key))?.as_str()
+}
+
+/// Serialize offsets as a hit's `highlights`, in field and position order
+pub fn offsets_json(mut offsets: Vec<MatchOffset>) -> Json {
+    offsets.sort_by(|a, b| (&a.field, a.start).cmp(&(&b.field, b.start)));
+    serde_json::to_string(&offsets).unwrap_or_else(|_| "[]".to_string())
+}
+
+/// Remove the marks from a highlighted value, returning the plain value and the marked byte ranges
+fn strip_marks(marked: &str) -> (String, Vec<(usize, usize)>) {
+    let mut plain = String::with_capacity(marked.len());
+    let mut ranges = Vec::new();
+    let mut open = None;
+    let mut rest = marked;
+    while let Some(c) = rest.chars().next() {
+        if rest.starts_with(MARK_START) {
+            open = Some(plain.len());
+        } else if rest.starts_with(MARK_END) {
+            if let Some(start) = open.take() {
+                ranges.push((start, plain.len()));
+            }
+        } else {
+            plain.push(c);
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+    (plain, ranges)
+}
+
+/// Offsets from Elasticsearch-style highlights, `{field: [marked value, ..]}`
+///
+/// A marked value counts only when, without its marks, it equals the field
+/// value in `content`, so fragments cut short by the provider are skipped.
+pub fn offsets_from_marked(content: &Value, highlights: &Value) -> Vec<MatchOffset> {
+    let Some(highlights) = highlights.as_object() else {
+        return Vec::new();
+    };
+    let mut offsets = Vec::new();
+    for (field, fragments) in highlights {
+        let Some(text) = field_text(content, field) else {
+            continue;
+        };
+        for fragment in fragments.as_array().into_iter().flatten().filter_map(Value::as_str) {
+            let (plain, ranges) = strip_marks(fragment);
+            if plain == text {
+                offsets.extend(ranges.into_iter().filter_map(|(start, end)| MatchOffset::from_bytes(field, text, start, end)));
+            }
+        }
+    }
+    offsets
+}
+
+/// Offsets from Meilisearch `_matchesPosition`, `{field: [{start, length}, ..]}` in bytes
+pub fn offsets_from_positions(content: &Value, positions: &Value) -> Vec<MatchOffset> {
+    let Some(positions) = positions.as_object() else {
+        return Vec::new();
+    };
+    let mut offsets = Vec::new();
+    for (field, matches) in positions {
+        let Some(text) = field_text(content, field) else {
+            continue;
+        };
+        for position in matches.as_array().into_iter().flatten() {
+            // Matches inside an array carry the element indices and do not point into a string
+            if position.get("indices").is_some() {
+                continue;
+            }
+            let start = position.get("start").and_then(Value::as_u64).unwrap_or(0) as usize;
+            let length = position.get("length").and_then(Value::as_u64).unwrap_or(0) as usize;
+            offsets.extend(MatchOffset::from_bytes(field, text, start, start + length));
+        }
+    }
+    offsets
+}
+
+/// Lowercased words of the query that can match, and prefixes from trailing `*` wildcards
+fn query_words(q: &str) -> (HashSet<String>, Vec<String>) {
+    let mut words = HashSet::new();
+    let mut prefixes = Vec::new();
+    for clause in ParsedQuery::parse(q).clauses.into_iter().filter(|c| c.occur != Occur::MustNot) {
+        match clause.clause {
+            Clause::Term(text) | Clause::Phrase { text, .. } => {
+                words.extend(words_of(&text).map(|(_, word)| word.to_lowercase()));
+            }
+            Clause::Wildcard(pattern) => {
+                if let Some(prefix) = pattern.strip_suffix('*').filter(|p| !p.contains(['*', '?']) && !p.is_empty()) {
+                    prefixes.push(prefix.to_lowercase());
+                }
+            }
+            Clause::Regex(_) => {}
+        }
+    }
+    (words, prefixes)
+}
+
+/// Runs of letters and digits with their byte position
+fn words_of(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(move |word| (word.as_ptr() as usize - text.as_ptr() as usize, word))
+}
+
+/// Offsets of the words of `q` in the `fields` of `content`, ignoring case
+///
+/// Excluded terms and regular expressions are not matched.
+pub fn match_offsets(content: &Value, fields: &[String], q: &str) -> Vec<MatchOffset> {
+    let (words, prefixes) = query_words(q);
+    let mut offsets = Vec::new();
+    for field in fields {
+        let Some(text) = field_text(content, field) else {
+            continue;
+        };
+        for (start, word) in words_of(text) {
+            let lower = word.to_lowercase();
+            if words.contains(&lower) || prefixes.iter().any(|p| lower.starts_with(p.as_str())) {
+                offsets.extend(MatchOffset::from_bytes(field, text, start, start + word.len()));
+            }
+        }
+    }
+    offsets
+}
+
+/// Replace the marked highlights of hits with offsets
+pub fn replace_marked(hits: &mut [SearchHit]) {
+    for hit in hits {
+        let content = hit.content.as_deref().and_then(|c| serde_json::from_str(c).ok()).unwrap_or(Value::Null);
+        let highlights = hit.highlights.as_deref().and_then(|h| serde_json::from_str(h).ok()).unwrap_or(Value::Null);
+        hit.highlights = Some(offsets_json(offsets_from_marked(&content, &highlights)));
+    }
+}
+
+/// Set the highlights of hits to the offsets of the words of `q` in `fields`
+pub fn replace_matched(hits: &mut [SearchHit], fields: &[String], q: &str) {
+    for hit in hits {
+        let content = hit.content.as_deref().and_then(|c| serde_json::from_str(c).ok()).unwrap_or(Value::Null);
+        hit.highlights = Some(offsets_json(match_offsets(&content, fields, q)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn mark(text: &str) -> String {
+        format!("{}{}{}", MARK_START, text, MARK_END)
+    }
+
+    fn spans(offsets: &[MatchOffset]) -> Vec<(&str, usize, usize)> {
+        offsets.iter().map(|o| (o.field.as_str(), o.start, o.end)).collect()
+    }
+
+    fn hit(content: Option<&str>, highlights: Option<&str>) -> SearchHit {
+        SearchHit {
+            id: "1".to_string(),
+            score: None,
+            content: content.map(str::to_string),
+            highlights: highlights.map(str::to_string),
+            explanation: None,
+            distance_meters: None,
+        }
+    }
+
+    #[test]
+    fn test_byte_ranges_splitting_a_character_are_refused() {
+        let text = "Crème brûlée";
+        let offset = MatchOffset::from_bytes("title", text, 7, 15).unwrap();
+        assert_eq!((offset.char_start, offset.char_end), (6, 12));
+        assert_eq!(MatchOffset::from_bytes("title", text, 3, 4), None, "inside è");
+        assert_eq!(MatchOffset::from_bytes("title", text, 7, 99), None);
+        assert_eq!(MatchOffset::from_bytes("title", text, 8, 7), None);
+        let empty = MatchOffset::from_bytes("title", text, 7, 7).unwrap();
+        assert_eq!((empty.char_start, empty.char_end), (6, 6));
+    }
+
+    #[test]
+    fn test_offsets_are_serialized_by_field_then_position() {
+        let text = "one two three";
+        let offsets = vec![
+            MatchOffset::from_bytes("title", text, 8, 13).unwrap(),
+            MatchOffset::from_bytes("body", text, 4, 7).unwrap(),
+            MatchOffset::from_bytes("title", text, 0, 3).unwrap(),
+        ];
+        let sorted: Vec<MatchOffset> = serde_json::from_str(&offsets_json(offsets)).unwrap();
+        assert_eq!(spans(&sorted), [("body", 4, 7), ("title", 0, 3), ("title", 8, 13)]);
+        assert_eq!(offsets_json(Vec::new()), "[]");
+    }
+
+    #[test]
+    fn test_marked_values_count_bytes_and_chars() {
+        let content = json!({ "title": "Crème brûlée recipes", "meta": { "tag": "dessert" } });
+        let highlights = json!({
+            "title": [format!("Crème {} {}", mark("brûlée"), mark("recipes"))],
+            "meta.tag": [mark("dessert")],
+        });
+        let offsets = offsets_from_marked(&content, &highlights);
+        assert_eq!(spans(&offsets), [("meta.tag", 0, 7), ("title", 7, 15), ("title", 16, 23)]);
+        assert_eq!((offsets[2].char_start, offsets[2].char_end), (13, 20));
+    }
+
+    #[test]
+    fn test_fragments_cut_short_are_skipped() {
+        let content = json!({ "body": "The spice must flow. The spice extends life." });
+        let highlights = json!({
+            "body": [
+                format!("The {} must flow.", mark("spice")),
+                format!("The {} must flow. The {} extends life.", mark("spice"), mark("spice")),
+            ]
+        });
+        assert_eq!(spans(&offsets_from_marked(&content, &highlights)), [("body", 4, 9), ("body", 25, 30)]);
+    }
+
+    #[test]
+    fn test_unbalanced_marks_only_keep_closed_matches() {
+        let content = json!({ "title": "red shoes" });
+        let unclosed = json!({ "title": [format!("{}red shoes", MARK_START)] });
+        assert!(offsets_from_marked(&content, &unclosed).is_empty());
+        let stray_end = json!({ "title": [format!("red{} {}", MARK_END, mark("shoes"))] });
+        assert_eq!(spans(&offsets_from_marked(&content, &stray_end)), [("title", 4, 9)]);
+    }
+
+    #[test]
+    fn test_marks_on_missing_or_non_string_fields_are_ignored() {
+        let content = json!({ "tags": ["red"], "price": 10 });
+        let highlights = json!({ "tags": [mark("red")], "price": [mark("10")], "title": [mark("red")] });
+        assert!(offsets_from_marked(&content, &highlights).is_empty());
+        assert!(offsets_from_marked(&content, &json!([mark("red")])).is_empty());
+    }
+
+    #[test]
+    fn test_positions_inside_arrays_or_characters_are_skipped() {
+        let content = json!({ "title": "Crème brûlée", "tags": ["dessert"] });
+        let positions = json!({
+            "title": [{ "start": 7, "length": 5 }, { "start": 9, "length": 1 }, { "start": 10, "length": 50 }],
+            "tags": [{ "start": 0, "length": 7, "indices": [0] }]
+        });
+        assert_eq!(spans(&offsets_from_positions(&content, &positions)), [("title", 7, 12)]);
+        assert!(offsets_from_positions(&content, &Value::Null).is_empty());
+    }
+
+    #[test]
+    fn test_query_words_match_whole_words_ignoring_case() {
+        let content = json!({ "title": "Ärger mit RUST-Code", "author": { "name": "Rusty Ärgerlich" } });
+        let fields = ["title".to_string(), "author.name".to_string()];
+        assert_eq!(spans(&match_offsets(&content, &fields, "ärger rust")), [("title", 0, 6), ("title", 11, 15)]);
+        assert_eq!(
+            spans(&match_offsets(&content, &fields, "\"mit rust\"")),
+            [("title", 7, 10), ("title", 11, 15)],
+            "phrases match word by word"
+        );
+    }
+
+    #[test]
+    fn test_excluded_terms_and_regexes_are_not_matched() {
+        let content = json!({ "body": "rust, RUST and rusty" });
+        let fields = ["body".to_string()];
+        assert_eq!(spans(&match_offsets(&content, &fields, "rust -rusty")), [("body", 0, 4), ("body", 6, 10)]);
+        assert!(match_offsets(&content, &fields, "-rust /rust.*/").is_empty());
+    }
+
+    #[test]
+    fn test_only_trailing_wildcards_match_as_prefixes() {
+        let content = json!({ "title": "Rust for Rustaceans", "tags": "trust" });
+        let fields = ["title".to_string(), "tags".to_string()];
+        assert_eq!(spans(&match_offsets(&content, &fields, "RUST*")), [("title", 0, 4), ("title", 9, 19)]);
+        assert!(match_offsets(&content, &fields, "*rust").is_empty());
+        assert!(match_offsets(&content, &fields, "r?st*").is_empty());
+        assert!(match_offsets(&content, &fields, "*").is_empty());
+    }
+
+    #[test]
+    fn test_hits_without_content_get_empty_offsets() {
+        let mut hits = vec![
+            hit(None, Some("{}")),
+            hit(Some("not json"), None),
+            hit(Some(r#"{"title":"red shoes"}"#), Some(&json!({ "title": [mark("red") + " shoes"] }).to_string())),
+        ];
+        replace_marked(&mut hits);
+        assert_eq!(hits[0].highlights.as_deref(), Some("[]"));
+        assert_eq!(hits[1].highlights.as_deref(), Some("[]"));
+        let offsets: Vec<MatchOffset> = serde_json::from_str(hits[2].highlights.as_deref().unwrap()).unwrap();
+        assert_eq!(spans(&offsets), [("title", 0, 3)]);
+
+        replace_matched(&mut hits, &["title".to_string()], "shoes");
+        assert_eq!(hits[0].highlights.as_deref(), Some("[]"));
+        let offsets: Vec<MatchOffset> = serde_json::from_str(hits[2].highlights.as_deref().unwrap()).unwrap();
+        assert_eq!(spans(&offsets), [("title", 4, 9)]);
+    }
+}
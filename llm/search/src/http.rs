@@ -0,0 +1,860 @@
+//! HTTP client for the search providers
+//!
+//! Providers build requests on a [`Client`], whose API follows the blocking
+//! client of `reqwest`: build a request, `send` it, read the [`Response`].
+//! The bytes travel through an [`HttpClient`] transport. In a component that
+//! is [`WasiHttp`], which hands each request to the host through
+//! `wasi:http/outgoing-handler` and waits on the pollable of its response, so
+//! no async runtime has to drive the I/O. Native builds, used by tests and
+//! command-line tools, send requests with `hyper`. A test can plug in its own
+//! transport with [`ClientBuilder::transport`].
+//!
+//! Clients share one transport per process, so connections opened for one
+//! provider call are kept for the next. In a component the host owns the
+//! connections and decides how long they stay open. The native transport
+//! keeps up to [`POOL_SIZE_ENV`] idle connections per host for
+//! [`IDLE_TIMEOUT_ENV`], and offers HTTP/2 to TLS servers of clients built
+//! with [`ClientBuilder::http2`] unless [`HTTP2_ENV`] turns it off.
+//!
+//! A client built with a [`RetryPolicy`] sends requests that are safe to
+//! repeat again when they fail with a 5xx status, a timeout or a connection
+//! error (see [`crate::retry`]).
+//!
+//! Inside a [`crate::deadline`] scope every request is sent with the time left
+//! of the deadline as its timeout, in place of the client's, and is not sent
+//! at all once it has run out. Retries stop when the deadline would pass
+//! during the wait.
+//!
+//! A client built with [`ClientBuilder::accept_compressed`] asks for gzip or
+//! deflate responses. Any response with a `Content-Encoding` of either is
+//! decoded before it is returned (see [`crate::compression`]).
+
+use std::fmt;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use log::debug;
+use http::header::{HeaderName, HeaderValue, ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use crate::compression::{self, Compression};
+use crate::config::env_helpers::get_env_parsed;
+use crate::deadline;
+use crate::endpoint_pool;
+use crate::retry::{RetryBudget, RetryPolicy};
+
+pub use http::{header, HeaderMap, Method, StatusCode};
+pub use url::Url;
+
+#[cfg(target_arch = "wasm32")]
+mod wasi;
+#[cfg(target_arch = "wasm32")]
+pub use self::wasi::WasiHttp;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod native;
+#[cfg(not(target_arch = "wasm32"))]
+pub use self::native::NativeHttp;
+
+/// Sends requests and reads their responses whole
+pub trait HttpClient: Send + Sync {
+    fn execute(&self, request: Request) -> Result<Response, Error>;
+}
+
+/// Environment variable holding the idle connections kept per host
+pub const POOL_SIZE_ENV: &str = "SEARCH_HTTP_POOL_SIZE";
+
+/// Environment variable holding how long an idle connection is kept, in milliseconds
+pub const IDLE_TIMEOUT_ENV: &str = "SEARCH_HTTP_IDLE_TIMEOUT_MS";
+
+/// Environment variable that turns HTTP/2 off with `false`
+pub const HTTP2_ENV: &str = "SEARCH_HTTP2";
+
+/// Idle connections a transport keeps open for reuse
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolConfig {
+    pub max_idle_per_host: usize,
+    pub idle_timeout: Duration,
+
+    /// Whether clients that allow HTTP/2 may use it
+    pub http2: bool,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self { max_idle_per_host: 16, idle_timeout: Duration::from_secs(90), http2: true }
+    }
+}
+
+impl PoolConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            max_idle_per_host: get_env_parsed(POOL_SIZE_ENV).ok().flatten().unwrap_or(defaults.max_idle_per_host),
+            idle_timeout: get_env_parsed::<u64>(IDLE_TIMEOUT_ENV)
+                .ok()
+                .flatten()
+                .map_or(defaults.idle_timeout, Duration::from_millis),
+            http2: get_env_parsed(HTTP2_ENV).ok().flatten().unwrap_or(defaults.http2),
+        }
+    }
+}
+
+/// Transport of the target, shared by every client of the process
+///
+/// `http2` picks the transport that offers HTTP/2, when the pool
+/// configuration allows it.
+pub fn default_transport(http2: bool) -> Arc<dyn HttpClient> {
+    static HTTP1: OnceLock<Arc<dyn HttpClient>> = OnceLock::new();
+    static HTTP2: OnceLock<Arc<dyn HttpClient>> = OnceLock::new();
+    let pool = PoolConfig::from_env();
+    let http2 = http2 && pool.http2;
+    let shared = if http2 { &HTTP2 } else { &HTTP1 };
+    shared.get_or_init(|| new_transport(pool, http2)).clone()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn new_transport(_pool: PoolConfig, _http2: bool) -> Arc<dyn HttpClient> {
+    Arc::new(WasiHttp)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn new_transport(pool: PoolConfig, http2: bool) -> Arc<dyn HttpClient> {
+    Arc::new(NativeHttp::new(pool, http2))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ErrorKind {
+    Builder,
+    Connect,
+    Timeout,
+    Request,
+    Status,
+    Body,
+    Decode,
+}
+
+/// Failure to build, send or read a request
+#[derive(Debug, Clone)]
+pub struct Error {
+    kind: ErrorKind,
+    message: String,
+    status: Option<StatusCode>,
+}
+
+impl Error {
+    fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self { kind, message: message.into(), status: None }
+    }
+
+    /// Error status that persisted through every retry
+    pub fn status_error(status: StatusCode, message: impl Into<String>) -> Self {
+        Self { status: Some(status), ..Self::new(ErrorKind::Status, message) }
+    }
+
+    pub fn builder(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Builder, message)
+    }
+
+    pub fn connect(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Connect, message)
+    }
+
+    pub fn timeout(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Timeout, message)
+    }
+
+    pub fn request(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Request, message)
+    }
+
+    pub fn body(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Body, message)
+    }
+
+    pub fn decode(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Decode, message)
+    }
+
+    pub fn is_builder(&self) -> bool {
+        self.kind == ErrorKind::Builder
+    }
+
+    pub fn is_connect(&self) -> bool {
+        self.kind == ErrorKind::Connect
+    }
+
+    pub fn is_timeout(&self) -> bool {
+        self.kind == ErrorKind::Timeout
+    }
+
+    pub fn is_body(&self) -> bool {
+        self.kind == ErrorKind::Body
+    }
+
+    pub fn is_decode(&self) -> bool {
+        self.kind == ErrorKind::Decode
+    }
+
+    pub fn is_status(&self) -> bool {
+        self.kind == ErrorKind::Status
+    }
+
+    pub fn status(&self) -> Option<StatusCode> {
+        self.status
+    }
+
+    fn with_context(mut self, context: &str) -> Self {
+        self.message = format!("{}; {}", self.message, context);
+        self
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            ErrorKind::Builder => "invalid request",
+            ErrorKind::Connect => "connection failed",
+            ErrorKind::Timeout => "request timed out",
+            ErrorKind::Request => "request failed",
+            ErrorKind::Status => "server error",
+            ErrorKind::Body => "failed to read body",
+            ErrorKind::Decode => "failed to decode body",
+        };
+        write!(f, "{}: {}", kind, self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// A request ready for a transport
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub method: Method,
+    pub url: Url,
+    pub headers: HeaderMap,
+    pub body: Option<Vec<u8>>,
+    pub timeout: Option<Duration>,
+}
+
+/// A response with its body read
+#[derive(Debug, Clone)]
+pub struct Response {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Vec<u8>,
+    url: Url,
+}
+
+impl Response {
+    pub fn new(status: StatusCode, headers: HeaderMap, body: Vec<u8>, url: Url) -> Self {
+        Self { status, headers, body, url }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// The body as text; invalid UTF-8 is replaced rather than refused
+    pub fn text(self) -> Result<String, Error> {
+        Ok(match String::from_utf8(self.body) {
+            Ok(text) => text,
+            Err(e) => String::from_utf8_lossy(e.as_bytes()).into_owned(),
+        })
+    }
+
+    pub fn bytes(self) -> Result<Vec<u8>, Error> {
+        Ok(self.body)
+    }
+
+    pub fn json<T: DeserializeOwned>(self) -> Result<T, Error> {
+        serde_json::from_slice(&self.body).map_err(|e| Error::decode(e.to_string()))
+    }
+}
+
+/// Something a request can be sent to
+pub trait IntoUrl {
+    fn into_url(self) -> Result<Url, Error>;
+}
+
+impl IntoUrl for Url {
+    fn into_url(self) -> Result<Url, Error> {
+        Ok(self)
+    }
+}
+
+impl IntoUrl for &str {
+    fn into_url(self) -> Result<Url, Error> {
+        Url::parse(self).map_err(|e| Error::builder(format!("Invalid URL {}: {}", self, e)))
+    }
+}
+
+impl IntoUrl for &String {
+    fn into_url(self) -> Result<Url, Error> {
+        self.as_str().into_url()
+    }
+}
+
+impl IntoUrl for String {
+    fn into_url(self) -> Result<Url, Error> {
+        self.as_str().into_url()
+    }
+}
+
+/// `value` percent-encoded as one segment of a URL path
+///
+/// Everything but RFC 3986 unreserved characters is encoded, so a document ID
+/// holding `/`, `?`, `#` or spaces still names that document.
+pub fn path_segment(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Client with the headers and timeout shared by its requests
+#[derive(Clone)]
+pub struct Client {
+    transport: Arc<dyn HttpClient>,
+    default_headers: HeaderMap,
+    timeout: Option<Duration>,
+    retry: RetryPolicy,
+}
+
+impl fmt::Debug for Client {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Client")
+            .field("default_headers", &self.default_headers)
+            .field("timeout", &self.timeout)
+            .field("retry", &self.retry)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for Client {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Client {
+    /// Client on the default transport, without default headers, timeout or retries
+    pub fn new() -> Self {
+        Self {
+            transport: default_transport(false),
+            default_headers: HeaderMap::new(),
+            timeout: None,
+            retry: RetryPolicy::NONE,
+        }
+    }
+
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::default()
+    }
+
+    pub fn request(&self, method: Method, url: impl IntoUrl) -> RequestBuilder {
+        let request = url.into_url().map(|url| Request {
+            method,
+            url,
+            headers: self.default_headers.clone(),
+            body: None,
+            timeout: self.timeout,
+        });
+        RequestBuilder { client: self.clone(), request }
+    }
+
+    pub fn get(&self, url: impl IntoUrl) -> RequestBuilder {
+        self.request(Method::GET, url)
+    }
+
+    pub fn post(&self, url: impl IntoUrl) -> RequestBuilder {
+        self.request(Method::POST, url)
+    }
+
+    pub fn put(&self, url: impl IntoUrl) -> RequestBuilder {
+        self.request(Method::PUT, url)
+    }
+
+    pub fn patch(&self, url: impl IntoUrl) -> RequestBuilder {
+        self.request(Method::PATCH, url)
+    }
+
+    pub fn delete(&self, url: impl IntoUrl) -> RequestBuilder {
+        self.request(Method::DELETE, url)
+    }
+
+    pub fn head(&self, url: impl IntoUrl) -> RequestBuilder {
+        self.request(Method::HEAD, url)
+    }
+}
+
+#[derive(Default)]
+pub struct ClientBuilder {
+    transport: Option<Arc<dyn HttpClient>>,
+    default_headers: HeaderMap,
+    timeout: Option<Duration>,
+    retry: RetryPolicy,
+    http2: bool,
+    accept_compressed: bool,
+}
+
+impl ClientBuilder {
+    /// Headers sent with every request unless the request sets them itself
+    pub fn default_headers(mut self, headers: HeaderMap) -> Self {
+        self.default_headers = headers;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Retry requests that are safe to repeat under `policy`
+    pub fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Allow HTTP/2, for servers known to speak it well; TLS servers without it fall back to HTTP/1.1
+    pub fn http2(mut self, enabled: bool) -> Self {
+        self.http2 = enabled;
+        self
+    }
+
+    /// Ask for gzip or deflate responses, unless a request sets its own `Accept-Encoding`
+    pub fn accept_compressed(mut self, enabled: bool) -> Self {
+        self.accept_compressed = enabled;
+        self
+    }
+
+    /// Send requests through `transport` instead of the target's default
+    pub fn transport(mut self, transport: Arc<dyn HttpClient>) -> Self {
+        self.transport = Some(transport);
+        self
+    }
+
+    pub fn build(mut self) -> Result<Client, Error> {
+        if self.accept_compressed {
+            self.default_headers
+                .entry(ACCEPT_ENCODING)
+                .or_insert_with(|| HeaderValue::from_static(compression::ACCEPTED_ENCODINGS));
+        }
+        Ok(Client {
+            transport: self.transport.unwrap_or_else(|| default_transport(self.http2)),
+            default_headers: self.default_headers,
+            timeout: self.timeout,
+            retry: self.retry,
+        })
+    }
+}
+
+/// A request being built; errors are kept until [`send`](Self::send)
+pub struct RequestBuilder {
+    client: Client,
+    request: Result<Request, Error>,
+}
+
+impl RequestBuilder {
+    fn map(mut self, f: impl FnOnce(&mut Request) -> Result<(), Error>) -> Self {
+        if let Ok(request) = &mut self.request {
+            if let Err(e) = f(request) {
+                self.request = Err(e);
+            }
+        }
+        self
+    }
+
+    /// Set a header, replacing a default header of the same name
+    pub fn header<K, V>(self, name: K, value: V) -> Self
+    where
+        K: TryInto<HeaderName>,
+        K::Error: fmt::Display,
+        V: TryInto<HeaderValue>,
+        V::Error: fmt::Display,
+    {
+        let header = name
+            .try_into()
+            .map_err(|e| Error::builder(format!("Invalid header name: {}", e)))
+            .and_then(|name| {
+                value
+                    .try_into()
+                    .map(|value| (name, value))
+                    .map_err(|e| Error::builder(format!("Invalid header value: {}", e)))
+            });
+        self.map(|request| {
+            let (name, value) = header?;
+            request.headers.insert(name, value);
+            Ok(())
+        })
+    }
+
+    /// Append query parameters to the URL
+    pub fn query<K: AsRef<str>, V: AsRef<str>>(self, pairs: &[(K, V)]) -> Self {
+        self.map(|request| {
+            request.url.query_pairs_mut().extend_pairs(pairs.iter().map(|(k, v)| (k.as_ref(), v.as_ref())));
+            Ok(())
+        })
+    }
+
+    /// Send `body` as JSON, with a JSON content type unless one is set
+    pub fn json<T: Serialize + ?Sized>(self, body: &T) -> Self {
+        let body = serde_json::to_vec(body).map_err(|e| Error::builder(format!("Failed to serialize body: {}", e)));
+        self.map(|request| {
+            request.body = Some(body?);
+            request
+                .headers
+                .entry(CONTENT_TYPE)
+                .or_insert_with(|| HeaderValue::from_static("application/json"));
+            Ok(())
+        })
+    }
+
+    pub fn body(self, body: impl Into<Vec<u8>>) -> Self {
+        let body = body.into();
+        self.map(|request| {
+            request.body = Some(body);
+            Ok(())
+        })
+    }
+
+    pub fn timeout(self, timeout: Duration) -> Self {
+        self.map(|request| {
+            request.timeout = Some(timeout);
+            Ok(())
+        })
+    }
+
+    /// Send the request, retrying it under the client's policy when it is safe to repeat
+    ///
+    /// When the retries run out, a 5xx response becomes an error, and both
+    /// it and a transport error name the retries made and their budget.
+    pub fn send(self) -> Result<Response, Error> {
+        let request = self.request?;
+        let execute = |mut request: Request| {
+            if let Some(deadline) = deadline::current() {
+                let remaining = deadline.remaining();
+                if remaining.is_zero() {
+                    return Err(Error::timeout(format!(
+                        "deadline of {} ms spent before the request was sent",
+                        deadline.budget().as_millis()
+                    )));
+                }
+                request.timeout = Some(remaining);
+            }
+            self.client.transport.execute(request).and_then(decompressed)
+        };
+        if self.client.retry.max_retries == 0 || !endpoint_pool::is_idempotent(&request.method, request.url.path()) {
+            return execute(request);
+        }
+
+        let mut budget = RetryBudget::start(self.client.retry);
+        loop {
+            let result = execute(request.clone());
+            let failure = match &result {
+                Ok(response) if response.status().is_server_error() => format!("HTTP {}", response.status()),
+                Err(e) if e.is_timeout() || e.is_connect() => e.to_string(),
+                _ => return result,
+            };
+            match budget.next_delay() {
+                Some(delay) if deadline::current().is_some_and(|deadline| deadline.remaining() <= delay) => {
+                    debug!("{} {} failed ({}), no retry: the deadline runs out first", request.method, request.url, failure);
+                    return result;
+                }
+                Some(delay) => {
+                    debug!(
+                        "{} {} failed ({}), retry {} in {} ms",
+                        request.method,
+                        request.url,
+                        failure,
+                        budget.retries(),
+                        delay.as_millis()
+                    );
+                    std::thread::sleep(delay);
+                }
+                None if budget.retries() == 0 => return result,
+                None => return Err(exhausted(result, &budget)),
+            }
+        }
+    }
+}
+
+/// The response with a gzip or deflate body decoded, and the headers describing the encoded body dropped
+fn decompressed(mut response: Response) -> Result<Response, Error> {
+    let encoding = response
+        .headers
+        .get(CONTENT_ENCODING)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<Compression>().ok())
+        .filter(|compression| compression.is_enabled());
+    let Some(encoding) = encoding else {
+        return Ok(response);
+    };
+    if !response.body.is_empty() {
+        response.body = encoding
+            .decompress(&response.body)
+            .map_err(|e| Error::body(format!("Failed to decode {} response: {}", encoding.content_encoding().unwrap_or_default(), e)))?;
+    }
+    response.headers.remove(CONTENT_ENCODING);
+    response.headers.remove(CONTENT_LENGTH);
+    Ok(response)
+}
+
+/// Error for the last failure of a request whose retries ran out
+fn exhausted(result: Result<Response, Error>, budget: &RetryBudget) -> Error {
+    match result {
+        Ok(response) => {
+            let status = response.status();
+            let body = response.text().unwrap_or_default();
+            let body: String = body.chars().take(MAX_ERROR_BODY_CHARS).collect();
+            Error::status_error(status, format!("HTTP {}: {}", status, body)).with_context(&budget.summary())
+        }
+        Err(e) => e.with_context(&budget.summary()),
+    }
+}
+
+/// Longest part of an error body kept in an error message
+const MAX_ERROR_BODY_CHARS: usize = 500;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Transport answering every request with its own method, URL and headers
+    #[derive(Default)]
+    struct Echo {
+        seen: Mutex<Vec<Request>>,
+    }
+
+    impl HttpClient for Echo {
+        fn execute(&self, request: Request) -> Result<Response, Error> {
+            self.seen.lock().unwrap().push(request.clone());
+            let body = serde_json::json!({
+                "method": request.method.as_str(),
+                "url": request.url.as_str(),
+                "body": String::from_utf8_lossy(request.body.as_deref().unwrap_or_default()),
+            });
+            Ok(Response::new(StatusCode::OK, HeaderMap::new(), body.to_string().into_bytes(), request.url))
+        }
+    }
+
+    #[test]
+    fn test_path_segments_encode_reserved_characters() {
+        assert_eq!(path_segment("sku-42_a.b~c"), "sku-42_a.b~c");
+        assert_eq!(path_segment("a/b?c#d e"), "a%2Fb%3Fc%23d%20e");
+        assert_eq!(path_segment("café"), "caf%C3%A9");
+    }
+
+    #[test]
+    fn test_requests_carry_defaults_and_builder_settings() {
+        let echo = Arc::new(Echo::default());
+        let mut headers = HeaderMap::new();
+        headers.insert("X-Api-Key", HeaderValue::from_static("secret"));
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("text/plain"));
+        let client = Client::builder()
+            .default_headers(headers)
+            .timeout(Duration::from_secs(5))
+            .transport(echo.clone())
+            .build()
+            .unwrap();
+
+        let response = client
+            .post("http://localhost:9200/products/_search")
+            .header("X-Request-Id", "abc")
+            .query(&[("q", "a b")])
+            .json(&serde_json::json!({"size": 1}))
+            .send()
+            .unwrap();
+        assert!(response.status().is_success());
+        let echoed: serde_json::Value = response.json().unwrap();
+        assert_eq!(echoed["url"], "http://localhost:9200/products/_search?q=a+b");
+        assert_eq!(echoed["body"], r#"{"size":1}"#);
+
+        let seen = echo.seen.lock().unwrap();
+        assert_eq!(seen[0].headers["X-Api-Key"], "secret");
+        assert_eq!(seen[0].headers["X-Request-Id"], "abc");
+        assert_eq!(seen[0].headers[CONTENT_TYPE], "text/plain");
+        assert_eq!(seen[0].timeout, Some(Duration::from_secs(5)));
+        drop(seen);
+
+        let err = client.get("not a url").send().unwrap_err();
+        assert!(err.is_builder());
+        assert!(client.get("http://localhost").header("X-Bad", "line\nbreak").send().unwrap_err().is_builder());
+    }
+
+    #[test]
+    fn test_deadline_replaces_client_timeout() {
+        let echo = Arc::new(Echo::default());
+        let client = Client::builder().timeout(Duration::from_secs(30)).transport(echo.clone()).build().unwrap();
+        {
+            let _deadline = deadline::begin(Some(250));
+            client.get("http://localhost/products").send().unwrap();
+        }
+        {
+            let _spent = deadline::begin(Some(0));
+            let err = client.get("http://localhost/products").send().unwrap_err();
+            assert!(err.is_timeout());
+            assert!(matches!(crate::error::SearchError::from(err), crate::error::SearchError::Timeout { remaining_ms: Some(0) }));
+        }
+        client.get("http://localhost/products").send().unwrap();
+
+        let seen = echo.seen.lock().unwrap();
+        assert_eq!(seen.len(), 2);
+        assert!(seen[0].timeout.is_some_and(|timeout| timeout <= Duration::from_millis(250)));
+        assert_eq!(seen[1].timeout, Some(Duration::from_secs(30)));
+    }
+
+    /// Transport answering with the `Accept-Encoding` of the request and a gzip body
+    struct Gzipped;
+
+    impl HttpClient for Gzipped {
+        fn execute(&self, request: Request) -> Result<Response, Error> {
+            let accepted = request.headers.get(ACCEPT_ENCODING).map(|v| v.to_str().unwrap().to_string());
+            let body = Compression::Gzip.compress(serde_json::json!({ "accepted": accepted }).to_string().into_bytes()).unwrap();
+            let mut headers = HeaderMap::new();
+            headers.insert(CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+            headers.insert(CONTENT_LENGTH, HeaderValue::from(body.len()));
+            Ok(Response::new(StatusCode::OK, headers, body, request.url))
+        }
+    }
+
+    #[test]
+    fn test_compressed_responses_decoded() {
+        let client = |accept: bool| Client::builder().accept_compressed(accept).transport(Arc::new(Gzipped)).build().unwrap();
+
+        let response = client(true).get("http://localhost/products").send().unwrap();
+        assert!(response.headers().get(CONTENT_ENCODING).is_none());
+        assert_eq!(response.json::<serde_json::Value>().unwrap()["accepted"], "gzip, deflate");
+
+        let response = client(false).get("http://localhost/products").send().unwrap();
+        assert_eq!(response.json::<serde_json::Value>().unwrap()["accepted"], serde_json::Value::Null);
+    }
+
+    /// Transport failing with `status` a set number of times before answering 200
+    struct Flaky {
+        failures: Mutex<u32>,
+        status: StatusCode,
+        attempts: Mutex<u32>,
+    }
+
+    impl Flaky {
+        fn new(failures: u32, status: StatusCode) -> Arc<Self> {
+            Arc::new(Self { failures: Mutex::new(failures), status, attempts: Mutex::new(0) })
+        }
+
+        fn attempts(&self) -> u32 {
+            *self.attempts.lock().unwrap()
+        }
+    }
+
+    impl HttpClient for Flaky {
+        fn execute(&self, request: Request) -> Result<Response, Error> {
+            *self.attempts.lock().unwrap() += 1;
+            let mut failures = self.failures.lock().unwrap();
+            let status = if *failures > 0 { self.status } else { StatusCode::OK };
+            *failures = failures.saturating_sub(1);
+            Ok(Response::new(status, HeaderMap::new(), b"busy".to_vec(), request.url))
+        }
+    }
+
+    /// Transport whose connections are always refused
+    #[derive(Default)]
+    struct Refused {
+        attempts: Mutex<u32>,
+    }
+
+    impl HttpClient for Refused {
+        fn execute(&self, _: Request) -> Result<Response, Error> {
+            *self.attempts.lock().unwrap() += 1;
+            Err(Error::connect("connection refused"))
+        }
+    }
+
+    fn retrying(transport: Arc<dyn HttpClient>) -> Client {
+        let policy = RetryPolicy { max_retries: 2, base_delay: Duration::from_millis(1), ..RetryPolicy::NONE };
+        Client::builder().retry(policy).transport(transport).build().unwrap()
+    }
+
+    #[test]
+    fn test_idempotent_requests_retried_until_they_succeed() {
+        let recovers = Flaky::new(2, StatusCode::SERVICE_UNAVAILABLE);
+        let response = retrying(recovers.clone()).get("http://localhost/products").send().unwrap();
+        assert!(response.status().is_success());
+        assert_eq!(recovers.attempts(), 3);
+
+        // Without a retry policy the first answer is final
+        let once = Flaky::new(2, StatusCode::SERVICE_UNAVAILABLE);
+        let client = Client::builder().transport(once.clone()).build().unwrap();
+        assert_eq!(client.get("http://localhost/products").send().unwrap().status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(once.attempts(), 1);
+    }
+
+    #[test]
+    fn test_exhausted_retries_turn_the_last_status_into_an_error() {
+        let down = Flaky::new(5, StatusCode::BAD_GATEWAY);
+        let err = retrying(down.clone()).post("http://localhost/products/_search").send().unwrap_err();
+        assert!(err.is_status());
+        assert_eq!(err.status(), Some(StatusCode::BAD_GATEWAY));
+        assert!(err.to_string().contains("HTTP 502 Bad Gateway: busy"), "{}", err);
+        assert!(err.to_string().contains("gave up after 2 retries"), "{}", err);
+        assert_eq!(down.attempts(), 3);
+    }
+
+    #[test]
+    fn test_connection_errors_retried_until_retries_run_out() {
+        let refused = Arc::new(Refused::default());
+        let err = retrying(refused.clone()).get("http://localhost/products/_doc/1").send().unwrap_err();
+        assert!(err.is_connect());
+        assert!(err.to_string().contains("gave up after 2 retries"), "{}", err);
+        assert_eq!(*refused.attempts.lock().unwrap(), 3);
+    }
+
+    #[test]
+    fn test_non_idempotent_requests_are_sent_once() {
+        let write = Flaky::new(5, StatusCode::SERVICE_UNAVAILABLE);
+        let response = retrying(write.clone()).post("http://localhost/products/_doc").send().unwrap();
+        assert_eq!((response.status(), write.attempts()), (StatusCode::SERVICE_UNAVAILABLE, 1));
+
+        let patch = Flaky::new(5, StatusCode::SERVICE_UNAVAILABLE);
+        retrying(patch.clone()).patch("http://localhost/collections/products").send().unwrap();
+        assert_eq!(patch.attempts(), 1);
+
+        // A refused write fails as it is, without a retry summary
+        let refused = Arc::new(Refused::default());
+        let err = retrying(refused.clone()).post("http://localhost/_bulk").send().unwrap_err();
+        assert!(err.is_connect());
+        assert!(!err.to_string().contains("gave up"), "{}", err);
+        assert_eq!(*refused.attempts.lock().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_client_errors_are_not_retried() {
+        let missing = Flaky::new(5, StatusCode::NOT_FOUND);
+        assert_eq!(retrying(missing.clone()).get("http://localhost/x").send().unwrap().status(), StatusCode::NOT_FOUND);
+        assert_eq!(missing.attempts(), 1);
+    }
+
+    #[test]
+    fn test_no_retry_when_the_deadline_runs_out_during_the_wait() {
+        let policy = RetryPolicy { max_retries: 3, base_delay: Duration::from_secs(1), ..RetryPolicy::NONE };
+        let flaky = Flaky::new(5, StatusCode::SERVICE_UNAVAILABLE);
+        let client = Client::builder().retry(policy).transport(flaky.clone()).build().unwrap();
+
+        let _deadline = deadline::begin(Some(100));
+        let response = client.get("http://localhost/products").send().unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(flaky.attempts(), 1);
+    }
+}
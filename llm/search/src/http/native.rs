@@ -0,0 +1,170 @@
+//! Transport for native builds, over `hyper`
+
+use std::sync::{mpsc, OnceLock};
+use http::Request as HttpRequest;
+use http_body_util::{BodyExt, Full};
+use hyper::body::Bytes;
+use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::client::legacy::Client;
+use hyper_util::rt::{TokioExecutor, TokioTimer};
+use tokio::runtime::{Builder, Runtime};
+use super::{Error, HttpClient, PoolConfig, Request, Response};
+
+/// Runtime driving the connections of every native transport
+///
+/// It runs on threads of its own, so callers inside another runtime can
+/// block on a request without stalling its I/O.
+fn io_runtime() -> Result<&'static Runtime, Error> {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    if let Some(runtime) = RUNTIME.get() {
+        return Ok(runtime);
+    }
+    let runtime = Builder::new_multi_thread()
+        .worker_threads(1)
+        .thread_name("search-http")
+        .enable_all()
+        .build()
+        .map_err(|e| Error::request(format!("Failed to start the HTTP runtime: {}", e)))?;
+    Ok(RUNTIME.get_or_init(|| runtime))
+}
+
+/// Blocking transport keeping idle connections open for reuse, for tests and tools run outside a component
+#[derive(Clone)]
+pub struct NativeHttp {
+    client: Client<HttpsConnector<HttpConnector>, Full<Bytes>>,
+}
+
+impl std::fmt::Debug for NativeHttp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NativeHttp").finish_non_exhaustive()
+    }
+}
+
+impl NativeHttp {
+    /// Transport with the pool of `pool`, offering HTTP/2 to TLS servers when `http2` is set
+    pub fn new(pool: PoolConfig, http2: bool) -> Self {
+        let connector = HttpsConnectorBuilder::new().with_webpki_roots().https_or_http().enable_http1();
+        let connector = if http2 { connector.enable_http2().build() } else { connector.build() };
+        let client = Client::builder(TokioExecutor::new())
+            .pool_timer(TokioTimer::new())
+            .pool_idle_timeout(pool.idle_timeout)
+            .pool_max_idle_per_host(pool.max_idle_per_host)
+            .build(connector);
+        Self { client }
+    }
+}
+
+impl Default for NativeHttp {
+    fn default() -> Self {
+        Self::new(PoolConfig::default(), false)
+    }
+}
+
+impl HttpClient for NativeHttp {
+    fn execute(&self, request: Request) -> Result<Response, Error> {
+        let client = self.client.clone();
+        let (sender, receiver) = mpsc::channel();
+        io_runtime()?.spawn(async move {
+            let _ = sender.send(send(client, request).await);
+        });
+        receiver.recv().map_err(|_| Error::request("HTTP runtime stopped before the response arrived"))?
+    }
+}
+
+async fn send(client: Client<HttpsConnector<HttpConnector>, Full<Bytes>>, request: Request) -> Result<Response, Error> {
+    let mut outgoing = HttpRequest::builder()
+        .method(request.method.clone())
+        .uri(request.url.as_str())
+        .body(Full::new(Bytes::from(request.body.unwrap_or_default())))
+        .map_err(|e| Error::builder(e.to_string()))?;
+    *outgoing.headers_mut() = request.headers;
+
+    let exchange = async {
+        let response = client.request(outgoing).await.map_err(|e| {
+            if e.is_connect() {
+                Error::connect(error_chain(&e))
+            } else {
+                Error::request(error_chain(&e))
+            }
+        })?;
+        let (parts, body) = response.into_parts();
+        let body = body.collect().await.map_err(|e| Error::body(e.to_string()))?.to_bytes();
+        Ok(Response::new(parts.status, parts.headers, body.to_vec(), request.url))
+    };
+    match request.timeout {
+        Some(timeout) => tokio::time::timeout(timeout, exchange)
+            .await
+            .map_err(|_| Error::timeout(format!("no response within {} ms", timeout.as_millis())))?,
+        None => exchange.await,
+    }
+}
+
+/// An error with its sources, which name the cause a client error only wraps
+fn error_chain(error: &dyn std::error::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        message = format!("{}: {}", message, cause);
+        source = cause.source();
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use http::{HeaderMap, Method};
+    use url::Url;
+
+    #[test]
+    fn test_connections_reused_across_requests() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let connections = Arc::new(AtomicUsize::new(0));
+        let accepted = connections.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                accepted.fetch_add(1, Ordering::SeqCst);
+                std::thread::spawn(move || {
+                    let mut buffer = [0u8; 4096];
+                    while let Ok(read) = stream.read(&mut buffer) {
+                        if read == 0 {
+                            break;
+                        }
+                        let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok");
+                    }
+                });
+            }
+        });
+
+        let transport = NativeHttp::new(PoolConfig { max_idle_per_host: 4, ..PoolConfig::default() }, false);
+        for _ in 0..3 {
+            let request = Request {
+                method: Method::GET,
+                url: Url::parse(&format!("http://{}/health", address)).unwrap(),
+                headers: HeaderMap::new(),
+                body: None,
+                timeout: Some(Duration::from_secs(5)),
+            };
+            let response = transport.execute(request).unwrap();
+            assert_eq!(response.text().unwrap(), "ok");
+        }
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+
+        let refused = Request {
+            method: Method::GET,
+            url: Url::parse("http://127.0.0.1:1/").unwrap(),
+            headers: HeaderMap::new(),
+            body: None,
+            timeout: None,
+        };
+        assert!(transport.execute(refused).unwrap_err().is_connect());
+    }
+}
@@ -0,0 +1,167 @@
+//! Transport over `wasi:http/outgoing-handler`
+
+use std::time::Duration;
+use http::{HeaderMap, HeaderName, HeaderValue, Method, StatusCode};
+use url::Url;
+use wasi::http::outgoing_handler;
+use wasi::http::types::{
+    ErrorCode, Fields, IncomingBody, Method as WasiMethod, OutgoingBody, OutgoingRequest, RequestOptions, Scheme,
+};
+use wasi::io::streams::StreamError;
+use super::{Error, HttpClient, Request, Response};
+
+/// Most a blocking write accepts at once
+const WRITE_CHUNK: usize = 4096;
+
+const READ_CHUNK: u64 = 64 * 1024;
+
+/// Hands requests to the host and blocks on their pollables
+#[derive(Debug, Default, Clone, Copy)]
+pub struct WasiHttp;
+
+impl HttpClient for WasiHttp {
+    fn execute(&self, request: Request) -> Result<Response, Error> {
+        let outgoing = outgoing_request(&request)?;
+        let body = outgoing.body().map_err(|()| Error::request("Request body already taken"))?;
+        let future = outgoing_handler::handle(outgoing, Some(request_options(request.timeout)))
+            .map_err(from_error_code)?;
+        write_body(&body, request.body.as_deref().unwrap_or_default())?;
+        OutgoingBody::finish(body, None).map_err(from_error_code)?;
+
+        let incoming = loop {
+            match future.get() {
+                Some(Ok(result)) => break result.map_err(from_error_code)?,
+                Some(Err(())) => return Err(Error::request("Response already taken")),
+                None => future.subscribe().block(),
+            }
+        };
+
+        let status = StatusCode::from_u16(incoming.status()).map_err(|e| Error::decode(e.to_string()))?;
+        let mut headers = HeaderMap::new();
+        for (name, value) in incoming.headers().entries() {
+            if let (Ok(name), Ok(value)) = (HeaderName::from_bytes(name.as_bytes()), HeaderValue::from_bytes(&value)) {
+                headers.append(name, value);
+            }
+        }
+        let body = read_body(incoming.consume().map_err(|()| Error::body("Response body already taken"))?)?;
+        Ok(Response::new(status, headers, body, request.url))
+    }
+}
+
+fn outgoing_request(request: &Request) -> Result<OutgoingRequest, Error> {
+    let entries: Vec<(String, Vec<u8>)> = request
+        .headers
+        .iter()
+        .map(|(name, value)| (name.as_str().to_string(), value.as_bytes().to_vec()))
+        .collect();
+    let headers = Fields::from_list(&entries).map_err(|e| Error::builder(format!("Invalid headers: {:?}", e)))?;
+    let outgoing = OutgoingRequest::new(headers);
+
+    let url = &request.url;
+    outgoing
+        .set_method(&wasi_method(&request.method))
+        .map_err(|()| Error::builder(format!("Unsupported method {}", request.method)))?;
+    let scheme = match url.scheme() {
+        "http" => Scheme::Http,
+        "https" => Scheme::Https,
+        other => Scheme::Other(other.to_string()),
+    };
+    outgoing.set_scheme(Some(&scheme)).map_err(|()| Error::builder(format!("Unsupported scheme in {}", url)))?;
+    outgoing
+        .set_authority(Some(&authority(url)))
+        .map_err(|()| Error::builder(format!("Invalid authority in {}", url)))?;
+    let path = match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
+    };
+    outgoing.set_path_with_query(Some(&path)).map_err(|()| Error::builder(format!("Invalid path in {}", url)))?;
+    Ok(outgoing)
+}
+
+fn authority(url: &Url) -> String {
+    let host = url.host_str().unwrap_or_default();
+    match url.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_string(),
+    }
+}
+
+fn wasi_method(method: &Method) -> WasiMethod {
+    match *method {
+        Method::GET => WasiMethod::Get,
+        Method::HEAD => WasiMethod::Head,
+        Method::POST => WasiMethod::Post,
+        Method::PUT => WasiMethod::Put,
+        Method::DELETE => WasiMethod::Delete,
+        Method::CONNECT => WasiMethod::Connect,
+        Method::OPTIONS => WasiMethod::Options,
+        Method::TRACE => WasiMethod::Trace,
+        Method::PATCH => WasiMethod::Patch,
+        _ => WasiMethod::Other(method.as_str().to_string()),
+    }
+}
+
+/// Apply the request timeout to every phase; hosts that refuse one run the request without it
+fn request_options(timeout: Option<Duration>) -> RequestOptions {
+    let options = RequestOptions::new();
+    if let Some(timeout) = timeout {
+        let nanos = u64::try_from(timeout.as_nanos()).unwrap_or(u64::MAX);
+        let _ = options.set_connect_timeout(Some(nanos));
+        let _ = options.set_first_byte_timeout(Some(nanos));
+        let _ = options.set_between_bytes_timeout(Some(nanos));
+    }
+    options
+}
+
+fn write_body(body: &OutgoingBody, bytes: &[u8]) -> Result<(), Error> {
+    if bytes.is_empty() {
+        return Ok(());
+    }
+    // The stream is dropped on return, before the body is finished
+    let stream = body.write().map_err(|()| Error::request("Request body stream already taken"))?;
+    for chunk in bytes.chunks(WRITE_CHUNK) {
+        stream
+            .blocking_write_and_flush(chunk)
+            .map_err(|e| Error::request(format!("Failed to send request body: {:?}", e)))?;
+    }
+    Ok(())
+}
+
+fn read_body(body: IncomingBody) -> Result<Vec<u8>, Error> {
+    let stream = body.stream().map_err(|()| Error::body("Response body stream already taken"))?;
+    let mut bytes = Vec::new();
+    loop {
+        match stream.blocking_read(READ_CHUNK) {
+            Ok(chunk) => bytes.extend_from_slice(&chunk),
+            Err(StreamError::Closed) => break,
+            Err(StreamError::LastOperationFailed(e)) => {
+                return Err(Error::body(format!("Failed to read response body: {}", e.to_debug_string())));
+            }
+        }
+    }
+    drop(stream);
+    IncomingBody::finish(body);
+    Ok(bytes)
+}
+
+fn from_error_code(code: ErrorCode) -> Error {
+    match code {
+        ErrorCode::DnsTimeout
+        | ErrorCode::ConnectionTimeout
+        | ErrorCode::ConnectionReadTimeout
+        | ErrorCode::ConnectionWriteTimeout
+        | ErrorCode::HttpResponseTimeout => Error::timeout(format!("{:?}", code)),
+        ErrorCode::DnsError(_)
+        | ErrorCode::DestinationNotFound
+        | ErrorCode::DestinationUnavailable
+        | ErrorCode::DestinationIpProhibited
+        | ErrorCode::DestinationIpUnroutable
+        | ErrorCode::ConnectionRefused
+        | ErrorCode::ConnectionTerminated
+        | ErrorCode::ConnectionLimitReached
+        | ErrorCode::TlsProtocolError
+        | ErrorCode::TlsCertificateError
+        | ErrorCode::TlsAlertReceived(_) => Error::connect(format!("{:?}", code)),
+        other => Error::request(format!("{:?}", other)),
+    }
+}
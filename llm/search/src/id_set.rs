@@ -0,0 +1,336 @@
+//! Filters on large sets of document IDs
+//!
+//! Restricting a search to a few thousand known documents, such as the ones a
+//! user may see, would otherwise take an enormous hand-built filter string in
+//! each provider's syntax. An [`IdSetFilter`] in a query's filters is written
+//! once and translated per provider:
+//!
+//! - Elasticsearch and OpenSearch take a `terms` query, split into batches
+//!   of [`MAX_TERMS_PER_CLAUSE`] values. They can also read the IDs from a
+//!   field of a stored document with a terms lookup, so the set never travels
+//!   with the query.
+//! - Typesense takes `field:[..]`. Long filters no longer fit the URL of a
+//!   search, so the provider sends such searches in a request body instead.
+//! - Meilisearch takes `field IN [..]`.
+//! - Algolia takes an `OR` of `field:id` filters, at most
+//!   [`ALGOLIA_MAX_IDS`] of them per search; a larger set is searched one
+//!   batch at a time and the hits merged.
+
+use serde_json::{json, Value};
+use crate::error::{SearchError, SearchResult};
+
+/// Name of the filter, `id_set(<field>, <ids or lookup>)`
+pub const ID_SET_FILTER: &str = "id_set";
+
+/// Values per `terms` clause, the default `index.max_terms_count` of Elasticsearch and OpenSearch
+pub const MAX_TERMS_PER_CLAUSE: usize = 65_536;
+
+/// IDs Algolia accepts in one filter expression
+pub const ALGOLIA_MAX_IDS: usize = 1_000;
+
+/// Where the IDs of a set are kept
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IdSource {
+    /// IDs listed in the filter
+    Ids(Vec<String>),
+
+    /// IDs read from the field at `path` of document `id` in `index`
+    Lookup { index: String, id: String, path: String },
+}
+
+/// Filter keeping the documents whose field holds one of a set of IDs
+///
+/// Written `id_set(field, ["a", "b", ..])`, or for Elasticsearch and
+/// OpenSearch `id_set(field, {"index": "acl", "id": "user-1", "path": "docs"})`
+/// to take the IDs from a stored document. Numbers in the list are read as
+/// their decimal form, and duplicates are dropped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdSetFilter {
+    pub field: String,
+    pub source: IdSource,
+}
+
+impl IdSetFilter {
+    /// Filter on a list of IDs
+    pub fn new(field: impl Into<String>, ids: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self { field: field.into(), source: IdSource::Ids(dedup(ids.into_iter().map(Into::into))) }
+    }
+
+    /// Filter on the IDs stored at `path` of a document
+    pub fn lookup(field: impl Into<String>, index: impl Into<String>, id: impl Into<String>, path: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            source: IdSource::Lookup { index: index.into(), id: id.into(), path: path.into() },
+        }
+    }
+
+    /// Parse an ID set filter; `None` when `filter` is not one
+    pub fn parse(filter: &str) -> Option<SearchResult<Self>> {
+        let args = filter.trim().strip_prefix(ID_SET_FILTER)?.trim_start().strip_prefix('(')?;
+        let parsed = match args.trim_end().strip_suffix(')') {
+            Some(args) => Self::parse_args(args),
+            None => Err("missing closing parenthesis".to_string()),
+        };
+        // Keep the message short; the filter itself may hold thousands of IDs
+        Some(parsed.map_err(|e| SearchError::invalid_query(format!("Invalid {} filter: {}", ID_SET_FILTER, e))))
+    }
+
+    fn parse_args(args: &str) -> Result<Self, String> {
+        let (field, set) = args.split_once(',').ok_or("expected a field and IDs")?;
+        let field = field.trim();
+        if field.is_empty() || field.contains(char::is_whitespace) {
+            return Err(format!("invalid field \"{}\"", field));
+        }
+        match serde_json::from_str(set).map_err(|e| e.to_string())? {
+            Value::Array(ids) => {
+                let ids = ids
+                    .iter()
+                    .map(|id| match id {
+                        Value::String(id) if !id.is_empty() => Ok(id.clone()),
+                        Value::Number(id) => Ok(id.to_string()),
+                        _ => Err(format!("IDs must be non-empty strings or numbers, got {}", id)),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(Self::new(field, ids))
+            }
+            Value::Object(lookup) => {
+                let text = |key: &str| match lookup.get(key).and_then(Value::as_str) {
+                    Some(value) if !value.is_empty() => Ok(value.to_string()),
+                    _ => Err(format!("lookup needs a non-empty \"{}\"", key)),
+                };
+                if let Some(key) = lookup.keys().find(|key| !["index", "id", "path"].contains(&key.as_str())) {
+                    return Err(format!("unknown lookup key \"{}\"", key));
+                }
+                Ok(Self::lookup(field, text("index")?, text("id")?, text("path")?))
+            }
+            _ => Err("expected a list of IDs or a lookup object".to_string()),
+        }
+    }
+
+    /// The filter string [`parse`](Self::parse) reads back
+    pub fn to_filter(&self) -> String {
+        let set = match &self.source {
+            IdSource::Ids(ids) => json!(ids),
+            IdSource::Lookup { index, id, path } => json!({ "index": index, "id": id, "path": path }),
+        };
+        format!("{}({}, {})", ID_SET_FILTER, self.field, set)
+    }
+
+    /// Listed IDs, or an error naming `provider` for a lookup it cannot run
+    pub fn ids(&self, provider: &str) -> SearchResult<&[String]> {
+        match &self.source {
+            IdSource::Ids(ids) => Ok(ids),
+            IdSource::Lookup { .. } => Err(SearchError::invalid_query(format!(
+                "{} cannot look up {} IDs in another document; list them instead",
+                provider, ID_SET_FILTER
+            ))),
+        }
+    }
+
+    /// Elasticsearch and OpenSearch query, a terms lookup or `terms` clauses of at most [`MAX_TERMS_PER_CLAUSE`] IDs
+    ///
+    /// An empty list matches nothing, as it does on every other provider.
+    pub fn to_terms_query(&self) -> Value {
+        let ids = match &self.source {
+            IdSource::Lookup { index, id, path } => {
+                return json!({ "terms": { &self.field: { "index": index, "id": id, "path": path } } });
+            }
+            IdSource::Ids(ids) => ids,
+        };
+        let mut clauses: Vec<Value> = ids
+            .chunks(MAX_TERMS_PER_CLAUSE)
+            .map(|batch| json!({ "terms": { &self.field: batch } }))
+            .collect();
+        match clauses.len() {
+            0 => json!({ "bool": { "must_not": { "match_all": {} } } }),
+            1 => clauses.remove(0),
+            _ => json!({ "bool": { "should": clauses, "minimum_should_match": 1 } }),
+        }
+    }
+
+    /// Typesense `filter_by` expression, each ID quoted in backticks
+    pub fn to_typesense_filter(&self) -> SearchResult<String> {
+        let ids = self.ids("typesense")?;
+        if let Some(id) = ids.iter().find(|id| id.contains('`')) {
+            return Err(SearchError::invalid_query(format!("Typesense filters cannot hold the backtick in ID {}", id)));
+        }
+        let quoted: Vec<String> = ids.iter().map(|id| format!("`{}`", id)).collect();
+        Ok(format!("{}:[{}]", self.field, quoted.join(",")))
+    }
+
+    /// Meilisearch filter expression
+    pub fn to_meilisearch_filter(&self) -> SearchResult<String> {
+        let quoted: Vec<String> = self.ids("meilisearch")?.iter().map(|id| quote(id)).collect();
+        Ok(format!("{} IN [{}]", self.field, quoted.join(", ")))
+    }
+
+    /// Algolia `filters` expressions, one per batch of at most [`ALGOLIA_MAX_IDS`] IDs
+    ///
+    /// Each expression takes its own search. An empty set gives a single
+    /// expression matching nothing.
+    pub fn to_algolia_filters(&self) -> SearchResult<Vec<String>> {
+        let ids = self.ids("algolia")?;
+        if ids.is_empty() {
+            // Algolia has no false literal
+            return Ok(vec![format!("{0}:\"\" AND NOT {0}:\"\"", self.field)]);
+        }
+        let batches = ids
+            .chunks(ALGOLIA_MAX_IDS)
+            .map(|batch| {
+                let filters: Vec<String> = batch.iter().map(|id| format!("{}:{}", self.field, quote(id))).collect();
+                match filters.as_slice() {
+                    [only] => only.clone(),
+                    _ => format!("({})", filters.join(" OR ")),
+                }
+            })
+            .collect();
+        Ok(batches)
+    }
+}
+
+/// `ids` in their first order, without repeats
+fn dedup(ids: impl Iterator<Item = String>) -> Vec<String> {
+    let mut seen = std::collections::HashSet::new();
+    ids.filter(|id| seen.insert(id.clone())).collect()
+}
+
+/// Double-quoted string, with quotes and backslashes escaped
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(filter: &str) -> IdSetFilter {
+        IdSetFilter::parse(filter).expect("an id_set filter").unwrap()
+    }
+
+    fn parse_error(filter: &str) -> String {
+        match IdSetFilter::parse(filter) {
+            Some(Err(SearchError::InvalidQuery(message))) => message,
+            other => panic!("expected an invalid id_set filter for {}, got {:?}", filter, other),
+        }
+    }
+
+    fn invalid<T: std::fmt::Debug>(result: SearchResult<T>) -> String {
+        match result {
+            Err(SearchError::InvalidQuery(message)) => message,
+            other => panic!("expected an invalid query error, got {:?}", other),
+        }
+    }
+
+    fn numbered(count: usize) -> IdSetFilter {
+        IdSetFilter::new("id", (0..count).map(|i| i.to_string()))
+    }
+
+    #[test]
+    fn test_other_filters_are_not_id_sets() {
+        for filter in ["category:books", "id_settings(id, [\"a\"])", "id_set", "not id_set(id, [\"a\"])", ""] {
+            assert!(IdSetFilter::parse(filter).is_none(), "{:?}", filter);
+        }
+    }
+
+    #[test]
+    fn test_whitespace_around_the_arguments_is_allowed() {
+        assert_eq!(parse("  id_set ( doc_id ,\n [\"a\", \"b\"] )  "), IdSetFilter::new("doc_id", ["a", "b"]));
+    }
+
+    #[test]
+    fn test_ids_keep_their_first_order_without_repeats() {
+        assert_eq!(parse(r#"id_set(id, ["b", 3, "a", "b", "3", -7, 1.5])"#), IdSetFilter::new("id", ["b", "3", "a", "-7", "1.5"]));
+        assert_eq!(parse("id_set(id, [])"), IdSetFilter::new("id", Vec::<String>::new()));
+    }
+
+    #[test]
+    fn test_malformed_filters_name_the_problem() {
+        for (filter, problem) in [
+            (r#"id_set(id, ["a"]"#, "missing closing parenthesis"),
+            (r#"id_set(["a"])"#, "expected a field and IDs"),
+            (r#"id_set(doc id, ["a"])"#, "invalid field \"doc id\""),
+            (r#"id_set(, ["a"])"#, "invalid field \"\""),
+            (r#"id_set(id, ["a", ""])"#, "IDs must be non-empty strings or numbers, got \"\""),
+            (r#"id_set(id, [true])"#, "IDs must be non-empty strings or numbers, got true"),
+            (r#"id_set(id, "a")"#, "expected a list of IDs or a lookup object"),
+            (r#"id_set(id, {"index": "acl", "id": "u", "path": "docs", "routing": "x"})"#, "unknown lookup key \"routing\""),
+            (r#"id_set(id, {"index": "acl", "id": "u"})"#, "lookup needs a non-empty \"path\""),
+            (r#"id_set(id, {"index": "", "id": "u", "path": "docs"})"#, "lookup needs a non-empty \"index\""),
+        ] {
+            assert_eq!(parse_error(filter), format!("Invalid id_set filter: {}", problem), "{}", filter);
+        }
+        assert!(parse_error("id_set(id, [a, b])").starts_with("Invalid id_set filter: expected value"));
+    }
+
+    #[test]
+    fn test_filters_round_trip_through_their_text() {
+        for filter in [
+            IdSetFilter::new("id", ["a", "quote\"d", "back\\slash", "comma,separated"]),
+            IdSetFilter::new("id", Vec::<String>::new()),
+            IdSetFilter::lookup("doc_id", "acl", "user-1", "docs.visible"),
+        ] {
+            assert_eq!(parse(&filter.to_filter()), filter);
+        }
+    }
+
+    #[test]
+    fn test_terms_queries_split_at_the_clause_limit() {
+        assert_eq!(numbered(0).to_terms_query(), json!({ "bool": { "must_not": { "match_all": {} } } }));
+        assert_eq!(numbered(MAX_TERMS_PER_CLAUSE).to_terms_query()["terms"]["id"].as_array().map(Vec::len), Some(MAX_TERMS_PER_CLAUSE));
+
+        let query = numbered(2 * MAX_TERMS_PER_CLAUSE + 1).to_terms_query();
+        let lengths: Vec<usize> = query["bool"]["should"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|clause| clause["terms"]["id"].as_array().unwrap().len())
+            .collect();
+        assert_eq!(lengths, [MAX_TERMS_PER_CLAUSE, MAX_TERMS_PER_CLAUSE, 1]);
+        assert_eq!(query["bool"]["minimum_should_match"], 1);
+    }
+
+    #[test]
+    fn test_lookups_only_run_on_lucene_providers() {
+        let lookup = IdSetFilter::lookup("doc_id", "acl", "user-1", "docs");
+        assert_eq!(
+            lookup.to_terms_query(),
+            json!({ "terms": { "doc_id": { "index": "acl", "id": "user-1", "path": "docs" } } })
+        );
+        assert_eq!(
+            invalid(lookup.to_meilisearch_filter()),
+            "meilisearch cannot look up id_set IDs in another document; list them instead"
+        );
+        assert!(invalid(lookup.to_typesense_filter()).starts_with("typesense cannot look up"));
+        assert!(invalid(lookup.to_algolia_filters()).starts_with("algolia cannot look up"));
+    }
+
+    #[test]
+    fn test_typesense_filters_quote_ids_in_backticks() {
+        assert_eq!(IdSetFilter::new("id", ["a b", "c,d"]).to_typesense_filter().unwrap(), "id:[`a b`,`c,d`]");
+        assert_eq!(IdSetFilter::new("id", Vec::<String>::new()).to_typesense_filter().unwrap(), "id:[]");
+        assert_eq!(
+            invalid(IdSetFilter::new("id", ["a", "b`c"]).to_typesense_filter()),
+            "Typesense filters cannot hold the backtick in ID b`c"
+        );
+    }
+
+    #[test]
+    fn test_meilisearch_filters_escape_quotes_and_backslashes() {
+        assert_eq!(IdSetFilter::new("id", ["a\"b", "c\\d"]).to_meilisearch_filter().unwrap(), r#"id IN ["a\"b", "c\\d"]"#);
+        assert_eq!(IdSetFilter::new("id", Vec::<String>::new()).to_meilisearch_filter().unwrap(), "id IN []");
+    }
+
+    #[test]
+    fn test_algolia_filters_split_at_the_id_limit() {
+        assert_eq!(IdSetFilter::new("id", ["a"]).to_algolia_filters().unwrap(), [r#"id:"a""#]);
+        assert_eq!(IdSetFilter::new("id", ["a", "b\"c"]).to_algolia_filters().unwrap(), [r#"(id:"a" OR id:"b\"c")"#]);
+        assert_eq!(IdSetFilter::new("id", Vec::<String>::new()).to_algolia_filters().unwrap(), [r#"id:"" AND NOT id:"""#]);
+        assert_eq!(numbered(ALGOLIA_MAX_IDS).to_algolia_filters().unwrap().len(), 1);
+
+        let batches = numbered(2 * ALGOLIA_MAX_IDS + 1).to_algolia_filters().unwrap();
+        let counts: Vec<usize> = batches.iter().map(|batch| batch.matches("id:").count()).collect();
+        assert_eq!(counts, [ALGOLIA_MAX_IDS, ALGOLIA_MAX_IDS, 1]);
+        assert!(batches[0].starts_with(r#"(id:"0" OR id:"1" OR "#));
+        assert_eq!(batches[2], r#"id:"2000""#);
+    }
+}
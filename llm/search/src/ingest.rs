@@ -0,0 +1,611 @@
+//! Ingest pipelines
+//!
+//! A pipeline is a list of processors run over every document written to an
+//! index, before the provider converts it: renaming fields, coercing types,
+//! filling in defaults, computing fields from others, and removing or masking
+//! personal data that must not reach the search provider. Fields are addressed by dot paths, so
+//! `customer.email` reaches into nested objects.
+//!
+//! Pipelines are plain JSON, keyed by index name, with `*` applying to every
+//! index without a pipeline of its own:
+//!
+//! ```json
+//! {
+//!   "products": [
+//!     { "type": "rename", "from": "title", "to": "name" },
+//!     { "type": "convert", "field": "price", "to": "float" },
+//!     { "type": "default", "field": "in_stock", "value": true },
+//!     { "type": "template", "field": "label", "template": "{brand} {name}" },
+//!     { "type": "remove", "fields": ["supplier.contact"] },
+//!     { "type": "pii", "fields": ["reviews.text"], "action": "mask" }
+//!   ]
+//! }
+//! ```
+//!
+//! Pipelines in `SEARCH_INGEST_PIPELINES` are part of the worker's
+//! configuration, so they apply again after a restart. [`set_pipeline`]
+//! replaces one at runtime; it is kept in worker memory, which Golem persists.
+//! A document a processor fails on fails the write rather than being indexed
+//! half-transformed.
+//!
+//! Elasticsearch and OpenSearch also run pipelines of their own, with
+//! processors the worker lacks such as geoip, attachment and inference.
+//! `SEARCH_SERVER_PIPELINES` names the one each index's writes go through,
+//! keyed the same way, and [`set_server_pipeline`] replaces it at runtime.
+//! An upsert or bulk write may name its own, which wins over the index's.
+//! The provider's pipeline runs after the worker's.
+//!
+//! Writes to indexes with `Int64` or `Decimal` fields are also checked by
+//! [`numeric`](crate::numeric) after the worker's pipeline, so no value is
+//! stored rounded.
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use crate::error::{SearchError, SearchResult};
+use crate::numeric::{self, NumericFields};
+use crate::pii::{self, PiiAction, PiiKind};
+use crate::types::Doc;
+
+/// Environment variable holding the pipelines as a JSON object keyed by index
+pub const INGEST_PIPELINES_ENV: &str = "SEARCH_INGEST_PIPELINES";
+
+/// Pipeline key matching every index without a pipeline of its own
+pub const ALL_INDEXES: &str = "*";
+
+/// Environment variable naming the provider-side pipeline of each index, as a JSON object keyed by index
+pub const SERVER_PIPELINES_ENV: &str = "SEARCH_SERVER_PIPELINES";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConvertTo {
+    Integer,
+    Float,
+    Boolean,
+    String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Processor {
+    /// Move a field; nothing happens when it is missing
+    Rename { from: String, to: String },
+
+    /// Drop fields, such as personal data the index must not hold
+    Remove { fields: Vec<String> },
+
+    /// Coerce a field, or each element of an array field, to another type
+    Convert {
+        field: String,
+        to: ConvertTo,
+
+        /// Leave values that do not convert as they are instead of failing the write
+        #[serde(default)]
+        ignore_failure: bool,
+    },
+
+    /// Set a field that is missing or null
+    Default { field: String, value: Value },
+
+    /// Set a field from a template with `{path}` placeholders; missing fields render empty
+    Template { field: String, template: String },
+
+    /// Mask personal data in the strings of fields, or drop fields that hold any
+    Pii {
+        fields: Vec<String>,
+        #[serde(default = "all_pii_kinds")]
+        kinds: Vec<PiiKind>,
+        #[serde(default)]
+        action: PiiAction,
+    },
+}
+
+fn all_pii_kinds() -> Vec<PiiKind> {
+    PiiKind::ALL.to_vec()
+}
+
+impl Processor {
+    fn validate(&self) -> SearchResult<()> {
+        let paths: Vec<&String> = match self {
+            Processor::Rename { from, to } => vec![from, to],
+            Processor::Remove { fields } => fields.iter().collect(),
+            Processor::Pii { fields, kinds, .. } => {
+                if fields.is_empty() || kinds.is_empty() {
+                    return Err(SearchError::invalid_query("A pii processor needs fields and kinds"));
+                }
+                fields.iter().collect()
+            }
+            Processor::Convert { field, .. } | Processor::Default { field, .. } => vec![field],
+            Processor::Template { field, template } => {
+                placeholders(template)?;
+                vec![field]
+            }
+        };
+        for path in paths {
+            if path.split('.').any(str::is_empty) {
+                return Err(SearchError::invalid_query(format!("Invalid field path '{}' in ingest pipeline", path)));
+            }
+        }
+        Ok(())
+    }
+
+    fn apply(&self, document: &mut Map<String, Value>) -> Result<(), String> {
+        match self {
+            Processor::Rename { from, to } => {
+                if let Some(value) = remove_path(document, from) {
+                    set_path(document, to, value);
+                }
+            }
+            Processor::Remove { fields } => {
+                for field in fields {
+                    remove_path(document, field);
+                }
+            }
+            Processor::Convert { field, to, ignore_failure } => {
+                if let Some(value) = get_path_mut(document, field) {
+                    let converted = match &*value {
+                        Value::Array(items) => items.iter().map(|item| convert(item, *to)).collect::<Option<Vec<_>>>().map(Value::Array),
+                        Value::Null => Some(Value::Null),
+                        other => convert(other, *to),
+                    };
+                    match converted {
+                        Some(converted) => *value = converted,
+                        None if *ignore_failure => {}
+                        None => return Err(format!("cannot convert {} to {:?}", field, to).to_lowercase()),
+                    }
+                }
+            }
+            Processor::Default { field, value } => {
+                if get_path(document, field).is_none_or(Value::is_null) {
+                    set_path(document, field, value.clone());
+                }
+            }
+            Processor::Template { field, template } => {
+                let rendered = render(template, document);
+                set_path(document, field, Value::String(rendered));
+            }
+            Processor::Pii { fields, kinds, action } => {
+                for field in fields {
+                    match action {
+                        PiiAction::Mask => {
+                            if let Some(value) = get_path_mut(document, field) {
+                                mask_pii(value, kinds);
+                            }
+                        }
+                        PiiAction::Drop => {
+                            if get_path(document, field).is_some_and(|value| has_pii(value, kinds)) {
+                                remove_path(document, field);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Processors run in order over each document
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct IngestPipeline {
+    pub processors: Vec<Processor>,
+}
+
+impl IngestPipeline {
+    pub fn from_json(json: &str) -> SearchResult<Self> {
+        let pipeline: Self = serde_json::from_str(json)
+            .map_err(|e| SearchError::invalid_query(format!("Invalid ingest pipeline: {}", e)))?;
+        pipeline.validate()?;
+        Ok(pipeline)
+    }
+
+    pub fn validate(&self) -> SearchResult<()> {
+        self.processors.iter().try_for_each(Processor::validate)
+    }
+
+    /// Run the processors over a document's fields
+    pub fn apply(&self, id: &str, document: &mut Map<String, Value>) -> SearchResult<()> {
+        for (position, processor) in self.processors.iter().enumerate() {
+            processor.apply(document).map_err(|reason| {
+                SearchError::invalid_query(format!("Ingest processor {} failed on document {}: {}", position + 1, id, reason))
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Run the processors over a document's JSON content
+    pub fn apply_content(&self, id: &str, content: &str) -> SearchResult<String> {
+        let mut document = match serde_json::from_str(content) {
+            Ok(Value::Object(document)) => document,
+            _ => return Err(SearchError::invalid_query(format!("Document {} is not a JSON object", id))),
+        };
+        self.apply(id, &mut document)?;
+        Ok(Value::Object(document).to_string())
+    }
+}
+
+/// Parse pipelines keyed by index name
+pub fn parse_pipelines(json: &str) -> SearchResult<BTreeMap<String, IngestPipeline>> {
+    let pipelines: BTreeMap<String, IngestPipeline> = serde_json::from_str(json)
+        .map_err(|e| SearchError::invalid_query(format!("Invalid ingest pipelines: {}", e)))?;
+    for (index, pipeline) in &pipelines {
+        pipeline
+            .validate()
+            .map_err(|e| SearchError::invalid_query(format!("Ingest pipeline for {}: {}", index, e)))?;
+    }
+    Ok(pipelines)
+}
+
+/// Parse provider-side pipeline names keyed by index name
+pub fn parse_server_pipelines(json: &str) -> SearchResult<BTreeMap<String, String>> {
+    let pipelines: BTreeMap<String, String> = serde_json::from_str(json)
+        .map_err(|e| SearchError::invalid_query(format!("Invalid server pipelines: {}", e)))?;
+    match pipelines.iter().find(|(_, name)| name.trim().is_empty()) {
+        Some((index, _)) => Err(SearchError::invalid_query(format!("Server pipeline for {} has no name", index))),
+        None => Ok(pipelines),
+    }
+}
+
+struct Registry {
+    /// From the environment; an error is kept so every write reports it instead of skipping the pipeline
+    configured: Result<BTreeMap<String, Arc<IngestPipeline>>, String>,
+
+    /// Set at runtime; `None` removes the configured pipeline of an index
+    overrides: BTreeMap<String, Option<Arc<IngestPipeline>>>,
+
+    /// Provider-side pipeline names, kept like `configured`
+    server_configured: Result<BTreeMap<String, String>, String>,
+
+    /// Provider-side pipelines set at runtime, kept like `overrides`
+    server_overrides: BTreeMap<String, Option<String>>,
+}
+
+fn registry() -> MutexGuard<'static, Registry> {
+    static REGISTRY: OnceLock<Mutex<Registry>> = OnceLock::new();
+    REGISTRY
+        .get_or_init(|| {
+            let configured = match std::env::var(INGEST_PIPELINES_ENV) {
+                Ok(json) if !json.trim().is_empty() => parse_pipelines(&json)
+                    .map(|pipelines| pipelines.into_iter().map(|(index, p)| (index, Arc::new(p))).collect())
+                    .map_err(|e| format!("{}: {}", INGEST_PIPELINES_ENV, e)),
+                _ => Ok(BTreeMap::new()),
+            };
+            let server_configured = match std::env::var(SERVER_PIPELINES_ENV) {
+                Ok(json) if !json.trim().is_empty() => {
+                    parse_server_pipelines(&json).map_err(|e| format!("{}: {}", SERVER_PIPELINES_ENV, e))
+                }
+                _ => Ok(BTreeMap::new()),
+            };
+            Mutex::new(Registry {
+                configured,
+                overrides: BTreeMap::new(),
+                server_configured,
+                server_overrides: BTreeMap::new(),
+            })
+        })
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Replace the pipeline of `index`, or of every index with `*`; `None` turns it off
+pub fn set_pipeline(index: &str, pipeline: Option<IngestPipeline>) -> SearchResult<()> {
+    if let Some(pipeline) = &pipeline {
+        pipeline.validate()?;
+    }
+    registry().overrides.insert(index.to_string(), pipeline.map(Arc::new));
+    Ok(())
+}
+
+/// The pipeline documents written to `index` go through, if any
+pub fn pipeline_for(index: &str) -> SearchResult<Option<Arc<IngestPipeline>>> {
+    let registry = registry();
+    let configured = registry.configured.as_ref().map_err(|e| SearchError::invalid_query(e.clone()))?;
+    let lookup = |key: &str| match registry.overrides.get(key) {
+        Some(pipeline) => Some(pipeline.clone()),
+        None => configured.get(key).map(|pipeline| Some(pipeline.clone())),
+    };
+    Ok(lookup(index).or_else(|| lookup(ALL_INDEXES)).flatten())
+}
+
+/// Replace the provider-side pipeline of `index`, or of every index with `*`; `None` turns it off
+pub fn set_server_pipeline(index: &str, pipeline: Option<&str>) -> SearchResult<()> {
+    if pipeline.is_some_and(|name| name.trim().is_empty()) {
+        return Err(SearchError::invalid_query(format!("Server pipeline for {} has no name", index)));
+    }
+    registry().server_overrides.insert(index.to_string(), pipeline.map(str::to_string));
+    Ok(())
+}
+
+/// The provider-side pipeline documents written to `index` go through, if any
+pub fn server_pipeline_for(index: &str) -> SearchResult<Option<String>> {
+    let registry = registry();
+    let configured = registry.server_configured.as_ref().map_err(|e| SearchError::invalid_query(e.clone()))?;
+    let lookup = |key: &str| match registry.server_overrides.get(key) {
+        Some(pipeline) => Some(pipeline.clone()),
+        None => configured.get(key).map(|pipeline| Some(pipeline.clone())),
+    };
+    Ok(lookup(index).or_else(|| lookup(ALL_INDEXES)).flatten())
+}
+
+/// The provider-side pipeline of one write: `requested` when the call names one, otherwise that of `index`
+///
+/// Elasticsearch and OpenSearch read `_none` as no pipeline, so a call can also turn the configured one off.
+pub fn server_pipeline(index: &str, requested: Option<&str>) -> SearchResult<Option<String>> {
+    match requested {
+        Some(name) if name.trim().is_empty() => {
+            Err(SearchError::invalid_query(format!("Server pipeline for {} has no name", index)))
+        }
+        Some(name) => Ok(Some(name.to_string())),
+        None => server_pipeline_for(index),
+    }
+}
+
+/// A document's content after the pipeline of `index`, with its numeric fields checked; borrowed unchanged when there is nothing to do
+pub fn apply_document<'a>(index: &str, id: &str, content: &'a str) -> SearchResult<Cow<'a, str>> {
+    transform(pipeline_for(index)?.as_deref(), numeric::recall(index).as_ref(), id, content)
+}
+
+/// `docs` after the pipeline of `index`, with their numeric fields checked; borrowed unchanged when there is nothing to do
+pub fn apply<'a>(index: &str, docs: &'a [Doc]) -> SearchResult<Cow<'a, [Doc]>> {
+    let pipeline = pipeline_for(index)?;
+    let numeric = numeric::recall(index);
+    if pipeline.is_none() && numeric.is_none() {
+        return Ok(Cow::Borrowed(docs));
+    }
+    docs.iter()
+        .map(|doc| {
+            let content = transform(pipeline.as_deref(), numeric.as_ref(), &doc.id, &doc.content)?;
+            Ok(Doc { id: doc.id.clone(), content: content.into_owned() })
+        })
+        .collect::<SearchResult<Vec<_>>>()
+        .map(Cow::Owned)
+}
+
+fn transform<'a>(
+    pipeline: Option<&IngestPipeline>,
+    numeric: Option<&NumericFields>,
+    id: &str,
+    content: &'a str,
+) -> SearchResult<Cow<'a, str>> {
+    let mut content = Cow::Borrowed(content);
+    if let Some(pipeline) = pipeline {
+        content = Cow::Owned(pipeline.apply_content(id, &content)?);
+    }
+    if let Some(numeric) = numeric {
+        content = Cow::Owned(numeric.apply_content(id, &content)?);
+    }
+    Ok(content)
+}
+
+fn convert(value: &Value, to: ConvertTo) -> Option<Value> {
+    match (to, value) {
+        (ConvertTo::String, Value::String(_)) => Some(value.clone()),
+        (ConvertTo::String, Value::Number(_) | Value::Bool(_)) => Some(Value::String(value.to_string())),
+        (ConvertTo::Integer, Value::Number(n)) => n.as_i64().or_else(|| n.as_f64().filter(|f| f.fract() == 0.0).map(|f| f as i64)).map(Value::from),
+        (ConvertTo::Integer, Value::String(s)) => s.trim().parse::<i64>().ok().map(Value::from),
+        (ConvertTo::Integer, Value::Bool(b)) => Some(Value::from(*b as i64)),
+        (ConvertTo::Float, Value::Number(n)) => n.as_f64().map(Value::from),
+        (ConvertTo::Float, Value::String(s)) => s.trim().parse::<f64>().ok().filter(|f| f.is_finite()).map(Value::from),
+        (ConvertTo::Boolean, Value::Bool(_)) => Some(value.clone()),
+        (ConvertTo::Boolean, Value::String(s)) => match s.trim().to_lowercase().as_str() {
+            "true" | "yes" | "1" => Some(Value::Bool(true)),
+            "false" | "no" | "0" => Some(Value::Bool(false)),
+            _ => None,
+        },
+        (ConvertTo::Boolean, Value::Number(n)) => match n.as_i64() {
+            Some(0) => Some(Value::Bool(false)),
+            Some(1) => Some(Value::Bool(true)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Mask the strings in a value, including those nested in arrays and objects
+fn mask_pii(value: &mut Value, kinds: &[PiiKind]) {
+    match value {
+        Value::String(text) => {
+            if let Cow::Owned(masked) = pii::mask(text, kinds) {
+                *text = masked;
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|item| mask_pii(item, kinds)),
+        Value::Object(fields) => fields.values_mut().for_each(|field| mask_pii(field, kinds)),
+        _ => {}
+    }
+}
+
+fn has_pii(value: &Value, kinds: &[PiiKind]) -> bool {
+    match value {
+        Value::String(text) => pii::contains_pii(text, kinds),
+        Value::Array(items) => items.iter().any(|item| has_pii(item, kinds)),
+        Value::Object(fields) => fields.values().any(|field| has_pii(field, kinds)),
+        _ => false,
+    }
+}
+
+/// Placeholder paths of a template, checking that every brace is closed
+fn placeholders(template: &str) -> SearchResult<Vec<&str>> {
+    let mut paths = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}') else {
+            return Err(SearchError::invalid_query(format!("Unclosed placeholder in template '{}'", template)));
+        };
+        paths.push(&rest[start + 1..start + end]);
+        rest = &rest[start + end + 1..];
+    }
+    Ok(paths)
+}
+
+fn render(template: &str, document: &Map<String, Value>) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+        rendered.push_str(&rest[..start]);
+        match get_path(document, &rest[start + 1..start + end]) {
+            Some(Value::String(s)) => rendered.push_str(s),
+            Some(Value::Null) | None => {}
+            Some(other) => rendered.push_str(&other.to_string()),
+        }
+        rest = &rest[start + end + 1..];
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+fn get_path<'a>(document: &'a Map<String, Value>, path: &str) -> Option<&'a Value> {
+    let (parent, last) = match path.rsplit_once('.') {
+        Some((parent, last)) => (parent.split('.').try_fold(document, |object, key| object.get(key)?.as_object())?, last),
+        None => (document, path),
+    };
+    parent.get(last)
+}
+
+fn get_path_mut<'a>(document: &'a mut Map<String, Value>, path: &str) -> Option<&'a mut Value> {
+    let mut segments = path.split('.');
+    let mut value = document.get_mut(segments.next()?)?;
+    for key in segments {
+        value = value.as_object_mut()?.get_mut(key)?;
+    }
+    Some(value)
+}
+
+fn remove_path(document: &mut Map<String, Value>, path: &str) -> Option<Value> {
+    match path.rsplit_once('.') {
+        Some((parent, last)) => get_path_mut(document, parent)?.as_object_mut()?.remove(last),
+        None => document.remove(path),
+    }
+}
+
+/// Set a field, creating objects along the path and replacing values that are not objects
+fn set_path(document: &mut Map<String, Value>, path: &str, value: Value) {
+    let (parents, last) = match path.rsplit_once('.') {
+        Some((parents, last)) => (Some(parents), last),
+        None => (None, path),
+    };
+    let mut object = document;
+    for key in parents.into_iter().flat_map(|parents| parents.split('.')) {
+        let entry = object.entry(key.to_string()).or_insert_with(|| Value::Object(Map::new()));
+        if !entry.is_object() {
+            *entry = Value::Object(Map::new());
+        }
+        object = entry.as_object_mut().expect("just made an object");
+    }
+    object.insert(last.to_string(), value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn run(pipeline: &str, document: Value) -> SearchResult<Value> {
+        let pipeline = IngestPipeline::from_json(pipeline)?;
+        pipeline.apply_content("1", &document.to_string()).map(|content| serde_json::from_str(&content).unwrap())
+    }
+
+    #[test]
+    fn test_processors() {
+        let pipeline = r#"[
+            {"type": "rename", "from": "title", "to": "name"},
+            {"type": "rename", "from": "vendor.name", "to": "brand"},
+            {"type": "convert", "field": "price", "to": "float"},
+            {"type": "convert", "field": "sizes", "to": "integer"},
+            {"type": "default", "field": "in_stock", "value": true},
+            {"type": "default", "field": "rating", "value": 0},
+            {"type": "template", "field": "label.en", "template": "{brand} {name} ({missing})"},
+            {"type": "remove", "fields": ["vendor.email", "internal"]}
+        ]"#;
+        let document = json!({
+            "title": "Trail Runner",
+            "price": "89.90",
+            "sizes": ["41", 42],
+            "rating": 4,
+            "vendor": { "name": "Acme", "email": "sales@acme.test" },
+            "internal": "x"
+        });
+        assert_eq!(
+            run(pipeline, document).unwrap(),
+            json!({
+                "name": "Trail Runner",
+                "brand": "Acme",
+                "price": 89.9,
+                "sizes": [41, 42],
+                "rating": 4,
+                "in_stock": true,
+                "label": { "en": "Acme Trail Runner ()" },
+                "vendor": {}
+            })
+        );
+
+        let pii = r#"[
+            {"type": "pii", "fields": ["reviews"]},
+            {"type": "pii", "fields": ["contact", "notes"], "kinds": ["phone"], "action": "drop"}
+        ]"#;
+        let document = json!({
+            "reviews": [{ "text": "Ask ada@example.com", "stars": 5 }, "Call 555-123-4567"],
+            "contact": { "phone": "+1 555 123 4567" },
+            "notes": "No phone, only ada@example.com"
+        });
+        assert_eq!(
+            run(pii, document).unwrap(),
+            json!({
+                "reviews": [{ "text": "Ask [email]", "stars": 5 }, "Call [phone]"],
+                "notes": "No phone, only ada@example.com"
+            })
+        );
+
+        let strict = r#"[{"type": "convert", "field": "price", "to": "integer"}]"#;
+        assert!(run(strict, json!({ "price": "n/a" })).is_err());
+        let lenient = r#"[{"type": "convert", "field": "price", "to": "integer", "ignore_failure": true}]"#;
+        assert_eq!(run(lenient, json!({ "price": "n/a" })).unwrap(), json!({ "price": "n/a" }));
+    }
+
+    #[test]
+    fn test_pipeline_definitions() {
+        assert!(IngestPipeline::from_json(r#"[{"type": "uppercase", "field": "a"}]"#).is_err());
+        assert!(IngestPipeline::from_json(r#"[{"type": "remove", "fields": ["a..b"]}]"#).is_err());
+        assert!(IngestPipeline::from_json(r#"[{"type": "template", "field": "a", "template": "{b"}]"#).is_err());
+        assert!(IngestPipeline::from_json(r#"[{"type": "pii", "fields": ["a"], "kinds": []}]"#).is_err());
+        assert!(IngestPipeline::from_json(r#"[{"type": "pii", "fields": ["a"], "kinds": ["ssn"]}]"#).is_err());
+
+        let pipelines = parse_pipelines(r#"{"*": [{"type": "remove", "fields": ["ssn"]}], "logs": []}"#).unwrap();
+        assert_eq!(pipelines.len(), 2);
+
+        set_pipeline("ingest-test", IngestPipeline::from_json(r#"[{"type": "remove", "fields": ["ssn"]}]"#).ok()).unwrap();
+        let docs = vec![Doc { id: "1".to_string(), content: r#"{"name": "Ada", "ssn": "123"}"#.to_string() }];
+        assert_eq!(apply("ingest-test", &docs).unwrap()[0].content, r#"{"name":"Ada"}"#);
+        set_pipeline("ingest-test", None).unwrap();
+        assert!(matches!(apply("ingest-test", &docs).unwrap(), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_server_pipelines() {
+        let pipelines = parse_server_pipelines(r#"{"*": "enrich", "logs": "geoip"}"#).unwrap();
+        assert_eq!(pipelines.get("logs").map(String::as_str), Some("geoip"));
+        assert!(parse_server_pipelines(r#"{"logs": " "}"#).is_err());
+        assert!(parse_server_pipelines(r#"{"logs": ["geoip"]}"#).is_err());
+
+        set_server_pipeline("server-pipeline-test", Some("attachment")).unwrap();
+        assert_eq!(server_pipeline_for("server-pipeline-test").unwrap().as_deref(), Some("attachment"));
+        set_server_pipeline("server-pipeline-test", None).unwrap();
+        assert_eq!(server_pipeline_for("server-pipeline-test").unwrap(), None);
+        assert!(set_server_pipeline("server-pipeline-test", Some("")).is_err());
+    }
+
+    #[test]
+    fn test_per_call_server_pipeline_wins() {
+        set_server_pipeline("server-pipeline-call-test", Some("geoip")).unwrap();
+        assert_eq!(server_pipeline("server-pipeline-call-test", None).unwrap().as_deref(), Some("geoip"));
+        assert_eq!(server_pipeline("server-pipeline-call-test", Some("attachment")).unwrap().as_deref(), Some("attachment"));
+        assert_eq!(server_pipeline("server-pipeline-call-test", Some("_none")).unwrap().as_deref(), Some("_none"));
+        assert!(server_pipeline("server-pipeline-call-test", Some(" ")).is_err());
+    }
+}
@@ -0,0 +1,126 @@
+//! Runtime and provider shared by the calls of one component instance
+//!
+//! A component export is a plain function, so a call that builds its own
+//! tokio runtime, reads the configuration and opens an HTTP client pays for
+//! all three every time. [`runtime`] hands out one runtime for the life of
+//! the instance, and a [`ProviderCache`] keeps the provider built by the
+//! first call for the calls after it. A provider that fails to build is not
+//! cached, so the next call tries again.
+//!
+//! Component instances are single-threaded: the runtime runs on the calling
+//! thread and each provider crate keeps its cache in a `thread_local!`.
+
+use std::cell::RefCell;
+use std::future::Future;
+use std::rc::Rc;
+use std::sync::OnceLock;
+use std::thread::LocalKey;
+use tokio::runtime::{Builder, Runtime};
+use crate::error::{SearchError, SearchResult};
+
+static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+
+/// The instance's runtime, built on first use
+pub fn runtime() -> SearchResult<&'static Runtime> {
+    if let Some(runtime) = RUNTIME.get() {
+        return Ok(runtime);
+    }
+    let runtime = Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| SearchError::internal(format!("Failed to create async runtime: {}", e)))?;
+    Ok(RUNTIME.get_or_init(|| runtime))
+}
+
+/// Slot for the provider of one component instance
+#[derive(Debug)]
+pub struct ProviderCache<P> {
+    provider: RefCell<Option<Rc<P>>>,
+}
+
+impl<P> ProviderCache<P> {
+    pub const fn new() -> Self {
+        Self { provider: RefCell::new(None) }
+    }
+
+    pub fn get(&self) -> Option<Rc<P>> {
+        self.provider.borrow().clone()
+    }
+
+    /// Keep `provider` for later calls
+    pub fn insert(&self, provider: P) -> Rc<P> {
+        let provider = Rc::new(provider);
+        *self.provider.borrow_mut() = Some(provider.clone());
+        provider
+    }
+
+    /// Drop the cached provider, so the next call builds a new one
+    pub fn clear(&self) {
+        self.provider.borrow_mut().take();
+    }
+}
+
+impl<P> Default for ProviderCache<P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The provider cached in `cache`, built with `create` when there is none
+pub async fn get_or_try_init<P, E, F, Fut>(cache: &'static LocalKey<ProviderCache<P>>, create: F) -> Result<Rc<P>, E>
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<P, E>>,
+{
+    if let Some(provider) = cache.with(ProviderCache::get) {
+        return Ok(provider);
+    }
+    let provider = create().await?;
+    Ok(cache.with(|cache| cache.insert(provider)))
+}
+
+/// Blocking form of [`get_or_try_init`], for providers built without I/O
+pub fn get_or_try_init_sync<P, E>(cache: &'static LocalKey<ProviderCache<P>>, create: impl FnOnce() -> Result<P, E>) -> Result<Rc<P>, E> {
+    if let Some(provider) = cache.with(ProviderCache::get) {
+        return Ok(provider);
+    }
+    let provider = create()?;
+    Ok(cache.with(|cache| cache.insert(provider)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    thread_local! {
+        static CACHE: ProviderCache<u32> = const { ProviderCache::new() };
+        static BUILT: Cell<u32> = const { Cell::new(0) };
+    }
+
+    async fn build(fail: bool) -> Result<u32, String> {
+        if fail {
+            return Err("unreachable".to_string());
+        }
+        BUILT.with(|built| built.set(built.get() + 1));
+        Ok(BUILT.with(Cell::get))
+    }
+
+    #[test]
+    fn test_provider_built_once_and_failures_not_cached() {
+        let runtime = runtime().unwrap();
+        assert!(std::ptr::eq(runtime, super::runtime().unwrap()));
+
+        assert!(runtime.block_on(get_or_try_init(&CACHE, || build(true))).is_err());
+        assert!(CACHE.with(ProviderCache::get).is_none());
+
+        let first = runtime.block_on(get_or_try_init(&CACHE, || build(false))).unwrap();
+        let second = runtime.block_on(get_or_try_init(&CACHE, || build(false))).unwrap();
+        assert_eq!((*first, *second), (1, 1));
+        assert!(Rc::ptr_eq(&first, &second));
+
+        CACHE.with(ProviderCache::clear);
+        let rebuilt = get_or_try_init_sync(&CACHE, || Ok::<_, String>(7)).unwrap();
+        assert_eq!(*rebuilt, 7);
+    }
+}
@@ -0,0 +1,153 @@
+//! JSON parsing for provider responses
+//!
+//! Provider clients built on golem-search decode response bodies through this
+//! module. With the optional `simd-json` feature, parsing uses SIMD-accelerated
+//! `simd-json` on targets that support it (x86_64, aarch64, and wasm32 built
+//! with `+simd128`); everywhere else it falls back to `serde_json`. Borrowed
+//! [`RawValue`](crate::raw::RawValue) views always use `serde_json`, since
+//! `simd-json` parses in place and cannot hand out slices of the original body.
+//!
+//! `benches/json_parsing.rs` compares the backends. Decoding into
+//! `serde_json::Value` is roughly on par either way; for hit pages the borrowed
+//! `raw` path is the larger win, so prefer it for new response handling.
+
+use serde::de::DeserializeOwned;
+use crate::error::{SearchError, SearchResult};
+
+#[cfg(all(
+    feature = "simd-json",
+    any(
+        target_arch = "x86_64",
+        target_arch = "aarch64",
+        all(target_arch = "wasm32", target_feature = "simd128")
+    )
+))]
+mod backend {
+    use super::*;
+
+    pub const NAME: &str = "simd-json";
+
+    pub fn from_str<T: DeserializeOwned>(body: &str) -> SearchResult<T> {
+        // simd-json rewrites the buffer while parsing, so it needs its own copy
+        let mut buffer = body.as_bytes().to_vec();
+        simd_json::serde::from_slice(&mut buffer)
+            .map_err(|e| SearchError::InvalidQuery(format!("JSON parsing error: {}", e)))
+    }
+}
+
+#[cfg(not(all(
+    feature = "simd-json",
+    any(
+        target_arch = "x86_64",
+        target_arch = "aarch64",
+        all(target_arch = "wasm32", target_feature = "simd128")
+    )
+)))]
+mod backend {
+    use super::*;
+
+    pub const NAME: &str = "serde_json";
+
+    pub fn from_str<T: DeserializeOwned>(body: &str) -> SearchResult<T> {
+        Ok(serde_json::from_str(body)?)
+    }
+}
+
+/// Name of the parser in use: `simd-json` or `serde_json`
+pub fn backend_name() -> &'static str {
+    backend::NAME
+}
+
+/// Parse a JSON document
+pub fn from_str<T: DeserializeOwned>(body: &str) -> SearchResult<T> {
+    backend::from_str(body)
+}
+
+/// Read and parse the body of an HTTP response
+pub fn from_response<T: DeserializeOwned>(response: crate::http::Response) -> SearchResult<T> {
+    let body = response
+        .text()
+        .map_err(|e| SearchError::internal(format!("Failed to read response body: {}", e)))?;
+    from_str(&body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use serde_json::{json, Value};
+    use crate::http::{HeaderMap, Response, StatusCode};
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Hit {
+        #[serde(rename = "_id")]
+        id: String,
+        #[serde(rename = "_score")]
+        score: Option<f64>,
+    }
+
+    fn response(status: u16, body: &[u8]) -> Response {
+        let url = url::Url::parse("http://localhost:9200/books/_search").unwrap();
+        Response::new(StatusCode::from_u16(status).unwrap(), HeaderMap::new(), body.to_vec(), url)
+    }
+
+    fn message<T: std::fmt::Debug>(result: SearchResult<T>) -> String {
+        match result {
+            Err(SearchError::InvalidQuery(message)) => message,
+            other => panic!("expected an invalid query error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_backend_follows_the_feature() {
+        let expected = if cfg!(feature = "simd-json") && cfg!(any(target_arch = "x86_64", target_arch = "aarch64")) {
+            "simd-json"
+        } else {
+            "serde_json"
+        };
+        assert_eq!(backend_name(), expected);
+    }
+
+    #[test]
+    fn test_values_match_serde_json() {
+        let body = r#"{"hits": {"total": {"value": 2}, "hits": [{"_id": "1", "_score": 1.5, "_source": {"title": "café"}}]}}"#;
+        assert_eq!(from_str::<Value>(body).unwrap(), serde_json::from_str::<Value>(body).unwrap());
+    }
+
+    #[test]
+    fn test_escapes_and_number_limits_are_preserved() {
+        let parsed: Value = from_str(r#"{"text": "tab\there \"q\" 🦀 é", "big": 18446744073709551615, "neg": -9223372036854775808, "exp": 1.5e-7}"#).unwrap();
+        assert_eq!(parsed["text"], "tab\there \"q\" 🦀 é");
+        assert_eq!(parsed["big"].as_u64(), Some(u64::MAX));
+        assert_eq!(parsed["neg"].as_i64(), Some(i64::MIN));
+        assert_eq!(parsed["exp"].as_f64(), Some(1.5e-7));
+    }
+
+    #[test]
+    fn test_typed_results_ignore_unknown_fields() {
+        let hits: Vec<Hit> = from_str(r#"[{"_id": "1", "_score": null, "_index": "books"}, {"_id": "2", "_score": 0.5}]"#).unwrap();
+        assert_eq!(hits, [Hit { id: "1".to_string(), score: None }, Hit { id: "2".to_string(), score: Some(0.5) }]);
+        assert!(message(from_str::<Vec<Hit>>(r#"[{"_score": 1}]"#)).contains("_id"));
+    }
+
+    #[test]
+    fn test_incomplete_or_trailing_input_is_refused() {
+        for body in ["", "   ", "{\"hits\": [", "{} {}", "{\"a\": 1,}", "NaN"] {
+            assert!(message(from_str::<Value>(body)).starts_with("JSON parsing error: "), "{:?}", body);
+        }
+        assert_eq!(from_str::<Value>(" \n{}\t ").unwrap(), json!({}));
+    }
+
+    #[test]
+    fn test_response_bodies_are_parsed_whatever_the_status() {
+        let error: Value = from_response(response(404, br#"{"error": {"type": "index_not_found_exception"}}"#)).unwrap();
+        assert_eq!(error["error"]["type"], "index_not_found_exception");
+        assert!(message(from_response::<Value>(response(200, b""))).starts_with("JSON parsing error"));
+    }
+
+    #[test]
+    fn test_invalid_utf8_in_responses_is_replaced() {
+        let parsed: Value = from_response(response(200, b"{\"title\": \"caf\xff\"}")).unwrap();
+        assert_eq!(parsed["title"], "caf\u{FFFD}");
+    }
+}
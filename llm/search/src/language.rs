@@ -0,0 +1,604 @@
+//! Routing documents to per-language indexes
+//!
+//! Text is analyzed best by an index set up for its language, so a catalog in
+//! several languages is commonly split into `products_en`, `products_de` and
+//! so on. A [`LanguageRoute`] does the split for one logical index: each
+//! document written to `products` goes to the index of its language, and a
+//! search on `products` runs on every language index and merges the hits by
+//! score. A search whose config `language` names one of the route's languages runs
+//! on that index alone.
+//!
+//! Routes are read from [`LANGUAGE_ROUTES_ENV`] as a JSON object keyed by
+//! logical index:
+//!
+//! ```json
+//! {
+//!   "products": {
+//!     "languages": ["en", "de", "fr"],
+//!     "default": "en",
+//!     "field": "lang",
+//!     "text_fields": ["title", "description"]
+//!   }
+//! }
+//! ```
+//!
+//! A document's language is its `field` when that names one of the
+//! languages; otherwise the registered [`LanguageDetector`] reads its
+//! `text_fields`, or every top-level string when none are listed. Documents
+//! the detector cannot place, or places in a language without an index, go
+//! to the `default` language, the first one unless set. The built-in
+//! [`StopwordDetector`] counts common words and knows English, German,
+//! French, Spanish, Italian, Dutch and Portuguese; [`set_detector`] installs
+//! a better one.
+//!
+//! Scores from different indexes are only roughly comparable, and facet
+//! counts of different indexes are not merged, so merged results carry no
+//! facets. Cursors are issued per index and are refused on a merged search.
+
+use std::collections::{BTreeMap, HashSet};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use log::warn;
+use serde::Deserialize;
+use serde_json::Value;
+use crate::cursor::DEFAULT_PER_PAGE;
+use crate::error::{SearchError, SearchResult};
+use crate::types::{Doc, SearchQuery, SearchResults};
+
+/// Environment variable holding the language routes as a JSON object keyed by index
+pub const LANGUAGE_ROUTES_ENV: &str = "SEARCH_LANGUAGE_ROUTES";
+
+/// Guesses the language of a text
+pub trait LanguageDetector: Send + Sync {
+    /// ISO 639-1 code of the text's language, or `None` when unsure
+    fn detect(&self, text: &str) -> Option<String>;
+}
+
+fn custom_detector() -> MutexGuard<'static, Option<Arc<dyn LanguageDetector>>> {
+    static DETECTOR: OnceLock<Mutex<Option<Arc<dyn LanguageDetector>>>> = OnceLock::new();
+    DETECTOR
+        .get_or_init(|| Mutex::new(None))
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Install the detector used for every route; `None` restores the built-in one
+pub fn set_detector(detector: Option<Arc<dyn LanguageDetector>>) {
+    *custom_detector() = detector;
+}
+
+/// The installed detector, or the built-in [`StopwordDetector`]
+pub fn detector() -> Arc<dyn LanguageDetector> {
+    custom_detector().clone().unwrap_or_else(|| Arc::new(StopwordDetector))
+}
+
+const STOPWORDS: &[(&str, &[&str])] = &[
+    ("en", &["the", "and", "of", "to", "is", "in", "with", "for", "this", "that", "are", "it"]),
+    ("de", &["der", "die", "das", "und", "ist", "mit", "für", "nicht", "ein", "eine", "auf", "den"]),
+    ("fr", &["le", "la", "les", "et", "est", "des", "une", "pour", "avec", "dans", "du", "pas"]),
+    ("es", &["el", "los", "las", "y", "es", "con", "para", "una", "del", "por", "que", "muy"]),
+    ("it", &["il", "gli", "e", "è", "di", "con", "per", "una", "della", "che", "non", "sono"]),
+    ("nl", &["de", "het", "een", "en", "is", "van", "met", "voor", "niet", "op", "dat", "zijn"]),
+    ("pt", &["o", "os", "as", "e", "é", "com", "para", "uma", "do", "da", "não", "em"]),
+];
+
+/// Fewest stopwords a text needs before its language is guessed
+const MIN_STOPWORDS: usize = 2;
+
+/// Detector counting each language's most common words
+///
+/// It is cheap and needs no data files, but it only places texts of a few
+/// words or more in the languages it knows.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StopwordDetector;
+
+impl LanguageDetector for StopwordDetector {
+    fn detect(&self, text: &str) -> Option<String> {
+        let words: Vec<String> = text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(str::to_lowercase)
+            .collect();
+        let mut counts: Vec<(&str, usize)> = STOPWORDS
+            .iter()
+            .map(|(language, stopwords)| {
+                (*language, words.iter().filter(|word| stopwords.contains(&word.as_str())).count())
+            })
+            .collect();
+        counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+        match counts.as_slice() {
+            [(language, best), (_, second), ..] if *best >= MIN_STOPWORDS && best > second => {
+                Some(language.to_string())
+            }
+            _ => None,
+        }
+    }
+}
+
+/// How the documents of one logical index are split by language
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LanguageRoute {
+    /// Languages with an index of their own
+    pub languages: Vec<String>,
+
+    /// Language of documents that fit no other; the first language when unset
+    #[serde(default)]
+    pub default: Option<String>,
+
+    /// Document field holding the language, used before detection
+    #[serde(default)]
+    pub field: Option<String>,
+
+    /// Fields given to the detector; every top-level string when empty
+    #[serde(default)]
+    pub text_fields: Vec<String>,
+}
+
+impl LanguageRoute {
+    fn from_entry(entry: Value) -> Result<Self, String> {
+        let route: Self = serde_json::from_value(entry).map_err(|e| e.to_string())?;
+        if route.languages.is_empty() {
+            return Err("no languages".to_string());
+        }
+        match &route.default {
+            Some(default) if !route.languages.contains(default) => {
+                Err(format!("default language {} is not one of the languages", default))
+            }
+            _ => Ok(route),
+        }
+    }
+
+    pub fn default_language(&self) -> &str {
+        self.default.as_deref().unwrap_or(&self.languages[0])
+    }
+
+    /// Index holding the documents of `language`
+    pub fn index_for(&self, index: &str, language: &str) -> String {
+        format!("{}_{}", index, language)
+    }
+
+    /// Every language index, in the order of the languages
+    pub fn indexes(&self, index: &str) -> Vec<String> {
+        self.languages.iter().map(|language| self.index_for(index, language)).collect()
+    }
+
+    /// Indexes a search runs on: the one of the query's language when it has one, all of them otherwise
+    pub fn search_indexes(&self, index: &str, query: &SearchQuery) -> Vec<String> {
+        match query.config.as_ref().and_then(|config| config.language.as_ref()) {
+            Some(language) if self.languages.contains(language) => vec![self.index_for(index, language)],
+            _ => self.indexes(index),
+        }
+    }
+
+    /// Language of a document, falling back to the default
+    pub fn language_of(&self, document: &Value, detector: &dyn LanguageDetector) -> &str {
+        let known = |language: &str| self.languages.iter().find(|known| known.as_str() == language);
+        if let Some(language) = self.field.as_deref().and_then(|field| document.get(field)).and_then(Value::as_str) {
+            if let Some(known) = known(&language.to_lowercase()) {
+                return known;
+            }
+        }
+        let text = self.detection_text(document);
+        detector
+            .detect(&text)
+            .and_then(|language| known(&language.to_lowercase()))
+            .map_or_else(|| self.default_language(), String::as_str)
+    }
+
+    fn detection_text(&self, document: &Value) -> String {
+        let Some(object) = document.as_object() else {
+            return String::new();
+        };
+        let texts: Vec<&str> = if self.text_fields.is_empty() {
+            object.values().filter_map(Value::as_str).collect()
+        } else {
+            self.text_fields.iter().filter_map(|field| object.get(field)).filter_map(Value::as_str).collect()
+        };
+        texts.join("\n")
+    }
+
+    /// Documents grouped by the index of their language, groups in the order of the languages
+    pub fn route(&self, index: &str, docs: &[Doc]) -> Vec<(String, Vec<Doc>)> {
+        let detector = detector();
+        let mut groups: BTreeMap<usize, Vec<Doc>> = BTreeMap::new();
+        for doc in docs {
+            let document: Value = serde_json::from_str(&doc.content).unwrap_or(Value::Null);
+            let language = self.language_of(&document, detector.as_ref());
+            let position = self.languages.iter().position(|known| known == language).unwrap_or_default();
+            groups.entry(position).or_default().push(doc.clone());
+        }
+        groups
+            .into_iter()
+            .map(|(position, docs)| (self.index_for(index, &self.languages[position]), docs))
+            .collect()
+    }
+}
+
+/// Language routes, keyed by logical index
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LanguageRoutes {
+    routes: BTreeMap<String, LanguageRoute>,
+}
+
+impl LanguageRoutes {
+    pub fn from_env() -> Self {
+        std::env::var(LANGUAGE_ROUTES_ENV).map(|json| Self::parse(&json)).unwrap_or_default()
+    }
+
+    /// Parse routes keyed by index, skipping malformed ones
+    pub fn parse(json: &str) -> Self {
+        let entries: BTreeMap<String, Value> = match serde_json::from_str(json) {
+            Ok(entries) => entries,
+            Err(e) => {
+                warn!("Ignoring {}: {}", LANGUAGE_ROUTES_ENV, e);
+                return Self::default();
+            }
+        };
+        let mut routes = BTreeMap::new();
+        for (index, entry) in entries {
+            match LanguageRoute::from_entry(entry) {
+                Ok(route) => {
+                    routes.insert(index, route);
+                }
+                Err(e) => warn!("Ignoring malformed language route for {}: {}", index, e),
+            }
+        }
+        Self { routes }
+    }
+
+    pub fn for_index(&self, index: &str) -> Option<&LanguageRoute> {
+        self.routes.get(index)
+    }
+}
+
+/// Check routes strictly, reporting the first malformed one
+pub fn check_routes(json: &str) -> SearchResult<()> {
+    let entries: BTreeMap<String, Value> = serde_json::from_str(json)
+        .map_err(|e| SearchError::invalid_query(format!("Invalid language routes: {}", e)))?;
+    for (index, entry) in entries {
+        LanguageRoute::from_entry(entry)
+            .map_err(|e| SearchError::invalid_query(format!("Invalid language route for {}: {}", index, e)))?;
+    }
+    Ok(())
+}
+
+/// Query to send to each language index: the hits up to the end of the requested page, from the start
+pub fn fan_out_query(query: &SearchQuery) -> SearchResult<SearchQuery> {
+    if query.config.as_ref().is_some_and(|config| config.cursor.is_some()) {
+        return Err(SearchError::invalid_query(
+            "Cursors are not supported on a search across language indexes; use page or offset",
+        ));
+    }
+    let (offset, per_page) = page_window(query);
+    let mut fan_out = query.clone();
+    fan_out.page = Some(1);
+    fan_out.offset = Some(0);
+    fan_out.per_page = Some(offset + per_page);
+    Ok(fan_out)
+}
+
+fn page_window(query: &SearchQuery) -> (u32, u32) {
+    let per_page = query.per_page.unwrap_or(DEFAULT_PER_PAGE);
+    let offset = query
+        .offset
+        .unwrap_or_else(|| query.page.unwrap_or(1).saturating_sub(1).saturating_mul(per_page));
+    (offset, per_page)
+}
+
+/// Merge the results of a [`fan_out_query`] into the page `query` asked for
+///
+/// Hits are ordered by score, ties keeping the order of the indexes; totals
+/// add up, and stay exact only when every index counted exactly.
+pub fn merge_results(query: &SearchQuery, results: Vec<SearchResults>) -> SearchResults {
+    let (offset, per_page) = page_window(query);
+    let mut seen = HashSet::new();
+    let mut hits = Vec::new();
+    let mut total = Some(0u32);
+    let mut took_ms = None;
+    let mut truncated = false;
+    let mut exact = Some(true);
+    let mut request_id = None;
+    for result in results {
+        total = total.zip(result.total).map(|(sum, total)| sum.saturating_add(total));
+        took_ms = took_ms.max(result.took_ms);
+        truncated |= result.truncated;
+        exact = match (exact, result.total_exact) {
+            (Some(true), Some(true)) => Some(true),
+            (Some(false), _) | (_, Some(false)) => Some(false),
+            _ => None,
+        };
+        request_id = request_id.or(result.request_id);
+        // A document moved to another language can briefly sit in both indexes
+        hits.extend(result.hits.into_iter().filter(|hit| seen.insert(hit.id.clone())));
+    }
+    hits.sort_by(|a, b| b.score.unwrap_or(f64::MIN).total_cmp(&a.score.unwrap_or(f64::MIN)));
+    let hits = hits.into_iter().skip(offset as usize).take(per_page as usize).collect();
+    SearchResults {
+        total,
+        page: query.page,
+        per_page: Some(per_page),
+        hits,
+        facets: None,
+        took_ms,
+        truncated,
+        request_id,
+        groups: None,
+        next_cursor: None,
+        total_exact: exact,
+        facet_metadata: Vec::new(),
+        relaxations: Vec::new(),
+        original_query: None,
+        corrected_query: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{SearchConfig, SearchHit};
+    use serde_json::json;
+
+    /// Detector answering with a fixed language and keeping the text it was given
+    struct Fixed(Option<&'static str>, Mutex<String>);
+
+    impl Fixed {
+        fn new(language: Option<&'static str>) -> Self {
+            Self(language, Mutex::new(String::new()))
+        }
+
+        fn text(&self) -> String {
+            self.1.lock().unwrap().clone()
+        }
+    }
+
+    impl LanguageDetector for Fixed {
+        fn detect(&self, text: &str) -> Option<String> {
+            *self.1.lock().unwrap() = text.to_string();
+            self.0.map(str::to_string)
+        }
+    }
+
+    fn doc(id: &str, content: Value) -> Doc {
+        Doc { id: id.to_string(), content: content.to_string() }
+    }
+
+    fn route(json: &str) -> LanguageRoute {
+        LanguageRoute::from_entry(serde_json::from_str(json).unwrap()).unwrap()
+    }
+
+    fn config(language: Option<&str>, cursor: Option<&str>) -> SearchConfig {
+        SearchConfig {
+            timeout_ms: None,
+            boost_fields: Vec::new(),
+            attributes_to_retrieve: Vec::new(),
+            language: language.map(str::to_string),
+            typo_tolerance: None,
+            exact_match_boost: None,
+            provider_params: None,
+            explain: false,
+            consistency_token: None,
+            sample: None,
+            normalization: None,
+            fuzziness: None,
+            minimum_should_match: None,
+            cursor: cursor.map(str::to_string),
+            exact_total: false,
+        }
+    }
+
+    fn paged(page: Option<u32>, offset: Option<u32>, per_page: Option<u32>) -> SearchQuery {
+        SearchQuery { page, offset, per_page, ..SearchQuery::match_all() }
+    }
+
+    fn hit(id: &str, score: Option<f64>) -> SearchHit {
+        SearchHit { id: id.to_string(), score, content: None, highlights: None, explanation: None, distance_meters: None }
+    }
+
+    fn results(hits: Vec<SearchHit>, total: Option<u32>, total_exact: Option<bool>) -> SearchResults {
+        SearchResults {
+            total,
+            page: Some(1),
+            per_page: Some(10),
+            hits,
+            facets: Some("{}".to_string()),
+            took_ms: Some(3),
+            truncated: false,
+            request_id: None,
+            groups: None,
+            next_cursor: None,
+            total_exact,
+            facet_metadata: Vec::new(),
+            relaxations: Vec::new(),
+            original_query: None,
+            corrected_query: None,
+        }
+    }
+
+    fn ids(results: &SearchResults) -> Vec<&str> {
+        results.hits.iter().map(|hit| hit.id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_malformed_routes_are_skipped_and_reported() {
+        let routes = LanguageRoutes::parse(
+            r#"{
+                "products": {"languages": ["en", "de"]},
+                "orders": {"languages": []},
+                "users": {"languages": ["en"], "default": "fr"},
+                "reviews": {"languages": ["en"], "analyzer": "icu"}
+            }"#,
+        );
+        assert_eq!(routes.for_index("products").unwrap().default_language(), "en");
+        for index in ["orders", "users", "reviews"] {
+            assert!(routes.for_index(index).is_none(), "{}", index);
+        }
+        assert_eq!(LanguageRoutes::parse("not json"), LanguageRoutes::default());
+
+        let error = |json: &str| match check_routes(json) {
+            Err(SearchError::InvalidQuery(message)) => message,
+            other => panic!("expected an invalid query error, got {:?}", other),
+        };
+        assert_eq!(error(r#"{"orders": {"languages": []}}"#), "Invalid language route for orders: no languages");
+        assert_eq!(
+            error(r#"{"users": {"languages": ["en"], "default": "fr"}}"#),
+            "Invalid language route for users: default language fr is not one of the languages"
+        );
+        assert!(error(r#"{"reviews": {"languages": ["en"], "analyzer": "icu"}}"#).contains("unknown field `analyzer`"));
+        assert!(error("[]").starts_with("Invalid language routes: "));
+        assert!(check_routes(r#"{"products": {"languages": ["en", "de"], "default": "de"}}"#).is_ok());
+    }
+
+    #[test]
+    fn test_stopword_detection_needs_a_clear_winner() {
+        assert_eq!(StopwordDetector.detect("The table is made of oak, and it is solid.").as_deref(), Some("en"));
+        assert_eq!(StopwordDetector.detect("DER TISCH IST AUS HOLZ UND NICHT AUS METALL").as_deref(), Some("de"));
+        assert_eq!(StopwordDetector.detect("La chaise et la table pour le salon").as_deref(), Some("fr"));
+        assert_eq!(StopwordDetector.detect("The oak table").as_deref(), None, "a single stopword");
+        assert_eq!(StopwordDetector.detect("e e").as_deref(), None, "Italian and Portuguese tie");
+        assert_eq!(StopwordDetector.detect("").as_deref(), None);
+    }
+
+    #[test]
+    fn test_the_language_field_wins_when_it_names_a_language_of_the_route() {
+        let route = route(r#"{"languages": ["en", "de"], "field": "lang"}"#);
+        let detector = Fixed::new(Some("de"));
+        assert_eq!(route.language_of(&json!({ "lang": "EN", "title": "Der Stuhl" }), &detector), "en");
+        assert_eq!(route.language_of(&json!({ "lang": "fr" }), &detector), "de", "unknown languages fall back to detection");
+        assert_eq!(route.language_of(&json!({ "lang": 7 }), &detector), "de");
+    }
+
+    #[test]
+    fn test_detected_languages_without_an_index_go_to_the_default() {
+        let route = route(r#"{"languages": ["en", "de"], "default": "de"}"#);
+        assert_eq!(route.language_of(&json!({ "title": "x" }), &Fixed::new(Some("EN"))), "en");
+        assert_eq!(route.language_of(&json!({ "title": "x" }), &Fixed::new(Some("fr"))), "de");
+        assert_eq!(route.language_of(&json!({ "title": "x" }), &Fixed::new(None)), "de");
+    }
+
+    #[test]
+    fn test_detection_reads_the_text_fields_or_every_top_level_string() {
+        let document = json!({ "title": "Oak table", "sku": "T-1", "price": 90, "meta": { "note": "nested" } });
+
+        let listed = route(r#"{"languages": ["en"], "text_fields": ["title", "price", "missing"]}"#);
+        let detector = Fixed::new(None);
+        listed.language_of(&document, &detector);
+        assert_eq!(detector.text(), "Oak table");
+
+        let all = route(r#"{"languages": ["en"]}"#);
+        all.language_of(&document, &detector);
+        let mut lines: Vec<String> = detector.text().lines().map(str::to_string).collect();
+        lines.sort();
+        assert_eq!(lines, ["Oak table", "T-1"]);
+
+        all.language_of(&json!(["Oak table"]), &detector);
+        assert_eq!(detector.text(), "", "documents that are not objects have no text");
+    }
+
+    #[test]
+    fn test_groups_follow_the_order_of_the_languages() {
+        let route = route(r#"{"languages": ["en", "de"], "default": "de", "field": "lang"}"#);
+        let docs = [
+            doc("1", json!({ "lang": "de" })),
+            doc("2", json!({ "lang": "en" })),
+            Doc { id: "3".to_string(), content: "not json".to_string() },
+            doc("4", json!({ "lang": "en" })),
+        ];
+        let groups: Vec<(String, Vec<String>)> = route
+            .route("products", &docs)
+            .into_iter()
+            .map(|(index, docs)| (index, docs.into_iter().map(|doc| doc.id).collect()))
+            .collect();
+        assert_eq!(
+            groups,
+            [
+                ("products_en".to_string(), vec!["2".to_string(), "4".to_string()]),
+                ("products_de".to_string(), vec!["1".to_string(), "3".to_string()]),
+            ]
+        );
+        assert!(route.route("products", &[]).is_empty());
+    }
+
+    #[test]
+    fn test_searches_with_a_known_language_run_on_its_index_alone() {
+        let route = route(r#"{"languages": ["en", "de"]}"#);
+        let with_language = |language: Option<&str>| SearchQuery { config: Some(config(language, None)), ..SearchQuery::match_all() };
+        assert_eq!(route.search_indexes("products", &with_language(Some("de"))), ["products_de"]);
+        assert_eq!(route.search_indexes("products", &with_language(Some("fr"))), ["products_en", "products_de"]);
+        assert_eq!(route.search_indexes("products", &with_language(None)), ["products_en", "products_de"]);
+        assert_eq!(route.search_indexes("products", &SearchQuery::match_all()), ["products_en", "products_de"]);
+    }
+
+    #[test]
+    fn test_fan_out_queries_fetch_from_the_start_to_the_end_of_the_page() {
+        let window = |query: SearchQuery| {
+            let fan_out = fan_out_query(&query).unwrap();
+            (fan_out.page, fan_out.offset, fan_out.per_page)
+        };
+        assert_eq!(window(paged(Some(3), None, Some(20))), (Some(1), Some(0), Some(60)));
+        assert_eq!(window(paged(Some(3), Some(5), Some(20))), (Some(1), Some(0), Some(25)), "the offset wins over the page");
+        assert_eq!(window(paged(Some(0), None, None)), (Some(1), Some(0), Some(DEFAULT_PER_PAGE)));
+
+        let with_cursor = SearchQuery { config: Some(config(None, Some("abc"))), ..SearchQuery::match_all() };
+        assert!(fan_out_query(&with_cursor).is_err());
+    }
+
+    #[test]
+    fn test_merged_hits_are_ranked_by_score_keeping_the_first_copy() {
+        let merged = merge_results(
+            &paged(None, None, Some(10)),
+            vec![
+                results(vec![hit("en-1", Some(9.0)), hit("moved", Some(4.0)), hit("en-2", None)], Some(3), Some(true)),
+                results(vec![hit("de-1", Some(4.0)), hit("moved", Some(8.0)), hit("de-2", Some(-1.0))], Some(3), Some(true)),
+            ],
+        );
+        assert_eq!(ids(&merged), ["en-1", "moved", "de-1", "de-2", "en-2"], "ties keep the order of the indexes");
+        assert_eq!(merged.hits[1].score, Some(4.0), "the copy in the first index is kept");
+    }
+
+    #[test]
+    fn test_merged_pages_are_cut_from_the_combined_ranking() {
+        let merged = merge_results(
+            &paged(Some(2), None, Some(2)),
+            vec![
+                results(vec![hit("en-1", Some(9.0)), hit("en-2", Some(4.0)), hit("en-3", Some(1.0))], Some(3), Some(true)),
+                results(vec![hit("de-1", Some(7.0)), hit("de-2", Some(5.0))], Some(2), Some(true)),
+            ],
+        );
+        assert_eq!(ids(&merged), ["de-2", "en-2"]);
+        assert_eq!((merged.page, merged.per_page, merged.facets), (Some(2), Some(2), None));
+
+        let beyond = merge_results(&paged(Some(9), None, Some(2)), vec![results(vec![hit("en-1", Some(1.0))], Some(1), Some(true))]);
+        assert!(beyond.hits.is_empty());
+    }
+
+    #[test]
+    fn test_merged_totals_are_exact_only_when_every_index_counted_exactly() {
+        let merge = |parts: Vec<(Option<u32>, Option<bool>)>| {
+            let merged = merge_results(
+                &SearchQuery::match_all(),
+                parts.into_iter().map(|(total, exact)| results(Vec::new(), total, exact)).collect(),
+            );
+            (merged.total, merged.total_exact)
+        };
+        assert_eq!(merge(vec![(Some(3), Some(true)), (Some(12), Some(true))]), (Some(15), Some(true)));
+        assert_eq!(merge(vec![(Some(3), Some(true)), (Some(12), None)]), (Some(15), None));
+        assert_eq!(merge(vec![(Some(3), None), (Some(10_000), Some(false))]), (Some(10_003), Some(false)));
+        assert_eq!(merge(vec![(Some(3), Some(true)), (None, Some(true))]), (None, Some(true)));
+        assert_eq!(merge(vec![(Some(u32::MAX), Some(true)), (Some(1), Some(true))]), (Some(u32::MAX), Some(true)));
+    }
+
+    #[test]
+    fn test_merged_metadata_takes_the_slowest_and_any_truncation() {
+        let mut slow = results(Vec::new(), Some(0), Some(true));
+        slow.took_ms = Some(40);
+        slow.truncated = true;
+        slow.request_id = Some("req-2".to_string());
+        let mut fast = results(Vec::new(), Some(0), Some(true));
+        fast.took_ms = None;
+        fast.request_id = Some("req-1".to_string());
+
+        let merged = merge_results(&SearchQuery::match_all(), vec![fast, slow]);
+        assert_eq!((merged.took_ms, merged.truncated, merged.request_id.as_deref()), (Some(40), true, Some("req-1")));
+        assert_eq!(merged.next_cursor, None);
+    }
+}
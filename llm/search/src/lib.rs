@@ -3,16 +3,92 @@
 //! This library provides shared functionality for implementing search providers
 //! that conform to the `golem:search` interface specification.
 
+pub mod alerts;
+pub mod analysis;
+pub mod audit;
+pub mod batching;
+pub mod blob;
+pub mod cache;
 pub mod capabilities;
+pub mod change_feed;
+pub mod checksum;
+pub mod circuit_breaker;
+pub mod compiled_query;
+pub mod compression;
 pub mod config;
+pub mod consistency;
+pub mod cost;
+pub mod cursor;
+pub mod deadline;
+pub mod delta_sync;
+pub mod describe;
+pub mod diagnose;
+pub mod dry_run;
+pub mod endpoint_pool;
 pub mod error;
+pub mod events;
+pub mod explain;
+pub mod facets;
 pub mod fallbacks;
+pub mod field_mask;
+pub mod geo;
+pub mod highlight;
+pub mod http;
+pub mod id_set;
+pub mod ingest;
+pub mod instance;
+pub mod json;
+pub mod language;
+pub mod lookup;
+pub mod metrics;
+pub mod ndjson;
+pub mod numeric;
+pub mod outbox;
+pub mod pii;
+pub mod promotion;
+pub mod provider_params;
+pub mod query;
+pub mod query_ast;
+pub mod query_split;
+pub mod quota;
+pub mod rate_limit;
+pub mod raw;
+pub mod relevance;
+pub mod replay;
+pub mod request_id;
+pub mod rescue;
+pub mod response_limits;
+pub mod retry;
+pub mod rollover;
+pub mod router;
+pub mod routing;
+pub mod sampling;
+pub mod schema;
+pub mod session;
+pub mod settings;
+pub mod shutdown;
+pub mod slow_query;
+pub mod snapshot;
+pub mod spellfix;
+pub mod suggestions;
+pub mod tasks;
+pub mod telemetry;
+pub mod templates;
 pub mod testing;
 pub mod types;
+pub mod ui_metadata;
 pub mod utils;
+pub mod version;
+pub mod watch;
+pub mod write_buffer;
 
 #[cfg(feature = "durability")]
 pub mod durability;
+#[cfg(feature = "durability")]
+pub mod reindex;
+
+#[cfg(feature = "arrow")]
+pub mod columnar;
 
 // Re-export commonly used items
 pub use error::{SearchError, SearchResult};
@@ -20,6 +96,8 @@ pub use types::{SearchProvider, SearchCapabilities};
 pub use config::SearchConfig;
 pub use capabilities::{CapabilityMatrix, ProviderCapabilities, FeatureSupport, DegradationStrategy};
 pub use fallbacks::FallbackProcessor;
+pub use lookup::LookupSpec;
+pub use settings::{IndexSettings, SettingsDiff};
 pub use testing::{TestConfig, TestResult, ProviderTestRunner, TestDataGenerator, UniversalTestQueries};
 
 // TODO: WIT bindings will be generated here when the WIT file is properly configured
@@ -33,7 +111,7 @@ pub use types::{
     Doc, SearchQuery, SearchResults, Schema, SearchHit, FieldType, SchemaField,
     HighlightConfig, SearchConfig as SearchConfigType,
     QueryBuilder, DocumentBuilder, SchemaBuilder,
-    IndexName, DocumentId, Json,
+    IndexName, DocumentId, Json, QueryText, MATCH_ALL,
 };
 
 /// Placeholder component struct for future WIT implementation
@@ -121,6 +199,7 @@ mod tests {
                 cloud_id: None,
                 ca_cert: None,
             },
+            quotas: Default::default(),
         };
         
         assert!(valid_config.validate().is_ok());
@@ -135,6 +214,7 @@ mod tests {
                 app_id: "".to_string(), // Empty app_id
                 api_key: "test_key".to_string(),
             },
+            quotas: Default::default(),
         };
         
         assert!(invalid_config.validate().is_err());
@@ -149,6 +229,7 @@ mod tests {
                 api_key: Some("test_key".to_string()),
                 master_key: None,
             },
+            quotas: Default::default(),
         };
         
         assert!(meilisearch_config.validate().is_ok());
@@ -229,6 +310,7 @@ mod tests {
             pre_tag: Some("<mark>".to_string()),
             post_tag: Some("</mark>".to_string()),
             max_length: Some(150),
+            offsets: false,
         };
         
         assert!(validate_highlight_config(&valid_highlight).is_ok());
@@ -239,6 +321,7 @@ mod tests {
             pre_tag: Some("<mark>".to_string()),
             post_tag: Some("</mark>".to_string()),
             max_length: Some(150),
+            offsets: false,
         };
         
         assert!(validate_highlight_config(&invalid_highlight).is_err());
@@ -249,6 +332,7 @@ mod tests {
             pre_tag: None,
             post_tag: None,
             max_length: Some(150),
+            offsets: false,
         };
         
         assert!(validate_highlight_config(&no_tags).is_ok()); // Should be valid
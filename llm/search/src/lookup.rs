@@ -0,0 +1,352 @@
+//! Client-side lookup joins between indexes
+//!
+//! None of the supported providers can join across indexes, so a lookup is
+//! executed as a second batched query against the lookup index followed by a
+//! client-side merge. Providers only need to fetch documents whose
+//! `foreign_field` matches the keys returned by [`collect_lookup_keys`], at
+//! most [`MAX_LOOKUP_KEYS`] of them per query (see [`fetch_in_batches`]).
+//!
+//! A key can match several lookup documents, as when joining a parent to its
+//! children; every match is attached to the hit.
+
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::error::{SearchError, SearchResult};
+use crate::types::{IndexName, SearchResults};
+
+/// Key under which joined documents are attached to each hit's content
+pub const LOOKUP_FIELD: &str = "_lookup";
+
+/// Maximum number of distinct keys sent in a single lookup query; more keys take several queries
+pub const MAX_LOOKUP_KEYS: usize = 1000;
+
+/// Most lookup documents read by a single lookup query
+pub const MAX_LOOKUP_DOCS: usize = 10_000;
+
+/// Description of a lookup join
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LookupSpec {
+    /// Index holding the documents to join
+    pub index: IndexName,
+
+    /// Field in the primary hits whose value is used as the join key
+    pub local_field: String,
+
+    /// Field in the lookup index that must match the join key
+    pub foreign_field: String,
+
+    /// Fields to copy from the lookup documents (empty copies all fields)
+    #[serde(default)]
+    pub fields: Vec<String>,
+}
+
+impl LookupSpec {
+    /// Parse and validate a lookup specification from JSON
+    pub fn from_json(json: &str) -> SearchResult<Self> {
+        let spec: LookupSpec = serde_json::from_str(json)
+            .map_err(|e| SearchError::invalid_query(format!("Invalid lookup JSON: {}", e)))?;
+        spec.validate()?;
+        Ok(spec)
+    }
+
+    /// Validate the lookup specification
+    pub fn validate(&self) -> SearchResult<()> {
+        if self.index.is_empty() {
+            return Err(SearchError::invalid_query("Lookup index cannot be empty"));
+        }
+        if self.local_field.is_empty() || self.foreign_field.is_empty() {
+            return Err(SearchError::invalid_query("Lookup fields cannot be empty"));
+        }
+        Ok(())
+    }
+}
+
+/// Collect the distinct join keys referenced by the primary hits, in hit order
+pub fn collect_lookup_keys(results: &SearchResults, spec: &LookupSpec) -> Vec<String> {
+    lookup_keys_from_contents(results.hits.iter().map(|h| h.content.as_deref()), spec)
+}
+
+/// Collect distinct join keys from raw JSON hit contents
+///
+/// Useful for providers whose result types are generated from WIT rather than
+/// using [`SearchResults`] directly.
+pub fn lookup_keys_from_contents<'a>(
+    contents: impl IntoIterator<Item = Option<&'a str>>,
+    spec: &LookupSpec,
+) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let mut keys = Vec::new();
+
+    for content in contents {
+        let Some(content) = content.and_then(|c| serde_json::from_str::<Value>(c).ok()) else {
+            continue;
+        };
+
+        for key in key_values(content.get(&spec.local_field)) {
+            if seen.insert(key.clone()) {
+                keys.push(key);
+            }
+        }
+    }
+
+    keys
+}
+
+/// Fetch the lookup documents matching `keys`, sending at most [`MAX_LOOKUP_KEYS`] keys per query
+pub async fn fetch_in_batches<F, Fut, E>(keys: &[String], fetch: F) -> Result<Vec<Value>, E>
+where
+    F: Fn(Vec<String>) -> Fut,
+    Fut: Future<Output = Result<Vec<Value>, E>>,
+{
+    let mut docs = Vec::new();
+    for batch in keys.chunks(MAX_LOOKUP_KEYS) {
+        docs.extend(fetch(batch.to_vec()).await?);
+    }
+    Ok(docs)
+}
+
+/// Fail a lookup query whose keys matched more documents than it read
+///
+/// `matched` is the total the provider reports, when it reports one.
+pub fn check_lookup_complete(spec: &LookupSpec, read: usize, matched: Option<u64>) -> SearchResult<()> {
+    match matched {
+        Some(matched) if matched > read as u64 => Err(SearchError::invalid_query(format!(
+            "Lookup in {} matched {} documents, more than the {} one query reads",
+            spec.index, matched, read
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Lookup documents indexed by their join key
+pub struct LookupTable<'a> {
+    spec: &'a LookupSpec,
+    by_key: HashMap<String, Vec<Value>>,
+}
+
+impl<'a> LookupTable<'a> {
+    /// Index the lookup documents by `foreign_field`, keeping only the requested fields
+    pub fn new(spec: &'a LookupSpec, lookup_docs: &[Value]) -> Self {
+        let mut by_key: HashMap<String, Vec<Value>> = HashMap::new();
+        for doc in lookup_docs {
+            for key in key_values(doc.get(&spec.foreign_field)) {
+                by_key.entry(key).or_default().push(project_fields(doc, &spec.fields));
+            }
+        }
+        Self { spec, by_key }
+    }
+
+    /// Attach the matching lookup documents to a hit's JSON content
+    ///
+    /// Joined documents are stored as `_lookup.<index>`. A single-valued key
+    /// with exactly one match stores it as an object; multi-valued keys, and keys
+    /// matching several documents, store every match in an array. Content
+    /// without a match is returned unchanged.
+    pub fn enrich(&self, raw: &str) -> SearchResult<String> {
+        let mut content: Value = serde_json::from_str(raw)?;
+
+        let local = content.get(&self.spec.local_field);
+        let matches: Vec<Value> = key_values(local)
+            .iter()
+            .filter_map(|k| self.by_key.get(k))
+            .flatten()
+            .cloned()
+            .collect();
+
+        let joined = match (local, matches.len()) {
+            (_, 0) => return Ok(raw.to_string()),
+            (Some(Value::Array(_)), _) | (_, 2..) => Value::Array(matches),
+            (_, _) => matches.into_iter().next().unwrap_or(Value::Null),
+        };
+
+        if let Value::Object(map) = &mut content {
+            let lookup = map
+                .entry(LOOKUP_FIELD.to_string())
+                .or_insert_with(|| Value::Object(serde_json::Map::new()));
+            if let Value::Object(lookup) = lookup {
+                lookup.insert(self.spec.index.clone(), joined);
+            }
+        }
+
+        Ok(serde_json::to_string(&content)?)
+    }
+}
+
+/// Attach the matching lookup documents to each primary hit under [`LOOKUP_FIELD`]
+pub fn merge_lookup(results: &mut SearchResults, spec: &LookupSpec, lookup_docs: &[Value]) -> SearchResult<()> {
+    let table = LookupTable::new(spec, lookup_docs);
+
+    for hit in &mut results.hits {
+        if let Some(raw) = hit.content.as_deref() {
+            hit.content = Some(table.enrich(raw)?);
+        }
+    }
+
+    Ok(())
+}
+
+/// Order documents fetched by id to match the requested ids
+///
+/// Returns one slot per requested id, `None` where no document with that id
+/// was returned. Numeric ids match by their string form.
+pub fn align_to_ids<'a>(ids: &[String], docs: &'a [Value], id_field: &str) -> Vec<Option<&'a Value>> {
+    let by_id: HashMap<String, &Value> = docs
+        .iter()
+        .filter_map(|doc| match doc.get(id_field) {
+            Some(Value::String(s)) => Some((s.clone(), doc)),
+            Some(Value::Number(n)) => Some((n.to_string(), doc)),
+            _ => None,
+        })
+        .collect();
+
+    ids.iter().map(|id| by_id.get(id).copied()).collect()
+}
+
+/// Normalize a join field into string keys, flattening arrays
+fn key_values(value: Option<&Value>) -> Vec<String> {
+    match value {
+        Some(Value::String(s)) => vec![s.clone()],
+        Some(Value::Number(n)) => vec![n.to_string()],
+        Some(Value::Bool(b)) => vec![b.to_string()],
+        Some(Value::Array(items)) => items.iter().flat_map(|v| key_values(Some(v))).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Keep only the requested fields of a lookup document
+fn project_fields(doc: &Value, fields: &[String]) -> Value {
+    if fields.is_empty() {
+        return doc.clone();
+    }
+
+    let projected = fields
+        .iter()
+        .filter_map(|f| doc.get(f).map(|v| (f.clone(), v.clone())))
+        .collect::<serde_json::Map<_, _>>();
+    Value::Object(projected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::SearchHit;
+    use serde_json::json;
+
+    fn spec() -> LookupSpec {
+        LookupSpec {
+            index: "authors".to_string(),
+            local_field: "author_id".to_string(),
+            foreign_field: "id".to_string(),
+            fields: vec!["name".to_string()],
+        }
+    }
+
+    fn results() -> SearchResults {
+        let hit = |id: &str, content: Value| SearchHit {
+            id: id.to_string(),
+            score: None,
+            content: Some(content.to_string()),
+            highlights: None,
+            explanation: None,
+            distance_meters: None,
+        };
+
+        SearchResults {
+            total: Some(3),
+            page: None,
+            per_page: None,
+            hits: vec![
+                hit("1", json!({"title": "A", "author_id": "a1"})),
+                hit("2", json!({"title": "B", "author_id": 7})),
+                hit("3", json!({"title": "C", "author_id": "a1"})),
+            ],
+            facets: None,
+            took_ms: None,
+            truncated: false,
+            request_id: None,
+            groups: None,
+            next_cursor: None,
+            total_exact: None,
+            facet_metadata: Vec::new(),
+            relaxations: Vec::new(),
+            original_query: None,
+            corrected_query: None,
+        }
+    }
+
+    #[test]
+    fn test_collect_lookup_keys_dedupes() {
+        let keys = collect_lookup_keys(&results(), &spec());
+        assert_eq!(keys, vec!["a1".to_string(), "7".to_string()]);
+    }
+
+    #[test]
+    fn test_merge_lookup_projects_fields() {
+        let mut results = results();
+        let docs = vec![json!({"id": "a1", "name": "Ada", "email": "ada@example.com"})];
+
+        merge_lookup(&mut results, &spec(), &docs).unwrap();
+
+        let first: Value = serde_json::from_str(results.hits[0].content.as_ref().unwrap()).unwrap();
+        assert_eq!(first[LOOKUP_FIELD]["authors"], json!({"name": "Ada"}));
+
+        let second: Value = serde_json::from_str(results.hits[1].content.as_ref().unwrap()).unwrap();
+        assert!(second.get(LOOKUP_FIELD).is_none());
+    }
+
+    #[test]
+    fn test_merge_lookup_attaches_every_child() {
+        let mut results = results();
+        let spec = LookupSpec {
+            index: "books".to_string(),
+            local_field: "author_id".to_string(),
+            foreign_field: "author".to_string(),
+            fields: vec!["title".to_string()],
+        };
+        let docs = vec![
+            json!({"author": "a1", "title": "First"}),
+            json!({"author": "a1", "title": "Second"}),
+            json!({"author": 7, "title": "Only"}),
+        ];
+
+        merge_lookup(&mut results, &spec, &docs).unwrap();
+
+        let first: Value = serde_json::from_str(results.hits[0].content.as_ref().unwrap()).unwrap();
+        assert_eq!(first[LOOKUP_FIELD]["books"], json!([{"title": "First"}, {"title": "Second"}]));
+        let second: Value = serde_json::from_str(results.hits[1].content.as_ref().unwrap()).unwrap();
+        assert_eq!(second[LOOKUP_FIELD]["books"], json!({"title": "Only"}));
+
+        assert!(check_lookup_complete(&spec, 3, Some(3)).is_ok());
+        assert!(check_lookup_complete(&spec, 3, Some(4)).is_err());
+    }
+
+    #[test]
+    fn test_keys_past_the_limit_take_further_queries() {
+        let contents: Vec<String> = (0..2500).map(|i| json!({"author_id": i}).to_string()).collect();
+        let keys = lookup_keys_from_contents(contents.iter().map(|c| Some(c.as_str())), &spec());
+        assert_eq!(keys.len(), 2500);
+
+        let batches = std::cell::RefCell::new(Vec::new());
+        let fetch = |batch: Vec<String>| {
+            batches.borrow_mut().push(batch.len());
+            let docs = batch.into_iter().map(|key| json!({"id": key})).collect();
+            async move { Ok::<_, SearchError>(docs) }
+        };
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let docs = runtime.block_on(fetch_in_batches(&keys, fetch)).unwrap();
+        assert_eq!(docs.len(), 2500);
+        assert_eq!(*batches.borrow(), vec![1000, 1000, 500]);
+    }
+
+    #[test]
+    fn test_align_to_ids_keeps_request_order() {
+        let docs = vec![json!({"id": 2, "name": "b"}), json!({"id": "a", "name": "a"})];
+        let ids = vec!["a".to_string(), "missing".to_string(), "2".to_string(), "a".to_string()];
+
+        let aligned = align_to_ids(&ids, &docs, "id");
+        let names: Vec<Option<&str>> = aligned.iter().map(|d| d.and_then(|d| d["name"].as_str())).collect();
+        assert_eq!(names, vec![Some("a"), None, Some("b"), Some("a")]);
+    }
+}
@@ -0,0 +1,344 @@
+//! In-process metrics in Prometheus text format
+//!
+//! Every operation run through [`crate::telemetry::traced`] is counted here,
+//! with its latency and, on failure, its error kind; cache lookups are counted
+//! by the memory accountant. Components have no listening socket to be scraped,
+//! so providers expose [`render_prometheus`] as a `metrics-prometheus` export
+//! that a sidecar worker calls and forwards to Prometheus.
+//!
+//! Counters live for the lifetime of the worker instance and start from zero
+//! when it is recreated, which Prometheus handles as an ordinary counter reset.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use crate::cache::CacheKind;
+use crate::error::SearchError;
+
+/// Upper bounds of the latency histogram buckets, in seconds
+pub const LATENCY_BUCKETS: [f64; 12] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+/// Errors that can be counted by kind
+pub trait ErrorKind {
+    /// Short snake_case label for the `kind` metric label
+    fn error_kind(&self) -> &'static str;
+}
+
+impl ErrorKind for SearchError {
+    fn error_kind(&self) -> &'static str {
+        match self {
+            SearchError::IndexNotFound(_) => "index_not_found",
+            SearchError::IndexAlreadyExists(_) => "index_already_exists",
+            SearchError::InvalidQuery(_) => "invalid_query",
+            SearchError::Unsupported => "unsupported",
+            SearchError::Internal(_) => "internal",
+            SearchError::Timeout { .. } => "timeout",
+            SearchError::RateLimited { .. } => "rate_limited",
+            SearchError::ResponseTooLarge { .. } => "response_too_large",
+            SearchError::Backpressure { .. } => "backpressure",
+            SearchError::Unavailable { .. } => "unavailable",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Histogram {
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, seconds: f64) {
+        // Buckets are stored non-cumulatively and summed up when rendered
+        if let Some(slot) = LATENCY_BUCKETS.iter().position(|&bound| seconds <= bound) {
+            self.buckets[slot] += 1;
+        }
+        self.sum += seconds;
+        self.count += 1;
+    }
+}
+
+/// Counters and histograms of one worker
+#[derive(Debug, Default)]
+pub struct Registry {
+    requests: BTreeMap<(String, String), u64>,
+    errors: BTreeMap<(String, String, &'static str), u64>,
+    latency: BTreeMap<(String, String), Histogram>,
+    cache: BTreeMap<&'static str, (u64, u64)>,
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record one finished operation and, if it failed, its error kind
+    pub fn record_operation(&mut self, provider: &str, operation: &str, elapsed: Duration, error: Option<&'static str>) {
+        let key = (provider.to_string(), operation.to_string());
+        *self.requests.entry(key.clone()).or_default() += 1;
+        if let Some(kind) = error {
+            *self.errors.entry((key.0.clone(), key.1.clone(), kind)).or_default() += 1;
+        }
+        self.latency.entry(key).or_default().observe(elapsed.as_secs_f64());
+    }
+
+    /// Record a cache lookup
+    pub fn record_cache_lookup(&mut self, kind: CacheKind, hit: bool) {
+        let (hits, misses) = self.cache.entry(cache_label(kind)).or_default();
+        if hit {
+            *hits += 1;
+        } else {
+            *misses += 1;
+        }
+    }
+
+    /// Render everything in the Prometheus text exposition format (version 0.0.4)
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        header(&mut out, "golem_search_requests_total", "counter", "Provider operations executed");
+        for ((provider, operation), count) in &self.requests {
+            sample(&mut out, "golem_search_requests_total", &[("provider", provider), ("operation", operation)], *count as f64);
+        }
+
+        header(&mut out, "golem_search_errors_total", "counter", "Failed provider operations by error kind");
+        for ((provider, operation, kind), count) in &self.errors {
+            let labels = [("provider", provider.as_str()), ("operation", operation.as_str()), ("kind", kind)];
+            sample(&mut out, "golem_search_errors_total", &labels, *count as f64);
+        }
+
+        header(&mut out, "golem_search_request_duration_seconds", "histogram", "Provider operation latency");
+        for ((provider, operation), histogram) in &self.latency {
+            let mut cumulative = 0;
+            for (bound, count) in LATENCY_BUCKETS.iter().zip(histogram.buckets) {
+                cumulative += count;
+                let le = bound.to_string();
+                let labels = [("provider", provider.as_str()), ("operation", operation.as_str()), ("le", le.as_str())];
+                sample(&mut out, "golem_search_request_duration_seconds_bucket", &labels, cumulative as f64);
+            }
+            let labels = [("provider", provider.as_str()), ("operation", operation.as_str()), ("le", "+Inf")];
+            sample(&mut out, "golem_search_request_duration_seconds_bucket", &labels, histogram.count as f64);
+
+            let labels = [("provider", provider.as_str()), ("operation", operation.as_str())];
+            sample(&mut out, "golem_search_request_duration_seconds_sum", &labels, histogram.sum);
+            sample(&mut out, "golem_search_request_duration_seconds_count", &labels, histogram.count as f64);
+        }
+
+        header(&mut out, "golem_search_cache_lookups_total", "counter", "Cache lookups by outcome");
+        for (kind, (hits, misses)) in &self.cache {
+            sample(&mut out, "golem_search_cache_lookups_total", &[("kind", kind), ("outcome", "hit")], *hits as f64);
+            sample(&mut out, "golem_search_cache_lookups_total", &[("kind", kind), ("outcome", "miss")], *misses as f64);
+        }
+
+        header(&mut out, "golem_search_cache_hit_ratio", "gauge", "Share of cache lookups that were hits");
+        for (kind, (hits, misses)) in &self.cache {
+            let total = hits + misses;
+            if total > 0 {
+                sample(&mut out, "golem_search_cache_hit_ratio", &[("kind", kind)], *hits as f64 / total as f64);
+            }
+        }
+
+        out
+    }
+}
+
+fn cache_label(kind: CacheKind) -> &'static str {
+    match kind {
+        CacheKind::Client => "client",
+        CacheKind::Schema => "schema",
+        CacheKind::Result => "result",
+        CacheKind::CompiledQuery => "compiled_query",
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn sample(out: &mut String, name: &str, labels: &[(&str, &str)], value: f64) {
+    let labels: Vec<String> = labels
+        .iter()
+        .map(|(key, value)| format!("{}=\"{}\"", key, escape_label(value)))
+        .collect();
+    let _ = writeln!(out, "{}{{{}}} {}", name, labels.join(","), value);
+}
+
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// The worker-wide registry
+pub fn global() -> &'static Mutex<Registry> {
+    static REGISTRY: OnceLock<Mutex<Registry>> = OnceLock::new();
+    REGISTRY.get_or_init(|| Mutex::new(Registry::new()))
+}
+
+/// Record one finished operation in the global registry
+pub fn record_operation(provider: &str, operation: &str, elapsed: Duration, error: Option<&'static str>) {
+    global().lock().unwrap().record_operation(provider, operation, elapsed, error);
+}
+
+/// Record a cache lookup in the global registry
+pub fn record_cache_lookup(kind: CacheKind, hit: bool) {
+    global().lock().unwrap().record_cache_lookup(kind, hit);
+}
+
+/// Snapshot of the global registry in Prometheus text format
+pub fn render_prometheus() -> String {
+    global().lock().unwrap().render()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Value of the sample `series` (name and labels) in `text`
+    fn value(text: &str, series: &str) -> Option<String> {
+        text.lines()
+            .find_map(|line| line.strip_prefix(series).and_then(|rest| rest.strip_prefix(' ')))
+            .map(str::to_string)
+    }
+
+    fn bucket(text: &str, le: &str) -> String {
+        let series = format!("golem_search_request_duration_seconds_bucket{{provider=\"typesense\",operation=\"search\",le=\"{}\"}}", le);
+        value(text, &series).unwrap_or_else(|| panic!("no bucket {}", le))
+    }
+
+    fn samples(text: &str, name: &str) -> Vec<String> {
+        text.lines().filter(|line| line.starts_with(&format!("{}{{", name))).map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_bucket_bounds_are_inclusive_and_cumulative() {
+        let mut registry = Registry::new();
+        for elapsed in [Duration::ZERO, Duration::from_millis(5), Duration::from_micros(5_001), Duration::from_secs(30)] {
+            registry.record_operation("typesense", "search", elapsed, None);
+        }
+        let text = registry.render();
+        assert_eq!(bucket(&text, "0.005"), "2");
+        assert_eq!(bucket(&text, "0.01"), "3");
+        assert_eq!(bucket(&text, "10"), "3");
+        assert_eq!(bucket(&text, "30"), "4");
+        assert_eq!(bucket(&text, "+Inf"), "4");
+    }
+
+    #[test]
+    fn test_slow_operations_only_reach_the_infinite_bucket() {
+        let mut registry = Registry::new();
+        registry.record_operation("typesense", "search", Duration::from_secs(60), None);
+        registry.record_operation("typesense", "search", Duration::from_millis(500), None);
+        let text = registry.render();
+        assert_eq!(bucket(&text, "30"), "1");
+        assert_eq!(bucket(&text, "+Inf"), "2");
+        let labels = "{provider=\"typesense\",operation=\"search\"}";
+        assert_eq!(value(&text, &format!("golem_search_request_duration_seconds_sum{}", labels)).as_deref(), Some("60.5"));
+        assert_eq!(value(&text, &format!("golem_search_request_duration_seconds_count{}", labels)).as_deref(), Some("2"));
+    }
+
+    #[test]
+    fn test_errors_are_counted_per_operation_and_kind() {
+        let mut registry = Registry::new();
+        registry.record_operation("meilisearch", "search", Duration::ZERO, Some("timeout"));
+        registry.record_operation("meilisearch", "search", Duration::ZERO, Some("timeout"));
+        registry.record_operation("meilisearch", "search", Duration::ZERO, Some("rate_limited"));
+        registry.record_operation("meilisearch", "search", Duration::ZERO, None);
+        registry.record_operation("meilisearch", "get", Duration::ZERO, None);
+
+        let text = registry.render();
+        assert_eq!(
+            samples(&text, "golem_search_errors_total"),
+            [
+                "golem_search_errors_total{provider=\"meilisearch\",operation=\"search\",kind=\"rate_limited\"} 1",
+                "golem_search_errors_total{provider=\"meilisearch\",operation=\"search\",kind=\"timeout\"} 2",
+            ]
+        );
+        assert_eq!(
+            samples(&text, "golem_search_requests_total"),
+            [
+                "golem_search_requests_total{provider=\"meilisearch\",operation=\"get\"} 1",
+                "golem_search_requests_total{provider=\"meilisearch\",operation=\"search\"} 4",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_empty_registries_render_only_headers() {
+        let text = Registry::new().render();
+        assert!(text.lines().all(|line| line.starts_with("# ")), "{}", text);
+        let types: Vec<&str> = text.lines().filter_map(|line| line.strip_prefix("# TYPE ")).collect();
+        assert_eq!(
+            types,
+            [
+                "golem_search_requests_total counter",
+                "golem_search_errors_total counter",
+                "golem_search_request_duration_seconds histogram",
+                "golem_search_cache_lookups_total counter",
+                "golem_search_cache_hit_ratio gauge",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cache_lookups_are_split_by_kind_and_outcome() {
+        let mut registry = Registry::new();
+        for hit in [true, true, true, false] {
+            registry.record_cache_lookup(CacheKind::Result, hit);
+        }
+        registry.record_cache_lookup(CacheKind::CompiledQuery, false);
+
+        let text = registry.render();
+        assert_eq!(
+            samples(&text, "golem_search_cache_hit_ratio"),
+            ["golem_search_cache_hit_ratio{kind=\"compiled_query\"} 0", "golem_search_cache_hit_ratio{kind=\"result\"} 0.75"]
+        );
+        assert_eq!(value(&text, "golem_search_cache_lookups_total{kind=\"result\",outcome=\"miss\"}").as_deref(), Some("1"));
+        assert_eq!(value(&text, "golem_search_cache_lookups_total{kind=\"compiled_query\",outcome=\"hit\"}").as_deref(), Some("0"));
+        assert!(value(&text, "golem_search_cache_lookups_total{kind=\"schema\",outcome=\"hit\"}").is_none());
+    }
+
+    #[test]
+    fn test_label_values_are_escaped() {
+        let mut registry = Registry::new();
+        registry.record_operation("elastic\"search", "back\\slash\nnewline", Duration::ZERO, None);
+        let text = registry.render();
+        assert_eq!(
+            samples(&text, "golem_search_requests_total"),
+            ["golem_search_requests_total{provider=\"elastic\\\"search\",operation=\"back\\\\slash\\nnewline\"} 1"]
+        );
+    }
+
+    #[test]
+    fn test_every_error_has_its_own_kind() {
+        let errors = [
+            SearchError::IndexNotFound(String::new()),
+            SearchError::IndexAlreadyExists(String::new()),
+            SearchError::InvalidQuery(String::new()),
+            SearchError::Unsupported,
+            SearchError::Internal(String::new()),
+            SearchError::Timeout { remaining_ms: None },
+            SearchError::RateLimited { retry_after_ms: None },
+            SearchError::ResponseTooLarge { size: 2, limit: 1 },
+            SearchError::Backpressure { queue_depth: 1, retry_after_ms: None },
+            SearchError::Unavailable { retry_after_ms: None },
+        ];
+        let kinds: std::collections::HashSet<&str> = errors.iter().map(ErrorKind::error_kind).collect();
+        assert_eq!(kinds.len(), errors.len());
+        assert!(kinds.iter().all(|kind| kind.chars().all(|c| c.is_ascii_lowercase() || c == '_')));
+    }
+
+    #[test]
+    fn test_global_registry_accumulates_across_calls() {
+        record_operation("metrics-global-test", "search", Duration::from_millis(1), None);
+        record_operation("metrics-global-test", "search", Duration::from_millis(1), Some("internal"));
+        let text = render_prometheus();
+        let labels = "{provider=\"metrics-global-test\",operation=\"search\"}";
+        assert_eq!(value(&text, &format!("golem_search_requests_total{}", labels)).as_deref(), Some("2"));
+        assert_eq!(
+            value(&text, "golem_search_errors_total{provider=\"metrics-global-test\",operation=\"search\",kind=\"internal\"}").as_deref(),
+            Some("1")
+        );
+    }
+}
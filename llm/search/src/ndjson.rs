@@ -0,0 +1,197 @@
+//! Incremental NDJSON body builder for bulk APIs
+//!
+//! Bulk endpoints (`_bulk` in Elasticsearch/OpenSearch) take newline-delimited
+//! JSON. Rather than formatting every operation into an intermediate `String`
+//! and concatenating, [`NdjsonWriter`] serializes each operation straight into
+//! the request buffer, optionally through the encoder of a [`Compression`], so
+//! the uncompressed body never has to exist in memory at once.
+
+use std::io::Write;
+use serde::Serialize;
+use crate::compression::{Compression, Encoder};
+use crate::config::env_helpers::get_env_parsed;
+use crate::error::{SearchError, SearchResult};
+
+/// Environment variable enabling gzip compression of bulk bodies, unless `SEARCH_PROVIDER_COMPRESSION` is set
+pub const BULK_GZIP_ENV: &str = "SEARCH_BULK_GZIP";
+
+/// Whether bulk bodies should be gzip-compressed, from `SEARCH_BULK_GZIP`
+pub fn gzip_from_env() -> bool {
+    get_env_parsed::<bool>(BULK_GZIP_ENV).ok().flatten().unwrap_or(false)
+}
+
+/// Writes JSON values as NDJSON lines into a (possibly compressed) buffer
+pub struct NdjsonWriter {
+    sink: Encoder,
+    compression: Compression,
+    lines: usize,
+    uncompressed_len: usize,
+}
+
+/// A finished NDJSON request body
+#[derive(Debug, Clone)]
+pub struct NdjsonBody {
+    /// Encoded body bytes
+    pub bytes: Vec<u8>,
+
+    /// Compression of `bytes`
+    pub compression: Compression,
+
+    /// Number of NDJSON lines written
+    pub lines: usize,
+
+    /// Size of the body before compression
+    pub uncompressed_len: usize,
+}
+
+impl NdjsonBody {
+    /// Value for the `Content-Encoding` header, if any
+    pub fn content_encoding(&self) -> Option<&'static str> {
+        self.compression.content_encoding()
+    }
+}
+
+impl NdjsonWriter {
+    /// Create a writer compressing with `compression`
+    pub fn new(compression: Compression) -> Self {
+        Self {
+            sink: compression.encoder(),
+            compression,
+            lines: 0,
+            uncompressed_len: 0,
+        }
+    }
+
+    /// Append a single value as one NDJSON line
+    pub fn push<T: Serialize>(&mut self, value: &T) -> SearchResult<()> {
+        let line = serde_json::to_vec(value)?;
+        self.write_line(&line)
+    }
+
+    /// Append every value of an iterator, consuming them one at a time
+    pub fn extend<T, I>(&mut self, values: I) -> SearchResult<()>
+    where
+        T: Serialize,
+        I: IntoIterator<Item = T>,
+    {
+        for value in values {
+            self.push(&value)?;
+        }
+        Ok(())
+    }
+
+    /// Number of lines written so far
+    pub fn lines(&self) -> usize {
+        self.lines
+    }
+
+    /// Finish encoding and return the body
+    pub fn finish(self) -> SearchResult<NdjsonBody> {
+        let bytes = self
+            .sink
+            .finish()
+            .map_err(|e| SearchError::internal(format!("Failed to compress bulk body: {}", e)))?;
+
+        Ok(NdjsonBody {
+            bytes,
+            compression: self.compression,
+            lines: self.lines,
+            uncompressed_len: self.uncompressed_len,
+        })
+    }
+
+    fn write_line(&mut self, line: &[u8]) -> SearchResult<()> {
+        self.sink
+            .write_all(line)
+            .and_then(|_| self.sink.write_all(b"\n"))
+            .map_err(|e| SearchError::internal(format!("Failed to write bulk body: {}", e)))?;
+
+        self.lines += 1;
+        self.uncompressed_len += line.len() + 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::collections::BTreeMap;
+
+    fn operations() -> Vec<serde_json::Value> {
+        vec![json!({"index": {"_id": "1"}}), json!({"name": "Widget"})]
+    }
+
+    fn written(compression: Compression, values: Vec<serde_json::Value>) -> NdjsonBody {
+        let mut writer = NdjsonWriter::new(compression);
+        writer.extend(values).unwrap();
+        writer.finish().unwrap()
+    }
+
+    /// A value JSON cannot hold when it has entries: object keys must be strings
+    fn pairs(entries: &[((u8, u8), u8)]) -> BTreeMap<(u8, u8), u8> {
+        entries.iter().copied().collect()
+    }
+
+    #[test]
+    fn test_plain_body_is_newline_delimited() {
+        let body = written(Compression::None, operations());
+        assert_eq!(body.content_encoding(), None);
+        assert_eq!((body.lines, body.uncompressed_len), (2, 40));
+        assert_eq!(String::from_utf8(body.bytes).unwrap(), "{\"index\":{\"_id\":\"1\"}}\n{\"name\":\"Widget\"}\n");
+    }
+
+    #[test]
+    fn test_newlines_in_values_stay_escaped_on_one_line() {
+        let body = written(Compression::None, vec![json!({"text": "line one\nline two\r\n"})]);
+        let text = String::from_utf8(body.bytes).unwrap();
+        assert_eq!(text.lines().count(), 1);
+        assert_eq!(text, "{\"text\":\"line one\\nline two\\r\\n\"}\n");
+    }
+
+    #[test]
+    fn test_uncompressed_length_counts_bytes() {
+        let body = written(Compression::Gzip, vec![json!("café 🦀")]);
+        assert_eq!(body.uncompressed_len, "\"café 🦀\"\n".len());
+        assert_eq!(body.uncompressed_len, 13);
+    }
+
+    #[test]
+    fn test_compressed_bodies_decode_to_the_plain_body() {
+        let plain = written(Compression::None, operations());
+        for compression in [Compression::Gzip, Compression::Deflate] {
+            let body = written(compression, operations());
+            assert_eq!(body.content_encoding(), compression.content_encoding());
+            assert_eq!((body.lines, body.uncompressed_len), (plain.lines, plain.bytes.len()));
+            assert_eq!(compression.decompress(&body.bytes).unwrap(), plain.bytes, "{:?}", compression);
+        }
+    }
+
+    #[test]
+    fn test_empty_bodies_have_no_lines() {
+        let plain = written(Compression::None, Vec::new());
+        assert!(plain.bytes.is_empty());
+        let gzip = written(Compression::Gzip, Vec::new());
+        assert_eq!((gzip.lines, gzip.uncompressed_len), (0, 0));
+        assert!(Compression::Gzip.decompress(&gzip.bytes).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_unserializable_values_are_refused_without_writing() {
+        let mut writer = NdjsonWriter::new(Compression::None);
+        writer.push(&json!({"index": {}})).unwrap();
+        assert!(matches!(writer.push(&pairs(&[((1, 2), 3)])), Err(SearchError::InvalidQuery(_))));
+        assert_eq!(writer.lines(), 1);
+        writer.push(&json!({"title": "after"})).unwrap();
+        assert_eq!(String::from_utf8(writer.finish().unwrap().bytes).unwrap(), "{\"index\":{}}\n{\"title\":\"after\"}\n");
+    }
+
+    #[test]
+    fn test_extend_stops_at_the_first_failure() {
+        let mut writer = NdjsonWriter::new(Compression::None);
+        let values = [pairs(&[]), pairs(&[((1, 2), 3)]), pairs(&[])];
+        assert!(writer.extend(&values).is_err());
+        assert_eq!(writer.lines(), 1);
+        assert_eq!(writer.finish().unwrap().bytes, b"{}\n");
+    }
+}
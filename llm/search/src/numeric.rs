@@ -0,0 +1,319 @@
+//! 64-bit integers and exact decimals in documents
+//!
+//! `Integer` fields are 32-bit on Typesense, and `Float` fields round amounts
+//! such as prices. Schemas type such fields as `Int64` or `Decimal` instead,
+//! which providers store without loss:
+//!
+//! | Type    | Elasticsearch / OpenSearch | Typesense | Meilisearch                  |
+//! |---------|----------------------------|-----------|------------------------------|
+//! | Int64   | `long`                     | `int64`   | number, exact up to 2^53 - 1 |
+//! | Decimal | `keyword`                  | `string`  | string                       |
+//!
+//! Decimals are kept as their decimal text, so they match and facet on
+//! exact values but are not ranged or sorted numerically. Elasticsearch,
+//! OpenSearch and Typesense remember which keyword fields are decimals in the
+//! index metadata, under [`DECIMAL_META_KEY`].
+//!
+//! Writes are checked against the fields kept by [`remember`] when the index
+//! was created or its schema read: an `Int64` value must be a whole number in
+//! range, given as a number or a string, and a `Decimal` value a number or a
+//! decimal string such as `"19.99"`. Numbers given for decimals are written as
+//! strings, and strings given for integers as numbers. A document with any
+//! other value fails the write rather than being stored rounded.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use serde_json::{Map, Value};
+use crate::error::{SearchError, SearchResult};
+use crate::types::{FieldType, Schema};
+
+/// Key of the decimal field names in the metadata of an index
+pub const DECIMAL_META_KEY: &str = "golem_decimal";
+
+/// Largest integer Meilisearch stores exactly, as it keeps numbers as doubles
+pub const MEILISEARCH_MAX_INTEGER: i64 = (1 << 53) - 1;
+
+/// The `Int64` and `Decimal` fields of an index
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NumericFields {
+    pub int64: Vec<String>,
+    pub decimal: Vec<String>,
+
+    /// Largest magnitude the provider stores exactly for `Int64` fields
+    pub max_integer: i64,
+}
+
+impl NumericFields {
+    /// Fields of `schema` as `provider` stores them
+    pub fn new(provider: &str, schema: &Schema) -> Self {
+        let named = |field_type: FieldType| {
+            schema.fields.iter().filter(|f| f.field_type == field_type).map(|f| f.name.clone()).collect()
+        };
+        Self {
+            int64: named(FieldType::Int64),
+            decimal: named(FieldType::Decimal),
+            max_integer: if provider == "meilisearch" { MEILISEARCH_MAX_INTEGER } else { i64::MAX },
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.int64.is_empty() && self.decimal.is_empty()
+    }
+
+    /// Check the numeric fields of document `id`, rewriting values given in the other accepted form
+    pub fn apply(&self, id: &str, document: &mut Map<String, Value>) -> SearchResult<()> {
+        for field in &self.int64 {
+            if let Some(value) = document.get_mut(field) {
+                *value = self.int64_value(value).ok_or_else(|| {
+                    invalid(id, field, value, &format!("a whole number within ±{}", self.max_integer))
+                })?;
+            }
+        }
+        for field in &self.decimal {
+            if let Some(value) = document.get_mut(field) {
+                *value = decimal_value(value).ok_or_else(|| invalid(id, field, value, "a decimal number"))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// A document's content with its numeric fields checked
+    pub fn apply_content(&self, id: &str, content: &str) -> SearchResult<String> {
+        let mut document: Map<String, Value> = serde_json::from_str(content)
+            .map_err(|e| SearchError::invalid_query(format!("Document {} is not a JSON object: {}", id, e)))?;
+        self.apply(id, &mut document)?;
+        Ok(Value::Object(document).to_string())
+    }
+
+    fn int64_value(&self, value: &Value) -> Option<Value> {
+        let number = match value {
+            Value::Null => return Some(Value::Null),
+            Value::Number(n) => n.as_i64(),
+            Value::String(s) => s.trim().parse::<i64>().ok(),
+            _ => None,
+        }?;
+        (number.checked_abs()? <= self.max_integer).then(|| Value::from(number))
+    }
+}
+
+/// A decimal as its text; numbers are written in their shortest exact form
+fn decimal_value(value: &Value) -> Option<Value> {
+    match value {
+        Value::Null => Some(Value::Null),
+        Value::Number(n) if n.as_f64().is_some_and(f64::is_finite) => Some(Value::String(n.to_string())),
+        Value::String(s) if is_decimal(s.trim()) => Some(Value::String(s.trim().to_string())),
+        _ => None,
+    }
+}
+
+/// Whether `text` is a plain decimal number, such as `-12`, `19.99` or `0.5`
+fn is_decimal(text: &str) -> bool {
+    let digits = text.strip_prefix(['-', '+']).unwrap_or(text);
+    let (whole, fraction) = digits.split_once('.').unwrap_or((digits, "0"));
+    !whole.is_empty() && !fraction.is_empty() && (whole.chars().chain(fraction.chars())).all(|c| c.is_ascii_digit())
+}
+
+fn invalid(id: &str, field: &str, value: &Value, expected: &str) -> SearchError {
+    SearchError::invalid_query(format!("Document {}: {} is {}, expected {}", id, field, value, expected))
+}
+
+fn registry() -> MutexGuard<'static, HashMap<String, NumericFields>> {
+    static REGISTRY: OnceLock<Mutex<HashMap<String, NumericFields>>> = OnceLock::new();
+    REGISTRY
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Keep the numeric fields of an index's schema in worker memory, for checking its writes
+pub fn remember(provider: &str, index: &str, schema: &Schema) {
+    let fields = NumericFields::new(provider, schema);
+    if fields.is_empty() {
+        registry().remove(index);
+    } else {
+        registry().insert(index.to_string(), fields);
+    }
+}
+
+/// The numeric fields kept by [`remember`]
+pub fn recall(index: &str) -> Option<NumericFields> {
+    registry().get(index).cloned()
+}
+
+/// Drop the numeric fields of a deleted index
+pub fn forget(index: &str) {
+    registry().remove(index);
+}
+
+/// Names of the decimal fields of `schema`, for the index metadata; `None` without any
+pub fn to_meta(schema: &Schema) -> Option<Value> {
+    let fields: Vec<&str> = schema
+        .fields
+        .iter()
+        .filter(|f| f.field_type == FieldType::Decimal)
+        .map(|f| f.name.as_str())
+        .collect();
+    (!fields.is_empty()).then(|| Value::from(fields))
+}
+
+/// Type the keyword fields named in the index metadata as decimals
+pub fn apply_meta(schema: &mut Schema, meta: Option<&Value>) {
+    let Some(names) = meta.and_then(Value::as_array) else {
+        return;
+    };
+    for field in &mut schema.fields {
+        if names.iter().any(|name| name.as_str() == Some(&field.name)) {
+            field.field_type = FieldType::Decimal;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::types::SchemaField;
+
+    fn field(name: &str, field_type: FieldType) -> SchemaField {
+        SchemaField {
+            name: name.to_string(),
+            field_type,
+            required: false,
+            facet: false,
+            sort: false,
+            index: true,
+            analysis: None,
+            ui: None,
+        }
+    }
+
+    fn schema() -> Schema {
+        Schema {
+            fields: vec![field("id", FieldType::Int64), field("price", FieldType::Decimal), field("name", FieldType::Text)],
+            primary_key: None,
+        }
+    }
+
+    fn checked(provider: &str, content: Value) -> SearchResult<Value> {
+        let content = NumericFields::new(provider, &schema()).apply_content("1", &content.to_string())?;
+        Ok(serde_json::from_str(&content).unwrap())
+    }
+
+    fn refused(provider: &str, content: Value) -> String {
+        match checked(provider, content.clone()) {
+            Err(SearchError::InvalidQuery(message)) => message,
+            other => panic!("expected {} to be refused, got {:?}", content, other),
+        }
+    }
+
+    #[test]
+    fn test_integers_are_accepted_as_numbers_or_strings() {
+        assert_eq!(checked("typesense", json!({ "id": " 9007199254740993 " })).unwrap()["id"], json!(9_007_199_254_740_993_i64));
+        assert_eq!(checked("typesense", json!({ "id": -42 })).unwrap()["id"], json!(-42));
+        assert_eq!(checked("typesense", json!({ "id": i64::MAX })).unwrap()["id"], json!(i64::MAX));
+        assert_eq!(checked("typesense", json!({ "id": null })).unwrap()["id"], Value::Null);
+    }
+
+    #[test]
+    fn test_integers_that_would_lose_precision_are_refused() {
+        for id in [json!(1.5), json!(1.0), json!("1.0"), json!("1e3"), json!(""), json!(true), json!([1]), json!(u64::MAX), json!(i64::MIN)] {
+            refused("typesense", json!({ "id": id }));
+        }
+        assert_eq!(
+            refused("typesense", json!({ "id": 1.5 })),
+            format!("Document 1: id is 1.5, expected a whole number within ±{}", i64::MAX)
+        );
+    }
+
+    #[test]
+    fn test_meilisearch_integers_stay_within_double_precision() {
+        for id in [MEILISEARCH_MAX_INTEGER, -MEILISEARCH_MAX_INTEGER] {
+            assert!(checked("meilisearch", json!({ "id": id })).is_ok(), "{}", id);
+            assert!(checked("meilisearch", json!({ "id": (id + id.signum()).to_string() })).is_err(), "{}", id);
+        }
+        assert!(checked("opensearch", json!({ "id": MEILISEARCH_MAX_INTEGER + 1 })).is_ok());
+    }
+
+    #[test]
+    fn test_decimals_are_written_as_their_text() {
+        for (price, text) in [
+            (json!(19.99), "19.99"),
+            (json!(10), "10"),
+            (json!(0.1), "0.1"),
+            (json!(" -0.10 "), "-0.10"),
+            (json!("+5"), "+5"),
+            (json!("007"), "007"),
+        ] {
+            assert_eq!(checked("elasticsearch", json!({ "price": price })).unwrap()["price"], json!(text), "{}", price);
+        }
+        assert_eq!(checked("elasticsearch", json!({ "price": null })).unwrap()["price"], Value::Null);
+    }
+
+    #[test]
+    fn test_decimals_in_other_notations_are_refused() {
+        for price in [".5", "5.", "1e3", "NaN", "1,5", "", "--1", "1.2.3", "0x10", "٣"] {
+            let message = refused("typesense", json!({ "price": price }));
+            assert!(message.ends_with("expected a decimal number"), "{:?}: {}", price, message);
+        }
+        refused("typesense", json!({ "price": false }));
+    }
+
+    #[test]
+    fn test_other_and_missing_fields_are_left_alone() {
+        let document = json!({ "name": "1.0", "tags": ["x"] });
+        assert_eq!(checked("typesense", document.clone()).unwrap(), document);
+    }
+
+    #[test]
+    fn test_contents_must_be_json_objects() {
+        for content in ["[1]", "not json", "42"] {
+            let fields = NumericFields::new("typesense", &schema());
+            match fields.apply_content("7", content) {
+                Err(SearchError::InvalidQuery(message)) => assert!(message.starts_with("Document 7 is not a JSON object"), "{}", message),
+                other => panic!("expected an invalid query error for {}, got {:?}", content, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_indexes_without_numeric_fields_are_not_remembered() {
+        remember("typesense", "numeric-registry", &schema());
+        let fields = recall("numeric-registry").unwrap();
+        assert_eq!((fields.int64, fields.decimal, fields.max_integer), (vec!["id".to_string()], vec!["price".to_string()], i64::MAX));
+
+        let text_only = Schema { fields: vec![field("name", FieldType::Text)], primary_key: None };
+        remember("typesense", "numeric-registry", &text_only);
+        assert_eq!(recall("numeric-registry"), None, "a schema without numeric fields replaces the old one");
+
+        remember("meilisearch", "numeric-registry", &schema());
+        assert_eq!(recall("numeric-registry").unwrap().max_integer, MEILISEARCH_MAX_INTEGER);
+        forget("numeric-registry");
+        assert_eq!(recall("numeric-registry"), None);
+    }
+
+    #[test]
+    fn test_decimal_fields_round_trip_through_meta() {
+        let meta = to_meta(&schema());
+        assert_eq!(meta, Some(json!(["price"])));
+
+        let mut read = schema();
+        read.fields[1].field_type = FieldType::Keyword;
+        apply_meta(&mut read, meta.as_ref());
+        assert_eq!(read, schema());
+    }
+
+    #[test]
+    fn test_meta_only_names_existing_decimal_fields() {
+        assert_eq!(to_meta(&Schema { fields: vec![field("id", FieldType::Int64)], primary_key: None }), None);
+
+        let keyword = || Schema { fields: vec![field("sku", FieldType::Keyword)], primary_key: None };
+        for meta in [json!(["price", 3]), json!("sku"), json!({ "sku": true })] {
+            let mut read = keyword();
+            apply_meta(&mut read, Some(&meta));
+            assert_eq!(read, keyword(), "{}", meta);
+        }
+        let mut read = keyword();
+        apply_meta(&mut read, None);
+        assert_eq!(read, keyword());
+    }
+}
@@ -0,0 +1,515 @@
+//! Transactional outbox for index writes
+//!
+//! A worker that updates its own state and then writes to the search backend
+//! ends up with the two out of sync whenever the second step fails. Recording
+//! the write in the outbox instead keeps it in worker memory, which Golem
+//! persists together with the rest of the worker's state: the write exists
+//! exactly when the invocation that recorded it succeeded. [`drain`] applies
+//! recorded writes to the provider afterwards, in order, and removes each one
+//! only once the provider has acknowledged it.
+//!
+//! Upserts and deletes by ID are idempotent, so an entry applied twice, as
+//! when the worker restarts between applying and removing it, leaves the index
+//! as a single application would. Callers that may record the same write twice,
+//! such as retried invocations, pass an idempotency key and the duplicate is
+//! dropped.
+//!
+//! A failed entry is retried by the next drain and holds back later entries
+//! for the same document until then, so writes to one document never apply
+//! out of order; other documents proceed. Entries failing with an error that
+//! will not go away on its own, or more than `SEARCH_OUTBOX_MAX_ATTEMPTS`
+//! times, become dead letters: they keep blocking their document until
+//! requeued or discarded.
+
+use std::collections::{HashSet, VecDeque};
+use std::fmt::Display;
+use std::future::Future;
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use chrono::Utc;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use crate::config::env_helpers::get_env_parsed;
+use crate::metrics::ErrorKind;
+use crate::types::Doc;
+
+/// Environment variable holding the attempts an entry gets before it becomes a dead letter
+pub const OUTBOX_MAX_ATTEMPTS_ENV: &str = "SEARCH_OUTBOX_MAX_ATTEMPTS";
+
+pub const DEFAULT_OUTBOX_MAX_ATTEMPTS: u32 = 5;
+
+/// Idempotency keys of applied entries remembered to drop late duplicates
+const REMEMBERED_KEYS: usize = 10_000;
+
+/// A write to apply to an index
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Mutation {
+    Upsert { doc: Doc },
+    Delete { id: String },
+}
+
+impl Mutation {
+    pub fn document_id(&self) -> &str {
+        match self {
+            Mutation::Upsert { doc } => &doc.id,
+            Mutation::Delete { id } => id,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutboxEntry {
+    /// Position in the outbox; entries are applied in sequence order
+    pub sequence: u64,
+    pub index: String,
+    pub mutation: Mutation,
+    pub idempotency_key: Option<String>,
+
+    /// RFC 3339 timestamp
+    pub recorded_at: String,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+
+    /// Given up on; blocks later writes to the same document until requeued or discarded
+    pub dead: bool,
+}
+
+/// Outcome of one drain pass
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DrainReport {
+    pub applied: usize,
+
+    /// Entries that failed and will be retried by the next drain
+    pub retrying: usize,
+
+    /// Entries that failed for good during this pass
+    pub dead_lettered: usize,
+
+    /// Entries held back behind a failed or dead entry for the same document
+    pub blocked: usize,
+
+    /// Whether the pass stopped early because the provider asked to back off
+    pub throttled: bool,
+
+    /// Entries left in the outbox, dead letters included
+    pub remaining: usize,
+}
+
+/// Recorded writes, oldest first
+#[derive(Debug)]
+pub struct Outbox {
+    max_attempts: u32,
+    next_sequence: u64,
+    entries: VecDeque<OutboxEntry>,
+
+    /// Keys of entries still in the outbox or applied recently
+    known_keys: HashSet<String>,
+    applied_keys: VecDeque<String>,
+}
+
+impl Outbox {
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            next_sequence: 1,
+            entries: VecDeque::new(),
+            known_keys: HashSet::new(),
+            applied_keys: VecDeque::new(),
+        }
+    }
+
+    /// Max attempts from `SEARCH_OUTBOX_MAX_ATTEMPTS`
+    pub fn from_env() -> Self {
+        Self::new(
+            get_env_parsed::<u32>(OUTBOX_MAX_ATTEMPTS_ENV)
+                .ok()
+                .flatten()
+                .unwrap_or(DEFAULT_OUTBOX_MAX_ATTEMPTS),
+        )
+    }
+
+    /// Record a write, returning its sequence number, or `None` when `idempotency_key` was seen before
+    pub fn record(&mut self, index: &str, mutation: Mutation, idempotency_key: Option<String>) -> Option<u64> {
+        if let Some(key) = &idempotency_key {
+            if !self.known_keys.insert(key.clone()) {
+                return None;
+            }
+        }
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+        self.entries.push_back(OutboxEntry {
+            sequence,
+            index: index.to_string(),
+            mutation,
+            idempotency_key,
+            recorded_at: Utc::now().to_rfc3339(),
+            attempts: 0,
+            last_error: None,
+            dead: false,
+        });
+        Some(sequence)
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &OutboxEntry> {
+        self.entries.iter()
+    }
+
+    /// Remove an applied entry
+    pub fn complete(&mut self, sequence: u64) {
+        let Some(position) = self.position(sequence) else {
+            return;
+        };
+        if let Some(key) = self.entries.remove(position).and_then(|entry| entry.idempotency_key) {
+            self.applied_keys.push_back(key);
+            if self.applied_keys.len() > REMEMBERED_KEYS {
+                if let Some(forgotten) = self.applied_keys.pop_front() {
+                    self.known_keys.remove(&forgotten);
+                }
+            }
+        }
+    }
+
+    /// Count a failed attempt, returning whether the entry became a dead letter
+    pub fn fail(&mut self, sequence: u64, error: String, retryable: bool) -> bool {
+        let max_attempts = self.max_attempts;
+        let Some(entry) = self.position(sequence).and_then(|position| self.entries.get_mut(position)) else {
+            return false;
+        };
+        entry.attempts += 1;
+        entry.last_error = Some(error);
+        entry.dead = !retryable || entry.attempts >= max_attempts;
+        entry.dead
+    }
+
+    /// Give dead letters a fresh set of attempts, returning how many were requeued
+    pub fn requeue_dead(&mut self) -> usize {
+        let mut requeued = 0;
+        for entry in self.entries.iter_mut().filter(|entry| entry.dead) {
+            entry.dead = false;
+            entry.attempts = 0;
+            requeued += 1;
+        }
+        requeued
+    }
+
+    /// Drop dead letters, returning how many were dropped
+    ///
+    /// Their idempotency keys are forgotten, so the writes can be recorded again.
+    pub fn discard_dead(&mut self) -> usize {
+        let before = self.entries.len();
+        let known_keys = &mut self.known_keys;
+        self.entries.retain(|entry| {
+            if entry.dead {
+                if let Some(key) = &entry.idempotency_key {
+                    known_keys.remove(key);
+                }
+            }
+            !entry.dead
+        });
+        before - self.entries.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn position(&self, sequence: u64) -> Option<usize> {
+        self.entries.iter().position(|entry| entry.sequence == sequence)
+    }
+}
+
+fn global_outbox() -> MutexGuard<'static, Outbox> {
+    static OUTBOX: OnceLock<Mutex<Outbox>> = OnceLock::new();
+    OUTBOX
+        .get_or_init(|| Mutex::new(Outbox::from_env()))
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Record a write in the worker's outbox; see [`Outbox::record`]
+pub fn record(index: &str, mutation: Mutation, idempotency_key: Option<String>) -> Option<u64> {
+    global_outbox().record(index, mutation, idempotency_key)
+}
+
+/// Entries waiting in the worker's outbox, dead letters included
+pub fn entries() -> Vec<OutboxEntry> {
+    global_outbox().entries().cloned().collect()
+}
+
+pub fn requeue_dead() -> usize {
+    global_outbox().requeue_dead()
+}
+
+pub fn discard_dead() -> usize {
+    global_outbox().discard_dead()
+}
+
+/// Apply the worker's outbox in order through `apply`
+///
+/// Entries recorded while the drain runs are left for the next one.
+pub async fn drain<F, Fut, E>(mut apply: F) -> DrainReport
+where
+    F: FnMut(String, Mutation) -> Fut,
+    Fut: Future<Output = Result<(), E>>,
+    E: ErrorKind + Display,
+{
+    let snapshot = entries();
+    let mut blocked_documents = HashSet::new();
+    let mut report = DrainReport::default();
+
+    for entry in snapshot {
+        let document = (entry.index.clone(), entry.mutation.document_id().to_string());
+        if entry.dead || blocked_documents.contains(&document) {
+            if !entry.dead {
+                report.blocked += 1;
+            }
+            blocked_documents.insert(document);
+            continue;
+        }
+
+        match apply(entry.index.clone(), entry.mutation.clone()).await {
+            Ok(()) => {
+                global_outbox().complete(entry.sequence);
+                report.applied += 1;
+            }
+            Err(e) => {
+                let kind = e.error_kind();
+                let retryable = matches!(kind, "timeout" | "rate_limited" | "backpressure" | "internal");
+                if global_outbox().fail(entry.sequence, e.to_string(), retryable) {
+                    warn!("Outbox entry {} for {}/{} is a dead letter: {}", entry.sequence, document.0, document.1, e);
+                    report.dead_lettered += 1;
+                } else {
+                    report.retrying += 1;
+                }
+                blocked_documents.insert(document);
+
+                // The backend asked for a pause; the rest would only fail the same way
+                if matches!(kind, "rate_limited" | "backpressure") {
+                    report.throttled = true;
+                    break;
+                }
+            }
+        }
+    }
+
+    report.remaining = global_outbox().len();
+    info!(
+        "Outbox drain: {} applied, {} retrying, {} dead, {} remaining",
+        report.applied, report.retrying, report.dead_lettered, report.remaining
+    );
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::future::ready;
+    use crate::error::SearchError;
+
+    fn upsert(id: &str) -> Mutation {
+        Mutation::Upsert { doc: Doc { id: id.to_string(), content: "{}".to_string() } }
+    }
+
+    fn delete(id: &str) -> Mutation {
+        Mutation::Delete { id: id.to_string() }
+    }
+
+    fn key(key: &str) -> Option<String> {
+        Some(key.to_string())
+    }
+
+    fn label(index: &str, mutation: &Mutation) -> String {
+        let op = match mutation {
+            Mutation::Upsert { .. } => "upsert",
+            Mutation::Delete { .. } => "delete",
+        };
+        format!("{}/{}/{}", index, mutation.document_id(), op)
+    }
+
+    #[test]
+    fn test_duplicate_keys_are_dropped_while_pending_and_after_applying() {
+        let mut outbox = Outbox::new(3);
+        assert_eq!(outbox.record("books", upsert("1"), key("order-7")), Some(1));
+        assert_eq!(outbox.record("books", upsert("1"), key("order-7")), None);
+
+        outbox.complete(1);
+        assert_eq!(outbox.record("books", upsert("1"), key("order-7")), None);
+        assert!(outbox.is_empty());
+    }
+
+    #[test]
+    fn test_writes_without_a_key_are_never_deduplicated() {
+        let mut outbox = Outbox::new(3);
+        assert_eq!(outbox.record("books", upsert("1"), None), Some(1));
+        assert_eq!(outbox.record("books", upsert("1"), None), Some(2));
+        assert_eq!(outbox.len(), 2);
+    }
+
+    #[test]
+    fn test_only_the_most_recent_applied_keys_are_remembered() {
+        let mut outbox = Outbox::new(3);
+        for n in 0..=REMEMBERED_KEYS {
+            let sequence = outbox.record("books", upsert("1"), Some(format!("k{}", n))).unwrap();
+            outbox.complete(sequence);
+        }
+        assert!(outbox.record("books", upsert("1"), key("k0")).is_some());
+        assert!(outbox.record("books", upsert("1"), key("k1")).is_none());
+        assert!(outbox.record("books", upsert("1"), Some(format!("k{}", REMEMBERED_KEYS))).is_none());
+    }
+
+    #[test]
+    fn test_sequence_numbers_are_not_reused_after_completion() {
+        let mut outbox = Outbox::new(3);
+        let first = outbox.record("books", upsert("1"), None).unwrap();
+        outbox.complete(first);
+        assert_eq!(outbox.record("books", upsert("2"), None), Some(first + 1));
+    }
+
+    #[test]
+    fn test_retryable_failures_become_dead_at_the_attempt_limit() {
+        let mut outbox = Outbox::new(3);
+        let sequence = outbox.record("books", upsert("1"), None).unwrap();
+        assert!(!outbox.fail(sequence, "timeout 1".to_string(), true));
+        assert!(!outbox.fail(sequence, "timeout 2".to_string(), true));
+        assert!(outbox.fail(sequence, "timeout 3".to_string(), true));
+
+        let entry = outbox.entries().next().unwrap();
+        assert_eq!(entry.attempts, 3);
+        assert_eq!(entry.last_error.as_deref(), Some("timeout 3"));
+    }
+
+    #[test]
+    fn test_permanent_failures_are_dead_on_the_first_attempt() {
+        let mut outbox = Outbox::new(5);
+        let sequence = outbox.record("books", delete("1"), None).unwrap();
+        assert!(outbox.fail(sequence, "invalid".to_string(), false));
+        assert_eq!(outbox.entries().next().unwrap().attempts, 1);
+    }
+
+    #[test]
+    fn test_zero_max_attempts_still_allows_one_attempt() {
+        let mut outbox = Outbox::new(0);
+        let sequence = outbox.record("books", upsert("1"), None).unwrap();
+        assert!(outbox.fail(sequence, "timeout".to_string(), true));
+    }
+
+    #[test]
+    fn test_unknown_sequences_are_ignored() {
+        let mut outbox = Outbox::new(3);
+        outbox.record("books", upsert("1"), None);
+        assert!(!outbox.fail(42, "timeout".to_string(), false));
+        outbox.complete(42);
+        assert_eq!(outbox.len(), 1);
+        assert!(outbox.entries().all(|entry| entry.attempts == 0));
+    }
+
+    #[test]
+    fn test_requeue_resets_attempts_and_keeps_the_last_error() {
+        let mut outbox = Outbox::new(1);
+        let dead = outbox.record("books", upsert("1"), None).unwrap();
+        let live = outbox.record("books", upsert("2"), None).unwrap();
+        outbox.fail(dead, "invalid".to_string(), false);
+
+        assert_eq!(outbox.requeue_dead(), 1);
+        assert_eq!(outbox.requeue_dead(), 0);
+        let entry = outbox.entries().find(|entry| entry.sequence == dead).unwrap();
+        assert!(!entry.dead);
+        assert_eq!(entry.attempts, 0);
+        assert_eq!(entry.last_error.as_deref(), Some("invalid"));
+        assert!(outbox.entries().any(|entry| entry.sequence == live));
+    }
+
+    #[test]
+    fn test_discard_forgets_only_the_keys_of_dead_letters() {
+        let mut outbox = Outbox::new(3);
+        let dead = outbox.record("books", upsert("1"), key("dead")).unwrap();
+        outbox.record("books", upsert("2"), key("live"));
+        outbox.fail(dead, "invalid".to_string(), false);
+
+        assert_eq!(outbox.discard_dead(), 1);
+        assert_eq!(outbox.len(), 1);
+        assert!(outbox.record("books", upsert("1"), key("dead")).is_some());
+        assert!(outbox.record("books", upsert("2"), key("live")).is_none());
+    }
+
+    #[test]
+    fn test_mutations_are_tagged_by_operation() {
+        assert_eq!(serde_json::to_value(delete("7")).unwrap(), serde_json::json!({"op": "delete", "id": "7"}));
+        let upsert: Mutation = serde_json::from_value(serde_json::json!({
+            "op": "upsert",
+            "doc": {"id": "7", "content": "{}"}
+        }))
+        .unwrap();
+        assert_eq!(upsert.document_id(), "7");
+    }
+
+    // The only test touching the worker's outbox, so the drains see nothing else
+    #[test]
+    fn test_drain_keeps_per_document_order_across_failures() {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+
+        record("outbox-books", upsert("1"), None);
+        record("outbox-books", delete("1"), None);
+        record("outbox-books", upsert("2"), None);
+        record("outbox-films", upsert("1"), None);
+        record("outbox-books", upsert("3"), None);
+        record("outbox-books", delete("3"), None);
+
+        // A timeout holds back the delete of books/1, but not films/1 with the same ID
+        let mut applied = Vec::new();
+        let report = runtime.block_on(drain(|index, mutation| {
+            let result = match mutation.document_id() {
+                "1" if index == "outbox-books" => Err(SearchError::Timeout { remaining_ms: None }),
+                "3" => Err(SearchError::InvalidQuery("bad doc".to_string())),
+                _ => Ok(()),
+            };
+            applied.push(label(&index, &mutation));
+            ready(result)
+        }));
+        assert_eq!(
+            applied,
+            ["outbox-books/1/upsert", "outbox-books/2/upsert", "outbox-films/1/upsert", "outbox-books/3/upsert"]
+        );
+        assert_eq!(
+            report,
+            DrainReport { applied: 2, retrying: 1, dead_lettered: 1, blocked: 2, throttled: false, remaining: 4 }
+        );
+
+        // The retry applies books/1 in order; the dead letter still blocks books/3
+        let mut applied = Vec::new();
+        let report = runtime.block_on(drain(|index, mutation| {
+            applied.push(label(&index, &mutation));
+            ready(Ok::<(), SearchError>(()))
+        }));
+        assert_eq!(applied, ["outbox-books/1/upsert", "outbox-books/1/delete"]);
+        assert_eq!((report.applied, report.blocked, report.remaining), (2, 1, 2));
+        assert_eq!(entries()[0].last_error.as_deref(), Some("Invalid query: bad doc"));
+
+        // Throttling stops the pass at the first entry
+        assert_eq!(requeue_dead(), 1);
+        let mut calls = 0;
+        let report = runtime.block_on(drain(|_, _| {
+            calls += 1;
+            ready(Err(SearchError::RateLimited { retry_after_ms: Some(100) }))
+        }));
+        assert_eq!(calls, 1);
+        assert!(report.throttled);
+        assert_eq!((report.retrying, report.remaining), (1, 2));
+
+        // Writes recorded during a drain wait for the next one
+        let report = runtime.block_on(drain(|_, _| {
+            record("outbox-books", upsert("9"), None);
+            ready(Ok::<(), SearchError>(()))
+        }));
+        assert_eq!((report.applied, report.remaining), (2, 2));
+        assert!(entries().iter().all(|entry| entry.mutation.document_id() == "9"));
+
+        runtime.block_on(drain(|_, _| ready(Ok::<(), SearchError>(()))));
+        assert!(entries().is_empty());
+        assert_eq!(discard_dead(), 0);
+    }
+}
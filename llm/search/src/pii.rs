@@ -0,0 +1,257 @@
+//! Personal data detection
+//!
+//! Finds email addresses, phone numbers and payment card numbers in text, for
+//! the ingest pipeline's `pii` processor. Detection is by pattern, tuned to
+//! keep false positives on ordinary document text low:
+//!
+//! - A card number is 13 to 19 digits, optionally grouped by spaces or
+//!   dashes, that passes the Luhn check.
+//! - A phone number has 9 to 15 digits, optionally with a `+` country code,
+//!   a parenthesized area code and space, dot or dash separators. Shorter
+//!   runs are left alone, so dates and most counts are not mistaken for one.
+//!
+//! Matched text is replaced by a placeholder naming its kind, such as
+//! `[email]`.
+
+use std::borrow::Cow;
+use std::sync::OnceLock;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PiiKind {
+    Email,
+    Phone,
+    CreditCard,
+}
+
+impl PiiKind {
+    pub const ALL: [PiiKind; 3] = [PiiKind::Email, PiiKind::Phone, PiiKind::CreditCard];
+
+    /// Text that replaces a masked match
+    pub fn placeholder(self) -> &'static str {
+        match self {
+            PiiKind::Email => "[email]",
+            PiiKind::Phone => "[phone]",
+            PiiKind::CreditCard => "[credit-card]",
+        }
+    }
+
+    fn regex(self) -> &'static Regex {
+        static EMAIL: OnceLock<Regex> = OnceLock::new();
+        static PHONE: OnceLock<Regex> = OnceLock::new();
+        static CARD: OnceLock<Regex> = OnceLock::new();
+        match self {
+            PiiKind::Email => EMAIL.get_or_init(|| {
+                Regex::new(r"(?i)\b[a-z0-9._%+-]+@[a-z0-9-]+(?:\.[a-z0-9-]+)*\.[a-z]{2,}\b").unwrap()
+            }),
+            PiiKind::Phone => PHONE.get_or_init(|| {
+                Regex::new(r"(?:\+\d{1,3}[ .-]?)?(?:\(\d{1,4}\)[ .-]?)?\d{2,5}(?:[ .-]?\d{2,5}){1,5}").unwrap()
+            }),
+            PiiKind::CreditCard => CARD.get_or_init(|| Regex::new(r"\b\d(?:[ -]?\d){12,18}\b").unwrap()),
+        }
+    }
+
+    /// Whether a pattern match really is this kind
+    fn confirms(self, text: &str) -> bool {
+        let digits: Vec<u32> = text.chars().filter_map(|c| c.to_digit(10)).collect();
+        match self {
+            PiiKind::Email => true,
+            PiiKind::Phone => (9..=15).contains(&digits.len()),
+            PiiKind::CreditCard => luhn(&digits),
+        }
+    }
+}
+
+/// Action on fields holding personal data
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PiiAction {
+    /// Replace each match with its placeholder
+    #[default]
+    Mask,
+
+    /// Remove the whole field
+    Drop,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PiiMatch {
+    pub kind: PiiKind,
+
+    /// Byte range of the match
+    pub start: usize,
+    pub end: usize,
+}
+
+fn luhn(digits: &[u32]) -> bool {
+    if !(13..=19).contains(&digits.len()) {
+        return false;
+    }
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(position, &digit)| match position % 2 {
+            0 => digit,
+            _ if digit > 4 => digit * 2 - 9,
+            _ => digit * 2,
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+/// Matches of `kinds` in `text`, in order and not overlapping
+///
+/// Card numbers are found first, then emails, then phone numbers, so the
+/// digits of a card or an email address are not reported again as a phone.
+pub fn detect(text: &str, kinds: &[PiiKind]) -> Vec<PiiMatch> {
+    let mut matches: Vec<PiiMatch> = Vec::new();
+    for kind in [PiiKind::CreditCard, PiiKind::Email, PiiKind::Phone] {
+        if !kinds.contains(&kind) {
+            continue;
+        }
+        for found in kind.regex().find_iter(text) {
+            let (start, end) = (found.start(), found.end());
+            // A digit run glued to letters, like an order number, is not a phone
+            let glued = text[..start].chars().next_back().is_some_and(char::is_alphanumeric)
+                || text[end..].chars().next().is_some_and(char::is_alphanumeric);
+            let overlaps = matches.iter().any(|m| start < m.end && m.start < end);
+            if !overlaps && !glued && kind.confirms(found.as_str()) {
+                matches.push(PiiMatch { kind, start, end });
+            }
+        }
+    }
+    matches.sort_by_key(|m| m.start);
+    matches
+}
+
+pub fn contains_pii(text: &str, kinds: &[PiiKind]) -> bool {
+    !detect(text, kinds).is_empty()
+}
+
+/// `text` with every match of `kinds` replaced by its placeholder
+pub fn mask<'a>(text: &'a str, kinds: &[PiiKind]) -> Cow<'a, str> {
+    let matches = detect(text, kinds);
+    if matches.is_empty() {
+        return Cow::Borrowed(text);
+    }
+    let mut masked = String::with_capacity(text.len());
+    let mut last = 0;
+    for m in matches {
+        masked.push_str(&text[last..m.start]);
+        masked.push_str(m.kind.placeholder());
+        last = m.end;
+    }
+    masked.push_str(&text[last..]);
+    Cow::Owned(masked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(text: &str, kinds: &[PiiKind]) -> Vec<PiiKind> {
+        detect(text, kinds).into_iter().map(|m| m.kind).collect()
+    }
+
+    #[test]
+    fn test_cards_may_be_grouped_by_spaces_or_dashes() {
+        for text in ["4111111111111111", "4111 1111 1111 1111", "4111-1111-1111-1111", "4111 1111-1111 1111"] {
+            assert_eq!(kinds(text, &PiiKind::ALL), [PiiKind::CreditCard], "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_card_length_is_thirteen_to_nineteen_digits() {
+        assert_eq!(kinds("4222222222222", &[PiiKind::CreditCard]), [PiiKind::CreditCard]);
+        // Luhn-valid, but one digit short and one too many
+        assert!(kinds("422222222224", &[PiiKind::CreditCard]).is_empty());
+        assert!(kinds("42222222222222222224", &[PiiKind::CreditCard]).is_empty());
+    }
+
+    #[test]
+    fn test_card_numbers_failing_luhn_are_not_reported_as_phones() {
+        assert!(!contains_pii("Tracking 4111 1111 1111 1112", &PiiKind::ALL));
+    }
+
+    #[test]
+    fn test_phone_numbers_have_nine_to_fifteen_digits() {
+        assert!(!contains_pii("Call 1234 5678", &[PiiKind::Phone]));
+        assert!(contains_pii("Call 123 456 789", &[PiiKind::Phone]));
+        assert!(contains_pii("Call +1 (234) 567.890.12345", &[PiiKind::Phone]));
+        assert!(!contains_pii("Call +12 (345) 678.901.23456", &[PiiKind::Phone]));
+    }
+
+    #[test]
+    fn test_digit_runs_glued_to_letters_are_not_phones() {
+        assert!(!contains_pii("Order A123456789 shipped", &PiiKind::ALL));
+        assert!(!contains_pii("Order 123456789B shipped", &PiiKind::ALL));
+        assert!(contains_pii("Order (123456789) shipped", &PiiKind::ALL));
+    }
+
+    #[test]
+    fn test_dates_isbns_and_prices_are_kept() {
+        for text in ["Published 2024-01-15", "ISBN 978-0-441-17271-9", "Price: 1,299.00", "Rated 4.5 by 1200 readers"] {
+            assert!(!contains_pii(text, &PiiKind::ALL), "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_emails_need_a_top_level_domain() {
+        assert!(contains_pii("ADA.Lovelace+books@mail.Example.CO.uk", &[PiiKind::Email]));
+        assert!(!contains_pii("root@localhost", &[PiiKind::Email]));
+        assert!(!contains_pii("@example.com", &[PiiKind::Email]));
+    }
+
+    #[test]
+    fn test_digits_of_an_email_are_not_reported_again_as_a_phone() {
+        let text = "555.123.4567@example.com";
+        assert_eq!(kinds(text, &PiiKind::ALL), [PiiKind::Email]);
+        // Without emails to claim them, the digits are a phone
+        assert_eq!(kinds(text, &[PiiKind::Phone]), [PiiKind::Phone]);
+    }
+
+    #[test]
+    fn test_matches_are_sorted_byte_ranges() {
+        let text = "é 555-123-4567 ada@example.org";
+        let matches = detect(text, &PiiKind::ALL);
+        assert_eq!(
+            matches,
+            [
+                PiiMatch { kind: PiiKind::Phone, start: 3, end: 15 },
+                PiiMatch { kind: PiiKind::Email, start: 16, end: 31 },
+            ]
+        );
+        assert_eq!(&text[matches[1].start..matches[1].end], "ada@example.org");
+    }
+
+    #[test]
+    fn test_no_kinds_detect_nothing() {
+        assert!(detect("ada@example.org 4111 1111 1111 1111", &[]).is_empty());
+    }
+
+    #[test]
+    fn test_mask_replaces_only_the_requested_kinds() {
+        let text = "Mail ada@example.org, call +44 (20) 7946 0958; card 4111 1111 1111 1111.";
+        assert_eq!(mask(text, &PiiKind::ALL), "Mail [email], call [phone]; card [credit-card].");
+        assert_eq!(
+            mask(text, &[PiiKind::CreditCard]),
+            "Mail ada@example.org, call +44 (20) 7946 0958; card [credit-card]."
+        );
+    }
+
+    #[test]
+    fn test_mask_borrows_text_without_matches() {
+        assert!(matches!(mask("Dune, by Frank Herbert", &PiiKind::ALL), Cow::Borrowed(_)));
+        assert!(matches!(mask("", &PiiKind::ALL), Cow::Borrowed("")));
+    }
+
+    #[test]
+    fn test_kinds_and_actions_use_snake_case_names() {
+        assert_eq!(serde_json::to_value(PiiKind::CreditCard).unwrap(), "credit_card");
+        assert_eq!(serde_json::from_value::<PiiAction>(serde_json::json!("drop")).unwrap(), PiiAction::Drop);
+        assert_eq!(PiiAction::default(), PiiAction::Mask);
+    }
+}
@@ -0,0 +1,445 @@
+//! Promoting a staging index behind a live alias
+//!
+//! Blue/green reindexing builds a new index next to the live one and then
+//! points the alias readers search through at it. [`promote`] makes that last
+//! step safe: the staging index has to hold about as many documents as the
+//! alias does now, within [`PromotionChecks::doc_count_tolerance`], and every
+//! sample query has to run on it and find documents wherever the live index
+//! finds some. Only then is the alias swapped, in one atomic update that
+//! removes the indexes behind it and adds the staging index.
+//!
+//! After the swap the same checks run again through the alias. Should they
+//! fail, the alias is swapped back to the indexes it covered before and the
+//! report says the promotion was rolled back. An alias that does not exist yet
+//! is created, with nothing to compare the staging index to. Elasticsearch and
+//! OpenSearch provide the alias updates.
+
+use std::future::Future;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use crate::error::{SearchError, SearchResult};
+use crate::telemetry::SpanOutput;
+use crate::types::{SearchQuery, SearchResults};
+
+/// Default largest difference in document count, as a share of the live count
+pub const DEFAULT_DOC_COUNT_TOLERANCE: f64 = 0.05;
+
+/// What the staging index has to pass before and after the swap
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PromotionChecks {
+    /// Largest difference in document count from the live index, as a share of the live count
+    pub doc_count_tolerance: f64,
+
+    /// Query texts that have to succeed, and find documents wherever the live index does
+    pub sample_queries: Vec<String>,
+}
+
+impl Default for PromotionChecks {
+    fn default() -> Self {
+        Self { doc_count_tolerance: DEFAULT_DOC_COUNT_TOLERANCE, sample_queries: Vec::new() }
+    }
+}
+
+impl PromotionChecks {
+    /// Parse a JSON object of checks; missing or blank input uses the defaults
+    pub fn from_json(json: Option<&str>) -> SearchResult<Self> {
+        let checks: Self = match json.map(str::trim).filter(|j| !j.is_empty()) {
+            Some(json) => serde_json::from_str(json)
+                .map_err(|e| SearchError::invalid_query(format!("Invalid promotion checks: {}", e)))?,
+            None => Self::default(),
+        };
+        checks.validate()?;
+        Ok(checks)
+    }
+
+    pub fn validate(&self) -> SearchResult<()> {
+        if !self.doc_count_tolerance.is_finite() || self.doc_count_tolerance < 0.0 {
+            return Err(SearchError::invalid_query("doc_count_tolerance must be a share of at least 0"));
+        }
+        Ok(())
+    }
+}
+
+/// Outcome of one check
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PromotionCheck {
+    /// `doc_count` or `query:<text>`, prefixed with `alias:` for the checks after the swap
+    pub name: String,
+    pub passed: bool,
+    pub detail: String,
+}
+
+/// Outcome of a promotion
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PromotionReport {
+    pub alias: String,
+    pub staging_index: String,
+
+    /// Indexes behind the alias before the promotion
+    pub previous_indexes: Vec<String>,
+
+    /// Whether the alias points at the staging index now
+    pub promoted: bool,
+
+    /// Whether the alias was swapped and then back after failing the checks
+    pub rolled_back: bool,
+
+    pub checks: Vec<PromotionCheck>,
+}
+
+impl PromotionReport {
+    /// Whether every check run so far passed
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|check| check.passed)
+    }
+}
+
+impl SpanOutput for PromotionReport {}
+
+/// Search running a sample query, for its hit count only
+pub fn sample_query(text: &str) -> SearchQuery {
+    SearchQuery {
+        q: Some(text.to_string()),
+        filters: Vec::new(),
+        sort: Vec::new(),
+        facets: Vec::new(),
+        page: None,
+        per_page: Some(1),
+        offset: None,
+        highlight: None,
+        config: None,
+    }
+}
+
+/// Documents matched, or the hits returned when the total is unknown
+pub fn hit_count(results: &SearchResults) -> u64 {
+    results.total.map_or(results.hits.len() as u64, u64::from)
+}
+
+/// Indexes in a `GET _alias/<alias>` response
+pub fn alias_indexes(aliases: &Value) -> Vec<String> {
+    let mut indexes: Vec<String> = aliases.as_object().map(|indexes| indexes.keys().cloned().collect()).unwrap_or_default();
+    indexes.sort();
+    indexes
+}
+
+/// `POST _aliases` body moving `alias` from the `from` indexes to the `to` ones
+pub fn swap_actions(alias: &str, from: &[String], to: &[String]) -> Value {
+    let removes = from.iter().map(|index| json!({ "remove": { "index": index, "alias": alias } }));
+    let adds = to.iter().map(|index| json!({ "add": { "index": index, "alias": alias } }));
+    json!({ "actions": removes.chain(adds).collect::<Vec<_>>() })
+}
+
+/// Check of the staging document count against the live one
+pub fn doc_count_check(name: &str, staging: u64, live: Option<u64>, tolerance: f64) -> PromotionCheck {
+    let Some(live) = live else {
+        return PromotionCheck {
+            name: name.to_string(),
+            passed: true,
+            detail: format!("{} documents, nothing live to compare", staging),
+        };
+    };
+    let difference = staging.abs_diff(live) as f64;
+    let allowed = live as f64 * tolerance;
+    PromotionCheck {
+        name: name.to_string(),
+        passed: difference <= allowed,
+        detail: format!("{} documents against {} live, {} apart with {:.0} allowed", staging, live, difference, allowed),
+    }
+}
+
+/// Check of a sample query, which has to find documents when the live index does
+pub fn sample_check(name: &str, staging: &SearchResult<u64>, live: Option<u64>) -> PromotionCheck {
+    let (passed, detail) = match (staging, live) {
+        (Err(e), _) => (false, format!("failed: {}", e)),
+        (Ok(0), Some(live)) if live > 0 => (false, format!("found nothing, live found {}", live)),
+        (Ok(hits), Some(live)) => (true, format!("found {}, live found {}", hits, live)),
+        (Ok(hits), None) => (true, format!("found {}", hits)),
+    };
+    PromotionCheck { name: name.to_string(), passed, detail }
+}
+
+/// Check `staging` for `alias`, swap the alias over to it, and verify the alias, swapping back on failure
+///
+/// `previous` are the indexes behind the alias now. `count` counts the
+/// documents of an index or alias, `hits` counts the hits of a sample query on
+/// one and `update` applies a `POST _aliases` body. Checks that fail before
+/// the swap leave the alias alone; errors reaching the backend are returned.
+pub async fn promote<C, CFut, H, HFut, U, UFut>(
+    alias: &str,
+    staging: &str,
+    previous: Vec<String>,
+    checks: &PromotionChecks,
+    count: C,
+    hits: H,
+    update: U,
+) -> SearchResult<PromotionReport>
+where
+    C: Fn(String) -> CFut,
+    CFut: Future<Output = SearchResult<u64>>,
+    H: Fn(String, String) -> HFut,
+    HFut: Future<Output = SearchResult<u64>>,
+    U: Fn(Value) -> UFut,
+    UFut: Future<Output = SearchResult<()>>,
+{
+    checks.validate()?;
+    if previous.iter().any(|index| index == staging) {
+        return Err(SearchError::invalid_query(format!("{} is already behind alias {}", staging, alias)));
+    }
+    let mut report = PromotionReport {
+        alias: alias.to_string(),
+        staging_index: staging.to_string(),
+        previous_indexes: previous.clone(),
+        promoted: false,
+        rolled_back: false,
+        checks: Vec::new(),
+    };
+    let live = !previous.is_empty();
+
+    let staging_count = count(staging.to_string()).await?;
+    let live_count = if live { Some(count(alias.to_string()).await?) } else { None };
+    report.checks.push(doc_count_check("doc_count", staging_count, live_count, checks.doc_count_tolerance));
+    let mut staging_hits = Vec::with_capacity(checks.sample_queries.len());
+    for text in &checks.sample_queries {
+        let live_hits = if live { hits(alias.to_string(), text.clone()).await.ok() } else { None };
+        let found = hits(staging.to_string(), text.clone()).await;
+        report.checks.push(sample_check(&format!("query:{}", text), &found, live_hits));
+        staging_hits.push(found.ok());
+    }
+    if !report.passed() {
+        warn!("Staging index {} failed its checks, alias {} left alone", staging, alias);
+        return Ok(report);
+    }
+
+    let to = [staging.to_string()];
+    update(swap_actions(alias, &previous, &to)).await?;
+    report.promoted = true;
+
+    let alias_count = count(alias.to_string()).await;
+    report.checks.push(match alias_count {
+        Ok(alias_count) => doc_count_check("alias:doc_count", alias_count, Some(staging_count), checks.doc_count_tolerance),
+        Err(e) => PromotionCheck { name: "alias:doc_count".to_string(), passed: false, detail: format!("failed: {}", e) },
+    });
+    for (text, staging_hits) in checks.sample_queries.iter().zip(staging_hits) {
+        let found = hits(alias.to_string(), text.clone()).await;
+        report.checks.push(sample_check(&format!("alias:query:{}", text), &found, staging_hits));
+    }
+    if report.passed() {
+        info!("Promoted {} behind alias {}", staging, alias);
+        return Ok(report);
+    }
+
+    warn!("Alias {} failed its checks on {}, rolling back", alias, staging);
+    update(swap_actions(alias, &to, &previous)).await.map_err(|e| {
+        SearchError::Internal(format!("Rolling alias {} back failed, it still points at {}: {}", alias, staging, e))
+    })?;
+    report.promoted = false;
+    report.rolled_back = true;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+    use std::future::ready;
+
+    /// Indexes with fixed counts behind a `products` alias; queries through the alias find `alias_hits`
+    struct Backend {
+        counts: HashMap<&'static str, u64>,
+        staging_hits: u64,
+        alias_hits: u64,
+        fail_second_update: bool,
+        behind: RefCell<Vec<String>>,
+        updates: RefCell<Vec<Value>>,
+        calls: RefCell<usize>,
+    }
+
+    impl Backend {
+        fn new(previous: &[&str], counts: &[(&'static str, u64)]) -> Self {
+            Self {
+                counts: counts.iter().copied().collect(),
+                staging_hits: 5,
+                alias_hits: 5,
+                fail_second_update: false,
+                behind: RefCell::new(previous.iter().map(|index| index.to_string()).collect()),
+                updates: RefCell::new(Vec::new()),
+                calls: RefCell::new(0),
+            }
+        }
+
+        fn promote(&self, checks: &PromotionChecks) -> SearchResult<PromotionReport> {
+            let count = |index: String| {
+                *self.calls.borrow_mut() += 1;
+                let target = if index == "products" { self.behind.borrow().first().cloned() } else { Some(index.clone()) };
+                let found = target.and_then(|target| self.counts.get(target.as_str()).copied());
+                ready(found.ok_or(SearchError::IndexNotFound(index)))
+            };
+            let hits = |index: String, _text: String| {
+                *self.calls.borrow_mut() += 1;
+                ready(Ok(if index == "products" { self.alias_hits } else { self.staging_hits }))
+            };
+            let update = |body: Value| {
+                self.updates.borrow_mut().push(body.clone());
+                if self.fail_second_update && self.updates.borrow().len() == 2 {
+                    return ready(Err(SearchError::Internal("cluster unavailable".to_string())));
+                }
+                let added = body["actions"].as_array().unwrap().iter().filter_map(|action| action.pointer("/add/index"));
+                *self.behind.borrow_mut() = added.map(|index| index.as_str().unwrap().to_string()).collect();
+                ready(Ok(()))
+            };
+            let previous = self.behind.borrow().clone();
+            let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+            runtime.block_on(promote("products", "products-v2", previous, checks, count, hits, update))
+        }
+    }
+
+    fn with_queries(queries: &[&str]) -> PromotionChecks {
+        PromotionChecks { sample_queries: queries.iter().map(|q| q.to_string()).collect(), ..PromotionChecks::default() }
+    }
+
+    fn names(report: &PromotionReport) -> Vec<&str> {
+        report.checks.iter().map(|check| check.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_doc_count_tolerance_is_inclusive_in_both_directions() {
+        for (staging, passed) in [(949, false), (950, true), (1050, true), (1051, false)] {
+            assert_eq!(doc_count_check("doc_count", staging, Some(1000), 0.05).passed, passed, "{}", staging);
+        }
+    }
+
+    #[test]
+    fn test_zero_tolerance_needs_equal_counts() {
+        assert!(doc_count_check("doc_count", 1000, Some(1000), 0.0).passed);
+        assert!(!doc_count_check("doc_count", 1001, Some(1000), 0.0).passed);
+    }
+
+    #[test]
+    fn test_an_empty_live_index_only_accepts_an_empty_staging_index() {
+        assert!(doc_count_check("doc_count", 0, Some(0), 0.05).passed);
+        assert!(!doc_count_check("doc_count", 5, Some(0), 0.05).passed);
+        assert!(doc_count_check("doc_count", 5, None, 0.05).passed);
+    }
+
+    #[test]
+    fn test_sample_queries_only_fail_on_nothing_found_where_live_finds_something() {
+        assert!(sample_check("q", &Ok(1), Some(100)).passed);
+        assert!(sample_check("q", &Ok(0), Some(0)).passed);
+        assert!(sample_check("q", &Ok(0), None).passed);
+        assert!(!sample_check("q", &Ok(0), Some(1)).passed);
+
+        let failed = sample_check("q", &Err(SearchError::Unsupported), Some(0));
+        assert!(!failed.passed);
+        assert!(failed.detail.starts_with("failed: "), "{}", failed.detail);
+    }
+
+    #[test]
+    fn test_checks_json_defaults_and_validation() {
+        assert_eq!(PromotionChecks::from_json(Some("  ")).unwrap(), PromotionChecks::default());
+        let checks = PromotionChecks::from_json(Some(r#"{"sample_queries": ["shoes"]}"#)).unwrap();
+        assert_eq!(checks.doc_count_tolerance, DEFAULT_DOC_COUNT_TOLERANCE);
+        assert!(PromotionChecks::from_json(Some(r#"{"doc_count_tolerance": -0.01}"#)).is_err());
+        assert!(PromotionChecks::from_json(Some(r#"{"tolerance": 0.1}"#)).is_err());
+        assert!(PromotionChecks { doc_count_tolerance: f64::NAN, ..PromotionChecks::default() }.validate().is_err());
+    }
+
+    #[test]
+    fn test_alias_indexes_are_sorted_and_tolerate_other_responses() {
+        let aliases = json!({ "products-v2": { "aliases": {} }, "products-v1": { "aliases": {} } });
+        assert_eq!(alias_indexes(&aliases), ["products-v1", "products-v2"]);
+        assert!(alias_indexes(&json!([])).is_empty());
+    }
+
+    #[test]
+    fn test_hit_count_falls_back_to_returned_hits() {
+        let hit = json!({ "id": "1", "score": null, "content": null, "highlights": null });
+        let mut results: SearchResults = serde_json::from_value(json!({
+            "total": 42, "page": null, "per_page": null, "hits": [hit.clone(), hit], "facets": null, "took_ms": null
+        }))
+        .unwrap();
+        assert_eq!(hit_count(&results), 42);
+        results.total = None;
+        assert_eq!(hit_count(&results), 2);
+    }
+
+    #[test]
+    fn test_swapping_from_nothing_only_adds() {
+        assert_eq!(
+            swap_actions("products", &[], &["products-v2".to_string()]),
+            json!({ "actions": [{ "add": { "index": "products-v2", "alias": "products" } }] })
+        );
+    }
+
+    #[test]
+    fn test_an_index_already_behind_the_alias_is_refused_without_calls() {
+        let backend = Backend::new(&["products-v2"], &[("products-v2", 10)]);
+        assert!(matches!(backend.promote(&PromotionChecks::default()), Err(SearchError::InvalidQuery(_))));
+        assert_eq!(*backend.calls.borrow(), 0);
+        assert!(backend.updates.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_failed_checks_before_the_swap_leave_the_alias_alone() {
+        let mut backend = Backend::new(&["products-v1"], &[("products-v1", 1000), ("products-v2", 1000)]);
+        backend.staging_hits = 0;
+        let report = backend.promote(&with_queries(&["shoes", "boots"])).unwrap();
+        assert!(!report.promoted && !report.rolled_back);
+        assert_eq!(names(&report), ["doc_count", "query:shoes", "query:boots"]);
+        assert!(backend.updates.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_count_errors_before_the_swap_are_returned() {
+        let backend = Backend::new(&["products-v1"], &[("products-v1", 1000)]);
+        assert!(matches!(backend.promote(&PromotionChecks::default()), Err(SearchError::IndexNotFound(_))));
+        assert!(backend.updates.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_a_missing_alias_is_created_without_comparison() {
+        let backend = Backend::new(&[], &[("products-v2", 10)]);
+        let report = backend.promote(&with_queries(&["shoes"])).unwrap();
+        assert!(report.promoted);
+        assert_eq!(names(&report), ["doc_count", "query:shoes", "alias:doc_count", "alias:query:shoes"]);
+        assert_eq!(*backend.behind.borrow(), ["products-v2"]);
+        assert_eq!(backend.updates.borrow()[0]["actions"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_promotion_replaces_every_index_behind_the_alias() {
+        let backend = Backend::new(&["products-a", "products-b"], &[("products-a", 100), ("products-v2", 100)]);
+        let report = backend.promote(&PromotionChecks::default()).unwrap();
+        assert!(report.promoted && report.passed());
+        assert_eq!(report.previous_indexes, ["products-a", "products-b"]);
+        assert_eq!(*backend.behind.borrow(), ["products-v2"]);
+        assert_eq!(backend.updates.borrow()[0]["actions"].as_array().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_failed_alias_checks_swap_back_to_the_previous_indexes() {
+        let mut backend = Backend::new(&["products-v1"], &[("products-v1", 1000), ("products-v2", 990)]);
+        // The staging index answers directly, but not yet through the alias
+        backend.alias_hits = 0;
+        let report = backend.promote(&with_queries(&["shoes"])).unwrap();
+        assert!(!report.promoted && report.rolled_back);
+        assert_eq!(backend.updates.borrow().len(), 2);
+        assert_eq!(*backend.behind.borrow(), ["products-v1"]);
+        let failed: Vec<&str> = report.checks.iter().filter(|check| !check.passed).map(|check| check.name.as_str()).collect();
+        assert_eq!(failed, ["alias:query:shoes"]);
+    }
+
+    #[test]
+    fn test_a_failed_rollback_says_where_the_alias_points() {
+        let mut backend = Backend::new(&["products-v1"], &[("products-v1", 1000), ("products-v2", 1000)]);
+        backend.alias_hits = 0;
+        backend.fail_second_update = true;
+        let Err(SearchError::Internal(message)) = backend.promote(&with_queries(&["shoes"])) else {
+            panic!("expected the rollback to fail");
+        };
+        assert!(message.contains("still points at products-v2"), "{}", message);
+    }
+}
@@ -0,0 +1,161 @@
+//! Validated passthrough of provider-specific query parameters
+//!
+//! `SearchConfig::provider_params` lets callers reach provider knobs that have no
+//! portable equivalent. Each provider declares an allowlist of top-level keys it
+//! accepts; anything else is rejected with `InvalidQuery` instead of being sent
+//! to the backend, so the passthrough cannot clobber fields the provider builds
+//! itself (query, pagination, filters).
+
+use serde_json::{Map, Value};
+use crate::error::{SearchError, SearchResult};
+
+/// Parse and validate a provider params JSON object against an allowlist
+///
+/// Returns an empty map when no params were supplied.
+pub fn parse_provider_params(raw: Option<&str>, allowed: &[&str]) -> SearchResult<Map<String, Value>> {
+    let Some(raw) = raw.map(str::trim).filter(|r| !r.is_empty()) else {
+        return Ok(Map::new());
+    };
+
+    let params = match serde_json::from_str::<Value>(raw) {
+        Ok(Value::Object(map)) => map,
+        Ok(_) => return Err(SearchError::invalid_query("provider_params must be a JSON object")),
+        Err(e) => return Err(SearchError::invalid_query(format!("Invalid provider_params JSON: {}", e))),
+    };
+
+    let rejected: Vec<&str> = params
+        .keys()
+        .map(|k| k.as_str())
+        .filter(|k| !allowed.contains(k))
+        .collect();
+
+    if !rejected.is_empty() {
+        return Err(SearchError::invalid_query(format!(
+            "Unsupported provider_params: {} (allowed: {})",
+            rejected.join(", "),
+            allowed.join(", ")
+        )));
+    }
+
+    Ok(params)
+}
+
+/// Merge validated params into a JSON request body, overriding existing keys
+pub fn merge_into_body(body: &mut Value, params: &Map<String, Value>) {
+    if let Value::Object(target) = body {
+        for (key, value) in params {
+            target.insert(key.clone(), value.clone());
+        }
+    }
+}
+
+/// Render validated params as URL query pairs for GET-style APIs
+///
+/// Strings are passed verbatim, other values use their JSON representation.
+pub fn to_query_pairs(params: &Map<String, Value>) -> Vec<(String, String)> {
+    params
+        .iter()
+        .map(|(key, value)| {
+            let rendered = match value {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            (key.clone(), rendered)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const ALLOWED: &[&str] = &["track_total_hits", "min_score"];
+
+    fn parse(raw: &str) -> SearchResult<Map<String, Value>> {
+        parse_provider_params(Some(raw), ALLOWED)
+    }
+
+    fn message(result: SearchResult<Map<String, Value>>) -> String {
+        match result {
+            Err(SearchError::InvalidQuery(message)) => message,
+            other => panic!("expected InvalidQuery, got {:?}", other),
+        }
+    }
+
+    fn params(value: Value) -> Map<String, Value> {
+        value.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn test_missing_or_blank_params_are_empty() {
+        assert!(parse_provider_params(None, ALLOWED).unwrap().is_empty());
+        assert!(parse(" \n\t").unwrap().is_empty());
+        assert!(parse("{}").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_every_rejected_key_is_named_with_the_allowlist() {
+        assert_eq!(
+            message(parse(r#"{"size": 5, "min_score": 1, "query": {}}"#)),
+            "Unsupported provider_params: query, size (allowed: track_total_hits, min_score)"
+        );
+    }
+
+    #[test]
+    fn test_an_empty_allowlist_rejects_any_key() {
+        assert!(parse_provider_params(Some(r#"{"min_score": 1}"#), &[]).is_err());
+        assert!(parse_provider_params(Some("{}"), &[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_keys_are_matched_exactly() {
+        assert!(parse(r#"{"Min_Score": 1}"#).is_err());
+        assert!(parse(r#"{" min_score": 1}"#).is_err());
+    }
+
+    #[test]
+    fn test_only_objects_are_accepted() {
+        for raw in ["[1, 2]", r#""min_score""#, "null", "3"] {
+            assert_eq!(message(parse(raw)), "provider_params must be a JSON object", "{}", raw);
+        }
+    }
+
+    #[test]
+    fn test_malformed_json_is_reported() {
+        assert!(message(parse(r#"{"min_score": }"#)).starts_with("Invalid provider_params JSON: "));
+    }
+
+    #[test]
+    fn test_nested_values_are_passed_unchecked() {
+        let parsed = parse(r#"{"track_total_hits": {"query": {"match_all": {}}}}"#).unwrap();
+        assert_eq!(parsed["track_total_hits"], json!({"query": {"match_all": {}}}));
+    }
+
+    #[test]
+    fn test_merge_overrides_existing_keys() {
+        let mut body = json!({"size": 10, "min_score": 0.1});
+        merge_into_body(&mut body, &params(json!({"min_score": 0.5})));
+        assert_eq!(body, json!({"size": 10, "min_score": 0.5}));
+    }
+
+    #[test]
+    fn test_merge_leaves_non_object_bodies_alone() {
+        let mut body = json!([{"size": 10}]);
+        merge_into_body(&mut body, &params(json!({"min_score": 0.5})));
+        assert_eq!(body, json!([{"size": 10}]));
+    }
+
+    #[test]
+    fn test_query_pairs_pass_strings_verbatim_and_render_the_rest_as_json() {
+        let pairs = to_query_pairs(&params(json!({
+            "a": "plain text",
+            "b": 0.5,
+            "c": null,
+            "d": ["x", 1],
+            "e": ""
+        })));
+        let expected = [("a", "plain text"), ("b", "0.5"), ("c", "null"), ("d", r#"["x",1]"#), ("e", "")];
+        assert_eq!(pairs, expected.map(|(key, value)| (key.to_string(), value.to_string())));
+    }
+}
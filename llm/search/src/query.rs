@@ -0,0 +1,1336 @@
+//! Query string operators shared by every provider
+//!
+//! Query text may use a small, widely known syntax on top of plain terms:
+//!
+//! - `"exact phrase"` matches the words in order
+//! - `"a b"~3` matches the words within 3 positions of each other
+//! - `+term` requires a term, `-term` excludes it; both also apply to phrases
+//! - `comp*` and `te?t` are wildcard terms (a trailing `?` is kept as text),
+//!   `/joh?n(ath[oa]n)?/` is a regular expression
+//!
+//! [`Fuzziness`] sets how many typos a term may contain, for callers that
+//! need exact codes or forgiving prose. [`MinimumShouldMatch`] sets how many
+//! of the plain terms a document must contain.
+//!
+//! Wildcards and regular expressions are checked against [`PatternLimits`]
+//! before they are sent, since a leading wildcard or a long pattern can make
+//! the engine scan every term in the index.
+//!
+//! [`ParsedQuery::parse`] turns the text into clauses, which providers then
+//! translate: Elasticsearch and OpenSearch build a `bool` query through
+//! [`ParsedQuery::to_lucene_query`], Typesense and Meilisearch receive the
+//! subset of the syntax they understand from [`ParsedQuery::to_simple_syntax`].
+//!
+//! [`parse_lucene`] handles a larger subset of Lucene query syntax, with
+//! boolean operators, fields and ranges, for callers coming from Elasticsearch
+//! query strings. It produces a typed [`QueryNode`] tree and reports syntax
+//! errors instead of guessing.
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use crate::config::env_helpers::get_env_parsed;
+use crate::error::{SearchError, SearchResult};
+
+/// Environment variable capping the wildcards a pattern may start with
+pub const MAX_LEADING_WILDCARDS_ENV: &str = "SEARCH_MAX_LEADING_WILDCARDS";
+
+/// Environment variable capping the length of wildcard and regex patterns
+pub const MAX_PATTERN_LENGTH_ENV: &str = "SEARCH_MAX_PATTERN_LENGTH";
+
+/// Guardrails for wildcard and regular expression patterns
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PatternLimits {
+    /// Wildcards allowed before the first literal character; `*` alone is always allowed
+    pub max_leading_wildcards: usize,
+
+    /// Longest pattern accepted, in characters
+    pub max_pattern_length: usize,
+}
+
+impl Default for PatternLimits {
+    fn default() -> Self {
+        Self { max_leading_wildcards: 0, max_pattern_length: 128 }
+    }
+}
+
+impl PatternLimits {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            max_leading_wildcards: get_env_parsed(MAX_LEADING_WILDCARDS_ENV)
+                .ok()
+                .flatten()
+                .unwrap_or(defaults.max_leading_wildcards),
+            max_pattern_length: get_env_parsed(MAX_PATTERN_LENGTH_ENV)
+                .ok()
+                .flatten()
+                .unwrap_or(defaults.max_pattern_length),
+        }
+    }
+
+    /// Reject a pattern that is too long or starts with too many wildcards
+    pub fn check(&self, pattern: &str, regex: bool) -> SearchResult<()> {
+        let length = pattern.chars().count();
+        if length > self.max_pattern_length {
+            return Err(SearchError::invalid_query(format!(
+                "Pattern of {} characters exceeds the limit of {}",
+                length, self.max_pattern_length
+            )));
+        }
+        if pattern == "*" {
+            return Ok(());
+        }
+
+        let leading = if regex { leading_regex_wildcards(pattern) } else { pattern.chars().take_while(|c| matches!(c, '*' | '?')).count() };
+        if leading > self.max_leading_wildcards {
+            return Err(SearchError::invalid_query(format!(
+                "Pattern {} starts with {} wildcard(s); at most {} allowed",
+                pattern, leading, self.max_leading_wildcards
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Number of leading `.`, `.*`, `.+` and `.?` units in a regular expression
+fn leading_regex_wildcards(pattern: &str) -> usize {
+    let mut chars = pattern.chars().peekable();
+    let mut count = 0;
+    while chars.next_if_eq(&'.').is_some() {
+        chars.next_if(|c| matches!(c, '*' | '+' | '?'));
+        count += 1;
+    }
+    count
+}
+
+/// Wildcard term: any `*`, or a `?` before the end so prose like `why?` stays a term
+fn is_wildcard(term: &str) -> bool {
+    term.contains('*') || term.trim_end_matches('?').contains('?')
+}
+
+/// Edits a query term may be away from an indexed term and still match
+///
+/// Tight matching suits codes and SKUs, where `AB-1234` must not match
+/// `AB-1235`; prose benefits from the engine's length-based default.
+/// Serialized as `"0"`, `"1"`, `"2"` or `"auto"`; JSON numbers are accepted too.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "FuzzinessRepr")]
+pub enum Fuzziness {
+    /// No typos
+    Exact,
+    One,
+    Two,
+
+    /// Typos allowed by term length, as the engine decides
+    #[default]
+    Auto,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FuzzinessRepr {
+    Edits(u8),
+    Text(String),
+}
+
+impl TryFrom<FuzzinessRepr> for Fuzziness {
+    type Error = SearchError;
+
+    fn try_from(repr: FuzzinessRepr) -> SearchResult<Self> {
+        match repr {
+            FuzzinessRepr::Edits(edits) => Self::parse(&edits.to_string()),
+            FuzzinessRepr::Text(text) => Self::parse(&text),
+        }
+    }
+}
+
+impl From<Fuzziness> for String {
+    fn from(fuzziness: Fuzziness) -> Self {
+        fuzziness.as_str().to_string()
+    }
+}
+
+impl Fuzziness {
+    pub fn parse(value: &str) -> SearchResult<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "0" => Ok(Self::Exact),
+            "1" => Ok(Self::One),
+            "2" => Ok(Self::Two),
+            "auto" => Ok(Self::Auto),
+            other => Err(SearchError::invalid_query(format!(
+                "Invalid fuzziness {}; expected 0, 1, 2 or auto",
+                other
+            ))),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Exact => "0",
+            Self::One => "1",
+            Self::Two => "2",
+            Self::Auto => "auto",
+        }
+    }
+
+    /// Fixed edit distance, or `None` when the engine picks it per term
+    pub fn max_typos(&self) -> Option<u8> {
+        match self {
+            Self::Exact => Some(0),
+            Self::One => Some(1),
+            Self::Two => Some(2),
+            Self::Auto => None,
+        }
+    }
+
+    /// Value of the Elasticsearch `fuzziness` parameter
+    pub fn to_lucene(&self) -> Value {
+        match self.max_typos() {
+            Some(edits) => json!(edits),
+            None => json!("AUTO"),
+        }
+    }
+
+    /// Add `fuzziness` to every term match in an Elasticsearch query
+    ///
+    /// Phrase matches are left alone, since Elasticsearch rejects fuzziness
+    /// on them, as are wildcard, regex and range clauses.
+    pub fn apply_to_lucene(&self, query: &mut Value) {
+        match query {
+            Value::Array(items) => items.iter_mut().for_each(|item| self.apply_to_lucene(item)),
+            Value::Object(map) => {
+                for (key, value) in map.iter_mut() {
+                    match key.as_str() {
+                        "multi_match" => {
+                            let kind = value.get("type").and_then(Value::as_str).unwrap_or("best_fields");
+                            if matches!(kind, "best_fields" | "most_fields" | "bool_prefix") {
+                                value["fuzziness"] = self.to_lucene();
+                            }
+                        }
+                        "match" => {
+                            if let Some(fields) = value.as_object_mut() {
+                                fields.values_mut().filter(|f| f.is_object()).for_each(|f| f["fuzziness"] = self.to_lucene());
+                            }
+                        }
+                        _ => self.apply_to_lucene(value),
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Parse a fuzziness passed as text, as the WIT interfaces carry it
+pub fn parse_fuzziness(value: Option<&str>) -> SearchResult<Option<Fuzziness>> {
+    value.filter(|v| !v.trim().is_empty()).map(Fuzziness::parse).transpose()
+}
+
+/// How many of the plain terms of a query a document must contain
+///
+/// Serialized as `"all"`, `"any"`, a count such as `"2"`, a count of terms that
+/// may be missing such as `"-1"`, or a share such as `"75%"` or `"-25%"`, as
+/// Elasticsearch reads them. Terms marked `+` or `-` and phrases are not counted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub enum MinimumShouldMatch {
+    All,
+    Any,
+
+    /// At least this many terms; a negative count is how many may be missing
+    Count(i32),
+
+    /// At least this share of the terms, rounded down; a negative share is how much may be missing
+    Percent(i32),
+}
+
+impl TryFrom<String> for MinimumShouldMatch {
+    type Error = SearchError;
+
+    fn try_from(value: String) -> SearchResult<Self> {
+        Self::parse(&value)
+    }
+}
+
+impl From<MinimumShouldMatch> for String {
+    fn from(minimum: MinimumShouldMatch) -> Self {
+        minimum.to_string()
+    }
+}
+
+impl std::fmt::Display for MinimumShouldMatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::All => write!(f, "all"),
+            Self::Any => write!(f, "any"),
+            Self::Count(count) => write!(f, "{}", count),
+            Self::Percent(percent) => write!(f, "{}%", percent),
+        }
+    }
+}
+
+impl MinimumShouldMatch {
+    pub fn parse(value: &str) -> SearchResult<Self> {
+        let value = value.trim().to_ascii_lowercase();
+        let invalid = || {
+            SearchError::invalid_query(format!(
+                "Invalid minimum_should_match {}; expected all, any, a non-zero count such as 2 or -1, or a share such as 75%",
+                value
+            ))
+        };
+        let minimum = match value.as_str() {
+            "all" => Self::All,
+            "any" => Self::Any,
+            _ => match value.strip_suffix('%') {
+                Some(percent) => Self::Percent(percent.trim().parse().map_err(|_| invalid())?),
+                None => Self::Count(value.parse().map_err(|_| invalid())?),
+            },
+        };
+        match minimum {
+            Self::Count(0) | Self::Percent(0) => Err(invalid()),
+            Self::Percent(percent) if percent.abs() > 100 => Err(invalid()),
+            minimum => Ok(minimum),
+        }
+    }
+
+    /// Whether every term has to match
+    pub fn is_all(&self) -> bool {
+        matches!(self, Self::All | Self::Percent(100))
+    }
+
+    /// Whether any one term is enough
+    pub fn is_any(&self) -> bool {
+        matches!(self, Self::Any | Self::Count(1) | Self::Percent(-100))
+    }
+
+    /// Value of the Elasticsearch `minimum_should_match` parameter
+    pub fn to_lucene(&self) -> Value {
+        match self {
+            Self::All => json!("100%"),
+            Self::Any => json!("1"),
+            minimum => json!(minimum.to_string()),
+        }
+    }
+
+    /// Set `minimum_should_match` on the term matches of an Elasticsearch query that any term may satisfy
+    ///
+    /// Those are the matches with the `or` operator; a required term or a
+    /// phrase is one clause already. Next to required clauses the plain terms
+    /// only add to the score, so their match becomes required too.
+    pub fn apply_to_lucene(&self, query: &mut Value) {
+        match query {
+            Value::Array(items) => items.iter_mut().for_each(|item| self.apply_to_lucene(item)),
+            Value::Object(map) => {
+                for (key, value) in map.iter_mut() {
+                    match key.as_str() {
+                        "bool" => {
+                            require_optional_terms(value);
+                            self.apply_to_lucene(value);
+                        }
+                        "multi_match" if value.get("operator").and_then(Value::as_str) == Some("or") => {
+                            value["minimum_should_match"] = self.to_lucene();
+                        }
+                        "match" => {
+                            if let Some(fields) = value.as_object_mut() {
+                                fields
+                                    .values_mut()
+                                    .filter(|f| f.get("operator").and_then(Value::as_str) == Some("or"))
+                                    .for_each(|f| f["minimum_should_match"] = self.to_lucene());
+                            }
+                        }
+                        _ => self.apply_to_lucene(value),
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Move the `or` term match of a `bool` query from its optional clauses to its required ones
+fn require_optional_terms(bool_query: &mut Value) {
+    let is_terms = |clause: &Value| {
+        clause.get("multi_match").and_then(|m| m.get("operator")).and_then(Value::as_str) == Some("or")
+    };
+    let terms = bool_query
+        .get_mut("should")
+        .and_then(Value::as_array_mut)
+        .and_then(|should| should.iter().position(is_terms).map(|i| should.remove(i)));
+    let Some(terms) = terms else {
+        return;
+    };
+    match bool_query.get_mut("must").and_then(Value::as_array_mut) {
+        Some(must) => must.push(terms),
+        None => bool_query["must"] = json!([terms]),
+    }
+    bool_query["minimum_should_match"] = json!(0);
+}
+
+/// Parse a minimum_should_match passed as text, as the WIT interfaces carry it
+pub fn parse_minimum_should_match(value: Option<&str>) -> SearchResult<Option<MinimumShouldMatch>> {
+    value.filter(|v| !v.trim().is_empty()).map(MinimumShouldMatch::parse).transpose()
+}
+
+/// Whether a clause must, may or must not match
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Occur {
+    Should,
+    Must,
+    MustNot,
+}
+
+/// A single term, phrase or pattern
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Clause {
+    Term(String),
+
+    /// Words matched in order, or within `slop` positions of each other
+    Phrase { text: String, slop: Option<u32> },
+
+    /// Term with `*` and `?` wildcards
+    Wildcard(String),
+
+    /// Regular expression, without the surrounding slashes
+    Regex(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueryClause {
+    pub occur: Occur,
+    pub clause: Clause,
+}
+
+/// Query text split into terms, phrases and their operators
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParsedQuery {
+    pub clauses: Vec<QueryClause>,
+}
+
+/// Query text in the syntax a provider understands, with what was lost on the way
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimpleSyntax {
+    pub text: String,
+
+    /// Whether every term has to match for the required terms to be honored
+    pub require_all_terms: bool,
+
+    pub warnings: Vec<String>,
+}
+
+impl ParsedQuery {
+    /// Parse query text
+    ///
+    /// Parsing never fails: an unterminated quote runs to the end of the text
+    /// and lone operators are ignored.
+    pub fn parse(text: &str) -> Self {
+        let chars: Vec<char> = text.chars().collect();
+        let mut clauses = Vec::new();
+        let mut i = 0;
+
+        while i < chars.len() {
+            if chars[i].is_whitespace() {
+                i += 1;
+                continue;
+            }
+
+            let occur = match chars[i] {
+                '+' => Occur::Must,
+                '-' => Occur::MustNot,
+                _ => Occur::Should,
+            };
+            if occur != Occur::Should {
+                i += 1;
+            }
+
+            if chars.get(i) == Some(&'"') {
+                let start = i + 1;
+                let end = chars[start..].iter().position(|c| *c == '"').map_or(chars.len(), |p| start + p);
+                let text = normalize_space(&chars[start..end].iter().collect::<String>());
+                i = (end + 1).min(chars.len());
+
+                let mut slop = None;
+                if chars.get(i) == Some(&'~') {
+                    let digits: String = chars[i + 1..].iter().take_while(|c| c.is_ascii_digit()).collect();
+                    i += 1 + digits.len();
+                    slop = digits.parse().ok();
+                }
+                if !text.is_empty() {
+                    clauses.push(QueryClause { occur, clause: Clause::Phrase { text, slop } });
+                }
+            } else {
+                let start = i;
+                while i < chars.len() && !chars[i].is_whitespace() {
+                    i += 1;
+                }
+                let term: String = chars[start..i].iter().collect();
+                let clause = match term.as_str() {
+                    "" | "*" => continue,
+                    t if t.len() > 2 && t.starts_with('/') && t.ends_with('/') => Clause::Regex(t[1..t.len() - 1].to_string()),
+                    t if is_wildcard(t) => Clause::Wildcard(term),
+                    _ => Clause::Term(term),
+                };
+                clauses.push(QueryClause { occur, clause });
+            }
+        }
+
+        Self { clauses }
+    }
+
+    /// Whether the text uses any operator, so plain-text handling would change its meaning
+    pub fn has_operators(&self) -> bool {
+        self.clauses
+            .iter()
+            .any(|c| c.occur != Occur::Should || !matches!(c.clause, Clause::Term(_)))
+    }
+
+    /// Turn optional and required terms into single-word phrases
+    ///
+    /// For engines without a per-query typo setting, where a quoted word is
+    /// the only way to ask for an exact match. Quoted words are required there,
+    /// so every remaining term has to match.
+    pub fn exact_terms(mut self) -> Self {
+        for clause in &mut self.clauses {
+            if let (Occur::Should | Occur::Must, Clause::Term(term)) = (clause.occur, &clause.clause) {
+                clause.clause = Clause::Phrase { text: term.clone(), slop: None };
+            }
+        }
+        self
+    }
+
+    /// Check every wildcard and regular expression against `limits`
+    pub fn check_patterns(&self, limits: &PatternLimits) -> SearchResult<()> {
+        self.clauses.iter().try_for_each(|c| match &c.clause {
+            Clause::Wildcard(pattern) => limits.check(pattern, false),
+            Clause::Regex(pattern) => limits.check(pattern, true),
+            Clause::Term(_) | Clause::Phrase { .. } => Ok(()),
+        })
+    }
+
+    /// Plain terms without an operator, joined by spaces
+    pub fn optional_terms(&self) -> String {
+        self.clauses
+            .iter()
+            .filter_map(|c| match (&c.occur, &c.clause) {
+                (Occur::Should, Clause::Term(term)) => Some(term.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Elasticsearch/OpenSearch query for the clauses
+    ///
+    /// Plain terms keep the default `best_fields` match. When only exclusions
+    /// are given, every other document matches.
+    pub fn to_lucene_query(&self) -> Value {
+        let optional = self.optional_terms();
+        let mut must = Vec::new();
+        let mut should = Vec::new();
+        let mut must_not = Vec::new();
+
+        for clause in &self.clauses {
+            let query = match (&clause.occur, &clause.clause) {
+                (Occur::Should, Clause::Term(_)) => continue,
+                (_, Clause::Term(term)) => json!({ "multi_match": { "query": term, "type": "best_fields" } }),
+                (_, Clause::Phrase { text, slop }) => json!({
+                    "multi_match": { "query": text, "type": "phrase", "slop": slop.unwrap_or(0) }
+                }),
+                (_, Clause::Wildcard(pattern)) => {
+                    json!({ "query_string": { "query": escape_lucene(pattern, true), "analyze_wildcard": true } })
+                }
+                (_, Clause::Regex(pattern)) => json!({ "query_string": { "query": format!("/{}/", pattern) } }),
+            };
+            match clause.occur {
+                Occur::Must => must.push(query),
+                Occur::Should => should.push(query),
+                Occur::MustNot => must_not.push(query),
+            }
+        }
+        if !optional.is_empty() {
+            should.insert(0, json!({ "multi_match": { "query": optional, "type": "best_fields", "operator": "or" } }));
+        }
+
+        if must.is_empty() && should.is_empty() {
+            must.push(json!({ "match_all": {} }));
+        }
+        // Without required clauses at least one optional clause has to match
+        let minimum_should_match = if must.is_empty() { 1 } else { 0 };
+        json!({
+            "bool": {
+                "must": must,
+                "should": should,
+                "must_not": must_not,
+                "minimum_should_match": minimum_should_match
+            }
+        })
+    }
+
+    /// Query text for engines that know quoted phrases and `-` exclusions only
+    ///
+    /// Proximity degrades to an exact phrase. Required terms are kept as plain
+    /// terms and reported through `require_all_terms`, which the provider maps
+    /// to its all-words matching mode. A trailing `*` becomes a prefix search,
+    /// which these engines run on the last word of the query; any other
+    /// wildcard or a regular expression is `Unsupported`.
+    pub fn to_simple_syntax(&self, provider: &str) -> SearchResult<SimpleSyntax> {
+        let mut parts = Vec::new();
+        let mut prefixes = Vec::new();
+        let mut warnings = Vec::new();
+        let mut require_all_terms = false;
+
+        for clause in &self.clauses {
+            let text = match &clause.clause {
+                Clause::Term(term) => term.clone(),
+                Clause::Phrase { text, slop } => {
+                    if slop.is_some_and(|s| s > 0) {
+                        warnings.push(format!("{} has no proximity search; \"{}\" is matched as an exact phrase", provider, text));
+                    }
+                    format!("\"{}\"", text)
+                }
+                Clause::Wildcard(pattern) => match pattern.strip_suffix('*') {
+                    Some(prefix) if !is_wildcard(prefix) && clause.occur != Occur::MustNot => {
+                        if clause.occur == Occur::Must {
+                            require_all_terms = true;
+                        }
+                        prefixes.push(prefix.to_string());
+                        continue;
+                    }
+                    _ => return Err(unsupported_pattern(provider, pattern)),
+                },
+                Clause::Regex(pattern) => return Err(unsupported_pattern(provider, &format!("/{}/", pattern))),
+            };
+            match clause.occur {
+                Occur::MustNot => parts.push(format!("-{}", text)),
+                Occur::Must => {
+                    require_all_terms = true;
+                    parts.push(text);
+                }
+                Occur::Should => parts.push(text),
+            }
+        }
+
+        if prefixes.len() > 1 {
+            return Err(unsupported_pattern(provider, &format!("{}*", prefixes[1])));
+        }
+        parts.extend(prefixes);
+
+        if require_all_terms && self.clauses.iter().any(|c| c.occur == Occur::Should) {
+            warnings.push(format!("{} cannot mix required and optional terms; every term is required", provider));
+        }
+        Ok(SimpleSyntax { text: parts.join(" "), require_all_terms, warnings })
+    }
+}
+
+fn unsupported_pattern(provider: &str, pattern: &str) -> SearchError {
+    warn!("{} cannot match {}; only one trailing * per query can be emulated as a prefix search", provider, pattern);
+    SearchError::Unsupported
+}
+
+fn normalize_space(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// One end of a range; `None` in [`QueryNode::Range`] means unbounded (`*`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RangeBound {
+    pub value: String,
+    pub inclusive: bool,
+}
+
+/// Typed form of a Lucene-lite query string
+///
+/// `Bool` follows Lucene semantics: `should` clauses are optional when there
+/// is a `must` clause, otherwise at least one of them has to match, and a
+/// bool with only `must_not` clauses matches every other document.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum QueryNode {
+    MatchAll,
+
+    /// A term, matched on `field` or on every field
+    Term { field: Option<String>, value: String },
+
+    Phrase { field: Option<String>, text: String, slop: Option<u32> },
+
+    /// A term with `*` and `?` wildcards; `field:*` matches documents that have the field
+    Wildcard { field: Option<String>, pattern: String },
+
+    /// A regular expression in Lucene syntax, written `/pattern/`
+    Regex { field: Option<String>, pattern: String },
+
+    Range { field: String, lower: Option<RangeBound>, upper: Option<RangeBound> },
+
+    Bool { must: Vec<QueryNode>, should: Vec<QueryNode>, must_not: Vec<QueryNode> },
+}
+
+impl QueryNode {
+    /// Whether every leaf names a field, as filters require
+    pub fn is_fielded(&self) -> bool {
+        match self {
+            Self::MatchAll | Self::Range { .. } => true,
+            Self::Term { field, .. }
+            | Self::Phrase { field, .. }
+            | Self::Wildcard { field, .. }
+            | Self::Regex { field, .. } => field.is_some(),
+            Self::Bool { must, should, must_not } => must.iter().chain(should).chain(must_not).all(Self::is_fielded),
+        }
+    }
+
+    /// Check every wildcard and regular expression against `limits`
+    pub fn check_patterns(&self, limits: &PatternLimits) -> SearchResult<()> {
+        match self {
+            Self::Wildcard { pattern, .. } => limits.check(pattern, false),
+            Self::Regex { pattern, .. } => limits.check(pattern, true),
+            Self::Bool { must, should, must_not } => {
+                must.iter().chain(should).chain(must_not).try_for_each(|n| n.check_patterns(limits))
+            }
+            Self::MatchAll | Self::Term { .. } | Self::Phrase { .. } | Self::Range { .. } => Ok(()),
+        }
+    }
+
+    /// Elasticsearch/OpenSearch query DSL for the node
+    pub fn to_lucene_query(&self) -> Value {
+        match self {
+            Self::MatchAll => json!({ "match_all": {} }),
+            Self::Term { field: Some(field), value } => json!({ "match": { field: { "query": value } } }),
+            Self::Term { field: None, value } => json!({ "multi_match": { "query": value, "type": "best_fields" } }),
+            Self::Phrase { field: Some(field), text, slop } => {
+                json!({ "match_phrase": { field: { "query": text, "slop": slop.unwrap_or(0) } } })
+            }
+            Self::Phrase { field: None, text, slop } => {
+                json!({ "multi_match": { "query": text, "type": "phrase", "slop": slop.unwrap_or(0) } })
+            }
+            Self::Wildcard { field: Some(field), pattern } if pattern == "*" => json!({ "exists": { "field": field } }),
+            Self::Wildcard { field: Some(field), pattern } => json!({ "wildcard": { field: { "value": pattern } } }),
+            Self::Wildcard { field: None, pattern } => {
+                json!({ "query_string": { "query": escape_lucene(pattern, true), "analyze_wildcard": true } })
+            }
+            Self::Regex { field: Some(field), pattern } => json!({ "regexp": { field: { "value": pattern } } }),
+            Self::Regex { field: None, pattern } => json!({ "query_string": { "query": format!("/{}/", pattern) } }),
+            Self::Range { field, lower, upper } => {
+                let mut range = serde_json::Map::new();
+                if let Some(lower) = lower {
+                    range.insert(if lower.inclusive { "gte" } else { "gt" }.to_string(), json!(lower.value));
+                }
+                if let Some(upper) = upper {
+                    range.insert(if upper.inclusive { "lte" } else { "lt" }.to_string(), json!(upper.value));
+                }
+                json!({ "range": { field: range } })
+            }
+            Self::Bool { must, should, must_not } => {
+                let translate = |nodes: &[QueryNode]| nodes.iter().map(QueryNode::to_lucene_query).collect::<Vec<_>>();
+                let mut query = json!({
+                    "bool": { "must": translate(must), "should": translate(should), "must_not": translate(must_not) }
+                });
+                if must.is_empty() && !should.is_empty() {
+                    query["bool"]["minimum_should_match"] = json!(1);
+                }
+                query
+            }
+        }
+    }
+
+    /// Query string that [`parse_lucene`] reads back as an equivalent node
+    ///
+    /// Nodes the parser produced come back unchanged. A `Bool` with a single
+    /// `should` clause is written as that clause, and an empty one as `*:*`.
+    pub fn to_query_string(&self) -> String {
+        let fielded = |field: &Option<String>, value: String| match field {
+            Some(field) => format!("{}:{}", escape_query_word(field, false), value),
+            None => value,
+        };
+        match self {
+            Self::MatchAll => "*:*".to_string(),
+            Self::Term { field, value } => fielded(field, escape_query_word(value, false)),
+            Self::Phrase { field, text, slop } => {
+                let slop = slop.map(|slop| format!("~{}", slop)).unwrap_or_default();
+                fielded(field, format!("\"{}\"{}", text.replace('"', ""), slop))
+            }
+            Self::Wildcard { field, pattern } => fielded(field, escape_query_word(pattern, true)),
+            Self::Regex { field, pattern } => fielded(field, format!("/{}/", pattern.replace('/', "\\/"))),
+            Self::Range { field, lower, upper } => {
+                let bound = |bound: &Option<RangeBound>| match bound {
+                    None => "*".to_string(),
+                    Some(bound) if bound.value.contains(char::is_whitespace) => format!("\"{}\"", bound.value),
+                    Some(bound) => escape_query_word(&bound.value, false),
+                };
+                let open = if lower.as_ref().is_none_or(|b| b.inclusive) { '[' } else { '{' };
+                let close = if upper.as_ref().is_none_or(|b| b.inclusive) { ']' } else { '}' };
+                fielded(&Some(field.clone()), format!("{}{} TO {}{}", open, bound(lower), bound(upper), close))
+            }
+            Self::Bool { must, should, must_not } if must.is_empty() && must_not.is_empty() && should.len() == 1 => {
+                should[0].to_query_string()
+            }
+            Self::Bool { must, should, must_not } if must.is_empty() && should.is_empty() && must_not.is_empty() => {
+                "*:*".to_string()
+            }
+            Self::Bool { must, should, must_not } => {
+                let clause = |prefix: &str, node: &QueryNode| match node {
+                    Self::Bool { .. } => format!("{}({})", prefix, node.to_query_string()),
+                    _ => format!("{}{}", prefix, node.to_query_string()),
+                };
+                must.iter()
+                    .map(|node| clause("+", node))
+                    .chain(should.iter().map(|node| clause("", node)))
+                    .chain(must_not.iter().map(|node| clause("-", node)))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            }
+        }
+    }
+}
+
+/// A term or field name escaped so the tokenizer reads it back as one word
+///
+/// Operators spelled as words, such as `AND`, cannot be escaped and are
+/// quoted instead, which reads back as a phrase of one word.
+fn escape_query_word(text: &str, keep_wildcards: bool) -> String {
+    if text.is_empty() || matches!(text, "AND" | "OR" | "NOT" | "TO") {
+        return format!("\"{}\"", text);
+    }
+    text.chars()
+        .map(|c| match c {
+            c if c.is_whitespace() => format!("\\{}", c),
+            c => escape_lucene(c.encode_utf8(&mut [0; 4]), keep_wildcards),
+        })
+        .collect()
+}
+
+/// Escape Lucene reserved characters, keeping `*` and `?` when `keep_wildcards` is set
+fn escape_lucene(text: &str, keep_wildcards: bool) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        let wildcard = matches!(c, '*' | '?');
+        if (wildcard && !keep_wildcards) || (!wildcard && "+-=&|><!(){}[]^\"~:\\/".contains(c)) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word { text: String, wildcard: bool },
+    Phrase { text: String, slop: Option<u32> },
+    Regex(String),
+    Colon,
+    LParen,
+    RParen,
+    /// `[` or `{`; the flag tells whether the bound is inclusive
+    RangeOpen(bool),
+    RangeClose(bool),
+    And,
+    Or,
+    Not,
+    Plus,
+    Minus,
+}
+
+fn syntax_error(position: usize, message: impl std::fmt::Display) -> SearchError {
+    SearchError::invalid_query(format!("Query syntax error at {}: {}", position, message))
+}
+
+fn tokenize(text: &str) -> SearchResult<Vec<(usize, Token)>> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        let next = chars.get(i + 1).copied();
+        let token = match c {
+            c if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            ':' => Token::Colon,
+            '[' => Token::RangeOpen(true),
+            '{' => Token::RangeOpen(false),
+            ']' => Token::RangeClose(true),
+            '}' => Token::RangeClose(false),
+            '&' if next == Some('&') => {
+                i += 1;
+                Token::And
+            }
+            '|' if next == Some('|') => {
+                i += 1;
+                Token::Or
+            }
+            '+' | '-' | '!' if next.is_some_and(|n| !n.is_whitespace()) => match c {
+                '+' => Token::Plus,
+                '-' => Token::Minus,
+                _ => Token::Not,
+            },
+            '^' => return Err(syntax_error(start, "boosts are not supported")),
+            '/' => {
+                let mut pattern = String::new();
+                let mut j = i + 1;
+                loop {
+                    match chars.get(j) {
+                        None => return Err(syntax_error(start, "unterminated regular expression")),
+                        Some('/') => break,
+                        Some('\\') if chars.get(j + 1) == Some(&'/') => {
+                            pattern.push('/');
+                            j += 2;
+                        }
+                        Some(c) => {
+                            pattern.push(*c);
+                            j += 1;
+                        }
+                    }
+                }
+                i = j;
+                Token::Regex(pattern)
+            }
+            '"' => {
+                let end = chars[i + 1..]
+                    .iter()
+                    .position(|c| *c == '"')
+                    .map(|p| i + 1 + p)
+                    .ok_or_else(|| syntax_error(start, "unterminated phrase"))?;
+                let text = normalize_space(&chars[i + 1..end].iter().collect::<String>());
+                i = end;
+                let mut slop = None;
+                if chars.get(i + 1) == Some(&'~') {
+                    let digits: String = chars[i + 2..].iter().take_while(|c| c.is_ascii_digit()).collect();
+                    slop = Some(digits.parse().map_err(|_| syntax_error(i + 1, "~ after a phrase needs a distance"))?);
+                    i += 1 + digits.len();
+                }
+                Token::Phrase { text, slop }
+            }
+            _ => {
+                let mut word = String::new();
+                let mut wildcard = false;
+                while i < chars.len() {
+                    let c = chars[i];
+                    if c == '\\' {
+                        let escaped = chars.get(i + 1).ok_or_else(|| syntax_error(i, "dangling escape"))?;
+                        word.push(*escaped);
+                        i += 2;
+                        continue;
+                    }
+                    if c.is_whitespace() || "():[]{}\"/".contains(c) {
+                        break;
+                    }
+                    if c == '~' {
+                        return Err(syntax_error(i, "fuzzy terms are not supported"));
+                    }
+                    if c == '^' {
+                        return Err(syntax_error(i, "boosts are not supported"));
+                    }
+                    wildcard |= matches!(c, '*' | '?');
+                    word.push(c);
+                    i += 1;
+                }
+                let token = match word.as_str() {
+                    "AND" => Token::And,
+                    "OR" => Token::Or,
+                    "NOT" => Token::Not,
+                    _ => Token::Word { text: word, wildcard },
+                };
+                tokens.push((start, token));
+                continue;
+            }
+        };
+        tokens.push((start, token));
+        i += 1;
+    }
+    Ok(tokens)
+}
+
+struct LuceneParser {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+    end: usize,
+}
+
+impl LuceneParser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(_, t)| t)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens.get(self.pos).map_or(self.end, |(p, _)| *p)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).map(|(_, t)| t.clone());
+        self.pos += 1;
+        token
+    }
+
+    /// Clauses joined by `OR` or by nothing, Lucene's default operator
+    fn parse_or(&mut self, field: Option<&str>) -> SearchResult<QueryNode> {
+        let mut items = Vec::new();
+        loop {
+            items.push(self.parse_and(field)?);
+            match self.peek() {
+                Some(Token::Or) => {
+                    self.next();
+                }
+                None | Some(Token::RParen) => break,
+                _ => {}
+            }
+        }
+
+        if let [(Occur::Should, _)] = items.as_slice() {
+            return Ok(items.remove(0).1);
+        }
+        let (mut must, mut should, mut must_not) = (Vec::new(), Vec::new(), Vec::new());
+        for (occur, node) in items {
+            match occur {
+                Occur::Must => must.push(node),
+                Occur::Should => should.push(node),
+                Occur::MustNot => must_not.push(node),
+            }
+        }
+        Ok(QueryNode::Bool { must, should, must_not })
+    }
+
+    /// Clauses joined by `AND`, which binds tighter than `OR`
+    fn parse_and(&mut self, field: Option<&str>) -> SearchResult<(Occur, QueryNode)> {
+        let first = self.parse_unary(field)?;
+        if self.peek() != Some(&Token::And) {
+            return Ok(first);
+        }
+
+        let (mut must, mut must_not) = (Vec::new(), Vec::new());
+        let mut push = |(occur, node): (Occur, QueryNode)| match occur {
+            Occur::MustNot => must_not.push(node),
+            _ => must.push(node),
+        };
+        push(first);
+        while self.peek() == Some(&Token::And) {
+            self.next();
+            push(self.parse_unary(field)?);
+        }
+        Ok((Occur::Should, QueryNode::Bool { must, should: Vec::new(), must_not }))
+    }
+
+    fn parse_unary(&mut self, field: Option<&str>) -> SearchResult<(Occur, QueryNode)> {
+        let occur = match self.peek() {
+            Some(Token::Not | Token::Minus) => Occur::MustNot,
+            Some(Token::Plus) => Occur::Must,
+            _ => return Ok((Occur::Should, self.parse_primary(field)?)),
+        };
+        self.next();
+        Ok((occur, self.parse_primary(field)?))
+    }
+
+    fn parse_primary(&mut self, field: Option<&str>) -> SearchResult<QueryNode> {
+        let position = self.position();
+        match self.next() {
+            Some(Token::LParen) => {
+                let node = self.parse_or(field)?;
+                match self.next() {
+                    Some(Token::RParen) => Ok(node),
+                    _ => Err(syntax_error(position, "unbalanced parenthesis")),
+                }
+            }
+            Some(Token::Word { text, wildcard }) if self.peek() == Some(&Token::Colon) => {
+                self.next();
+                if text == "*" {
+                    return match self.next() {
+                        Some(Token::Word { text, .. }) if text == "*" => Ok(QueryNode::MatchAll),
+                        _ => Err(syntax_error(position, "only *:* may use * as a field")),
+                    };
+                }
+                if wildcard {
+                    return Err(syntax_error(position, "field names cannot contain wildcards"));
+                }
+                match self.peek() {
+                    Some(Token::LParen) => self.parse_primary(Some(&text)),
+                    Some(Token::RangeOpen(_)) => self.parse_range(text),
+                    _ => self.parse_value(Some(&text)),
+                }
+            }
+            Some(token) => {
+                self.pos -= 1;
+                match token {
+                    Token::Word { .. } | Token::Phrase { .. } | Token::Regex(_) => self.parse_value(field),
+                    Token::RangeOpen(_) => match field {
+                        Some(field) => self.parse_range(field.to_string()),
+                        None => Err(syntax_error(position, "ranges need a field")),
+                    },
+                    _ => Err(syntax_error(position, "expected a term")),
+                }
+            }
+            None => Err(syntax_error(position, "unexpected end of query")),
+        }
+    }
+
+    fn parse_value(&mut self, field: Option<&str>) -> SearchResult<QueryNode> {
+        let position = self.position();
+        let field = field.map(str::to_string);
+        match self.next() {
+            Some(Token::Word { text, wildcard: false }) => Ok(QueryNode::Term { field, value: text }),
+            Some(Token::Word { text, wildcard: true }) if text == "*" && field.is_none() => Ok(QueryNode::MatchAll),
+            Some(Token::Word { text, wildcard: true }) => Ok(QueryNode::Wildcard { field, pattern: text }),
+            Some(Token::Phrase { text, slop }) => Ok(QueryNode::Phrase { field, text, slop }),
+            Some(Token::Regex(pattern)) => Ok(QueryNode::Regex { field, pattern }),
+            _ => Err(syntax_error(position, "expected a term")),
+        }
+    }
+
+    /// `[a TO b]`, `{a TO b}` or a mix; `*` leaves a side open
+    fn parse_range(&mut self, field: String) -> SearchResult<QueryNode> {
+        let position = self.position();
+        let Some(Token::RangeOpen(lower_inclusive)) = self.next() else {
+            return Err(syntax_error(position, "expected a range"));
+        };
+        let lower = self.parse_bound()?;
+        match self.next() {
+            Some(Token::Word { text, .. }) if text == "TO" => {}
+            _ => return Err(syntax_error(position, "ranges are written [low TO high]")),
+        }
+        let upper = self.parse_bound()?;
+        let Some(Token::RangeClose(upper_inclusive)) = self.next() else {
+            return Err(syntax_error(position, "unterminated range"));
+        };
+        Ok(QueryNode::Range {
+            field,
+            lower: lower.map(|value| RangeBound { value, inclusive: lower_inclusive }),
+            upper: upper.map(|value| RangeBound { value, inclusive: upper_inclusive }),
+        })
+    }
+
+    /// A range bound, `None` for `*`
+    fn parse_bound(&mut self) -> SearchResult<Option<String>> {
+        let position = self.position();
+        let negative = self.peek() == Some(&Token::Minus);
+        if negative {
+            self.next();
+        }
+        match self.next() {
+            Some(Token::Word { text, .. }) if text == "*" && !negative => Ok(None),
+            Some(Token::Word { text, .. }) if negative => Ok(Some(format!("-{}", text))),
+            Some(Token::Word { text, .. } | Token::Phrase { text, .. }) if !negative => Ok(Some(text)),
+            _ => Err(syntax_error(position, "expected a range bound")),
+        }
+    }
+}
+
+/// Parse a query string in Lucene-lite syntax
+///
+/// The supported subset:
+///
+/// | Syntax | Meaning |
+/// |--------|---------|
+/// | `a b`, `a OR b`, `a \|\| b` | either term (OR is the default operator) |
+/// | `a AND b`, `a && b` | both terms; AND binds tighter than OR |
+/// | `NOT a`, `!a`, `-a` | exclude; `+a` requires |
+/// | `(a OR b) AND c` | grouping |
+/// | `field:value`, `field:"a phrase"`, `field:(a OR b)` | match on one field |
+/// | `"a phrase"`, `"a phrase"~2` | phrase, optionally within a distance |
+/// | `field:[1 TO 5]`, `field:{1 TO 5}`, `field:[1 TO *]` | inclusive, exclusive and open ranges |
+/// | `te?t`, `test*`, `field:*` | wildcards; `field:*` means the field exists |
+/// | `/joh?n/`, `field:/[0-9]{3}/` | regular expressions |
+/// | `*:*`, `*` | every document |
+///
+/// Reserved characters are escaped with a backslash. Boosts (`^`) and fuzzy
+/// terms (`~` after a word) are rejected. Patterns are not checked here; see
+/// [`QueryNode::check_patterns`].
+pub fn parse_lucene(text: &str) -> SearchResult<QueryNode> {
+    let tokens = tokenize(text)?;
+    if tokens.is_empty() {
+        return Ok(QueryNode::MatchAll);
+    }
+
+    let mut parser = LuceneParser { tokens, pos: 0, end: text.chars().count() };
+    let node = parser.parse_or(None)?;
+    if parser.peek().is_some() {
+        return Err(syntax_error(parser.position(), "unbalanced parenthesis"));
+    }
+    Ok(node)
+}
+
+/// Elasticsearch/OpenSearch filter clause for a filter string
+///
+/// Plain `field:value` filters stay exact `term` matches. Anything else, such
+/// as `price:[10 TO 100]` or `brand:acme OR brand:globex`, goes through
+/// [`parse_lucene`] and must name a field in every clause.
+pub fn lucene_filter_clause(filter: &str) -> SearchResult<Value> {
+    if let Some((field, value)) = filter.split_once(':') {
+        let plain = |s: &str| !s.is_empty() && !s.chars().any(|c| c.is_whitespace() || "()[]{}\"*?\\".contains(c));
+        if plain(field) && plain(value) && !value.starts_with(['+', '-', '!']) && !value.contains(':') {
+            return Ok(json!({ "term": { field: value } }));
+        }
+    }
+
+    let node = parse_lucene(filter)?;
+    node.check_patterns(&PatternLimits::from_env())?;
+    if !node.is_fielded() {
+        return Err(SearchError::invalid_query(format!("Filter {} has a clause without a field", filter)));
+    }
+    Ok(node.to_lucene_query())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_operators() {
+        let parsed = ParsedQuery::parse(r#"+rust -"garbage collector" "zero  cost"~2 fast e-mail - "#);
+        assert_eq!(
+            parsed.clauses,
+            vec![
+                QueryClause { occur: Occur::Must, clause: Clause::Term("rust".to_string()) },
+                QueryClause {
+                    occur: Occur::MustNot,
+                    clause: Clause::Phrase { text: "garbage collector".to_string(), slop: None },
+                },
+                QueryClause {
+                    occur: Occur::Should,
+                    clause: Clause::Phrase { text: "zero cost".to_string(), slop: Some(2) },
+                },
+                QueryClause { occur: Occur::Should, clause: Clause::Term("fast".to_string()) },
+                QueryClause { occur: Occur::Should, clause: Clause::Term("e-mail".to_string()) },
+            ]
+        );
+        assert_eq!(parsed.optional_terms(), "fast e-mail");
+        assert!(!ParsedQuery::parse("plain words").has_operators());
+        assert_eq!(ParsedQuery::parse(r#""unterminated phrase"#).clauses.len(), 1);
+
+        let built = crate::types::QueryBuilder::new()
+            .match_all()
+            .require("rust")
+            .proximity("zero cost", 2)
+            .exclude("java")
+            .build();
+        assert_eq!(built.q.as_deref(), Some(r#"+rust "zero cost"~2 -java"#));
+    }
+
+    #[test]
+    fn test_translations() {
+        let lucene = ParsedQuery::parse(r#"+rust "zero cost"~2 -java"#).to_lucene_query();
+        assert_eq!(lucene["bool"]["must"][0]["multi_match"]["query"], "rust");
+        assert_eq!(lucene["bool"]["should"][0]["multi_match"]["slop"], 2);
+        assert_eq!(lucene["bool"]["must_not"][0]["multi_match"]["query"], "java");
+
+        let exclusions_only = ParsedQuery::parse("-java").to_lucene_query();
+        assert_eq!(exclusions_only["bool"]["must"], json!([{ "match_all": {} }]));
+
+        let simple = ParsedQuery::parse(r#"+rust "zero cost"~2 -java"#).to_simple_syntax("typesense").unwrap();
+        assert_eq!(simple.text, r#"rust "zero cost" -java"#);
+        assert!(simple.require_all_terms);
+        assert_eq!(simple.warnings.len(), 2, "{:?}", simple.warnings);
+    }
+
+    #[test]
+    fn test_parse_lucene() {
+        let node = parse_lucene(r#"title:"rust book"~1 AND (lang:en OR lang:de) NOT draft:true price:[10 TO *}"#).unwrap();
+        let QueryNode::Bool { must, should, must_not } = &node else { panic!("{:?}", node) };
+        assert!(must.is_empty());
+        assert_eq!(must_not, &vec![QueryNode::Term { field: Some("draft".to_string()), value: "true".to_string() }]);
+        assert_eq!(
+            should[1],
+            QueryNode::Range {
+                field: "price".to_string(),
+                lower: Some(RangeBound { value: "10".to_string(), inclusive: true }),
+                upper: None,
+            }
+        );
+        let QueryNode::Bool { must: and, .. } = &should[0] else { panic!("{:?}", should[0]) };
+        assert_eq!(and[0], QueryNode::Phrase { field: Some("title".to_string()), text: "rust book".to_string(), slop: Some(1) });
+        assert!(matches!(&and[1], QueryNode::Bool { should, .. } if should.len() == 2));
+
+        assert_eq!(parse_lucene("  ").unwrap(), QueryNode::MatchAll);
+        assert_eq!(parse_lucene("*:*").unwrap(), QueryNode::MatchAll);
+        assert_eq!(
+            parse_lucene(r"path:a\:b").unwrap(),
+            QueryNode::Term { field: Some("path".to_string()), value: "a:b".to_string() }
+        );
+        assert_eq!(
+            parse_lucene("tags:(red blue)").unwrap().to_lucene_query()["bool"]["should"][1],
+            json!({ "match": { "tags": { "query": "blue" } } })
+        );
+        assert_eq!(parse_lucene("sku:AB*").unwrap(), QueryNode::Wildcard { field: Some("sku".to_string()), pattern: "AB*".to_string() });
+    }
+
+    #[test]
+    fn test_lucene_errors_and_filters() {
+        for bad in ["(a OR b", "a OR b)", r#""open"#, "title:[1 TO", "a^2", "roam~1", "[1 TO 2]", "a AND"] {
+            assert!(matches!(parse_lucene(bad), Err(SearchError::InvalidQuery(_))), "{}", bad);
+        }
+
+        assert_eq!(lucene_filter_clause("category:books").unwrap(), json!({ "term": { "category": "books" } }));
+        assert_eq!(
+            lucene_filter_clause("price:{-5 TO 100]").unwrap(),
+            json!({ "range": { "price": { "gt": "-5", "lte": "100" } } })
+        );
+        assert_eq!(lucene_filter_clause("in_stock:*").unwrap(), json!({ "exists": { "field": "in_stock" } }));
+        assert!(lucene_filter_clause("category:books OR cheap").is_err());
+    }
+
+    #[test]
+    fn test_patterns_are_limited_and_emulated() {
+        let limits = PatternLimits::default();
+        let parsed = ParsedQuery::parse("serv* /v[0-9]+/ why?");
+        assert_eq!(parsed.clauses[0].clause, Clause::Wildcard("serv*".to_string()));
+        assert_eq!(parsed.clauses[1].clause, Clause::Regex("v[0-9]+".to_string()));
+        assert_eq!(parsed.clauses[2].clause, Clause::Term("why?".to_string()));
+        assert!(parsed.check_patterns(&limits).is_ok());
+
+        assert!(ParsedQuery::parse("*ing").check_patterns(&limits).is_err());
+        assert!(ParsedQuery::parse("/.*ing/").check_patterns(&limits).is_err());
+        let lenient = PatternLimits { max_leading_wildcards: 1, ..limits };
+        assert!(ParsedQuery::parse("*ing").check_patterns(&lenient).is_ok());
+        assert!(limits.check(&"a".repeat(129), false).is_err());
+        assert!(parse_lucene("sku:??12").unwrap().check_patterns(&limits).is_err());
+        assert_eq!(
+            parse_lucene("code:/ab[0-9]/").unwrap().to_lucene_query(),
+            json!({ "regexp": { "code": { "value": "ab[0-9]" } } })
+        );
+
+        let prefix = ParsedQuery::parse("rust serv*").to_simple_syntax("meilisearch").unwrap();
+        assert_eq!(prefix.text, "rust serv");
+        assert!(matches!(ParsedQuery::parse("a* b*").to_simple_syntax("meilisearch"), Err(SearchError::Unsupported)));
+        assert!(matches!(ParsedQuery::parse("te?t").to_simple_syntax("typesense"), Err(SearchError::Unsupported)));
+        assert!(matches!(ParsedQuery::parse("/v[0-9]/").to_simple_syntax("typesense"), Err(SearchError::Unsupported)));
+    }
+
+    #[test]
+    fn test_fuzziness() {
+        assert_eq!(Fuzziness::parse("AUTO").unwrap(), Fuzziness::Auto);
+        assert!(Fuzziness::parse("3").is_err());
+        assert_eq!(parse_fuzziness(Some(" ")).unwrap(), None);
+        assert_eq!(serde_json::from_str::<Fuzziness>("1").unwrap(), Fuzziness::One);
+        assert_eq!(serde_json::to_string(&Fuzziness::Exact).unwrap(), "\"0\"");
+
+        let mut query = ParsedQuery::parse("+sku \"red shoe\" size").to_lucene_query();
+        Fuzziness::Exact.apply_to_lucene(&mut query);
+        assert_eq!(query["bool"]["must"][0]["multi_match"]["fuzziness"], json!(0));
+        assert!(query["bool"]["must"][1]["multi_match"].get("fuzziness").is_none(), "phrases stay exact");
+        assert_eq!(query["bool"]["should"][0]["multi_match"]["fuzziness"], json!(0));
+
+        let exact = ParsedQuery::parse("ab-12 -old").exact_terms().to_simple_syntax("meilisearch").unwrap();
+        assert_eq!(exact.text, "\"ab-12\" -old");
+    }
+
+    #[test]
+    fn test_minimum_should_match() {
+        assert_eq!(MinimumShouldMatch::parse(" ALL ").unwrap(), MinimumShouldMatch::All);
+        assert_eq!(MinimumShouldMatch::parse("-1").unwrap(), MinimumShouldMatch::Count(-1));
+        assert_eq!(MinimumShouldMatch::parse("75 %").unwrap(), MinimumShouldMatch::Percent(75));
+        assert!(MinimumShouldMatch::parse("0").is_err());
+        assert!(MinimumShouldMatch::parse("150%").is_err());
+        assert!(MinimumShouldMatch::parse("most").is_err());
+        assert!(MinimumShouldMatch::Percent(100).is_all());
+        assert!(MinimumShouldMatch::Count(1).is_any());
+        assert_eq!(parse_minimum_should_match(Some("")).unwrap(), None);
+        assert_eq!(serde_json::to_string(&MinimumShouldMatch::Percent(-25)).unwrap(), "\"-25%\"");
+
+        let mut query = ParsedQuery::parse("+red wool socks \"knee high\"").to_lucene_query();
+        MinimumShouldMatch::Count(2).apply_to_lucene(&mut query);
+        assert!(query["bool"]["must"][0]["multi_match"].get("minimum_should_match").is_none(), "required terms stay as they are");
+        assert_eq!(query["bool"]["must"][1]["multi_match"]["query"], json!("wool socks"));
+        assert_eq!(query["bool"]["must"][1]["multi_match"]["minimum_should_match"], json!("2"));
+        assert_eq!(query["bool"]["should"].as_array().unwrap().len(), 1, "the phrase stays optional");
+        assert_eq!(query["bool"]["minimum_should_match"], json!(0));
+    }
+}
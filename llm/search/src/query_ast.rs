@@ -0,0 +1,284 @@
+//! Query trees for callers outside Rust
+//!
+//! Components written in other languages build queries as text in the
+//! Lucene-lite syntax of [`parse_lucene`]. To validate such a query, or to
+//! rewrite it, say adding a clause, they need its tree, and WIT has no
+//! recursive types. A [`QueryAst`] holds the [`QueryNode`] tree as a flat
+//! list: every node is an [`AstNode`], and a boolean node points at its
+//! clauses by their index in the list. [`parse_query`] turns text into such a
+//! list and [`render_query`] turns it back.
+//!
+//! Clauses come before the node that holds them, and the root is named
+//! explicitly, so a list built by hand cannot form a cycle.
+
+use serde::{Deserialize, Serialize};
+use crate::error::{SearchError, SearchResult};
+use crate::query::{parse_lucene, PatternLimits, QueryNode, RangeBound};
+
+/// A node of a flattened query tree
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AstNode {
+    MatchAll,
+
+    Term { field: Option<String>, value: String },
+
+    Phrase { field: Option<String>, text: String, slop: Option<u32> },
+
+    Wildcard { field: Option<String>, pattern: String },
+
+    Regex { field: Option<String>, pattern: String },
+
+    Range { field: String, lower: Option<RangeBound>, upper: Option<RangeBound> },
+
+    /// Clauses given as indexes of earlier nodes
+    Bool { must: Vec<u32>, should: Vec<u32>, must_not: Vec<u32> },
+}
+
+/// A query tree as a list of nodes and the index of its root
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueryAst {
+    pub nodes: Vec<AstNode>,
+    pub root: u32,
+}
+
+impl QueryAst {
+    /// Flatten `node`, clauses first and the root last
+    pub fn from_node(node: &QueryNode) -> Self {
+        let mut nodes = Vec::new();
+        let root = flatten(node, &mut nodes);
+        Self { nodes, root }
+    }
+
+    /// Rebuild the tree
+    ///
+    /// Fails when a clause index does not point at an earlier node, when a
+    /// node is used as a clause twice, or when the root does not exist.
+    pub fn to_node(&self) -> SearchResult<QueryNode> {
+        let invalid = |message: String| SearchError::invalid_query(format!("Invalid query AST: {}", message));
+        let mut built: Vec<Option<QueryNode>> = Vec::with_capacity(self.nodes.len());
+
+        for (position, node) in self.nodes.iter().enumerate() {
+            let mut take = |indexes: &[u32]| -> SearchResult<Vec<QueryNode>> {
+                indexes
+                    .iter()
+                    .map(|&index| {
+                        if index as usize >= position {
+                            return Err(invalid(format!("node {} refers to node {}, which does not come before it", position, index)));
+                        }
+                        built[index as usize]
+                            .take()
+                            .ok_or_else(|| invalid(format!("node {} is used as a clause more than once", index)))
+                    })
+                    .collect()
+            };
+            let node = match node {
+                AstNode::MatchAll => QueryNode::MatchAll,
+                AstNode::Term { field, value } => QueryNode::Term { field: field.clone(), value: value.clone() },
+                AstNode::Phrase { field, text, slop } => {
+                    QueryNode::Phrase { field: field.clone(), text: text.clone(), slop: *slop }
+                }
+                AstNode::Wildcard { field, pattern } => QueryNode::Wildcard { field: field.clone(), pattern: pattern.clone() },
+                AstNode::Regex { field, pattern } => QueryNode::Regex { field: field.clone(), pattern: pattern.clone() },
+                AstNode::Range { field, lower, upper } => {
+                    QueryNode::Range { field: field.clone(), lower: lower.clone(), upper: upper.clone() }
+                }
+                AstNode::Bool { must, should, must_not } => {
+                    QueryNode::Bool { must: take(must)?, should: take(should)?, must_not: take(must_not)? }
+                }
+            };
+            built.push(Some(node));
+        }
+
+        built
+            .get_mut(self.root as usize)
+            .and_then(Option::take)
+            .ok_or_else(|| invalid(format!("root {} is not an unused node", self.root)))
+    }
+}
+
+/// Push `node` and its clauses onto `nodes`, returning the index of `node`
+fn flatten(node: &QueryNode, nodes: &mut Vec<AstNode>) -> u32 {
+    let ast = match node {
+        QueryNode::MatchAll => AstNode::MatchAll,
+        QueryNode::Term { field, value } => AstNode::Term { field: field.clone(), value: value.clone() },
+        QueryNode::Phrase { field, text, slop } => AstNode::Phrase { field: field.clone(), text: text.clone(), slop: *slop },
+        QueryNode::Wildcard { field, pattern } => AstNode::Wildcard { field: field.clone(), pattern: pattern.clone() },
+        QueryNode::Regex { field, pattern } => AstNode::Regex { field: field.clone(), pattern: pattern.clone() },
+        QueryNode::Range { field, lower, upper } => {
+            AstNode::Range { field: field.clone(), lower: lower.clone(), upper: upper.clone() }
+        }
+        QueryNode::Bool { must, should, must_not } => {
+            let mut clauses = |nodes_of: &[QueryNode]| nodes_of.iter().map(|n| flatten(n, nodes)).collect::<Vec<_>>();
+            let must = clauses(must);
+            let should = clauses(should);
+            let must_not = clauses(must_not);
+            AstNode::Bool { must, should, must_not }
+        }
+    };
+    nodes.push(ast);
+    (nodes.len() - 1) as u32
+}
+
+/// Parse query text into its tree, with the errors a search would report
+///
+/// Besides syntax errors this checks wildcards and regular expressions
+/// against the configured [`PatternLimits`].
+pub fn parse_query(text: &str) -> SearchResult<QueryAst> {
+    let node = parse_lucene(text)?;
+    node.check_patterns(&PatternLimits::from_env())?;
+    Ok(QueryAst::from_node(&node))
+}
+
+/// Query text for a tree, which [`parse_query`] reads back as an equivalent tree
+pub fn render_query(ast: &QueryAst) -> SearchResult<String> {
+    Ok(ast.to_node()?.to_query_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn term(value: &str) -> AstNode {
+        AstNode::Term { field: None, value: value.to_string() }
+    }
+
+    fn should(clauses: Vec<u32>) -> AstNode {
+        AstNode::Bool { must: Vec::new(), should: clauses, must_not: Vec::new() }
+    }
+
+    fn invalid(ast: &QueryAst) -> String {
+        match ast.to_node() {
+            Err(SearchError::InvalidQuery(message)) => message,
+            other => panic!("expected InvalidQuery, got {:?}", other),
+        }
+    }
+
+    fn query_term(value: &str) -> QueryNode {
+        QueryNode::Term { field: None, value: value.to_string() }
+    }
+
+    #[test]
+    fn test_clauses_are_flattened_before_their_bool() {
+        let node = QueryNode::Bool {
+            must: vec![query_term("a")],
+            should: vec![query_term("b")],
+            must_not: vec![query_term("c")],
+        };
+        let ast = QueryAst::from_node(&node);
+        assert_eq!(
+            ast,
+            QueryAst {
+                nodes: vec![term("a"), term("b"), term("c"), AstNode::Bool { must: vec![0], should: vec![1], must_not: vec![2] }],
+                root: 3,
+            }
+        );
+        assert_eq!(ast.to_node().unwrap(), node);
+    }
+
+    #[test]
+    fn test_nested_bools_come_before_the_bool_holding_them() {
+        let inner = QueryNode::Bool { must: Vec::new(), should: vec![query_term("a"), query_term("b")], must_not: Vec::new() };
+        let outer = QueryNode::Bool { must: vec![inner, query_term("c")], should: Vec::new(), must_not: Vec::new() };
+        let ast = QueryAst::from_node(&outer);
+        assert_eq!(ast.nodes[2], should(vec![0, 1]));
+        assert_eq!(ast.nodes[4], AstNode::Bool { must: vec![2, 3], should: Vec::new(), must_not: Vec::new() });
+        assert_eq!(ast.to_node().unwrap(), outer);
+    }
+
+    #[test]
+    fn test_a_single_leaf_is_its_own_root() {
+        let ast = QueryAst::from_node(&QueryNode::MatchAll);
+        assert_eq!(ast, QueryAst { nodes: vec![AstNode::MatchAll], root: 0 });
+    }
+
+    #[test]
+    fn test_clauses_must_point_backwards() {
+        let forward = QueryAst { nodes: vec![should(vec![1]), term("a")], root: 0 };
+        assert_eq!(invalid(&forward), "Invalid query AST: node 0 refers to node 1, which does not come before it");
+        let itself = QueryAst { nodes: vec![should(vec![0])], root: 0 };
+        assert_eq!(invalid(&itself), "Invalid query AST: node 0 refers to node 0, which does not come before it");
+    }
+
+    #[test]
+    fn test_a_clause_cannot_be_shared() {
+        let within = QueryAst { nodes: vec![term("a"), should(vec![0, 0])], root: 1 };
+        assert_eq!(invalid(&within), "Invalid query AST: node 0 is used as a clause more than once");
+        let across = QueryAst { nodes: vec![term("a"), should(vec![0]), should(vec![0, 1])], root: 2 };
+        assert_eq!(invalid(&across), "Invalid query AST: node 0 is used as a clause more than once");
+    }
+
+    #[test]
+    fn test_the_root_must_exist_and_be_unused() {
+        assert_eq!(invalid(&QueryAst { nodes: vec![term("a")], root: 3 }), "Invalid query AST: root 3 is not an unused node");
+        assert_eq!(invalid(&QueryAst { nodes: Vec::new(), root: 0 }), "Invalid query AST: root 0 is not an unused node");
+        let clause_as_root = QueryAst { nodes: vec![term("a"), should(vec![0])], root: 0 };
+        assert_eq!(invalid(&clause_as_root), "Invalid query AST: root 0 is not an unused node");
+    }
+
+    #[test]
+    fn test_nodes_outside_the_root_are_ignored() {
+        let ast = QueryAst { nodes: vec![term("a"), term("unused"), should(vec![0])], root: 2 };
+        assert_eq!(render_query(&ast).unwrap(), "a");
+        let leaf_root = QueryAst { nodes: vec![term("a"), term("b")], root: 0 };
+        assert_eq!(render_query(&leaf_root).unwrap(), "a");
+    }
+
+    #[test]
+    fn test_a_clause_added_by_hand_is_rendered() {
+        let mut ast = parse_query("brand:acme").unwrap();
+        ast.nodes.push(AstNode::Range {
+            field: "price".to_string(),
+            lower: Some(RangeBound { value: "10".to_string(), inclusive: true }),
+            upper: None,
+        });
+        ast.nodes.push(AstNode::Bool { must: vec![ast.root, 1], should: Vec::new(), must_not: Vec::new() });
+        ast.root = 2;
+        let rendered = render_query(&ast).unwrap();
+        let reparsed = parse_query(&rendered).unwrap();
+        assert_eq!(reparsed.to_node().unwrap(), ast.to_node().unwrap(), "{}", rendered);
+    }
+
+    #[test]
+    fn test_parse_and_render_round_trip() {
+        for text in [
+            "*:*",
+            r#"title:"zero cost"~2 AND -tag:java"#,
+            "(brand:acme OR brand:globex) AND price:{10 TO *]",
+            r#"path:a\ b\:c te?t* /jo\/hn/ +"AND""#,
+            "created:[-5 TO 2024-01-01} NOT (a && b)",
+        ] {
+            let ast = parse_query(text).unwrap();
+            let rendered = render_query(&ast).unwrap();
+            assert_eq!(parse_query(&rendered).unwrap(), ast, "{} rendered as {}", text, rendered);
+        }
+    }
+
+    #[test]
+    fn test_rendering_normalizes_operators() {
+        assert_eq!(render_query(&parse_query("brand:acme OR brand:globex").unwrap()).unwrap(), "brand:acme brand:globex");
+    }
+
+    #[test]
+    fn test_parse_reports_syntax_and_pattern_errors() {
+        assert!(matches!(parse_query("title:(unbalanced"), Err(SearchError::InvalidQuery(_))));
+        // Leading wildcards are refused by default, a lone `*` is not
+        assert!(matches!(parse_query("title:*ust"), Err(SearchError::InvalidQuery(_))));
+        assert!(parse_query("title:*").is_ok());
+        assert!(parse_query(&format!("title:{}*", "a".repeat(128))).is_err());
+    }
+
+    #[test]
+    fn test_pattern_limits_only_apply_to_parsed_text() {
+        let ast = QueryAst { nodes: vec![AstNode::Wildcard { field: None, pattern: "*ust".to_string() }], root: 0 };
+        assert!(render_query(&ast).is_ok());
+    }
+
+    #[test]
+    fn test_nodes_serialize_tagged_by_variant() {
+        assert_eq!(
+            serde_json::to_value(term("a")).unwrap(),
+            serde_json::json!({ "Term": { "field": null, "value": "a" } })
+        );
+        assert_eq!(serde_json::to_value(AstNode::MatchAll).unwrap(), serde_json::json!("MatchAll"));
+    }
+}
@@ -0,0 +1,668 @@
+//! Queries over the facet and filter limits of a provider, sent in parts
+//!
+//! Providers refuse a query with more facets or filters than they take in
+//! one request, the `max_facets` and `max_filters` of its
+//! [`PerformanceLimits`]. Rather than failing with the provider's 400, such a
+//! query is split:
+//!
+//! - Facets beyond `max_facets` are counted by extra requests asking for no
+//!   hits, and their counts merged into the facets of the first request.
+//! - Filters beyond `max_filters` are split into groups, each sent as a query
+//!   of its own. The hits of the first group keep their order, and only those
+//!   matching every other group are returned. Each group reads at most
+//!   [`SPLIT_MAX_MATCHES_ENV`] matches; when one has more, the total is a
+//!   lower bound and `total_exact` is false.
+//!
+//! Facet counts cover one request's filters, so [`SplitLimits::check`]
+//! refuses facets over split filters, as well as cursors, which cannot page
+//! through an intersection.
+
+use std::collections::HashSet;
+use std::future::Future;
+use futures::future::try_join_all;
+use log::{debug, warn};
+use serde_json::Value;
+use crate::capabilities::PerformanceLimits;
+use crate::config::env_helpers::get_env_parsed;
+use crate::cursor::DEFAULT_PER_PAGE;
+use crate::error::{SearchError, SearchResult};
+use crate::types::{SearchQuery, SearchResults};
+
+/// Environment variable holding the matches read per filter group of a split query
+pub const SPLIT_MAX_MATCHES_ENV: &str = "SEARCH_SPLIT_MAX_MATCHES";
+
+/// Default matches read per filter group of a split query
+pub const DEFAULT_MAX_MATCHES: u32 = 1_000;
+
+/// Hits fetched per request while reading the matches of a filter group
+const FETCH_PAGE: u32 = 250;
+
+/// A provider's query, as far as splitting it goes
+pub trait SplitQuery: Clone {
+    fn facets(&self) -> &[String];
+
+    fn filters(&self) -> &[String];
+
+    fn cursor(&self) -> Option<&str>;
+
+    /// Page, hits per page and offset asked for
+    fn paging(&self) -> (Option<u32>, Option<u32>, Option<u32>);
+
+    /// The query with `facets` and `filters` in place of its own
+    fn with_parts(&self, facets: &[String], filters: &[String]) -> Self;
+
+    /// The query asking for `per_page` hits from `offset`, without a cursor
+    fn with_window(&self, offset: u32, per_page: u32) -> Self;
+}
+
+/// A provider's results, as far as merging them goes
+pub trait SplitResults {
+    /// IDs of the hits, in order
+    fn hit_ids(&self) -> Vec<&str>;
+
+    fn total(&self) -> Option<u32>;
+
+    fn facets_mut(&mut self) -> &mut Option<String>;
+
+    /// Append the hits of the following page
+    fn extend_hits(&mut self, page: Self);
+
+    /// Keep the hits whose ID `keep` accepts
+    fn retain_hits(&mut self, keep: &dyn Fn(&str) -> bool);
+
+    /// Cut the hits down to `per_page` from `offset`, as a page of `total` matches
+    fn set_page(&mut self, offset: u32, per_page: u32, total: u32, total_exact: bool);
+}
+
+/// Limits a query is split to fit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SplitLimits {
+    pub max_facets: Option<usize>,
+    pub max_filters: Option<usize>,
+
+    /// Hits fetched per request while reading the matches of a filter group
+    pub page_size: u32,
+
+    /// Matches read per filter group
+    pub max_matches: u32,
+}
+
+impl SplitLimits {
+    /// Limits of a provider, with the matches of `SEARCH_SPLIT_MAX_MATCHES`
+    pub fn new(limits: &PerformanceLimits) -> Self {
+        Self {
+            max_facets: limits.max_facets.map(|max| max.max(1) as usize),
+            max_filters: limits.max_filters.map(|max| max.max(1) as usize),
+            page_size: limits.max_results_per_page.map_or(FETCH_PAGE, |max| max.clamp(1, FETCH_PAGE)),
+            max_matches: get_env_parsed::<u32>(SPLIT_MAX_MATCHES_ENV).ok().flatten().unwrap_or(DEFAULT_MAX_MATCHES),
+        }
+    }
+
+    /// Whether `query` is over the limits
+    pub fn splits(&self, query: &impl SplitQuery) -> bool {
+        self.max_facets.is_some_and(|max| query.facets().len() > max) || self.splits_filters(query)
+    }
+
+    fn splits_filters(&self, query: &impl SplitQuery) -> bool {
+        self.max_filters.is_some_and(|max| query.filters().len() > max)
+    }
+
+    /// Refuse a query whose filters would be split along with facets or a cursor
+    pub fn check(&self, query: &impl SplitQuery) -> SearchResult<()> {
+        if !self.splits_filters(query) {
+            return Ok(());
+        }
+        let over = format!(
+            "{} filters are over the limit of {} per request",
+            query.filters().len(),
+            self.max_filters.unwrap_or_default()
+        );
+        if !query.facets().is_empty() {
+            return Err(SearchError::invalid_query(format!(
+                "{}; facets cannot be counted over filters sent in parts",
+                over
+            )));
+        }
+        if query.cursor().is_some() {
+            return Err(SearchError::invalid_query(format!(
+                "{}; filters sent in parts are paged with page or offset, not a cursor",
+                over
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Run `query` with `search`, in parts when it is over `limits`
+///
+/// Queries are checked with [`SplitLimits::check`] first. Errors of `search`
+/// are returned as they are, so providers keep their own error type.
+pub async fn search_split<Q, R, E, F, Fut>(query: &Q, limits: &SplitLimits, search: F) -> Result<R, E>
+where
+    Q: SplitQuery,
+    R: SplitResults,
+    F: Fn(Q) -> Fut,
+    Fut: Future<Output = Result<R, E>>,
+{
+    let filter_groups = groups(query.filters(), limits.max_filters);
+    if filter_groups.len() > 1 {
+        return search_filter_groups(query, &filter_groups, limits, &search).await;
+    }
+
+    let facet_groups = groups(query.facets(), limits.max_facets);
+    if facet_groups.len() <= 1 {
+        return search(query.clone()).await;
+    }
+    debug!("Counting {} facets in {} requests", query.facets().len(), facet_groups.len());
+    let filters = query.filters();
+    let first = search(query.with_parts(&facet_groups[0], filters));
+    let rest = try_join_all(
+        facet_groups[1..].iter().map(|facets| search(query.with_parts(facets, filters).with_window(0, 0))),
+    );
+    let (mut results, rest) = futures::try_join!(first, rest)?;
+    for mut part in rest {
+        merge_facets(results.facets_mut(), part.facets_mut().take());
+    }
+    Ok(results)
+}
+
+/// Hits of the first filter group that match every other group
+async fn search_filter_groups<Q, R, E, F, Fut>(
+    query: &Q,
+    groups: &[Vec<String>],
+    limits: &SplitLimits,
+    search: &F,
+) -> Result<R, E>
+where
+    Q: SplitQuery,
+    R: SplitResults,
+    F: Fn(Q) -> Fut,
+    Fut: Future<Output = Result<R, E>>,
+{
+    debug!("Intersecting {} filters in {} groups", query.filters().len(), groups.len());
+
+    let Some((first, rest)) = groups.split_first() else {
+        return search(query.clone()).await;
+    };
+    let first = read_matches(query.with_parts(&[], first), limits, search);
+    let rest = try_join_all(rest.iter().map(|filters| read_matches(query.with_parts(&[], filters), limits, search)));
+    let ((mut results, mut complete), rest) = futures::try_join!(first, rest)?;
+    let mut allowed: Option<HashSet<String>> = None;
+    for (part, part_complete) in rest {
+        complete &= part_complete;
+        let ids = part.hit_ids();
+        allowed = Some(match allowed {
+            Some(allowed) => ids.into_iter().filter(|id| allowed.contains(*id)).map(String::from).collect(),
+            None => ids.into_iter().map(String::from).collect(),
+        });
+    }
+    let allowed = allowed.unwrap_or_default();
+    results.retain_hits(&|id| allowed.contains(id));
+
+    let (offset, per_page) = window(query);
+    let total = results.hit_ids().len() as u32;
+    results.set_page(offset, per_page, total, complete);
+    Ok(results)
+}
+
+/// Up to `max_matches` hits of `query`, and whether they are all of its matches
+async fn read_matches<Q, R, E, F, Fut>(query: Q, limits: &SplitLimits, search: &F) -> Result<(R, bool), E>
+where
+    Q: SplitQuery,
+    R: SplitResults,
+    F: Fn(Q) -> Fut,
+    Fut: Future<Output = Result<R, E>>,
+{
+    let mut asked = limits.page_size.min(limits.max_matches);
+    let mut results = search(query.with_window(0, asked)).await?;
+    let mut read = results.hit_ids().len() as u32;
+    let mut last = read;
+    while last == asked && read < limits.max_matches && results.total().is_none_or(|total| read < total) {
+        asked = limits.page_size.min(limits.max_matches - read);
+        let page = search(query.with_window(read, asked)).await?;
+        last = page.hit_ids().len() as u32;
+        read += last;
+        results.extend_hits(page);
+    }
+    let complete = last < asked || results.total().is_some_and(|total| read >= total);
+    Ok((results, complete))
+}
+
+/// `items` in groups of at most `max`
+fn groups(items: &[String], max: Option<usize>) -> Vec<Vec<String>> {
+    match max {
+        Some(max) if items.len() > max => items.chunks(max).map(<[String]>::to_vec).collect(),
+        _ => vec![items.to_vec()],
+    }
+}
+
+/// First hit and hits per page of the page `query` asks for
+fn window(query: &impl SplitQuery) -> (u32, u32) {
+    let (page, per_page, offset) = query.paging();
+    let per_page = per_page.unwrap_or(DEFAULT_PER_PAGE);
+    let offset = offset.unwrap_or_else(|| page.unwrap_or(1).saturating_sub(1).saturating_mul(per_page));
+    (offset, per_page)
+}
+
+/// Add the facets of a part to those of the first request
+///
+/// Facets keyed by field, as most providers return them, are joined by key;
+/// facets in a list, as Typesense returns them, are appended. Facets of
+/// another shape are left out with a warning.
+pub fn merge_facets(facets: &mut Option<String>, part: Option<String>) {
+    let Some(part) = part else {
+        return;
+    };
+    let Some(first) = facets.as_deref() else {
+        *facets = Some(part);
+        return;
+    };
+    let merged = match (serde_json::from_str(first), serde_json::from_str(&part)) {
+        (Ok(Value::Object(mut first)), Ok(Value::Object(part))) => {
+            first.extend(part);
+            Value::Object(first)
+        }
+        (Ok(Value::Array(mut first)), Ok(Value::Array(part))) => {
+            first.extend(part);
+            Value::Array(first)
+        }
+        _ => {
+            warn!("Facets of a split query differ in shape; leaving out {}", part);
+            return;
+        }
+    };
+    *facets = Some(merged.to_string());
+}
+
+impl SplitQuery for SearchQuery {
+    fn facets(&self) -> &[String] {
+        &self.facets
+    }
+
+    fn filters(&self) -> &[String] {
+        &self.filters
+    }
+
+    fn cursor(&self) -> Option<&str> {
+        self.config.as_ref().and_then(|c| c.cursor.as_deref())
+    }
+
+    fn paging(&self) -> (Option<u32>, Option<u32>, Option<u32>) {
+        (self.page, self.per_page, self.offset)
+    }
+
+    fn with_parts(&self, facets: &[String], filters: &[String]) -> Self {
+        Self { facets: facets.to_vec(), filters: filters.to_vec(), ..self.clone() }
+    }
+
+    fn with_window(&self, offset: u32, per_page: u32) -> Self {
+        let mut query = Self { page: None, per_page: Some(per_page), offset: Some(offset), ..self.clone() };
+        if let Some(config) = query.config.as_mut() {
+            config.cursor = None;
+        }
+        query
+    }
+}
+
+impl SplitResults for SearchResults {
+    fn hit_ids(&self) -> Vec<&str> {
+        self.hits.iter().map(|hit| hit.id.as_str()).collect()
+    }
+
+    fn total(&self) -> Option<u32> {
+        self.total
+    }
+
+    fn facets_mut(&mut self) -> &mut Option<String> {
+        &mut self.facets
+    }
+
+    fn extend_hits(&mut self, page: Self) {
+        self.hits.extend(page.hits);
+        self.took_ms = match (self.took_ms, page.took_ms) {
+            (Some(took), Some(more)) => Some(took.saturating_add(more)),
+            (took, more) => took.or(more),
+        };
+    }
+
+    fn retain_hits(&mut self, keep: &dyn Fn(&str) -> bool) {
+        self.hits.retain(|hit| keep(&hit.id));
+    }
+
+    fn set_page(&mut self, offset: u32, per_page: u32, total: u32, total_exact: bool) {
+        self.hits = self.hits.drain(..).skip(offset as usize).take(per_page as usize).collect();
+        self.total = Some(total);
+        self.total_exact = Some(total_exact);
+        self.page = Some(offset.checked_div(per_page).map_or(1, |page| page + 1));
+        self.per_page = Some(per_page);
+        self.next_cursor = None;
+        self.groups = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use futures::executor::block_on;
+    use crate::types::{SearchConfig, SearchHit};
+
+    fn results(ids: &[&str], total: u32, facets: Option<&str>) -> SearchResults {
+        SearchResults {
+            total: Some(total),
+            page: None,
+            per_page: None,
+            hits: ids
+                .iter()
+                .map(|id| SearchHit {
+                    id: id.to_string(),
+                    score: None,
+                    content: None,
+                    highlights: None,
+                    explanation: None,
+                    distance_meters: None,
+                })
+                .collect(),
+            facets: facets.map(String::from),
+            took_ms: Some(1),
+            truncated: false,
+            request_id: None,
+            groups: None,
+            next_cursor: None,
+            total_exact: None,
+            facet_metadata: Vec::new(),
+            relaxations: Vec::new(),
+            original_query: None,
+            corrected_query: None,
+        }
+    }
+
+    fn query(facets: &[&str], filters: &[&str]) -> SearchQuery {
+        SearchQuery {
+            q: Some("shoes".to_string()),
+            filters: filters.iter().map(|f| f.to_string()).collect(),
+            sort: Vec::new(),
+            facets: facets.iter().map(|f| f.to_string()).collect(),
+            page: Some(1),
+            per_page: Some(2),
+            offset: None,
+            highlight: None,
+            config: None,
+        }
+    }
+
+    fn limits() -> SplitLimits {
+        SplitLimits { max_facets: Some(2), max_filters: Some(2), page_size: 3, max_matches: 5 }
+    }
+
+    fn performance(max_facets: Option<u32>, max_filters: Option<u32>, max_per_page: Option<u32>) -> PerformanceLimits {
+        PerformanceLimits {
+            max_batch_size: None,
+            max_query_length: None,
+            max_facets,
+            max_filters,
+            max_results_per_page: max_per_page,
+            default_timeout_seconds: None,
+            rate_limit_rps: None,
+        }
+    }
+
+    fn invalid(result: SearchResult<()>) -> String {
+        match result {
+            Err(SearchError::InvalidQuery(message)) => message,
+            other => panic!("expected InvalidQuery, got {:?}", other),
+        }
+    }
+
+    /// The window of `ids` a query asks for, with `total` set when the backend knows it
+    fn window_of(ids: &[&str], q: &SearchQuery, total_known: bool) -> SearchResults {
+        let start = (q.offset.unwrap_or(0) as usize).min(ids.len());
+        let end = (start + q.per_page.unwrap_or(DEFAULT_PER_PAGE) as usize).min(ids.len());
+        let mut page = results(&ids[start..end], ids.len() as u32, None);
+        if !total_known {
+            page.total = None;
+        }
+        page
+    }
+
+    /// Search over filter groups: a query with `f1` ranks `ranked`, any other group matches `matched`
+    fn intersect(
+        split: &SearchQuery,
+        limits: &SplitLimits,
+        ranked: &[&str],
+        matched: &[&str],
+    ) -> (SearchResults, Vec<(Vec<String>, u32, u32)>) {
+        let sent = RefCell::new(Vec::new());
+        let merged = block_on(search_split(split, limits, |q: SearchQuery| {
+            sent.borrow_mut().push((q.filters.clone(), q.offset.unwrap(), q.per_page.unwrap()));
+            let ids = if q.filters.iter().any(|f| f == "f1") { ranked } else { matched };
+            let page = window_of(ids, &q, true);
+            async move { SearchResult::Ok(page) }
+        }))
+        .unwrap();
+        (merged, sent.into_inner())
+    }
+
+    #[test]
+    fn test_provider_limits_are_clamped() {
+        let limits = SplitLimits::new(&performance(Some(0), Some(0), Some(0)));
+        assert_eq!((limits.max_facets, limits.max_filters, limits.page_size), (Some(1), Some(1), 1));
+        assert_eq!(SplitLimits::new(&performance(None, None, Some(1_000))).page_size, FETCH_PAGE);
+        assert_eq!(SplitLimits::new(&performance(None, None, None)).page_size, FETCH_PAGE);
+    }
+
+    #[test]
+    fn test_queries_at_the_limits_are_not_split() {
+        assert!(!limits().splits(&query(&["brand", "color"], &["f1", "f2"])));
+        assert!(limits().splits(&query(&[], &["f1", "f2", "f3"])));
+        let unlimited = SplitLimits { max_facets: None, max_filters: None, ..limits() };
+        assert!(!unlimited.splits(&query(&["a", "b", "c", "d"], &["f1", "f2", "f3", "f4"])));
+    }
+
+    #[test]
+    fn test_facets_are_refused_only_over_split_filters() {
+        assert!(limits().check(&query(&["a", "b", "c"], &["f1"])).is_ok());
+        assert_eq!(
+            invalid(limits().check(&query(&["brand"], &["f1", "f2", "f3"]))),
+            "3 filters are over the limit of 2 per request; facets cannot be counted over filters sent in parts"
+        );
+    }
+
+    #[test]
+    fn test_cursors_are_refused_only_over_split_filters() {
+        let config = SearchConfig { cursor: Some("next".to_string()), ..SearchConfig::default() };
+        let with_cursor = |filters: &[&str]| SearchQuery { config: Some(config.clone()), ..query(&[], filters) };
+        assert!(limits().check(&with_cursor(&["f1", "f2"])).is_ok());
+        assert!(invalid(limits().check(&with_cursor(&["f1", "f2", "f3"]))).ends_with("paged with page or offset, not a cursor"));
+    }
+
+    #[test]
+    fn test_queries_within_the_limits_are_sent_unchanged() {
+        let original = query(&["brand"], &["f1"]);
+        let sent = RefCell::new(Vec::new());
+        block_on(search_split(&original, &limits(), |q: SearchQuery| {
+            sent.borrow_mut().push(q);
+            async { SearchResult::Ok(results(&["a"], 1, None)) }
+        }))
+        .unwrap();
+        let sent = sent.into_inner();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(serde_json::to_value(&sent[0]).unwrap(), serde_json::to_value(&original).unwrap());
+    }
+
+    #[test]
+    fn test_extra_facet_requests_ask_for_no_hits() {
+        let sent = RefCell::new(Vec::new());
+        let split = query(&["brand", "color", "size", "fit", "cut"], &["in_stock:true"]);
+        let merged = block_on(search_split(&split, &limits(), |q: SearchQuery| {
+            sent.borrow_mut().push((q.facets.len(), q.filters.clone(), q.page, q.per_page));
+            let facets = q.facets.iter().map(|f| format!("\"{}\":{{}}", f)).collect::<Vec<_>>().join(",");
+            let page = results(&["a", "b"], 7, Some(format!("{{{}}}", facets).as_str()));
+            async move { SearchResult::Ok(page) }
+        }))
+        .unwrap();
+
+        let filters = vec!["in_stock:true".to_string()];
+        assert_eq!(
+            sent.into_inner(),
+            [(2, filters.clone(), Some(1), Some(2)), (2, filters.clone(), None, Some(0)), (1, filters, None, Some(0))]
+        );
+        let facets: Value = serde_json::from_str(merged.facets.as_deref().unwrap()).unwrap();
+        assert_eq!(facets.as_object().unwrap().len(), 5);
+        assert_eq!(merged.total, Some(7));
+    }
+
+    #[test]
+    fn test_a_failed_facet_request_fails_the_search() {
+        let split = query(&["brand", "color", "size"], &[]);
+        let result = block_on(search_split(&split, &limits(), |q: SearchQuery| async move {
+            match q.per_page {
+                Some(0) => Err(SearchError::Timeout { remaining_ms: None }),
+                _ => Ok(results(&["a"], 1, Some("{}"))),
+            }
+        }));
+        assert!(matches!(result, Err(SearchError::Timeout { .. })));
+    }
+
+    #[test]
+    fn test_every_filter_group_has_to_match() {
+        // Groups [f1, f2], [f3, f4] and [f5]; the last two match b..f, the intersection of both
+        let split = SearchQuery { per_page: Some(10), ..query(&[], &["f1", "f2", "f3", "f4", "f5"]) };
+        let wide = SplitLimits { max_matches: 10, ..limits() };
+        let (merged, sent) = intersect(&split, &wide, &["a", "b", "c", "d"], &["d", "c", "b", "z"]);
+        assert_eq!(merged.hit_ids(), ["b", "c", "d"]);
+        assert_eq!((merged.total, merged.total_exact), (Some(3), Some(true)));
+        let groups: HashSet<Vec<String>> = sent.into_iter().map(|(filters, _, _)| filters).collect();
+        assert_eq!(groups.len(), 3);
+    }
+
+    #[test]
+    fn test_the_first_group_keeps_its_ranking() {
+        let split = SearchQuery { per_page: Some(10), ..query(&[], &["f1", "f2", "f3"]) };
+        let (merged, _) = intersect(&split, &limits(), &["c", "a", "b"], &["a", "b", "c"]);
+        assert_eq!(merged.hit_ids(), ["c", "a", "b"]);
+    }
+
+    #[test]
+    fn test_reading_stops_at_a_short_page() {
+        let split = SearchQuery { per_page: Some(10), ..query(&[], &["f1", "f2", "f3"]) };
+        let wide = SplitLimits { max_matches: 100, ..limits() };
+        let (merged, sent) = intersect(&split, &wide, &["a", "b", "c", "d"], &["a", "b", "c", "d"]);
+        let first_group: Vec<(u32, u32)> =
+            sent.iter().filter(|(filters, _, _)| filters[0] == "f1").map(|&(_, offset, per_page)| (offset, per_page)).collect();
+        assert_eq!(first_group, [(0, 3), (3, 3)]);
+        assert_eq!(merged.total_exact, Some(true));
+    }
+
+    #[test]
+    fn test_reading_stops_at_the_known_total() {
+        let split = SearchQuery { per_page: Some(10), ..query(&[], &["f1", "f2", "f3"]) };
+        let wide = SplitLimits { max_matches: 100, ..limits() };
+        let (merged, sent) = intersect(&split, &wide, &["a", "b", "c"], &["a", "b", "c"]);
+        assert_eq!(sent.len(), 2);
+        assert_eq!(merged.total_exact, Some(true));
+    }
+
+    #[test]
+    fn test_reading_without_a_total_continues_until_a_short_page() {
+        let split = SearchQuery { per_page: Some(10), ..query(&[], &["f1", "f2", "f3"]) };
+        let wide = SplitLimits { max_matches: 100, ..limits() };
+        let calls = RefCell::new(0);
+        let merged = block_on(search_split(&split, &wide, |q: SearchQuery| {
+            *calls.borrow_mut() += 1;
+            let page = window_of(&["a", "b", "c"], &q, false);
+            async move { SearchResult::Ok(page) }
+        }))
+        .unwrap();
+        // A full page, then an empty one, for each of the two groups
+        assert_eq!(*calls.borrow(), 4);
+        assert_eq!((merged.total, merged.total_exact), (Some(3), Some(true)));
+    }
+
+    #[test]
+    fn test_the_last_read_asks_only_for_the_matches_left() {
+        let split = query(&[], &["f1", "f2", "f3"]);
+        let ids = ["a", "b", "c", "d", "e", "f", "g"];
+        let (merged, sent) = intersect(&split, &limits(), &ids, &ids);
+        let first_group: Vec<(u32, u32)> =
+            sent.iter().filter(|(filters, _, _)| filters[0] == "f1").map(|&(_, offset, per_page)| (offset, per_page)).collect();
+        assert_eq!(first_group, [(0, 3), (3, 2)]);
+        assert_eq!((merged.total, merged.total_exact), (Some(5), Some(false)));
+    }
+
+    #[test]
+    fn test_an_incomplete_later_group_makes_the_total_a_lower_bound() {
+        let split = SearchQuery { per_page: Some(10), ..query(&[], &["f1", "f2", "f3"]) };
+        let (merged, _) = intersect(&split, &limits(), &["a", "b"], &["a", "b", "c", "d", "e", "f"]);
+        assert_eq!(merged.hit_ids(), ["a", "b"]);
+        assert_eq!(merged.total_exact, Some(false));
+    }
+
+    #[test]
+    fn test_intersections_are_paged_by_offset_or_page() {
+        let ids = ["a", "b", "c", "d", "e"];
+        let by_offset = SearchQuery { page: None, offset: Some(3), ..query(&[], &["f1", "f2", "f3"]) };
+        let (merged, _) = intersect(&by_offset, &limits(), &ids, &ids);
+        assert_eq!(merged.hit_ids(), ["d", "e"]);
+        assert_eq!((merged.page, merged.per_page), (Some(2), Some(2)));
+
+        let page_zero = SearchQuery { page: Some(0), ..query(&[], &["f1", "f2", "f3"]) };
+        let (merged, _) = intersect(&page_zero, &limits(), &ids, &ids);
+        assert_eq!(merged.hit_ids(), ["a", "b"]);
+
+        let beyond = SearchQuery { page: Some(9), ..query(&[], &["f1", "f2", "f3"]) };
+        let (merged, _) = intersect(&beyond, &limits(), &ids, &ids);
+        assert!(merged.hit_ids().is_empty());
+        assert_eq!(merged.total, Some(5));
+    }
+
+    #[test]
+    fn test_window_requests_drop_the_cursor() {
+        let config = SearchConfig { cursor: Some("next".to_string()), ..SearchConfig::default() };
+        let windowed = SearchQuery { config: Some(config), ..query(&[], &[]) }.with_window(6, 3);
+        assert_eq!((windowed.page, windowed.offset, windowed.per_page), (None, Some(6), Some(3)));
+        assert_eq!(windowed.cursor(), None);
+    }
+
+    #[test]
+    fn test_extended_hits_add_up_their_time() {
+        let mut first = results(&["a"], 2, None);
+        first.extend_hits(results(&["b"], 2, None));
+        assert_eq!((first.hit_ids(), first.took_ms), (vec!["a", "b"], Some(2)));
+        first.took_ms = None;
+        first.extend_hits(results(&["c"], 2, None));
+        assert_eq!(first.took_ms, Some(1));
+    }
+
+    #[test]
+    fn test_facets_keyed_by_field_are_joined() {
+        let mut facets = Some(r#"{"brand":{"acme":3}}"#.to_string());
+        merge_facets(&mut facets, Some(r#"{"size":{"m":1}}"#.to_string()));
+        assert_eq!(facets.as_deref(), Some(r#"{"brand":{"acme":3},"size":{"m":1}}"#));
+    }
+
+    #[test]
+    fn test_facet_lists_are_appended() {
+        let mut listed = Some(r#"[{"field_name":"brand"}]"#.to_string());
+        merge_facets(&mut listed, Some(r#"[{"field_name":"size"}]"#.to_string()));
+        assert_eq!(listed.as_deref(), Some(r#"[{"field_name":"brand"},{"field_name":"size"}]"#));
+    }
+
+    #[test]
+    fn test_facets_of_another_shape_are_left_out() {
+        let first = r#"[{"field_name":"brand"}]"#;
+        let mut facets = Some(first.to_string());
+        merge_facets(&mut facets, Some("{}".to_string()));
+        merge_facets(&mut facets, Some("not json".to_string()));
+        assert_eq!(facets.as_deref(), Some(first));
+    }
+
+    #[test]
+    fn test_missing_facets_on_either_side() {
+        let mut facets = None;
+        merge_facets(&mut facets, Some("{}".to_string()));
+        assert_eq!(facets.as_deref(), Some("{}"));
+        merge_facets(&mut facets, None);
+        assert_eq!(facets.as_deref(), Some("{}"));
+    }
+}
@@ -0,0 +1,497 @@
+//! Per-tenant query and indexing quotas
+//!
+//! A multi-tenant search component can cap how many queries per minute and
+//! how many indexed documents per day each tenant may send to the backend.
+//! Limits come from [`QuotaConfig`] (part of [`crate::config::SearchConfig`],
+//! or read directly from the `SEARCH_QUOTA_*` variables) and are enforced as
+//! token buckets: a tenant can burst up to its whole allowance and then
+//! continues at the average rate. Exceeding a quota fails with
+//! `SearchError::RateLimited` carrying the time until the request would fit.
+//!
+//! The tenant of an operation is the one set with [`tenant_scope`]; failing
+//! that, the part of the index name before `SEARCH_QUOTA_TENANT_SEPARATOR`
+//! when one is configured (`acme__products` belongs to `acme`), and otherwise
+//! the shared [`DEFAULT_TENANT`]. Buckets live in worker memory, so each
+//! worker enforces its quotas independently.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use log::warn;
+use crate::config::env_helpers::get_env_parsed;
+use crate::error::{SearchError, SearchResult};
+
+/// Environment variable holding the default queries-per-minute limit
+pub const QUOTA_QUERIES_ENV: &str = "SEARCH_QUOTA_QUERIES_PER_MINUTE";
+
+/// Environment variable holding the default indexed-documents-per-day limit
+pub const QUOTA_DOCS_ENV: &str = "SEARCH_QUOTA_DOCS_PER_DAY";
+
+/// Environment variable with per-tenant limits as `tenant:queries:docs` entries
+pub const QUOTA_TENANTS_ENV: &str = "SEARCH_QUOTA_TENANTS";
+
+/// Environment variable holding the separator between tenant and index name
+pub const QUOTA_TENANT_SEPARATOR_ENV: &str = "SEARCH_QUOTA_TENANT_SEPARATOR";
+
+/// Tenant of operations that cannot be attributed to a specific one
+pub const DEFAULT_TENANT: &str = "default";
+
+const MINUTE: Duration = Duration::from_secs(60);
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Limits for one tenant; `None` means unlimited
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuotaLimits {
+    pub max_queries_per_minute: Option<u32>,
+    pub max_docs_per_day: Option<u64>,
+}
+
+/// Quota settings for all tenants
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct QuotaConfig {
+    /// Limits for tenants without an entry in `tenants`
+    #[serde(default)]
+    pub defaults: QuotaLimits,
+
+    /// Per-tenant overrides
+    #[serde(default)]
+    pub tenants: HashMap<String, QuotaLimits>,
+
+    /// Separator that splits a tenant prefix off index names
+    #[serde(default)]
+    pub tenant_separator: Option<String>,
+}
+
+impl QuotaConfig {
+    /// Read the `SEARCH_QUOTA_*` variables; everything is unlimited when they are unset
+    pub fn from_env() -> Self {
+        let defaults = QuotaLimits {
+            max_queries_per_minute: get_env_parsed::<u32>(QUOTA_QUERIES_ENV).ok().flatten(),
+            max_docs_per_day: get_env_parsed::<u64>(QUOTA_DOCS_ENV).ok().flatten(),
+        };
+        let tenants = std::env::var(QUOTA_TENANTS_ENV)
+            .map(|spec| Self::parse_tenants(&spec))
+            .unwrap_or_default();
+        let tenant_separator = std::env::var(QUOTA_TENANT_SEPARATOR_ENV).ok().filter(|s| !s.is_empty());
+
+        Self { defaults, tenants, tenant_separator }
+    }
+
+    /// Parse `tenant:queries:docs` entries separated by commas
+    ///
+    /// An empty limit means unlimited, so `batch::50000` caps only indexing.
+    /// Malformed entries are skipped with a warning.
+    pub fn parse_tenants(spec: &str) -> HashMap<String, QuotaLimits> {
+        let mut tenants = HashMap::new();
+        for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let parts: Vec<&str> = entry.split(':').map(str::trim).collect();
+            let limit = |part: &str| -> Result<Option<u64>, ()> {
+                if part.is_empty() {
+                    Ok(None)
+                } else {
+                    part.parse::<u64>().map(Some).map_err(|_| ())
+                }
+            };
+
+            match parts.as_slice() {
+                [tenant, queries, docs] if !tenant.is_empty() => match (limit(queries), limit(docs)) {
+                    (Ok(queries), Ok(docs)) => {
+                        let limits = QuotaLimits {
+                            max_queries_per_minute: queries.map(|q| q.min(u64::from(u32::MAX)) as u32),
+                            max_docs_per_day: docs,
+                        };
+                        tenants.insert(tenant.to_string(), limits);
+                    }
+                    _ => warn!("Ignoring quota entry with invalid limits: {}", entry),
+                },
+                _ => warn!("Ignoring malformed quota entry: {}", entry),
+            }
+        }
+        tenants
+    }
+
+    /// Limits that apply to `tenant`
+    pub fn limits_for(&self, tenant: &str) -> QuotaLimits {
+        self.tenants.get(tenant).copied().unwrap_or(self.defaults)
+    }
+
+    /// Whether any limit is configured at all
+    pub fn is_enabled(&self) -> bool {
+        self.defaults != QuotaLimits::default() || !self.tenants.is_empty()
+    }
+}
+
+thread_local! {
+    static TENANT: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Attributes operations on this thread to a tenant until dropped
+#[derive(Debug)]
+pub struct TenantScope {
+    previous: Option<String>,
+}
+
+impl Drop for TenantScope {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        TENANT.with(|tenant| *tenant.borrow_mut() = previous);
+    }
+}
+
+/// Tenant set for this thread with [`tenant_scope`], if any
+pub fn current_tenant() -> Option<String> {
+    TENANT.with(|tenant| tenant.borrow().clone())
+}
+
+/// Run the following operations on behalf of `tenant`
+pub fn tenant_scope(tenant: impl Into<String>) -> TenantScope {
+    let tenant = tenant.into();
+    TENANT.with(|current| TenantScope { previous: current.borrow_mut().replace(tenant) })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Resource {
+    Queries,
+    Documents,
+}
+
+/// Token bucket refilled continuously over `period`
+#[derive(Debug, Clone)]
+pub(crate) struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    per_second: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    pub(crate) fn new(capacity: u64, period: Duration, now: Instant) -> Self {
+        let capacity = capacity as f64;
+        Self {
+            capacity,
+            tokens: capacity,
+            per_second: capacity / period.as_secs_f64(),
+            updated: now,
+        }
+    }
+
+    /// Take `amount` tokens, or return how long until they are available
+    pub(crate) fn take(&mut self, amount: f64, now: Instant) -> Result<(), Duration> {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.per_second).min(self.capacity);
+        self.updated = now;
+
+        if self.tokens >= amount {
+            self.tokens -= amount;
+            return Ok(());
+        }
+        if self.per_second <= 0.0 {
+            return Err(Duration::MAX);
+        }
+        Err(Duration::from_secs_f64((amount - self.tokens) / self.per_second))
+    }
+}
+
+/// Tracks quota usage of all tenants
+#[derive(Debug, Default)]
+pub struct QuotaEnforcer {
+    config: QuotaConfig,
+    buckets: Mutex<HashMap<(String, Resource), TokenBucket>>,
+}
+
+impl QuotaEnforcer {
+    pub fn new(config: QuotaConfig) -> Self {
+        Self { config, buckets: Mutex::new(HashMap::new()) }
+    }
+
+    pub fn config(&self) -> &QuotaConfig {
+        &self.config
+    }
+
+    /// Tenant an operation on `index` is attributed to
+    pub fn tenant_for(&self, index: &str) -> String {
+        if let Some(tenant) = current_tenant() {
+            return tenant;
+        }
+        match &self.config.tenant_separator {
+            Some(separator) => match index.split_once(separator.as_str()) {
+                Some((tenant, _)) if !tenant.is_empty() => tenant.to_string(),
+                _ => DEFAULT_TENANT.to_string(),
+            },
+            None => DEFAULT_TENANT.to_string(),
+        }
+    }
+
+    /// Count one query against the tenant of `index`
+    pub fn check_query(&self, index: &str) -> SearchResult<()> {
+        self.check_query_at(index, Instant::now())
+    }
+
+    /// Count `count` indexed documents against the tenant of `index`
+    pub fn check_documents(&self, index: &str, count: usize) -> SearchResult<()> {
+        self.check_documents_at(index, count, Instant::now())
+    }
+
+    fn check_query_at(&self, index: &str, now: Instant) -> SearchResult<()> {
+        let tenant = self.tenant_for(index);
+        let Some(limit) = self.config.limits_for(&tenant).max_queries_per_minute else {
+            return Ok(());
+        };
+        self.acquire(&tenant, Resource::Queries, u64::from(limit), MINUTE, 1, now)
+    }
+
+    fn check_documents_at(&self, index: &str, count: usize, now: Instant) -> SearchResult<()> {
+        let tenant = self.tenant_for(index);
+        let Some(limit) = self.config.limits_for(&tenant).max_docs_per_day else {
+            return Ok(());
+        };
+        if count as u64 > limit {
+            return Err(SearchError::invalid_query(format!(
+                "Batch of {} documents exceeds the daily indexing quota of {} for tenant {}",
+                count, limit, tenant
+            )));
+        }
+        self.acquire(&tenant, Resource::Documents, limit, DAY, count as u64, now)
+    }
+
+    fn acquire(&self, tenant: &str, resource: Resource, limit: u64, period: Duration, amount: u64, now: Instant) -> SearchResult<()> {
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = buckets
+            .entry((tenant.to_string(), resource))
+            .or_insert_with(|| TokenBucket::new(limit, period, now));
+
+        bucket.take(amount as f64, now).map_err(|wait| {
+            let retry_after_ms = u64::try_from(wait.as_millis()).unwrap_or(u64::MAX).max(1);
+            warn!(
+                "Tenant {} exceeded its {:?} quota of {} per {} s; retry in {} ms",
+                tenant,
+                resource,
+                limit,
+                period.as_secs(),
+                retry_after_ms
+            );
+            SearchError::RateLimited { retry_after_ms: Some(retry_after_ms) }
+        })
+    }
+}
+
+static ENFORCER: OnceLock<Mutex<Arc<QuotaEnforcer>>> = OnceLock::new();
+
+fn slot() -> &'static Mutex<Arc<QuotaEnforcer>> {
+    ENFORCER.get_or_init(|| Mutex::new(Arc::new(QuotaEnforcer::new(QuotaConfig::from_env()))))
+}
+
+/// The worker-wide enforcer, configured from the environment on first use
+pub fn global() -> Arc<QuotaEnforcer> {
+    slot().lock().unwrap().clone()
+}
+
+/// Replace the worker-wide quota settings, resetting all usage
+pub fn configure(config: QuotaConfig) {
+    *slot().lock().unwrap() = Arc::new(QuotaEnforcer::new(config));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queries(limit: u32) -> QuotaLimits {
+        QuotaLimits { max_queries_per_minute: Some(limit), max_docs_per_day: None }
+    }
+
+    fn docs(limit: u64) -> QuotaLimits {
+        QuotaLimits { max_queries_per_minute: None, max_docs_per_day: Some(limit) }
+    }
+
+    fn enforcer(defaults: QuotaLimits, separator: Option<&str>) -> QuotaEnforcer {
+        QuotaEnforcer::new(QuotaConfig {
+            defaults,
+            tenants: HashMap::new(),
+            tenant_separator: separator.map(String::from),
+        })
+    }
+
+    fn retry_after(result: SearchResult<()>) -> u64 {
+        match result {
+            Err(SearchError::RateLimited { retry_after_ms: Some(ms) }) => ms,
+            other => panic!("expected a rate limit, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_tenant_entries_may_leave_either_limit_empty() {
+        let tenants = QuotaConfig::parse_tenants(" acme : 60 : , batch::50000 ,");
+        assert_eq!(tenants["acme"], queries(60));
+        assert_eq!(tenants["batch"], docs(50_000));
+        assert_eq!(tenants.len(), 2);
+    }
+
+    #[test]
+    fn test_malformed_tenant_entries_are_skipped() {
+        let tenants = QuotaConfig::parse_tenants("acme:60, :1:2, bad:x:1, neg:-1:, extra:1:2:3, ok:1:2");
+        assert_eq!(tenants.keys().collect::<Vec<_>>(), ["ok"]);
+        assert!(QuotaConfig::parse_tenants("").is_empty());
+    }
+
+    #[test]
+    fn test_query_limits_saturate_at_u32() {
+        let tenants = QuotaConfig::parse_tenants("big:99999999999:");
+        assert_eq!(tenants["big"].max_queries_per_minute, Some(u32::MAX));
+    }
+
+    #[test]
+    fn test_a_later_tenant_entry_wins() {
+        assert_eq!(QuotaConfig::parse_tenants("acme:1:, acme:2:")["acme"], queries(2));
+    }
+
+    #[test]
+    fn test_tenant_limits_replace_the_defaults_entirely() {
+        let config = QuotaConfig {
+            defaults: QuotaLimits { max_queries_per_minute: Some(10), max_docs_per_day: Some(100) },
+            tenants: QuotaConfig::parse_tenants("batch::50000"),
+            tenant_separator: None,
+        };
+        assert_eq!(config.limits_for("batch"), docs(50_000));
+        assert_eq!(config.limits_for("other"), config.defaults);
+    }
+
+    #[test]
+    fn test_quotas_are_enabled_by_defaults_or_tenants() {
+        assert!(!QuotaConfig::default().is_enabled());
+        assert!(QuotaConfig { defaults: queries(1), ..Default::default() }.is_enabled());
+        // A tenant entry without limits still turns quotas on
+        assert!(QuotaConfig { tenants: QuotaConfig::parse_tenants("free::"), ..Default::default() }.is_enabled());
+    }
+
+    #[test]
+    fn test_index_prefixes_name_the_tenant() {
+        let prefixed = enforcer(QuotaLimits::default(), Some("__"));
+        assert_eq!(prefixed.tenant_for("acme__books__v2"), "acme");
+        assert_eq!(prefixed.tenant_for("__books"), DEFAULT_TENANT);
+        assert_eq!(prefixed.tenant_for("books"), DEFAULT_TENANT);
+        assert_eq!(enforcer(QuotaLimits::default(), None).tenant_for("acme__books"), DEFAULT_TENANT);
+    }
+
+    #[test]
+    fn test_tenant_scopes_win_over_prefixes_and_nest() {
+        let enforcer = enforcer(QuotaLimits::default(), Some("__"));
+        {
+            let _outer = tenant_scope("outer");
+            assert_eq!(enforcer.tenant_for("acme__books"), "outer");
+            {
+                let _inner = tenant_scope("inner");
+                assert_eq!(current_tenant().as_deref(), Some("inner"));
+            }
+            assert_eq!(current_tenant().as_deref(), Some("outer"));
+        }
+        assert_eq!(current_tenant(), None);
+    }
+
+    #[test]
+    fn test_a_full_bucket_allows_a_burst_then_refills_gradually() {
+        let enforcer = enforcer(queries(60), None);
+        let start = Instant::now();
+        for _ in 0..60 {
+            assert!(enforcer.check_query_at("books", start).is_ok());
+        }
+        assert_eq!(retry_after(enforcer.check_query_at("books", start)), 1_000);
+
+        // One query's worth after a second, and no more
+        let later = start + Duration::from_secs(1);
+        assert!(enforcer.check_query_at("books", later).is_ok());
+        assert!(enforcer.check_query_at("books", later).is_err());
+    }
+
+    #[test]
+    fn test_refills_stop_at_the_capacity() {
+        let enforcer = enforcer(queries(2), None);
+        let start = Instant::now();
+        assert!(enforcer.check_query_at("books", start).is_ok());
+        let idle = start + Duration::from_secs(3_600);
+        assert!(enforcer.check_query_at("books", idle).is_ok());
+        assert!(enforcer.check_query_at("books", idle).is_ok());
+        assert!(enforcer.check_query_at("books", idle).is_err());
+    }
+
+    #[test]
+    fn test_retry_times_are_at_least_a_millisecond() {
+        let enforcer = enforcer(queries(60), None);
+        let start = Instant::now();
+        for _ in 0..60 {
+            enforcer.check_query_at("books", start).unwrap();
+        }
+        assert_eq!(retry_after(enforcer.check_query_at("books", start + Duration::from_micros(999_900))), 1);
+    }
+
+    #[test]
+    fn test_a_zero_limit_never_refills() {
+        let enforcer = enforcer(queries(0), None);
+        assert_eq!(retry_after(enforcer.check_query_at("books", Instant::now())), u64::MAX);
+    }
+
+    #[test]
+    fn test_an_earlier_clock_reading_does_not_refill() {
+        let enforcer = enforcer(queries(1), None);
+        let start = Instant::now() + Duration::from_secs(60);
+        enforcer.check_query_at("books", start).unwrap();
+        assert!(enforcer.check_query_at("books", start - Duration::from_secs(60)).is_err());
+    }
+
+    #[test]
+    fn test_batches_up_to_the_daily_quota_are_accepted() {
+        let enforcer = enforcer(docs(1_000), None);
+        let start = Instant::now();
+        assert!(enforcer.check_documents_at("books", 1_000, start).is_ok());
+        // A document refills every 86.4 seconds
+        assert_eq!(retry_after(enforcer.check_documents_at("books", 1, start)), 86_400);
+        assert!(enforcer.check_documents_at("books", 0, start).is_ok());
+    }
+
+    #[test]
+    fn test_batches_over_the_daily_quota_can_never_fit() {
+        let enforcer = enforcer(docs(1_000), None);
+        let _tenant = tenant_scope("acme");
+        match enforcer.check_documents_at("books", 1_001, Instant::now()) {
+            Err(SearchError::InvalidQuery(message)) => {
+                assert_eq!(message, "Batch of 1001 documents exceeds the daily indexing quota of 1000 for tenant acme")
+            }
+            other => panic!("expected InvalidQuery, got {:?}", other),
+        }
+        // The refused batch used none of the quota
+        assert!(enforcer.check_documents_at("books", 1_000, Instant::now()).is_ok());
+    }
+
+    #[test]
+    fn test_queries_and_documents_have_separate_buckets() {
+        let enforcer = enforcer(QuotaLimits { max_queries_per_minute: Some(1), max_docs_per_day: Some(1) }, None);
+        let start = Instant::now();
+        assert!(enforcer.check_query_at("books", start).is_ok());
+        assert!(enforcer.check_documents_at("books", 1, start).is_ok());
+        assert!(enforcer.check_query_at("books", start).is_err());
+        assert!(enforcer.check_documents_at("books", 1, start).is_err());
+    }
+
+    #[test]
+    fn test_tenants_have_separate_buckets() {
+        let enforcer = enforcer(queries(1), Some("__"));
+        let start = Instant::now();
+        assert!(enforcer.check_query_at("acme__books", start).is_ok());
+        assert!(enforcer.check_query_at("acme__films", start).is_err());
+        assert!(enforcer.check_query_at("globex__books", start).is_ok());
+    }
+
+    #[test]
+    fn test_configure_resets_usage() {
+        configure(QuotaConfig { defaults: queries(1), ..Default::default() });
+        let _tenant = tenant_scope("quota-configure-test");
+        assert!(global().check_query("books").is_ok());
+        assert!(global().check_query("books").is_err());
+
+        configure(QuotaConfig { defaults: queries(1), ..Default::default() });
+        assert!(global().check_query("books").is_ok());
+        configure(QuotaConfig::default());
+        assert!(!global().config().is_enabled());
+    }
+}
@@ -0,0 +1,416 @@
+//! Provider rate limits
+//!
+//! A provider answering 429 usually says when to come back: in `Retry-After`,
+//! as seconds or an HTTP date, or in `X-RateLimit-Reset` (also
+//! `RateLimit-Reset`) along with an `X-RateLimit-Remaining` of zero. Clients
+//! send their requests through the [`Throttle`] of their provider, which reads
+//! those headers from every response. A 429 fails with
+//! `SearchError::RateLimited` carrying the time the provider asked for, and
+//! the throttle holds back further requests until then rather than letting
+//! them be refused too.
+//!
+//! `<PROVIDER>_RATE_LIMIT`, such as `ELASTICSEARCH_RATE_LIMIT`, or
+//! [`RATE_LIMIT_ENV`] for every provider, also caps the requests sent, as
+//! `100/s`, `6000/min` or `50000/h`. The cap is a token bucket, so a burst up
+//! to the whole allowance goes out at once. A request that would exceed it
+//! waits for its turn when that is at most [`MAX_WAIT_ENV`] away and within
+//! the deadline of the operation, and otherwise fails with `RateLimited`.
+//! Throttles are kept per provider for the lifetime of the worker.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use chrono::DateTime;
+use log::{debug, warn};
+use crate::config::env_helpers::get_env_parsed;
+use crate::deadline;
+use crate::error::{SearchError, SearchResult};
+use crate::http::{HeaderMap, Response, StatusCode};
+use crate::quota::TokenBucket;
+
+/// Environment variable capping the requests of every provider, such as `100/s`
+pub const RATE_LIMIT_ENV: &str = "SEARCH_PROVIDER_RATE_LIMIT";
+
+/// Environment variable with the longest a request waits for the cap, in milliseconds
+pub const MAX_WAIT_ENV: &str = "SEARCH_RATE_LIMIT_MAX_WAIT_MS";
+
+/// Default longest wait for the cap
+pub const DEFAULT_MAX_WAIT: Duration = Duration::from_secs(1);
+
+/// Reset values above this are Unix timestamps rather than seconds to wait
+const EPOCH_SECONDS: u64 = 1_000_000_000;
+
+/// Requests allowed per period
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub requests: u64,
+    pub period: Duration,
+}
+
+impl std::str::FromStr for RateLimit {
+    type Err = SearchError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || SearchError::invalid_query(format!("Invalid rate limit: {} (expected requests/s, /min or /h)", s.trim()));
+        let (requests, period) = s.trim().split_once('/').ok_or_else(invalid)?;
+        let requests = requests.trim().parse::<u64>().ok().filter(|r| *r > 0).ok_or_else(invalid)?;
+        let period = match period.trim().to_ascii_lowercase().as_str() {
+            "s" | "sec" | "second" => Duration::from_secs(1),
+            "m" | "min" | "minute" => Duration::from_secs(60),
+            "h" | "hour" => Duration::from_secs(60 * 60),
+            _ => return Err(invalid()),
+        };
+        Ok(Self { requests, period })
+    }
+}
+
+impl RateLimit {
+    /// Cap of `provider` from `<PROVIDER>_RATE_LIMIT`, or else `SEARCH_PROVIDER_RATE_LIMIT`
+    pub fn from_env(provider: &str) -> Option<Self> {
+        get_env_parsed::<RateLimit>(&provider_env(provider))
+            .ok()
+            .flatten()
+            .or_else(|| get_env_parsed::<RateLimit>(RATE_LIMIT_ENV).ok().flatten())
+    }
+}
+
+/// Name of the variable capping the requests of `provider`
+pub fn provider_env(provider: &str) -> String {
+    format!("{}_RATE_LIMIT", provider.to_ascii_uppercase())
+}
+
+#[derive(Debug, Default)]
+struct ThrottleState {
+    bucket: Option<TokenBucket>,
+
+    /// Set while the provider asked for requests to stop
+    paused_until: Option<Instant>,
+}
+
+/// Requests of one provider, held to its cap and to the waits it asked for
+#[derive(Debug)]
+pub struct Throttle {
+    max_wait: Duration,
+    state: Mutex<ThrottleState>,
+}
+
+impl Throttle {
+    pub fn new(limit: Option<RateLimit>, max_wait: Duration) -> Self {
+        let bucket = limit.map(|limit| TokenBucket::new(limit.requests, limit.period, Instant::now()));
+        Self { max_wait, state: Mutex::new(ThrottleState { bucket, paused_until: None }) }
+    }
+
+    /// Throttle of `provider` as the environment configures it
+    pub fn from_env(provider: &str) -> Self {
+        let max_wait = get_env_parsed::<u64>(MAX_WAIT_ENV)
+            .ok()
+            .flatten()
+            .map_or(DEFAULT_MAX_WAIT, Duration::from_millis);
+        Self::new(RateLimit::from_env(provider), max_wait)
+    }
+
+    /// Take a turn for one request, waiting for it when that is allowed
+    pub fn acquire(&self) -> SearchResult<()> {
+        loop {
+            let Some(wait) = self.wait(Instant::now()) else {
+                return Ok(());
+            };
+            let in_time = deadline::current().is_none_or(|deadline| deadline.remaining() > wait);
+            if wait > self.max_wait || !in_time {
+                return Err(SearchError::RateLimited { retry_after_ms: Some(millis(wait)) });
+            }
+            debug!("Rate limit reached, request held back {} ms", wait.as_millis());
+            std::thread::sleep(wait);
+        }
+    }
+
+    /// Time until the next turn, or `None` with the turn taken
+    fn wait(&self, now: Instant) -> Option<Duration> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(until) = state.paused_until {
+            if until > now {
+                return Some(until - now);
+            }
+            state.paused_until = None;
+        }
+        state.bucket.as_mut()?.take(1.0, now).err()
+    }
+
+    /// Read the rate limit headers of a response; a 429 becomes `RateLimited`
+    pub fn record(&self, status: StatusCode, headers: &HeaderMap) -> SearchResult<()> {
+        let pause = retry_after(status, headers, SystemTime::now());
+        if let Some(pause) = pause {
+            let until = Instant::now() + pause;
+            let mut state = self.state.lock().unwrap();
+            state.paused_until = Some(state.paused_until.map_or(until, |paused| paused.max(until)));
+        }
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            let retry_after_ms = pause.map(millis);
+            warn!(
+                "Provider rate limit reached{}",
+                retry_after_ms.map(|ms| format!(", retrying after {} ms", ms)).unwrap_or_default()
+            );
+            return Err(SearchError::RateLimited { retry_after_ms });
+        }
+        Ok(())
+    }
+
+    /// Send a request with `send` in its turn, and record its response
+    pub fn send<F>(&self, send: F) -> anyhow::Result<Response>
+    where
+        F: FnOnce() -> anyhow::Result<Response>,
+    {
+        self.acquire()?;
+        let response = send()?;
+        self.record(response.status(), response.headers())?;
+        Ok(response)
+    }
+}
+
+/// Time a response asks to wait before the next request, if any
+///
+/// `Retry-After` wins; the reset of a rate limit counts once no request is left
+/// of it, or on a 429.
+pub fn retry_after(status: StatusCode, headers: &HeaderMap, now: SystemTime) -> Option<Duration> {
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok()).map(str::trim);
+    if let Some(value) = header("retry-after") {
+        return match value.parse::<u64>() {
+            Ok(seconds) => Some(Duration::from_secs(seconds)),
+            Err(_) => DateTime::parse_from_rfc2822(value)
+                .ok()
+                .map(|date| SystemTime::from(date).duration_since(now).unwrap_or_default()),
+        };
+    }
+    let exhausted = status == StatusCode::TOO_MANY_REQUESTS
+        || header("x-ratelimit-remaining").or_else(|| header("ratelimit-remaining")) == Some("0");
+    if !exhausted {
+        return None;
+    }
+    let reset = header("x-ratelimit-reset").or_else(|| header("ratelimit-reset"))?;
+    let reset = reset.parse::<f64>().ok().filter(|reset| reset.is_finite() && *reset >= 0.0)?;
+    if reset < EPOCH_SECONDS as f64 {
+        return Some(Duration::from_secs_f64(reset));
+    }
+    let now = now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs_f64();
+    // Some providers give milliseconds since the epoch
+    let reset = if reset > EPOCH_SECONDS as f64 * 1000.0 { reset / 1000.0 } else { reset };
+    Some(Duration::from_secs_f64((reset - now).max(0.0)))
+}
+
+/// The rate limit rejection carried by `error`, when it is one
+///
+/// Clients pass it on inside their `anyhow` errors, which providers otherwise
+/// classify by message.
+pub fn rate_limited(error: &anyhow::Error) -> Option<SearchError> {
+    match error.downcast_ref::<SearchError>() {
+        Some(rejection @ SearchError::RateLimited { .. }) => Some(rejection.clone()),
+        _ => None,
+    }
+}
+
+fn millis(duration: Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX).max(1)
+}
+
+/// Throttle shared by the clients of `provider` in this worker
+pub fn throttle(provider: &str) -> Arc<Throttle> {
+    static THROTTLES: OnceLock<Mutex<HashMap<String, Arc<Throttle>>>> = OnceLock::new();
+    THROTTLES
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .unwrap()
+        .entry(provider.to_string())
+        .or_insert_with(|| Arc::new(Throttle::from_env(provider)))
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::header::HeaderValue;
+
+    const LIMITED: StatusCode = StatusCode::TOO_MANY_REQUESTS;
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    fn now() -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(1_700_000_000)
+    }
+
+    fn hint(status: StatusCode, pairs: &[(&'static str, &str)]) -> Option<Duration> {
+        retry_after(status, &headers(pairs), now())
+    }
+
+    fn rejected_for(result: SearchResult<()>) -> Option<u64> {
+        match result {
+            Err(SearchError::RateLimited { retry_after_ms }) => retry_after_ms,
+            other => panic!("expected a rate limit, got {:?}", other),
+        }
+    }
+
+    fn per_minute(requests: u64) -> Option<RateLimit> {
+        Some(RateLimit { requests, period: Duration::from_secs(60) })
+    }
+
+    #[test]
+    fn test_limits_accept_period_aliases_in_any_case() {
+        for (text, seconds) in [("1/s", 1), ("1/Sec", 1), ("1/SECOND", 1), ("1/m", 60), ("1/minute", 60), ("1/h", 3_600), ("1/Hour", 3_600)] {
+            assert_eq!(text.parse::<RateLimit>().unwrap().period, Duration::from_secs(seconds), "{}", text);
+        }
+        assert_eq!(" 6000 / MIN ".parse::<RateLimit>().unwrap(), RateLimit { requests: 6_000, period: Duration::from_secs(60) });
+    }
+
+    #[test]
+    fn test_malformed_limits_are_refused() {
+        for text in ["100", "0/s", "-1/s", "1.5/s", "x/s", "100/day", "/s", "100/"] {
+            assert!(text.parse::<RateLimit>().is_err(), "{}", text);
+        }
+        match " 5/day ".parse::<RateLimit>() {
+            Err(SearchError::InvalidQuery(message)) => {
+                assert_eq!(message, "Invalid rate limit: 5/day (expected requests/s, /min or /h)")
+            }
+            other => panic!("expected InvalidQuery, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_provider_variables_are_upper_case() {
+        assert_eq!(provider_env("elasticsearch"), "ELASTICSEARCH_RATE_LIMIT");
+        std::env::set_var(provider_env("rate-limit-env-test"), "30/min");
+        assert_eq!(RateLimit::from_env("rate-limit-env-test"), per_minute(30));
+    }
+
+    #[test]
+    fn test_retry_after_may_be_seconds_or_a_date() {
+        assert_eq!(hint(LIMITED, &[("retry-after", " 7 ")]), Some(Duration::from_secs(7)));
+        assert_eq!(hint(LIMITED, &[("Retry-After", "Tue, 14 Nov 2023 22:13:40 GMT")]), Some(Duration::from_secs(20)));
+    }
+
+    #[test]
+    fn test_a_retry_date_in_the_past_means_no_wait() {
+        assert_eq!(hint(LIMITED, &[("retry-after", "Mon, 13 Nov 2023 00:00:00 GMT")]), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_retry_after_counts_whatever_the_status() {
+        assert_eq!(hint(StatusCode::SERVICE_UNAVAILABLE, &[("retry-after", "3")]), Some(Duration::from_secs(3)));
+        assert_eq!(hint(StatusCode::OK, &[("retry-after", "3")]), Some(Duration::from_secs(3)));
+    }
+
+    #[test]
+    fn test_an_unreadable_retry_after_hides_the_reset() {
+        assert_eq!(hint(LIMITED, &[("retry-after", "soon"), ("x-ratelimit-reset", "5")]), None);
+    }
+
+    #[test]
+    fn test_resets_count_once_the_allowance_is_spent() {
+        assert_eq!(hint(StatusCode::OK, &[("x-ratelimit-remaining", "3"), ("x-ratelimit-reset", "5")]), None);
+        assert_eq!(hint(StatusCode::OK, &[("x-ratelimit-reset", "5")]), None);
+        assert_eq!(hint(StatusCode::OK, &[("ratelimit-remaining", "0"), ("ratelimit-reset", "5")]), Some(Duration::from_secs(5)));
+        assert_eq!(hint(LIMITED, &[("x-ratelimit-remaining", "3"), ("x-ratelimit-reset", "5")]), Some(Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn test_resets_may_be_a_wait_or_a_timestamp() {
+        assert_eq!(hint(LIMITED, &[("ratelimit-reset", "2.5")]), Some(Duration::from_millis(2_500)));
+        assert_eq!(hint(LIMITED, &[("x-ratelimit-reset", "1700000030")]), Some(Duration::from_secs(30)));
+        assert_eq!(hint(LIMITED, &[("x-ratelimit-reset", "1700000005000")]), Some(Duration::from_secs(5)));
+        assert_eq!(hint(LIMITED, &[("x-ratelimit-reset", "1600000000")]), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_unusable_resets_are_ignored() {
+        for reset in ["-1", "NaN", "inf", "later"] {
+            assert_eq!(hint(LIMITED, &[("x-ratelimit-reset", reset)]), None, "{}", reset);
+        }
+        assert_eq!(hint(LIMITED, &[]), None);
+    }
+
+    #[test]
+    fn test_an_uncapped_throttle_never_holds_back() {
+        let throttle = Throttle::new(None, Duration::ZERO);
+        for _ in 0..1_000 {
+            assert!(throttle.acquire().is_ok());
+        }
+    }
+
+    #[test]
+    fn test_the_cap_allows_a_burst_then_refuses_beyond_the_longest_wait() {
+        let throttle = Throttle::new(per_minute(2), Duration::ZERO);
+        assert!(throttle.acquire().is_ok());
+        assert!(throttle.acquire().is_ok());
+        assert!(rejected_for(throttle.acquire()).is_some_and(|ms| ms > 29_000 && ms <= 30_000));
+    }
+
+    #[test]
+    fn test_short_waits_for_the_cap_are_waited_out() {
+        let throttle = Throttle::new(Some(RateLimit { requests: 50, period: Duration::from_secs(1) }), Duration::from_secs(1));
+        let start = Instant::now();
+        for _ in 0..51 {
+            assert!(throttle.acquire().is_ok());
+        }
+        assert!(start.elapsed() >= Duration::from_millis(10));
+    }
+
+    #[test]
+    fn test_waits_past_the_deadline_are_refused() {
+        let throttle = Throttle::new(Some(RateLimit { requests: 1, period: Duration::from_millis(500) }), Duration::from_secs(1));
+        let _deadline = deadline::begin(Some(50));
+        assert!(throttle.acquire().is_ok());
+        assert!(rejected_for(throttle.acquire()).is_some_and(|ms| ms > 400));
+    }
+
+    #[test]
+    fn test_a_rejection_pauses_every_request() {
+        let throttle = Throttle::new(None, Duration::ZERO);
+        assert_eq!(rejected_for(throttle.record(LIMITED, &headers(&[("retry-after", "10")]))), Some(10_000));
+        assert!(rejected_for(throttle.acquire()).is_some_and(|ms| ms > 9_000));
+        assert_eq!(throttle.wait(Instant::now() + Duration::from_secs(11)), None);
+    }
+
+    #[test]
+    fn test_a_spent_allowance_pauses_without_failing_the_response() {
+        let throttle = Throttle::new(None, Duration::ZERO);
+        let spent = headers(&[("x-ratelimit-remaining", "0"), ("x-ratelimit-reset", "5")]);
+        assert!(throttle.record(StatusCode::OK, &spent).is_ok());
+        assert!(throttle.acquire().is_err());
+    }
+
+    #[test]
+    fn test_a_shorter_pause_does_not_cut_a_longer_one() {
+        let throttle = Throttle::new(None, Duration::ZERO);
+        let _ = throttle.record(LIMITED, &headers(&[("retry-after", "10")]));
+        let _ = throttle.record(LIMITED, &headers(&[("retry-after", "1")]));
+        assert!(throttle.wait(Instant::now() + Duration::from_secs(5)).is_some());
+    }
+
+    #[test]
+    fn test_a_rejection_without_hints_does_not_pause() {
+        let throttle = Throttle::new(None, Duration::ZERO);
+        assert_eq!(rejected_for(throttle.record(LIMITED, &HeaderMap::new())), None);
+        assert!(throttle.acquire().is_ok());
+    }
+
+    #[test]
+    fn test_only_rate_limit_rejections_are_recovered_from_errors() {
+        let rejection = SearchError::RateLimited { retry_after_ms: Some(5) };
+        assert!(matches!(
+            rate_limited(&anyhow::Error::new(rejection)),
+            Some(SearchError::RateLimited { retry_after_ms: Some(5) })
+        ));
+        assert!(rate_limited(&anyhow::Error::new(SearchError::Unsupported)).is_none());
+        assert!(rate_limited(&anyhow::anyhow!("429 Too Many Requests")).is_none());
+    }
+
+    #[test]
+    fn test_providers_share_one_throttle() {
+        assert!(Arc::ptr_eq(&throttle("rate-limit-shared-test"), &throttle("rate-limit-shared-test")));
+        assert!(!Arc::ptr_eq(&throttle("rate-limit-shared-test"), &throttle("rate-limit-other-test")));
+    }
+}
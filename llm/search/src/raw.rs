@@ -0,0 +1,334 @@
+//! Borrowed views over provider search responses
+//!
+//! Parsing a whole response page into `serde_json::Value` and serializing each
+//! `_source` back to a string allocates every field twice. The types here
+//! borrow from the response body instead: document sources, highlights and
+//! aggregations are kept as [`RawValue`] slices and copied into the result
+//! exactly once. Only the small metadata fields (id, score) are decoded.
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use serde::Deserialize;
+use serde_json::Value;
+use crate::cursor::LucenePage;
+use crate::error::{SearchError, SearchResult};
+use crate::explain::explanation_json;
+use crate::types::{Json, SearchGroup, SearchHit, SearchResults};
+
+pub use serde_json::value::RawValue;
+
+/// Copy an optional raw JSON slice into an owned string
+pub fn raw_to_json(raw: Option<&RawValue>) -> Option<Json> {
+    raw.map(|r| r.get().to_string())
+}
+
+/// Build a normalized explanation from raw provider details
+///
+/// The details are only decoded when present, which is the case only when the
+/// query asked for explanations.
+pub fn raw_explanation(provider: &str, score: Option<f64>, raw: Option<&RawValue>) -> Option<Json> {
+    let details = raw.and_then(|r| serde_json::from_str::<Value>(r.get()).ok());
+    explanation_json(provider, score, details.as_ref())
+}
+
+#[derive(Deserialize)]
+struct DocumentId<'a> {
+    #[serde(borrow, default)]
+    id: Option<Cow<'a, str>>,
+}
+
+/// Read a document's string `id` field without decoding the rest of it
+pub fn raw_document_id(raw: &RawValue) -> Option<String> {
+    serde_json::from_str::<DocumentId>(raw.get())
+        .ok()
+        .and_then(|d| d.id)
+        .map(Cow::into_owned)
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LuceneTotal {
+    Count(u64),
+    Object {
+        value: u64,
+        /// `eq` for an exact count, `gte` for a lower bound
+        #[serde(default)]
+        relation: Option<String>,
+    },
+}
+
+/// A single hit of an Elasticsearch/OpenSearch response
+#[derive(Deserialize)]
+pub struct LuceneHit<'a> {
+    #[serde(rename = "_id", borrow)]
+    pub id: Cow<'a, str>,
+
+    #[serde(rename = "_score", default)]
+    pub score: Option<f64>,
+
+    #[serde(rename = "_source", borrow, default)]
+    pub source: Option<&'a RawValue>,
+
+    #[serde(borrow, default)]
+    pub highlight: Option<&'a RawValue>,
+
+    #[serde(rename = "_explanation", borrow, default)]
+    pub explanation: Option<&'a RawValue>,
+
+    /// Requested fields, including the collapse field of collapsed searches
+    #[serde(borrow, default)]
+    pub fields: Option<BTreeMap<Cow<'a, str>, Vec<Value>>>,
+
+    /// Sort values of the hit, present when the request sorted explicitly
+    #[serde(default)]
+    pub sort: Option<Vec<Value>>,
+
+    #[serde(borrow, default)]
+    inner_hits: Option<BTreeMap<Cow<'a, str>, LuceneInnerHits<'a>>>,
+}
+
+impl LuceneHit<'_> {
+    fn to_search_hit(&self, provider: &str) -> SearchHit {
+        SearchHit {
+            explanation: raw_explanation(provider, self.score, self.explanation),
+            id: self.id.to_string(),
+            score: self.score,
+            content: raw_to_json(self.source),
+            highlights: raw_to_json(self.highlight),
+            distance_meters: None,
+        }
+    }
+
+    /// Group of a collapsed search, keyed by the values of `field`
+    ///
+    /// The group's total and hits come from its first `inner_hits` section;
+    /// without one the group holds just this hit and its size is unknown.
+    fn to_group(&self, field: &str, provider: &str) -> SearchGroup {
+        let key = self
+            .fields
+            .as_ref()
+            .and_then(|fields| fields.get(field))
+            .map(|values| values.iter().map(group_key_text).collect())
+            .unwrap_or_default();
+        match self.inner_hits.as_ref().and_then(|inner| inner.values().next()) {
+            Some(inner) => SearchGroup {
+                key,
+                total: inner.hits.total.as_ref().map(LuceneTotal::value),
+                hits: inner.hits.hits.iter().map(|hit| hit.to_search_hit(provider)).collect(),
+            },
+            None => SearchGroup { key, total: None, hits: vec![self.to_search_hit(provider)] },
+        }
+    }
+}
+
+/// Text of one group key value: strings as is, anything else as JSON
+pub fn group_key_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Field a Lucene request collapses on, if any
+pub fn collapse_field(request: &Value) -> Option<&str> {
+    request.get("collapse")?.get("field")?.as_str()
+}
+
+#[derive(Deserialize)]
+struct LuceneInnerHits<'a> {
+    #[serde(borrow)]
+    hits: LuceneHits<'a>,
+}
+
+impl LuceneTotal {
+    /// The total, saturating at `u32::MAX` rather than wrapping
+    fn value(&self) -> u32 {
+        match self {
+            LuceneTotal::Count(n) | LuceneTotal::Object { value: n, .. } => u32::try_from(*n).unwrap_or(u32::MAX),
+        }
+    }
+
+    /// Whether the total counts every match; only the object form says so
+    fn is_exact(&self) -> Option<bool> {
+        match self {
+            LuceneTotal::Count(_) => None,
+            LuceneTotal::Object { relation, .. } => relation.as_deref().map(|r| r == "eq"),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct LuceneHits<'a> {
+    #[serde(default)]
+    total: Option<LuceneTotal>,
+
+    #[serde(borrow)]
+    hits: Vec<LuceneHit<'a>>,
+}
+
+/// Borrowed view of an Elasticsearch/OpenSearch `_search` response
+#[derive(Deserialize)]
+pub struct LuceneResponse<'a> {
+    #[serde(default)]
+    took: Option<u64>,
+
+    #[serde(borrow)]
+    hits: LuceneHits<'a>,
+
+    #[serde(borrow, default)]
+    aggregations: Option<&'a RawValue>,
+}
+
+impl<'a> LuceneResponse<'a> {
+    /// Parse a response body without copying document sources
+    pub fn parse(body: &'a str) -> SearchResult<Self> {
+        serde_json::from_str(body)
+            .map_err(|e| SearchError::internal(format!("Failed to parse search response: {}", e)))
+    }
+
+    /// Hits in response order
+    pub fn hits(&self) -> &[LuceneHit<'a>] {
+        &self.hits.hits
+    }
+
+    /// Convert into shared search results, copying each raw slice once
+    pub fn into_results(self, provider: &str) -> SearchResults {
+        self.into_grouped_results(provider, None)
+    }
+
+    /// Convert a search that may have collapsed on `collapse_field`
+    ///
+    /// Each top-level hit of a collapsed search stands for one group.
+    pub fn into_grouped_results(self, provider: &str, collapse_field: Option<&str>) -> SearchResults {
+        let total = self.hits.total.as_ref().map(LuceneTotal::value);
+        let total_exact = self.hits.total.as_ref().and_then(LuceneTotal::is_exact);
+        let groups = collapse_field
+            .map(|field| self.hits.hits.iter().map(|hit| hit.to_group(field, provider)).collect());
+        let hits = self.hits.hits.iter().map(|hit| hit.to_search_hit(provider)).collect();
+
+        SearchResults {
+            total,
+            page: None,
+            per_page: None,
+            hits,
+            facets: raw_to_json(self.aggregations),
+            took_ms: self.took.map(|t| t as u32),
+            truncated: false,
+            request_id: None,
+            groups,
+            next_cursor: None,
+            total_exact,
+            facet_metadata: Vec::new(),
+            relaxations: Vec::new(),
+            original_query: None,
+            corrected_query: None,
+        }
+    }
+}
+
+/// Parse an Elasticsearch/OpenSearch `_search` response body into search results
+pub fn lucene_response_to_results(body: &str, provider: &str) -> SearchResult<SearchResults> {
+    Ok(LuceneResponse::parse(body)?.into_results(provider))
+}
+
+/// Parse the response to `request`, which was positioned at `page`
+///
+/// Hits are grouped when the request used `collapse`, and `next_cursor` is
+/// set when more hits follow.
+pub fn lucene_page_to_results(body: &str, request: &Value, page: &LucenePage) -> SearchResult<SearchResults> {
+    let response = LuceneResponse::parse(body)?;
+    let last_sort = response.hits().last().and_then(|hit| hit.sort.clone());
+    let distances: Option<Vec<Option<f64>>> = geo_distance_sort_position(request)
+        .map(|position| response.hits().iter().map(|hit| hit.sort.as_ref()?.get(position)?.as_f64()).collect());
+    let mut results = response.into_grouped_results(&page.provider, collapse_field(request));
+    results.next_cursor = page.next_cursor(request, results.hits.len(), results.total, last_sort.as_deref());
+    for (hit, distance) in results.hits.iter_mut().zip(distances.into_iter().flatten()) {
+        hit.distance_meters = distance;
+    }
+    Ok(results)
+}
+
+/// Position of the `_geo_distance` sort of a request, whose sort value is the hit's distance
+fn geo_distance_sort_position(request: &Value) -> Option<usize> {
+    request.get("sort")?.as_array()?.iter().position(|sort| sort.get("_geo_distance").is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RESPONSE: &str = r#"{
+        "took": 3,
+        "hits": {
+            "total": {"value": 2, "relation": "eq"},
+            "hits": [
+                {"_id": "1", "_score": 1.5, "_source": {"title": "Rust", "tags": ["a", "b"]},
+                 "highlight": {"title": ["<em>Rust</em>"]}},
+                {"_id": "2", "_score": null, "_source": {"title": "Go"}}
+            ]
+        },
+        "aggregations": {"tags": {"buckets": []}}
+    }"#;
+
+    #[test]
+    fn test_lucene_response_keeps_sources_verbatim() {
+        let results = lucene_response_to_results(RESPONSE, "elasticsearch").unwrap();
+
+        assert_eq!(results.total, Some(2));
+        assert_eq!(results.total_exact, Some(true));
+        assert_eq!(results.took_ms, Some(3));
+        assert_eq!(results.hits.len(), 2);
+        assert_eq!(results.hits[0].content.as_deref(), Some(r#"{"title": "Rust", "tags": ["a", "b"]}"#));
+        assert_eq!(results.hits[0].highlights.as_deref(), Some(r#"{"title": ["<em>Rust</em>"]}"#));
+        assert_eq!(results.hits[1].score, None);
+        assert!(results.hits[1].explanation.is_none());
+        assert_eq!(results.facets.as_deref(), Some(r#"{"tags": {"buckets": []}}"#));
+    }
+
+    #[test]
+    fn test_totals_past_u32_saturate() {
+        let body = r#"{"hits": {"total": {"value": 5000000000, "relation": "eq"}, "hits": []}}"#;
+        assert_eq!(lucene_response_to_results(body, "elasticsearch").unwrap().total, Some(u32::MAX));
+
+        let body = r#"{"hits": {"total": 4294967296, "hits": []}}"#;
+        assert_eq!(lucene_response_to_results(body, "opensearch").unwrap().total, Some(u32::MAX));
+    }
+
+    #[test]
+    fn test_raw_document_id() {
+        let doc: &RawValue = serde_json::from_str(r#"{"name": "x", "id": "doc-\"7\""}"#).unwrap();
+        assert_eq!(raw_document_id(doc), Some("doc-\"7\"".to_string()));
+
+        let doc: &RawValue = serde_json::from_str(r#"{"id": 7}"#).unwrap();
+        assert_eq!(raw_document_id(doc), None);
+    }
+
+    #[test]
+    fn test_collapsed_response_groups() {
+        let body = r#"{
+            "hits": {
+                "total": {"value": 5, "relation": "eq"},
+                "hits": [
+                    {"_id": "1", "_score": 2.0, "_source": {"brand": "acme"}, "fields": {"brand": ["acme"]},
+                     "inner_hits": {"top": {"hits": {"total": {"value": 3}, "hits": [
+                        {"_id": "1", "_score": 2.0, "_source": {"brand": "acme"}},
+                        {"_id": "4", "_score": 1.0, "_source": {"brand": "acme"}}
+                     ]}}}},
+                    {"_id": "2", "_score": 1.0, "_source": {"year": 2020}, "fields": {"year": [2020]}}
+                ]
+            }
+        }"#;
+        let mut request = serde_json::json!({ "collapse": { "field": "brand", "inner_hits": { "name": "top" } } });
+        let page = LucenePage::apply(&mut request, "elasticsearch", "products", None).unwrap();
+        let results = lucene_page_to_results(body, &request, &page).unwrap();
+        let groups = results.groups.unwrap();
+        assert_eq!(results.hits.len(), 2);
+        assert_eq!(groups[0].key, vec!["acme"]);
+        assert_eq!(groups[0].total, Some(3));
+        assert_eq!(groups[0].hits.iter().map(|h| h.id.as_str()).collect::<Vec<_>>(), vec!["1", "4"]);
+        assert!(groups[1].key.is_empty(), "no brand field on the hit");
+        assert_eq!((groups[1].total, groups[1].hits.len()), (None, 1));
+
+        assert!(lucene_response_to_results(RESPONSE, "elasticsearch").unwrap().groups.is_none());
+    }
+}
@@ -0,0 +1,558 @@
+//! Reindex jobs with progress reporting
+//!
+//! Copying a large index into a new one can take hours, longer than any single
+//! invocation should run. A reindex job therefore moves forward in steps: each
+//! [`ReindexJob::run`] reads the source page by page and writes every page to
+//! the destination until [`STEP_MS_ENV`] has passed, then returns. The job
+//! state, a [`BatchOperationState`] whose checkpoint data is the position of
+//! the next page, is saved after every page, so the next step, or a worker
+//! replaying its invocations, continues from there.
+//!
+//! Jobs are saved by ID in a [`JobStore`], which providers keep on the backend
+//! itself, in [`JOBS_INDEX`]. Any worker using the same backend can then read
+//! the [`status`] of a job between steps, with documents copied, rate, ETA and
+//! recent errors, and [`cancel`] it, which stops it before its next page. A
+//! page the destination refuses is counted as failed and skipped; a page that
+//! cannot be read stops the step, to be read again by the next one.
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::future::Future;
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use std::time::{Duration, Instant};
+use chrono::Utc;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use crate::config::env_helpers::get_env_parsed;
+use crate::durability::{utils::generate_operation_id, BatchOperationState, BatchOperationType, FailedItem};
+use crate::error::{SearchError, SearchResult};
+use crate::events::{self, LifecycleEventKind};
+use crate::metrics::ErrorKind;
+use crate::router::LocalBoxFuture;
+use crate::Doc;
+
+/// Environment variable with the milliseconds one step of a job copies for before returning
+pub const STEP_MS_ENV: &str = "SEARCH_REINDEX_STEP_MS";
+
+const DEFAULT_STEP_MS: u64 = 10_000;
+
+/// Documents read from the source at a time
+pub const REINDEX_PAGE_SIZE: usize = 250;
+
+/// Most recent errors a job keeps and reports in its status
+const KEPT_ERRORS: usize = 10;
+
+/// Index jobs are stored in, on the backend whose indexes they copy
+pub const JOBS_INDEX: &str = "golem-reindex-jobs";
+
+/// Where jobs are saved, readable by every worker using the backend
+///
+/// A job is one document of [`JOBS_INDEX`], written only by the worker
+/// running it. A cancellation is a document of its own, written by whichever
+/// worker cancels the job, so saved progress never overwrites it.
+pub trait JobStore {
+    type Error: From<SearchError>;
+
+    /// The document with `id` in [`JOBS_INDEX`], `None` when it or the index is missing
+    fn load_job<'a>(&'a self, id: &'a str) -> LocalBoxFuture<'a, Result<Option<Doc>, Self::Error>>;
+
+    /// Write a document to [`JOBS_INDEX`], replacing the one with its ID and creating the index when missing
+    fn save_job(&self, doc: Doc) -> LocalBoxFuture<'_, Result<(), Self::Error>>;
+}
+
+/// ID of the document recording that the job `id` was cancelled
+fn cancellation_id(id: &str) -> String {
+    format!("{}-cancelled", id)
+}
+
+/// Time one step of a job copies for
+pub fn step_budget() -> Duration {
+    Duration::from_millis(get_env_parsed::<u64>(STEP_MS_ENV).ok().flatten().unwrap_or(DEFAULT_STEP_MS))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Running,
+    Completed,
+    Cancelled,
+}
+
+/// Documents read from the source, with the position of the page after them
+#[derive(Debug, Clone)]
+pub struct SourcePage<D> {
+    pub docs: Vec<D>,
+
+    /// `None` once the source has no more documents
+    pub next: Option<String>,
+}
+
+/// Progress of a job, as reported to operators
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReindexStatus {
+    pub id: String,
+    pub provider: String,
+    pub source: String,
+    pub destination: String,
+    pub state: JobState,
+
+    /// Documents written to the destination
+    pub docs_processed: u64,
+
+    /// Documents in pages the destination refused
+    pub docs_failed: u64,
+
+    /// Documents in the source when the job started, when the provider counts them
+    pub docs_total: Option<u64>,
+
+    /// Documents copied per second of stepping, failed ones included
+    pub docs_per_second: Option<f64>,
+
+    /// Seconds of stepping left at the current rate
+    pub eta_seconds: Option<u64>,
+
+    /// Most recent failures, oldest first
+    pub errors: Vec<FailedItem>,
+
+    pub started_at: String,
+    pub last_checkpoint: Option<String>,
+}
+
+/// A copy of one index into another, advanced by [`ReindexJob::run`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReindexJob {
+    pub id: String,
+    pub provider: String,
+    pub source: String,
+    state: JobState,
+
+    /// Progress against the destination; the checkpoint data is the next source position
+    checkpoint: BatchOperationState,
+    docs_failed: u64,
+    docs_total: Option<u64>,
+
+    /// Time spent stepping, for the rate
+    active: Duration,
+}
+
+impl ReindexJob {
+    pub fn state(&self) -> JobState {
+        self.state
+    }
+
+    pub fn destination(&self) -> &str {
+        &self.checkpoint.index_name
+    }
+
+    pub fn status(&self) -> ReindexStatus {
+        let processed = self.checkpoint.processed_items as u64;
+        let done = processed + self.docs_failed;
+        let seconds = self.active.as_secs_f64();
+        let rate = (seconds > 0.0).then(|| done as f64 / seconds);
+        let eta_seconds = match (self.state, self.docs_total, rate) {
+            (JobState::Completed, _, _) => Some(0),
+            (JobState::Running, Some(total), Some(rate)) if rate > 0.0 => {
+                Some((total.saturating_sub(done) as f64 / rate).ceil() as u64)
+            }
+            _ => None,
+        };
+        ReindexStatus {
+            id: self.id.clone(),
+            provider: self.provider.clone(),
+            source: self.source.clone(),
+            destination: self.destination().to_string(),
+            state: self.state,
+            docs_processed: processed,
+            docs_failed: self.docs_failed,
+            docs_total: self.docs_total,
+            docs_per_second: rate,
+            eta_seconds,
+            errors: self.checkpoint.failed_items.clone(),
+            started_at: self.checkpoint.started_at.clone(),
+            last_checkpoint: self.checkpoint.last_checkpoint.clone(),
+        }
+    }
+
+    /// Copy pages for up to `budget`, at least one, checkpointing to `store` after each
+    ///
+    /// `read` reads the source page at a position, `None` being the first, and
+    /// `write` writes documents to the destination. A read error is recorded
+    /// and returned, leaving the position for the next step.
+    pub async fn run<S, D, R, RFut, W, WFut, E>(&mut self, store: &S, budget: Duration, read: R, write: W) -> Result<(), E>
+    where
+        S: JobStore<Error = E>,
+        R: Fn(String, Option<String>) -> RFut,
+        RFut: Future<Output = Result<SourcePage<D>, E>>,
+        W: Fn(String, Vec<D>) -> WFut,
+        WFut: Future<Output = Result<(), E>>,
+        E: ErrorKind + Display + From<SearchError>,
+    {
+        let started = Instant::now();
+        while self.state == JobState::Running {
+            if store.load_job(&cancellation_id(&self.id)).await?.is_some() {
+                info!("Reindex job {} was cancelled after {} documents", self.id, self.checkpoint.processed_items);
+                self.state = JobState::Cancelled;
+                remember(self);
+                break;
+            }
+
+            let position = self.checkpoint.checkpoint_data.clone();
+            let page_started = Instant::now();
+            let page = match read(self.source.clone(), position.clone()).await {
+                Ok(page) => page,
+                Err(e) => {
+                    warn!("Reindex job {} could not read {}: {}", self.id, self.source, e);
+                    self.fail(position, format!("reading: {}", e), &e);
+                    self.active += page_started.elapsed();
+                    if let Err(saved) = self.save(store).await {
+                        warn!("Reindex job {} could not be saved: {}", self.id, saved);
+                    }
+                    return Err(e);
+                }
+            };
+
+            let count = page.docs.len();
+            if count > 0 {
+                match write(self.destination().to_string(), page.docs).await {
+                    Ok(()) => self.checkpoint.processed_items += count,
+                    Err(e) => {
+                        warn!("Reindex job {} skipped {} documents: {}", self.id, count, e);
+                        self.docs_failed += count as u64;
+                        self.fail(position, format!("writing {} documents: {}", count, e), &e);
+                    }
+                }
+            }
+            self.active += page_started.elapsed();
+            self.checkpoint.checkpoint_data = page.next;
+            if self.checkpoint.checkpoint_data.is_none() {
+                self.complete();
+            }
+            self.save(store).await?;
+
+            if started.elapsed() >= budget {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Record a failure at `position`, replacing an earlier one there, as when a read is retried
+    fn fail<E: ErrorKind>(&mut self, position: Option<String>, message: String, error: &E) {
+        let item_id = position.unwrap_or_else(|| "start".to_string());
+        let failed = &mut self.checkpoint.failed_items;
+        failed.retain(|item| item.item_id != item_id);
+        failed.push(FailedItem {
+            item_id,
+            error_message: message,
+            retryable: matches!(error.error_kind(), "timeout" | "rate_limited" | "backpressure" | "internal"),
+        });
+        if failed.len() > KEPT_ERRORS {
+            failed.remove(0);
+        }
+    }
+
+    fn complete(&mut self) {
+        self.state = JobState::Completed;
+        info!(
+            "Reindex job {} copied {} documents from {} to {}, {} failed",
+            self.id, self.checkpoint.processed_items, self.source, self.destination(), self.docs_failed
+        );
+        events::emit(
+            LifecycleEventKind::ReindexCompleted,
+            &self.provider,
+            self.destination(),
+            json!({
+                "job": self.id,
+                "source": self.source,
+                "docs_processed": self.checkpoint.processed_items,
+                "docs_failed": self.docs_failed,
+            }),
+        );
+    }
+
+    /// Save the job to `store`, and in the worker for its next step
+    async fn save<S: JobStore>(&mut self, store: &S) -> Result<(), S::Error> {
+        self.checkpoint.last_checkpoint = Some(Utc::now().to_rfc3339());
+        remember(self);
+        store.save_job(self.to_doc()?).await
+    }
+
+    /// The job as a document of [`JOBS_INDEX`], its state held as a JSON string
+    fn to_doc(&self) -> SearchResult<Doc> {
+        let job = serde_json::to_string(self).map_err(|e| SearchError::Internal(e.to_string()))?;
+        Ok(Doc { id: self.id.clone(), content: json!({ "job": job }).to_string() })
+    }
+
+    fn from_doc(doc: &Doc) -> SearchResult<Self> {
+        let content: Value = serde_json::from_str(&doc.content)?;
+        let job = content["job"]
+            .as_str()
+            .ok_or_else(|| SearchError::Internal(format!("Reindex job {} has no saved state", doc.id)))?;
+        Ok(serde_json::from_str(job)?)
+    }
+}
+
+/// Keep the latest state of a job in the worker
+///
+/// Writes to some backends are applied after they are acknowledged, so the
+/// worker running a job reads its own copy rather than the stored one.
+fn remember(job: &ReindexJob) {
+    global_jobs().insert(job.id.clone(), job.clone());
+}
+
+fn global_jobs() -> MutexGuard<'static, HashMap<String, ReindexJob>> {
+    static JOBS: OnceLock<Mutex<HashMap<String, ReindexJob>>> = OnceLock::new();
+    JOBS.get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// IDs of the last documents read kept in a listing position
+const POSITION_IDS: usize = 16;
+
+/// Position in a listing of documents in a stable order: how many were read, and the IDs of the last ones
+pub fn listing_position(offset: usize, read_ids: &[&str]) -> String {
+    let last_ids = &read_ids[read_ids.len().saturating_sub(POSITION_IDS)..];
+    json!({ "offset": offset, "last_ids": last_ids }).to_string()
+}
+
+/// Where to continue reading `ids`, listed in a stable order, from a [`listing_position`]
+///
+/// Documents deleted before the position shift the ones after it, so the last
+/// documents read are looked up by ID, the latest first. When they are all
+/// gone, reading continues as many documents before the offset; copying a
+/// document twice is harmless, skipping one is not.
+pub fn resume_listing(ids: &[&str], position: Option<&str>) -> SearchResult<usize> {
+    #[derive(Deserialize)]
+    struct ListingPosition {
+        offset: usize,
+        last_ids: Vec<String>,
+    }
+
+    let Some(position) = position else {
+        return Ok(0);
+    };
+    let position: ListingPosition = serde_json::from_str(position)
+        .map_err(|e| SearchError::invalid_query(format!("Invalid reindex position {}: {}", position, e)))?;
+    let Some(last) = position.last_ids.last() else {
+        return Ok(position.offset.min(ids.len()));
+    };
+    if position.offset > 0 && ids.get(position.offset - 1) == Some(&last.as_str()) {
+        return Ok(position.offset);
+    }
+    let found = position.last_ids.iter().rev().find_map(|last| ids.iter().position(|id| id == last));
+    Ok(match found {
+        Some(index) => index + 1,
+        None => position.offset.saturating_sub(position.last_ids.len()).min(ids.len()),
+    })
+}
+
+/// Save a job copying `source` into `destination` to `store`, returning its ID
+///
+/// `docs_total` is the size of the source, for the ETA.
+pub async fn start<S: JobStore>(
+    store: &S,
+    provider: &str,
+    source: &str,
+    destination: &str,
+    docs_total: Option<u64>,
+) -> Result<String, S::Error> {
+    if source == destination {
+        return Err(SearchError::invalid_query("A reindex job needs a destination other than its source").into());
+    }
+    let id = generate_operation_id("reindex");
+    let mut job = ReindexJob {
+        id: id.clone(),
+        provider: provider.to_string(),
+        source: source.to_string(),
+        state: JobState::Running,
+        checkpoint: BatchOperationState {
+            operation_type: BatchOperationType::IndexRebuilding,
+            index_name: destination.to_string(),
+            total_items: docs_total.unwrap_or(0) as usize,
+            processed_items: 0,
+            failed_items: Vec::new(),
+            checkpoint_data: None,
+            started_at: Utc::now().to_rfc3339(),
+            last_checkpoint: None,
+        },
+        docs_failed: 0,
+        docs_total,
+        active: Duration::ZERO,
+    };
+    job.save(store).await?;
+    info!("Started reindex job {} from {} to {}", id, source, destination);
+    Ok(id)
+}
+
+/// The job with `id`, which has to belong to `provider`
+///
+/// The worker running the job has its latest state; any other reads the one
+/// saved in `store`. A job cancelled from another worker is reported as cancelled.
+pub async fn job<S: JobStore>(store: &S, provider: &str, id: &str) -> Result<ReindexJob, S::Error> {
+    let remembered = global_jobs().get(id).cloned();
+    let mut job = match remembered {
+        Some(job) => job,
+        None => match store.load_job(id).await? {
+            Some(doc) => ReindexJob::from_doc(&doc)?,
+            None => return Err(no_job(id).into()),
+        },
+    };
+    if job.provider != provider {
+        return Err(no_job(id).into());
+    }
+    if job.state == JobState::Running && store.load_job(&cancellation_id(id)).await?.is_some() {
+        job.state = JobState::Cancelled;
+    }
+    Ok(job)
+}
+
+fn no_job(id: &str) -> SearchError {
+    SearchError::invalid_query(format!("No reindex job {}", id))
+}
+
+pub async fn status<S: JobStore>(store: &S, provider: &str, id: &str) -> Result<ReindexStatus, S::Error> {
+    Ok(job(store, provider, id).await?.status())
+}
+
+/// Stop a running job before its next page, from any worker; finished jobs are left as they are
+pub async fn cancel<S: JobStore>(store: &S, provider: &str, id: &str) -> Result<ReindexStatus, S::Error> {
+    let mut job = job(store, provider, id).await?;
+    if job.state == JobState::Running {
+        let cancellation = json!({ "cancelled_at": Utc::now().to_rfc3339() });
+        store.save_job(Doc { id: cancellation_id(id), content: cancellation.to_string() }).await?;
+        info!("Cancelled reindex job {} after {} documents", id, job.checkpoint.processed_items);
+        job.state = JobState::Cancelled;
+    }
+    Ok(job.status())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    /// Documents of [`JOBS_INDEX`] on a backend shared by every worker
+    #[derive(Default)]
+    struct Backend {
+        docs: RefCell<HashMap<String, Doc>>,
+    }
+
+    impl JobStore for Backend {
+        type Error = SearchError;
+
+        fn load_job<'a>(&'a self, id: &'a str) -> LocalBoxFuture<'a, SearchResult<Option<Doc>>> {
+            let doc = self.docs.borrow().get(id).cloned();
+            Box::pin(async move { Ok(doc) })
+        }
+
+        fn save_job(&self, doc: Doc) -> LocalBoxFuture<'_, SearchResult<()>> {
+            self.docs.borrow_mut().insert(doc.id.clone(), doc);
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    fn pages(position: Option<String>) -> SearchResult<SourcePage<u32>> {
+        let page: u32 = position.map_or(0, |p| p.parse().unwrap());
+        Ok(SourcePage { docs: vec![page * 10, page * 10 + 1], next: (page < 2).then(|| (page + 1).to_string()) })
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(future)
+    }
+
+    #[test]
+    fn test_job_copies_every_page_and_counts_refused_ones() {
+        let backend = Backend::default();
+        let written = RefCell::new(Vec::new());
+        let id = block_on(start(&backend, "test", "products-v1", "products-v2", Some(6))).unwrap();
+        let mut running = block_on(job(&backend, "test", &id)).unwrap();
+
+        block_on(running.run(
+            &backend,
+            Duration::from_secs(60),
+            |_, position| async move { pages(position) },
+            |index, docs| {
+                assert_eq!(index, "products-v2");
+                let refused = docs.contains(&10);
+                written.borrow_mut().extend(docs);
+                async move { if refused { Err(SearchError::Internal("full".to_string())) } else { Ok(()) } }
+            },
+        ))
+        .unwrap();
+
+        let report = block_on(status(&backend, "test", &id)).unwrap();
+        assert_eq!(report.state, JobState::Completed);
+        assert_eq!(report.docs_processed, 4);
+        assert_eq!(report.docs_failed, 2);
+        assert_eq!(report.eta_seconds, Some(0));
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.errors[0].item_id, "1");
+        assert!(report.errors[0].retryable);
+        assert_eq!(written.borrow().len(), 6);
+        assert!(block_on(status(&backend, "other", &id)).is_err());
+        assert!(block_on(start(&backend, "test", "products", "products", None)).is_err());
+    }
+
+    #[test]
+    fn test_other_workers_follow_and_cancel_a_job_through_the_store() {
+        let backend = Backend::default();
+        let id = block_on(start(&backend, "test", "orders-v1", "orders-v2", Some(6))).unwrap();
+        let reads = RefCell::new(0);
+        let read = |_: String, position: Option<String>| {
+            *reads.borrow_mut() += 1;
+            async move { pages(position) }
+        };
+        let write = |_: String, _: Vec<u32>| async { Ok::<_, SearchError>(()) };
+
+        let mut running = block_on(job(&backend, "test", &id)).unwrap();
+        // A step without a budget still copies one page
+        block_on(running.run(&backend, Duration::ZERO, &read, &write)).unwrap();
+        assert_eq!(*reads.borrow(), 1);
+
+        // Another worker only has what the backend stores
+        global_jobs().remove(&id);
+        let report = block_on(status(&backend, "test", &id)).unwrap();
+        assert_eq!((report.state, report.docs_processed), (JobState::Running, 2));
+        assert_eq!(report.docs_total, Some(6));
+        assert_eq!(block_on(cancel(&backend, "test", &id)).unwrap().state, JobState::Cancelled);
+        assert!(block_on(status(&Backend::default(), "test", &id)).is_err());
+
+        // The worker running the job stops before its next page, and its saves keep the cancellation
+        block_on(running.run(&backend, Duration::from_secs(60), &read, &write)).unwrap();
+        assert_eq!(*reads.borrow(), 1);
+        assert_eq!(running.state(), JobState::Cancelled);
+        global_jobs().remove(&id);
+        let report = block_on(status(&backend, "test", &id)).unwrap();
+        assert_eq!((report.state, report.docs_processed), (JobState::Cancelled, 2));
+    }
+
+    #[test]
+    fn test_retried_failures_replace_each_other_and_listings_resume_after_deletes() {
+        let backend = Backend::default();
+        let id = block_on(start(&backend, "test", "users-v1", "users-v2", None)).unwrap();
+        let mut failing = block_on(job(&backend, "test", &id)).unwrap();
+        let read = |_: String, _: Option<String>| async { Err::<SourcePage<u32>, _>(SearchError::Internal("down".to_string())) };
+        let write = |_: String, _: Vec<u32>| async { Ok::<_, SearchError>(()) };
+        for _ in 0..3 {
+            assert!(block_on(failing.run(&backend, Duration::ZERO, read, write)).is_err());
+        }
+        assert_eq!(block_on(status(&backend, "test", &id)).unwrap().errors.len(), 1);
+        for position in 0..20 {
+            failing.fail(Some(position.to_string()), "failed".to_string(), &SearchError::Unsupported);
+        }
+        assert_eq!(failing.checkpoint.failed_items.len(), KEPT_ERRORS);
+        assert_eq!(failing.checkpoint.failed_items[0].item_id, "10");
+
+        let ids = ["a", "b", "c", "d"];
+        let position = listing_position(2, &["a", "b"]);
+        assert_eq!(resume_listing(&ids, None).unwrap(), 0);
+        assert_eq!(resume_listing(&ids, Some(&position)).unwrap(), 2);
+        // "a" was deleted after the position was taken, shifting the rest back
+        assert_eq!(resume_listing(&["b", "c", "d"], Some(&position)).unwrap(), 1);
+        assert_eq!(resume_listing(&["a", "c", "d"], Some(&position)).unwrap(), 1);
+        assert_eq!(resume_listing(&["c", "d"], Some(&position)).unwrap(), 0);
+        assert!(resume_listing(&ids, Some("b")).is_err());
+    }
+}
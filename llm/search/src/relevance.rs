@@ -0,0 +1,435 @@
+//! Relevance evaluation with judgment lists
+//!
+//! A judgment list grades documents for a set of queries: 0 is irrelevant
+//! and higher grades are better matches. [`evaluate`] runs every judged query
+//! through a search function, typically one provider with one configuration,
+//! and scores the ranking it returns with nDCG@k, MRR and precision@k.
+//! Evaluating the same list against two configurations and passing both
+//! reports to [`compare`] shows whether a ranking change helped, and which
+//! queries it made worse.
+//!
+//! Judgment lists load from JSON, an array of `{"query", "ratings"}` objects
+//! with ratings keyed by document ID, or from tab-separated lines of query,
+//! document ID and grade. Documents a search returns that have no grade count
+//! as irrelevant; [`QueryEvaluation::unjudged`] tells how many there were, so
+//! gaps in the list can be filled.
+
+use std::collections::BTreeMap;
+use std::future::Future;
+use serde::{Deserialize, Serialize};
+use crate::error::{SearchError, SearchResult};
+use crate::types::SearchResults;
+
+/// Cut-off used unless the caller picks one
+pub const DEFAULT_EVALUATION_K: usize = 10;
+
+/// Lowest grade counted as relevant by MRR and precision
+pub const RELEVANT_GRADE: u32 = 1;
+
+/// nDCG differences below this are treated as ties by [`compare`]
+const NDCG_TOLERANCE: f64 = 1e-9;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Judgment {
+    pub query: String,
+
+    /// Grades by document ID
+    pub ratings: BTreeMap<String, u32>,
+}
+
+impl Judgment {
+    pub fn grade(&self, id: &str) -> u32 {
+        self.ratings.get(id).copied().unwrap_or(0)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct JudgmentList {
+    pub judgments: Vec<Judgment>,
+}
+
+impl JudgmentList {
+    pub fn from_json(json: &str) -> SearchResult<Self> {
+        serde_json::from_str(json).map_err(|e| SearchError::invalid_query(format!("Invalid judgment list: {}", e)))
+    }
+
+    /// Parse `query<TAB>document id<TAB>grade` lines; blank lines and lines starting with `#` are skipped
+    ///
+    /// Lines of the same query are grouped, in the order queries first appear.
+    pub fn from_tsv(tsv: &str) -> SearchResult<Self> {
+        let mut list = Self::default();
+        for (number, line) in tsv.lines().enumerate() {
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let columns: Vec<&str> = line.split('\t').collect();
+            let [query, id, grade] = columns[..] else {
+                return Err(SearchError::invalid_query(format!(
+                    "Judgment line {} needs 3 tab-separated columns, found {}",
+                    number + 1,
+                    columns.len()
+                )));
+            };
+            let grade = grade.trim().parse::<u32>().map_err(|e| {
+                SearchError::invalid_query(format!("Judgment line {} has an invalid grade: {}", number + 1, e))
+            })?;
+
+            let position = match list.judgments.iter().position(|j| j.query == query) {
+                Some(position) => position,
+                None => {
+                    list.judgments.push(Judgment { query: query.to_string(), ratings: BTreeMap::new() });
+                    list.judgments.len() - 1
+                }
+            };
+            list.judgments[position].ratings.insert(id.trim().to_string(), grade);
+        }
+        Ok(list)
+    }
+}
+
+/// Scores of one query's ranking
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueryEvaluation {
+    pub query: String,
+    pub ndcg: f64,
+
+    /// 1 / rank of the first relevant hit in the top k, or 0 without one
+    pub reciprocal_rank: f64,
+    pub precision: f64,
+
+    /// Hits considered, at most k
+    pub returned: usize,
+
+    /// Hits in the top k without a grade
+    pub unjudged: usize,
+}
+
+impl QueryEvaluation {
+    /// Score `ranking`, a list of document IDs best first, against `judgment`
+    pub fn score(judgment: &Judgment, ranking: &[String], k: usize) -> Self {
+        let k = k.max(1);
+        let top = &ranking[..ranking.len().min(k)];
+        let grades: Vec<u32> = top.iter().map(|id| judgment.grade(id)).collect();
+
+        let mut ideal: Vec<u32> = judgment.ratings.values().copied().collect();
+        ideal.sort_unstable_by(|a, b| b.cmp(a));
+        ideal.truncate(k);
+        let ideal_dcg = dcg(&ideal);
+
+        let first_relevant = grades.iter().position(|grade| *grade >= RELEVANT_GRADE);
+        Self {
+            query: judgment.query.clone(),
+            ndcg: if ideal_dcg > 0.0 { dcg(&grades) / ideal_dcg } else { 0.0 },
+            reciprocal_rank: first_relevant.map_or(0.0, |rank| 1.0 / (rank + 1) as f64),
+            precision: grades.iter().filter(|grade| **grade >= RELEVANT_GRADE).count() as f64 / k as f64,
+            returned: top.len(),
+            unjudged: top.iter().filter(|id| !judgment.ratings.contains_key(id.as_str())).count(),
+        }
+    }
+}
+
+/// Discounted cumulative gain with exponential gain, rewarding highly graded documents near the top
+fn dcg(grades: &[u32]) -> f64 {
+    grades
+        .iter()
+        .enumerate()
+        .map(|(rank, grade)| (2f64.powi(*grade as i32) - 1.0) / ((rank + 2) as f64).log2())
+        .sum()
+}
+
+/// Scores of one run over a judgment list
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EvaluationReport {
+    /// What was evaluated, such as a provider and configuration name
+    pub label: String,
+    pub k: usize,
+
+    /// Means over all queries
+    pub ndcg: f64,
+    pub mrr: f64,
+    pub precision: f64,
+
+    pub queries: Vec<QueryEvaluation>,
+}
+
+impl EvaluationReport {
+    pub fn new(label: &str, k: usize, queries: Vec<QueryEvaluation>) -> Self {
+        let mean = |metric: fn(&QueryEvaluation) -> f64| {
+            if queries.is_empty() {
+                0.0
+            } else {
+                queries.iter().map(metric).sum::<f64>() / queries.len() as f64
+            }
+        };
+        Self {
+            label: label.to_string(),
+            k,
+            ndcg: mean(|q| q.ndcg),
+            mrr: mean(|q| q.reciprocal_rank),
+            precision: mean(|q| q.precision),
+            queries,
+        }
+    }
+}
+
+/// Document IDs of a result page, best first
+pub fn ranked_ids(results: &SearchResults) -> Vec<String> {
+    results.hits.iter().map(|hit| hit.id.clone()).collect()
+}
+
+/// Run every judged query through `search` and score the top `k` IDs it returns
+///
+/// `search` gets the query text and returns document IDs best first; it
+/// should request at least `k` hits. The first failing search fails the run.
+pub async fn evaluate<S, Fut>(label: &str, judgments: &JudgmentList, k: usize, mut search: S) -> SearchResult<EvaluationReport>
+where
+    S: FnMut(String) -> Fut,
+    Fut: Future<Output = SearchResult<Vec<String>>>,
+{
+    let mut queries = Vec::with_capacity(judgments.judgments.len());
+    for judgment in &judgments.judgments {
+        let ranking = search(judgment.query.clone()).await?;
+        queries.push(QueryEvaluation::score(judgment, &ranking, k));
+    }
+    Ok(EvaluationReport::new(label, k.max(1), queries))
+}
+
+/// Difference between two runs over the same judgment list
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RelevanceComparison {
+    pub baseline: String,
+    pub candidate: String,
+
+    /// Candidate minus baseline
+    pub ndcg_delta: f64,
+    pub mrr_delta: f64,
+    pub precision_delta: f64,
+
+    /// Queries whose nDCG went up or down
+    pub improved: Vec<String>,
+    pub regressed: Vec<String>,
+}
+
+/// Compare a candidate run with a baseline, query by query
+///
+/// Queries only one of the reports contains are left out of the per-query lists.
+pub fn compare(baseline: &EvaluationReport, candidate: &EvaluationReport) -> RelevanceComparison {
+    let before: BTreeMap<&str, f64> = baseline.queries.iter().map(|q| (q.query.as_str(), q.ndcg)).collect();
+    let mut comparison = RelevanceComparison {
+        baseline: baseline.label.clone(),
+        candidate: candidate.label.clone(),
+        ndcg_delta: candidate.ndcg - baseline.ndcg,
+        mrr_delta: candidate.mrr - baseline.mrr,
+        precision_delta: candidate.precision - baseline.precision,
+        improved: Vec::new(),
+        regressed: Vec::new(),
+    };
+    for query in &candidate.queries {
+        let Some(before) = before.get(query.query.as_str()) else {
+            continue;
+        };
+        if query.ndcg > before + NDCG_TOLERANCE {
+            comparison.improved.push(query.query.clone());
+        } else if query.ndcg < before - NDCG_TOLERANCE {
+            comparison.regressed.push(query.query.clone());
+        }
+    }
+    comparison
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    fn judgment(query: &str, ratings: &[(&str, u32)]) -> Judgment {
+        Judgment { query: query.to_string(), ratings: ratings.iter().map(|(id, grade)| (id.to_string(), *grade)).collect() }
+    }
+
+    fn invalid(result: SearchResult<JudgmentList>) -> String {
+        match result {
+            Err(SearchError::InvalidQuery(message)) => message,
+            other => panic!("expected InvalidQuery, got {:?}", other),
+        }
+    }
+
+    fn close(actual: f64, expected: f64) -> bool {
+        (actual - expected).abs() < 1e-4
+    }
+
+    fn report(label: &str, ndcgs: &[(&str, f64)]) -> EvaluationReport {
+        let queries = ndcgs
+            .iter()
+            .map(|(query, ndcg)| QueryEvaluation {
+                query: query.to_string(),
+                ndcg: *ndcg,
+                reciprocal_rank: 0.0,
+                precision: 0.0,
+                returned: 0,
+                unjudged: 0,
+            })
+            .collect();
+        EvaluationReport::new(label, 10, queries)
+    }
+
+    #[test]
+    fn test_tsv_groups_interleaved_queries_in_first_appearance_order() {
+        let list = JudgmentList::from_tsv("emma\t9\t2\n\n# comment\ndune\t1\t3\nemma\t8\t1\ndune\t2\t0\n").unwrap();
+        assert_eq!(list.judgments, [judgment("emma", &[("9", 2), ("8", 1)]), judgment("dune", &[("1", 3), ("2", 0)])]);
+    }
+
+    #[test]
+    fn test_tsv_trims_ids_and_grades_but_not_queries() {
+        let list = JudgmentList::from_tsv(" dune \t 1 \t 3 \r\n").unwrap();
+        assert_eq!(list.judgments, [judgment(" dune ", &[("1", 3)])]);
+    }
+
+    #[test]
+    fn test_a_later_tsv_grade_replaces_an_earlier_one() {
+        let list = JudgmentList::from_tsv("dune\t1\t3\ndune\t1\t0\n").unwrap();
+        assert_eq!(list.judgments[0].grade("1"), 0);
+    }
+
+    #[test]
+    fn test_tsv_errors_name_the_line_counting_skipped_ones() {
+        assert_eq!(
+            invalid(JudgmentList::from_tsv("# header\n\ndune\t1")),
+            "Judgment line 3 needs 3 tab-separated columns, found 2"
+        );
+        assert_eq!(
+            invalid(JudgmentList::from_tsv("dune\t1\t3\t\n")),
+            "Judgment line 1 needs 3 tab-separated columns, found 4"
+        );
+        for grade in ["-1", "2.5", "high", ""] {
+            let message = invalid(JudgmentList::from_tsv(&format!("dune\t1\t3\ndune\t2\t{}", grade)));
+            assert!(message.starts_with("Judgment line 2 has an invalid grade: "), "{}", message);
+        }
+    }
+
+    #[test]
+    fn test_json_judgments_need_a_query_and_ratings() {
+        assert_eq!(
+            JudgmentList::from_json(r#"[{"query": "dune", "ratings": {"1": 3}}]"#).unwrap().judgments,
+            [judgment("dune", &[("1", 3)])]
+        );
+        assert!(invalid(JudgmentList::from_json(r#"[{"query": "dune"}]"#)).starts_with("Invalid judgment list: "));
+        assert!(JudgmentList::from_json(r#"[{"query": "dune", "ratings": {"1": -1}}]"#).is_err());
+        assert!(JudgmentList::from_json("[]").unwrap().judgments.is_empty());
+    }
+
+    #[test]
+    fn test_ndcg_uses_exponential_gain_and_log_discount() {
+        let dune = judgment("dune", &[("1", 3), ("2", 1)]);
+        assert!(close(QueryEvaluation::score(&dune, &ids(&["1", "2"]), 2).ndcg, 1.0));
+        // (1 + 7 / log2 3) / (7 + 1 / log2 3)
+        assert!(close(QueryEvaluation::score(&dune, &ids(&["2", "1"]), 2).ndcg, 0.7098));
+    }
+
+    #[test]
+    fn test_the_ideal_ranking_is_cut_at_k() {
+        let dune = judgment("dune", &[("1", 3), ("2", 3), ("3", 3)]);
+        assert!(close(QueryEvaluation::score(&dune, &ids(&["3"]), 1).ndcg, 1.0));
+        assert!(close(QueryEvaluation::score(&dune, &ids(&["3"]), 3).ndcg, 1.0 / (1.0 + 1.0 / 3f64.log2() + 0.5)));
+    }
+
+    #[test]
+    fn test_queries_without_relevant_documents_score_zero() {
+        let nothing = judgment("dune", &[("1", 0)]);
+        let scored = QueryEvaluation::score(&nothing, &ids(&["1"]), 10);
+        assert_eq!((scored.ndcg, scored.reciprocal_rank, scored.precision), (0.0, 0.0, 0.0));
+        assert_eq!(scored.unjudged, 0);
+    }
+
+    #[test]
+    fn test_hits_below_k_are_ignored() {
+        let dune = judgment("dune", &[("1", 3)]);
+        let scored = QueryEvaluation::score(&dune, &ids(&["x", "y", "1"]), 2);
+        assert_eq!((scored.ndcg, scored.reciprocal_rank, scored.returned, scored.unjudged), (0.0, 0.0, 2, 2));
+    }
+
+    #[test]
+    fn test_precision_divides_by_k_even_with_fewer_hits() {
+        let dune = judgment("dune", &[("1", 1), ("2", 1)]);
+        let scored = QueryEvaluation::score(&dune, &ids(&["1", "2"]), 10);
+        assert!(close(scored.precision, 0.2));
+        assert_eq!(scored.returned, 2);
+    }
+
+    #[test]
+    fn test_reciprocal_rank_counts_the_first_relevant_hit() {
+        let dune = judgment("dune", &[("1", 0), ("2", 1), ("3", 3)]);
+        assert!(close(QueryEvaluation::score(&dune, &ids(&["1", "x", "2", "3"]), 10).reciprocal_rank, 1.0 / 3.0));
+    }
+
+    #[test]
+    fn test_a_zero_cut_off_scores_the_top_hit() {
+        let dune = judgment("dune", &[("1", 1)]);
+        let scored = QueryEvaluation::score(&dune, &ids(&["1", "2"]), 0);
+        assert_eq!((scored.returned, scored.precision), (1, 1.0));
+    }
+
+    #[test]
+    fn test_reports_average_over_queries() {
+        let averaged = report("run", &[("a", 1.0), ("b", 0.5)]);
+        assert!(close(averaged.ndcg, 0.75));
+        let empty = report("run", &[]);
+        assert_eq!((empty.ndcg, empty.mrr, empty.precision), (0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_evaluate_sends_each_query_and_records_the_cut_off() {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let list = JudgmentList { judgments: vec![judgment("dune", &[("1", 3)]), judgment("emma", &[("9", 2)])] };
+        let mut sent = Vec::new();
+        let report = runtime
+            .block_on(evaluate("bm25", &list, 0, |query| {
+                sent.push(query);
+                async { Ok(ids(&["1"])) }
+            }))
+            .unwrap();
+        assert_eq!(sent, ["dune", "emma"]);
+        assert_eq!((report.label.as_str(), report.k), ("bm25", 1));
+        assert!(close(report.ndcg, 0.5));
+    }
+
+    #[test]
+    fn test_evaluate_stops_at_the_first_failed_search() {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let list = JudgmentList { judgments: vec![judgment("a", &[]), judgment("b", &[]), judgment("c", &[])] };
+        let mut calls = 0;
+        let failed = runtime.block_on(evaluate("down", &list, 10, |query| {
+            calls += 1;
+            async move {
+                match query.as_str() {
+                    "b" => Err(SearchError::Timeout { remaining_ms: None }),
+                    _ => Ok(Vec::new()),
+                }
+            }
+        }));
+        assert!(matches!(failed, Err(SearchError::Timeout { .. })));
+        assert_eq!(calls, 2);
+    }
+
+    #[test]
+    fn test_compare_lists_queries_that_moved() {
+        let baseline = report("bm25", &[("up", 0.5), ("down", 0.5), ("same", 0.5)]);
+        let candidate = report("boosted", &[("up", 0.6), ("down", 0.4), ("same", 0.5 + 1e-12)]);
+        let comparison = compare(&baseline, &candidate);
+        assert_eq!((comparison.baseline.as_str(), comparison.candidate.as_str()), ("bm25", "boosted"));
+        assert_eq!(comparison.improved, ["up"]);
+        assert_eq!(comparison.regressed, ["down"]);
+        assert!(close(comparison.ndcg_delta, 0.0));
+    }
+
+    #[test]
+    fn test_compare_skips_queries_missing_from_either_run() {
+        let comparison = compare(&report("a", &[("old", 0.1), ("both", 0.5)]), &report("b", &[("new", 0.9), ("both", 0.5)]));
+        assert!(comparison.improved.is_empty() && comparison.regressed.is_empty());
+        // The means still cover every query of each run
+        assert!(close(comparison.ndcg_delta, 0.4));
+    }
+}
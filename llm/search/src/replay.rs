@@ -0,0 +1,385 @@
+//! Query replay
+//!
+//! [`replay`] runs recorded production queries again, against a new index, a
+//! different provider or a changed configuration, and reports how latency
+//! and results moved. It backs two workflows:
+//!
+//! - Migration: replay the slow query log of the old provider against the new
+//!   one before switching traffic over.
+//! - A/B: replay a set of queries against configuration A, keep the report's
+//!   [`ReplayReport::as_baseline`], then replay that baseline against
+//!   configuration B to see how much the top hits overlap.
+//!
+//! Queries come from the slow query log, which keeps the portable form of
+//! every query it records, or from any JSON list of [`RecordedQuery`]. A
+//! failing query is reported in its outcome and does not stop the run.
+
+use std::future::Future;
+use std::time::Instant;
+use serde::{Deserialize, Serialize};
+use crate::error::SearchResult;
+use crate::slow_query::{get_slow_queries, SlowQueryEntry};
+use crate::types::{SearchQuery, SearchResults};
+
+/// Hits compared per query unless the caller picks another depth
+pub const DEFAULT_REPLAY_DEPTH: usize = 10;
+
+/// A query as it ran in production
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedQuery {
+    pub index: String,
+    pub query: SearchQuery,
+
+    /// Latency when it was recorded
+    #[serde(default)]
+    pub duration_ms: Option<u64>,
+
+    /// Document IDs it returned, best first; overlap is only measured when known
+    #[serde(default)]
+    pub hits: Option<Vec<String>>,
+}
+
+impl RecordedQuery {
+    /// The query of a slow query log entry, if the provider recorded it
+    pub fn from_slow_query(entry: &SlowQueryEntry) -> Option<Self> {
+        Some(Self {
+            index: entry.index.clone(),
+            query: entry.query.clone()?,
+            duration_ms: Some(entry.duration_ms),
+            hits: None,
+        })
+    }
+}
+
+/// Every replayable query in this worker's slow query log, oldest first
+pub fn from_slow_query_log() -> Vec<RecordedQuery> {
+    get_slow_queries().iter().filter_map(RecordedQuery::from_slow_query).collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReplayConfig {
+    /// What the queries are replayed against, such as a provider and index name
+    pub label: String,
+
+    /// Index every query runs against instead of the one it was recorded on
+    pub index: Option<String>,
+
+    /// Top hits compared with the recorded ones
+    pub depth: usize,
+}
+
+impl ReplayConfig {
+    pub fn new(label: &str) -> Self {
+        Self { label: label.to_string(), index: None, depth: DEFAULT_REPLAY_DEPTH }
+    }
+
+    pub fn with_index(mut self, index: &str) -> Self {
+        self.index = Some(index.to_string());
+        self
+    }
+
+    pub fn with_depth(mut self, depth: usize) -> Self {
+        self.depth = depth.max(1);
+        self
+    }
+}
+
+/// How one query did when replayed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayOutcome {
+    /// Index the query was replayed against
+    pub index: String,
+    pub query: SearchQuery,
+    pub recorded_ms: Option<u64>,
+    pub replayed_ms: u64,
+
+    /// Top hits of the replay, best first
+    pub hits: Vec<String>,
+
+    /// Shared top hits over the size of the larger top list, from 0 to 1
+    pub overlap: Option<f64>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayReport {
+    pub label: String,
+    pub queries: usize,
+    pub failed: usize,
+
+    /// Latency percentiles of the recorded and the replayed queries
+    pub recorded_p50_ms: Option<u64>,
+    pub recorded_p95_ms: Option<u64>,
+    pub replayed_p50_ms: Option<u64>,
+    pub replayed_p95_ms: Option<u64>,
+    pub replayed_max_ms: Option<u64>,
+
+    /// Mean overlap over the queries with recorded hits that did not fail
+    pub mean_overlap: Option<f64>,
+    pub outcomes: Vec<ReplayOutcome>,
+}
+
+impl ReplayReport {
+    fn new(label: &str, outcomes: Vec<ReplayOutcome>) -> Self {
+        let recorded: Vec<u64> = outcomes.iter().filter_map(|o| o.recorded_ms).collect();
+        let replayed: Vec<u64> = outcomes.iter().filter(|o| o.error.is_none()).map(|o| o.replayed_ms).collect();
+        let overlaps: Vec<f64> = outcomes.iter().filter_map(|o| o.overlap).collect();
+        Self {
+            label: label.to_string(),
+            queries: outcomes.len(),
+            failed: outcomes.iter().filter(|o| o.error.is_some()).count(),
+            recorded_p50_ms: percentile(&recorded, 50),
+            recorded_p95_ms: percentile(&recorded, 95),
+            replayed_p50_ms: percentile(&replayed, 50),
+            replayed_p95_ms: percentile(&replayed, 95),
+            replayed_max_ms: replayed.iter().max().copied(),
+            mean_overlap: if overlaps.is_empty() {
+                None
+            } else {
+                Some(overlaps.iter().sum::<f64>() / overlaps.len() as f64)
+            },
+            outcomes,
+        }
+    }
+
+    /// The successful replays as recorded queries, to replay against another configuration
+    pub fn as_baseline(&self) -> Vec<RecordedQuery> {
+        self.outcomes
+            .iter()
+            .filter(|o| o.error.is_none())
+            .map(|o| RecordedQuery {
+                index: o.index.clone(),
+                query: o.query.clone(),
+                duration_ms: Some(o.replayed_ms),
+                hits: Some(o.hits.clone()),
+            })
+            .collect()
+    }
+}
+
+/// Nearest-rank percentile
+fn percentile(values: &[u64], percent: usize) -> Option<u64> {
+    let mut sorted = values.to_vec();
+    sorted.sort_unstable();
+    let rank = (sorted.len() * percent).div_ceil(100).max(1);
+    sorted.get(rank - 1).copied()
+}
+
+fn overlap(recorded: &[String], replayed: &[String], depth: usize) -> f64 {
+    let recorded = &recorded[..recorded.len().min(depth)];
+    let replayed = &replayed[..replayed.len().min(depth)];
+    let larger = recorded.len().max(replayed.len());
+    if larger == 0 {
+        return 1.0;
+    }
+    replayed.iter().filter(|id| recorded.contains(id)).count() as f64 / larger as f64
+}
+
+/// Run every recorded query through `search` and compare it with the recording
+///
+/// `search` gets the index and the query to run. Queries are replayed one at
+/// a time so their latencies do not skew each other.
+pub async fn replay<S, Fut>(queries: &[RecordedQuery], config: &ReplayConfig, mut search: S) -> ReplayReport
+where
+    S: FnMut(String, SearchQuery) -> Fut,
+    Fut: Future<Output = SearchResult<SearchResults>>,
+{
+    let depth = config.depth.max(1);
+    let mut outcomes = Vec::with_capacity(queries.len());
+    for recorded in queries {
+        let index = config.index.clone().unwrap_or_else(|| recorded.index.clone());
+        let started = Instant::now();
+        let result = search(index.clone(), recorded.query.clone()).await;
+        let replayed_ms = started.elapsed().as_millis() as u64;
+
+        let (hits, error) = match result {
+            Ok(results) => (results.hits.into_iter().take(depth).map(|hit| hit.id).collect::<Vec<_>>(), None),
+            Err(e) => (Vec::new(), Some(e.to_string())),
+        };
+        let overlap = match (&recorded.hits, &error) {
+            (Some(before), None) => Some(overlap(before, &hits, depth)),
+            _ => None,
+        };
+        outcomes.push(ReplayOutcome {
+            index,
+            query: recorded.query.clone(),
+            recorded_ms: recorded.duration_ms,
+            replayed_ms,
+            hits,
+            overlap,
+            error,
+        });
+    }
+    ReplayReport::new(&config.label, outcomes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::SearchError;
+    use crate::slow_query::SlowQueryLog;
+    use crate::types::{QueryBuilder, SearchHit};
+    use serde_json::json;
+    use std::time::Duration;
+
+    fn results(ids: &[&str]) -> SearchResults {
+        let hits: Vec<SearchHit> = ids
+            .iter()
+            .map(|id| serde_json::from_value::<SearchHit>(json!({ "id": id })).unwrap())
+            .collect();
+        serde_json::from_value(json!({ "hits": hits })).unwrap()
+    }
+
+    fn ids(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    fn recorded(q: &str, duration_ms: Option<u64>, hits: Option<&[&str]>) -> RecordedQuery {
+        RecordedQuery {
+            index: "books".to_string(),
+            query: QueryBuilder::new().query(q).build(),
+            duration_ms,
+            hits: hits.map(ids),
+        }
+    }
+
+    /// Replay where each query returns the IDs its text lists, and `fail` fails
+    fn run(queries: &[RecordedQuery], config: &ReplayConfig) -> ReplayReport {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        runtime.block_on(replay(queries, config, |_, query| {
+            let text = query.q.unwrap_or_default();
+            async move {
+                match text.as_str() {
+                    "fail" => Err(SearchError::Timeout { remaining_ms: None }),
+                    text => Ok(results(&text.split_whitespace().collect::<Vec<_>>())),
+                }
+            }
+        }))
+    }
+
+    #[test]
+    fn test_percentiles_use_the_nearest_rank() {
+        let values: Vec<u64> = (1..=20).rev().map(|n| n * 10).collect();
+        assert_eq!(percentile(&values, 50), Some(100));
+        assert_eq!(percentile(&values, 95), Some(190));
+        assert_eq!(percentile(&[7], 95), Some(7));
+        assert_eq!(percentile(&[7], 0), Some(7));
+        assert_eq!(percentile(&[], 50), None);
+    }
+
+    #[test]
+    fn test_overlap_ignores_order_and_divides_by_the_larger_list() {
+        assert_eq!(overlap(&ids(&["1", "2"]), &ids(&["2", "1"]), 10), 1.0);
+        assert_eq!(overlap(&ids(&["1"]), &ids(&["1", "2", "3", "4"]), 10), 0.25);
+        assert_eq!(overlap(&ids(&["1", "2"]), &[], 10), 0.0);
+    }
+
+    #[test]
+    fn test_two_empty_lists_overlap_fully() {
+        assert_eq!(overlap(&[], &[], 10), 1.0);
+    }
+
+    #[test]
+    fn test_overlap_only_looks_at_the_top_of_both_lists() {
+        assert_eq!(overlap(&ids(&["1", "2", "3"]), &ids(&["1", "3", "2"]), 2), 0.5);
+    }
+
+    #[test]
+    fn test_depth_is_at_least_one() {
+        assert_eq!(ReplayConfig::new("run").with_depth(0).depth, 1);
+        let config = ReplayConfig { depth: 0, ..ReplayConfig::new("run") };
+        let report = run(&[recorded("1 2 3", None, None)], &config);
+        assert_eq!(report.outcomes[0].hits, ["1"]);
+    }
+
+    #[test]
+    fn test_replayed_hits_are_cut_at_the_depth() {
+        let report = run(&[recorded("1 2 3", None, Some(&["3", "2", "1"]))], &ReplayConfig::new("run").with_depth(2));
+        assert_eq!(report.outcomes[0].hits, ["1", "2"]);
+        assert_eq!(report.outcomes[0].overlap, Some(0.5));
+    }
+
+    #[test]
+    fn test_queries_run_on_their_own_index_unless_overridden() {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let queries = [recorded("1", None, None), RecordedQuery { index: "films".to_string(), ..recorded("2", None, None) }];
+        for (config, expected) in [
+            (ReplayConfig::new("run"), ["books", "films"]),
+            (ReplayConfig::new("run").with_index("books-v2"), ["books-v2", "books-v2"]),
+        ] {
+            let mut indexes = Vec::new();
+            let report = runtime.block_on(replay(&queries, &config, |index, _| {
+                indexes.push(index);
+                async { Ok(results(&[])) }
+            }));
+            assert_eq!(indexes, expected);
+            assert_eq!(report.outcomes.iter().map(|o| o.index.as_str()).collect::<Vec<_>>(), expected);
+        }
+    }
+
+    #[test]
+    fn test_failures_are_reported_without_stopping_the_run() {
+        let report = run(&[recorded("fail", Some(5), Some(&["1"])), recorded("1", Some(5), None)], &ReplayConfig::new("run"));
+        assert_eq!((report.queries, report.failed), (2, 1));
+        let failed = &report.outcomes[0];
+        assert_eq!(failed.error.as_deref(), Some(SearchError::Timeout { remaining_ms: None }.to_string().as_str()));
+        assert!(failed.hits.is_empty());
+        // No overlap for a failed query, even with recorded hits
+        assert_eq!(failed.overlap, None);
+        assert_eq!(report.mean_overlap, None);
+    }
+
+    #[test]
+    fn test_failed_queries_are_left_out_of_replayed_latency() {
+        let report = run(&[recorded("fail", Some(5), None)], &ReplayConfig::new("run"));
+        assert_eq!((report.replayed_p50_ms, report.replayed_p95_ms, report.replayed_max_ms), (None, None, None));
+        assert_eq!(report.recorded_p50_ms, Some(5));
+    }
+
+    #[test]
+    fn test_queries_without_a_recorded_latency_are_left_out_of_recorded_percentiles() {
+        let report = run(&[recorded("1", None, None), recorded("2", Some(80), None)], &ReplayConfig::new("run"));
+        assert_eq!((report.recorded_p50_ms, report.recorded_p95_ms), (Some(80), Some(80)));
+        assert!(report.replayed_p50_ms.is_some());
+    }
+
+    #[test]
+    fn test_mean_overlap_covers_only_queries_with_recorded_hits() {
+        let queries = [recorded("1 2", None, Some(&["1", "2"])), recorded("1", None, Some(&["2"])), recorded("3", None, None)];
+        let report = run(&queries, &ReplayConfig::new("run"));
+        assert_eq!(report.mean_overlap, Some(0.5));
+    }
+
+    #[test]
+    fn test_baselines_keep_successful_replays_with_their_hits() {
+        let report = run(&[recorded("fail", None, None), recorded("1 2", Some(5), None)], &ReplayConfig::new("run").with_index("books-v2"));
+        let baseline = report.as_baseline();
+        assert_eq!(baseline.len(), 1);
+        assert_eq!(baseline[0].index, "books-v2");
+        assert_eq!(baseline[0].hits.as_deref(), Some(ids(&["1", "2"]).as_slice()));
+        assert_eq!(baseline[0].duration_ms, Some(report.outcomes[1].replayed_ms));
+
+        // Replaying a baseline unchanged overlaps fully
+        assert_eq!(run(&baseline, &ReplayConfig::new("again")).mean_overlap, Some(1.0));
+    }
+
+    #[test]
+    fn test_only_slow_queries_with_a_portable_query_are_replayable() {
+        let mut log = SlowQueryLog::new(Some(Duration::from_millis(10)), 10);
+        let query = QueryBuilder::new().query("dune").build();
+        log.record_search("typesense", "books", &json!({ "q": "dune" }), Some(&query), Duration::from_millis(40));
+        log.record("typesense", "books", &json!({ "q": "emma" }), Duration::from_millis(40));
+
+        let recorded: Vec<RecordedQuery> = log.entries().iter().filter_map(RecordedQuery::from_slow_query).collect();
+        assert_eq!(recorded.len(), 1);
+        assert_eq!(recorded[0].query.q.as_deref(), Some("dune"));
+        assert_eq!((recorded[0].duration_ms, recorded[0].hits.as_ref()), (Some(40), None));
+    }
+
+    #[test]
+    fn test_recorded_queries_need_only_an_index_and_query() {
+        let query = serde_json::to_value(QueryBuilder::new().query("dune").build()).unwrap();
+        let recorded: RecordedQuery = serde_json::from_value(json!({ "index": "books", "query": query })).unwrap();
+        assert_eq!((recorded.duration_ms, recorded.hits), (None, None));
+    }
+}
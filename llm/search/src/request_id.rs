@@ -0,0 +1,177 @@
+//! Request IDs for end-to-end tracing
+//!
+//! Every operation gets an ID that is sent to the backend as `X-Request-Id`,
+//! written into log lines and error messages, and returned with search
+//! results, so a failing call can be matched with the backend's own logs.
+//!
+//! An operation opens a [`RequestScope`]; clients read the active ID with
+//! [`current_or_generate`]. Component calls run on a single thread, so the
+//! active ID is kept thread-local. Nested scopes reuse the outer ID, which
+//! keeps the HTTP calls of one operation (a search plus its lookup join, say)
+//! under a single ID.
+
+use std::cell::RefCell;
+use std::fmt::Display;
+use crate::error::SearchError;
+
+/// Header carrying the request ID
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+thread_local! {
+    static CURRENT: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// A fresh request ID
+pub fn generate() -> String {
+    uuid::Uuid::new_v4().simple().to_string()
+}
+
+/// ID of the operation running on this thread, if any
+pub fn current() -> Option<String> {
+    CURRENT.with(|current| current.borrow().clone())
+}
+
+/// ID of the running operation, or a fresh one for a call made outside any scope
+pub fn current_or_generate() -> String {
+    current().unwrap_or_else(generate)
+}
+
+/// Marks the extent of one operation; the previous ID is restored on drop
+#[derive(Debug)]
+pub struct RequestScope {
+    id: String,
+    previous: Option<String>,
+}
+
+impl RequestScope {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl Drop for RequestScope {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        CURRENT.with(|current| *current.borrow_mut() = previous);
+    }
+}
+
+/// Start an operation, reusing the active ID when called inside another one
+pub fn begin() -> RequestScope {
+    CURRENT.with(|current| {
+        let previous = current.borrow().clone();
+        let id = previous.clone().unwrap_or_else(generate);
+        *current.borrow_mut() = Some(id.clone());
+        RequestScope { id, previous }
+    })
+}
+
+/// Append the active request ID to a message
+pub fn tag(message: impl Display) -> String {
+    match current() {
+        Some(id) => format!("{} [request-id: {}]", message, id),
+        None => message.to_string(),
+    }
+}
+
+/// Add the active request ID to an error's message
+pub fn annotate(error: SearchError) -> SearchError {
+    match error {
+        SearchError::IndexNotFound(msg) => SearchError::IndexNotFound(tag(msg)),
+        SearchError::IndexAlreadyExists(msg) => SearchError::IndexAlreadyExists(tag(msg)),
+        SearchError::InvalidQuery(msg) => SearchError::InvalidQuery(tag(msg)),
+        SearchError::Internal(msg) => SearchError::Internal(tag(msg)),
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_ids_are_unique_hex() {
+        let id = generate();
+        assert_eq!(id.len(), 32);
+        assert!(id.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(generate(), generate());
+    }
+
+    #[test]
+    fn test_calls_outside_a_scope_get_a_fresh_id_each() {
+        assert_eq!(current(), None);
+        assert_ne!(current_or_generate(), current_or_generate());
+    }
+
+    #[test]
+    fn test_calls_inside_a_scope_reuse_its_id() {
+        let scope = begin();
+        assert_eq!(current_or_generate(), scope.id());
+        assert_eq!(current_or_generate(), scope.id());
+    }
+
+    #[test]
+    fn test_nested_scopes_share_the_outer_id() {
+        let outer = begin();
+        {
+            let inner = begin();
+            assert_eq!(inner.id(), outer.id());
+        }
+        // Dropping the inner scope keeps the outer one active
+        assert_eq!(current().as_deref(), Some(outer.id()));
+        drop(outer);
+        assert_eq!(current(), None);
+    }
+
+    #[test]
+    fn test_consecutive_scopes_get_their_own_ids() {
+        let first = begin().id().to_string();
+        let second = begin().id().to_string();
+        assert_ne!(first, second);
+        assert_eq!(current(), None);
+    }
+
+    #[test]
+    fn test_scopes_are_per_thread() {
+        let _scope = begin();
+        assert_eq!(std::thread::spawn(current).join().unwrap(), None);
+    }
+
+    #[test]
+    fn test_tag_appends_the_active_id() {
+        assert_eq!(tag("boom"), "boom");
+        let scope = begin();
+        assert_eq!(tag(format_args!("{} failed", "search")), format!("search failed [request-id: {}]", scope.id()));
+    }
+
+    #[test]
+    fn test_annotate_tags_every_error_with_a_message() {
+        let scope = begin();
+        let suffix = format!(" [request-id: {}]", scope.id());
+        for error in [
+            SearchError::IndexNotFound("books".to_string()),
+            SearchError::IndexAlreadyExists("books".to_string()),
+            SearchError::InvalidQuery("bad".to_string()),
+            SearchError::Internal("boom".to_string()),
+        ] {
+            let expected = format!("{}{}", error, suffix);
+            assert_eq!(annotate(error).to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_annotate_leaves_structured_errors_alone() {
+        let _scope = begin();
+        assert!(matches!(annotate(SearchError::Timeout { remaining_ms: Some(5) }), SearchError::Timeout { remaining_ms: Some(5) }));
+        assert!(matches!(
+            annotate(SearchError::RateLimited { retry_after_ms: Some(7) }),
+            SearchError::RateLimited { retry_after_ms: Some(7) }
+        ));
+        assert!(matches!(annotate(SearchError::Unsupported), SearchError::Unsupported));
+    }
+
+    #[test]
+    fn test_annotate_outside_a_scope_keeps_the_message() {
+        assert_eq!(annotate(SearchError::Internal("boom".to_string())).to_string(), "Internal error: boom");
+    }
+}
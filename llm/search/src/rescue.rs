@@ -0,0 +1,406 @@
+//! Zero-results rescue
+//!
+//! A search that finds nothing leaves a user at a dead end. With
+//! [`RESCUE_ENV`] set, providers search again with the query relaxed one
+//! step at a time until something is found:
+//!
+//! - `drop_filters`: the filters are left out.
+//! - `typos`: typos are allowed, by term length as the engine decides.
+//! - `any_terms`: a document has to contain any of the query terms, not all.
+//! - `popular`: the text is left out too, so the best-ranked documents are
+//!   returned; `popular=sales:desc` sorts them by a field instead.
+//!
+//! Steps are applied on top of each other in the order listed, and a step
+//! that would not change the query is skipped. The results say which steps
+//! were applied in `relaxations`, so a UI can show "showing results for…".
+//! When every step still finds nothing, the results of the original query
+//! are returned.
+//!
+//! Before any step, a query finding few hits may be corrected for spelling
+//! (see [`crate::spellfix`]); a correction that finds enough hits is returned
+//! with `original_query` and `corrected_query` set.
+
+use std::fmt;
+use std::future::Future;
+use log::debug;
+use crate::config::env_helpers::get_env_parsed;
+use crate::error::{SearchError, SearchResult};
+use crate::query::{Fuzziness, MinimumShouldMatch};
+use crate::spellfix::{self, Correction, SpellFixConfig};
+use crate::suggestions::Suggestion;
+use crate::types::{QueryText, SearchConfig, SearchQuery, SearchResults};
+
+/// Environment variable listing the rescue steps, such as `drop_filters,typos,any_terms,popular`
+pub const RESCUE_ENV: &str = "SEARCH_ZERO_RESULTS_RESCUE";
+
+/// One way of relaxing a query
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Relaxation {
+    DropFilters,
+    Typos,
+    AnyTerms,
+
+    /// Match every document, sorted by `sort` when given
+    Popular { sort: Option<String> },
+}
+
+impl Relaxation {
+    /// Name reported in `relaxations`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::DropFilters => "drop_filters",
+            Self::Typos => "typos",
+            Self::AnyTerms => "any_terms",
+            Self::Popular { .. } => "popular",
+        }
+    }
+}
+
+impl fmt::Display for Relaxation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Popular { sort: Some(sort) } => write!(f, "popular={}", sort),
+            other => f.write_str(other.name()),
+        }
+    }
+}
+
+impl std::str::FromStr for Relaxation {
+    type Err = SearchError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (step, argument) = match s.trim().split_once('=') {
+            Some((step, argument)) => (step.trim(), Some(argument.trim()).filter(|a| !a.is_empty())),
+            None => (s.trim(), None),
+        };
+        match (step.to_ascii_lowercase().as_str(), argument) {
+            ("drop_filters", None) => Ok(Self::DropFilters),
+            ("typos", None) => Ok(Self::Typos),
+            ("any_terms", None) => Ok(Self::AnyTerms),
+            ("popular", sort) => Ok(Self::Popular { sort: sort.map(str::to_string) }),
+            _ => Err(SearchError::invalid_query(format!(
+                "Unknown rescue step: {} (expected drop_filters, typos, any_terms or popular[=field:order])",
+                s.trim()
+            ))),
+        }
+    }
+}
+
+/// Steps tried, in order, on a query that found nothing
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RescuePlan {
+    steps: Vec<Relaxation>,
+    spellfix: Option<SpellFixConfig>,
+}
+
+impl RescuePlan {
+    /// Plan from a comma-separated list of steps
+    pub fn parse(spec: &str) -> SearchResult<Self> {
+        let steps = spec
+            .split(',')
+            .filter(|step| !step.trim().is_empty())
+            .map(str::parse)
+            .collect::<SearchResult<_>>()?;
+        Ok(Self { steps, spellfix: None })
+    }
+
+    /// Plan of `SEARCH_ZERO_RESULTS_RESCUE`, empty when unset or invalid, with the spell-fix of the environment
+    pub fn from_env() -> Self {
+        let plan: Self = get_env_parsed::<String>(RESCUE_ENV)
+            .ok()
+            .flatten()
+            .and_then(|spec| Self::parse(&spec).ok())
+            .unwrap_or_default();
+        Self { spellfix: SpellFixConfig::from_env(), ..plan }
+    }
+
+    /// The plan, correcting spelling first as `spellfix` says
+    pub fn with_spellfix(self, spellfix: SpellFixConfig) -> Self {
+        Self { spellfix: Some(spellfix), ..self }
+    }
+
+    pub fn steps(&self) -> &[Relaxation] {
+        &self.steps
+    }
+
+    pub fn spellfix(&self) -> Option<&SpellFixConfig> {
+        self.spellfix.as_ref()
+    }
+
+    /// Queries to try in turn, each with the steps applied to reach it
+    pub fn attempts<Q: RescueQuery>(&self, query: &Q) -> Vec<(Q, Vec<Relaxation>)> {
+        let mut attempts = Vec::new();
+        let mut relaxed = query.clone();
+        let mut applied = Vec::new();
+        for step in &self.steps {
+            if let Some(next) = relaxed.relaxed(step) {
+                applied.push(step.clone());
+                attempts.push((next.clone(), applied.clone()));
+                relaxed = next;
+            }
+        }
+        attempts
+    }
+}
+
+/// A provider's query, as far as relaxing it goes
+pub trait RescueQuery: Clone {
+    /// The query with `relaxation` applied, or `None` when it would not change
+    fn relaxed(&self, relaxation: &Relaxation) -> Option<Self>;
+
+    /// Text to check the spelling of; `None` for a query matching everything
+    fn query_text(&self) -> Option<&str>;
+
+    /// The query searching `text` instead
+    fn with_text(&self, text: &str) -> Self;
+}
+
+/// A provider's results, as far as rescuing them goes
+pub trait RescueResults {
+    /// Whether the query matched no document at all
+    fn found_nothing(&self) -> bool;
+
+    /// Record the steps that produced the results
+    fn set_relaxations(&mut self, relaxations: &[Relaxation]);
+
+    /// Documents matched, or the hits returned when the total is unknown
+    fn hit_count(&self) -> u64;
+
+    /// Record the spelling correction that produced the results
+    fn set_correction(&mut self, correction: &Correction);
+}
+
+/// Run `query` with `search`, corrected and relaxed by `plan` while it finds too little
+///
+/// `lookup` returns the suggestions close to a text for the spell-fix; when it
+/// fails the query is not corrected. Errors of `search` are returned as they
+/// are, so providers keep their own error type.
+pub async fn search_rescued<Q, R, E, F, Fut, L, LFut>(query: &Q, plan: &RescuePlan, lookup: L, mut search: F) -> Result<R, E>
+where
+    Q: RescueQuery,
+    R: RescueResults,
+    E: fmt::Display,
+    F: FnMut(Q) -> Fut,
+    Fut: Future<Output = Result<R, E>>,
+    L: FnOnce(String) -> LFut,
+    LFut: Future<Output = Result<Vec<Suggestion>, E>>,
+{
+    let mut results = search(query.clone()).await?;
+    if let (Some(config), Some(text)) = (plan.spellfix(), query.query_text()) {
+        if config.checks(results.hit_count()) {
+            match lookup(text.to_string()).await {
+                Ok(candidates) => {
+                    if let Some(correction) = spellfix::best_correction(text, &candidates, config.min_confidence) {
+                        let mut corrected = search(query.with_text(&correction.corrected)).await?;
+                        if config.improves(results.hit_count(), corrected.hit_count()) {
+                            debug!("Corrected \"{}\" to \"{}\" with confidence {:.2}", correction.original, correction.corrected, correction.confidence);
+                            corrected.set_correction(&correction);
+                            results = corrected;
+                        }
+                    }
+                }
+                Err(e) => debug!("Spelling lookup failed, searching the query as typed: {}", e),
+            }
+        }
+    }
+    if !results.found_nothing() {
+        return Ok(results);
+    }
+    for (relaxed, relaxations) in plan.attempts(query) {
+        let mut rescued = search(relaxed).await?;
+        if !rescued.found_nothing() {
+            debug!("Query found nothing; rescued with {}", names(&relaxations).join(", "));
+            rescued.set_relaxations(&relaxations);
+            return Ok(rescued);
+        }
+    }
+    Ok(results)
+}
+
+/// Names of `relaxations`, as results report them
+pub fn names(relaxations: &[Relaxation]) -> Vec<String> {
+    relaxations.iter().map(|r| r.name().to_string()).collect()
+}
+
+/// Fuzziness of the `typos` step, unless the query allows as many typos already
+///
+/// `unset` is what the provider allows without a fuzziness.
+pub fn relaxed_fuzziness(current: Option<Fuzziness>, unset: Fuzziness) -> Option<Fuzziness> {
+    match current.unwrap_or(unset) {
+        Fuzziness::Exact | Fuzziness::One => Some(Fuzziness::Auto),
+        Fuzziness::Two | Fuzziness::Auto => None,
+    }
+}
+
+/// Whether the `any_terms` step changes a query with `text`, which requires `current` of its terms
+///
+/// Without a minimum every provider already matches documents with any of the terms.
+pub fn widens_terms(text: QueryText<'_>, current: Option<MinimumShouldMatch>) -> bool {
+    let several_terms = matches!(text, QueryText::Text(text) if text.split_whitespace().nth(1).is_some());
+    several_terms && current.is_some_and(|minimum| !minimum.is_any())
+}
+
+impl RescueQuery for SearchQuery {
+    fn relaxed(&self, relaxation: &Relaxation) -> Option<Self> {
+        let config = self.config.as_ref();
+        let mut query = self.clone();
+        match relaxation {
+            Relaxation::DropFilters => {
+                if self.filters.is_empty() {
+                    return None;
+                }
+                query.filters.clear();
+            }
+            Relaxation::Typos => {
+                if self.is_match_all() {
+                    return None;
+                }
+                let fuzziness = relaxed_fuzziness(config.and_then(|c| c.fuzziness), Fuzziness::Exact)?;
+                query.config.get_or_insert_with(SearchConfig::default).fuzziness = Some(fuzziness);
+            }
+            Relaxation::AnyTerms => {
+                if !widens_terms(self.text(), config.and_then(|c| c.minimum_should_match)) {
+                    return None;
+                }
+                query.config.get_or_insert_with(SearchConfig::default).minimum_should_match = Some(MinimumShouldMatch::Any);
+            }
+            Relaxation::Popular { sort } => {
+                let sorted = sort.as_ref().is_none_or(|sort| self.sort == [sort.clone()]);
+                if self.is_match_all() && self.filters.is_empty() && sorted {
+                    return None;
+                }
+                query.q = None;
+                query.filters.clear();
+                if let Some(sort) = sort {
+                    query.sort = vec![sort.clone()];
+                }
+            }
+        }
+        Some(query)
+    }
+
+    fn query_text(&self) -> Option<&str> {
+        match self.text() {
+            QueryText::Text(text) => Some(text),
+            QueryText::MatchAll => None,
+        }
+    }
+
+    fn with_text(&self, text: &str) -> Self {
+        Self { q: Some(text.to_string()), ..self.clone() }
+    }
+}
+
+impl RescueResults for SearchResults {
+    fn found_nothing(&self) -> bool {
+        self.total.map_or(self.hits.is_empty(), |total| total == 0)
+    }
+
+    fn set_relaxations(&mut self, relaxations: &[Relaxation]) {
+        self.relaxations = names(relaxations);
+    }
+
+    fn hit_count(&self) -> u64 {
+        self.total.map_or(self.hits.len() as u64, u64::from)
+    }
+
+    fn set_correction(&mut self, correction: &Correction) {
+        self.original_query = Some(correction.original.clone());
+        self.corrected_query = Some(correction.corrected.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::QueryBuilder;
+
+    #[test]
+    fn test_plan_parsed_and_steps_applied_in_turn() {
+        let plan = RescuePlan::parse("drop_filters, typos,any_terms , popular=sales:desc").unwrap();
+        assert_eq!(plan.steps().len(), 4);
+        assert_eq!(plan.steps()[3].to_string(), "popular=sales:desc");
+        assert!(RescuePlan::parse("drop_filters,everything").is_err());
+        assert!(RescuePlan::parse("typos=2").is_err());
+
+        let mut query = QueryBuilder::new().query("red shoes").filter("brand:acme").build();
+        query.config = Some(SearchConfig { minimum_should_match: Some(MinimumShouldMatch::All), ..SearchConfig::default() });
+        let attempts = plan.attempts(&query);
+        assert_eq!(attempts.len(), 4);
+        assert!(attempts[0].0.filters.is_empty());
+        assert_eq!(attempts[1].0.config.as_ref().unwrap().fuzziness, Some(Fuzziness::Auto));
+        assert_eq!(names(&attempts[2].1), ["drop_filters", "typos", "any_terms"]);
+        let popular = &attempts[3].0;
+        assert!(popular.is_match_all());
+        assert_eq!(popular.sort, ["sales:desc"]);
+
+        // Steps that change nothing are skipped
+        let attempts = plan.attempts(&QueryBuilder::new().query("shoes").build());
+        assert_eq!(names(&attempts.last().unwrap().1), ["typos", "popular"]);
+    }
+
+    fn results(total: u32) -> SearchResults {
+        SearchResults {
+            total: Some(total),
+            page: Some(1),
+            per_page: Some(10),
+            hits: Vec::new(),
+            facets: None,
+            took_ms: Some(1),
+            truncated: false,
+            request_id: None,
+            groups: None,
+            next_cursor: None,
+            total_exact: None,
+            facet_metadata: Vec::new(),
+            relaxations: Vec::new(),
+            original_query: None,
+            corrected_query: None,
+        }
+    }
+
+    async fn lookup(_text: String) -> SearchResult<Vec<Suggestion>> {
+        Ok(vec![Suggestion { query: "red shoes".to_string(), popularity: 3 }])
+    }
+
+    #[test]
+    fn test_rescued_results_report_relaxations() {
+        let plan = RescuePlan::parse("drop_filters,popular").unwrap();
+        let query = QueryBuilder::new().query("shoes").filter("brand:acme").build();
+        let search = |query: SearchQuery| async move {
+            let found = query.filters.is_empty() && query.is_match_all();
+            Ok::<_, SearchError>(results(found as u32))
+        };
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+
+        let rescued = runtime.block_on(search_rescued(&query, &plan, lookup, search)).unwrap();
+        assert_eq!(rescued.relaxations, ["drop_filters", "popular"]);
+        assert_eq!(rescued.total, Some(1));
+        assert_eq!(rescued.corrected_query, None);
+
+        let rescued = runtime.block_on(search_rescued(&query, &RescuePlan::default(), lookup, search)).unwrap();
+        assert!(rescued.found_nothing());
+        assert!(rescued.relaxations.is_empty());
+    }
+
+    #[test]
+    fn test_misspelled_query_corrected_before_relaxing() {
+        let plan = RescuePlan::parse("drop_filters").unwrap().with_spellfix(SpellFixConfig::new(0.8));
+        let search = |query: SearchQuery| async move {
+            Ok::<_, SearchError>(results(if query.q.as_deref() == Some("red shoes") { 12 } else { 1 }))
+        };
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+
+        let query = QueryBuilder::new().query("rde shoes").filter("brand:acme").build();
+        let corrected = runtime.block_on(search_rescued(&query, &plan, lookup, search)).unwrap();
+        assert_eq!(corrected.total, Some(12));
+        assert_eq!(corrected.original_query.as_deref(), Some("rde shoes"));
+        assert_eq!(corrected.corrected_query.as_deref(), Some("red shoes"));
+        assert!(corrected.relaxations.is_empty());
+
+        // Too far from any suggestion to be corrected
+        let query = QueryBuilder::new().query("blue hats").build();
+        let results = runtime.block_on(search_rescued(&query, &plan, lookup, search)).unwrap();
+        assert_eq!((results.total, results.corrected_query), (Some(1), None));
+    }
+}
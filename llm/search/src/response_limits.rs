@@ -0,0 +1,308 @@
+//! Response size limits
+//!
+//! A single query asking for thousands of large documents can produce a
+//! response body big enough to exhaust a Golem worker's memory. Providers check
+//! the raw body length against [`ResponseLimits`] before parsing and then trim
+//! the parsed hits so the results handed back to the caller stay within the
+//! same budget. Depending on the policy an oversized response is either
+//! truncated (with [`SearchResults::truncated`] set) or rejected with
+//! [`SearchError::ResponseTooLarge`].
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+use crate::config::env_helpers::get_env_parsed;
+use crate::error::{SearchError, SearchResult};
+use crate::types::{SearchHit, SearchResults};
+
+/// Environment variable holding the maximum response size in bytes
+pub const MAX_RESPONSE_BYTES_ENV: &str = "SEARCH_MAX_RESPONSE_BYTES";
+
+/// Environment variable selecting the oversize policy (`truncate` or `error`)
+pub const OVERSIZE_POLICY_ENV: &str = "SEARCH_RESPONSE_OVERSIZE_POLICY";
+
+/// Default response size limit (64 MiB)
+pub const DEFAULT_MAX_RESPONSE_BYTES: usize = 64 * 1024 * 1024;
+
+/// What to do when a response exceeds the limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OversizePolicy {
+    /// Drop trailing hits until the results fit and flag them as truncated
+    #[default]
+    Truncate,
+
+    /// Fail the query with `SearchError::ResponseTooLarge`
+    Error,
+}
+
+impl std::str::FromStr for OversizePolicy {
+    type Err = SearchError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "truncate" => Ok(Self::Truncate),
+            "error" => Ok(Self::Error),
+            other => Err(SearchError::invalid_query(format!("Unknown response oversize policy: {}", other))),
+        }
+    }
+}
+
+/// Size limit and policy applied to search responses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResponseLimits {
+    /// Maximum response size in bytes
+    pub max_bytes: usize,
+
+    /// Behaviour when `max_bytes` is exceeded
+    pub policy: OversizePolicy,
+}
+
+impl Default for ResponseLimits {
+    fn default() -> Self {
+        Self {
+            max_bytes: DEFAULT_MAX_RESPONSE_BYTES,
+            policy: OversizePolicy::default(),
+        }
+    }
+}
+
+impl ResponseLimits {
+    /// Read limits from `SEARCH_MAX_RESPONSE_BYTES` and `SEARCH_RESPONSE_OVERSIZE_POLICY`
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            max_bytes: get_env_parsed::<usize>(MAX_RESPONSE_BYTES_ENV)
+                .ok()
+                .flatten()
+                .filter(|&bytes| bytes > 0)
+                .unwrap_or(defaults.max_bytes),
+            policy: get_env_parsed::<OversizePolicy>(OVERSIZE_POLICY_ENV)
+                .ok()
+                .flatten()
+                .unwrap_or(defaults.policy),
+        }
+    }
+
+    fn too_large(&self, size: usize) -> SearchError {
+        SearchError::ResponseTooLarge { size, limit: self.max_bytes }
+    }
+
+    /// Check a raw response body before it is parsed
+    ///
+    /// Under the `Error` policy an oversized body is rejected here, before any
+    /// parsing allocates. Under `Truncate` it is let through and trimmed later
+    /// by [`enforce`](Self::enforce) or [`truncate_hits`](Self::truncate_hits).
+    pub fn check_body(&self, len: usize) -> SearchResult<()> {
+        if len > self.max_bytes && self.policy == OversizePolicy::Error {
+            return Err(self.too_large(len));
+        }
+        Ok(())
+    }
+
+    /// Drop trailing hits until their combined size fits the limit
+    ///
+    /// `size_of` estimates the payload size of a hit. Returns whether any hit
+    /// was dropped, or `ResponseTooLarge` under the `Error` policy.
+    pub fn truncate_hits<H>(&self, hits: &mut Vec<H>, size_of: impl Fn(&H) -> usize) -> SearchResult<bool> {
+        let mut total = 0usize;
+        let keep = hits
+            .iter()
+            .position(|hit| {
+                total = total.saturating_add(size_of(hit));
+                total > self.max_bytes
+            });
+
+        let Some(keep) = keep else {
+            return Ok(false);
+        };
+
+        if self.policy == OversizePolicy::Error {
+            let size = hits.iter().map(&size_of).fold(0usize, usize::saturating_add);
+            return Err(self.too_large(size));
+        }
+
+        warn!(
+            "Search response exceeds {} bytes; returning {} of {} hits",
+            self.max_bytes,
+            keep,
+            hits.len()
+        );
+        hits.truncate(keep);
+        Ok(true)
+    }
+
+    /// Apply the limit to shared search results, setting `truncated` when hits are dropped
+    pub fn enforce(&self, results: &mut SearchResults) -> SearchResult<()> {
+        if self.truncate_hits(&mut results.hits, hit_size)? {
+            results.truncated = true;
+        }
+        Ok(())
+    }
+}
+
+/// Approximate payload size of a hit
+pub fn hit_size(hit: &SearchHit) -> usize {
+    hit.id.len()
+        + hit.content.as_ref().map_or(0, String::len)
+        + hit.highlights.as_ref().map_or(0, String::len)
+        + hit.explanation.as_ref().map_or(0, String::len)
+}
+
+/// Approximate payload size of a result page
+pub fn results_size(results: &SearchResults) -> usize {
+    let group_hits = results.groups.iter().flatten().flat_map(|group| &group.hits);
+    results.hits.iter().chain(group_hits).map(hit_size).sum::<usize>() + results.facets.as_ref().map_or(0, String::len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::SearchGroup;
+
+    fn hit(id: &str, content: &str) -> SearchHit {
+        SearchHit {
+            id: id.to_string(),
+            score: None,
+            content: Some(content.to_string()),
+            highlights: None,
+            explanation: None,
+            distance_meters: None,
+        }
+    }
+
+    fn results(hits: Vec<SearchHit>) -> SearchResults {
+        SearchResults {
+            total: Some(hits.len() as u32),
+            page: None,
+            per_page: None,
+            hits,
+            facets: None,
+            took_ms: None,
+            truncated: false,
+            request_id: None,
+            groups: None,
+            next_cursor: None,
+            total_exact: None,
+            facet_metadata: Vec::new(),
+            relaxations: Vec::new(),
+            original_query: None,
+            corrected_query: None,
+        }
+    }
+
+    fn limits(max_bytes: usize, policy: OversizePolicy) -> ResponseLimits {
+        ResponseLimits { max_bytes, policy }
+    }
+
+    #[test]
+    fn test_policies_parse_trimmed_and_in_any_case() {
+        assert_eq!(" Truncate ".parse::<OversizePolicy>().unwrap(), OversizePolicy::Truncate);
+        assert_eq!("ERROR".parse::<OversizePolicy>().unwrap(), OversizePolicy::Error);
+        match " Drop ".parse::<OversizePolicy>() {
+            Err(SearchError::InvalidQuery(message)) => assert_eq!(message, "Unknown response oversize policy: drop"),
+            other => panic!("expected InvalidQuery, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_defaults_truncate_at_64_mib() {
+        assert_eq!(ResponseLimits::default(), limits(64 * 1024 * 1024, OversizePolicy::Truncate));
+    }
+
+    #[test]
+    fn test_bodies_at_the_limit_pass() {
+        assert!(limits(10, OversizePolicy::Error).check_body(10).is_ok());
+        assert!(matches!(
+            limits(10, OversizePolicy::Error).check_body(11),
+            Err(SearchError::ResponseTooLarge { size: 11, limit: 10 })
+        ));
+    }
+
+    #[test]
+    fn test_oversized_bodies_pass_the_truncate_policy_for_trimming_later() {
+        assert!(limits(10, OversizePolicy::Truncate).check_body(usize::MAX).is_ok());
+    }
+
+    #[test]
+    fn test_hits_exactly_filling_the_limit_are_kept() {
+        let mut hits = vec![5, 5];
+        assert!(!limits(10, OversizePolicy::Truncate).truncate_hits(&mut hits, |size| *size).unwrap());
+        assert_eq!(hits, [5, 5]);
+    }
+
+    #[test]
+    fn test_truncation_keeps_the_hits_before_the_one_that_overflows() {
+        let mut hits = vec![4, 6, 1, 1];
+        assert!(limits(9, OversizePolicy::Truncate).truncate_hits(&mut hits, |size| *size).unwrap());
+        // The small hits after the overflowing one are dropped as well, to keep the ranking
+        assert_eq!(hits, [4]);
+    }
+
+    #[test]
+    fn test_a_first_hit_over_the_limit_leaves_no_hits() {
+        let mut hits = vec![11, 1];
+        assert!(limits(10, OversizePolicy::Truncate).truncate_hits(&mut hits, |size| *size).unwrap());
+        assert!(hits.is_empty());
+    }
+
+    #[test]
+    fn test_no_hits_never_truncate() {
+        let mut hits: Vec<usize> = Vec::new();
+        assert!(!limits(0, OversizePolicy::Error).truncate_hits(&mut hits, |size| *size).unwrap());
+    }
+
+    #[test]
+    fn test_the_error_policy_reports_the_size_of_every_hit() {
+        let mut hits = vec![8, 8, 8];
+        assert!(matches!(
+            limits(10, OversizePolicy::Error).truncate_hits(&mut hits, |size| *size),
+            Err(SearchError::ResponseTooLarge { size: 24, limit: 10 })
+        ));
+        assert_eq!(hits.len(), 3);
+    }
+
+    #[test]
+    fn test_huge_sizes_saturate() {
+        let mut hits = vec![usize::MAX, usize::MAX];
+        assert!(matches!(
+            limits(10, OversizePolicy::Error).truncate_hits(&mut hits, |size| *size),
+            Err(SearchError::ResponseTooLarge { size: usize::MAX, .. })
+        ));
+    }
+
+    #[test]
+    fn test_enforce_flags_truncated_results_and_keeps_the_total() {
+        let mut results = results(vec![hit("1", "0123456789"), hit("2", "0123456789"), hit("3", "0123456789")]);
+        limits(25, OversizePolicy::Truncate).enforce(&mut results).unwrap();
+        assert!(results.truncated);
+        assert_eq!(results.hits.len(), 2);
+        assert_eq!(results.total, Some(3));
+    }
+
+    #[test]
+    fn test_enforce_keeps_an_earlier_truncation_flag() {
+        let mut small = results(vec![hit("1", "{}")]);
+        small.truncated = true;
+        limits(25, OversizePolicy::Truncate).enforce(&mut small).unwrap();
+        assert!(small.truncated);
+        assert_eq!(small.hits.len(), 1);
+    }
+
+    #[test]
+    fn test_hit_size_counts_every_text_payload() {
+        let mut full = hit("42", "0123456789");
+        full.score = Some(1.0);
+        full.highlights = Some("abc".to_string());
+        full.explanation = Some("abcd".to_string());
+        assert_eq!(hit_size(&full), 2 + 10 + 3 + 4);
+        assert_eq!(hit_size(&SearchHit { content: None, ..hit("42", "") }), 2);
+    }
+
+    #[test]
+    fn test_results_size_includes_groups_and_facets() {
+        let mut page = results(vec![hit("1", "0123456789")]);
+        page.facets = Some("{\"a\":1}".to_string());
+        page.groups = Some(vec![SearchGroup { key: vec!["x".to_string()], total: None, hits: vec![hit("2", "01234")] }]);
+        assert_eq!(results_size(&page), 11 + 7 + 6);
+    }
+}
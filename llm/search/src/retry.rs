@@ -0,0 +1,178 @@
+//! Retrying failed requests
+//!
+//! A node that restarts or sheds load answers with a 5xx status, times out or
+//! refuses connections for a moment, and the same request usually succeeds
+//! when sent again shortly after. The HTTP client retries requests that are
+//! safe to repeat, up to `SEARCH_PROVIDER_MAX_RETRIES` times, waiting longer
+//! after each failure. Waits are jittered, so workers that failed together do
+//! not retry together.
+//!
+//! The retries of one request also share a time budget, [`RETRY_BUDGET_ENV`],
+//! so a slow node cannot stretch a call far beyond its timeout. When the
+//! retries or the budget run out, the error names the retries made and the
+//! budget they used.
+
+use std::time::{Duration, Instant};
+use crate::config::env_helpers::get_env_parsed;
+
+/// Environment variable holding the time the retries of one request may take, in milliseconds
+pub const RETRY_BUDGET_ENV: &str = "SEARCH_RETRY_BUDGET_MS";
+
+/// Default time the retries of one request may take, attempts and waits included
+pub const DEFAULT_RETRY_BUDGET: Duration = Duration::from_secs(10);
+
+/// Wait before the first retry, doubled for each retry after it
+const BASE_DELAY: Duration = Duration::from_millis(100);
+
+/// Longest wait between two attempts
+const MAX_DELAY: Duration = Duration::from_secs(2);
+
+/// How often and how long failed requests are retried
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+
+    /// Time the retries of one request may take, from its first attempt
+    pub budget: Duration,
+}
+
+impl RetryPolicy {
+    /// Policy that never retries
+    pub const NONE: Self = Self {
+        max_retries: 0,
+        base_delay: BASE_DELAY,
+        max_delay: MAX_DELAY,
+        budget: DEFAULT_RETRY_BUDGET,
+    };
+
+    /// Up to `max_retries` retries, with the budget of `SEARCH_RETRY_BUDGET_MS`
+    pub fn new(max_retries: u32) -> Self {
+        let budget = get_env_parsed::<u64>(RETRY_BUDGET_ENV)
+            .ok()
+            .flatten()
+            .map_or(DEFAULT_RETRY_BUDGET, Duration::from_millis);
+        Self { max_retries, budget, ..Self::NONE }
+    }
+
+    /// Longest wait before retry `retry`, counted from 0
+    pub fn backoff(&self, retry: u32) -> Duration {
+        self.base_delay.saturating_mul(2u32.saturating_pow(retry)).min(self.max_delay)
+    }
+
+    /// Wait before retry `retry`: between half the backoff and all of it, picked by `seed`
+    pub fn delay(&self, retry: u32, seed: u64) -> Duration {
+        let backoff = self.backoff(retry);
+        let half = backoff / 2;
+        let spread = (backoff - half).as_millis() as u64;
+        half + Duration::from_millis(if spread == 0 { 0 } else { seed % (spread + 1) })
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::NONE
+    }
+}
+
+/// Retries made for one request
+#[derive(Debug, Clone)]
+pub struct RetryBudget {
+    policy: RetryPolicy,
+    started: Instant,
+    retries: u32,
+}
+
+impl RetryBudget {
+    /// Budget of a request whose first attempt starts now
+    pub fn start(policy: RetryPolicy) -> Self {
+        Self { policy, started: Instant::now(), retries: 0 }
+    }
+
+    pub fn retries(&self) -> u32 {
+        self.retries
+    }
+
+    /// Wait before the next retry, or `None` when the retries or the time budget are spent
+    pub fn next_delay(&mut self) -> Option<Duration> {
+        if self.retries >= self.policy.max_retries {
+            return None;
+        }
+        let delay = self.policy.delay(self.retries, uuid::Uuid::new_v4().as_u64_pair().0);
+        if self.started.elapsed() + delay > self.policy.budget {
+            return None;
+        }
+        self.retries += 1;
+        Some(delay)
+    }
+
+    /// What was spent, for the error of a request that kept failing
+    pub fn summary(&self) -> String {
+        format!(
+            "gave up after {} {} in {} ms (budget: {} retries, {} ms)",
+            self.retries,
+            if self.retries == 1 { "retry" } else { "retries" },
+            self.started.elapsed().as_millis(),
+            self.policy.max_retries,
+            self.policy.budget.as_millis()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_doubles_up_to_the_cap() {
+        let policy = RetryPolicy::NONE;
+        let backoffs: Vec<u128> = (0..7).map(|retry| policy.backoff(retry).as_millis()).collect();
+        assert_eq!(backoffs, [100, 200, 400, 800, 1600, 2000, 2000]);
+        assert_eq!(policy.backoff(u32::MAX), MAX_DELAY);
+    }
+
+    #[test]
+    fn test_jittered_delay_stays_between_half_and_all_of_the_backoff() {
+        let policy = RetryPolicy::NONE;
+        assert_eq!(policy.delay(2, 0), Duration::from_millis(200));
+        assert_eq!(policy.delay(2, 200), Duration::from_millis(400));
+        assert!((0..50).all(|seed| (200..=400).contains(&policy.delay(2, seed * 7919).as_millis())));
+
+        let no_backoff = RetryPolicy { base_delay: Duration::ZERO, ..policy };
+        assert_eq!(no_backoff.delay(3, u64::MAX), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_retries_stop_at_the_retry_limit() {
+        let mut budget = RetryBudget::start(RetryPolicy { max_retries: 3, base_delay: Duration::ZERO, ..RetryPolicy::NONE });
+        assert!((0..3).all(|_| budget.next_delay().is_some()));
+        assert_eq!(budget.next_delay(), None);
+        assert_eq!(budget.retries(), 3);
+        assert!(budget.summary().starts_with("gave up after 3 retries in "), "{}", budget.summary());
+        assert!(budget.summary().ends_with("(budget: 3 retries, 10000 ms)"), "{}", budget.summary());
+
+        assert_eq!(RetryBudget::start(RetryPolicy::NONE).next_delay(), None);
+    }
+
+    #[test]
+    fn test_time_budget_stops_retries_before_the_retry_limit() {
+        // The second wait is at least 20 ms, which no longer fits once 15 ms have passed
+        let policy = RetryPolicy {
+            max_retries: 10,
+            base_delay: Duration::from_millis(20),
+            max_delay: Duration::from_secs(1),
+            budget: Duration::from_millis(30),
+        };
+        let mut budget = RetryBudget::start(policy);
+        assert!(budget.next_delay().is_some());
+        std::thread::sleep(Duration::from_millis(15));
+        assert_eq!(budget.next_delay(), None);
+        assert_eq!(budget.retries(), 1);
+        assert!(budget.summary().starts_with("gave up after 1 retry in "), "{}", budget.summary());
+
+        let mut spent = RetryBudget::start(RetryPolicy { budget: Duration::ZERO, ..policy });
+        assert_eq!(spent.next_delay(), None);
+        assert_eq!(spent.retries(), 0);
+    }
+}
@@ -0,0 +1,339 @@
+//! Rolling indexes behind a write alias
+//!
+//! Time-series and log data is written to an alias whose write index is
+//! replaced by a fresh one once it grows too large or too old, so each index
+//! stays small and old ones can be dropped whole. Elasticsearch and OpenSearch
+//! do the rollover themselves; this module holds the conditions, the names of
+//! the generated indexes and the reading of the rollover response.
+//!
+//! The first index of an alias is `<alias>-000001`. The providers create it,
+//! with the alias as its write index, on the first rollover of an alias that
+//! does not exist yet. Later indexes are named by the backend, which counts
+//! the trailing number up. Writes through the alias land in the write index
+//! and searches cover every index behind it.
+
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use crate::error::{SearchError, SearchResult};
+use crate::telemetry::SpanOutput;
+
+/// Name of the first index behind `alias`
+pub fn first_index(alias: &str) -> String {
+    format!("{}-000001", alias)
+}
+
+/// When the write index is rolled over; any met condition is enough, and none rolls over unconditionally
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RolloverConditions {
+    /// Documents in the write index
+    pub max_docs: Option<u64>,
+
+    /// Primary store size, such as `50gb`
+    pub max_size: Option<String>,
+
+    /// Time since the write index was created, such as `7d`
+    pub max_age: Option<String>,
+}
+
+const SIZE_UNITS: &[&str] = &["b", "kb", "mb", "gb", "tb", "pb"];
+const AGE_UNITS: &[&str] = &["nanos", "micros", "ms", "s", "m", "h", "d"];
+
+/// Whether `value` is a positive whole number followed by one of `units`
+fn has_unit(value: &str, units: &[&str]) -> bool {
+    let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    number.parse::<u64>().is_ok_and(|n| n > 0) && units.contains(&unit.to_ascii_lowercase().as_str())
+}
+
+impl RolloverConditions {
+    /// Parse a JSON object of conditions; missing or blank input rolls over unconditionally
+    pub fn from_json(json: Option<&str>) -> SearchResult<Self> {
+        let conditions: Self = match json.map(str::trim).filter(|j| !j.is_empty()) {
+            Some(json) => serde_json::from_str(json)
+                .map_err(|e| SearchError::invalid_query(format!("Invalid rollover conditions: {}", e)))?,
+            None => Self::default(),
+        };
+        conditions.validate()?;
+        Ok(conditions)
+    }
+
+    pub fn validate(&self) -> SearchResult<()> {
+        if self.max_docs == Some(0) {
+            return Err(SearchError::invalid_query("max_docs must be positive"));
+        }
+        if let Some(size) = self.max_size.as_deref().filter(|size| !has_unit(size, SIZE_UNITS)) {
+            return Err(SearchError::invalid_query(format!("max_size {} is not a size such as 50gb", size)));
+        }
+        if let Some(age) = self.max_age.as_deref().filter(|age| !has_unit(age, AGE_UNITS)) {
+            return Err(SearchError::invalid_query(format!("max_age {} is not a duration such as 7d", age)));
+        }
+        Ok(())
+    }
+
+    /// Body of a `_rollover` request creating the new index with `mappings` and `settings`
+    pub fn to_request(&self, mappings: Option<Value>, settings: Option<Value>) -> Value {
+        let mut conditions = serde_json::Map::new();
+        if let Some(max_docs) = self.max_docs {
+            conditions.insert("max_docs".to_string(), json!(max_docs));
+        }
+        if let Some(max_size) = &self.max_size {
+            conditions.insert("max_size".to_string(), json!(max_size));
+        }
+        if let Some(max_age) = &self.max_age {
+            conditions.insert("max_age".to_string(), json!(max_age));
+        }
+        let mut body = json!({ "conditions": conditions });
+        if let Some(mappings) = mappings {
+            body["mappings"] = mappings;
+        }
+        if let Some(settings) = settings {
+            body["settings"] = settings;
+        }
+        body
+    }
+}
+
+/// `aliases` section of a create index request making the index the write index of `alias`
+pub fn write_alias(alias: &str) -> Value {
+    json!({ alias: { "is_write_index": true } })
+}
+
+/// Write index of `alias` in a `GET _alias/<alias>` response
+///
+/// An alias over a single index writes to it even without `is_write_index`.
+pub fn write_index(aliases: &Value, alias: &str) -> Option<String> {
+    let indexes = aliases.as_object()?;
+    let flagged = indexes.iter().find(|(_, entry)| {
+        entry.pointer(&format!("/aliases/{}/is_write_index", alias)).and_then(Value::as_bool) == Some(true)
+    });
+    match flagged {
+        Some((index, _)) => Some(index.clone()),
+        None if indexes.len() == 1 => indexes.keys().next().cloned(),
+        None => None,
+    }
+}
+
+/// Outcome of a rollover
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RolloverResult {
+    pub alias: String,
+
+    /// Write index before the rollover, `None` when the alias was just created
+    pub old_index: Option<String>,
+
+    /// Write index after the rollover, or the one it would have been in a dry run
+    pub new_index: String,
+
+    pub rolled_over: bool,
+
+    pub dry_run: bool,
+
+    /// Each condition as the backend describes it, such as `[max_docs: 1000]`, and whether it was met
+    pub conditions: BTreeMap<String, bool>,
+}
+
+impl RolloverResult {
+    /// Result of creating the first index of `alias` instead of rolling over
+    pub fn created(alias: &str, dry_run: bool) -> Self {
+        Self {
+            alias: alias.to_string(),
+            old_index: None,
+            new_index: first_index(alias),
+            rolled_over: false,
+            dry_run,
+            conditions: BTreeMap::new(),
+        }
+    }
+
+    /// Read a `_rollover` response
+    pub fn from_response(alias: &str, response: &Value) -> SearchResult<Self> {
+        let text = |key: &str| response.get(key).and_then(Value::as_str).map(str::to_string);
+        let new_index = text("new_index")
+            .ok_or_else(|| SearchError::Internal(format!("Rollover response for {} has no new index", alias)))?;
+        let conditions = response
+            .get("conditions")
+            .and_then(Value::as_object)
+            .map(|conditions| conditions.iter().map(|(name, met)| (name.clone(), met.as_bool() == Some(true))).collect())
+            .unwrap_or_default();
+        Ok(Self {
+            alias: alias.to_string(),
+            old_index: text("old_index"),
+            new_index,
+            rolled_over: response.get("rolled_over").and_then(Value::as_bool).unwrap_or(false),
+            dry_run: response.get("dry_run").and_then(Value::as_bool).unwrap_or(false),
+            conditions,
+        })
+    }
+}
+
+impl SpanOutput for RolloverResult {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(json: &str) -> SearchResult<RolloverConditions> {
+        RolloverConditions::from_json(Some(json))
+    }
+
+    fn invalid(result: SearchResult<RolloverConditions>) -> String {
+        match result {
+            Err(SearchError::InvalidQuery(message)) => message,
+            other => panic!("expected InvalidQuery, got {:?}", other),
+        }
+    }
+
+    fn size(size: &str) -> RolloverConditions {
+        RolloverConditions { max_size: Some(size.to_string()), ..RolloverConditions::default() }
+    }
+
+    fn age(age: &str) -> RolloverConditions {
+        RolloverConditions { max_age: Some(age.to_string()), ..RolloverConditions::default() }
+    }
+
+    #[test]
+    fn test_first_indexes_are_numbered_with_six_digits() {
+        assert_eq!(first_index("logs"), "logs-000001");
+    }
+
+    #[test]
+    fn test_missing_or_blank_conditions_roll_over_unconditionally() {
+        assert_eq!(RolloverConditions::from_json(None).unwrap(), RolloverConditions::default());
+        assert_eq!(RolloverConditions::from_json(Some(" \n")).unwrap(), RolloverConditions::default());
+        assert_eq!(parse("{}").unwrap().to_request(None, None), json!({ "conditions": {} }));
+    }
+
+    #[test]
+    fn test_every_size_unit_is_accepted_in_any_case() {
+        for unit in SIZE_UNITS {
+            assert!(size(&format!("50{}", unit)).validate().is_ok(), "{}", unit);
+            assert!(size(&format!("50{}", unit.to_ascii_uppercase())).validate().is_ok(), "{}", unit);
+        }
+    }
+
+    #[test]
+    fn test_sizes_need_a_positive_whole_number_and_a_unit() {
+        for bad in ["50", "gb", "0gb", "1.5gb", "-1gb", "50 gb", "50gib", ""] {
+            assert!(size(bad).validate().is_err(), "{}", bad);
+        }
+        assert_eq!(invalid(parse(r#"{"max_size": "50 gigs"}"#)), "max_size 50 gigs is not a size such as 50gb");
+    }
+
+    #[test]
+    fn test_every_age_unit_is_accepted() {
+        for unit in AGE_UNITS {
+            assert!(age(&format!("7{}", unit)).validate().is_ok(), "{}", unit);
+        }
+    }
+
+    #[test]
+    fn test_ages_need_a_positive_whole_number_and_a_unit() {
+        for bad in ["7", "0d", "7w", "7days", "1.5h", "d"] {
+            assert!(age(bad).validate().is_err(), "{}", bad);
+        }
+        assert_eq!(invalid(parse(r#"{"max_age": "7w"}"#)), "max_age 7w is not a duration such as 7d");
+    }
+
+    #[test]
+    fn test_max_docs_must_be_positive() {
+        assert_eq!(invalid(parse(r#"{"max_docs": 0}"#)), "max_docs must be positive");
+        assert!(parse(r#"{"max_docs": -1}"#).is_err());
+        assert!(parse(r#"{"max_docs": 1}"#).is_ok());
+    }
+
+    #[test]
+    fn test_unknown_or_malformed_conditions_are_refused() {
+        assert!(invalid(parse(r#"{"max_shards": 3}"#)).starts_with("Invalid rollover conditions: "));
+        assert!(invalid(parse(r#"{"max_docs": "many"}"#)).starts_with("Invalid rollover conditions: "));
+        assert!(parse("3").is_err());
+    }
+
+    #[test]
+    fn test_requests_carry_every_condition_mappings_and_settings() {
+        let conditions = parse(r#"{"max_docs": 1000, "max_size": "50gb", "max_age": "7d"}"#).unwrap();
+        let request = conditions.to_request(Some(json!({ "properties": {} })), Some(json!({ "number_of_shards": 1 })));
+        assert_eq!(
+            request,
+            json!({
+                "conditions": { "max_docs": 1000, "max_size": "50gb", "max_age": "7d" },
+                "mappings": { "properties": {} },
+                "settings": { "number_of_shards": 1 }
+            })
+        );
+    }
+
+    #[test]
+    fn test_write_aliases_are_flagged() {
+        assert_eq!(write_alias("logs"), json!({ "logs": { "is_write_index": true } }));
+    }
+
+    #[test]
+    fn test_the_flagged_index_is_the_write_index() {
+        let aliases = json!({
+            "logs-000001": { "aliases": { "logs": { "is_write_index": false } } },
+            "logs-000002": { "aliases": { "logs": { "is_write_index": true } } }
+        });
+        assert_eq!(write_index(&aliases, "logs").as_deref(), Some("logs-000002"));
+    }
+
+    #[test]
+    fn test_a_single_index_is_the_write_index_unless_another_is_flagged() {
+        assert_eq!(write_index(&json!({ "logs-000001": { "aliases": { "logs": {} } } }), "logs").as_deref(), Some("logs-000001"));
+        let unflagged = json!({ "logs-000001": { "aliases": { "logs": { "is_write_index": false } } } });
+        assert_eq!(write_index(&unflagged, "logs").as_deref(), Some("logs-000001"));
+    }
+
+    #[test]
+    fn test_several_unflagged_indexes_have_no_write_index() {
+        let aliases = json!({
+            "logs-000001": { "aliases": { "logs": {} } },
+            // Flagged for another alias only
+            "logs-000002": { "aliases": { "logs": {}, "metrics": { "is_write_index": true } } }
+        });
+        assert_eq!(write_index(&aliases, "logs"), None);
+        assert_eq!(write_index(&json!({}), "logs"), None);
+        assert_eq!(write_index(&json!([]), "logs"), None);
+    }
+
+    #[test]
+    fn test_responses_are_read_with_their_conditions() {
+        let response = json!({
+            "acknowledged": true,
+            "old_index": "logs-000002",
+            "new_index": "logs-000003",
+            "rolled_over": true,
+            "dry_run": false,
+            "conditions": { "[max_docs: 1000]": true, "[max_age: 7d]": false, "[max_size: 5gb]": "true" }
+        });
+        let result = RolloverResult::from_response("logs", &response).unwrap();
+        assert_eq!(result.old_index.as_deref(), Some("logs-000002"));
+        assert_eq!((result.new_index.as_str(), result.rolled_over, result.dry_run), ("logs-000003", true, false));
+        // Only a JSON true counts as met
+        let met: Vec<(&str, bool)> = result.conditions.iter().map(|(name, met)| (name.as_str(), *met)).collect();
+        assert_eq!(met, [("[max_age: 7d]", false), ("[max_docs: 1000]", true), ("[max_size: 5gb]", false)]);
+    }
+
+    #[test]
+    fn test_responses_without_flags_did_not_roll_over() {
+        let result = RolloverResult::from_response("logs", &json!({ "new_index": "logs-000002" })).unwrap();
+        assert_eq!((result.old_index, result.rolled_over, result.dry_run), (None, false, false));
+        assert!(result.conditions.is_empty());
+    }
+
+    #[test]
+    fn test_responses_need_a_new_index() {
+        match RolloverResult::from_response("logs", &json!({ "rolled_over": true, "new_index": 3 })) {
+            Err(SearchError::Internal(message)) => assert_eq!(message, "Rollover response for logs has no new index"),
+            other => panic!("expected an internal error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_creating_an_alias_reports_its_first_index() {
+        let created = RolloverResult::created("logs", true);
+        assert_eq!((created.old_index, created.new_index.as_str()), (None, "logs-000001"));
+        assert!(created.dry_run && !created.rolled_over);
+    }
+}
@@ -0,0 +1,636 @@
+//! Routing indexes across providers
+//!
+//! One deployment can keep its logs on OpenSearch and its product catalog on
+//! Algolia while callers see a single search interface. [`IndexRoutes`] maps
+//! index name patterns to provider names, read from [`ROUTES_ENV`] as
+//! `logs-*=opensearch,products=algolia,*=elasticsearch`; the first pattern
+//! that matches wins, and `*` matches any run of characters.
+//!
+//! Providers take part by registering a [`RoutedBackend`] under their name
+//! with [`register`]. A [`Router`] sends each operation on an index to the
+//! backend its route names, and fans the operations that concern no single
+//! index, such as listing indexes, out to every routed backend.
+//! An index lives only on the backend its route names, so that backend's
+//! errors are returned as they are rather than retried on another route, and
+//! an operation fanned out to several backends fails with the first error.
+//!
+//! An index with a [`LanguageRoute`] is a logical index over one real index
+//! per language: the router writes each document to the index of its
+//! language and runs searches on all of them, merging the results. The
+//! language indexes are routed to providers like any other index, so
+//! `products_*=algolia` sends all of them to Algolia.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use futures::future::try_join_all;
+pub use futures::future::LocalBoxFuture;
+use log::warn;
+use crate::describe::ProviderDescription;
+use crate::error::{SearchError, SearchResult};
+use crate::language::{self, LanguageRoute, LanguageRoutes};
+use crate::types::{Doc, Schema, SearchQuery, SearchResults};
+
+/// Index routes, as `pattern=provider` entries separated by commas
+pub const ROUTES_ENV: &str = "SEARCH_ROUTES";
+
+/// Operations a provider serves for the indexes routed to it
+pub trait RoutedBackend {
+    fn search<'a>(&'a self, index: &'a str, query: &'a SearchQuery) -> LocalBoxFuture<'a, SearchResult<SearchResults>>;
+
+    fn get<'a>(&'a self, index: &'a str, id: &'a str) -> LocalBoxFuture<'a, SearchResult<Option<Doc>>>;
+
+    /// Write `docs` through the provider-side ingest `pipeline`, or the index's when `None`
+    fn upsert_many<'a>(&'a self, index: &'a str, docs: &'a [Doc], pipeline: Option<&'a str>) -> LocalBoxFuture<'a, SearchResult<()>>;
+
+    fn delete<'a>(&'a self, index: &'a str, id: &'a str) -> LocalBoxFuture<'a, SearchResult<()>>;
+
+    fn create_index<'a>(&'a self, name: &'a str, schema: Option<&'a Schema>) -> LocalBoxFuture<'a, SearchResult<()>>;
+
+    fn delete_index<'a>(&'a self, name: &'a str) -> LocalBoxFuture<'a, SearchResult<()>>;
+
+    fn list_indexes(&self) -> LocalBoxFuture<'_, SearchResult<Vec<String>>>;
+
+    fn get_schema<'a>(&'a self, index: &'a str) -> LocalBoxFuture<'a, SearchResult<Schema>>;
+
+    fn describe(&self) -> LocalBoxFuture<'_, ProviderDescription>;
+}
+
+/// A registered backend, shared by every router
+pub type Backend = Arc<dyn RoutedBackend + Send + Sync>;
+
+fn backends() -> MutexGuard<'static, HashMap<String, Backend>> {
+    static BACKENDS: OnceLock<Mutex<HashMap<String, Backend>>> = OnceLock::new();
+    BACKENDS
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Register the backend of `provider`, replacing an earlier one
+pub fn register(provider: &str, backend: Backend) {
+    backends().insert(provider.to_lowercase(), backend);
+}
+
+/// Backend registered for `provider`
+pub fn backend(provider: &str) -> Option<Backend> {
+    backends().get(&provider.to_lowercase()).cloned()
+}
+
+/// Names of the registered providers, sorted
+pub fn registered() -> Vec<String> {
+    let mut providers: Vec<String> = backends().keys().cloned().collect();
+    providers.sort();
+    providers
+}
+
+/// Index name patterns and the providers they route to, in order
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IndexRoutes {
+    routes: Vec<(String, String)>,
+}
+
+impl IndexRoutes {
+    pub fn from_env() -> Self {
+        std::env::var(ROUTES_ENV).map(|spec| Self::parse(&spec)).unwrap_or_default()
+    }
+
+    /// Parse `pattern=provider` entries separated by commas, skipping malformed ones
+    pub fn parse(spec: &str) -> Self {
+        let mut routes = Vec::new();
+        for entry in spec.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+            match entry.split_once('=') {
+                Some((pattern, provider)) if !pattern.trim().is_empty() && !provider.trim().is_empty() => {
+                    routes.push((pattern.trim().to_string(), provider.trim().to_lowercase()));
+                }
+                _ => warn!("Ignoring malformed index route: {}", entry),
+            }
+        }
+        Self { routes }
+    }
+
+    /// Provider of the first route whose pattern matches `index`
+    pub fn provider_for(&self, index: &str) -> Option<&str> {
+        self.routes
+            .iter()
+            .find(|(pattern, _)| pattern_matches(pattern, index))
+            .map(|(_, provider)| provider.as_str())
+    }
+
+    /// Every provider named by a route, in the order of their first route
+    pub fn providers(&self) -> Vec<&str> {
+        let mut providers: Vec<&str> = Vec::new();
+        for (_, provider) in &self.routes {
+            if !providers.contains(&provider.as_str()) {
+                providers.push(provider);
+            }
+        }
+        providers
+    }
+}
+
+/// Whether `name` matches `pattern`, where `*` stands for any run of characters
+fn pattern_matches(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+/// Sends operations to the backends of the indexes they concern
+#[derive(Debug, Clone, Default)]
+pub struct Router {
+    routes: IndexRoutes,
+    languages: LanguageRoutes,
+}
+
+impl Router {
+    pub fn new(routes: IndexRoutes) -> Self {
+        Self { routes, languages: LanguageRoutes::default() }
+    }
+
+    /// Split the indexes of `languages` into one index per language
+    pub fn with_languages(mut self, languages: LanguageRoutes) -> Self {
+        self.languages = languages;
+        self
+    }
+
+    /// Router over the routes of `SEARCH_ROUTES` and `SEARCH_LANGUAGE_ROUTES`
+    pub fn from_env() -> Self {
+        Self::new(IndexRoutes::from_env()).with_languages(LanguageRoutes::from_env())
+    }
+
+    pub fn routes(&self) -> &IndexRoutes {
+        &self.routes
+    }
+
+    fn language_route(&self, index: &str) -> Option<&LanguageRoute> {
+        self.languages.for_index(index)
+    }
+
+    /// Provider and backend serving `index`
+    pub fn backend_for(&self, index: &str) -> SearchResult<(&str, Backend)> {
+        let provider = self
+            .routes
+            .provider_for(index)
+            .ok_or_else(|| SearchError::invalid_query(format!("No route in {} matches index {}", ROUTES_ENV, index)))?;
+        let backend = backend(provider).ok_or_else(|| {
+            SearchError::Internal(format!("Index {} routes to {}, which has no registered backend", index, provider))
+        })?;
+        Ok((provider, backend))
+    }
+
+    /// Routed providers with a registered backend; the others are logged and skipped
+    fn routed_backends(&self) -> Vec<(&str, Backend)> {
+        self.routes
+            .providers()
+            .into_iter()
+            .filter_map(|provider| match backend(provider) {
+                Some(backend) => Some((provider, backend)),
+                None => {
+                    warn!("Skipping {}, which has no registered backend", provider);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Search `index`, or the language indexes behind it with their hits merged
+    pub async fn search(&self, index: &str, query: &SearchQuery) -> SearchResult<SearchResults> {
+        let Some(route) = self.language_route(index) else {
+            return self.backend_for(index)?.1.search(index, query).await;
+        };
+        let indexes = route.search_indexes(index, query);
+        if let [only] = indexes.as_slice() {
+            return self.backend_for(only)?.1.search(only, query).await;
+        }
+        let fan_out = language::fan_out_query(query)?;
+        let searches = indexes.iter().map(|index| {
+            let fan_out = &fan_out;
+            async move { self.backend_for(index)?.1.search(index, fan_out).await }
+        });
+        Ok(language::merge_results(query, try_join_all(searches).await?))
+    }
+
+    /// Get a document, looking through the language indexes in order
+    pub async fn get(&self, index: &str, id: &str) -> SearchResult<Option<Doc>> {
+        let Some(route) = self.language_route(index) else {
+            return self.backend_for(index)?.1.get(index, id).await;
+        };
+        for index in route.indexes(index) {
+            if let Some(doc) = self.backend_for(&index)?.1.get(&index, id).await? {
+                return Ok(Some(doc));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Write documents to `index`, or each to the index of its language
+    pub async fn upsert_many(&self, index: &str, docs: &[Doc]) -> SearchResult<()> {
+        self.upsert_many_with_pipeline(index, docs, None).await
+    }
+
+    /// Write documents as [`Router::upsert_many`] does, through the provider-side ingest `pipeline`
+    pub async fn upsert_many_with_pipeline(&self, index: &str, docs: &[Doc], pipeline: Option<&str>) -> SearchResult<()> {
+        let Some(route) = self.language_route(index) else {
+            return self.backend_for(index)?.1.upsert_many(index, docs, pipeline).await;
+        };
+        for (index, docs) in route.route(index, docs) {
+            self.backend_for(&index)?.1.upsert_many(&index, &docs, pipeline).await?;
+        }
+        Ok(())
+    }
+
+    /// Delete a document; behind a language route, from every language index
+    pub async fn delete(&self, index: &str, id: &str) -> SearchResult<()> {
+        for index in self.physical_indexes(index) {
+            self.backend_for(&index)?.1.delete(&index, id).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn create_index(&self, name: &str, schema: Option<&Schema>) -> SearchResult<()> {
+        for name in self.physical_indexes(name) {
+            self.backend_for(&name)?.1.create_index(&name, schema).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn delete_index(&self, name: &str) -> SearchResult<()> {
+        for name in self.physical_indexes(name) {
+            self.backend_for(&name)?.1.delete_index(&name).await?;
+        }
+        Ok(())
+    }
+
+    /// Schema of `index`, or of the default language index behind it
+    pub async fn get_schema(&self, index: &str) -> SearchResult<Schema> {
+        let index = match self.language_route(index) {
+            Some(route) => route.index_for(index, route.default_language()),
+            None => index.to_string(),
+        };
+        self.backend_for(&index)?.1.get_schema(&index).await
+    }
+
+    /// The language indexes behind `index`, or `index` itself
+    fn physical_indexes(&self, index: &str) -> Vec<String> {
+        match self.language_route(index) {
+            Some(route) => route.indexes(index),
+            None => vec![index.to_string()],
+        }
+    }
+
+    /// Indexes of every routed backend that route back to it, sorted
+    ///
+    /// An index a backend holds but the routes send elsewhere cannot be
+    /// reached through the router, so it is left out.
+    pub async fn list_indexes(&self) -> SearchResult<Vec<String>> {
+        let mut indexes = Vec::new();
+        for (provider, backend) in self.routed_backends() {
+            for index in backend.list_indexes().await? {
+                if self.routes.provider_for(&index) == Some(provider) {
+                    indexes.push(index);
+                }
+            }
+        }
+        indexes.sort();
+        indexes.dedup();
+        Ok(indexes)
+    }
+
+    /// Description of every routed backend, by provider
+    pub async fn describe(&self) -> BTreeMap<String, ProviderDescription> {
+        let mut descriptions = BTreeMap::new();
+        for (provider, backend) in self.routed_backends() {
+            descriptions.insert(provider.to_string(), backend.describe().await);
+        }
+        descriptions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use futures::FutureExt;
+    use serde_json::json;
+    use crate::capabilities::typesense_capability_matrix;
+    use crate::describe;
+    use crate::types::SearchConfig;
+
+    /// Backend recording the calls it gets, with fixed indexes, hits and stored documents
+    #[derive(Default)]
+    struct Recording {
+        indexes: Vec<&'static str>,
+        hits: HashMap<&'static str, Vec<(&'static str, f64)>>,
+        stored: Vec<(&'static str, &'static str)>,
+        down: bool,
+        calls: Mutex<Vec<String>>,
+    }
+
+    impl Recording {
+        fn listing(indexes: &[&'static str]) -> Self {
+            Self { indexes: indexes.to_vec(), ..Self::default() }
+        }
+
+        fn record(&self, call: String) -> SearchResult<()> {
+            self.calls.lock().unwrap().push(call);
+            if self.down {
+                return Err(SearchError::Unavailable { retry_after_ms: None });
+            }
+            Ok(())
+        }
+
+        fn calls(&self) -> Vec<String> {
+            self.calls.lock().unwrap().clone()
+        }
+    }
+
+    impl RoutedBackend for Recording {
+        fn search<'a>(&'a self, index: &'a str, query: &'a SearchQuery) -> LocalBoxFuture<'a, SearchResult<SearchResults>> {
+            let recorded = self.record(format!("search {} {:?}", index, query.per_page));
+            let hits: Vec<_> = self.hits.get(index).into_iter().flatten().map(|(id, score)| json!({ "id": id, "score": score })).collect();
+            async move {
+                recorded?;
+                Ok(serde_json::from_value(json!({ "total": hits.len(), "hits": hits })).unwrap())
+            }
+            .boxed_local()
+        }
+
+        fn get<'a>(&'a self, index: &'a str, id: &'a str) -> LocalBoxFuture<'a, SearchResult<Option<Doc>>> {
+            let recorded = self.record(format!("get {} {}", index, id));
+            let found = self.stored.contains(&(index, id)).then(|| Doc { id: id.to_string(), content: index.to_string() });
+            async move { recorded.map(|_| found) }.boxed_local()
+        }
+
+        fn upsert_many<'a>(&'a self, index: &'a str, docs: &'a [Doc], _: Option<&'a str>) -> LocalBoxFuture<'a, SearchResult<()>> {
+            let ids: Vec<&str> = docs.iter().map(|doc| doc.id.as_str()).collect();
+            let recorded = self.record(format!("upsert {} {}", index, ids.join(",")));
+            async move { recorded }.boxed_local()
+        }
+
+        fn delete<'a>(&'a self, index: &'a str, id: &'a str) -> LocalBoxFuture<'a, SearchResult<()>> {
+            let recorded = self.record(format!("delete {} {}", index, id));
+            async move { recorded }.boxed_local()
+        }
+
+        fn create_index<'a>(&'a self, name: &'a str, _: Option<&'a Schema>) -> LocalBoxFuture<'a, SearchResult<()>> {
+            let recorded = self.record(format!("create {}", name));
+            async move { recorded }.boxed_local()
+        }
+
+        fn delete_index<'a>(&'a self, name: &'a str) -> LocalBoxFuture<'a, SearchResult<()>> {
+            let recorded = self.record(format!("drop {}", name));
+            async move { recorded }.boxed_local()
+        }
+
+        fn list_indexes(&self) -> LocalBoxFuture<'_, SearchResult<Vec<String>>> {
+            let recorded = self.record("list".to_string());
+            let indexes = self.indexes.iter().map(|index| index.to_string()).collect();
+            async move { recorded.map(|_| indexes) }.boxed_local()
+        }
+
+        fn get_schema<'a>(&'a self, index: &'a str) -> LocalBoxFuture<'a, SearchResult<Schema>> {
+            let recorded = self.record(format!("schema {}", index));
+            async move { recorded.map(|_| Schema { fields: Vec::new(), primary_key: None }) }.boxed_local()
+        }
+
+        fn describe(&self) -> LocalBoxFuture<'_, ProviderDescription> {
+            async { describe::describe(typesense_capability_matrix(), None) }.boxed_local()
+        }
+    }
+
+    fn registered_as(provider: &str, backend: Recording) -> Arc<Recording> {
+        let backend = Arc::new(backend);
+        register(provider, backend.clone());
+        backend
+    }
+
+    fn doc(id: &str, lang: &str) -> Doc {
+        Doc { id: id.to_string(), content: json!({ "lang": lang }).to_string() }
+    }
+
+    fn in_language(language: &str) -> SearchQuery {
+        let config = SearchConfig { language: Some(language.to_string()), ..SearchConfig::default() };
+        SearchQuery { config: Some(config), ..crate::types::QueryBuilder::new().query("dune").build() }
+    }
+
+    /// `products` split into `products_en` and `products_de`, with German routed to `de_provider`
+    fn language_router(en_provider: &str, de_provider: &str) -> Router {
+        let routes = IndexRoutes::parse(&format!("products_de={},products_*={}", de_provider, en_provider));
+        let languages = LanguageRoutes::parse(r#"{"products": {"languages": ["en", "de"], "field": "lang"}}"#);
+        Router::new(routes).with_languages(languages)
+    }
+
+    #[test]
+    fn test_routes_are_trimmed_lowercased_and_kept_in_order() {
+        let routes = IndexRoutes::parse(" logs-* = OpenSearch ,, products=algolia, *=elasticsearch ");
+        assert_eq!(routes.provider_for("logs-2024.06"), Some("opensearch"));
+        assert_eq!(routes.provider_for("products"), Some("algolia"));
+        assert_eq!(routes.providers(), ["opensearch", "algolia", "elasticsearch"]);
+    }
+
+    #[test]
+    fn test_malformed_routes_are_skipped() {
+        let routes = IndexRoutes::parse("logs, =algolia, products=, a=b=c, orders=typesense");
+        assert_eq!(routes.providers(), ["b=c", "typesense"]);
+        assert_eq!(routes.provider_for("logs"), None);
+        assert_eq!(IndexRoutes::parse(""), IndexRoutes::default());
+    }
+
+    #[test]
+    fn test_the_first_matching_route_wins() {
+        let routes = IndexRoutes::parse("*=elasticsearch,logs-*=opensearch");
+        assert_eq!(routes.provider_for("logs-1"), Some("elasticsearch"));
+    }
+
+    #[test]
+    fn test_providers_are_listed_once_in_order_of_their_first_route() {
+        let routes = IndexRoutes::parse("a=typesense,b=algolia,c=TYPESENSE");
+        assert_eq!(routes.providers(), ["typesense", "algolia"]);
+    }
+
+    #[test]
+    fn test_patterns_without_a_star_match_exactly() {
+        assert!(pattern_matches("products", "products"));
+        assert!(!pattern_matches("products", "products-v2"));
+        assert!(!pattern_matches("products", "Products"));
+    }
+
+    #[test]
+    fn test_stars_match_any_run_including_an_empty_one() {
+        assert!(pattern_matches("*", ""));
+        assert!(pattern_matches("logs-*", "logs-"));
+        assert!(pattern_matches("*-archive", "-archive"));
+        assert!(pattern_matches("*-archive-*", "orders-archive-2023"));
+        assert!(pattern_matches("a**b", "ab"));
+    }
+
+    #[test]
+    fn test_literal_parts_of_a_pattern_cannot_overlap() {
+        assert!(pattern_matches("a*b*a", "aba"));
+        assert!(!pattern_matches("a*a", "a"));
+        assert!(!pattern_matches("ab*ba", "aba"));
+        assert!(!pattern_matches("*-archive-*", "orders-archive"));
+    }
+
+    #[test]
+    fn test_backends_are_registered_case_insensitively() {
+        registered_as("Router-Test-Case", Recording::default());
+        assert!(backend("router-test-case").is_some());
+        assert!(registered().contains(&"router-test-case".to_string()));
+    }
+
+    #[test]
+    fn test_indexes_without_a_route_or_backend_are_errors() {
+        let router = Router::new(IndexRoutes::parse("orders=router-test-missing"));
+        match router.backend_for("users") {
+            Err(SearchError::InvalidQuery(message)) => assert_eq!(message, "No route in SEARCH_ROUTES matches index users"),
+            other => panic!("expected InvalidQuery, got {:?}", other.map(|(provider, _)| provider)),
+        }
+        match router.backend_for("orders") {
+            Err(SearchError::Internal(message)) => {
+                assert_eq!(message, "Index orders routes to router-test-missing, which has no registered backend")
+            }
+            other => panic!("expected Internal, got {:?}", other.map(|(provider, _)| provider)),
+        }
+    }
+
+    #[test]
+    fn test_operations_go_to_the_routed_backend_only() {
+        let logs = registered_as("router-test-only-logs", Recording::default());
+        let rest = registered_as("router-test-only-rest", Recording::default());
+        let router = Router::new(IndexRoutes::parse("logs-*=router-test-only-logs,*=router-test-only-rest"));
+
+        block_on(router.upsert_many("logs-1", &[doc("1", "en"), doc("2", "en")])).unwrap();
+        block_on(router.delete("logs-1", "2")).unwrap();
+        block_on(router.create_index("products", None)).unwrap();
+        assert_eq!(logs.calls(), ["upsert logs-1 1,2", "delete logs-1 2"]);
+        assert_eq!(rest.calls(), ["create products"]);
+    }
+
+    #[test]
+    fn test_backend_errors_are_returned_without_trying_another_route() {
+        let down = registered_as("router-test-down", Recording { down: true, ..Recording::default() });
+        let fallback = registered_as("router-test-fallback", Recording::default());
+        let router = Router::new(IndexRoutes::parse("logs-*=router-test-down,*=router-test-fallback"));
+
+        let query = crate::types::QueryBuilder::new().query("error").build();
+        assert!(matches!(block_on(router.search("logs-1", &query)), Err(SearchError::Unavailable { .. })));
+        assert_eq!(down.calls().len(), 1);
+        assert!(fallback.calls().is_empty());
+    }
+
+    #[test]
+    fn test_listing_keeps_only_indexes_that_route_back() {
+        registered_as("router-test-list-logs", Recording::listing(&["logs-2", "logs-1", "products"]));
+        registered_as("router-test-list-catalog", Recording::listing(&["products", "logs-stray", "products"]));
+        let router = Router::new(IndexRoutes::parse(
+            "logs-*=router-test-list-logs,products=router-test-list-catalog,orders=router-test-list-missing",
+        ));
+        assert_eq!(block_on(router.list_indexes()).unwrap(), ["logs-1", "logs-2", "products"]);
+    }
+
+    #[test]
+    fn test_listing_fails_with_the_first_backend_error() {
+        registered_as("router-test-list-down", Recording { down: true, ..Recording::listing(&["a"]) });
+        let router = Router::new(IndexRoutes::parse("*=router-test-list-down"));
+        assert!(matches!(block_on(router.list_indexes()), Err(SearchError::Unavailable { .. })));
+    }
+
+    #[test]
+    fn test_describe_covers_registered_routed_backends() {
+        registered_as("router-test-describe", Recording::default());
+        let router = Router::new(IndexRoutes::parse("a=router-test-describe,b=router-test-describe-missing"));
+        assert_eq!(block_on(router.describe()).keys().collect::<Vec<_>>(), ["router-test-describe"]);
+    }
+
+    #[test]
+    fn test_documents_are_written_to_the_index_of_their_language() {
+        let en = registered_as("router-test-lang-write-en", Recording::default());
+        let de = registered_as("router-test-lang-write-de", Recording::default());
+        let router = language_router("router-test-lang-write-en", "router-test-lang-write-de");
+
+        block_on(router.upsert_many("products", &[doc("1", "de"), doc("2", "en"), doc("3", "DE"), doc("4", "fr")])).unwrap();
+        // Unknown languages go to the default, the first one
+        assert_eq!(en.calls(), ["upsert products_en 2,4"]);
+        assert_eq!(de.calls(), ["upsert products_de 1,3"]);
+    }
+
+    #[test]
+    fn test_a_search_in_one_language_runs_on_its_index_alone() {
+        let en = registered_as("router-test-lang-one-en", Recording::default());
+        let de = registered_as("router-test-lang-one-de", Recording::default());
+        let router = language_router("router-test-lang-one-en", "router-test-lang-one-de");
+
+        block_on(router.search("products", &in_language("de"))).unwrap();
+        assert!(en.calls().is_empty());
+        assert_eq!(de.calls(), [format!("search products_de {:?}", in_language("de").per_page)]);
+    }
+
+    #[test]
+    fn test_other_searches_fan_out_and_merge_by_score() {
+        let mut en = Recording::default();
+        en.hits.insert("products_en", vec![("en-1", 0.9), ("en-2", 0.2)]);
+        let mut de = Recording::default();
+        de.hits.insert("products_de", vec![("de-1", 0.5)]);
+        let en = registered_as("router-test-lang-all-en", en);
+        let de = registered_as("router-test-lang-all-de", de);
+        let router = language_router("router-test-lang-all-en", "router-test-lang-all-de");
+
+        let query = SearchQuery { page: Some(2), per_page: Some(1), ..in_language("fr") };
+        let results = block_on(router.search("products", &query)).unwrap();
+        // Each index is asked for every hit up to the end of the page
+        assert_eq!(en.calls(), ["search products_en Some(2)"]);
+        assert_eq!(de.calls(), ["search products_de Some(2)"]);
+        assert_eq!(results.hits.iter().map(|hit| hit.id.as_str()).collect::<Vec<_>>(), ["de-1"]);
+        assert_eq!(results.total, Some(3));
+    }
+
+    #[test]
+    fn test_cursors_are_refused_across_language_indexes() {
+        registered_as("router-test-lang-cursor", Recording::default());
+        let router = language_router("router-test-lang-cursor", "router-test-lang-cursor");
+        let mut query = in_language("fr");
+        query.config.as_mut().unwrap().cursor = Some("next".to_string());
+        assert!(matches!(block_on(router.search("products", &query)), Err(SearchError::InvalidQuery(_))));
+    }
+
+    #[test]
+    fn test_gets_look_through_the_language_indexes_in_order() {
+        let en = registered_as("router-test-lang-get-en", Recording::default());
+        let de = registered_as("router-test-lang-get-de", Recording { stored: vec![("products_de", "7")], ..Recording::default() });
+        let router = language_router("router-test-lang-get-en", "router-test-lang-get-de");
+
+        assert_eq!(block_on(router.get("products", "7")).unwrap().map(|doc| doc.content), Some("products_de".to_string()));
+        assert!(block_on(router.get("products", "8")).unwrap().is_none());
+        assert_eq!(en.calls(), ["get products_en 7", "get products_en 8"]);
+        assert_eq!(de.calls(), ["get products_de 7", "get products_de 8"]);
+    }
+
+    #[test]
+    fn test_index_operations_cover_every_language_index() {
+        let en = registered_as("router-test-lang-admin-en", Recording::default());
+        let de = registered_as("router-test-lang-admin-de", Recording::default());
+        let router = language_router("router-test-lang-admin-en", "router-test-lang-admin-de");
+
+        block_on(router.create_index("products", None)).unwrap();
+        block_on(router.delete("products", "1")).unwrap();
+        block_on(router.get_schema("products")).unwrap();
+        block_on(router.delete_index("products")).unwrap();
+        // The schema comes from the default language index
+        assert_eq!(en.calls(), ["create products_en", "delete products_en 1", "schema products_en", "drop products_en"]);
+        assert_eq!(de.calls(), ["create products_de", "delete products_de 1", "drop products_de"]);
+    }
+}
@@ -0,0 +1,269 @@
+//! Custom routing for Elasticsearch and OpenSearch
+//!
+//! Both place a document on the shard picked by hashing its routing value,
+//! which is the document ID unless one is given. Multi-tenant clusters route
+//! by tenant instead, so a tenant's documents share a shard and a search with
+//! the same routing only visits that shard.
+//!
+//! Writes take the routing value from a document field named by
+//! [`ROUTING_FIELD_ENV`], either one field for every index or
+//! `index:field` entries. Reads by ID and deletes cannot see the document, so
+//! they take the routing value explicitly. Searches take `routing` and
+//! `preference` from `provider_params`; they are sent as URL parameters
+//! rather than merged into the request body.
+
+use std::collections::HashMap;
+use log::warn;
+use serde_json::{Map, Value};
+use crate::error::{SearchError, SearchResult};
+use crate::types::SearchQuery;
+
+/// Document field whose value routes writes, as `field` or `index:field,...`
+pub const ROUTING_FIELD_ENV: &str = "SEARCH_ROUTING_FIELD";
+
+/// `provider_params` keys sent as URL parameters of a search
+pub const SEARCH_URL_PARAMS: &[&str] = &["routing", "preference"];
+
+/// Routing fields, for every index or per index
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RoutingFields {
+    default: Option<String>,
+    per_index: HashMap<String, String>,
+}
+
+impl RoutingFields {
+    pub fn from_env() -> Self {
+        std::env::var(ROUTING_FIELD_ENV).map(|spec| Self::parse(&spec)).unwrap_or_default()
+    }
+
+    /// Parse a single field name, or `index:field` entries separated by commas, skipping malformed ones
+    pub fn parse(spec: &str) -> Self {
+        let mut fields = Self::default();
+        for item in spec.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+            match item.split_once(':') {
+                Some((index, field)) if !index.trim().is_empty() && !field.trim().is_empty() => {
+                    fields.per_index.insert(index.trim().to_string(), field.trim().to_string());
+                }
+                Some(_) => warn!("Ignoring malformed routing field: {}", item),
+                None => fields.default = Some(item.to_string()),
+            }
+        }
+        fields
+    }
+
+    /// Routing field of an index, falling back to the one for every index
+    pub fn for_index(&self, index: &str) -> Option<&str> {
+        self.per_index.get(index).or(self.default.as_ref()).map(String::as_str)
+    }
+
+    /// Routing value of a document, `None` when the index is not routed or the field is missing
+    ///
+    /// Strings and numbers route; other values are not a usable shard key.
+    pub fn document_routing(&self, index: &str, document: &Value) -> Option<String> {
+        match document.get(self.for_index(index)?)? {
+            Value::String(value) if !value.is_empty() => Some(value.clone()),
+            Value::Number(value) => Some(value.to_string()),
+            _ => None,
+        }
+    }
+}
+
+/// Routing value of a document written to `index`, per [`ROUTING_FIELD_ENV`]
+pub fn document_routing(index: &str, document: &Value) -> Option<String> {
+    RoutingFields::from_env().document_routing(index, document)
+}
+
+/// Remove the URL parameters of a search from validated provider params
+///
+/// A list of routing values is joined with commas, which searches the shards
+/// of every value.
+pub fn take_search_url_params(params: &mut Map<String, Value>) -> SearchResult<Vec<(String, String)>> {
+    let mut pairs = Vec::new();
+    for key in SEARCH_URL_PARAMS {
+        let Some(value) = params.remove(*key) else {
+            continue;
+        };
+        let rendered = match value {
+            Value::String(value) => value,
+            Value::Number(value) => value.to_string(),
+            Value::Array(values) => values
+                .iter()
+                .map(|value| match value {
+                    Value::String(value) => Ok(value.clone()),
+                    Value::Number(value) => Ok(value.to_string()),
+                    _ => Err(SearchError::invalid_query(format!("{} values must be strings or numbers", key))),
+                })
+                .collect::<SearchResult<Vec<_>>>()?
+                .join(","),
+            _ => return Err(SearchError::invalid_query(format!("{} must be a string or a list of strings", key))),
+        };
+        if !rendered.is_empty() {
+            pairs.push((key.to_string(), rendered));
+        }
+    }
+    Ok(pairs)
+}
+
+/// URL parameters of a search, from the `provider_params` of its config
+///
+/// Params that do not parse give none here; converting the query reports them.
+pub fn search_url_params(query: &SearchQuery) -> SearchResult<Vec<(String, String)>> {
+    let raw = query.config.as_ref().and_then(|c| c.provider_params.as_deref());
+    match raw.map(serde_json::from_str::<Value>) {
+        Some(Ok(Value::Object(mut params))) => take_search_url_params(&mut params),
+        _ => Ok(Vec::new()),
+    }
+}
+
+/// `path` with `pairs` appended to its query string
+pub fn with_query_params(path: &str, pairs: &[(String, String)]) -> String {
+    if pairs.is_empty() {
+        return path.to_string();
+    }
+    let mut serializer = url::form_urlencoded::Serializer::new(String::new());
+    serializer.extend_pairs(pairs);
+    let separator = if path.contains('?') { '&' } else { '?' };
+    format!("{}{}{}", path, separator, serializer.finish())
+}
+
+/// `path` routed to the shard of `routing`, unchanged without one
+pub fn with_routing(path: &str, routing: Option<&str>) -> String {
+    let pairs: Vec<(String, String)> = routing.map(|r| ("routing".to_string(), r.to_string())).into_iter().collect();
+    with_query_params(path, &pairs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::types::{QueryBuilder, SearchConfig};
+
+    fn params(value: Value) -> Map<String, Value> {
+        value.as_object().cloned().unwrap()
+    }
+
+    fn taken(value: Value) -> SearchResult<Vec<(String, String)>> {
+        take_search_url_params(&mut params(value))
+    }
+
+    fn invalid(result: SearchResult<Vec<(String, String)>>) -> String {
+        match result {
+            Err(SearchError::InvalidQuery(message)) => message,
+            other => panic!("expected InvalidQuery, got {:?}", other),
+        }
+    }
+
+    fn pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect()
+    }
+
+    fn with_params(raw: Option<&str>) -> SearchQuery {
+        let config = SearchConfig { provider_params: raw.map(String::from), ..SearchConfig::default() };
+        SearchQuery { config: Some(config), ..QueryBuilder::new().query("dune").build() }
+    }
+
+    #[test]
+    fn test_per_index_fields_win_over_the_default() {
+        let fields = RoutingFields::parse(" orders : customer_id , tenant_id ");
+        assert_eq!(fields.for_index("orders"), Some("customer_id"));
+        assert_eq!(fields.for_index("products"), Some("tenant_id"));
+    }
+
+    #[test]
+    fn test_without_a_default_only_listed_indexes_are_routed() {
+        let fields = RoutingFields::parse("orders:customer_id");
+        assert_eq!(fields.for_index("products"), None);
+        assert_eq!(RoutingFields::parse("").for_index("orders"), None);
+    }
+
+    #[test]
+    fn test_a_later_default_replaces_an_earlier_one() {
+        assert_eq!(RoutingFields::parse("tenant_id,org_id").for_index("products"), Some("org_id"));
+    }
+
+    #[test]
+    fn test_malformed_entries_are_skipped() {
+        let fields = RoutingFields::parse(":customer_id, orders:, ,");
+        assert_eq!(fields, RoutingFields::default());
+        // Only the first colon separates the index
+        assert_eq!(RoutingFields::parse("orders:meta:tenant").for_index("orders"), Some("meta:tenant"));
+    }
+
+    #[test]
+    fn test_strings_and_numbers_route() {
+        let fields = RoutingFields::parse("tenant");
+        assert_eq!(fields.document_routing("a", &json!({ "tenant": "acme" })).as_deref(), Some("acme"));
+        assert_eq!(fields.document_routing("a", &json!({ "tenant": 42 })).as_deref(), Some("42"));
+        assert_eq!(fields.document_routing("a", &json!({ "tenant": 1.5 })).as_deref(), Some("1.5"));
+    }
+
+    #[test]
+    fn test_unusable_routing_values_leave_the_document_unrouted() {
+        let fields = RoutingFields::parse("tenant");
+        for value in [json!(""), json!(null), json!(true), json!(["acme"]), json!({ "id": "acme" })] {
+            assert_eq!(fields.document_routing("a", &json!({ "tenant": value.clone() })), None, "{}", value);
+        }
+        assert_eq!(fields.document_routing("a", &json!({ "other": "acme" })), None);
+        assert_eq!(fields.document_routing("a", &json!("acme")), None);
+    }
+
+    #[test]
+    fn test_only_url_params_are_taken() {
+        let mut remaining = params(json!({ "routing": "acme", "preference": "_local", "min_score": 1 }));
+        let taken = take_search_url_params(&mut remaining).unwrap();
+        assert_eq!(taken, pairs(&[("routing", "acme"), ("preference", "_local")]));
+        assert_eq!(remaining.keys().collect::<Vec<_>>(), ["min_score"]);
+    }
+
+    #[test]
+    fn test_routing_lists_are_joined_with_commas() {
+        assert_eq!(taken(json!({ "routing": ["acme", 7] })).unwrap(), pairs(&[("routing", "acme,7")]));
+        assert_eq!(taken(json!({ "routing": 7 })).unwrap(), pairs(&[("routing", "7")]));
+    }
+
+    #[test]
+    fn test_empty_values_are_taken_without_a_param() {
+        let mut remaining = params(json!({ "routing": "", "preference": [] }));
+        assert!(take_search_url_params(&mut remaining).unwrap().is_empty());
+        assert!(remaining.is_empty());
+    }
+
+    #[test]
+    fn test_other_value_types_are_refused() {
+        assert_eq!(invalid(taken(json!({ "routing": { "tenant": "acme" } }))), "routing must be a string or a list of strings");
+        assert_eq!(invalid(taken(json!({ "preference": null }))), "preference must be a string or a list of strings");
+        assert_eq!(invalid(taken(json!({ "routing": ["acme", true] }))), "routing values must be strings or numbers");
+    }
+
+    #[test]
+    fn test_search_params_come_from_the_query_config() {
+        let query = with_params(Some(r#"{"routing": "acme", "min_score": 1}"#));
+        assert_eq!(search_url_params(&query).unwrap(), pairs(&[("routing", "acme")]));
+        // The query's own params are left untouched
+        assert!(query.config.unwrap().provider_params.unwrap().contains("routing"));
+    }
+
+    #[test]
+    fn test_missing_or_unparsable_params_give_no_search_params() {
+        assert!(search_url_params(&QueryBuilder::new().query("dune").build()).unwrap().is_empty());
+        for raw in [None, Some("not json"), Some("[1]")] {
+            assert!(search_url_params(&with_params(raw)).unwrap().is_empty(), "{:?}", raw);
+        }
+        assert!(search_url_params(&with_params(Some(r#"{"routing": {}}"#))).is_err());
+    }
+
+    #[test]
+    fn test_params_are_url_encoded_and_appended() {
+        let appended = with_query_params("docs/_search", &pairs(&[("routing", "acme,globex"), ("preference", "a b&c")]));
+        assert_eq!(appended, "docs/_search?routing=acme%2Cglobex&preference=a+b%26c");
+        assert_eq!(with_query_params("docs/_search?size=0", &pairs(&[("routing", "x")])), "docs/_search?size=0&routing=x");
+        assert_eq!(with_query_params("docs/_search", &[]), "docs/_search");
+    }
+
+    #[test]
+    fn test_with_routing_only_changes_routed_paths() {
+        assert_eq!(with_routing("docs/_doc/1?_source_includes=title", Some("acme")), "docs/_doc/1?_source_includes=title&routing=acme");
+        assert_eq!(with_routing("docs/_doc/1", Some("a/b")), "docs/_doc/1?routing=a%2Fb");
+        assert_eq!(with_routing("docs/_doc/1", None), "docs/_doc/1");
+    }
+}
@@ -0,0 +1,333 @@
+//! Random sampling of matching documents
+//!
+//! Preview UIs and dataset QA want a handful of arbitrary matches rather than
+//! the top-ranked ones. Lucene engines can do this server-side by replacing the
+//! relevance score with a seeded `random_score` ([`random_score_query`]).
+//! Engines without random scoring page through the matches instead and keep a
+//! uniform sample with reservoir sampling ([`sample_pages`]); that scan is
+//! capped at [`MAX_SAMPLE_SCAN`] documents, so on very large result sets the
+//! sample is drawn from the first matches only. [`scan_pages`] is the same
+//! scan for other client-side work over all matches.
+
+use std::future::Future;
+use serde_json::{json, Value};
+
+/// Page size used when scanning matches for client-side sampling
+pub const SAMPLE_PAGE_SIZE: u32 = 250;
+
+/// Maximum number of matches scanned for client-side sampling
+pub const MAX_SAMPLE_SCAN: u32 = 10_000;
+
+/// Small seeded generator (SplitMix64); sampling needs speed, not crypto strength
+#[derive(Debug, Clone)]
+pub struct SampleRng(u64);
+
+impl SampleRng {
+    pub fn with_seed(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    /// Generator seeded from a fresh random UUID
+    pub fn from_entropy() -> Self {
+        Self(random_seed())
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform value in `0..bound`
+    pub fn below(&mut self, bound: u64) -> u64 {
+        if bound == 0 {
+            return 0;
+        }
+        self.next_u64() % bound
+    }
+}
+
+/// Fresh seed for a sampling request
+pub fn random_seed() -> u64 {
+    uuid::Uuid::new_v4().as_u64_pair().0
+}
+
+/// Fixed-size uniform sample over a stream of unknown length (Algorithm R)
+#[derive(Debug, Clone)]
+pub struct Reservoir<T> {
+    capacity: usize,
+    seen: u64,
+    items: Vec<T>,
+    rng: SampleRng,
+}
+
+impl<T> Reservoir<T> {
+    pub fn new(capacity: usize, rng: SampleRng) -> Self {
+        Self {
+            capacity,
+            seen: 0,
+            items: Vec::with_capacity(capacity),
+            rng,
+        }
+    }
+
+    /// Offer the next item of the stream
+    pub fn offer(&mut self, item: T) {
+        self.seen += 1;
+        if self.items.len() < self.capacity {
+            self.items.push(item);
+            return;
+        }
+
+        let slot = self.rng.below(self.seen) as usize;
+        if slot < self.capacity {
+            self.items[slot] = item;
+        }
+    }
+
+    /// Number of items offered so far
+    pub fn seen(&self) -> u64 {
+        self.seen
+    }
+
+    /// The sample in random order
+    pub fn into_shuffled(mut self) -> Vec<T> {
+        for i in (1..self.items.len()).rev() {
+            let j = self.rng.below(i as u64 + 1) as usize;
+            self.items.swap(i, j);
+        }
+        self.items
+    }
+}
+
+/// Wrap a Lucene query so that hits come back in seeded random order
+///
+/// The original query still decides which documents match; only the score is
+/// replaced. `_seq_no` is used as the random field because it is always
+/// present and doc-valued.
+pub fn random_score_query(query: Value, seed: u64) -> Value {
+    json!({
+        "function_score": {
+            "query": query,
+            "random_score": {
+                "seed": seed,
+                "field": "_seq_no"
+            },
+            "boost_mode": "replace"
+        }
+    })
+}
+
+/// Page through all matches with `fetch_page(offset, limit)`, handing each hit to `visit`
+///
+/// Scanning stops at the first short page or after [`MAX_SAMPLE_SCAN`] hits.
+/// Returns the number of hits scanned.
+pub async fn scan_pages<H, F, Fut, E>(mut fetch_page: F, mut visit: impl FnMut(H)) -> Result<u64, E>
+where
+    F: FnMut(u32, u32) -> Fut,
+    Fut: Future<Output = Result<Vec<H>, E>>,
+{
+    let mut offset = 0;
+    while offset < MAX_SAMPLE_SCAN {
+        let limit = SAMPLE_PAGE_SIZE.min(MAX_SAMPLE_SCAN - offset);
+        let page = fetch_page(offset, limit).await?;
+        let fetched = page.len() as u32;
+        page.into_iter().for_each(&mut visit);
+
+        offset += fetched;
+        if fetched < limit {
+            break;
+        }
+    }
+    Ok(u64::from(offset))
+}
+
+/// Draw `sample` hits by paging through all matches with `fetch_page(offset, limit)`
+///
+/// Returns the sampled hits in random order together with the number of hits
+/// scanned.
+pub async fn sample_pages<H, F, Fut, E>(sample: u32, fetch_page: F) -> Result<(Vec<H>, u64), E>
+where
+    F: FnMut(u32, u32) -> Fut,
+    Fut: Future<Output = Result<Vec<H>, E>>,
+{
+    if sample == 0 {
+        return Ok((Vec::new(), 0));
+    }
+    let mut reservoir = Reservoir::new(sample as usize, SampleRng::from_entropy());
+    scan_pages(fetch_page, |hit| reservoir.offer(hit)).await?;
+
+    let seen = reservoir.seen();
+    Ok((reservoir.into_shuffled(), seen))
+}
+
+/// Total match count implied by a sampling scan
+///
+/// Known only when the scan reached the end of the matches before the cap.
+pub fn scanned_total(scanned: u64) -> Option<u32> {
+    (scanned < u64::from(MAX_SAMPLE_SCAN)).then_some(scanned as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    fn block_on<T>(future: impl Future<Output = T>) -> T {
+        tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(future)
+    }
+
+    /// Page fetcher over `total` numbered matches that records its calls
+    fn matches(total: u32, calls: &RefCell<Vec<(u32, u32)>>) -> impl FnMut(u32, u32) -> std::future::Ready<Result<Vec<u32>, ()>> + '_ {
+        move |offset, limit| {
+            calls.borrow_mut().push((offset, limit));
+            std::future::ready(Ok((offset..(offset + limit).min(total)).collect()))
+        }
+    }
+
+    #[test]
+    fn test_same_seed_gives_the_same_sequence() {
+        let mut first = SampleRng::with_seed(7);
+        let mut second = SampleRng::with_seed(7);
+        let mut other = SampleRng::with_seed(8);
+        let a: Vec<u64> = (0..4).map(|_| first.next_u64()).collect();
+        assert_eq!(a, (0..4).map(|_| second.next_u64()).collect::<Vec<_>>());
+        assert_ne!(a, (0..4).map(|_| other.next_u64()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_below_stays_in_bounds() {
+        let mut rng = SampleRng::with_seed(0);
+        assert_eq!(rng.below(0), 0);
+        assert_eq!(rng.below(1), 0);
+        assert!((0..1000).all(|_| rng.below(3) < 3));
+    }
+
+    #[test]
+    fn test_reservoir_keeps_capacity_and_covers_stream() {
+        let mut counts = [0u32; 20];
+        for seed in 0..500 {
+            let mut reservoir = Reservoir::new(5, SampleRng::with_seed(seed));
+            for i in 0..20 {
+                reservoir.offer(i);
+            }
+            for i in reservoir.into_shuffled() {
+                counts[i] += 1;
+            }
+        }
+        // Every element should be picked roughly 500 * 5 / 20 = 125 times
+        assert!(counts.iter().all(|&c| c > 60 && c < 200), "{:?}", counts);
+    }
+
+    #[test]
+    fn test_short_streams_are_kept_whole() {
+        let mut reservoir = Reservoir::new(10, SampleRng::with_seed(3));
+        (0..4).for_each(|i| reservoir.offer(i));
+        let mut sample = reservoir.into_shuffled();
+        sample.sort();
+        assert_eq!(sample, vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_zero_capacity_counts_without_keeping() {
+        let mut reservoir = Reservoir::new(0, SampleRng::with_seed(3));
+        (0..4).for_each(|i| reservoir.offer(i));
+        assert_eq!(reservoir.seen(), 4);
+        assert!(reservoir.into_shuffled().is_empty());
+    }
+
+    #[test]
+    fn test_shuffle_reorders_without_losing_items() {
+        let orders: Vec<Vec<u32>> = (0..20)
+            .map(|seed| {
+                let mut reservoir = Reservoir::new(6, SampleRng::with_seed(seed));
+                (0..6).for_each(|i| reservoir.offer(i));
+                reservoir.into_shuffled()
+            })
+            .collect();
+        for order in &orders {
+            let mut sorted = order.clone();
+            sorted.sort();
+            assert_eq!(sorted, (0..6).collect::<Vec<_>>());
+        }
+        assert!(orders.iter().any(|order| order != &orders[0]));
+    }
+
+    #[test]
+    fn test_random_score_replaces_only_the_score() {
+        let query = json!({ "match": { "title": "dune" } });
+        assert_eq!(
+            random_score_query(query.clone(), 42),
+            json!({
+                "function_score": {
+                    "query": query,
+                    "random_score": { "seed": 42, "field": "_seq_no" },
+                    "boost_mode": "replace"
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_scan_stops_at_a_short_page() {
+        let calls = RefCell::new(Vec::new());
+        let mut visited = 0;
+        let scanned = block_on(scan_pages(matches(600, &calls), |_| visited += 1)).unwrap();
+        assert_eq!((scanned, visited), (600, 600));
+        assert_eq!(*calls.borrow(), vec![(0, 250), (250, 250), (500, 250)]);
+    }
+
+    #[test]
+    fn test_scan_of_an_exact_page_multiple_needs_an_empty_page() {
+        let calls = RefCell::new(Vec::new());
+        assert_eq!(block_on(scan_pages(matches(500, &calls), |_| {})).unwrap(), 500);
+        assert_eq!(calls.borrow().last(), Some(&(500, 250)));
+    }
+
+    #[test]
+    fn test_scan_is_capped() {
+        let calls = RefCell::new(Vec::new());
+        let scanned = block_on(scan_pages(matches(u32::MAX, &calls), |_| {})).unwrap();
+        assert_eq!(scanned, u64::from(MAX_SAMPLE_SCAN));
+        assert_eq!(calls.borrow().len(), 40);
+        assert_eq!(scanned_total(scanned), None);
+    }
+
+    #[test]
+    fn test_scan_errors_stop_the_scan() {
+        let mut visited = Vec::new();
+        let result = block_on(scan_pages(
+            |offset, _| std::future::ready(if offset == 0 { Ok(vec![1; 250]) } else { Err("boom") }),
+            |hit| visited.push(hit),
+        ));
+        assert_eq!(result, Err("boom"));
+        assert_eq!(visited.len(), 250);
+    }
+
+    #[test]
+    fn test_empty_sample_fetches_nothing() {
+        let calls = RefCell::new(Vec::new());
+        let (sample, scanned) = block_on(sample_pages(0, matches(600, &calls))).unwrap();
+        assert!(sample.is_empty());
+        assert_eq!(scanned, 0);
+        assert!(calls.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_sample_larger_than_the_matches_returns_them_all() {
+        let calls = RefCell::new(Vec::new());
+        let (mut sample, scanned) = block_on(sample_pages(10, matches(3, &calls))).unwrap();
+        sample.sort();
+        assert_eq!(sample, vec![0, 1, 2]);
+        assert_eq!(scanned_total(scanned), Some(3));
+    }
+
+    #[test]
+    fn test_total_is_known_only_below_the_cap() {
+        assert_eq!(scanned_total(0), Some(0));
+        assert_eq!(scanned_total(u64::from(MAX_SAMPLE_SCAN) - 1), Some(MAX_SAMPLE_SCAN - 1));
+        assert_eq!(scanned_total(u64::from(MAX_SAMPLE_SCAN)), None);
+    }
+}
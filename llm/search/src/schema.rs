@@ -0,0 +1,432 @@
+//! Schema compatibility checks
+//!
+//! Engines differ in which schema changes they accept on a live index.
+//! Adding a field is safe everywhere, but changing a mapped type is a reindex
+//! on Elasticsearch/OpenSearch, a drop-and-re-add on Typesense and a no-op on
+//! the schemaless engines. [`check_schema_compatibility`] classifies every
+//! difference between two schemas for a given provider so that
+//! `ensure_index` and migrations can tell what can be applied in place and
+//! what needs a new index.
+
+use crate::analysis::FieldAnalysis;
+use crate::error::{SearchError, SearchResult};
+use crate::types::{FieldType, Schema, SchemaField};
+
+/// How a schema change can be applied to an existing index
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ChangeKind {
+    /// New field; existing documents are unaffected
+    Additive,
+
+    /// Existing field updated through the provider's settings or schema API
+    InPlace,
+
+    /// Needs a new index and a reindex of all documents
+    Reindex,
+}
+
+/// What changed for a single field
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldChange {
+    Added,
+    Removed,
+    TypeChanged { from: FieldType, to: FieldType },
+
+    /// Facet, sort or index flags changed
+    OptionsChanged,
+
+    /// The tokenizer, language or stemming of a text field changed
+    AnalysisChanged,
+
+    /// Only the `required` flag changed
+    RequiredChanged,
+
+    /// The primary key differs (`field` holds the desired key)
+    PrimaryKeyChanged { from: String },
+}
+
+/// A classified difference between the current and desired schema
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaChange {
+    pub field: String,
+    pub change: FieldChange,
+    pub kind: ChangeKind,
+}
+
+/// All differences between two schemas for one provider
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CompatibilityReport {
+    pub provider: String,
+    pub changes: Vec<SchemaChange>,
+}
+
+impl CompatibilityReport {
+    /// Whether the schemas are equivalent for this provider
+    pub fn is_noop(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// The most disruptive change kind, if there are changes
+    pub fn worst(&self) -> Option<ChangeKind> {
+        self.changes.iter().map(|c| c.kind).max()
+    }
+
+    /// Whether any change needs a reindex
+    pub fn requires_reindex(&self) -> bool {
+        self.worst() == Some(ChangeKind::Reindex)
+    }
+
+    /// Names of fields the desired schema adds
+    pub fn added(&self) -> Vec<String> {
+        self.changes
+            .iter()
+            .filter(|c| c.change == FieldChange::Added)
+            .map(|c| c.field.clone())
+            .collect()
+    }
+
+    /// Names of existing fields with in-place updates
+    pub fn updated_in_place(&self) -> Vec<String> {
+        self.changes
+            .iter()
+            .filter(|c| c.kind == ChangeKind::InPlace && c.change != FieldChange::Removed)
+            .map(|c| c.field.clone())
+            .collect()
+    }
+
+    /// Fail if the desired schema cannot be reached without a reindex
+    ///
+    /// Removed fields are ignored: an index may carry more fields than a
+    /// caller declares, and dropping them is never done implicitly.
+    pub fn ensure_applicable(&self, index: &str) -> SearchResult<()> {
+        let blocking: Vec<String> = self
+            .changes
+            .iter()
+            .filter(|c| c.kind == ChangeKind::Reindex && c.change != FieldChange::Removed)
+            .map(|c| format!("{}: {:?}", c.field, c.change))
+            .collect();
+
+        if blocking.is_empty() {
+            return Ok(());
+        }
+        Err(SearchError::invalid_query(format!(
+            "Index {} needs a reindex for {} schema change(s): {}",
+            index,
+            blocking.len(),
+            blocking.join(", ")
+        )))
+    }
+
+    /// The subset of `desired` holding the fields to add or update in place
+    pub fn patch_schema(&self, desired: &Schema) -> Schema {
+        let mut names = self.added();
+        names.extend(self.updated_in_place());
+        Schema {
+            fields: desired.fields.iter().filter(|f| names.contains(&f.name)).cloned().collect(),
+            primary_key: desired.primary_key.clone(),
+        }
+    }
+}
+
+/// Classify every difference between `current` and `desired` for `provider`
+///
+/// Types are compared as the provider stores them, so changes that collapse
+/// to the same storage type (Text and Keyword on Typesense, say) are not
+/// reported.
+pub fn check_schema_compatibility(provider: &str, current: &Schema, desired: &Schema) -> CompatibilityReport {
+    let mut changes = Vec::new();
+    let mut push = |field: &str, change: FieldChange| {
+        let kind = classify(provider, &change);
+        changes.push(SchemaChange { field: field.to_string(), change, kind });
+    };
+
+    for field in &desired.fields {
+        let Some(existing) = current.fields.iter().find(|f| f.name == field.name) else {
+            push(&field.name, FieldChange::Added);
+            continue;
+        };
+
+        if storage_type(provider, existing.field_type) != storage_type(provider, field.field_type) {
+            push(&field.name, FieldChange::TypeChanged { from: existing.field_type, to: field.field_type });
+        } else if options(existing) != options(field) {
+            push(&field.name, FieldChange::OptionsChanged);
+        } else if native_analysis(provider, existing) != native_analysis(provider, field) {
+            push(&field.name, FieldChange::AnalysisChanged);
+        } else if existing.required != field.required {
+            push(&field.name, FieldChange::RequiredChanged);
+        }
+    }
+
+    for field in &current.fields {
+        if !desired.fields.iter().any(|f| f.name == field.name) {
+            push(&field.name, FieldChange::Removed);
+        }
+    }
+
+    // Only Meilisearch reports a primary key that reflects a real setting
+    if provider == "meilisearch" {
+        if let (Some(from), Some(to)) = (&current.primary_key, &desired.primary_key) {
+            if from != to {
+                push(to, FieldChange::PrimaryKeyChanged { from: from.clone() });
+            }
+        }
+    }
+
+    CompatibilityReport { provider: provider.to_string(), changes }
+}
+
+fn options(field: &SchemaField) -> (bool, bool, bool) {
+    (field.facet, field.sort, field.index)
+}
+
+/// Analysis settings the provider stores; only text fields are analyzed
+fn native_analysis(provider: &str, field: &SchemaField) -> String {
+    match (&field.analysis, field.field_type) {
+        (Some(analysis), FieldType::Text) => analysis.native_form(provider),
+        _ => FieldAnalysis::default().native_form(provider),
+    }
+}
+
+/// Whether the provider keeps no typed schema at all
+fn schemaless(provider: &str) -> bool {
+    matches!(provider, "meilisearch" | "algolia")
+}
+
+/// Name of the type a provider actually stores for a field type
+fn storage_type(provider: &str, field_type: FieldType) -> &'static str {
+    match (provider, field_type) {
+        (p, _) if schemaless(p) => "any",
+        ("typesense", FieldType::Text | FieldType::Keyword | FieldType::Decimal) => "string",
+        ("typesense", FieldType::Integer) => "int32",
+        ("typesense", FieldType::Int64 | FieldType::Date) => "int64",
+        (_, FieldType::Text) => "text",
+        (_, FieldType::Keyword | FieldType::Decimal) => "keyword",
+        (_, FieldType::Integer) => "integer",
+        (_, FieldType::Int64) => "long",
+        (_, FieldType::Float) => "float",
+        (_, FieldType::Boolean) => "boolean",
+        (_, FieldType::Date) => "date",
+        (_, FieldType::GeoPoint) => "geo_point",
+    }
+}
+
+fn classify(provider: &str, change: &FieldChange) -> ChangeKind {
+    // Mappings on Lucene engines are append-only
+    let lucene = !schemaless(provider) && provider != "typesense";
+    match change {
+        FieldChange::Added => ChangeKind::Additive,
+        FieldChange::RequiredChanged => ChangeKind::InPlace,
+        FieldChange::PrimaryKeyChanged { .. } => ChangeKind::Reindex,
+        FieldChange::Removed | FieldChange::OptionsChanged if lucene => ChangeKind::Reindex,
+        FieldChange::Removed | FieldChange::OptionsChanged => ChangeKind::InPlace,
+        FieldChange::TypeChanged { .. } | FieldChange::AnalysisChanged if schemaless(provider) => ChangeKind::InPlace,
+        // Typesense only sets a field's locale and stemming when the field is created
+        FieldChange::AnalysisChanged => ChangeKind::Reindex,
+        // Typesense could drop and re-add the field, but that discards its data
+        FieldChange::TypeChanged { .. } => ChangeKind::Reindex,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::Tokenizer;
+
+    const PROVIDERS: [&str; 5] = ["elasticsearch", "opensearch", "typesense", "meilisearch", "algolia"];
+
+    fn field(name: &str, field_type: FieldType) -> SchemaField {
+        SchemaField {
+            name: name.to_string(),
+            field_type,
+            required: false,
+            facet: false,
+            sort: false,
+            index: true,
+            analysis: None,
+            ui: None,
+        }
+    }
+
+    fn schema(fields: Vec<SchemaField>) -> Schema {
+        Schema { fields, primary_key: Some("id".to_string()) }
+    }
+
+    fn analyzed(name: &str, analysis: FieldAnalysis) -> SchemaField {
+        SchemaField { analysis: Some(analysis), ..field(name, FieldType::Text) }
+    }
+
+    /// The single change between `current` and `desired` for `provider`
+    fn only_change(provider: &str, current: SchemaField, desired: SchemaField) -> (FieldChange, ChangeKind) {
+        let report = check_schema_compatibility(provider, &schema(vec![current]), &schema(vec![desired]));
+        assert_eq!(report.changes.len(), 1, "{}: {:?}", provider, report.changes);
+        let change = report.changes.into_iter().next().unwrap();
+        (change.change, change.kind)
+    }
+
+    #[test]
+    fn test_identical_schemas_are_a_noop() {
+        let schema = schema(vec![field("title", FieldType::Text), analyzed("body", FieldAnalysis::stemmed("en"))]);
+        for provider in PROVIDERS {
+            let report = check_schema_compatibility(provider, &schema, &schema);
+            assert!(report.is_noop(), "{}", provider);
+            assert_eq!(report.worst(), None);
+            assert!(report.ensure_applicable("books").is_ok());
+        }
+    }
+
+    #[test]
+    fn test_added_fields_are_additive_everywhere() {
+        for provider in PROVIDERS {
+            let current = schema(vec![field("title", FieldType::Text)]);
+            let desired = schema(vec![field("title", FieldType::Text), field("price", FieldType::Float)]);
+            let report = check_schema_compatibility(provider, &current, &desired);
+            assert_eq!(report.added(), vec!["price".to_string()]);
+            assert_eq!(report.worst(), Some(ChangeKind::Additive));
+        }
+    }
+
+    #[test]
+    fn test_removed_fields_never_block_but_need_a_reindex_on_lucene() {
+        let current = schema(vec![field("title", FieldType::Text), field("legacy", FieldType::Keyword)]);
+        let desired = schema(vec![field("title", FieldType::Text)]);
+
+        let elastic = check_schema_compatibility("elasticsearch", &current, &desired);
+        assert_eq!(elastic.changes[0].change, FieldChange::Removed);
+        assert!(elastic.requires_reindex());
+        assert!(elastic.ensure_applicable("books").is_ok());
+
+        let typesense = check_schema_compatibility("typesense", &current, &desired);
+        assert_eq!(typesense.worst(), Some(ChangeKind::InPlace));
+        assert!(typesense.updated_in_place().is_empty());
+        assert!(typesense.patch_schema(&desired).fields.is_empty());
+    }
+
+    #[test]
+    fn test_types_sharing_a_storage_type_are_not_changes() {
+        let same = |provider, from, to| check_schema_compatibility(provider, &schema(vec![field("f", from)]), &schema(vec![field("f", to)])).is_noop();
+        assert!(same("typesense", FieldType::Text, FieldType::Keyword));
+        assert!(same("typesense", FieldType::Keyword, FieldType::Decimal));
+        assert!(same("typesense", FieldType::Int64, FieldType::Date));
+        assert!(same("elasticsearch", FieldType::Keyword, FieldType::Decimal));
+        assert!(!same("elasticsearch", FieldType::Text, FieldType::Keyword));
+        assert!(!same("typesense", FieldType::Integer, FieldType::Int64));
+    }
+
+    #[test]
+    fn test_type_changes_need_a_reindex_unless_schemaless() {
+        let from = field("price", FieldType::Integer);
+        let to = field("price", FieldType::Float);
+        let changed = FieldChange::TypeChanged { from: FieldType::Integer, to: FieldType::Float };
+        for provider in ["elasticsearch", "opensearch", "typesense"] {
+            assert_eq!(only_change(provider, from.clone(), to.clone()), (changed.clone(), ChangeKind::Reindex), "{}", provider);
+        }
+        // Schemaless engines store any type, so a type change is a plain update there
+        for provider in ["meilisearch", "algolia"] {
+            assert!(check_schema_compatibility(provider, &schema(vec![from.clone()]), &schema(vec![to.clone()])).is_noop());
+        }
+    }
+
+    #[test]
+    fn test_option_changes_are_in_place_outside_lucene() {
+        let facet = SchemaField { facet: true, ..field("tag", FieldType::Keyword) };
+        let plain = field("tag", FieldType::Keyword);
+        assert_eq!(only_change("opensearch", plain.clone(), facet.clone()), (FieldChange::OptionsChanged, ChangeKind::Reindex));
+        assert_eq!(only_change("typesense", plain.clone(), facet.clone()), (FieldChange::OptionsChanged, ChangeKind::InPlace));
+        let unindexed = SchemaField { index: false, ..field("tag", FieldType::Keyword) };
+        assert_eq!(only_change("algolia", plain, unindexed), (FieldChange::OptionsChanged, ChangeKind::InPlace));
+    }
+
+    #[test]
+    fn test_only_the_most_significant_difference_is_reported() {
+        let current = field("price", FieldType::Integer);
+        let desired = SchemaField { facet: true, required: true, ..field("price", FieldType::Float) };
+        assert!(matches!(only_change("elasticsearch", current.clone(), desired.clone()).0, FieldChange::TypeChanged { .. }));
+        assert_eq!(only_change("meilisearch", current, desired).0, FieldChange::OptionsChanged);
+    }
+
+    #[test]
+    fn test_required_changes_are_in_place_everywhere() {
+        let required = SchemaField { required: true, ..field("title", FieldType::Text) };
+        for provider in PROVIDERS {
+            let report = check_schema_compatibility(provider, &schema(vec![field("title", FieldType::Text)]), &schema(vec![required.clone()]));
+            assert_eq!(report.changes[0].change, FieldChange::RequiredChanged);
+            assert_eq!(report.updated_in_place(), vec!["title".to_string()]);
+        }
+    }
+
+    #[test]
+    fn test_analysis_changes_need_a_reindex_on_engines_that_store_it() {
+        let plain = field("body", FieldType::Text);
+        let korean = analyzed("body", FieldAnalysis::with_tokenizer(Tokenizer::Nori));
+        assert_eq!(only_change("elasticsearch", plain.clone(), korean.clone()), (FieldChange::AnalysisChanged, ChangeKind::Reindex));
+        assert_eq!(only_change("typesense", plain.clone(), korean.clone()), (FieldChange::AnalysisChanged, ChangeKind::Reindex));
+        assert_eq!(only_change("meilisearch", plain, korean), (FieldChange::AnalysisChanged, ChangeKind::InPlace));
+    }
+
+    #[test]
+    fn test_analysis_the_provider_ignores_is_not_a_change() {
+        // Meilisearch never stems, and Algolia keeps no per-field analysis
+        let plain = schema(vec![field("body", FieldType::Text)]);
+        let stemmed = schema(vec![analyzed("body", FieldAnalysis { stemming: Some(true), ..FieldAnalysis::default() })]);
+        assert!(check_schema_compatibility("meilisearch", &plain, &stemmed).is_noop());
+        let korean = schema(vec![analyzed("body", FieldAnalysis::with_tokenizer(Tokenizer::Nori))]);
+        assert!(check_schema_compatibility("algolia", &plain, &korean).is_noop());
+    }
+
+    #[test]
+    fn test_analysis_of_non_text_fields_is_ignored() {
+        let keyword = field("sku", FieldType::Keyword);
+        let with_analysis = SchemaField { analysis: Some(FieldAnalysis::with_tokenizer(Tokenizer::Nori)), ..keyword.clone() };
+        for provider in PROVIDERS {
+            assert!(check_schema_compatibility(provider, &schema(vec![keyword.clone()]), &schema(vec![with_analysis.clone()])).is_noop());
+        }
+    }
+
+    #[test]
+    fn test_primary_key_changes_count_only_on_meilisearch() {
+        let current = schema(vec![]);
+        let desired = Schema { fields: vec![], primary_key: Some("sku".to_string()) };
+        let report = check_schema_compatibility("meilisearch", &current, &desired);
+        assert_eq!(
+            report.changes,
+            vec![SchemaChange {
+                field: "sku".to_string(),
+                change: FieldChange::PrimaryKeyChanged { from: "id".to_string() },
+                kind: ChangeKind::Reindex,
+            }]
+        );
+        assert!(report.ensure_applicable("books").is_err());
+
+        assert!(check_schema_compatibility("typesense", &current, &desired).is_noop());
+        let unknown = Schema { fields: vec![], primary_key: None };
+        assert!(check_schema_compatibility("meilisearch", &unknown, &desired).is_noop());
+        assert!(check_schema_compatibility("meilisearch", &current, &unknown).is_noop());
+    }
+
+    #[test]
+    fn test_blocking_changes_are_listed_in_the_error() {
+        let current = schema(vec![field("price", FieldType::Integer), field("tag", FieldType::Keyword), field("legacy", FieldType::Text)]);
+        let desired = schema(vec![field("price", FieldType::Float), SchemaField { sort: true, ..field("tag", FieldType::Keyword) }]);
+        let report = check_schema_compatibility("elasticsearch", &current, &desired);
+        match report.ensure_applicable("books") {
+            Err(SearchError::InvalidQuery(message)) => assert_eq!(
+                message,
+                "Index books needs a reindex for 2 schema change(s): price: TypeChanged { from: Integer, to: Float }, tag: OptionsChanged"
+            ),
+            other => panic!("expected InvalidQuery, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_patch_schema_holds_added_and_in_place_fields() {
+        let current = schema(vec![field("title", FieldType::Text), field("tag", FieldType::Keyword), field("legacy", FieldType::Text)]);
+        let tag = SchemaField { facet: true, ..field("tag", FieldType::Keyword) };
+        let desired = Schema {
+            fields: vec![field("title", FieldType::Text), tag.clone(), field("price", FieldType::Float)],
+            primary_key: Some("sku".to_string()),
+        };
+        let patch = check_schema_compatibility("typesense", &current, &desired).patch_schema(&desired);
+        assert_eq!(patch.fields, vec![tag, field("price", FieldType::Float)]);
+        assert_eq!(patch.primary_key.as_deref(), Some("sku"));
+    }
+}
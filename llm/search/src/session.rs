@@ -0,0 +1,110 @@
+//! State carried by a search session
+//!
+//! A session is opened once and reused for many calls: the provider behind
+//! it keeps its client, so later calls skip configuration parsing and client
+//! construction. It also remembers a default index, the consistency token of
+//! its latest write, so later searches read that write, and a user token that
+//! providers send to the backend for personalization and analytics.
+//!
+//! While a session call runs, its user token is active on the thread;
+//! clients read it with [`current_user_token`], the same way they read the
+//! request ID.
+
+use std::cell::RefCell;
+use crate::error::{SearchError, SearchResult};
+
+thread_local! {
+    static USER_TOKEN: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// User token of the session call running on this thread, if any
+pub fn current_user_token() -> Option<String> {
+    USER_TOKEN.with(|current| current.borrow().clone())
+}
+
+/// Keeps a user token active; the previous token is restored on drop
+#[derive(Debug)]
+pub struct UserScope {
+    previous: Option<String>,
+}
+
+impl Drop for UserScope {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        USER_TOKEN.with(|current| *current.borrow_mut() = previous);
+    }
+}
+
+/// Defaults a session applies to every call
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SessionContext {
+    pub default_index: Option<String>,
+
+    /// Token of the latest write made through the session
+    pub consistency_token: Option<String>,
+
+    pub user_token: Option<String>,
+}
+
+impl SessionContext {
+    pub fn new(default_index: Option<String>, user_token: Option<String>) -> Self {
+        Self { default_index, consistency_token: None, user_token }
+    }
+
+    /// The index given to a call, or the session's default index
+    pub fn index<'a>(&'a self, index: Option<&'a str>) -> SearchResult<&'a str> {
+        index
+            .or(self.default_index.as_deref())
+            .ok_or_else(|| SearchError::invalid_query("No index given and the session has no default index"))
+    }
+
+    /// Fill in the session's consistency token unless the query carries its own
+    pub fn apply_consistency(&self, token: &mut Option<String>) {
+        if token.is_none() {
+            token.clone_from(&self.consistency_token);
+        }
+    }
+
+    /// Remember the token of a write so later searches wait for it
+    pub fn record_write(&mut self, token: String) {
+        self.consistency_token = Some(token);
+    }
+
+    /// Make the session's user token active until the returned scope is dropped
+    pub fn enter(&self) -> UserScope {
+        let token = self.user_token.clone();
+        USER_TOKEN.with(|current| UserScope { previous: current.replace(token) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_context_defaults_and_user_scope() {
+        let mut session = SessionContext::new(Some("products".to_string()), Some("user-7".to_string()));
+        assert_eq!(session.index(None).unwrap(), "products");
+        assert_eq!(session.index(Some("orders")).unwrap(), "orders");
+        assert!(SessionContext::default().index(None).is_err());
+
+        let mut token = None;
+        session.apply_consistency(&mut token);
+        assert_eq!(token, None);
+        session.record_write("write-1".to_string());
+        session.apply_consistency(&mut token);
+        assert_eq!(token.as_deref(), Some("write-1"));
+        let mut explicit = Some("write-0".to_string());
+        session.apply_consistency(&mut explicit);
+        assert_eq!(explicit.as_deref(), Some("write-0"));
+
+        assert_eq!(current_user_token(), None);
+        {
+            let _scope = session.enter();
+            assert_eq!(current_user_token().as_deref(), Some("user-7"));
+            let _inner = SessionContext::default().enter();
+            assert_eq!(current_user_token(), None);
+        }
+        assert_eq!(current_user_token(), None);
+    }
+}
@@ -0,0 +1,271 @@
+//! Index settings snapshots for configuration-as-code workflows
+//!
+//! An `IndexSettings` document captures everything needed to recreate the
+//! relevance configuration of an index: its schema, synonyms, ranking rules
+//! and stop words. Providers export their native settings into this shape and
+//! apply it back idempotently, so the JSON form can be versioned in git.
+
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::analysis::Normalization;
+use crate::error::{SearchError, SearchResult};
+use crate::types::Schema;
+
+/// Current version of the exported settings format
+pub const SETTINGS_FORMAT_VERSION: u32 = 1;
+
+/// Portable snapshot of an index configuration
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct IndexSettings {
+    /// Format version, used to reject snapshots from incompatible releases
+    #[serde(default = "default_version")]
+    pub version: u32,
+
+    /// Index schema, if the provider exposes one
+    #[serde(default)]
+    pub schema: Option<Schema>,
+
+    /// Synonym groups keyed by term; each term maps to its equivalents
+    #[serde(default)]
+    pub synonyms: BTreeMap<String, Vec<String>>,
+
+    /// Ordered ranking rules in the provider's own syntax
+    #[serde(default)]
+    pub ranking_rules: Vec<String>,
+
+    /// Words ignored at query and index time
+    #[serde(default)]
+    pub stop_words: Vec<String>,
+
+    /// Text folding applied by the index analyzers
+    #[serde(default)]
+    pub normalization: Option<Normalization>,
+
+    /// Provider-specific settings that have no portable equivalent
+    #[serde(default)]
+    pub provider_settings: Option<Value>,
+}
+
+fn default_version() -> u32 {
+    SETTINGS_FORMAT_VERSION
+}
+
+impl Default for IndexSettings {
+    fn default() -> Self {
+        Self {
+            version: SETTINGS_FORMAT_VERSION,
+            schema: None,
+            synonyms: BTreeMap::new(),
+            ranking_rules: Vec::new(),
+            stop_words: Vec::new(),
+            normalization: None,
+            provider_settings: None,
+        }
+    }
+}
+
+impl IndexSettings {
+    /// Serialize the settings to pretty-printed JSON suitable for committing
+    pub fn to_json(&self) -> SearchResult<String> {
+        serde_json::to_string_pretty(self)
+            .map_err(|e| SearchError::internal(format!("Failed to serialize settings: {}", e)))
+    }
+
+    /// Parse settings from JSON, validating the format version
+    pub fn from_json(json: &str) -> SearchResult<Self> {
+        let settings: IndexSettings = serde_json::from_str(json)
+            .map_err(|e| SearchError::invalid_query(format!("Invalid settings JSON: {}", e)))?;
+
+        if settings.version > SETTINGS_FORMAT_VERSION {
+            return Err(SearchError::invalid_query(format!(
+                "Unsupported settings version {} (max supported {})",
+                settings.version, SETTINGS_FORMAT_VERSION
+            )));
+        }
+
+        Ok(settings.normalized())
+    }
+
+    /// Return a copy with stable ordering so repeated exports produce identical JSON
+    pub fn normalized(mut self) -> Self {
+        for values in self.synonyms.values_mut() {
+            values.sort();
+            values.dedup();
+        }
+        self.stop_words.sort();
+        self.stop_words.dedup();
+        if let Some(schema) = self.schema.as_mut() {
+            schema.fields.sort_by(|a, b| a.name.cmp(&b.name));
+            // An explicit standard tokenizer reads back as no hint
+            for field in &mut schema.fields {
+                field.analysis = field.analysis.take().filter(|a| !a.is_default());
+            }
+        }
+        self
+    }
+}
+
+/// A single setting whose current value differs from the desired state
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SettingChange {
+    /// Dotted path of the setting, e.g. `synonyms.phone` or `schema.fields.title`
+    pub path: String,
+
+    /// Value currently applied to the index, if any
+    pub current: Option<Value>,
+
+    /// Value requested by the desired settings, if any
+    pub desired: Option<Value>,
+}
+
+/// Drift report between an index's current settings and a desired snapshot
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct SettingsDiff {
+    /// Individual differences, ordered by path
+    pub changes: Vec<SettingChange>,
+
+    /// Whether the desired settings were applied after computing the diff
+    pub reconciled: bool,
+}
+
+impl SettingsDiff {
+    /// Returns true when the index already matches the desired settings
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Serialize the report to JSON
+    pub fn to_json(&self) -> SearchResult<String> {
+        serde_json::to_string_pretty(self)
+            .map_err(|e| SearchError::internal(format!("Failed to serialize settings diff: {}", e)))
+    }
+
+    fn push<T: Serialize>(&mut self, path: String, current: Option<&T>, desired: Option<&T>) {
+        self.changes.push(SettingChange {
+            path,
+            current: current.and_then(|v| serde_json::to_value(v).ok()),
+            desired: desired.and_then(|v| serde_json::to_value(v).ok()),
+        });
+    }
+}
+
+impl IndexSettings {
+    /// Compare these (current) settings against a desired snapshot
+    ///
+    /// Sections the desired snapshot leaves unset (`schema`, `normalization`,
+    /// `provider_settings`, empty `ranking_rules`) are treated as unmanaged and
+    /// never reported as drift.
+    pub fn diff(&self, desired: &IndexSettings) -> SettingsDiff {
+        let current = self.clone().normalized();
+        let desired = desired.clone().normalized();
+        let mut diff = SettingsDiff::default();
+
+        if let Some(desired_schema) = &desired.schema {
+            let current_schema = current.schema.as_ref();
+            let current_pk = current_schema.and_then(|s| s.primary_key.as_ref());
+            if current_pk != desired_schema.primary_key.as_ref() {
+                diff.push("schema.primary_key".to_string(), current_pk, desired_schema.primary_key.as_ref());
+            }
+
+            let current_fields: BTreeMap<&str, _> = current_schema
+                .map(|s| s.fields.iter().map(|f| (f.name.as_str(), f)).collect())
+                .unwrap_or_default();
+            let desired_fields: BTreeMap<&str, _> = desired_schema.fields
+                .iter()
+                .map(|f| (f.name.as_str(), f))
+                .collect();
+
+            for name in current_fields.keys().chain(desired_fields.keys()).collect::<std::collections::BTreeSet<_>>() {
+                let (c, d) = (current_fields.get(name), desired_fields.get(name));
+                if c != d {
+                    diff.push(format!("schema.fields.{}", name), c, d);
+                }
+            }
+        }
+
+        for term in current.synonyms.keys().chain(desired.synonyms.keys()).collect::<std::collections::BTreeSet<_>>() {
+            let (c, d) = (current.synonyms.get(term), desired.synonyms.get(term));
+            if c != d {
+                diff.push(format!("synonyms.{}", term), c, d);
+            }
+        }
+
+        if !desired.ranking_rules.is_empty() && current.ranking_rules != desired.ranking_rules {
+            diff.push("ranking_rules".to_string(), Some(&current.ranking_rules), Some(&desired.ranking_rules));
+        }
+
+        if current.stop_words != desired.stop_words {
+            diff.push("stop_words".to_string(), Some(&current.stop_words), Some(&desired.stop_words));
+        }
+
+        if desired.normalization.is_some() && current.normalization != desired.normalization {
+            diff.push("normalization".to_string(), current.normalization.as_ref(), desired.normalization.as_ref());
+        }
+
+        if desired.provider_settings.is_some() && current.provider_settings != desired.provider_settings {
+            diff.push("provider_settings".to_string(), current.provider_settings.as_ref(), desired.provider_settings.as_ref());
+        }
+
+        diff
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::SchemaBuilder;
+
+    #[test]
+    fn test_settings_roundtrip() {
+        let mut settings = IndexSettings {
+            schema: Some(SchemaBuilder::new().primary_key("id").text_field("title").build()),
+            ranking_rules: vec!["words".to_string(), "typo".to_string()],
+            stop_words: vec!["the".to_string(), "a".to_string()],
+            ..Default::default()
+        };
+        settings.synonyms.insert("phone".to_string(), vec!["mobile".to_string()]);
+
+        let json = settings.to_json().unwrap();
+        let parsed = IndexSettings::from_json(&json).unwrap();
+
+        assert_eq!(parsed, settings.normalized());
+    }
+
+    #[test]
+    fn test_settings_rejects_future_version() {
+        let json = r#"{"version": 99}"#;
+        assert!(IndexSettings::from_json(json).is_err());
+
+        let parsed = IndexSettings::from_json("{}").unwrap();
+        assert_eq!(parsed.version, SETTINGS_FORMAT_VERSION);
+    }
+
+    #[test]
+    fn test_settings_diff() {
+        let mut current = IndexSettings {
+            schema: Some(SchemaBuilder::new().primary_key("id").text_field("title").build()),
+            stop_words: vec!["the".to_string()],
+            ..Default::default()
+        };
+        current.synonyms.insert("phone".to_string(), vec!["mobile".to_string()]);
+
+        assert!(current.diff(&current).is_empty());
+
+        let mut desired = current.clone();
+        desired.schema = Some(SchemaBuilder::new().primary_key("id").text_field("title").keyword_field("tag").build());
+        desired.synonyms.clear();
+
+        let diff = current.diff(&desired);
+        let paths: Vec<&str> = diff.changes.iter().map(|c| c.path.as_str()).collect();
+        assert_eq!(paths, vec!["schema.fields.tag", "synonyms.phone"]);
+        assert!(diff.changes[1].desired.is_none());
+
+        // Normalization is only managed when the desired snapshot sets it
+        desired = current.clone();
+        current.normalization = Some(Normalization { fold_diacritics: true, ..Normalization::default() });
+        assert!(current.diff(&desired).is_empty());
+        desired.normalization = Some(Normalization::default());
+        assert_eq!(current.diff(&desired).changes[0].path, "normalization");
+    }
+}
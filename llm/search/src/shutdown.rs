@@ -0,0 +1,226 @@
+//! Draining a worker before it is suspended or redeployed
+//!
+//! Upserts held in the write buffer and writes recorded in the outbox only
+//! reach the backend on a later write or drain. A worker that is about to be
+//! suspended or replaced by a new deployment calls `flush` or `shutdown` so
+//! nothing is left behind: [`flush`] writes every buffered batch and drains
+//! the outbox, and `shutdown` also releases the scroll contexts that reads
+//! interrupted by an error left open on the backend. Scrolls are tracked here
+//! from the moment they are opened until they are cleared.
+//!
+//! Both keep going after a failure and report what stayed behind, rather than
+//! stopping at the first error, so a caller can decide whether to retry.
+
+use std::collections::BTreeSet;
+use std::fmt::Display;
+use std::future::Future;
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use crate::outbox::DrainReport;
+use crate::write_buffer;
+
+/// What a flush or shutdown wrote, released and left behind
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShutdownReport {
+    /// Buffered upserts written
+    pub flushed: usize,
+
+    /// Buffered upserts still waiting, after a failed flush
+    pub unflushed: usize,
+
+    /// Why the write buffer could not be flushed
+    pub flush_error: Option<String>,
+
+    pub outbox: DrainReport,
+
+    /// Scroll contexts released
+    pub scrolls_closed: usize,
+
+    /// Scroll contexts that could not be released; they expire on their own
+    pub scrolls_failed: usize,
+}
+
+impl ShutdownReport {
+    /// Whether nothing was left behind; dead letters are kept for inspection and do not count
+    pub fn is_complete(&self) -> bool {
+        let outbox_pending = self.outbox.remaining.saturating_sub(self.outbox.dead_lettered);
+        self.unflushed == 0 && self.flush_error.is_none() && outbox_pending == 0 && self.scrolls_failed == 0
+    }
+}
+
+fn open_scrolls() -> MutexGuard<'static, BTreeSet<(String, String)>> {
+    static SCROLLS: OnceLock<Mutex<BTreeSet<(String, String)>>> = OnceLock::new();
+    SCROLLS
+        .get_or_init(|| Mutex::new(BTreeSet::new()))
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Remember a scroll context opened on the backend of `provider`
+pub fn track_scroll(provider: &str, scroll_id: &str) {
+    open_scrolls().insert((provider.to_string(), scroll_id.to_string()));
+}
+
+/// Forget a scroll context that was cleared or replaced by the next page
+pub fn forget_scroll(provider: &str, scroll_id: &str) {
+    open_scrolls().remove(&(provider.to_string(), scroll_id.to_string()));
+}
+
+/// Scroll contexts of `provider` still open
+pub fn tracked_scrolls(provider: &str) -> Vec<String> {
+    open_scrolls()
+        .iter()
+        .filter(|(p, _)| p == provider)
+        .map(|(_, scroll_id)| scroll_id.clone())
+        .collect()
+}
+
+/// Write the whole write buffer, then drain the outbox
+///
+/// The outbox is drained even when the write buffer fails to flush.
+pub async fn flush<E: Display>(
+    flush_writes: impl Future<Output = Result<u32, E>>,
+    drain_outbox: impl Future<Output = DrainReport>,
+) -> ShutdownReport {
+    let mut report = ShutdownReport::default();
+    match flush_writes.await {
+        Ok(written) => report.flushed = written as usize,
+        Err(e) => {
+            warn!("Failed to flush the write buffer: {}", e);
+            report.flush_error = Some(e.to_string());
+        }
+    }
+    report.unflushed = write_buffer::pending();
+    report.outbox = drain_outbox.await;
+    report
+}
+
+/// Release every tracked scroll context of `provider` with `clear`, returning how many were and were not released
+pub async fn close_scrolls<F, Fut, E>(provider: &str, mut clear: F) -> (usize, usize)
+where
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = Result<(), E>>,
+    E: Display,
+{
+    let (mut closed, mut failed) = (0, 0);
+    for scroll_id in tracked_scrolls(provider) {
+        match clear(scroll_id.clone()).await {
+            Ok(()) => closed += 1,
+            Err(e) => {
+                warn!("Failed to clear {} scroll: {}", provider, e);
+                failed += 1;
+            }
+        }
+        // A scroll that failed to clear expires with its keep-alive; retrying it later gains nothing
+        forget_scroll(provider, &scroll_id);
+    }
+    (closed, failed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_an_empty_report_is_complete() {
+        assert!(ShutdownReport::default().is_complete());
+    }
+
+    #[test]
+    fn test_dead_letters_do_not_make_a_report_incomplete() {
+        let outbox = DrainReport { dead_lettered: 2, remaining: 2, ..DrainReport::default() };
+        assert!(ShutdownReport { outbox, ..ShutdownReport::default() }.is_complete());
+        let outbox = DrainReport { dead_lettered: 2, retrying: 1, remaining: 3, ..DrainReport::default() };
+        assert!(!ShutdownReport { outbox, ..ShutdownReport::default() }.is_complete());
+    }
+
+    #[test]
+    fn test_anything_left_behind_makes_a_report_incomplete() {
+        for report in [
+            ShutdownReport { unflushed: 1, ..ShutdownReport::default() },
+            ShutdownReport { flush_error: Some("bulk rejected".to_string()), ..ShutdownReport::default() },
+            ShutdownReport { scrolls_failed: 1, ..ShutdownReport::default() },
+        ] {
+            assert!(!report.is_complete(), "{:?}", report);
+        }
+        // Released scrolls and written upserts are not leftovers
+        assert!(ShutdownReport { flushed: 3, scrolls_closed: 2, ..ShutdownReport::default() }.is_complete());
+    }
+
+    #[test]
+    fn test_flush_drains_the_outbox_after_a_failed_write() {
+        let steps = RefCell::new(Vec::new());
+        let report = block_on(flush(
+            async {
+                steps.borrow_mut().push("flush");
+                Err::<u32, _>("bulk rejected")
+            },
+            async {
+                steps.borrow_mut().push("drain");
+                DrainReport { applied: 2, ..DrainReport::default() }
+            },
+        ));
+        assert_eq!(*steps.borrow(), ["flush", "drain"]);
+        assert_eq!(report.flush_error.as_deref(), Some("bulk rejected"));
+        assert_eq!((report.flushed, report.outbox.applied), (0, 2));
+        assert!(!report.is_complete());
+    }
+
+    #[test]
+    fn test_flush_counts_written_upserts() {
+        let report = block_on(flush(async { Ok::<_, String>(3) }, async { DrainReport::default() }));
+        assert_eq!(report, ShutdownReport { flushed: 3, ..ShutdownReport::default() });
+    }
+
+    #[test]
+    fn test_scrolls_are_tracked_once_per_provider() {
+        track_scroll("shutdown-track-a", "s2");
+        track_scroll("shutdown-track-a", "s1");
+        track_scroll("shutdown-track-a", "s1");
+        track_scroll("shutdown-track-b", "s1");
+        assert_eq!(tracked_scrolls("shutdown-track-a"), ["s1", "s2"]);
+
+        forget_scroll("shutdown-track-a", "s1");
+        forget_scroll("shutdown-track-a", "unknown");
+        assert_eq!(tracked_scrolls("shutdown-track-a"), ["s2"]);
+        assert_eq!(tracked_scrolls("shutdown-track-b"), ["s1"]);
+    }
+
+    #[test]
+    fn test_close_scrolls_only_touches_its_provider() {
+        track_scroll("shutdown-close", "a");
+        track_scroll("shutdown-close-other", "b");
+        let cleared = RefCell::new(Vec::new());
+        let result = block_on(close_scrolls("shutdown-close", |scroll_id| {
+            cleared.borrow_mut().push(scroll_id);
+            async { Ok::<_, String>(()) }
+        }));
+        assert_eq!(result, (1, 0));
+        assert_eq!(*cleared.borrow(), ["a"]);
+        assert_eq!(tracked_scrolls("shutdown-close-other"), ["b"]);
+    }
+
+    #[test]
+    fn test_scrolls_that_fail_to_clear_are_forgotten() {
+        track_scroll("shutdown-fail", "a");
+        track_scroll("shutdown-fail", "b");
+        let result = block_on(close_scrolls("shutdown-fail", |scroll_id| async move {
+            if scroll_id == "b" { Err("gone") } else { Ok(()) }
+        }));
+        assert_eq!(result, (1, 1));
+        assert!(tracked_scrolls("shutdown-fail").is_empty());
+    }
+
+    #[test]
+    fn test_closing_without_tracked_scrolls_clears_nothing() {
+        let mut calls = 0;
+        let result = block_on(close_scrolls("shutdown-none", |_| {
+            calls += 1;
+            async { Ok::<_, String>(()) }
+        }));
+        assert_eq!((result, calls), ((0, 0), 0));
+    }
+}
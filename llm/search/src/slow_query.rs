@@ -0,0 +1,333 @@
+//! Slow query log
+//!
+//! Searches that take longer than `SEARCH_SLOW_QUERY_MS` are logged at `warn`
+//! level and kept in a bounded in-memory ring buffer so they can be inspected
+//! later through `get_slow_queries()`. Request parameters are sanitized before
+//! being stored: credential-like keys are redacted and long strings truncated.
+
+use std::collections::VecDeque;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use log::warn;
+use crate::config::env_helpers::get_env_parsed;
+use crate::types::SearchQuery;
+
+/// Environment variable holding the slow query threshold in milliseconds
+pub const SLOW_QUERY_ENV: &str = "SEARCH_SLOW_QUERY_MS";
+
+/// Environment variable holding the ring buffer capacity
+pub const SLOW_QUERY_BUFFER_ENV: &str = "SEARCH_SLOW_QUERY_BUFFER";
+
+/// Default number of slow queries kept in memory
+pub const DEFAULT_SLOW_QUERY_CAPACITY: usize = 100;
+
+/// Strings longer than this are truncated in the log
+const MAX_LOGGED_STRING_LEN: usize = 256;
+
+/// Key fragments whose values are never stored
+const SENSITIVE_KEYS: &[&str] = &["key", "token", "secret", "password", "authorization"];
+
+/// A single recorded slow query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SlowQueryEntry {
+    /// Provider that executed the query
+    pub provider: String,
+
+    /// Index the query ran against
+    pub index: String,
+
+    /// Sanitized request parameters
+    pub params: Value,
+
+    /// Wall-clock duration in milliseconds
+    pub duration_ms: u64,
+
+    /// Time the query completed (RFC 3339)
+    pub timestamp: String,
+
+    /// Portable form of the query, without its tuning config, for replaying it elsewhere
+    #[serde(default)]
+    pub query: Option<SearchQuery>,
+}
+
+/// Bounded ring buffer of slow queries
+#[derive(Debug)]
+pub struct SlowQueryLog {
+    threshold: Option<Duration>,
+    capacity: usize,
+    entries: VecDeque<SlowQueryEntry>,
+}
+
+impl SlowQueryLog {
+    /// Create a log with an explicit threshold (`None` disables recording)
+    pub fn new(threshold: Option<Duration>, capacity: usize) -> Self {
+        Self {
+            threshold,
+            capacity: capacity.max(1),
+            entries: VecDeque::new(),
+        }
+    }
+
+    /// Create a log configured from `SEARCH_SLOW_QUERY_MS` / `SEARCH_SLOW_QUERY_BUFFER`
+    pub fn from_env() -> Self {
+        let threshold = get_env_parsed::<u64>(SLOW_QUERY_ENV)
+            .ok()
+            .flatten()
+            .map(Duration::from_millis);
+        let capacity = get_env_parsed::<usize>(SLOW_QUERY_BUFFER_ENV)
+            .ok()
+            .flatten()
+            .unwrap_or(DEFAULT_SLOW_QUERY_CAPACITY);
+        Self::new(threshold, capacity)
+    }
+
+    /// Record the query if it exceeded the threshold; returns true when recorded
+    pub fn record(&mut self, provider: &str, index: &str, params: &Value, elapsed: Duration) -> bool {
+        self.record_search(provider, index, params, None, elapsed)
+    }
+
+    /// Record the query along with its portable form, so `replay` can run it again
+    ///
+    /// The query's config is left out: provider parameters, cursors and
+    /// consistency tokens only mean something to the provider that ran it.
+    pub fn record_search(
+        &mut self,
+        provider: &str,
+        index: &str,
+        params: &Value,
+        query: Option<&SearchQuery>,
+        elapsed: Duration,
+    ) -> bool {
+        match self.threshold {
+            Some(threshold) if elapsed >= threshold => {}
+            _ => return false,
+        }
+
+        let entry = SlowQueryEntry {
+            provider: provider.to_string(),
+            index: index.to_string(),
+            params: sanitize_params(params),
+            duration_ms: elapsed.as_millis() as u64,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            query: query.map(|query| SearchQuery { config: None, ..query.clone() }),
+        };
+
+        warn!(
+            "Slow {} query on index {} took {} ms: {}",
+            entry.provider, entry.index, entry.duration_ms, entry.params
+        );
+
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+        true
+    }
+
+    /// Recorded entries, oldest first
+    pub fn entries(&self) -> Vec<SlowQueryEntry> {
+        self.entries.iter().cloned().collect()
+    }
+
+    /// Remove all recorded entries
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+fn global_log() -> &'static Mutex<SlowQueryLog> {
+    static LOG: OnceLock<Mutex<SlowQueryLog>> = OnceLock::new();
+    LOG.get_or_init(|| Mutex::new(SlowQueryLog::from_env()))
+}
+
+/// Record a query in the process-wide slow query log
+pub fn record_query(provider: &str, index: &str, params: &Value, elapsed: Duration) -> bool {
+    global_log()
+        .lock()
+        .map(|mut log| log.record(provider, index, params, elapsed))
+        .unwrap_or(false)
+}
+
+/// Record a query and its portable form in the process-wide slow query log
+pub fn record_search(provider: &str, index: &str, params: &Value, query: &SearchQuery, elapsed: Duration) -> bool {
+    global_log()
+        .lock()
+        .map(|mut log| log.record_search(provider, index, params, Some(query), elapsed))
+        .unwrap_or(false)
+}
+
+/// Slow queries recorded in this worker, oldest first
+pub fn get_slow_queries() -> Vec<SlowQueryEntry> {
+    global_log()
+        .lock()
+        .map(|log| log.entries())
+        .unwrap_or_default()
+}
+
+/// Clear the process-wide slow query log
+pub fn clear_slow_queries() {
+    if let Ok(mut log) = global_log().lock() {
+        log.clear();
+    }
+}
+
+/// Redact credential-like keys and truncate long strings
+pub fn sanitize_params(params: &Value) -> Value {
+    match params {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(k, v)| {
+                    let lower = k.to_lowercase();
+                    if SENSITIVE_KEYS.iter().any(|s| lower.contains(s)) {
+                        (k.clone(), Value::String("[redacted]".to_string()))
+                    } else {
+                        (k.clone(), sanitize_params(v))
+                    }
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(sanitize_params).collect()),
+        Value::String(s) if s.chars().count() > MAX_LOGGED_STRING_LEN => {
+            let truncated: String = s.chars().take(MAX_LOGGED_STRING_LEN).collect();
+            Value::String(format!("{}...", truncated))
+        }
+        other => other.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::types::{QueryBuilder, SearchConfig};
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    fn indexes(log: &SlowQueryLog) -> Vec<String> {
+        log.entries().into_iter().map(|e| e.index).collect()
+    }
+
+    #[test]
+    fn test_queries_at_the_threshold_are_recorded() {
+        let mut log = SlowQueryLog::new(Some(ms(100)), 10);
+        assert!(!log.record("test", "fast", &json!({}), ms(99)));
+        assert!(log.record("test", "slow", &json!({}), ms(100)));
+        assert_eq!(indexes(&log), ["slow"]);
+    }
+
+    #[test]
+    fn test_without_a_threshold_nothing_is_recorded() {
+        let mut log = SlowQueryLog::new(None, 10);
+        assert!(!log.record("test", "idx", &json!({}), Duration::from_secs(3600)));
+        assert!(log.entries().is_empty());
+    }
+
+    #[test]
+    fn test_a_zero_threshold_records_everything() {
+        let mut log = SlowQueryLog::new(Some(Duration::ZERO), 10);
+        assert!(log.record("test", "idx", &json!({}), Duration::ZERO));
+    }
+
+    #[test]
+    fn test_the_oldest_entries_are_evicted() {
+        let mut log = SlowQueryLog::new(Some(ms(1)), 2);
+        for index in ["a", "b", "c"] {
+            log.record("test", index, &json!({}), ms(5));
+        }
+        assert_eq!(indexes(&log), ["b", "c"]);
+    }
+
+    #[test]
+    fn test_zero_capacity_keeps_the_latest_entry() {
+        let mut log = SlowQueryLog::new(Some(ms(1)), 0);
+        log.record("test", "a", &json!({}), ms(5));
+        log.record("test", "b", &json!({}), ms(5));
+        assert_eq!(indexes(&log), ["b"]);
+    }
+
+    #[test]
+    fn test_entries_hold_sanitized_params_and_whole_milliseconds() {
+        let mut log = SlowQueryLog::new(Some(ms(1)), 10);
+        log.record("typesense", "books", &json!({ "q": "dune", "api_key": "abc" }), Duration::from_micros(1_999));
+        let entry = &log.entries()[0];
+        assert_eq!((entry.provider.as_str(), entry.index.as_str(), entry.duration_ms), ("typesense", "books", 1));
+        assert_eq!(entry.params, json!({ "q": "dune", "api_key": "[redacted]" }));
+        assert!(chrono::DateTime::parse_from_rfc3339(&entry.timestamp).is_ok());
+        assert!(entry.query.is_none());
+    }
+
+    #[test]
+    fn test_recorded_searches_drop_their_config() {
+        let mut log = SlowQueryLog::new(Some(ms(1)), 10);
+        let config = SearchConfig { provider_params: Some(r#"{"routing": "acme"}"#.to_string()), ..SearchConfig::default() };
+        let query = SearchQuery { config: Some(config), ..QueryBuilder::new().query("dune").build() };
+        assert!(log.record_search("test", "books", &json!({}), Some(&query), ms(5)));
+
+        let stored = log.entries().remove(0).query.unwrap();
+        assert_eq!(stored.q.as_deref(), Some("dune"));
+        assert!(stored.config.is_none());
+    }
+
+    #[test]
+    fn test_clear_empties_the_log() {
+        let mut log = SlowQueryLog::new(Some(ms(1)), 10);
+        log.record("test", "a", &json!({}), ms(5));
+        log.clear();
+        assert!(log.entries().is_empty());
+        assert!(log.record("test", "b", &json!({}), ms(5)));
+    }
+
+    #[test]
+    fn test_sensitive_keys_match_any_case_and_position() {
+        let sanitized = sanitize_params(&json!({
+            "Authorization": "Bearer abc",
+            "X-API-KEY": "abc",
+            "session_token": { "value": "abc" },
+            "db_password": ["abc"],
+            "q": "secret plans"
+        }));
+        assert_eq!(
+            sanitized,
+            json!({
+                "Authorization": "[redacted]",
+                "X-API-KEY": "[redacted]",
+                "session_token": "[redacted]",
+                "db_password": "[redacted]",
+                "q": "secret plans"
+            })
+        );
+    }
+
+    #[test]
+    fn test_sanitizing_reaches_into_arrays() {
+        let sanitized = sanitize_params(&json!({ "filters": [{ "secret": "abc", "field": "tag" }, 3, null] }));
+        assert_eq!(sanitized, json!({ "filters": [{ "secret": "[redacted]", "field": "tag" }, 3, null] }));
+    }
+
+    #[test]
+    fn test_long_strings_are_truncated_by_characters() {
+        let limit = "x".repeat(MAX_LOGGED_STRING_LEN);
+        assert_eq!(sanitize_params(&json!(limit)), json!(limit));
+
+        let long = "é".repeat(MAX_LOGGED_STRING_LEN + 1);
+        let truncated = sanitize_params(&json!(long));
+        assert_eq!(truncated, json!(format!("{}...", "é".repeat(MAX_LOGGED_STRING_LEN))));
+    }
+
+    #[test]
+    fn test_entries_without_a_query_deserialize() {
+        let entry: SlowQueryEntry = serde_json::from_value(json!({
+            "provider": "test",
+            "index": "books",
+            "params": {},
+            "duration_ms": 120,
+            "timestamp": "2024-01-01T00:00:00+00:00"
+        }))
+        .unwrap();
+        assert!(entry.query.is_none());
+    }
+}
@@ -0,0 +1,304 @@
+//! Index snapshots
+//!
+//! `create_snapshot` backs up one index and returns a [`SnapshotInfo`] whose
+//! `id` is what `restore_snapshot` takes later. How the backup is taken
+//! depends on the provider:
+//!
+//! - Elasticsearch and OpenSearch snapshot the index into the repository
+//!   named by `SEARCH_SNAPSHOT_REPOSITORY`, which must already be registered
+//!   on the cluster.
+//! - Meilisearch creates a dump. Dumps cover the whole instance and are
+//!   imported when Meilisearch starts, so they cannot be restored through the
+//!   API.
+//! - Typesense and Algolia have no backup API, so the index settings and every
+//!   document are written as NDJSON to a [`BlobSink`]: a header line, then one
+//!   document per line. The whole index passes through the worker's memory.
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::blob::BlobSink;
+use crate::compression::Compression;
+use crate::error::{SearchError, SearchResult};
+use crate::ndjson::NdjsonWriter;
+use crate::telemetry::SpanOutput;
+
+/// Environment variable holding the snapshot repository Elasticsearch and OpenSearch snapshot into
+pub const SNAPSHOT_REPOSITORY_ENV: &str = "SEARCH_SNAPSHOT_REPOSITORY";
+
+/// Documents written per request when restoring an NDJSON snapshot
+pub const RESTORE_BATCH_SIZE: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SnapshotKind {
+    /// Native snapshot in a cluster snapshot repository
+    Repository,
+
+    /// Meilisearch dump of the whole instance
+    Dump,
+
+    /// NDJSON blob written by the worker
+    Ndjson,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotInfo {
+    /// Passed to `restore_snapshot`
+    pub id: String,
+    pub index: String,
+    pub provider: String,
+    pub kind: SnapshotKind,
+
+    /// Repository, dump file or blob the snapshot is stored in
+    pub location: Option<String>,
+    pub documents: Option<u64>,
+
+    /// RFC 3339 timestamp
+    pub created_at: String,
+}
+
+impl SnapshotInfo {
+    /// A snapshot taken now
+    pub fn new(id: String, index: &str, provider: &str, kind: SnapshotKind) -> Self {
+        Self {
+            id,
+            index: index.to_string(),
+            provider: provider.to_string(),
+            kind,
+            location: None,
+            documents: None,
+            created_at: Utc::now().to_rfc3339(),
+        }
+    }
+}
+
+impl SpanOutput for SnapshotInfo {}
+
+/// First line of an NDJSON snapshot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotHeader {
+    pub id: String,
+    pub index: String,
+    pub provider: String,
+    pub created_at: String,
+    pub documents: u64,
+
+    /// Provider settings or schema needed to recreate the index
+    pub settings: Value,
+}
+
+impl SnapshotHeader {
+    pub fn info(&self, location: Option<String>) -> SnapshotInfo {
+        SnapshotInfo {
+            id: self.id.clone(),
+            index: self.index.clone(),
+            provider: self.provider.clone(),
+            kind: SnapshotKind::Ndjson,
+            location,
+            documents: Some(self.documents),
+            created_at: self.created_at.clone(),
+        }
+    }
+}
+
+/// Repository from `SEARCH_SNAPSHOT_REPOSITORY`
+pub fn repository_from_env() -> SearchResult<String> {
+    std::env::var(SNAPSHOT_REPOSITORY_ENV).ok().filter(|repository| !repository.is_empty()).ok_or_else(|| {
+        SearchError::invalid_query(format!(
+            "Set {} to a snapshot repository registered on the cluster",
+            SNAPSHOT_REPOSITORY_ENV
+        ))
+    })
+}
+
+/// A new snapshot ID for `index`: its name, lowercased as snapshot names must be, and a timestamp
+pub fn new_snapshot_id(index: &str) -> String {
+    let name: String = index
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c.to_ascii_lowercase() } else { '-' })
+        .collect();
+    format!("{}-{}", name.trim_start_matches(['-', '_']), Utc::now().format("%Y%m%d%H%M%S%3f"))
+}
+
+fn blob_key(id: &str) -> String {
+    format!("{}.ndjson", id)
+}
+
+/// Write a header line and one line per document to `sink`
+pub fn write_ndjson(
+    sink: &dyn BlobSink,
+    index: &str,
+    provider: &str,
+    settings: Value,
+    documents: &[Value],
+) -> SearchResult<SnapshotInfo> {
+    let header = SnapshotHeader {
+        id: new_snapshot_id(index),
+        index: index.to_string(),
+        provider: provider.to_string(),
+        created_at: Utc::now().to_rfc3339(),
+        documents: documents.len() as u64,
+        settings,
+    };
+
+    let mut writer = NdjsonWriter::new(Compression::None);
+    writer.push(&header)?;
+    writer.extend(documents)?;
+    let location = sink.put(&blob_key(&header.id), &writer.finish()?.bytes)?;
+    Ok(header.info(Some(location)))
+}
+
+/// Read a snapshot written by [`write_ndjson`] for `provider`
+pub fn read_ndjson(sink: &dyn BlobSink, id: &str, provider: &str) -> SearchResult<(SnapshotHeader, Vec<Value>)> {
+    let bytes = sink.get(&blob_key(id))?;
+    let text = String::from_utf8(bytes)
+        .map_err(|e| SearchError::invalid_query(format!("Snapshot {} is not UTF-8: {}", id, e)))?;
+    let mut lines = text.lines().filter(|line| !line.trim().is_empty());
+
+    let header: SnapshotHeader = serde_json::from_str(lines.next().unwrap_or_default())
+        .map_err(|e| SearchError::invalid_query(format!("Snapshot {} has no valid header: {}", id, e)))?;
+    if header.provider != provider {
+        return Err(SearchError::invalid_query(format!(
+            "Snapshot {} was taken from {}, not {}",
+            id, header.provider, provider
+        )));
+    }
+
+    let documents = lines
+        .enumerate()
+        .map(|(line, text)| {
+            serde_json::from_str(text).map_err(|e| {
+                SearchError::invalid_query(format!("Snapshot {} has an invalid document on line {}: {}", id, line + 2, e))
+            })
+        })
+        .collect::<SearchResult<Vec<Value>>>()?;
+    if documents.len() as u64 != header.documents {
+        return Err(SearchError::invalid_query(format!(
+            "Snapshot {} is incomplete: {} of {} documents",
+            id,
+            documents.len(),
+            header.documents
+        )));
+    }
+    Ok((header, documents))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+    use crate::blob::FsBlobSink;
+    use serde_json::json;
+
+    #[derive(Default)]
+    struct MemorySink(RefCell<HashMap<String, Vec<u8>>>);
+
+    impl BlobSink for MemorySink {
+        fn put(&self, key: &str, bytes: &[u8]) -> SearchResult<String> {
+            self.0.borrow_mut().insert(key.to_string(), bytes.to_vec());
+            Ok(format!("memory://{}", key))
+        }
+
+        fn get(&self, key: &str) -> SearchResult<Vec<u8>> {
+            self.0.borrow().get(key).cloned().ok_or_else(|| SearchError::invalid_query(format!("No blob '{}'", key)))
+        }
+    }
+
+    fn header(documents: u64) -> String {
+        json!({
+            "id": "books-1",
+            "index": "books",
+            "provider": "typesense",
+            "created_at": "2025-01-01T00:00:00Z",
+            "documents": documents,
+            "settings": {}
+        })
+        .to_string()
+    }
+
+    fn read_text(text: &str) -> SearchResult<(SnapshotHeader, Vec<Value>)> {
+        let sink = MemorySink::default();
+        sink.put("books-1.ndjson", text.as_bytes()).unwrap();
+        read_ndjson(&sink, "books-1", "typesense")
+    }
+
+    fn message(result: SearchResult<(SnapshotHeader, Vec<Value>)>) -> String {
+        match result {
+            Err(SearchError::InvalidQuery(message)) => message,
+            other => panic!("expected an invalid query error, got {:?}", other.map(|(header, _)| header)),
+        }
+    }
+
+    #[test]
+    fn test_snapshot_ids_are_lowercase_names_with_a_timestamp() {
+        let id = new_snapshot_id("Products/EU");
+        assert!(id.starts_with("products-eu-"));
+        assert!(id.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-'));
+        assert!(new_snapshot_id("_logs").starts_with("logs-"));
+        assert!(new_snapshot_id("--Café").starts_with("caf--"));
+        assert_eq!(new_snapshot_id("books").len(), "books-".len() + 17);
+    }
+
+    #[test]
+    fn test_ndjson_round_trip_through_the_filesystem() {
+        let root = std::env::temp_dir().join(format!("golem-search-snapshot-{}", uuid::Uuid::new_v4()));
+        let sink = FsBlobSink::new(&root);
+        let documents = vec![json!({ "id": "1", "title": "Dune" }), json!({ "id": "2", "title": "Emma\nWoodhouse" })];
+
+        let info = write_ndjson(&sink, "books", "typesense", json!({ "fields": [] }), &documents).unwrap();
+        assert_eq!((info.kind, info.documents), (SnapshotKind::Ndjson, Some(2)));
+        assert!(info.location.unwrap().ends_with(&format!("{}.ndjson", info.id)));
+
+        let (header, restored) = read_ndjson(&sink, &info.id, "typesense").unwrap();
+        assert_eq!((header.index.as_str(), header.settings), ("books", json!({ "fields": [] })));
+        assert_eq!(restored, documents);
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_empty_indexes_round_trip() {
+        let sink = MemorySink::default();
+        let info = write_ndjson(&sink, "empty", "algolia", Value::Null, &[]).unwrap();
+        let (header, documents) = read_ndjson(&sink, &info.id, "algolia").unwrap();
+        assert_eq!(header.documents, 0);
+        assert!(documents.is_empty());
+    }
+
+    #[test]
+    fn test_snapshots_of_another_provider_are_refused() {
+        let sink = MemorySink::default();
+        let info = write_ndjson(&sink, "books", "typesense", Value::Null, &[]).unwrap();
+        let result = read_ndjson(&sink, &info.id, "algolia");
+        assert!(message(result).contains("taken from typesense, not algolia"));
+    }
+
+    #[test]
+    fn test_truncated_or_padded_snapshots_are_refused() {
+        let document = r#"{"id":"1"}"#;
+        assert!(message(read_text(&format!("{}\n{}", header(2), document))).contains("incomplete: 1 of 2"));
+        assert!(message(read_text(&format!("{}\n{}\n{}", header(1), document, document))).contains("2 of 1"));
+    }
+
+    #[test]
+    fn test_invalid_lines_are_reported_with_their_line_number() {
+        assert!(message(read_text(&format!("{}\n{{\"id\":\"1\"}}\n{{oops", header(2)))).contains("line 3"));
+        assert!(message(read_text("")).contains("no valid header"));
+        assert!(message(read_text("{\"id\":\"1\"}")).contains("no valid header"));
+    }
+
+    #[test]
+    fn test_blank_lines_are_ignored() {
+        let (_, documents) = read_text(&format!("\n{}\n\n{{\"id\":\"1\"}}\n  \n", header(1))).unwrap();
+        assert_eq!(documents, [json!({ "id": "1" })]);
+    }
+
+    #[test]
+    fn test_unreadable_blobs_are_refused() {
+        let sink = MemorySink::default();
+        sink.put("books-1.ndjson", &[0xff, 0xfe]).unwrap();
+        assert!(message(read_ndjson(&sink, "books-1", "typesense")).contains("not UTF-8"));
+        assert!(read_ndjson(&sink, "missing", "typesense").is_err());
+    }
+}
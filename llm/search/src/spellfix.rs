@@ -0,0 +1,278 @@
+//! Spell-fix auto-apply
+//!
+//! A misspelled query, such as "rde shoes", finds few documents or none. With
+//! [`CONFIDENCE_ENV`] set, the rescue pipeline (see [`crate::rescue`]) looks
+//! the text up in the suggestions index of the searched index, allowing typos.
+//! The closest suggestion is the correction, with the similarity of the two
+//! texts as its confidence. When the confidence reaches the threshold, the
+//! corrected query is searched too, and its results replace the original ones
+//! when they have [`HIT_RATIO_ENV`] times as many hits. The results then name
+//! the original and the corrected query, for a "showing results for…" line.
+//!
+//! Only queries finding at most [`MAX_HITS_ENV`] hits are looked up, so well
+//! spelled queries cost one search as before. A text that is a suggestion
+//! itself is never corrected. Without a suggestions index (see
+//! [`crate::suggestions`]) nothing is corrected.
+
+use crate::config::env_helpers::get_env_parsed;
+use crate::query::Fuzziness;
+use crate::suggestions::{self, Suggestion};
+use crate::types::{SearchConfig, SearchQuery};
+
+/// Environment variable with the confidence, from 0 to 1, a correction needs; spell-fix is off without it
+pub const CONFIDENCE_ENV: &str = "SEARCH_SPELLFIX_MIN_CONFIDENCE";
+
+/// Environment variable with how many times the hits of the original query a correction has to find
+pub const HIT_RATIO_ENV: &str = "SEARCH_SPELLFIX_MIN_HIT_RATIO";
+
+/// Environment variable with the most hits a query may find and still be looked up
+pub const MAX_HITS_ENV: &str = "SEARCH_SPELLFIX_MAX_HITS";
+
+/// Suggestions compared with a query text
+pub const LOOKUP_LIMIT: u32 = 5;
+
+const DEFAULT_HIT_RATIO: f64 = 2.0;
+const DEFAULT_MAX_HITS: u64 = 10;
+
+/// When a correction is applied
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpellFixConfig {
+    pub min_confidence: f64,
+    pub min_hit_ratio: f64,
+    pub max_hits: u64,
+}
+
+impl SpellFixConfig {
+    /// Configuration with the default hit ratio and hit limit
+    pub fn new(min_confidence: f64) -> Self {
+        Self {
+            min_confidence: min_confidence.clamp(0.0, 1.0),
+            min_hit_ratio: DEFAULT_HIT_RATIO,
+            max_hits: DEFAULT_MAX_HITS,
+        }
+    }
+
+    /// Configuration from the environment; `None` without `SEARCH_SPELLFIX_MIN_CONFIDENCE`
+    pub fn from_env() -> Option<Self> {
+        let mut config = Self::new(get_env_parsed::<f64>(CONFIDENCE_ENV).ok().flatten()?);
+        if let Some(ratio) = get_env_parsed::<f64>(HIT_RATIO_ENV).ok().flatten() {
+            config.min_hit_ratio = ratio.max(1.0);
+        }
+        if let Some(max_hits) = get_env_parsed::<u64>(MAX_HITS_ENV).ok().flatten() {
+            config.max_hits = max_hits;
+        }
+        Some(config)
+    }
+
+    /// Whether a query finding `hits` is looked up
+    pub fn checks(&self, hits: u64) -> bool {
+        hits <= self.max_hits
+    }
+
+    /// Whether a correction finding `corrected` hits replaces a query finding `original`
+    pub fn improves(&self, original: u64, corrected: u64) -> bool {
+        corrected > original && corrected as f64 >= original as f64 * self.min_hit_ratio
+    }
+}
+
+/// A query text and the suggestion it was corrected to
+#[derive(Debug, Clone, PartialEq)]
+pub struct Correction {
+    pub original: String,
+    pub corrected: String,
+
+    /// Similarity of the two texts, from 0 to 1
+    pub confidence: f64,
+}
+
+/// Search of a suggestions index for suggestions close to `text`, typos allowed
+pub fn lookup_query(text: &str) -> SearchQuery {
+    SearchQuery {
+        q: Some(suggestions::prefix_text(text)),
+        filters: Vec::new(),
+        sort: Vec::new(),
+        facets: Vec::new(),
+        page: None,
+        per_page: Some(LOOKUP_LIMIT),
+        offset: None,
+        highlight: None,
+        config: Some(SearchConfig { fuzziness: Some(Fuzziness::Auto), ..SearchConfig::default() }),
+    }
+}
+
+/// The correction of `text` among `candidates`, if one is confident enough
+///
+/// The most similar candidate wins, the most popular one on a tie.
+pub fn best_correction(text: &str, candidates: &[Suggestion], min_confidence: f64) -> Option<Correction> {
+    let original = suggestions::prefix_text(text);
+    if original.is_empty() {
+        return None;
+    }
+    let candidates: Vec<(String, u64)> = candidates
+        .iter()
+        .map(|candidate| (suggestions::prefix_text(&candidate.query), candidate.popularity))
+        .collect();
+    if candidates.iter().any(|(candidate, _)| *candidate == original) {
+        return None;
+    }
+    candidates
+        .into_iter()
+        .filter(|(candidate, _)| !candidate.is_empty())
+        .map(|(candidate, popularity)| (similarity(&original, &candidate), popularity, candidate))
+        .filter(|(confidence, _, _)| *confidence >= min_confidence)
+        .max_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)))
+        .map(|(confidence, _, corrected)| Correction { original: text.to_string(), corrected, confidence })
+}
+
+/// Similarity of two texts: one less the share of characters edited to turn one into the other
+///
+/// Swapping two neighbouring characters counts as one edit, as it is the most
+/// common typo.
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 1.0;
+    }
+    1.0 - edit_distance(&a, &b) as f64 / longest as f64
+}
+
+/// Optimal string alignment distance
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let width = b.len() + 1;
+    let mut distances: Vec<usize> = vec![0; (a.len() + 1) * width];
+    for i in 0..=a.len() {
+        for j in 0..=b.len() {
+            distances[i * width + j] = if i == 0 || j == 0 {
+                i + j
+            } else {
+                let substitution = distances[(i - 1) * width + j - 1] + usize::from(a[i - 1] != b[j - 1]);
+                let mut distance = substitution
+                    .min(distances[(i - 1) * width + j] + 1)
+                    .min(distances[i * width + j - 1] + 1);
+                if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                    distance = distance.min(distances[(i - 2) * width + j - 2] + 1);
+                }
+                distance
+            };
+        }
+    }
+    distances[a.len() * width + b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn suggestion(query: &str, popularity: u64) -> Suggestion {
+        Suggestion { query: query.to_string(), popularity }
+    }
+
+    #[test]
+    fn test_a_swap_of_neighbours_is_one_edit() {
+        assert_eq!(similarity("rde shoes", "red shoes"), 1.0 - 1.0 / 9.0);
+        assert_eq!(similarity("red shoes", "rde shoes"), similarity("rde shoes", "red shoes"));
+    }
+
+    #[test]
+    fn test_swapped_characters_are_not_edited_again() {
+        // Optimal string alignment: "ca" to "abc" takes three edits, not two
+        assert_eq!(similarity("ca", "abc"), 0.0);
+    }
+
+    #[test]
+    fn test_similarity_bounds() {
+        assert_eq!(similarity("", ""), 1.0);
+        assert_eq!(similarity("shoes", ""), 0.0);
+        assert_eq!(similarity("abc", "xyz"), 0.0);
+        assert_eq!(similarity("shoes", "shoes"), 1.0);
+    }
+
+    #[test]
+    fn test_similarity_counts_characters_not_bytes() {
+        assert_eq!(similarity("café", "cafe"), 0.75);
+    }
+
+    #[test]
+    fn test_the_most_similar_suggestion_is_the_correction() {
+        let candidates = [suggestion("red shorts", 50), suggestion("rose shoes", 90), suggestion("Red Shoes", 10)];
+        let correction = best_correction("Rde Shoes!", &candidates, 0.8).unwrap();
+        assert_eq!(
+            correction,
+            Correction { original: "Rde Shoes!".to_string(), corrected: "red shoes".to_string(), confidence: 1.0 - 1.0 / 9.0 }
+        );
+    }
+
+    #[test]
+    fn test_ties_go_to_the_more_popular_suggestion() {
+        let candidates = [suggestion("cat", 5), suggestion("car", 50), suggestion("cab", 20)];
+        assert_eq!(best_correction("cax", &candidates, 0.5).unwrap().corrected, "car");
+    }
+
+    #[test]
+    fn test_confidence_at_the_threshold_is_enough() {
+        let candidates = [suggestion("red shoes", 1)];
+        let confidence = similarity("rde shoes", "red shoes");
+        assert!(best_correction("rde shoes", &candidates, confidence).is_some());
+        assert!(best_correction("rde shoes", &candidates, confidence + 1e-9).is_none());
+    }
+
+    #[test]
+    fn test_texts_matching_a_suggestion_are_left_alone() {
+        // Normalized, "Red-Shoes" is the suggestion itself, however close another one is
+        let candidates = [suggestion("red shoes", 1), suggestion("red shoe", 100)];
+        assert_eq!(best_correction("Red-Shoes", &candidates, 0.0), None);
+    }
+
+    #[test]
+    fn test_texts_without_words_are_not_corrected() {
+        assert_eq!(best_correction(" ?! ", &[suggestion("red shoes", 1)], 0.0), None);
+        assert_eq!(best_correction("rde shoes", &[], 0.0), None);
+    }
+
+    #[test]
+    fn test_suggestions_without_words_are_skipped() {
+        assert_eq!(best_correction("x", &[suggestion("--", 100)], 0.0), None);
+    }
+
+    #[test]
+    fn test_lookup_allows_typos_among_a_few_suggestions() {
+        let query = lookup_query("  Rde SHOES ");
+        assert_eq!(query.q.as_deref(), Some("rde shoes"));
+        assert_eq!(query.per_page, Some(LOOKUP_LIMIT));
+        assert_eq!(query.config.unwrap().fuzziness, Some(Fuzziness::Auto));
+    }
+
+    #[test]
+    fn test_confidence_is_clamped() {
+        assert_eq!(SpellFixConfig::new(1.5).min_confidence, 1.0);
+        assert_eq!(SpellFixConfig::new(-0.5).min_confidence, 0.0);
+        let config = SpellFixConfig::new(0.8);
+        assert_eq!((config.min_hit_ratio, config.max_hits), (DEFAULT_HIT_RATIO, DEFAULT_MAX_HITS));
+    }
+
+    #[test]
+    fn test_only_queries_with_few_hits_are_checked() {
+        let config = SpellFixConfig::new(0.8);
+        assert!(config.checks(0));
+        assert!(config.checks(DEFAULT_MAX_HITS));
+        assert!(!config.checks(DEFAULT_MAX_HITS + 1));
+    }
+
+    #[test]
+    fn test_corrections_need_the_hit_ratio() {
+        let config = SpellFixConfig::new(0.8);
+        assert!(config.improves(0, 1));
+        assert!(config.improves(3, 6));
+        assert!(!config.improves(3, 5));
+        assert!(!config.improves(0, 0));
+    }
+
+    #[test]
+    fn test_a_ratio_of_one_still_needs_more_hits() {
+        let config = SpellFixConfig { min_hit_ratio: 1.0, ..SpellFixConfig::new(0.8) };
+        assert!(config.improves(3, 4));
+        assert!(!config.improves(3, 3));
+    }
+}
@@ -0,0 +1,376 @@
+//! Query suggestions
+//!
+//! Algolia Query Suggestions keeps a second index, `<index>_query_suggestions`,
+//! holding one record per popular query, and autocomplete searches that index
+//! instead of the documents. This module builds the same index for providers
+//! without such a product.
+//!
+//! A [`SuggestionsBuilder`] collects candidates from search analytics, as
+//! [`PopularQuery`] counts, and from a title field of the source index.
+//! Queries that found nothing or were searched fewer than
+//! [`SuggestionsConfig::min_count`] times are left out; titles are always
+//! kept. The provider writes the records with its delta sync, so suggestions
+//! that fell out of the set are deleted and unchanged ones are not rewritten.
+//! `suggest` then prefix-matches the typed text against the suggestions,
+//! most popular first.
+
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use crate::checksum::checksum;
+use crate::error::{SearchError, SearchResult};
+use crate::types::{Doc, Schema, SchemaBuilder, SearchQuery, SearchResults};
+
+/// Appended to an index name to name its suggestions index, as Algolia does
+pub const SUGGESTIONS_SUFFIX: &str = "_query_suggestions";
+
+/// Name of the suggestions index of `index`
+pub fn suggestions_index(index: &str) -> String {
+    format!("{}{}", index, SUGGESTIONS_SUFFIX)
+}
+
+/// A query users searched, from analytics
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PopularQuery {
+    pub query: String,
+
+    /// Times it was searched
+    pub count: u64,
+
+    /// Matches it found, when known; queries that found nothing are not suggested
+    #[serde(default)]
+    pub hits: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SuggestionsConfig {
+    /// Field of the source index whose values are suggested as well
+    pub title_field: Option<String>,
+
+    /// Searches a query needs before it is suggested
+    pub min_count: u64,
+
+    /// Letters and digits a suggestion needs
+    pub min_letters: usize,
+
+    /// Suggestions kept, most popular first
+    pub max_suggestions: usize,
+
+    /// Words that keep a suggestion out, compared ignoring case
+    pub banned_words: Vec<String>,
+}
+
+impl Default for SuggestionsConfig {
+    fn default() -> Self {
+        Self {
+            title_field: None,
+            min_count: 2,
+            min_letters: 3,
+            max_suggestions: 1000,
+            banned_words: Vec::new(),
+        }
+    }
+}
+
+impl SuggestionsConfig {
+    /// Parse a JSON config; missing or blank input gives the defaults
+    pub fn from_json(json: Option<&str>) -> SearchResult<Self> {
+        match json.map(str::trim).filter(|j| !j.is_empty()) {
+            Some(json) => serde_json::from_str(json)
+                .map_err(|e| SearchError::invalid_query(format!("Invalid suggestions config: {}", e))),
+            None => Ok(Self::default()),
+        }
+    }
+}
+
+/// Parse popular queries passed as a JSON list
+pub fn parse_popular_queries(json: &str) -> SearchResult<Vec<PopularQuery>> {
+    serde_json::from_str(json).map_err(|e| SearchError::invalid_query(format!("Invalid popular queries: {}", e)))
+}
+
+/// A suggestion returned to autocomplete
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Suggestion {
+    pub query: String,
+    pub popularity: u64,
+}
+
+/// Lowercased words of `text`, keeping only letters and digits
+fn normalize(text: &str) -> String {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[derive(Debug, Default)]
+struct Candidate {
+    searches: u64,
+    titles: u64,
+}
+
+/// Collects suggestion candidates and turns them into suggestion records
+#[derive(Debug)]
+pub struct SuggestionsBuilder {
+    config: SuggestionsConfig,
+    candidates: HashMap<String, Candidate>,
+}
+
+impl SuggestionsBuilder {
+    pub fn new(config: SuggestionsConfig) -> Self {
+        Self { config, candidates: HashMap::new() }
+    }
+
+    /// Add analytics counts; queries differing only in case or punctuation are merged
+    pub fn add_queries(&mut self, queries: &[PopularQuery]) {
+        for query in queries.iter().filter(|q| q.hits != Some(0)) {
+            self.candidates.entry(normalize(&query.query)).or_default().searches += query.count;
+        }
+    }
+
+    /// Add a document title; each document holding it adds one to its popularity
+    pub fn add_title(&mut self, title: &str) {
+        self.candidates.entry(normalize(title)).or_default().titles += 1;
+    }
+
+    fn is_allowed(&self, text: &str, candidate: &Candidate) -> bool {
+        let letters = text.chars().filter(|c| c.is_alphanumeric()).count();
+        let banned = text.split(' ').any(|word| self.config.banned_words.iter().any(|b| b.eq_ignore_ascii_case(word)));
+        letters >= self.config.min_letters
+            && !banned
+            && (candidate.titles > 0 || candidate.searches >= self.config.min_count)
+    }
+
+    /// The suggestions kept, most popular first
+    pub fn suggestions(&self) -> Vec<Suggestion> {
+        let mut suggestions: Vec<Suggestion> = self
+            .candidates
+            .iter()
+            .filter(|(text, candidate)| self.is_allowed(text, candidate))
+            .map(|(text, candidate)| Suggestion { query: text.clone(), popularity: candidate.searches + candidate.titles })
+            .collect();
+        suggestions.sort_by(|a, b| b.popularity.cmp(&a.popularity).then_with(|| a.query.cmp(&b.query)));
+        suggestions.truncate(self.config.max_suggestions);
+        suggestions
+    }
+
+    /// Records of the suggestions index, keyed by a hash of the suggestion
+    pub fn documents(&self) -> Vec<Doc> {
+        self.suggestions()
+            .into_iter()
+            .map(|suggestion| {
+                let id = checksum(&suggestion.query).iter().map(|b| format!("{:02x}", b)).collect();
+                let nb_words = suggestion.query.split(' ').count();
+                let content = json!({ "query": suggestion.query, "popularity": suggestion.popularity, "nb_words": nb_words });
+                Doc { id, content: content.to_string() }
+            })
+            .collect()
+    }
+}
+
+/// Schema of a suggestions index
+pub fn schema() -> Schema {
+    SchemaBuilder::new()
+        .text_field("query")
+        .integer_field("popularity")
+        .integer_field("nb_words")
+        .build()
+}
+
+/// Words typed so far, as matched against suggestions
+pub fn prefix_text(prefix: &str) -> String {
+    normalize(prefix)
+}
+
+/// Search of a suggestions index for `prefix`, every word required and the last matching as a prefix
+///
+/// A prefix ending in whitespace has its last word complete. An empty prefix
+/// matches every suggestion, so autocomplete can show the most popular ones.
+pub fn suggest_query(prefix: &str, limit: u32) -> SearchQuery {
+    let text = prefix_text(prefix);
+    // Every word is required, so "red sh" does not suggest a more popular "red wine"
+    let mut q: Vec<String> = text.split(' ').filter(|word| !word.is_empty()).map(|word| format!("+{}", word)).collect();
+    if let Some(last) = q.last_mut().filter(|_| !prefix.ends_with(char::is_whitespace)) {
+        last.push('*');
+    }
+    SearchQuery {
+        q: (!q.is_empty()).then(|| q.join(" ")),
+        filters: Vec::new(),
+        sort: vec!["popularity:desc".to_string()],
+        facets: Vec::new(),
+        page: None,
+        per_page: Some(limit),
+        offset: None,
+        highlight: None,
+        config: None,
+    }
+}
+
+/// Read a suggestion record as stored by [`SuggestionsBuilder::documents`]
+pub fn from_document(document: &Value) -> Option<Suggestion> {
+    Some(Suggestion {
+        query: document.get("query")?.as_str()?.to_string(),
+        popularity: document.get("popularity").and_then(Value::as_u64).unwrap_or(0),
+    })
+}
+
+/// Suggestions of a search of the suggestions index, in result order
+pub fn from_results(results: &SearchResults) -> Vec<Suggestion> {
+    results
+        .hits
+        .iter()
+        .filter_map(|hit| serde_json::from_str(hit.content.as_deref()?).ok())
+        .filter_map(|document: Value| from_document(&document))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn popular(query: &str, count: u64, hits: Option<u64>) -> PopularQuery {
+        PopularQuery { query: query.to_string(), count, hits }
+    }
+
+    fn built(config: SuggestionsConfig, queries: &[PopularQuery], titles: &[&str]) -> Vec<(String, u64)> {
+        let mut builder = SuggestionsBuilder::new(config);
+        builder.add_queries(queries);
+        titles.iter().for_each(|title| builder.add_title(title));
+        builder.suggestions().into_iter().map(|s| (s.query, s.popularity)).collect()
+    }
+
+    fn invalid<T: std::fmt::Debug>(result: SearchResult<T>) -> String {
+        match result {
+            Err(SearchError::InvalidQuery(message)) => message,
+            other => panic!("expected InvalidQuery, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_suggestions_index_name() {
+        assert_eq!(suggestions_index("products"), "products_query_suggestions");
+    }
+
+    #[test]
+    fn test_config_fills_in_defaults() {
+        assert_eq!(SuggestionsConfig::from_json(None).unwrap(), SuggestionsConfig::default());
+        assert_eq!(SuggestionsConfig::from_json(Some("  ")).unwrap(), SuggestionsConfig::default());
+        let config = SuggestionsConfig::from_json(Some(r#"{"min_count": 5}"#)).unwrap();
+        assert_eq!(config, SuggestionsConfig { min_count: 5, ..SuggestionsConfig::default() });
+    }
+
+    #[test]
+    fn test_config_refuses_unknown_fields() {
+        let message = invalid(SuggestionsConfig::from_json(Some(r#"{"min_hits": 5}"#)));
+        assert!(message.starts_with("Invalid suggestions config: unknown field `min_hits`"), "{}", message);
+    }
+
+    #[test]
+    fn test_popular_queries_need_a_query_and_count() {
+        let queries = parse_popular_queries(r#"[{"query": "red shoes", "count": 3}]"#).unwrap();
+        assert_eq!(queries, [popular("red shoes", 3, None)]);
+        assert!(invalid(parse_popular_queries(r#"[{"query": "red shoes"}]"#)).starts_with("Invalid popular queries: "));
+    }
+
+    #[test]
+    fn test_queries_differing_in_case_or_punctuation_are_merged() {
+        let queries = [popular("Red Shoes", 3, Some(40)), popular("red-shoes!", 2, None)];
+        assert_eq!(built(SuggestionsConfig::default(), &queries, &[]), [("red shoes".to_string(), 5)]);
+    }
+
+    #[test]
+    fn test_queries_that_found_nothing_are_never_suggested() {
+        let queries = [popular("green socks", 90, Some(0)), popular("green socks", 1, None)];
+        assert!(built(SuggestionsConfig::default(), &queries, &[]).is_empty());
+    }
+
+    #[test]
+    fn test_queries_need_the_minimum_count_but_titles_do_not() {
+        let queries = [popular("red shoes", 2, None), popular("blue hats", 1, None), popular("trail runner", 1, None)];
+        assert_eq!(
+            built(SuggestionsConfig::default(), &queries, &["Trail Runner"]),
+            [("red shoes".to_string(), 2), ("trail runner".to_string(), 2)]
+        );
+    }
+
+    #[test]
+    fn test_short_suggestions_count_letters_and_digits_only() {
+        let titles = ["a b", "A-B-C", "ab", "x1"];
+        assert_eq!(built(SuggestionsConfig::default(), &[], &titles), [("a b c".to_string(), 1)]);
+    }
+
+    #[test]
+    fn test_banned_words_match_whole_words_in_any_case() {
+        let config = SuggestionsConfig { banned_words: vec!["Spam".to_string()], ..SuggestionsConfig::default() };
+        let titles = ["SPAM offers", "spammer guide"];
+        assert_eq!(built(config, &[], &titles), [("spammer guide".to_string(), 1)]);
+    }
+
+    #[test]
+    fn test_ties_are_ordered_by_text_before_truncating() {
+        let config = SuggestionsConfig { max_suggestions: 2, ..SuggestionsConfig::default() };
+        let queries = [popular("red shoes", 5, None)];
+        assert_eq!(
+            built(config, &queries, &["zebra", "apple", "mango"]),
+            [("red shoes".to_string(), 5), ("apple".to_string(), 1)]
+        );
+    }
+
+    #[test]
+    fn test_documents_are_keyed_by_their_text() {
+        let mut first = SuggestionsBuilder::new(SuggestionsConfig::default());
+        first.add_title("Trail Runner 2");
+        first.add_title("blue hats");
+        let mut second = SuggestionsBuilder::new(SuggestionsConfig::default());
+        second.add_title("TRAIL runner 2");
+
+        let documents = first.documents();
+        let trail = documents.iter().find(|d| d.content.contains("trail")).unwrap();
+        assert_eq!(trail.id, second.documents()[0].id);
+        assert_eq!(trail.id.len(), 32);
+        assert_ne!(documents[0].id, documents[1].id);
+        let record: Value = serde_json::from_str(&trail.content).unwrap();
+        assert_eq!(record, json!({ "query": "trail runner 2", "popularity": 1, "nb_words": 3 }));
+    }
+
+    #[test]
+    fn test_suggest_query_prefix_matches_only_an_unfinished_last_word() {
+        assert_eq!(suggest_query("Red sh", 5).q.as_deref(), Some("+red +sh*"));
+        assert_eq!(suggest_query("red ", 5).q.as_deref(), Some("+red"));
+        assert_eq!(suggest_query("red!", 5).q.as_deref(), Some("+red*"));
+    }
+
+    #[test]
+    fn test_suggest_query_without_words_lists_the_most_popular() {
+        for prefix in ["", "  ", "?!"] {
+            let query = suggest_query(prefix, 8);
+            assert_eq!((query.q, query.per_page), (None, Some(8)), "{:?}", prefix);
+            assert_eq!(query.sort, ["popularity:desc"]);
+        }
+    }
+
+    #[test]
+    fn test_records_need_a_text_query() {
+        assert_eq!(from_document(&json!({ "query": "red shoes" })), Some(Suggestion { query: "red shoes".to_string(), popularity: 0 }));
+        assert_eq!(from_document(&json!({ "query": 3, "popularity": 1 })), None);
+        assert_eq!(from_document(&json!({ "popularity": 1 })), None);
+    }
+
+    #[test]
+    fn test_unreadable_hits_are_skipped_in_order() {
+        let results: SearchResults = serde_json::from_value(json!({
+            "hits": [
+                { "id": "1", "content": r#"{"query": "red shoes", "popularity": 5}"# },
+                { "id": "2" },
+                { "id": "3", "content": "not json" },
+                { "id": "4", "content": r#"{"title": "no query"}"# },
+                { "id": "5", "content": r#"{"query": "blue hats", "popularity": 9}"# }
+            ]
+        }))
+        .unwrap();
+        let queries: Vec<String> = from_results(&results).into_iter().map(|s| s.query).collect();
+        assert_eq!(queries, ["red shoes", "blue hats"]);
+    }
+}
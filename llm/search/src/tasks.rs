@@ -0,0 +1,256 @@
+//! Pending indexing tasks
+//!
+//! Meilisearch and Algolia queue writes and apply them asynchronously, and
+//! Elasticsearch and OpenSearch queue cluster state changes (index creation,
+//! mapping and settings updates) on the master node. When documents that
+//! were just written do not show up in searches, the pending tasks of the
+//! index tell whether the provider is still working through a backlog.
+//!
+//! Typesense applies writes before acknowledging them and has no queue.
+//!
+//! With `SEARCH_MAX_PENDING_TASKS` set, batch writes first look at the queue
+//! depth and fail with [`SearchError::Backpressure`] past it, rather than
+//! piling more tasks onto a backend that is already behind.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::config::env_helpers::get_env_parsed;
+use crate::error::{SearchError, SearchResult};
+
+/// Number of tasks listed per request; `total` still counts them all
+pub const MAX_LISTED_TASKS: u32 = 100;
+
+/// Environment variable holding the queue depth past which batch writes are refused
+pub const MAX_PENDING_TASKS_ENV: &str = "SEARCH_MAX_PENDING_TASKS";
+
+/// Environment variable holding the base retry delay, in milliseconds, of a backpressure error
+pub const BACKPRESSURE_RETRY_ENV: &str = "SEARCH_BACKPRESSURE_RETRY_MS";
+
+pub const DEFAULT_BACKPRESSURE_RETRY_MS: u64 = 5_000;
+
+/// Longest retry delay suggested, however deep the queue
+const MAX_BACKPRESSURE_RETRY_MS: u64 = 60_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskStatus {
+    Enqueued,
+    Processing,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingTask {
+    pub id: String,
+    pub status: TaskStatus,
+
+    /// Provider task type, such as `documentAdditionOrUpdate` or `put-mapping`
+    pub kind: Option<String>,
+
+    /// RFC 3339 timestamps, when the provider reports them
+    pub enqueued_at: Option<String>,
+    pub started_at: Option<String>,
+
+    /// Time spent in the queue so far, when the provider reports it
+    pub queued_ms: Option<u64>,
+}
+
+/// Queued and processing tasks of one index
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingTasks {
+    pub provider: String,
+    pub index: String,
+
+    /// Number of pending tasks; may exceed `tasks.len()` when the listing was capped
+    pub total: u64,
+    pub tasks: Vec<PendingTask>,
+}
+
+impl PendingTasks {
+    /// Parse a Meilisearch `GET /tasks` response filtered on enqueued and processing tasks
+    pub fn from_meilisearch(index: &str, body: &Value) -> Self {
+        let tasks: Vec<PendingTask> = body
+            .get("results")
+            .and_then(Value::as_array)
+            .map(|results| {
+                results
+                    .iter()
+                    .map(|task| PendingTask {
+                        id: task.get("uid").map(json_text).unwrap_or_default(),
+                        status: match task.get("status").and_then(Value::as_str) {
+                            Some("processing") => TaskStatus::Processing,
+                            _ => TaskStatus::Enqueued,
+                        },
+                        kind: task.get("type").and_then(Value::as_str).map(str::to_string),
+                        enqueued_at: task.get("enqueuedAt").and_then(Value::as_str).map(str::to_string),
+                        started_at: task.get("startedAt").and_then(Value::as_str).map(str::to_string),
+                        queued_ms: None,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let total = body.get("total").and_then(Value::as_u64).unwrap_or(tasks.len() as u64);
+        Self { provider: "meilisearch".to_string(), index: index.to_string(), total, tasks }
+    }
+
+    /// Parse an Elasticsearch/OpenSearch `GET _cluster/pending_tasks` response
+    ///
+    /// The queue is cluster-wide; only tasks whose source names `index`, as
+    /// `[books]` or `[books/<uuid>]`, are kept.
+    pub fn from_cluster_pending_tasks(provider: &str, index: &str, body: &Value) -> Self {
+        let names_index = |source: &str| {
+            source.contains(&format!("[{}]", index)) || source.contains(&format!("[{}/", index))
+        };
+        let tasks: Vec<PendingTask> = body
+            .get("tasks")
+            .and_then(Value::as_array)
+            .map(|tasks| {
+                tasks
+                    .iter()
+                    .filter(|task| task.get("source").and_then(Value::as_str).is_some_and(names_index))
+                    .map(|task| PendingTask {
+                        id: task.get("insert_order").map(json_text).unwrap_or_default(),
+                        status: match task.get("executing").and_then(Value::as_bool) {
+                            Some(true) => TaskStatus::Processing,
+                            _ => TaskStatus::Enqueued,
+                        },
+                        // "put-mapping [books/abc123]" -> "put-mapping"
+                        kind: task
+                            .get("source")
+                            .and_then(Value::as_str)
+                            .and_then(|s| s.split_whitespace().next())
+                            .map(str::to_string),
+                        enqueued_at: None,
+                        started_at: None,
+                        queued_ms: task.get("time_in_queue_millis").and_then(Value::as_u64),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self { provider: provider.to_string(), index: index.to_string(), total: tasks.len() as u64, tasks }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.total == 0
+    }
+}
+
+/// Queue depth limit applied before batch writes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Backpressure {
+    /// Pending tasks allowed before writes are refused; `None` disables the check
+    pub max_pending: Option<u64>,
+
+    /// Retry delay suggested when the queue is just past the limit
+    pub retry_after_ms: u64,
+}
+
+impl Default for Backpressure {
+    fn default() -> Self {
+        Self { max_pending: None, retry_after_ms: DEFAULT_BACKPRESSURE_RETRY_MS }
+    }
+}
+
+impl Backpressure {
+    /// Read the limit from `SEARCH_MAX_PENDING_TASKS` and `SEARCH_BACKPRESSURE_RETRY_MS`
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            max_pending: get_env_parsed::<u64>(MAX_PENDING_TASKS_ENV).ok().flatten().filter(|&max| max > 0),
+            retry_after_ms: get_env_parsed::<u64>(BACKPRESSURE_RETRY_ENV)
+                .ok()
+                .flatten()
+                .unwrap_or(defaults.retry_after_ms),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.max_pending.is_some()
+    }
+
+    /// Refuse a write when `queue_depth` exceeds the limit
+    ///
+    /// The suggested delay grows with how far the queue is past the limit:
+    /// twice the limit waits twice the base delay.
+    pub fn check(&self, queue_depth: u64) -> SearchResult<()> {
+        let Some(max_pending) = self.max_pending else {
+            return Ok(());
+        };
+        if queue_depth <= max_pending {
+            return Ok(());
+        }
+        let retry_after_ms = (self.retry_after_ms.saturating_mul(queue_depth) / max_pending).min(MAX_BACKPRESSURE_RETRY_MS);
+        Err(SearchError::Backpressure { queue_depth, retry_after_ms: Some(retry_after_ms) })
+    }
+}
+
+fn json_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_meilisearch_tasks() {
+        let body = json!({
+            "results": [
+                { "uid": 42, "indexUid": "books", "status": "processing", "type": "documentAdditionOrUpdate",
+                  "enqueuedAt": "2024-05-01T10:00:00Z", "startedAt": "2024-05-01T10:00:02Z" },
+                { "uid": 43, "indexUid": "books", "status": "enqueued", "type": "settingsUpdate",
+                  "enqueuedAt": "2024-05-01T10:00:01Z", "startedAt": null }
+            ],
+            "total": 250, "limit": 100, "from": 43, "next": 41
+        });
+        let pending = PendingTasks::from_meilisearch("books", &body);
+        assert_eq!(pending.total, 250);
+        assert_eq!(pending.tasks.len(), 2);
+        assert_eq!(pending.tasks[0].id, "42");
+        assert_eq!(pending.tasks[0].status, TaskStatus::Processing);
+        assert_eq!(pending.tasks[1].kind.as_deref(), Some("settingsUpdate"));
+        assert_eq!(pending.tasks[1].started_at, None);
+    }
+
+    #[test]
+    fn test_cluster_pending_tasks_for_index() {
+        let body = json!({ "tasks": [
+            { "insert_order": 101, "priority": "URGENT", "source": "put-mapping [books/Xb3]",
+              "executing": true, "time_in_queue_millis": 86 },
+            { "insert_order": 102, "priority": "URGENT", "source": "create-index [books_v2], cause [api]",
+              "executing": false, "time_in_queue_millis": 12 }
+        ]});
+        let pending = PendingTasks::from_cluster_pending_tasks("elasticsearch", "books_v2", &body);
+        assert_eq!(pending.total, 1);
+        assert_eq!(pending.tasks[0].id, "102");
+        assert_eq!(pending.tasks[0].kind.as_deref(), Some("create-index"));
+        assert_eq!(pending.tasks[0].status, TaskStatus::Enqueued);
+        assert_eq!(pending.tasks[0].queued_ms, Some(12));
+
+        let pending = PendingTasks::from_cluster_pending_tasks("opensearch", "books", &body);
+        assert_eq!(pending.tasks[0].status, TaskStatus::Processing);
+        assert_eq!(pending.tasks[0].kind.as_deref(), Some("put-mapping"));
+    }
+
+    #[test]
+    fn test_backpressure_check() {
+        assert!(Backpressure::default().check(u64::MAX).is_ok());
+
+        let limit = Backpressure { max_pending: Some(500), retry_after_ms: 2_000 };
+        assert!(limit.check(500).is_ok());
+        match limit.check(1_000) {
+            Err(SearchError::Backpressure { queue_depth, retry_after_ms }) => {
+                assert_eq!(queue_depth, 1_000);
+                assert_eq!(retry_after_ms, Some(4_000));
+            }
+            other => panic!("expected backpressure, got {:?}", other),
+        }
+        assert!(matches!(
+            limit.check(1_000_000),
+            Err(SearchError::Backpressure { retry_after_ms: Some(60_000), .. })
+        ));
+    }
+}
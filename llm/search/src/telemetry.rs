@@ -0,0 +1,422 @@
+//! OpenTelemetry tracing of provider operations
+//!
+//! Providers run each operation through [`traced`]. With the `otel` feature
+//! enabled that call becomes a client span carrying the database semantic
+//! convention attributes (`db.system`, `db.operation`, `db.collection.name`
+//! and, where it applies, `db.response.returned_rows`), exported over OTLP/HTTP
+//! to the collector named by the standard `OTEL_EXPORTER_OTLP_*` variables.
+//! The trace ID is the operation's request ID, so a span can be looked up from
+//! the `X-Request-Id` a backend logged. Without the feature [`traced`] only
+//! awaits the operation.
+//!
+//! Spans are encoded as OTLP JSON and sent with the crate's HTTP client; the
+//! `opentelemetry-otlp` transports (tonic, async reqwest) do not build for
+//! WASI components.
+
+use std::fmt::Display;
+use std::future::Future;
+use std::time::Instant;
+use crate::metrics::{self, ErrorKind};
+use crate::types::SearchResults;
+
+/// Operation results that report how many rows they returned
+pub trait SpanOutput {
+    /// Value of `db.response.returned_rows`, when it means something for the operation
+    fn returned_rows(&self) -> Option<u64> {
+        None
+    }
+}
+
+impl SpanOutput for SearchResults {
+    fn returned_rows(&self) -> Option<u64> {
+        Some(self.hits.len() as u64)
+    }
+}
+
+impl<T> SpanOutput for Option<T> {
+    fn returned_rows(&self) -> Option<u64> {
+        Some(u64::from(self.is_some()))
+    }
+}
+
+impl<T> SpanOutput for Vec<Option<T>> {
+    fn returned_rows(&self) -> Option<u64> {
+        Some(self.iter().filter(|doc| doc.is_some()).count() as u64)
+    }
+}
+
+impl SpanOutput for () {}
+impl SpanOutput for bool {}
+impl SpanOutput for String {}
+
+/// Run one provider operation, inside a span when the `otel` feature is enabled
+///
+/// The operation is also counted in [`crate::metrics`], whether or not the
+/// feature is enabled.
+pub async fn traced<T, E, Fut>(provider: &str, operation: &str, index: &str, future: Fut) -> Result<T, E>
+where
+    T: SpanOutput,
+    E: Display + ErrorKind,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let started = Instant::now();
+
+    #[cfg(feature = "otel")]
+    let result = {
+        let span = otel::Span::start(provider, operation, index);
+        let result = future.await;
+        span.end(&result);
+        result
+    };
+
+    #[cfg(not(feature = "otel"))]
+    let result = {
+        let _ = index;
+        future.await
+    };
+
+    metrics::record_operation(provider, operation, started.elapsed(), result.as_ref().err().map(ErrorKind::error_kind));
+    result
+}
+
+/// Export spans still waiting for a full batch
+pub fn flush() {
+    #[cfg(feature = "otel")]
+    otel::flush();
+}
+
+#[cfg(feature = "otel")]
+pub use otel::{
+    OTEL_BATCH_SIZE_ENV, OTEL_DISABLED_ENV, OTEL_ENDPOINT_ENV, OTEL_HEADERS_ENV, OTEL_SERVICE_NAME_ENV,
+    OTEL_TRACES_ENDPOINT_ENV,
+};
+
+#[cfg(feature = "otel")]
+mod otel {
+    use std::cell::RefCell;
+    use std::fmt::Display;
+    use std::sync::{Mutex, OnceLock};
+    use std::time::{SystemTime, UNIX_EPOCH};
+    use log::{debug, warn};
+    use serde_json::{json, Value};
+    use super::SpanOutput;
+    use crate::config::env_helpers::{get_env_or_default, get_env_parsed};
+    use crate::request_id::{self, RequestScope};
+    use crate::sampling::random_seed;
+
+    /// Base URL of the OTLP/HTTP collector; `/v1/traces` is appended
+    pub const OTEL_ENDPOINT_ENV: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+
+    /// Full trace export URL, taking precedence over the base endpoint
+    pub const OTEL_TRACES_ENDPOINT_ENV: &str = "OTEL_EXPORTER_OTLP_TRACES_ENDPOINT";
+
+    /// Extra export headers as `key=value` pairs separated by commas
+    pub const OTEL_HEADERS_ENV: &str = "OTEL_EXPORTER_OTLP_HEADERS";
+
+    /// `service.name` resource attribute
+    pub const OTEL_SERVICE_NAME_ENV: &str = "OTEL_SERVICE_NAME";
+
+    /// Number of finished spans sent per export
+    pub const OTEL_BATCH_SIZE_ENV: &str = "OTEL_BSP_MAX_EXPORT_BATCH_SIZE";
+
+    /// Set to `true` to turn tracing off without rebuilding
+    pub const OTEL_DISABLED_ENV: &str = "OTEL_SDK_DISABLED";
+
+    const DEFAULT_ENDPOINT: &str = "http://localhost:4318";
+    const SCOPE_NAME: &str = "golem-search";
+
+    // OTLP span kind and status codes
+    const SPAN_KIND_CLIENT: u8 = 3;
+    const STATUS_OK: u8 = 1;
+    const STATUS_ERROR: u8 = 2;
+
+    struct ExporterConfig {
+        enabled: bool,
+        url: String,
+        headers: Vec<(String, String)>,
+        service_name: String,
+        batch_size: usize,
+    }
+
+    impl ExporterConfig {
+        fn from_env() -> Self {
+            let url = std::env::var(OTEL_TRACES_ENDPOINT_ENV).unwrap_or_else(|_| {
+                let base = get_env_or_default(OTEL_ENDPOINT_ENV, DEFAULT_ENDPOINT);
+                format!("{}/v1/traces", base.trim_end_matches('/'))
+            });
+            let headers = get_env_or_default(OTEL_HEADERS_ENV, "")
+                .split(',')
+                .filter_map(|pair| pair.split_once('='))
+                .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+                .collect();
+
+            // Components can be suspended between calls, so nothing is held back by default
+            Self {
+                enabled: !get_env_parsed::<bool>(OTEL_DISABLED_ENV).ok().flatten().unwrap_or(false),
+                url,
+                headers,
+                service_name: get_env_or_default(OTEL_SERVICE_NAME_ENV, SCOPE_NAME),
+                batch_size: get_env_parsed::<usize>(OTEL_BATCH_SIZE_ENV).ok().flatten().unwrap_or(1).max(1),
+            }
+        }
+    }
+
+    fn config() -> &'static ExporterConfig {
+        static CONFIG: OnceLock<ExporterConfig> = OnceLock::new();
+        CONFIG.get_or_init(ExporterConfig::from_env)
+    }
+
+    fn pending() -> &'static Mutex<Vec<FinishedSpan>> {
+        static PENDING: OnceLock<Mutex<Vec<FinishedSpan>>> = OnceLock::new();
+        PENDING.get_or_init(|| Mutex::new(Vec::new()))
+    }
+
+    thread_local! {
+        // IDs of the spans open on this thread, innermost last
+        static OPEN: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub(super) struct FinishedSpan {
+        pub trace_id: String,
+        pub span_id: String,
+        pub parent_span_id: Option<String>,
+        pub name: String,
+        pub start_ns: u128,
+        pub end_ns: u128,
+        pub attributes: Vec<(&'static str, Value)>,
+        pub error: Option<String>,
+    }
+
+    /// An open span; the request scope keeps the trace ID active for nested calls
+    pub(super) struct Span {
+        scope: RequestScope,
+        span_id: String,
+        parent_span_id: Option<String>,
+        provider: String,
+        operation: String,
+        index: String,
+        start_ns: u128,
+    }
+
+    fn now_ns() -> u128 {
+        SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0)
+    }
+
+    impl Span {
+        pub fn start(provider: &str, operation: &str, index: &str) -> Self {
+            let span_id = format!("{:016x}", random_seed());
+            let parent_span_id = OPEN.with(|open| {
+                let mut open = open.borrow_mut();
+                let parent = open.last().cloned();
+                open.push(span_id.clone());
+                parent
+            });
+
+            Self {
+                scope: request_id::begin(),
+                span_id,
+                parent_span_id,
+                provider: provider.to_string(),
+                operation: operation.to_string(),
+                index: index.to_string(),
+                start_ns: now_ns(),
+            }
+        }
+
+        pub fn end<T: SpanOutput, E: Display>(self, result: &Result<T, E>) {
+            let outermost = OPEN.with(|open| {
+                let mut open = open.borrow_mut();
+                open.retain(|id| id != &self.span_id);
+                open.is_empty()
+            });
+
+            let mut attributes = vec![
+                ("db.system", json!(self.provider)),
+                ("db.operation", json!(self.operation)),
+                ("db.collection.name", json!(self.index)),
+            ];
+            if let Ok(Some(rows)) = result.as_ref().map(SpanOutput::returned_rows) {
+                attributes.push(("db.response.returned_rows", json!(rows)));
+            }
+
+            let span = FinishedSpan {
+                trace_id: self.scope.id().to_string(),
+                span_id: self.span_id,
+                parent_span_id: self.parent_span_id,
+                name: format!("{} {}", self.operation, self.index),
+                start_ns: self.start_ns,
+                end_ns: now_ns(),
+                attributes,
+                error: result.as_ref().err().map(|e| e.to_string()),
+            };
+            record(span, outermost);
+        }
+    }
+
+    fn record(span: FinishedSpan, outermost: bool) {
+        let config = config();
+        if !config.enabled {
+            return;
+        }
+
+        let batch = {
+            let mut pending = pending().lock().unwrap();
+            pending.push(span);
+            // Exporting inside a parent span would add its own latency to the parent
+            if outermost && pending.len() >= config.batch_size {
+                std::mem::take(&mut *pending)
+            } else {
+                return;
+            }
+        };
+        export(config, batch);
+    }
+
+    pub fn flush() {
+        let config = config();
+        let batch = std::mem::take(&mut *pending().lock().unwrap());
+        if config.enabled && !batch.is_empty() {
+            export(config, batch);
+        }
+    }
+
+    fn export(config: &ExporterConfig, spans: Vec<FinishedSpan>) {
+        let count = spans.len();
+        let body = encode(&config.service_name, &spans);
+
+        let mut request = crate::http::Client::new()
+            .post(&config.url)
+            .header("Content-Type", "application/json");
+        for (key, value) in &config.headers {
+            request = request.header(key.as_str(), value.as_str());
+        }
+
+        // Tracing must never fail the traced operation, so errors are only logged
+        match request.body(body.to_string()).send() {
+            Ok(response) if response.status().is_success() => {
+                debug!("Exported {} span(s) to {}", count, config.url);
+            }
+            Ok(response) => warn!("Dropped {} span(s): collector returned {}", count, response.status()),
+            Err(e) => warn!("Dropped {} span(s): {}", count, e),
+        }
+    }
+
+    fn attribute(key: &str, value: &Value) -> Value {
+        let value = match value {
+            // OTLP JSON carries 64-bit integers as strings
+            Value::Number(n) if n.is_u64() || n.is_i64() => json!({ "intValue": n.to_string() }),
+            Value::Number(n) => json!({ "doubleValue": n }),
+            Value::Bool(b) => json!({ "boolValue": b }),
+            Value::String(s) => json!({ "stringValue": s }),
+            other => json!({ "stringValue": other.to_string() }),
+        };
+        json!({ "key": key, "value": value })
+    }
+
+    /// OTLP/HTTP JSON body for an `ExportTraceServiceRequest`
+    pub(super) fn encode(service_name: &str, spans: &[FinishedSpan]) -> Value {
+        let spans: Vec<Value> = spans
+            .iter()
+            .map(|span| {
+                let status = match &span.error {
+                    Some(message) => json!({ "code": STATUS_ERROR, "message": message }),
+                    None => json!({ "code": STATUS_OK }),
+                };
+                let mut encoded = json!({
+                    "traceId": span.trace_id,
+                    "spanId": span.span_id,
+                    "name": span.name,
+                    "kind": SPAN_KIND_CLIENT,
+                    "startTimeUnixNano": span.start_ns.to_string(),
+                    "endTimeUnixNano": span.end_ns.to_string(),
+                    "attributes": span.attributes.iter().map(|(k, v)| attribute(k, v)).collect::<Vec<_>>(),
+                    "status": status,
+                });
+                if let Some(parent) = &span.parent_span_id {
+                    encoded["parentSpanId"] = json!(parent);
+                }
+                encoded
+            })
+            .collect();
+
+        json!({
+            "resourceSpans": [{
+                "resource": {
+                    "attributes": [attribute("service.name", &json!(service_name))]
+                },
+                "scopeSpans": [{
+                    "scope": { "name": SCOPE_NAME },
+                    "spans": spans
+                }]
+            }]
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::SearchError;
+    use crate::types::{Doc, SearchHit};
+
+    #[test]
+    fn test_traced_passes_results_through() {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let hit = SearchHit { id: "1".to_string(), score: None, content: None, highlights: None, explanation: None, distance_meters: None };
+        let results = SearchResults {
+            total: Some(2),
+            page: None,
+            per_page: None,
+            hits: vec![hit.clone(), hit],
+            facets: None,
+            took_ms: None,
+            truncated: false,
+            request_id: None,
+            groups: None,
+            next_cursor: None,
+            total_exact: None,
+            facet_metadata: Vec::new(),
+            relaxations: Vec::new(),
+            original_query: None,
+            corrected_query: None,
+        };
+        assert_eq!(results.returned_rows(), Some(2));
+        assert_eq!(vec![None, Some(Doc { id: "1".to_string(), content: "{}".to_string() })].returned_rows(), Some(1));
+        assert_eq!(().returned_rows(), None);
+
+        let ok = runtime.block_on(traced("elasticsearch", "search", "books", async { Ok::<_, SearchError>(results) }));
+        assert_eq!(ok.unwrap().hits.len(), 2);
+
+        let err = runtime.block_on(traced("elasticsearch", "get", "books", async {
+            Err::<Option<Doc>, _>(SearchError::IndexNotFound("books".to_string()))
+        }));
+        assert!(matches!(err, Err(SearchError::IndexNotFound(_))));
+    }
+
+    #[cfg(feature = "otel")]
+    #[test]
+    fn test_encode_otlp_spans() {
+        let span = otel::FinishedSpan {
+            trace_id: "0af7651916cd43dd8448eb211c80319c".to_string(),
+            span_id: "b7ad6b7169203331".to_string(),
+            parent_span_id: None,
+            name: "search books".to_string(),
+            start_ns: 1_700_000_000_000_000_000,
+            end_ns: 1_700_000_000_250_000_000,
+            attributes: vec![("db.system", serde_json::json!("typesense")), ("db.response.returned_rows", serde_json::json!(12))],
+            error: Some("Timeout".to_string()),
+        };
+
+        let body = otel::encode("catalog", &[span]);
+        let resource = &body["resourceSpans"][0];
+        assert_eq!(resource["resource"]["attributes"][0]["value"]["stringValue"], "catalog");
+
+        let encoded = &resource["scopeSpans"][0]["spans"][0];
+        assert_eq!(encoded["traceId"], "0af7651916cd43dd8448eb211c80319c");
+        assert_eq!(encoded["startTimeUnixNano"], "1700000000000000000");
+        assert_eq!(encoded["attributes"][1]["value"]["intValue"], "12");
+        assert_eq!(encoded["status"]["code"], 2);
+        assert!(encoded.get("parentSpanId").is_none());
+    }
+}
@@ -0,0 +1,375 @@
+//! Index templates for common domains
+//!
+//! A template is a ready-made blueprint for one kind of index: its schema,
+//! synonyms, stop words and the fields that break ties between equally
+//! relevant hits. Providers instantiate one with `create_index_from_template`,
+//! which creates the index and applies whatever of the settings the provider
+//! supports.
+//!
+//! | Template | For |
+//! |----------|-----|
+//! | `products` | e-commerce catalogues: faceted by brand, category and price, ranked by popularity |
+//! | `articles` | news, blog posts and knowledge-base articles, newest first |
+//! | `logs` | structured log lines, filtered by level and service, newest first |
+//! | `rag-chunks` | document chunks retrieved for retrieval-augmented generation |
+//!
+//! Ranking is portable: `field:desc` or `field:asc` on a sortable field.
+//! [`IndexTemplate::settings`] turns it into each provider's rules:
+//! Meilisearch custom ranking rules after its built-in ones, Algolia custom
+//! ranking and the Typesense default sorting field, which only sorts numeric
+//! fields in descending order. ElasticSearch and OpenSearch rank by text
+//! relevance only; sort at query time instead.
+
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use crate::error::{SearchError, SearchResult};
+use crate::settings::IndexSettings;
+use crate::telemetry::SpanOutput;
+use crate::types::{FieldType, Schema, SchemaBuilder, SchemaField};
+
+/// Names accepted by [`template`]
+pub const TEMPLATE_NAMES: [&str; 4] = ["products", "articles", "logs", "rag-chunks"];
+
+/// Ranking rules Meilisearch applies before custom ones, in its default order
+const MEILISEARCH_BUILTIN_RULES: [&str; 6] = ["words", "typo", "proximity", "attribute", "sort", "exactness"];
+
+const ENGLISH_STOP_WORDS: [&str; 24] = [
+    "a", "an", "and", "are", "as", "at", "be", "by", "for", "from", "in", "is", "it", "of", "on", "or", "that",
+    "the", "this", "to", "was", "were", "will", "with",
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexTemplate {
+    pub name: String,
+    pub description: String,
+    pub schema: Schema,
+
+    /// Terms mapped to the words they also match
+    pub synonyms: BTreeMap<String, Vec<String>>,
+
+    /// Tie-breakers after text relevance, as `field:desc` or `field:asc`
+    pub ranking: Vec<String>,
+    pub stop_words: Vec<String>,
+}
+
+impl SpanOutput for IndexTemplate {}
+
+/// Changes to a template, applied by [`instantiate`]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TemplateOverrides {
+    /// Fields added to the schema, replacing template fields of the same name
+    pub fields: Vec<SchemaField>,
+    pub remove_fields: Vec<String>,
+    pub primary_key: Option<String>,
+
+    /// Merged into the template synonyms; an empty list removes a term
+    pub synonyms: BTreeMap<String, Vec<String>>,
+
+    /// Replaces the template ranking
+    pub ranking: Option<Vec<String>>,
+
+    /// Replaces the template stop words
+    pub stop_words: Option<Vec<String>>,
+}
+
+impl TemplateOverrides {
+    /// Overrides from a JSON object; no overrides when `None`
+    pub fn from_json(json: Option<&str>) -> SearchResult<Self> {
+        match json.map(str::trim).filter(|json| !json.is_empty()) {
+            Some(json) => serde_json::from_str(json)
+                .map_err(|e| SearchError::invalid_query(format!("Invalid template overrides: {}", e))),
+            None => Ok(Self::default()),
+        }
+    }
+}
+
+impl IndexTemplate {
+    /// Ranking rules as `(field, descending)` pairs
+    pub fn ranking_fields(&self) -> SearchResult<Vec<(&str, bool)>> {
+        self.ranking
+            .iter()
+            .map(|rule| match rule.rsplit_once(':') {
+                Some((field, "desc")) if !field.is_empty() => Ok((field, true)),
+                Some((field, "asc")) if !field.is_empty() => Ok((field, false)),
+                _ => Err(SearchError::invalid_query(format!(
+                    "Invalid ranking rule '{}'; expected field:desc or field:asc",
+                    rule
+                ))),
+            })
+            .collect()
+    }
+
+    /// Settings to apply for `provider`, with ranking in the provider's own syntax
+    pub fn settings(&self, provider: &str) -> IndexSettings {
+        let ranking = self.ranking_fields().unwrap_or_default();
+        let ranking_rules = match provider {
+            "meilisearch" => MEILISEARCH_BUILTIN_RULES
+                .iter()
+                .map(|rule| rule.to_string())
+                .chain(ranking.iter().map(|(field, desc)| format!("{}:{}", field, if *desc { "desc" } else { "asc" })))
+                .collect(),
+            "algolia" => ranking
+                .iter()
+                .map(|(field, desc)| format!("{}({})", if *desc { "desc" } else { "asc" }, field))
+                .collect(),
+            _ => Vec::new(),
+        };
+        IndexSettings {
+            schema: Some(self.schema.clone()),
+            synonyms: self.synonyms.clone(),
+            ranking_rules,
+            stop_words: self.stop_words.clone(),
+            ..IndexSettings::default()
+        }
+    }
+
+    /// Typesense default sorting field: the first numeric or date field ranked descending
+    pub fn default_sorting_field(&self) -> Option<&str> {
+        let ranking = self.ranking_fields().unwrap_or_default();
+        let (field, _) = ranking.first().filter(|(_, desc)| *desc)?;
+        self.schema
+            .fields
+            .iter()
+            .find(|f| f.name == *field)
+            .filter(|f| matches!(f.field_type, FieldType::Integer | FieldType::Int64 | FieldType::Float | FieldType::Date))
+            .map(|f| f.name.as_str())
+    }
+
+    fn validate(&self) -> SearchResult<()> {
+        if let Some(key) = &self.schema.primary_key {
+            if !self.schema.fields.iter().any(|f| &f.name == key) {
+                return Err(SearchError::invalid_query(format!("Primary key '{}' is not a field of the schema", key)));
+            }
+        }
+        for (field, _) in self.ranking_fields()? {
+            if !self.schema.fields.iter().any(|f| f.name == field && f.sort) {
+                return Err(SearchError::invalid_query(format!("Ranking field '{}' is not a sortable field", field)));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn synonyms(groups: &[(&str, &[&str])]) -> BTreeMap<String, Vec<String>> {
+    groups
+        .iter()
+        .map(|(term, equivalents)| (term.to_string(), equivalents.iter().map(|s| s.to_string()).collect()))
+        .collect()
+}
+
+/// A field returned with hits but neither searched, filtered nor sorted on
+fn stored(schema: SchemaBuilder, name: &str) -> SchemaBuilder {
+    schema.field(name.to_string(), FieldType::Keyword, false, false, false, false)
+}
+
+fn products() -> IndexTemplate {
+    let schema = SchemaBuilder::new()
+        .primary_key("id")
+        .keyword_field("id")
+        .text_field("name")
+        .text_field("description")
+        .keyword_field("sku")
+        .keyword_field("brand")
+        .keyword_field("category")
+        .keyword_field("tags")
+        .float_field("price")
+        .keyword_field("currency")
+        .boolean_field("in_stock")
+        .float_field("rating")
+        .integer_field("popularity")
+        .date_field("created_at");
+    let schema = stored(schema, "image_url");
+    IndexTemplate {
+        name: "products".to_string(),
+        description: "E-commerce products, faceted by brand, category and price, ranked by popularity".to_string(),
+        schema: schema.build(),
+        synonyms: synonyms(&[
+            ("tv", &["television"]),
+            ("television", &["tv"]),
+            ("phone", &["smartphone", "mobile"]),
+            ("laptop", &["notebook"]),
+            ("sneakers", &["trainers"]),
+            ("trainers", &["sneakers"]),
+        ]),
+        ranking: vec!["popularity:desc".to_string(), "rating:desc".to_string()],
+        stop_words: vec!["a".to_string(), "an".to_string(), "the".to_string()],
+    }
+}
+
+fn articles() -> IndexTemplate {
+    let schema = SchemaBuilder::new()
+        .primary_key("id")
+        .keyword_field("id")
+        .text_field("title")
+        .text_field("summary")
+        .text_field("body")
+        .keyword_field("author")
+        .keyword_field("section")
+        .keyword_field("tags")
+        .keyword_field("language")
+        .date_field("published_at")
+        .date_field("updated_at");
+    let schema = stored(schema, "url");
+    IndexTemplate {
+        name: "articles".to_string(),
+        description: "News, blog and knowledge-base articles, newest first among equally relevant hits".to_string(),
+        schema: schema.build(),
+        synonyms: synonyms(&[("howto", &["how to", "guide", "tutorial"]), ("faq", &["frequently asked questions"])]),
+        ranking: vec!["published_at:desc".to_string()],
+        stop_words: ENGLISH_STOP_WORDS.iter().map(|word| word.to_string()).collect(),
+    }
+}
+
+fn logs() -> IndexTemplate {
+    let schema = SchemaBuilder::new()
+        .primary_key("id")
+        .keyword_field("id")
+        .date_field("timestamp")
+        .keyword_field("level")
+        .keyword_field("service")
+        .keyword_field("host")
+        .keyword_field("environment")
+        .text_field("message")
+        .keyword_field("trace_id")
+        .keyword_field("span_id")
+        .integer_field("status_code")
+        .float_field("duration_ms");
+    IndexTemplate {
+        name: "logs".to_string(),
+        description: "Structured log lines, filtered by level, service and host, newest first".to_string(),
+        schema: schema.build(),
+        synonyms: synonyms(&[("err", &["error"]), ("warn", &["warning"]), ("fatal", &["critical"])]),
+        ranking: vec!["timestamp:desc".to_string()],
+        // Words such as "not" and "no" carry meaning in log messages
+        stop_words: Vec::new(),
+    }
+}
+
+fn rag_chunks() -> IndexTemplate {
+    let schema = SchemaBuilder::new()
+        .primary_key("id")
+        .keyword_field("id")
+        .keyword_field("document_id")
+        .integer_field("chunk_index")
+        .text_field("title")
+        .text_field("content")
+        .keyword_field("source")
+        .keyword_field("language")
+        .integer_field("token_count")
+        .date_field("updated_at");
+    let schema = stored(schema, "url");
+    IndexTemplate {
+        name: "rag-chunks".to_string(),
+        description: "Document chunks for retrieval-augmented generation, filtered by source and document".to_string(),
+        schema: schema.build(),
+        synonyms: BTreeMap::new(),
+        // Pure relevance: a chunk's position says nothing about how well it answers a question
+        ranking: Vec::new(),
+        stop_words: ENGLISH_STOP_WORDS.iter().map(|word| word.to_string()).collect(),
+    }
+}
+
+/// The template called `name`, unchanged
+pub fn template(name: &str) -> SearchResult<IndexTemplate> {
+    match name {
+        "products" => Ok(products()),
+        "articles" => Ok(articles()),
+        "logs" => Ok(logs()),
+        "rag-chunks" => Ok(rag_chunks()),
+        _ => Err(SearchError::invalid_query(format!(
+            "Unknown template '{}'; available: {}",
+            name,
+            TEMPLATE_NAMES.join(", ")
+        ))),
+    }
+}
+
+/// The template called `name` with `overrides` applied and checked
+pub fn instantiate(name: &str, overrides: &TemplateOverrides) -> SearchResult<IndexTemplate> {
+    let mut template = template(name)?;
+
+    for field in &overrides.remove_fields {
+        if !template.schema.fields.iter().any(|f| &f.name == field) {
+            return Err(SearchError::invalid_query(format!("Template '{}' has no field '{}'", name, field)));
+        }
+        template.schema.fields.retain(|f| &f.name != field);
+        template.ranking.retain(|rule| rule.rsplit_once(':').map(|(f, _)| f) != Some(field.as_str()));
+    }
+    for field in &overrides.fields {
+        match template.schema.fields.iter_mut().find(|f| f.name == field.name) {
+            Some(existing) => *existing = field.clone(),
+            None => template.schema.fields.push(field.clone()),
+        }
+    }
+    if let Some(key) = &overrides.primary_key {
+        template.schema.primary_key = Some(key.clone());
+    }
+
+    for (term, equivalents) in &overrides.synonyms {
+        if equivalents.is_empty() {
+            template.synonyms.remove(term);
+        } else {
+            template.synonyms.insert(term.clone(), equivalents.clone());
+        }
+    }
+    if let Some(ranking) = &overrides.ranking {
+        template.ranking = ranking.clone();
+    }
+    if let Some(stop_words) = &overrides.stop_words {
+        template.stop_words = stop_words.clone();
+    }
+
+    template.validate()?;
+    Ok(template)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_templates_are_valid() {
+        for name in TEMPLATE_NAMES {
+            let template = instantiate(name, &TemplateOverrides::default()).unwrap();
+            assert_eq!(template.name, name);
+            assert_eq!(template.schema.primary_key.as_deref(), Some("id"));
+        }
+        assert!(template("catalog").is_err());
+
+        let products = template("products").unwrap();
+        assert_eq!(products.default_sorting_field(), Some("popularity"));
+        assert_eq!(products.settings("algolia").ranking_rules, ["desc(popularity)", "desc(rating)"]);
+        let meilisearch = products.settings("meilisearch").ranking_rules;
+        assert_eq!(meilisearch.first().map(String::as_str), Some("words"));
+        assert_eq!(meilisearch.last().map(String::as_str), Some("rating:desc"));
+        assert!(products.settings("elasticsearch").ranking_rules.is_empty());
+    }
+
+    #[test]
+    fn test_overrides() {
+        let overrides = TemplateOverrides::from_json(Some(
+            r#"{
+                "fields": [{"name": "weight_kg", "field_type": "Float", "required": false, "facet": true, "sort": true, "index": true}],
+                "remove_fields": ["popularity", "currency"],
+                "synonyms": {"tv": [], "couch": ["sofa"]},
+                "stop_words": []
+            }"#,
+        ))
+        .unwrap();
+        let products = instantiate("products", &overrides).unwrap();
+        let names: Vec<&str> = products.schema.fields.iter().map(|f| f.name.as_str()).collect();
+        assert!(names.contains(&"weight_kg") && !names.contains(&"currency"));
+        // Ranking on a removed field is dropped with it
+        assert_eq!(products.ranking, ["rating:desc"]);
+        assert!(!products.synonyms.contains_key("tv"));
+        assert_eq!(products.synonyms["couch"], ["sofa"]);
+        assert!(products.stop_words.is_empty());
+
+        let unsortable = TemplateOverrides { ranking: Some(vec!["name:desc".to_string()]), ..Default::default() };
+        assert!(instantiate("products", &unsortable).is_err());
+        let missing = TemplateOverrides { remove_fields: vec!["color".to_string()], ..Default::default() };
+        assert!(instantiate("products", &missing).is_err());
+        assert!(TemplateOverrides::from_json(Some(r#"{"colour": 1}"#)).is_err());
+    }
+}
@@ -8,7 +8,7 @@ use std::time::{Duration, Instant};
 use serde::{Deserialize, Serialize};
 use crate::types::{
     SearchQuery, SearchResults, SearchHit, Doc, Schema, SchemaField, FieldType,
-    HighlightConfig, SearchConfig,
+    HighlightConfig, SearchConfig, MATCH_ALL,
 };
 use crate::error::{SearchError, SearchResult};
 use crate::capabilities::{CapabilityMatrix, FeatureSupport};
@@ -251,6 +251,8 @@ impl TestDataGenerator {
                     facet: false,
                     sort: false,
                     index: true,
+                    analysis: None,
+                    ui: None,
                 },
                 SchemaField {
                     name: "category".to_string(),
@@ -259,6 +261,8 @@ impl TestDataGenerator {
                     facet: true,
                     sort: true,
                     index: true,
+                    analysis: None,
+                    ui: None,
                 },
                 SchemaField {
                     name: "price".to_string(),
@@ -267,6 +271,8 @@ impl TestDataGenerator {
                     facet: true,
                     sort: true,
                     index: true,
+                    analysis: None,
+                    ui: None,
                 },
                 SchemaField {
                     name: "rating".to_string(),
@@ -275,6 +281,8 @@ impl TestDataGenerator {
                     facet: false,
                     sort: true,
                     index: true,
+                    analysis: None,
+                    ui: None,
                 },
                 SchemaField {
                     name: "in_stock".to_string(),
@@ -283,6 +291,8 @@ impl TestDataGenerator {
                     facet: true,
                     sort: false,
                     index: true,
+                    analysis: None,
+                    ui: None,
                 },
             ],
             TestDomain::News => vec![
@@ -293,6 +303,8 @@ impl TestDataGenerator {
                     facet: false,
                     sort: false,
                     index: true,
+                    analysis: None,
+                    ui: None,
                 },
                 SchemaField {
                     name: "category".to_string(),
@@ -301,6 +313,8 @@ impl TestDataGenerator {
                     facet: true,
                     sort: true,
                     index: true,
+                    analysis: None,
+                    ui: None,
                 },
                 SchemaField {
                     name: "author".to_string(),
@@ -309,6 +323,8 @@ impl TestDataGenerator {
                     facet: true,
                     sort: true,
                     index: true,
+                    analysis: None,
+                    ui: None,
                 },
                 SchemaField {
                     name: "published_at".to_string(),
@@ -317,6 +333,8 @@ impl TestDataGenerator {
                     facet: false,
                     sort: true,
                     index: true,
+                    analysis: None,
+                    ui: None,
                 },
             ],
             TestDomain::Academic => vec![
@@ -327,6 +345,8 @@ impl TestDataGenerator {
                     facet: false,
                     sort: false,
                     index: true,
+                    analysis: None,
+                    ui: None,
                 },
                 SchemaField {
                     name: "subject".to_string(),
@@ -335,6 +355,8 @@ impl TestDataGenerator {
                     facet: true,
                     sort: true,
                     index: true,
+                    analysis: None,
+                    ui: None,
                 },
                 SchemaField {
                     name: "published_year".to_string(),
@@ -343,6 +365,8 @@ impl TestDataGenerator {
                     facet: true,
                     sort: true,
                     index: true,
+                    analysis: None,
+                    ui: None,
                 },
                 SchemaField {
                     name: "citations".to_string(),
@@ -351,6 +375,8 @@ impl TestDataGenerator {
                     facet: false,
                     sort: true,
                     index: true,
+                    analysis: None,
+                    ui: None,
                 },
             ],
             TestDomain::Technical => vec![
@@ -361,6 +387,8 @@ impl TestDataGenerator {
                     facet: false,
                     sort: false,
                     index: true,
+                    analysis: None,
+                    ui: None,
                 },
                 SchemaField {
                     name: "technology".to_string(),
@@ -369,6 +397,8 @@ impl TestDataGenerator {
                     facet: true,
                     sort: true,
                     index: true,
+                    analysis: None,
+                    ui: None,
                 },
                 SchemaField {
                     name: "complexity".to_string(),
@@ -377,6 +407,8 @@ impl TestDataGenerator {
                     facet: true,
                     sort: true,
                     index: true,
+                    analysis: None,
+                    ui: None,
                 },
             ],
         };
@@ -506,6 +538,7 @@ impl UniversalTestQueries {
                     pre_tag: Some("<mark>".to_string()),
                     post_tag: Some("</mark>".to_string()),
                     max_length: Some(200),
+                    offsets: false,
                 }),
                 config: None,
             },
@@ -528,6 +561,7 @@ impl UniversalTestQueries {
                     pre_tag: Some("<em>".to_string()),
                     post_tag: Some("</em>".to_string()),
                     max_length: Some(150),
+                    offsets: false,
                 }),
                 config: Some(SearchConfig {
                     timeout_ms: Some(5000),
@@ -537,6 +571,14 @@ impl UniversalTestQueries {
                     typo_tolerance: Some(true),
                     exact_match_boost: Some(1.5),
                     provider_params: None,
+                    explain: false,
+                    consistency_token: None,
+                    sample: None,
+                    normalization: None,
+                    fuzziness: None,
+                    minimum_should_match: None,
+                    cursor: None,
+                    exact_total: false,
                 }),
             },
         ]
@@ -595,6 +637,50 @@ impl UniversalTestQueries {
             },
         ]
     }
+
+    /// Every way of asking for all documents
+    ///
+    /// A provider must answer each of these with the whole index.
+    pub fn match_all_queries() -> Vec<SearchQuery> {
+        let per_page = |q: Option<&str>| SearchQuery {
+            q: q.map(str::to_string),
+            per_page: Some(1000),
+            ..SearchQuery::match_all()
+        };
+        vec![per_page(None), per_page(Some("")), per_page(Some("  ")), per_page(Some(MATCH_ALL))]
+    }
+
+    /// Check the results of [`Self::match_all_queries`] against an index of `document_count` documents
+    pub fn assert_match_all(document_count: usize, results: &[SearchResults]) -> Vec<AssertionResult> {
+        let ids = |r: &SearchResults| {
+            let mut ids: Vec<_> = r.hits.iter().map(|h| h.id.clone()).collect();
+            ids.sort();
+            ids
+        };
+        let expected_ids = results.first().map(ids).unwrap_or_default();
+
+        results
+            .iter()
+            .enumerate()
+            .flat_map(|(i, r)| {
+                let total = r.total.map_or_else(|| "none".to_string(), |t| t.to_string());
+                [
+                    AssertionResult {
+                        description: format!("match-all variant {} returns every document", i),
+                        passed: r.total == Some(document_count as u32),
+                        expected: document_count.to_string(),
+                        actual: total,
+                    },
+                    AssertionResult {
+                        description: format!("match-all variant {} returns the same hits as variant 0", i),
+                        passed: ids(r) == expected_ids,
+                        expected: format!("{} hits", expected_ids.len()),
+                        actual: format!("{} hits", r.hits.len()),
+                    },
+                ]
+            })
+            .collect()
+    }
 }
 
 /// Test result analysis and reporting
@@ -710,6 +796,7 @@ pub struct TestReport {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::QueryBuilder;
     
     #[test]
     fn test_data_generator() {
@@ -756,4 +843,36 @@ mod tests {
         assert!(!highlighting_queries.is_empty());
         assert!(highlighting_queries.iter().any(|q| q.highlight.is_some()));
     }
+
+    #[test]
+    fn test_match_all_variants() {
+        let queries = UniversalTestQueries::match_all_queries();
+        assert!(queries.iter().all(SearchQuery::is_match_all));
+        assert!(!QueryBuilder::new().query("*books").build().is_match_all());
+
+        let hit = |id: &str| SearchHit { id: id.to_string(), score: Some(1.0), content: None, highlights: None, explanation: None, distance_meters: None };
+        let results = |ids: &[&str]| SearchResults {
+            total: Some(ids.len() as u32),
+            page: None,
+            per_page: None,
+            hits: ids.iter().map(|id| hit(id)).collect(),
+            facets: None,
+            took_ms: None,
+            truncated: false,
+            request_id: None,
+            groups: None,
+            next_cursor: None,
+            total_exact: None,
+            facet_metadata: Vec::new(),
+            relaxations: Vec::new(),
+            original_query: None,
+            corrected_query: None,
+        };
+
+        let same = [results(&["a", "b"]), results(&["b", "a"])];
+        assert!(UniversalTestQueries::assert_match_all(2, &same).iter().all(|a| a.passed));
+
+        let differ = [results(&["a", "b"]), results(&["a"])];
+        assert_eq!(UniversalTestQueries::assert_match_all(2, &differ).iter().filter(|a| !a.passed).count(), 2);
+    }
 }
\ No newline at end of file
@@ -5,6 +5,10 @@
 
 use std::collections::HashMap;
 use serde::{Deserialize, Serialize};
+use crate::analysis::{FieldAnalysis, Normalization};
+use crate::facets::{FacetSpec, RangeBucket};
+use crate::query::{Fuzziness, MinimumShouldMatch};
+use crate::ui_metadata::{FacetMetadata, FieldUi};
 
 // Type aliases for common types (these will be replaced with WIT types at the component level)
 pub type IndexName = String;
@@ -25,10 +29,13 @@ pub struct HighlightConfig {
     pub pre_tag: Option<String>,
     pub post_tag: Option<String>,
     pub max_length: Option<u32>,
+    /// Return match offsets instead of tagged snippets (see `highlight::MatchOffset`)
+    #[serde(default)]
+    pub offsets: bool,
 }
 
 /// Advanced search tuning
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct SearchConfig {
     pub timeout_ms: Option<u32>,
     pub boost_fields: Vec<(String, f32)>,
@@ -37,6 +44,30 @@ pub struct SearchConfig {
     pub typo_tolerance: Option<bool>,
     pub exact_match_boost: Option<f32>,
     pub provider_params: Option<Json>,
+    /// Request per-hit relevance explanations (see `explain::Explanation`)
+    #[serde(default)]
+    pub explain: bool,
+    /// Token from an earlier write; the search waits until that write is applied
+    #[serde(default)]
+    pub consistency_token: Option<String>,
+    /// Return this many randomly chosen matches instead of the top-ranked page
+    #[serde(default)]
+    pub sample: Option<u32>,
+    /// Text folding for this query; steps the provider lacks are applied to `q`
+    #[serde(default)]
+    pub normalization: Option<Normalization>,
+    /// Typos allowed per term; takes precedence over `typo_tolerance`
+    #[serde(default)]
+    pub fuzziness: Option<Fuzziness>,
+    /// How many of the plain query terms a document must contain
+    #[serde(default)]
+    pub minimum_should_match: Option<MinimumShouldMatch>,
+    /// `next_cursor` of an earlier page; the search continues after it, ignoring `page` and `offset`
+    #[serde(default)]
+    pub cursor: Option<String>,
+    /// Count every match instead of an estimate or a capped total; slower on large result sets
+    #[serde(default)]
+    pub exact_total: bool,
 }
 
 /// Search request
@@ -53,6 +84,49 @@ pub struct SearchQuery {
     pub config: Option<SearchConfig>,
 }
 
+/// Query text that matches every document, same as a missing or blank `q`
+pub const MATCH_ALL: &str = "*";
+
+/// Full-text part of a search request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryText<'a> {
+    /// Every document matches; filters, sorting and paging still apply
+    MatchAll,
+
+    /// Text to match, trimmed
+    Text(&'a str),
+}
+
+impl<'a> QueryText<'a> {
+    /// Classify a raw `q`, for callers holding a query type other than [`SearchQuery`]
+    pub fn parse(q: Option<&'a str>) -> Self {
+        match q.map(str::trim) {
+            None | Some("") | Some(MATCH_ALL) => QueryText::MatchAll,
+            Some(text) => QueryText::Text(text),
+        }
+    }
+}
+
+impl SearchQuery {
+    /// Query matching every document
+    pub fn match_all() -> Self {
+        QueryBuilder::new().match_all().build()
+    }
+
+    /// Full-text part of the query
+    ///
+    /// A missing `q`, a blank one and [`MATCH_ALL`] all mean match-all, so every
+    /// provider returns the whole index for them instead of each applying its
+    /// own convention.
+    pub fn text(&self) -> QueryText<'_> {
+        QueryText::parse(self.q.as_deref())
+    }
+
+    pub fn is_match_all(&self) -> bool {
+        self.text() == QueryText::MatchAll
+    }
+}
+
 /// Search hit
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchHit {
@@ -60,6 +134,12 @@ pub struct SearchHit {
     pub score: Option<f64>,
     pub content: Option<Json>,
     pub highlights: Option<Json>,
+    /// Normalized scoring explanation, present when the query set `explain`
+    #[serde(default)]
+    pub explanation: Option<Json>,
+    /// Meters from the origin of a `geo_distance` sort (see `geo::GeoDistanceSort`)
+    #[serde(default)]
+    pub distance_meters: Option<f64>,
 }
 
 /// Search result set
@@ -71,6 +151,44 @@ pub struct SearchResults {
     pub hits: Vec<SearchHit>,
     pub facets: Option<Json>,
     pub took_ms: Option<u32>,
+    /// Set when hits were dropped to stay under the response size limit
+    #[serde(default)]
+    pub truncated: bool,
+    /// ID of the operation that produced the results, also sent as `X-Request-Id`
+    #[serde(default)]
+    pub request_id: Option<String>,
+    /// Hits per group when the query collapsed or grouped results
+    #[serde(default)]
+    pub groups: Option<Vec<SearchGroup>>,
+    /// Opaque position of the following page; unset on the last page
+    #[serde(default)]
+    pub next_cursor: Option<String>,
+    /// Whether `total` counts every match, rather than an estimate or a lower bound; unset when unknown
+    #[serde(default)]
+    pub total_exact: Option<bool>,
+    /// Display metadata of the requested facets, in display order
+    #[serde(default)]
+    pub facet_metadata: Vec<FacetMetadata>,
+    /// Rescue steps applied after the query found nothing (see `rescue::Relaxation`)
+    #[serde(default)]
+    pub relaxations: Vec<String>,
+    /// Query as typed, when the results are for its spelling correction (see `spellfix`)
+    #[serde(default)]
+    pub original_query: Option<String>,
+    /// Correction of `original_query` the results are for
+    #[serde(default)]
+    pub corrected_query: Option<String>,
+}
+
+/// Matches sharing a value of the grouping field
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchGroup {
+    /// Grouping field values; strings as is, other values as JSON
+    pub key: Vec<String>,
+    /// Matches in the group, when the provider counts them
+    pub total: Option<u32>,
+    /// Best hits of the group
+    pub hits: Vec<SearchHit>,
 }
 
 /// Field schema types
@@ -83,10 +201,14 @@ pub enum FieldType {
     Boolean,
     Date,
     GeoPoint,
+    /// 64-bit integer, for IDs and timestamps past the 32-bit range
+    Int64,
+    /// Exact decimal number, such as an amount of money, stored as its text
+    Decimal,
 }
 
 /// Field definition
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct SchemaField {
     pub name: String,
     pub field_type: FieldType,
@@ -94,10 +216,18 @@ pub struct SchemaField {
     pub facet: bool,
     pub sort: bool,
     pub index: bool,
+
+    /// Tokenizer and other analysis hints for text fields
+    #[serde(default)]
+    pub analysis: Option<FieldAnalysis>,
+
+    /// How search UIs display the field's facet
+    #[serde(default)]
+    pub ui: Option<FieldUi>,
 }
 
 /// Index schema
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Schema {
     pub fields: Vec<SchemaField>,
     pub primary_key: Option<String>,
@@ -310,6 +440,41 @@ impl QueryBuilder {
         self.query.q = Some(q.into());
         self
     }
+
+    /// Match every document
+    pub fn match_all(mut self) -> Self {
+        self.query.q = Some(MATCH_ALL.to_string());
+        self
+    }
+
+    /// Append a clause to the query string
+    fn push_clause(mut self, clause: String) -> Self {
+        self.query.q = Some(match self.query.q.take().filter(|q| !q.trim().is_empty() && q != MATCH_ALL) {
+            Some(q) => format!("{} {}", q, clause),
+            None => clause,
+        });
+        self
+    }
+
+    /// Match the words of `phrase` in order
+    pub fn phrase<S: AsRef<str>>(self, phrase: S) -> Self {
+        self.push_clause(format!("\"{}\"", phrase.as_ref().replace('"', "")))
+    }
+
+    /// Match the words of `phrase` within `slop` positions of each other
+    pub fn proximity<S: AsRef<str>>(self, phrase: S, slop: u32) -> Self {
+        self.push_clause(format!("\"{}\"~{}", phrase.as_ref().replace('"', ""), slop))
+    }
+
+    /// Require `term` in every hit
+    pub fn require<S: AsRef<str>>(self, term: S) -> Self {
+        self.push_clause(format!("+{}", term.as_ref()))
+    }
+
+    /// Drop hits containing `term`
+    pub fn exclude<S: AsRef<str>>(self, term: S) -> Self {
+        self.push_clause(format!("-{}", term.as_ref()))
+    }
     
     /// Add a filter
     pub fn filter<S: Into<String>>(mut self, filter: S) -> Self {
@@ -349,6 +514,13 @@ impl QueryBuilder {
         self
     }
     
+    /// Add a numeric range facet counting documents per bucket
+    pub fn range_facet<S: Into<String>>(mut self, field: S, buckets: Vec<RangeBucket>) -> Self {
+        let spec = FacetSpec::Range { field: field.into(), buckets };
+        self.query.facets.push(spec.to_string());
+        self
+    }
+    
     /// Set pagination
     pub fn page(mut self, page: u32, per_page: u32) -> Self {
         self.query.page = Some(page);
@@ -489,6 +661,8 @@ impl SchemaBuilder {
             facet,
             sort,
             index,
+            analysis: None,
+            ui: None,
         });
         self
     }
@@ -498,6 +672,15 @@ impl SchemaBuilder {
         self.field(name.into(), FieldType::Text, false, false, false, true)
     }
     
+    /// Add a text field with analysis hints, such as a CJK tokenizer
+    pub fn analyzed_text_field<S: Into<String>>(mut self, name: S, analysis: FieldAnalysis) -> Self {
+        self = self.text_field(name);
+        if let Some(field) = self.fields.last_mut() {
+            field.analysis = Some(analysis);
+        }
+        self
+    }
+    
     /// Add a keyword field
     pub fn keyword_field<S: Into<String>>(self, name: S) -> Self {
         self.field(name.into(), FieldType::Keyword, false, true, true, true)
@@ -508,6 +691,16 @@ impl SchemaBuilder {
         self.field(name.into(), FieldType::Integer, false, true, true, true)
     }
     
+    /// Add a 64-bit integer field
+    pub fn int64_field<S: Into<String>>(self, name: S) -> Self {
+        self.field(name.into(), FieldType::Int64, false, true, true, true)
+    }
+    
+    /// Add an exact decimal field; faceted, but not sortable as a number
+    pub fn decimal_field<S: Into<String>>(self, name: S) -> Self {
+        self.field(name.into(), FieldType::Decimal, false, true, false, true)
+    }
+    
     /// Add a float field
     pub fn float_field<S: Into<String>>(self, name: S) -> Self {
         self.field(name.into(), FieldType::Float, false, true, true, true)
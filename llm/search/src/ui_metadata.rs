@@ -0,0 +1,212 @@
+//! Display metadata for search UIs
+//!
+//! A schema field can carry a [`FieldUi`] with the label, filter widget and
+//! panel position of its facet. Providers keep it with the index, and
+//! searches that request facets return a [`FacetMetadata`] per facet field,
+//! in display order, so a frontend can render the filter panel of any index
+//! without knowing its fields in advance. Facets of fields without metadata
+//! are labelled with the field name and get a widget suited to the facet.
+//!
+//! Elasticsearch and OpenSearch keep the metadata in the mapping `_meta` and
+//! Typesense in the collection metadata, both under [`UI_META_KEY`].
+//! Meilisearch has no index metadata, so it is kept in worker memory with
+//! [`remember`] and read back with [`recall`].
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use crate::error::{SearchError, SearchResult};
+use crate::facets::FacetSpec;
+use crate::types::{FieldType, Schema};
+
+/// Key of the field UI metadata in a provider's index metadata
+pub const UI_META_KEY: &str = "golem_ui";
+
+/// Filter control a frontend renders for a facet
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FacetWidget {
+    /// Several values can be selected
+    Checkbox,
+
+    /// One value can be selected
+    Radio,
+
+    /// One value, picked from a list
+    Dropdown,
+
+    /// On or off, for boolean fields
+    Toggle,
+
+    /// Numeric bounds
+    Range,
+
+    /// Start and end dates
+    DateRange,
+}
+
+/// Display metadata of a schema field
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FieldUi {
+    /// Label shown instead of the field name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub widget: Option<FacetWidget>,
+
+    /// Position in the filter panel, lowest first; fields without one come last
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sort_priority: Option<i32>,
+}
+
+/// How a frontend should display one facet of a search result
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FacetMetadata {
+    pub field: String,
+    pub display_name: String,
+    pub widget: FacetWidget,
+    pub sort_priority: Option<i32>,
+}
+
+/// Parse field UI metadata passed as JSON
+pub fn parse_field_ui(json: Option<&str>) -> SearchResult<Option<FieldUi>> {
+    json.filter(|j| !j.trim().is_empty())
+        .map(|j| serde_json::from_str(j).map_err(|e| SearchError::invalid_query(format!("Invalid field UI metadata: {}", e))))
+        .transpose()
+}
+
+/// Widget for a facet whose field sets none
+fn default_widget(spec: &FacetSpec, field_type: Option<&FieldType>) -> FacetWidget {
+    match (spec, field_type) {
+        (FacetSpec::Range { .. } | FacetSpec::Stats(_), _) => FacetWidget::Range,
+        (FacetSpec::DateHistogram { .. }, _) => FacetWidget::DateRange,
+        (FacetSpec::Terms(_), Some(FieldType::Boolean)) => FacetWidget::Toggle,
+        (FacetSpec::Terms(_), _) => FacetWidget::Checkbox,
+    }
+}
+
+/// Display metadata of the fields of the requested `facets`, in display order
+///
+/// Facets that do not parse are left out; the search reports them.
+pub fn facet_metadata(schema: &Schema, facets: &[String]) -> Vec<FacetMetadata> {
+    let mut seen = HashSet::new();
+    let mut metadata: Vec<FacetMetadata> = facets
+        .iter()
+        .filter_map(|facet| FacetSpec::parse(facet).ok())
+        .filter(|spec| seen.insert(spec.field().to_string()))
+        .map(|spec| {
+            let field = schema.fields.iter().find(|f| f.name == spec.field());
+            let ui = field.and_then(|f| f.ui.clone()).unwrap_or_default();
+            FacetMetadata {
+                field: spec.field().to_string(),
+                display_name: ui.display_name.unwrap_or_else(|| spec.field().to_string()),
+                widget: ui.widget.unwrap_or_else(|| default_widget(&spec, field.map(|f| &f.field_type))),
+                sort_priority: ui.sort_priority,
+            }
+        })
+        .collect();
+    // Stable, so facets without a priority keep the order they were requested in
+    metadata.sort_by_key(|m| (m.sort_priority.is_none(), m.sort_priority));
+    metadata
+}
+
+/// The UI metadata of a schema as an object keyed by field name, or `None` when no field has any
+pub fn to_meta(schema: &Schema) -> Option<Value> {
+    let fields: Map<String, Value> = schema
+        .fields
+        .iter()
+        .filter_map(|field| Some((field.name.clone(), serde_json::to_value(field.ui.as_ref()?).ok()?)))
+        .collect();
+    (!fields.is_empty()).then_some(Value::Object(fields))
+}
+
+/// Set the UI metadata of schema fields from an object written by [`to_meta`]
+///
+/// Entries for fields the schema lacks, or that do not parse, are ignored.
+pub fn apply_meta(schema: &mut Schema, meta: Option<&Value>) {
+    let Some(meta) = meta.and_then(Value::as_object) else {
+        return;
+    };
+    for field in &mut schema.fields {
+        if let Some(ui) = meta.get(&field.name).and_then(|ui| serde_json::from_value(ui.clone()).ok()) {
+            field.ui = Some(ui);
+        }
+    }
+}
+
+fn registry() -> MutexGuard<'static, HashMap<String, Value>> {
+    static REGISTRY: OnceLock<Mutex<HashMap<String, Value>>> = OnceLock::new();
+    REGISTRY
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Keep the UI metadata of an index's schema in worker memory
+pub fn remember(index: &str, schema: &Schema) {
+    match to_meta(schema) {
+        Some(meta) => registry().insert(index.to_string(), meta),
+        None => registry().remove(index),
+    };
+}
+
+/// The UI metadata kept by [`remember`], as written by [`to_meta`]
+pub fn recall(index: &str) -> Option<Value> {
+    registry().get(index).cloned()
+}
+
+/// Drop the UI metadata of a deleted index
+pub fn forget(index: &str) {
+    registry().remove(index);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::SchemaField;
+
+    fn field(name: &str, field_type: FieldType, ui: Option<FieldUi>) -> SchemaField {
+        SchemaField { name: name.to_string(), field_type, required: false, facet: true, sort: false, index: true, analysis: None, ui }
+    }
+
+    fn schema() -> Schema {
+        let brand = FieldUi { display_name: Some("Brand".to_string()), sort_priority: Some(2), ..FieldUi::default() };
+        let price = FieldUi { widget: Some(FacetWidget::Dropdown), sort_priority: Some(1), ..FieldUi::default() };
+        Schema {
+            fields: vec![
+                field("brand", FieldType::Keyword, Some(brand)),
+                field("price", FieldType::Float, Some(price)),
+                field("in_stock", FieldType::Boolean, None),
+                field("color", FieldType::Keyword, None),
+            ],
+            primary_key: None,
+        }
+    }
+
+    #[test]
+    fn test_facet_metadata_order_and_defaults() {
+        let facets = ["color", "in_stock", "brand", "price:[0-25, 25+]", "price:stats"].map(String::from);
+        let metadata = facet_metadata(&schema(), &facets);
+        let fields: Vec<&str> = metadata.iter().map(|m| m.field.as_str()).collect();
+        assert_eq!(fields, ["price", "brand", "color", "in_stock"]);
+        assert_eq!(metadata[1].display_name, "Brand");
+        assert_eq!(metadata[1].widget, FacetWidget::Checkbox);
+        assert_eq!(metadata[2].display_name, "color");
+        assert_eq!(metadata[3].widget, FacetWidget::Toggle);
+    }
+
+    #[test]
+    fn test_meta_round_trip() {
+        let meta = to_meta(&schema()).unwrap();
+        assert_eq!(meta["brand"], serde_json::json!({ "display_name": "Brand", "sort_priority": 2 }));
+        assert!(meta.get("color").is_none());
+
+        let mut read = schema();
+        read.fields.iter_mut().for_each(|f| f.ui = None);
+        apply_meta(&mut read, Some(&meta));
+        assert_eq!(read, schema());
+    }
+}
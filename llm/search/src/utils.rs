@@ -252,12 +252,8 @@ pub mod query_utils {
     
     /// Validate that a query is well-formed
     pub fn validate_query(query: &SearchQuery) -> SearchResult<()> {
-        // Check for empty or invalid query string
+        // A blank query string matches all documents; only its length is checked
         if let Some(ref q) = query.q {
-            if q.trim().is_empty() {
-                return Err(SearchError::invalid_query("Query string cannot be empty"));
-            }
-            
             if q.len() > 10000 {
                 return Err(SearchError::invalid_query("Query string too long"));
             }
@@ -317,6 +313,7 @@ pub mod query_utils {
             pre_tag: Some("<mark>".to_string()),
             post_tag: Some("</mark>".to_string()),
             max_length: Some(200),
+            offsets: false,
         }
     }
     
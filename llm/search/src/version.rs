@@ -0,0 +1,153 @@
+//! Server version detection and feature gating
+//!
+//! Some features only exist on recent servers: Meilisearch vector search,
+//! Elasticsearch's top-level `knn`, Typesense schema alterations. Older servers
+//! reject them with a bare 400 that does not say why. Providers detect the
+//! server version once per worker and endpoint, and check it with [`require`]
+//! before using such a feature, turning the 400 into `SearchError::Unsupported`.
+
+use std::future::Future;
+use log::{debug, warn};
+use crate::cache::{self, CacheKind};
+use crate::error::{SearchError, SearchResult};
+
+/// A `major.minor.patch` server version
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ServerVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl ServerVersion {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self { major, minor, patch }
+    }
+
+    /// Parse version strings such as `8.11.1`, `v0.25.2` or `1.6.0-rc.1`
+    ///
+    /// Missing minor or patch components count as zero; pre-release and build
+    /// suffixes are ignored.
+    pub fn parse(version: &str) -> Option<Self> {
+        let version = version.trim().trim_start_matches(['v', 'V']);
+        let core = version.split(['-', '+']).next()?;
+
+        let mut parts = core.split('.').map(|p| p.parse::<u32>());
+        let major = parts.next()?.ok()?;
+        let minor = parts.next().unwrap_or(Ok(0)).ok()?;
+        let patch = parts.next().unwrap_or(Ok(0)).ok()?;
+        Some(Self { major, minor, patch })
+    }
+}
+
+impl std::fmt::Display for ServerVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// A feature that needs a minimum server version
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VersionedFeature {
+    pub provider: &'static str,
+    pub name: &'static str,
+    pub min_version: ServerVersion,
+}
+
+/// Meilisearch `vector`/`hybrid` search, stable since 1.6
+pub const MEILISEARCH_VECTOR_SEARCH: VersionedFeature = VersionedFeature {
+    provider: "meilisearch",
+    name: "vector search",
+    min_version: ServerVersion::new(1, 6, 0),
+};
+
+/// Elasticsearch top-level `knn` search option, added in 8.0
+pub const ELASTIC_KNN: VersionedFeature = VersionedFeature {
+    provider: "elasticsearch",
+    name: "kNN search",
+    min_version: ServerVersion::new(8, 0, 0),
+};
+
+/// Typesense `PATCH /collections/{name}` schema changes, added in 0.23
+pub const TYPESENSE_SCHEMA_ALTER: VersionedFeature = VersionedFeature {
+    provider: "typesense",
+    name: "schema alteration",
+    min_version: ServerVersion::new(0, 23, 0),
+};
+
+/// Typesense numeric facet ranges in `facet_by`, added in 0.25
+pub const TYPESENSE_RANGE_FACETS: VersionedFeature = VersionedFeature {
+    provider: "typesense",
+    name: "range facets",
+    min_version: ServerVersion::new(0, 25, 0),
+};
+
+/// Check that the server supports `feature`
+///
+/// An unknown version is given the benefit of the doubt, leaving the decision
+/// to the server.
+pub fn require(feature: &VersionedFeature, version: Option<ServerVersion>) -> SearchResult<()> {
+    match version {
+        Some(version) if version < feature.min_version => {
+            warn!(
+                "{} {} does not support {} (needs {} or later)",
+                feature.provider, version, feature.name, feature.min_version
+            );
+            Err(SearchError::Unsupported)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Server version for `endpoint`, detected with `detect` on first use and then cached
+///
+/// Detection failures are not cached, and yield `None`.
+pub async fn cached_version<F, Fut, E>(provider: &str, endpoint: &str, detect: F) -> Option<ServerVersion>
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<ServerVersion, E>>,
+    E: std::fmt::Display,
+{
+    let key = format!("{}/version@{}", provider, endpoint);
+    if let Some(version) = cache::global().get::<ServerVersion>(CacheKind::Client, &key) {
+        return Some(*version);
+    }
+
+    match detect().await {
+        Ok(version) => {
+            debug!("Detected {} {} at {}", provider, version, endpoint);
+            cache::global().insert(CacheKind::Client, &key, version, key.len() + std::mem::size_of::<ServerVersion>());
+            Some(version)
+        }
+        Err(e) => {
+            debug!("Could not detect {} version at {}: {}", provider, endpoint, e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_versions() {
+        assert_eq!(ServerVersion::parse("8.11.1"), Some(ServerVersion::new(8, 11, 1)));
+        assert_eq!(ServerVersion::parse("v0.25.2"), Some(ServerVersion::new(0, 25, 2)));
+        assert_eq!(ServerVersion::parse("1.6.0-rc.1"), Some(ServerVersion::new(1, 6, 0)));
+        assert_eq!(ServerVersion::parse("2"), Some(ServerVersion::new(2, 0, 0)));
+        assert_eq!(ServerVersion::parse("latest"), None);
+        assert_eq!(ServerVersion::new(1, 10, 0).to_string(), "1.10.0");
+    }
+
+    #[test]
+    fn test_require_gates_on_minimum_version() {
+        assert!(require(&ELASTIC_KNN, Some(ServerVersion::new(8, 0, 0))).is_ok());
+        assert!(require(&ELASTIC_KNN, None).is_ok());
+        assert!(matches!(
+            require(&ELASTIC_KNN, Some(ServerVersion::new(7, 17, 9))),
+            Err(SearchError::Unsupported)
+        ));
+        assert!(ServerVersion::new(1, 10, 0) > MEILISEARCH_VECTOR_SEARCH.min_version);
+    }
+}
@@ -0,0 +1,231 @@
+//! Change summaries for watching an index
+//!
+//! A cache in front of an index has to learn when the index changed, and
+//! re-running its queries to find out defeats the cache. An index watch polls
+//! what the backend reports about writes, which is cheap, and summarizes the
+//! changes since the previous poll as documents added, updated and deleted.
+//!
+//! What the backend reports varies. Meilisearch lists its finished indexing
+//! tasks with the documents each one indexed or deleted. Elasticsearch and
+//! OpenSearch keep index and delete counters per index. Typesense reports only
+//! the number of documents, so its summaries see the net change: updates go
+//! unnoticed and an insert offset by a delete cancels out.
+//! [`ChangeSummary::updates_counted`] tells the two kinds apart.
+//!
+//! Every summary carries a [`WatchCursor`]; a watch opened with it resumes
+//! where the summary ended.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::error::{SearchError, SearchResult};
+
+/// Documents written and deleted, either since the index was created or between two polls
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WriteCounters {
+    /// Documents added or replaced
+    pub indexed: u64,
+    pub deleted: u64,
+}
+
+impl WriteCounters {
+    /// Primary-shard counters from an Elasticsearch or OpenSearch `_stats/indexing` response
+    pub fn from_index_stats(stats: &Value) -> Option<Self> {
+        let indexing = stats.pointer("/_all/primaries/indexing")?;
+        Some(Self {
+            indexed: indexing.get("index_total")?.as_u64()?,
+            deleted: indexing.get("delete_total")?.as_u64()?,
+        })
+    }
+
+    /// Writes counted since `earlier`, or `None` when the counters were reset in between
+    ///
+    /// Elasticsearch and OpenSearch keep these counters in memory, so they
+    /// restart when a shard moves or a node restarts.
+    pub fn since(&self, earlier: &Self) -> Option<Self> {
+        Some(Self {
+            indexed: self.indexed.checked_sub(earlier.indexed)?,
+            deleted: self.deleted.checked_sub(earlier.deleted)?,
+        })
+    }
+
+    pub fn add(&mut self, other: Self) {
+        self.indexed += other.indexed;
+        self.deleted += other.deleted;
+    }
+}
+
+/// Position of a watch in an index's history
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WatchCursor {
+    pub provider: String,
+    pub index: String,
+    pub documents: u64,
+
+    /// Write counters at this position (Elasticsearch, OpenSearch)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub counters: Option<WriteCounters>,
+
+    /// Newest finished indexing task at this position (Meilisearch)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task: Option<u64>,
+}
+
+impl WatchCursor {
+    pub fn new(provider: &str, index: &str, documents: u64) -> Self {
+        Self { provider: provider.to_string(), index: index.to_string(), documents, counters: None, task: None }
+    }
+
+    pub fn encode(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    /// Decode a cursor and check that it was issued for `index` of `provider`
+    pub fn parse_for(cursor: &str, provider: &str, index: &str) -> SearchResult<Self> {
+        let cursor: Self = serde_json::from_str(cursor)
+            .map_err(|e| SearchError::invalid_query(format!("Invalid watch cursor: {}", e)))?;
+        if cursor.provider != provider || cursor.index != index {
+            return Err(SearchError::invalid_query(format!(
+                "Watch cursor was issued for {} on {}, not {} on {}",
+                cursor.index, cursor.provider, index, provider
+            )));
+        }
+        Ok(cursor)
+    }
+}
+
+/// Changes to an index between two positions
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChangeSummary {
+    pub index: String,
+    pub added: u64,
+    pub updated: u64,
+    pub deleted: u64,
+
+    /// Whether the backend reported writes; otherwise the counts are the net change in documents
+    pub updates_counted: bool,
+
+    /// Cursor to resume from, passed as `since` to a new watch
+    pub cursor: String,
+}
+
+impl ChangeSummary {
+    /// Summary marking `position` as the start of a watch
+    pub fn start(position: &WatchCursor) -> Self {
+        Self { index: position.index.clone(), cursor: position.encode(), ..Self::default() }
+    }
+
+    /// Changes from `before` to `after`, given the writes applied in between when the backend reports them
+    ///
+    /// The documents added are those the net change and the deletes account
+    /// for; the other writes replaced existing documents.
+    pub fn between(before: &WatchCursor, after: &WatchCursor, writes: Option<WriteCounters>) -> Self {
+        let net = after.documents as i64 - before.documents as i64;
+        let mut summary = Self::start(after);
+        match writes {
+            Some(writes) => {
+                summary.added = (net + writes.deleted as i64).clamp(0, writes.indexed as i64) as u64;
+                summary.updated = writes.indexed - summary.added;
+                summary.deleted = writes.deleted;
+                summary.updates_counted = true;
+            }
+            None => {
+                summary.added = net.max(0) as u64;
+                summary.deleted = (-net).max(0) as u64;
+            }
+        }
+        summary
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added == 0 && self.updated == 0 && self.deleted == 0
+    }
+}
+
+/// One page of a Meilisearch task list, newest first
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TaskPage {
+    /// Documents indexed and deleted by the page's tasks newer than the cursor
+    pub writes: WriteCounters,
+
+    /// Newest task uid on the page
+    pub newest: Option<u64>,
+
+    /// `from` of the next page, `None` once the list reaches the cursor or ends
+    pub next: Option<u64>,
+}
+
+impl TaskPage {
+    /// Read a `GET /tasks` response, counting the tasks after task `after`
+    pub fn from_meilisearch(body: &Value, after: Option<u64>) -> Self {
+        let mut page = Self::default();
+        let mut reached = false;
+        for task in body.get("results").and_then(Value::as_array).into_iter().flatten() {
+            let Some(uid) = task.get("uid").and_then(Value::as_u64) else {
+                continue;
+            };
+            page.newest = Some(page.newest.map_or(uid, |newest| newest.max(uid)));
+            if after.is_some_and(|after| uid <= after) {
+                reached = true;
+                continue;
+            }
+            let count = |field: &str| task.pointer(&format!("/details/{}", field)).and_then(Value::as_u64).unwrap_or(0);
+            page.writes.indexed += count("indexedDocuments") + count("editedDocuments");
+            page.writes.deleted += count("deletedDocuments");
+        }
+        if !reached {
+            page.next = body.get("next").and_then(Value::as_u64);
+        }
+        page
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_summary_splits_writes_into_adds_and_updates() {
+        let mut before = WatchCursor::new("elasticsearch", "products", 100);
+        before.counters = Some(WriteCounters { indexed: 500, deleted: 20 });
+        let cursor = WatchCursor::parse_for(&before.encode(), "elasticsearch", "products").unwrap();
+        assert_eq!(cursor, before);
+        assert!(WatchCursor::parse_for(&before.encode(), "opensearch", "products").is_err());
+
+        let mut after = WatchCursor::new("elasticsearch", "products", 105);
+        after.counters = Some(WriteCounters { indexed: 512, deleted: 23 });
+        let writes = after.counters.unwrap().since(&before.counters.unwrap());
+        let summary = ChangeSummary::between(&before, &after, writes);
+        assert_eq!((summary.added, summary.updated, summary.deleted), (8, 4, 3));
+        assert!(summary.updates_counted);
+        assert_eq!(WatchCursor::parse_for(&summary.cursor, "elasticsearch", "products").unwrap(), after);
+
+        // Counters restarted: only the net change is known
+        let reset = WriteCounters { indexed: 2, deleted: 0 };
+        assert_eq!(reset.since(&before.counters.unwrap()), None);
+        let shrunk = WatchCursor::new("elasticsearch", "products", 90);
+        let summary = ChangeSummary::between(&before, &shrunk, None);
+        assert_eq!((summary.added, summary.updated, summary.deleted), (0, 0, 10));
+        assert!(!summary.updates_counted);
+        assert!(ChangeSummary::start(&shrunk).is_empty());
+    }
+
+    #[test]
+    fn test_task_page_stops_at_cursor() {
+        let body = json!({
+            "results": [
+                {"uid": 42, "type": "documentDeletion", "details": {"deletedDocuments": 2}},
+                {"uid": 41, "type": "documentAdditionOrUpdate", "details": {"indexedDocuments": 10}},
+                {"uid": 40, "type": "documentAdditionOrUpdate", "details": {"indexedDocuments": 99}}
+            ],
+            "next": 39
+        });
+        let page = TaskPage::from_meilisearch(&body, Some(40));
+        assert_eq!(page.writes, WriteCounters { indexed: 10, deleted: 2 });
+        assert_eq!((page.newest, page.next), (Some(42), None));
+
+        let page = TaskPage::from_meilisearch(&body, Some(12));
+        assert_eq!(page.writes.indexed, 109);
+        assert_eq!(page.next, Some(39));
+    }
+}
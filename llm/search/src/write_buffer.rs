@@ -0,0 +1,350 @@
+//! Write-ahead buffering of upserts
+//!
+//! Bursty writers that upsert a few documents per call pay one HTTP request
+//! per call. With `SEARCH_WRITE_BUFFER_DOCS` set, upserts are collected per
+//! index in the worker's state instead and written as one batch once the
+//! index holds that many documents, `SEARCH_WRITE_BUFFER_BYTES` of content,
+//! or documents older than `SEARCH_WRITE_BUFFER_MAX_AGE_MS`. A worker has no
+//! timers, so age is only checked when another write or a `flush_writes`
+//! call comes in; callers that stop writing should flush explicitly.
+//!
+//! The buffer lives in worker memory, which Golem restores after a restart
+//! by replaying the worker, so acknowledged upserts are not lost. A batch that
+//! fails to write is put back and retried by the next flush. Buffered
+//! documents are not searchable until flushed; deletes drop any buffered
+//! version of the documents they remove so a later flush does not revive them.
+
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use std::time::{Duration, Instant};
+use crate::config::env_helpers::get_env_parsed;
+use crate::types::Doc;
+
+/// Environment variable holding the documents per index that trigger a flush; enables buffering
+pub const WRITE_BUFFER_DOCS_ENV: &str = "SEARCH_WRITE_BUFFER_DOCS";
+
+/// Environment variable holding the content bytes per index that trigger a flush
+pub const WRITE_BUFFER_BYTES_ENV: &str = "SEARCH_WRITE_BUFFER_BYTES";
+
+/// Environment variable holding how long a document may wait before its index is flushed
+pub const WRITE_BUFFER_MAX_AGE_ENV: &str = "SEARCH_WRITE_BUFFER_MAX_AGE_MS";
+
+pub const DEFAULT_WRITE_BUFFER_BYTES: usize = 5 * 1024 * 1024;
+
+pub const DEFAULT_WRITE_BUFFER_MAX_AGE_MS: u64 = 1_000;
+
+/// Flush thresholds, per index
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WriteBufferConfig {
+    /// Buffered documents that trigger a flush; 0 disables buffering
+    pub max_docs: usize,
+    pub max_bytes: usize,
+    pub max_age: Duration,
+}
+
+impl Default for WriteBufferConfig {
+    fn default() -> Self {
+        Self {
+            max_docs: 0,
+            max_bytes: DEFAULT_WRITE_BUFFER_BYTES,
+            max_age: Duration::from_millis(DEFAULT_WRITE_BUFFER_MAX_AGE_MS),
+        }
+    }
+}
+
+impl WriteBufferConfig {
+    /// Read thresholds from the `SEARCH_WRITE_BUFFER_*` variables
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            max_docs: get_env_parsed::<usize>(WRITE_BUFFER_DOCS_ENV).ok().flatten().unwrap_or(defaults.max_docs),
+            max_bytes: get_env_parsed::<usize>(WRITE_BUFFER_BYTES_ENV)
+                .ok()
+                .flatten()
+                .filter(|&bytes| bytes > 0)
+                .unwrap_or(defaults.max_bytes),
+            max_age: get_env_parsed::<u64>(WRITE_BUFFER_MAX_AGE_ENV)
+                .ok()
+                .flatten()
+                .map_or(defaults.max_age, Duration::from_millis),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.max_docs > 0
+    }
+}
+
+/// Documents buffered for one index, in arrival order, one version per ID
+#[derive(Debug, Default)]
+struct IndexBuffer {
+    docs: Vec<Doc>,
+    positions: HashMap<String, usize>,
+    bytes: usize,
+    oldest: Option<Instant>,
+}
+
+impl IndexBuffer {
+    /// Add a document; a newer version replaces a buffered one in place
+    fn insert(&mut self, doc: Doc) {
+        let size = doc_size(&doc);
+        match self.positions.get(&doc.id) {
+            Some(&position) => {
+                self.bytes = self.bytes - doc_size(&self.docs[position]) + size;
+                self.docs[position] = doc;
+            }
+            None => {
+                self.positions.insert(doc.id.clone(), self.docs.len());
+                self.docs.push(doc);
+                self.bytes += size;
+            }
+        }
+    }
+
+    fn take(&mut self) -> Vec<Doc> {
+        self.positions.clear();
+        self.bytes = 0;
+        self.oldest = None;
+        std::mem::take(&mut self.docs)
+    }
+
+    fn is_due(&self, config: &WriteBufferConfig, now: Instant) -> bool {
+        self.docs.len() >= config.max_docs
+            || self.bytes >= config.max_bytes
+            || self.oldest.is_some_and(|oldest| now.duration_since(oldest) >= config.max_age)
+    }
+}
+
+fn doc_size(doc: &Doc) -> usize {
+    doc.id.len() + doc.content.len()
+}
+
+/// Upserts waiting to be written, per index
+#[derive(Debug)]
+pub struct WriteBuffer {
+    config: WriteBufferConfig,
+    indexes: BTreeMap<String, IndexBuffer>,
+}
+
+impl WriteBuffer {
+    pub fn new(config: WriteBufferConfig) -> Self {
+        Self { config, indexes: BTreeMap::new() }
+    }
+
+    pub fn config(&self) -> &WriteBufferConfig {
+        &self.config
+    }
+
+    /// Buffer `docs` for `index` and take every batch now due, on any index
+    pub fn push(&mut self, index: &str, docs: impl IntoIterator<Item = Doc>, now: Instant) -> Vec<(String, Vec<Doc>)> {
+        let buffer = self.indexes.entry(index.to_string()).or_default();
+        for doc in docs {
+            buffer.insert(doc);
+        }
+        buffer.oldest.get_or_insert(now);
+        self.take_due(now)
+    }
+
+    /// Take the batches of indexes past a threshold
+    pub fn take_due(&mut self, now: Instant) -> Vec<(String, Vec<Doc>)> {
+        let config = self.config;
+        let due: Vec<String> = self
+            .indexes
+            .iter()
+            .filter(|(_, buffer)| buffer.is_due(&config, now))
+            .map(|(index, _)| index.clone())
+            .collect();
+        due.into_iter().filter_map(|index| self.take_index(&index)).collect()
+    }
+
+    /// Take the batch of `index`, or of every index
+    pub fn take(&mut self, index: Option<&str>) -> Vec<(String, Vec<Doc>)> {
+        let indexes: Vec<String> = match index {
+            Some(index) => vec![index.to_string()],
+            None => self.indexes.keys().cloned().collect(),
+        };
+        indexes.into_iter().filter_map(|index| self.take_index(&index)).collect()
+    }
+
+    fn take_index(&mut self, index: &str) -> Option<(String, Vec<Doc>)> {
+        let mut buffer = self.indexes.remove(index)?;
+        let docs = buffer.take();
+        (!docs.is_empty()).then(|| (index.to_string(), docs))
+    }
+
+    /// Put back a batch that failed to write
+    ///
+    /// Versions buffered since the batch was taken are newer and win.
+    pub fn restore(&mut self, index: &str, docs: Vec<Doc>, now: Instant) {
+        let buffer = self.indexes.entry(index.to_string()).or_default();
+        let oldest = buffer.oldest;
+        let newer = buffer.take();
+        for doc in docs.into_iter().chain(newer) {
+            buffer.insert(doc);
+        }
+        buffer.oldest = Some(oldest.unwrap_or(now));
+    }
+
+    /// Drop buffered versions of deleted documents
+    pub fn discard(&mut self, index: &str, ids: &[String]) {
+        let Some(buffer) = self.indexes.get_mut(index) else {
+            return;
+        };
+        let oldest = buffer.oldest;
+        let kept: Vec<Doc> = buffer.take().into_iter().filter(|doc| !ids.contains(&doc.id)).collect();
+        if kept.is_empty() {
+            self.indexes.remove(index);
+            return;
+        }
+        for doc in kept {
+            buffer.insert(doc);
+        }
+        buffer.oldest = oldest;
+    }
+
+    /// Drop everything buffered for a deleted or cleared index
+    pub fn discard_index(&mut self, index: &str) {
+        self.indexes.remove(index);
+    }
+
+    /// Number of documents waiting to be written
+    pub fn len(&self) -> usize {
+        self.indexes.values().map(|buffer| buffer.docs.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+fn global_buffer() -> MutexGuard<'static, WriteBuffer> {
+    static BUFFER: OnceLock<Mutex<WriteBuffer>> = OnceLock::new();
+    BUFFER
+        .get_or_init(|| Mutex::new(WriteBuffer::new(WriteBufferConfig::from_env())))
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Whether upserts go through the worker's write buffer
+pub fn is_enabled() -> bool {
+    global_buffer().config().is_enabled()
+}
+
+/// Buffer upserts for `index` and write the batches that became due
+///
+/// `write` is called once per due batch with the index and its documents.
+pub async fn buffer_upserts<F, Fut, E>(index: &str, docs: Vec<Doc>, write: F) -> Result<(), E>
+where
+    F: FnMut(String, Vec<Doc>) -> Fut,
+    Fut: Future<Output = Result<(), E>>,
+{
+    let due = global_buffer().push(index, docs, Instant::now());
+    write_batches(due, write).await.map(|_| ())
+}
+
+/// Write what is buffered for `index`, or for every index, returning the number of documents written
+pub async fn flush<F, Fut, E>(index: Option<&str>, write: F) -> Result<usize, E>
+where
+    F: FnMut(String, Vec<Doc>) -> Fut,
+    Fut: Future<Output = Result<(), E>>,
+{
+    let batches = global_buffer().take(index);
+    write_batches(batches, write).await
+}
+
+/// Number of upserts waiting in the buffer, on every index
+pub fn pending() -> usize {
+    global_buffer().len()
+}
+
+/// Drop buffered versions of documents deleted from `index`
+pub fn discard(index: &str, ids: &[String]) {
+    global_buffer().discard(index, ids);
+}
+
+/// Drop everything buffered for `index`
+pub fn discard_index(index: &str) {
+    global_buffer().discard_index(index);
+}
+
+/// Write batches in order; on failure the failed batch and the rest are put back
+async fn write_batches<F, Fut, E>(batches: Vec<(String, Vec<Doc>)>, mut write: F) -> Result<usize, E>
+where
+    F: FnMut(String, Vec<Doc>) -> Fut,
+    Fut: Future<Output = Result<(), E>>,
+{
+    let mut written = 0;
+    let mut batches = batches.into_iter();
+    while let Some((index, docs)) = batches.next() {
+        let count = docs.len();
+        if let Err(e) = write(index.clone(), docs.clone()).await {
+            let now = Instant::now();
+            let mut buffer = global_buffer();
+            buffer.restore(&index, docs, now);
+            for (index, docs) in batches {
+                buffer.restore(&index, docs, now);
+            }
+            return Err(e);
+        }
+        written += count;
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doc(id: &str, content: &str) -> Doc {
+        Doc { id: id.to_string(), content: content.to_string() }
+    }
+
+    fn config(max_docs: usize) -> WriteBufferConfig {
+        WriteBufferConfig { max_docs, max_bytes: 1_000, max_age: Duration::from_millis(500) }
+    }
+
+    #[test]
+    fn test_flushes_on_thresholds() {
+        let mut buffer = WriteBuffer::new(config(3));
+        let start = Instant::now();
+
+        assert!(buffer.push("books", [doc("1", "{}"), doc("2", "{}")], start).is_empty());
+        // A newer version replaces the buffered one instead of counting twice
+        assert!(buffer.push("books", [doc("1", r#"{"v":2}"#)], start).is_empty());
+        let due = buffer.push("books", [doc("3", "{}")], start);
+        assert_eq!(due.len(), 1);
+        let ids: Vec<&str> = due[0].1.iter().map(|d| d.id.as_str()).collect();
+        assert_eq!(ids, ["1", "2", "3"]);
+        assert_eq!(due[0].1[0].content, r#"{"v":2}"#);
+        assert!(buffer.is_empty());
+
+        // Age is checked on the next write, to any index
+        assert!(buffer.push("authors", [doc("a", "{}")], start).is_empty());
+        let due = buffer.push("books", [doc("4", "{}")], start + Duration::from_millis(600));
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].0, "authors");
+        assert_eq!(buffer.len(), 1);
+    }
+
+    #[test]
+    fn test_restore_and_discard() {
+        let mut buffer = WriteBuffer::new(config(10));
+        let now = Instant::now();
+        buffer.push("books", [doc("1", "old"), doc("2", "{}")], now);
+        let (_, failed) = buffer.take(Some("books")).remove(0);
+
+        // Written while the failed batch was in flight; must survive the restore
+        buffer.push("books", [doc("1", "new")], now);
+        buffer.restore("books", failed, now);
+        let (_, docs) = buffer.take(None).remove(0);
+        assert_eq!(docs.len(), 2);
+        assert_eq!(docs.iter().find(|d| d.id == "1").unwrap().content, "new");
+
+        buffer.push("books", [doc("1", "{}"), doc("2", "{}")], now);
+        buffer.discard("books", &["2".to_string()]);
+        assert_eq!(buffer.len(), 1);
+        buffer.discard("books", &["1".to_string()]);
+        assert!(buffer.take(None).is_empty());
+    }
+}